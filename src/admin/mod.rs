@@ -4,6 +4,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::error::GatewayError;
 use crate::logging::time::{parse_datetime_string, to_beijing_string};
+use crate::server::pagination::PageRequest;
 
 const CLIENT_TOKEN_ID_PREFIX: &str = "atk_";
 
@@ -38,14 +39,31 @@ pub struct ClientToken {
     pub enabled: bool,
     pub expires_at: Option<DateTime<Utc>>, // None 表示不过期
     pub created_at: DateTime<Utc>,
-    pub amount_spent: f64,                 // 累计消费金额（默认 0）
-    pub prompt_tokens_spent: i64,          // 累计提示/输入 tokens
-    pub completion_tokens_spent: i64,      // 累计补全/回复 tokens
-    pub total_tokens_spent: i64,           // 累计总 tokens
-    pub remark: Option<String>,            // 备注
-    pub organization_id: Option<String>,   // 所属组织 ID（暂按字符串）
-    pub ip_whitelist: Option<Vec<String>>, // IP 白名单（JSON 数组）
-    pub ip_blacklist: Option<Vec<String>>, // IP 黑名单（JSON 数组）
+    pub amount_spent: f64,                    // 累计消费金额（默认 0）
+    pub prompt_tokens_spent: i64,             // 累计提示/输入 tokens
+    pub completion_tokens_spent: i64,         // 累计补全/回复 tokens
+    pub total_tokens_spent: i64,              // 累计总 tokens
+    pub remark: Option<String>,               // 备注
+    pub organization_id: Option<String>,      // 所属组织 ID（暂按字符串）
+    pub ip_whitelist: Option<Vec<String>>,    // IP 白名单（JSON 数组）
+    pub ip_blacklist: Option<Vec<String>>,    // IP 黑名单（JSON 数组）
+    pub allow_streaming: bool,                // 是否允许 stream=true 请求
+    pub sandbox: bool,                        // 沙箱令牌：不访问真实上游、不计费
+    pub strip_reasoning: bool,                // 响应中剥离 reasoning_content（思考内容）
+    pub usage_webhook_url: Option<String>,    // 每次请求完成后推送用量的 Webhook 地址
+    pub signing_secret: Option<String>,       // 请求签名（HMAC）密钥；为空时不支持签名认证
+    pub require_signature: bool,              // 强制签名认证：拒绝直接携带 Bearer Token 的请求
+    pub parent_token_id: Option<String>,      // 父令牌 ID（令牌交换签发的子令牌）；用量向上汇总
+    pub allow_debug_capture: bool, // 允许通过 X-Gateway-Debug: capture 保存单次请求的完整正文
+    pub allow_provider_override: bool, // 允许通过 provider 字段或 X-Gateway-Provider 头指定供应商/密钥
+    pub auto_truncate_prompt: bool,    // 提示超出模型上下文窗口时自动丢弃最早的对话消息
+    pub semantic_cache: bool,          // 非流式请求使用语义响应缓存（相似提示直接返回缓存结果）
+    pub allow_login_codes: bool,       // 允许为本令牌的终端用户签发 Web 控制台登录码
+    pub max_requests: Option<i64>,     // 累计请求次数上限；None 表示不限制
+    pub max_requests_per_day: Option<i64>, // 每日（北京时间）请求次数上限；None 表示不限制
+    pub watermark_responses: bool, // 响应中注入网关水印（请求 ID、令牌哈希、时间戳），用于追溯泄露的输出
+    pub queue_weight: Option<i64>, // 排队公平调度权重（1-100）；None 表示默认权重 1
+    pub usage_webhook_secret: Option<String>, // 用量 Webhook 签名密钥（网关生成，独立于令牌明文）
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -79,12 +97,85 @@ pub struct CreateTokenPayload {
     pub ip_whitelist: Option<Vec<String>>,
     #[serde(default)]
     pub ip_blacklist: Option<Vec<String>>,
+    #[serde(default = "default_enabled_true")]
+    pub allow_streaming: bool,
+    #[serde(default)]
+    pub sandbox: bool,
+    #[serde(default)]
+    pub strip_reasoning: bool,
+    #[serde(default)]
+    pub usage_webhook_url: Option<String>,
+    #[serde(default)]
+    pub signing_secret: Option<String>,
+    #[serde(default)]
+    pub require_signature: bool,
+    #[serde(default)]
+    pub parent_token_id: Option<String>, // 父令牌 ID：创建为其子令牌（组织/绑定用户继承父令牌）
+    #[serde(default)]
+    pub allow_debug_capture: bool,
+    #[serde(default)]
+    pub allow_provider_override: bool,
+    #[serde(default)]
+    pub auto_truncate_prompt: bool,
+    #[serde(default)]
+    pub semantic_cache: bool,
+    #[serde(default)]
+    pub allow_login_codes: bool,
+    #[serde(default)]
+    pub max_requests: Option<i64>, // 累计请求次数上限（可选）
+    #[serde(default)]
+    pub max_requests_per_day: Option<i64>, // 每日请求次数上限（可选）
+    #[serde(default)]
+    pub watermark_responses: bool,
+    #[serde(default)]
+    pub queue_weight: Option<i64>, // 排队公平调度权重（可选）
+    #[serde(skip)]
+    pub usage_webhook_secret: Option<String>, // 由处理器在设置 usage_webhook_url 时生成，不接受客户端传入
 }
 
 fn default_enabled_true() -> bool {
     true
 }
 
+impl Default for CreateTokenPayload {
+    /// 与反序列化空对象 `{}` 的结果一致：仅 enabled 与 allow_streaming 默认开启
+    fn default() -> Self {
+        Self {
+            id: None,
+            user_id: None,
+            name: None,
+            token: None,
+            allowed_models: None,
+            model_blacklist: None,
+            max_tokens: None,
+            max_amount: None,
+            enabled: true,
+            expires_at: None,
+            remark: None,
+            organization_id: None,
+            ip_whitelist: None,
+            ip_blacklist: None,
+            allow_streaming: true,
+            sandbox: false,
+            strip_reasoning: false,
+            usage_webhook_url: None,
+            signing_secret: None,
+            require_signature: false,
+            parent_token_id: None,
+            allow_debug_capture: false,
+            allow_provider_override: false,
+            auto_truncate_prompt: false,
+            semantic_cache: false,
+            allow_login_codes: false,
+            max_requests: None,
+            max_requests_per_day: None,
+            watermark_responses: false,
+            queue_weight: None,
+            usage_webhook_secret: None,
+        }
+    }
+}
+
 fn deserialize_patch_option<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
 where
     D: serde::Deserializer<'de>,
@@ -119,6 +210,69 @@ pub struct UpdateTokenPayload {
     pub ip_whitelist: Option<Option<Vec<String>>>, // 同上
     #[serde(default, deserialize_with = "deserialize_patch_option")]
     pub ip_blacklist: Option<Option<Vec<String>>>, // 同上
+    #[serde(default)]
+    pub allow_streaming: Option<bool>,
+    #[serde(default)]
+    pub sandbox: Option<bool>,
+    #[serde(default)]
+    pub strip_reasoning: Option<bool>,
+    #[serde(default, deserialize_with = "deserialize_patch_option")]
+    pub usage_webhook_url: Option<Option<String>>, // 同上
+    #[serde(default, deserialize_with = "deserialize_patch_option")]
+    pub signing_secret: Option<Option<String>>, // 同上
+    #[serde(default)]
+    pub require_signature: Option<bool>,
+    #[serde(default)]
+    pub allow_debug_capture: Option<bool>,
+    #[serde(default)]
+    pub allow_provider_override: Option<bool>,
+    #[serde(default)]
+    pub auto_truncate_prompt: Option<bool>,
+    #[serde(default)]
+    pub semantic_cache: Option<bool>,
+    #[serde(default)]
+    pub allow_login_codes: Option<bool>,
+    #[serde(default, deserialize_with = "deserialize_patch_option")]
+    pub max_requests: Option<Option<i64>>, // 同上
+    #[serde(default, deserialize_with = "deserialize_patch_option")]
+    pub max_requests_per_day: Option<Option<i64>>, // 同上
+    #[serde(default)]
+    pub watermark_responses: Option<bool>,
+    #[serde(default, deserialize_with = "deserialize_patch_option")]
+    pub queue_weight: Option<Option<i64>>, // 同上
+    #[serde(skip)]
+    pub usage_webhook_secret: Option<Option<String>>, // 同上（仅服务端设置）
+}
+
+/// 已发送的令牌通知记录（用于去重与审计）
+#[derive(Debug, Clone, Serialize)]
+pub struct TokenNotificationRecord {
+    pub token_id: String,
+    pub kind: String,
+    /// 去重依据，例如到期通知使用令牌的 expires_at
+    pub reference: String,
+    pub channel: String,
+    pub recipient: Option<String>,
+    pub success: bool,
+    pub error: Option<String>,
+    pub sent_at: DateTime<Utc>,
+}
+
+/// 闲置令牌自动停用策略的逐令牌设置
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TokenAutoDisablePrefs {
+    /// 为 true 时该令牌永不因闲置被自动停用
+    pub exempt: bool,
+    /// 最近一次被自动停用的时间（重新启用后从该时间起重新计算闲置期）
+    pub disabled_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TokenRotationPrefs {
+    /// 为 true 时该令牌不受轮换策略约束
+    pub exempt: bool,
+    /// 最近一次轮换时间；为空时以创建时间计算令牌年龄
+    pub rotated_at: Option<DateTime<Utc>>,
 }
 
 #[async_trait]
@@ -141,6 +295,18 @@ pub trait TokenStore: Send + Sync {
     ) -> Result<Option<ClientToken>, GatewayError>;
     async fn list_tokens(&self) -> Result<Vec<ClientToken>, GatewayError>;
     async fn list_tokens_by_user(&self, user_id: &str) -> Result<Vec<ClientToken>, GatewayError>;
+    async fn list_tokens_by_organization(
+        &self,
+        organization_id: &str,
+    ) -> Result<Vec<ClientToken>, GatewayError>;
+    /// 分页查询令牌（可限定组织），返回当前页与总数
+    async fn list_tokens_page(
+        &self,
+        organization_id: Option<&str>,
+        page: &PageRequest,
+    ) -> Result<(Vec<ClientToken>, u64), GatewayError>;
+    /// 直接子令牌（parent_token_id = parent_id）
+    async fn list_child_tokens(&self, parent_id: &str) -> Result<Vec<ClientToken>, GatewayError>;
     async fn add_amount_spent(&self, token: &str, delta: f64) -> Result<(), GatewayError>;
     async fn add_usage_spent(
         &self,
@@ -158,6 +324,41 @@ pub trait TokenStore: Send + Sync {
         payload: UpdateTokenPayload,
     ) -> Result<Option<ClientToken>, GatewayError>;
     async fn set_enabled_by_id(&self, id: &str, enabled: bool) -> Result<bool, GatewayError>;
+    // 令牌通知：退订标记与发送记录
+    async fn get_notifications_opt_out(&self, id: &str) -> Result<bool, GatewayError>;
+    async fn set_notifications_opt_out(&self, id: &str, opt_out: bool) -> Result<(), GatewayError>;
+    async fn record_token_notification(
+        &self,
+        record: &TokenNotificationRecord,
+    ) -> Result<(), GatewayError>;
+    /// 是否已成功发送过同一 kind/reference 的通知
+    async fn token_notification_sent(
+        &self,
+        id: &str,
+        kind: &str,
+        reference: &str,
+    ) -> Result<bool, GatewayError>;
+    async fn list_token_notifications(
+        &self,
+        id: &str,
+        limit: i64,
+    ) -> Result<Vec<TokenNotificationRecord>, GatewayError>;
+    // 闲置令牌自动停用：豁免标记与停用时间
+    async fn get_auto_disable_prefs(&self, id: &str)
+    -> Result<TokenAutoDisablePrefs, GatewayError>;
+    async fn set_auto_disable_exempt(&self, id: &str, exempt: bool) -> Result<(), GatewayError>;
+    async fn mark_auto_disabled(&self, id: &str, at: DateTime<Utc>) -> Result<(), GatewayError>;
+    async fn get_rotation_prefs(&self, id: &str) -> Result<TokenRotationPrefs, GatewayError>;
+    async fn set_rotation_exempt(&self, id: &str, exempt: bool) -> Result<(), GatewayError>;
+    /// 替换令牌值（id 与用量不变）并记录轮换时间；令牌不存在时返回 false
+    async fn rotate_token(
+        &self,
+        id: &str,
+        new_token: &str,
+        at: DateTime<Utc>,
+    ) -> Result<bool, GatewayError>;
+    /// 原样写入一条令牌（保留 id/token/累计用量），token 已存在时忽略；用于双写迁移同步新建的令牌
+    async fn import_token(&self, token: &ClientToken) -> Result<(), GatewayError>;
 }
 
 // SQLite 的实现由 DatabaseLogger 提供（见 logging/database_client_tokens.rs）
@@ -349,6 +550,63 @@ fn row_to_client_token(r: &tokio_postgres::Row) -> Result<ClientToken, GatewayEr
         .ok()
         .flatten()
         .or_else(|| r.try_get::<usize, String>(18).ok());
+    let allow_streaming = r
+        .try_get::<usize, Option<bool>>(19)
+        .ok()
+        .flatten()
+        .unwrap_or(true);
+    let sandbox = r
+        .try_get::<usize, Option<bool>>(20)
+        .ok()
+        .flatten()
+        .unwrap_or(false);
+    let strip_reasoning = r
+        .try_get::<usize, Option<bool>>(21)
+        .ok()
+        .flatten()
+        .unwrap_or(false);
+    let usage_webhook_url = r.try_get::<usize, Option<String>>(22).ok().flatten();
+    let signing_secret = r.try_get::<usize, Option<String>>(23).ok().flatten();
+    let require_signature = r
+        .try_get::<usize, Option<bool>>(24)
+        .ok()
+        .flatten()
+        .unwrap_or(false);
+    let parent_token_id = r.try_get::<usize, Option<String>>(25).ok().flatten();
+    let allow_debug_capture = r
+        .try_get::<usize, Option<bool>>(26)
+        .ok()
+        .flatten()
+        .unwrap_or(false);
+    let allow_provider_override = r
+        .try_get::<usize, Option<bool>>(27)
+        .ok()
+        .flatten()
+        .unwrap_or(false);
+    let auto_truncate_prompt = r
+        .try_get::<usize, Option<bool>>(28)
+        .ok()
+        .flatten()
+        .unwrap_or(false);
+    let semantic_cache = r
+        .try_get::<usize, Option<bool>>(29)
+        .ok()
+        .flatten()
+        .unwrap_or(false);
+    let allow_login_codes = r
+        .try_get::<usize, Option<bool>>(30)
+        .ok()
+        .flatten()
+        .unwrap_or(false);
+    let max_requests = r.try_get::<usize, Option<i64>>(31).ok().flatten();
+    let max_requests_per_day = r.try_get::<usize, Option<i64>>(32).ok().flatten();
+    let watermark_responses = r
+        .try_get::<usize, Option<bool>>(33)
+        .ok()
+        .flatten()
+        .unwrap_or(false);
+    let queue_weight = r.try_get::<usize, Option<i64>>(34).ok().flatten();
+    let usage_webhook_secret = r.try_get::<usize, Option<String>>(35).ok().flatten();
     let id = id_opt.unwrap_or_else(|| client_token_id_for_token(&token));
     let name = normalize_client_token_name(name_opt, &id);
     Ok(ClientToken {
@@ -371,6 +629,23 @@ fn row_to_client_token(r: &tokio_postgres::Row) -> Result<ClientToken, GatewayEr
         organization_id,
         ip_whitelist: decode_json_string_list("ip_whitelist", ip_whitelist_s)?,
         ip_blacklist: decode_json_string_list("ip_blacklist", ip_blacklist_s)?,
+        allow_streaming,
+        sandbox,
+        strip_reasoning,
+        usage_webhook_url,
+        signing_secret,
+        require_signature,
+        parent_token_id,
+        allow_debug_capture,
+        allow_provider_override,
+        auto_truncate_prompt,
+        semantic_cache,
+        allow_login_codes,
+        max_requests,
+        max_requests_per_day,
+        watermark_responses,
+        queue_weight,
+        usage_webhook_secret,
     })
 }
 
@@ -487,7 +762,24 @@ async fn ensure_client_tokens_table_pg(
                 organization_id TEXT,
                 ip_whitelist TEXT,
                 ip_blacklist TEXT,
-                model_blacklist TEXT
+                model_blacklist TEXT,
+                allow_streaming BOOLEAN NOT NULL DEFAULT TRUE,
+                sandbox BOOLEAN NOT NULL DEFAULT FALSE,
+                strip_reasoning BOOLEAN NOT NULL DEFAULT FALSE,
+                usage_webhook_url TEXT,
+                signing_secret TEXT,
+                require_signature BOOLEAN NOT NULL DEFAULT FALSE,
+                parent_token_id TEXT,
+                allow_debug_capture BOOLEAN NOT NULL DEFAULT FALSE,
+                allow_provider_override BOOLEAN NOT NULL DEFAULT FALSE,
+                auto_truncate_prompt BOOLEAN NOT NULL DEFAULT FALSE,
+                semantic_cache BOOLEAN NOT NULL DEFAULT FALSE,
+                allow_login_codes BOOLEAN NOT NULL DEFAULT FALSE,
+                max_requests BIGINT,
+                max_requests_per_day BIGINT,
+                watermark_responses BOOLEAN NOT NULL DEFAULT FALSE,
+                queue_weight BIGINT,
+                usage_webhook_secret TEXT
             )"#,
             &[],
         )
@@ -561,6 +853,115 @@ async fn ensure_client_tokens_table_pg(
             &[],
         )
         .await;
+    let _ = client
+        .execute(
+            "ALTER TABLE client_tokens ADD COLUMN allow_streaming BOOLEAN NOT NULL DEFAULT TRUE",
+            &[],
+        )
+        .await;
+    let _ = client
+        .execute(
+            "ALTER TABLE client_tokens ADD COLUMN sandbox BOOLEAN NOT NULL DEFAULT FALSE",
+            &[],
+        )
+        .await;
+    let _ = client
+        .execute(
+            "ALTER TABLE client_tokens ADD COLUMN strip_reasoning BOOLEAN NOT NULL DEFAULT FALSE",
+            &[],
+        )
+        .await;
+    let _ = client
+        .execute(
+            "ALTER TABLE client_tokens ADD COLUMN usage_webhook_url TEXT",
+            &[],
+        )
+        .await;
+    let _ = client
+        .execute(
+            "ALTER TABLE client_tokens ADD COLUMN signing_secret TEXT",
+            &[],
+        )
+        .await;
+    let _ = client
+        .execute(
+            "ALTER TABLE client_tokens ADD COLUMN require_signature BOOLEAN NOT NULL DEFAULT FALSE",
+            &[],
+        )
+        .await;
+    let _ = client
+        .execute(
+            "ALTER TABLE client_tokens ADD COLUMN parent_token_id TEXT",
+            &[],
+        )
+        .await;
+    let _ = client
+        .execute(
+            "ALTER TABLE client_tokens ADD COLUMN allow_debug_capture BOOLEAN NOT NULL DEFAULT FALSE",
+            &[],
+        )
+        .await;
+    let _ = client
+        .execute(
+            "ALTER TABLE client_tokens ADD COLUMN allow_provider_override BOOLEAN NOT NULL DEFAULT FALSE",
+            &[],
+        )
+        .await;
+    let _ = client
+        .execute(
+            "ALTER TABLE client_tokens ADD COLUMN auto_truncate_prompt BOOLEAN NOT NULL DEFAULT FALSE",
+            &[],
+        )
+        .await;
+    let _ = client
+        .execute(
+            "ALTER TABLE client_tokens ADD COLUMN semantic_cache BOOLEAN NOT NULL DEFAULT FALSE",
+            &[],
+        )
+        .await;
+    let _ = client
+        .execute(
+            "ALTER TABLE client_tokens ADD COLUMN allow_login_codes BOOLEAN NOT NULL DEFAULT FALSE",
+            &[],
+        )
+        .await;
+    let _ = client
+        .execute(
+            "ALTER TABLE client_tokens ADD COLUMN max_requests BIGINT",
+            &[],
+        )
+        .await;
+    let _ = client
+        .execute(
+            "ALTER TABLE client_tokens ADD COLUMN max_requests_per_day BIGINT",
+            &[],
+        )
+        .await;
+    let _ = client
+        .execute(
+            "ALTER TABLE client_tokens ADD COLUMN watermark_responses BOOLEAN NOT NULL DEFAULT FALSE",
+            &[],
+        )
+        .await;
+    let _ = client
+        .execute(
+            "ALTER TABLE client_tokens ADD COLUMN queue_weight BIGINT",
+            &[],
+        )
+        .await;
+    let _ = client
+        .execute(
+            "ALTER TABLE client_tokens ADD COLUMN usage_webhook_secret TEXT",
+            &[],
+        )
+        .await;
+    // 已配置用量 Webhook 的存量令牌补发签名密钥（此前使用令牌明文签名）
+    let _ = client
+        .execute(
+            "UPDATE client_tokens SET usage_webhook_secret = 'whsec_' || replace(gen_random_uuid()::text, '-', '') || replace(gen_random_uuid()::text, '-', '') WHERE usage_webhook_url IS NOT NULL AND usage_webhook_secret IS NULL",
+            &[],
+        )
+        .await;
     let _ = client
         .execute(
             "CREATE UNIQUE INDEX IF NOT EXISTS client_tokens_id_uidx ON client_tokens(id)",
@@ -573,6 +974,12 @@ async fn ensure_client_tokens_table_pg(
             &[],
         )
         .await;
+    let _ = client
+        .execute(
+            "CREATE INDEX IF NOT EXISTS client_tokens_parent_token_id_idx ON client_tokens(parent_token_id)",
+            &[],
+        )
+        .await;
 
     // Backfill id/name for existing rows (best-effort)
     if let Ok(rows) = client
@@ -612,6 +1019,66 @@ async fn ensure_client_tokens_table_pg(
             &[],
         )
         .await;
+    let _ = client
+        .execute(
+            r#"CREATE TABLE IF NOT EXISTS client_token_prefs (
+                token_id TEXT PRIMARY KEY,
+                notifications_opt_out BOOLEAN NOT NULL DEFAULT FALSE,
+                auto_disable_exempt BOOLEAN NOT NULL DEFAULT FALSE,
+                auto_disabled_at TEXT,
+                rotation_exempt BOOLEAN NOT NULL DEFAULT FALSE,
+                rotated_at TEXT,
+                updated_at TEXT NOT NULL
+            )"#,
+            &[],
+        )
+        .await;
+    let _ = client
+        .execute(
+            "ALTER TABLE client_token_prefs ADD COLUMN IF NOT EXISTS auto_disable_exempt BOOLEAN NOT NULL DEFAULT FALSE",
+            &[],
+        )
+        .await;
+    let _ = client
+        .execute(
+            "ALTER TABLE client_token_prefs ADD COLUMN IF NOT EXISTS auto_disabled_at TEXT",
+            &[],
+        )
+        .await;
+    let _ = client
+        .execute(
+            "ALTER TABLE client_token_prefs ADD COLUMN IF NOT EXISTS rotation_exempt BOOLEAN NOT NULL DEFAULT FALSE",
+            &[],
+        )
+        .await;
+    let _ = client
+        .execute(
+            "ALTER TABLE client_token_prefs ADD COLUMN IF NOT EXISTS rotated_at TEXT",
+            &[],
+        )
+        .await;
+    let _ = client
+        .execute(
+            r#"CREATE TABLE IF NOT EXISTS client_token_notifications (
+                id BIGSERIAL PRIMARY KEY,
+                token_id TEXT NOT NULL,
+                kind TEXT NOT NULL,
+                reference TEXT NOT NULL,
+                channel TEXT NOT NULL,
+                recipient TEXT,
+                success BOOLEAN NOT NULL,
+                error TEXT,
+                sent_at TEXT NOT NULL
+            )"#,
+            &[],
+        )
+        .await;
+    let _ = client
+        .execute(
+            "CREATE INDEX IF NOT EXISTS client_token_notifications_token_idx ON client_token_notifications(token_id, kind, reference)",
+            &[],
+        )
+        .await;
     let _ = client
         .execute(
             r#"CREATE TABLE IF NOT EXISTS organizations (
@@ -676,8 +1143,8 @@ impl TokenStore for PgTokenStore {
         }
         self.client
             .execute(
-                "INSERT INTO client_tokens (id, user_id, name, token, allowed_models, max_tokens, enabled, expires_at, created_at, max_amount, amount_spent, prompt_tokens_spent, completion_tokens_spent, total_tokens_spent, remark, organization_id, ip_whitelist, ip_blacklist, model_blacklist) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, 0, 0, 0, 0, $11, $12, $13, $14, $15)",
-                &[&id, &payload.user_id, &name, &token, &allowed_models_s, &payload.max_tokens, &payload.enabled, &expires_s, &to_beijing_string(&now), &payload.max_amount, &payload.remark, &payload.organization_id, &ip_whitelist_s, &ip_blacklist_s, &model_blacklist_s],
+                "INSERT INTO client_tokens (id, user_id, name, token, allowed_models, max_tokens, enabled, expires_at, created_at, max_amount, amount_spent, prompt_tokens_spent, completion_tokens_spent, total_tokens_spent, remark, organization_id, ip_whitelist, ip_blacklist, model_blacklist, allow_streaming, sandbox, strip_reasoning, usage_webhook_url, signing_secret, require_signature, parent_token_id, allow_debug_capture, allow_provider_override, auto_truncate_prompt, semantic_cache, allow_login_codes, max_requests, max_requests_per_day, watermark_responses, queue_weight, usage_webhook_secret) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, 0, 0, 0, 0, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21, $22, $23, $24, $25, $26, $27, $28, $29, $30, $31, $32)",
+                &[&id, &payload.user_id, &name, &token, &allowed_models_s, &payload.max_tokens, &payload.enabled, &expires_s, &to_beijing_string(&now), &payload.max_amount, &payload.remark, &payload.organization_id, &ip_whitelist_s, &ip_blacklist_s, &model_blacklist_s, &payload.allow_streaming, &payload.sandbox, &payload.strip_reasoning, &payload.usage_webhook_url, &payload.signing_secret, &payload.require_signature, &payload.parent_token_id, &payload.allow_debug_capture, &payload.allow_provider_override, &payload.auto_truncate_prompt, &payload.semantic_cache, &payload.allow_login_codes, &payload.max_requests, &payload.max_requests_per_day, &payload.watermark_responses, &payload.queue_weight, &payload.usage_webhook_secret],
             )
             .await
             .map_err(|e| GatewayError::Config(format!("DB error: {}", e)))?;
@@ -702,6 +1169,23 @@ impl TokenStore for PgTokenStore {
             organization_id: payload.organization_id,
             ip_whitelist: payload.ip_whitelist,
             ip_blacklist: payload.ip_blacklist,
+            allow_streaming: payload.allow_streaming,
+            sandbox: payload.sandbox,
+            strip_reasoning: payload.strip_reasoning,
+            usage_webhook_url: payload.usage_webhook_url,
+            signing_secret: payload.signing_secret,
+            require_signature: payload.require_signature,
+            parent_token_id: payload.parent_token_id,
+            allow_debug_capture: payload.allow_debug_capture,
+            allow_provider_override: payload.allow_provider_override,
+            auto_truncate_prompt: payload.auto_truncate_prompt,
+            semantic_cache: payload.semantic_cache,
+            allow_login_codes: payload.allow_login_codes,
+            max_requests: payload.max_requests,
+            max_requests_per_day: payload.max_requests_per_day,
+            watermark_responses: payload.watermark_responses,
+            queue_weight: payload.queue_weight,
+            usage_webhook_secret: payload.usage_webhook_secret,
         })
     }
 
@@ -713,7 +1197,7 @@ impl TokenStore for PgTokenStore {
         // read existing
         let row = self.client
             .query_opt(
-                "SELECT id, user_id, name, token, allowed_models, max_tokens, enabled, expires_at, created_at, max_amount, amount_spent, prompt_tokens_spent, completion_tokens_spent, total_tokens_spent, remark, organization_id, ip_whitelist, ip_blacklist, model_blacklist FROM client_tokens WHERE token = $1",
+                "SELECT id, user_id, name, token, allowed_models, max_tokens, enabled, expires_at, created_at, max_amount, amount_spent, prompt_tokens_spent, completion_tokens_spent, total_tokens_spent, remark, organization_id, ip_whitelist, ip_blacklist, model_blacklist, allow_streaming, sandbox, strip_reasoning, usage_webhook_url, signing_secret, require_signature, parent_token_id, allow_debug_capture, allow_provider_override, auto_truncate_prompt, semantic_cache, allow_login_codes, max_requests, max_requests_per_day, watermark_responses, queue_weight, usage_webhook_secret FROM client_tokens WHERE token = $1",
                 &[&token],
             )
             .await
@@ -757,6 +1241,54 @@ impl TokenStore for PgTokenStore {
         if let Some(v) = payload.ip_blacklist {
             current.ip_blacklist = v;
         }
+        if let Some(v) = payload.allow_streaming {
+            current.allow_streaming = v;
+        }
+        if let Some(v) = payload.sandbox {
+            current.sandbox = v;
+        }
+        if let Some(v) = payload.strip_reasoning {
+            current.strip_reasoning = v;
+        }
+        if let Some(v) = payload.usage_webhook_url {
+            current.usage_webhook_url = v;
+        }
+        if let Some(v) = payload.signing_secret {
+            current.signing_secret = v;
+        }
+        if let Some(v) = payload.require_signature {
+            current.require_signature = v;
+        }
+        if let Some(v) = payload.allow_debug_capture {
+            current.allow_debug_capture = v;
+        }
+        if let Some(v) = payload.allow_provider_override {
+            current.allow_provider_override = v;
+        }
+        if let Some(v) = payload.auto_truncate_prompt {
+            current.auto_truncate_prompt = v;
+        }
+        if let Some(v) = payload.semantic_cache {
+            current.semantic_cache = v;
+        }
+        if let Some(v) = payload.allow_login_codes {
+            current.allow_login_codes = v;
+        }
+        if let Some(v) = payload.max_requests {
+            current.max_requests = v;
+        }
+        if let Some(v) = payload.max_requests_per_day {
+            current.max_requests_per_day = v;
+        }
+        if let Some(v) = payload.watermark_responses {
+            current.watermark_responses = v;
+        }
+        if let Some(v) = payload.queue_weight {
+            current.queue_weight = v;
+        }
+        if let Some(v) = payload.usage_webhook_secret {
+            current.usage_webhook_secret = v;
+        }
 
         let ip_whitelist_s = encode_json_string_list("ip_whitelist", &current.ip_whitelist)?;
         let ip_blacklist_s = encode_json_string_list("ip_blacklist", &current.ip_blacklist)?;
@@ -771,8 +1303,8 @@ impl TokenStore for PgTokenStore {
         }
         self.client
             .execute(
-                "UPDATE client_tokens SET name = $2, allowed_models = $3, max_tokens = $4, enabled = $5, expires_at = $6, max_amount = $7, remark = $8, organization_id = $9, ip_whitelist = $10, ip_blacklist = $11, model_blacklist = $12 WHERE token = $1",
-                &[&token, &current.name, &join_allowed_models(&current.allowed_models), &current.max_tokens, &current.enabled, &current.expires_at.as_ref().map(to_beijing_string), &current.max_amount, &current.remark, &current.organization_id, &ip_whitelist_s, &ip_blacklist_s, &join_allowed_models(&current.model_blacklist)],
+                "UPDATE client_tokens SET name = $2, allowed_models = $3, max_tokens = $4, enabled = $5, expires_at = $6, max_amount = $7, remark = $8, organization_id = $9, ip_whitelist = $10, ip_blacklist = $11, model_blacklist = $12, allow_streaming = $13, sandbox = $14, strip_reasoning = $15, usage_webhook_url = $16, signing_secret = $17, require_signature = $18, allow_debug_capture = $19, allow_provider_override = $20, auto_truncate_prompt = $21, semantic_cache = $22, allow_login_codes = $23, max_requests = $24, max_requests_per_day = $25, watermark_responses = $26, queue_weight = $27, usage_webhook_secret = $28 WHERE token = $1",
+                &[&token, &current.name, &join_allowed_models(&current.allowed_models), &current.max_tokens, &current.enabled, &current.expires_at.as_ref().map(to_beijing_string), &current.max_amount, &current.remark, &current.organization_id, &ip_whitelist_s, &ip_blacklist_s, &join_allowed_models(&current.model_blacklist), &current.allow_streaming, &current.sandbox, &current.strip_reasoning, &current.usage_webhook_url, &current.signing_secret, &current.require_signature, &current.allow_debug_capture, &current.allow_provider_override, &current.auto_truncate_prompt, &current.semantic_cache, &current.allow_login_codes, &current.max_requests, &current.max_requests_per_day, &current.watermark_responses, &current.queue_weight, &current.usage_webhook_secret],
             )
             .await
             .map_err(|e| GatewayError::Config(format!("DB error: {}", e)))?;
@@ -811,7 +1343,7 @@ impl TokenStore for PgTokenStore {
     async fn get_token(&self, token: &str) -> Result<Option<ClientToken>, GatewayError> {
         let row = self.client
             .query_opt(
-                "SELECT id, user_id, name, token, allowed_models, max_tokens, enabled, expires_at, created_at, max_amount, amount_spent, prompt_tokens_spent, completion_tokens_spent, total_tokens_spent, remark, organization_id, ip_whitelist, ip_blacklist, model_blacklist FROM client_tokens WHERE token = $1",
+                "SELECT id, user_id, name, token, allowed_models, max_tokens, enabled, expires_at, created_at, max_amount, amount_spent, prompt_tokens_spent, completion_tokens_spent, total_tokens_spent, remark, organization_id, ip_whitelist, ip_blacklist, model_blacklist, allow_streaming, sandbox, strip_reasoning, usage_webhook_url, signing_secret, require_signature, parent_token_id, allow_debug_capture, allow_provider_override, auto_truncate_prompt, semantic_cache, allow_login_codes, max_requests, max_requests_per_day, watermark_responses, queue_weight, usage_webhook_secret FROM client_tokens WHERE token = $1",
                 &[&token],
             )
             .await
@@ -826,7 +1358,7 @@ impl TokenStore for PgTokenStore {
     async fn get_token_by_id(&self, id: &str) -> Result<Option<ClientToken>, GatewayError> {
         let row = self.client
             .query_opt(
-                "SELECT id, user_id, name, token, allowed_models, max_tokens, enabled, expires_at, created_at, max_amount, amount_spent, prompt_tokens_spent, completion_tokens_spent, total_tokens_spent, remark, organization_id, ip_whitelist, ip_blacklist, model_blacklist FROM client_tokens WHERE id = $1",
+                "SELECT id, user_id, name, token, allowed_models, max_tokens, enabled, expires_at, created_at, max_amount, amount_spent, prompt_tokens_spent, completion_tokens_spent, total_tokens_spent, remark, organization_id, ip_whitelist, ip_blacklist, model_blacklist, allow_streaming, sandbox, strip_reasoning, usage_webhook_url, signing_secret, require_signature, parent_token_id, allow_debug_capture, allow_provider_override, auto_truncate_prompt, semantic_cache, allow_login_codes, max_requests, max_requests_per_day, watermark_responses, queue_weight, usage_webhook_secret FROM client_tokens WHERE id = $1",
                 &[&id],
             )
             .await
@@ -845,7 +1377,7 @@ impl TokenStore for PgTokenStore {
         let row = self
             .client
             .query_opt(
-                "SELECT id, user_id, name, token, allowed_models, max_tokens, enabled, expires_at, created_at, max_amount, amount_spent, prompt_tokens_spent, completion_tokens_spent, total_tokens_spent, remark, organization_id, ip_whitelist, ip_blacklist, model_blacklist FROM client_tokens WHERE id = $1 AND user_id = $2",
+                "SELECT id, user_id, name, token, allowed_models, max_tokens, enabled, expires_at, created_at, max_amount, amount_spent, prompt_tokens_spent, completion_tokens_spent, total_tokens_spent, remark, organization_id, ip_whitelist, ip_blacklist, model_blacklist, allow_streaming, sandbox, strip_reasoning, usage_webhook_url, signing_secret, require_signature, parent_token_id, allow_debug_capture, allow_provider_override, auto_truncate_prompt, semantic_cache, allow_login_codes, max_requests, max_requests_per_day, watermark_responses, queue_weight, usage_webhook_secret FROM client_tokens WHERE id = $1 AND user_id = $2",
                 &[&id, &user_id],
             )
             .await
@@ -859,7 +1391,7 @@ impl TokenStore for PgTokenStore {
     async fn list_tokens(&self) -> Result<Vec<ClientToken>, GatewayError> {
         let rows = self.client
             .query(
-                "SELECT id, user_id, name, token, allowed_models, max_tokens, enabled, expires_at, created_at, max_amount, amount_spent, prompt_tokens_spent, completion_tokens_spent, total_tokens_spent, remark, organization_id, ip_whitelist, ip_blacklist, model_blacklist FROM client_tokens ORDER BY created_at DESC",
+                "SELECT id, user_id, name, token, allowed_models, max_tokens, enabled, expires_at, created_at, max_amount, amount_spent, prompt_tokens_spent, completion_tokens_spent, total_tokens_spent, remark, organization_id, ip_whitelist, ip_blacklist, model_blacklist, allow_streaming, sandbox, strip_reasoning, usage_webhook_url, signing_secret, require_signature, parent_token_id, allow_debug_capture, allow_provider_override, auto_truncate_prompt, semantic_cache, allow_login_codes, max_requests, max_requests_per_day, watermark_responses, queue_weight, usage_webhook_secret FROM client_tokens ORDER BY created_at DESC",
                 &[],
             )
             .await
@@ -873,7 +1405,7 @@ impl TokenStore for PgTokenStore {
         let rows = self
             .client
             .query(
-                "SELECT id, user_id, name, token, allowed_models, max_tokens, enabled, expires_at, created_at, max_amount, amount_spent, prompt_tokens_spent, completion_tokens_spent, total_tokens_spent, remark, organization_id, ip_whitelist, ip_blacklist, model_blacklist FROM client_tokens WHERE user_id = $1 ORDER BY created_at DESC",
+                "SELECT id, user_id, name, token, allowed_models, max_tokens, enabled, expires_at, created_at, max_amount, amount_spent, prompt_tokens_spent, completion_tokens_spent, total_tokens_spent, remark, organization_id, ip_whitelist, ip_blacklist, model_blacklist, allow_streaming, sandbox, strip_reasoning, usage_webhook_url, signing_secret, require_signature, parent_token_id, allow_debug_capture, allow_provider_override, auto_truncate_prompt, semantic_cache, allow_login_codes, max_requests, max_requests_per_day, watermark_responses, queue_weight, usage_webhook_secret FROM client_tokens WHERE user_id = $1 ORDER BY created_at DESC",
                 &[&user_id],
             )
             .await
@@ -883,6 +1415,75 @@ impl TokenStore for PgTokenStore {
             .collect::<Result<Vec<_>, _>>()
     }
 
+    async fn list_tokens_by_organization(
+        &self,
+        organization_id: &str,
+    ) -> Result<Vec<ClientToken>, GatewayError> {
+        let rows = self
+            .client
+            .query(
+                "SELECT id, user_id, name, token, allowed_models, max_tokens, enabled, expires_at, created_at, max_amount, amount_spent, prompt_tokens_spent, completion_tokens_spent, total_tokens_spent, remark, organization_id, ip_whitelist, ip_blacklist, model_blacklist, allow_streaming, sandbox, strip_reasoning, usage_webhook_url, signing_secret, require_signature, parent_token_id, allow_debug_capture, allow_provider_override, auto_truncate_prompt, semantic_cache, allow_login_codes, max_requests, max_requests_per_day, watermark_responses, queue_weight, usage_webhook_secret FROM client_tokens WHERE organization_id = $1 ORDER BY created_at DESC",
+                &[&organization_id],
+            )
+            .await
+            .map_err(|e| GatewayError::Config(format!("DB error: {}", e)))?;
+        rows.into_iter()
+            .map(|r| row_to_client_token(&r))
+            .collect::<Result<Vec<_>, _>>()
+    }
+
+    async fn list_tokens_page(
+        &self,
+        organization_id: Option<&str>,
+        page: &PageRequest,
+    ) -> Result<(Vec<ClientToken>, u64), GatewayError> {
+        let filter = if organization_id.is_some() {
+            "WHERE organization_id = $1"
+        } else {
+            ""
+        };
+        let params: Vec<&(dyn tokio_postgres::types::ToSql + Sync)> = match organization_id.as_ref()
+        {
+            Some(org) => vec![org],
+            None => Vec::new(),
+        };
+        let total: i64 = self
+            .client
+            .query_one(
+                &format!("SELECT COUNT(*) FROM client_tokens {}", filter),
+                &params,
+            )
+            .await
+            .map_err(|e| GatewayError::Config(format!("DB error: {}", e)))?
+            .get(0);
+        let rows = self.client
+            .query(
+                &format!("SELECT id, user_id, name, token, allowed_models, max_tokens, enabled, expires_at, created_at, max_amount, amount_spent, prompt_tokens_spent, completion_tokens_spent, total_tokens_spent, remark, organization_id, ip_whitelist, ip_blacklist, model_blacklist, allow_streaming, sandbox, strip_reasoning, usage_webhook_url, signing_secret, require_signature, parent_token_id, allow_debug_capture, allow_provider_override, auto_truncate_prompt, semantic_cache, allow_login_codes, max_requests, max_requests_per_day, watermark_responses, queue_weight, usage_webhook_secret FROM client_tokens {} {}", filter, page.sql_tail(&["id"])),
+                &params,
+            )
+            .await
+            .map_err(|e| GatewayError::Config(format!("DB error: {}", e)))?;
+        let tokens = rows
+            .into_iter()
+            .map(|r| row_to_client_token(&r))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok((tokens, total.max(0) as u64))
+    }
+
+    async fn list_child_tokens(&self, parent_id: &str) -> Result<Vec<ClientToken>, GatewayError> {
+        let rows = self
+            .client
+            .query(
+                "SELECT id, user_id, name, token, allowed_models, max_tokens, enabled, expires_at, created_at, max_amount, amount_spent, prompt_tokens_spent, completion_tokens_spent, total_tokens_spent, remark, organization_id, ip_whitelist, ip_blacklist, model_blacklist, allow_streaming, sandbox, strip_reasoning, usage_webhook_url, signing_secret, require_signature, parent_token_id, allow_debug_capture, allow_provider_override, auto_truncate_prompt, semantic_cache, allow_login_codes, max_requests, max_requests_per_day, watermark_responses, queue_weight, usage_webhook_secret FROM client_tokens WHERE parent_token_id = $1 ORDER BY created_at DESC",
+                &[&parent_id],
+            )
+            .await
+            .map_err(|e| GatewayError::Config(format!("DB error: {}", e)))?;
+        rows.into_iter()
+            .map(|r| row_to_client_token(&r))
+            .collect::<Result<Vec<_>, _>>()
+    }
+
     async fn delete_token(&self, token: &str) -> Result<bool, GatewayError> {
         let res = self
             .client
@@ -909,7 +1510,7 @@ impl TokenStore for PgTokenStore {
         let row = self
             .client
             .query_opt(
-                "SELECT id, user_id, name, token, allowed_models, max_tokens, enabled, expires_at, created_at, max_amount, amount_spent, prompt_tokens_spent, completion_tokens_spent, total_tokens_spent, remark, organization_id, ip_whitelist, ip_blacklist, model_blacklist FROM client_tokens WHERE id = $1",
+                "SELECT id, user_id, name, token, allowed_models, max_tokens, enabled, expires_at, created_at, max_amount, amount_spent, prompt_tokens_spent, completion_tokens_spent, total_tokens_spent, remark, organization_id, ip_whitelist, ip_blacklist, model_blacklist, allow_streaming, sandbox, strip_reasoning, usage_webhook_url, signing_secret, require_signature, parent_token_id, allow_debug_capture, allow_provider_override, auto_truncate_prompt, semantic_cache, allow_login_codes, max_requests, max_requests_per_day, watermark_responses, queue_weight, usage_webhook_secret FROM client_tokens WHERE id = $1",
                 &[&id],
             )
             .await
@@ -960,4 +1561,252 @@ impl TokenStore for PgTokenStore {
             .map_err(|e| GatewayError::Config(format!("DB error: {}", e)))?;
         Ok(())
     }
+    async fn get_notifications_opt_out(&self, id: &str) -> Result<bool, GatewayError> {
+        let row = self
+            .client
+            .query_opt(
+                "SELECT notifications_opt_out FROM client_token_prefs WHERE token_id = $1",
+                &[&id],
+            )
+            .await
+            .map_err(|e| GatewayError::Config(format!("DB error: {}", e)))?;
+        Ok(row.map(|r| r.get::<usize, bool>(0)).unwrap_or(false))
+    }
+
+    async fn set_notifications_opt_out(&self, id: &str, opt_out: bool) -> Result<(), GatewayError> {
+        let now = to_beijing_string(&Utc::now());
+        self.client
+            .execute(
+                "INSERT INTO client_token_prefs (token_id, notifications_opt_out, updated_at)
+                 VALUES ($1, $2, $3)
+                 ON CONFLICT (token_id) DO UPDATE SET notifications_opt_out = EXCLUDED.notifications_opt_out, updated_at = EXCLUDED.updated_at",
+                &[&id, &opt_out, &now],
+            )
+            .await
+            .map_err(|e| GatewayError::Config(format!("DB error: {}", e)))?;
+        Ok(())
+    }
+
+    async fn record_token_notification(
+        &self,
+        record: &TokenNotificationRecord,
+    ) -> Result<(), GatewayError> {
+        let sent_at = to_beijing_string(&record.sent_at);
+        self.client
+            .execute(
+                "INSERT INTO client_token_notifications (token_id, kind, reference, channel, recipient, success, error, sent_at)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+                &[
+                    &record.token_id,
+                    &record.kind,
+                    &record.reference,
+                    &record.channel,
+                    &record.recipient,
+                    &record.success,
+                    &record.error,
+                    &sent_at,
+                ],
+            )
+            .await
+            .map_err(|e| GatewayError::Config(format!("DB error: {}", e)))?;
+        Ok(())
+    }
+
+    async fn token_notification_sent(
+        &self,
+        id: &str,
+        kind: &str,
+        reference: &str,
+    ) -> Result<bool, GatewayError> {
+        let row = self
+            .client
+            .query_opt(
+                "SELECT 1 FROM client_token_notifications WHERE token_id = $1 AND kind = $2 AND reference = $3 AND success = TRUE LIMIT 1",
+                &[&id, &kind, &reference],
+            )
+            .await
+            .map_err(|e| GatewayError::Config(format!("DB error: {}", e)))?;
+        Ok(row.is_some())
+    }
+
+    async fn list_token_notifications(
+        &self,
+        id: &str,
+        limit: i64,
+    ) -> Result<Vec<TokenNotificationRecord>, GatewayError> {
+        let rows = self
+            .client
+            .query(
+                "SELECT token_id, kind, reference, channel, recipient, success, error, sent_at
+                 FROM client_token_notifications WHERE token_id = $1 ORDER BY id DESC LIMIT $2",
+                &[&id, &limit],
+            )
+            .await
+            .map_err(|e| GatewayError::Config(format!("DB error: {}", e)))?;
+        Ok(rows
+            .into_iter()
+            .map(|r| TokenNotificationRecord {
+                token_id: r.get(0),
+                kind: r.get(1),
+                reference: r.get(2),
+                channel: r.get(3),
+                recipient: r.get(4),
+                success: r.get(5),
+                error: r.get(6),
+                sent_at: parse_datetime_string(&r.get::<usize, String>(7)).unwrap_or(Utc::now()),
+            })
+            .collect())
+    }
+    async fn get_auto_disable_prefs(
+        &self,
+        id: &str,
+    ) -> Result<TokenAutoDisablePrefs, GatewayError> {
+        let row = self
+            .client
+            .query_opt(
+                "SELECT auto_disable_exempt, auto_disabled_at FROM client_token_prefs WHERE token_id = $1",
+                &[&id],
+            )
+            .await
+            .map_err(|e| GatewayError::Config(format!("DB error: {}", e)))?;
+        Ok(row
+            .map(|r| TokenAutoDisablePrefs {
+                exempt: r.get::<usize, bool>(0),
+                disabled_at: r
+                    .get::<usize, Option<String>>(1)
+                    .and_then(|s| parse_datetime_string(&s).ok()),
+            })
+            .unwrap_or_default())
+    }
+
+    async fn set_auto_disable_exempt(&self, id: &str, exempt: bool) -> Result<(), GatewayError> {
+        let now = to_beijing_string(&Utc::now());
+        self.client
+            .execute(
+                "INSERT INTO client_token_prefs (token_id, auto_disable_exempt, updated_at)
+                 VALUES ($1, $2, $3)
+                 ON CONFLICT (token_id) DO UPDATE SET auto_disable_exempt = EXCLUDED.auto_disable_exempt, updated_at = EXCLUDED.updated_at",
+                &[&id, &exempt, &now],
+            )
+            .await
+            .map_err(|e| GatewayError::Config(format!("DB error: {}", e)))?;
+        Ok(())
+    }
+
+    async fn mark_auto_disabled(&self, id: &str, at: DateTime<Utc>) -> Result<(), GatewayError> {
+        let at = to_beijing_string(&at);
+        self.client
+            .execute(
+                "INSERT INTO client_token_prefs (token_id, auto_disabled_at, updated_at)
+                 VALUES ($1, $2, $2)
+                 ON CONFLICT (token_id) DO UPDATE SET auto_disabled_at = EXCLUDED.auto_disabled_at, updated_at = EXCLUDED.updated_at",
+                &[&id, &at],
+            )
+            .await
+            .map_err(|e| GatewayError::Config(format!("DB error: {}", e)))?;
+        Ok(())
+    }
+
+    async fn get_rotation_prefs(&self, id: &str) -> Result<TokenRotationPrefs, GatewayError> {
+        let row = self
+            .client
+            .query_opt(
+                "SELECT rotation_exempt, rotated_at FROM client_token_prefs WHERE token_id = $1",
+                &[&id],
+            )
+            .await
+            .map_err(|e| GatewayError::Config(format!("DB error: {}", e)))?;
+        Ok(row
+            .map(|r| TokenRotationPrefs {
+                exempt: r.get::<usize, bool>(0),
+                rotated_at: r
+                    .get::<usize, Option<String>>(1)
+                    .and_then(|s| parse_datetime_string(&s).ok()),
+            })
+            .unwrap_or_default())
+    }
+
+    async fn set_rotation_exempt(&self, id: &str, exempt: bool) -> Result<(), GatewayError> {
+        let now = to_beijing_string(&Utc::now());
+        self.client
+            .execute(
+                "INSERT INTO client_token_prefs (token_id, rotation_exempt, updated_at)
+                 VALUES ($1, $2, $3)
+                 ON CONFLICT (token_id) DO UPDATE SET rotation_exempt = EXCLUDED.rotation_exempt, updated_at = EXCLUDED.updated_at",
+                &[&id, &exempt, &now],
+            )
+            .await
+            .map_err(|e| GatewayError::Config(format!("DB error: {}", e)))?;
+        Ok(())
+    }
+
+    async fn rotate_token(
+        &self,
+        id: &str,
+        new_token: &str,
+        at: DateTime<Utc>,
+    ) -> Result<bool, GatewayError> {
+        let affected = self
+            .client
+            .execute(
+                "UPDATE client_tokens SET token = $2 WHERE id = $1",
+                &[&id, &new_token],
+            )
+            .await
+            .map_err(|e| GatewayError::Config(format!("DB error: {}", e)))?;
+        if affected == 0 {
+            return Ok(false);
+        }
+        let at = to_beijing_string(&at);
+        self.client
+            .execute(
+                "INSERT INTO client_token_prefs (token_id, rotated_at, updated_at)
+                 VALUES ($1, $2, $2)
+                 ON CONFLICT (token_id) DO UPDATE SET rotated_at = EXCLUDED.rotated_at, updated_at = EXCLUDED.updated_at",
+                &[&id, &at],
+            )
+            .await
+            .map_err(|e| GatewayError::Config(format!("DB error: {}", e)))?;
+        Ok(true)
+    }
+
+    async fn import_token(&self, t: &ClientToken) -> Result<(), GatewayError> {
+        let allowed_models_s = join_allowed_models(&t.allowed_models);
+        let model_blacklist_s = join_allowed_models(&t.model_blacklist);
+        let ip_whitelist_s = encode_json_string_list("ip_whitelist", &t.ip_whitelist)?;
+        let ip_blacklist_s = encode_json_string_list("ip_blacklist", &t.ip_blacklist)?;
+        let expires_s = t.expires_at.as_ref().map(to_beijing_string);
+        let created_s = to_beijing_string(&t.created_at);
+        if let Some(organization_id) = t.organization_id.as_deref() {
+            self.client
+                .execute(
+                    "INSERT INTO organizations (name) VALUES ($1) ON CONFLICT (name) DO NOTHING",
+                    &[&organization_id],
+                )
+                .await
+                .map_err(|e| GatewayError::Config(format!("DB error: {}", e)))?;
+        }
+        self.client
+            .execute(
+                "INSERT INTO client_tokens (id, user_id, name, token, allowed_models, max_tokens, enabled, expires_at, created_at, max_amount, amount_spent, prompt_tokens_spent, completion_tokens_spent, total_tokens_spent, remark, organization_id, ip_whitelist, ip_blacklist, model_blacklist, allow_streaming, sandbox, strip_reasoning, usage_webhook_url, signing_secret, require_signature, parent_token_id, allow_debug_capture, allow_provider_override, auto_truncate_prompt, semantic_cache, allow_login_codes, max_requests, max_requests_per_day, watermark_responses, queue_weight, usage_webhook_secret) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21, $22, $23, $24, $25, $26, $27, $28, $29, $30, $31, $32, $33, $34, $35, $36) ON CONFLICT (token) DO NOTHING",
+                &[&t.id, &t.user_id, &t.name, &t.token, &allowed_models_s, &t.max_tokens, &t.enabled, &expires_s, &created_s, &t.max_amount, &t.amount_spent, &t.prompt_tokens_spent, &t.completion_tokens_spent, &t.total_tokens_spent, &t.remark, &t.organization_id, &ip_whitelist_s, &ip_blacklist_s, &model_blacklist_s, &t.allow_streaming, &t.sandbox, &t.strip_reasoning, &t.usage_webhook_url, &t.signing_secret, &t.require_signature, &t.parent_token_id, &t.allow_debug_capture, &t.allow_provider_override, &t.auto_truncate_prompt, &t.semantic_cache, &t.allow_login_codes, &t.max_requests, &t.max_requests_per_day, &t.watermark_responses, &t.queue_weight, &t.usage_webhook_secret],
+            )
+            .await
+            .map_err(|e| GatewayError::Config(format!("DB error: {}", e)))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn create_token_payload_default_matches_empty_request() {
+        let parsed: CreateTokenPayload = serde_json::from_str("{}").unwrap();
+        assert_eq!(
+            format!("{:?}", parsed),
+            format!("{:?}", CreateTokenPayload::default())
+        );
+    }
 }
@@ -0,0 +1,447 @@
+//! `gateway bench`：对运行中的网关发起并发聊天请求，统计吞吐、延迟分位数与错误率。
+//! 搭配 mock 供应商（模型 `mock-chat`）可在发布前度量分发与日志链路的性能回归。
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use gateway::client::{ChatCompletionRequest, ChatMessage, ClientError, GatewayClient};
+use serde::Serialize;
+
+use crate::error::GatewayError;
+
+const USAGE: &str = "usage: gateway bench --token <client-token> [options]
+
+options:
+  --url <base-url>        gateway base URL (default http://127.0.0.1:8080)
+  --token <token>         client token, or set GW_BENCH_TOKEN
+  --model <model>         model to request (default mock-chat)
+  --mode <mode>           once | stream | mixed (default once)
+  --concurrency <n>       concurrent workers (default 8)
+  --requests <n>          total requests (default 200)
+  --duration <secs>       run for a fixed time instead of a request count
+  --prompt <text>         user message (default \"ping\")
+  --max-tokens <n>        max_tokens sent with each request
+  --timeout <secs>        per-request timeout (default 60)
+  --json                  print the report as JSON";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BenchMode {
+    Once,
+    Stream,
+    /// 交替发送流式与非流式请求
+    Mixed,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchOptions {
+    pub url: String,
+    pub token: String,
+    pub model: String,
+    pub mode: BenchMode,
+    pub concurrency: usize,
+    pub requests: Option<u64>,
+    pub duration: Option<Duration>,
+    pub prompt: String,
+    pub max_tokens: Option<u32>,
+    pub timeout: Duration,
+    pub json: bool,
+}
+
+fn parse_value<T: std::str::FromStr>(flag: &str, raw: Option<String>) -> Result<T, String> {
+    let raw = raw.ok_or_else(|| format!("{} requires a value", flag))?;
+    raw.parse()
+        .map_err(|_| format!("invalid value for {}: {}", flag, raw))
+}
+
+impl BenchOptions {
+    /// 解析 `bench` 之后的参数；出错时返回带用法说明的错误信息
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut opts = BenchOptions {
+            url: "http://127.0.0.1:8080".into(),
+            token: std::env::var("GW_BENCH_TOKEN").unwrap_or_default(),
+            model: "mock-chat".into(),
+            mode: BenchMode::Once,
+            concurrency: 8,
+            requests: None,
+            duration: None,
+            prompt: "ping".into(),
+            max_tokens: None,
+            timeout: Duration::from_secs(60),
+            json: false,
+        };
+        let mut args = args.into_iter();
+        while let Some(flag) = args.next() {
+            match flag.as_str() {
+                "--url" => opts.url = parse_value(&flag, args.next())?,
+                "--token" => opts.token = parse_value(&flag, args.next())?,
+                "--model" => opts.model = parse_value(&flag, args.next())?,
+                "--prompt" => opts.prompt = parse_value(&flag, args.next())?,
+                "--mode" => {
+                    opts.mode = match args.next().as_deref() {
+                        Some("once") => BenchMode::Once,
+                        Some("stream") => BenchMode::Stream,
+                        Some("mixed") => BenchMode::Mixed,
+                        other => {
+                            return Err(format!("invalid value for --mode: {:?}", other));
+                        }
+                    }
+                }
+                "--concurrency" => opts.concurrency = parse_value(&flag, args.next())?,
+                "--requests" => opts.requests = Some(parse_value(&flag, args.next())?),
+                "--duration" => {
+                    opts.duration = Some(Duration::from_secs(parse_value(&flag, args.next())?))
+                }
+                "--max-tokens" => opts.max_tokens = Some(parse_value(&flag, args.next())?),
+                "--timeout" => opts.timeout = Duration::from_secs(parse_value(&flag, args.next())?),
+                "--json" => opts.json = true,
+                "-h" | "--help" => return Err(USAGE.into()),
+                other => return Err(format!("unknown option: {}\n\n{}", other, USAGE)),
+            }
+        }
+        if opts.token.trim().is_empty() {
+            return Err(format!("missing --token\n\n{}", USAGE));
+        }
+        if opts.concurrency == 0 {
+            return Err("--concurrency must be at least 1".into());
+        }
+        if opts.requests.is_some() && opts.duration.is_some() {
+            return Err("--requests and --duration are mutually exclusive".into());
+        }
+        if opts.requests.is_none() && opts.duration.is_none() {
+            opts.requests = Some(200);
+        }
+        opts.url = opts.url.trim_end_matches('/').to_string();
+        Ok(opts)
+    }
+
+    fn stream_for(&self, index: u64) -> bool {
+        match self.mode {
+            BenchMode::Once => false,
+            BenchMode::Stream => true,
+            BenchMode::Mixed => index % 2 == 1,
+        }
+    }
+}
+
+/// 单次请求的结果
+#[derive(Debug, Clone)]
+struct Sample {
+    stream: bool,
+    latency: Duration,
+    /// 流式请求收到首个数据块（chunk）的耗时
+    first_byte: Option<Duration>,
+    /// 成功时为 None；失败时为状态码、"transport" 或 "decode"
+    error: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct LatencySummary {
+    pub p50_ms: f64,
+    pub p90_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+    pub mean_ms: f64,
+}
+
+impl LatencySummary {
+    fn from_samples(mut values: Vec<Duration>) -> Option<Self> {
+        if values.is_empty() {
+            return None;
+        }
+        values.sort();
+        let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+        let total: Duration = values.iter().sum();
+        Some(Self {
+            p50_ms: ms(percentile(&values, 50.0)),
+            p90_ms: ms(percentile(&values, 90.0)),
+            p99_ms: ms(percentile(&values, 99.0)),
+            max_ms: ms(*values.last().unwrap()),
+            mean_ms: ms(total / values.len() as u32),
+        })
+    }
+}
+
+/// 最近秩法分位数（values 须已排序且非空）
+fn percentile(values: &[Duration], p: f64) -> Duration {
+    let rank = ((p / 100.0) * values.len() as f64).ceil() as usize;
+    values[rank.clamp(1, values.len()) - 1]
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BenchReport {
+    pub model: String,
+    pub concurrency: usize,
+    pub total: u64,
+    pub succeeded: u64,
+    pub failed: u64,
+    pub error_rate: f64,
+    pub elapsed_secs: f64,
+    pub throughput_rps: f64,
+    pub latency: Option<LatencySummary>,
+    /// 仅统计流式请求
+    pub time_to_first_byte: Option<LatencySummary>,
+    /// 错误分类（HTTP 状态码、transport 或 decode）-> 次数
+    pub errors: BTreeMap<String, u64>,
+}
+
+impl BenchReport {
+    fn from_samples(opts: &BenchOptions, samples: &[Sample], elapsed: Duration) -> Self {
+        let total = samples.len() as u64;
+        let mut errors = BTreeMap::new();
+        for e in samples.iter().filter_map(|s| s.error.as_ref()) {
+            *errors.entry(e.clone()).or_insert(0) += 1;
+        }
+        let failed: u64 = errors.values().sum();
+        let ok = samples.iter().filter(|s| s.error.is_none());
+        let elapsed_secs = elapsed.as_secs_f64();
+        Self {
+            model: opts.model.clone(),
+            concurrency: opts.concurrency,
+            total,
+            succeeded: total - failed,
+            failed,
+            error_rate: if total == 0 {
+                0.0
+            } else {
+                failed as f64 / total as f64
+            },
+            elapsed_secs,
+            throughput_rps: if elapsed_secs > 0.0 {
+                total as f64 / elapsed_secs
+            } else {
+                0.0
+            },
+            latency: LatencySummary::from_samples(ok.clone().map(|s| s.latency).collect()),
+            time_to_first_byte: LatencySummary::from_samples(
+                ok.filter(|s| s.stream)
+                    .filter_map(|s| s.first_byte)
+                    .collect(),
+            ),
+            errors,
+        }
+    }
+}
+
+impl fmt::Display for BenchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "model {} | concurrency {} | {:.2}s",
+            self.model, self.concurrency, self.elapsed_secs
+        )?;
+        writeln!(
+            f,
+            "requests {} | ok {} | failed {} ({:.2}%) | {:.1} req/s",
+            self.total,
+            self.succeeded,
+            self.failed,
+            self.error_rate * 100.0,
+            self.throughput_rps
+        )?;
+        let mut line = |label: &str, s: &Option<LatencySummary>| match s {
+            Some(s) => writeln!(
+                f,
+                "{:<8} p50 {:.1}ms | p90 {:.1}ms | p99 {:.1}ms | max {:.1}ms | mean {:.1}ms",
+                label, s.p50_ms, s.p90_ms, s.p99_ms, s.max_ms, s.mean_ms
+            ),
+            None => Ok(()),
+        };
+        line("latency", &self.latency)?;
+        line("ttfb", &self.time_to_first_byte)?;
+        for (kind, count) in &self.errors {
+            writeln!(f, "error {}: {}", kind, count)?;
+        }
+        Ok(())
+    }
+}
+
+/// 失败分类：网关错误取 HTTP 状态码，其余为 transport / decode
+fn error_kind(err: &ClientError) -> String {
+    match err {
+        ClientError::Api { status, .. } => status.to_string(),
+        ClientError::Decode(_) => "decode".into(),
+        ClientError::Transport(_) | ClientError::BaseUrl(_) => "transport".into(),
+    }
+}
+
+async fn send_one(client: &GatewayClient, opts: &BenchOptions, stream: bool) -> Sample {
+    let mut request =
+        ChatCompletionRequest::new(&opts.model, vec![ChatMessage::user(&opts.prompt)]);
+    request.max_tokens = opts.max_tokens;
+    let start = Instant::now();
+    let sample = |first_byte, error| Sample {
+        stream,
+        latency: start.elapsed(),
+        first_byte,
+        error,
+    };
+    if !stream {
+        return match client.chat_completion(&request).await {
+            Ok(_) => sample(None, None),
+            Err(err) => sample(None, Some(error_kind(&err))),
+        };
+    }
+    let mut chunks = match client.chat_completion_stream(&request).await {
+        Ok(chunks) => chunks,
+        Err(err) => return sample(None, Some(error_kind(&err))),
+    };
+    let mut first_byte = None;
+    while let Some(chunk) = chunks.next().await {
+        if let Err(err) = chunk {
+            return sample(first_byte, Some(error_kind(&err)));
+        }
+        first_byte.get_or_insert_with(|| start.elapsed());
+    }
+    sample(first_byte, None)
+}
+
+pub async fn run(opts: BenchOptions) -> Result<BenchReport, GatewayError> {
+    let http = reqwest::Client::builder()
+        .timeout(opts.timeout)
+        .pool_max_idle_per_host(opts.concurrency)
+        .build()?;
+    let client = GatewayClient::with_http(http, &opts.url)
+        .map_err(|e| GatewayError::Config(e.to_string()))?
+        .with_token(&opts.token);
+    let opts = Arc::new(opts);
+    let next = Arc::new(AtomicU64::new(0));
+    let started = Instant::now();
+    let deadline = opts.duration.map(|d| started + d);
+
+    let workers: Vec<_> = (0..opts.concurrency)
+        .map(|_| {
+            let client = client.clone();
+            let opts = opts.clone();
+            let next = next.clone();
+            tokio::spawn(async move {
+                let mut samples = Vec::new();
+                loop {
+                    if deadline.is_some_and(|d| Instant::now() >= d) {
+                        break;
+                    }
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    if opts.requests.is_some_and(|n| index >= n) {
+                        break;
+                    }
+                    samples.push(send_one(&client, &opts, opts.stream_for(index)).await);
+                }
+                samples
+            })
+        })
+        .collect();
+
+    let mut samples = Vec::new();
+    for worker in workers {
+        samples.extend(
+            worker
+                .await
+                .map_err(|e| GatewayError::Config(format!("bench worker failed: {}", e)))?,
+        );
+    }
+    Ok(BenchReport::from_samples(
+        &opts,
+        &samples,
+        started.elapsed(),
+    ))
+}
+
+/// 命令行入口：解析参数、执行压测并打印报告；参数错误时返回退出码 2
+pub async fn main(args: impl IntoIterator<Item = String>) -> Result<(), GatewayError> {
+    let opts = match BenchOptions::parse(args) {
+        Ok(opts) => opts,
+        Err(msg) => {
+            eprintln!("{}", msg);
+            std::process::exit(2);
+        }
+    };
+    let json = opts.json;
+    let report = run(opts).await?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print!("{}", report);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(raw: &[&str]) -> Vec<String> {
+        raw.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn parses_options_and_rejects_conflicts() {
+        let opts = BenchOptions::parse(args(&[
+            "--token",
+            "atk_x",
+            "--url",
+            "http://gw:8080/",
+            "--mode",
+            "mixed",
+            "--concurrency",
+            "4",
+            "--duration",
+            "10",
+        ]))
+        .unwrap();
+        assert_eq!(opts.url, "http://gw:8080");
+        assert_eq!(opts.mode, BenchMode::Mixed);
+        assert_eq!(opts.concurrency, 4);
+        assert_eq!(opts.duration, Some(Duration::from_secs(10)));
+        assert_eq!(opts.requests, None);
+        assert!(!opts.stream_for(0) && opts.stream_for(1));
+
+        let default_count = BenchOptions::parse(args(&["--token", "t"])).unwrap();
+        assert_eq!(default_count.requests, Some(200));
+        assert!(
+            BenchOptions::parse(args(&[
+                "--token",
+                "t",
+                "--requests",
+                "1",
+                "--duration",
+                "1"
+            ]))
+            .is_err()
+        );
+        assert!(BenchOptions::parse(args(&["--token", "t", "--mode", "burst"])).is_err());
+        assert!(BenchOptions::parse(args(&["--token", "t", "--concurrency", "x"])).is_err());
+    }
+
+    #[test]
+    fn report_computes_percentiles_and_error_rate() {
+        let opts = BenchOptions::parse(args(&["--token", "t"])).unwrap();
+        let mut samples: Vec<Sample> = (1..=100)
+            .map(|ms| Sample {
+                stream: ms % 2 == 0,
+                latency: Duration::from_millis(ms),
+                first_byte: Some(Duration::from_millis(ms / 2)),
+                error: None,
+            })
+            .collect();
+        samples.push(Sample {
+            stream: false,
+            latency: Duration::from_millis(5000),
+            first_byte: None,
+            error: Some("502".into()),
+        });
+        let report = BenchReport::from_samples(&opts, &samples, Duration::from_secs(2));
+        assert_eq!(
+            (report.total, report.succeeded, report.failed),
+            (101, 100, 1)
+        );
+        assert_eq!(report.errors.get("502"), Some(&1));
+        let latency = report.latency.unwrap();
+        assert_eq!(latency.p50_ms, 50.0);
+        assert_eq!(latency.p99_ms, 99.0);
+        assert_eq!(latency.max_ms, 100.0);
+        assert!((report.throughput_rps - 50.5).abs() < 1e-9);
+        assert_eq!(report.time_to_first_byte.unwrap().max_ms, 50.0);
+    }
+}
@@ -0,0 +1,368 @@
+//! 类型化的网关 HTTP 客户端：覆盖 /v1 数据面（聊天、模型、令牌余额/用量）与常用管理面接口
+//! （客户端令牌、供应商、能力矩阵、指标汇总），字段与 openapi.yaml 保持一致。
+//!
+//! 数据面使用 Client Token，管理面使用管理员 AccessToken 或 TUI Session Token，均以 bearer 方式发送：
+//!
+//! ```no_run
+//! # async fn demo() -> Result<(), gateway::client::ClientError> {
+//! use gateway::client::{ChatCompletionRequest, ChatMessage, GatewayClient};
+//!
+//! let client = GatewayClient::new("http://127.0.0.1:8080")?.with_token("atk_xxx");
+//! let reply = client
+//!     .chat_completion(&ChatCompletionRequest::new("mock-chat", vec![ChatMessage::user("ping")]))
+//!     .await?;
+//! println!("{}", reply.text().unwrap_or_default());
+//! # Ok(())
+//! # }
+//! ```
+
+mod stream;
+mod types;
+
+pub use stream::ChatStream;
+pub use types::*;
+
+use std::time::Duration;
+
+use reqwest::{Method, RequestBuilder, Response};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ClientError {
+    #[error("transport error: {0}")]
+    Transport(#[from] reqwest::Error),
+    /// 网关返回的非 2xx 响应；code/message 取自错误体 `{"code","message"}`
+    #[error("gateway returned {status} ({code}): {message}")]
+    Api {
+        status: u16,
+        code: String,
+        message: String,
+    },
+    #[error("invalid response body: {0}")]
+    Decode(#[from] serde_json::Error),
+    #[error("invalid base url: {0}")]
+    BaseUrl(String),
+}
+
+impl ClientError {
+    /// 网关返回的 HTTP 状态码（传输或解码错误时为 None）
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            ClientError::Api { status, .. } => Some(*status),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GatewayClient {
+    http: reqwest::Client,
+    base_url: String,
+    token: Option<String>,
+}
+
+impl GatewayClient {
+    /// 使用默认的 reqwest 客户端（60 秒超时）
+    pub fn new(base_url: impl Into<String>) -> Result<Self, ClientError> {
+        let http = reqwest::Client::builder()
+            .timeout(Duration::from_secs(60))
+            .build()?;
+        Self::with_http(http, base_url)
+    }
+
+    /// 复用调用方配置好的 reqwest 客户端（连接池、超时、代理等）
+    pub fn with_http(
+        http: reqwest::Client,
+        base_url: impl Into<String>,
+    ) -> Result<Self, ClientError> {
+        let base_url = base_url.into().trim_end_matches('/').to_string();
+        if !(base_url.starts_with("http://") || base_url.starts_with("https://")) {
+            return Err(ClientError::BaseUrl(base_url));
+        }
+        Ok(Self {
+            http,
+            base_url,
+            token: None,
+        })
+    }
+
+    /// 设置 bearer 凭据：数据面为 Client Token，管理面为管理员 AccessToken / TUI Session Token
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        let builder = self
+            .http
+            .request(method, format!("{}{}", self.base_url, path));
+        match self.token.as_deref() {
+            Some(token) => builder.bearer_auth(token),
+            None => builder,
+        }
+    }
+
+    /// 发送请求；非 2xx 时解析错误体为 ClientError::Api
+    async fn send(&self, builder: RequestBuilder) -> Result<Response, ClientError> {
+        let resp = builder.send().await?;
+        let status = resp.status();
+        if status.is_success() {
+            return Ok(resp);
+        }
+        let body = resp.text().await.unwrap_or_default();
+        let (code, message) = types::parse_error_body(&body);
+        Err(ClientError::Api {
+            status: status.as_u16(),
+            code: code.unwrap_or_else(|| "http_error".into()),
+            message: message.unwrap_or(body),
+        })
+    }
+
+    async fn json<T: DeserializeOwned>(&self, builder: RequestBuilder) -> Result<T, ClientError> {
+        let bytes = self.send(builder).await?.bytes().await?;
+        Ok(serde_json::from_slice(&bytes)?)
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T, ClientError> {
+        self.json(self.request(Method::GET, path)).await
+    }
+
+    async fn with_body<B: Serialize + ?Sized, T: DeserializeOwned>(
+        &self,
+        method: Method,
+        path: &str,
+        body: &B,
+    ) -> Result<T, ClientError> {
+        self.json(self.request(method, path).json(body)).await
+    }
+
+    // ---------------- 数据面（Client Token） ----------------
+
+    /// 非流式聊天补全（忽略请求中的 stream 字段）
+    pub async fn chat_completion(
+        &self,
+        request: &ChatCompletionRequest,
+    ) -> Result<ChatCompletion, ClientError> {
+        let request = ChatCompletionRequest {
+            stream: Some(false),
+            ..request.clone()
+        };
+        self.with_body(Method::POST, "/v1/chat/completions", &request)
+            .await
+    }
+
+    /// 流式聊天补全：逐个返回 SSE 数据块，直到 `[DONE]`
+    pub async fn chat_completion_stream(
+        &self,
+        request: &ChatCompletionRequest,
+    ) -> Result<ChatStream, ClientError> {
+        let request = ChatCompletionRequest {
+            stream: Some(true),
+            ..request.clone()
+        };
+        let resp = self
+            .send(
+                self.request(Method::POST, "/v1/chat/completions")
+                    .json(&request),
+            )
+            .await?;
+        Ok(ChatStream::new(resp))
+    }
+
+    pub async fn list_models(&self) -> Result<ModelList, ClientError> {
+        self.get("/v1/models").await
+    }
+
+    pub async fn token_balance(&self) -> Result<TokenBalance, ClientError> {
+        self.get("/v1/token/balance").await
+    }
+
+    /// 最近的聊天用量明细（limit 取值 1..200，默认 20）
+    pub async fn token_usage(&self, limit: Option<u32>) -> Result<TokenUsage, ClientError> {
+        match limit {
+            Some(limit) => self.get(&format!("/v1/token/usage?limit={}", limit)).await,
+            None => self.get("/v1/token/usage").await,
+        }
+    }
+
+    // ---------------- 管理面（管理员身份） ----------------
+
+    pub async fn list_tokens(&self) -> Result<Vec<ClientToken>, ClientError> {
+        self.get::<Listing<ClientToken>>("/admin/tokens")
+            .await
+            .map(Listing::into_items)
+    }
+
+    pub async fn get_token(&self, id: &str) -> Result<ClientToken, ClientError> {
+        self.get(&format!("/admin/tokens/{}", encode_segment(id)))
+            .await
+    }
+
+    pub async fn create_token(&self, payload: &CreateToken) -> Result<ClientToken, ClientError> {
+        self.with_body(Method::POST, "/admin/tokens", payload).await
+    }
+
+    pub async fn update_token(
+        &self,
+        id: &str,
+        payload: &UpdateToken,
+    ) -> Result<ClientToken, ClientError> {
+        let path = format!("/admin/tokens/{}", encode_segment(id));
+        self.with_body(Method::PUT, &path, payload).await
+    }
+
+    pub async fn set_token_enabled(&self, id: &str, enabled: bool) -> Result<(), ClientError> {
+        let path = format!("/admin/tokens/{}/toggle", encode_segment(id));
+        self.send(
+            self.request(Method::POST, &path)
+                .json(&serde_json::json!({ "enabled": enabled })),
+        )
+        .await
+        .map(drop)
+    }
+
+    pub async fn delete_token(&self, id: &str) -> Result<(), ClientError> {
+        let path = format!("/admin/tokens/{}", encode_segment(id));
+        self.send(self.request(Method::DELETE, &path))
+            .await
+            .map(drop)
+    }
+
+    pub async fn list_providers(&self) -> Result<Vec<ProviderInfo>, ClientError> {
+        self.get::<Listing<ProviderInfo>>("/providers")
+            .await
+            .map(Listing::into_items)
+    }
+
+    pub async fn get_provider(&self, name: &str) -> Result<ProviderInfo, ClientError> {
+        self.get(&format!("/providers/{}", encode_segment(name)))
+            .await
+    }
+
+    pub async fn set_provider_enabled(&self, name: &str, enabled: bool) -> Result<(), ClientError> {
+        let path = format!("/providers/{}/toggle", encode_segment(name));
+        self.send(
+            self.request(Method::POST, &path)
+                .json(&serde_json::json!({ "enabled": enabled })),
+        )
+        .await
+        .map(drop)
+    }
+
+    pub async fn provider_capabilities(
+        &self,
+        name: &str,
+    ) -> Result<ProviderCapabilities, ClientError> {
+        self.get(&format!(
+            "/admin/providers/{}/capabilities",
+            encode_segment(name)
+        ))
+        .await
+    }
+
+    /// 最近 window_minutes 分钟的请求汇总（默认由网关决定）
+    pub async fn metrics_summary(
+        &self,
+        window_minutes: Option<i64>,
+    ) -> Result<MetricsSummary, ClientError> {
+        match window_minutes {
+            Some(minutes) => {
+                self.get(&format!(
+                    "/admin/metrics/summary?window_minutes={}",
+                    minutes
+                ))
+                .await
+            }
+            None => self.get("/admin/metrics/summary").await,
+        }
+    }
+}
+
+/// 路径参数的百分号编码（令牌 ID、供应商名可能含保留字符）
+fn encode_segment(raw: &str) -> String {
+    let mut out = String::with_capacity(raw.len());
+    for byte in raw.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'.' | b'~') {
+            out.push(byte as char);
+        } else {
+            out.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::{HeaderMap, StatusCode};
+    use axum::routing::{get, post};
+    use axum::{Json, Router};
+    use serde_json::{Value, json};
+
+    async fn serve(app: Router) -> GatewayClient {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        GatewayClient::new(format!("http://{}/", addr))
+            .unwrap()
+            .with_token("atk_test")
+    }
+
+    #[tokio::test]
+    async fn decodes_responses_and_gateway_errors() {
+        let app = Router::new()
+            .route(
+                "/v1/chat/completions",
+                post(|headers: HeaderMap, Json(body): Json<Value>| async move {
+                    assert_eq!(headers["authorization"], "Bearer atk_test");
+                    assert_eq!(body["stream"], false);
+                    assert_eq!(body["tools"][0]["type"], "function");
+                    Json(json!({
+                        "id": "c1", "object": "chat.completion", "created": 1, "model": "m1",
+                        "choices": [{"index": 0, "message": {"role": "assistant", "content": "pong"}, "finish_reason": "stop"}],
+                        "usage": {"prompt_tokens": 3, "completion_tokens": 1, "total_tokens": 4}
+                    }))
+                }),
+            )
+            .route(
+                "/admin/tokens",
+                get(|| async { Json(json!({"items": [{"id": "t1", "name": "a", "token": "atk_1", "enabled": true}], "total": 1, "limit": 50})) }),
+            )
+            .route(
+                "/v1/models",
+                get(|| async {
+                    (
+                        StatusCode::TOO_MANY_REQUESTS,
+                        Json(json!({"code": "rate_limited", "message": "slow down"})),
+                    )
+                }),
+            );
+        let client = serve(app).await;
+
+        let mut request = ChatCompletionRequest::new("m1", vec![ChatMessage::user("ping")]);
+        request
+            .extra
+            .insert("tools".into(), json!([{"type": "function"}]));
+        let reply = client.chat_completion(&request).await.unwrap();
+        assert_eq!(reply.text(), Some("pong"));
+        assert_eq!(reply.usage.unwrap().total_tokens, 4);
+
+        let tokens = client.list_tokens().await.unwrap();
+        assert_eq!(tokens[0].id, "t1");
+
+        let err = client.list_models().await.unwrap_err();
+        assert_eq!(err.status(), Some(429));
+        assert!(matches!(err, ClientError::Api { ref code, .. } if code == "rate_limited"));
+    }
+
+    #[test]
+    fn rejects_relative_base_url_and_encodes_segments() {
+        assert!(GatewayClient::new("127.0.0.1:8080").is_err());
+        assert_eq!(encode_segment("team a/b"), "team%20a%2Fb");
+    }
+}
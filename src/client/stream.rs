@@ -0,0 +1,99 @@
+use axum::body::Bytes;
+use futures_util::StreamExt;
+use futures_util::stream::BoxStream;
+
+use super::{ChatCompletionChunk, ClientError};
+
+/// 流式聊天响应：按 SSE 事件解析出 `chat.completion.chunk`，收到 `data: [DONE]` 或连接结束时终止
+pub struct ChatStream {
+    body: BoxStream<'static, reqwest::Result<Bytes>>,
+    buffer: Vec<u8>,
+    done: bool,
+}
+
+impl ChatStream {
+    pub(crate) fn new(resp: reqwest::Response) -> Self {
+        Self::from_bytes(resp.bytes_stream().boxed())
+    }
+
+    fn from_bytes(body: BoxStream<'static, reqwest::Result<Bytes>>) -> Self {
+        Self {
+            body,
+            buffer: Vec::new(),
+            done: false,
+        }
+    }
+
+    /// 下一个数据块；流结束时返回 None
+    pub async fn next(&mut self) -> Option<Result<ChatCompletionChunk, ClientError>> {
+        loop {
+            if self.done {
+                return None;
+            }
+            if let Some(data) = self.next_data() {
+                if data == "[DONE]" {
+                    self.done = true;
+                    return None;
+                }
+                return Some(serde_json::from_str(&data).map_err(ClientError::from));
+            }
+            match self.body.next().await {
+                Some(Ok(bytes)) => self.buffer.extend_from_slice(&bytes),
+                Some(Err(err)) => {
+                    self.done = true;
+                    return Some(Err(err.into()));
+                }
+                None => {
+                    self.done = true;
+                    return None;
+                }
+            }
+        }
+    }
+
+    /// 从缓冲区取出下一个完整事件的 data 字段（多行 data 以换行拼接；忽略注释与其他字段）
+    fn next_data(&mut self) -> Option<String> {
+        loop {
+            let end = self.buffer.windows(2).position(|w| w == b"\n\n")?;
+            let event: Vec<u8> = self.buffer.drain(..end + 2).collect();
+            let text = String::from_utf8_lossy(&event).replace('\r', "");
+            let data: Vec<&str> = text
+                .lines()
+                .filter_map(|line| line.strip_prefix("data:"))
+                .map(|v| v.strip_prefix(' ').unwrap_or(v))
+                .collect();
+            if !data.is_empty() {
+                return Some(data.join("\n"));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn parses_events_split_across_reads() {
+        let parts: Vec<reqwest::Result<Bytes>> = vec![
+            Ok(Bytes::from(
+                ": keep-alive\n\ndata: {\"id\":\"c1\",\"choices\":[{\"index\":0,",
+            )),
+            Ok(Bytes::from(
+                "\"delta\":{\"content\":\"po\"}}]}\n\ndata: {\"choices\":[{\"delta\":{\"content\":\"ng\"},\"finish_reason\":\"stop\"}]}\n\n",
+            )),
+            Ok(Bytes::from("data: [DONE]\n\ndata: {\"ignored\":true}\n\n")),
+        ];
+        let mut stream = ChatStream::from_bytes(futures_util::stream::iter(parts).boxed());
+        let mut text = String::new();
+        let mut finish = None;
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk.unwrap();
+            text.push_str(chunk.choices[0].delta.content.as_deref().unwrap_or(""));
+            finish = chunk.choices[0].finish_reason.clone().or(finish);
+        }
+        assert_eq!(text, "pong");
+        assert_eq!(finish.as_deref(), Some("stop"));
+        assert!(stream.next().await.is_none());
+    }
+}
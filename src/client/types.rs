@@ -0,0 +1,378 @@
+//! 网关 API 的请求/响应类型（与 openapi.yaml 对应）。
+//! 响应类型对缺失字段取默认值、忽略未知字段，网关新增字段不会破坏旧客户端。
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+// ---------------- 聊天 ----------------
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub role: String,
+    /// 文本或多模态内容数组
+    #[serde(default)]
+    pub content: Value,
+    /// tool_calls、name 等其余字段原样保留
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl ChatMessage {
+    pub fn new(role: impl Into<String>, content: impl Into<String>) -> Self {
+        Self {
+            role: role.into(),
+            content: Value::String(content.into()),
+            extra: Map::new(),
+        }
+    }
+
+    pub fn system(content: impl Into<String>) -> Self {
+        Self::new("system", content)
+    }
+
+    pub fn user(content: impl Into<String>) -> Self {
+        Self::new("user", content)
+    }
+
+    pub fn assistant(content: impl Into<String>) -> Self {
+        Self::new("assistant", content)
+    }
+
+    /// 文本内容（多模态内容返回 None）
+    pub fn text(&self) -> Option<&str> {
+        self.content.as_str()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChatCompletionRequest {
+    /// `provider/model` 或模型名
+    pub model: String,
+    pub messages: Vec<ChatMessage>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stream: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<u32>,
+    /// tools、response_format、seed 等其余 OpenAI 兼容参数
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl ChatCompletionRequest {
+    pub fn new(model: impl Into<String>, messages: Vec<ChatMessage>) -> Self {
+        Self {
+            model: model.into(),
+            messages,
+            stream: None,
+            temperature: None,
+            max_tokens: None,
+            top_p: None,
+            top_k: None,
+            extra: Map::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Usage {
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+    pub total_tokens: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChatChoice {
+    #[serde(default)]
+    pub index: u32,
+    pub message: ChatMessage,
+    #[serde(default)]
+    pub finish_reason: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChatCompletion {
+    #[serde(default)]
+    pub id: String,
+    #[serde(default)]
+    pub object: String,
+    #[serde(default)]
+    pub created: i64,
+    #[serde(default)]
+    pub model: String,
+    #[serde(default)]
+    pub choices: Vec<ChatChoice>,
+    #[serde(default)]
+    pub usage: Option<Usage>,
+}
+
+impl ChatCompletion {
+    /// 第一个候选的文本内容
+    pub fn text(&self) -> Option<&str> {
+        self.choices.first().and_then(|c| c.message.text())
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChunkDelta {
+    pub role: Option<String>,
+    pub content: Option<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChunkChoice {
+    pub index: u32,
+    pub delta: ChunkDelta,
+    pub finish_reason: Option<String>,
+}
+
+/// 流式响应中的一个 `chat.completion.chunk`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChatCompletionChunk {
+    pub id: String,
+    pub object: String,
+    pub created: i64,
+    pub model: String,
+    pub choices: Vec<ChunkChoice>,
+    pub usage: Option<Usage>,
+}
+
+// ---------------- 模型与令牌 ----------------
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ModelInfo {
+    pub id: String,
+    pub object: String,
+    pub created: i64,
+    pub owned_by: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ModelList {
+    pub object: String,
+    pub data: Vec<ModelInfo>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TokenBalance {
+    pub token: String,
+    pub amount_spent: f64,
+    pub max_amount: Option<f64>,
+    pub remaining: Option<f64>,
+    pub total_tokens_spent: i64,
+    pub max_tokens: Option<i64>,
+    /// 请求次数配额状态（未设置配额时为 null）
+    pub requests: Option<Value>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UsageItem {
+    /// RFC3339（UTC）
+    pub timestamp: String,
+    pub provider: Option<String>,
+    pub model: Option<String>,
+    pub status_code: u16,
+    pub response_time_ms: i64,
+    pub prompt_tokens: Option<u32>,
+    pub completion_tokens: Option<u32>,
+    pub total_tokens: Option<u32>,
+    pub amount_spent: Option<f64>,
+    pub error_message: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TokenUsage {
+    pub token: String,
+    pub limit: u32,
+    pub total_cost: f64,
+    pub prompt_tokens_spent: i64,
+    pub completion_tokens_spent: i64,
+    pub total_tokens_spent: i64,
+    pub items: Vec<UsageItem>,
+}
+
+// ---------------- 管理面 ----------------
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClientToken {
+    pub id: String,
+    pub user_id: Option<String>,
+    pub name: String,
+    pub token: String,
+    pub allowed_models: Option<Vec<String>>,
+    pub model_blacklist: Option<Vec<String>>,
+    pub max_amount: Option<f64>,
+    pub amount_spent: f64,
+    pub prompt_tokens_spent: i64,
+    pub completion_tokens_spent: i64,
+    pub total_tokens_spent: i64,
+    pub enabled: bool,
+    pub expires_at: Option<String>,
+    pub created_at: String,
+    pub remark: Option<String>,
+    pub organization_id: Option<String>,
+    /// 其余令牌设置（IP 名单、流式开关、配额等）
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct CreateToken {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<String>,
+    /// 为空时由网关生成
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allowed_models: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model_blacklist: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_amount: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    /// RFC3339
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remark: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub organization_id: Option<String>,
+}
+
+/// 部分更新：外层 None 表示不修改，`Some(None)` 表示清空
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct UpdateToken {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allowed_models: Option<Option<Vec<String>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model_blacklist: Option<Option<Vec<String>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_amount: Option<Option<f64>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<Option<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remark: Option<Option<String>>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProviderInfo {
+    pub name: String,
+    pub display_name: Option<String>,
+    pub collection: String,
+    pub api_type: String,
+    pub base_url: String,
+    /// 已脱敏
+    pub api_keys: Vec<String>,
+    pub models_endpoint: Option<String>,
+    pub provider_config: Option<Value>,
+    pub enabled: bool,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+    pub cached_models_count: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ApiVersionInfo {
+    pub effective: Option<String>,
+    pub default: Option<String>,
+    pub supported: Vec<String>,
+    pub format: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FeatureSupport {
+    pub feature: String,
+    /// supported | degraded | dropped
+    pub support: String,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProviderCapabilities {
+    pub provider: String,
+    pub api_type: String,
+    pub api_version: Option<ApiVersionInfo>,
+    /// warn | reject | ignore
+    pub unsupported_features: String,
+    pub features: Vec<FeatureSupport>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TopItem {
+    pub name: String,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MetricsSummary {
+    pub window_minutes: i64,
+    pub total_requests: usize,
+    pub success_requests: usize,
+    pub error_requests: usize,
+    pub error_rate: f64,
+    pub average_latency_ms: f64,
+    pub p95_latency_ms: Option<f64>,
+    pub total_amount_spent: f64,
+    pub total_tokens: u64,
+    pub unique_clients: usize,
+    pub top_providers: Vec<TopItem>,
+    pub top_models: Vec<TopItem>,
+    pub generated_at: String,
+}
+
+/// 列表接口：未分页时为数组，分页时为 `{items,total,limit,next_cursor}` 信封
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub(crate) enum Listing<T> {
+    All(Vec<T>),
+    Page { items: Vec<T> },
+}
+
+impl<T> Listing<T> {
+    pub(crate) fn into_items(self) -> Vec<T> {
+        match self {
+            Listing::All(items) | Listing::Page { items } => items,
+        }
+    }
+}
+
+/// 解析错误体：网关统一格式 `{"code","message"}`，兼容 OpenAI 风格 `{"error":{...}}`
+pub(crate) fn parse_error_body(body: &str) -> (Option<String>, Option<String>) {
+    let Ok(value) = serde_json::from_str::<Value>(body) else {
+        return (None, None);
+    };
+    let object = value
+        .get("error")
+        .filter(|e| e.is_object())
+        .unwrap_or(&value);
+    let field = |name: &str| object.get(name).and_then(Value::as_str).map(str::to_string);
+    (field("code"), field("message"))
+}
@@ -1,7 +1,7 @@
 use crate::error::{GatewayError, Result as AppResult};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::path::Path;
 use std::str::FromStr;
 
@@ -26,10 +26,21 @@ pub struct Settings {
 pub struct ProviderConfig {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub azure_deployment: Option<String>,
+    /// Azure OpenAI：上游模型名 -> deployment 名；未映射的模型回落到 `azure_deployment`
+    #[serde(
+        default,
+        deserialize_with = "deserialize_default_on_null",
+        skip_serializing_if = "BTreeMap::is_empty"
+    )]
+    pub azure_deployments: BTreeMap<String, String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub azure_api_version: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub google_api_version: Option<String>,
+    /// 固定上游 API 版本：Anthropic 作为 anthropic-version 请求头发送；
+    /// Azure / Gemini 未填写专用版本字段时使用此值
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_version: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub aws_region: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
@@ -56,6 +67,30 @@ pub struct ProviderConfig {
     pub xf_spark_api_key: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub xf_spark_api_secret: Option<String>,
+    /// mock：每次响应前的固定延迟（毫秒）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mock_latency_ms: Option<u64>,
+    /// mock：请求失败概率（0-100）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mock_failure_percent: Option<u8>,
+    /// mock：固定回复内容；为空时回显最后一条用户消息
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mock_reply: Option<String>,
+    /// mock：上报的 completion tokens；为空时按回复长度估算
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mock_completion_tokens: Option<u32>,
+    /// 上游证书固定：叶子证书公钥的 SPKI 哈希（`sha256/<base64>`），见 `tls_pinning`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tls_pins: Vec<String>,
+    /// 提示（messages 序列化后）字节数上限，超出时分发前返回 413；见 `payload_limits`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_prompt_bytes: Option<u64>,
+    /// 提示估算 token 数上限（按字符数 / 4 估算），超出时分发前返回 413
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_prompt_tokens: Option<u32>,
+    /// 按令牌/模型优先使用带指定标签的密钥，见 `routing::key_labels`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub key_label_rules: Vec<crate::routing::KeyLabelRule>,
 }
 
 impl ProviderConfig {
@@ -65,6 +100,7 @@ impl ProviderConfig {
             .map(str::trim)
             .filter(|value| !value.is_empty())
             .is_none()
+            && self.azure_deployments.is_empty()
             && self
                 .azure_api_version
                 .as_deref()
@@ -77,6 +113,12 @@ impl ProviderConfig {
                 .map(str::trim)
                 .filter(|value| !value.is_empty())
                 .is_none()
+            && self
+                .api_version
+                .as_deref()
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .is_none()
             && self
                 .aws_region
                 .as_deref()
@@ -155,6 +197,19 @@ impl ProviderConfig {
                 .map(str::trim)
                 .filter(|value| !value.is_empty())
                 .is_none()
+            && self.mock_latency_ms.is_none()
+            && self.mock_failure_percent.is_none()
+            && self
+                .mock_reply
+                .as_deref()
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .is_none()
+            && self.mock_completion_tokens.is_none()
+            && self.tls_pins().is_empty()
+            && self.max_prompt_bytes.is_none()
+            && self.max_prompt_tokens.is_none()
+            && self.key_label_rules.is_empty()
     }
 
     pub fn azure_deployment(&self) -> Option<&str> {
@@ -164,6 +219,15 @@ impl ProviderConfig {
             .filter(|value| !value.is_empty())
     }
 
+    /// 按上游模型名选择 Azure deployment：优先使用映射，其次使用默认 deployment
+    pub fn azure_deployment_for(&self, model: &str) -> Option<&str> {
+        self.azure_deployments
+            .get(model.trim())
+            .map(|value| value.trim())
+            .filter(|value| !value.is_empty())
+            .or_else(|| self.azure_deployment())
+    }
+
     pub fn azure_api_version(&self) -> Option<&str> {
         self.azure_api_version
             .as_deref()
@@ -178,6 +242,13 @@ impl ProviderConfig {
             .filter(|value| !value.is_empty())
     }
 
+    pub fn api_version(&self) -> Option<&str> {
+        self.api_version
+            .as_deref()
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+    }
+
     pub fn aws_region(&self) -> Option<&str> {
         self.aws_region
             .as_deref()
@@ -268,6 +339,10 @@ impl ProviderConfig {
         self.baidu_access_key().is_some() && self.baidu_secret_key().is_some()
     }
 
+    pub fn tls_pins(&self) -> &[String] {
+        &self.tls_pins
+    }
+
     pub fn to_storage_json(&self) -> Option<String> {
         if self.is_empty() {
             return None;
@@ -383,6 +458,11 @@ pub enum ProviderType {
     XfSpark,
     ThreeSixtyZhinao,
     StepFun,
+    /// 本地 OpenAI 兼容运行时（Ollama / vLLM / LM Studio）：可不配置密钥，
+    /// 上游缺少 usage 时由网关估算，模型列表优先读取 Ollama 的 `/api/tags`
+    Local,
+    /// 进程内模拟供应商：不发起网络请求，用于本地开发与 CI
+    Mock,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -408,6 +488,7 @@ pub enum ProviderProtocolFamily {
     VertexAI,
     Cohere,
     Zhipu,
+    Mock,
     Unsupported,
 }
 
@@ -450,6 +531,8 @@ impl ProviderType {
             ProviderType::XfSpark => "xf_spark",
             ProviderType::ThreeSixtyZhinao => "360_zhinao",
             ProviderType::StepFun => "stepfun",
+            ProviderType::Local => "local",
+            ProviderType::Mock => "mock",
         }
     }
 
@@ -500,7 +583,9 @@ impl ProviderType {
             | ProviderType::BaiduErnieV2
             | ProviderType::XfSpark
             | ProviderType::ThreeSixtyZhinao
-            | ProviderType::StepFun => ProviderAuthMode::Bearer,
+            | ProviderType::StepFun
+            | ProviderType::Local
+            | ProviderType::Mock => ProviderAuthMode::Bearer,
         }
     }
 
@@ -572,6 +657,14 @@ impl ProviderType {
                 test_connection_family: ProviderProtocolFamily::BaiduErnie,
                 openai_compatible: false,
             },
+            ProviderType::Mock => ProviderCapabilities {
+                auth_mode: self.auth_mode(),
+                supports_auto_model_discovery: true,
+                supports_models_endpoint: false,
+                requires_models_endpoint: false,
+                test_connection_family: ProviderProtocolFamily::Mock,
+                openai_compatible: false,
+            },
             ProviderType::MiniMax
             | ProviderType::BaiduErnieV2
             | ProviderType::XfSpark
@@ -597,7 +690,8 @@ impl ProviderType {
             | ProviderType::Custom
             | ProviderType::XAI
             | ProviderType::Doubao
-            | ProviderType::Yi => ProviderCapabilities {
+            | ProviderType::Yi
+            | ProviderType::Local => ProviderCapabilities {
                 auth_mode: self.auth_mode(),
                 supports_auto_model_discovery: true,
                 supports_models_endpoint: true,
@@ -611,6 +705,34 @@ impl ProviderType {
     pub fn supports_test_connection(self) -> bool {
         self.capabilities().test_connection_family != ProviderProtocolFamily::Unsupported
     }
+
+    /// 未配置任何密钥时仍可调度（不发送认证头），用于无鉴权的本地运行时
+    pub fn allows_keyless(self) -> bool {
+        matches!(self, ProviderType::Local)
+    }
+
+    /// 上游原生支持 n>1（单次调用返回多个 choices）；其余类型由网关拆分调用
+    pub fn supports_native_n_choices(self) -> bool {
+        matches!(
+            self,
+            ProviderType::OpenAI | ProviderType::AzureOpenAI | ProviderType::Custom
+        )
+    }
+
+    /// 上游提供 OpenAI 兼容的语音转写接口 `/v1/audio/transcriptions`（Whisper 及本地 Whisper 服务）
+    pub fn supports_audio_transcriptions(self) -> bool {
+        matches!(self, ProviderType::OpenAI | ProviderType::Local)
+    }
+
+    /// 上游提供 OpenAI 兼容的语音合成接口 `/v1/audio/speech`（TTS 及本地 TTS 服务）
+    pub fn supports_audio_speech(self) -> bool {
+        matches!(self, ProviderType::OpenAI | ProviderType::Local)
+    }
+
+    /// 上游提供旧版文本补全接口 `/v1/completions`，可直接转发 prompt；其余类型转换为聊天消息
+    pub fn supports_legacy_completions(self) -> bool {
+        matches!(self, ProviderType::OpenAI | ProviderType::Local)
+    }
 }
 
 impl FromStr for ProviderType {
@@ -650,6 +772,10 @@ impl FromStr for ProviderType {
                 Ok(ProviderType::ThreeSixtyZhinao)
             }
             "stepfun" | "step_fun" | "step-fun" => Ok(ProviderType::StepFun),
+            "local" | "ollama" | "vllm" | "lm_studio" | "lm-studio" | "lmstudio" => {
+                Ok(ProviderType::Local)
+            }
+            "mock" => Ok(ProviderType::Mock),
             other => {
                 tracing::warn!(
                     raw_api_type = other,
@@ -755,6 +881,11 @@ mod tests {
             ProviderType::from_str("stepfun").unwrap(),
             ProviderType::StepFun
         );
+        assert_eq!(ProviderType::from_str("mock").unwrap(), ProviderType::Mock);
+        for alias in ["local", "ollama", "vllm", "lm_studio", "LM-Studio"] {
+            assert_eq!(ProviderType::from_str(alias).unwrap(), ProviderType::Local);
+        }
+        assert_eq!(ProviderType::Local.as_str(), "local");
     }
 
     #[test]
@@ -831,6 +962,253 @@ pub struct ServerConfig {
     pub pricing_sync_enabled: bool,
     #[serde(default = "default_pricing_sync_default_ttl_hours")]
     pub pricing_sync_default_ttl_hours: u16,
+    /// 面向用户的错误提示语言：zh-CN（默认）或 en-US
+    #[serde(default)]
+    pub language: crate::i18n::Language,
+    /// 令牌到期前多少天发送提醒；为空表示不发送
+    #[serde(default)]
+    pub token_expiry_notice_days: Option<u32>,
+    /// 令牌通知的 Webhook 地址（POST JSON）；邮件渠道使用 RESEND_API_KEY/RESEND_FROM
+    #[serde(default)]
+    pub notification_webhook_url: Option<String>,
+    /// 连续多少天没有请求的令牌会被自动停用；为空表示不启用该策略
+    #[serde(default)]
+    pub inactive_token_disable_days: Option<u32>,
+    /// 新增管理员公钥的默认有效期（天）；为空表示默认永不过期
+    #[serde(default)]
+    pub admin_key_max_age_days: Option<u32>,
+    /// 管理员公钥到期前多少天通过 Webhook 与 TUI 登录响应提醒轮换；为空表示不提醒
+    #[serde(default)]
+    pub admin_key_expiry_notice_days: Option<u32>,
+    /// 沙箱令牌的固定回复内容；为空时回显最后一条用户消息
+    #[serde(default)]
+    pub sandbox_reply: Option<String>,
+    /// Idempotency-Key 缓存结果的保留时长（秒），默认 24 小时
+    #[serde(default = "default_idempotency_ttl_secs")]
+    pub idempotency_ttl_secs: u64,
+    /// Web 会话 Cookie 的 SameSite 属性：lax（默认）/ strict / none（none 时强制 Secure）
+    #[serde(default)]
+    pub session_cookie_same_site: SameSitePolicy,
+    /// 是否为会话 Cookie 加 Secure；为空时按 X-Forwarded-Proto 自动判断
+    #[serde(default)]
+    pub session_cookie_secure: Option<bool>,
+    /// Web 会话自签发起的最长有效期（秒），到期必须重新登录，默认 8 小时
+    #[serde(default = "default_session_absolute_timeout_secs")]
+    pub session_absolute_timeout_secs: u64,
+    /// Web 会话连续无请求多久后失效（秒），默认 1 小时；0 表示不启用空闲超时
+    #[serde(default = "default_session_idle_timeout_secs")]
+    pub session_idle_timeout_secs: u64,
+    /// 允许超级管理员使用账号密码 + TOTP 登录 Web 管理端（默认关闭，仅公钥/登录码）
+    #[serde(default)]
+    pub admin_password_login: bool,
+    /// 捕获的请求正文超过该字节数时不归档（默认 1 MiB；0 表示不限制），归档正文以 zstd 压缩存储
+    #[serde(default = "default_capture_body_max_bytes")]
+    pub capture_body_max_bytes: usize,
+    /// 逐块捕获流式响应（含每块到达时间），可通过 `/admin/logs/{id}/transcript` 重建完整回复；
+    /// 捕获总量同样受 capture_body_max_bytes 限制，默认关闭
+    #[serde(default)]
+    pub capture_stream_chunks: bool,
+    /// 关闭时等待进行中请求/流结束的最长时间（秒，默认 30），超时后强制退出
+    #[serde(default = "default_drain_timeout_secs")]
+    pub drain_timeout_secs: u64,
+    /// X-Gateway-Debug: capture 捕获的完整正文保留时长（秒），默认 15 分钟
+    #[serde(default = "default_debug_capture_ttl_secs")]
+    pub debug_capture_ttl_secs: u64,
+    /// 多实例部署时其他副本的基础地址（如 http://10.0.0.2:8000），管理变更后向其广播缓存失效事件
+    #[serde(default)]
+    pub cluster_peers: Vec<String>,
+    /// 副本间请求的 HMAC 签名密钥；未配置时既不广播也不接收事件
+    #[serde(default)]
+    pub cluster_secret: Option<String>,
+    /// 各上游模型的上下文窗口（token 数），键为上游模型名；开启 auto_truncate_prompt 的令牌据此截断超长对话
+    #[serde(default)]
+    pub model_context_windows: HashMap<String, u32>,
+    /// 语义响应缓存；为空表示不启用（令牌还需开启 semantic_cache）
+    #[serde(default)]
+    pub semantic_cache: Option<SemanticCacheConfig>,
+    /// 各上游模型的最大并发生成数，键为上游模型名；未配置的模型不限制
+    #[serde(default)]
+    pub model_concurrency: HashMap<String, ModelConcurrencyConfig>,
+    /// 模型并发排队时的公平调度单位：token（默认，按令牌）或 organization（按令牌所属组织）
+    #[serde(default)]
+    pub fair_queue_key: FairQueueKey,
+    /// 对 o 系列推理模型（o1/o3/o4-mini 等）自动移除 temperature/top_p 等不支持的参数，
+    /// 并将 max_tokens 改写为 max_completion_tokens；默认开启
+    #[serde(default = "default_reasoning_param_rules")]
+    pub reasoning_param_rules: bool,
+    /// 各上游模型的参数转换规则，键为上游模型名；配置后替代该模型的内置推理模型规则
+    #[serde(default)]
+    pub model_param_rules: HashMap<String, ModelParamRules>,
+    /// 供应商进行中请求数的饱和告警；为空表示不告警（计数本身始终可在 /metrics 查看）
+    #[serde(default)]
+    pub in_flight_alerts: Option<InFlightAlertConfig>,
+    /// Prometheus 抓取 /metrics 使用的 Bearer Token；为空时需超级管理员身份
+    #[serde(default)]
+    pub metrics_token: Option<String>,
+    /// 数据库不可用时的降级运行
+    #[serde(default)]
+    pub degraded_mode: DegradedModeConfig,
+    /// 请求使用了目标供应商会丢弃的字段时的处理方式，见 `providers::capabilities`
+    #[serde(default)]
+    pub unsupported_features: UnsupportedFeaturePolicy,
+    /// 识别用户消息的语言与内容类别（代码/正文）并写入请求日志，仅保存标签不保存原文（默认关闭）
+    #[serde(default)]
+    pub prompt_profiling: bool,
+    /// 覆盖内置定时任务的 cron 表达式（UTC），键为任务名，见 `GET /admin/jobs`
+    #[serde(default)]
+    pub job_schedules: HashMap<String, String>,
+    /// 在客户端 API 响应上附加剩余请求次数、剩余预算与限流窗口重置时间等提示头（默认关闭）
+    #[serde(default)]
+    pub budget_hint_headers: bool,
+    /// 令牌金额/tokens 计数在内存中合并、按此间隔（秒）批量写库；0 表示每次请求直接写库（默认）。
+    /// 仅支持单实例，配置了 cluster_peers 时忽略
+    #[serde(default)]
+    pub token_usage_flush_interval_secs: u64,
+}
+
+/// 降级运行：令牌校验回退到缓存快照，请求日志暂存到本地文件待数据库恢复后回放
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DegradedModeConfig {
+    /// 数据库读取失败时可使用的令牌快照最长时效（秒），默认 300；0 表示不回退
+    #[serde(default = "default_token_snapshot_max_age_secs")]
+    pub token_snapshot_max_age_secs: u64,
+    /// 请求日志暂存文件（JSON Lines）
+    #[serde(default = "default_log_spool_path")]
+    pub log_spool_path: String,
+    /// 暂存文件大小上限（字节），超出后丢弃新日志，默认 64 MiB
+    #[serde(default = "default_log_spool_max_bytes")]
+    pub log_spool_max_bytes: u64,
+    /// 单次写入请求日志的超时（毫秒），超时后转入回放队列，默认 5000；0 表示不限制
+    #[serde(default = "default_log_write_timeout_ms")]
+    pub log_write_timeout_ms: u64,
+}
+
+impl Default for DegradedModeConfig {
+    fn default() -> Self {
+        Self {
+            token_snapshot_max_age_secs: default_token_snapshot_max_age_secs(),
+            log_spool_path: default_log_spool_path(),
+            log_spool_max_bytes: default_log_spool_max_bytes(),
+            log_write_timeout_ms: default_log_write_timeout_ms(),
+        }
+    }
+}
+
+fn default_token_snapshot_max_age_secs() -> u64 {
+    300
+}
+
+fn default_log_spool_path() -> String {
+    "data/request_log_spool.jsonl".to_string()
+}
+
+fn default_log_spool_max_bytes() -> u64 {
+    64 * 1024 * 1024
+}
+
+fn default_log_write_timeout_ms() -> u64 {
+    5000
+}
+
+/// 饱和告警：某供应商进行中的上游请求数持续 sustain_secs 达到阈值时告警
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InFlightAlertConfig {
+    pub threshold: u32,
+    /// 持续多久（秒）才告警，默认 60 秒
+    #[serde(default = "default_in_flight_alert_sustain_secs")]
+    pub sustain_secs: u64,
+    /// 按供应商覆盖阈值；0 表示该供应商不告警
+    #[serde(default)]
+    pub providers: HashMap<String, u32>,
+}
+
+impl InFlightAlertConfig {
+    pub fn threshold_for(&self, provider: &str) -> u32 {
+        self.providers
+            .get(provider)
+            .copied()
+            .unwrap_or(self.threshold)
+    }
+}
+
+fn default_in_flight_alert_sustain_secs() -> u64 {
+    60
+}
+
+/// 转发前对请求参数的转换（在参数策略之后应用）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ModelParamRules {
+    /// 上游不支持、需移除的参数
+    #[serde(default)]
+    pub strip: Vec<String>,
+    /// 参数改名：原参数名 -> 上游参数名
+    #[serde(default)]
+    pub rename: BTreeMap<String, String>,
+}
+
+fn default_reasoning_param_rules() -> bool {
+    true
+}
+
+/// 单个模型的并发上限
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelConcurrencyConfig {
+    pub max_in_flight: u32,
+    /// 达到上限时的处理方式：queue（默认，排队等待）或 fail_fast（直接返回 429）
+    #[serde(default)]
+    pub mode: ModelConcurrencyMode,
+    /// 排队等待的最长时间（秒），超时返回 429，默认 30 秒
+    #[serde(default = "default_model_queue_timeout_secs")]
+    pub queue_timeout_secs: u64,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ModelConcurrencyMode {
+    #[default]
+    Queue,
+    FailFast,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum FairQueueKey {
+    #[default]
+    Token,
+    Organization,
+}
+
+fn default_model_queue_timeout_secs() -> u64 {
+    30
+}
+
+/// 语义响应缓存：用指定供应商的 embedding 模型向量化提示，相似度达到阈值时直接返回缓存的响应
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SemanticCacheConfig {
+    /// 提供 /embeddings 的供应商名（需兼容 OpenAI 协议）
+    pub embedding_provider: String,
+    pub embedding_model: String,
+    /// 余弦相似度阈值（0-1），默认 0.95
+    #[serde(default = "default_semantic_cache_threshold")]
+    pub similarity_threshold: f32,
+    /// 缓存条目保留时长（秒），默认 1 小时
+    #[serde(default = "default_semantic_cache_ttl_secs")]
+    pub ttl_secs: u64,
+    /// 最多保留的条目数，超出时淘汰最早写入的条目
+    #[serde(default = "default_semantic_cache_max_entries")]
+    pub max_entries: usize,
+}
+
+fn default_semantic_cache_threshold() -> f32 {
+    0.95
+}
+
+fn default_semantic_cache_ttl_secs() -> u64 {
+    3600
+}
+
+fn default_semantic_cache_max_entries() -> usize {
+    2000
 }
 
 impl Default for ServerConfig {
@@ -842,10 +1220,74 @@ impl Default for ServerConfig {
             pricing_mode: PricingMode::default(),
             pricing_sync_enabled: default_pricing_sync_enabled(),
             pricing_sync_default_ttl_hours: default_pricing_sync_default_ttl_hours(),
+            language: crate::i18n::Language::default(),
+            token_expiry_notice_days: None,
+            notification_webhook_url: None,
+            inactive_token_disable_days: None,
+            admin_key_max_age_days: None,
+            admin_key_expiry_notice_days: None,
+            sandbox_reply: None,
+            idempotency_ttl_secs: default_idempotency_ttl_secs(),
+            session_cookie_same_site: SameSitePolicy::default(),
+            session_cookie_secure: None,
+            session_absolute_timeout_secs: default_session_absolute_timeout_secs(),
+            session_idle_timeout_secs: default_session_idle_timeout_secs(),
+            admin_password_login: false,
+            capture_body_max_bytes: default_capture_body_max_bytes(),
+            capture_stream_chunks: false,
+            drain_timeout_secs: default_drain_timeout_secs(),
+            debug_capture_ttl_secs: default_debug_capture_ttl_secs(),
+            cluster_peers: Vec::new(),
+            cluster_secret: None,
+            model_context_windows: HashMap::new(),
+            semantic_cache: None,
+            model_concurrency: HashMap::new(),
+            fair_queue_key: FairQueueKey::default(),
+            reasoning_param_rules: default_reasoning_param_rules(),
+            model_param_rules: HashMap::new(),
+            in_flight_alerts: None,
+            metrics_token: None,
+            degraded_mode: DegradedModeConfig::default(),
+            unsupported_features: UnsupportedFeaturePolicy::default(),
+            prompt_profiling: false,
+            job_schedules: HashMap::new(),
+            budget_hint_headers: false,
+            token_usage_flush_interval_secs: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SameSitePolicy {
+    Strict,
+    #[default]
+    Lax,
+    None,
+}
+
+impl SameSitePolicy {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Strict => "Strict",
+            Self::Lax => "Lax",
+            Self::None => "None",
         }
     }
 }
 
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum UnsupportedFeaturePolicy {
+    /// 照常转发，在响应头 `x-gateway-dropped-features` 中列出被丢弃的字段
+    #[default]
+    Warn,
+    /// 分发前以 400 拒绝
+    Reject,
+    /// 不检查
+    Ignore,
+}
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
 #[serde(rename_all = "snake_case")]
 pub enum PricingMode {
@@ -878,6 +1320,85 @@ pub struct LoggingConfig {
     pub pg_schema: Option<String>,
     #[serde(default)]
     pub pg_pool_size: Option<usize>,
+    /// SQLite 持续备份：定期快照并上传到 S3 兼容存储或二级目录；为空表示不启用（PostgreSQL 模式忽略）
+    #[serde(default)]
+    pub replication: Option<ReplicationConfig>,
+    /// SQLite 与 PostgreSQL 双写迁移模式（需同时配置 database_path 与 pg_url）；为空表示不启用
+    #[serde(default)]
+    pub migration: Option<MigrationConfig>,
+}
+
+/// 双写迁移：写入同时落到两个后端，读取来自主后端，可在运行期切换主后端
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MigrationConfig {
+    /// 启动时的主后端：sqlite（默认）或 postgres
+    #[serde(default)]
+    pub primary: MigrationBackend,
+    /// 一致性检查间隔（秒），默认 300；0 表示只在手动触发时检查
+    #[serde(default = "default_migration_check_interval_secs")]
+    pub check_interval_secs: u64,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum MigrationBackend {
+    #[default]
+    Sqlite,
+    Postgres,
+}
+
+fn default_migration_check_interval_secs() -> u64 {
+    300
+}
+
+/// SQLite 备份目标，`path` 与 `s3` 二选一
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplicationConfig {
+    /// 快照间隔（秒），数据库无变化时跳过上传，默认 60
+    #[serde(default = "default_replication_interval_secs")]
+    pub interval_secs: u64,
+    /// 二级目录（如挂载的网络盘）
+    #[serde(default)]
+    pub path: Option<String>,
+    #[serde(default)]
+    pub s3: Option<S3ReplicaConfig>,
+    /// 保留最近多少份快照（仅目录目标；S3 请使用桶的生命周期规则），默认 48
+    #[serde(default = "default_replication_retain")]
+    pub retain: usize,
+}
+
+/// S3 兼容存储（AWS S3 / MinIO / R2 等，使用 path-style 地址）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct S3ReplicaConfig {
+    pub endpoint: String,
+    pub bucket: String,
+    #[serde(default = "default_s3_region")]
+    pub region: String,
+    /// 对象键前缀，默认 gateway
+    #[serde(default = "default_s3_prefix")]
+    pub prefix: String,
+    /// 为空时读取环境变量 GW_REPLICA_S3_ACCESS_KEY_ID
+    #[serde(default)]
+    pub access_key_id: Option<String>,
+    /// 为空时读取环境变量 GW_REPLICA_S3_SECRET_ACCESS_KEY
+    #[serde(default)]
+    pub secret_access_key: Option<String>,
+}
+
+fn default_replication_interval_secs() -> u64 {
+    60
+}
+
+fn default_replication_retain() -> usize {
+    48
+}
+
+fn default_s3_region() -> String {
+    "us-east-1".to_string()
+}
+
+fn default_s3_prefix() -> String {
+    "gateway".to_string()
 }
 
 impl Default for LoggingConfig {
@@ -888,6 +1409,8 @@ impl Default for LoggingConfig {
             pg_url: None,
             pg_schema: None,
             pg_pool_size: None,
+            replication: None,
+            migration: None,
         }
     }
 }
@@ -904,6 +1427,30 @@ fn default_pricing_sync_default_ttl_hours() -> u16 {
     168
 }
 
+fn default_idempotency_ttl_secs() -> u64 {
+    86_400
+}
+
+fn default_session_absolute_timeout_secs() -> u64 {
+    8 * 60 * 60
+}
+
+fn default_session_idle_timeout_secs() -> u64 {
+    60 * 60
+}
+
+fn default_capture_body_max_bytes() -> usize {
+    1024 * 1024
+}
+
+fn default_drain_timeout_secs() -> u64 {
+    30
+}
+
+fn default_debug_capture_ttl_secs() -> u64 {
+    15 * 60
+}
+
 fn default_provider_enabled() -> bool {
     true
 }
@@ -6,7 +6,7 @@ use tokio_postgres::Client;
 // Keeps behavior compatible with prior implementation while improving robustness.
 pub fn spawn_keepalive(client: Arc<Client>, min_secs: u64, max_secs: u64) {
     let max_secs = max_secs.max(min_secs + 1);
-    tokio::spawn(async move {
+    crate::server::tasks::task_registry().spawn("pg_keepalive", async move {
         loop {
             let jitter = rand::random_range(min_secs..=max_secs);
             tokio::time::sleep(std::time::Duration::from_secs(jitter)).await;
@@ -36,11 +36,38 @@ pub enum GatewayError {
     #[error("Rate limited: {0}")]
     RateLimited(String),
 
+    /// 上游返回 429 并给出 Retry-After（秒），透传给调用方并用于冷却对应密钥
+    #[error("Rate limited: {1}")]
+    UpstreamRateLimited(u64, String),
+
     #[error("Unauthorized: {0}")]
     Unauthorized(String),
 
     #[error("Forbidden: {0}")]
     Forbidden(String),
+
+    #[error("Conflict: {0}")]
+    Conflict(String),
+
+    /// 客户端令牌超过轮换期限（含宽限期），需先轮换才能继续使用
+    #[error("Token rotation required: {0}")]
+    TokenRotationRequired(String),
+
+    /// 上游拒绝了配置的 API 版本（版本头/参数无效或不受支持）
+    #[error("API version mismatch: {0}")]
+    ApiVersionMismatch(String),
+
+    /// 故障注入产生的模拟错误（状态码由注入规则指定）
+    #[error("Injected fault: {1}")]
+    FaultInjected(u16, String),
+
+    /// 网关处于关闭排空阶段，不再接受新请求
+    #[error("Shutting down: {0}")]
+    ShuttingDown(String),
+
+    /// 请求体超出供应商配置的大小上限
+    #[error("Payload too large: {0}")]
+    PayloadTooLarge(String),
 }
 
 pub type Result<T> = std::result::Result<T, GatewayError>;
@@ -53,16 +80,24 @@ struct ErrorBody {
 
 impl GatewayError {
     fn user_message(&self) -> String {
-        match self {
+        let message = match self {
             GatewayError::Http(err) => format_reqwest_error(err),
             GatewayError::TimeParse(s)
             | GatewayError::Config(s)
             | GatewayError::NotFound(s)
             | GatewayError::RateLimited(s)
+            | GatewayError::UpstreamRateLimited(_, s)
             | GatewayError::Unauthorized(s)
-            | GatewayError::Forbidden(s) => s.clone(),
+            | GatewayError::Forbidden(s)
+            | GatewayError::Conflict(s)
+            | GatewayError::TokenRotationRequired(s)
+            | GatewayError::ApiVersionMismatch(s)
+            | GatewayError::FaultInjected(_, s)
+            | GatewayError::ShuttingDown(s)
+            | GatewayError::PayloadTooLarge(s) => s.clone(),
             _ => self.to_string(),
-        }
+        };
+        crate::i18n::localize(&message).into_owned()
     }
 
     pub fn status_code(&self) -> StatusCode {
@@ -71,16 +106,46 @@ impl GatewayError {
             | GatewayError::Balance(BalanceError::NoApiKeysAvailable) => {
                 StatusCode::SERVICE_UNAVAILABLE
             }
+            GatewayError::ShuttingDown(_) => StatusCode::SERVICE_UNAVAILABLE,
             GatewayError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
-            GatewayError::Forbidden(_) => StatusCode::FORBIDDEN,
-            GatewayError::Http(_) => StatusCode::BAD_GATEWAY,
+            GatewayError::Forbidden(_) | GatewayError::TokenRotationRequired(_) => {
+                StatusCode::FORBIDDEN
+            }
+            GatewayError::Conflict(_) => StatusCode::CONFLICT,
+            GatewayError::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            GatewayError::FaultInjected(status, _) => {
+                StatusCode::from_u16(*status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+            GatewayError::Http(_) | GatewayError::ApiVersionMismatch(_) => StatusCode::BAD_GATEWAY,
             GatewayError::Config(_) => StatusCode::BAD_REQUEST,
             GatewayError::NotFound(_) => StatusCode::NOT_FOUND,
-            GatewayError::RateLimited(_) => StatusCode::TOO_MANY_REQUESTS,
+            GatewayError::RateLimited(_)
+            | GatewayError::UpstreamRateLimited(..)
+            | GatewayError::Balance(BalanceError::ApiKeysRateLimited) => {
+                StatusCode::TOO_MANY_REQUESTS
+            }
             _ => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
 
+    /// 上游给出的重试等待秒数（仅上游 429 且带 Retry-After 时存在）
+    pub fn retry_after(&self) -> Option<u64> {
+        match self {
+            GatewayError::UpstreamRateLimited(secs, _) => Some(*secs),
+            _ => None,
+        }
+    }
+
+    /// 为上游限流错误附加 Retry-After；其他错误或未提供 Retry-After 时原样返回
+    pub fn with_retry_after(self, retry_after: Option<u64>) -> Self {
+        match (self, retry_after) {
+            (GatewayError::RateLimited(message), Some(secs)) => {
+                GatewayError::UpstreamRateLimited(secs, message)
+            }
+            (err, _) => err,
+        }
+    }
+
     fn code(&self) -> &'static str {
         match self {
             GatewayError::Http(_) => "http_error",
@@ -92,9 +157,15 @@ impl GatewayError {
             GatewayError::TimeParse(_) => "time_parse_error",
             GatewayError::Config(_) => "config_error",
             GatewayError::NotFound(_) => "not_found",
-            GatewayError::RateLimited(_) => "rate_limited",
+            GatewayError::RateLimited(_) | GatewayError::UpstreamRateLimited(..) => "rate_limited",
             GatewayError::Unauthorized(_) => "unauthorized",
             GatewayError::Forbidden(_) => "forbidden",
+            GatewayError::Conflict(_) => "conflict",
+            GatewayError::TokenRotationRequired(_) => "token_rotation_required",
+            GatewayError::ApiVersionMismatch(_) => "api_version_mismatch",
+            GatewayError::FaultInjected(..) => "fault_injected",
+            GatewayError::ShuttingDown(_) => "shutting_down",
+            GatewayError::PayloadTooLarge(_) => "payload_too_large",
         }
     }
 }
@@ -135,10 +206,17 @@ fn format_reqwest_error(err: &reqwest::Error) -> String {
 impl IntoResponse for GatewayError {
     fn into_response(self) -> axum::response::Response {
         let status = self.status_code();
+        let retry_after = self.retry_after();
         let body = ErrorBody {
             code: self.code(),
             message: self.user_message(),
         };
-        (status, Json(body)).into_response()
+        let mut response = (status, Json(body)).into_response();
+        if let Some(secs) = retry_after {
+            response
+                .headers_mut()
+                .insert(axum::http::header::RETRY_AFTER, secs.into());
+        }
+        response
     }
 }
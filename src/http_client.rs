@@ -48,7 +48,7 @@ pub fn maybe_disable_proxy(builder: ClientBuilder, url: &str) -> ClientBuilder {
 }
 
 pub fn client_for_url(url: &str) -> Result<reqwest::Client, reqwest::Error> {
-    let builder = reqwest::Client::builder();
+    let builder = crate::tls_pinning::apply(reqwest::Client::builder(), url);
     maybe_disable_proxy(builder, url).build()
 }
 
@@ -0,0 +1,337 @@
+use std::borrow::Cow;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use serde::{Deserialize, Serialize};
+
+/// 面向用户的提示语言。默认保持现有中文提示（zh-CN）。
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub enum Language {
+    #[default]
+    #[serde(rename = "zh-CN", alias = "zh", alias = "zh_cn")]
+    ZhCn,
+    #[serde(rename = "en-US", alias = "en", alias = "en_us")]
+    EnUs,
+}
+
+static CURRENT_LANGUAGE: AtomicU8 = AtomicU8::new(0);
+
+/// 进程级语言设置（启动时根据配置写入一次）
+pub fn set_language(lang: Language) {
+    let v = match lang {
+        Language::ZhCn => 0,
+        Language::EnUs => 1,
+    };
+    CURRENT_LANGUAGE.store(v, Ordering::Relaxed);
+}
+
+pub fn current_language() -> Language {
+    match CURRENT_LANGUAGE.load(Ordering::Relaxed) {
+        1 => Language::EnUs,
+        _ => Language::ZhCn,
+    }
+}
+
+// 中文 → 英文对照表；`{}` 为占位符，按出现顺序替换
+const ZH_TO_EN: &[(&str, &str)] = &[
+    (
+        "余额不足：密钥已失效；充值/订阅后需手动启用密钥",
+        "Insufficient balance: the key has been disabled; re-enable it manually after recharging or subscribing",
+    ),
+    ("管理员身份认证失败", "Admin authentication failed"),
+    (
+        "生成频率过快，请稍后再试",
+        "Too many requests, please try again later",
+    ),
+    ("请求不存在", "Request not found"),
+    ("来源请求不存在", "Source request not found"),
+    ("无权访问该请求", "Access to this request is denied"),
+    ("历史快照不存在", "Snapshot not found"),
+    ("无权访问该历史快照", "Access to this snapshot is denied"),
+    ("历史快照已损坏", "Snapshot is corrupted"),
+    ("历史快照详情格式非法", "Snapshot detail is malformed"),
+    ("对比记录不存在", "Compare run not found"),
+    ("无权访问该对比记录", "Access to this compare run is denied"),
+    (
+        "无权保存该对比结果",
+        "Not allowed to save this compare result",
+    ),
+    ("对比记录已损坏", "Compare run is corrupted"),
+    ("对比结果格式非法", "Compare result is malformed"),
+    (
+        "快照来源请求与对比结果不匹配",
+        "Snapshot source request does not match the compare result",
+    ),
+    ("实验模板不存在", "Experiment template not found"),
+    (
+        "无权访问该实验模板",
+        "Access to this experiment template is denied",
+    ),
+    (
+        "无权修改该实验模板",
+        "Not allowed to modify this experiment template",
+    ),
+    ("没有可更新的模板内容", "Nothing to update in the template"),
+    ("模板名称不能为空", "Template name must not be empty"),
+    (
+        "模板 scope 仅支持 personal",
+        "Template scope only supports personal",
+    ),
+    (
+        "请求快照缺失，当前日志不可回放",
+        "Request snapshot is missing; this log cannot be replayed",
+    ),
+    (
+        "请求快照缺失，当前日志不可加入实验",
+        "Request snapshot is missing; this log cannot be added to an experiment",
+    ),
+    ("请求快照格式非法", "Request snapshot is malformed"),
+    (
+        "请求快照无法反序列化为可回放请求",
+        "Request snapshot cannot be deserialized into a replayable request",
+    ),
+    (
+        "请求快照已损坏，当前日志不可回放",
+        "Request snapshot is corrupted; this log cannot be replayed",
+    ),
+    (
+        "当前请求缺少可用令牌，无法回放",
+        "This request has no usable token and cannot be replayed",
+    ),
+    (
+        "当前请求缺少可用令牌，无法加入实验",
+        "This request has no usable token and cannot be added to an experiment",
+    ),
+    (
+        "当前请求类型暂不支持回放",
+        "Replay is not supported for this request type",
+    ),
+    (
+        "当前请求类型暂不支持原样回放",
+        "Verbatim replay is not supported for this request type",
+    ),
+    (
+        "当前请求类型暂不支持加入实验",
+        "This request type cannot be added to an experiment",
+    ),
+    (
+        "管理员公钥长度异常",
+        "Admin public key has an invalid length",
+    ),
+    ("管理员公钥解析失败", "Failed to parse admin public key"),
+    ("管理员公钥已禁用", "Admin public key is disabled"),
+    ("管理员公钥已过期", "Admin public key has expired"),
+    (
+        "管理员公钥不存在或未注册",
+        "Admin public key does not exist or is not registered",
+    ),
+    ("公钥长度必须为 32 字节", "Public key must be 32 bytes"),
+    ("公钥长度不正确", "Public key length is invalid"),
+    ("公钥解析失败", "Failed to parse public key"),
+    (
+        "public_key_b64 无法解码",
+        "public_key_b64 cannot be decoded",
+    ),
+    ("签名验证失败", "Signature verification failed"),
+    ("签名长度错误", "Signature length is invalid"),
+    ("签名格式错误", "Signature format is invalid"),
+    ("挑战已过期", "Challenge has expired"),
+    (
+        "挑战与指纹不匹配",
+        "Challenge does not match the fingerprint",
+    ),
+    (
+        "挑战不存在或已过期",
+        "Challenge does not exist or has expired",
+    ),
+    ("旧密码不正确", "Old password is incorrect"),
+    ("old_password 不能为空", "old_password must not be empty"),
+    (
+        "new_password 长度至少 7 位",
+        "new_password must be at least 7 characters",
+    ),
+    ("username 已被占用", "username is already taken"),
+    ("username 不能为空", "username must not be empty"),
+    ("user_id 不存在", "user_id does not exist"),
+    ("不允许传入 id", "id must not be provided"),
+    ("不允许修改 id", "id cannot be modified"),
+    ("id 不能为空", "id must not be empty"),
+    ("name 不能为空", "name must not be empty"),
+    ("name 长度不能超过 64", "name must not exceed 64 characters"),
+    (
+        "name 不能包含控制字符",
+        "name must not contain control characters",
+    ),
+    ("provider 不能为空", "provider must not be empty"),
+    (
+        "organization_id 不能为空",
+        "organization_id must not be empty",
+    ),
+    ("base_url 不能为空", "base_url must not be empty"),
+    ("base_url 不是合法的 URL", "base_url is not a valid URL"),
+    (
+        "base_url 仅允许 http/https",
+        "base_url only allows http/https",
+    ),
+    ("base_url 缺少 host", "base_url is missing a host"),
+    ("base_url 域名解析失败", "Failed to resolve base_url host"),
+    (
+        "base_url 不允许指向本机/内网",
+        "base_url must not point to localhost or a private network",
+    ),
+    (
+        "models_endpoint 拼接失败",
+        "Failed to build models_endpoint URL",
+    ),
+    (
+        "models_endpoint 不是合法的 URL",
+        "models_endpoint is not a valid URL",
+    ),
+    ("models URL 拼接失败", "Failed to build models URL"),
+    (
+        "解析上游模型列表失败（非 OpenAI 兼容响应）",
+        "Failed to parse upstream model list (not an OpenAI-compatible response)",
+    ),
+    (
+        "解析 Google Gemini 模型列表失败。",
+        "Failed to parse Google Gemini model list.",
+    ),
+    (
+        "解析 Cohere 模型列表失败。",
+        "Failed to parse Cohere model list.",
+    ),
+    (
+        "Cohere 请求缺少模型名称。",
+        "Cohere request is missing a model name.",
+    ),
+    (
+        "AWS Claude 需要填写模型名称。",
+        "AWS Claude requires a model name.",
+    ),
+    (
+        "百度文心旧版未返回 result 字段。",
+        "Baidu ERNIE (legacy) did not return a result field.",
+    ),
+    (
+        "{} 数量不能超过 {}",
+        "{} must not contain more than {} items",
+    ),
+    (
+        "{} 单条长度不能超过 {}",
+        "each item in {} must not exceed {} characters",
+    ),
+    ("{} 长度不能超过 {}", "{} must not exceed {} characters"),
+    ("{} 不能包含空字符串", "{} must not contain empty strings"),
+    (
+        "{} 不能包含控制字符",
+        "{} must not contain control characters",
+    ),
+    ("{} 中包含不存在的模型: {}", "{} contains unknown model: {}"),
+    ("{} base_url 无效：{}", "{} base_url is invalid: {}"),
+    ("{} 请求地址无效：{}", "{} request URL is invalid: {}"),
+    ("{} 无效：{}", "{} is invalid: {}"),
+    (
+        "allowed_models 与 model_blacklist 不可同时设置（白名单/黑名单互斥）",
+        "allowed_models and model_blacklist cannot both be set (allowlist and blocklist are mutually exclusive)",
+    ),
+    (
+        "网关正在关闭，暂不接受新请求",
+        "Gateway is shutting down and not accepting new requests",
+    ),
+];
+
+// 按 `{}` 切分模板并依次匹配，返回各占位符捕获的内容
+fn match_template<'a>(template: &str, message: &'a str) -> Option<Vec<&'a str>> {
+    let parts: Vec<&str> = template.split("{}").collect();
+    if parts.len() == 1 {
+        return (template == message).then(Vec::new);
+    }
+    let first = parts[0];
+    let last = parts[parts.len() - 1];
+    let mut rest = message.strip_prefix(first)?;
+    let mut captures = Vec::with_capacity(parts.len() - 1);
+    for part in &parts[1..parts.len() - 1] {
+        if part.is_empty() {
+            return None;
+        }
+        let idx = rest.find(part)?;
+        captures.push(&rest[..idx]);
+        rest = &rest[idx + part.len()..];
+    }
+    if last.is_empty() {
+        captures.push(rest);
+    } else {
+        captures.push(rest.strip_suffix(last)?);
+    }
+    if captures.iter().any(|c| c.is_empty()) {
+        return None;
+    }
+    Some(captures)
+}
+
+fn fill_template(template: &str, captures: &[&str]) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut parts = template.split("{}");
+    if let Some(first) = parts.next() {
+        out.push_str(first);
+    }
+    for (i, part) in parts.enumerate() {
+        out.push_str(captures.get(i).copied().unwrap_or_default());
+        out.push_str(part);
+    }
+    out
+}
+
+/// 将面向用户的提示按指定语言输出；未收录的文本原样返回
+pub fn localize_for(lang: Language, message: &str) -> Cow<'_, str> {
+    if lang == Language::ZhCn {
+        return Cow::Borrowed(message);
+    }
+    for (zh, en) in ZH_TO_EN {
+        if let Some(captures) = match_template(zh, message) {
+            return Cow::Owned(fill_template(en, &captures));
+        }
+    }
+    Cow::Borrowed(message)
+}
+
+pub fn localize(message: &str) -> Cow<'_, str> {
+    localize_for(current_language(), message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zh_cn_keeps_original_text() {
+        assert_eq!(localize_for(Language::ZhCn, "挑战已过期"), "挑战已过期");
+    }
+
+    #[test]
+    fn en_us_translates_exact_and_templated_messages() {
+        assert_eq!(
+            localize_for(Language::EnUs, "挑战已过期"),
+            "Challenge has expired"
+        );
+        assert_eq!(
+            localize_for(Language::EnUs, "allowed_models 数量不能超过 200"),
+            "allowed_models must not contain more than 200 items"
+        );
+        assert_eq!(
+            localize_for(Language::EnUs, "Vertex AI Token 无效：bad"),
+            "Vertex AI Token is invalid: bad"
+        );
+        assert_eq!(
+            localize_for(Language::EnUs, "token disabled"),
+            "token disabled"
+        );
+    }
+
+    #[test]
+    fn language_deserializes_from_locale_tags() {
+        let lang: Language = serde_json::from_str("\"en-US\"").unwrap();
+        assert_eq!(lang, Language::EnUs);
+        let lang: Language = serde_json::from_str("\"zh\"").unwrap();
+        assert_eq!(lang, Language::ZhCn);
+    }
+}
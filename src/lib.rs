@@ -0,0 +1,4 @@
+//! 网关的 Rust 客户端库。服务端由 main.rs 构建；此库仅导出 `client`，
+//! 供 `gateway bench`、集成测试与外部 Rust 服务以类型化接口调用网关。
+
+pub mod client;
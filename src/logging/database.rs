@@ -1,12 +1,20 @@
+use crate::logging::payload_archive::{self, ArchivedPayload, ENCODING_ZSTD};
 use crate::logging::time::{
     BEIJING_OFFSET, DATETIME_FORMAT, parse_beijing_string, to_beijing_string, to_iso8601_utc_string,
 };
 use crate::logging::types::{
-    ProviderKeyStatsAgg, RequestLog, RequestLogDetailRecord, StoredCompareRun,
-    StoredRequestLabSnapshot, StoredRequestLabSource, StoredRequestLabTemplate,
+    AdminNotificationRecord, AudioPriceRecord, DebugCaptureRecord, ImagePriceRecord,
+    LatencyBreakdown, LogColumns, MaintenanceWindowRecord, MetricsReportRecord, ParamPolicyRecord,
+    PromptProfile, ProviderBudgetRecord, ProviderEgressDaily, ProviderKeyStatsAgg,
+    RequestHeatmapCell, RequestLog, RequestLogDetailRecord, SpeechPriceRecord, StatementRecord,
+    StoredCompareRun, StoredIdempotentResponse, StoredRequestLabSnapshot, StoredRequestLabSource,
+    StoredRequestLabTemplate, StreamCaptureRecord, TokenActivity, TokenActivityDay,
+    TokenModelUsage, TokenRecentError, TokenRequestCount, TokenUsageDaily, TokenUsageDelta,
+    UsageWebhookDeadLetter,
 };
 use crate::server::storage_traits::{
-    AdminPublicKeyRecord, LoginCodeRecord, TuiSessionRecord, WebSessionRecord,
+    AdminMfaRecord, AdminPublicKeyRecord, LoginCodeRecord, TuiSessionRecord, UserLoginCodeRecord,
+    WebSessionRecord,
 };
 use chrono::{DateTime, Duration, SecondsFormat, TimeZone, Utc};
 use rusqlite::{Connection, OptionalExtension, Result};
@@ -98,7 +106,24 @@ fn ensure_client_tokens_table_sqlite(conn: &Connection) -> Result<()> {
             organization_id TEXT,
             ip_whitelist TEXT,
             ip_blacklist TEXT,
-            model_blacklist TEXT
+            model_blacklist TEXT,
+            allow_streaming INTEGER NOT NULL DEFAULT 1,
+            sandbox INTEGER NOT NULL DEFAULT 0,
+            strip_reasoning INTEGER NOT NULL DEFAULT 0,
+            usage_webhook_url TEXT,
+            signing_secret TEXT,
+            require_signature INTEGER NOT NULL DEFAULT 0,
+            parent_token_id TEXT,
+            allow_debug_capture INTEGER NOT NULL DEFAULT 0,
+            allow_provider_override INTEGER NOT NULL DEFAULT 0,
+            auto_truncate_prompt INTEGER NOT NULL DEFAULT 0,
+            semantic_cache INTEGER NOT NULL DEFAULT 0,
+            allow_login_codes INTEGER NOT NULL DEFAULT 0,
+            max_requests INTEGER,
+            max_requests_per_day INTEGER,
+            watermark_responses INTEGER NOT NULL DEFAULT 0,
+            queue_weight INTEGER,
+            usage_webhook_secret TEXT
         )",
         [],
     )?;
@@ -135,6 +160,79 @@ fn ensure_client_tokens_table_sqlite(conn: &Connection) -> Result<()> {
         "ALTER TABLE client_tokens ADD COLUMN model_blacklist TEXT",
         [],
     );
+    let _ = conn.execute(
+        "ALTER TABLE client_tokens ADD COLUMN allow_streaming INTEGER NOT NULL DEFAULT 1",
+        [],
+    );
+    let _ = conn.execute(
+        "ALTER TABLE client_tokens ADD COLUMN sandbox INTEGER NOT NULL DEFAULT 0",
+        [],
+    );
+    let _ = conn.execute(
+        "ALTER TABLE client_tokens ADD COLUMN strip_reasoning INTEGER NOT NULL DEFAULT 0",
+        [],
+    );
+    let _ = conn.execute(
+        "ALTER TABLE client_tokens ADD COLUMN usage_webhook_url TEXT",
+        [],
+    );
+    let _ = conn.execute(
+        "ALTER TABLE client_tokens ADD COLUMN signing_secret TEXT",
+        [],
+    );
+    let _ = conn.execute(
+        "ALTER TABLE client_tokens ADD COLUMN require_signature INTEGER NOT NULL DEFAULT 0",
+        [],
+    );
+    let _ = conn.execute(
+        "ALTER TABLE client_tokens ADD COLUMN parent_token_id TEXT",
+        [],
+    );
+    let _ = conn.execute(
+        "ALTER TABLE client_tokens ADD COLUMN allow_debug_capture INTEGER NOT NULL DEFAULT 0",
+        [],
+    );
+    let _ = conn.execute(
+        "ALTER TABLE client_tokens ADD COLUMN allow_provider_override INTEGER NOT NULL DEFAULT 0",
+        [],
+    );
+    let _ = conn.execute(
+        "ALTER TABLE client_tokens ADD COLUMN auto_truncate_prompt INTEGER NOT NULL DEFAULT 0",
+        [],
+    );
+    let _ = conn.execute(
+        "ALTER TABLE client_tokens ADD COLUMN semantic_cache INTEGER NOT NULL DEFAULT 0",
+        [],
+    );
+    let _ = conn.execute(
+        "ALTER TABLE client_tokens ADD COLUMN allow_login_codes INTEGER NOT NULL DEFAULT 0",
+        [],
+    );
+    let _ = conn.execute(
+        "ALTER TABLE client_tokens ADD COLUMN max_requests INTEGER",
+        [],
+    );
+    let _ = conn.execute(
+        "ALTER TABLE client_tokens ADD COLUMN max_requests_per_day INTEGER",
+        [],
+    );
+    let _ = conn.execute(
+        "ALTER TABLE client_tokens ADD COLUMN watermark_responses INTEGER NOT NULL DEFAULT 0",
+        [],
+    );
+    let _ = conn.execute(
+        "ALTER TABLE client_tokens ADD COLUMN queue_weight INTEGER",
+        [],
+    );
+    let _ = conn.execute(
+        "ALTER TABLE client_tokens ADD COLUMN usage_webhook_secret TEXT",
+        [],
+    );
+    // 已配置用量 Webhook 的存量令牌补发签名密钥（此前使用令牌明文签名）
+    let _ = conn.execute(
+        "UPDATE client_tokens SET usage_webhook_secret = 'whsec_' || lower(hex(randomblob(32))) WHERE usage_webhook_url IS NOT NULL AND usage_webhook_secret IS NULL",
+        [],
+    );
     let _ = conn.execute(
         "CREATE UNIQUE INDEX IF NOT EXISTS client_tokens_id_uidx ON client_tokens(id)",
         [],
@@ -143,6 +241,10 @@ fn ensure_client_tokens_table_sqlite(conn: &Connection) -> Result<()> {
         "CREATE INDEX IF NOT EXISTS client_tokens_user_id_idx ON client_tokens(user_id)",
         [],
     );
+    let _ = conn.execute(
+        "CREATE INDEX IF NOT EXISTS client_tokens_parent_token_id_idx ON client_tokens(parent_token_id)",
+        [],
+    );
 
     // 历史脏数据清理（best-effort）：
     // 只要 token 绑定用户（user_id != NULL/''），就必须走“用户订阅余额”，其 max_amount 必须清空。
@@ -169,9 +271,10 @@ impl crate::server::storage_traits::LoginStore for DatabaseLogger {
             let created = encode_ts(&key.created_at);
             let last_used_val = key.last_used_at.as_ref().map(encode_ts);
             let last_used = last_used_val.as_deref();
+            let expires = key.expires_at.as_ref().map(encode_ts);
             let comment = key.comment.as_deref();
             conn.execute(
-                "INSERT OR REPLACE INTO admin_public_keys (fingerprint, public_key, comment, enabled, created_at, last_used_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                "INSERT OR REPLACE INTO admin_public_keys (fingerprint, public_key, comment, enabled, created_at, last_used_at, expires_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
                 rusqlite::params![
                     &key.fingerprint,
                     &key.public_key,
@@ -179,6 +282,7 @@ impl crate::server::storage_traits::LoginStore for DatabaseLogger {
                     if key.enabled { 1 } else { 0 },
                     &created,
                     last_used,
+                    expires.as_deref(),
                 ],
             )?;
             Ok(())
@@ -193,17 +297,22 @@ impl crate::server::storage_traits::LoginStore for DatabaseLogger {
         Box::pin(async move {
             let conn = self.connection.lock().await;
             let mut stmt = conn.prepare(
-                "SELECT fingerprint, public_key, comment, enabled, created_at, last_used_at FROM admin_public_keys WHERE fingerprint = ?1",
+                "SELECT fingerprint, public_key, comment, enabled, created_at, last_used_at, expires_at FROM admin_public_keys WHERE fingerprint = ?1",
             )?;
             let record = stmt
                 .query_row([fingerprint], |row| {
                     let created_raw: String = row.get(4)?;
                     let last_used_raw: Option<String> = row.get(5)?;
+                    let expires_raw: Option<String> = row.get(6)?;
                     let created_at = decode_ts(&created_raw)?;
                     let last_used_at = match last_used_raw {
                         Some(v) => Some(decode_ts(&v)?),
                         None => None,
                     };
+                    let expires_at = match expires_raw {
+                        Some(v) => Some(decode_ts(&v)?),
+                        None => None,
+                    };
                     Ok(AdminPublicKeyRecord {
                         fingerprint: row.get(0)?,
                         public_key: row.get(1)?,
@@ -211,6 +320,7 @@ impl crate::server::storage_traits::LoginStore for DatabaseLogger {
                         enabled: row.get::<_, i64>(3)? != 0,
                         created_at,
                         last_used_at,
+                        expires_at,
                     })
                 })
                 .optional()?;
@@ -241,16 +351,21 @@ impl crate::server::storage_traits::LoginStore for DatabaseLogger {
         Box::pin(async move {
             let conn = self.connection.lock().await;
             let mut stmt = conn.prepare(
-                "SELECT fingerprint, public_key, comment, enabled, created_at, last_used_at FROM admin_public_keys",
+                "SELECT fingerprint, public_key, comment, enabled, created_at, last_used_at, expires_at FROM admin_public_keys",
             )?;
             let rows = stmt.query_map([], |row| {
                 let created_raw: String = row.get(4)?;
                 let last_used_raw: Option<String> = row.get(5)?;
+                let expires_raw: Option<String> = row.get(6)?;
                 let created_at = decode_ts(&created_raw)?;
                 let last_used_at = match last_used_raw {
                     Some(v) => Some(decode_ts(&v)?),
                     None => None,
                 };
+                let expires_at = match expires_raw {
+                    Some(v) => Some(decode_ts(&v)?),
+                    None => None,
+                };
                 Ok(AdminPublicKeyRecord {
                     fingerprint: row.get(0)?,
                     public_key: row.get(1)?,
@@ -258,6 +373,7 @@ impl crate::server::storage_traits::LoginStore for DatabaseLogger {
                     enabled: row.get::<_, i64>(3)? != 0,
                     created_at,
                     last_used_at,
+                    expires_at,
                 })
             })?;
             let mut out = Vec::new();
@@ -268,6 +384,23 @@ impl crate::server::storage_traits::LoginStore for DatabaseLogger {
         })
     }
 
+    fn update_admin_key_status<'a>(
+        &'a self,
+        fingerprint: &'a str,
+        enabled: bool,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> crate::server::storage_traits::BoxFuture<'a, rusqlite::Result<bool>> {
+        Box::pin(async move {
+            let conn = self.connection.lock().await;
+            let expires = expires_at.as_ref().map(encode_ts);
+            let rows = conn.execute(
+                "UPDATE admin_public_keys SET enabled = ?2, expires_at = ?3 WHERE fingerprint = ?1",
+                rusqlite::params![fingerprint, if enabled { 1 } else { 0 }, expires.as_deref()],
+            )?;
+            Ok(rows > 0)
+        })
+    }
+
     fn delete_admin_key<'a>(
         &'a self,
         fingerprint: &'a str,
@@ -571,6 +704,79 @@ impl crate::server::storage_traits::LoginStore for DatabaseLogger {
         })
     }
 
+    fn insert_user_login_code<'a>(
+        &'a self,
+        code: &'a UserLoginCodeRecord,
+    ) -> crate::server::storage_traits::BoxFuture<'a, rusqlite::Result<()>> {
+        Box::pin(async move {
+            let conn = self.connection.lock().await;
+            conn.execute(
+                "INSERT INTO user_login_codes (code_hash, user_id, token_id, created_at, expires_at, used_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                rusqlite::params![
+                    &code.code_hash,
+                    &code.user_id,
+                    &code.token_id,
+                    encode_ts(&code.created_at),
+                    encode_ts(&code.expires_at),
+                    code.used_at.as_ref().map(encode_ts),
+                ],
+            )?;
+            Ok(())
+        })
+    }
+
+    fn count_user_login_codes_since<'a>(
+        &'a self,
+        token_id: &'a str,
+        since: DateTime<Utc>,
+    ) -> crate::server::storage_traits::BoxFuture<'a, rusqlite::Result<u64>> {
+        Box::pin(async move {
+            let conn = self.connection.lock().await;
+            let count: i64 = conn.query_row(
+                "SELECT COUNT(*) FROM user_login_codes WHERE token_id = ?1 AND created_at >= ?2",
+                rusqlite::params![token_id, encode_ts(&since)],
+                |row| row.get(0),
+            )?;
+            Ok(count as u64)
+        })
+    }
+
+    fn redeem_user_login_code<'a>(
+        &'a self,
+        code_hash: &'a str,
+        now: DateTime<Utc>,
+    ) -> crate::server::storage_traits::BoxFuture<'a, rusqlite::Result<Option<UserLoginCodeRecord>>>
+    {
+        Box::pin(async move {
+            let conn = self.connection.lock().await;
+            let now_raw = encode_ts(&now);
+            let updated = conn.execute(
+                "UPDATE user_login_codes SET used_at = ?2 WHERE code_hash = ?1 AND used_at IS NULL AND expires_at > ?2",
+                rusqlite::params![code_hash, &now_raw],
+            )?;
+            if updated == 0 {
+                return Ok(None);
+            }
+            conn.query_row(
+                "SELECT code_hash, user_id, token_id, created_at, expires_at FROM user_login_codes WHERE code_hash = ?1",
+                [code_hash],
+                |row| {
+                    let created_raw: String = row.get(3)?;
+                    let expires_raw: String = row.get(4)?;
+                    Ok(UserLoginCodeRecord {
+                        code_hash: row.get(0)?,
+                        user_id: row.get(1)?,
+                        token_id: row.get(2)?,
+                        created_at: decode_ts(&created_raw)?,
+                        expires_at: decode_ts(&expires_raw)?,
+                        used_at: Some(now),
+                    })
+                },
+            )
+            .optional()
+        })
+    }
+
     fn insert_web_session<'a>(
         &'a self,
         session: &'a WebSessionRecord,
@@ -580,7 +786,7 @@ impl crate::server::storage_traits::LoginStore for DatabaseLogger {
             let created = encode_ts(&session.created_at);
             let expires = encode_ts(&session.expires_at);
             conn.execute(
-                "INSERT INTO web_sessions (session_id, fingerprint, created_at, expires_at, revoked, issued_by_code) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                "INSERT INTO web_sessions (session_id, fingerprint, created_at, expires_at, revoked, issued_by_code, user_id) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
                 rusqlite::params![
                     &session.session_id,
                     session.fingerprint.as_deref(),
@@ -588,6 +794,7 @@ impl crate::server::storage_traits::LoginStore for DatabaseLogger {
                     &expires,
                     if session.revoked { 1 } else { 0 },
                     session.issued_by_code.as_deref(),
+                    session.user_id.as_deref(),
                 ],
             )?;
             Ok(())
@@ -602,21 +809,10 @@ impl crate::server::storage_traits::LoginStore for DatabaseLogger {
         Box::pin(async move {
             let conn = self.connection.lock().await;
             let mut stmt = conn.prepare(
-                "SELECT session_id, fingerprint, created_at, expires_at, revoked, issued_by_code FROM web_sessions WHERE session_id = ?1",
+                "SELECT session_id, fingerprint, created_at, expires_at, revoked, issued_by_code, last_seen_at, user_id FROM web_sessions WHERE session_id = ?1",
             )?;
             let rec = stmt
-                .query_row([session_id], |row| {
-                    let created_raw: String = row.get(2)?;
-                    let expires_raw: String = row.get(3)?;
-                    Ok(WebSessionRecord {
-                        session_id: row.get(0)?,
-                        fingerprint: row.get::<_, Option<String>>(1)?,
-                        created_at: decode_ts(&created_raw)?,
-                        expires_at: decode_ts(&expires_raw)?,
-                        revoked: row.get::<_, i64>(4)? != 0,
-                        issued_by_code: row.get::<_, Option<String>>(5)?,
-                    })
-                })
+                .query_row([session_id], web_session_from_row)
                 .optional()?;
             Ok(rec)
         })
@@ -635,6 +831,122 @@ impl crate::server::storage_traits::LoginStore for DatabaseLogger {
             Ok(affected > 0)
         })
     }
+
+    fn touch_web_session<'a>(
+        &'a self,
+        session_id: &'a str,
+        when: DateTime<Utc>,
+    ) -> crate::server::storage_traits::BoxFuture<'a, rusqlite::Result<()>> {
+        Box::pin(async move {
+            let conn = self.connection.lock().await;
+            conn.execute(
+                "UPDATE web_sessions SET last_seen_at = ?2 WHERE session_id = ?1",
+                rusqlite::params![session_id, encode_ts(&when)],
+            )?;
+            Ok(())
+        })
+    }
+
+    fn list_web_sessions<'a>(
+        &'a self,
+    ) -> crate::server::storage_traits::BoxFuture<'a, rusqlite::Result<Vec<WebSessionRecord>>> {
+        Box::pin(async move {
+            let conn = self.connection.lock().await;
+            let mut stmt = conn.prepare(
+                "SELECT session_id, fingerprint, created_at, expires_at, revoked, issued_by_code, last_seen_at, user_id FROM web_sessions WHERE revoked = 0 ORDER BY created_at DESC",
+            )?;
+            let rows = stmt.query_map([], web_session_from_row)?;
+            rows.collect()
+        })
+    }
+
+    fn get_admin_mfa<'a>(
+        &'a self,
+        user_id: &'a str,
+    ) -> crate::server::storage_traits::BoxFuture<'a, rusqlite::Result<Option<AdminMfaRecord>>>
+    {
+        Box::pin(async move {
+            let conn = self.connection.lock().await;
+            let mut stmt = conn.prepare(
+                "SELECT user_id, totp_secret, totp_enabled, recovery_codes, last_totp_step, failed_attempts, locked_until, updated_at FROM admin_mfa WHERE user_id = ?1",
+            )?;
+            let rec = stmt
+                .query_row([user_id], |row| {
+                    let recovery_raw: Option<String> = row.get(3)?;
+                    let locked_raw: Option<String> = row.get(6)?;
+                    let updated_raw: String = row.get(7)?;
+                    Ok(AdminMfaRecord {
+                        user_id: row.get(0)?,
+                        totp_secret: row.get(1)?,
+                        totp_enabled: row.get::<_, i64>(2)? != 0,
+                        recovery_code_hashes: recovery_raw
+                            .and_then(|s| serde_json::from_str(&s).ok())
+                            .unwrap_or_default(),
+                        last_totp_step: row.get(4)?,
+                        failed_attempts: row.get::<_, i64>(5)? as u32,
+                        locked_until: locked_raw.as_deref().map(decode_ts).transpose()?,
+                        updated_at: decode_ts(&updated_raw)?,
+                    })
+                })
+                .optional()?;
+            Ok(rec)
+        })
+    }
+
+    fn upsert_admin_mfa<'a>(
+        &'a self,
+        record: &'a AdminMfaRecord,
+    ) -> crate::server::storage_traits::BoxFuture<'a, rusqlite::Result<()>> {
+        Box::pin(async move {
+            let conn = self.connection.lock().await;
+            let recovery =
+                serde_json::to_string(&record.recovery_code_hashes).unwrap_or_else(|_| "[]".into());
+            conn.execute(
+                "INSERT OR REPLACE INTO admin_mfa (user_id, totp_secret, totp_enabled, recovery_codes, last_totp_step, failed_attempts, locked_until, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                rusqlite::params![
+                    &record.user_id,
+                    record.totp_secret.as_deref(),
+                    if record.totp_enabled { 1 } else { 0 },
+                    recovery,
+                    record.last_totp_step,
+                    record.failed_attempts as i64,
+                    record.locked_until.as_ref().map(encode_ts),
+                    encode_ts(&record.updated_at),
+                ],
+            )?;
+            Ok(())
+        })
+    }
+
+    fn delete_admin_mfa<'a>(
+        &'a self,
+        user_id: &'a str,
+    ) -> crate::server::storage_traits::BoxFuture<'a, rusqlite::Result<bool>> {
+        Box::pin(async move {
+            let conn = self.connection.lock().await;
+            let affected = conn.execute(
+                "DELETE FROM admin_mfa WHERE user_id = ?1",
+                rusqlite::params![user_id],
+            )?;
+            Ok(affected > 0)
+        })
+    }
+}
+
+fn web_session_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<WebSessionRecord> {
+    let created_raw: String = row.get(2)?;
+    let expires_raw: String = row.get(3)?;
+    let last_seen_raw: Option<String> = row.get(6)?;
+    Ok(WebSessionRecord {
+        session_id: row.get(0)?,
+        fingerprint: row.get::<_, Option<String>>(1)?,
+        created_at: decode_ts(&created_raw)?,
+        expires_at: decode_ts(&expires_raw)?,
+        revoked: row.get::<_, i64>(4)? != 0,
+        issued_by_code: row.get::<_, Option<String>>(5)?,
+        last_seen_at: last_seen_raw.as_deref().map(decode_ts).transpose()?,
+        user_id: row.get::<_, Option<String>>(7)?,
+    })
 }
 
 fn encode_ts(dt: &DateTime<Utc>) -> String {
@@ -650,6 +962,18 @@ fn decode_ts(raw: &str) -> rusqlite::Result<DateTime<Utc>> {
 }
 
 impl DatabaseLogger {
+    /// 自打开以来本连接写入的行数（所有写入都经由该连接，可用于判断数据库是否有变化）
+    pub async fn total_changes(&self) -> u64 {
+        self.connection.lock().await.total_changes()
+    }
+
+    /// 通过 `VACUUM INTO` 写出一致性快照；返回快照时刻的 total_changes
+    pub async fn snapshot_into(&self, dest: &std::path::Path) -> Result<u64> {
+        let conn = self.connection.lock().await;
+        conn.execute("VACUUM INTO ?1", [dest.to_string_lossy()])?;
+        Ok(conn.total_changes())
+    }
+
     #[allow(clippy::collapsible_if)]
     pub async fn new(database_path: &str) -> Result<Self> {
         // 确保数据库文件的目录存在
@@ -691,7 +1015,12 @@ impl DatabaseLogger {
                 error_message TEXT,
                 client_token TEXT,
                 user_id TEXT,
-                amount_spent REAL
+                amount_spent REAL,
+                pre_dispatch_ms INTEGER,
+                upstream_ms INTEGER,
+                post_process_ms INTEGER,
+                prompt_language TEXT,
+                content_category TEXT
             )",
             [],
         )?;
@@ -719,6 +1048,18 @@ impl DatabaseLogger {
             "ALTER TABLE request_logs ADD COLUMN reasoning_tokens INTEGER",
             [],
         );
+        for column in ["pre_dispatch_ms", "upstream_ms", "post_process_ms"] {
+            let _ = conn.execute(
+                &format!("ALTER TABLE request_logs ADD COLUMN {} INTEGER", column),
+                [],
+            );
+        }
+        for column in ["prompt_language", "content_category"] {
+            let _ = conn.execute(
+                &format!("ALTER TABLE request_logs ADD COLUMN {} TEXT", column),
+                [],
+            );
+        }
 
         conn.execute(
             "CREATE TABLE IF NOT EXISTS cached_models (
@@ -741,6 +1082,12 @@ impl DatabaseLogger {
                 enc INTEGER NOT NULL DEFAULT 0,
                 active INTEGER NOT NULL DEFAULT 1,
                 weight INTEGER NOT NULL DEFAULT 1,
+                spend_cap REAL,
+                rpm_limit INTEGER,
+                tpm_limit INTEGER,
+                openai_organization TEXT,
+                openai_project TEXT,
+                labels TEXT,
                 created_at TEXT NOT NULL,
                 PRIMARY KEY (provider, key_value)
             )",
@@ -778,12 +1125,33 @@ impl DatabaseLogger {
             )",
             [],
         )?;
+        // 组织委派管理员：每个用户最多被委派管理一个组织
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS organization_admins (
+                user_id TEXT PRIMARY KEY,
+                organization_id TEXT NOT NULL,
+                created_at TEXT NOT NULL
+            )",
+            [],
+        )?;
 
         // Best-effort migrations for provider keys/config
         let _ = conn.execute(
             "ALTER TABLE provider_keys ADD COLUMN weight INTEGER NOT NULL DEFAULT 1",
             [],
         );
+        let _ = conn.execute("ALTER TABLE provider_keys ADD COLUMN spend_cap REAL", []);
+        let _ = conn.execute("ALTER TABLE provider_keys ADD COLUMN rpm_limit INTEGER", []);
+        let _ = conn.execute("ALTER TABLE provider_keys ADD COLUMN tpm_limit INTEGER", []);
+        let _ = conn.execute(
+            "ALTER TABLE provider_keys ADD COLUMN openai_organization TEXT",
+            [],
+        );
+        let _ = conn.execute(
+            "ALTER TABLE provider_keys ADD COLUMN openai_project TEXT",
+            [],
+        );
+        let _ = conn.execute("ALTER TABLE provider_keys ADD COLUMN labels TEXT", []);
         let _ = conn.execute(
             "ALTER TABLE providers ADD COLUMN enabled INTEGER NOT NULL DEFAULT 1",
             [],
@@ -857,10 +1225,16 @@ impl DatabaseLogger {
                 timestamp TEXT NOT NULL,
                 operation TEXT NOT NULL,
                 provider TEXT,
-                details TEXT
+                details TEXT,
+                actor TEXT
             )",
             [],
         )?;
+        let _ = conn.execute("ALTER TABLE provider_ops_logs ADD COLUMN actor TEXT", []);
+        let _ = conn.execute(
+            "CREATE INDEX IF NOT EXISTS provider_ops_logs_provider_idx ON provider_ops_logs(provider, id)",
+            [],
+        );
 
         // Favorites table (best-effort, used by admin UI)
         conn.execute(
@@ -896,6 +1270,7 @@ impl DatabaseLogger {
                 email TEXT NOT NULL UNIQUE,
                 phone_number TEXT NOT NULL,
                 balance REAL NOT NULL DEFAULT 0,
+                budget_amount REAL,
                 status TEXT NOT NULL,
                 role TEXT NOT NULL,
                 password_hash TEXT,
@@ -914,6 +1289,7 @@ impl DatabaseLogger {
             "ALTER TABLE users ADD COLUMN balance REAL NOT NULL DEFAULT 0",
             [],
         );
+        let _ = conn.execute("ALTER TABLE users ADD COLUMN budget_amount REAL", []);
         // Ensure there is at most one superadmin.
         let _ = conn.execute(
             "CREATE UNIQUE INDEX IF NOT EXISTS users_one_superadmin_uidx ON users(role) WHERE role='superadmin'",
@@ -963,6 +1339,57 @@ impl DatabaseLogger {
                 selected_provider TEXT,
                 selected_key_id TEXT,
                 first_token_latency_ms INTEGER,
+                param_policy_applied TEXT,
+                provider_override TEXT,
+                prompt_truncation TEXT,
+                FOREIGN KEY (request_log_id) REFERENCES request_logs(id) ON DELETE CASCADE
+            )",
+            [],
+        )?;
+        let _ = conn.execute(
+            "ALTER TABLE request_log_details ADD COLUMN param_policy_applied TEXT",
+            [],
+        );
+        let _ = conn.execute(
+            "ALTER TABLE request_log_details ADD COLUMN provider_override TEXT",
+            [],
+        );
+        let _ = conn.execute(
+            "ALTER TABLE request_log_details ADD COLUMN prompt_truncation TEXT",
+            [],
+        );
+        // 捕获的请求/响应正文（zstd 压缩），与 request_log_details 分表以免拖慢日志查询
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS request_log_payloads (
+                request_log_id INTEGER PRIMARY KEY,
+                encoding TEXT NOT NULL,
+                request_body BLOB,
+                response_body BLOB,
+                original_bytes INTEGER NOT NULL DEFAULT 0,
+                stored_bytes INTEGER NOT NULL DEFAULT 0,
+                FOREIGN KEY (request_log_id) REFERENCES request_logs(id) ON DELETE CASCADE
+            )",
+            [],
+        )?;
+        // X-Gateway-Debug: capture 捕获的完整正文（zstd 压缩），过期后由保留任务清理
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS request_log_debug_captures (
+                request_log_id INTEGER PRIMARY KEY,
+                request_body BLOB,
+                response_body BLOB,
+                created_at TEXT NOT NULL,
+                expires_at TEXT NOT NULL,
+                FOREIGN KEY (request_log_id) REFERENCES request_logs(id) ON DELETE CASCADE
+            )",
+            [],
+        )?;
+        // 流式响应逐块捕获（chunks 为 zstd 压缩的 JSON 数组），随请求日志保留期清理
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS request_log_stream_chunks (
+                request_log_id INTEGER PRIMARY KEY,
+                chunks BLOB NOT NULL,
+                chunk_count INTEGER NOT NULL DEFAULT 0,
+                truncated INTEGER NOT NULL DEFAULT 0,
                 FOREIGN KEY (request_log_id) REFERENCES request_logs(id) ON DELETE CASCADE
             )",
             [],
@@ -982,33 +1409,190 @@ impl DatabaseLogger {
             [],
         );
         conn.execute(
-            "CREATE TABLE IF NOT EXISTS request_lab_sources (
-                user_id TEXT NOT NULL,
-                source_request_id INTEGER NOT NULL,
-                requested_model TEXT,
-                effective_model TEXT,
-                provider TEXT,
-                method TEXT NOT NULL,
-                path TEXT NOT NULL,
-                status_code INTEGER NOT NULL,
-                source_timestamp TEXT NOT NULL,
-                added_at TEXT NOT NULL,
-                PRIMARY KEY (user_id, source_request_id)
+            "CREATE TABLE IF NOT EXISTS idempotency_keys (
+                token_id TEXT NOT NULL,
+                idempotency_key TEXT NOT NULL,
+                request_fingerprint TEXT NOT NULL,
+                response_json TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                expires_at TEXT NOT NULL,
+                PRIMARY KEY (token_id, idempotency_key)
             )",
             [],
         )?;
-        let _ = conn.execute(
-            "CREATE INDEX IF NOT EXISTS request_lab_sources_user_id_added_at_idx ON request_lab_sources(user_id, added_at)",
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS usage_webhook_dead_letters (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                token_id TEXT NOT NULL,
+                url TEXT NOT NULL,
+                payload_json TEXT NOT NULL,
+                attempts INTEGER NOT NULL,
+                last_error TEXT,
+                created_at TEXT NOT NULL
+            )",
             [],
-        );
+        )?;
         conn.execute(
-            "CREATE TABLE IF NOT EXISTS request_lab_snapshots (
-                id TEXT PRIMARY KEY,
-                user_id TEXT NOT NULL,
-                source_request_id INTEGER NOT NULL,
-                compare_run_id TEXT NOT NULL,
-                note TEXT,
-                created_at TEXT NOT NULL,
+            "CREATE TABLE IF NOT EXISTS admin_notifications (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                kind TEXT NOT NULL,
+                severity TEXT NOT NULL,
+                title TEXT NOT NULL,
+                message TEXT NOT NULL,
+                reference TEXT,
+                created_at TEXT NOT NULL,
+                read_at TEXT
+            )",
+            [],
+        )?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS maintenance_windows (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                provider TEXT NOT NULL,
+                starts_at TEXT NOT NULL,
+                ends_at TEXT NOT NULL,
+                message TEXT,
+                created_by TEXT,
+                created_at TEXT NOT NULL
+            )",
+            [],
+        )?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS param_policies (
+                id TEXT PRIMARY KEY,
+                model TEXT,
+                token_id TEXT,
+                rules_json TEXT NOT NULL,
+                enabled INTEGER NOT NULL DEFAULT 1,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            )",
+            [],
+        )?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS billing_statements (
+                id TEXT PRIMARY KEY,
+                scope TEXT NOT NULL,
+                subject_id TEXT NOT NULL,
+                period TEXT NOT NULL,
+                body TEXT NOT NULL,
+                created_at TEXT NOT NULL
+            )",
+            [],
+        )?;
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS billing_statements_subject_idx ON billing_statements(scope, subject_id, period)",
+            [],
+        )?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS metrics_reports (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                period TEXT NOT NULL,
+                channel TEXT NOT NULL,
+                target TEXT NOT NULL,
+                template TEXT NOT NULL DEFAULT '',
+                enabled INTEGER NOT NULL DEFAULT 1,
+                last_sent_at TEXT,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            )",
+            [],
+        )?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS provider_budgets (
+                provider TEXT PRIMARY KEY,
+                monthly_budget REAL NOT NULL,
+                warn_thresholds TEXT NOT NULL,
+                stop_routing_when_exhausted INTEGER NOT NULL DEFAULT 1,
+                updated_at TEXT NOT NULL
+            )",
+            [],
+        )?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS image_prices (
+                provider TEXT NOT NULL,
+                model TEXT NOT NULL,
+                size TEXT NOT NULL DEFAULT '*',
+                quality TEXT NOT NULL DEFAULT '*',
+                price_per_image REAL NOT NULL,
+                currency TEXT,
+                updated_at TEXT NOT NULL,
+                PRIMARY KEY (provider, model, size, quality)
+            )",
+            [],
+        )?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS audio_prices (
+                provider TEXT NOT NULL,
+                model TEXT NOT NULL,
+                price_per_minute REAL NOT NULL,
+                currency TEXT,
+                updated_at TEXT NOT NULL,
+                PRIMARY KEY (provider, model)
+            )",
+            [],
+        )?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS speech_prices (
+                provider TEXT NOT NULL,
+                model TEXT NOT NULL,
+                price_per_million_chars REAL NOT NULL,
+                currency TEXT,
+                updated_at TEXT NOT NULL,
+                PRIMARY KEY (provider, model)
+            )",
+            [],
+        )?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS provider_egress_daily (
+                day TEXT NOT NULL,
+                provider TEXT NOT NULL,
+                api_key TEXT NOT NULL,
+                requests INTEGER NOT NULL DEFAULT 0,
+                request_bytes INTEGER NOT NULL DEFAULT 0,
+                response_bytes INTEGER NOT NULL DEFAULT 0,
+                PRIMARY KEY (day, provider, api_key)
+            )",
+            [],
+        )?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS token_request_counts (
+                day TEXT NOT NULL,
+                token_id TEXT NOT NULL,
+                requests INTEGER NOT NULL DEFAULT 0,
+                PRIMARY KEY (day, token_id)
+            )",
+            [],
+        )?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS request_lab_sources (
+                user_id TEXT NOT NULL,
+                source_request_id INTEGER NOT NULL,
+                requested_model TEXT,
+                effective_model TEXT,
+                provider TEXT,
+                method TEXT NOT NULL,
+                path TEXT NOT NULL,
+                status_code INTEGER NOT NULL,
+                source_timestamp TEXT NOT NULL,
+                added_at TEXT NOT NULL,
+                PRIMARY KEY (user_id, source_request_id)
+            )",
+            [],
+        )?;
+        let _ = conn.execute(
+            "CREATE INDEX IF NOT EXISTS request_lab_sources_user_id_added_at_idx ON request_lab_sources(user_id, added_at)",
+            [],
+        );
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS request_lab_snapshots (
+                id TEXT PRIMARY KEY,
+                user_id TEXT NOT NULL,
+                source_request_id INTEGER NOT NULL,
+                compare_run_id TEXT NOT NULL,
+                note TEXT,
+                created_at TEXT NOT NULL,
                 snapshot_json TEXT NOT NULL,
                 source_requested_model TEXT,
                 source_effective_model TEXT,
@@ -1113,10 +1697,15 @@ impl DatabaseLogger {
                 comment TEXT,
                 enabled INTEGER NOT NULL DEFAULT 1,
                 created_at TEXT NOT NULL,
-                last_used_at TEXT
+                last_used_at TEXT,
+                expires_at TEXT
             )",
             [],
         )?;
+        let _ = conn.execute(
+            "ALTER TABLE admin_public_keys ADD COLUMN expires_at TEXT",
+            [],
+        );
 
         conn.execute(
             "CREATE TABLE IF NOT EXISTS tui_sessions (
@@ -1147,6 +1736,22 @@ impl DatabaseLogger {
             [],
         )?;
 
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS user_login_codes (
+                code_hash TEXT PRIMARY KEY,
+                user_id TEXT NOT NULL,
+                token_id TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                expires_at TEXT NOT NULL,
+                used_at TEXT
+            )",
+            [],
+        )?;
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS user_login_codes_token_idx ON user_login_codes(token_id, created_at)",
+            [],
+        )?;
+
         conn.execute(
             "CREATE TABLE IF NOT EXISTS web_sessions (
                 session_id TEXT PRIMARY KEY,
@@ -1154,7 +1759,25 @@ impl DatabaseLogger {
                 created_at TEXT NOT NULL,
                 expires_at TEXT NOT NULL,
                 revoked INTEGER NOT NULL DEFAULT 0,
-                issued_by_code TEXT
+                issued_by_code TEXT,
+                last_seen_at TEXT,
+                user_id TEXT
+            )",
+            [],
+        )?;
+        let _ = conn.execute("ALTER TABLE web_sessions ADD COLUMN last_seen_at TEXT", []);
+        let _ = conn.execute("ALTER TABLE web_sessions ADD COLUMN user_id TEXT", []);
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS admin_mfa (
+                user_id TEXT PRIMARY KEY,
+                totp_secret TEXT,
+                totp_enabled INTEGER NOT NULL DEFAULT 0,
+                recovery_codes TEXT,
+                last_totp_step INTEGER,
+                failed_attempts INTEGER NOT NULL DEFAULT 0,
+                locked_until TEXT,
+                updated_at TEXT NOT NULL
             )",
             [],
         )?;
@@ -1177,6 +1800,63 @@ impl DatabaseLogger {
             [],
         )?;
 
+        // 令牌通知：退订标记与发送记录
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS client_token_prefs (
+                token_id TEXT PRIMARY KEY,
+                notifications_opt_out INTEGER NOT NULL DEFAULT 0,
+                auto_disable_exempt INTEGER NOT NULL DEFAULT 0,
+                auto_disabled_at TEXT,
+                rotation_exempt INTEGER NOT NULL DEFAULT 0,
+                rotated_at TEXT,
+                updated_at TEXT NOT NULL
+            )",
+            [],
+        )?;
+        let _ = conn.execute(
+            "ALTER TABLE client_token_prefs ADD COLUMN auto_disable_exempt INTEGER NOT NULL DEFAULT 0",
+            [],
+        );
+        let _ = conn.execute(
+            "ALTER TABLE client_token_prefs ADD COLUMN auto_disabled_at TEXT",
+            [],
+        );
+        let _ = conn.execute(
+            "ALTER TABLE client_token_prefs ADD COLUMN rotation_exempt INTEGER NOT NULL DEFAULT 0",
+            [],
+        );
+        let _ = conn.execute(
+            "ALTER TABLE client_token_prefs ADD COLUMN rotated_at TEXT",
+            [],
+        );
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS client_token_notifications (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                token_id TEXT NOT NULL,
+                kind TEXT NOT NULL,
+                reference TEXT NOT NULL,
+                channel TEXT NOT NULL,
+                recipient TEXT,
+                success INTEGER NOT NULL,
+                error TEXT,
+                sent_at TEXT NOT NULL
+            )",
+            [],
+        )?;
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS client_token_notifications_token_idx ON client_token_notifications(token_id, kind, reference)",
+            [],
+        )?;
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS gateway_settings (
+                key TEXT PRIMARY KEY,
+                value TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            )",
+            [],
+        )?;
+
         conn.execute(
             "CREATE TABLE IF NOT EXISTS password_reset_tokens (
                 id TEXT PRIMARY KEY,
@@ -1228,8 +1908,9 @@ impl DatabaseLogger {
                 timestamp, method, path, request_type, requested_model, effective_model, model, provider,
                 api_key, status_code, response_time_ms, prompt_tokens,
                 completion_tokens, total_tokens, cached_tokens, reasoning_tokens, error_message,
-                client_token, user_id, amount_spent
-            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20)",
+                client_token, user_id, amount_spent, pre_dispatch_ms, upstream_ms, post_process_ms,
+                prompt_language, content_category
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23, ?24, ?25)",
             rusqlite::params![
                 to_beijing_string(&log.timestamp),
                 &log.method,
@@ -1251,12 +1932,63 @@ impl DatabaseLogger {
                 &log.client_token,
                 &log.user_id,
                 &log.amount_spent,
+                log.latency.pre_dispatch_ms,
+                log.latency.upstream_ms,
+                log.latency.post_process_ms,
+                &log.profile.language,
+                &log.profile.category,
             ],
         )?;
 
         Ok(conn.last_insert_rowid())
     }
 
+    /// 按保留期清理请求日志（明细表随外键级联删除）
+    pub async fn purge_request_logs_before(&self, cutoff: DateTime<Utc>) -> Result<u64> {
+        let conn = self.connection.lock().await;
+        let cutoff = to_beijing_string(&cutoff);
+        conn.execute(
+            "DELETE FROM request_log_details WHERE request_log_id IN (SELECT id FROM request_logs WHERE timestamp < ?1)",
+            [&cutoff],
+        )?;
+        conn.execute(
+            "DELETE FROM request_log_payloads WHERE request_log_id IN (SELECT id FROM request_logs WHERE timestamp < ?1)",
+            [&cutoff],
+        )?;
+        conn.execute(
+            "DELETE FROM request_log_debug_captures WHERE request_log_id IN (SELECT id FROM request_logs WHERE timestamp < ?1)",
+            [&cutoff],
+        )?;
+        conn.execute(
+            "DELETE FROM request_log_stream_chunks WHERE request_log_id IN (SELECT id FROM request_logs WHERE timestamp < ?1)",
+            [&cutoff],
+        )?;
+        let affected = conn.execute("DELETE FROM request_logs WHERE timestamp < ?1", [&cutoff])?;
+        Ok(affected as u64)
+    }
+
+    pub async fn last_request_at_by_client_token(
+        &self,
+    ) -> Result<std::collections::HashMap<String, DateTime<Utc>>> {
+        let conn = self.connection.lock().await;
+        let mut stmt = conn.prepare(
+            "SELECT client_token, MAX(timestamp) FROM request_logs
+             WHERE client_token IS NOT NULL AND client_token <> ''
+             GROUP BY client_token",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?;
+        let mut out = std::collections::HashMap::new();
+        for row in rows {
+            let (token_id, ts) = row?;
+            if let Ok(ts) = parse_beijing_string(&ts) {
+                out.insert(token_id, ts);
+            }
+        }
+        Ok(out)
+    }
+
     // 模型缓存相关方法已拆分至 database_cache.rs
 
     #[allow(dead_code)]
@@ -1275,7 +2007,8 @@ impl DatabaseLogger {
                 "SELECT id, timestamp, method, path, request_type, requested_model, effective_model, model, provider,
                         api_key, status_code, response_time_ms, prompt_tokens,
                         completion_tokens, total_tokens, cached_tokens, reasoning_tokens, error_message,
-                        client_token, user_id, amount_spent
+                        client_token, user_id, amount_spent, pre_dispatch_ms, upstream_ms, post_process_ms,
+                prompt_language, content_category
                  FROM request_logs
                  WHERE id < ?1
                  ORDER BY id DESC
@@ -1286,7 +2019,8 @@ impl DatabaseLogger {
                 "SELECT id, timestamp, method, path, request_type, requested_model, effective_model, model, provider,
                         api_key, status_code, response_time_ms, prompt_tokens,
                         completion_tokens, total_tokens, cached_tokens, reasoning_tokens, error_message,
-                        client_token, user_id, amount_spent
+                        client_token, user_id, amount_spent, pre_dispatch_ms, upstream_ms, post_process_ms,
+                prompt_language, content_category
                  FROM request_logs
                  ORDER BY id DESC
                  LIMIT ?1",
@@ -1307,6 +2041,43 @@ impl DatabaseLogger {
         Ok(logs)
     }
 
+    pub async fn get_logs_with_columns(
+        &self,
+        limit: i32,
+        cursor: Option<i64>,
+        method_path: Option<(&str, &str)>,
+        columns: &LogColumns,
+    ) -> Result<Vec<RequestLog>> {
+        let mut conditions: Vec<String> = Vec::new();
+        let mut params: Vec<rusqlite::types::Value> = Vec::new();
+        if let Some((method, path)) = method_path {
+            params.push(method.to_string().into());
+            conditions.push(format!("method = ?{}", params.len()));
+            params.push(path.to_string().into());
+            conditions.push(format!("path = ?{}", params.len()));
+        }
+        if let Some(cursor_id) = cursor {
+            params.push(cursor_id.into());
+            conditions.push(format!("id < ?{}", params.len()));
+        }
+        params.push((limit as i64).into());
+        let where_clause = if conditions.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", conditions.join(" AND "))
+        };
+        let sql = format!(
+            "SELECT {} FROM request_logs {} ORDER BY id DESC LIMIT ?{}",
+            columns.select_list(),
+            where_clause,
+            params.len()
+        );
+        let conn = self.connection.lock().await;
+        let mut stmt = conn.prepare(&sql)?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(params), map_request_log_row)?;
+        rows.collect()
+    }
+
     #[allow(dead_code)]
     pub async fn get_request_logs(
         &self,
@@ -1320,7 +2091,8 @@ impl DatabaseLogger {
                 "SELECT id, timestamp, method, path, request_type, requested_model, effective_model, model, provider,
                         api_key, status_code, response_time_ms, prompt_tokens,
                         completion_tokens, total_tokens, cached_tokens, reasoning_tokens, error_message,
-                        client_token, user_id, amount_spent
+                        client_token, user_id, amount_spent, pre_dispatch_ms, upstream_ms, post_process_ms,
+                prompt_language, content_category
                  FROM request_logs
                  WHERE id < ?1
                  ORDER BY id DESC
@@ -1331,7 +2103,8 @@ impl DatabaseLogger {
                 "SELECT id, timestamp, method, path, request_type, requested_model, effective_model, model, provider,
                         api_key, status_code, response_time_ms, prompt_tokens,
                         completion_tokens, total_tokens, cached_tokens, reasoning_tokens, error_message,
-                        client_token, user_id, amount_spent
+                        client_token, user_id, amount_spent, pre_dispatch_ms, upstream_ms, post_process_ms,
+                prompt_language, content_category
                  FROM request_logs
                  ORDER BY id DESC
                  LIMIT ?1",
@@ -1344,169 +2117,1134 @@ impl DatabaseLogger {
             stmt.query_map([limit], map_request_log_row)?
         };
 
-        let mut out = Vec::new();
-        for r in rows {
-            out.push(r?);
-        }
-        Ok(out)
+        let mut out = Vec::new();
+        for r in rows {
+            out.push(r?);
+        }
+        Ok(out)
+    }
+
+    pub async fn get_request_log_by_id(&self, id: i64) -> Result<Option<RequestLog>> {
+        let conn = self.connection.lock().await;
+        let mut stmt = conn.prepare(
+            "SELECT id, timestamp, method, path, request_type, requested_model, effective_model, model, provider,
+                    api_key, status_code, response_time_ms, prompt_tokens,
+                    completion_tokens, total_tokens, cached_tokens, reasoning_tokens, error_message,
+                    client_token, user_id, amount_spent, pre_dispatch_ms, upstream_ms, post_process_ms,
+                prompt_language, content_category
+             FROM request_logs WHERE id = ?1 LIMIT 1",
+        )?;
+        stmt.query_row([id], map_request_log_row).optional()
+    }
+
+    /// 正文写入 request_log_payloads（压缩），明细表中的正文列留空
+    pub async fn upsert_request_log_detail(&self, detail: RequestLogDetailRecord) -> Result<()> {
+        let payload = ArchivedPayload::pack(
+            detail.request_payload_snapshot.as_deref(),
+            detail.response_preview.as_deref(),
+        );
+        let conn = self.connection.lock().await;
+        conn.execute(
+            "INSERT INTO request_log_details (
+                request_log_id, request_payload_snapshot, response_preview, upstream_status,
+                fallback_triggered, fallback_reason, selected_provider, selected_key_id, first_token_latency_ms,
+                param_policy_applied, provider_override, prompt_truncation
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)
+            ON CONFLICT(request_log_id) DO UPDATE SET
+                request_payload_snapshot = excluded.request_payload_snapshot,
+                response_preview = excluded.response_preview,
+                upstream_status = excluded.upstream_status,
+                fallback_triggered = excluded.fallback_triggered,
+                fallback_reason = excluded.fallback_reason,
+                selected_provider = excluded.selected_provider,
+                selected_key_id = excluded.selected_key_id,
+                first_token_latency_ms = excluded.first_token_latency_ms,
+                param_policy_applied = excluded.param_policy_applied,
+                provider_override = excluded.provider_override,
+                prompt_truncation = excluded.prompt_truncation",
+            rusqlite::params![
+                detail.request_log_id,
+                None::<String>,
+                None::<String>,
+                detail.upstream_status,
+                detail.fallback_triggered.map(|v| if v { 1 } else { 0 }),
+                detail.fallback_reason,
+                detail.selected_provider,
+                detail.selected_key_id,
+                detail.first_token_latency_ms,
+                detail.param_policy_applied,
+                detail.provider_override,
+                detail.prompt_truncation,
+            ],
+        )?;
+        match payload {
+            Some(p) => {
+                conn.execute(
+                    "INSERT INTO request_log_payloads (
+                        request_log_id, encoding, request_body, response_body, original_bytes, stored_bytes
+                    ) VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                    ON CONFLICT(request_log_id) DO UPDATE SET
+                        encoding = excluded.encoding,
+                        request_body = excluded.request_body,
+                        response_body = excluded.response_body,
+                        original_bytes = excluded.original_bytes,
+                        stored_bytes = excluded.stored_bytes",
+                    rusqlite::params![
+                        detail.request_log_id,
+                        ENCODING_ZSTD,
+                        p.request_body,
+                        p.response_body,
+                        p.original_bytes,
+                        p.stored_bytes,
+                    ],
+                )?;
+            }
+            None => {
+                conn.execute(
+                    "DELETE FROM request_log_payloads WHERE request_log_id = ?1",
+                    [detail.request_log_id],
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn get_request_log_detail(
+        &self,
+        request_log_id: i64,
+    ) -> Result<Option<RequestLogDetailRecord>> {
+        let conn = self.connection.lock().await;
+        let mut stmt = conn.prepare(
+            "SELECT d.request_log_id, d.request_payload_snapshot, d.response_preview, d.upstream_status,
+                    d.fallback_triggered, d.fallback_reason, d.selected_provider, d.selected_key_id,
+                    d.first_token_latency_ms, p.request_body, p.response_body, d.param_policy_applied,
+                    d.provider_override, d.prompt_truncation
+             FROM request_log_details d
+             LEFT JOIN request_log_payloads p ON p.request_log_id = d.request_log_id
+             WHERE d.request_log_id = ?1 LIMIT 1",
+        )?;
+        stmt.query_row([request_log_id], |row| {
+            // 旧数据的正文仍以明文存于明细表
+            let unpack = |blob: Option<Vec<u8>>, legacy: Option<String>| {
+                blob.and_then(|b| payload_archive::decompress(&b))
+                    .or(legacy)
+            };
+            Ok(RequestLogDetailRecord {
+                request_log_id: row.get(0)?,
+                request_payload_snapshot: unpack(row.get(9)?, row.get(1)?),
+                response_preview: unpack(row.get(10)?, row.get(2)?),
+                upstream_status: row.get(3)?,
+                fallback_triggered: row.get::<_, Option<i64>>(4)?.map(|value| value != 0),
+                fallback_reason: row.get(5)?,
+                selected_provider: row.get(6)?,
+                selected_key_id: row.get(7)?,
+                first_token_latency_ms: row.get(8)?,
+                param_policy_applied: row.get(11)?,
+                provider_override: row.get(12)?,
+                prompt_truncation: row.get(13)?,
+            })
+        })
+        .optional()
+    }
+
+    pub async fn save_compare_run(&self, run: StoredCompareRun) -> Result<()> {
+        let conn = self.connection.lock().await;
+        conn.execute(
+            "INSERT INTO compare_runs (id, user_id, source_request_id, created_at, result_json)
+             VALUES (?1, ?2, ?3, ?4, ?5)
+             ON CONFLICT(id) DO UPDATE SET
+                user_id = excluded.user_id,
+                source_request_id = excluded.source_request_id,
+                created_at = excluded.created_at,
+                result_json = excluded.result_json",
+            rusqlite::params![
+                run.id,
+                run.user_id,
+                run.source_request_id,
+                to_beijing_string(&run.created_at),
+                run.result_json,
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub async fn get_compare_run(&self, id: &str) -> Result<Option<StoredCompareRun>> {
+        let conn = self.connection.lock().await;
+        let mut stmt = conn.prepare(
+            "SELECT id, user_id, source_request_id, created_at, result_json
+             FROM compare_runs WHERE id = ?1 LIMIT 1",
+        )?;
+        stmt.query_row([id], |row| {
+            let created_at: String = row.get(3)?;
+            Ok(StoredCompareRun {
+                id: row.get(0)?,
+                user_id: row.get(1)?,
+                source_request_id: row.get(2)?,
+                created_at: parse_beijing_string(&created_at)
+                    .unwrap_or_else(|_| chrono::Utc::now()),
+                result_json: row.get(4)?,
+            })
+        })
+        .optional()
+    }
+
+    pub async fn get_idempotent_response(
+        &self,
+        token_id: &str,
+        idempotency_key: &str,
+    ) -> Result<Option<StoredIdempotentResponse>> {
+        let conn = self.connection.lock().await;
+        let mut stmt = conn.prepare(
+            "SELECT token_id, idempotency_key, request_fingerprint, response_json, created_at, expires_at
+             FROM idempotency_keys WHERE token_id = ?1 AND idempotency_key = ?2 AND expires_at > ?3",
+        )?;
+        let now = to_beijing_string(&Utc::now());
+        stmt.query_row(rusqlite::params![token_id, idempotency_key, now], |row| {
+            let created_at: String = row.get(4)?;
+            let expires_at: String = row.get(5)?;
+            Ok(StoredIdempotentResponse {
+                token_id: row.get(0)?,
+                idempotency_key: row.get(1)?,
+                request_fingerprint: row.get(2)?,
+                response_json: row.get(3)?,
+                created_at: parse_beijing_string(&created_at)
+                    .unwrap_or_else(|_| chrono::Utc::now()),
+                expires_at: parse_beijing_string(&expires_at)
+                    .unwrap_or_else(|_| chrono::Utc::now()),
+            })
+        })
+        .optional()
+    }
+
+    /// 写入幂等响应，并顺带清理已过期的记录
+    pub async fn save_idempotent_response(&self, record: StoredIdempotentResponse) -> Result<()> {
+        let conn = self.connection.lock().await;
+        conn.execute(
+            "DELETE FROM idempotency_keys WHERE expires_at <= ?1",
+            [to_beijing_string(&Utc::now())],
+        )?;
+        conn.execute(
+            "INSERT INTO idempotency_keys (token_id, idempotency_key, request_fingerprint, response_json, created_at, expires_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+             ON CONFLICT(token_id, idempotency_key) DO UPDATE SET
+                request_fingerprint = excluded.request_fingerprint,
+                response_json = excluded.response_json,
+                created_at = excluded.created_at,
+                expires_at = excluded.expires_at",
+            rusqlite::params![
+                record.token_id,
+                record.idempotency_key,
+                record.request_fingerprint,
+                record.response_json,
+                to_beijing_string(&record.created_at),
+                to_beijing_string(&record.expires_at),
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// 写入调试捕获，并顺带清理已过期的记录
+    pub async fn save_debug_capture(&self, record: DebugCaptureRecord) -> Result<()> {
+        let conn = self.connection.lock().await;
+        conn.execute(
+            "DELETE FROM request_log_debug_captures WHERE expires_at <= ?1",
+            [to_beijing_string(&Utc::now())],
+        )?;
+        conn.execute(
+            "INSERT INTO request_log_debug_captures (request_log_id, request_body, response_body, created_at, expires_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)
+             ON CONFLICT(request_log_id) DO UPDATE SET
+                request_body = excluded.request_body,
+                response_body = excluded.response_body,
+                created_at = excluded.created_at,
+                expires_at = excluded.expires_at",
+            rusqlite::params![
+                record.request_log_id,
+                record.request_body.as_deref().map(payload_archive::compress),
+                record.response_body.as_deref().map(payload_archive::compress),
+                to_beijing_string(&record.created_at),
+                to_beijing_string(&record.expires_at),
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub async fn get_debug_capture(
+        &self,
+        request_log_id: i64,
+    ) -> Result<Option<DebugCaptureRecord>> {
+        let conn = self.connection.lock().await;
+        let mut stmt = conn.prepare(
+            "SELECT request_log_id, request_body, response_body, created_at, expires_at
+             FROM request_log_debug_captures WHERE request_log_id = ?1 AND expires_at > ?2",
+        )?;
+        let now = to_beijing_string(&Utc::now());
+        stmt.query_row(rusqlite::params![request_log_id, now], |row| {
+            let created_at: String = row.get(3)?;
+            let expires_at: String = row.get(4)?;
+            Ok(DebugCaptureRecord {
+                request_log_id: row.get(0)?,
+                request_body: row
+                    .get::<_, Option<Vec<u8>>>(1)?
+                    .and_then(|b| payload_archive::decompress(&b)),
+                response_body: row
+                    .get::<_, Option<Vec<u8>>>(2)?
+                    .and_then(|b| payload_archive::decompress(&b)),
+                created_at: parse_beijing_string(&created_at)
+                    .unwrap_or_else(|_| chrono::Utc::now()),
+                expires_at: parse_beijing_string(&expires_at)
+                    .unwrap_or_else(|_| chrono::Utc::now()),
+            })
+        })
+        .optional()
+    }
+
+    pub async fn save_stream_capture(&self, record: StreamCaptureRecord) -> Result<()> {
+        let encoded = serde_json::to_string(&record.chunks)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+        let conn = self.connection.lock().await;
+        conn.execute(
+            "INSERT INTO request_log_stream_chunks (request_log_id, chunks, chunk_count, truncated)
+             VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(request_log_id) DO UPDATE SET
+                chunks = excluded.chunks,
+                chunk_count = excluded.chunk_count,
+                truncated = excluded.truncated",
+            rusqlite::params![
+                record.request_log_id,
+                payload_archive::compress(&encoded),
+                record.chunks.len() as i64,
+                record.truncated as i64,
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub async fn get_stream_capture(
+        &self,
+        request_log_id: i64,
+    ) -> Result<Option<StreamCaptureRecord>> {
+        let conn = self.connection.lock().await;
+        let mut stmt = conn.prepare(
+            "SELECT request_log_id, chunks, truncated
+             FROM request_log_stream_chunks WHERE request_log_id = ?1",
+        )?;
+        stmt.query_row([request_log_id], |row| {
+            let chunks: Vec<u8> = row.get(1)?;
+            Ok(StreamCaptureRecord {
+                request_log_id: row.get(0)?,
+                chunks: payload_archive::decompress(&chunks)
+                    .and_then(|json| serde_json::from_str(&json).ok())
+                    .unwrap_or_default(),
+                truncated: row.get::<_, i64>(2)? != 0,
+            })
+        })
+        .optional()
+    }
+
+    pub async fn purge_expired_debug_captures(&self, now: DateTime<Utc>) -> Result<u64> {
+        let conn = self.connection.lock().await;
+        let affected = conn.execute(
+            "DELETE FROM request_log_debug_captures WHERE expires_at <= ?1",
+            [to_beijing_string(&now)],
+        )?;
+        Ok(affected as u64)
+    }
+
+    pub async fn insert_usage_webhook_dead_letter(
+        &self,
+        letter: UsageWebhookDeadLetter,
+    ) -> Result<i64> {
+        let conn = self.connection.lock().await;
+        conn.execute(
+            "INSERT INTO usage_webhook_dead_letters (token_id, url, payload_json, attempts, last_error, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            rusqlite::params![
+                letter.token_id,
+                letter.url,
+                letter.payload_json,
+                letter.attempts,
+                letter.last_error,
+                to_beijing_string(&letter.created_at),
+            ],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    pub async fn list_usage_webhook_dead_letters(
+        &self,
+        limit: i32,
+    ) -> Result<Vec<UsageWebhookDeadLetter>> {
+        let conn = self.connection.lock().await;
+        let mut stmt = conn.prepare(
+            "SELECT id, token_id, url, payload_json, attempts, last_error, created_at
+             FROM usage_webhook_dead_letters ORDER BY id DESC LIMIT ?1",
+        )?;
+        let rows = stmt.query_map([limit], usage_webhook_dead_letter_from_row)?;
+        rows.collect()
+    }
+
+    pub async fn get_usage_webhook_dead_letter(
+        &self,
+        id: i64,
+    ) -> Result<Option<UsageWebhookDeadLetter>> {
+        let conn = self.connection.lock().await;
+        let mut stmt = conn.prepare(
+            "SELECT id, token_id, url, payload_json, attempts, last_error, created_at
+             FROM usage_webhook_dead_letters WHERE id = ?1",
+        )?;
+        stmt.query_row([id], usage_webhook_dead_letter_from_row)
+            .optional()
+    }
+
+    pub async fn delete_usage_webhook_dead_letter(&self, id: i64) -> Result<bool> {
+        let conn = self.connection.lock().await;
+        let affected =
+            conn.execute("DELETE FROM usage_webhook_dead_letters WHERE id = ?1", [id])?;
+        Ok(affected > 0)
+    }
+
+    /// 写入一条管理员通知；同 kind 同 reference 的未读通知已存在时跳过并返回 None
+    pub async fn insert_admin_notification(
+        &self,
+        notification: AdminNotificationRecord,
+    ) -> Result<Option<i64>> {
+        let conn = self.connection.lock().await;
+        let affected = conn.execute(
+            "INSERT INTO admin_notifications (kind, severity, title, message, reference, created_at)
+             SELECT ?1, ?2, ?3, ?4, ?5, ?6
+             WHERE ?5 IS NULL OR NOT EXISTS (
+                 SELECT 1 FROM admin_notifications
+                 WHERE kind = ?1 AND reference = ?5 AND read_at IS NULL
+             )",
+            rusqlite::params![
+                notification.kind,
+                notification.severity,
+                notification.title,
+                notification.message,
+                notification.reference,
+                to_beijing_string(&notification.created_at),
+            ],
+        )?;
+        Ok((affected > 0).then(|| conn.last_insert_rowid()))
+    }
+
+    pub async fn list_admin_notifications(
+        &self,
+        unread_only: bool,
+        limit: i32,
+    ) -> Result<Vec<AdminNotificationRecord>> {
+        let conn = self.connection.lock().await;
+        let mut stmt = conn.prepare(
+            "SELECT id, kind, severity, title, message, reference, created_at, read_at
+             FROM admin_notifications WHERE (?1 = 0 OR read_at IS NULL)
+             ORDER BY id DESC LIMIT ?2",
+        )?;
+        let rows = stmt.query_map(
+            rusqlite::params![unread_only, limit],
+            admin_notification_from_row,
+        )?;
+        rows.collect()
+    }
+
+    pub async fn count_unread_admin_notifications(&self) -> Result<u64> {
+        let conn = self.connection.lock().await;
+        let count: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM admin_notifications WHERE read_at IS NULL",
+            [],
+            |row| row.get(0),
+        )?;
+        Ok(count.max(0) as u64)
+    }
+
+    /// 标记通知为已读；ids 为 None 时标记全部未读通知
+    pub async fn mark_admin_notifications_read(
+        &self,
+        ids: Option<Vec<i64>>,
+        at: DateTime<Utc>,
+    ) -> Result<u64> {
+        let conn = self.connection.lock().await;
+        let at = to_beijing_string(&at);
+        let affected = match ids {
+            None => conn.execute(
+                "UPDATE admin_notifications SET read_at = ?1 WHERE read_at IS NULL",
+                [&at],
+            )?,
+            Some(ids) => {
+                let mut affected = 0;
+                for id in ids {
+                    affected += conn.execute(
+                        "UPDATE admin_notifications SET read_at = ?1 WHERE id = ?2 AND read_at IS NULL",
+                        rusqlite::params![at, id],
+                    )?;
+                }
+                affected
+            }
+        };
+        Ok(affected as u64)
+    }
+
+    pub async fn insert_maintenance_window(&self, window: MaintenanceWindowRecord) -> Result<i64> {
+        let conn = self.connection.lock().await;
+        conn.execute(
+            "INSERT INTO maintenance_windows (provider, starts_at, ends_at, message, created_by, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            rusqlite::params![
+                window.provider,
+                to_beijing_string(&window.starts_at),
+                to_beijing_string(&window.ends_at),
+                window.message,
+                window.created_by,
+                to_beijing_string(&window.created_at),
+            ],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    pub async fn list_maintenance_windows(
+        &self,
+        ending_after: DateTime<Utc>,
+    ) -> Result<Vec<MaintenanceWindowRecord>> {
+        let conn = self.connection.lock().await;
+        let mut stmt = conn.prepare(
+            "SELECT id, provider, starts_at, ends_at, message, created_by, created_at
+             FROM maintenance_windows WHERE ends_at > ?1 ORDER BY starts_at, id",
+        )?;
+        let rows = stmt.query_map(
+            [to_beijing_string(&ending_after)],
+            maintenance_window_from_row,
+        )?;
+        rows.collect()
+    }
+
+    pub async fn delete_maintenance_window(&self, id: i64) -> Result<bool> {
+        let conn = self.connection.lock().await;
+        let affected = conn.execute("DELETE FROM maintenance_windows WHERE id = ?1", [id])?;
+        Ok(affected > 0)
+    }
+
+    pub async fn list_param_policies(&self) -> Result<Vec<ParamPolicyRecord>> {
+        let conn = self.connection.lock().await;
+        let mut stmt = conn.prepare(
+            "SELECT id, model, token_id, rules_json, enabled, created_at, updated_at
+             FROM param_policies ORDER BY created_at, id",
+        )?;
+        let rows = stmt.query_map([], param_policy_from_row)?;
+        rows.collect()
+    }
+
+    pub async fn upsert_param_policy(&self, policy: ParamPolicyRecord) -> Result<()> {
+        let conn = self.connection.lock().await;
+        conn.execute(
+            "INSERT INTO param_policies (id, model, token_id, rules_json, enabled, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+             ON CONFLICT(id) DO UPDATE SET
+                model = excluded.model,
+                token_id = excluded.token_id,
+                rules_json = excluded.rules_json,
+                enabled = excluded.enabled,
+                updated_at = excluded.updated_at",
+            rusqlite::params![
+                policy.id,
+                policy.model,
+                policy.token_id,
+                policy.rules_json,
+                if policy.enabled { 1 } else { 0 },
+                to_beijing_string(&policy.created_at),
+                to_beijing_string(&policy.updated_at),
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub async fn delete_param_policy(&self, id: &str) -> Result<bool> {
+        let conn = self.connection.lock().await;
+        let affected = conn.execute("DELETE FROM param_policies WHERE id = ?1", [id])?;
+        Ok(affected > 0)
+    }
+
+    pub async fn list_metrics_reports(&self) -> Result<Vec<MetricsReportRecord>> {
+        let conn = self.connection.lock().await;
+        let mut stmt = conn.prepare(
+            "SELECT id, name, period, channel, target, template, enabled, last_sent_at, created_at, updated_at
+             FROM metrics_reports ORDER BY created_at, id",
+        )?;
+        let rows = stmt.query_map([], metrics_report_from_row)?;
+        rows.collect()
+    }
+
+    pub async fn upsert_metrics_report(&self, report: MetricsReportRecord) -> Result<()> {
+        let conn = self.connection.lock().await;
+        conn.execute(
+            "INSERT INTO metrics_reports (id, name, period, channel, target, template, enabled, last_sent_at, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+             ON CONFLICT(id) DO UPDATE SET
+                name = excluded.name,
+                period = excluded.period,
+                channel = excluded.channel,
+                target = excluded.target,
+                template = excluded.template,
+                enabled = excluded.enabled,
+                updated_at = excluded.updated_at",
+            rusqlite::params![
+                report.id,
+                report.name,
+                report.period,
+                report.channel,
+                report.target,
+                report.template,
+                if report.enabled { 1 } else { 0 },
+                report.last_sent_at.as_ref().map(to_beijing_string),
+                to_beijing_string(&report.created_at),
+                to_beijing_string(&report.updated_at),
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub async fn delete_metrics_report(&self, id: &str) -> Result<bool> {
+        let conn = self.connection.lock().await;
+        let affected = conn.execute("DELETE FROM metrics_reports WHERE id = ?1", [id])?;
+        Ok(affected > 0)
+    }
+
+    pub async fn mark_metrics_report_sent(&self, id: &str, sent_at: DateTime<Utc>) -> Result<()> {
+        let conn = self.connection.lock().await;
+        conn.execute(
+            "UPDATE metrics_reports SET last_sent_at = ?2 WHERE id = ?1",
+            rusqlite::params![id, to_beijing_string(&sent_at)],
+        )?;
+        Ok(())
+    }
+
+    pub async fn aggregate_token_usage_daily(
+        &self,
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+    ) -> Result<Vec<TokenUsageDaily>> {
+        let conn = self.connection.lock().await;
+        let mut stmt = conn.prepare(
+            "SELECT client_token,
+                    substr(timestamp, 1, 10) AS day,
+                    COALESCE(NULLIF(effective_model, ''), model) AS billed_model,
+                    COUNT(*),
+                    COALESCE(SUM(prompt_tokens), 0),
+                    COALESCE(SUM(completion_tokens), 0),
+                    COALESCE(SUM(total_tokens), 0),
+                    COALESCE(SUM(amount_spent), 0.0)
+             FROM request_logs
+             WHERE client_token IS NOT NULL
+               AND COALESCE(NULLIF(effective_model, ''), model) IS NOT NULL
+               AND timestamp >= ?1
+               AND timestamp < ?2
+             GROUP BY client_token, day, billed_model
+             ORDER BY day, client_token, billed_model",
+        )?;
+        let rows = stmt.query_map(
+            rusqlite::params![to_beijing_string(&since), to_beijing_string(&until)],
+            |row| {
+                Ok(TokenUsageDaily {
+                    client_token: row.get(0)?,
+                    day: row.get(1)?,
+                    model: row.get(2)?,
+                    requests: row.get::<_, i64>(3)?.max(0) as u64,
+                    prompt_tokens: row.get::<_, i64>(4)?.max(0) as u64,
+                    completion_tokens: row.get::<_, i64>(5)?.max(0) as u64,
+                    total_tokens: row.get::<_, i64>(6)?.max(0) as u64,
+                    cost: row.get(7)?,
+                })
+            },
+        )?;
+        rows.collect()
+    }
+
+    pub async fn insert_statement(&self, statement: StatementRecord) -> Result<bool> {
+        let conn = self.connection.lock().await;
+        let affected = conn.execute(
+            "INSERT OR IGNORE INTO billing_statements (id, scope, subject_id, period, body, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            rusqlite::params![
+                statement.id,
+                statement.scope,
+                statement.subject_id,
+                statement.period,
+                statement.body,
+                to_beijing_string(&statement.created_at),
+            ],
+        )?;
+        Ok(affected > 0)
+    }
+
+    pub async fn get_statement(&self, id: &str) -> Result<Option<StatementRecord>> {
+        let conn = self.connection.lock().await;
+        conn.query_row(
+            "SELECT id, scope, subject_id, period, body, created_at FROM billing_statements WHERE id = ?1",
+            [id],
+            statement_from_row,
+        )
+        .optional()
+    }
+
+    pub async fn list_statements(
+        &self,
+        scope: Option<&str>,
+        subject_id: Option<&str>,
+        period: Option<&str>,
+    ) -> Result<Vec<StatementRecord>> {
+        let conn = self.connection.lock().await;
+        let mut stmt = conn.prepare(
+            "SELECT id, scope, subject_id, period, body, created_at FROM billing_statements
+             WHERE (?1 IS NULL OR scope = ?1)
+               AND (?2 IS NULL OR subject_id = ?2)
+               AND (?3 IS NULL OR period = ?3)
+             ORDER BY period DESC, scope, subject_id",
+        )?;
+        let rows = stmt.query_map(
+            rusqlite::params![scope, subject_id, period],
+            statement_from_row,
+        )?;
+        rows.collect()
+    }
+
+    pub async fn list_provider_budgets(&self) -> Result<Vec<ProviderBudgetRecord>> {
+        let conn = self.connection.lock().await;
+        let mut stmt = conn.prepare(
+            "SELECT provider, monthly_budget, warn_thresholds, stop_routing_when_exhausted, updated_at
+             FROM provider_budgets ORDER BY provider",
+        )?;
+        let rows = stmt.query_map([], provider_budget_from_row)?;
+        rows.collect()
+    }
+
+    pub async fn get_provider_budget(
+        &self,
+        provider: &str,
+    ) -> Result<Option<ProviderBudgetRecord>> {
+        let conn = self.connection.lock().await;
+        let mut stmt = conn.prepare(
+            "SELECT provider, monthly_budget, warn_thresholds, stop_routing_when_exhausted, updated_at
+             FROM provider_budgets WHERE provider = ?1",
+        )?;
+        stmt.query_row([provider], provider_budget_from_row)
+            .optional()
+    }
+
+    pub async fn upsert_provider_budget(&self, budget: ProviderBudgetRecord) -> Result<()> {
+        let conn = self.connection.lock().await;
+        conn.execute(
+            "INSERT INTO provider_budgets (provider, monthly_budget, warn_thresholds, stop_routing_when_exhausted, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)
+             ON CONFLICT(provider) DO UPDATE SET
+                monthly_budget = excluded.monthly_budget,
+                warn_thresholds = excluded.warn_thresholds,
+                stop_routing_when_exhausted = excluded.stop_routing_when_exhausted,
+                updated_at = excluded.updated_at",
+            rusqlite::params![
+                budget.provider,
+                budget.monthly_budget,
+                serde_json::to_string(&budget.warn_thresholds).unwrap_or_else(|_| "[]".into()),
+                if budget.stop_routing_when_exhausted { 1 } else { 0 },
+                to_beijing_string(&budget.updated_at),
+            ],
+        )?;
+        Ok(())
     }
 
-    pub async fn get_logs_by_method_path(
-        &self,
-        method: &str,
-        path: &str,
-        limit: i32,
-        cursor: Option<i64>,
-    ) -> Result<Vec<RequestLog>> {
+    pub async fn delete_provider_budget(&self, provider: &str) -> Result<bool> {
         let conn = self.connection.lock().await;
-        let mut stmt = if cursor.is_some() {
-            conn.prepare(
-                "SELECT id, timestamp, method, path, request_type, requested_model, effective_model, model, provider,
-                        api_key, status_code, response_time_ms, prompt_tokens,
-                        completion_tokens, total_tokens, cached_tokens, reasoning_tokens, error_message,
-                        client_token, user_id, amount_spent
-                 FROM request_logs
-                 WHERE method = ?1 AND path = ?2 AND id < ?3
-                 ORDER BY id DESC
-                 LIMIT ?4",
-            )?
-        } else {
-            conn.prepare(
-                "SELECT id, timestamp, method, path, request_type, requested_model, effective_model, model, provider,
-                        api_key, status_code, response_time_ms, prompt_tokens,
-                        completion_tokens, total_tokens, cached_tokens, reasoning_tokens, error_message,
-                        client_token, user_id, amount_spent
-                 FROM request_logs
-                 WHERE method = ?1 AND path = ?2
-                 ORDER BY id DESC
-                 LIMIT ?3",
-            )?
-        };
+        let affected = conn.execute(
+            "DELETE FROM provider_budgets WHERE provider = ?1",
+            [provider],
+        )?;
+        Ok(affected > 0)
+    }
 
-        let rows = if let Some(cursor_id) = cursor {
-            stmt.query_map(
-                rusqlite::params![method, path, cursor_id, limit],
-                map_request_log_row,
-            )?
-        } else {
-            stmt.query_map(rusqlite::params![method, path, limit], map_request_log_row)?
-        };
+    pub async fn list_image_prices(&self, provider: Option<&str>) -> Result<Vec<ImagePriceRecord>> {
+        let conn = self.connection.lock().await;
+        let mut stmt = conn.prepare(
+            "SELECT provider, model, size, quality, price_per_image, currency, updated_at
+             FROM image_prices WHERE (?1 IS NULL OR provider = ?1)
+             ORDER BY provider, model, size, quality",
+        )?;
+        let rows = stmt.query_map([provider], image_price_from_row)?;
+        rows.collect()
+    }
 
-        let mut logs = Vec::new();
-        for r in rows {
-            logs.push(r?);
-        }
-        Ok(logs)
+    pub async fn upsert_image_price(&self, price: ImagePriceRecord) -> Result<()> {
+        let conn = self.connection.lock().await;
+        conn.execute(
+            "INSERT INTO image_prices (provider, model, size, quality, price_per_image, currency, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+             ON CONFLICT(provider, model, size, quality) DO UPDATE SET
+                price_per_image = excluded.price_per_image,
+                currency = excluded.currency,
+                updated_at = excluded.updated_at",
+            rusqlite::params![
+                price.provider,
+                price.model,
+                price.size,
+                price.quality,
+                price.price_per_image,
+                price.currency,
+                to_beijing_string(&price.updated_at),
+            ],
+        )?;
+        Ok(())
     }
 
-    pub async fn get_request_log_by_id(&self, id: i64) -> Result<Option<RequestLog>> {
+    pub async fn delete_image_price(
+        &self,
+        provider: &str,
+        model: &str,
+        size: &str,
+        quality: &str,
+    ) -> Result<bool> {
+        let conn = self.connection.lock().await;
+        let affected = conn.execute(
+            "DELETE FROM image_prices WHERE provider = ?1 AND model = ?2 AND size = ?3 AND quality = ?4",
+            [provider, model, size, quality],
+        )?;
+        Ok(affected > 0)
+    }
+
+    pub async fn list_audio_prices(&self, provider: Option<&str>) -> Result<Vec<AudioPriceRecord>> {
         let conn = self.connection.lock().await;
         let mut stmt = conn.prepare(
-            "SELECT id, timestamp, method, path, request_type, requested_model, effective_model, model, provider,
-                    api_key, status_code, response_time_ms, prompt_tokens,
-                    completion_tokens, total_tokens, cached_tokens, reasoning_tokens, error_message,
-                    client_token, user_id, amount_spent
-             FROM request_logs WHERE id = ?1 LIMIT 1",
+            "SELECT provider, model, price_per_minute, currency, updated_at
+             FROM audio_prices WHERE (?1 IS NULL OR provider = ?1)
+             ORDER BY provider, model",
         )?;
-        stmt.query_row([id], map_request_log_row).optional()
+        let rows = stmt.query_map([provider], audio_price_from_row)?;
+        rows.collect()
     }
 
-    pub async fn upsert_request_log_detail(&self, detail: RequestLogDetailRecord) -> Result<()> {
+    pub async fn upsert_audio_price(&self, price: AudioPriceRecord) -> Result<()> {
         let conn = self.connection.lock().await;
         conn.execute(
-            "INSERT INTO request_log_details (
-                request_log_id, request_payload_snapshot, response_preview, upstream_status,
-                fallback_triggered, fallback_reason, selected_provider, selected_key_id, first_token_latency_ms
-            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
-            ON CONFLICT(request_log_id) DO UPDATE SET
-                request_payload_snapshot = excluded.request_payload_snapshot,
-                response_preview = excluded.response_preview,
-                upstream_status = excluded.upstream_status,
-                fallback_triggered = excluded.fallback_triggered,
-                fallback_reason = excluded.fallback_reason,
-                selected_provider = excluded.selected_provider,
-                selected_key_id = excluded.selected_key_id,
-                first_token_latency_ms = excluded.first_token_latency_ms",
+            "INSERT INTO audio_prices (provider, model, price_per_minute, currency, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)
+             ON CONFLICT(provider, model) DO UPDATE SET
+                price_per_minute = excluded.price_per_minute,
+                currency = excluded.currency,
+                updated_at = excluded.updated_at",
             rusqlite::params![
-                detail.request_log_id,
-                detail.request_payload_snapshot,
-                detail.response_preview,
-                detail.upstream_status,
-                detail.fallback_triggered.map(|v| if v { 1 } else { 0 }),
-                detail.fallback_reason,
-                detail.selected_provider,
-                detail.selected_key_id,
-                detail.first_token_latency_ms,
+                price.provider,
+                price.model,
+                price.price_per_minute,
+                price.currency,
+                to_beijing_string(&price.updated_at),
             ],
         )?;
         Ok(())
     }
 
-    pub async fn get_request_log_detail(
+    pub async fn delete_audio_price(&self, provider: &str, model: &str) -> Result<bool> {
+        let conn = self.connection.lock().await;
+        let affected = conn.execute(
+            "DELETE FROM audio_prices WHERE provider = ?1 AND model = ?2",
+            [provider, model],
+        )?;
+        Ok(affected > 0)
+    }
+
+    pub async fn list_speech_prices(
         &self,
-        request_log_id: i64,
-    ) -> Result<Option<RequestLogDetailRecord>> {
+        provider: Option<&str>,
+    ) -> Result<Vec<SpeechPriceRecord>> {
         let conn = self.connection.lock().await;
         let mut stmt = conn.prepare(
-            "SELECT request_log_id, request_payload_snapshot, response_preview, upstream_status,
-                    fallback_triggered, fallback_reason, selected_provider, selected_key_id, first_token_latency_ms
-             FROM request_log_details WHERE request_log_id = ?1 LIMIT 1",
+            "SELECT provider, model, price_per_million_chars, currency, updated_at
+             FROM speech_prices WHERE (?1 IS NULL OR provider = ?1)
+             ORDER BY provider, model",
         )?;
-        stmt.query_row([request_log_id], |row| {
-            Ok(RequestLogDetailRecord {
-                request_log_id: row.get(0)?,
-                request_payload_snapshot: row.get(1)?,
-                response_preview: row.get(2)?,
-                upstream_status: row.get(3)?,
-                fallback_triggered: row.get::<_, Option<i64>>(4)?.map(|value| value != 0),
-                fallback_reason: row.get(5)?,
-                selected_provider: row.get(6)?,
-                selected_key_id: row.get(7)?,
-                first_token_latency_ms: row.get(8)?,
-            })
-        })
-        .optional()
+        let rows = stmt.query_map([provider], speech_price_from_row)?;
+        rows.collect()
     }
 
-    pub async fn save_compare_run(&self, run: StoredCompareRun) -> Result<()> {
+    pub async fn upsert_speech_price(&self, price: SpeechPriceRecord) -> Result<()> {
         let conn = self.connection.lock().await;
         conn.execute(
-            "INSERT INTO compare_runs (id, user_id, source_request_id, created_at, result_json)
+            "INSERT INTO speech_prices (provider, model, price_per_million_chars, currency, updated_at)
              VALUES (?1, ?2, ?3, ?4, ?5)
-             ON CONFLICT(id) DO UPDATE SET
-                user_id = excluded.user_id,
-                source_request_id = excluded.source_request_id,
-                created_at = excluded.created_at,
-                result_json = excluded.result_json",
+             ON CONFLICT(provider, model) DO UPDATE SET
+                price_per_million_chars = excluded.price_per_million_chars,
+                currency = excluded.currency,
+                updated_at = excluded.updated_at",
             rusqlite::params![
-                run.id,
-                run.user_id,
-                run.source_request_id,
-                to_beijing_string(&run.created_at),
-                run.result_json,
+                price.provider,
+                price.model,
+                price.price_per_million_chars,
+                price.currency,
+                to_beijing_string(&price.updated_at),
             ],
         )?;
         Ok(())
     }
 
-    pub async fn get_compare_run(&self, id: &str) -> Result<Option<StoredCompareRun>> {
+    pub async fn delete_speech_price(&self, provider: &str, model: &str) -> Result<bool> {
+        let conn = self.connection.lock().await;
+        let affected = conn.execute(
+            "DELETE FROM speech_prices WHERE provider = ?1 AND model = ?2",
+            [provider, model],
+        )?;
+        Ok(affected > 0)
+    }
+
+    pub async fn sum_provider_spend_since(
+        &self,
+        since: DateTime<Utc>,
+    ) -> Result<std::collections::HashMap<String, f64>> {
         let conn = self.connection.lock().await;
         let mut stmt = conn.prepare(
-            "SELECT id, user_id, source_request_id, created_at, result_json
-             FROM compare_runs WHERE id = ?1 LIMIT 1",
+            "SELECT provider, SUM(amount_spent) FROM request_logs
+             WHERE timestamp >= ?1 AND provider IS NOT NULL AND amount_spent IS NOT NULL
+             GROUP BY provider",
         )?;
-        stmt.query_row([id], |row| {
-            let created_at: String = row.get(3)?;
-            Ok(StoredCompareRun {
-                id: row.get(0)?,
-                user_id: row.get(1)?,
-                source_request_id: row.get(2)?,
-                created_at: parse_beijing_string(&created_at)
-                    .unwrap_or_else(|_| chrono::Utc::now()),
-                result_json: row.get(4)?,
+        let rows = stmt.query_map([to_beijing_string(&since)], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, f64>(1)?))
+        })?;
+        rows.collect()
+    }
+
+    pub async fn add_provider_egress(&self, rows: Vec<ProviderEgressDaily>) -> Result<()> {
+        let mut conn = self.connection.lock().await;
+        let tx = conn.transaction()?;
+        for row in &rows {
+            tx.execute(
+                "INSERT INTO provider_egress_daily (day, provider, api_key, requests, request_bytes, response_bytes)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                 ON CONFLICT(day, provider, api_key) DO UPDATE SET
+                    requests = requests + excluded.requests,
+                    request_bytes = request_bytes + excluded.request_bytes,
+                    response_bytes = response_bytes + excluded.response_bytes",
+                rusqlite::params![
+                    row.day,
+                    row.provider,
+                    row.api_key,
+                    row.requests,
+                    row.request_bytes,
+                    row.response_bytes,
+                ],
+            )?;
+        }
+        tx.commit()
+    }
+
+    pub async fn add_token_request_counts(&self, rows: Vec<TokenRequestCount>) -> Result<()> {
+        let mut conn = self.connection.lock().await;
+        let tx = conn.transaction()?;
+        for row in &rows {
+            tx.execute(
+                "INSERT INTO token_request_counts (day, token_id, requests) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(day, token_id) DO UPDATE SET requests = requests + excluded.requests",
+                rusqlite::params![row.day, row.token_id, row.requests],
+            )?;
+        }
+        tx.commit()
+    }
+
+    pub async fn sum_token_request_counts(
+        &self,
+        day: &str,
+    ) -> Result<std::collections::HashMap<String, (i64, i64)>> {
+        let conn = self.connection.lock().await;
+        let mut stmt = conn.prepare(
+            "SELECT token_id, SUM(requests), SUM(CASE WHEN day = ?1 THEN requests ELSE 0 END)
+             FROM token_request_counts GROUP BY token_id",
+        )?;
+        let rows = stmt.query_map([day], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                (row.get::<_, i64>(1)?, row.get::<_, i64>(2)?),
+            ))
+        })?;
+        rows.collect()
+    }
+
+    pub async fn max_request_log_id(&self) -> Result<i64> {
+        let conn = self.connection.lock().await;
+        conn.query_row("SELECT COALESCE(MAX(id), 0) FROM request_logs", [], |row| {
+            row.get(0)
+        })
+    }
+
+    pub async fn sum_token_usage_between(
+        &self,
+        after_id: i64,
+        until_id: i64,
+        excluded_ids: &[i64],
+    ) -> Result<Vec<TokenUsageDelta>> {
+        let conn = self.connection.lock().await;
+        // 排除的 ID 均为整数，直接拼入 NOT IN 列表
+        let excluded = excluded_ids
+            .iter()
+            .map(i64::to_string)
+            .collect::<Vec<_>>()
+            .join(",");
+        let mut stmt = conn.prepare(&format!(
+            "SELECT client_token, COALESCE(SUM(amount_spent), 0), COALESCE(SUM(prompt_tokens), 0),
+                    COALESCE(SUM(completion_tokens), 0), COALESCE(SUM(total_tokens), 0)
+             FROM request_logs
+             WHERE id > ?1 AND id <= ?2 AND client_token IS NOT NULL AND id NOT IN ({})
+             GROUP BY client_token",
+            excluded
+        ))?;
+        let rows = stmt.query_map([after_id, until_id], |row| {
+            Ok(TokenUsageDelta {
+                token_id: row.get(0)?,
+                amount_spent: row.get(1)?,
+                prompt_tokens: row.get(2)?,
+                completion_tokens: row.get(3)?,
+                total_tokens: row.get(4)?,
             })
+        })?;
+        rows.collect()
+    }
+
+    pub async fn request_heatmap(
+        &self,
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+        model: Option<&str>,
+        provider: Option<&str>,
+    ) -> Result<Vec<RequestHeatmapCell>> {
+        let conn = self.connection.lock().await;
+        let mut stmt = conn.prepare(
+            "SELECT CAST(strftime('%w', substr(timestamp, 1, 10)) AS INTEGER) AS weekday,
+                    CAST(substr(timestamp, 12, 2) AS INTEGER) AS hour,
+                    COUNT(*),
+                    COALESCE(SUM(CASE WHEN status_code >= 400 THEN 1 ELSE 0 END), 0)
+             FROM request_logs
+             WHERE method = 'POST' AND path = '/v1/chat/completions'
+               AND timestamp >= ?1 AND timestamp < ?2
+               AND (?3 IS NULL OR COALESCE(NULLIF(effective_model, ''), model) = ?3)
+               AND (?4 IS NULL OR provider = ?4)
+             GROUP BY weekday, hour
+             ORDER BY weekday, hour",
+        )?;
+        let rows = stmt.query_map(
+            rusqlite::params![
+                to_beijing_string(&since),
+                to_beijing_string(&until),
+                model,
+                provider
+            ],
+            |row| {
+                Ok(RequestHeatmapCell {
+                    weekday: row.get::<_, i64>(0)?.clamp(0, 6) as u8,
+                    hour: row.get::<_, i64>(1)?.clamp(0, 23) as u8,
+                    requests: row.get::<_, i64>(2)?.max(0) as u64,
+                    errors: row.get::<_, i64>(3)?.max(0) as u64,
+                })
+            },
+        )?;
+        rows.collect()
+    }
+
+    pub async fn token_activity(
+        &self,
+        client_token: &str,
+        since: DateTime<Utc>,
+        top_models: u32,
+        recent_errors: u32,
+    ) -> Result<TokenActivity> {
+        let conn = self.connection.lock().await;
+        let last_used_at: Option<String> = conn.query_row(
+            "SELECT MAX(timestamp) FROM request_logs WHERE client_token = ?1",
+            [client_token],
+            |row| row.get(0),
+        )?;
+        let since = to_beijing_string(&since);
+        let mut stmt = conn.prepare(
+            "SELECT substr(timestamp, 1, 10) AS day, COUNT(*),
+                    COALESCE(SUM(CASE WHEN status_code >= 400 THEN 1 ELSE 0 END), 0),
+                    COALESCE(SUM(amount_spent), 0), COALESCE(SUM(total_tokens), 0)
+             FROM request_logs
+             WHERE client_token = ?1 AND timestamp >= ?2
+             GROUP BY day
+             ORDER BY day",
+        )?;
+        let daily = stmt
+            .query_map(rusqlite::params![client_token, since], |row| {
+                Ok(TokenActivityDay {
+                    date: row.get(0)?,
+                    requests: row.get::<_, i64>(1)?.max(0) as u64,
+                    errors: row.get::<_, i64>(2)?.max(0) as u64,
+                    amount_spent: row.get(3)?,
+                    total_tokens: row.get(4)?,
+                })
+            })?
+            .collect::<Result<Vec<_>>>()?;
+        let mut stmt = conn.prepare(
+            "SELECT COALESCE(NULLIF(effective_model, ''), model) AS m, COUNT(*) AS cnt,
+                    COALESCE(SUM(amount_spent), 0), COALESCE(SUM(total_tokens), 0)
+             FROM request_logs
+             WHERE client_token = ?1 AND timestamp >= ?2
+               AND COALESCE(NULLIF(effective_model, ''), model) IS NOT NULL
+             GROUP BY m
+             ORDER BY cnt DESC, m
+             LIMIT ?3",
+        )?;
+        let top_models = stmt
+            .query_map(rusqlite::params![client_token, since, top_models], |row| {
+                Ok(TokenModelUsage {
+                    model: row.get(0)?,
+                    requests: row.get::<_, i64>(1)?.max(0) as u64,
+                    amount_spent: row.get(2)?,
+                    total_tokens: row.get(3)?,
+                })
+            })?
+            .collect::<Result<Vec<_>>>()?;
+        let mut stmt = conn.prepare(
+            "SELECT timestamp, path, COALESCE(NULLIF(effective_model, ''), model), status_code,
+                    error_message
+             FROM request_logs
+             WHERE client_token = ?1 AND status_code >= 400
+             ORDER BY id DESC
+             LIMIT ?2",
+        )?;
+        let recent_errors = stmt
+            .query_map(rusqlite::params![client_token, recent_errors], |row| {
+                let timestamp: String = row.get(0)?;
+                Ok(TokenRecentError {
+                    timestamp: parse_beijing_string(&timestamp).unwrap_or_else(|_| Utc::now()),
+                    path: row.get(1)?,
+                    model: row.get(2)?,
+                    status_code: row.get(3)?,
+                    error_message: row.get(4)?,
+                })
+            })?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(TokenActivity {
+            last_used_at: last_used_at.and_then(|t| parse_beijing_string(&t).ok()),
+            daily,
+            top_models,
+            recent_errors,
         })
-        .optional()
+    }
+
+    pub async fn list_provider_egress(
+        &self,
+        since_day: &str,
+        until_day: &str,
+    ) -> Result<Vec<ProviderEgressDaily>> {
+        let conn = self.connection.lock().await;
+        let mut stmt = conn.prepare(
+            "SELECT day, provider, api_key, requests, request_bytes, response_bytes
+             FROM provider_egress_daily WHERE day >= ?1 AND day <= ?2
+             ORDER BY day, provider, api_key",
+        )?;
+        let rows = stmt.query_map([since_day, until_day], |row| {
+            Ok(ProviderEgressDaily {
+                day: row.get(0)?,
+                provider: row.get(1)?,
+                api_key: row.get(2)?,
+                requests: row.get(3)?,
+                request_bytes: row.get(4)?,
+                response_bytes: row.get(5)?,
+            })
+        })?;
+        rows.collect()
     }
 
     pub async fn upsert_request_lab_source(
@@ -1935,7 +3673,8 @@ impl DatabaseLogger {
             "SELECT id, timestamp, method, path, request_type, requested_model, effective_model, model, provider,
                     api_key, status_code, response_time_ms, prompt_tokens,
                     completion_tokens, total_tokens, cached_tokens, reasoning_tokens, error_message,
-                    client_token, user_id, amount_spent
+                    client_token, user_id, amount_spent, pre_dispatch_ms, upstream_ms, post_process_ms,
+                prompt_language, content_category
              FROM request_logs WHERE client_token = ?1 ORDER BY id DESC LIMIT ?2",
         )?;
         let rows = stmt.query_map(rusqlite::params![token, limit], |row| {
@@ -1961,6 +3700,15 @@ impl DatabaseLogger {
                 client_token: row.get(18)?,
                 user_id: row.get(19)?,
                 amount_spent: row.get(20)?,
+                latency: LatencyBreakdown {
+                    pre_dispatch_ms: row.get(21)?,
+                    upstream_ms: row.get(22)?,
+                    post_process_ms: row.get(23)?,
+                },
+                profile: PromptProfile {
+                    language: row.get(24)?,
+                    category: row.get(25)?,
+                },
             })
         })?;
         let mut out = Vec::new();
@@ -2100,6 +3848,15 @@ fn map_request_log_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<RequestLog>
         client_token: row.get(18)?,
         user_id: row.get(19)?,
         amount_spent: row.get(20)?,
+        latency: LatencyBreakdown {
+            pre_dispatch_ms: row.get(21)?,
+            upstream_ms: row.get(22)?,
+            post_process_ms: row.get(23)?,
+        },
+        profile: PromptProfile {
+            language: row.get(24)?,
+            category: row.get(25)?,
+        },
     })
 }
 
@@ -2114,3 +3871,137 @@ fn parse_ts(value: &str) -> rusqlite::Result<DateTime<Utc>> {
         .ok_or_else(|| rusqlite::Error::ExecuteReturnedResults)?;
     Ok(local.with_timezone(&Utc))
 }
+
+fn param_policy_from_row(row: &rusqlite::Row<'_>) -> Result<ParamPolicyRecord> {
+    let created_at: String = row.get(5)?;
+    let updated_at: String = row.get(6)?;
+    Ok(ParamPolicyRecord {
+        id: row.get(0)?,
+        model: row.get(1)?,
+        token_id: row.get(2)?,
+        rules_json: row.get(3)?,
+        enabled: row.get::<_, i64>(4)? != 0,
+        created_at: parse_beijing_string(&created_at).unwrap_or_else(|_| Utc::now()),
+        updated_at: parse_beijing_string(&updated_at).unwrap_or_else(|_| Utc::now()),
+    })
+}
+
+fn metrics_report_from_row(row: &rusqlite::Row<'_>) -> Result<MetricsReportRecord> {
+    let last_sent_at: Option<String> = row.get(7)?;
+    let created_at: String = row.get(8)?;
+    let updated_at: String = row.get(9)?;
+    Ok(MetricsReportRecord {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        period: row.get(2)?,
+        channel: row.get(3)?,
+        target: row.get(4)?,
+        template: row.get(5)?,
+        enabled: row.get::<_, i64>(6)? != 0,
+        last_sent_at: last_sent_at.and_then(|s| parse_beijing_string(&s).ok()),
+        created_at: parse_beijing_string(&created_at).unwrap_or_else(|_| Utc::now()),
+        updated_at: parse_beijing_string(&updated_at).unwrap_or_else(|_| Utc::now()),
+    })
+}
+
+fn statement_from_row(row: &rusqlite::Row<'_>) -> Result<StatementRecord> {
+    let created_at: String = row.get(5)?;
+    Ok(StatementRecord {
+        id: row.get(0)?,
+        scope: row.get(1)?,
+        subject_id: row.get(2)?,
+        period: row.get(3)?,
+        body: row.get(4)?,
+        created_at: parse_beijing_string(&created_at).unwrap_or_else(|_| Utc::now()),
+    })
+}
+
+fn provider_budget_from_row(row: &rusqlite::Row<'_>) -> Result<ProviderBudgetRecord> {
+    let warn_thresholds: String = row.get(2)?;
+    let updated_at: String = row.get(4)?;
+    Ok(ProviderBudgetRecord {
+        provider: row.get(0)?,
+        monthly_budget: row.get(1)?,
+        warn_thresholds: serde_json::from_str(&warn_thresholds).unwrap_or_default(),
+        stop_routing_when_exhausted: row.get::<_, i64>(3)? != 0,
+        updated_at: parse_beijing_string(&updated_at).unwrap_or_else(|_| Utc::now()),
+    })
+}
+
+fn image_price_from_row(row: &rusqlite::Row<'_>) -> Result<ImagePriceRecord> {
+    let updated_at: String = row.get(6)?;
+    Ok(ImagePriceRecord {
+        provider: row.get(0)?,
+        model: row.get(1)?,
+        size: row.get(2)?,
+        quality: row.get(3)?,
+        price_per_image: row.get(4)?,
+        currency: row.get(5)?,
+        updated_at: parse_beijing_string(&updated_at).unwrap_or_else(|_| Utc::now()),
+    })
+}
+
+fn audio_price_from_row(row: &rusqlite::Row<'_>) -> Result<AudioPriceRecord> {
+    let updated_at: String = row.get(4)?;
+    Ok(AudioPriceRecord {
+        provider: row.get(0)?,
+        model: row.get(1)?,
+        price_per_minute: row.get(2)?,
+        currency: row.get(3)?,
+        updated_at: parse_beijing_string(&updated_at).unwrap_or_else(|_| Utc::now()),
+    })
+}
+
+fn speech_price_from_row(row: &rusqlite::Row<'_>) -> Result<SpeechPriceRecord> {
+    let updated_at: String = row.get(4)?;
+    Ok(SpeechPriceRecord {
+        provider: row.get(0)?,
+        model: row.get(1)?,
+        price_per_million_chars: row.get(2)?,
+        currency: row.get(3)?,
+        updated_at: parse_beijing_string(&updated_at).unwrap_or_else(|_| Utc::now()),
+    })
+}
+
+fn maintenance_window_from_row(row: &rusqlite::Row<'_>) -> Result<MaintenanceWindowRecord> {
+    let starts_at: String = row.get(2)?;
+    let ends_at: String = row.get(3)?;
+    let created_at: String = row.get(6)?;
+    Ok(MaintenanceWindowRecord {
+        id: row.get(0)?,
+        provider: row.get(1)?,
+        starts_at: parse_beijing_string(&starts_at).unwrap_or_else(|_| Utc::now()),
+        ends_at: parse_beijing_string(&ends_at).unwrap_or_else(|_| Utc::now()),
+        message: row.get(4)?,
+        created_by: row.get(5)?,
+        created_at: parse_beijing_string(&created_at).unwrap_or_else(|_| Utc::now()),
+    })
+}
+
+fn admin_notification_from_row(row: &rusqlite::Row<'_>) -> Result<AdminNotificationRecord> {
+    let created_at: String = row.get(6)?;
+    let read_at: Option<String> = row.get(7)?;
+    Ok(AdminNotificationRecord {
+        id: row.get(0)?,
+        kind: row.get(1)?,
+        severity: row.get(2)?,
+        title: row.get(3)?,
+        message: row.get(4)?,
+        reference: row.get(5)?,
+        created_at: parse_beijing_string(&created_at).unwrap_or_else(|_| Utc::now()),
+        read_at: read_at.and_then(|s| parse_beijing_string(&s).ok()),
+    })
+}
+
+fn usage_webhook_dead_letter_from_row(row: &rusqlite::Row<'_>) -> Result<UsageWebhookDeadLetter> {
+    let created_at: String = row.get(6)?;
+    Ok(UsageWebhookDeadLetter {
+        id: row.get(0)?,
+        token_id: row.get(1)?,
+        url: row.get(2)?,
+        payload_json: row.get(3)?,
+        attempts: row.get(4)?,
+        last_error: row.get(5)?,
+        created_at: parse_beijing_string(&created_at).unwrap_or_else(|_| Utc::now()),
+    })
+}
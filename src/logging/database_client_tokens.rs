@@ -2,12 +2,14 @@ use async_trait::async_trait;
 use chrono::Utc;
 
 use crate::admin::{
-    ClientToken, CreateTokenPayload, TokenStore, UpdateTokenPayload, client_token_id_for_token,
+    ClientToken, CreateTokenPayload, TokenAutoDisablePrefs, TokenNotificationRecord,
+    TokenRotationPrefs, TokenStore, UpdateTokenPayload, client_token_id_for_token,
     decode_json_string_list, encode_json_string_list, normalize_client_token_name,
 };
 use crate::error::GatewayError;
 use crate::logging::database::DatabaseLogger;
 use crate::logging::time::{parse_beijing_string, parse_datetime_string, to_beijing_string};
+use crate::server::pagination::PageRequest;
 
 fn join_allowed_models(v: &Option<Vec<String>>) -> Option<String> {
     v.as_ref().map(|list| list.join(","))
@@ -23,6 +25,174 @@ fn parse_allowed_models(s: Option<String>) -> Option<Vec<String>> {
     .and_then(|v| if v.is_empty() { None } else { Some(v) })
 }
 
+impl DatabaseLogger {
+    /// 按单列等值条件查询令牌（列名由调用方固定传入，不接受用户输入）
+    async fn list_tokens_where(
+        &self,
+        column: &str,
+        value: &str,
+    ) -> Result<Vec<ClientToken>, GatewayError> {
+        self.query_tokens(
+            &format!("WHERE {} = ?1 ORDER BY created_at DESC", column),
+            [value],
+        )
+        .await
+    }
+
+    /// 查询令牌；clause 为 FROM client_tokens 之后的条件/排序/分页片段（由调用方固定拼接，不接受用户输入）
+    async fn query_tokens(
+        &self,
+        clause: &str,
+        params: impl rusqlite::Params,
+    ) -> Result<Vec<ClientToken>, GatewayError> {
+        let conn = self.connection.lock().await;
+        let mut stmt = conn.prepare(&format!("SELECT id, user_id, name, token, allowed_models, max_tokens, enabled, expires_at, created_at, max_amount, amount_spent, prompt_tokens_spent, completion_tokens_spent, total_tokens_spent, remark, organization_id, ip_whitelist, ip_blacklist, model_blacklist, allow_streaming, sandbox, strip_reasoning, usage_webhook_url, signing_secret, require_signature, parent_token_id, allow_debug_capture, allow_provider_override, auto_truncate_prompt, semantic_cache, allow_login_codes, max_requests, max_requests_per_day, watermark_responses, queue_weight, usage_webhook_secret FROM client_tokens {}", clause))?;
+        let rows = stmt.query_map(params, |row| {
+            Ok((
+                row.get::<_, Option<String>>(0)?,
+                row.get::<_, Option<String>>(1)?,
+                row.get::<_, Option<String>>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, Option<String>>(4)?,
+                row.get::<_, Option<i64>>(5)?,
+                row.get::<_, i64>(6)?,
+                row.get::<_, Option<String>>(7)?,
+                row.get::<_, String>(8)?,
+                row.get::<_, Option<f64>>(9)?,
+                row.get::<_, Option<f64>>(10)?,
+                row.get::<_, Option<i64>>(11)?,
+                row.get::<_, Option<i64>>(12)?,
+                row.get::<_, Option<i64>>(13)?,
+                row.get::<_, Option<String>>(14)?,
+                row.get::<_, Option<String>>(15)?,
+                row.get::<_, Option<String>>(16)?,
+                row.get::<_, Option<String>>(17)?,
+                row.get::<_, Option<String>>(18)?,
+                row.get::<_, Option<i64>>(19)?,
+                row.get::<_, Option<i64>>(20)?,
+                row.get::<_, Option<i64>>(21)?,
+                row.get::<_, Option<String>>(22)?,
+                row.get::<_, Option<String>>(23)?,
+                row.get::<_, Option<i64>>(24)?,
+                row.get::<_, Option<String>>(25)?,
+                row.get::<_, Option<i64>>(26)?,
+                row.get::<_, Option<i64>>(27)?,
+                row.get::<_, Option<i64>>(28)?,
+                row.get::<_, Option<i64>>(29)?,
+                row.get::<_, Option<i64>>(30)?,
+                row.get::<_, Option<i64>>(31)?,
+                row.get::<_, Option<i64>>(32)?,
+                row.get::<_, Option<i64>>(33)?,
+                row.get::<_, Option<i64>>(34)?,
+                row.get::<_, Option<String>>(35)?,
+            ))
+        })?;
+        let mut out = Vec::new();
+        for r in rows {
+            let (
+                id0,
+                user_id,
+                name0,
+                token,
+                allowed,
+                max_tokens,
+                enabled_i,
+                expires,
+                created_at_s,
+                max_amount,
+                amount_spent,
+                prompt_tokens_spent,
+                completion_tokens_spent,
+                total_tokens_spent,
+                remark,
+                organization_id,
+                ip_whitelist_s,
+                ip_blacklist_s,
+                model_blacklist_s,
+                allow_streaming_i,
+                sandbox_i,
+                strip_reasoning_i,
+                usage_webhook_url_s,
+                signing_secret_s,
+                require_signature_i,
+                parent_token_id_s,
+                allow_debug_capture_i,
+                allow_provider_override_i,
+                auto_truncate_prompt_i,
+                semantic_cache_i,
+                allow_login_codes_i,
+                max_requests,
+                max_requests_per_day,
+                watermark_responses_i,
+                queue_weight,
+                usage_webhook_secret,
+            ) = r?;
+            let needs_id_backfill = id0.as_deref().filter(|s| !s.is_empty()).is_none();
+            let needs_name_backfill = name0.as_deref().filter(|s| !s.trim().is_empty()).is_none();
+            let id = id0
+                .as_deref()
+                .filter(|s| !s.is_empty())
+                .map(|s| s.to_string())
+                .unwrap_or_else(|| client_token_id_for_token(&token));
+            let name = normalize_client_token_name(name0.clone(), &id);
+            if needs_id_backfill {
+                let _ = conn.execute(
+                    "UPDATE client_tokens SET id = ?2 WHERE token = ?1 AND (id IS NULL OR id = '')",
+                    (&token, &id),
+                );
+            }
+            if needs_name_backfill {
+                let _ = conn.execute(
+                    "UPDATE client_tokens SET name = ?2 WHERE token = ?1 AND (name IS NULL OR name = '')",
+                    (&token, &name),
+                );
+            }
+            out.push(ClientToken {
+                id,
+                user_id,
+                name,
+                token,
+                allowed_models: parse_allowed_models(allowed),
+                model_blacklist: parse_allowed_models(model_blacklist_s),
+                max_tokens,
+                max_amount,
+                enabled: enabled_i != 0,
+                expires_at: match expires {
+                    Some(s) => parse_beijing_string(&s).ok(),
+                    None => None,
+                },
+                created_at: parse_beijing_string(&created_at_s).unwrap_or(Utc::now()),
+                amount_spent: amount_spent.unwrap_or(0.0),
+                prompt_tokens_spent: prompt_tokens_spent.unwrap_or(0),
+                completion_tokens_spent: completion_tokens_spent.unwrap_or(0),
+                total_tokens_spent: total_tokens_spent.unwrap_or(0),
+                remark,
+                organization_id,
+                ip_whitelist: decode_json_string_list("ip_whitelist", ip_whitelist_s)?,
+                ip_blacklist: decode_json_string_list("ip_blacklist", ip_blacklist_s)?,
+                allow_streaming: allow_streaming_i.map(|v| v != 0).unwrap_or(true),
+                sandbox: sandbox_i.map(|v| v != 0).unwrap_or(false),
+                strip_reasoning: strip_reasoning_i.map(|v| v != 0).unwrap_or(false),
+                usage_webhook_url: usage_webhook_url_s,
+                signing_secret: signing_secret_s,
+                require_signature: require_signature_i.map(|v| v != 0).unwrap_or(false),
+                parent_token_id: parent_token_id_s,
+                allow_debug_capture: allow_debug_capture_i.map(|v| v != 0).unwrap_or(false),
+                allow_provider_override: allow_provider_override_i.map(|v| v != 0).unwrap_or(false),
+                auto_truncate_prompt: auto_truncate_prompt_i.map(|v| v != 0).unwrap_or(false),
+                semantic_cache: semantic_cache_i.map(|v| v != 0).unwrap_or(false),
+                allow_login_codes: allow_login_codes_i.map(|v| v != 0).unwrap_or(false),
+                max_requests,
+                max_requests_per_day,
+                watermark_responses: watermark_responses_i.map(|v| v != 0).unwrap_or(false),
+                queue_weight,
+                usage_webhook_secret,
+            });
+        }
+        Ok(out)
+    }
+}
+
 #[async_trait]
 impl TokenStore for DatabaseLogger {
     async fn create_token(&self, payload: CreateTokenPayload) -> Result<ClientToken, GatewayError> {
@@ -59,8 +229,8 @@ impl TokenStore for DatabaseLogger {
             )?;
         }
         conn.execute(
-            "INSERT INTO client_tokens (id, user_id, name, token, allowed_models, max_tokens, enabled, expires_at, created_at, max_amount, amount_spent, prompt_tokens_spent, completion_tokens_spent, total_tokens_spent, remark, organization_id, ip_whitelist, ip_blacklist, model_blacklist) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, 0, 0, 0, 0, ?11, ?12, ?13, ?14, ?15)",
-            (
+            "INSERT INTO client_tokens (id, user_id, name, token, allowed_models, max_tokens, enabled, expires_at, created_at, max_amount, amount_spent, prompt_tokens_spent, completion_tokens_spent, total_tokens_spent, remark, organization_id, ip_whitelist, ip_blacklist, model_blacklist, allow_streaming, sandbox, strip_reasoning, usage_webhook_url, signing_secret, require_signature, parent_token_id, allow_debug_capture, allow_provider_override, auto_truncate_prompt, semantic_cache, allow_login_codes, max_requests, max_requests_per_day, watermark_responses, queue_weight, usage_webhook_secret) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, 0, 0, 0, 0, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23, ?24, ?25, ?26, ?27, ?28, ?29, ?30, ?31, ?32)",
+            rusqlite::params![
                 &id,
                 &payload.user_id,
                 &name,
@@ -76,7 +246,24 @@ impl TokenStore for DatabaseLogger {
                 &ip_whitelist_s,
                 &ip_blacklist_s,
                 &model_blacklist_s,
-            ),
+                if payload.allow_streaming { 1 } else { 0 },
+                if payload.sandbox { 1 } else { 0 },
+                if payload.strip_reasoning { 1 } else { 0 },
+                &payload.usage_webhook_url,
+                &payload.signing_secret,
+                if payload.require_signature { 1 } else { 0 },
+                &payload.parent_token_id,
+                if payload.allow_debug_capture { 1 } else { 0 },
+                if payload.allow_provider_override { 1 } else { 0 },
+                if payload.auto_truncate_prompt { 1 } else { 0 },
+                if payload.semantic_cache { 1 } else { 0 },
+                if payload.allow_login_codes { 1 } else { 0 },
+                payload.max_requests,
+                payload.max_requests_per_day,
+                if payload.watermark_responses { 1 } else { 0 },
+                payload.queue_weight,
+                &payload.usage_webhook_secret,
+            ],
         )?;
 
         Ok(ClientToken {
@@ -102,6 +289,23 @@ impl TokenStore for DatabaseLogger {
             organization_id: payload.organization_id,
             ip_whitelist: payload.ip_whitelist,
             ip_blacklist: payload.ip_blacklist,
+            allow_streaming: payload.allow_streaming,
+            sandbox: payload.sandbox,
+            strip_reasoning: payload.strip_reasoning,
+            usage_webhook_url: payload.usage_webhook_url,
+            signing_secret: payload.signing_secret,
+            require_signature: payload.require_signature,
+            parent_token_id: payload.parent_token_id,
+            allow_debug_capture: payload.allow_debug_capture,
+            allow_provider_override: payload.allow_provider_override,
+            auto_truncate_prompt: payload.auto_truncate_prompt,
+            semantic_cache: payload.semantic_cache,
+            allow_login_codes: payload.allow_login_codes,
+            max_requests: payload.max_requests,
+            max_requests_per_day: payload.max_requests_per_day,
+            watermark_responses: payload.watermark_responses,
+            queue_weight: payload.queue_weight,
+            usage_webhook_secret: payload.usage_webhook_secret,
         })
     }
 
@@ -112,7 +316,7 @@ impl TokenStore for DatabaseLogger {
     ) -> Result<Option<ClientToken>, GatewayError> {
         let conn = self.connection.lock().await;
         use rusqlite::OptionalExtension;
-        let mut stmt = conn.prepare("SELECT id, user_id, name, token, allowed_models, max_tokens, enabled, expires_at, created_at, max_amount, amount_spent, prompt_tokens_spent, completion_tokens_spent, total_tokens_spent, remark, organization_id, ip_whitelist, ip_blacklist, model_blacklist FROM client_tokens WHERE token = ?1")?;
+        let mut stmt = conn.prepare("SELECT id, user_id, name, token, allowed_models, max_tokens, enabled, expires_at, created_at, max_amount, amount_spent, prompt_tokens_spent, completion_tokens_spent, total_tokens_spent, remark, organization_id, ip_whitelist, ip_blacklist, model_blacklist, allow_streaming, sandbox, strip_reasoning, usage_webhook_url, signing_secret, require_signature, parent_token_id, allow_debug_capture, allow_provider_override, auto_truncate_prompt, semantic_cache, allow_login_codes, max_requests, max_requests_per_day, watermark_responses, queue_weight, usage_webhook_secret FROM client_tokens WHERE token = ?1")?;
         let row_opt = stmt
             .query_row([token], |row| {
                 Ok((
@@ -135,6 +339,23 @@ impl TokenStore for DatabaseLogger {
                     row.get::<_, Option<String>>(16)?,
                     row.get::<_, Option<String>>(17)?,
                     row.get::<_, Option<String>>(18)?,
+                    row.get::<_, Option<i64>>(19)?,
+                    row.get::<_, Option<i64>>(20)?,
+                    row.get::<_, Option<i64>>(21)?,
+                    row.get::<_, Option<String>>(22)?,
+                    row.get::<_, Option<String>>(23)?,
+                    row.get::<_, Option<i64>>(24)?,
+                    row.get::<_, Option<String>>(25)?,
+                    row.get::<_, Option<i64>>(26)?,
+                    row.get::<_, Option<i64>>(27)?,
+                    row.get::<_, Option<i64>>(28)?,
+                    row.get::<_, Option<i64>>(29)?,
+                    row.get::<_, Option<i64>>(30)?,
+                    row.get::<_, Option<i64>>(31)?,
+                    row.get::<_, Option<i64>>(32)?,
+                    row.get::<_, Option<i64>>(33)?,
+                    row.get::<_, Option<i64>>(34)?,
+                    row.get::<_, Option<String>>(35)?,
                 ))
             })
             .optional()?;
@@ -158,6 +379,23 @@ impl TokenStore for DatabaseLogger {
             ip_whitelist0,
             ip_blacklist0,
             model_blacklist0,
+            allow_streaming0,
+            sandbox0,
+            strip_reasoning0,
+            usage_webhook_url0,
+            signing_secret0,
+            require_signature0,
+            parent_token_id0,
+            allow_debug_capture0,
+            allow_provider_override0,
+            auto_truncate_prompt0,
+            semantic_cache0,
+            allow_login_codes0,
+            max_requests0,
+            max_requests_per_day0,
+            watermark_responses0,
+            queue_weight0,
+            usage_webhook_secret0,
         )) = row_opt
         else {
             return Ok(None);
@@ -193,6 +431,23 @@ impl TokenStore for DatabaseLogger {
         let mut organization_id = organization_id0;
         let mut ip_whitelist = decode_json_string_list("ip_whitelist", ip_whitelist0)?;
         let mut ip_blacklist = decode_json_string_list("ip_blacklist", ip_blacklist0)?;
+        let mut allow_streaming = allow_streaming0.map(|v| v != 0).unwrap_or(true);
+        let mut sandbox = sandbox0.map(|v| v != 0).unwrap_or(false);
+        let mut strip_reasoning = strip_reasoning0.map(|v| v != 0).unwrap_or(false);
+        let mut usage_webhook_url = usage_webhook_url0;
+        let mut signing_secret = signing_secret0;
+        let mut require_signature = require_signature0.map(|v| v != 0).unwrap_or(false);
+        let parent_token_id = parent_token_id0;
+        let mut allow_debug_capture = allow_debug_capture0.map(|v| v != 0).unwrap_or(false);
+        let mut allow_provider_override = allow_provider_override0.map(|v| v != 0).unwrap_or(false);
+        let mut auto_truncate_prompt = auto_truncate_prompt0.map(|v| v != 0).unwrap_or(false);
+        let mut semantic_cache = semantic_cache0.map(|v| v != 0).unwrap_or(false);
+        let mut allow_login_codes = allow_login_codes0.map(|v| v != 0).unwrap_or(false);
+        let mut max_requests = max_requests0;
+        let mut max_requests_per_day = max_requests_per_day0;
+        let mut watermark_responses = watermark_responses0.map(|v| v != 0).unwrap_or(false);
+        let mut queue_weight = queue_weight0;
+        let mut usage_webhook_secret = usage_webhook_secret0;
         let amount_spent = amount_spent0.unwrap_or(0.0);
         let prompt_tokens_spent = prompt0.unwrap_or(0);
         let completion_tokens_spent = completion0.unwrap_or(0);
@@ -234,6 +489,54 @@ impl TokenStore for DatabaseLogger {
         if let Some(v) = payload.ip_blacklist {
             ip_blacklist = v;
         }
+        if let Some(v) = payload.allow_streaming {
+            allow_streaming = v;
+        }
+        if let Some(v) = payload.sandbox {
+            sandbox = v;
+        }
+        if let Some(v) = payload.strip_reasoning {
+            strip_reasoning = v;
+        }
+        if let Some(v) = payload.usage_webhook_url {
+            usage_webhook_url = v;
+        }
+        if let Some(v) = payload.signing_secret {
+            signing_secret = v;
+        }
+        if let Some(v) = payload.require_signature {
+            require_signature = v;
+        }
+        if let Some(v) = payload.allow_debug_capture {
+            allow_debug_capture = v;
+        }
+        if let Some(v) = payload.allow_provider_override {
+            allow_provider_override = v;
+        }
+        if let Some(v) = payload.auto_truncate_prompt {
+            auto_truncate_prompt = v;
+        }
+        if let Some(v) = payload.semantic_cache {
+            semantic_cache = v;
+        }
+        if let Some(v) = payload.allow_login_codes {
+            allow_login_codes = v;
+        }
+        if let Some(v) = payload.max_requests {
+            max_requests = v;
+        }
+        if let Some(v) = payload.max_requests_per_day {
+            max_requests_per_day = v;
+        }
+        if let Some(v) = payload.watermark_responses {
+            watermark_responses = v;
+        }
+        if let Some(v) = payload.queue_weight {
+            queue_weight = v;
+        }
+        if let Some(v) = payload.usage_webhook_secret {
+            usage_webhook_secret = v;
+        }
 
         let ip_whitelist_s = encode_json_string_list("ip_whitelist", &ip_whitelist)?;
         let ip_blacklist_s = encode_json_string_list("ip_blacklist", &ip_blacklist)?;
@@ -244,8 +547,8 @@ impl TokenStore for DatabaseLogger {
             )?;
         }
         conn.execute(
-            "UPDATE client_tokens SET name = ?2, allowed_models = ?3, max_tokens = ?4, enabled = ?5, expires_at = ?6, max_amount = ?7, remark = ?8, organization_id = ?9, ip_whitelist = ?10, ip_blacklist = ?11, model_blacklist = ?12 WHERE token = ?1",
-            (
+            "UPDATE client_tokens SET name = ?2, allowed_models = ?3, max_tokens = ?4, enabled = ?5, expires_at = ?6, max_amount = ?7, remark = ?8, organization_id = ?9, ip_whitelist = ?10, ip_blacklist = ?11, model_blacklist = ?12, allow_streaming = ?13, sandbox = ?14, strip_reasoning = ?15, usage_webhook_url = ?16, signing_secret = ?17, require_signature = ?18, allow_debug_capture = ?19, allow_provider_override = ?20, auto_truncate_prompt = ?21, semantic_cache = ?22, allow_login_codes = ?23, max_requests = ?24, max_requests_per_day = ?25, watermark_responses = ?26, queue_weight = ?27, usage_webhook_secret = ?28 WHERE token = ?1",
+            rusqlite::params![
                 &tok,
                 &name,
                 join_allowed_models(&allowed_models),
@@ -258,7 +561,23 @@ impl TokenStore for DatabaseLogger {
                 ip_whitelist_s.clone(),
                 ip_blacklist_s.clone(),
                 join_allowed_models(&model_blacklist),
-            ),
+                if allow_streaming { 1 } else { 0 },
+                if sandbox { 1 } else { 0 },
+                if strip_reasoning { 1 } else { 0 },
+                usage_webhook_url.clone(),
+                signing_secret.clone(),
+                if require_signature { 1 } else { 0 },
+                if allow_debug_capture { 1 } else { 0 },
+                if allow_provider_override { 1 } else { 0 },
+                if auto_truncate_prompt { 1 } else { 0 },
+                if semantic_cache { 1 } else { 0 },
+                if allow_login_codes { 1 } else { 0 },
+                max_requests,
+                max_requests_per_day,
+                if watermark_responses { 1 } else { 0 },
+                queue_weight,
+                usage_webhook_secret.clone(),
+            ],
         )?;
 
         Ok(Some(ClientToken {
@@ -284,6 +603,23 @@ impl TokenStore for DatabaseLogger {
             organization_id,
             ip_whitelist,
             ip_blacklist,
+            allow_streaming,
+            sandbox,
+            strip_reasoning,
+            usage_webhook_url,
+            signing_secret,
+            require_signature,
+            parent_token_id,
+            allow_debug_capture,
+            allow_provider_override,
+            auto_truncate_prompt,
+            semantic_cache,
+            allow_login_codes,
+            max_requests,
+            max_requests_per_day,
+            watermark_responses,
+            queue_weight,
+            usage_webhook_secret,
         }))
     }
 
@@ -312,7 +648,7 @@ impl TokenStore for DatabaseLogger {
     async fn get_token(&self, token: &str) -> Result<Option<ClientToken>, GatewayError> {
         let conn = self.connection.lock().await;
         use rusqlite::OptionalExtension;
-        let mut stmt = conn.prepare("SELECT id, user_id, name, token, allowed_models, max_tokens, enabled, expires_at, created_at, max_amount, amount_spent, prompt_tokens_spent, completion_tokens_spent, total_tokens_spent, remark, organization_id, ip_whitelist, ip_blacklist, model_blacklist FROM client_tokens WHERE token = ?1")?;
+        let mut stmt = conn.prepare("SELECT id, user_id, name, token, allowed_models, max_tokens, enabled, expires_at, created_at, max_amount, amount_spent, prompt_tokens_spent, completion_tokens_spent, total_tokens_spent, remark, organization_id, ip_whitelist, ip_blacklist, model_blacklist, allow_streaming, sandbox, strip_reasoning, usage_webhook_url, signing_secret, require_signature, parent_token_id, allow_debug_capture, allow_provider_override, auto_truncate_prompt, semantic_cache, allow_login_codes, max_requests, max_requests_per_day, watermark_responses, queue_weight, usage_webhook_secret FROM client_tokens WHERE token = ?1")?;
         let row = stmt
             .query_row([token], |row| {
                 Ok((
@@ -335,6 +671,23 @@ impl TokenStore for DatabaseLogger {
                     row.get::<_, Option<String>>(16)?,
                     row.get::<_, Option<String>>(17)?,
                     row.get::<_, Option<String>>(18)?,
+                    row.get::<_, Option<i64>>(19)?,
+                    row.get::<_, Option<i64>>(20)?,
+                    row.get::<_, Option<i64>>(21)?,
+                    row.get::<_, Option<String>>(22)?,
+                    row.get::<_, Option<String>>(23)?,
+                    row.get::<_, Option<i64>>(24)?,
+                    row.get::<_, Option<String>>(25)?,
+                    row.get::<_, Option<i64>>(26)?,
+                    row.get::<_, Option<i64>>(27)?,
+                    row.get::<_, Option<i64>>(28)?,
+                    row.get::<_, Option<i64>>(29)?,
+                    row.get::<_, Option<i64>>(30)?,
+                    row.get::<_, Option<i64>>(31)?,
+                    row.get::<_, Option<i64>>(32)?,
+                    row.get::<_, Option<i64>>(33)?,
+                    row.get::<_, Option<i64>>(34)?,
+                    row.get::<_, Option<String>>(35)?,
                 ))
             })
             .optional()?;
@@ -358,6 +711,23 @@ impl TokenStore for DatabaseLogger {
             ip_whitelist_s,
             ip_blacklist_s,
             model_blacklist_s,
+            allow_streaming_i,
+            sandbox_i,
+            strip_reasoning_i,
+            usage_webhook_url_s,
+            signing_secret_s,
+            require_signature_i,
+            parent_token_id_s,
+            allow_debug_capture_i,
+            allow_provider_override_i,
+            auto_truncate_prompt_i,
+            semantic_cache_i,
+            allow_login_codes_i,
+            max_requests,
+            max_requests_per_day,
+            watermark_responses_i,
+            queue_weight,
+            usage_webhook_secret,
         )) = row
         {
             let needs_id_backfill = id0.as_deref().filter(|s| !s.is_empty()).is_none();
@@ -403,6 +773,23 @@ impl TokenStore for DatabaseLogger {
                 organization_id,
                 ip_whitelist: decode_json_string_list("ip_whitelist", ip_whitelist_s)?,
                 ip_blacklist: decode_json_string_list("ip_blacklist", ip_blacklist_s)?,
+                allow_streaming: allow_streaming_i.map(|v| v != 0).unwrap_or(true),
+                sandbox: sandbox_i.map(|v| v != 0).unwrap_or(false),
+                strip_reasoning: strip_reasoning_i.map(|v| v != 0).unwrap_or(false),
+                usage_webhook_url: usage_webhook_url_s,
+                signing_secret: signing_secret_s,
+                require_signature: require_signature_i.map(|v| v != 0).unwrap_or(false),
+                parent_token_id: parent_token_id_s,
+                allow_debug_capture: allow_debug_capture_i.map(|v| v != 0).unwrap_or(false),
+                allow_provider_override: allow_provider_override_i.map(|v| v != 0).unwrap_or(false),
+                auto_truncate_prompt: auto_truncate_prompt_i.map(|v| v != 0).unwrap_or(false),
+                semantic_cache: semantic_cache_i.map(|v| v != 0).unwrap_or(false),
+                allow_login_codes: allow_login_codes_i.map(|v| v != 0).unwrap_or(false),
+                max_requests,
+                max_requests_per_day,
+                watermark_responses: watermark_responses_i.map(|v| v != 0).unwrap_or(false),
+                queue_weight,
+                usage_webhook_secret,
             }))
         } else {
             Ok(None)
@@ -412,7 +799,7 @@ impl TokenStore for DatabaseLogger {
     async fn get_token_by_id(&self, id: &str) -> Result<Option<ClientToken>, GatewayError> {
         let conn = self.connection.lock().await;
         use rusqlite::OptionalExtension;
-        let mut stmt = conn.prepare("SELECT id, user_id, name, token, allowed_models, max_tokens, enabled, expires_at, created_at, max_amount, amount_spent, prompt_tokens_spent, completion_tokens_spent, total_tokens_spent, remark, organization_id, ip_whitelist, ip_blacklist, model_blacklist FROM client_tokens WHERE id = ?1")?;
+        let mut stmt = conn.prepare("SELECT id, user_id, name, token, allowed_models, max_tokens, enabled, expires_at, created_at, max_amount, amount_spent, prompt_tokens_spent, completion_tokens_spent, total_tokens_spent, remark, organization_id, ip_whitelist, ip_blacklist, model_blacklist, allow_streaming, sandbox, strip_reasoning, usage_webhook_url, signing_secret, require_signature, parent_token_id, allow_debug_capture, allow_provider_override, auto_truncate_prompt, semantic_cache, allow_login_codes, max_requests, max_requests_per_day, watermark_responses, queue_weight, usage_webhook_secret FROM client_tokens WHERE id = ?1")?;
         let row = stmt
             .query_row([id], |row| {
                 Ok((
@@ -435,6 +822,23 @@ impl TokenStore for DatabaseLogger {
                     row.get::<_, Option<String>>(16)?,
                     row.get::<_, Option<String>>(17)?,
                     row.get::<_, Option<String>>(18)?,
+                    row.get::<_, Option<i64>>(19)?,
+                    row.get::<_, Option<i64>>(20)?,
+                    row.get::<_, Option<i64>>(21)?,
+                    row.get::<_, Option<String>>(22)?,
+                    row.get::<_, Option<String>>(23)?,
+                    row.get::<_, Option<i64>>(24)?,
+                    row.get::<_, Option<String>>(25)?,
+                    row.get::<_, Option<i64>>(26)?,
+                    row.get::<_, Option<i64>>(27)?,
+                    row.get::<_, Option<i64>>(28)?,
+                    row.get::<_, Option<i64>>(29)?,
+                    row.get::<_, Option<i64>>(30)?,
+                    row.get::<_, Option<i64>>(31)?,
+                    row.get::<_, Option<i64>>(32)?,
+                    row.get::<_, Option<i64>>(33)?,
+                    row.get::<_, Option<i64>>(34)?,
+                    row.get::<_, Option<String>>(35)?,
                 ))
             })
             .optional()?;
@@ -458,6 +862,23 @@ impl TokenStore for DatabaseLogger {
             ip_whitelist_s,
             ip_blacklist_s,
             model_blacklist_s,
+            allow_streaming_i,
+            sandbox_i,
+            strip_reasoning_i,
+            usage_webhook_url_s,
+            signing_secret_s,
+            require_signature_i,
+            parent_token_id_s,
+            allow_debug_capture_i,
+            allow_provider_override_i,
+            auto_truncate_prompt_i,
+            semantic_cache_i,
+            allow_login_codes_i,
+            max_requests,
+            max_requests_per_day,
+            watermark_responses_i,
+            queue_weight,
+            usage_webhook_secret,
         )) = row
         else {
             return Ok(None);
@@ -505,6 +926,23 @@ impl TokenStore for DatabaseLogger {
             organization_id,
             ip_whitelist: decode_json_string_list("ip_whitelist", ip_whitelist_s)?,
             ip_blacklist: decode_json_string_list("ip_blacklist", ip_blacklist_s)?,
+            allow_streaming: allow_streaming_i.map(|v| v != 0).unwrap_or(true),
+            sandbox: sandbox_i.map(|v| v != 0).unwrap_or(false),
+            strip_reasoning: strip_reasoning_i.map(|v| v != 0).unwrap_or(false),
+            usage_webhook_url: usage_webhook_url_s,
+            signing_secret: signing_secret_s,
+            require_signature: require_signature_i.map(|v| v != 0).unwrap_or(false),
+            parent_token_id: parent_token_id_s,
+            allow_debug_capture: allow_debug_capture_i.map(|v| v != 0).unwrap_or(false),
+            allow_provider_override: allow_provider_override_i.map(|v| v != 0).unwrap_or(false),
+            auto_truncate_prompt: auto_truncate_prompt_i.map(|v| v != 0).unwrap_or(false),
+            semantic_cache: semantic_cache_i.map(|v| v != 0).unwrap_or(false),
+            allow_login_codes: allow_login_codes_i.map(|v| v != 0).unwrap_or(false),
+            max_requests,
+            max_requests_per_day,
+            watermark_responses: watermark_responses_i.map(|v| v != 0).unwrap_or(false),
+            queue_weight,
+            usage_webhook_secret,
         }))
     }
 
@@ -515,7 +953,7 @@ impl TokenStore for DatabaseLogger {
     ) -> Result<Option<ClientToken>, GatewayError> {
         let conn = self.connection.lock().await;
         use rusqlite::OptionalExtension;
-        let mut stmt = conn.prepare("SELECT id, user_id, name, token, allowed_models, max_tokens, enabled, expires_at, created_at, max_amount, amount_spent, prompt_tokens_spent, completion_tokens_spent, total_tokens_spent, remark, organization_id, ip_whitelist, ip_blacklist, model_blacklist FROM client_tokens WHERE id = ?1 AND user_id = ?2")?;
+        let mut stmt = conn.prepare("SELECT id, user_id, name, token, allowed_models, max_tokens, enabled, expires_at, created_at, max_amount, amount_spent, prompt_tokens_spent, completion_tokens_spent, total_tokens_spent, remark, organization_id, ip_whitelist, ip_blacklist, model_blacklist, allow_streaming, sandbox, strip_reasoning, usage_webhook_url, signing_secret, require_signature, parent_token_id, allow_debug_capture, allow_provider_override, auto_truncate_prompt, semantic_cache, allow_login_codes, max_requests, max_requests_per_day, watermark_responses, queue_weight, usage_webhook_secret FROM client_tokens WHERE id = ?1 AND user_id = ?2")?;
         let row = stmt
             .query_row((id, user_id), |row| {
                 Ok((
@@ -538,6 +976,23 @@ impl TokenStore for DatabaseLogger {
                     row.get::<_, Option<String>>(16)?,
                     row.get::<_, Option<String>>(17)?,
                     row.get::<_, Option<String>>(18)?,
+                    row.get::<_, Option<i64>>(19)?,
+                    row.get::<_, Option<i64>>(20)?,
+                    row.get::<_, Option<i64>>(21)?,
+                    row.get::<_, Option<String>>(22)?,
+                    row.get::<_, Option<String>>(23)?,
+                    row.get::<_, Option<i64>>(24)?,
+                    row.get::<_, Option<String>>(25)?,
+                    row.get::<_, Option<i64>>(26)?,
+                    row.get::<_, Option<i64>>(27)?,
+                    row.get::<_, Option<i64>>(28)?,
+                    row.get::<_, Option<i64>>(29)?,
+                    row.get::<_, Option<i64>>(30)?,
+                    row.get::<_, Option<i64>>(31)?,
+                    row.get::<_, Option<i64>>(32)?,
+                    row.get::<_, Option<i64>>(33)?,
+                    row.get::<_, Option<i64>>(34)?,
+                    row.get::<_, Option<String>>(35)?,
                 ))
             })
             .optional()?;
@@ -561,6 +1016,23 @@ impl TokenStore for DatabaseLogger {
             ip_whitelist_s,
             ip_blacklist_s,
             model_blacklist_s,
+            allow_streaming_i,
+            sandbox_i,
+            strip_reasoning_i,
+            usage_webhook_url_s,
+            signing_secret_s,
+            require_signature_i,
+            parent_token_id_s,
+            allow_debug_capture_i,
+            allow_provider_override_i,
+            auto_truncate_prompt_i,
+            semantic_cache_i,
+            allow_login_codes_i,
+            max_requests,
+            max_requests_per_day,
+            watermark_responses_i,
+            queue_weight,
+            usage_webhook_secret,
         )) = row
         else {
             return Ok(None);
@@ -608,12 +1080,29 @@ impl TokenStore for DatabaseLogger {
             organization_id,
             ip_whitelist: decode_json_string_list("ip_whitelist", ip_whitelist_s)?,
             ip_blacklist: decode_json_string_list("ip_blacklist", ip_blacklist_s)?,
+            allow_streaming: allow_streaming_i.map(|v| v != 0).unwrap_or(true),
+            sandbox: sandbox_i.map(|v| v != 0).unwrap_or(false),
+            strip_reasoning: strip_reasoning_i.map(|v| v != 0).unwrap_or(false),
+            usage_webhook_url: usage_webhook_url_s,
+            signing_secret: signing_secret_s,
+            require_signature: require_signature_i.map(|v| v != 0).unwrap_or(false),
+            parent_token_id: parent_token_id_s,
+            allow_debug_capture: allow_debug_capture_i.map(|v| v != 0).unwrap_or(false),
+            allow_provider_override: allow_provider_override_i.map(|v| v != 0).unwrap_or(false),
+            auto_truncate_prompt: auto_truncate_prompt_i.map(|v| v != 0).unwrap_or(false),
+            semantic_cache: semantic_cache_i.map(|v| v != 0).unwrap_or(false),
+            allow_login_codes: allow_login_codes_i.map(|v| v != 0).unwrap_or(false),
+            max_requests,
+            max_requests_per_day,
+            watermark_responses: watermark_responses_i.map(|v| v != 0).unwrap_or(false),
+            queue_weight,
+            usage_webhook_secret,
         }))
     }
 
     async fn list_tokens(&self) -> Result<Vec<ClientToken>, GatewayError> {
         let conn = self.connection.lock().await;
-        let mut stmt = conn.prepare("SELECT id, user_id, name, token, allowed_models, max_tokens, enabled, expires_at, created_at, max_amount, amount_spent, prompt_tokens_spent, completion_tokens_spent, total_tokens_spent, remark, organization_id, ip_whitelist, ip_blacklist, model_blacklist FROM client_tokens ORDER BY created_at DESC")?;
+        let mut stmt = conn.prepare("SELECT id, user_id, name, token, allowed_models, max_tokens, enabled, expires_at, created_at, max_amount, amount_spent, prompt_tokens_spent, completion_tokens_spent, total_tokens_spent, remark, organization_id, ip_whitelist, ip_blacklist, model_blacklist, allow_streaming, sandbox, strip_reasoning, usage_webhook_url, signing_secret, require_signature, parent_token_id, allow_debug_capture, allow_provider_override, auto_truncate_prompt, semantic_cache, allow_login_codes, max_requests, max_requests_per_day, watermark_responses, queue_weight, usage_webhook_secret FROM client_tokens ORDER BY created_at DESC")?;
         let rows = stmt.query_map([], |row| {
             Ok((
                 row.get::<_, Option<String>>(0)?,
@@ -635,6 +1124,23 @@ impl TokenStore for DatabaseLogger {
                 row.get::<_, Option<String>>(16)?,
                 row.get::<_, Option<String>>(17)?,
                 row.get::<_, Option<String>>(18)?,
+                row.get::<_, Option<i64>>(19)?,
+                row.get::<_, Option<i64>>(20)?,
+                row.get::<_, Option<i64>>(21)?,
+                row.get::<_, Option<String>>(22)?,
+                row.get::<_, Option<String>>(23)?,
+                row.get::<_, Option<i64>>(24)?,
+                row.get::<_, Option<String>>(25)?,
+                row.get::<_, Option<i64>>(26)?,
+                row.get::<_, Option<i64>>(27)?,
+                row.get::<_, Option<i64>>(28)?,
+                row.get::<_, Option<i64>>(29)?,
+                row.get::<_, Option<i64>>(30)?,
+                row.get::<_, Option<i64>>(31)?,
+                row.get::<_, Option<i64>>(32)?,
+                row.get::<_, Option<i64>>(33)?,
+                row.get::<_, Option<i64>>(34)?,
+                row.get::<_, Option<String>>(35)?,
             ))
         })?;
         let mut out = Vec::new();
@@ -659,6 +1165,23 @@ impl TokenStore for DatabaseLogger {
                 ip_whitelist_s,
                 ip_blacklist_s,
                 model_blacklist_s,
+                allow_streaming_i,
+                sandbox_i,
+                strip_reasoning_i,
+                usage_webhook_url_s,
+                signing_secret_s,
+                require_signature_i,
+                parent_token_id_s,
+                allow_debug_capture_i,
+                allow_provider_override_i,
+                auto_truncate_prompt_i,
+                semantic_cache_i,
+                allow_login_codes_i,
+                max_requests,
+                max_requests_per_day,
+                watermark_responses_i,
+                queue_weight,
+                usage_webhook_secret,
             ) = r?;
             let needs_id_backfill = id0.as_deref().filter(|s| !s.is_empty()).is_none();
             let needs_name_backfill = name0.as_deref().filter(|s| !s.trim().is_empty()).is_none();
@@ -703,106 +1226,69 @@ impl TokenStore for DatabaseLogger {
                 organization_id,
                 ip_whitelist: decode_json_string_list("ip_whitelist", ip_whitelist_s)?,
                 ip_blacklist: decode_json_string_list("ip_blacklist", ip_blacklist_s)?,
+                allow_streaming: allow_streaming_i.map(|v| v != 0).unwrap_or(true),
+                sandbox: sandbox_i.map(|v| v != 0).unwrap_or(false),
+                strip_reasoning: strip_reasoning_i.map(|v| v != 0).unwrap_or(false),
+                usage_webhook_url: usage_webhook_url_s,
+                signing_secret: signing_secret_s,
+                require_signature: require_signature_i.map(|v| v != 0).unwrap_or(false),
+                parent_token_id: parent_token_id_s,
+                allow_debug_capture: allow_debug_capture_i.map(|v| v != 0).unwrap_or(false),
+                allow_provider_override: allow_provider_override_i.map(|v| v != 0).unwrap_or(false),
+                auto_truncate_prompt: auto_truncate_prompt_i.map(|v| v != 0).unwrap_or(false),
+                semantic_cache: semantic_cache_i.map(|v| v != 0).unwrap_or(false),
+                allow_login_codes: allow_login_codes_i.map(|v| v != 0).unwrap_or(false),
+                max_requests,
+                max_requests_per_day,
+                watermark_responses: watermark_responses_i.map(|v| v != 0).unwrap_or(false),
+                queue_weight,
+                usage_webhook_secret,
             });
         }
         Ok(out)
     }
 
     async fn list_tokens_by_user(&self, user_id: &str) -> Result<Vec<ClientToken>, GatewayError> {
-        let conn = self.connection.lock().await;
-        let mut stmt = conn.prepare("SELECT id, user_id, name, token, allowed_models, max_tokens, enabled, expires_at, created_at, max_amount, amount_spent, prompt_tokens_spent, completion_tokens_spent, total_tokens_spent, remark, organization_id, ip_whitelist, ip_blacklist, model_blacklist FROM client_tokens WHERE user_id = ?1 ORDER BY created_at DESC")?;
-        let rows = stmt.query_map([user_id], |row| {
-            Ok((
-                row.get::<_, Option<String>>(0)?,
-                row.get::<_, Option<String>>(1)?,
-                row.get::<_, Option<String>>(2)?,
-                row.get::<_, String>(3)?,
-                row.get::<_, Option<String>>(4)?,
-                row.get::<_, Option<i64>>(5)?,
-                row.get::<_, i64>(6)?,
-                row.get::<_, Option<String>>(7)?,
-                row.get::<_, String>(8)?,
-                row.get::<_, Option<f64>>(9)?,
-                row.get::<_, Option<f64>>(10)?,
-                row.get::<_, Option<i64>>(11)?,
-                row.get::<_, Option<i64>>(12)?,
-                row.get::<_, Option<i64>>(13)?,
-                row.get::<_, Option<String>>(14)?,
-                row.get::<_, Option<String>>(15)?,
-                row.get::<_, Option<String>>(16)?,
-                row.get::<_, Option<String>>(17)?,
-                row.get::<_, Option<String>>(18)?,
-            ))
-        })?;
-        let mut out = Vec::new();
-        for r in rows {
-            let (
-                id0,
-                user_id,
-                name0,
-                token,
-                allowed,
-                max_tokens,
-                enabled_i,
-                expires,
-                created_at_s,
-                max_amount,
-                amount_spent,
-                prompt_tokens_spent,
-                completion_tokens_spent,
-                total_tokens_spent,
-                remark,
-                organization_id,
-                ip_whitelist_s,
-                ip_blacklist_s,
-                model_blacklist_s,
-            ) = r?;
-            let needs_id_backfill = id0.as_deref().filter(|s| !s.is_empty()).is_none();
-            let needs_name_backfill = name0.as_deref().filter(|s| !s.trim().is_empty()).is_none();
-            let id = id0
-                .as_deref()
-                .filter(|s| !s.is_empty())
-                .map(|s| s.to_string())
-                .unwrap_or_else(|| client_token_id_for_token(&token));
-            let name = normalize_client_token_name(name0.clone(), &id);
-            if needs_id_backfill {
-                let _ = conn.execute(
-                    "UPDATE client_tokens SET id = ?2 WHERE token = ?1 AND (id IS NULL OR id = '')",
-                    (&token, &id),
-                );
-            }
-            if needs_name_backfill {
-                let _ = conn.execute(
-                    "UPDATE client_tokens SET name = ?2 WHERE token = ?1 AND (name IS NULL OR name = '')",
-                    (&token, &name),
-                );
-            }
-            out.push(ClientToken {
-                id,
-                user_id,
-                name,
-                token,
-                allowed_models: parse_allowed_models(allowed),
-                model_blacklist: parse_allowed_models(model_blacklist_s),
-                max_tokens,
-                max_amount,
-                enabled: enabled_i != 0,
-                expires_at: match expires {
-                    Some(s) => parse_beijing_string(&s).ok(),
-                    None => None,
-                },
-                created_at: parse_beijing_string(&created_at_s).unwrap_or(Utc::now()),
-                amount_spent: amount_spent.unwrap_or(0.0),
-                prompt_tokens_spent: prompt_tokens_spent.unwrap_or(0),
-                completion_tokens_spent: completion_tokens_spent.unwrap_or(0),
-                total_tokens_spent: total_tokens_spent.unwrap_or(0),
-                remark,
-                organization_id,
-                ip_whitelist: decode_json_string_list("ip_whitelist", ip_whitelist_s)?,
-                ip_blacklist: decode_json_string_list("ip_blacklist", ip_blacklist_s)?,
-            });
-        }
-        Ok(out)
+        self.list_tokens_where("user_id", user_id).await
+    }
+
+    async fn list_tokens_by_organization(
+        &self,
+        organization_id: &str,
+    ) -> Result<Vec<ClientToken>, GatewayError> {
+        self.list_tokens_where("organization_id", organization_id)
+            .await
+    }
+
+    async fn list_tokens_page(
+        &self,
+        organization_id: Option<&str>,
+        page: &PageRequest,
+    ) -> Result<(Vec<ClientToken>, u64), GatewayError> {
+        let filter = if organization_id.is_some() {
+            "WHERE organization_id = ?1"
+        } else {
+            ""
+        };
+        let total: i64 = {
+            let conn = self.connection.lock().await;
+            conn.query_row(
+                &format!("SELECT COUNT(*) FROM client_tokens {}", filter),
+                rusqlite::params_from_iter(organization_id),
+                |row| row.get(0),
+            )?
+        };
+        let tokens = self
+            .query_tokens(
+                &format!("{} {}", filter, page.sql_tail(&["id"])),
+                rusqlite::params_from_iter(organization_id),
+            )
+            .await?;
+        Ok((tokens, total.max(0) as u64))
+    }
+
+    async fn list_child_tokens(&self, parent_id: &str) -> Result<Vec<ClientToken>, GatewayError> {
+        self.list_tokens_where("parent_token_id", parent_id).await
     }
 
     async fn add_amount_spent(&self, token: &str, delta: f64) -> Result<(), GatewayError> {
@@ -866,6 +1352,255 @@ impl TokenStore for DatabaseLogger {
         )?;
         Ok(affected > 0)
     }
+
+    async fn get_notifications_opt_out(&self, id: &str) -> Result<bool, GatewayError> {
+        use rusqlite::OptionalExtension;
+        let conn = self.connection.lock().await;
+        let v: Option<i64> = conn
+            .query_row(
+                "SELECT notifications_opt_out FROM client_token_prefs WHERE token_id = ?1",
+                [id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(v.unwrap_or(0) != 0)
+    }
+
+    async fn set_notifications_opt_out(&self, id: &str, opt_out: bool) -> Result<(), GatewayError> {
+        let conn = self.connection.lock().await;
+        conn.execute(
+            "INSERT INTO client_token_prefs (token_id, notifications_opt_out, updated_at)
+             VALUES (?1, ?2, ?3)
+             ON CONFLICT(token_id) DO UPDATE SET notifications_opt_out = excluded.notifications_opt_out, updated_at = excluded.updated_at",
+            (id, if opt_out { 1 } else { 0 }, to_beijing_string(&Utc::now())),
+        )?;
+        Ok(())
+    }
+
+    async fn record_token_notification(
+        &self,
+        record: &TokenNotificationRecord,
+    ) -> Result<(), GatewayError> {
+        let conn = self.connection.lock().await;
+        conn.execute(
+            "INSERT INTO client_token_notifications (token_id, kind, reference, channel, recipient, success, error, sent_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            rusqlite::params![
+                record.token_id,
+                record.kind,
+                record.reference,
+                record.channel,
+                record.recipient,
+                if record.success { 1 } else { 0 },
+                record.error,
+                to_beijing_string(&record.sent_at),
+            ],
+        )?;
+        Ok(())
+    }
+
+    async fn token_notification_sent(
+        &self,
+        id: &str,
+        kind: &str,
+        reference: &str,
+    ) -> Result<bool, GatewayError> {
+        use rusqlite::OptionalExtension;
+        let conn = self.connection.lock().await;
+        let found = conn
+            .query_row(
+                "SELECT 1 FROM client_token_notifications WHERE token_id = ?1 AND kind = ?2 AND reference = ?3 AND success = 1 LIMIT 1",
+                (id, kind, reference),
+                |_| Ok(()),
+            )
+            .optional()?;
+        Ok(found.is_some())
+    }
+
+    async fn list_token_notifications(
+        &self,
+        id: &str,
+        limit: i64,
+    ) -> Result<Vec<TokenNotificationRecord>, GatewayError> {
+        let conn = self.connection.lock().await;
+        let mut stmt = conn.prepare(
+            "SELECT token_id, kind, reference, channel, recipient, success, error, sent_at
+             FROM client_token_notifications WHERE token_id = ?1 ORDER BY id DESC LIMIT ?2",
+        )?;
+        let rows = stmt.query_map((id, limit), |row| {
+            let sent_at: String = row.get(7)?;
+            Ok(TokenNotificationRecord {
+                token_id: row.get(0)?,
+                kind: row.get(1)?,
+                reference: row.get(2)?,
+                channel: row.get(3)?,
+                recipient: row.get(4)?,
+                success: row.get::<_, i64>(5)? != 0,
+                error: row.get(6)?,
+                sent_at: parse_beijing_string(&sent_at).unwrap_or_else(|_| Utc::now()),
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+    }
+
+    async fn get_auto_disable_prefs(
+        &self,
+        id: &str,
+    ) -> Result<TokenAutoDisablePrefs, GatewayError> {
+        use rusqlite::OptionalExtension;
+        let conn = self.connection.lock().await;
+        let row: Option<(i64, Option<String>)> = conn
+            .query_row(
+                "SELECT auto_disable_exempt, auto_disabled_at FROM client_token_prefs WHERE token_id = ?1",
+                [id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        Ok(row
+            .map(|(exempt, disabled_at)| TokenAutoDisablePrefs {
+                exempt: exempt != 0,
+                disabled_at: disabled_at.and_then(|s| parse_beijing_string(&s).ok()),
+            })
+            .unwrap_or_default())
+    }
+
+    async fn set_auto_disable_exempt(&self, id: &str, exempt: bool) -> Result<(), GatewayError> {
+        let conn = self.connection.lock().await;
+        conn.execute(
+            "INSERT INTO client_token_prefs (token_id, auto_disable_exempt, updated_at)
+             VALUES (?1, ?2, ?3)
+             ON CONFLICT(token_id) DO UPDATE SET auto_disable_exempt = excluded.auto_disable_exempt, updated_at = excluded.updated_at",
+            (id, if exempt { 1 } else { 0 }, to_beijing_string(&Utc::now())),
+        )?;
+        Ok(())
+    }
+
+    async fn mark_auto_disabled(
+        &self,
+        id: &str,
+        at: chrono::DateTime<Utc>,
+    ) -> Result<(), GatewayError> {
+        let conn = self.connection.lock().await;
+        let at = to_beijing_string(&at);
+        conn.execute(
+            "INSERT INTO client_token_prefs (token_id, auto_disabled_at, updated_at)
+             VALUES (?1, ?2, ?2)
+             ON CONFLICT(token_id) DO UPDATE SET auto_disabled_at = excluded.auto_disabled_at, updated_at = excluded.updated_at",
+            (id, &at),
+        )?;
+        Ok(())
+    }
+
+    async fn get_rotation_prefs(&self, id: &str) -> Result<TokenRotationPrefs, GatewayError> {
+        use rusqlite::OptionalExtension;
+        let conn = self.connection.lock().await;
+        let row: Option<(i64, Option<String>)> = conn
+            .query_row(
+                "SELECT rotation_exempt, rotated_at FROM client_token_prefs WHERE token_id = ?1",
+                [id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        Ok(row
+            .map(|(exempt, rotated_at)| TokenRotationPrefs {
+                exempt: exempt != 0,
+                rotated_at: rotated_at.and_then(|s| parse_beijing_string(&s).ok()),
+            })
+            .unwrap_or_default())
+    }
+
+    async fn set_rotation_exempt(&self, id: &str, exempt: bool) -> Result<(), GatewayError> {
+        let conn = self.connection.lock().await;
+        conn.execute(
+            "INSERT INTO client_token_prefs (token_id, rotation_exempt, updated_at)
+             VALUES (?1, ?2, ?3)
+             ON CONFLICT(token_id) DO UPDATE SET rotation_exempt = excluded.rotation_exempt, updated_at = excluded.updated_at",
+            (id, if exempt { 1 } else { 0 }, to_beijing_string(&Utc::now())),
+        )?;
+        Ok(())
+    }
+
+    async fn rotate_token(
+        &self,
+        id: &str,
+        new_token: &str,
+        at: chrono::DateTime<Utc>,
+    ) -> Result<bool, GatewayError> {
+        let mut conn = self.connection.lock().await;
+        let tx = conn.transaction()?;
+        let affected = tx.execute(
+            "UPDATE client_tokens SET token = ?2 WHERE id = ?1",
+            (id, new_token),
+        )?;
+        if affected == 0 {
+            return Ok(false);
+        }
+        let at = to_beijing_string(&at);
+        tx.execute(
+            "INSERT INTO client_token_prefs (token_id, rotated_at, updated_at)
+             VALUES (?1, ?2, ?2)
+             ON CONFLICT(token_id) DO UPDATE SET rotated_at = excluded.rotated_at, updated_at = excluded.updated_at",
+            (id, &at),
+        )?;
+        tx.commit()?;
+        Ok(true)
+    }
+
+    async fn import_token(&self, t: &ClientToken) -> Result<(), GatewayError> {
+        let allowed_models_s = join_allowed_models(&t.allowed_models);
+        let model_blacklist_s = join_allowed_models(&t.model_blacklist);
+        let ip_whitelist_s = encode_json_string_list("ip_whitelist", &t.ip_whitelist)?;
+        let ip_blacklist_s = encode_json_string_list("ip_blacklist", &t.ip_blacklist)?;
+        let conn = self.connection.lock().await;
+        if let Some(organization_id) = t.organization_id.as_deref() {
+            conn.execute(
+                "INSERT OR IGNORE INTO organizations (name) VALUES (?1)",
+                [organization_id],
+            )?;
+        }
+        conn.execute(
+            "INSERT OR IGNORE INTO client_tokens (id, user_id, name, token, allowed_models, max_tokens, enabled, expires_at, created_at, max_amount, amount_spent, prompt_tokens_spent, completion_tokens_spent, total_tokens_spent, remark, organization_id, ip_whitelist, ip_blacklist, model_blacklist, allow_streaming, sandbox, strip_reasoning, usage_webhook_url, signing_secret, require_signature, parent_token_id, allow_debug_capture, allow_provider_override, auto_truncate_prompt, semantic_cache, allow_login_codes, max_requests, max_requests_per_day, watermark_responses, queue_weight, usage_webhook_secret) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23, ?24, ?25, ?26, ?27, ?28, ?29, ?30, ?31, ?32, ?33, ?34, ?35, ?36)",
+            rusqlite::params![
+                &t.id,
+                &t.user_id,
+                &t.name,
+                &t.token,
+                &allowed_models_s,
+                t.max_tokens,
+                if t.enabled { 1 } else { 0 },
+                t.expires_at.as_ref().map(to_beijing_string),
+                to_beijing_string(&t.created_at),
+                t.max_amount,
+                t.amount_spent,
+                t.prompt_tokens_spent,
+                t.completion_tokens_spent,
+                t.total_tokens_spent,
+                &t.remark,
+                &t.organization_id,
+                &ip_whitelist_s,
+                &ip_blacklist_s,
+                &model_blacklist_s,
+                if t.allow_streaming { 1 } else { 0 },
+                if t.sandbox { 1 } else { 0 },
+                if t.strip_reasoning { 1 } else { 0 },
+                &t.usage_webhook_url,
+                &t.signing_secret,
+                if t.require_signature { 1 } else { 0 },
+                &t.parent_token_id,
+                if t.allow_debug_capture { 1 } else { 0 },
+                if t.allow_provider_override { 1 } else { 0 },
+                if t.auto_truncate_prompt { 1 } else { 0 },
+                if t.semantic_cache { 1 } else { 0 },
+                if t.allow_login_codes { 1 } else { 0 },
+                t.max_requests,
+                t.max_requests_per_day,
+                if t.watermark_responses { 1 } else { 0 },
+                t.queue_weight,
+                &t.usage_webhook_secret,
+            ],
+        )?;
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -901,39 +1636,17 @@ mod tests {
 
         let t1 = db
             .create_token(CreateTokenPayload {
-                id: None,
                 user_id: Some(user.id.clone()),
                 name: Some("t1".into()),
-                token: None,
-                allowed_models: None,
-                model_blacklist: None,
-                max_tokens: None,
-                max_amount: None,
-                enabled: true,
-                expires_at: None,
-                remark: None,
-                organization_id: None,
-                ip_whitelist: None,
-                ip_blacklist: None,
+                ..Default::default()
             })
             .await
             .unwrap();
         let _t2 = db
             .create_token(CreateTokenPayload {
-                id: None,
                 user_id: Some(user.id.clone()),
                 name: Some("t2".into()),
-                token: None,
-                allowed_models: None,
-                model_blacklist: None,
-                max_tokens: None,
-                max_amount: None,
-                enabled: true,
-                expires_at: None,
-                remark: None,
-                organization_id: None,
-                ip_whitelist: None,
-                ip_blacklist: None,
+                ..Default::default()
             })
             .await
             .unwrap();
@@ -1030,20 +1743,9 @@ mod tests {
 
         let created = db
             .create_token(CreateTokenPayload {
-                id: None,
-                user_id: None,
                 name: Some("org-token".into()),
-                token: None,
-                allowed_models: None,
-                model_blacklist: None,
-                max_tokens: None,
-                max_amount: None,
-                enabled: true,
-                expires_at: None,
-                remark: None,
                 organization_id: Some("team-alpha".into()),
-                ip_whitelist: None,
-                ip_blacklist: None,
+                ..Default::default()
             })
             .await
             .unwrap();
@@ -1059,4 +1761,74 @@ mod tests {
         let persisted = reopened.list_organizations().await.unwrap();
         assert!(persisted.iter().any(|id| id == "team-alpha"));
     }
+
+    #[tokio::test]
+    async fn sqlite_token_notifications_roundtrip() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("test.db");
+        let db = DatabaseLogger::new(db_path.to_str().unwrap())
+            .await
+            .unwrap();
+
+        assert!(!db.get_notifications_opt_out("atk_1").await.unwrap());
+        db.set_notifications_opt_out("atk_1", true).await.unwrap();
+        assert!(db.get_notifications_opt_out("atk_1").await.unwrap());
+        db.set_notifications_opt_out("atk_1", false).await.unwrap();
+        assert!(!db.get_notifications_opt_out("atk_1").await.unwrap());
+
+        let mut record = TokenNotificationRecord {
+            token_id: "atk_1".into(),
+            kind: "token_expiring".into(),
+            reference: "2030-01-01T00:00:00Z".into(),
+            channel: "webhook".into(),
+            recipient: Some("https://hooks.example.com".into()),
+            success: false,
+            error: Some("timeout".into()),
+            sent_at: Utc::now(),
+        };
+        db.record_token_notification(&record).await.unwrap();
+        assert!(
+            !db.token_notification_sent("atk_1", "token_expiring", "2030-01-01T00:00:00Z")
+                .await
+                .unwrap()
+        );
+        record.success = true;
+        record.error = None;
+        db.record_token_notification(&record).await.unwrap();
+        assert!(
+            db.token_notification_sent("atk_1", "token_expiring", "2030-01-01T00:00:00Z")
+                .await
+                .unwrap()
+        );
+
+        let history = db.list_token_notifications("atk_1", 10).await.unwrap();
+        assert_eq!(history.len(), 2);
+        assert!(history[0].success);
+    }
+
+    #[tokio::test]
+    async fn sqlite_auto_disable_prefs_roundtrip() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("test.db");
+        let db = DatabaseLogger::new(db_path.to_str().unwrap())
+            .await
+            .unwrap();
+
+        let prefs = db.get_auto_disable_prefs("atk_1").await.unwrap();
+        assert!(!prefs.exempt);
+        assert!(prefs.disabled_at.is_none());
+
+        db.set_notifications_opt_out("atk_1", true).await.unwrap();
+        db.set_auto_disable_exempt("atk_1", true).await.unwrap();
+        let at = chrono::DateTime::parse_from_rfc3339("2030-01-01T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        db.mark_auto_disabled("atk_1", at).await.unwrap();
+
+        let prefs = db.get_auto_disable_prefs("atk_1").await.unwrap();
+        assert!(prefs.exempt);
+        assert_eq!(prefs.disabled_at, Some(at));
+        // 各字段独立更新，互不覆盖
+        assert!(db.get_notifications_opt_out("atk_1").await.unwrap());
+    }
 }
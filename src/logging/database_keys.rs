@@ -1,10 +1,11 @@
 use chrono::{NaiveDateTime, TimeZone, Utc};
 use rusqlite::Result;
+use std::collections::BTreeMap;
 
 use super::database::DatabaseLogger;
 use crate::config::settings::KeyLogStrategy;
 use crate::logging::time::{BEIJING_OFFSET, DATETIME_FORMAT};
-use crate::routing::ProviderKeyEntry;
+use crate::routing::{OpenAIAccountHeaders, ProviderKeyEntry};
 use crate::server::storage_traits::ProviderKeyEntryWithCreatedAt;
 
 impl DatabaseLogger {
@@ -42,13 +43,17 @@ impl DatabaseLogger {
     ) -> Result<Vec<ProviderKeyEntry>> {
         let conn = self.connection.lock().await;
         let mut stmt = conn.prepare(
-            "SELECT key_value, enc, active, weight FROM provider_keys WHERE provider = ?1 ORDER BY created_at",
+            "SELECT key_value, enc, active, weight, spend_cap, rpm_limit, tpm_limit, openai_organization, openai_project, labels FROM provider_keys WHERE provider = ?1 ORDER BY created_at",
         )?;
         let rows = stmt.query_map([provider], |row| {
             let value: String = row.get(0)?;
             let enc: i64 = row.get(1)?;
             let active: i64 = row.get(2)?;
             let weight: i64 = row.get(3)?;
+            let spend_cap: Option<f64> = row.get(4)?;
+            let rpm_limit: Option<i64> = row.get(5)?;
+            let tpm_limit: Option<i64> = row.get(6)?;
+            let labels: Option<String> = row.get(9)?;
             let decrypted =
                 crate::crypto::unprotect(strategy, provider, &value, enc != 0).unwrap_or_default();
             let weight_u32 = if weight >= 1 { weight as u32 } else { 1 };
@@ -56,6 +61,16 @@ impl DatabaseLogger {
                 value: decrypted,
                 active: active != 0,
                 weight: weight_u32,
+                spend_cap,
+                rpm_limit: rpm_limit.and_then(|v| u32::try_from(v).ok()),
+                tpm_limit: tpm_limit.and_then(|v| u32::try_from(v).ok()),
+                openai_account: OpenAIAccountHeaders {
+                    openai_organization: row.get(7)?,
+                    openai_project: row.get(8)?,
+                },
+                labels: labels
+                    .and_then(|raw| serde_json::from_str(&raw).ok())
+                    .unwrap_or_default(),
             })
         })?;
 
@@ -182,6 +197,83 @@ impl DatabaseLogger {
         Ok(affected > 0)
     }
 
+    pub async fn set_provider_key_limits(
+        &self,
+        provider: &str,
+        key: &str,
+        spend_cap: Option<f64>,
+        rpm_limit: Option<u32>,
+        tpm_limit: Option<u32>,
+        strategy: &Option<KeyLogStrategy>,
+    ) -> Result<bool> {
+        let conn = self.connection.lock().await;
+        let (stored, enc) = crate::crypto::protect(strategy, provider, key);
+        let rpm_limit = rpm_limit.map(i64::from);
+        let tpm_limit = tpm_limit.map(i64::from);
+        let mut affected = conn.execute(
+            "UPDATE provider_keys SET spend_cap = ?3, rpm_limit = ?4, tpm_limit = ?5 WHERE provider = ?1 AND key_value = ?2",
+            (provider, stored, spend_cap, rpm_limit, tpm_limit),
+        )?;
+        // 兼容已存明文的情况
+        if enc {
+            affected += conn.execute(
+                "UPDATE provider_keys SET spend_cap = ?3, rpm_limit = ?4, tpm_limit = ?5 WHERE provider = ?1 AND key_value = ?2",
+                (provider, key, spend_cap, rpm_limit, tpm_limit),
+            )?;
+        }
+        Ok(affected > 0)
+    }
+
+    pub async fn set_provider_key_openai_headers(
+        &self,
+        provider: &str,
+        key: &str,
+        headers: &OpenAIAccountHeaders,
+        strategy: &Option<KeyLogStrategy>,
+    ) -> Result<bool> {
+        let conn = self.connection.lock().await;
+        let (stored, enc) = crate::crypto::protect(strategy, provider, key);
+        let org = headers.openai_organization.as_deref();
+        let project = headers.openai_project.as_deref();
+        let mut affected = conn.execute(
+            "UPDATE provider_keys SET openai_organization = ?3, openai_project = ?4 WHERE provider = ?1 AND key_value = ?2",
+            (provider, stored, org, project),
+        )?;
+        // 兼容已存明文的情况
+        if enc {
+            affected += conn.execute(
+                "UPDATE provider_keys SET openai_organization = ?3, openai_project = ?4 WHERE provider = ?1 AND key_value = ?2",
+                (provider, key, org, project),
+            )?;
+        }
+        Ok(affected > 0)
+    }
+
+    pub async fn set_provider_key_labels(
+        &self,
+        provider: &str,
+        key: &str,
+        labels: &BTreeMap<String, String>,
+        strategy: &Option<KeyLogStrategy>,
+    ) -> Result<bool> {
+        let conn = self.connection.lock().await;
+        let (stored, enc) = crate::crypto::protect(strategy, provider, key);
+        let labels = (!labels.is_empty())
+            .then(|| serde_json::to_string(labels).unwrap_or_else(|_| "{}".into()));
+        let mut affected = conn.execute(
+            "UPDATE provider_keys SET labels = ?3 WHERE provider = ?1 AND key_value = ?2",
+            (provider, stored, labels.as_deref()),
+        )?;
+        // 兼容已存明文的情况
+        if enc {
+            affected += conn.execute(
+                "UPDATE provider_keys SET labels = ?3 WHERE provider = ?1 AND key_value = ?2",
+                (provider, key, labels.as_deref()),
+            )?;
+        }
+        Ok(affected > 0)
+    }
+
     pub async fn remove_provider_key(
         &self,
         provider: &str,
@@ -1,4 +1,5 @@
-use rusqlite::Result;
+use chrono::Utc;
+use rusqlite::{OptionalExtension, Result};
 
 use super::database::DatabaseLogger;
 
@@ -28,4 +29,55 @@ impl DatabaseLogger {
         )?;
         Ok(())
     }
+
+    /// 委派用户管理指定组织（覆盖该用户此前的委派）
+    pub async fn set_organization_admin(&self, organization_id: &str, user_id: &str) -> Result<()> {
+        let conn = self.connection.lock().await;
+        conn.execute(
+            "INSERT INTO organization_admins (user_id, organization_id, created_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT(user_id) DO UPDATE SET organization_id = excluded.organization_id",
+            (
+                user_id,
+                organization_id,
+                crate::logging::time::to_beijing_string(&Utc::now()),
+            ),
+        )?;
+        Ok(())
+    }
+
+    pub async fn remove_organization_admin(
+        &self,
+        organization_id: &str,
+        user_id: &str,
+    ) -> Result<bool> {
+        let conn = self.connection.lock().await;
+        let affected = conn.execute(
+            "DELETE FROM organization_admins WHERE user_id = ?1 AND organization_id = ?2",
+            (user_id, organization_id),
+        )?;
+        Ok(affected > 0)
+    }
+
+    pub async fn list_organization_admins(&self, organization_id: &str) -> Result<Vec<String>> {
+        let conn = self.connection.lock().await;
+        let mut stmt = conn.prepare(
+            "SELECT user_id FROM organization_admins WHERE organization_id = ?1 ORDER BY created_at, user_id",
+        )?;
+        let rows = stmt.query_map([organization_id], |row| row.get(0))?;
+        let mut out = Vec::new();
+        for row in rows {
+            out.push(row?);
+        }
+        Ok(out)
+    }
+
+    pub async fn get_admin_organization(&self, user_id: &str) -> Result<Option<String>> {
+        let conn = self.connection.lock().await;
+        conn.query_row(
+            "SELECT organization_id FROM organization_admins WHERE user_id = ?1",
+            [user_id],
+            |row| row.get(0),
+        )
+        .optional()
+    }
 }
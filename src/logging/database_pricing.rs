@@ -3,6 +3,7 @@ use rusqlite::Result;
 use super::database::DatabaseLogger;
 use crate::logging::time::{parse_datetime_string, to_iso8601_utc_string};
 use crate::logging::{ModelPriceRecord, ModelPriceSource, ModelPriceStatus, ModelPriceUpsert};
+use crate::server::pagination::PageRequest;
 
 fn parse_price_source(raw: &str) -> ModelPriceSource {
     match raw {
@@ -34,46 +35,60 @@ fn price_status_str(status: ModelPriceStatus) -> &'static str {
     }
 }
 
+fn upsert_model_price_row(conn: &rusqlite::Connection, price: &ModelPriceUpsert) -> Result<()> {
+    conn.execute(
+        "INSERT INTO model_prices (
+            provider,
+            model,
+            prompt_price_per_million,
+            completion_price_per_million,
+            currency,
+            model_type,
+            source,
+            status,
+            synced_at,
+            expires_at
+        )
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+         ON CONFLICT(provider, model) DO UPDATE SET
+            prompt_price_per_million = excluded.prompt_price_per_million,
+            completion_price_per_million = excluded.completion_price_per_million,
+            currency = excluded.currency,
+            model_type = excluded.model_type,
+            source = excluded.source,
+            status = excluded.status,
+            synced_at = excluded.synced_at,
+            expires_at = excluded.expires_at",
+        (
+            &price.provider,
+            &price.model,
+            price.prompt_price_per_million,
+            price.completion_price_per_million,
+            price.currency.as_deref(),
+            price.model_type.as_deref(),
+            price_source_str(price.source),
+            price_status_str(price.status),
+            price.synced_at.as_ref().map(to_iso8601_utc_string),
+            price.expires_at.as_ref().map(to_iso8601_utc_string),
+        ),
+    )?;
+    Ok(())
+}
+
 impl DatabaseLogger {
     pub async fn upsert_model_price(&self, price: ModelPriceUpsert) -> Result<()> {
         let conn = self.connection.lock().await;
-        conn.execute(
-            "INSERT INTO model_prices (
-                provider,
-                model,
-                prompt_price_per_million,
-                completion_price_per_million,
-                currency,
-                model_type,
-                source,
-                status,
-                synced_at,
-                expires_at
-            )
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
-             ON CONFLICT(provider, model) DO UPDATE SET
-                prompt_price_per_million = excluded.prompt_price_per_million,
-                completion_price_per_million = excluded.completion_price_per_million,
-                currency = excluded.currency,
-                model_type = excluded.model_type,
-                source = excluded.source,
-                status = excluded.status,
-                synced_at = excluded.synced_at,
-                expires_at = excluded.expires_at",
-            (
-                &price.provider,
-                &price.model,
-                price.prompt_price_per_million,
-                price.completion_price_per_million,
-                price.currency.as_deref(),
-                price.model_type.as_deref(),
-                price_source_str(price.source),
-                price_status_str(price.status),
-                price.synced_at.as_ref().map(to_iso8601_utc_string),
-                price.expires_at.as_ref().map(to_iso8601_utc_string),
-            ),
-        )?;
-        Ok(())
+        upsert_model_price_row(&conn, &price)
+    }
+
+    /// 在单个事务中批量写入价格，任一行失败则全部回滚
+    pub async fn upsert_model_prices(&self, prices: Vec<ModelPriceUpsert>) -> Result<()> {
+        let mut conn = self.connection.lock().await;
+        let tx = conn.transaction()?;
+        for price in &prices {
+            upsert_model_price_row(&tx, price)?;
+        }
+        tx.commit()
     }
 
     pub async fn get_model_price(
@@ -132,66 +147,74 @@ impl DatabaseLogger {
 
     pub async fn list_model_prices(&self, provider: Option<&str>) -> Result<Vec<ModelPriceRecord>> {
         let conn = self.connection.lock().await;
-        if let Some(p) = provider {
-            let mut stmt = conn.prepare(
-                "SELECT provider, model, prompt_price_per_million, completion_price_per_million, currency, model_type, source, status, synced_at, expires_at
-                 FROM model_prices WHERE provider = ?1 ORDER BY model",
-            )?;
-            let rows = stmt.query_map([p], |row| {
-                Ok(ModelPriceRecord {
-                    provider: row.get(0)?,
-                    model: row.get(1)?,
-                    prompt_price_per_million: row.get(2)?,
-                    completion_price_per_million: row.get(3)?,
-                    currency: row.get(4)?,
-                    model_type: row.get(5)?,
-                    source: parse_price_source(&row.get::<_, String>(6)?),
-                    status: parse_price_status(&row.get::<_, String>(7)?),
-                    synced_at: row
-                        .get::<_, Option<String>>(8)?
-                        .and_then(|raw| parse_datetime_string(&raw).ok()),
-                    expires_at: row
-                        .get::<_, Option<String>>(9)?
-                        .and_then(|raw| parse_datetime_string(&raw).ok()),
-                })
-            })?;
-            let mut out = Vec::new();
-            for r in rows {
-                out.push(r?);
-            }
-            Ok(out)
+        let (filter, order) = match provider {
+            Some(_) => ("WHERE provider = ?1", "ORDER BY model"),
+            None => ("", "ORDER BY provider, model"),
+        };
+        query_model_prices(
+            &conn,
+            &format!("{} {}", filter, order),
+            rusqlite::params_from_iter(provider),
+        )
+    }
+
+    pub async fn list_model_prices_page(
+        &self,
+        provider: Option<&str>,
+        page: &PageRequest,
+    ) -> Result<(Vec<ModelPriceRecord>, u64)> {
+        let conn = self.connection.lock().await;
+        let filter = if provider.is_some() {
+            "WHERE provider = ?1"
         } else {
-            let mut stmt = conn.prepare(
-                "SELECT provider, model, prompt_price_per_million, completion_price_per_million, currency, model_type, source, status, synced_at, expires_at
-                 FROM model_prices ORDER BY provider, model",
-            )?;
-            let rows = stmt.query_map([], |row| {
-                Ok(ModelPriceRecord {
-                    provider: row.get(0)?,
-                    model: row.get(1)?,
-                    prompt_price_per_million: row.get(2)?,
-                    completion_price_per_million: row.get(3)?,
-                    currency: row.get(4)?,
-                    model_type: row.get(5)?,
-                    source: parse_price_source(&row.get::<_, String>(6)?),
-                    status: parse_price_status(&row.get::<_, String>(7)?),
-                    synced_at: row
-                        .get::<_, Option<String>>(8)?
-                        .and_then(|raw| parse_datetime_string(&raw).ok()),
-                    expires_at: row
-                        .get::<_, Option<String>>(9)?
-                        .and_then(|raw| parse_datetime_string(&raw).ok()),
-                })
-            })?;
-            let mut out = Vec::new();
-            for r in rows {
-                out.push(r?);
-            }
-            Ok(out)
-        }
+            ""
+        };
+        let total: i64 = conn.query_row(
+            &format!("SELECT COUNT(*) FROM model_prices {}", filter),
+            rusqlite::params_from_iter(provider),
+            |row| row.get(0),
+        )?;
+        let items = query_model_prices(
+            &conn,
+            &format!("{} {}", filter, page.sql_tail(&["provider", "model"])),
+            rusqlite::params_from_iter(provider),
+        )?;
+        Ok((items, total.max(0) as u64))
     }
 }
 
+/// clause 为 FROM model_prices 之后的条件/排序/分页片段（由调用方固定拼接）
+fn query_model_prices(
+    conn: &rusqlite::Connection,
+    clause: &str,
+    params: impl rusqlite::Params,
+) -> Result<Vec<ModelPriceRecord>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT provider, model, prompt_price_per_million, completion_price_per_million, currency, model_type, source, status, synced_at, expires_at
+         FROM model_prices {}",
+        clause
+    ))?;
+    let rows = stmt.query_map(params, |row| {
+        Ok(ModelPriceRecord {
+            provider: row.get(0)?,
+            model: row.get(1)?,
+            prompt_price_per_million: row.get(2)?,
+            completion_price_per_million: row.get(3)?,
+            currency: row.get(4)?,
+            model_type: row.get(5)?,
+            source: parse_price_source(&row.get::<_, String>(6)?),
+            status: parse_price_status(&row.get::<_, String>(7)?),
+            synced_at: row
+                .get::<_, Option<String>>(8)?
+                .and_then(|raw| parse_datetime_string(&raw).ok()),
+            expires_at: row
+                .get::<_, Option<String>>(9)?
+                .and_then(|raw| parse_datetime_string(&raw).ok()),
+        })
+    })?;
+    rows.collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1,6 +1,6 @@
 use rusqlite::Result;
 
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 
 use crate::logging::time::{parse_datetime_string, to_beijing_string};
 use crate::logging::types::ProviderOpLog;
@@ -11,13 +11,14 @@ impl DatabaseLogger {
     pub async fn log_provider_op(&self, op: ProviderOpLog) -> Result<i64> {
         let conn = self.connection.lock().await;
         conn.execute(
-            "INSERT INTO provider_ops_logs (timestamp, operation, provider, details)
-             VALUES (?1, ?2, ?3, ?4)",
+            "INSERT INTO provider_ops_logs (timestamp, operation, provider, details, actor)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
             (
                 to_beijing_string(&op.timestamp),
                 &op.operation,
                 &op.provider,
                 &op.details,
+                &op.actor,
             ),
         )?;
         Ok(conn.last_insert_rowid())
@@ -31,7 +32,7 @@ impl DatabaseLogger {
         let conn = self.connection.lock().await;
         let mut stmt = if cursor.is_some() {
             conn.prepare(
-                "SELECT id, timestamp, operation, provider, details
+                "SELECT id, timestamp, operation, provider, details, actor
                  FROM provider_ops_logs
                  WHERE id < ?1
                  ORDER BY id DESC
@@ -39,7 +40,7 @@ impl DatabaseLogger {
             )?
         } else {
             conn.prepare(
-                "SELECT id, timestamp, operation, provider, details
+                "SELECT id, timestamp, operation, provider, details, actor
                  FROM provider_ops_logs
                  ORDER BY id DESC
                  LIMIT ?1",
@@ -58,6 +59,48 @@ impl DatabaseLogger {
         }
         Ok(out)
     }
+
+    /// 某个供应商的操作日志（按 id 倒序，cursor 为上一页最后一条的 id）
+    pub async fn get_provider_ops_logs_for_provider(
+        &self,
+        provider: &str,
+        since: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+        limit: i32,
+        cursor: Option<i64>,
+    ) -> Result<Vec<ProviderOpLog>> {
+        let conn = self.connection.lock().await;
+        let mut stmt = conn.prepare(
+            "SELECT id, timestamp, operation, provider, details, actor
+             FROM provider_ops_logs
+             WHERE provider = ?1
+               AND (?2 IS NULL OR timestamp >= ?2)
+               AND (?3 IS NULL OR timestamp < ?3)
+               AND (?4 IS NULL OR id < ?4)
+             ORDER BY id DESC
+             LIMIT ?5",
+        )?;
+        let rows = stmt.query_map(
+            rusqlite::params![
+                provider,
+                since.as_ref().map(to_beijing_string),
+                until.as_ref().map(to_beijing_string),
+                cursor,
+                limit
+            ],
+            map_provider_op_row,
+        )?;
+        rows.collect()
+    }
+
+    pub async fn purge_provider_ops_logs_before(&self, cutoff: DateTime<Utc>) -> Result<u64> {
+        let conn = self.connection.lock().await;
+        let affected = conn.execute(
+            "DELETE FROM provider_ops_logs WHERE timestamp < ?1",
+            [to_beijing_string(&cutoff)],
+        )?;
+        Ok(affected as u64)
+    }
 }
 
 fn map_provider_op_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<ProviderOpLog> {
@@ -68,6 +111,7 @@ fn map_provider_op_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<ProviderOpLo
         operation: row.get(2)?,
         provider: row.get(3)?,
         details: row.get(4)?,
+        actor: row.get(5)?,
     })
 }
 
@@ -102,4 +146,67 @@ mod tests {
             .with_timezone(&Utc);
         assert_eq!(logs[0].timestamp, expected);
     }
+
+    #[tokio::test]
+    async fn provider_ops_query_filters_by_provider_and_time_and_purges() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("gateway.db");
+        let logger = DatabaseLogger::new(db_path.to_str().unwrap())
+            .await
+            .unwrap();
+
+        let base = chrono::DateTime::parse_from_rfc3339("2026-03-01T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        for (hours, provider) in [(0, "p1"), (1, "p2"), (2, "p1"), (3, "p1")] {
+            logger
+                .log_provider_op(ProviderOpLog {
+                    id: None,
+                    timestamp: base + chrono::Duration::hours(hours),
+                    operation: format!("op_{}", hours),
+                    provider: Some(provider.into()),
+                    details: None,
+                    actor: Some("jwt:admin@example.com".into()),
+                })
+                .await
+                .unwrap();
+        }
+
+        let all = logger
+            .get_provider_ops_logs_for_provider("p1", None, None, 10, None)
+            .await
+            .unwrap();
+        let ops: Vec<_> = all.iter().map(|l| l.operation.as_str()).collect();
+        assert_eq!(ops, vec!["op_3", "op_2", "op_0"]);
+        assert_eq!(all[0].actor.as_deref(), Some("jwt:admin@example.com"));
+
+        let windowed = logger
+            .get_provider_ops_logs_for_provider(
+                "p1",
+                Some(base + chrono::Duration::hours(1)),
+                Some(base + chrono::Duration::hours(3)),
+                10,
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(windowed.len(), 1);
+        assert_eq!(windowed[0].operation, "op_2");
+
+        let page = logger
+            .get_provider_ops_logs_for_provider("p1", None, None, 10, all[0].id)
+            .await
+            .unwrap();
+        assert_eq!(page.len(), 2);
+
+        let purged = logger
+            .purge_provider_ops_logs_before(base + chrono::Duration::hours(2))
+            .await
+            .unwrap();
+        assert_eq!(purged, 2);
+        assert_eq!(
+            logger.get_provider_ops_logs(10, None).await.unwrap().len(),
+            2
+        );
+    }
 }
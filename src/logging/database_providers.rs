@@ -5,6 +5,7 @@ use crate::logging::time::{parse_datetime_string, to_iso8601_utc_string};
 use crate::routing::KeyRotationStrategy;
 
 use super::database::DatabaseLogger;
+use crate::server::pagination::PageRequest;
 
 impl DatabaseLogger {
     pub async fn insert_provider(&self, provider: &Provider) -> Result<bool> {
@@ -140,41 +141,14 @@ impl DatabaseLogger {
              WHERE created_at IS NULL OR created_at = '' OR updated_at IS NULL OR updated_at = ''",
             [&now_utc],
         );
-        let mut stmt = conn.prepare(
-            "SELECT name, display_name, collection, api_type, base_url, models_endpoint, provider_config, enabled, created_at, updated_at FROM providers ORDER BY name",
-        )?;
-        let rows = stmt.query_map([], |row| {
-            let name: String = row.get(0)?;
-            let display_name: Option<String> = row.get(1)?;
-            let collection: String = row.get(2)?;
-            let api_type: String = row.get(3)?;
-            let base_url: String = row.get(4)?;
-            let models_endpoint: Option<String> = row.get(5)?;
-            let provider_config_raw: Option<String> = row.get(6)?;
-            let enabled: i64 = row.get(7)?;
-            let created_at_raw: Option<String> = row.get(8)?;
-            let updated_at_raw: Option<String> = row.get(9)?;
-            let (api_type, api_type_raw) = ProviderType::from_storage_with_raw(&api_type);
-            Ok(Provider {
-                name,
-                display_name,
-                collection,
-                api_type,
-                api_type_raw,
-                base_url,
-                api_keys: Vec::new(),
-                models_endpoint,
-                provider_config: ProviderConfig::from_storage_json(provider_config_raw),
-                enabled: enabled != 0,
-                created_at: created_at_raw.and_then(|s| parse_datetime_string(&s).ok()),
-                updated_at: updated_at_raw.and_then(|s| parse_datetime_string(&s).ok()),
-            })
-        })?;
-        let mut out = Vec::new();
-        for r in rows {
-            out.push(r?);
-        }
-        Ok(out)
+        query_providers(&conn, "ORDER BY name")
+    }
+
+    pub async fn list_providers_page(&self, page: &PageRequest) -> Result<(Vec<Provider>, u64)> {
+        let conn = self.connection.lock().await;
+        let total: i64 = conn.query_row("SELECT COUNT(*) FROM providers", [], |row| row.get(0))?;
+        let providers = query_providers(&conn, &page.sql_tail(&["name"]))?;
+        Ok((providers, total.max(0) as u64))
     }
 
     pub async fn set_provider_enabled(&self, name: &str, enabled: bool) -> Result<bool> {
@@ -258,6 +232,46 @@ impl DatabaseLogger {
     }
 }
 
+/// clause 为 FROM providers 之后的排序/分页片段（由调用方固定拼接）
+fn query_providers(conn: &rusqlite::Connection, clause: &str) -> Result<Vec<Provider>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT name, display_name, collection, api_type, base_url, models_endpoint, provider_config, enabled, created_at, updated_at FROM providers {}",
+        clause
+    ))?;
+    let rows = stmt.query_map([], |row| {
+        let name: String = row.get(0)?;
+        let display_name: Option<String> = row.get(1)?;
+        let collection: String = row.get(2)?;
+        let api_type: String = row.get(3)?;
+        let base_url: String = row.get(4)?;
+        let models_endpoint: Option<String> = row.get(5)?;
+        let provider_config_raw: Option<String> = row.get(6)?;
+        let enabled: i64 = row.get(7)?;
+        let created_at_raw: Option<String> = row.get(8)?;
+        let updated_at_raw: Option<String> = row.get(9)?;
+        let (api_type, api_type_raw) = ProviderType::from_storage_with_raw(&api_type);
+        Ok(Provider {
+            name,
+            display_name,
+            collection,
+            api_type,
+            api_type_raw,
+            base_url,
+            api_keys: Vec::new(),
+            models_endpoint,
+            provider_config: ProviderConfig::from_storage_json(provider_config_raw),
+            enabled: enabled != 0,
+            created_at: created_at_raw.and_then(|s| parse_datetime_string(&s).ok()),
+            updated_at: updated_at_raw.and_then(|s| parse_datetime_string(&s).ok()),
+        })
+    })?;
+    let mut out = Vec::new();
+    for r in rows {
+        out.push(r?);
+    }
+    Ok(out)
+}
+
 fn provider_type_to_str(t: &ProviderType) -> &'static str {
     t.as_str()
 }
@@ -308,4 +322,50 @@ mod tests {
         assert_eq!(created2, created1);
         assert!(updated2 >= updated1);
     }
+
+    #[tokio::test]
+    async fn providers_page_sorts_and_counts_in_sql() {
+        use crate::server::pagination::{PageQuery, SortFields, SortOrder};
+
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("gateway.db");
+        let logger = DatabaseLogger::new(db_path.to_str().unwrap())
+            .await
+            .unwrap();
+        for (name, collection) in [("a", "x"), ("b", "y"), ("c", "x")] {
+            let p = Provider {
+                name: name.into(),
+                display_name: None,
+                collection: collection.into(),
+                api_type: ProviderType::OpenAI,
+                api_type_raw: None,
+                base_url: "http://example.com".into(),
+                api_keys: vec![],
+                models_endpoint: None,
+                provider_config: ProviderConfig::default(),
+                enabled: true,
+                created_at: None,
+                updated_at: None,
+            };
+            assert!(logger.insert_provider(&p).await.unwrap());
+        }
+        let fields = SortFields {
+            allowed: &["name", "collection"],
+            default: "name",
+            default_order: SortOrder::Asc,
+        };
+        let page = PageQuery {
+            limit: Some(2),
+            sort: Some("collection".into()),
+            order: Some(SortOrder::Desc),
+            ..Default::default()
+        }
+        .resolve(&fields)
+        .unwrap()
+        .unwrap();
+        let (providers, total) = logger.list_providers_page(&page).await.unwrap();
+        assert_eq!(total, 3);
+        let names: Vec<_> = providers.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["b", "a"]);
+    }
 }
@@ -0,0 +1,37 @@
+use rusqlite::OptionalExtension;
+
+use crate::server::storage_traits::{BoxFuture, SettingsStore};
+
+use super::database::DatabaseLogger;
+use super::time::to_beijing_string;
+
+impl SettingsStore for DatabaseLogger {
+    fn get_setting<'a>(&'a self, key: &'a str) -> BoxFuture<'a, rusqlite::Result<Option<String>>> {
+        Box::pin(async move {
+            let conn = self.connection.lock().await;
+            conn.query_row(
+                "SELECT value FROM gateway_settings WHERE key = ?1",
+                [key],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()
+        })
+    }
+
+    fn set_setting<'a>(
+        &'a self,
+        key: &'a str,
+        value: &'a str,
+    ) -> BoxFuture<'a, rusqlite::Result<()>> {
+        Box::pin(async move {
+            let conn = self.connection.lock().await;
+            conn.execute(
+                "INSERT INTO gateway_settings (key, value, updated_at)
+                 VALUES (?1, ?2, ?3)
+                 ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at",
+                rusqlite::params![key, value, to_beijing_string(&chrono::Utc::now())],
+            )?;
+            Ok(())
+        })
+    }
+}
@@ -546,6 +546,45 @@ impl UserStore for DatabaseLogger {
             .unwrap_or(0.0);
         Ok(Some(balance))
     }
+
+    async fn get_budget(&self, user_id: &str) -> Result<Option<f64>, GatewayError> {
+        let conn = self.connection.lock().await;
+        let budget = conn.query_row(
+            "SELECT budget_amount FROM users WHERE id = ?1",
+            [user_id],
+            |row| row.get::<_, Option<f64>>(0),
+        );
+        match budget.optional() {
+            Ok(budget) => Ok(budget.flatten()),
+            Err(e) if is_missing_column_error(&e) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn set_budget(
+        &self,
+        user_id: &str,
+        max_amount: Option<f64>,
+    ) -> Result<bool, GatewayError> {
+        let now_s = to_beijing_string(&Utc::now());
+        let conn = self.connection.lock().await;
+        let update = conn.execute(
+            "UPDATE users SET budget_amount = ?2, updated_at = ?3 WHERE id = ?1",
+            rusqlite::params![user_id, max_amount, &now_s],
+        );
+        let updated = match update {
+            Ok(n) => n,
+            Err(e) if is_missing_column_error(&e) => {
+                let _ = conn.execute("ALTER TABLE users ADD COLUMN budget_amount REAL", []);
+                conn.execute(
+                    "UPDATE users SET budget_amount = ?2, updated_at = ?3 WHERE id = ?1",
+                    rusqlite::params![user_id, max_amount, &now_s],
+                )?
+            }
+            Err(e) => return Err(e.into()),
+        };
+        Ok(updated > 0)
+    }
 }
 
 #[cfg(test)]
@@ -668,6 +707,13 @@ mod tests {
 
         let fetched = db.get_user(&user.id).await.unwrap().unwrap();
         assert!((fetched.balance - 8.25).abs() < 1e-9);
+
+        assert_eq!(db.get_budget(&user.id).await.unwrap(), None);
+        assert!(db.set_budget(&user.id, Some(20.0)).await.unwrap());
+        assert_eq!(db.get_budget(&user.id).await.unwrap(), Some(20.0));
+        assert!(db.set_budget(&user.id, None).await.unwrap());
+        assert_eq!(db.get_budget(&user.id).await.unwrap(), None);
+        assert!(!db.set_budget("missing", Some(1.0)).await.unwrap());
     }
 
     #[tokio::test]
@@ -0,0 +1,182 @@
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use std::sync::{Mutex, OnceLock};
+
+use tracing::level_filters::LevelFilter;
+use tracing_subscriber::{EnvFilter, Registry, reload};
+
+use crate::error::GatewayError;
+
+pub type FilterReloadHandle = reload::Handle<EnvFilter, Registry>;
+
+const MODULE_LEVELS_MAX: usize = 64;
+const MODULE_PATH_MAX_LEN: usize = 128;
+
+static RELOAD_HANDLE: OnceLock<FilterReloadHandle> = OnceLock::new();
+
+/// 基础过滤指令（RUST_LOG 或运行期设置）与按模块覆盖的级别；实际生效的过滤器由两者合成
+#[derive(Default)]
+struct FilterState {
+    base: String,
+    modules: BTreeMap<String, String>,
+}
+
+fn state() -> &'static Mutex<FilterState> {
+    static STATE: OnceLock<Mutex<FilterState>> = OnceLock::new();
+    STATE.get_or_init(Default::default)
+}
+
+/// 启动时注册可热更新的日志过滤器句柄
+pub fn install(handle: FilterReloadHandle) {
+    if let Ok(base) = handle.with_current(|filter| filter.to_string()) {
+        state().lock().unwrap_or_else(|e| e.into_inner()).base = base;
+    }
+    let _ = RELOAD_HANDLE.set(handle);
+}
+
+/// 校验 EnvFilter 指令（例如 `info` 或 `gateway=debug,tower_http=warn`）
+pub fn validate_directives(directives: &str) -> Result<(), GatewayError> {
+    let trimmed = directives.trim();
+    if trimmed.is_empty() {
+        return Err(GatewayError::Config("log_level 不能为空".into()));
+    }
+    EnvFilter::try_new(trimmed)
+        .map(|_| ())
+        .map_err(|e| GatewayError::Config(format!("invalid log level directives: {}", e)))
+}
+
+/// 校验并规范化模块级别映射：级别统一为小写，模块路径需为 tracing target 形式（如 `server::streaming`）
+pub fn normalize_module_levels(
+    levels: BTreeMap<String, String>,
+) -> Result<BTreeMap<String, String>, GatewayError> {
+    if levels.len() > MODULE_LEVELS_MAX {
+        return Err(GatewayError::Config(format!(
+            "最多只能覆盖 {} 个模块的日志级别",
+            MODULE_LEVELS_MAX
+        )));
+    }
+    let mut out = BTreeMap::new();
+    for (module, level) in levels {
+        let module = module.trim().to_string();
+        if module.is_empty()
+            || module.len() > MODULE_PATH_MAX_LEN
+            || !module
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | ':' | '-'))
+        {
+            return Err(GatewayError::Config(format!(
+                "invalid module path: {:?}",
+                module
+            )));
+        }
+        let level = level.trim().to_ascii_lowercase();
+        if LevelFilter::from_str(&level).is_err() {
+            return Err(GatewayError::Config(format!(
+                "invalid level {:?} for module {} (expected trace/debug/info/warn/error/off)",
+                level, module
+            )));
+        }
+        out.insert(module, level);
+    }
+    Ok(out)
+}
+
+/// 合成过滤指令：基础指令在前，模块覆盖在后。
+/// 未带 crate 前缀的模块路径同时匹配网关自身（`gateway::<path>`），便于直接填写 `server::streaming`
+fn compose(base: &str, modules: &BTreeMap<String, String>) -> String {
+    let crate_prefix = concat!(env!("CARGO_CRATE_NAME"), "::");
+    let mut parts: Vec<String> = Vec::new();
+    if !base.trim().is_empty() {
+        parts.push(base.trim().to_string());
+    }
+    for (module, level) in modules {
+        parts.push(format!("{}={}", module, level));
+        if !module.starts_with(crate_prefix) && module != env!("CARGO_CRATE_NAME") {
+            parts.push(format!("{}{}={}", crate_prefix, module, level));
+        }
+    }
+    parts.join(",")
+}
+
+fn reload(state: &FilterState) -> Result<(), GatewayError> {
+    let directives = compose(&state.base, &state.modules);
+    EnvFilter::try_new(&directives)
+        .map_err(|e| GatewayError::Config(format!("invalid log level directives: {}", e)))?;
+    if let Some(handle) = RELOAD_HANDLE.get() {
+        handle
+            .reload(EnvFilter::new(directives))
+            .map_err(|e| GatewayError::Config(format!("failed to reload log filter: {}", e)))?;
+    }
+    Ok(())
+}
+
+/// 运行期替换全局日志过滤器（保留按模块覆盖的级别）；未注册句柄（例如单元测试）时仅做校验
+pub fn set_directives(directives: &str) -> Result<(), GatewayError> {
+    validate_directives(directives)?;
+    let mut state = state().lock().unwrap_or_else(|e| e.into_inner());
+    let previous = std::mem::replace(&mut state.base, directives.trim().to_string());
+    if let Err(e) = reload(&state) {
+        state.base = previous;
+        return Err(e);
+    }
+    Ok(())
+}
+
+/// 整体替换按模块覆盖的日志级别（空映射表示清除全部覆盖），立即生效且不持久化
+pub fn set_module_levels(levels: BTreeMap<String, String>) -> Result<(), GatewayError> {
+    let levels = normalize_module_levels(levels)?;
+    let mut state = state().lock().unwrap_or_else(|e| e.into_inner());
+    let previous = std::mem::replace(&mut state.modules, levels);
+    if let Err(e) = reload(&state) {
+        state.modules = previous;
+        return Err(e);
+    }
+    Ok(())
+}
+
+/// 当前按模块覆盖的日志级别
+pub fn module_levels() -> BTreeMap<String, String> {
+    state()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .modules
+        .clone()
+}
+
+/// 当前生效的过滤器指令
+pub fn current_directives() -> Option<String> {
+    RELOAD_HANDLE
+        .get()
+        .and_then(|handle| handle.with_current(|filter| filter.to_string()).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn module_levels_are_validated_and_composed_onto_base() {
+        let levels = normalize_module_levels(BTreeMap::from([
+            ("server::streaming".to_string(), " DEBUG ".to_string()),
+            ("tower_http".to_string(), "warn".to_string()),
+        ]))
+        .unwrap();
+        assert_eq!(levels["server::streaming"], "debug");
+        assert_eq!(
+            compose("info", &levels),
+            "info,server::streaming=debug,gateway::server::streaming=debug,\
+             tower_http=warn,gateway::tower_http=warn"
+        );
+        assert!(EnvFilter::try_new(compose("info", &levels)).is_ok());
+        assert_eq!(compose("", &BTreeMap::new()), "");
+
+        for (module, level) in [
+            ("server::streaming", "verbose"),
+            ("a=b", "info"),
+            ("", "info"),
+        ] {
+            let bad = BTreeMap::from([(module.to_string(), level.to_string())]);
+            assert!(normalize_module_levels(bad).is_err());
+        }
+    }
+}
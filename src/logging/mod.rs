@@ -12,14 +12,18 @@ pub mod database_pricing;
 pub mod database_provider_ops;
 pub mod database_providers;
 pub mod database_refresh_tokens;
+pub mod database_settings;
 pub mod database_subscription;
 pub mod database_users;
+pub mod level;
+pub mod payload_archive;
 pub mod postgres_balance;
 pub mod postgres_password_reset_tokens;
 pub mod postgres_refresh_tokens;
 pub mod postgres_store;
 pub mod postgres_subscription;
 pub mod postgres_users;
+pub mod ring_buffer;
 pub mod time;
 pub mod types;
 
@@ -0,0 +1,173 @@
+//! 捕获的请求/响应正文以 zstd 压缩后单独存入 request_log_payloads，
+//! 日志列表不读取该表，只有在读取单条日志详情时才解压。
+
+use std::io::Read;
+
+/// request_log_payloads.encoding 的取值
+pub const ENCODING_ZSTD: &str = "zstd";
+/// 压缩级别：日志写入在请求路径上，取速度与压缩率的折中
+const COMPRESSION_LEVEL: i32 = 3;
+/// 解压上限，防止损坏或恶意数据解压出超大内容
+const MAX_DECOMPRESSED_BYTES: u64 = 64 * 1024 * 1024;
+
+/// 待写入 request_log_payloads 的压缩正文
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArchivedPayload {
+    pub request_body: Option<Vec<u8>>,
+    pub response_body: Option<Vec<u8>>,
+    /// 压缩前的总字节数
+    pub original_bytes: i64,
+    /// 压缩后的总字节数
+    pub stored_bytes: i64,
+}
+
+impl ArchivedPayload {
+    /// 两个正文都为空时返回 None（不写入压缩表）
+    pub fn pack(request_body: Option<&str>, response_body: Option<&str>) -> Option<Self> {
+        if request_body.is_none() && response_body.is_none() {
+            return None;
+        }
+        let request = request_body.map(compress);
+        let response = response_body.map(compress);
+        let original_bytes = [request_body, response_body]
+            .iter()
+            .flatten()
+            .map(|s| s.len() as i64)
+            .sum();
+        let stored_bytes = [&request, &response]
+            .iter()
+            .filter_map(|b| b.as_ref())
+            .map(|b| b.len() as i64)
+            .sum();
+        Some(Self {
+            request_body: request,
+            response_body: response,
+            original_bytes,
+            stored_bytes,
+        })
+    }
+}
+
+pub fn compress(text: &str) -> Vec<u8> {
+    zstd::bulk::compress(text.as_bytes(), COMPRESSION_LEVEL)
+        .expect("zstd compression of in-memory buffer")
+}
+
+/// 解压失败（数据损坏或非 UTF-8）时返回 None，调用方按正文缺失处理
+pub fn decompress(data: &[u8]) -> Option<String> {
+    let mut out = Vec::new();
+    let decoded = zstd::stream::read::Decoder::new(data)
+        .and_then(|d| d.take(MAX_DECOMPRESSED_BYTES).read_to_end(&mut out));
+    if let Err(e) = decoded {
+        tracing::warn!("Failed to decompress archived request log payload: {}", e);
+        return None;
+    }
+    String::from_utf8(out).ok()
+}
+
+/// 超过 max_bytes 的正文不归档（0 表示不限制）
+pub fn cap_captured_body(body: Option<String>, max_bytes: usize) -> Option<String> {
+    match body {
+        Some(b) if max_bytes > 0 && b.len() > max_bytes => {
+            tracing::debug!(
+                size = b.len(),
+                max_bytes,
+                "Captured body exceeds capture_body_max_bytes, skipping archival"
+            );
+            None
+        }
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn payload_round_trips_and_shrinks_repetitive_bodies() {
+        let prompt = "{\"messages\":[".to_string() + &"{\"role\":\"user\"},".repeat(500) + "]}";
+        let packed = ArchivedPayload::pack(Some(&prompt), None).unwrap();
+        assert_eq!(packed.original_bytes, prompt.len() as i64);
+        assert!(packed.stored_bytes < packed.original_bytes / 10);
+        assert!(packed.response_body.is_none());
+        assert_eq!(
+            decompress(packed.request_body.as_deref().unwrap()).as_deref(),
+            Some(prompt.as_str())
+        );
+        assert!(ArchivedPayload::pack(None, None).is_none());
+        assert!(decompress(b"not zstd").is_none());
+    }
+
+    #[tokio::test]
+    async fn sqlite_detail_bodies_are_stored_compressed_and_read_back() {
+        use crate::logging::DatabaseLogger;
+        use crate::logging::types::RequestLogDetailRecord;
+
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("payloads.db");
+        let db = DatabaseLogger::new(db_path.to_str().unwrap())
+            .await
+            .unwrap();
+        let log_id = {
+            let conn = db.connection.lock().await;
+            conn.execute(
+                "INSERT INTO request_logs (timestamp, method, path, status_code, response_time_ms)
+                 VALUES ('2026-01-01 00:00:00', 'POST', '/v1/chat/completions', 200, 5)",
+                [],
+            )
+            .unwrap();
+            conn.last_insert_rowid()
+        };
+        let snapshot = format!("{{\"prompt\":\"{}\"}}", "hello ".repeat(2000));
+        db.upsert_request_log_detail(RequestLogDetailRecord {
+            request_log_id: log_id,
+            request_payload_snapshot: Some(snapshot.clone()),
+            response_preview: Some("hi".into()),
+            upstream_status: Some(200),
+            fallback_triggered: None,
+            fallback_reason: None,
+            selected_provider: None,
+            selected_key_id: None,
+            first_token_latency_ms: None,
+            param_policy_applied: None,
+            provider_override: None,
+            prompt_truncation: None,
+        })
+        .await
+        .unwrap();
+
+        {
+            let conn = db.connection.lock().await;
+            let (plain, encoding, stored): (Option<String>, String, i64) = conn
+                .query_row(
+                    "SELECT d.request_payload_snapshot, p.encoding, p.stored_bytes
+                     FROM request_log_details d JOIN request_log_payloads p
+                       ON p.request_log_id = d.request_log_id",
+                    [],
+                    |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)),
+                )
+                .unwrap();
+            assert!(plain.is_none());
+            assert_eq!(encoding, ENCODING_ZSTD);
+            assert!((stored as usize) < snapshot.len() / 10);
+        }
+
+        let detail = db.get_request_log_detail(log_id).await.unwrap().unwrap();
+        assert_eq!(detail.request_payload_snapshot, Some(snapshot));
+        assert_eq!(detail.response_preview.as_deref(), Some("hi"));
+    }
+
+    #[test]
+    fn oversized_bodies_are_not_captured() {
+        assert_eq!(cap_captured_body(Some("abcd".into()), 3), None);
+        assert_eq!(
+            cap_captured_body(Some("abc".into()), 3).as_deref(),
+            Some("abc")
+        );
+        assert_eq!(
+            cap_captured_body(Some("abcd".into()), 0).as_deref(),
+            Some("abcd")
+        );
+    }
+}
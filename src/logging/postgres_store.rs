@@ -1,3 +1,4 @@
+use std::collections::BTreeMap;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicUsize, Ordering};
 
@@ -6,21 +7,29 @@ use tokio_postgres::{Client, NoTls, Row};
 
 use crate::config::settings::{KeyLogStrategy, Provider, ProviderConfig, ProviderType};
 use crate::error::GatewayError;
+use crate::logging::payload_archive::{self, ArchivedPayload, ENCODING_ZSTD};
 use crate::logging::time::{parse_datetime_string, to_beijing_string, to_iso8601_utc_string};
 use crate::logging::types::{
-    ProviderOpLog, RequestLogDetailRecord, StoredCompareRun, StoredRequestLabSnapshot,
-    StoredRequestLabSource, StoredRequestLabTemplate,
+    AdminNotificationRecord, AudioPriceRecord, DebugCaptureRecord, ImagePriceRecord,
+    LatencyBreakdown, LogColumns, MaintenanceWindowRecord, MetricsReportRecord, ParamPolicyRecord,
+    PromptProfile, ProviderBudgetRecord, ProviderEgressDaily, ProviderOpLog, RequestHeatmapCell,
+    RequestLogDetailRecord, SpeechPriceRecord, StatementRecord, StoredCompareRun,
+    StoredIdempotentResponse, StoredRequestLabSnapshot, StoredRequestLabSource,
+    StoredRequestLabTemplate, StreamCaptureRecord, TokenActivity, TokenActivityDay,
+    TokenModelUsage, TokenRecentError, TokenRequestCount, TokenUsageDaily, TokenUsageDelta,
+    UsageWebhookDeadLetter,
 };
 use crate::logging::{
     CachedModel, ModelPriceRecord, ModelPriceSource, ModelPriceStatus, ModelPriceUpsert,
     ProviderKeyStatsAgg, RequestLog,
 };
 use crate::providers::openai::Model;
-use crate::routing::{KeyRotationStrategy, ProviderKeyEntry};
+use crate::routing::{KeyRotationStrategy, OpenAIAccountHeaders, ProviderKeyEntry};
+use crate::server::pagination::PageRequest;
 use crate::server::storage_traits::{
-    AdminPublicKeyRecord, BoxFuture, FavoriteKind, FavoritesStore, LoginCodeRecord, LoginStore,
-    ModelCache, OrganizationStore, ProviderKeyEntryWithCreatedAt, ProviderStore, RequestLogStore,
-    TuiSessionRecord, WebSessionRecord,
+    AdminMfaRecord, AdminPublicKeyRecord, BoxFuture, FavoriteKind, FavoritesStore, LoginCodeRecord,
+    LoginStore, ModelCache, OrganizationStore, ProviderKeyEntryWithCreatedAt, ProviderStore,
+    RequestLogStore, SettingsStore, TuiSessionRecord, UserLoginCodeRecord, WebSessionRecord,
 };
 
 fn pg_err<E: std::fmt::Display>(e: E) -> rusqlite::Error {
@@ -147,6 +156,123 @@ fn pg_row_opt_datetime(row: &Row, idx: usize) -> Option<DateTime<Utc>> {
         .and_then(|raw| parse_datetime_string(&raw).ok())
 }
 
+fn pg_usage_webhook_dead_letter(row: &Row) -> UsageWebhookDeadLetter {
+    UsageWebhookDeadLetter {
+        id: pg_row_i64_or(row, 0, 0),
+        token_id: pg_row_string(row, 1),
+        url: pg_row_string(row, 2),
+        payload_json: pg_row_string(row, 3),
+        attempts: pg_row_i64_or(row, 4, 0),
+        last_error: pg_row_opt_string(row, 5),
+        created_at: pg_row_datetime_or_now(row, 6),
+    }
+}
+
+fn pg_maintenance_window(row: &Row) -> MaintenanceWindowRecord {
+    MaintenanceWindowRecord {
+        id: pg_row_i64_or(row, 0, 0),
+        provider: pg_row_string(row, 1),
+        starts_at: pg_row_datetime_or_now(row, 2),
+        ends_at: pg_row_datetime_or_now(row, 3),
+        message: pg_row_opt_string(row, 4),
+        created_by: pg_row_opt_string(row, 5),
+        created_at: pg_row_datetime_or_now(row, 6),
+    }
+}
+
+fn pg_admin_notification(row: &Row) -> AdminNotificationRecord {
+    AdminNotificationRecord {
+        id: pg_row_i64_or(row, 0, 0),
+        kind: pg_row_string(row, 1),
+        severity: pg_row_string(row, 2),
+        title: pg_row_string(row, 3),
+        message: pg_row_string(row, 4),
+        reference: pg_row_opt_string(row, 5),
+        created_at: pg_row_datetime_or_now(row, 6),
+        read_at: pg_row_opt_datetime(row, 7),
+    }
+}
+
+fn pg_param_policy(row: &Row) -> ParamPolicyRecord {
+    ParamPolicyRecord {
+        id: pg_row_string(row, 0),
+        model: pg_row_opt_string(row, 1),
+        token_id: pg_row_opt_string(row, 2),
+        rules_json: pg_row_string(row, 3),
+        enabled: row.try_get::<usize, bool>(4).unwrap_or(true),
+        created_at: pg_row_datetime_or_now(row, 5),
+        updated_at: pg_row_datetime_or_now(row, 6),
+    }
+}
+
+fn pg_metrics_report(row: &Row) -> MetricsReportRecord {
+    MetricsReportRecord {
+        id: pg_row_string(row, 0),
+        name: pg_row_string(row, 1),
+        period: pg_row_string(row, 2),
+        channel: pg_row_string(row, 3),
+        target: pg_row_string(row, 4),
+        template: pg_row_string(row, 5),
+        enabled: pg_row_bool_or(row, 6, true),
+        last_sent_at: pg_row_opt_datetime(row, 7),
+        created_at: pg_row_datetime_or_now(row, 8),
+        updated_at: pg_row_datetime_or_now(row, 9),
+    }
+}
+
+fn pg_statement(row: &Row) -> StatementRecord {
+    StatementRecord {
+        id: pg_row_string(row, 0),
+        scope: pg_row_string(row, 1),
+        subject_id: pg_row_string(row, 2),
+        period: pg_row_string(row, 3),
+        body: pg_row_string(row, 4),
+        created_at: pg_row_datetime_or_now(row, 5),
+    }
+}
+
+fn pg_provider_budget(row: &Row) -> ProviderBudgetRecord {
+    ProviderBudgetRecord {
+        provider: pg_row_string(row, 0),
+        monthly_budget: pg_row_f64_or(row, 1, 0.0),
+        warn_thresholds: serde_json::from_str(&pg_row_string(row, 2)).unwrap_or_default(),
+        stop_routing_when_exhausted: pg_row_bool_or(row, 3, true),
+        updated_at: pg_row_datetime_or_now(row, 4),
+    }
+}
+
+fn pg_image_price(row: &Row) -> ImagePriceRecord {
+    ImagePriceRecord {
+        provider: pg_row_string(row, 0),
+        model: pg_row_string(row, 1),
+        size: pg_row_string(row, 2),
+        quality: pg_row_string(row, 3),
+        price_per_image: pg_row_f64_or(row, 4, 0.0),
+        currency: pg_row_opt_string(row, 5),
+        updated_at: pg_row_datetime_or_now(row, 6),
+    }
+}
+
+fn pg_audio_price(row: &Row) -> AudioPriceRecord {
+    AudioPriceRecord {
+        provider: pg_row_string(row, 0),
+        model: pg_row_string(row, 1),
+        price_per_minute: pg_row_f64_or(row, 2, 0.0),
+        currency: pg_row_opt_string(row, 3),
+        updated_at: pg_row_datetime_or_now(row, 4),
+    }
+}
+
+fn pg_speech_price(row: &Row) -> SpeechPriceRecord {
+    SpeechPriceRecord {
+        provider: pg_row_string(row, 0),
+        model: pg_row_string(row, 1),
+        price_per_million_chars: pg_row_f64_or(row, 2, 0.0),
+        currency: pg_row_opt_string(row, 3),
+        updated_at: pg_row_datetime_or_now(row, 4),
+    }
+}
+
 fn pg_row_bytes(row: &Row, idx: usize) -> Vec<u8> {
     row.try_get::<usize, Vec<u8>>(idx).unwrap_or_default()
 }
@@ -237,7 +363,12 @@ impl PgLogStore {
                 error_message TEXT,
                 client_token TEXT,
                 user_id TEXT,
-                amount_spent DOUBLE PRECISION
+                amount_spent DOUBLE PRECISION,
+                pre_dispatch_ms BIGINT,
+                upstream_ms BIGINT,
+                post_process_ms BIGINT,
+                prompt_language TEXT,
+                content_category TEXT
             )"#,
                 &[],
             )
@@ -253,6 +384,28 @@ impl PgLogStore {
         let _ = client
             .execute("ALTER TABLE request_logs ADD COLUMN user_id TEXT", &[])
             .await;
+        for column in ["pre_dispatch_ms", "upstream_ms", "post_process_ms"] {
+            let _ = client
+                .execute(
+                    &format!(
+                        "ALTER TABLE request_logs ADD COLUMN IF NOT EXISTS {} BIGINT",
+                        column
+                    ),
+                    &[],
+                )
+                .await;
+        }
+        for column in ["prompt_language", "content_category"] {
+            let _ = client
+                .execute(
+                    &format!(
+                        "ALTER TABLE request_logs ADD COLUMN IF NOT EXISTS {} TEXT",
+                        column
+                    ),
+                    &[],
+                )
+                .await;
+        }
         let _ = client
             .execute(
                 "ALTER TABLE request_logs ADD COLUMN requested_model TEXT",
@@ -276,7 +429,10 @@ impl PgLogStore {
                 fallback_reason TEXT,
                 selected_provider TEXT,
                 selected_key_id TEXT,
-                first_token_latency_ms BIGINT
+                first_token_latency_ms BIGINT,
+                param_policy_applied TEXT,
+                provider_override TEXT,
+                prompt_truncation TEXT
             )"#,
                 &[],
             )
@@ -284,6 +440,40 @@ impl PgLogStore {
             .map_err(|e| {
                 GatewayError::Config(format!("Failed to init request_log_details: {}", e))
             })?;
+        let _ = client
+            .execute(
+                "ALTER TABLE request_log_details ADD COLUMN IF NOT EXISTS param_policy_applied TEXT",
+                &[],
+            )
+            .await;
+        let _ = client
+            .execute(
+                "ALTER TABLE request_log_details ADD COLUMN IF NOT EXISTS provider_override TEXT",
+                &[],
+            )
+            .await;
+        let _ = client
+            .execute(
+                "ALTER TABLE request_log_details ADD COLUMN IF NOT EXISTS prompt_truncation TEXT",
+                &[],
+            )
+            .await;
+        client
+            .execute(
+                r#"CREATE TABLE IF NOT EXISTS request_log_payloads (
+                request_log_id BIGINT PRIMARY KEY REFERENCES request_logs(id) ON DELETE CASCADE,
+                encoding TEXT NOT NULL,
+                request_body BYTEA,
+                response_body BYTEA,
+                original_bytes BIGINT NOT NULL DEFAULT 0,
+                stored_bytes BIGINT NOT NULL DEFAULT 0
+            )"#,
+                &[],
+            )
+            .await
+            .map_err(|e| {
+                GatewayError::Config(format!("Failed to init request_log_payloads: {}", e))
+            })?;
         client
             .execute(
                 r#"CREATE TABLE IF NOT EXISTS compare_runs (
@@ -297,6 +487,248 @@ impl PgLogStore {
             )
             .await
             .map_err(|e| GatewayError::Config(format!("Failed to init compare_runs: {}", e)))?;
+        client
+            .execute(
+                r#"CREATE TABLE IF NOT EXISTS request_log_debug_captures (
+                request_log_id BIGINT PRIMARY KEY REFERENCES request_logs(id) ON DELETE CASCADE,
+                request_body BYTEA,
+                response_body BYTEA,
+                created_at TEXT NOT NULL,
+                expires_at TEXT NOT NULL
+            )"#,
+                &[],
+            )
+            .await
+            .map_err(|e| {
+                GatewayError::Config(format!("Failed to init request_log_debug_captures: {}", e))
+            })?;
+        client
+            .execute(
+                r#"CREATE TABLE IF NOT EXISTS request_log_stream_chunks (
+                request_log_id BIGINT PRIMARY KEY REFERENCES request_logs(id) ON DELETE CASCADE,
+                chunks BYTEA NOT NULL,
+                chunk_count BIGINT NOT NULL DEFAULT 0,
+                truncated BOOLEAN NOT NULL DEFAULT FALSE
+            )"#,
+                &[],
+            )
+            .await
+            .map_err(|e| {
+                GatewayError::Config(format!("Failed to init request_log_stream_chunks: {}", e))
+            })?;
+        client
+            .execute(
+                r#"CREATE TABLE IF NOT EXISTS idempotency_keys (
+                token_id TEXT NOT NULL,
+                idempotency_key TEXT NOT NULL,
+                request_fingerprint TEXT NOT NULL,
+                response_json TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                expires_at TEXT NOT NULL,
+                PRIMARY KEY (token_id, idempotency_key)
+            )"#,
+                &[],
+            )
+            .await
+            .map_err(|e| GatewayError::Config(format!("Failed to init idempotency_keys: {}", e)))?;
+        client
+            .execute(
+                r#"CREATE TABLE IF NOT EXISTS usage_webhook_dead_letters (
+                id BIGSERIAL PRIMARY KEY,
+                token_id TEXT NOT NULL,
+                url TEXT NOT NULL,
+                payload_json TEXT NOT NULL,
+                attempts BIGINT NOT NULL,
+                last_error TEXT,
+                created_at TEXT NOT NULL
+            )"#,
+                &[],
+            )
+            .await
+            .map_err(|e| {
+                GatewayError::Config(format!("Failed to init usage_webhook_dead_letters: {}", e))
+            })?;
+        client
+            .execute(
+                r#"CREATE TABLE IF NOT EXISTS admin_notifications (
+                id BIGSERIAL PRIMARY KEY,
+                kind TEXT NOT NULL,
+                severity TEXT NOT NULL,
+                title TEXT NOT NULL,
+                message TEXT NOT NULL,
+                reference TEXT,
+                created_at TEXT NOT NULL,
+                read_at TEXT
+            )"#,
+                &[],
+            )
+            .await
+            .map_err(|e| {
+                GatewayError::Config(format!("Failed to init admin_notifications: {}", e))
+            })?;
+        client
+            .execute(
+                r#"CREATE TABLE IF NOT EXISTS maintenance_windows (
+                id BIGSERIAL PRIMARY KEY,
+                provider TEXT NOT NULL,
+                starts_at TEXT NOT NULL,
+                ends_at TEXT NOT NULL,
+                message TEXT,
+                created_by TEXT,
+                created_at TEXT NOT NULL
+            )"#,
+                &[],
+            )
+            .await
+            .map_err(|e| {
+                GatewayError::Config(format!("Failed to init maintenance_windows: {}", e))
+            })?;
+        client
+            .execute(
+                r#"CREATE TABLE IF NOT EXISTS param_policies (
+                id TEXT PRIMARY KEY,
+                model TEXT,
+                token_id TEXT,
+                rules_json TEXT NOT NULL,
+                enabled BOOLEAN NOT NULL DEFAULT TRUE,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            )"#,
+                &[],
+            )
+            .await
+            .map_err(|e| GatewayError::Config(format!("Failed to init param_policies: {}", e)))?;
+        client
+            .execute(
+                r#"CREATE TABLE IF NOT EXISTS metrics_reports (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                period TEXT NOT NULL,
+                channel TEXT NOT NULL,
+                target TEXT NOT NULL,
+                template TEXT NOT NULL DEFAULT '',
+                enabled BOOLEAN NOT NULL DEFAULT TRUE,
+                last_sent_at TEXT,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            )"#,
+                &[],
+            )
+            .await
+            .map_err(|e| GatewayError::Config(format!("Failed to init metrics_reports: {}", e)))?;
+        client
+            .execute(
+                r#"CREATE TABLE IF NOT EXISTS billing_statements (
+                id TEXT PRIMARY KEY,
+                scope TEXT NOT NULL,
+                subject_id TEXT NOT NULL,
+                period TEXT NOT NULL,
+                body TEXT NOT NULL,
+                created_at TEXT NOT NULL
+            )"#,
+                &[],
+            )
+            .await
+            .map_err(|e| {
+                GatewayError::Config(format!("Failed to init billing_statements: {}", e))
+            })?;
+        client
+            .execute(
+                "CREATE INDEX IF NOT EXISTS billing_statements_subject_idx ON billing_statements (scope, subject_id, period)",
+                &[],
+            )
+            .await
+            .map_err(|e| {
+                GatewayError::Config(format!("Failed to init billing_statements: {}", e))
+            })?;
+        client
+            .execute(
+                r#"CREATE TABLE IF NOT EXISTS provider_budgets (
+                provider TEXT PRIMARY KEY,
+                monthly_budget DOUBLE PRECISION NOT NULL,
+                warn_thresholds TEXT NOT NULL,
+                stop_routing_when_exhausted BOOLEAN NOT NULL DEFAULT TRUE,
+                updated_at TEXT NOT NULL
+            )"#,
+                &[],
+            )
+            .await
+            .map_err(|e| GatewayError::Config(format!("Failed to init provider_budgets: {}", e)))?;
+        client
+            .execute(
+                r#"CREATE TABLE IF NOT EXISTS image_prices (
+                provider TEXT NOT NULL,
+                model TEXT NOT NULL,
+                size TEXT NOT NULL DEFAULT '*',
+                quality TEXT NOT NULL DEFAULT '*',
+                price_per_image DOUBLE PRECISION NOT NULL,
+                currency TEXT,
+                updated_at TEXT NOT NULL,
+                PRIMARY KEY (provider, model, size, quality)
+            )"#,
+                &[],
+            )
+            .await
+            .map_err(|e| GatewayError::Config(format!("Failed to init image_prices: {}", e)))?;
+        client
+            .execute(
+                r#"CREATE TABLE IF NOT EXISTS audio_prices (
+                provider TEXT NOT NULL,
+                model TEXT NOT NULL,
+                price_per_minute DOUBLE PRECISION NOT NULL,
+                currency TEXT,
+                updated_at TEXT NOT NULL,
+                PRIMARY KEY (provider, model)
+            )"#,
+                &[],
+            )
+            .await
+            .map_err(|e| GatewayError::Config(format!("Failed to init audio_prices: {}", e)))?;
+        client
+            .execute(
+                r#"CREATE TABLE IF NOT EXISTS speech_prices (
+                provider TEXT NOT NULL,
+                model TEXT NOT NULL,
+                price_per_million_chars DOUBLE PRECISION NOT NULL,
+                currency TEXT,
+                updated_at TEXT NOT NULL,
+                PRIMARY KEY (provider, model)
+            )"#,
+                &[],
+            )
+            .await
+            .map_err(|e| GatewayError::Config(format!("Failed to init speech_prices: {}", e)))?;
+        client
+            .execute(
+                r#"CREATE TABLE IF NOT EXISTS provider_egress_daily (
+                day TEXT NOT NULL,
+                provider TEXT NOT NULL,
+                api_key TEXT NOT NULL,
+                requests BIGINT NOT NULL DEFAULT 0,
+                request_bytes BIGINT NOT NULL DEFAULT 0,
+                response_bytes BIGINT NOT NULL DEFAULT 0,
+                PRIMARY KEY (day, provider, api_key)
+            )"#,
+                &[],
+            )
+            .await
+            .map_err(|e| {
+                GatewayError::Config(format!("Failed to init provider_egress_daily: {}", e))
+            })?;
+        client
+            .execute(
+                r#"CREATE TABLE IF NOT EXISTS token_request_counts (
+                day TEXT NOT NULL,
+                token_id TEXT NOT NULL,
+                requests BIGINT NOT NULL DEFAULT 0,
+                PRIMARY KEY (day, token_id)
+            )"#,
+                &[],
+            )
+            .await
+            .map_err(|e| {
+                GatewayError::Config(format!("Failed to init token_request_counts: {}", e))
+            })?;
         client
             .batch_execute(
                 r#"
@@ -517,7 +949,8 @@ impl PgLogStore {
                 timestamp TEXT NOT NULL,
                 operation TEXT NOT NULL,
                 provider TEXT,
-                details TEXT
+                details TEXT,
+                actor TEXT
             )"#,
                 &[],
             )
@@ -525,6 +958,18 @@ impl PgLogStore {
             .map_err(|e| {
                 GatewayError::Config(format!("Failed to init provider_ops_logs: {}", e))
             })?;
+        let _ = client
+            .execute(
+                "ALTER TABLE provider_ops_logs ADD COLUMN IF NOT EXISTS actor TEXT",
+                &[],
+            )
+            .await;
+        let _ = client
+            .execute(
+                "CREATE INDEX IF NOT EXISTS provider_ops_logs_provider_idx ON provider_ops_logs(provider, id)",
+                &[],
+            )
+            .await;
 
         client
             .execute(
@@ -707,6 +1152,19 @@ impl PgLogStore {
             )
             .await
             .map_err(|e| GatewayError::Config(format!("Failed to init organizations: {}", e)))?;
+        client
+            .execute(
+                r#"CREATE TABLE IF NOT EXISTS organization_admins (
+                user_id TEXT PRIMARY KEY,
+                organization_id TEXT NOT NULL,
+                created_at TIMESTAMPTZ NOT NULL
+            )"#,
+                &[],
+            )
+            .await
+            .map_err(|e| {
+                GatewayError::Config(format!("Failed to init organization_admins: {}", e))
+            })?;
         let _ = client
             .execute(
                 "INSERT INTO organizations (name) VALUES ('default') ON CONFLICT (name) DO NOTHING",
@@ -733,6 +1191,12 @@ impl PgLogStore {
                 enc BOOLEAN NOT NULL DEFAULT FALSE,
                 active BOOLEAN NOT NULL DEFAULT TRUE,
                 weight INTEGER NOT NULL DEFAULT 1,
+                spend_cap DOUBLE PRECISION,
+                rpm_limit INTEGER,
+                tpm_limit INTEGER,
+                openai_organization TEXT,
+                openai_project TEXT,
+                labels TEXT,
                 created_at TEXT NOT NULL,
                 PRIMARY KEY (provider, key_value)
             )"#,
@@ -746,37 +1210,73 @@ impl PgLogStore {
                 &[],
             )
             .await;
-
-        // Favorites table (used by admin UI)
-        client
+        let _ = client
             .execute(
-                r#"CREATE TABLE IF NOT EXISTS favorites (
-                kind TEXT NOT NULL,
-                target TEXT NOT NULL,
-                favorite BOOLEAN NOT NULL DEFAULT TRUE,
-                PRIMARY KEY (kind, target)
-            )"#,
+                "ALTER TABLE provider_keys ADD COLUMN IF NOT EXISTS spend_cap DOUBLE PRECISION",
                 &[],
             )
-            .await
-            .map_err(|e| GatewayError::Config(format!("Failed to init favorites: {}", e)))?;
-        // Best-effort migrations for existing deployments
+            .await;
         let _ = client
             .execute(
-                "ALTER TABLE favorites ADD COLUMN favorite BOOLEAN NOT NULL DEFAULT TRUE",
+                "ALTER TABLE provider_keys ADD COLUMN IF NOT EXISTS rpm_limit INTEGER",
                 &[],
             )
             .await;
         let _ = client
             .execute(
-                "CREATE INDEX IF NOT EXISTS favorites_kind_favorite_idx ON favorites(kind, favorite)",
+                "ALTER TABLE provider_keys ADD COLUMN IF NOT EXISTS tpm_limit INTEGER",
                 &[],
             )
             .await;
-
-        client
+        let _ = client
             .execute(
-                r#"CREATE TABLE IF NOT EXISTS model_redirects (
+                "ALTER TABLE provider_keys ADD COLUMN IF NOT EXISTS openai_organization TEXT",
+                &[],
+            )
+            .await;
+        let _ = client
+            .execute(
+                "ALTER TABLE provider_keys ADD COLUMN IF NOT EXISTS openai_project TEXT",
+                &[],
+            )
+            .await;
+        let _ = client
+            .execute(
+                "ALTER TABLE provider_keys ADD COLUMN IF NOT EXISTS labels TEXT",
+                &[],
+            )
+            .await;
+
+        // Favorites table (used by admin UI)
+        client
+            .execute(
+                r#"CREATE TABLE IF NOT EXISTS favorites (
+                kind TEXT NOT NULL,
+                target TEXT NOT NULL,
+                favorite BOOLEAN NOT NULL DEFAULT TRUE,
+                PRIMARY KEY (kind, target)
+            )"#,
+                &[],
+            )
+            .await
+            .map_err(|e| GatewayError::Config(format!("Failed to init favorites: {}", e)))?;
+        // Best-effort migrations for existing deployments
+        let _ = client
+            .execute(
+                "ALTER TABLE favorites ADD COLUMN favorite BOOLEAN NOT NULL DEFAULT TRUE",
+                &[],
+            )
+            .await;
+        let _ = client
+            .execute(
+                "CREATE INDEX IF NOT EXISTS favorites_kind_favorite_idx ON favorites(kind, favorite)",
+                &[],
+            )
+            .await;
+
+        client
+            .execute(
+                r#"CREATE TABLE IF NOT EXISTS model_redirects (
                 provider TEXT NOT NULL,
                 source_model TEXT NOT NULL,
                 target_model TEXT NOT NULL,
@@ -797,7 +1297,8 @@ impl PgLogStore {
                 comment TEXT,
                 enabled BOOLEAN NOT NULL DEFAULT TRUE,
                 created_at TIMESTAMPTZ NOT NULL,
-                last_used_at TIMESTAMPTZ
+                last_used_at TIMESTAMPTZ,
+                expires_at TIMESTAMPTZ
             )"#,
                 &[],
             )
@@ -805,6 +1306,12 @@ impl PgLogStore {
             .map_err(|e| {
                 GatewayError::Config(format!("Failed to init admin_public_keys: {}", e))
             })?;
+        let _ = client
+            .execute(
+                "ALTER TABLE admin_public_keys ADD COLUMN IF NOT EXISTS expires_at TIMESTAMPTZ",
+                &[],
+            )
+            .await;
 
         client.execute(
             r#"CREATE TABLE IF NOT EXISTS tui_sessions (
@@ -836,6 +1343,27 @@ impl PgLogStore {
             .await
             .map_err(|e| GatewayError::Config(format!("Failed to init login_codes: {}", e)))?;
 
+        client
+            .execute(
+                r#"CREATE TABLE IF NOT EXISTS user_login_codes (
+                code_hash TEXT PRIMARY KEY,
+                user_id TEXT NOT NULL,
+                token_id TEXT NOT NULL,
+                created_at TIMESTAMPTZ NOT NULL,
+                expires_at TIMESTAMPTZ NOT NULL,
+                used_at TIMESTAMPTZ
+            )"#,
+                &[],
+            )
+            .await
+            .map_err(|e| GatewayError::Config(format!("Failed to init user_login_codes: {}", e)))?;
+        let _ = client
+            .execute(
+                "CREATE INDEX IF NOT EXISTS user_login_codes_token_idx ON user_login_codes (token_id, created_at)",
+                &[],
+            )
+            .await;
+
         client
             .execute(
                 r#"CREATE TABLE IF NOT EXISTS web_sessions (
@@ -844,12 +1372,40 @@ impl PgLogStore {
                 created_at TIMESTAMPTZ NOT NULL,
                 expires_at TIMESTAMPTZ NOT NULL,
                 revoked BOOLEAN NOT NULL DEFAULT FALSE,
-                issued_by_code TEXT
+                issued_by_code TEXT,
+                last_seen_at TIMESTAMPTZ,
+                user_id TEXT
             )"#,
                 &[],
             )
             .await
             .map_err(|e| GatewayError::Config(format!("Failed to init web_sessions: {}", e)))?;
+        let _ = client
+            .execute(
+                "ALTER TABLE web_sessions ADD COLUMN last_seen_at TIMESTAMPTZ",
+                &[],
+            )
+            .await;
+        let _ = client
+            .execute("ALTER TABLE web_sessions ADD COLUMN user_id TEXT", &[])
+            .await;
+
+        client
+            .execute(
+                r#"CREATE TABLE IF NOT EXISTS admin_mfa (
+                user_id TEXT PRIMARY KEY,
+                totp_secret TEXT,
+                totp_enabled BOOLEAN NOT NULL DEFAULT FALSE,
+                recovery_codes TEXT,
+                last_totp_step BIGINT,
+                failed_attempts INTEGER NOT NULL DEFAULT 0,
+                locked_until TIMESTAMPTZ,
+                updated_at TIMESTAMPTZ NOT NULL
+            )"#,
+                &[],
+            )
+            .await
+            .map_err(|e| GatewayError::Config(format!("Failed to init admin_mfa: {}", e)))?;
 
         client
             .execute(
@@ -864,6 +1420,7 @@ impl PgLogStore {
                 email TEXT NOT NULL UNIQUE,
                 phone_number TEXT NOT NULL,
                 balance DOUBLE PRECISION NOT NULL DEFAULT 0,
+                budget_amount DOUBLE PRECISION,
                 status TEXT NOT NULL,
                 role TEXT NOT NULL,
                 password_hash TEXT,
@@ -894,6 +1451,12 @@ impl PgLogStore {
                 &[],
             )
             .await;
+        let _ = client
+            .execute(
+                "ALTER TABLE users ADD COLUMN budget_amount DOUBLE PRECISION",
+                &[],
+            )
+            .await;
         // Ensure there is at most one superadmin.
         let _ = client
             .execute(
@@ -963,6 +1526,18 @@ impl PgLogStore {
             )
             .await;
 
+        client
+            .execute(
+                r#"CREATE TABLE IF NOT EXISTS gateway_settings (
+                key TEXT PRIMARY KEY,
+                value TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            )"#,
+                &[],
+            )
+            .await
+            .map_err(|e| GatewayError::Config(format!("Failed to init gateway_settings: {}", e)))?;
+
         client
             .execute(
                 r#"CREATE TABLE IF NOT EXISTS password_reset_tokens (
@@ -988,274 +1563,1632 @@ impl PgLogStore {
 
         Ok(store)
     }
-}
+}
+
+impl PgLogStore {
+    fn row_to_request_log(r: Row) -> RequestLog {
+        RequestLog {
+            id: pg_row_i64(&r, 0),
+            timestamp: pg_row_datetime_or_now(&r, 1),
+            method: pg_row_string(&r, 2),
+            path: pg_row_string(&r, 3),
+            request_type: pg_row_string(&r, 4),
+            requested_model: pg_row_opt_string(&r, 5),
+            effective_model: pg_row_opt_string(&r, 6),
+            model: pg_row_opt_string(&r, 7),
+            provider: pg_row_opt_string(&r, 8),
+            api_key: pg_row_opt_string(&r, 9),
+            status_code: pg_row_u16_or(&r, 10, 0),
+            response_time_ms: pg_row_i64_or(&r, 11, 0),
+            prompt_tokens: pg_row_u32_opt(&r, 12),
+            completion_tokens: pg_row_u32_opt(&r, 13),
+            total_tokens: pg_row_u32_opt(&r, 14),
+            cached_tokens: pg_row_u32_opt(&r, 15),
+            reasoning_tokens: pg_row_u32_opt(&r, 16),
+            error_message: pg_row_opt_string(&r, 17),
+            client_token: pg_row_opt_string(&r, 18),
+            user_id: pg_row_opt_string(&r, 19),
+            amount_spent: r.try_get::<usize, Option<f64>>(20).ok().flatten(),
+            latency: LatencyBreakdown {
+                pre_dispatch_ms: pg_row_i64(&r, 21),
+                upstream_ms: pg_row_i64(&r, 22),
+                post_process_ms: pg_row_i64(&r, 23),
+            },
+            profile: PromptProfile {
+                language: pg_row_opt_string(&r, 24),
+                category: pg_row_opt_string(&r, 25),
+            },
+        }
+    }
+}
+
+/// model_prices 表的一行（provider, model, prompt/completion 单价, currency, model_type,
+/// source, status, synced_at, expires_at）
+fn pg_row_to_model_price(r: &Row) -> ModelPriceRecord {
+    ModelPriceRecord {
+        provider: pg_row_string(r, 0),
+        model: pg_row_string(r, 1),
+        prompt_price_per_million: pg_row_f64_or(r, 2, 0.0),
+        completion_price_per_million: pg_row_f64_or(r, 3, 0.0),
+        currency: pg_row_opt_string(r, 4),
+        model_type: pg_row_opt_string(r, 5),
+        source: pg_price_source(r, 6),
+        status: pg_price_status(r, 7),
+        synced_at: pg_row_opt_string(r, 8).and_then(|raw| parse_datetime_string(&raw).ok()),
+        expires_at: pg_row_opt_string(r, 9).and_then(|raw| parse_datetime_string(&raw).ok()),
+    }
+}
+
+impl RequestLogStore for PgLogStore {
+    fn log_request<'a>(&'a self, log: RequestLog) -> BoxFuture<'a, rusqlite::Result<i64>> {
+        Box::pin(async move {
+            let client = self.pool.pick();
+            let row = client
+                .query_one(
+                    "INSERT INTO request_logs (timestamp, method, path, request_type, requested_model, effective_model, model, provider, api_key, status_code, response_time_ms, prompt_tokens, completion_tokens, total_tokens, cached_tokens, reasoning_tokens, error_message, client_token, user_id, amount_spent, pre_dispatch_ms, upstream_ms, post_process_ms, prompt_language, content_category)
+                     VALUES ($1,$2,$3,$4,$5,$6,$7,$8,$9,$10,$11,$12,$13,$14,$15,$16,$17,$18,$19,$20,$21,$22,$23,$24,$25)
+                     RETURNING id",
+                    &[&to_beijing_string(&log.timestamp), &log.method, &log.path, &log.request_type, &log.requested_model, &log.effective_model, &log.model, &log.provider, &log.api_key, &i32::from(log.status_code), &log.response_time_ms, &log.prompt_tokens.map(|v| v as i32), &log.completion_tokens.map(|v| v as i32), &log.total_tokens.map(|v| v as i32), &log.cached_tokens.map(|v| v as i32), &log.reasoning_tokens.map(|v| v as i32), &log.error_message, &log.client_token, &log.user_id, &log.amount_spent, &log.latency.pre_dispatch_ms, &log.latency.upstream_ms, &log.latency.post_process_ms, &log.profile.language, &log.profile.category],
+                )
+                .await
+                .map_err(pg_err)?;
+            Ok(pg_row_i64_or(&row, 0, 0))
+        })
+    }
+
+    fn get_recent_logs_with_cursor<'a>(
+        &'a self,
+        limit: i32,
+        cursor: Option<i64>,
+    ) -> BoxFuture<'a, rusqlite::Result<Vec<RequestLog>>> {
+        Box::pin(async move {
+            let client = self.pool.pick();
+            let lim: i64 = limit as i64;
+            let rows = if let Some(cursor_id) = cursor {
+                client
+                    .query(
+                        "SELECT id, timestamp, method, path, request_type, requested_model, effective_model, model, provider, api_key, status_code, response_time_ms, prompt_tokens, completion_tokens, total_tokens, cached_tokens, reasoning_tokens, error_message, client_token, user_id, amount_spent, pre_dispatch_ms, upstream_ms, post_process_ms, prompt_language, content_category FROM request_logs WHERE id < $1 ORDER BY id DESC LIMIT $2",
+                        &[&cursor_id, &lim],
+                    )
+                    .await
+                    .map_err(pg_err)?
+            } else {
+                client
+                    .query(
+                        "SELECT id, timestamp, method, path, request_type, requested_model, effective_model, model, provider, api_key, status_code, response_time_ms, prompt_tokens, completion_tokens, total_tokens, cached_tokens, reasoning_tokens, error_message, client_token, user_id, amount_spent, pre_dispatch_ms, upstream_ms, post_process_ms, prompt_language, content_category FROM request_logs ORDER BY id DESC LIMIT $1",
+                        &[&lim],
+                    )
+                    .await
+                    .map_err(pg_err)?
+            };
+            Ok(rows.into_iter().map(Self::row_to_request_log).collect())
+        })
+    }
+
+    fn get_logs_with_columns<'a>(
+        &'a self,
+        limit: i32,
+        cursor: Option<i64>,
+        method_path: Option<(&'a str, &'a str)>,
+        columns: &'a LogColumns,
+    ) -> BoxFuture<'a, rusqlite::Result<Vec<RequestLog>>> {
+        Box::pin(async move {
+            let client = self.pool.pick();
+            let lim: i64 = limit as i64;
+            let mut conditions: Vec<String> = Vec::new();
+            let mut params: Vec<&(dyn tokio_postgres::types::ToSql + Sync)> = Vec::new();
+            if let Some((method, path)) = method_path.as_ref() {
+                params.push(method);
+                conditions.push(format!("method = ${}", params.len()));
+                params.push(path);
+                conditions.push(format!("path = ${}", params.len()));
+            }
+            if let Some(cursor_id) = cursor.as_ref() {
+                params.push(cursor_id);
+                conditions.push(format!("id < ${}", params.len()));
+            }
+            params.push(&lim);
+            let where_clause = if conditions.is_empty() {
+                String::new()
+            } else {
+                format!("WHERE {}", conditions.join(" AND "))
+            };
+            let sql = format!(
+                "SELECT {} FROM request_logs {} ORDER BY id DESC LIMIT ${}",
+                columns.select_list(),
+                where_clause,
+                params.len()
+            );
+            let rows = client.query(&sql, &params).await.map_err(pg_err)?;
+            Ok(rows.into_iter().map(Self::row_to_request_log).collect())
+        })
+    }
+
+    fn get_request_logs<'a>(
+        &'a self,
+        limit: i32,
+        cursor: Option<i64>,
+    ) -> BoxFuture<'a, rusqlite::Result<Vec<RequestLog>>> {
+        Box::pin(async move {
+            let client = self.pool.pick();
+            let lim: i64 = limit as i64;
+            let rows = if let Some(cursor_id) = cursor {
+                client
+                    .query(
+                        "SELECT id, timestamp, method, path, request_type, requested_model, effective_model, model, provider, api_key, status_code, response_time_ms, prompt_tokens, completion_tokens, total_tokens, cached_tokens, reasoning_tokens, error_message, client_token, user_id, amount_spent, pre_dispatch_ms, upstream_ms, post_process_ms, prompt_language, content_category FROM request_logs WHERE id < $1 ORDER BY id DESC LIMIT $2",
+                        &[&cursor_id, &lim],
+                    )
+                    .await
+                    .map_err(pg_err)?
+            } else {
+                client
+                    .query(
+                        "SELECT id, timestamp, method, path, request_type, requested_model, effective_model, model, provider, api_key, status_code, response_time_ms, prompt_tokens, completion_tokens, total_tokens, cached_tokens, reasoning_tokens, error_message, client_token, user_id, amount_spent, pre_dispatch_ms, upstream_ms, post_process_ms, prompt_language, content_category FROM request_logs ORDER BY id DESC LIMIT $1",
+                        &[&lim],
+                    )
+                    .await
+                    .map_err(pg_err)?
+            };
+            Ok(rows.into_iter().map(Self::row_to_request_log).collect())
+        })
+    }
+
+    fn get_request_log_by_id<'a>(
+        &'a self,
+        id: i64,
+    ) -> BoxFuture<'a, rusqlite::Result<Option<RequestLog>>> {
+        Box::pin(async move {
+            let client = self.pool.pick();
+            let row = client
+                .query_opt(
+                    "SELECT id, timestamp, method, path, request_type, requested_model, effective_model, model, provider, api_key, status_code, response_time_ms, prompt_tokens, completion_tokens, total_tokens, cached_tokens, reasoning_tokens, error_message, client_token, user_id, amount_spent, pre_dispatch_ms, upstream_ms, post_process_ms, prompt_language, content_category FROM request_logs WHERE id = $1 LIMIT 1",
+                    &[&id],
+                )
+                .await
+                .map_err(pg_err)?;
+            Ok(row.map(Self::row_to_request_log))
+        })
+    }
+
+    fn upsert_request_log_detail<'a>(
+        &'a self,
+        detail: RequestLogDetailRecord,
+    ) -> BoxFuture<'a, rusqlite::Result<()>> {
+        Box::pin(async move {
+            let payload = ArchivedPayload::pack(
+                detail.request_payload_snapshot.as_deref(),
+                detail.response_preview.as_deref(),
+            );
+            let no_body: Option<String> = None;
+            let client = self.pool.pick();
+            client
+                .execute(
+                    "INSERT INTO request_log_details (
+                        request_log_id, request_payload_snapshot, response_preview, upstream_status,
+                        fallback_triggered, fallback_reason, selected_provider, selected_key_id, first_token_latency_ms,
+                        param_policy_applied, provider_override, prompt_truncation
+                    ) VALUES ($1,$2,$3,$4,$5,$6,$7,$8,$9,$10,$11,$12)
+                    ON CONFLICT (request_log_id) DO UPDATE SET
+                        request_payload_snapshot = EXCLUDED.request_payload_snapshot,
+                        response_preview = EXCLUDED.response_preview,
+                        upstream_status = EXCLUDED.upstream_status,
+                        fallback_triggered = EXCLUDED.fallback_triggered,
+                        fallback_reason = EXCLUDED.fallback_reason,
+                        selected_provider = EXCLUDED.selected_provider,
+                        selected_key_id = EXCLUDED.selected_key_id,
+                        first_token_latency_ms = EXCLUDED.first_token_latency_ms,
+                        param_policy_applied = EXCLUDED.param_policy_applied,
+                        provider_override = EXCLUDED.provider_override,
+                        prompt_truncation = EXCLUDED.prompt_truncation",
+                    &[
+                        &detail.request_log_id,
+                        &no_body,
+                        &no_body,
+                        &detail.upstream_status,
+                        &detail.fallback_triggered,
+                        &detail.fallback_reason,
+                        &detail.selected_provider,
+                        &detail.selected_key_id,
+                        &detail.first_token_latency_ms,
+                        &detail.param_policy_applied,
+                        &detail.provider_override,
+                        &detail.prompt_truncation,
+                    ],
+                )
+                .await
+                .map_err(pg_err)?;
+            match payload {
+                Some(p) => {
+                    client
+                        .execute(
+                            "INSERT INTO request_log_payloads (
+                                request_log_id, encoding, request_body, response_body, original_bytes, stored_bytes
+                            ) VALUES ($1,$2,$3,$4,$5,$6)
+                            ON CONFLICT (request_log_id) DO UPDATE SET
+                                encoding = EXCLUDED.encoding,
+                                request_body = EXCLUDED.request_body,
+                                response_body = EXCLUDED.response_body,
+                                original_bytes = EXCLUDED.original_bytes,
+                                stored_bytes = EXCLUDED.stored_bytes",
+                            &[
+                                &detail.request_log_id,
+                                &ENCODING_ZSTD,
+                                &p.request_body,
+                                &p.response_body,
+                                &p.original_bytes,
+                                &p.stored_bytes,
+                            ],
+                        )
+                        .await
+                        .map_err(pg_err)?;
+                }
+                None => {
+                    client
+                        .execute(
+                            "DELETE FROM request_log_payloads WHERE request_log_id = $1",
+                            &[&detail.request_log_id],
+                        )
+                        .await
+                        .map_err(pg_err)?;
+                }
+            }
+            Ok(())
+        })
+    }
+
+    fn get_request_log_detail<'a>(
+        &'a self,
+        request_log_id: i64,
+    ) -> BoxFuture<'a, rusqlite::Result<Option<RequestLogDetailRecord>>> {
+        Box::pin(async move {
+            let client = self.pool.pick();
+            let row = client
+                .query_opt(
+                    "SELECT d.request_log_id, d.request_payload_snapshot, d.response_preview, d.upstream_status, d.fallback_triggered, d.fallback_reason, d.selected_provider, d.selected_key_id, d.first_token_latency_ms, p.request_body, p.response_body, d.param_policy_applied, d.provider_override, d.prompt_truncation FROM request_log_details d LEFT JOIN request_log_payloads p ON p.request_log_id = d.request_log_id WHERE d.request_log_id = $1 LIMIT 1",
+                    &[&request_log_id],
+                )
+                .await
+                .map_err(pg_err)?;
+            // 旧数据的正文仍以明文存于明细表
+            let unpack = |row: &Row, blob_idx: usize, legacy_idx: usize| {
+                row.try_get::<usize, Option<Vec<u8>>>(blob_idx)
+                    .ok()
+                    .flatten()
+                    .and_then(|b| payload_archive::decompress(&b))
+                    .or_else(|| pg_row_opt_string(row, legacy_idx))
+            };
+            Ok(row.map(|row| RequestLogDetailRecord {
+                request_log_id: pg_row_i64_or(&row, 0, 0),
+                request_payload_snapshot: unpack(&row, 9, 1),
+                response_preview: unpack(&row, 10, 2),
+                upstream_status: pg_row_i64(&row, 3),
+                fallback_triggered: row.try_get::<usize, Option<bool>>(4).ok().flatten(),
+                fallback_reason: pg_row_opt_string(&row, 5),
+                selected_provider: pg_row_opt_string(&row, 6),
+                selected_key_id: pg_row_opt_string(&row, 7),
+                first_token_latency_ms: pg_row_i64(&row, 8),
+                param_policy_applied: pg_row_opt_string(&row, 11),
+                provider_override: pg_row_opt_string(&row, 12),
+                prompt_truncation: pg_row_opt_string(&row, 13),
+            }))
+        })
+    }
+
+    fn save_compare_run<'a>(
+        &'a self,
+        run: StoredCompareRun,
+    ) -> BoxFuture<'a, rusqlite::Result<()>> {
+        Box::pin(async move {
+            let client = self.pool.pick();
+            client
+                .execute(
+                    "INSERT INTO compare_runs (id, user_id, source_request_id, created_at, result_json)
+                     VALUES ($1,$2,$3,$4,$5)
+                     ON CONFLICT (id) DO UPDATE SET
+                        user_id = EXCLUDED.user_id,
+                        source_request_id = EXCLUDED.source_request_id,
+                        created_at = EXCLUDED.created_at,
+                        result_json = EXCLUDED.result_json",
+                    &[
+                        &run.id,
+                        &run.user_id,
+                        &run.source_request_id,
+                        &to_beijing_string(&run.created_at),
+                        &run.result_json,
+                    ],
+                )
+                .await
+                .map_err(pg_err)?;
+            Ok(())
+        })
+    }
+
+    fn get_compare_run<'a>(
+        &'a self,
+        id: &'a str,
+    ) -> BoxFuture<'a, rusqlite::Result<Option<StoredCompareRun>>> {
+        Box::pin(async move {
+            let client = self.pool.pick();
+            let row = client
+                .query_opt(
+                    "SELECT id, user_id, source_request_id, created_at, result_json FROM compare_runs WHERE id = $1 LIMIT 1",
+                    &[&id],
+                )
+                .await
+                .map_err(pg_err)?;
+            Ok(row.map(|row| StoredCompareRun {
+                id: pg_row_string(&row, 0),
+                user_id: pg_row_string(&row, 1),
+                source_request_id: pg_row_i64_or(&row, 2, 0),
+                created_at: pg_row_datetime_or_now(&row, 3),
+                result_json: pg_row_string(&row, 4),
+            }))
+        })
+    }
+
+    fn get_idempotent_response<'a>(
+        &'a self,
+        token_id: &'a str,
+        idempotency_key: &'a str,
+    ) -> BoxFuture<'a, rusqlite::Result<Option<StoredIdempotentResponse>>> {
+        Box::pin(async move {
+            let client = self.pool.pick();
+            let now = to_beijing_string(&Utc::now());
+            let row = client
+                .query_opt(
+                    "SELECT token_id, idempotency_key, request_fingerprint, response_json, created_at, expires_at FROM idempotency_keys WHERE token_id = $1 AND idempotency_key = $2 AND expires_at > $3",
+                    &[&token_id, &idempotency_key, &now],
+                )
+                .await
+                .map_err(pg_err)?;
+            Ok(row.map(|row| StoredIdempotentResponse {
+                token_id: pg_row_string(&row, 0),
+                idempotency_key: pg_row_string(&row, 1),
+                request_fingerprint: pg_row_string(&row, 2),
+                response_json: pg_row_string(&row, 3),
+                created_at: pg_row_datetime_or_now(&row, 4),
+                expires_at: pg_row_datetime_or_now(&row, 5),
+            }))
+        })
+    }
+
+    fn save_idempotent_response<'a>(
+        &'a self,
+        record: StoredIdempotentResponse,
+    ) -> BoxFuture<'a, rusqlite::Result<()>> {
+        Box::pin(async move {
+            let client = self.pool.pick();
+            client
+                .execute(
+                    "DELETE FROM idempotency_keys WHERE expires_at <= $1",
+                    &[&to_beijing_string(&Utc::now())],
+                )
+                .await
+                .map_err(pg_err)?;
+            client
+                .execute(
+                    "INSERT INTO idempotency_keys (token_id, idempotency_key, request_fingerprint, response_json, created_at, expires_at)
+                     VALUES ($1,$2,$3,$4,$5,$6)
+                     ON CONFLICT (token_id, idempotency_key) DO UPDATE SET
+                        request_fingerprint = EXCLUDED.request_fingerprint,
+                        response_json = EXCLUDED.response_json,
+                        created_at = EXCLUDED.created_at,
+                        expires_at = EXCLUDED.expires_at",
+                    &[
+                        &record.token_id,
+                        &record.idempotency_key,
+                        &record.request_fingerprint,
+                        &record.response_json,
+                        &to_beijing_string(&record.created_at),
+                        &to_beijing_string(&record.expires_at),
+                    ],
+                )
+                .await
+                .map_err(pg_err)?;
+            Ok(())
+        })
+    }
+
+    fn save_debug_capture<'a>(
+        &'a self,
+        record: DebugCaptureRecord,
+    ) -> BoxFuture<'a, rusqlite::Result<()>> {
+        Box::pin(async move {
+            let client = self.pool.pick();
+            client
+                .execute(
+                    "DELETE FROM request_log_debug_captures WHERE expires_at <= $1",
+                    &[&to_beijing_string(&Utc::now())],
+                )
+                .await
+                .map_err(pg_err)?;
+            client
+                .execute(
+                    "INSERT INTO request_log_debug_captures (request_log_id, request_body, response_body, created_at, expires_at)
+                     VALUES ($1,$2,$3,$4,$5)
+                     ON CONFLICT (request_log_id) DO UPDATE SET
+                        request_body = EXCLUDED.request_body,
+                        response_body = EXCLUDED.response_body,
+                        created_at = EXCLUDED.created_at,
+                        expires_at = EXCLUDED.expires_at",
+                    &[
+                        &record.request_log_id,
+                        &record.request_body.as_deref().map(payload_archive::compress),
+                        &record.response_body.as_deref().map(payload_archive::compress),
+                        &to_beijing_string(&record.created_at),
+                        &to_beijing_string(&record.expires_at),
+                    ],
+                )
+                .await
+                .map_err(pg_err)?;
+            Ok(())
+        })
+    }
+
+    fn get_debug_capture<'a>(
+        &'a self,
+        request_log_id: i64,
+    ) -> BoxFuture<'a, rusqlite::Result<Option<DebugCaptureRecord>>> {
+        Box::pin(async move {
+            let client = self.pool.pick();
+            let now = to_beijing_string(&Utc::now());
+            let row = client
+                .query_opt(
+                    "SELECT request_log_id, request_body, response_body, created_at, expires_at FROM request_log_debug_captures WHERE request_log_id = $1 AND expires_at > $2",
+                    &[&request_log_id, &now],
+                )
+                .await
+                .map_err(pg_err)?;
+            let unpack = |row: &Row, idx: usize| {
+                row.try_get::<usize, Option<Vec<u8>>>(idx)
+                    .ok()
+                    .flatten()
+                    .and_then(|b| payload_archive::decompress(&b))
+            };
+            Ok(row.map(|row| DebugCaptureRecord {
+                request_log_id: pg_row_i64_or(&row, 0, 0),
+                request_body: unpack(&row, 1),
+                response_body: unpack(&row, 2),
+                created_at: pg_row_datetime_or_now(&row, 3),
+                expires_at: pg_row_datetime_or_now(&row, 4),
+            }))
+        })
+    }
+
+    fn purge_expired_debug_captures<'a>(
+        &'a self,
+        now: DateTime<Utc>,
+    ) -> BoxFuture<'a, rusqlite::Result<u64>> {
+        Box::pin(async move {
+            let client = self.pool.pick();
+            let affected = client
+                .execute(
+                    "DELETE FROM request_log_debug_captures WHERE expires_at <= $1",
+                    &[&to_beijing_string(&now)],
+                )
+                .await
+                .map_err(pg_err)?;
+            Ok(affected)
+        })
+    }
+
+    fn save_stream_capture<'a>(
+        &'a self,
+        record: StreamCaptureRecord,
+    ) -> BoxFuture<'a, rusqlite::Result<()>> {
+        Box::pin(async move {
+            let encoded = serde_json::to_string(&record.chunks)
+                .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+            let client = self.pool.pick();
+            client
+                .execute(
+                    "INSERT INTO request_log_stream_chunks (request_log_id, chunks, chunk_count, truncated)
+                     VALUES ($1,$2,$3,$4)
+                     ON CONFLICT (request_log_id) DO UPDATE SET
+                        chunks = EXCLUDED.chunks,
+                        chunk_count = EXCLUDED.chunk_count,
+                        truncated = EXCLUDED.truncated",
+                    &[
+                        &record.request_log_id,
+                        &payload_archive::compress(&encoded),
+                        &(record.chunks.len() as i64),
+                        &record.truncated,
+                    ],
+                )
+                .await
+                .map_err(pg_err)?;
+            Ok(())
+        })
+    }
+
+    fn get_stream_capture<'a>(
+        &'a self,
+        request_log_id: i64,
+    ) -> BoxFuture<'a, rusqlite::Result<Option<StreamCaptureRecord>>> {
+        Box::pin(async move {
+            let client = self.pool.pick();
+            let row = client
+                .query_opt(
+                    "SELECT request_log_id, chunks, truncated FROM request_log_stream_chunks WHERE request_log_id = $1",
+                    &[&request_log_id],
+                )
+                .await
+                .map_err(pg_err)?;
+            Ok(row.map(|row| StreamCaptureRecord {
+                request_log_id: pg_row_i64_or(&row, 0, 0),
+                chunks: row
+                    .try_get::<usize, Vec<u8>>(1)
+                    .ok()
+                    .and_then(|b| payload_archive::decompress(&b))
+                    .and_then(|json| serde_json::from_str(&json).ok())
+                    .unwrap_or_default(),
+                truncated: row.try_get::<usize, bool>(2).unwrap_or(false),
+            }))
+        })
+    }
+
+    fn insert_usage_webhook_dead_letter<'a>(
+        &'a self,
+        letter: UsageWebhookDeadLetter,
+    ) -> BoxFuture<'a, rusqlite::Result<i64>> {
+        Box::pin(async move {
+            let client = self.pool.pick();
+            let row = client
+                .query_one(
+                    "INSERT INTO usage_webhook_dead_letters (token_id, url, payload_json, attempts, last_error, created_at)
+                     VALUES ($1,$2,$3,$4,$5,$6)
+                     RETURNING id",
+                    &[
+                        &letter.token_id,
+                        &letter.url,
+                        &letter.payload_json,
+                        &letter.attempts,
+                        &letter.last_error,
+                        &to_beijing_string(&letter.created_at),
+                    ],
+                )
+                .await
+                .map_err(pg_err)?;
+            Ok(pg_row_i64_or(&row, 0, 0))
+        })
+    }
+
+    fn list_usage_webhook_dead_letters<'a>(
+        &'a self,
+        limit: i32,
+    ) -> BoxFuture<'a, rusqlite::Result<Vec<UsageWebhookDeadLetter>>> {
+        Box::pin(async move {
+            let client = self.pool.pick();
+            let rows = client
+                .query(
+                    "SELECT id, token_id, url, payload_json, attempts, last_error, created_at FROM usage_webhook_dead_letters ORDER BY id DESC LIMIT $1",
+                    &[&(limit as i64)],
+                )
+                .await
+                .map_err(pg_err)?;
+            Ok(rows.iter().map(pg_usage_webhook_dead_letter).collect())
+        })
+    }
+
+    fn get_usage_webhook_dead_letter<'a>(
+        &'a self,
+        id: i64,
+    ) -> BoxFuture<'a, rusqlite::Result<Option<UsageWebhookDeadLetter>>> {
+        Box::pin(async move {
+            let client = self.pool.pick();
+            let row = client
+                .query_opt(
+                    "SELECT id, token_id, url, payload_json, attempts, last_error, created_at FROM usage_webhook_dead_letters WHERE id = $1",
+                    &[&id],
+                )
+                .await
+                .map_err(pg_err)?;
+            Ok(row.as_ref().map(pg_usage_webhook_dead_letter))
+        })
+    }
+
+    fn delete_usage_webhook_dead_letter<'a>(
+        &'a self,
+        id: i64,
+    ) -> BoxFuture<'a, rusqlite::Result<bool>> {
+        Box::pin(async move {
+            let client = self.pool.pick();
+            let affected = client
+                .execute(
+                    "DELETE FROM usage_webhook_dead_letters WHERE id = $1",
+                    &[&id],
+                )
+                .await
+                .map_err(pg_err)?;
+            Ok(affected > 0)
+        })
+    }
+
+    fn insert_admin_notification<'a>(
+        &'a self,
+        notification: AdminNotificationRecord,
+    ) -> BoxFuture<'a, rusqlite::Result<Option<i64>>> {
+        Box::pin(async move {
+            let client = self.pool.pick();
+            let row = client
+                .query_opt(
+                    "INSERT INTO admin_notifications (kind, severity, title, message, reference, created_at)
+                     SELECT $1, $2, $3, $4, $5::TEXT, $6
+                     WHERE $5::TEXT IS NULL OR NOT EXISTS (
+                         SELECT 1 FROM admin_notifications
+                         WHERE kind = $1 AND reference = $5::TEXT AND read_at IS NULL
+                     )
+                     RETURNING id",
+                    &[
+                        &notification.kind,
+                        &notification.severity,
+                        &notification.title,
+                        &notification.message,
+                        &notification.reference,
+                        &to_beijing_string(&notification.created_at),
+                    ],
+                )
+                .await
+                .map_err(pg_err)?;
+            Ok(row.map(|row| pg_row_i64_or(&row, 0, 0)))
+        })
+    }
+
+    fn list_admin_notifications<'a>(
+        &'a self,
+        unread_only: bool,
+        limit: i32,
+    ) -> BoxFuture<'a, rusqlite::Result<Vec<AdminNotificationRecord>>> {
+        Box::pin(async move {
+            let client = self.pool.pick();
+            let rows = client
+                .query(
+                    "SELECT id, kind, severity, title, message, reference, created_at, read_at
+                     FROM admin_notifications WHERE (NOT $1 OR read_at IS NULL)
+                     ORDER BY id DESC LIMIT $2",
+                    &[&unread_only, &(limit as i64)],
+                )
+                .await
+                .map_err(pg_err)?;
+            Ok(rows.iter().map(pg_admin_notification).collect())
+        })
+    }
+
+    fn count_unread_admin_notifications<'a>(&'a self) -> BoxFuture<'a, rusqlite::Result<u64>> {
+        Box::pin(async move {
+            let client = self.pool.pick();
+            let row = client
+                .query_one(
+                    "SELECT COUNT(*) FROM admin_notifications WHERE read_at IS NULL",
+                    &[],
+                )
+                .await
+                .map_err(pg_err)?;
+            Ok(pg_row_i64_or(&row, 0, 0).max(0) as u64)
+        })
+    }
+
+    fn mark_admin_notifications_read<'a>(
+        &'a self,
+        ids: Option<Vec<i64>>,
+        at: DateTime<Utc>,
+    ) -> BoxFuture<'a, rusqlite::Result<u64>> {
+        Box::pin(async move {
+            let client = self.pool.pick();
+            let at = to_beijing_string(&at);
+            let affected = match ids {
+                None => client
+                    .execute(
+                        "UPDATE admin_notifications SET read_at = $1 WHERE read_at IS NULL",
+                        &[&at],
+                    )
+                    .await
+                    .map_err(pg_err)?,
+                Some(ids) => client
+                    .execute(
+                        "UPDATE admin_notifications SET read_at = $1 WHERE id = ANY($2) AND read_at IS NULL",
+                        &[&at, &ids],
+                    )
+                    .await
+                    .map_err(pg_err)?,
+            };
+            Ok(affected)
+        })
+    }
+
+    fn insert_maintenance_window<'a>(
+        &'a self,
+        window: MaintenanceWindowRecord,
+    ) -> BoxFuture<'a, rusqlite::Result<i64>> {
+        Box::pin(async move {
+            let client = self.pool.pick();
+            let row = client
+                .query_one(
+                    "INSERT INTO maintenance_windows (provider, starts_at, ends_at, message, created_by, created_at)
+                     VALUES ($1,$2,$3,$4,$5,$6)
+                     RETURNING id",
+                    &[
+                        &window.provider,
+                        &to_beijing_string(&window.starts_at),
+                        &to_beijing_string(&window.ends_at),
+                        &window.message,
+                        &window.created_by,
+                        &to_beijing_string(&window.created_at),
+                    ],
+                )
+                .await
+                .map_err(pg_err)?;
+            Ok(pg_row_i64_or(&row, 0, 0))
+        })
+    }
+
+    fn list_maintenance_windows<'a>(
+        &'a self,
+        ending_after: DateTime<Utc>,
+    ) -> BoxFuture<'a, rusqlite::Result<Vec<MaintenanceWindowRecord>>> {
+        Box::pin(async move {
+            let client = self.pool.pick();
+            let rows = client
+                .query(
+                    "SELECT id, provider, starts_at, ends_at, message, created_by, created_at
+                     FROM maintenance_windows WHERE ends_at > $1 ORDER BY starts_at, id",
+                    &[&to_beijing_string(&ending_after)],
+                )
+                .await
+                .map_err(pg_err)?;
+            Ok(rows.iter().map(pg_maintenance_window).collect())
+        })
+    }
+
+    fn delete_maintenance_window<'a>(&'a self, id: i64) -> BoxFuture<'a, rusqlite::Result<bool>> {
+        Box::pin(async move {
+            let client = self.pool.pick();
+            let affected = client
+                .execute("DELETE FROM maintenance_windows WHERE id = $1", &[&id])
+                .await
+                .map_err(pg_err)?;
+            Ok(affected > 0)
+        })
+    }
+
+    fn list_param_policies<'a>(
+        &'a self,
+    ) -> BoxFuture<'a, rusqlite::Result<Vec<ParamPolicyRecord>>> {
+        Box::pin(async move {
+            let client = self.pool.pick();
+            let rows = client
+                .query(
+                    "SELECT id, model, token_id, rules_json, enabled, created_at, updated_at FROM param_policies ORDER BY created_at, id",
+                    &[],
+                )
+                .await
+                .map_err(pg_err)?;
+            Ok(rows.iter().map(pg_param_policy).collect())
+        })
+    }
+
+    fn upsert_param_policy<'a>(
+        &'a self,
+        policy: ParamPolicyRecord,
+    ) -> BoxFuture<'a, rusqlite::Result<()>> {
+        Box::pin(async move {
+            let client = self.pool.pick();
+            client
+                .execute(
+                    "INSERT INTO param_policies (id, model, token_id, rules_json, enabled, created_at, updated_at)
+                     VALUES ($1,$2,$3,$4,$5,$6,$7)
+                     ON CONFLICT (id) DO UPDATE SET
+                        model = EXCLUDED.model,
+                        token_id = EXCLUDED.token_id,
+                        rules_json = EXCLUDED.rules_json,
+                        enabled = EXCLUDED.enabled,
+                        updated_at = EXCLUDED.updated_at",
+                    &[
+                        &policy.id,
+                        &policy.model,
+                        &policy.token_id,
+                        &policy.rules_json,
+                        &policy.enabled,
+                        &to_beijing_string(&policy.created_at),
+                        &to_beijing_string(&policy.updated_at),
+                    ],
+                )
+                .await
+                .map_err(pg_err)?;
+            Ok(())
+        })
+    }
+
+    fn delete_param_policy<'a>(&'a self, id: &'a str) -> BoxFuture<'a, rusqlite::Result<bool>> {
+        Box::pin(async move {
+            let client = self.pool.pick();
+            let affected = client
+                .execute("DELETE FROM param_policies WHERE id = $1", &[&id])
+                .await
+                .map_err(pg_err)?;
+            Ok(affected > 0)
+        })
+    }
+
+    fn list_metrics_reports<'a>(
+        &'a self,
+    ) -> BoxFuture<'a, rusqlite::Result<Vec<MetricsReportRecord>>> {
+        Box::pin(async move {
+            let client = self.pool.pick();
+            let rows = client
+                .query(
+                    "SELECT id, name, period, channel, target, template, enabled, last_sent_at, created_at, updated_at FROM metrics_reports ORDER BY created_at, id",
+                    &[],
+                )
+                .await
+                .map_err(pg_err)?;
+            Ok(rows.iter().map(pg_metrics_report).collect())
+        })
+    }
+
+    fn upsert_metrics_report<'a>(
+        &'a self,
+        report: MetricsReportRecord,
+    ) -> BoxFuture<'a, rusqlite::Result<()>> {
+        Box::pin(async move {
+            let client = self.pool.pick();
+            client
+                .execute(
+                    "INSERT INTO metrics_reports (id, name, period, channel, target, template, enabled, last_sent_at, created_at, updated_at)
+                     VALUES ($1,$2,$3,$4,$5,$6,$7,$8,$9,$10)
+                     ON CONFLICT (id) DO UPDATE SET
+                        name = EXCLUDED.name,
+                        period = EXCLUDED.period,
+                        channel = EXCLUDED.channel,
+                        target = EXCLUDED.target,
+                        template = EXCLUDED.template,
+                        enabled = EXCLUDED.enabled,
+                        updated_at = EXCLUDED.updated_at",
+                    &[
+                        &report.id,
+                        &report.name,
+                        &report.period,
+                        &report.channel,
+                        &report.target,
+                        &report.template,
+                        &report.enabled,
+                        &report.last_sent_at.as_ref().map(to_beijing_string),
+                        &to_beijing_string(&report.created_at),
+                        &to_beijing_string(&report.updated_at),
+                    ],
+                )
+                .await
+                .map_err(pg_err)?;
+            Ok(())
+        })
+    }
+
+    fn delete_metrics_report<'a>(&'a self, id: &'a str) -> BoxFuture<'a, rusqlite::Result<bool>> {
+        Box::pin(async move {
+            let client = self.pool.pick();
+            let affected = client
+                .execute("DELETE FROM metrics_reports WHERE id = $1", &[&id])
+                .await
+                .map_err(pg_err)?;
+            Ok(affected > 0)
+        })
+    }
+
+    fn mark_metrics_report_sent<'a>(
+        &'a self,
+        id: &'a str,
+        sent_at: DateTime<Utc>,
+    ) -> BoxFuture<'a, rusqlite::Result<()>> {
+        Box::pin(async move {
+            let client = self.pool.pick();
+            client
+                .execute(
+                    "UPDATE metrics_reports SET last_sent_at = $2 WHERE id = $1",
+                    &[&id, &to_beijing_string(&sent_at)],
+                )
+                .await
+                .map_err(pg_err)?;
+            Ok(())
+        })
+    }
+
+    fn aggregate_token_usage_daily<'a>(
+        &'a self,
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+    ) -> BoxFuture<'a, rusqlite::Result<Vec<TokenUsageDaily>>> {
+        Box::pin(async move {
+            let client = self.pool.pick();
+            let rows = client
+                .query(
+                    "SELECT client_token,
+                            LEFT(timestamp, 10) AS day,
+                            COALESCE(NULLIF(effective_model, ''), model) AS billed_model,
+                            COUNT(*)::bigint,
+                            COALESCE(SUM(prompt_tokens), 0)::bigint,
+                            COALESCE(SUM(completion_tokens), 0)::bigint,
+                            COALESCE(SUM(total_tokens), 0)::bigint,
+                            COALESCE(SUM(amount_spent), 0)::double precision
+                     FROM request_logs
+                     WHERE client_token IS NOT NULL
+                       AND COALESCE(NULLIF(effective_model, ''), model) IS NOT NULL
+                       AND timestamp >= $1
+                       AND timestamp < $2
+                     GROUP BY client_token, day, billed_model
+                     ORDER BY day, client_token, billed_model",
+                    &[&to_beijing_string(&since), &to_beijing_string(&until)],
+                )
+                .await
+                .map_err(pg_err)?;
+            Ok(rows
+                .iter()
+                .map(|row| TokenUsageDaily {
+                    client_token: pg_row_string(row, 0),
+                    day: pg_row_string(row, 1),
+                    model: pg_row_string(row, 2),
+                    requests: pg_row_i64_or(row, 3, 0).max(0) as u64,
+                    prompt_tokens: pg_row_i64_or(row, 4, 0).max(0) as u64,
+                    completion_tokens: pg_row_i64_or(row, 5, 0).max(0) as u64,
+                    total_tokens: pg_row_i64_or(row, 6, 0).max(0) as u64,
+                    cost: pg_row_f64_or(row, 7, 0.0),
+                })
+                .collect())
+        })
+    }
+
+    fn insert_statement<'a>(
+        &'a self,
+        statement: StatementRecord,
+    ) -> BoxFuture<'a, rusqlite::Result<bool>> {
+        Box::pin(async move {
+            let client = self.pool.pick();
+            let affected = client
+                .execute(
+                    "INSERT INTO billing_statements (id, scope, subject_id, period, body, created_at)
+                     VALUES ($1,$2,$3,$4,$5,$6)
+                     ON CONFLICT (id) DO NOTHING",
+                    &[
+                        &statement.id,
+                        &statement.scope,
+                        &statement.subject_id,
+                        &statement.period,
+                        &statement.body,
+                        &to_beijing_string(&statement.created_at),
+                    ],
+                )
+                .await
+                .map_err(pg_err)?;
+            Ok(affected > 0)
+        })
+    }
+
+    fn get_statement<'a>(
+        &'a self,
+        id: &'a str,
+    ) -> BoxFuture<'a, rusqlite::Result<Option<StatementRecord>>> {
+        Box::pin(async move {
+            let client = self.pool.pick();
+            let row = client
+                .query_opt(
+                    "SELECT id, scope, subject_id, period, body, created_at FROM billing_statements WHERE id = $1",
+                    &[&id],
+                )
+                .await
+                .map_err(pg_err)?;
+            Ok(row.as_ref().map(pg_statement))
+        })
+    }
+
+    fn list_statements<'a>(
+        &'a self,
+        scope: Option<&'a str>,
+        subject_id: Option<&'a str>,
+        period: Option<&'a str>,
+    ) -> BoxFuture<'a, rusqlite::Result<Vec<StatementRecord>>> {
+        Box::pin(async move {
+            let client = self.pool.pick();
+            let rows = client
+                .query(
+                    "SELECT id, scope, subject_id, period, body, created_at FROM billing_statements
+                     WHERE ($1::text IS NULL OR scope = $1)
+                       AND ($2::text IS NULL OR subject_id = $2)
+                       AND ($3::text IS NULL OR period = $3)
+                     ORDER BY period DESC, scope, subject_id",
+                    &[&scope, &subject_id, &period],
+                )
+                .await
+                .map_err(pg_err)?;
+            Ok(rows.iter().map(pg_statement).collect())
+        })
+    }
+
+    fn list_provider_budgets<'a>(
+        &'a self,
+    ) -> BoxFuture<'a, rusqlite::Result<Vec<ProviderBudgetRecord>>> {
+        Box::pin(async move {
+            let client = self.pool.pick();
+            let rows = client
+                .query(
+                    "SELECT provider, monthly_budget, warn_thresholds, stop_routing_when_exhausted, updated_at FROM provider_budgets ORDER BY provider",
+                    &[],
+                )
+                .await
+                .map_err(pg_err)?;
+            Ok(rows.iter().map(pg_provider_budget).collect())
+        })
+    }
+
+    fn get_provider_budget<'a>(
+        &'a self,
+        provider: &'a str,
+    ) -> BoxFuture<'a, rusqlite::Result<Option<ProviderBudgetRecord>>> {
+        Box::pin(async move {
+            let client = self.pool.pick();
+            let row = client
+                .query_opt(
+                    "SELECT provider, monthly_budget, warn_thresholds, stop_routing_when_exhausted, updated_at FROM provider_budgets WHERE provider = $1",
+                    &[&provider],
+                )
+                .await
+                .map_err(pg_err)?;
+            Ok(row.as_ref().map(pg_provider_budget))
+        })
+    }
+
+    fn upsert_provider_budget<'a>(
+        &'a self,
+        budget: ProviderBudgetRecord,
+    ) -> BoxFuture<'a, rusqlite::Result<()>> {
+        Box::pin(async move {
+            let client = self.pool.pick();
+            let thresholds =
+                serde_json::to_string(&budget.warn_thresholds).unwrap_or_else(|_| "[]".into());
+            client
+                .execute(
+                    "INSERT INTO provider_budgets (provider, monthly_budget, warn_thresholds, stop_routing_when_exhausted, updated_at)
+                     VALUES ($1,$2,$3,$4,$5)
+                     ON CONFLICT (provider) DO UPDATE SET
+                        monthly_budget = EXCLUDED.monthly_budget,
+                        warn_thresholds = EXCLUDED.warn_thresholds,
+                        stop_routing_when_exhausted = EXCLUDED.stop_routing_when_exhausted,
+                        updated_at = EXCLUDED.updated_at",
+                    &[
+                        &budget.provider,
+                        &budget.monthly_budget,
+                        &thresholds,
+                        &budget.stop_routing_when_exhausted,
+                        &to_beijing_string(&budget.updated_at),
+                    ],
+                )
+                .await
+                .map_err(pg_err)?;
+            Ok(())
+        })
+    }
+
+    fn delete_provider_budget<'a>(
+        &'a self,
+        provider: &'a str,
+    ) -> BoxFuture<'a, rusqlite::Result<bool>> {
+        Box::pin(async move {
+            let client = self.pool.pick();
+            let affected = client
+                .execute(
+                    "DELETE FROM provider_budgets WHERE provider = $1",
+                    &[&provider],
+                )
+                .await
+                .map_err(pg_err)?;
+            Ok(affected > 0)
+        })
+    }
+
+    fn list_image_prices<'a>(
+        &'a self,
+        provider: Option<&'a str>,
+    ) -> BoxFuture<'a, rusqlite::Result<Vec<ImagePriceRecord>>> {
+        Box::pin(async move {
+            let client = self.pool.pick();
+            let rows = client
+                .query(
+                    "SELECT provider, model, size, quality, price_per_image, currency, updated_at FROM image_prices
+                     WHERE ($1::text IS NULL OR provider = $1)
+                     ORDER BY provider, model, size, quality",
+                    &[&provider],
+                )
+                .await
+                .map_err(pg_err)?;
+            Ok(rows.iter().map(pg_image_price).collect())
+        })
+    }
+
+    fn upsert_image_price<'a>(
+        &'a self,
+        price: ImagePriceRecord,
+    ) -> BoxFuture<'a, rusqlite::Result<()>> {
+        Box::pin(async move {
+            let client = self.pool.pick();
+            client
+                .execute(
+                    "INSERT INTO image_prices (provider, model, size, quality, price_per_image, currency, updated_at)
+                     VALUES ($1,$2,$3,$4,$5,$6,$7)
+                     ON CONFLICT (provider, model, size, quality) DO UPDATE SET
+                        price_per_image = EXCLUDED.price_per_image,
+                        currency = EXCLUDED.currency,
+                        updated_at = EXCLUDED.updated_at",
+                    &[
+                        &price.provider,
+                        &price.model,
+                        &price.size,
+                        &price.quality,
+                        &price.price_per_image,
+                        &price.currency,
+                        &to_beijing_string(&price.updated_at),
+                    ],
+                )
+                .await
+                .map_err(pg_err)?;
+            Ok(())
+        })
+    }
+
+    fn delete_image_price<'a>(
+        &'a self,
+        provider: &'a str,
+        model: &'a str,
+        size: &'a str,
+        quality: &'a str,
+    ) -> BoxFuture<'a, rusqlite::Result<bool>> {
+        Box::pin(async move {
+            let client = self.pool.pick();
+            let affected = client
+                .execute(
+                    "DELETE FROM image_prices WHERE provider = $1 AND model = $2 AND size = $3 AND quality = $4",
+                    &[&provider, &model, &size, &quality],
+                )
+                .await
+                .map_err(pg_err)?;
+            Ok(affected > 0)
+        })
+    }
+
+    fn list_audio_prices<'a>(
+        &'a self,
+        provider: Option<&'a str>,
+    ) -> BoxFuture<'a, rusqlite::Result<Vec<AudioPriceRecord>>> {
+        Box::pin(async move {
+            let client = self.pool.pick();
+            let rows = client
+                .query(
+                    "SELECT provider, model, price_per_minute, currency, updated_at FROM audio_prices
+                     WHERE ($1::text IS NULL OR provider = $1)
+                     ORDER BY provider, model",
+                    &[&provider],
+                )
+                .await
+                .map_err(pg_err)?;
+            Ok(rows.iter().map(pg_audio_price).collect())
+        })
+    }
+
+    fn upsert_audio_price<'a>(
+        &'a self,
+        price: AudioPriceRecord,
+    ) -> BoxFuture<'a, rusqlite::Result<()>> {
+        Box::pin(async move {
+            let client = self.pool.pick();
+            client
+                .execute(
+                    "INSERT INTO audio_prices (provider, model, price_per_minute, currency, updated_at)
+                     VALUES ($1,$2,$3,$4,$5)
+                     ON CONFLICT (provider, model) DO UPDATE SET
+                        price_per_minute = EXCLUDED.price_per_minute,
+                        currency = EXCLUDED.currency,
+                        updated_at = EXCLUDED.updated_at",
+                    &[
+                        &price.provider,
+                        &price.model,
+                        &price.price_per_minute,
+                        &price.currency,
+                        &to_beijing_string(&price.updated_at),
+                    ],
+                )
+                .await
+                .map_err(pg_err)?;
+            Ok(())
+        })
+    }
+
+    fn delete_audio_price<'a>(
+        &'a self,
+        provider: &'a str,
+        model: &'a str,
+    ) -> BoxFuture<'a, rusqlite::Result<bool>> {
+        Box::pin(async move {
+            let client = self.pool.pick();
+            let affected = client
+                .execute(
+                    "DELETE FROM audio_prices WHERE provider = $1 AND model = $2",
+                    &[&provider, &model],
+                )
+                .await
+                .map_err(pg_err)?;
+            Ok(affected > 0)
+        })
+    }
+
+    fn list_speech_prices<'a>(
+        &'a self,
+        provider: Option<&'a str>,
+    ) -> BoxFuture<'a, rusqlite::Result<Vec<SpeechPriceRecord>>> {
+        Box::pin(async move {
+            let client = self.pool.pick();
+            let rows = client
+                .query(
+                    "SELECT provider, model, price_per_million_chars, currency, updated_at FROM speech_prices
+                     WHERE ($1::text IS NULL OR provider = $1)
+                     ORDER BY provider, model",
+                    &[&provider],
+                )
+                .await
+                .map_err(pg_err)?;
+            Ok(rows.iter().map(pg_speech_price).collect())
+        })
+    }
+
+    fn upsert_speech_price<'a>(
+        &'a self,
+        price: SpeechPriceRecord,
+    ) -> BoxFuture<'a, rusqlite::Result<()>> {
+        Box::pin(async move {
+            let client = self.pool.pick();
+            client
+                .execute(
+                    "INSERT INTO speech_prices (provider, model, price_per_million_chars, currency, updated_at)
+                     VALUES ($1,$2,$3,$4,$5)
+                     ON CONFLICT (provider, model) DO UPDATE SET
+                        price_per_million_chars = EXCLUDED.price_per_million_chars,
+                        currency = EXCLUDED.currency,
+                        updated_at = EXCLUDED.updated_at",
+                    &[
+                        &price.provider,
+                        &price.model,
+                        &price.price_per_million_chars,
+                        &price.currency,
+                        &to_beijing_string(&price.updated_at),
+                    ],
+                )
+                .await
+                .map_err(pg_err)?;
+            Ok(())
+        })
+    }
 
-impl PgLogStore {
-    fn row_to_request_log(r: Row) -> RequestLog {
-        RequestLog {
-            id: pg_row_i64(&r, 0),
-            timestamp: pg_row_datetime_or_now(&r, 1),
-            method: pg_row_string(&r, 2),
-            path: pg_row_string(&r, 3),
-            request_type: pg_row_string(&r, 4),
-            requested_model: pg_row_opt_string(&r, 5),
-            effective_model: pg_row_opt_string(&r, 6),
-            model: pg_row_opt_string(&r, 7),
-            provider: pg_row_opt_string(&r, 8),
-            api_key: pg_row_opt_string(&r, 9),
-            status_code: pg_row_u16_or(&r, 10, 0),
-            response_time_ms: pg_row_i64_or(&r, 11, 0),
-            prompt_tokens: pg_row_u32_opt(&r, 12),
-            completion_tokens: pg_row_u32_opt(&r, 13),
-            total_tokens: pg_row_u32_opt(&r, 14),
-            cached_tokens: pg_row_u32_opt(&r, 15),
-            reasoning_tokens: pg_row_u32_opt(&r, 16),
-            error_message: pg_row_opt_string(&r, 17),
-            client_token: pg_row_opt_string(&r, 18),
-            user_id: pg_row_opt_string(&r, 19),
-            amount_spent: r.try_get::<usize, Option<f64>>(20).ok().flatten(),
-        }
+    fn delete_speech_price<'a>(
+        &'a self,
+        provider: &'a str,
+        model: &'a str,
+    ) -> BoxFuture<'a, rusqlite::Result<bool>> {
+        Box::pin(async move {
+            let client = self.pool.pick();
+            let affected = client
+                .execute(
+                    "DELETE FROM speech_prices WHERE provider = $1 AND model = $2",
+                    &[&provider, &model],
+                )
+                .await
+                .map_err(pg_err)?;
+            Ok(affected > 0)
+        })
     }
-}
 
-impl RequestLogStore for PgLogStore {
-    fn log_request<'a>(&'a self, log: RequestLog) -> BoxFuture<'a, rusqlite::Result<i64>> {
+    fn sum_provider_spend_since<'a>(
+        &'a self,
+        since: DateTime<Utc>,
+    ) -> BoxFuture<'a, rusqlite::Result<std::collections::HashMap<String, f64>>> {
         Box::pin(async move {
             let client = self.pool.pick();
-            let row = client
-                .query_one(
-                    "INSERT INTO request_logs (timestamp, method, path, request_type, requested_model, effective_model, model, provider, api_key, status_code, response_time_ms, prompt_tokens, completion_tokens, total_tokens, cached_tokens, reasoning_tokens, error_message, client_token, user_id, amount_spent)
-                     VALUES ($1,$2,$3,$4,$5,$6,$7,$8,$9,$10,$11,$12,$13,$14,$15,$16,$17,$18,$19,$20)
-                     RETURNING id",
-                    &[&to_beijing_string(&log.timestamp), &log.method, &log.path, &log.request_type, &log.requested_model, &log.effective_model, &log.model, &log.provider, &log.api_key, &i32::from(log.status_code), &log.response_time_ms, &log.prompt_tokens.map(|v| v as i32), &log.completion_tokens.map(|v| v as i32), &log.total_tokens.map(|v| v as i32), &log.cached_tokens.map(|v| v as i32), &log.reasoning_tokens.map(|v| v as i32), &log.error_message, &log.client_token, &log.user_id, &log.amount_spent],
+            let rows = client
+                .query(
+                    "SELECT provider, SUM(amount_spent) FROM request_logs
+                     WHERE timestamp >= $1 AND provider IS NOT NULL AND amount_spent IS NOT NULL
+                     GROUP BY provider",
+                    &[&to_beijing_string(&since)],
                 )
                 .await
                 .map_err(pg_err)?;
-            Ok(pg_row_i64_or(&row, 0, 0))
+            Ok(rows
+                .iter()
+                .map(|row| (pg_row_string(row, 0), pg_row_f64_or(row, 1, 0.0)))
+                .collect())
         })
     }
 
-    fn get_recent_logs_with_cursor<'a>(
+    fn add_provider_egress<'a>(
         &'a self,
-        limit: i32,
-        cursor: Option<i64>,
-    ) -> BoxFuture<'a, rusqlite::Result<Vec<RequestLog>>> {
+        rows: Vec<ProviderEgressDaily>,
+    ) -> BoxFuture<'a, rusqlite::Result<()>> {
         Box::pin(async move {
             let client = self.pool.pick();
-            let lim: i64 = limit as i64;
-            let rows = if let Some(cursor_id) = cursor {
-                client
-                    .query(
-                        "SELECT id, timestamp, method, path, request_type, requested_model, effective_model, model, provider, api_key, status_code, response_time_ms, prompt_tokens, completion_tokens, total_tokens, cached_tokens, reasoning_tokens, error_message, client_token, user_id, amount_spent FROM request_logs WHERE id < $1 ORDER BY id DESC LIMIT $2",
-                        &[&cursor_id, &lim],
-                    )
-                    .await
-                    .map_err(pg_err)?
-            } else {
+            for row in &rows {
                 client
-                    .query(
-                        "SELECT id, timestamp, method, path, request_type, requested_model, effective_model, model, provider, api_key, status_code, response_time_ms, prompt_tokens, completion_tokens, total_tokens, cached_tokens, reasoning_tokens, error_message, client_token, user_id, amount_spent FROM request_logs ORDER BY id DESC LIMIT $1",
-                        &[&lim],
+                    .execute(
+                        "INSERT INTO provider_egress_daily (day, provider, api_key, requests, request_bytes, response_bytes)
+                         VALUES ($1,$2,$3,$4,$5,$6)
+                         ON CONFLICT (day, provider, api_key) DO UPDATE SET
+                            requests = provider_egress_daily.requests + EXCLUDED.requests,
+                            request_bytes = provider_egress_daily.request_bytes + EXCLUDED.request_bytes,
+                            response_bytes = provider_egress_daily.response_bytes + EXCLUDED.response_bytes",
+                        &[
+                            &row.day,
+                            &row.provider,
+                            &row.api_key,
+                            &row.requests,
+                            &row.request_bytes,
+                            &row.response_bytes,
+                        ],
                     )
                     .await
-                    .map_err(pg_err)?
-            };
-            Ok(rows.into_iter().map(Self::row_to_request_log).collect())
+                    .map_err(pg_err)?;
+            }
+            Ok(())
         })
     }
 
-    fn get_request_logs<'a>(
+    fn add_token_request_counts<'a>(
         &'a self,
-        limit: i32,
-        cursor: Option<i64>,
-    ) -> BoxFuture<'a, rusqlite::Result<Vec<RequestLog>>> {
+        rows: Vec<TokenRequestCount>,
+    ) -> BoxFuture<'a, rusqlite::Result<()>> {
         Box::pin(async move {
             let client = self.pool.pick();
-            let lim: i64 = limit as i64;
-            let rows = if let Some(cursor_id) = cursor {
-                client
-                    .query(
-                        "SELECT id, timestamp, method, path, request_type, requested_model, effective_model, model, provider, api_key, status_code, response_time_ms, prompt_tokens, completion_tokens, total_tokens, cached_tokens, reasoning_tokens, error_message, client_token, user_id, amount_spent FROM request_logs WHERE id < $1 ORDER BY id DESC LIMIT $2",
-                        &[&cursor_id, &lim],
-                    )
-                    .await
-                    .map_err(pg_err)?
-            } else {
+            for row in &rows {
                 client
-                    .query(
-                        "SELECT id, timestamp, method, path, request_type, requested_model, effective_model, model, provider, api_key, status_code, response_time_ms, prompt_tokens, completion_tokens, total_tokens, cached_tokens, reasoning_tokens, error_message, client_token, user_id, amount_spent FROM request_logs ORDER BY id DESC LIMIT $1",
-                        &[&lim],
+                    .execute(
+                        "INSERT INTO token_request_counts (day, token_id, requests) VALUES ($1,$2,$3)
+                         ON CONFLICT (day, token_id) DO UPDATE SET
+                            requests = token_request_counts.requests + EXCLUDED.requests",
+                        &[&row.day, &row.token_id, &row.requests],
                     )
                     .await
-                    .map_err(pg_err)?
-            };
-            Ok(rows.into_iter().map(Self::row_to_request_log).collect())
+                    .map_err(pg_err)?;
+            }
+            Ok(())
         })
     }
 
-    fn get_logs_by_method_path<'a>(
+    fn sum_token_request_counts<'a>(
         &'a self,
-        method: &'a str,
-        path: &'a str,
-        limit: i32,
-        cursor: Option<i64>,
-    ) -> BoxFuture<'a, rusqlite::Result<Vec<RequestLog>>> {
+        day: &'a str,
+    ) -> BoxFuture<'a, rusqlite::Result<std::collections::HashMap<String, (i64, i64)>>> {
         Box::pin(async move {
             let client = self.pool.pick();
-            let lim: i64 = limit as i64;
-            let rows = if let Some(cursor_id) = cursor {
-                client
-                    .query(
-                        "SELECT id, timestamp, method, path, request_type, requested_model, effective_model, model, provider, api_key, status_code, response_time_ms, prompt_tokens, completion_tokens, total_tokens, cached_tokens, reasoning_tokens, error_message, client_token, user_id, amount_spent FROM request_logs WHERE method = $1 AND path = $2 AND id < $3 ORDER BY id DESC LIMIT $4",
-                        &[&method, &path, &cursor_id, &lim],
-                    )
-                    .await
-                    .map_err(pg_err)?
-            } else {
-                client
-                    .query(
-                        "SELECT id, timestamp, method, path, request_type, requested_model, effective_model, model, provider, api_key, status_code, response_time_ms, prompt_tokens, completion_tokens, total_tokens, cached_tokens, reasoning_tokens, error_message, client_token, user_id, amount_spent FROM request_logs WHERE method = $1 AND path = $2 ORDER BY id DESC LIMIT $3",
-                        &[&method, &path, &lim],
+            let rows = client
+                .query(
+                    "SELECT token_id, SUM(requests)::BIGINT, SUM(CASE WHEN day = $1 THEN requests ELSE 0 END)::BIGINT
+                     FROM token_request_counts GROUP BY token_id",
+                    &[&day],
+                )
+                .await
+                .map_err(pg_err)?;
+            Ok(rows
+                .iter()
+                .map(|row| {
+                    (
+                        pg_row_string(row, 0),
+                        (pg_row_i64_or(row, 1, 0), pg_row_i64_or(row, 2, 0)),
                     )
-                    .await
-                    .map_err(pg_err)?
-            };
-            Ok(rows.into_iter().map(Self::row_to_request_log).collect())
+                })
+                .collect())
         })
     }
 
-    fn get_request_log_by_id<'a>(
-        &'a self,
-        id: i64,
-    ) -> BoxFuture<'a, rusqlite::Result<Option<RequestLog>>> {
+    fn max_request_log_id<'a>(&'a self) -> BoxFuture<'a, rusqlite::Result<i64>> {
         Box::pin(async move {
             let client = self.pool.pick();
             let row = client
-                .query_opt(
-                    "SELECT id, timestamp, method, path, request_type, requested_model, effective_model, model, provider, api_key, status_code, response_time_ms, prompt_tokens, completion_tokens, total_tokens, cached_tokens, reasoning_tokens, error_message, client_token, user_id, amount_spent FROM request_logs WHERE id = $1 LIMIT 1",
-                    &[&id],
-                )
+                .query_one("SELECT COALESCE(MAX(id), 0)::BIGINT FROM request_logs", &[])
                 .await
                 .map_err(pg_err)?;
-            Ok(row.map(Self::row_to_request_log))
+            Ok(pg_row_i64_or(&row, 0, 0))
         })
     }
 
-    fn upsert_request_log_detail<'a>(
+    fn sum_token_usage_between<'a>(
         &'a self,
-        detail: RequestLogDetailRecord,
-    ) -> BoxFuture<'a, rusqlite::Result<()>> {
+        after_id: i64,
+        until_id: i64,
+        excluded_ids: &'a [i64],
+    ) -> BoxFuture<'a, rusqlite::Result<Vec<TokenUsageDelta>>> {
         Box::pin(async move {
             let client = self.pool.pick();
-            client
-                .execute(
-                    "INSERT INTO request_log_details (
-                        request_log_id, request_payload_snapshot, response_preview, upstream_status,
-                        fallback_triggered, fallback_reason, selected_provider, selected_key_id, first_token_latency_ms
-                    ) VALUES ($1,$2,$3,$4,$5,$6,$7,$8,$9)
-                    ON CONFLICT (request_log_id) DO UPDATE SET
-                        request_payload_snapshot = EXCLUDED.request_payload_snapshot,
-                        response_preview = EXCLUDED.response_preview,
-                        upstream_status = EXCLUDED.upstream_status,
-                        fallback_triggered = EXCLUDED.fallback_triggered,
-                        fallback_reason = EXCLUDED.fallback_reason,
-                        selected_provider = EXCLUDED.selected_provider,
-                        selected_key_id = EXCLUDED.selected_key_id,
-                        first_token_latency_ms = EXCLUDED.first_token_latency_ms",
-                    &[
-                        &detail.request_log_id,
-                        &detail.request_payload_snapshot,
-                        &detail.response_preview,
-                        &detail.upstream_status,
-                        &detail.fallback_triggered,
-                        &detail.fallback_reason,
-                        &detail.selected_provider,
-                        &detail.selected_key_id,
-                        &detail.first_token_latency_ms,
-                    ],
+            let excluded_ids = excluded_ids.to_vec();
+            let rows = client
+                .query(
+                    "SELECT client_token, COALESCE(SUM(amount_spent), 0)::DOUBLE PRECISION,
+                            COALESCE(SUM(prompt_tokens), 0)::BIGINT, COALESCE(SUM(completion_tokens), 0)::BIGINT,
+                            COALESCE(SUM(total_tokens), 0)::BIGINT
+                     FROM request_logs
+                     WHERE id > $1 AND id <= $2 AND client_token IS NOT NULL
+                       AND NOT (id = ANY($3))
+                     GROUP BY client_token",
+                    &[&after_id, &until_id, &excluded_ids],
                 )
                 .await
                 .map_err(pg_err)?;
-            Ok(())
+            Ok(rows
+                .iter()
+                .map(|row| TokenUsageDelta {
+                    token_id: pg_row_string(row, 0),
+                    amount_spent: pg_row_f64_or(row, 1, 0.0),
+                    prompt_tokens: pg_row_i64_or(row, 2, 0),
+                    completion_tokens: pg_row_i64_or(row, 3, 0),
+                    total_tokens: pg_row_i64_or(row, 4, 0),
+                })
+                .collect())
         })
     }
 
-    fn get_request_log_detail<'a>(
+    fn request_heatmap<'a>(
         &'a self,
-        request_log_id: i64,
-    ) -> BoxFuture<'a, rusqlite::Result<Option<RequestLogDetailRecord>>> {
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+        model: Option<&'a str>,
+        provider: Option<&'a str>,
+    ) -> BoxFuture<'a, rusqlite::Result<Vec<RequestHeatmapCell>>> {
         Box::pin(async move {
             let client = self.pool.pick();
-            let row = client
-                .query_opt(
-                    "SELECT request_log_id, request_payload_snapshot, response_preview, upstream_status, fallback_triggered, fallback_reason, selected_provider, selected_key_id, first_token_latency_ms FROM request_log_details WHERE request_log_id = $1 LIMIT 1",
-                    &[&request_log_id],
+            let rows = client
+                .query(
+                    "SELECT EXTRACT(DOW FROM LEFT(timestamp, 10)::date)::bigint AS weekday,
+                            SUBSTRING(timestamp, 12, 2)::bigint AS hour,
+                            COUNT(*)::bigint,
+                            COUNT(*) FILTER (WHERE status_code >= 400)::bigint
+                     FROM request_logs
+                     WHERE method = 'POST' AND path = '/v1/chat/completions'
+                       AND timestamp >= $1 AND timestamp < $2
+                       AND ($3::text IS NULL OR COALESCE(NULLIF(effective_model, ''), model) = $3)
+                       AND ($4::text IS NULL OR provider = $4)
+                     GROUP BY weekday, hour
+                     ORDER BY weekday, hour",
+                    &[
+                        &to_beijing_string(&since),
+                        &to_beijing_string(&until),
+                        &model,
+                        &provider,
+                    ],
                 )
                 .await
                 .map_err(pg_err)?;
-            Ok(row.map(|row| RequestLogDetailRecord {
-                request_log_id: pg_row_i64_or(&row, 0, 0),
-                request_payload_snapshot: pg_row_opt_string(&row, 1),
-                response_preview: pg_row_opt_string(&row, 2),
-                upstream_status: pg_row_i64(&row, 3),
-                fallback_triggered: row.try_get::<usize, Option<bool>>(4).ok().flatten(),
-                fallback_reason: pg_row_opt_string(&row, 5),
-                selected_provider: pg_row_opt_string(&row, 6),
-                selected_key_id: pg_row_opt_string(&row, 7),
-                first_token_latency_ms: pg_row_i64(&row, 8),
-            }))
+            Ok(rows
+                .iter()
+                .map(|row| RequestHeatmapCell {
+                    weekday: pg_row_i64_or(row, 0, 0).clamp(0, 6) as u8,
+                    hour: pg_row_i64_or(row, 1, 0).clamp(0, 23) as u8,
+                    requests: pg_row_i64_or(row, 2, 0).max(0) as u64,
+                    errors: pg_row_i64_or(row, 3, 0).max(0) as u64,
+                })
+                .collect())
         })
     }
 
-    fn save_compare_run<'a>(
+    fn token_activity<'a>(
         &'a self,
-        run: StoredCompareRun,
-    ) -> BoxFuture<'a, rusqlite::Result<()>> {
+        client_token: &'a str,
+        since: DateTime<Utc>,
+        top_models: u32,
+        recent_errors: u32,
+    ) -> BoxFuture<'a, rusqlite::Result<TokenActivity>> {
         Box::pin(async move {
             let client = self.pool.pick();
-            client
-                .execute(
-                    "INSERT INTO compare_runs (id, user_id, source_request_id, created_at, result_json)
-                     VALUES ($1,$2,$3,$4,$5)
-                     ON CONFLICT (id) DO UPDATE SET
-                        user_id = EXCLUDED.user_id,
-                        source_request_id = EXCLUDED.source_request_id,
-                        created_at = EXCLUDED.created_at,
-                        result_json = EXCLUDED.result_json",
-                    &[
-                        &run.id,
-                        &run.user_id,
-                        &run.source_request_id,
-                        &to_beijing_string(&run.created_at),
-                        &run.result_json,
-                    ],
+            let since = to_beijing_string(&since);
+            let last_used = client
+                .query_one(
+                    "SELECT MAX(timestamp) FROM request_logs WHERE client_token = $1",
+                    &[&client_token],
                 )
                 .await
                 .map_err(pg_err)?;
-            Ok(())
+            let daily = client
+                .query(
+                    "SELECT LEFT(timestamp, 10) AS day, COUNT(*)::bigint,
+                            COUNT(*) FILTER (WHERE status_code >= 400)::bigint,
+                            COALESCE(SUM(amount_spent), 0)::double precision,
+                            COALESCE(SUM(total_tokens), 0)::bigint
+                     FROM request_logs
+                     WHERE client_token = $1 AND timestamp >= $2
+                     GROUP BY day
+                     ORDER BY day",
+                    &[&client_token, &since],
+                )
+                .await
+                .map_err(pg_err)?;
+            let models = client
+                .query(
+                    "SELECT COALESCE(NULLIF(effective_model, ''), model) AS m, COUNT(*)::bigint AS cnt,
+                            COALESCE(SUM(amount_spent), 0)::double precision,
+                            COALESCE(SUM(total_tokens), 0)::bigint
+                     FROM request_logs
+                     WHERE client_token = $1 AND timestamp >= $2
+                       AND COALESCE(NULLIF(effective_model, ''), model) IS NOT NULL
+                     GROUP BY m
+                     ORDER BY cnt DESC, m
+                     LIMIT $3",
+                    &[&client_token, &since, &(top_models as i64)],
+                )
+                .await
+                .map_err(pg_err)?;
+            let errors = client
+                .query(
+                    "SELECT timestamp, path, COALESCE(NULLIF(effective_model, ''), model), status_code,
+                            error_message
+                     FROM request_logs
+                     WHERE client_token = $1 AND status_code >= 400
+                     ORDER BY id DESC
+                     LIMIT $2",
+                    &[&client_token, &(recent_errors as i64)],
+                )
+                .await
+                .map_err(pg_err)?;
+            Ok(TokenActivity {
+                last_used_at: pg_row_opt_datetime(&last_used, 0),
+                daily: daily
+                    .iter()
+                    .map(|row| TokenActivityDay {
+                        date: pg_row_string(row, 0),
+                        requests: pg_row_i64_or(row, 1, 0).max(0) as u64,
+                        errors: pg_row_i64_or(row, 2, 0).max(0) as u64,
+                        amount_spent: pg_row_f64_or(row, 3, 0.0),
+                        total_tokens: pg_row_i64_or(row, 4, 0),
+                    })
+                    .collect(),
+                top_models: models
+                    .iter()
+                    .map(|row| TokenModelUsage {
+                        model: pg_row_string(row, 0),
+                        requests: pg_row_i64_or(row, 1, 0).max(0) as u64,
+                        amount_spent: pg_row_f64_or(row, 2, 0.0),
+                        total_tokens: pg_row_i64_or(row, 3, 0),
+                    })
+                    .collect(),
+                recent_errors: errors
+                    .iter()
+                    .map(|row| TokenRecentError {
+                        timestamp: pg_row_datetime_or_now(row, 0),
+                        path: pg_row_string(row, 1),
+                        model: pg_row_opt_string(row, 2),
+                        status_code: pg_row_u16_or(row, 3, 0),
+                        error_message: pg_row_opt_string(row, 4),
+                    })
+                    .collect(),
+            })
         })
     }
 
-    fn get_compare_run<'a>(
+    fn list_provider_egress<'a>(
         &'a self,
-        id: &'a str,
-    ) -> BoxFuture<'a, rusqlite::Result<Option<StoredCompareRun>>> {
+        since_day: &'a str,
+        until_day: &'a str,
+    ) -> BoxFuture<'a, rusqlite::Result<Vec<ProviderEgressDaily>>> {
         Box::pin(async move {
             let client = self.pool.pick();
-            let row = client
-                .query_opt(
-                    "SELECT id, user_id, source_request_id, created_at, result_json FROM compare_runs WHERE id = $1 LIMIT 1",
-                    &[&id],
+            let rows = client
+                .query(
+                    "SELECT day, provider, api_key, requests, request_bytes, response_bytes FROM provider_egress_daily WHERE day >= $1 AND day <= $2 ORDER BY day, provider, api_key",
+                    &[&since_day, &until_day],
                 )
                 .await
                 .map_err(pg_err)?;
-            Ok(row.map(|row| StoredCompareRun {
-                id: pg_row_string(&row, 0),
-                user_id: pg_row_string(&row, 1),
-                source_request_id: pg_row_i64_or(&row, 2, 0),
-                created_at: pg_row_datetime_or_now(&row, 3),
-                result_json: pg_row_string(&row, 4),
-            }))
+            Ok(rows
+                .iter()
+                .map(|row| ProviderEgressDaily {
+                    day: pg_row_string(row, 0),
+                    provider: pg_row_string(row, 1),
+                    api_key: pg_row_string(row, 2),
+                    requests: pg_row_i64_or(row, 3, 0),
+                    request_bytes: pg_row_i64_or(row, 4, 0),
+                    response_bytes: pg_row_i64_or(row, 5, 0),
+                })
+                .collect())
         })
     }
 
@@ -1763,7 +3696,7 @@ impl RequestLogStore for PgLogStore {
             let lim: i64 = limit as i64;
             let rows = client
                 .query(
-                    "SELECT id, timestamp, method, path, request_type, requested_model, effective_model, model, provider, api_key, status_code, response_time_ms, prompt_tokens, completion_tokens, total_tokens, cached_tokens, reasoning_tokens, error_message, client_token, user_id, amount_spent FROM request_logs WHERE client_token = $1 ORDER BY id DESC LIMIT $2",
+                    "SELECT id, timestamp, method, path, request_type, requested_model, effective_model, model, provider, api_key, status_code, response_time_ms, prompt_tokens, completion_tokens, total_tokens, cached_tokens, reasoning_tokens, error_message, client_token, user_id, amount_spent, pre_dispatch_ms, upstream_ms, post_process_ms, prompt_language, content_category FROM request_logs WHERE client_token = $1 ORDER BY id DESC LIMIT $2",
                     &[&token, &lim],
                 )
                 .await
@@ -1873,13 +3806,54 @@ impl RequestLogStore for PgLogStore {
         })
     }
 
+    fn purge_request_logs_before<'a>(
+        &'a self,
+        cutoff: DateTime<Utc>,
+    ) -> BoxFuture<'a, rusqlite::Result<u64>> {
+        Box::pin(async move {
+            let client = self.pool.pick();
+            let affected = client
+                .execute(
+                    "DELETE FROM request_logs WHERE timestamp < $1",
+                    &[&to_beijing_string(&cutoff)],
+                )
+                .await
+                .map_err(pg_err)?;
+            Ok(affected)
+        })
+    }
+
+    fn last_request_at_by_client_token<'a>(
+        &'a self,
+    ) -> BoxFuture<'a, rusqlite::Result<std::collections::HashMap<String, DateTime<Utc>>>> {
+        Box::pin(async move {
+            let client = self.pool.pick();
+            let rows = client
+                .query(
+                    "SELECT client_token, MAX(timestamp) FROM request_logs
+                     WHERE client_token IS NOT NULL AND client_token <> ''
+                     GROUP BY client_token",
+                    &[],
+                )
+                .await
+                .map_err(pg_err)?;
+            Ok(rows
+                .iter()
+                .filter_map(|row| {
+                    let ts = parse_datetime_string(&pg_row_string(row, 1)).ok()?;
+                    Some((pg_row_string(row, 0), ts))
+                })
+                .collect())
+        })
+    }
+
     fn log_provider_op<'a>(&'a self, op: ProviderOpLog) -> BoxFuture<'a, rusqlite::Result<i64>> {
         Box::pin(async move {
             let client = self.pool.pick();
             let res = client
                 .execute(
-                    "INSERT INTO provider_ops_logs (timestamp, operation, provider, details) VALUES ($1,$2,$3,$4)",
-                    &[&to_beijing_string(&op.timestamp), &op.operation, &op.provider, &op.details],
+                    "INSERT INTO provider_ops_logs (timestamp, operation, provider, details, actor) VALUES ($1,$2,$3,$4,$5)",
+                    &[&to_beijing_string(&op.timestamp), &op.operation, &op.provider, &op.details, &op.actor],
                 )
                 .await
                 .map_err(pg_err)?;
@@ -1898,7 +3872,7 @@ impl RequestLogStore for PgLogStore {
             let rows = if let Some(cursor_id) = cursor {
                 client
                     .query(
-                        "SELECT id, timestamp, operation, provider, details FROM provider_ops_logs WHERE id < $1 ORDER BY id DESC LIMIT $2",
+                        "SELECT id, timestamp, operation, provider, details, actor FROM provider_ops_logs WHERE id < $1 ORDER BY id DESC LIMIT $2",
                         &[&cursor_id, &lim],
                     )
                     .await
@@ -1906,37 +3880,59 @@ impl RequestLogStore for PgLogStore {
             } else {
                 client
                     .query(
-                        "SELECT id, timestamp, operation, provider, details FROM provider_ops_logs ORDER BY id DESC LIMIT $1",
+                        "SELECT id, timestamp, operation, provider, details, actor FROM provider_ops_logs ORDER BY id DESC LIMIT $1",
                         &[&lim],
                     )
                     .await
                     .map_err(pg_err)?
             };
-            Ok(rows
-                .into_iter()
-                .map(|row| {
-                    // `Row::get` panics on type mismatch; prefer `try_get` to avoid
-                    // disconnecting the client if the DB column type differs (e.g. TIMESTAMPTZ).
-                    let id = row
-                        .try_get::<usize, i64>(0)
-                        .ok()
-                        .or_else(|| row.try_get::<usize, i32>(0).ok().map(|v| v as i64));
-                    let timestamp = if let Ok(ts) = row.try_get::<usize, DateTime<Utc>>(1) {
-                        ts
-                    } else if let Ok(raw) = row.try_get::<usize, String>(1) {
-                        parse_datetime_string(&raw).unwrap_or_else(|_| chrono::Utc::now())
-                    } else {
-                        chrono::Utc::now()
-                    };
-                    ProviderOpLog {
-                        id,
-                        timestamp,
-                        operation: row.try_get(2).unwrap_or_default(),
-                        provider: row.try_get(3).ok(),
-                        details: row.try_get(4).ok(),
-                    }
-                })
-                .collect())
+            Ok(rows.iter().map(pg_row_to_provider_op).collect())
+        })
+    }
+
+    fn get_provider_ops_logs_for_provider<'a>(
+        &'a self,
+        provider: &'a str,
+        since: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+        limit: i32,
+        cursor: Option<i64>,
+    ) -> BoxFuture<'a, rusqlite::Result<Vec<ProviderOpLog>>> {
+        Box::pin(async move {
+            let client = self.pool.pick();
+            let since = since.as_ref().map(to_beijing_string);
+            let until = until.as_ref().map(to_beijing_string);
+            let lim: i64 = limit as i64;
+            let rows = client
+                .query(
+                    "SELECT id, timestamp, operation, provider, details, actor FROM provider_ops_logs
+                     WHERE provider = $1
+                       AND ($2::TEXT IS NULL OR timestamp >= $2)
+                       AND ($3::TEXT IS NULL OR timestamp < $3)
+                       AND ($4::BIGINT IS NULL OR id < $4)
+                     ORDER BY id DESC LIMIT $5",
+                    &[&provider, &since, &until, &cursor, &lim],
+                )
+                .await
+                .map_err(pg_err)?;
+            Ok(rows.iter().map(pg_row_to_provider_op).collect())
+        })
+    }
+
+    fn purge_provider_ops_logs_before<'a>(
+        &'a self,
+        cutoff: DateTime<Utc>,
+    ) -> BoxFuture<'a, rusqlite::Result<u64>> {
+        Box::pin(async move {
+            let client = self.pool.pick();
+            let affected = client
+                .execute(
+                    "DELETE FROM provider_ops_logs WHERE timestamp < $1",
+                    &[&to_beijing_string(&cutoff)],
+                )
+                .await
+                .map_err(pg_err)?;
+            Ok(affected)
         })
     }
 
@@ -2014,6 +4010,97 @@ impl RequestLogStore for PgLogStore {
         })
     }
 
+    fn upsert_model_prices<'a>(
+        &'a self,
+        prices: Vec<ModelPriceUpsert>,
+    ) -> BoxFuture<'a, rusqlite::Result<()>> {
+        Box::pin(async move {
+            if prices.is_empty() {
+                return Ok(());
+            }
+            let mut providers = Vec::with_capacity(prices.len());
+            let mut models = Vec::with_capacity(prices.len());
+            let mut prompts = Vec::with_capacity(prices.len());
+            let mut completions = Vec::with_capacity(prices.len());
+            let mut currencies = Vec::with_capacity(prices.len());
+            let mut model_types = Vec::with_capacity(prices.len());
+            let mut sources = Vec::with_capacity(prices.len());
+            let mut statuses = Vec::with_capacity(prices.len());
+            let mut synced = Vec::with_capacity(prices.len());
+            let mut expires = Vec::with_capacity(prices.len());
+            for price in prices {
+                providers.push(price.provider);
+                models.push(price.model);
+                prompts.push(price.prompt_price_per_million);
+                completions.push(price.completion_price_per_million);
+                currencies.push(price.currency);
+                model_types.push(price.model_type);
+                sources.push(pg_price_source_str(price.source).to_string());
+                statuses.push(pg_price_status_str(price.status).to_string());
+                synced.push(price.synced_at.as_ref().map(to_iso8601_utc_string));
+                expires.push(price.expires_at.as_ref().map(to_iso8601_utc_string));
+            }
+            // 单条语句内先 UPDATE 已有行再 INSERT 其余行，整体原子生效
+            let client = self.pool.pick();
+            client
+                .execute(
+                    "WITH data AS (
+                        SELECT * FROM UNNEST(
+                            $1::text[], $2::text[], $3::float8[], $4::float8[], $5::text[],
+                            $6::text[], $7::text[], $8::text[], $9::text[], $10::text[]
+                        ) AS d(provider, model, prompt, completion, currency, model_type,
+                               source, status, synced_at, expires_at)
+                    ), updated AS (
+                        UPDATE model_prices m
+                        SET prompt_price_per_million = d.prompt,
+                            completion_price_per_million = d.completion,
+                            currency = d.currency,
+                            model_type = d.model_type,
+                            source = d.source,
+                            status = d.status,
+                            synced_at = d.synced_at,
+                            expires_at = d.expires_at
+                        FROM data d
+                        WHERE m.provider = d.provider AND m.model = d.model
+                        RETURNING m.provider, m.model
+                    )
+                    INSERT INTO model_prices (
+                        provider,
+                        model,
+                        prompt_price_per_million,
+                        completion_price_per_million,
+                        currency,
+                        model_type,
+                        source,
+                        status,
+                        synced_at,
+                        expires_at
+                    )
+                    SELECT d.provider, d.model, d.prompt, d.completion, d.currency, d.model_type,
+                           d.source, d.status, d.synced_at, d.expires_at
+                    FROM data d
+                    WHERE NOT EXISTS (
+                        SELECT 1 FROM updated u WHERE u.provider = d.provider AND u.model = d.model
+                    )",
+                    &[
+                        &providers,
+                        &models,
+                        &prompts,
+                        &completions,
+                        &currencies,
+                        &model_types,
+                        &sources,
+                        &statuses,
+                        &synced,
+                        &expires,
+                    ],
+                )
+                .await
+                .map_err(pg_err)?;
+            Ok(())
+        })
+    }
+
     fn get_model_price<'a>(
         &'a self,
         provider: &'a str,
@@ -2051,59 +4138,69 @@ impl RequestLogStore for PgLogStore {
         provider: Option<&'a str>,
     ) -> BoxFuture<'a, rusqlite::Result<Vec<ModelPriceRecord>>> {
         Box::pin(async move {
-            let mut out = Vec::new();
-            if let Some(p) = provider {
-                let client = self.pool.pick();
-                let rows = client
-                    .query(
-                        "SELECT provider, model, prompt_price_per_million, completion_price_per_million, currency, model_type, source, status, synced_at, expires_at FROM model_prices WHERE provider = $1 ORDER BY model",
-                        &[&p],
-                    )
-                    .await
-                    .map_err(pg_err)?;
-                for r in rows {
-                    out.push(ModelPriceRecord {
-                        provider: pg_row_string(&r, 0),
-                        model: pg_row_string(&r, 1),
-                        prompt_price_per_million: pg_row_f64_or(&r, 2, 0.0),
-                        completion_price_per_million: pg_row_f64_or(&r, 3, 0.0),
-                        currency: pg_row_opt_string(&r, 4),
-                        model_type: pg_row_opt_string(&r, 5),
-                        source: pg_price_source(&r, 6),
-                        status: pg_price_status(&r, 7),
-                        synced_at: pg_row_opt_string(&r, 8)
-                            .and_then(|raw| parse_datetime_string(&raw).ok()),
-                        expires_at: pg_row_opt_string(&r, 9)
-                            .and_then(|raw| parse_datetime_string(&raw).ok()),
-                    });
-                }
+            let (filter, order) = match provider {
+                Some(_) => ("WHERE provider = $1", "ORDER BY model"),
+                None => ("", "ORDER BY provider, model"),
+            };
+            let params: Vec<&(dyn tokio_postgres::types::ToSql + Sync)> = match provider.as_ref() {
+                Some(p) => vec![p],
+                None => Vec::new(),
+            };
+            let rows = self
+                .pool
+                .pick()
+                .query(
+                    &format!(
+                        "SELECT provider, model, prompt_price_per_million, completion_price_per_million, currency, model_type, source, status, synced_at, expires_at FROM model_prices {} {}",
+                        filter, order
+                    ),
+                    &params,
+                )
+                .await
+                .map_err(pg_err)?;
+            Ok(rows.iter().map(pg_row_to_model_price).collect())
+        })
+    }
+
+    fn list_model_prices_page<'a>(
+        &'a self,
+        provider: Option<&'a str>,
+        page: &'a PageRequest,
+    ) -> BoxFuture<'a, rusqlite::Result<(Vec<ModelPriceRecord>, u64)>> {
+        Box::pin(async move {
+            let filter = if provider.is_some() {
+                "WHERE provider = $1"
             } else {
-                let client = self.pool.pick();
-                let rows = client
-                    .query(
-                        "SELECT provider, model, prompt_price_per_million, completion_price_per_million, currency, model_type, source, status, synced_at, expires_at FROM model_prices ORDER BY provider, model",
-                        &[],
-                    )
-                    .await
-                    .map_err(pg_err)?;
-                for r in rows {
-                    out.push(ModelPriceRecord {
-                        provider: pg_row_string(&r, 0),
-                        model: pg_row_string(&r, 1),
-                        prompt_price_per_million: pg_row_f64_or(&r, 2, 0.0),
-                        completion_price_per_million: pg_row_f64_or(&r, 3, 0.0),
-                        currency: pg_row_opt_string(&r, 4),
-                        model_type: pg_row_opt_string(&r, 5),
-                        source: pg_price_source(&r, 6),
-                        status: pg_price_status(&r, 7),
-                        synced_at: pg_row_opt_string(&r, 8)
-                            .and_then(|raw| parse_datetime_string(&raw).ok()),
-                        expires_at: pg_row_opt_string(&r, 9)
-                            .and_then(|raw| parse_datetime_string(&raw).ok()),
-                    });
-                }
-            }
-            Ok(out)
+                ""
+            };
+            let params: Vec<&(dyn tokio_postgres::types::ToSql + Sync)> = match provider.as_ref() {
+                Some(p) => vec![p],
+                None => Vec::new(),
+            };
+            let client = self.pool.pick();
+            let total: i64 = client
+                .query_one(
+                    &format!("SELECT COUNT(*) FROM model_prices {}", filter),
+                    &params,
+                )
+                .await
+                .map_err(pg_err)?
+                .get(0);
+            let rows = client
+                .query(
+                    &format!(
+                        "SELECT provider, model, prompt_price_per_million, completion_price_per_million, currency, model_type, source, status, synced_at, expires_at FROM model_prices {} {}",
+                        filter,
+                        page.sql_tail(&["provider", "model"])
+                    ),
+                    &params,
+                )
+                .await
+                .map_err(pg_err)?;
+            Ok((
+                rows.iter().map(pg_row_to_model_price).collect(),
+                total.max(0) as u64,
+            ))
         })
     }
 
@@ -2422,6 +4519,60 @@ impl ModelCache for PgLogStore {
     }
 }
 
+/// provider_ops_logs 表的一行（id, timestamp, operation, provider, details, actor）
+fn pg_row_to_provider_op(row: &Row) -> ProviderOpLog {
+    // `Row::get` panics on type mismatch; prefer `try_get` to avoid
+    // disconnecting the client if the DB column type differs (e.g. TIMESTAMPTZ).
+    let id = row
+        .try_get::<usize, i64>(0)
+        .ok()
+        .or_else(|| row.try_get::<usize, i32>(0).ok().map(|v| v as i64));
+    let timestamp = if let Ok(ts) = row.try_get::<usize, DateTime<Utc>>(1) {
+        ts
+    } else if let Ok(raw) = row.try_get::<usize, String>(1) {
+        parse_datetime_string(&raw).unwrap_or_else(|_| chrono::Utc::now())
+    } else {
+        chrono::Utc::now()
+    };
+    ProviderOpLog {
+        id,
+        timestamp,
+        operation: row.try_get(2).unwrap_or_default(),
+        provider: row.try_get(3).ok(),
+        details: row.try_get(4).ok(),
+        actor: row.try_get(5).ok(),
+    }
+}
+
+/// providers 表的一行（name, display_name, collection, api_type, base_url, models_endpoint,
+/// provider_config, enabled, created_at, updated_at）
+fn pg_row_to_provider(r: &Row) -> Provider {
+    let created_at = r
+        .try_get::<usize, DateTime<Utc>>(8)
+        .ok()
+        .or_else(|| pg_row_opt_string(r, 8).and_then(|s| parse_datetime_string(&s).ok()));
+    let updated_at = r
+        .try_get::<usize, DateTime<Utc>>(9)
+        .ok()
+        .or_else(|| pg_row_opt_string(r, 9).and_then(|s| parse_datetime_string(&s).ok()));
+    let api_type_raw = pg_row_string(r, 3);
+    let (api_type, api_type_raw) = ProviderType::from_storage_with_raw(&api_type_raw);
+    Provider {
+        name: pg_row_string(r, 0),
+        display_name: pg_row_opt_string(r, 1),
+        collection: pg_row_string(r, 2),
+        api_type,
+        api_type_raw,
+        base_url: pg_row_string(r, 4),
+        api_keys: Vec::new(),
+        models_endpoint: pg_row_opt_string(r, 5),
+        provider_config: ProviderConfig::from_storage_json(pg_row_opt_string(r, 6)),
+        enabled: pg_row_bool_or(r, 7, true),
+        created_at,
+        updated_at,
+    }
+}
+
 impl ProviderStore for PgLogStore {
     fn insert_provider<'a>(
         &'a self,
@@ -2568,32 +4719,35 @@ impl ProviderStore for PgLogStore {
                 )
                 .await
                 .map_err(pg_err)?;
-            let mut out = Vec::new();
-            for r in rows {
-                let created_at = r.try_get::<usize, DateTime<Utc>>(8).ok().or_else(|| {
-                    pg_row_opt_string(&r, 8).and_then(|s| parse_datetime_string(&s).ok())
-                });
-                let updated_at = r.try_get::<usize, DateTime<Utc>>(9).ok().or_else(|| {
-                    pg_row_opt_string(&r, 9).and_then(|s| parse_datetime_string(&s).ok())
-                });
-                let api_type_raw = pg_row_string(&r, 3);
-                let (api_type, api_type_raw) = ProviderType::from_storage_with_raw(&api_type_raw);
-                out.push(Provider {
-                    name: pg_row_string(&r, 0),
-                    display_name: pg_row_opt_string(&r, 1),
-                    collection: pg_row_string(&r, 2),
-                    api_type,
-                    api_type_raw,
-                    base_url: pg_row_string(&r, 4),
-                    api_keys: Vec::new(),
-                    models_endpoint: pg_row_opt_string(&r, 5),
-                    provider_config: ProviderConfig::from_storage_json(pg_row_opt_string(&r, 6)),
-                    enabled: pg_row_bool_or(&r, 7, true),
-                    created_at,
-                    updated_at,
-                });
-            }
-            Ok(out)
+            Ok(rows.iter().map(pg_row_to_provider).collect())
+        })
+    }
+
+    fn list_providers_page<'a>(
+        &'a self,
+        page: &'a PageRequest,
+    ) -> BoxFuture<'a, rusqlite::Result<(Vec<Provider>, u64)>> {
+        Box::pin(async move {
+            let client = self.pool.pick();
+            let total: i64 = client
+                .query_one("SELECT COUNT(*) FROM providers", &[])
+                .await
+                .map_err(pg_err)?
+                .get(0);
+            let rows = client
+                .query(
+                    &format!(
+                        "SELECT name, display_name, collection, api_type, base_url, models_endpoint, provider_config, enabled, created_at, updated_at FROM providers {}",
+                        page.sql_tail(&["name"])
+                    ),
+                    &[],
+                )
+                .await
+                .map_err(pg_err)?;
+            Ok((
+                rows.iter().map(pg_row_to_provider).collect(),
+                total.max(0) as u64,
+            ))
         })
     }
 
@@ -2810,7 +4964,7 @@ impl ProviderStore for PgLogStore {
             let client = self.pool.pick();
             let rows = client
                 .query(
-                    "SELECT key_value, enc, active, weight FROM provider_keys WHERE provider = $1 ORDER BY created_at",
+                    "SELECT key_value, enc, active, weight, spend_cap, rpm_limit, tpm_limit, openai_organization, openai_project, labels FROM provider_keys WHERE provider = $1 ORDER BY created_at",
                     &[&provider],
                 )
                 .await
@@ -2821,6 +4975,9 @@ impl ProviderStore for PgLogStore {
                 let enc = pg_row_bool_or(&r, 1, false);
                 let active = pg_row_bool_or(&r, 2, true);
                 let weight = pg_row_u32_or(&r, 3, 1);
+                let spend_cap = r.try_get::<usize, Option<f64>>(4).ok().flatten();
+                let rpm_limit = pg_row_u32_opt(&r, 5);
+                let tpm_limit = pg_row_u32_opt(&r, 6);
                 let decrypted =
                     crate::crypto::unprotect(strategy, provider, &value, enc).unwrap_or_default();
                 if !decrypted.is_empty() {
@@ -2828,6 +4985,19 @@ impl ProviderStore for PgLogStore {
                         value: decrypted,
                         active,
                         weight: if weight >= 1 { weight } else { 1 },
+                        spend_cap,
+                        rpm_limit,
+                        tpm_limit,
+                        openai_account: OpenAIAccountHeaders {
+                            openai_organization: r
+                                .try_get::<usize, Option<String>>(7)
+                                .ok()
+                                .flatten(),
+                            openai_project: r.try_get::<usize, Option<String>>(8).ok().flatten(),
+                        },
+                        labels: pg_row_opt_string(&r, 9)
+                            .and_then(|raw| serde_json::from_str(&raw).ok())
+                            .unwrap_or_default(),
                     });
                 }
             }
@@ -2849,47 +5019,160 @@ impl ProviderStore for PgLogStore {
                 )
                 .await
                 .map_err(pg_err)?;
-            let mut out = Vec::new();
-            for r in rows {
-                let value = pg_row_string(&r, 0);
-                let enc = pg_row_bool_or(&r, 1, false);
-                let created_at_raw = pg_row_string(&r, 4);
-                let created_at = parse_datetime_string(&created_at_raw).map_err(pg_err)?;
-
-                let decrypted =
-                    crate::crypto::unprotect(strategy, provider, &value, enc).unwrap_or_default();
-                if !decrypted.is_empty() {
-                    out.push(ProviderKeyEntryWithCreatedAt {
-                        value: decrypted,
-                        created_at,
-                    });
-                }
+            let mut out = Vec::new();
+            for r in rows {
+                let value = pg_row_string(&r, 0);
+                let enc = pg_row_bool_or(&r, 1, false);
+                let created_at_raw = pg_row_string(&r, 4);
+                let created_at = parse_datetime_string(&created_at_raw).map_err(pg_err)?;
+
+                let decrypted =
+                    crate::crypto::unprotect(strategy, provider, &value, enc).unwrap_or_default();
+                if !decrypted.is_empty() {
+                    out.push(ProviderKeyEntryWithCreatedAt {
+                        value: decrypted,
+                        created_at,
+                    });
+                }
+            }
+            Ok(out)
+        })
+    }
+
+    fn set_provider_key_weight<'a>(
+        &'a self,
+        provider: &'a str,
+        key: &'a str,
+        weight: u32,
+        strategy: &'a Option<KeyLogStrategy>,
+    ) -> BoxFuture<'a, rusqlite::Result<bool>> {
+        Box::pin(async move {
+            // 将 u32 转换为 i32，若超出范围则返回业务错误
+            let weight_i32: i32 = weight.try_into().map_err(|_| {
+                rusqlite::Error::SqliteFailure(
+                    rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_CONSTRAINT),
+                    Some(format!("weight {} exceeds i32::MAX", weight)),
+                )
+            })?;
+            let (stored, enc) = crate::crypto::protect(strategy, provider, key);
+            let client = self.pool.pick();
+            let mut affected = client
+                .execute(
+                    "UPDATE provider_keys SET weight = $3 WHERE provider = $1 AND key_value = $2",
+                    &[&provider, &stored, &weight_i32],
+                )
+                .await
+                .map_err(pg_err)?;
+            if enc {
+                let client = self.pool.pick();
+                affected += client
+                    .execute(
+                        "UPDATE provider_keys SET weight = $3 WHERE provider = $1 AND key_value = $2",
+                        &[&provider, &key, &weight_i32],
+                    )
+                    .await
+                    .map_err(pg_err)?;
+            }
+            Ok(affected > 0)
+        })
+    }
+
+    fn set_provider_key_limits<'a>(
+        &'a self,
+        provider: &'a str,
+        key: &'a str,
+        spend_cap: Option<f64>,
+        rpm_limit: Option<u32>,
+        tpm_limit: Option<u32>,
+        strategy: &'a Option<KeyLogStrategy>,
+    ) -> BoxFuture<'a, rusqlite::Result<bool>> {
+        Box::pin(async move {
+            let to_i32 = |name: &str, value: Option<u32>| -> rusqlite::Result<Option<i32>> {
+                value
+                    .map(|v| {
+                        i32::try_from(v).map_err(|_| {
+                            rusqlite::Error::SqliteFailure(
+                                rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_CONSTRAINT),
+                                Some(format!("{} {} exceeds i32::MAX", name, v)),
+                            )
+                        })
+                    })
+                    .transpose()
+            };
+            let rpm_limit = to_i32("rpm_limit", rpm_limit)?;
+            let tpm_limit = to_i32("tpm_limit", tpm_limit)?;
+            let (stored, enc) = crate::crypto::protect(strategy, provider, key);
+            let client = self.pool.pick();
+            let mut affected = client
+                .execute(
+                    "UPDATE provider_keys SET spend_cap = $3, rpm_limit = $4, tpm_limit = $5 WHERE provider = $1 AND key_value = $2",
+                    &[&provider, &stored, &spend_cap, &rpm_limit, &tpm_limit],
+                )
+                .await
+                .map_err(pg_err)?;
+            if enc {
+                let client = self.pool.pick();
+                affected += client
+                    .execute(
+                        "UPDATE provider_keys SET spend_cap = $3, rpm_limit = $4, tpm_limit = $5 WHERE provider = $1 AND key_value = $2",
+                        &[&provider, &key, &spend_cap, &rpm_limit, &tpm_limit],
+                    )
+                    .await
+                    .map_err(pg_err)?;
+            }
+            Ok(affected > 0)
+        })
+    }
+
+    fn set_provider_key_openai_headers<'a>(
+        &'a self,
+        provider: &'a str,
+        key: &'a str,
+        headers: &'a OpenAIAccountHeaders,
+        strategy: &'a Option<KeyLogStrategy>,
+    ) -> BoxFuture<'a, rusqlite::Result<bool>> {
+        Box::pin(async move {
+            let (stored, enc) = crate::crypto::protect(strategy, provider, key);
+            let org = &headers.openai_organization;
+            let project = &headers.openai_project;
+            let client = self.pool.pick();
+            let mut affected = client
+                .execute(
+                    "UPDATE provider_keys SET openai_organization = $3, openai_project = $4 WHERE provider = $1 AND key_value = $2",
+                    &[&provider, &stored, org, project],
+                )
+                .await
+                .map_err(pg_err)?;
+            if enc {
+                let client = self.pool.pick();
+                affected += client
+                    .execute(
+                        "UPDATE provider_keys SET openai_organization = $3, openai_project = $4 WHERE provider = $1 AND key_value = $2",
+                        &[&provider, &key, org, project],
+                    )
+                    .await
+                    .map_err(pg_err)?;
             }
-            Ok(out)
+            Ok(affected > 0)
         })
     }
 
-    fn set_provider_key_weight<'a>(
+    fn set_provider_key_labels<'a>(
         &'a self,
         provider: &'a str,
         key: &'a str,
-        weight: u32,
+        labels: &'a BTreeMap<String, String>,
         strategy: &'a Option<KeyLogStrategy>,
     ) -> BoxFuture<'a, rusqlite::Result<bool>> {
         Box::pin(async move {
-            // 将 u32 转换为 i32，若超出范围则返回业务错误
-            let weight_i32: i32 = weight.try_into().map_err(|_| {
-                rusqlite::Error::SqliteFailure(
-                    rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_CONSTRAINT),
-                    Some(format!("weight {} exceeds i32::MAX", weight)),
-                )
-            })?;
             let (stored, enc) = crate::crypto::protect(strategy, provider, key);
+            let labels = (!labels.is_empty())
+                .then(|| serde_json::to_string(labels).unwrap_or_else(|_| "{}".into()));
             let client = self.pool.pick();
             let mut affected = client
                 .execute(
-                    "UPDATE provider_keys SET weight = $3 WHERE provider = $1 AND key_value = $2",
-                    &[&provider, &stored, &weight_i32],
+                    "UPDATE provider_keys SET labels = $3 WHERE provider = $1 AND key_value = $2",
+                    &[&provider, &stored, &labels],
                 )
                 .await
                 .map_err(pg_err)?;
@@ -2897,8 +5180,8 @@ impl ProviderStore for PgLogStore {
                 let client = self.pool.pick();
                 affected += client
                     .execute(
-                        "UPDATE provider_keys SET weight = $3 WHERE provider = $1 AND key_value = $2",
-                        &[&provider, &key, &weight_i32],
+                        "UPDATE provider_keys SET labels = $3 WHERE provider = $1 AND key_value = $2",
+                        &[&provider, &key, &labels],
                     )
                     .await
                     .map_err(pg_err)?;
@@ -3047,6 +5330,110 @@ impl OrganizationStore for PgLogStore {
             Ok(())
         })
     }
+
+    fn set_organization_admin<'a>(
+        &'a self,
+        organization_id: &'a str,
+        user_id: &'a str,
+    ) -> BoxFuture<'a, rusqlite::Result<()>> {
+        Box::pin(async move {
+            let client = self.pool.pick();
+            client
+                .execute(
+                    "INSERT INTO organization_admins (user_id, organization_id, created_at) VALUES ($1, $2, $3)
+                     ON CONFLICT (user_id) DO UPDATE SET organization_id = EXCLUDED.organization_id",
+                    &[&user_id, &organization_id, &Utc::now()],
+                )
+                .await
+                .map_err(pg_err)?;
+            Ok(())
+        })
+    }
+
+    fn remove_organization_admin<'a>(
+        &'a self,
+        organization_id: &'a str,
+        user_id: &'a str,
+    ) -> BoxFuture<'a, rusqlite::Result<bool>> {
+        Box::pin(async move {
+            let client = self.pool.pick();
+            let affected = client
+                .execute(
+                    "DELETE FROM organization_admins WHERE user_id = $1 AND organization_id = $2",
+                    &[&user_id, &organization_id],
+                )
+                .await
+                .map_err(pg_err)?;
+            Ok(affected > 0)
+        })
+    }
+
+    fn list_organization_admins<'a>(
+        &'a self,
+        organization_id: &'a str,
+    ) -> BoxFuture<'a, rusqlite::Result<Vec<String>>> {
+        Box::pin(async move {
+            let client = self.pool.pick();
+            let rows = client
+                .query(
+                    "SELECT user_id FROM organization_admins WHERE organization_id = $1 ORDER BY created_at, user_id",
+                    &[&organization_id],
+                )
+                .await
+                .map_err(pg_err)?;
+            Ok(rows.iter().map(|row| pg_row_string(row, 0)).collect())
+        })
+    }
+
+    fn get_admin_organization<'a>(
+        &'a self,
+        user_id: &'a str,
+    ) -> BoxFuture<'a, rusqlite::Result<Option<String>>> {
+        Box::pin(async move {
+            let client = self.pool.pick();
+            let row = client
+                .query_opt(
+                    "SELECT organization_id FROM organization_admins WHERE user_id = $1",
+                    &[&user_id],
+                )
+                .await
+                .map_err(pg_err)?;
+            Ok(row.map(|r| pg_row_string(&r, 0)))
+        })
+    }
+}
+
+impl SettingsStore for PgLogStore {
+    fn get_setting<'a>(&'a self, key: &'a str) -> BoxFuture<'a, rusqlite::Result<Option<String>>> {
+        Box::pin(async move {
+            let client = self.pool.pick();
+            let row = client
+                .query_opt("SELECT value FROM gateway_settings WHERE key = $1", &[&key])
+                .await
+                .map_err(pg_err)?;
+            Ok(row.map(|r| pg_row_string(&r, 0)))
+        })
+    }
+
+    fn set_setting<'a>(
+        &'a self,
+        key: &'a str,
+        value: &'a str,
+    ) -> BoxFuture<'a, rusqlite::Result<()>> {
+        Box::pin(async move {
+            let client = self.pool.pick();
+            client
+                .execute(
+                    "INSERT INTO gateway_settings (key, value, updated_at)
+                     VALUES ($1, $2, $3)
+                     ON CONFLICT (key) DO UPDATE SET value = EXCLUDED.value, updated_at = EXCLUDED.updated_at",
+                    &[&key, &value, &to_beijing_string(&Utc::now())],
+                )
+                .await
+                .map_err(pg_err)?;
+            Ok(())
+        })
+    }
 }
 
 impl LoginStore for PgLogStore {
@@ -3061,7 +5448,7 @@ impl LoginStore for PgLogStore {
             let updated = client
                 .execute(
                     "UPDATE admin_public_keys
-                     SET public_key=$2, comment=$3, enabled=$4, created_at=$5, last_used_at=$6
+                     SET public_key=$2, comment=$3, enabled=$4, created_at=$5, last_used_at=$6, expires_at=$7
                      WHERE fingerprint=$1",
                     &[
                         &key.fingerprint,
@@ -3070,6 +5457,7 @@ impl LoginStore for PgLogStore {
                         &key.enabled,
                         &key.created_at,
                         &key.last_used_at,
+                        &key.expires_at,
                     ],
                 )
                 .await
@@ -3079,9 +5467,9 @@ impl LoginStore for PgLogStore {
                 let client = self.pool.pick();
                 client
                     .execute(
-                        "INSERT INTO admin_public_keys (fingerprint, public_key, comment, enabled, created_at, last_used_at)
-                         VALUES ($1, $2, $3, $4, $5, $6)",
-                        &[&key.fingerprint, &key.public_key, &comment, &key.enabled, &key.created_at, &key.last_used_at],
+                        "INSERT INTO admin_public_keys (fingerprint, public_key, comment, enabled, created_at, last_used_at, expires_at)
+                         VALUES ($1, $2, $3, $4, $5, $6, $7)",
+                        &[&key.fingerprint, &key.public_key, &comment, &key.enabled, &key.created_at, &key.last_used_at, &key.expires_at],
                     )
                     .await
                     .map_err(pg_err)?;
@@ -3099,7 +5487,7 @@ impl LoginStore for PgLogStore {
             let client = self.pool.pick();
             let row = client
                 .query_opt(
-                    "SELECT fingerprint, public_key, comment, enabled, created_at, last_used_at FROM admin_public_keys WHERE fingerprint = $1",
+                    "SELECT fingerprint, public_key, comment, enabled, created_at, last_used_at, expires_at FROM admin_public_keys WHERE fingerprint = $1",
                     &[&fingerprint],
             )
                 .await
@@ -3111,6 +5499,7 @@ impl LoginStore for PgLogStore {
                 enabled: pg_row_bool_or(&r, 3, true),
                 created_at: pg_row_datetime_or_now(&r, 4),
                 last_used_at: pg_row_opt_datetime(&r, 5),
+                expires_at: pg_row_opt_datetime(&r, 6),
             });
             Ok(rec)
         })
@@ -3139,7 +5528,7 @@ impl LoginStore for PgLogStore {
             let client = self.pool.pick();
             let rows = client
                 .query(
-                    "SELECT fingerprint, public_key, comment, enabled, created_at, last_used_at FROM admin_public_keys",
+                    "SELECT fingerprint, public_key, comment, enabled, created_at, last_used_at, expires_at FROM admin_public_keys",
                     &[],
                 )
                 .await
@@ -3153,12 +5542,32 @@ impl LoginStore for PgLogStore {
                     enabled: pg_row_bool_or(&r, 3, true),
                     created_at: pg_row_datetime_or_now(&r, 4),
                     last_used_at: pg_row_opt_datetime(&r, 5),
+                    expires_at: pg_row_opt_datetime(&r, 6),
                 });
             }
             Ok(out)
         })
     }
 
+    fn update_admin_key_status<'a>(
+        &'a self,
+        fingerprint: &'a str,
+        enabled: bool,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> BoxFuture<'a, rusqlite::Result<bool>> {
+        Box::pin(async move {
+            let client = self.pool.pick();
+            let rows = client
+                .execute(
+                    "UPDATE admin_public_keys SET enabled = $2, expires_at = $3 WHERE fingerprint = $1",
+                    &[&fingerprint, &enabled, &expires_at],
+                )
+                .await
+                .map_err(pg_err)?;
+            Ok(rows > 0)
+        })
+    }
+
     fn delete_admin_key<'a>(
         &'a self,
         fingerprint: &'a str,
@@ -3409,6 +5818,70 @@ impl LoginStore for PgLogStore {
         })
     }
 
+    fn insert_user_login_code<'a>(
+        &'a self,
+        code: &'a UserLoginCodeRecord,
+    ) -> BoxFuture<'a, rusqlite::Result<()>> {
+        Box::pin(async move {
+            let client = self.pool.pick();
+            client
+                .execute(
+                    "INSERT INTO user_login_codes (code_hash, user_id, token_id, created_at, expires_at, used_at)
+                     VALUES ($1, $2, $3, $4, $5, $6)",
+                    &[&code.code_hash, &code.user_id, &code.token_id, &code.created_at, &code.expires_at, &code.used_at],
+                )
+                .await
+                .map_err(pg_err)?;
+            Ok(())
+        })
+    }
+
+    fn count_user_login_codes_since<'a>(
+        &'a self,
+        token_id: &'a str,
+        since: DateTime<Utc>,
+    ) -> BoxFuture<'a, rusqlite::Result<u64>> {
+        Box::pin(async move {
+            let client = self.pool.pick();
+            let row = client
+                .query_one(
+                    "SELECT COUNT(*) FROM user_login_codes WHERE token_id = $1 AND created_at >= $2",
+                    &[&token_id, &since],
+                )
+                .await
+                .map_err(pg_err)?;
+            let count: i64 = row.get(0);
+            Ok(count as u64)
+        })
+    }
+
+    fn redeem_user_login_code<'a>(
+        &'a self,
+        code_hash: &'a str,
+        now: DateTime<Utc>,
+    ) -> BoxFuture<'a, rusqlite::Result<Option<UserLoginCodeRecord>>> {
+        Box::pin(async move {
+            let client = self.pool.pick();
+            let row = client
+                .query_opt(
+                    "UPDATE user_login_codes SET used_at = $2
+                     WHERE code_hash = $1 AND used_at IS NULL AND expires_at > $2
+                     RETURNING code_hash, user_id, token_id, created_at, expires_at",
+                    &[&code_hash, &now],
+                )
+                .await
+                .map_err(pg_err)?;
+            Ok(row.map(|r| UserLoginCodeRecord {
+                code_hash: pg_row_string(&r, 0),
+                user_id: pg_row_string(&r, 1),
+                token_id: pg_row_string(&r, 2),
+                created_at: pg_row_datetime_or_now(&r, 3),
+                expires_at: pg_row_datetime_or_now(&r, 4),
+                used_at: Some(now),
+            }))
+        })
+    }
+
     fn insert_web_session<'a>(
         &'a self,
         session: &'a WebSessionRecord,
@@ -3417,11 +5890,12 @@ impl LoginStore for PgLogStore {
             let client = self.pool.pick();
             let fingerprint = session.fingerprint.as_deref();
             let issued_by = session.issued_by_code.as_deref();
+            let user_id = session.user_id.as_deref();
             client
                 .execute(
-                    "INSERT INTO web_sessions (session_id, fingerprint, created_at, expires_at, revoked, issued_by_code)
-                     VALUES ($1, $2, $3, $4, $5, $6)",
-                    &[&session.session_id, &fingerprint, &session.created_at, &session.expires_at, &session.revoked, &issued_by],
+                    "INSERT INTO web_sessions (session_id, fingerprint, created_at, expires_at, revoked, issued_by_code, user_id)
+                     VALUES ($1, $2, $3, $4, $5, $6, $7)",
+                    &[&session.session_id, &fingerprint, &session.created_at, &session.expires_at, &session.revoked, &issued_by, &user_id],
                 )
                 .await
                 .map_err(pg_err)?;
@@ -3437,20 +5911,12 @@ impl LoginStore for PgLogStore {
             let client = self.pool.pick();
             let row = client
                 .query_opt(
-                    "SELECT session_id, fingerprint, created_at, expires_at, revoked, issued_by_code FROM web_sessions WHERE session_id = $1",
+                    "SELECT session_id, fingerprint, created_at, expires_at, revoked, issued_by_code, last_seen_at, user_id FROM web_sessions WHERE session_id = $1",
                     &[&session_id],
             )
                 .await
                 .map_err(pg_err)?;
-            let rec = row.map(|r| WebSessionRecord {
-                session_id: pg_row_string(&r, 0),
-                fingerprint: pg_row_opt_string(&r, 1),
-                created_at: pg_row_datetime_or_now(&r, 2),
-                expires_at: pg_row_datetime_or_now(&r, 3),
-                revoked: pg_row_bool_or(&r, 4, false),
-                issued_by_code: pg_row_opt_string(&r, 5),
-            });
-            Ok(rec)
+            Ok(row.map(|r| pg_web_session(&r)))
         })
     }
 
@@ -3470,6 +5936,123 @@ impl LoginStore for PgLogStore {
             Ok(rows > 0)
         })
     }
+
+    fn touch_web_session<'a>(
+        &'a self,
+        session_id: &'a str,
+        when: DateTime<Utc>,
+    ) -> BoxFuture<'a, rusqlite::Result<()>> {
+        Box::pin(async move {
+            let client = self.pool.pick();
+            client
+                .execute(
+                    "UPDATE web_sessions SET last_seen_at = $2 WHERE session_id = $1",
+                    &[&session_id, &when],
+                )
+                .await
+                .map_err(pg_err)?;
+            Ok(())
+        })
+    }
+
+    fn list_web_sessions<'a>(&'a self) -> BoxFuture<'a, rusqlite::Result<Vec<WebSessionRecord>>> {
+        Box::pin(async move {
+            let client = self.pool.pick();
+            let rows = client
+                .query(
+                    "SELECT session_id, fingerprint, created_at, expires_at, revoked, issued_by_code, last_seen_at, user_id FROM web_sessions WHERE revoked = FALSE ORDER BY created_at DESC",
+                    &[],
+                )
+                .await
+                .map_err(pg_err)?;
+            Ok(rows.iter().map(pg_web_session).collect())
+        })
+    }
+
+    fn get_admin_mfa<'a>(
+        &'a self,
+        user_id: &'a str,
+    ) -> BoxFuture<'a, rusqlite::Result<Option<AdminMfaRecord>>> {
+        Box::pin(async move {
+            let client = self.pool.pick();
+            let row = client
+                .query_opt(
+                    "SELECT user_id, totp_secret, totp_enabled, recovery_codes, last_totp_step, failed_attempts, locked_until, updated_at FROM admin_mfa WHERE user_id = $1",
+                    &[&user_id],
+                )
+                .await
+                .map_err(pg_err)?;
+            Ok(row.map(|r| AdminMfaRecord {
+                user_id: pg_row_string(&r, 0),
+                totp_secret: pg_row_opt_string(&r, 1),
+                totp_enabled: pg_row_bool_or(&r, 2, false),
+                recovery_code_hashes: pg_row_opt_string(&r, 3)
+                    .and_then(|s| serde_json::from_str(&s).ok())
+                    .unwrap_or_default(),
+                last_totp_step: pg_row_i64(&r, 4),
+                failed_attempts: pg_row_u32_or(&r, 5, 0),
+                locked_until: pg_row_opt_datetime(&r, 6),
+                updated_at: pg_row_datetime_or_now(&r, 7),
+            }))
+        })
+    }
+
+    fn upsert_admin_mfa<'a>(
+        &'a self,
+        record: &'a AdminMfaRecord,
+    ) -> BoxFuture<'a, rusqlite::Result<()>> {
+        Box::pin(async move {
+            let client = self.pool.pick();
+            let recovery =
+                serde_json::to_string(&record.recovery_code_hashes).unwrap_or_else(|_| "[]".into());
+            let failed = record.failed_attempts as i32;
+            client
+                .execute(
+                    "INSERT INTO admin_mfa (user_id, totp_secret, totp_enabled, recovery_codes, last_totp_step, failed_attempts, locked_until, updated_at)
+                     VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                     ON CONFLICT (user_id) DO UPDATE SET totp_secret = EXCLUDED.totp_secret, totp_enabled = EXCLUDED.totp_enabled,
+                        recovery_codes = EXCLUDED.recovery_codes, last_totp_step = EXCLUDED.last_totp_step,
+                        failed_attempts = EXCLUDED.failed_attempts, locked_until = EXCLUDED.locked_until, updated_at = EXCLUDED.updated_at",
+                    &[
+                        &record.user_id,
+                        &record.totp_secret,
+                        &record.totp_enabled,
+                        &recovery,
+                        &record.last_totp_step,
+                        &failed,
+                        &record.locked_until,
+                        &record.updated_at,
+                    ],
+                )
+                .await
+                .map_err(pg_err)?;
+            Ok(())
+        })
+    }
+
+    fn delete_admin_mfa<'a>(&'a self, user_id: &'a str) -> BoxFuture<'a, rusqlite::Result<bool>> {
+        Box::pin(async move {
+            let client = self.pool.pick();
+            let rows = client
+                .execute("DELETE FROM admin_mfa WHERE user_id = $1", &[&user_id])
+                .await
+                .map_err(pg_err)?;
+            Ok(rows > 0)
+        })
+    }
+}
+
+fn pg_web_session(r: &Row) -> WebSessionRecord {
+    WebSessionRecord {
+        session_id: pg_row_string(r, 0),
+        fingerprint: pg_row_opt_string(r, 1),
+        created_at: pg_row_datetime_or_now(r, 2),
+        expires_at: pg_row_datetime_or_now(r, 3),
+        revoked: pg_row_bool_or(r, 4, false),
+        issued_by_code: pg_row_opt_string(r, 5),
+        last_seen_at: pg_row_opt_datetime(r, 6),
+        user_id: pg_row_opt_string(r, 7),
+    }
 }
 
 fn provider_type_to_str(t: &ProviderType) -> &'static str {
@@ -3659,6 +6242,8 @@ mod tests {
                 cached_tokens: None,
                 reasoning_tokens: None,
                 error_message: None,
+                latency: Default::default(),
+                profile: Default::default(),
             },
         )
         .await
@@ -3687,6 +6272,8 @@ mod tests {
                 cached_tokens: None,
                 reasoning_tokens: None,
                 error_message: None,
+                latency: Default::default(),
+                profile: Default::default(),
             },
         )
         .await
@@ -3699,12 +6286,12 @@ mod tests {
         assert_eq!(logs.len(), 1);
         assert_eq!(logs[0].id, Some(first_id));
 
-        let path_logs = RequestLogStore::get_logs_by_method_path(
+        let path_logs = RequestLogStore::get_logs_with_columns(
             &store,
-            "POST",
-            "/v1/chat/completions",
             10,
             Some(second_id),
+            Some(("POST", "/v1/chat/completions")),
+            &crate::logging::types::LogColumns::all(),
         )
         .await
         .unwrap();
@@ -3719,6 +6306,7 @@ mod tests {
                 operation: "provider_create".into(),
                 provider: Some("openai".into()),
                 details: Some("first".into()),
+                actor: None,
             },
         )
         .await
@@ -3731,6 +6319,7 @@ mod tests {
                 operation: "provider_update".into(),
                 provider: Some("openai".into()),
                 details: Some("second".into()),
+                actor: None,
             },
         )
         .await
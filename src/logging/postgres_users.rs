@@ -353,4 +353,30 @@ impl UserStore for PgLogStore {
             .map_err(|e| GatewayError::Config(format!("DB error: {}", e)))?;
         Ok(row_opt.map(|row| row.get::<usize, f64>(0)))
     }
+
+    async fn get_budget(&self, user_id: &str) -> Result<Option<f64>, GatewayError> {
+        let client = self.pool.pick();
+        let row_opt = client
+            .query_opt("SELECT budget_amount FROM users WHERE id = $1", &[&user_id])
+            .await
+            .map_err(|e| GatewayError::Config(format!("DB error: {}", e)))?;
+        Ok(row_opt.and_then(|row| row.get::<usize, Option<f64>>(0)))
+    }
+
+    async fn set_budget(
+        &self,
+        user_id: &str,
+        max_amount: Option<f64>,
+    ) -> Result<bool, GatewayError> {
+        let client = self.pool.pick();
+        let now = Utc::now();
+        let updated = client
+            .execute(
+                "UPDATE users SET budget_amount = $2, updated_at = $3 WHERE id = $1",
+                &[&user_id, &max_amount, &now],
+            )
+            .await
+            .map_err(|e| GatewayError::Config(format!("DB error: {}", e)))?;
+        Ok(updated > 0)
+    }
 }
@@ -0,0 +1,231 @@
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::sync::{Mutex, OnceLock};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tokio::sync::broadcast;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::Layer;
+use tracing_subscriber::layer::Context;
+
+/// 内存中保留的最近日志条数
+const RING_CAPACITY: usize = 2000;
+const TAIL_CHANNEL_CAPACITY: usize = 512;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ServerLogEntry {
+    pub seq: u64,
+    pub timestamp: DateTime<Utc>,
+    pub level: String,
+    pub target: String,
+    pub message: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub fields: String,
+}
+
+struct RingState {
+    entries: VecDeque<ServerLogEntry>,
+    next_seq: u64,
+}
+
+pub struct ServerLogBuffer {
+    state: Mutex<RingState>,
+    tail: broadcast::Sender<ServerLogEntry>,
+}
+
+impl ServerLogBuffer {
+    fn new() -> Self {
+        let (tail, _) = broadcast::channel(TAIL_CHANNEL_CAPACITY);
+        Self {
+            state: Mutex::new(RingState {
+                entries: VecDeque::with_capacity(RING_CAPACITY),
+                next_seq: 1,
+            }),
+            tail,
+        }
+    }
+
+    fn push(&self, level: &Level, target: &str, message: String, fields: String) {
+        let entry = {
+            let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+            let entry = ServerLogEntry {
+                seq: state.next_seq,
+                timestamp: Utc::now(),
+                level: level.as_str().to_ascii_lowercase(),
+                target: target.to_string(),
+                message,
+                fields,
+            };
+            state.next_seq += 1;
+            if state.entries.len() >= RING_CAPACITY {
+                state.entries.pop_front();
+            }
+            state.entries.push_back(entry.clone());
+            entry
+        };
+        // 没有订阅者时发送失败是正常情况
+        let _ = self.tail.send(entry);
+    }
+
+    /// 按过滤条件返回最近的日志（按时间正序，最多 limit 条）
+    pub fn recent(&self, filter: &ServerLogFilter, limit: usize) -> Vec<ServerLogEntry> {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let mut out: Vec<ServerLogEntry> = state
+            .entries
+            .iter()
+            .rev()
+            .filter(|e| filter.matches(e))
+            .take(limit)
+            .cloned()
+            .collect();
+        out.reverse();
+        out
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<ServerLogEntry> {
+        self.tail.subscribe()
+    }
+}
+
+/// 全局日志缓冲区（tracing layer 与管理接口共享）
+pub fn server_log_buffer() -> &'static ServerLogBuffer {
+    static BUFFER: OnceLock<ServerLogBuffer> = OnceLock::new();
+    BUFFER.get_or_init(ServerLogBuffer::new)
+}
+
+fn level_rank(level: &str) -> Option<u8> {
+    match level.trim().to_ascii_lowercase().as_str() {
+        "trace" => Some(0),
+        "debug" => Some(1),
+        "info" => Some(2),
+        "warn" | "warning" => Some(3),
+        "error" => Some(4),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ServerLogFilter {
+    /// 最低级别（含）；例如 warn 会同时返回 warn 与 error
+    pub min_level: Option<u8>,
+    /// target 前缀（模块路径），例如 `gateway::server`
+    pub module: Option<String>,
+}
+
+impl ServerLogFilter {
+    pub fn parse(level: Option<&str>, module: Option<&str>) -> Result<Self, String> {
+        let min_level = match level.map(str::trim).filter(|s| !s.is_empty()) {
+            Some(raw) => Some(level_rank(raw).ok_or_else(|| format!("invalid level: {}", raw))?),
+            None => None,
+        };
+        let module = module
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(str::to_string);
+        Ok(Self { min_level, module })
+    }
+
+    pub fn matches(&self, entry: &ServerLogEntry) -> bool {
+        if let Some(min) = self.min_level
+            && level_rank(&entry.level).unwrap_or(0) < min
+        {
+            return false;
+        }
+        if let Some(module) = self.module.as_deref()
+            && !entry.target.starts_with(module)
+        {
+            return false;
+        }
+        true
+    }
+}
+
+#[derive(Default)]
+struct EventVisitor {
+    message: String,
+    fields: String,
+}
+
+impl Visit for EventVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message.push_str(value);
+        } else {
+            self.push_field(field, format_args!("{}", value));
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.message, "{:?}", value);
+        } else {
+            self.push_field(field, format_args!("{:?}", value));
+        }
+    }
+}
+
+impl EventVisitor {
+    fn push_field(&mut self, field: &Field, value: std::fmt::Arguments<'_>) {
+        if !self.fields.is_empty() {
+            self.fields.push(' ');
+        }
+        let _ = write!(self.fields, "{}={}", field.name(), value);
+    }
+}
+
+/// 将 tracing 事件写入内存环形缓冲区的 layer
+pub struct RingBufferLayer;
+
+impl<S: Subscriber> Layer<S> for RingBufferLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut visitor = EventVisitor::default();
+        event.record(&mut visitor);
+        let meta = event.metadata();
+        server_log_buffer().push(meta.level(), meta.target(), visitor.message, visitor.fields);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recent_applies_level_and_module_filters() {
+        let buffer = ServerLogBuffer::new();
+        buffer.push(
+            &Level::INFO,
+            "gateway::server",
+            "started".into(),
+            String::new(),
+        );
+        buffer.push(
+            &Level::WARN,
+            "gateway::routing",
+            "slow".into(),
+            "ms=5".into(),
+        );
+        buffer.push(
+            &Level::ERROR,
+            "gateway::server",
+            "boom".into(),
+            String::new(),
+        );
+
+        let all = buffer.recent(&ServerLogFilter::default(), 10);
+        assert_eq!(all.len(), 3);
+        assert_eq!(all[0].message, "started");
+
+        let warn = ServerLogFilter::parse(Some("warn"), None).unwrap();
+        let items = buffer.recent(&warn, 10);
+        assert_eq!(items.len(), 2);
+
+        let server = ServerLogFilter::parse(None, Some("gateway::server")).unwrap();
+        let items = buffer.recent(&server, 1);
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].message, "boom");
+
+        assert!(ServerLogFilter::parse(Some("loud"), None).is_err());
+    }
+}
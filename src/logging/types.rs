@@ -6,8 +6,15 @@ pub const REQ_TYPE_CHAT_ONCE: &str = "chat_once";
 pub const REQ_TYPE_CHAT_STREAM: &str = "chat_stream";
 pub const REQ_TYPE_CHAT_REPLAY: &str = "chat_replay";
 pub const REQ_TYPE_CHAT_COMPARE: &str = "chat_compare";
+pub const REQ_TYPE_CHAT_PLAN: &str = "chat_plan";
+pub const REQ_TYPE_CHAT_SANDBOX: &str = "chat_sandbox";
+pub const REQ_TYPE_CHAT_FAULT_INJECTED: &str = "chat_fault_injected";
+pub const REQ_TYPE_CHAT_IDEMPOTENT_REPLAY: &str = "chat_idempotent_replay";
+pub const REQ_TYPE_CHAT_SEMANTIC_CACHE_HIT: &str = "chat_semantic_cache_hit";
+pub const REQ_TYPE_IMAGE_GENERATION: &str = "image_generation";
 pub const REQ_TYPE_RECHARGE: &str = "recharge";
 pub const REQ_TYPE_MODELS_LIST: &str = "models_list";
+pub const REQ_TYPE_PRICING_CATALOG: &str = "pricing_catalog";
 pub const REQ_TYPE_PROVIDER_MODELS_LIST: &str = "provider_models_list";
 pub const REQ_TYPE_PROVIDER_MODELS_BASEURL_LIST: &str = "provider_models_baseurl_list";
 pub const REQ_TYPE_PROVIDER_KEY_ADD: &str = "provider_key_add";
@@ -17,8 +24,34 @@ pub const REQ_TYPE_PROVIDER_KEY_TOGGLE: &str = "provider_key_toggle";
 pub const REQ_TYPE_PROVIDER_KEY_CONFIG_GET: &str = "provider_key_config_get";
 pub const REQ_TYPE_PROVIDER_KEY_CONFIG_SET: &str = "provider_key_config_set";
 pub const REQ_TYPE_PROVIDER_KEY_WEIGHT_SET: &str = "provider_key_weight_set";
+pub const REQ_TYPE_PROVIDER_KEY_LIMITS_SET: &str = "provider_key_limits_set";
+pub const REQ_TYPE_PROVIDER_KEY_OPENAI_HEADERS_SET: &str = "provider_key_openai_headers_set";
+pub const REQ_TYPE_PROVIDER_KEY_LABELS_SET: &str = "provider_key_labels_set";
+pub const REQ_TYPE_PROVIDER_BUDGET_LIST: &str = "provider_budget_list";
+pub const REQ_TYPE_PROVIDER_BUDGET_SET: &str = "provider_budget_set";
+pub const REQ_TYPE_PROVIDER_BUDGET_DELETE: &str = "provider_budget_delete";
+pub const REQ_TYPE_IMAGE_PRICE_LIST: &str = "image_price_list";
+pub const REQ_TYPE_IMAGE_PRICE_SET: &str = "image_price_set";
+pub const REQ_TYPE_IMAGE_PRICE_DELETE: &str = "image_price_delete";
+pub const REQ_TYPE_AUDIO_TRANSCRIPTION: &str = "audio_transcription";
+pub const REQ_TYPE_AUDIO_PRICE_LIST: &str = "audio_price_list";
+pub const REQ_TYPE_AUDIO_PRICE_SET: &str = "audio_price_set";
+pub const REQ_TYPE_AUDIO_PRICE_DELETE: &str = "audio_price_delete";
+pub const REQ_TYPE_AUDIO_SPEECH: &str = "audio_speech";
+pub const REQ_TYPE_SPEECH_PRICE_LIST: &str = "speech_price_list";
+pub const REQ_TYPE_SPEECH_PRICE_SET: &str = "speech_price_set";
+pub const REQ_TYPE_SPEECH_PRICE_DELETE: &str = "speech_price_delete";
+/// 供应商月度花费越过告警阈值（写入供应商操作日志）
+pub const REQ_TYPE_PROVIDER_BUDGET_THRESHOLD: &str = "provider_budget_threshold";
+pub const REQ_TYPE_MAINTENANCE_WINDOW_LIST: &str = "maintenance_window_list";
+pub const REQ_TYPE_MAINTENANCE_WINDOW_CREATE: &str = "maintenance_window_create";
+pub const REQ_TYPE_MAINTENANCE_WINDOW_DELETE: &str = "maintenance_window_delete";
+/// 维护窗口开始/结束时调度器自动调整路由（写入供应商操作日志）
+pub const REQ_TYPE_PROVIDER_MAINTENANCE_START: &str = "provider_maintenance_start";
+pub const REQ_TYPE_PROVIDER_MAINTENANCE_END: &str = "provider_maintenance_end";
 pub const REQ_TYPE_PROVIDER_CACHE_UPDATE: &str = "provider_models_cache_update";
 pub const REQ_TYPE_PROVIDER_CACHE_DELETE: &str = "provider_models_cache_delete";
+pub const REQ_TYPE_PROVIDER_CACHE_RECONCILE: &str = "provider_models_cache_reconcile";
 pub const REQ_TYPE_PROVIDER_CREATE: &str = "provider_create";
 pub const REQ_TYPE_PROVIDER_UPDATE: &str = "provider_update";
 pub const REQ_TYPE_PROVIDER_DELETE: &str = "provider_delete";
@@ -26,12 +59,21 @@ pub const REQ_TYPE_PROVIDER_GET: &str = "provider_get";
 pub const REQ_TYPE_PROVIDER_LIST: &str = "provider_list";
 pub const REQ_TYPE_PROVIDER_ENABLED_SET: &str = "provider_enabled_set";
 pub const REQ_TYPE_PROVIDER_FAVORITE_SET: &str = "provider_favorite_set";
+pub const REQ_TYPE_PROVIDER_COLLECTION_ASSIGN: &str = "provider_collection_assign";
 pub const REQ_TYPE_PROVIDER_MODEL_REDIRECTS_LIST: &str = "provider_model_redirects_list";
 pub const REQ_TYPE_PROVIDER_MODEL_REDIRECTS_SET: &str = "provider_model_redirects_set";
 pub const REQ_TYPE_PROVIDER_MODEL_REDIRECTS_DELETE: &str = "provider_model_redirects_delete";
 pub const REQ_TYPE_PROVIDER_MODEL_TEST: &str = "provider_model_test";
+pub const REQ_TYPE_PROVIDER_DIAGNOSE: &str = "provider_diagnose";
+pub const REQ_TYPE_PROVIDER_TLS_CERTIFICATE: &str = "provider_tls_certificate";
+pub const REQ_TYPE_PROVIDER_CAPABILITIES: &str = "provider_capabilities";
+pub const REQ_TYPE_ADMIN_TOKEN_TEST: &str = "admin_token_test";
+pub const REQ_TYPE_ADMIN_COMPARE: &str = "admin_compare";
+pub const REQ_TYPE_ADMIN_NOTIFICATIONS_LIST: &str = "admin_notifications_list";
+pub const REQ_TYPE_ADMIN_NOTIFICATIONS_UNREAD_COUNT: &str = "admin_notifications_unread_count";
+pub const REQ_TYPE_ADMIN_NOTIFICATIONS_READ: &str = "admin_notifications_read";
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RequestLog {
     pub id: Option<i64>,
     pub timestamp: DateTime<Utc>,
@@ -58,6 +100,143 @@ pub struct RequestLog {
     pub cached_tokens: Option<u32>,
     pub reasoning_tokens: Option<u32>,
     pub error_message: Option<String>,
+    pub latency: LatencyBreakdown,
+    /// 提示语言与内容类别（需开启 prompt_profiling；不保存原文）
+    #[serde(default)]
+    pub profile: PromptProfile,
+}
+
+/// 单次请求耗时拆分（毫秒）：转发前处理、上游调用、响应后处理；
+/// 未调用上游的请求均为 None。流式请求的上游耗时持续到最后一个分片转发完毕
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LatencyBreakdown {
+    pub pre_dispatch_ms: Option<i64>,
+    pub upstream_ms: Option<i64>,
+    pub post_process_ms: Option<i64>,
+}
+
+impl LatencyBreakdown {
+    /// 由请求开始、上游开始/结束、请求结束四个时间点计算；upstream_finished 缺省为请求结束
+    pub fn from_marks(
+        start: DateTime<Utc>,
+        upstream_started: Option<DateTime<Utc>>,
+        upstream_finished: Option<DateTime<Utc>>,
+        end: DateTime<Utc>,
+    ) -> Self {
+        let Some(started) = upstream_started else {
+            return Self::default();
+        };
+        let finished = upstream_finished.unwrap_or(end);
+        let ms =
+            |from: DateTime<Utc>, to: DateTime<Utc>| Some((to - from).num_milliseconds().max(0));
+        Self {
+            pre_dispatch_ms: ms(start, started),
+            upstream_ms: ms(started, finished),
+            post_process_ms: ms(finished, end),
+        }
+    }
+
+    /// 网关自身开销（转发前 + 响应后）
+    pub fn overhead_ms(&self) -> Option<i64> {
+        match (self.pre_dispatch_ms, self.post_process_ms) {
+            (None, None) => None,
+            (pre, post) => Some(pre.unwrap_or(0) + post.unwrap_or(0)),
+        }
+    }
+}
+
+/// 按提示文本检测出的统计维度，仅用于聚合分析
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PromptProfile {
+    /// 语言（zh/ja/ko）或文字体系（latin/cyrillic/arabic/other）
+    pub language: Option<String>,
+    /// code / prose
+    pub category: Option<String>,
+}
+
+/// request_logs 的列（按 SELECT 顺序）及未选中时的占位表达式。
+/// 占位值保证行映射仍按位置读取，非空列用空串/0 占位
+const REQUEST_LOG_COLUMNS: [(&str, &str); 26] = [
+    ("id", "id"),
+    ("timestamp", "timestamp"),
+    ("method", "''"),
+    ("path", "''"),
+    ("request_type", "''"),
+    ("requested_model", "NULL"),
+    ("effective_model", "NULL"),
+    ("model", "NULL"),
+    ("provider", "NULL"),
+    ("api_key", "NULL"),
+    ("status_code", "0"),
+    ("response_time_ms", "0"),
+    ("prompt_tokens", "NULL"),
+    ("completion_tokens", "NULL"),
+    ("total_tokens", "NULL"),
+    ("cached_tokens", "NULL"),
+    ("reasoning_tokens", "NULL"),
+    ("error_message", "NULL"),
+    ("client_token", "NULL"),
+    ("user_id", "NULL"),
+    ("amount_spent", "NULL"),
+    ("pre_dispatch_ms", "NULL"),
+    ("upstream_ms", "NULL"),
+    ("post_process_ms", "NULL"),
+    ("prompt_language", "NULL"),
+    ("content_category", "NULL"),
+];
+
+/// 读取请求日志时实际查询的列；id/timestamp 用于排序与游标，总是读取
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LogColumns {
+    /// None 表示全部列
+    selected: Option<std::collections::BTreeSet<&'static str>>,
+}
+
+impl LogColumns {
+    pub fn all() -> Self {
+        Self { selected: None }
+    }
+
+    /// 仅读取指定列；未知列名会被忽略
+    pub fn only<'a>(columns: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut out = Self {
+            selected: Some(Default::default()),
+        };
+        out.extend(columns);
+        out
+    }
+
+    pub fn extend<'a>(&mut self, columns: impl IntoIterator<Item = &'a str>) {
+        let Some(selected) = self.selected.as_mut() else {
+            return;
+        };
+        for col in columns {
+            if let Some((name, _)) = REQUEST_LOG_COLUMNS.iter().find(|(name, _)| *name == col) {
+                selected.insert(name);
+            }
+        }
+    }
+
+    pub fn contains(&self, column: &str) -> bool {
+        column == "id"
+            || column == "timestamp"
+            || self.selected.as_ref().is_none_or(|s| s.contains(column))
+    }
+
+    /// 生成 SELECT 列表；未选中的列替换为同位置的占位值
+    pub fn select_list(&self) -> String {
+        REQUEST_LOG_COLUMNS
+            .iter()
+            .map(|(name, placeholder)| {
+                if self.contains(name) {
+                    (*name).to_string()
+                } else {
+                    format!("{} AS {}", placeholder, name)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -71,6 +250,274 @@ pub struct RequestLogDetailRecord {
     pub selected_provider: Option<String>,
     pub selected_key_id: Option<String>,
     pub first_token_latency_ms: Option<i64>,
+    /// 参数策略改写请求时的记录（JSON 数组）
+    #[serde(default)]
+    pub param_policy_applied: Option<String>,
+    /// 特权令牌指定供应商/密钥时的记录（JSON 对象）
+    #[serde(default)]
+    pub provider_override: Option<String>,
+    /// 自动截断超长对话时的记录（JSON 对象）
+    #[serde(default)]
+    pub prompt_truncation: Option<String>,
+}
+
+/// 幂等键缓存的成功响应（按 token_id + idempotency_key 唯一，过期后视为不存在）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredIdempotentResponse {
+    pub token_id: String,
+    pub idempotency_key: String,
+    pub request_fingerprint: String,
+    pub response_json: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// 通过 X-Gateway-Debug: capture 捕获的单次调用完整正文（不受 capture_body_max_bytes 限制，过期后视为不存在）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DebugCaptureRecord {
+    pub request_log_id: i64,
+    pub request_body: Option<String>,
+    pub response_body: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// 流式响应中转发给调用方的单个 SSE data 分片
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StreamChunk {
+    /// 相对请求开始的毫秒数
+    pub offset_ms: i64,
+    pub data: String,
+}
+
+/// 开启 capture_stream_chunks 时逐块捕获的流式响应（随请求日志保留期清理）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StreamCaptureRecord {
+    pub request_log_id: i64,
+    pub chunks: Vec<StreamChunk>,
+    /// 超过 capture_body_max_bytes 后停止捕获，后续分片缺失
+    pub truncated: bool,
+}
+
+/// 用量 Webhook 重试耗尽后的死信记录（管理端可查看、重新投递或删除）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageWebhookDeadLetter {
+    pub id: i64,
+    pub token_id: String,
+    pub url: String,
+    pub payload_json: String,
+    pub attempts: i64,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// 供应商计划维护窗口：[starts_at, ends_at) 期间该供应商不参与路由
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaintenanceWindowRecord {
+    pub id: i64,
+    pub provider: String,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+    /// 展示给调用方的维护说明
+    pub message: Option<String>,
+    pub created_by: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// 管理员通知收件箱中的一条系统事件（新管理员密钥、供应商异常、预算告警、需要迁移等）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdminNotificationRecord {
+    pub id: i64,
+    pub kind: String,
+    /// warning / critical
+    pub severity: String,
+    pub title: String,
+    pub message: String,
+    /// 去重键：同一 kind 下已有相同 reference 的未读通知时不再重复写入
+    pub reference: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub read_at: Option<DateTime<Utc>>,
+}
+
+/// 参数策略：转发前对匹配的模型/令牌收紧或移除请求参数（规则以 JSON 存储）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParamPolicyRecord {
+    pub id: String,
+    /// 为空表示所有模型；可写上游模型名或 provider/model
+    pub model: Option<String>,
+    /// 为空表示所有令牌
+    pub token_id: Option<String>,
+    pub rules_json: String,
+    pub enabled: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// 供应商月度花费预算（按北京时间自然月累计 request_logs.amount_spent）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProviderBudgetRecord {
+    pub provider: String,
+    pub monthly_budget: f64,
+    /// 告警阈值（预算百分比，升序）
+    pub warn_thresholds: Vec<u8>,
+    /// 预算耗尽后不再路由到该供应商
+    pub stop_routing_when_exhausted: bool,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// 图片生成按张计价（size/quality 为 `*` 时匹配任意取值，精确匹配优先）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImagePriceRecord {
+    pub provider: String,
+    pub model: String,
+    pub size: String,
+    pub quality: String,
+    pub price_per_image: f64,
+    pub currency: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// 语音转写按音频时长计价（每分钟单价，按秒折算）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AudioPriceRecord {
+    pub provider: String,
+    pub model: String,
+    pub price_per_minute: f64,
+    pub currency: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// 语音合成按输入文本字符数计价（每百万字符单价）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpeechPriceRecord {
+    pub provider: String,
+    pub model: String,
+    pub price_per_million_chars: f64,
+    pub currency: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// 定时指标报表：按日/周汇总用量并通过 Webhook 或邮件发送（模板存于数据库）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MetricsReportRecord {
+    pub id: String,
+    pub name: String,
+    /// daily | weekly
+    pub period: String,
+    /// webhook | email
+    pub channel: String,
+    /// Webhook URL 或收件邮箱
+    pub target: String,
+    /// 报表正文模板（`{{placeholder}}` 占位）；为空时使用内置模板
+    pub template: String,
+    pub enabled: bool,
+    pub last_sent_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// 按 (客户端令牌, 日期, 模型) 汇总的用量，日期为北京时间 YYYY-MM-DD
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TokenUsageDaily {
+    pub client_token: String,
+    pub day: String,
+    pub model: String,
+    pub requests: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub total_tokens: u64,
+    pub cost: f64,
+}
+
+/// 月度账单（生成后不可修改）；body 为账单内容 JSON
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StatementRecord {
+    pub id: String,
+    /// token | organization
+    pub scope: String,
+    pub subject_id: String,
+    /// YYYY-MM（北京时间自然月）
+    pub period: String,
+    pub body: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// 按天（北京时间）汇总的上游流量：写入时按 (day, provider, api_key) 累加
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ProviderEgressDaily {
+    /// YYYY-MM-DD
+    pub day: String,
+    pub provider: String,
+    /// 脱敏后的上游密钥（与 request_logs.api_key 一致）
+    pub api_key: String,
+    pub requests: i64,
+    pub request_bytes: i64,
+    pub response_bytes: i64,
+}
+
+/// 按天（北京时间）汇总的客户端令牌请求次数：写入时按 (day, token_id) 累加
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TokenRequestCount {
+    /// YYYY-MM-DD
+    pub day: String,
+    pub token_id: String,
+    pub requests: i64,
+}
+
+/// 请求日志中某个客户端令牌的用量合计（用于延迟写入计数的崩溃后补记）
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TokenUsageDelta {
+    pub token_id: String,
+    pub amount_spent: f64,
+    pub prompt_tokens: i64,
+    pub completion_tokens: i64,
+    pub total_tokens: i64,
+}
+
+/// 聊天请求按 (星期, 小时) 汇总的次数，时间为北京时间；weekday 0 为周日
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct RequestHeatmapCell {
+    pub weekday: u8,
+    pub hour: u8,
+    pub requests: u64,
+    pub errors: u64,
+}
+
+/// 令牌某日（北京时间）的请求汇总
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct TokenActivityDay {
+    /// YYYY-MM-DD
+    pub date: String,
+    pub requests: u64,
+    pub errors: u64,
+    pub amount_spent: f64,
+    pub total_tokens: i64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct TokenModelUsage {
+    pub model: String,
+    pub requests: u64,
+    pub amount_spent: f64,
+    pub total_tokens: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TokenRecentError {
+    pub timestamp: DateTime<Utc>,
+    pub path: String,
+    pub model: Option<String>,
+    pub status_code: u16,
+    pub error_message: Option<String>,
+}
+
+/// 令牌近期活动（在数据库中汇总）：daily 只含有请求的日期，top_models 按请求数降序
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct TokenActivity {
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub daily: Vec<TokenActivityDay>,
+    pub top_models: Vec<TokenModelUsage>,
+    pub recent_errors: Vec<TokenRecentError>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -189,6 +636,8 @@ pub struct ProviderOpLog {
     pub operation: String,
     pub provider: Option<String>,
     pub details: Option<String>,
+    /// 操作者（管理员身份标识）；后台任务触发或鉴权失败时为空
+    pub actor: Option<String>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
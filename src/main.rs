@@ -1,10 +1,12 @@
 mod admin;
 mod balance;
+mod bench;
 mod config;
 mod crypto;
 mod db;
 mod error;
 mod http_client;
+mod i18n;
 mod logging;
 mod password_reset_tokens;
 mod providers;
@@ -12,31 +14,63 @@ mod refresh_tokens;
 mod routing;
 mod server;
 mod subscription;
+mod tls_pinning;
 mod users;
 
-use tracing_subscriber::{EnvFilter, fmt};
+use tracing_subscriber::{EnvFilter, fmt, layer::SubscriberExt, reload, util::SubscriberInitExt};
 
 #[tokio::main]
 async fn main() -> crate::error::Result<()> {
     // Local development: load `.env` without panicking (no-op if missing).
     dotenvy::dotenv().ok();
 
-    // 使用自定义北京时间格式与环境过滤器
-    fmt()
-        .with_env_filter(EnvFilter::from_default_env())
-        .with_timer(crate::logging::time::BeijingTimer)
+    // 子命令：`gateway bench ...` 压测运行中的网关；`gateway db-restore ...` 从备份恢复 SQLite。均不启动服务
+    let mut args = std::env::args().skip(1);
+    match args.next().as_deref() {
+        Some("bench") => return bench::main(args).await,
+        Some("db-restore") => return server::db_replication::restore_main(args).await,
+        _ => {}
+    }
+
+    // 使用自定义北京时间格式与环境过滤器（过滤器可在运行期热更新）
+    let (filter, reload_handle) = reload::Layer::new(EnvFilter::from_default_env());
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt::layer().with_timer(crate::logging::time::BeijingTimer))
+        .with(crate::logging::ring_buffer::RingBufferLayer)
         .init();
+    crate::logging::level::install(reload_handle);
 
     let config = config::Settings::load()?;
+    i18n::set_language(config.server.language);
 
     // Use configured host/port to bind the server
     let addr = format!("{}:{}", config.server.host, config.server.port);
+    let drain_timeout = std::time::Duration::from_secs(config.server.drain_timeout_secs);
     let app = server::create_app(config).await?;
 
     let listener = tokio::net::TcpListener::bind(&addr).await?;
     tracing::info!("Gateway server running on http://{}", addr);
 
-    axum::serve(listener, app).await?;
+    // 收到关闭信号后先排空进行中的请求/流（期间 /admin/drain-status 仍可访问）；
+    // 超时或管理员强制结束时不再等待剩余连接
+    let drain = server::drain::drain_tracker();
+    let serve = axum::serve(listener, app).with_graceful_shutdown({
+        let drain = drain.clone();
+        async move {
+            let _ = tokio::signal::ctrl_c().await;
+            tracing::info!("Shutdown signal received, draining in-flight requests");
+            drain.drain(drain_timeout).await;
+        }
+    });
+    tokio::select! {
+        result = std::future::IntoFuture::into_future(serve) => result?,
+        _ = drain.abandoned() => {}
+    }
+    tracing::info!("Stopping background tasks");
+    server::tasks::task_registry()
+        .shutdown(std::time::Duration::from_secs(10))
+        .await;
 
     Ok(())
 }
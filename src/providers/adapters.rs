@@ -100,6 +100,9 @@ struct VertexAIAdapter;
 #[derive(Debug)]
 struct BaiduErnieAdapter;
 
+#[derive(Debug)]
+struct MockAdapter;
+
 static OPENAI_COMPAT_ADAPTER: ProtocolAdapter = ProtocolAdapter {
     family: ProviderProtocolFamily::OpenAI,
     auth_mode: ProviderAuthMode::Bearer,
@@ -124,6 +127,7 @@ static COHERE_ADAPTER: CohereAdapter = CohereAdapter;
 static AWS_CLAUDE_ADAPTER: AwsClaudeAdapter = AwsClaudeAdapter;
 static VERTEX_AI_ADAPTER: VertexAIAdapter = VertexAIAdapter;
 static BAIDU_ERNIE_ADAPTER: BaiduErnieAdapter = BaiduErnieAdapter;
+static MOCK_ADAPTER: MockAdapter = MockAdapter;
 
 pub fn adapter_for(provider_type: ProviderType) -> Option<&'static dyn ProviderAdapter> {
     match provider_type {
@@ -144,7 +148,8 @@ pub fn adapter_for(provider_type: ProviderType) -> Option<&'static dyn ProviderA
         | ProviderType::XfSpark
         | ProviderType::TencentHunyuan
         | ProviderType::ThreeSixtyZhinao
-        | ProviderType::StepFun => Some(&OPENAI_COMPAT_ADAPTER),
+        | ProviderType::StepFun
+        | ProviderType::Local => Some(&OPENAI_COMPAT_ADAPTER),
         ProviderType::Anthropic => Some(&ANTHROPIC_ADAPTER),
         ProviderType::Zhipu => Some(&ZHIPU_ADAPTER),
         ProviderType::AzureOpenAI => Some(&AZURE_OPENAI_ADAPTER),
@@ -153,6 +158,7 @@ pub fn adapter_for(provider_type: ProviderType) -> Option<&'static dyn ProviderA
         ProviderType::AwsClaude => Some(&AWS_CLAUDE_ADAPTER),
         ProviderType::VertexAI => Some(&VERTEX_AI_ADAPTER),
         ProviderType::BaiduErnie => Some(&BAIDU_ERNIE_ADAPTER),
+        ProviderType::Mock => Some(&MOCK_ADAPTER),
     }
 }
 
@@ -176,10 +182,125 @@ pub fn runtime_streaming_unsupported_message(_provider_type: ProviderType) -> Op
     None
 }
 
+pub const ANTHROPIC_VERSION_HEADER: &str = "anthropic-version";
+pub const ANTHROPIC_DEFAULT_API_VERSION: &str = "2023-06-01";
+const ANTHROPIC_SUPPORTED_API_VERSIONS: &[&str] = &["2023-06-01", "2023-01-01"];
+const GEMINI_DEFAULT_API_VERSION: &str = "v1beta";
+const GEMINI_SUPPORTED_API_VERSIONS: &[&str] = &["v1", "v1beta"];
+
+/// 上游 API 版本的取值规则
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApiVersionRule {
+    /// 仅允许列出的版本
+    OneOf(&'static [&'static str]),
+    /// 日期版本 `YYYY-MM-DD`，可带 `-preview` 后缀（Azure OpenAI 的 api-version）
+    Dated,
+}
+
+/// 适配器对 API 版本的支持情况；返回 None 的供应商类型不接受 api_version 配置
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ApiVersionSpec {
+    pub default: Option<&'static str>,
+    pub rule: ApiVersionRule,
+}
+
+pub fn api_version_spec(provider_type: ProviderType) -> Option<ApiVersionSpec> {
+    match provider_type {
+        ProviderType::Anthropic => Some(ApiVersionSpec {
+            default: Some(ANTHROPIC_DEFAULT_API_VERSION),
+            rule: ApiVersionRule::OneOf(ANTHROPIC_SUPPORTED_API_VERSIONS),
+        }),
+        ProviderType::AzureOpenAI => Some(ApiVersionSpec {
+            default: None,
+            rule: ApiVersionRule::Dated,
+        }),
+        ProviderType::GoogleGemini => Some(ApiVersionSpec {
+            default: Some(GEMINI_DEFAULT_API_VERSION),
+            rule: ApiVersionRule::OneOf(GEMINI_SUPPORTED_API_VERSIONS),
+        }),
+        _ => None,
+    }
+}
+
+fn is_dated_api_version(version: &str) -> bool {
+    let date = version.strip_suffix("-preview").unwrap_or(version);
+    chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").is_ok() && date.len() == 10
+}
+
+/// 实际发送给上游的版本：专用字段（azure_api_version / google_api_version）优先，
+/// 其次通用 api_version，最后是适配器默认值
+pub fn effective_api_version(
+    provider_type: ProviderType,
+    provider_config: &ProviderConfig,
+) -> Option<&str> {
+    let specific = match provider_type {
+        ProviderType::AzureOpenAI => provider_config.azure_api_version(),
+        ProviderType::GoogleGemini => provider_config.google_api_version(),
+        _ => None,
+    };
+    let spec = api_version_spec(provider_type)?;
+    specific
+        .or_else(|| provider_config.api_version())
+        .or(spec.default)
+}
+
+/// 保存供应商时校验配置的版本是否被适配器支持
+pub fn validate_api_version(
+    provider_type: ProviderType,
+    provider_config: &ProviderConfig,
+) -> Result<(), GatewayError> {
+    let specific = match provider_type {
+        ProviderType::AzureOpenAI => ("azure_api_version", provider_config.azure_api_version()),
+        ProviderType::GoogleGemini => ("google_api_version", provider_config.google_api_version()),
+        _ => ("", None),
+    };
+    for (field, version) in [("api_version", provider_config.api_version()), specific] {
+        let Some(version) = version else { continue };
+        let Some(spec) = api_version_spec(provider_type) else {
+            return Err(GatewayError::Config(format!(
+                "provider type '{}' 不支持配置 {}",
+                provider_type.as_str(),
+                field
+            )));
+        };
+        let supported = match spec.rule {
+            ApiVersionRule::OneOf(versions) => versions.contains(&version),
+            ApiVersionRule::Dated => is_dated_api_version(version),
+        };
+        if !supported {
+            let expected = match spec.rule {
+                ApiVersionRule::OneOf(versions) => versions.join(", "),
+                ApiVersionRule::Dated => "YYYY-MM-DD 或 YYYY-MM-DD-preview".to_string(),
+            };
+            return Err(GatewayError::Config(format!(
+                "{} '{}' 不受 {} 适配器支持（可选：{}）",
+                field,
+                version,
+                provider_type.as_str(),
+                expected
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// 上游错误信息是否指向 API 版本不匹配（版本头缺失/无效、api-version 不受支持等）
+pub(crate) fn is_api_version_error(status: StatusCode, message: &str) -> bool {
+    if !(status == StatusCode::BAD_REQUEST || status == StatusCode::NOT_FOUND) {
+        return false;
+    }
+    let lower = message.to_lowercase();
+    lower.contains("api-version")
+        || lower.contains("api version")
+        || lower.contains("apiversion")
+        || lower.contains(ANTHROPIC_VERSION_HEADER)
+}
+
 fn client_for_url(url: &str, timeout_secs: u64) -> Result<reqwest::Client, GatewayError> {
     let builder = reqwest::Client::builder()
         .redirect(Policy::none())
         .timeout(Duration::from_secs(timeout_secs));
+    let builder = crate::tls_pinning::apply(builder, url);
     Ok(crate::http_client::maybe_disable_proxy(builder, url).build()?)
 }
 
@@ -527,19 +648,24 @@ fn adapt_baidu_ernie_response(
 pub(crate) fn azure_openai_chat_completions_url(
     base_url: &Url,
     provider_config: &ProviderConfig,
+    model: &str,
 ) -> Result<String, (String, Option<String>)> {
-    let deployment = provider_config.azure_deployment().ok_or_else(|| {
-        (
-            "configuration_required".into(),
-            Some("Azure OpenAI 需要填写 deployment。".into()),
-        )
-    })?;
-    let api_version = provider_config.azure_api_version().ok_or_else(|| {
+    let deployment = provider_config.azure_deployment_for(model).ok_or_else(|| {
         (
             "configuration_required".into(),
-            Some("Azure OpenAI 需要填写 apiVersion。".into()),
+            Some(format!(
+                "Azure OpenAI 需要填写 deployment（或在 azure_deployments 中为模型 {} 配置映射）。",
+                model
+            )),
         )
     })?;
+    let api_version = effective_api_version(ProviderType::AzureOpenAI, provider_config)
+        .ok_or_else(|| {
+            (
+                "configuration_required".into(),
+                Some("Azure OpenAI 需要填写 apiVersion。".into()),
+            )
+        })?;
 
     let base = base_url.as_str().trim_end_matches('/');
     let path = base_url.path().trim_end_matches('/');
@@ -563,7 +689,8 @@ fn append_query_api_key(url: &Url, api_key: &str) -> Result<String, (String, Opt
 fn gemini_base_url(base_url: &Url, provider_config: &ProviderConfig) -> String {
     let base = base_url.as_str().trim_end_matches('/');
     let path = base_url.path().trim_end_matches('/');
-    let api_version = provider_config.google_api_version().unwrap_or("v1beta");
+    let api_version = effective_api_version(ProviderType::GoogleGemini, provider_config)
+        .unwrap_or(GEMINI_DEFAULT_API_VERSION);
     if path.ends_with("/v1beta") || path.ends_with("/v1") || path.ends_with(api_version) {
         base.to_string()
     } else {
@@ -618,6 +745,10 @@ fn classify_http_failure(status: StatusCode, body_snippet: &str) -> (String, Opt
         return ("rate_limited".into(), Some(snippet.to_string()));
     }
 
+    if is_api_version_error(status, snippet) {
+        return ("api_version_mismatch".into(), Some(snippet.to_string()));
+    }
+
     if status == StatusCode::NOT_FOUND {
         if lower.contains("model") && (lower.contains("not found") || lower.contains("not_found")) {
             return ("model_not_found".into(), Some(snippet.to_string()));
@@ -753,10 +884,34 @@ pub(crate) fn gateway_error_from_normalized(
     match error_type {
         "authentication_failed" => GatewayError::Unauthorized(fallback_message),
         "rate_limited" => GatewayError::RateLimited(fallback_message),
+        "api_version_mismatch" => GatewayError::ApiVersionMismatch(fallback_message),
         _ => GatewayError::Config(fallback_message),
     }
 }
 
+/// 解析上游响应的 Retry-After（秒数或 HTTP 日期），返回距现在需等待的秒数
+pub(crate) fn retry_after_secs(headers: &reqwest::header::HeaderMap) -> Option<u64> {
+    let value = headers
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(secs);
+    }
+    let at = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let secs = (at.with_timezone(&chrono::Utc) - chrono::Utc::now()).num_seconds();
+    Some(secs.max(0) as u64)
+}
+
+/// 上游 429 响应转为限流错误，并携带其 Retry-After
+pub(crate) fn upstream_rate_limited(
+    headers: &reqwest::header::HeaderMap,
+    message: String,
+) -> GatewayError {
+    GatewayError::RateLimited(message).with_retry_after(retry_after_secs(headers))
+}
+
 fn request_value(request: &ChatCompletionRequest) -> Result<serde_json::Value, GatewayError> {
     serde_json::to_value(request).map_err(GatewayError::from)
 }
@@ -1743,12 +1898,12 @@ pub(crate) fn classify_azure_error(status: StatusCode, bytes: &[u8]) -> (String,
     if status == StatusCode::UNAUTHORIZED || status == StatusCode::FORBIDDEN {
         return ("authentication_failed".into(), Some(message));
     }
+    if is_api_version_error(status, &message) {
+        return ("api_version_mismatch".into(), Some(message));
+    }
     if status == StatusCode::NOT_FOUND {
         return ("invalid_path".into(), Some(message));
     }
-    if status == StatusCode::BAD_REQUEST && lower.contains("api-version") {
-        return ("configuration_required".into(), Some(message));
-    }
     classify_http_failure(status, &message)
 }
 
@@ -1875,6 +2030,7 @@ impl ProtocolAdapter {
             | ProviderProtocolFamily::GoogleGemini
             | ProviderProtocolFamily::VertexAI
             | ProviderProtocolFamily::Cohere
+            | ProviderProtocolFamily::Mock
             | ProviderProtocolFamily::Unsupported => base_url.as_str().to_string(),
         }
     }
@@ -1900,6 +2056,7 @@ impl ProtocolAdapter {
             | ProviderProtocolFamily::GoogleGemini
             | ProviderProtocolFamily::VertexAI
             | ProviderProtocolFamily::Cohere
+            | ProviderProtocolFamily::Mock
             | ProviderProtocolFamily::Unsupported => json!({}),
         }
     }
@@ -1915,6 +2072,8 @@ impl ProviderAdapter for ProtocolAdapter {
 
         let mut headers = HeaderMap::new();
         match self.auth_mode {
+            // 无鉴权的本地运行时不配置密钥，不发送空的 Bearer 头
+            ProviderAuthMode::Bearer if api_key.is_empty() => {}
             ProviderAuthMode::Bearer => {
                 let value = HeaderValue::from_str(&format!("Bearer {api_key}"))
                     .map_err(|e| ("other".into(), Some(e.to_string())))?;
@@ -1929,7 +2088,10 @@ impl ProviderAdapter for ProtocolAdapter {
                 let api_key_value = HeaderValue::from_str(api_key)
                     .map_err(|e| ("other".into(), Some(e.to_string())))?;
                 headers.insert("x-api-key", api_key_value);
-                headers.insert("anthropic-version", HeaderValue::from_static("2023-06-01"));
+                headers.insert(
+                    ANTHROPIC_VERSION_HEADER,
+                    HeaderValue::from_static(ANTHROPIC_DEFAULT_API_VERSION),
+                );
             }
             ProviderAuthMode::Unsupported | ProviderAuthMode::SigV4 | ProviderAuthMode::OAuth => {}
         }
@@ -2008,7 +2170,16 @@ impl ProviderAdapter for ProtocolAdapter {
             .header("Accept", "application/json")
             .json(&payload);
 
-        for (name, value) in self.build_auth_headers(request.api_key)? {
+        let mut headers = self.build_auth_headers(request.api_key)?;
+        if self.family == ProviderProtocolFamily::Anthropic
+            && let Some(version) =
+                effective_api_version(ProviderType::Anthropic, request.provider_config)
+        {
+            let value = reqwest::header::HeaderValue::from_str(version)
+                .map_err(|e| ("configuration_required".into(), Some(e.to_string())))?;
+            headers.insert(ANTHROPIC_VERSION_HEADER, value);
+        }
+        for (name, value) in headers {
             if let Some(name) = name {
                 req = req.header(name, value);
             }
@@ -2132,13 +2303,15 @@ impl ProviderAdapter for BaiduErnieAdapter {
             .send()
             .await?;
         let status = resp.status();
+        let retry_after = retry_after_secs(resp.headers());
         let bytes = resp.bytes().await?;
         if !status.is_success() || baidu_requires_error(&bytes) {
             let (error_type, detail) = baidu_error_response(status, &bytes);
             return Err(gateway_error_from_normalized(
                 &error_type,
                 detail.unwrap_or_else(|| baidu_error_text(&bytes)),
-            ));
+            )
+            .with_retry_after(retry_after));
         }
 
         adapt_baidu_ernie_response(&request.request.model, &bytes)
@@ -2182,7 +2355,11 @@ impl ProviderAdapter for AzureOpenAIAdapter {
         &self,
         request: ConnectionTestRequest<'_>,
     ) -> Result<(), (String, Option<String>)> {
-        let url = azure_openai_chat_completions_url(request.base_url, request.provider_config)?;
+        let url = azure_openai_chat_completions_url(
+            request.base_url,
+            request.provider_config,
+            request.model,
+        )?;
         let client = client_for_url(&url, 30).map_err(|e| ("other".into(), Some(e.to_string())))?;
         let mut req = client
             .post(&url)
@@ -2223,11 +2400,14 @@ impl ProviderAdapter for AzureOpenAIAdapter {
     ) -> Result<RawAndTypedChatCompletion, GatewayError> {
         let base_url = Url::parse(request.base_url)
             .map_err(|err| GatewayError::Config(format!("Azure OpenAI base_url 无效：{err}")))?;
-        let url = azure_openai_chat_completions_url(&base_url, request.provider_config).map_err(
-            |(_, detail)| {
-                GatewayError::Config(detail.unwrap_or_else(|| "Azure OpenAI 配置不完整。".into()))
-            },
-        )?;
+        let url = azure_openai_chat_completions_url(
+            &base_url,
+            request.provider_config,
+            &request.request.model,
+        )
+        .map_err(|(_, detail)| {
+            GatewayError::Config(detail.unwrap_or_else(|| "Azure OpenAI 配置不完整。".into()))
+        })?;
         let client = client_for_url(&url, 60)?;
         let mut payload = request_value(request.request)?;
         if let Some(object) = payload.as_object_mut() {
@@ -2254,13 +2434,15 @@ impl ProviderAdapter for AzureOpenAIAdapter {
 
         let resp = req.send().await?;
         let status = resp.status();
+        let retry_after = retry_after_secs(resp.headers());
         let bytes = resp.bytes().await?;
         if !status.is_success() {
             let (error_type, detail) = classify_azure_error(status, &bytes);
             return Err(gateway_error_from_normalized(
                 &error_type,
                 detail.unwrap_or_else(|| azure_error_message(status, &bytes)),
-            ));
+            )
+            .with_retry_after(retry_after));
         }
 
         parse_openai_compatible_response(&bytes)
@@ -2302,13 +2484,15 @@ impl ProviderAdapter for GoogleGeminiAdapter {
             .send()
             .await?;
         let status = resp.status();
+        let retry_after = retry_after_secs(resp.headers());
         let bytes = resp.bytes().await?;
         if !status.is_success() {
             let (error_type, detail) = self.normalize_error(status, None, &bytes);
             return Err(gateway_error_from_normalized(
                 &error_type,
                 detail.unwrap_or_else(|| gemini_error_message(status, &bytes)),
-            ));
+            )
+            .with_retry_after(retry_after));
         }
 
         let parsed: GeminiModelsResponse = serde_json::from_slice(&bytes)
@@ -2399,13 +2583,15 @@ impl ProviderAdapter for GoogleGeminiAdapter {
             .send()
             .await?;
         let status = resp.status();
+        let retry_after = retry_after_secs(resp.headers());
         let bytes = resp.bytes().await?;
         if !status.is_success() {
             let (error_type, detail) = classify_gemini_error(status, &bytes);
             return Err(gateway_error_from_normalized(
                 &error_type,
                 detail.unwrap_or_else(|| gemini_error_message(status, &bytes)),
-            ));
+            )
+            .with_retry_after(retry_after));
         }
 
         adapt_gemini_response(&request.request.model, &bytes)
@@ -2532,13 +2718,15 @@ impl ProviderAdapter for AwsClaudeAdapter {
 
         let resp = req.send().await?;
         let status = resp.status();
+        let retry_after = retry_after_secs(resp.headers());
         let bytes = resp.bytes().await?;
         if !status.is_success() {
             let (error_type, detail) = classify_aws_claude_error(status, &bytes);
             return Err(gateway_error_from_normalized(
                 &error_type,
                 detail.unwrap_or_else(|| aws_claude_error_message(status, &bytes)),
-            ));
+            )
+            .with_retry_after(retry_after));
         }
 
         adapt_aws_claude_response(&request.request.model, &bytes)
@@ -2644,13 +2832,15 @@ impl ProviderAdapter for VertexAIAdapter {
             .send()
             .await?;
         let status = resp.status();
+        let retry_after = retry_after_secs(resp.headers());
         let bytes = resp.bytes().await?;
         if !status.is_success() {
             let (error_type, detail) = classify_vertex_error(status, &bytes);
             return Err(gateway_error_from_normalized(
                 &error_type,
                 detail.unwrap_or_else(|| vertex_error_message(status, &bytes)),
-            ));
+            )
+            .with_retry_after(retry_after));
         }
 
         adapt_gemini_response(&request.request.model, &bytes)
@@ -2702,13 +2892,15 @@ impl ProviderAdapter for CohereAdapter {
 
         let resp = req.send().await?;
         let status = resp.status();
+        let retry_after = retry_after_secs(resp.headers());
         let bytes = resp.bytes().await?;
         if !status.is_success() {
             let (error_type, detail) = self.normalize_error(status, None, &bytes);
             return Err(gateway_error_from_normalized(
                 &error_type,
                 detail.unwrap_or_else(|| cohere_error_message(status, &bytes)),
-            ));
+            )
+            .with_retry_after(retry_after));
         }
 
         let parsed: CohereModelsResponse = serde_json::from_slice(&bytes)
@@ -2802,24 +2994,154 @@ impl ProviderAdapter for CohereAdapter {
 
         let resp = req.send().await?;
         let status = resp.status();
+        let retry_after = retry_after_secs(resp.headers());
         let bytes = resp.bytes().await?;
         if !status.is_success() {
             let (error_type, detail) = classify_cohere_error(status, &bytes);
             return Err(gateway_error_from_normalized(
                 &error_type,
                 detail.unwrap_or_else(|| cohere_error_message(status, &bytes)),
-            ));
+            )
+            .with_retry_after(retry_after));
         }
 
         adapt_cohere_response(&request.request.model, &bytes)
     }
 }
 
+/// mock 供应商默认暴露的模型
+pub(crate) const MOCK_MODELS: &[&str] = &["mock-chat", "mock-chat-large"];
+
+/// 进程内生成 mock 回复：按配置延迟、按失败概率返回错误，否则返回确定性的 OpenAI 格式响应
+pub(crate) async fn mock_chat_completion(
+    provider_config: &ProviderConfig,
+    request: &ChatCompletionRequest,
+) -> Result<RawAndTypedChatCompletion, GatewayError> {
+    if let Some(ms) = provider_config.mock_latency_ms.filter(|ms| *ms > 0) {
+        tokio::time::sleep(Duration::from_millis(ms)).await;
+    }
+    let failure_percent = provider_config.mock_failure_percent.unwrap_or(0).min(100);
+    if failure_percent > 0 && rand::random_range(0..100u8) < failure_percent {
+        return Err(GatewayError::Config(
+            "mock provider: simulated upstream failure".into(),
+        ));
+    }
+
+    let content = match provider_config
+        .mock_reply
+        .as_deref()
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+    {
+        Some(reply) => reply.to_string(),
+        None => format!("mock: {}", mock_last_user_text(request)),
+    };
+    let prompt_tokens = serde_json::to_string(&request.messages)
+        .map(|s| s.chars().count().div_ceil(4) as u32)
+        .unwrap_or(0);
+    let completion_tokens = provider_config
+        .mock_completion_tokens
+        .unwrap_or_else(|| content.chars().count().div_ceil(4) as u32);
+    build_openai_style_response(
+        Some("chatcmpl-mock".into()),
+        &request.model,
+        content,
+        Some("stop"),
+        Some(json!({
+            "prompt_tokens": prompt_tokens,
+            "completion_tokens": completion_tokens,
+            "total_tokens": prompt_tokens + completion_tokens,
+        })),
+    )
+}
+
+fn mock_last_user_text(request: &ChatCompletionRequest) -> String {
+    let messages = serde_json::to_value(&request.messages).unwrap_or_default();
+    let content = messages
+        .as_array()
+        .and_then(|list| {
+            list.iter()
+                .rev()
+                .find(|m| m.get("role").and_then(|r| r.as_str()) == Some("user"))
+        })
+        .and_then(|m| m.get("content"));
+    match content {
+        Some(serde_json::Value::String(s)) => s.clone(),
+        Some(serde_json::Value::Array(parts)) => parts
+            .iter()
+            .filter_map(|p| p.get("text").and_then(|t| t.as_str()))
+            .collect::<Vec<_>>()
+            .join(""),
+        _ => String::new(),
+    }
+}
+
+#[async_trait]
+impl ProviderAdapter for MockAdapter {
+    fn build_auth_headers(
+        &self,
+        _api_key: &str,
+    ) -> Result<reqwest::header::HeaderMap, (String, Option<String>)> {
+        Ok(reqwest::header::HeaderMap::new())
+    }
+
+    fn normalize_error(
+        &self,
+        status: StatusCode,
+        _content_type: Option<&str>,
+        _bytes: &[u8],
+    ) -> (String, Option<String>) {
+        (
+            "other".into(),
+            Some(format!("mock provider returned {}", status)),
+        )
+    }
+
+    async fn list_models(
+        &self,
+        _request: ListModelsRequest<'_>,
+    ) -> Result<Vec<String>, GatewayError> {
+        Ok(MOCK_MODELS.iter().map(|m| m.to_string()).collect())
+    }
+
+    async fn test_connection(
+        &self,
+        _request: ConnectionTestRequest<'_>,
+    ) -> Result<(), (String, Option<String>)> {
+        Ok(())
+    }
+
+    async fn chat_completions(
+        &self,
+        request: ChatCompletionsRequest<'_>,
+    ) -> Result<RawAndTypedChatCompletion, GatewayError> {
+        mock_chat_completion(request.provider_config, request.request).await
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use serde_json::json;
 
+    #[test]
+    fn retry_after_accepts_seconds_and_http_dates() {
+        let mut headers = reqwest::header::HeaderMap::new();
+        assert_eq!(retry_after_secs(&headers), None);
+        headers.insert(reqwest::header::RETRY_AFTER, "12".parse().unwrap());
+        assert_eq!(retry_after_secs(&headers), Some(12));
+        let at = (chrono::Utc::now() + chrono::Duration::seconds(90)).to_rfc2822();
+        headers.insert(reqwest::header::RETRY_AFTER, at.parse().unwrap());
+        let secs = retry_after_secs(&headers).unwrap();
+        assert!((85..=90).contains(&secs));
+        headers.insert(reqwest::header::RETRY_AFTER, "soon".parse().unwrap());
+        assert_eq!(retry_after_secs(&headers), None);
+
+        let err = upstream_rate_limited(&reqwest::header::HeaderMap::new(), "busy".into());
+        assert!(matches!(err, GatewayError::RateLimited(_)));
+        assert_eq!(err.retry_after(), None);
+    }
+
     #[test]
     fn supported_provider_types_resolve_to_adapters() {
         assert!(adapter_for(ProviderType::OpenAI).is_some());
@@ -2839,6 +3161,37 @@ mod tests {
         assert!(adapter_for(ProviderType::Cohere).is_some());
         assert!(adapter_for(ProviderType::AwsClaude).is_some());
         assert!(adapter_for(ProviderType::VertexAI).is_some());
+        assert!(adapter_for(ProviderType::Mock).is_some());
+    }
+
+    #[tokio::test]
+    async fn mock_adapter_is_deterministic_and_honours_failure_rate() {
+        let request: ChatCompletionRequest = serde_json::from_value(json!({
+            "model": "mock-chat",
+            "messages": [{"role": "user", "content": "ping"}]
+        }))
+        .unwrap();
+        let config = ProviderConfig {
+            mock_completion_tokens: Some(7),
+            ..Default::default()
+        };
+        let first = mock_chat_completion(&config, &request).await.unwrap();
+        let second = mock_chat_completion(&config, &request).await.unwrap();
+        assert_eq!(
+            first.raw["choices"][0]["message"]["content"],
+            json!("mock: ping")
+        );
+        assert_eq!(
+            first.raw["choices"][0]["message"],
+            second.raw["choices"][0]["message"]
+        );
+        assert_eq!(first.typed.usage.unwrap().completion_tokens, 7);
+
+        let failing = ProviderConfig {
+            mock_failure_percent: Some(100),
+            ..Default::default()
+        };
+        assert!(mock_chat_completion(&failing, &request).await.is_err());
     }
 
     #[test]
@@ -2879,6 +3232,7 @@ mod tests {
                 google_api_version: None,
                 ..ProviderConfig::default()
             },
+            "gpt-4o",
         )
         .unwrap();
         assert_eq!(
@@ -2887,6 +3241,40 @@ mod tests {
         );
     }
 
+    #[test]
+    fn azure_url_maps_models_to_deployments() {
+        let base = Url::parse("https://demo.openai.azure.com/openai").unwrap();
+        let mapped = ProviderConfig {
+            azure_deployments: [
+                ("gpt-4o".to_string(), "gpt-4o-prod".to_string()),
+                ("gpt-4o-mini".to_string(), " ".to_string()),
+            ]
+            .into_iter()
+            .collect(),
+            api_version: Some("2024-10-21".into()),
+            ..ProviderConfig::default()
+        };
+        assert_eq!(
+            azure_openai_chat_completions_url(&base, &mapped, "gpt-4o").unwrap(),
+            "https://demo.openai.azure.com/openai/deployments/gpt-4o-prod/chat/completions?api-version=2024-10-21"
+        );
+        // 未映射（或映射为空）且无默认 deployment 时给出配置错误
+        let (kind, _) =
+            azure_openai_chat_completions_url(&base, &mapped, "gpt-4o-mini").unwrap_err();
+        assert_eq!(kind, "configuration_required");
+
+        let with_default = ProviderConfig {
+            azure_deployment: Some("shared".into()),
+            ..mapped
+        };
+        assert!(
+            azure_openai_chat_completions_url(&base, &with_default, "o1")
+                .unwrap()
+                .contains("/deployments/shared/")
+        );
+        assert!(!with_default.is_empty());
+    }
+
     #[test]
     fn gemini_base_url_respects_explicit_version() {
         let url = gemini_base_url(
@@ -2901,6 +3289,65 @@ mod tests {
         assert_eq!(url, "https://generativelanguage.googleapis.com/v1");
     }
 
+    #[test]
+    fn api_version_is_validated_against_adapter_support() {
+        let pinned = |version: &str| ProviderConfig {
+            api_version: Some(version.into()),
+            ..ProviderConfig::default()
+        };
+        assert!(validate_api_version(ProviderType::Anthropic, &pinned("2023-06-01")).is_ok());
+        assert!(validate_api_version(ProviderType::Anthropic, &pinned("2099-01-01")).is_err());
+        assert!(validate_api_version(ProviderType::GoogleGemini, &pinned("v1")).is_ok());
+        assert!(
+            validate_api_version(ProviderType::AzureOpenAI, &pinned("2025-04-01-preview")).is_ok()
+        );
+        assert!(validate_api_version(ProviderType::AzureOpenAI, &pinned("latest")).is_err());
+        assert!(validate_api_version(ProviderType::OpenAI, &pinned("v1")).is_err());
+        assert!(validate_api_version(ProviderType::OpenAI, &ProviderConfig::default()).is_ok());
+
+        let azure = ProviderConfig {
+            azure_api_version: Some("2024-06-01".into()),
+            api_version: Some("2024-10-21".into()),
+            ..ProviderConfig::default()
+        };
+        assert_eq!(
+            effective_api_version(ProviderType::AzureOpenAI, &azure),
+            Some("2024-06-01")
+        );
+        assert_eq!(
+            effective_api_version(ProviderType::Anthropic, &ProviderConfig::default()),
+            Some(ANTHROPIC_DEFAULT_API_VERSION)
+        );
+        assert_eq!(
+            effective_api_version(ProviderType::Anthropic, &pinned("2023-01-01")),
+            Some("2023-01-01")
+        );
+        assert_eq!(
+            gemini_base_url(
+                &Url::parse("https://generativelanguage.googleapis.com").unwrap(),
+                &pinned("v1"),
+            ),
+            "https://generativelanguage.googleapis.com/v1"
+        );
+    }
+
+    #[test]
+    fn api_version_rejections_are_classified_distinctly() {
+        let (ty, _) = classify_azure_error(
+            StatusCode::BAD_REQUEST,
+            br#"{"error":{"code":"404","message":"API version not supported"}}"#,
+        );
+        assert_eq!(ty, "api_version_mismatch");
+        let (ty, _) = classify_http_failure(
+            StatusCode::BAD_REQUEST,
+            r#"{"type":"error","error":{"message":"invalid anthropic-version header"}}"#,
+        );
+        assert_eq!(ty, "api_version_mismatch");
+        let err = gateway_error_from_normalized(&ty, "bad version".into());
+        assert_eq!(err.status_code(), StatusCode::BAD_GATEWAY);
+        assert!(matches!(err, GatewayError::ApiVersionMismatch(_)));
+    }
+
     #[test]
     fn baidu_ernie_model_paths_accept_alias_and_raw_endpoint() {
         assert_eq!(
@@ -1,50 +1,89 @@
 use anthropic_ai_sdk::types::message as anthropic;
 use serde_json::Value;
 
+use crate::providers::adapters::{
+    ANTHROPIC_VERSION_HEADER, is_api_version_error, upstream_rate_limited,
+};
+
 pub async fn chat_completions(
     base_url: &str,
     api_key: &str,
+    api_version: &str,
     request: &anthropic::CreateMessageParams,
 ) -> crate::error::Result<anthropic::CreateMessageResponse> {
+    let response =
+        send_messages(base_url, api_key, api_version, request, "application/json").await?;
+    let body = response.bytes().await?;
+
+    let raw: Value = serde_json::from_slice(&body)?;
+    if raw.get("object").and_then(Value::as_str) == Some("chat.completion")
+        || (raw.get("choices").is_some() && raw.get("content").is_none())
+    {
+        return Err(crate::error::GatewayError::Config(
+            "当前供应商返回的是 OpenAI Chat Completions 格式，不是 Anthropic Messages 格式；请把该供应商类型改成 OpenAI 兼容，而不是 Anthropic。".into(),
+        ));
+    }
+
+    Ok(serde_json::from_value(raw)?)
+}
+
+/// 以 `stream: true` 请求 Messages 接口，返回尚未读取的 SSE 响应；上游错误与非流式一致地映射
+pub async fn stream_messages(
+    base_url: &str,
+    api_key: &str,
+    api_version: &str,
+    request: &anthropic::CreateMessageParams,
+) -> crate::error::Result<reqwest::Response> {
+    send_messages(base_url, api_key, api_version, request, "text/event-stream").await
+}
+
+async fn send_messages(
+    base_url: &str,
+    api_key: &str,
+    api_version: &str,
+    request: &anthropic::CreateMessageParams,
+    accept: &str,
+) -> crate::error::Result<reqwest::Response> {
     let client = reqwest::Client::new();
     let url = format!("{}/v1/messages", base_url.trim_end_matches('/'));
     let response = client
         .post(&url)
         .header("x-api-key", api_key)
         .header("Content-Type", "application/json")
-        .header("anthropic-version", "2023-06-01")
+        .header("Accept", accept)
+        .header(ANTHROPIC_VERSION_HEADER, api_version)
         .json(request)
         .send()
         .await?;
     let status = response.status();
-    let body = response.bytes().await?;
-
-    if !status.is_success() {
-        let message = serde_json::from_slice::<Value>(&body)
-            .ok()
-            .and_then(|value| {
-                value
-                    .get("error")
-                    .and_then(|error| error.get("message").or_else(|| error.get("detail")))
-                    .and_then(Value::as_str)
-                    .map(str::to_string)
-            })
-            .or_else(|| {
-                let text = String::from_utf8_lossy(&body).trim().to_string();
-                if text.is_empty() { None } else { Some(text) }
-            })
-            .unwrap_or_else(|| format!("Anthropic upstream returned {}", status));
-        return Err(crate::error::GatewayError::Config(message));
+    if status.is_success() {
+        return Ok(response);
     }
 
-    let raw: Value = serde_json::from_slice(&body)?;
-    if raw.get("object").and_then(Value::as_str) == Some("chat.completion")
-        || (raw.get("choices").is_some() && raw.get("content").is_none())
-    {
-        return Err(crate::error::GatewayError::Config(
-            "当前供应商返回的是 OpenAI Chat Completions 格式，不是 Anthropic Messages 格式；请把该供应商类型改成 OpenAI 兼容，而不是 Anthropic。".into(),
-        ));
+    let headers = response.headers().clone();
+    let body = response.bytes().await?;
+    let message = serde_json::from_slice::<Value>(&body)
+        .ok()
+        .and_then(|value| {
+            value
+                .get("error")
+                .and_then(|error| error.get("message").or_else(|| error.get("detail")))
+                .and_then(Value::as_str)
+                .map(str::to_string)
+        })
+        .or_else(|| {
+            let text = String::from_utf8_lossy(&body).trim().to_string();
+            if text.is_empty() { None } else { Some(text) }
+        })
+        .unwrap_or_else(|| format!("Anthropic upstream returned {}", status));
+    if is_api_version_error(status, &message) {
+        return Err(crate::error::GatewayError::ApiVersionMismatch(format!(
+            "{} (anthropic-version: {})",
+            message, api_version
+        )));
     }
-
-    Ok(serde_json::from_value(raw)?)
+    if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+        return Err(upstream_rate_limited(&headers, message));
+    }
+    Err(crate::error::GatewayError::Config(message))
 }
@@ -30,8 +30,18 @@ impl AnthropicProvider {
     pub async fn chat_completions(
         base_url: &str,
         api_key: &str,
+        api_version: &str,
         request: &anthropic::CreateMessageParams,
     ) -> crate::error::Result<anthropic::CreateMessageResponse> {
-        client::chat_completions(base_url, api_key, request).await
+        client::chat_completions(base_url, api_key, api_version, request).await
+    }
+
+    pub async fn stream_messages(
+        base_url: &str,
+        api_key: &str,
+        api_version: &str,
+        request: &anthropic::CreateMessageParams,
+    ) -> crate::error::Result<reqwest::Response> {
+        client::stream_messages(base_url, api_key, api_version, request).await
     }
 }
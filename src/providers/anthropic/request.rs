@@ -159,7 +159,7 @@ pub fn convert_openai_to_anthropic(
         }
     }
 
-    anthropic::CreateMessageParams {
+    let mut params = anthropic::CreateMessageParams {
         model: openai_req.model.clone(),
         system: system_prompt,
         messages: mapped_messages,
@@ -174,5 +174,61 @@ pub fn convert_openai_to_anthropic(
         top_p: openai_req.top_p,
         stream: Some(openai_req.stream.unwrap_or(false)),
         ..Default::default()
+    };
+
+    // 开启 extended thinking 时：max_tokens 需大于思考预算（在可见输出上限基础上追加预算），
+    // 且上游不接受自定义 temperature / top_k / top_p，统一丢弃
+    if let Some(budget) = openai_req.reasoning_effort.as_ref().map(thinking_budget) {
+        params.max_tokens = params.max_tokens.saturating_add(budget);
+        params.thinking = Some(anthropic::Thinking {
+            budget_tokens: budget as usize,
+            type_: anthropic::ThinkingType::Enabled,
+        });
+        params.temperature = None;
+        params.top_k = None;
+        params.top_p = None;
+    }
+    params
+}
+
+/// OpenAI `reasoning_effort` 映射为 Claude extended thinking 的 budget_tokens（上游要求至少 1024）
+fn thinking_budget(effort: &oai::ReasoningEffort) -> u32 {
+    match effort {
+        oai::ReasoningEffort::Minimal | oai::ReasoningEffort::Low => 1024,
+        oai::ReasoningEffort::Medium => 4096,
+        oai::ReasoningEffort::High => 16384,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(reasoning_effort: Option<oai::ReasoningEffort>) -> ChatCompletionRequest {
+        serde_json::from_value(serde_json::json!({
+            "model": "claude-sonnet-4",
+            "messages": [{"role": "user", "content": "hi"}],
+            "max_tokens": 500,
+            "temperature": 0.2,
+            "reasoning_effort": reasoning_effort,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn reasoning_effort_enables_extended_thinking() {
+        let params =
+            convert_openai_to_anthropic(&request(Some(oai::ReasoningEffort::Medium)), Some(5));
+        let thinking = params.thinking.expect("thinking enabled");
+        assert_eq!(thinking.budget_tokens, 4096);
+        assert_eq!(params.max_tokens, 500 + 4096);
+        assert_eq!(params.temperature, None);
+        assert_eq!(params.top_k, None);
+
+        let plain = convert_openai_to_anthropic(&request(None), Some(5));
+        assert!(plain.thinking.is_none());
+        assert_eq!(plain.max_tokens, 500);
+        assert_eq!(plain.temperature, Some(0.2));
+        assert_eq!(plain.top_k, Some(5));
     }
 }
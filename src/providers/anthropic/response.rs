@@ -28,6 +28,23 @@ pub fn extract_reasoning_content(resp: &anthropic::CreateMessageResponse) -> Opt
     None
 }
 
+/// Anthropic usage 不单独返回思考 tokens（已计入 output_tokens），按思考文本字符数 / 4 粗略估算，
+/// 并以 output_tokens 为上限；仅用于 completion_tokens_details.reasoning_tokens 展示与统计
+fn estimate_reasoning_tokens(resp: &anthropic::CreateMessageResponse) -> Option<u32> {
+    let chars: usize = resp
+        .content
+        .iter()
+        .map(|block| match block {
+            anthropic::ContentBlock::Thinking { thinking, .. } => thinking.chars().count(),
+            _ => 0,
+        })
+        .sum();
+    if chars == 0 {
+        return None;
+    }
+    Some((chars.div_ceil(4) as u32).min(resp.usage.output_tokens))
+}
+
 #[allow(deprecated)]
 pub fn convert_anthropic_to_openai(
     resp: &anthropic::CreateMessageResponse,
@@ -88,7 +105,12 @@ pub fn convert_anthropic_to_openai(
         completion_tokens: resp.usage.output_tokens,
         total_tokens: resp.usage.input_tokens + resp.usage.output_tokens,
         prompt_tokens_details: None,
-        completion_tokens_details: None,
+        completion_tokens_details: estimate_reasoning_tokens(resp).map(|reasoning_tokens| {
+            oai::CompletionTokensDetails {
+                reasoning_tokens: Some(reasoning_tokens),
+                ..Default::default()
+            }
+        }),
     };
 
     let message = oai::ChatCompletionResponseMessage {
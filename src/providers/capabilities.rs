@@ -0,0 +1,366 @@
+//! 请求字段能力注册表：各供应商类型在 OpenAI 请求转换后保留哪些字段。
+//! OpenAI 兼容与 Azure 适配器原样转发；原生协议适配器（Gemini、Cohere、Bedrock 等）只转换文本消息与采样参数，
+//! 其余字段在转换时被丢弃。分发前据此检查请求（见 `UnsupportedFeaturePolicy`），
+//! 并经 `/admin/providers/{provider}/capabilities` 暴露。
+
+use async_openai::types::{
+    ChatCompletionRequestMessage, ChatCompletionRequestUserMessageContent,
+    ChatCompletionRequestUserMessageContentPart, ResponseFormat,
+};
+use serde::Serialize;
+
+use crate::config::settings::{ProviderType, UnsupportedFeaturePolicy};
+use crate::error::GatewayError;
+use crate::providers::openai::ChatCompletionRequest;
+
+/// 响应头：本次请求中被目标供应商丢弃的字段（逗号分隔）
+pub const DROPPED_FEATURES_HEADER: &str = "x-gateway-dropped-features";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Feature {
+    /// tools / tool_choice / functions
+    Tools,
+    /// 用户消息中的 image_url
+    Vision,
+    AudioInput,
+    /// response_format: json_object
+    JsonObject,
+    /// response_format: json_schema
+    JsonSchema,
+    StreamOptions,
+    /// logprobs / top_logprobs
+    Logprobs,
+    Seed,
+    Stop,
+    ReasoningEffort,
+    /// n > 1
+    MultipleChoices,
+}
+
+pub const FEATURES: [Feature; 11] = [
+    Feature::Tools,
+    Feature::Vision,
+    Feature::AudioInput,
+    Feature::JsonObject,
+    Feature::JsonSchema,
+    Feature::StreamOptions,
+    Feature::Logprobs,
+    Feature::Seed,
+    Feature::Stop,
+    Feature::ReasoningEffort,
+    Feature::MultipleChoices,
+];
+
+impl Feature {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Feature::Tools => "tools",
+            Feature::Vision => "vision",
+            Feature::AudioInput => "audio_input",
+            Feature::JsonObject => "json_object",
+            Feature::JsonSchema => "json_schema",
+            Feature::StreamOptions => "stream_options",
+            Feature::Logprobs => "logprobs",
+            Feature::Seed => "seed",
+            Feature::Stop => "stop",
+            Feature::ReasoningEffort => "reasoning_effort",
+            Feature::MultipleChoices => "multiple_choices",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Support {
+    /// 原样转发或等价转换
+    Supported,
+    /// 转换后语义有损（见 note）
+    Degraded,
+    /// 转换时丢弃
+    Dropped,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct FeatureSupport {
+    pub feature: Feature,
+    pub support: Support,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<&'static str>,
+}
+
+/// 请求转换方式相同的供应商类型归为一类
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Translation {
+    /// 请求体原样转发（OpenAI 兼容、Azure）
+    Passthrough,
+    Anthropic,
+    /// 在 OpenAI 请求体基础上改写（tool_choice 降级为 auto 等）
+    Zhipu,
+    /// 仅转换文本消息与采样参数（Gemini / Vertex、Cohere、Bedrock）
+    TextOnly,
+    /// 百度文心旧版：文本消息与 temperature / top_p / max_tokens
+    BaiduErnie,
+    Mock,
+}
+
+fn translation(provider_type: ProviderType) -> Translation {
+    match provider_type {
+        ProviderType::Anthropic => Translation::Anthropic,
+        ProviderType::Zhipu => Translation::Zhipu,
+        ProviderType::GoogleGemini
+        | ProviderType::VertexAI
+        | ProviderType::Cohere
+        | ProviderType::AwsClaude => Translation::TextOnly,
+        ProviderType::BaiduErnie => Translation::BaiduErnie,
+        ProviderType::Mock => Translation::Mock,
+        ProviderType::OpenAI
+        | ProviderType::AzureOpenAI
+        | ProviderType::Cloudflare
+        | ProviderType::Perplexity
+        | ProviderType::Mistral
+        | ProviderType::DeepSeek
+        | ProviderType::SiliconCloud
+        | ProviderType::Moonshot
+        | ProviderType::AlibabaQwen
+        | ProviderType::Custom
+        | ProviderType::XAI
+        | ProviderType::Doubao
+        | ProviderType::Yi
+        | ProviderType::MiniMax
+        | ProviderType::TencentHunyuan
+        | ProviderType::BaiduErnieV2
+        | ProviderType::XfSpark
+        | ProviderType::ThreeSixtyZhinao
+        | ProviderType::StepFun
+        | ProviderType::Local => Translation::Passthrough,
+    }
+}
+
+pub fn support(provider_type: ProviderType, feature: Feature) -> FeatureSupport {
+    use Support::*;
+    let translation = translation(provider_type);
+    let (support, note) = match (translation, feature) {
+        (_, Feature::MultipleChoices) if provider_type.supports_native_n_choices() => {
+            (Supported, None)
+        }
+        (_, Feature::MultipleChoices) => (
+            Degraded,
+            Some("gateway fans out n separate calls; non-streaming only"),
+        ),
+        (Translation::Passthrough, _) => (Supported, None),
+        (Translation::Mock, _) => (Dropped, None),
+        (Translation::Zhipu, Feature::Tools) => (
+            Degraded,
+            Some("tool_choice is downgraded to auto; none removes tools"),
+        ),
+        (Translation::Zhipu, _) => (Supported, None),
+        (_, Feature::StreamOptions) => (
+            Degraded,
+            Some("usage is always reported in the final chunk"),
+        ),
+        (Translation::Anthropic, Feature::Tools | Feature::Vision) => (Supported, None),
+        (Translation::Anthropic, Feature::ReasoningEffort) => (
+            Degraded,
+            Some("mapped to an extended thinking budget; temperature/top_p are dropped"),
+        ),
+        (Translation::TextOnly | Translation::BaiduErnie, Feature::Vision) => (
+            Degraded,
+            Some("image URLs are inlined into the prompt as text"),
+        ),
+        (Translation::TextOnly, Feature::Stop) => (Supported, None),
+        _ => (Dropped, None),
+    };
+    FeatureSupport {
+        feature,
+        support,
+        note,
+    }
+}
+
+/// 该供应商类型对全部字段的支持情况
+pub fn report(provider_type: ProviderType) -> Vec<FeatureSupport> {
+    FEATURES
+        .iter()
+        .map(|feature| support(provider_type, *feature))
+        .collect()
+}
+
+fn user_parts(
+    request: &ChatCompletionRequest,
+) -> impl Iterator<Item = &ChatCompletionRequestUserMessageContentPart> {
+    request.messages.iter().flat_map(|message| match message {
+        ChatCompletionRequestMessage::User(user) => match &user.content {
+            ChatCompletionRequestUserMessageContent::Array(parts) => parts.as_slice(),
+            _ => &[],
+        },
+        _ => &[],
+    })
+}
+
+/// 请求中实际使用的字段
+#[allow(deprecated)]
+pub fn requested_features(request: &ChatCompletionRequest) -> Vec<Feature> {
+    let used = |feature: Feature| match feature {
+        Feature::Tools => {
+            request.tools.as_ref().is_some_and(|t| !t.is_empty())
+                || request.functions.as_ref().is_some_and(|f| !f.is_empty())
+        }
+        Feature::Vision => user_parts(request)
+            .any(|p| matches!(p, ChatCompletionRequestUserMessageContentPart::ImageUrl(_))),
+        Feature::AudioInput => user_parts(request).any(|p| {
+            matches!(
+                p,
+                ChatCompletionRequestUserMessageContentPart::InputAudio(_)
+            )
+        }),
+        Feature::JsonObject => matches!(request.response_format, Some(ResponseFormat::JsonObject)),
+        Feature::JsonSchema => matches!(
+            request.response_format,
+            Some(ResponseFormat::JsonSchema { .. })
+        ),
+        Feature::StreamOptions => request.stream_options.is_some(),
+        Feature::Logprobs => request.logprobs == Some(true) || request.top_logprobs.is_some(),
+        Feature::Seed => request.seed.is_some(),
+        Feature::Stop => request.stop.is_some(),
+        Feature::ReasoningEffort => request.reasoning_effort.is_some(),
+        Feature::MultipleChoices => request.n.unwrap_or(1) > 1,
+    };
+    FEATURES.into_iter().filter(|f| used(*f)).collect()
+}
+
+/// 分发前检查：返回会被目标供应商丢弃的字段；策略为 reject 时改为返回错误
+pub fn check_request(
+    provider_type: ProviderType,
+    request: &ChatCompletionRequest,
+    policy: UnsupportedFeaturePolicy,
+) -> Result<Vec<Feature>, GatewayError> {
+    if policy == UnsupportedFeaturePolicy::Ignore {
+        return Ok(Vec::new());
+    }
+    let dropped: Vec<Feature> = requested_features(request)
+        .into_iter()
+        .filter(|f| support(provider_type, *f).support == Support::Dropped)
+        .collect();
+    if !dropped.is_empty() && policy == UnsupportedFeaturePolicy::Reject {
+        return Err(GatewayError::Config(format!(
+            "provider type '{}' does not support: {}; remove these fields or choose another provider",
+            provider_type.as_str(),
+            header_value(&dropped)
+        )));
+    }
+    Ok(dropped)
+}
+
+pub fn header_value(features: &[Feature]) -> String {
+    features
+        .iter()
+        .map(|f| f.as_str())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// 有字段被丢弃时附加 `x-gateway-dropped-features`
+pub fn insert_header(response: &mut axum::response::Response, features: &[Feature]) {
+    if features.is_empty() {
+        return;
+    }
+    if let Ok(value) = axum::http::HeaderValue::from_str(&header_value(features)) {
+        response
+            .headers_mut()
+            .insert(DROPPED_FEATURES_HEADER, value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn request(extra: serde_json::Value) -> ChatCompletionRequest {
+        let mut value = json!({
+            "model": "m1",
+            "messages": [{"role": "user", "content": [
+                {"type": "text", "text": "describe"},
+                {"type": "image_url", "image_url": {"url": "https://example.com/a.png"}}
+            ]}],
+        });
+        value
+            .as_object_mut()
+            .unwrap()
+            .extend(extra.as_object().unwrap().clone());
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn detects_requested_features() {
+        let req = request(json!({
+            "tools": [{"type": "function", "function": {"name": "f", "parameters": {}}}],
+            "response_format": {"type": "json_object"},
+            "logprobs": true,
+            "seed": 7,
+        }));
+        assert_eq!(
+            requested_features(&req),
+            vec![
+                Feature::Tools,
+                Feature::Vision,
+                Feature::JsonObject,
+                Feature::Logprobs,
+                Feature::Seed
+            ]
+        );
+        assert!(requested_features(&request(json!({"logprobs": false}))) == vec![Feature::Vision]);
+    }
+
+    #[test]
+    fn reports_dropped_fields_per_translation() {
+        let req = request(json!({
+            "tools": [{"type": "function", "function": {"name": "f", "parameters": {}}}],
+            "stop": ["END"],
+            "seed": 7,
+        }));
+        let warn = UnsupportedFeaturePolicy::Warn;
+        assert!(
+            check_request(ProviderType::OpenAI, &req, warn)
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(
+            check_request(ProviderType::Anthropic, &req, warn).unwrap(),
+            vec![Feature::Seed, Feature::Stop]
+        );
+        // 图片以文本内联属于有损转换，不计入丢弃
+        assert_eq!(
+            check_request(ProviderType::GoogleGemini, &req, warn).unwrap(),
+            vec![Feature::Tools, Feature::Seed]
+        );
+        assert!(
+            check_request(
+                ProviderType::GoogleGemini,
+                &req,
+                UnsupportedFeaturePolicy::Ignore
+            )
+            .unwrap()
+            .is_empty()
+        );
+        let err = check_request(ProviderType::Cohere, &req, UnsupportedFeaturePolicy::Reject)
+            .unwrap_err();
+        assert!(err.to_string().contains("does not support: tools,seed"));
+    }
+
+    #[test]
+    fn report_covers_every_feature() {
+        let report = report(ProviderType::Zhipu);
+        assert_eq!(report.len(), FEATURES.len());
+        assert_eq!(report[0].support, Support::Degraded);
+        assert_eq!(
+            support(ProviderType::Cohere, Feature::MultipleChoices).support,
+            Support::Degraded
+        );
+        assert_eq!(
+            support(ProviderType::Custom, Feature::MultipleChoices).support,
+            Support::Supported
+        );
+    }
+}
@@ -0,0 +1,84 @@
+//! 本地 OpenAI 兼容运行时（Ollama / vLLM / LM Studio）的辅助逻辑：
+//! 聊天请求沿用 OpenAI 兼容链路，模型列表优先读取 Ollama 原生的 `/api/tags`。
+
+use serde::Deserialize;
+
+use crate::providers::openai::{Model, ModelListResponse};
+
+/// Ollama 模型列表地址：`/api/tags` 位于服务根路径，忽略 base_url 末尾的 `/v1`
+pub fn tags_url(base_url: &str) -> String {
+    let base = base_url.trim().trim_end_matches('/');
+    let root = base.strip_suffix("/v1").unwrap_or(base);
+    format!("{}/api/tags", root)
+}
+
+#[derive(Debug, Deserialize)]
+struct TagsResponse {
+    models: Vec<Tag>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Tag {
+    name: String,
+    #[serde(default)]
+    modified_at: Option<String>,
+}
+
+/// 解析模型列表：兼容 OpenAI `/v1/models`（`data[].id`）与 Ollama `/api/tags`（`models[].name`）
+pub fn parse_model_list(bytes: &[u8], owned_by: &str) -> Option<Vec<Model>> {
+    if let Ok(list) = serde_json::from_slice::<ModelListResponse>(bytes) {
+        return Some(list.data);
+    }
+    let tags = serde_json::from_slice::<TagsResponse>(bytes).ok()?;
+    Some(
+        tags.models
+            .into_iter()
+            .map(|tag| Model {
+                created: tag
+                    .modified_at
+                    .as_deref()
+                    .and_then(|raw| chrono::DateTime::parse_from_rfc3339(raw).ok())
+                    .map(|at| at.timestamp().max(0) as u64)
+                    .unwrap_or(0),
+                id: tag.name,
+                object: "model".into(),
+                owned_by: owned_by.to_string(),
+                display_name: None,
+            })
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tags_url_strips_openai_suffix() {
+        assert_eq!(
+            tags_url("http://localhost:11434/v1/"),
+            "http://localhost:11434/api/tags"
+        );
+        assert_eq!(
+            tags_url("http://gpu-box:11434"),
+            "http://gpu-box:11434/api/tags"
+        );
+    }
+
+    #[test]
+    fn parses_ollama_tags_and_openai_lists() {
+        let tags = br#"{"models":[{"name":"llama3.1:8b","model":"llama3.1:8b","modified_at":"2024-08-01T10:00:00.123456789+08:00","size":4661224676}]}"#;
+        let models = parse_model_list(tags, "ollama").unwrap();
+        assert_eq!(models.len(), 1);
+        assert_eq!(models[0].id, "llama3.1:8b");
+        assert_eq!(models[0].owned_by, "ollama");
+        assert!(models[0].created > 0);
+
+        let openai = br#"{"object":"list","data":[{"id":"Qwen/Qwen2.5-7B","object":"model","created":1,"owned_by":"vllm"}]}"#;
+        let models = parse_model_list(openai, "local").unwrap();
+        assert_eq!(models[0].id, "Qwen/Qwen2.5-7B");
+        assert_eq!(models[0].owned_by, "vllm");
+
+        assert!(parse_model_list(b"not json", "local").is_none());
+    }
+}
@@ -1,5 +1,7 @@
 pub mod adapters;
 pub mod anthropic;
+pub mod capabilities;
+pub mod local;
 pub mod openai;
 pub mod zhipu;
 
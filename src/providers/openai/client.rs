@@ -1,9 +1,8 @@
 use crate::error::GatewayError;
-use crate::providers::adapters::gateway_error_from_normalized;
+use crate::providers::adapters::{gateway_error_from_normalized, upstream_rate_limited};
+use crate::routing::OpenAIAccountHeaders;
 
-use super::types::{
-    ChatCompletionRequest, ChatCompletionResponse, ModelListResponse, RawAndTypedChatCompletion,
-};
+use super::types::{ChatCompletionRequest, ChatCompletionResponse, RawAndTypedChatCompletion};
 use super::usage::usage_from_value;
 
 pub struct OpenAIProvider;
@@ -23,10 +22,20 @@ fn join_openai_compat_endpoint(base_url: &str, path: &str) -> String {
     }
 }
 
+/// 附加 Bearer 认证头；密钥为空（无鉴权的本地运行时）时不发送
+pub fn bearer_auth(builder: reqwest::RequestBuilder, api_key: &str) -> reqwest::RequestBuilder {
+    if api_key.is_empty() {
+        builder
+    } else {
+        builder.header("Authorization", format!("Bearer {}", api_key))
+    }
+}
+
 impl OpenAIProvider {
     pub async fn chat_completions(
         base_url: &str,
         api_key: &str,
+        account: &OpenAIAccountHeaders,
         request: &ChatCompletionRequest,
     ) -> Result<RawAndTypedChatCompletion, GatewayError> {
         let url = join_openai_compat_endpoint(base_url, "chat/completions");
@@ -36,16 +45,24 @@ impl OpenAIProvider {
             client: &reqwest::Client,
             url: &str,
             api_key: &str,
+            account: &OpenAIAccountHeaders,
             request: &ChatCompletionRequest,
         ) -> Result<Vec<u8>, GatewayError> {
-            let response = client
-                .post(url)
-                .header("Authorization", format!("Bearer {}", api_key))
+            let builder = bearer_auth(client.post(url), api_key)
                 .header("Content-Type", "application/json")
-                .header("Accept", "application/json")
-                .json(request)
-                .send()
-                .await?;
+                .header("Accept", "application/json");
+            let response = account.apply(builder).json(request).send().await?;
+            if response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS {
+                let headers = response.headers().clone();
+                let raw = serde_json::from_slice(&response.bytes().await?)
+                    .unwrap_or(serde_json::Value::Null);
+                let message = raw
+                    .pointer("/error/message")
+                    .and_then(|value| value.as_str())
+                    .unwrap_or("upstream rate limited")
+                    .to_string();
+                return Err(upstream_rate_limited(&headers, message));
+            }
             Ok(response.bytes().await?.to_vec())
         }
 
@@ -150,7 +167,7 @@ impl OpenAIProvider {
         // 非流式：优先严格解析；失败则宽松回退构造（兼容部分上游缺失 object 等字段）。
         // 若上游聚合器对特定模型仅支持 stream=true，会返回结构化错误（bad_response_body 等），此时自动重试一次 stream=true，
         // 并将 SSE 聚合为非流式 JSON 返回给前端（对前端保持一次性响应语义）。
-        let bytes = send_bytes(&client, &url, api_key, request).await?;
+        let bytes = send_bytes(&client, &url, api_key, account, request).await?;
         let mut dual = parse_non_stream_bytes(&bytes)?;
         if !request.stream.unwrap_or(false)
             && (is_retryable_stream_required_error(&dual.raw)
@@ -158,27 +175,185 @@ impl OpenAIProvider {
         {
             let mut streaming_req = request.clone();
             streaming_req.stream = Some(true);
-            let bytes2 = send_bytes(&client, &url, api_key, &streaming_req).await?;
+            let bytes2 = send_bytes(&client, &url, api_key, account, &streaming_req).await?;
             dual = parse_non_stream_bytes(&bytes2)?;
         }
         Ok(dual)
     }
 
-    pub async fn list_models(
+    /// 旧版文本补全（`/v1/completions`）：`body` 为已含 prompt 的原生请求体，
+    /// 响应转为聊天补全形状（`object` 保持 `text_completion`，choices 的 logprobs 原样保留），
+    /// 以便计费、日志与缓存沿用聊天链路
+    pub async fn text_completions(
         base_url: &str,
         api_key: &str,
-    ) -> Result<ModelListResponse, GatewayError> {
-        let url = join_openai_compat_endpoint(base_url, "models");
+        account: &OpenAIAccountHeaders,
+        body: &serde_json::Value,
+    ) -> Result<RawAndTypedChatCompletion, GatewayError> {
+        let url = join_openai_compat_endpoint(base_url, "completions");
         let client = crate::http_client::client_for_url(&url)?;
+        let builder = bearer_auth(client.post(&url), api_key)
+            .header("Content-Type", "application/json")
+            .header("Accept", "application/json");
+        let response = account.apply(builder).json(body).send().await?;
+        if response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            let headers = response.headers().clone();
+            return Err(upstream_rate_limited(
+                &headers,
+                "upstream rate limited".into(),
+            ));
+        }
+        let raw: serde_json::Value = serde_json::from_slice(&response.bytes().await?)?;
+        if let Some(err) = gateway_error_from_openai_payload(&raw) {
+            return Err(err);
+        }
+        let raw = text_completion_to_chat_shape(raw);
+        let typed = fallback_response_from_bytes(&serde_json::to_vec(&raw)?)?;
+        Ok(RawAndTypedChatCompletion { typed, raw })
+    }
 
-        let response = client
-            .get(&url)
-            .header("Authorization", format!("Bearer {}", api_key))
+    /// 图片生成（`/images/generations`）：请求体原样转发，返回上游 JSON（含 data 数组）
+    pub async fn image_generations(
+        base_url: &str,
+        api_key: &str,
+        account: &OpenAIAccountHeaders,
+        body: &serde_json::Value,
+    ) -> Result<serde_json::Value, GatewayError> {
+        let url = join_openai_compat_endpoint(base_url, "images/generations");
+        let client = crate::http_client::client_for_url(&url)?;
+        let builder = bearer_auth(client.post(&url), api_key)
             .header("Content-Type", "application/json")
+            .header("Accept", "application/json");
+        let response = account.apply(builder).json(body).send().await?;
+        if response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            let headers = response.headers().clone();
+            return Err(upstream_rate_limited(
+                &headers,
+                "upstream rate limited".into(),
+            ));
+        }
+        let raw: serde_json::Value = serde_json::from_slice(&response.bytes().await?)?;
+        if let Some(err) = gateway_error_from_openai_payload(&raw) {
+            return Err(err);
+        }
+        Ok(raw)
+    }
+
+    /// 语音转写（`/audio/transcriptions`）：multipart 请求体原样转发，
+    /// 成功时返回上游响应的 Content-Type 与原始字节（json/text/srt/vtt 等格式由请求决定）
+    pub async fn audio_transcriptions(
+        base_url: &str,
+        api_key: &str,
+        account: &OpenAIAccountHeaders,
+        content_type: &str,
+        body: Vec<u8>,
+    ) -> Result<(String, Vec<u8>), GatewayError> {
+        let url = join_openai_compat_endpoint(base_url, "audio/transcriptions");
+        let client = crate::http_client::client_for_url(&url)?;
+        let builder = bearer_auth(client.post(&url), api_key).header("Content-Type", content_type);
+        let response = account.apply(builder).body(body).send().await?;
+        let status = response.status();
+        if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            let headers = response.headers().clone();
+            return Err(upstream_rate_limited(
+                &headers,
+                "upstream rate limited".into(),
+            ));
+        }
+        let response_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("application/json")
+            .to_string();
+        let bytes = response.bytes().await?.to_vec();
+        if !status.is_success() {
+            let raw: serde_json::Value = serde_json::from_slice(&bytes).unwrap_or_default();
+            return Err(gateway_error_from_openai_payload(&raw).unwrap_or_else(|| {
+                gateway_error_from_normalized(
+                    "upstream_error",
+                    format!(
+                        "upstream returned {}: {}",
+                        status,
+                        String::from_utf8_lossy(&bytes)
+                    ),
+                )
+            }));
+        }
+        Ok((response_type, bytes))
+    }
+
+    /// 语音合成（`/audio/speech`）：JSON 请求体原样转发，成功时返回上游响应，
+    /// 由调用方以流的形式把音频字节转发给客户端
+    pub async fn audio_speech(
+        base_url: &str,
+        api_key: &str,
+        account: &OpenAIAccountHeaders,
+        body: &serde_json::Value,
+    ) -> Result<reqwest::Response, GatewayError> {
+        let url = join_openai_compat_endpoint(base_url, "audio/speech");
+        let client = crate::http_client::client_for_url(&url)?;
+        let builder =
+            bearer_auth(client.post(&url), api_key).header("Content-Type", "application/json");
+        let response = account.apply(builder).json(body).send().await?;
+        let status = response.status();
+        if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            let headers = response.headers().clone();
+            return Err(upstream_rate_limited(
+                &headers,
+                "upstream rate limited".into(),
+            ));
+        }
+        if !status.is_success() {
+            let bytes = response.bytes().await?;
+            let raw: serde_json::Value = serde_json::from_slice(&bytes).unwrap_or_default();
+            return Err(gateway_error_from_openai_payload(&raw).unwrap_or_else(|| {
+                gateway_error_from_normalized(
+                    "upstream_error",
+                    format!(
+                        "upstream returned {}: {}",
+                        status,
+                        String::from_utf8_lossy(&bytes)
+                    ),
+                )
+            }));
+        }
+        Ok(response)
+    }
+
+    /// 模型列表接口地址（OpenAI 兼容 `/v1/models`）
+    pub fn models_url(base_url: &str) -> String {
+        join_openai_compat_endpoint(base_url, "models")
+    }
+
+    /// 单条文本的向量（/embeddings，返回 data[0].embedding）
+    pub async fn embeddings(
+        base_url: &str,
+        api_key: &str,
+        account: &OpenAIAccountHeaders,
+        model: &str,
+        input: &str,
+    ) -> Result<Vec<f32>, GatewayError> {
+        let url = join_openai_compat_endpoint(base_url, "embeddings");
+        let client = crate::http_client::client_for_url(&url)?;
+
+        let builder =
+            bearer_auth(client.post(&url), api_key).header("Content-Type", "application/json");
+        let response = account
+            .apply(builder)
+            .json(&serde_json::json!({ "model": model, "input": input }))
             .send()
             .await?;
-
-        Ok(response.json::<ModelListResponse>().await?)
+        let raw: serde_json::Value = response.json().await?;
+        if let Some(err) = gateway_error_from_openai_payload(&raw) {
+            return Err(err);
+        }
+        raw.pointer("/data/0/embedding")
+            .and_then(|value| serde_json::from_value::<Vec<f32>>(value.clone()).ok())
+            .filter(|vector| !vector.is_empty())
+            .ok_or_else(|| {
+                GatewayError::Config("embedding response has no data[0].embedding".into())
+            })
     }
 
     // 备注：流式聊天统一由 server/streaming 模块处理（基于 reqwest-eventsource）
@@ -448,7 +623,23 @@ fn sse_chat_completion_to_json(bytes: &[u8]) -> Result<Vec<u8>, GatewayError> {
     Ok(serde_json::to_vec(&out)?)
 }
 
-fn gateway_error_from_openai_payload(raw: &serde_json::Value) -> Option<GatewayError> {
+/// 文本补全响应的 `choices[].text` 转为 `choices[].message.content`，其余字段不变
+fn text_completion_to_chat_shape(mut raw: serde_json::Value) -> serde_json::Value {
+    if let Some(choices) = raw.get_mut("choices").and_then(|v| v.as_array_mut()) {
+        for choice in choices.iter_mut().filter_map(|c| c.as_object_mut()) {
+            let text = choice
+                .remove("text")
+                .unwrap_or_else(|| serde_json::Value::String(String::new()));
+            choice.insert(
+                "message".to_string(),
+                serde_json::json!({"role": "assistant", "content": text}),
+            );
+        }
+    }
+    raw
+}
+
+pub(crate) fn gateway_error_from_openai_payload(raw: &serde_json::Value) -> Option<GatewayError> {
     let error = raw.get("error")?;
     if raw.get("choices").is_some() {
         return None;
@@ -1,6 +1,7 @@
 use crate::error::GatewayError;
 use crate::providers::openai::types::RawAndTypedChatCompletion;
 use async_openai::types as oai;
+use serde_json::{Value, json};
 
 // 轻量适配：
 // - 去除 data:image/...;base64, 前缀，只保留逗号后的纯 base64 数据
@@ -38,6 +39,102 @@ pub fn adapt_openai_request_for_zhipu(
     req
 }
 
+/// 构造智谱请求体：在 adapt_openai_request_for_zhipu 基础上映射智谱专有参数
+/// - temperature <= 0 映射为 do_sample=false（智谱不支持 0 温度采样），> 1 压至 1.0
+/// - web_search_options 转为智谱内置 web_search 工具
+/// - tool_choice 仅支持 auto：none 时去掉 tools，指定函数时降级为 auto
+pub fn build_zhipu_request_body(
+    req: oai::CreateChatCompletionRequest,
+) -> Result<Value, GatewayError> {
+    let req = adapt_openai_request_for_zhipu(req);
+    let web_search = req.web_search_options.is_some();
+    let mut body = serde_json::to_value(&req)?;
+    let Some(obj) = body.as_object_mut() else {
+        return Ok(body);
+    };
+
+    if let Some(t) = req.temperature {
+        if t <= 0.0 {
+            obj.remove("temperature");
+            obj.insert("do_sample".into(), Value::Bool(false));
+        } else if t > 1.0 {
+            obj.insert("temperature".into(), json!(1.0));
+        }
+    }
+
+    match obj.get("tool_choice") {
+        Some(Value::String(choice)) if choice == "none" => {
+            obj.remove("tool_choice");
+            obj.remove("tools");
+        }
+        Some(Value::String(choice)) if choice == "auto" => {}
+        Some(_) => {
+            obj.insert("tool_choice".into(), json!("auto"));
+        }
+        None => {}
+    }
+
+    if web_search {
+        obj.remove("web_search_options");
+        let tool = json!({
+            "type": "web_search",
+            "web_search": {"enable": true, "search_result": true}
+        });
+        match obj.get_mut("tools").and_then(|v| v.as_array_mut()) {
+            Some(tools) => tools.push(tool),
+            None => {
+                obj.insert("tools".into(), json!([tool]));
+            }
+        }
+    }
+
+    Ok(body)
+}
+
+/// 将智谱响应 / 流式 chunk 转为 OpenAI 兼容格式：
+/// - 过滤 web_search / retrieval 等非 function 类型的内置工具调用
+/// - 补齐流式 delta 中缺失的 index；arguments 为对象时序列化为字符串
+/// - finish_reason 的 sensitive 映射为 content_filter
+pub fn normalize_zhipu_response(v: &mut Value) {
+    let Some(choices) = v.get_mut("choices").and_then(|c| c.as_array_mut()) else {
+        return;
+    };
+    for choice in choices {
+        if choice.get("finish_reason").and_then(|f| f.as_str()) == Some("sensitive") {
+            choice["finish_reason"] = json!("content_filter");
+        }
+        for key in ["message", "delta"] {
+            let Some(msg) = choice.get_mut(key).and_then(|m| m.as_object_mut()) else {
+                continue;
+            };
+            let Some(calls) = msg.get_mut("tool_calls").and_then(|t| t.as_array_mut()) else {
+                continue;
+            };
+            calls.retain(|call| {
+                call.get("type")
+                    .and_then(|t| t.as_str())
+                    .is_none_or(|t| t == "function")
+            });
+            for (i, call) in calls.iter_mut().enumerate() {
+                if key == "delta" && call.get("index").is_none() {
+                    call["index"] = json!(i);
+                }
+                if call.get("type").is_none() {
+                    call["type"] = json!("function");
+                }
+                if let Some(args) = call.pointer_mut("/function/arguments")
+                    && !args.is_string()
+                {
+                    *args = Value::String(args.to_string());
+                }
+            }
+            if calls.is_empty() {
+                msg.remove("tool_calls");
+            }
+        }
+    }
+}
+
 pub async fn chat_completions(
     base_url: &str,
     api_key: &str,
@@ -53,7 +150,7 @@ pub async fn chat_completions(
         .header("Authorization", format!("Bearer {}", api_key))
         .header("Content-Type", "application/json")
         .header("Accept", "application/json")
-        .json(&adapt_openai_request_for_zhipu(request.clone()))
+        .json(&build_zhipu_request_body(request.clone())?)
         .send()
         .await?;
     let bytes = resp.bytes().await?;
@@ -62,20 +159,58 @@ pub async fn chat_completions(
         let _ = serde_json::from_slice::<serde_json::Value>(&bytes)?;
         unreachable!();
     }
-    let raw: serde_json::Value = serde_json::from_slice(&bytes)?;
-    let typed = match serde_json::from_slice::<oai::CreateChatCompletionResponse>(&bytes) {
+    let mut raw: serde_json::Value = serde_json::from_slice(&bytes)?;
+    normalize_zhipu_response(&mut raw);
+    let typed = match serde_json::from_value::<oai::CreateChatCompletionResponse>(raw.clone()) {
         Ok(ok) => ok,
-        Err(_) => fallback_response_from_bytes(&bytes)?,
+        Err(_) => fallback_response_from_value(&raw),
     };
     Ok(RawAndTypedChatCompletion { typed, raw })
 }
 
+/// OpenAI 图片生成请求体转为 CogView 格式：user 改名为 user_id，
+/// 去掉 CogView 不支持的 n 与 response_format（每次只生成一张，返回 URL）
+pub fn build_zhipu_image_body(body: &Value) -> Value {
+    let mut out = body.clone();
+    if let Some(obj) = out.as_object_mut() {
+        obj.remove("n");
+        obj.remove("response_format");
+        if let Some(user) = obj.remove("user") {
+            obj.insert("user_id".into(), user);
+        }
+    }
+    out
+}
+
+/// CogView 图片生成（`/api/paas/v4/images/generations`），单次一张
+pub async fn image_generations(
+    base_url: &str,
+    api_key: &str,
+    body: &Value,
+) -> Result<Value, GatewayError> {
+    let client = reqwest::Client::new();
+    let url = format!(
+        "{}/api/paas/v4/images/generations",
+        base_url.trim_end_matches('/')
+    );
+    let resp = client
+        .post(&url)
+        .header("Authorization", format!("Bearer {}", api_key))
+        .header("Content-Type", "application/json")
+        .header("Accept", "application/json")
+        .json(&build_zhipu_image_body(body))
+        .send()
+        .await?;
+    let raw: Value = serde_json::from_slice(&resp.bytes().await?)?;
+    if let Some(err) = crate::providers::openai::client::gateway_error_from_openai_payload(&raw) {
+        return Err(err);
+    }
+    Ok(raw)
+}
+
 #[allow(deprecated)]
-fn fallback_response_from_bytes(
-    bytes: &[u8],
-) -> Result<oai::CreateChatCompletionResponse, GatewayError> {
+fn fallback_response_from_value(v: &serde_json::Value) -> oai::CreateChatCompletionResponse {
     use async_openai::types as oai;
-    let v: serde_json::Value = serde_json::from_slice(bytes)?;
 
     // 与 OpenAI 回退逻辑一致，尽力填充字段
     let id = v
@@ -223,7 +358,7 @@ fn fallback_response_from_bytes(
         }
     }
 
-    Ok(oai::CreateChatCompletionResponse {
+    oai::CreateChatCompletionResponse {
         id,
         object,
         created,
@@ -232,5 +367,63 @@ fn fallback_response_from_bytes(
         usage,
         service_tier: None,
         system_fingerprint: None,
-    })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(extra: Value) -> oai::CreateChatCompletionRequest {
+        let mut body = json!({
+            "model": "glm-4",
+            "messages": [{"role": "user", "content": "hi"}],
+            "tools": [{"type": "function", "function": {"name": "lookup", "parameters": {}}}],
+        });
+        for (k, v) in extra.as_object().unwrap() {
+            body[k] = v.clone();
+        }
+        serde_json::from_value(body).unwrap()
+    }
+
+    #[test]
+    fn build_request_body_maps_zhipu_options() {
+        let body = build_zhipu_request_body(request(json!({
+            "temperature": 0.0,
+            "tool_choice": {"type": "function", "function": {"name": "lookup"}},
+            "web_search_options": {}
+        })))
+        .unwrap();
+        assert_eq!(body["do_sample"], json!(false));
+        assert!(body.get("temperature").is_none());
+        assert_eq!(body["tool_choice"], json!("auto"));
+        assert!(body.get("web_search_options").is_none());
+        assert_eq!(body["tools"][1]["type"], json!("web_search"));
+
+        let body = build_zhipu_request_body(request(json!({"tool_choice": "none"}))).unwrap();
+        assert!(body.get("tools").is_none());
+        assert!(body.get("tool_choice").is_none());
+    }
+
+    #[test]
+    fn normalize_response_converts_tool_call_deltas() {
+        let mut chunk = json!({
+            "choices": [{
+                "index": 0,
+                "finish_reason": "sensitive",
+                "delta": {"tool_calls": [
+                    {"id": "ws", "type": "web_search", "web_search": {}},
+                    {"id": "call_1", "function": {"name": "lookup", "arguments": {"q": "x"}}}
+                ]}
+            }]
+        });
+        normalize_zhipu_response(&mut chunk);
+        let choice = &chunk["choices"][0];
+        assert_eq!(choice["finish_reason"], json!("content_filter"));
+        let calls = choice["delta"]["tool_calls"].as_array().unwrap();
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0]["index"], json!(0));
+        assert_eq!(calls[0]["type"], json!("function"));
+        assert_eq!(calls[0]["function"]["arguments"], json!("{\"q\":\"x\"}"));
+    }
 }
@@ -0,0 +1,136 @@
+//! 密钥分组标签路由：供应商配置中的 `key_label_rules` 按令牌/模型匹配，
+//! 命中的规则让密钥轮换优先在带指定标签的密钥中进行；这些密钥均不可用时回落到全部密钥。
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use super::key_rotation::ProviderKeyEntry;
+
+/// 一条标签路由规则：tokens/models 为空表示不限
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyLabelRule {
+    /// 密钥需同时带有的标签
+    pub labels: BTreeMap<String, String>,
+    /// 适用的客户端令牌 ID
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tokens: Vec<String>,
+    /// 适用的模型（不含供应商前缀，重定向后的名称）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub models: Vec<String>,
+}
+
+/// 选择密钥时的请求上下文（管理面调用等无令牌场景为空）
+#[derive(Debug, Clone, Copy, Default)]
+pub struct KeyRoute<'a> {
+    pub token_id: Option<&'a str>,
+    pub model: Option<&'a str>,
+}
+
+impl KeyLabelRule {
+    fn applies_to(&self, route: KeyRoute<'_>) -> bool {
+        let token_ok = self.tokens.is_empty()
+            || route
+                .token_id
+                .is_some_and(|id| self.tokens.iter().any(|t| t == id));
+        let model_ok = self.models.is_empty()
+            || route
+                .model
+                .is_some_and(|m| self.models.iter().any(|x| x == m));
+        token_ok && model_ok
+    }
+
+    fn matches(&self, key: &ProviderKeyEntry) -> bool {
+        self.labels
+            .iter()
+            .all(|(name, value)| key.labels.get(name) == Some(value))
+    }
+}
+
+/// 按顺序取第一条适用且存在启用中匹配密钥的规则，返回其匹配的密钥；无规则命中时为 None
+pub fn preferred_keys(
+    rules: &[KeyLabelRule],
+    keys: &[ProviderKeyEntry],
+    route: KeyRoute<'_>,
+) -> Option<Vec<ProviderKeyEntry>> {
+    rules
+        .iter()
+        .filter(|rule| !rule.labels.is_empty() && rule.applies_to(route))
+        .map(|rule| {
+            keys.iter()
+                .filter(|key| rule.matches(key))
+                .cloned()
+                .collect::<Vec<_>>()
+        })
+        .find(|matched| matched.iter().any(|key| key.active))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(value: &str, labels: &[(&str, &str)]) -> ProviderKeyEntry {
+        ProviderKeyEntry {
+            value: value.into(),
+            active: true,
+            weight: 1,
+            spend_cap: None,
+            rpm_limit: None,
+            tpm_limit: None,
+            openai_account: Default::default(),
+            labels: labels
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        }
+    }
+
+    fn rule(labels: &[(&str, &str)], tokens: &[&str], models: &[&str]) -> KeyLabelRule {
+        KeyLabelRule {
+            labels: labels
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            tokens: tokens.iter().map(|s| s.to_string()).collect(),
+            models: models.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn first_applicable_rule_with_active_keys_wins() {
+        let keys = vec![
+            key("us-free", &[("region", "us"), ("tier", "free")]),
+            key("eu-paid", &[("region", "eu"), ("tier", "paid")]),
+            key("eu-free", &[("region", "eu"), ("tier", "free")]),
+        ];
+        let rules = vec![
+            rule(&[("tier", "paid")], &["tok-vip"], &[]),
+            rule(&[("region", "eu")], &[], &["gpt-4o"]),
+            rule(&[("region", "apac")], &[], &[]),
+        ];
+        let values = |route| {
+            preferred_keys(&rules, &keys, route)
+                .map(|keys| keys.into_iter().map(|k| k.value).collect::<Vec<_>>())
+        };
+
+        let vip = KeyRoute {
+            token_id: Some("tok-vip"),
+            model: Some("gpt-4o-mini"),
+        };
+        assert_eq!(values(vip), Some(vec!["eu-paid".to_string()]));
+        let eu = KeyRoute {
+            token_id: Some("tok-other"),
+            model: Some("gpt-4o"),
+        };
+        assert_eq!(
+            values(eu),
+            Some(vec!["eu-paid".to_string(), "eu-free".to_string()])
+        );
+        // 仅剩无匹配密钥的 apac 规则：不做偏好
+        let other = KeyRoute {
+            token_id: Some("tok-other"),
+            model: Some("gpt-4o-mini"),
+        };
+        assert_eq!(values(other), None);
+        assert_eq!(values(KeyRoute::default()), None);
+    }
+}
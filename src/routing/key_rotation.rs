@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
@@ -7,6 +8,8 @@ pub enum KeyRotationStrategy {
     Random,
     WeightedSequential,
     WeightedRandom,
+    /// 优先选择剩余额度最多的密钥，并避开接近速率上限的密钥
+    UsageWeighted,
 }
 
 impl Default for KeyRotationStrategy {
@@ -22,6 +25,7 @@ impl KeyRotationStrategy {
             "random" => Self::Random,
             "weighted_random" => Self::WeightedRandom,
             "weighted_sequential" => Self::WeightedSequential,
+            "usage_weighted" => Self::UsageWeighted,
             _ => Self::default(),
         }
     }
@@ -32,6 +36,7 @@ impl KeyRotationStrategy {
             Self::Random => "random",
             Self::WeightedSequential => "weighted_sequential",
             Self::WeightedRandom => "weighted_random",
+            Self::UsageWeighted => "usage_weighted",
         }
     }
 }
@@ -41,4 +46,77 @@ pub struct ProviderKeyEntry {
     pub value: String,
     pub active: bool,
     pub weight: u32,
+    /// 滚动 24 小时内的花费上限（usage_weighted 策略使用）
+    #[serde(default)]
+    pub spend_cap: Option<f64>,
+    /// 每分钟请求数上限；窗口用尽的密钥会被跳过
+    #[serde(default)]
+    pub rpm_limit: Option<u32>,
+    /// 每分钟 token 数上限；窗口用尽的密钥会被跳过
+    #[serde(default)]
+    pub tpm_limit: Option<u32>,
+    /// 该密钥所属的 OpenAI 组织/项目（部分上游账号要求携带）
+    #[serde(default, flatten)]
+    pub openai_account: OpenAIAccountHeaders,
+    /// 分组标签（如 region=eu、tier=paid），供 `key_labels` 路由规则匹配
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub labels: BTreeMap<String, String>,
+}
+
+/// 按密钥注入上游请求的 OpenAI-Organization / OpenAI-Project 头
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OpenAIAccountHeaders {
+    #[serde(default)]
+    pub openai_organization: Option<String>,
+    #[serde(default)]
+    pub openai_project: Option<String>,
+}
+
+impl OpenAIAccountHeaders {
+    /// 取 keys 中与 api_key 对应的配置；未找到时为空
+    pub fn for_key(keys: &[ProviderKeyEntry], api_key: &str) -> Self {
+        keys.iter()
+            .find(|k| k.value == api_key)
+            .map(|k| k.openai_account.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn apply(&self, builder: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        let mut builder = builder;
+        if let Some(org) = self.openai_organization.as_deref() {
+            builder = builder.header("OpenAI-Organization", org);
+        }
+        if let Some(project) = self.openai_project.as_deref() {
+            builder = builder.header("OpenAI-Project", project);
+        }
+        builder
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn openai_account_headers_follow_selected_key() {
+        let keys: Vec<ProviderKeyEntry> = serde_json::from_value(serde_json::json!([
+            { "value": "sk-a", "active": true, "weight": 1 },
+            { "value": "sk-b", "active": true, "weight": 1,
+              "openai_organization": "org-1", "openai_project": "proj-1" },
+        ]))
+        .unwrap();
+        assert_eq!(
+            OpenAIAccountHeaders::for_key(&keys, "sk-a"),
+            OpenAIAccountHeaders::default()
+        );
+        let account = OpenAIAccountHeaders::for_key(&keys, "sk-b");
+        assert_eq!(account.openai_organization.as_deref(), Some("org-1"));
+
+        let request = account
+            .apply(reqwest::Client::new().post("http://localhost/v1/chat/completions"))
+            .build()
+            .unwrap();
+        assert_eq!(request.headers()["OpenAI-Organization"], "org-1");
+        assert_eq!(request.headers()["OpenAI-Project"], "proj-1");
+    }
 }
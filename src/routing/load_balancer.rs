@@ -1,8 +1,10 @@
 use crate::config::{BalanceStrategy, Provider};
-use crate::routing::{KeyRotationStrategy, ProviderKeyEntry};
+use crate::routing::{KeyRotationStrategy, OpenAIAccountHeaders, ProviderKeyEntry};
+use crate::server::util::mask_key;
+use chrono::{DateTime, Duration, Utc};
 use rand::Rng;
 use rand::distr::{Distribution, weighted::WeightedIndex};
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 
@@ -11,6 +13,79 @@ pub struct LoadBalancerState {
     provider_counter: AtomicUsize,
     per_provider_key_counter: Mutex<HashMap<String, usize>>,
     per_provider_swrr_state: Mutex<HashMap<String, HashMap<String, i64>>>,
+    /// (provider, 脱敏 key) -> 近期用量，供 usage_weighted 策略使用
+    per_key_usage: Mutex<HashMap<(String, String), KeyUsage>>,
+    /// (provider, 脱敏 key) -> 上游 Retry-After 指定的冷却截止时间，期间不再选中该密钥
+    key_cooldowns: Mutex<HashMap<(String, String), DateTime<Utc>>>,
+}
+
+/// 用量达到 rpm/tpm 上限的该比例即视为“接近速率上限”
+const RATE_LIMIT_HEADROOM: f64 = 0.8;
+/// 上游 Retry-After 冷却的上限，避免异常取值长期屏蔽密钥
+const MAX_KEY_COOLDOWN_SECS: u64 = 3600;
+
+/// 单个密钥的近期用量：最近 60 秒的请求时间与 token 数、最近 24 小时按小时聚合的花费
+#[derive(Debug, Default)]
+struct KeyUsage {
+    requests: VecDeque<DateTime<Utc>>,
+    tokens: VecDeque<(DateTime<Utc>, u32)>,
+    hourly_spend: VecDeque<(i64, f64)>,
+}
+
+/// 某个密钥在当前窗口内的用量快照
+#[derive(Debug, Clone, Copy, Default)]
+struct KeyUsageSnapshot {
+    requests: usize,
+    tokens: u64,
+    spent: f64,
+}
+
+impl KeyUsageSnapshot {
+    /// 请求数或 token 数达到上限 × ratio 时返回 true
+    fn reaches_rate_limit(&self, entry: &ProviderKeyEntry, ratio: f64) -> bool {
+        entry
+            .rpm_limit
+            .is_some_and(|limit| self.requests as f64 >= f64::from(limit) * ratio)
+            || entry
+                .tpm_limit
+                .is_some_and(|limit| self.tokens as f64 >= f64::from(limit) * ratio)
+    }
+}
+
+impl KeyUsage {
+    fn prune(&mut self, now: DateTime<Utc>) {
+        let minute_ago = now - Duration::seconds(60);
+        while self.requests.front().is_some_and(|t| *t <= minute_ago) {
+            self.requests.pop_front();
+        }
+        while self.tokens.front().is_some_and(|(t, _)| *t <= minute_ago) {
+            self.tokens.pop_front();
+        }
+        let oldest_hour = (now - Duration::hours(24)).timestamp() / 3600;
+        while self
+            .hourly_spend
+            .front()
+            .is_some_and(|(hour, _)| *hour <= oldest_hour)
+        {
+            self.hourly_spend.pop_front();
+        }
+    }
+
+    fn add_spend(&mut self, now: DateTime<Utc>, amount: f64) {
+        let hour = now.timestamp() / 3600;
+        match self.hourly_spend.back_mut() {
+            Some((h, total)) if *h == hour => *total += amount,
+            _ => self.hourly_spend.push_back((hour, amount)),
+        }
+    }
+
+    fn snapshot(&self) -> KeyUsageSnapshot {
+        KeyUsageSnapshot {
+            requests: self.requests.len(),
+            tokens: self.tokens.iter().map(|(_, n)| u64::from(*n)).sum(),
+            spent: self.hourly_spend.iter().map(|(_, v)| v).sum(),
+        }
+    }
 }
 
 impl LoadBalancerState {
@@ -83,6 +158,162 @@ impl LoadBalancerState {
         best_idx
     }
 
+    /// 供应商或其密钥变更后清除轮询状态，按新的密钥集合与权重重新开始
+    pub fn forget_provider(&self, provider_name: &str) {
+        self.per_provider_key_counter
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(provider_name);
+        self.per_provider_swrr_state
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(provider_name);
+    }
+
+    /// 记录一次已完成请求的 token 用量与花费（masked_key 与请求日志中的脱敏值一致）
+    pub fn record_key_usage(
+        &self,
+        provider_name: &str,
+        masked_key: &str,
+        total_tokens: Option<u32>,
+        amount: Option<f64>,
+    ) {
+        self.record_key_usage_at(provider_name, masked_key, total_tokens, amount, Utc::now());
+    }
+
+    fn record_key_usage_at(
+        &self,
+        provider_name: &str,
+        masked_key: &str,
+        total_tokens: Option<u32>,
+        amount: Option<f64>,
+        now: DateTime<Utc>,
+    ) {
+        let tokens = total_tokens.filter(|n| *n > 0);
+        let amount = amount.filter(|v| v.is_finite() && *v > 0.0);
+        if tokens.is_none() && amount.is_none() {
+            return;
+        }
+        let mut map = self.per_key_usage.lock().unwrap_or_else(|e| e.into_inner());
+        let usage = map
+            .entry((provider_name.to_string(), masked_key.to_string()))
+            .or_default();
+        usage.prune(now);
+        if let Some(n) = tokens {
+            usage.tokens.push_back((now, n));
+        }
+        if let Some(v) = amount {
+            usage.add_spend(now, v);
+        }
+    }
+
+    /// 上游对该密钥返回 429 + Retry-After：在窗口结束前跳过该密钥
+    pub fn cool_down_key(&self, provider_name: &str, key: &str, retry_after_secs: u64) {
+        self.cool_down_key_at(provider_name, key, retry_after_secs, Utc::now());
+    }
+
+    fn cool_down_key_at(
+        &self,
+        provider_name: &str,
+        key: &str,
+        retry_after_secs: u64,
+        now: DateTime<Utc>,
+    ) {
+        let secs = retry_after_secs.min(MAX_KEY_COOLDOWN_SECS);
+        if secs == 0 {
+            return;
+        }
+        let until = now + Duration::seconds(secs as i64);
+        let mut map = self.key_cooldowns.lock().unwrap_or_else(|e| e.into_inner());
+        map.retain(|_, until| *until > now);
+        let entry = map
+            .entry((provider_name.to_string(), mask_key(key)))
+            .or_insert(until);
+        *entry = (*entry).max(until);
+    }
+
+    fn cooling_down(&self, provider_name: &str, key: &str, now: DateTime<Utc>) -> bool {
+        self.key_cooldowns
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&(provider_name.to_string(), mask_key(key)))
+            .is_some_and(|until| *until > now)
+    }
+
+    fn record_key_request_at(&self, provider_name: &str, key: &str, now: DateTime<Utc>) {
+        let mut map = self.per_key_usage.lock().unwrap_or_else(|e| e.into_inner());
+        let usage = map
+            .entry((provider_name.to_string(), mask_key(key)))
+            .or_default();
+        usage.prune(now);
+        usage.requests.push_back(now);
+    }
+
+    fn usage_snapshots(
+        &self,
+        provider_name: &str,
+        keys: &[&ProviderKeyEntry],
+        now: DateTime<Utc>,
+    ) -> Vec<KeyUsageSnapshot> {
+        let mut map = self.per_key_usage.lock().unwrap_or_else(|e| e.into_inner());
+        keys.iter()
+            .map(
+                |entry| match map.get_mut(&(provider_name.to_string(), mask_key(&entry.value))) {
+                    Some(usage) => {
+                        usage.prune(now);
+                        usage.snapshot()
+                    }
+                    None => KeyUsageSnapshot::default(),
+                },
+            )
+            .collect()
+    }
+
+    /// 剩余额度比例 × 权重 最高者优先；
+    /// 优先排除额度耗尽或接近速率上限的密钥，全部不满足时逐级放宽
+    fn next_usage_weighted_index(
+        active_keys: &[&ProviderKeyEntry],
+        snapshots: &[KeyUsageSnapshot],
+    ) -> usize {
+        // (remaining_fraction, recent_requests, near_rate_limit)
+        let stats: Vec<(f64, usize, bool)> = active_keys
+            .iter()
+            .zip(snapshots)
+            .map(|(entry, usage)| {
+                let remaining = match entry.spend_cap {
+                    Some(cap) if cap > 0.0 => ((cap - usage.spent) / cap).max(0.0),
+                    _ => 1.0,
+                };
+                let near_limit = usage.reaches_rate_limit(entry, RATE_LIMIT_HEADROOM);
+                (remaining, usage.requests, near_limit)
+            })
+            .collect();
+
+        // 0: 有剩余额度且未接近速率上限；1: 有剩余额度；2: 全部
+        for tier in 0..3 {
+            let best = stats
+                .iter()
+                .enumerate()
+                .filter(|(_, (remaining, _, near_limit))| match tier {
+                    0 => *remaining > 0.0 && !near_limit,
+                    1 => *remaining > 0.0,
+                    _ => true,
+                })
+                .max_by(|(ia, a), (ib, b)| {
+                    let score_a = a.0 * f64::from(active_keys[*ia].weight.max(1));
+                    let score_b = b.0 * f64::from(active_keys[*ib].weight.max(1));
+                    score_a
+                        .total_cmp(&score_b)
+                        .then_with(|| b.1.cmp(&a.1))
+                        .then_with(|| ib.cmp(ia))
+                });
+            if let Some((idx, _)) = best {
+                return idx;
+            }
+        }
+        0
+    }
+
     pub fn select_provider_key(
         &self,
         provider_name: &str,
@@ -108,6 +339,21 @@ impl LoadBalancerState {
             return Err(BalanceError::NoApiKeysAvailable);
         }
 
+        // 跳过当前 60 秒窗口内已达到 rpm/tpm 上限、或仍在上游 Retry-After 冷却期内的密钥
+        let now = Utc::now();
+        let (active, snapshots): (Vec<&ProviderKeyEntry>, Vec<KeyUsageSnapshot>) = active
+            .iter()
+            .copied()
+            .zip(self.usage_snapshots(provider_name, &active, now))
+            .filter(|(entry, usage)| {
+                !usage.reaches_rate_limit(entry, 1.0)
+                    && !self.cooling_down(provider_name, &entry.value, now)
+            })
+            .unzip();
+        if active.is_empty() {
+            return Err(BalanceError::ApiKeysRateLimited);
+        }
+
         let idx = match strategy {
             KeyRotationStrategy::Sequential => self.next_key_index(provider_name, active.len()),
             KeyRotationStrategy::Random => rng.random_range(0..active.len()),
@@ -120,9 +366,14 @@ impl LoadBalancerState {
             KeyRotationStrategy::WeightedSequential => {
                 self.next_weighted_sequential_index(provider_name, &active)
             }
+            KeyRotationStrategy::UsageWeighted => {
+                Self::next_usage_weighted_index(&active, &snapshots)
+            }
         };
 
-        Ok(active[idx].value.clone())
+        let selected = active[idx].value.clone();
+        self.record_key_request_at(provider_name, &selected, now);
+        Ok(selected)
     }
 }
 
@@ -136,6 +387,8 @@ pub struct LoadBalancer {
 pub enum BalanceError {
     NoProvidersAvailable,
     NoApiKeysAvailable,
+    /// 所有可用密钥都已达到 rpm/tpm 上限
+    ApiKeysRateLimited,
 }
 
 impl std::fmt::Display for BalanceError {
@@ -143,6 +396,9 @@ impl std::fmt::Display for BalanceError {
         match self {
             BalanceError::NoProvidersAvailable => write!(f, "No providers available"),
             BalanceError::NoApiKeysAvailable => write!(f, "No API keys available"),
+            BalanceError::ApiKeysRateLimited => {
+                write!(f, "All API keys have reached their rate limits")
+            }
         }
     }
 }
@@ -152,6 +408,8 @@ impl std::error::Error for BalanceError {}
 pub struct SelectedProvider {
     pub provider: Provider,
     pub api_key: String,
+    /// 选中密钥配置的 OpenAI 组织/项目头
+    pub openai_account: OpenAIAccountHeaders,
 }
 
 impl LoadBalancer {
@@ -241,6 +499,11 @@ mod tests {
                     value: value.clone(),
                     active: true,
                     weight: 1,
+                    spend_cap: None,
+                    rpm_limit: None,
+                    tpm_limit: None,
+                    openai_account: Default::default(),
+                    labels: Default::default(),
                 })
                 .collect::<Vec<_>>();
             let api_key = lb
@@ -269,16 +532,31 @@ mod tests {
                 value: "a".into(),
                 active: true,
                 weight: 1,
+                spend_cap: None,
+                rpm_limit: None,
+                tpm_limit: None,
+                openai_account: Default::default(),
+                labels: Default::default(),
             },
             ProviderKeyEntry {
                 value: "b".into(),
                 active: true,
                 weight: 3,
+                spend_cap: None,
+                rpm_limit: None,
+                tpm_limit: None,
+                openai_account: Default::default(),
+                labels: Default::default(),
             },
             ProviderKeyEntry {
                 value: "c".into(),
                 active: false,
                 weight: 100,
+                spend_cap: None,
+                rpm_limit: None,
+                tpm_limit: None,
+                openai_account: Default::default(),
+                labels: Default::default(),
             },
         ];
 
@@ -287,6 +565,11 @@ mod tests {
             value: "x".into(),
             active: false,
             weight: 1,
+            spend_cap: None,
+            rpm_limit: None,
+            tpm_limit: None,
+            openai_account: Default::default(),
+            labels: Default::default(),
         }];
         assert!(matches!(
             state.select_provider_key("p0", KeyRotationStrategy::Random, &disabled_only),
@@ -325,11 +608,21 @@ mod tests {
                 value: "a".into(),
                 active: true,
                 weight: 1,
+                spend_cap: None,
+                rpm_limit: None,
+                tpm_limit: None,
+                openai_account: Default::default(),
+                labels: Default::default(),
             },
             ProviderKeyEntry {
                 value: "b".into(),
                 active: true,
                 weight: 2,
+                spend_cap: None,
+                rpm_limit: None,
+                tpm_limit: None,
+                openai_account: Default::default(),
+                labels: Default::default(),
             },
         ];
         let mut out = Vec::new();
@@ -342,4 +635,136 @@ mod tests {
         }
         assert_eq!(out, vec!["b", "a", "b", "b", "a", "b"]);
     }
+
+    #[test]
+    fn usage_weighted_prefers_remaining_quota_and_avoids_rate_limited_keys() {
+        let state = LoadBalancerState::default();
+        let key = |value: &str, spend_cap: Option<f64>, rpm_limit: Option<u32>| ProviderKeyEntry {
+            value: value.into(),
+            active: true,
+            weight: 1,
+            spend_cap,
+            rpm_limit,
+            tpm_limit: None,
+            openai_account: Default::default(),
+            labels: Default::default(),
+        };
+        let keys = vec![
+            key("key-aaaa-0001", Some(10.0), None),
+            key("key-bbbb-0002", Some(10.0), None),
+        ];
+        state.record_key_usage("p0", &mask_key("key-aaaa-0001"), None, Some(6.0));
+        state.record_key_usage("p0", &mask_key("key-bbbb-0002"), None, Some(2.0));
+        let pick = |keys: &[ProviderKeyEntry]| {
+            state
+                .select_provider_key("p0", KeyRotationStrategy::UsageWeighted, keys)
+                .unwrap()
+        };
+        assert_eq!(pick(&keys), "key-bbbb-0002");
+
+        // 额度耗尽的密钥不再被选中
+        state.record_key_usage("p0", &mask_key("key-bbbb-0002"), None, Some(8.0));
+        assert_eq!(pick(&keys), "key-aaaa-0001");
+
+        // 接近每分钟请求上限时切换到其他密钥（即使剩余额度更少）
+        let keys = vec![
+            key("key-cccc-0003", None, Some(2)),
+            key("key-dddd-0004", Some(10.0), None),
+        ];
+        state.record_key_usage("p0", &mask_key("key-dddd-0004"), None, Some(9.0));
+        assert_eq!(pick(&keys), "key-cccc-0003");
+        assert_eq!(pick(&keys), "key-cccc-0003");
+        assert_eq!(pick(&keys), "key-dddd-0004");
+    }
+
+    #[test]
+    fn key_usage_expires_after_window() {
+        let state = LoadBalancerState::default();
+        let now = Utc::now();
+        let masked = mask_key("key-aaaa-0001");
+        state.record_key_usage_at("p0", &masked, None, Some(5.0), now - Duration::hours(25));
+        state.record_key_usage_at(
+            "p0",
+            &masked,
+            Some(300),
+            Some(1.5),
+            now - Duration::minutes(5),
+        );
+        state.record_key_usage_at("p0", &masked, Some(40), None, now - Duration::seconds(10));
+        state.record_key_request_at("p0", "key-aaaa-0001", now - Duration::seconds(90));
+        state.record_key_request_at("p0", "key-aaaa-0001", now);
+
+        let mut map = state.per_key_usage.lock().unwrap();
+        let usage = map.get_mut(&("p0".to_string(), masked)).unwrap();
+        usage.prune(now);
+        let snapshot = usage.snapshot();
+        assert!((snapshot.spent - 1.5).abs() < 1e-9);
+        assert_eq!(snapshot.requests, 1);
+        assert_eq!(snapshot.tokens, 40);
+    }
+
+    #[test]
+    fn keys_with_exhausted_rate_window_are_skipped() {
+        let state = LoadBalancerState::default();
+        let key = |value: &str, rpm_limit: Option<u32>, tpm_limit: Option<u32>| ProviderKeyEntry {
+            value: value.into(),
+            active: true,
+            weight: 1,
+            spend_cap: None,
+            rpm_limit,
+            tpm_limit,
+            openai_account: Default::default(),
+            labels: Default::default(),
+        };
+        let keys = vec![
+            key("key-aaaa-0001", Some(1), None),
+            key("key-bbbb-0002", None, Some(1000)),
+        ];
+        let pick = |keys: &[ProviderKeyEntry]| {
+            state.select_provider_key("p0", KeyRotationStrategy::Sequential, keys)
+        };
+        assert_eq!(pick(&keys).unwrap(), "key-aaaa-0001");
+        // a 已用完每分钟 1 次请求，顺序轮换也会跳过它
+        assert_eq!(pick(&keys).unwrap(), "key-bbbb-0002");
+        assert_eq!(pick(&keys).unwrap(), "key-bbbb-0002");
+
+        state.record_key_usage("p0", &mask_key("key-bbbb-0002"), Some(1200), None);
+        assert!(matches!(pick(&keys), Err(BalanceError::ApiKeysRateLimited)));
+    }
+
+    #[test]
+    fn keys_cooling_down_after_upstream_retry_after_are_skipped() {
+        let state = LoadBalancerState::default();
+        let keys: Vec<ProviderKeyEntry> = ["key-aaaa-0001", "key-bbbb-0002"]
+            .into_iter()
+            .map(|value| ProviderKeyEntry {
+                value: value.into(),
+                active: true,
+                weight: 1,
+                spend_cap: None,
+                rpm_limit: None,
+                tpm_limit: None,
+                openai_account: Default::default(),
+                labels: Default::default(),
+            })
+            .collect();
+        let pick = || state.select_provider_key("p0", KeyRotationStrategy::Sequential, &keys);
+
+        state.cool_down_key("p0", "key-aaaa-0001", 30);
+        for _ in 0..3 {
+            assert_eq!(pick().unwrap(), "key-bbbb-0002");
+        }
+        // 其他供应商的同名密钥不受影响
+        assert!(!state.cooling_down("p1", "key-aaaa-0001", Utc::now()));
+
+        state.cool_down_key("p0", "key-bbbb-0002", 30);
+        assert!(matches!(pick(), Err(BalanceError::ApiKeysRateLimited)));
+
+        // 冷却窗口结束后恢复可选
+        let later = Utc::now() + Duration::seconds(31);
+        assert!(!state.cooling_down("p0", "key-aaaa-0001", later));
+        state.cool_down_key_at("p0", "key-aaaa-0001", 86_400, Utc::now());
+        let capped = Utc::now() + Duration::seconds(MAX_KEY_COOLDOWN_SECS as i64 + 1);
+        assert!(!state.cooling_down("p0", "key-aaaa-0001", capped));
+    }
 }
@@ -1,5 +1,7 @@
+pub mod key_labels;
 pub mod key_rotation;
 pub mod load_balancer;
 
-pub use key_rotation::{KeyRotationStrategy, ProviderKeyEntry};
+pub use key_labels::{KeyLabelRule, KeyRoute};
+pub use key_rotation::{KeyRotationStrategy, OpenAIAccountHeaders, ProviderKeyEntry};
 pub use load_balancer::{LoadBalancer, LoadBalancerState, SelectedProvider};
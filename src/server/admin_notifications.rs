@@ -0,0 +1,124 @@
+use chrono::Utc;
+
+use crate::logging::types::AdminNotificationRecord;
+use crate::server::storage_traits::RequestLogStore;
+
+/// 首次启动自动生成了管理员登录密钥
+pub const KIND_ADMIN_KEY_GENERATED: &str = "admin_key_generated";
+/// 供应商进行中请求持续饱和
+pub const KIND_PROVIDER_UNHEALTHY: &str = "provider_unhealthy";
+/// 供应商月度花费越过预算告警阈值
+pub const KIND_PROVIDER_BUDGET: &str = "provider_budget";
+/// 双写迁移两端数据不一致，切换主后端前需要处理
+pub const KIND_MIGRATION_NEEDED: &str = "migration_needed";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Warning,
+    Critical,
+}
+
+impl Severity {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Warning => "warning",
+            Self::Critical => "critical",
+        }
+    }
+}
+
+/// 待写入收件箱的系统事件
+pub struct NewNotification {
+    pub kind: &'static str,
+    pub severity: Severity,
+    pub title: String,
+    pub message: String,
+    /// 去重键：相同 kind + reference 的通知在被标记已读前只保留一条
+    pub reference: Option<String>,
+}
+
+/// 写入管理员通知；失败仅记录日志，不影响触发事件的主流程
+pub async fn notify(
+    log_store: &(dyn RequestLogStore + Send + Sync),
+    notification: NewNotification,
+) {
+    let record = AdminNotificationRecord {
+        id: 0,
+        kind: notification.kind.to_string(),
+        severity: notification.severity.as_str().to_string(),
+        title: notification.title,
+        message: notification.message,
+        reference: notification.reference,
+        created_at: Utc::now(),
+        read_at: None,
+    };
+    if let Err(e) = log_store.insert_admin_notification(record).await {
+        tracing::warn!(
+            kind = notification.kind,
+            "Failed to write admin notification: {}",
+            e
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logging::DatabaseLogger;
+
+    fn budget_alert(reference: &str) -> NewNotification {
+        NewNotification {
+            kind: KIND_PROVIDER_BUDGET,
+            severity: Severity::Warning,
+            title: "budget".into(),
+            message: "80% used".into(),
+            reference: Some(reference.into()),
+        }
+    }
+
+    #[tokio::test]
+    async fn unread_duplicates_are_collapsed_until_read() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = DatabaseLogger::new(dir.path().join("n.db").to_str().unwrap())
+            .await
+            .unwrap();
+
+        notify(&db, budget_alert("openai:2026-10:80")).await;
+        notify(&db, budget_alert("openai:2026-10:80")).await;
+        notify(&db, budget_alert("openai:2026-10:100")).await;
+        assert_eq!(
+            RequestLogStore::count_unread_admin_notifications(&db)
+                .await
+                .unwrap(),
+            2
+        );
+
+        let items = RequestLogStore::list_admin_notifications(&db, false, 10)
+            .await
+            .unwrap();
+        let newest = items[0].id;
+        let marked =
+            RequestLogStore::mark_admin_notifications_read(&db, Some(vec![newest]), Utc::now())
+                .await
+                .unwrap();
+        assert_eq!(marked, 1);
+        let unread = RequestLogStore::list_admin_notifications(&db, true, 10)
+            .await
+            .unwrap();
+        assert_eq!(unread.len(), 1);
+        assert_eq!(unread[0].reference.as_deref(), Some("openai:2026-10:80"));
+
+        // 已读后同一事件再次发生会重新提醒
+        notify(&db, budget_alert("openai:2026-10:100")).await;
+        let marked = RequestLogStore::mark_admin_notifications_read(&db, None, Utc::now())
+            .await
+            .unwrap();
+        assert_eq!(marked, 2);
+        assert_eq!(
+            RequestLogStore::count_unread_admin_notifications(&db)
+                .await
+                .unwrap(),
+            0
+        );
+    }
+}
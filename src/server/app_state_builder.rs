@@ -0,0 +1,555 @@
+//! AppState 的构建：按配置打开各子系统存储（或由调用方注入），启动前逐项体检
+//! （连通性探测 + schema 版本），任一子系统异常即失败并汇总报告，再装配运行期组件与后台任务。
+
+use std::fmt;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+
+use super::{
+    AppState, admin_notifications, cluster, db_replication, degraded, dual_write, egress,
+    fault_injection, idempotency, in_flight, lenient_request, login, maintenance,
+    model_concurrency, payload_limits, provider_budget, request_quota, request_signing,
+    runtime_settings, scheduler, semantic_cache, tasks, token_usage_buffer, usage_webhooks,
+};
+use crate::admin::{PgTokenStore, TokenStore};
+use crate::balance::BalanceStore;
+use crate::config::Settings;
+use crate::config::settings::{MigrationBackend, MigrationConfig};
+use crate::error::{GatewayError, Result as AppResult};
+use crate::logging::DatabaseLogger;
+use crate::logging::postgres_store::PgLogStore;
+use crate::password_reset_tokens::PasswordResetTokenStore;
+use crate::refresh_tokens::RefreshTokenStore;
+use crate::routing::LoadBalancerState;
+use crate::server::storage_traits::{
+    FavoriteKind, FavoritesStore, LoginStore, ModelCache, OrganizationStore, ProviderStore,
+    RequestLogStore, SettingsStore,
+};
+use crate::subscription::SubscriptionStore;
+use crate::users::UserStore;
+
+/// 当前二进制对应的数据库 schema 版本；表结构出现不兼容变更时递增
+pub const SCHEMA_VERSION: u32 = 1;
+const SCHEMA_VERSION_KEY: &str = "schema_version";
+/// 单个子系统探测的超时，避免数据库不可达时启动卡住
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 各子系统的存储句柄；可由 [`Stores::open`] 按配置打开，也可在测试或自定义后端中直接构造
+#[derive(Clone)]
+pub struct Stores {
+    pub log_store: Arc<dyn RequestLogStore + Send + Sync>,
+    pub model_cache: Arc<dyn ModelCache + Send + Sync>,
+    pub providers: Arc<dyn ProviderStore + Send + Sync>,
+    pub token_store: Arc<dyn TokenStore + Send + Sync>,
+    pub favorites: Arc<dyn FavoritesStore + Send + Sync>,
+    pub organizations: Arc<dyn OrganizationStore + Send + Sync>,
+    pub login: Arc<dyn LoginStore + Send + Sync>,
+    pub users: Arc<dyn UserStore + Send + Sync>,
+    pub refresh_tokens: Arc<dyn RefreshTokenStore + Send + Sync>,
+    pub password_reset_tokens: Arc<dyn PasswordResetTokenStore + Send + Sync>,
+    pub balance: Arc<dyn BalanceStore + Send + Sync>,
+    pub subscriptions: Arc<dyn SubscriptionStore + Send + Sync>,
+    pub settings: Arc<dyn SettingsStore + Send + Sync>,
+}
+
+impl Stores {
+    /// 除令牌外所有子系统共用同一个后端（SQLite 与 PostgreSQL 均如此）
+    pub fn uniform<S>(store: Arc<S>, token_store: Arc<dyn TokenStore + Send + Sync>) -> Self
+    where
+        S: RequestLogStore
+            + ModelCache
+            + ProviderStore
+            + FavoritesStore
+            + OrganizationStore
+            + LoginStore
+            + UserStore
+            + RefreshTokenStore
+            + PasswordResetTokenStore
+            + BalanceStore
+            + SubscriptionStore
+            + SettingsStore
+            + Send
+            + Sync
+            + 'static,
+    {
+        Self {
+            log_store: store.clone(),
+            model_cache: store.clone(),
+            providers: store.clone(),
+            token_store,
+            favorites: store.clone(),
+            organizations: store.clone(),
+            login: store.clone(),
+            users: store.clone(),
+            refresh_tokens: store.clone(),
+            password_reset_tokens: store.clone(),
+            balance: store.clone(),
+            subscriptions: store.clone(),
+            settings: store,
+        }
+    }
+
+    /// 按配置打开存储：双写迁移、纯 PostgreSQL 或本地 SQLite
+    pub async fn open(config: &Settings) -> AppResult<Self> {
+        if let (Some(pg_url), Some(migration_cfg)) =
+            (&config.logging.pg_url, &config.logging.migration)
+        {
+            return open_dual_write_stores(config, pg_url, migration_cfg).await;
+        }
+        if let Some(pg_url) = &config.logging.pg_url {
+            // Strict Postgres-only mode (no SQLite fallback)
+            let pool_size = config.logging.pg_pool_size.unwrap_or(4);
+            let pglog = PgLogStore::connect(pg_url, &config.logging.pg_schema, pool_size).await?;
+            tracing::info!("Using PostgreSQL for logs and cache");
+            let ts = PgTokenStore::connect(pg_url, config.logging.pg_schema.as_deref()).await?;
+            return Ok(Self::uniform(Arc::new(pglog), Arc::new(ts)));
+        }
+        let db_logger = Arc::new(DatabaseLogger::new(&config.logging.database_path).await?);
+        if let Some(replication) = &config.logging.replication {
+            db_replication::spawn_replication_task(
+                &tasks::task_registry(),
+                db_logger.clone(),
+                &config.logging.database_path,
+                replication,
+            )?;
+        }
+        Ok(Self::uniform(db_logger.clone(), db_logger))
+    }
+
+    /// 逐个子系统执行只读探测；设置存储额外校验 schema 版本（首次启动时写入当前版本）
+    pub async fn check(&self) -> StoreHealthReport {
+        let now = Utc::now();
+        let checks = vec![
+            probe(
+                "request_logs",
+                self.log_store.get_recent_logs_with_cursor(1, None),
+            )
+            .await,
+            probe("model_cache", self.model_cache.get_cached_models(Some(""))).await,
+            probe("providers", self.providers.provider_exists("")).await,
+            probe("tokens", self.token_store.get_token_by_id("")).await,
+            probe(
+                "favorites",
+                self.favorites.is_favorite(FavoriteKind::Provider, ""),
+            )
+            .await,
+            probe("organizations", self.organizations.list_organizations()).await,
+            probe("login", self.login.get_admin_key("")).await,
+            probe("users", self.users.any_users()).await,
+            probe(
+                "refresh_tokens",
+                self.refresh_tokens.get_refresh_token_by_hash(""),
+            )
+            .await,
+            probe(
+                "password_reset_tokens",
+                self.password_reset_tokens
+                    .has_recent_active_password_reset_token("", now, now),
+            )
+            .await,
+            probe("balance", self.balance.list_transactions("", 1, 0)).await,
+            probe("subscriptions", self.subscriptions.get_published_plans()).await,
+            probe("settings", check_schema_version(self.settings.as_ref())).await,
+        ];
+        StoreHealthReport { checks }
+    }
+}
+
+/// 双写迁移模式：同时打开 SQLite 与 PostgreSQL，可迁移的存储走双写，
+/// 其余存储使用启动时的主后端
+async fn open_dual_write_stores(
+    config: &Settings,
+    pg_url: &str,
+    migration_cfg: &MigrationConfig,
+) -> AppResult<Stores> {
+    let pool_size = config.logging.pg_pool_size.unwrap_or(4);
+    let pg = Arc::new(PgLogStore::connect(pg_url, &config.logging.pg_schema, pool_size).await?);
+    let pg_tokens: Arc<dyn TokenStore + Send + Sync> =
+        Arc::new(PgTokenStore::connect(pg_url, config.logging.pg_schema.as_deref()).await?);
+    let sqlite = Arc::new(DatabaseLogger::new(&config.logging.database_path).await?);
+    tracing::info!(
+        primary = ?migration_cfg.primary,
+        "Dual-write migration mode enabled (SQLite + PostgreSQL)"
+    );
+
+    let state = Arc::new(dual_write::MigrationState::new(migration_cfg.primary));
+    let migration = Arc::new(dual_write::Migration::new(
+        state.clone(),
+        dual_write::BackendHandles {
+            tokens: sqlite.clone(),
+            providers: sqlite.clone(),
+            organizations: sqlite.clone(),
+            users: sqlite.clone(),
+            login: sqlite.clone(),
+            logs: sqlite.clone(),
+        },
+        dual_write::BackendHandles {
+            tokens: pg_tokens.clone(),
+            providers: pg.clone(),
+            organizations: pg.clone(),
+            users: pg.clone(),
+            login: pg.clone(),
+            logs: pg.clone(),
+        },
+        config.logging.key_log_strategy.clone(),
+    ));
+    dual_write::install(migration.clone());
+
+    let mut stores = match migration_cfg.primary {
+        MigrationBackend::Sqlite => Stores::uniform(sqlite.clone(), sqlite.clone()),
+        MigrationBackend::Postgres => Stores::uniform(pg.clone(), pg_tokens.clone()),
+    };
+    stores.providers = Arc::new(dual_write::Dual::<dyn ProviderStore + Send + Sync>::new(
+        state.clone(),
+        sqlite.clone(),
+        pg.clone(),
+    ));
+    stores.token_store = Arc::new(dual_write::Dual::<dyn TokenStore + Send + Sync>::new(
+        state.clone(),
+        sqlite.clone(),
+        pg_tokens,
+    ));
+    stores.favorites = Arc::new(dual_write::Dual::<dyn FavoritesStore + Send + Sync>::new(
+        state.clone(),
+        sqlite.clone(),
+        pg.clone(),
+    ));
+    stores.organizations = Arc::new(
+        dual_write::Dual::<dyn OrganizationStore + Send + Sync>::new(
+            state.clone(),
+            sqlite.clone(),
+            pg.clone(),
+        ),
+    );
+    stores.settings = Arc::new(dual_write::Dual::<dyn SettingsStore + Send + Sync>::new(
+        state, sqlite, pg,
+    ));
+    dual_write::spawn_check_task(
+        &tasks::task_registry(),
+        migration,
+        stores.log_store.clone(),
+        migration_cfg.check_interval_secs,
+    );
+    Ok(stores)
+}
+
+async fn probe<T, E: fmt::Display>(
+    subsystem: &'static str,
+    fut: impl Future<Output = Result<T, E>>,
+) -> StoreCheck {
+    let error = match tokio::time::timeout(PROBE_TIMEOUT, fut).await {
+        Ok(Ok(_)) => None,
+        Ok(Err(e)) => Some(e.to_string()),
+        Err(_) => Some(format!("timed out after {}s", PROBE_TIMEOUT.as_secs())),
+    };
+    StoreCheck { subsystem, error }
+}
+
+/// 数据库由更新版本的网关写入过时拒绝启动，避免旧二进制误写新表结构；
+/// 旧版本或首次启动时记录当前版本（表结构迁移在打开存储时已幂等执行）
+async fn check_schema_version(store: &(dyn SettingsStore + Send + Sync)) -> AppResult<()> {
+    let stored = match store.get_setting(SCHEMA_VERSION_KEY).await? {
+        Some(raw) => raw
+            .trim()
+            .parse::<u32>()
+            .map_err(|_| GatewayError::Config(format!("unrecognized schema version '{}'", raw)))?,
+        None => 0,
+    };
+    if stored > SCHEMA_VERSION {
+        return Err(GatewayError::Config(format!(
+            "database schema version {} is newer than this gateway supports ({}); upgrade the gateway",
+            stored, SCHEMA_VERSION
+        )));
+    }
+    if stored < SCHEMA_VERSION {
+        store
+            .set_setting(SCHEMA_VERSION_KEY, &SCHEMA_VERSION.to_string())
+            .await?;
+    }
+    Ok(())
+}
+
+/// 单个子系统的体检结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoreCheck {
+    pub subsystem: &'static str,
+    pub error: Option<String>,
+}
+
+/// 全部子系统的体检结果；Display 仅列出异常项
+#[derive(Debug, Clone, Default)]
+pub struct StoreHealthReport {
+    pub checks: Vec<StoreCheck>,
+}
+
+impl StoreHealthReport {
+    pub fn failures(&self) -> impl Iterator<Item = &StoreCheck> {
+        self.checks.iter().filter(|c| c.error.is_some())
+    }
+
+    pub fn is_healthy(&self) -> bool {
+        self.failures().next().is_none()
+    }
+}
+
+impl fmt::Display for StoreHealthReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "store health check failed")?;
+        for check in self.failures() {
+            write!(
+                f,
+                "; {}: {}",
+                check.subsystem,
+                check.error.as_deref().unwrap_or_default()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// AppState 构建器：
+/// - 未注入存储时按配置打开，注入时直接使用（测试替身、自定义后端）
+/// - 体检不通过立即返回，错误信息列出所有异常子系统
+/// - 通过后确保管理员密钥、加载运行期设置并启动后台任务
+pub struct AppStateBuilder {
+    config: Settings,
+    stores: Option<Stores>,
+}
+
+impl AppStateBuilder {
+    pub fn new(config: Settings) -> Self {
+        Self {
+            config,
+            stores: None,
+        }
+    }
+
+    /// 使用已打开的存储，不再按配置打开
+    #[allow(dead_code)]
+    pub fn stores(mut self, stores: Stores) -> Self {
+        self.stores = Some(stores);
+        self
+    }
+
+    pub async fn build(self) -> AppResult<Arc<AppState>> {
+        let config = self.config;
+        let stores = match self.stores {
+            Some(stores) => stores,
+            None => Stores::open(&config).await?,
+        };
+        let report = stores.check().await;
+        if !report.is_healthy() {
+            return Err(GatewayError::Config(report.to_string()));
+        }
+
+        if std::env::var("GATEWAY_BOOTSTRAP_CODE")
+            .ok()
+            .filter(|v| !v.is_empty())
+            .is_none()
+        {
+            tracing::warn!("GATEWAY_BOOTSTRAP_CODE not set; /auth/register will be disabled");
+        }
+
+        if let Some((fingerprint, path)) =
+            super::ensure_initial_admin_key(stores.login.clone()).await?
+        {
+            tracing::warn!(
+                "新管理员密钥已生成；指纹={}，私钥已写入 {}，请立即妥善备份并加载至 TUI 配置。",
+                fingerprint,
+                path.display()
+            );
+            tracing::warn!(
+                "该密钥仅首次生成，后续启动会复用现有密钥，如需轮换请通过 TUI 管理途径重置。"
+            );
+            admin_notifications::notify(
+                stores.log_store.as_ref(),
+                admin_notifications::NewNotification {
+                    kind: admin_notifications::KIND_ADMIN_KEY_GENERATED,
+                    severity: admin_notifications::Severity::Warning,
+                    title: "New admin key generated".into(),
+                    message: format!(
+                        "An admin login key was generated on boot (fingerprint {}); back up the private key at {}.",
+                        fingerprint,
+                        path.display()
+                    ),
+                    reference: Some(fingerprint),
+                },
+            )
+            .await;
+        }
+
+        let degraded_mode = Arc::new(degraded::DegradedMode::new(
+            config.server.degraded_mode.clone(),
+        ));
+        degraded::install(degraded_mode.clone());
+        let token_store: Arc<dyn TokenStore + Send + Sync> = Arc::new(
+            degraded::SnapshotTokenStore::new(stores.token_store.clone(), degraded_mode.clone()),
+        );
+
+        let task_registry = tasks::task_registry();
+        let usage_buffer = token_usage_buffer::install(
+            token_usage_buffer::flush_interval_secs(&config.server),
+            &task_registry,
+            token_store,
+            stores.log_store.clone(),
+            stores.settings.clone(),
+        )
+        .await?;
+        let settings_store = stores.settings.clone();
+        let mut app_state = assemble(
+            config,
+            Stores {
+                token_store: usage_buffer.token_store,
+                ..stores
+            },
+            task_registry.clone(),
+        );
+        app_state.usage_sections = usage_buffer.sections;
+        let app_state = Arc::new(app_state);
+        app_state.runtime_settings.load().await?;
+        degraded::spawn_replay_task(
+            &task_registry,
+            degraded_mode,
+            app_state.log_store.clone(),
+            settings_store.clone(),
+        );
+        egress::spawn_flush_task(
+            &task_registry,
+            app_state.egress_meter.clone(),
+            app_state.log_store.clone(),
+        );
+        provider_budget::spawn_sync_task(
+            &task_registry,
+            app_state.provider_spend.clone(),
+            app_state.log_store.clone(),
+        );
+        request_quota::spawn_sync_task(
+            &task_registry,
+            app_state.request_quota.clone(),
+            app_state.log_store.clone(),
+        );
+        scheduler::start_job_scheduler(app_state.clone(), settings_store).await?;
+        in_flight::spawn_alert_task(app_state.clone());
+        maintenance::spawn_scheduler_task(app_state.clone());
+        crate::tls_pinning::sync(&app_state).await?;
+        crate::tls_pinning::spawn_sync_task(app_state.clone());
+        Ok(app_state)
+    }
+
+    /// 测试用：直接以注入的存储组装 AppState，不体检、不生成管理员密钥、
+    /// 不加载运行期设置，也不启动后台任务
+    #[cfg(test)]
+    pub fn build_detached(self) -> Arc<AppState> {
+        let stores = self
+            .stores
+            .expect("build_detached requires injected stores");
+        Arc::new(assemble(
+            self.config,
+            stores,
+            Arc::new(tasks::TaskRegistry::default()),
+        ))
+    }
+}
+
+/// 由配置与存储组装 AppState；计数器、缓存等进程内组件取初始值，后台任务由调用方启动
+fn assemble(config: Settings, stores: Stores, task_registry: Arc<tasks::TaskRegistry>) -> AppState {
+    let login_manager = login::LoginManager::new(stores.login.clone()).with_web_session_timeouts(
+        config.server.session_absolute_timeout_secs,
+        config.server.session_idle_timeout_secs,
+    );
+    let cluster = Arc::new(cluster::ClusterPeers::from_config(&config.server));
+    AppState {
+        config,
+        load_balancer_state: Arc::new(LoadBalancerState::default()),
+        log_store: stores.log_store,
+        model_cache: stores.model_cache,
+        providers: stores.providers,
+        token_store: stores.token_store,
+        favorites_store: stores.favorites,
+        organizations: stores.organizations,
+        login_manager: Arc::new(login_manager),
+        user_store: stores.users,
+        refresh_token_store: stores.refresh_tokens,
+        password_reset_token_store: stores.password_reset_tokens,
+        balance_store: stores.balance,
+        subscription_store: stores.subscriptions,
+        runtime_settings: Arc::new(runtime_settings::RuntimeSettingsManager::new(
+            stores.settings,
+        )),
+        task_registry,
+        fault_injector: Arc::new(fault_injection::FaultInjector::default()),
+        idempotency_in_flight: Arc::new(idempotency::InFlightKeys::default()),
+        usage_webhooks: Arc::new(usage_webhooks::UsageWebhookQueue::default()),
+        signature_nonces: Arc::new(request_signing::NonceCache::default()),
+        egress_meter: Arc::new(egress::EgressMeter::default()),
+        provider_spend: Arc::new(provider_budget::ProviderSpendTracker::default()),
+        cluster,
+        semantic_cache: Arc::new(semantic_cache::SemanticCache::default()),
+        model_concurrency: Arc::new(model_concurrency::ModelConcurrency::default()),
+        in_flight: Arc::new(in_flight::InFlightTracker::default()),
+        request_quota: Arc::new(request_quota::RequestQuotaCounter::default()),
+        payload_sizes: Arc::new(payload_limits::PayloadSizeStats::default()),
+        maintenance: Arc::new(maintenance::MaintenanceSchedule::default()),
+        request_deviations: Arc::new(lenient_request::RequestDeviationStats::default()),
+        usage_sections: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::settings::{BalanceStrategy, LoadBalancing, LoggingConfig, ServerConfig};
+
+    async fn sqlite_stores(dir: &tempfile::TempDir) -> (Arc<DatabaseLogger>, Stores) {
+        let path = dir.path().join("gateway.db");
+        let logger = Arc::new(DatabaseLogger::new(path.to_str().unwrap()).await.unwrap());
+        (logger.clone(), Stores::uniform(logger.clone(), logger))
+    }
+
+    #[tokio::test]
+    async fn healthy_stores_record_schema_version() {
+        let dir = tempfile::tempdir().unwrap();
+        let (logger, stores) = sqlite_stores(&dir).await;
+        let report = stores.check().await;
+        assert!(report.is_healthy(), "{}", report);
+        assert_eq!(report.checks.len(), 13);
+        assert_eq!(
+            logger.get_setting(SCHEMA_VERSION_KEY).await.unwrap(),
+            Some(SCHEMA_VERSION.to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn build_fails_fast_naming_broken_subsystems() {
+        let dir = tempfile::tempdir().unwrap();
+        let (logger, stores) = sqlite_stores(&dir).await;
+        logger
+            .set_setting(SCHEMA_VERSION_KEY, &(SCHEMA_VERSION + 1).to_string())
+            .await
+            .unwrap();
+        let conn = rusqlite::Connection::open(dir.path().join("gateway.db")).unwrap();
+        conn.execute_batch("DROP TABLE organizations").unwrap();
+
+        let settings = Settings {
+            load_balancing: LoadBalancing {
+                strategy: BalanceStrategy::FirstAvailable,
+            },
+            server: ServerConfig::default(),
+            logging: LoggingConfig::default(),
+        };
+        let err = AppStateBuilder::new(settings)
+            .stores(stores)
+            .build()
+            .await
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(err.contains("organizations: "), "{}", err);
+        assert!(err.contains("settings: "), "{}", err);
+        assert!(err.contains("newer than this gateway supports"), "{}", err);
+        assert!(!err.contains("; tokens: "), "{}", err);
+    }
+}
@@ -0,0 +1,338 @@
+use axum::{
+    body::Body,
+    http::header,
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use serde_json::{Value, json};
+
+use crate::admin::client_token_id_for_token;
+use crate::error::GatewayError;
+use crate::logging::RequestLog;
+use crate::logging::types::{
+    LatencyBreakdown, PromptProfile, REQ_TYPE_AUDIO_SPEECH, RequestLogDetailRecord,
+    SpeechPriceRecord,
+};
+use crate::providers::openai::OpenAIProvider;
+use crate::routing::SelectedProvider;
+use crate::server::AppState;
+use crate::server::chat_plan::{DecisionTrace, check_token_limits};
+use crate::server::pricing::missing_price_allowed_for_chat;
+use crate::server::provider_dispatch::select_provider_for_model;
+use crate::server::usage_webhooks::{self, UsageEvent};
+use crate::server::util::mask_key;
+
+pub const AUDIO_SPEECH_PATH: &str = "/v1/audio/speech";
+/// 与 OpenAI 一致的单次输入上限（字符数）
+const MAX_INPUT_CHARS: usize = 4096;
+
+/// 校验后的语音合成请求；`body` 为客户端原始 JSON，转发时仅替换 model
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpeechRequest {
+    pub model: String,
+    pub input_chars: usize,
+    body: Value,
+}
+
+impl SpeechRequest {
+    pub fn parse(body: Value) -> Result<Self, GatewayError> {
+        let text = |key: &str| {
+            body.get(key)
+                .and_then(Value::as_str)
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+        };
+        let model = text("model")
+            .ok_or_else(|| GatewayError::Config("model is required".into()))?
+            .to_string();
+        text("voice").ok_or_else(|| GatewayError::Config("voice is required".into()))?;
+        let input_chars = body
+            .get("input")
+            .and_then(Value::as_str)
+            .map(|s| s.chars().count())
+            .filter(|n| *n > 0)
+            .ok_or_else(|| GatewayError::Config("input is required".into()))?;
+        if input_chars > MAX_INPUT_CHARS {
+            return Err(GatewayError::Config(format!(
+                "input must be at most {} characters",
+                MAX_INPUT_CHARS
+            )));
+        }
+        Ok(Self {
+            model,
+            input_chars,
+            body,
+        })
+    }
+
+    fn upstream_body(&self, upstream_model: &str) -> Value {
+        let mut body = self.body.clone();
+        body["model"] = json!(upstream_model);
+        body
+    }
+
+    /// 按每百万字符单价计算本次费用
+    fn cost(&self, price: &SpeechPriceRecord) -> f64 {
+        price.price_per_million_chars * self.input_chars as f64 / 1_000_000.0
+    }
+
+    /// 写入请求日志详情的计费摘要（不含输入文本本身）
+    fn log_snapshot(&self, price: Option<&SpeechPriceRecord>) -> String {
+        json!({
+            "model": self.model,
+            "voice": self.body.get("voice"),
+            "response_format": self.body.get("response_format"),
+            "input_chars": self.input_chars,
+            "price_per_million_chars": price.map(|p| p.price_per_million_chars),
+        })
+        .to_string()
+    }
+}
+
+/// 已通过检查、可直接调用上游的语音合成请求
+pub struct AdmittedSpeech {
+    pub request: SpeechRequest,
+    pub selected: SelectedProvider,
+    pub upstream_model: String,
+    pub price: Option<SpeechPriceRecord>,
+}
+
+/// 调用上游前的检查：令牌与用户额度（含按字符数计算的金额预算）、限流、供应商选择与单价查找
+pub async fn admit(
+    app_state: &AppState,
+    raw_client_token: &str,
+    request: SpeechRequest,
+) -> Result<AdmittedSpeech, GatewayError> {
+    let token = app_state
+        .token_store
+        .get_token(raw_client_token)
+        .await?
+        .ok_or_else(|| GatewayError::Config("invalid token".into()))?;
+    crate::server::sandbox::reject_sandbox_token(&token)?;
+    let mut trace = DecisionTrace::new(&request.model);
+    check_token_limits(app_state, &token, &request.model, &mut trace).await?;
+    app_state.runtime_settings.check_rate_limit(&token.id)?;
+    app_state.request_quota.try_acquire(&token, Utc::now())?;
+
+    let (selected, parsed_model) =
+        select_provider_for_model(app_state, &request.model, Some(&token.id)).await?;
+    if !selected.provider.api_type.supports_audio_speech() {
+        return Err(GatewayError::Config(format!(
+            "audio speech is not supported by provider '{}' ({:?})",
+            selected.provider.name, selected.provider.api_type
+        )));
+    }
+    let upstream_model = parsed_model.get_upstream_model_name().to_string();
+
+    let price = app_state
+        .log_store
+        .list_speech_prices(Some(&selected.provider.name))
+        .await?
+        .into_iter()
+        .find(|p| p.model == upstream_model);
+    if price.is_none() && !missing_price_allowed_for_chat(app_state) {
+        return Err(GatewayError::Config(format!(
+            "no speech price configured for {}/{}",
+            selected.provider.name, upstream_model
+        )));
+    }
+    // 费用在请求前即可确定，超出令牌金额预算时直接拒绝
+    if let (Some(max_amount), Some(price)) = (token.max_amount, price.as_ref())
+        && token.amount_spent + request.cost(price) > max_amount
+    {
+        return Err(GatewayError::Config("token budget exceeded".into()));
+    }
+    Ok(AdmittedSpeech {
+        request,
+        selected,
+        upstream_model,
+        price,
+    })
+}
+
+/// 调用上游并把音频以流的形式转发给客户端；按输入字符数计费，
+/// 上游开始返回音频时即记账并写入请求日志，失败的请求同样记录
+pub async fn execute(
+    app_state: &AppState,
+    start_time: DateTime<Utc>,
+    raw_client_token: &str,
+    admitted: AdmittedSpeech,
+) -> Result<Response, GatewayError> {
+    let AdmittedSpeech {
+        request,
+        selected,
+        upstream_model,
+        price,
+    } = admitted;
+    let provider_name = selected.provider.name.clone();
+    let api_key = mask_key(&selected.api_key);
+    let body = request.upstream_body(&upstream_model);
+    app_state.egress_meter.record_request(
+        &provider_name,
+        &api_key,
+        serde_json::to_vec(&body).map_or(0, |b| b.len() as i64),
+    );
+    let upstream_started_at = Utc::now();
+    let response = OpenAIProvider::audio_speech(
+        &selected.provider.base_url,
+        &selected.api_key,
+        &selected.openai_account,
+        &body,
+    )
+    .await;
+    let upstream_finished_at = Utc::now();
+    if let Err(e) = &response
+        && let Some(secs) = e.retry_after()
+    {
+        app_state
+            .load_balancer_state
+            .cool_down_key(&provider_name, &selected.api_key, secs);
+    }
+
+    let amount_spent = price
+        .as_ref()
+        .filter(|_| response.is_ok())
+        .map(|p| request.cost(p));
+    app_state
+        .load_balancer_state
+        .record_key_usage(&provider_name, &api_key, None, amount_spent);
+    crate::server::provider_budget::record_spend(app_state, &provider_name, amount_spent).await;
+
+    let end_time = Utc::now();
+    let log = RequestLog {
+        id: None,
+        timestamp: start_time,
+        method: "POST".to_string(),
+        path: AUDIO_SPEECH_PATH.to_string(),
+        request_type: REQ_TYPE_AUDIO_SPEECH.to_string(),
+        requested_model: Some(request.model.clone()),
+        effective_model: Some(upstream_model.clone()),
+        model: Some(upstream_model),
+        provider: Some(provider_name.clone()),
+        api_key: Some(api_key.clone()),
+        client_token: Some(client_token_id_for_token(raw_client_token)),
+        user_id: None,
+        amount_spent,
+        status_code: match &response {
+            Ok(_) => 200,
+            Err(e) => e.status_code().as_u16(),
+        },
+        response_time_ms: (end_time - start_time).num_milliseconds(),
+        prompt_tokens: None,
+        completion_tokens: None,
+        total_tokens: None,
+        cached_tokens: None,
+        reasoning_tokens: None,
+        error_message: response.as_ref().err().map(|e| e.to_string()),
+        latency: LatencyBreakdown::from_marks(
+            start_time,
+            Some(upstream_started_at),
+            Some(upstream_finished_at),
+            end_time,
+        ),
+        profile: PromptProfile::default(),
+    };
+    // 与图片生成一致：按金额计费，订阅余额不在此扣减；先于请求日志入账
+    let counted = match amount_spent {
+        Some(delta) => {
+            crate::server::request_logging::count_token_usage(
+                app_state,
+                raw_client_token,
+                Some(delta),
+                None,
+            )
+            .await
+        }
+        None => None,
+    };
+    let usage_event = UsageEvent::from_request_log(&log);
+    let log_id = match crate::server::degraded::log_request(app_state.log_store.as_ref(), log).await
+    {
+        Ok(id) => Some(id),
+        Err(e) => {
+            tracing::error!("Failed to log request: {}", e);
+            None
+        }
+    };
+    if let Some(counted) = counted {
+        counted.logged(log_id).await;
+    }
+    usage_webhooks::enqueue_for_request(app_state, Some(raw_client_token), usage_event, log_id)
+        .await;
+    if let Some(request_log_id) = log_id {
+        let detail = RequestLogDetailRecord {
+            request_log_id,
+            request_payload_snapshot: Some(request.log_snapshot(price.as_ref())),
+            response_preview: None,
+            upstream_status: Some(if response.is_ok() { 200 } else { 500 }),
+            fallback_triggered: None,
+            fallback_reason: None,
+            selected_provider: Some(provider_name.clone()),
+            selected_key_id: Some(api_key.clone()),
+            first_token_latency_ms: None,
+            param_policy_applied: None,
+            provider_override: None,
+            prompt_truncation: None,
+        };
+        if let Err(e) = app_state.log_store.upsert_request_log_detail(detail).await {
+            tracing::warn!("Failed to upsert request log detail: {}", e);
+        }
+    }
+
+    if amount_spent.is_some() {
+        crate::server::chat_pipeline::disable_token_if_over_limits(app_state, raw_client_token)
+            .await;
+    }
+
+    let upstream = response?;
+    let content_type = upstream
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("audio/mpeg")
+        .to_string();
+    let streamed = (
+        [(header::CONTENT_TYPE, content_type)],
+        Body::from_stream(upstream.bytes_stream()),
+    )
+        .into_response();
+    Ok(app_state
+        .egress_meter
+        .meter_stream_response(streamed, provider_name, api_key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_counts_characters_and_rewrites_model() {
+        let req = SpeechRequest::parse(json!({
+            "model": "oa/tts-1",
+            "voice": "alloy",
+            "input": "你好，world",
+            "response_format": "opus"
+        }))
+        .unwrap();
+        assert_eq!((req.model.as_str(), req.input_chars), ("oa/tts-1", 8));
+        let upstream = req.upstream_body("tts-1");
+        assert_eq!(upstream["model"], "tts-1");
+        assert_eq!(upstream["response_format"], "opus");
+
+        let price = SpeechPriceRecord {
+            provider: "oa".into(),
+            model: "tts-1".into(),
+            price_per_million_chars: 15.0,
+            currency: None,
+            updated_at: Utc::now(),
+        };
+        assert!((req.cost(&price) - 0.00012).abs() < 1e-12);
+
+        assert!(SpeechRequest::parse(json!({"model": "m", "voice": "alloy"})).is_err());
+        assert!(SpeechRequest::parse(json!({"model": "m", "input": "hi"})).is_err());
+        let long = "a".repeat(MAX_INPUT_CHARS + 1);
+        assert!(
+            SpeechRequest::parse(json!({"model": "m", "voice": "alloy", "input": long})).is_err()
+        );
+    }
+}
@@ -0,0 +1,554 @@
+use chrono::{DateTime, Utc};
+use serde_json::{Value, json};
+
+use crate::admin::client_token_id_for_token;
+use crate::error::GatewayError;
+use crate::logging::RequestLog;
+use crate::logging::types::{
+    AudioPriceRecord, LatencyBreakdown, PromptProfile, REQ_TYPE_AUDIO_TRANSCRIPTION,
+    RequestLogDetailRecord,
+};
+use crate::providers::openai::OpenAIProvider;
+use crate::routing::SelectedProvider;
+use crate::server::AppState;
+use crate::server::chat_plan::{DecisionTrace, check_token_limits};
+use crate::server::pricing::missing_price_allowed_for_chat;
+use crate::server::provider_dispatch::select_provider_for_model;
+use crate::server::usage_webhooks::{self, UsageEvent};
+use crate::server::util::mask_key;
+
+pub const AUDIO_TRANSCRIPTIONS_PATH: &str = "/v1/audio/transcriptions";
+/// 与 OpenAI 一致的单文件上限（25 MiB），另留少量余量给其他表单字段
+pub const MAX_AUDIO_UPLOAD_BYTES: usize = 26 * 1024 * 1024;
+const RESPONSE_FORMATS: [&str; 5] = ["json", "text", "srt", "verbose_json", "vtt"];
+
+/// multipart/form-data 中的一个字段；`headers` 为原始头部块，转发时原样写回
+#[derive(Debug, Clone, PartialEq)]
+struct FormPart {
+    name: String,
+    headers: Vec<u8>,
+    data: Vec<u8>,
+}
+
+impl FormPart {
+    fn text(name: &str, value: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            headers: format!("Content-Disposition: form-data; name=\"{}\"", name).into_bytes(),
+            data: value.as_bytes().to_vec(),
+        }
+    }
+
+    fn is_file(&self) -> bool {
+        disposition_param(&self.headers, "filename").is_some()
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    haystack
+        .get(from..)?
+        .windows(needle.len())
+        .position(|w| w == needle)
+        .map(|pos| pos + from)
+}
+
+/// 从 Content-Disposition 头中取出 `name="..."` 等参数
+fn disposition_param(headers: &[u8], param: &str) -> Option<String> {
+    let headers = String::from_utf8_lossy(headers);
+    let line = headers
+        .split("\r\n")
+        .find(|l| l.to_ascii_lowercase().starts_with("content-disposition:"))?;
+    line.split(';').skip(1).find_map(|item| {
+        let (key, value) = item.trim().split_once('=')?;
+        key.trim()
+            .eq_ignore_ascii_case(param)
+            .then(|| value.trim().trim_matches('"').to_string())
+    })
+}
+
+fn boundary_of(content_type: &str) -> Option<String> {
+    let (mime, params) = content_type.split_once(';')?;
+    if !mime.trim().eq_ignore_ascii_case("multipart/form-data") {
+        return None;
+    }
+    params.split(';').find_map(|item| {
+        let (key, value) = item.trim().split_once('=')?;
+        key.trim()
+            .eq_ignore_ascii_case("boundary")
+            .then(|| value.trim().trim_matches('"').to_string())
+            .filter(|b| !b.is_empty())
+    })
+}
+
+fn parse_multipart(body: &[u8], boundary: &str) -> Result<Vec<FormPart>, GatewayError> {
+    let invalid = || GatewayError::Config("malformed multipart body".into());
+    let delimiter = format!("--{}", boundary).into_bytes();
+    let next_delimiter = format!("\r\n--{}", boundary).into_bytes();
+    let mut pos = find(body, &delimiter, 0).ok_or_else(invalid)? + delimiter.len();
+    let mut parts = Vec::new();
+    loop {
+        match body.get(pos..pos + 2) {
+            Some(b"--") => return Ok(parts),
+            Some(b"\r\n") => pos += 2,
+            _ => return Err(invalid()),
+        }
+        let end = find(body, &next_delimiter, pos).ok_or_else(invalid)?;
+        let part = &body[pos..end];
+        let split = find(part, b"\r\n\r\n", 0).ok_or_else(invalid)?;
+        let headers = part[..split].to_vec();
+        let name = disposition_param(&headers, "name").ok_or_else(invalid)?;
+        parts.push(FormPart {
+            name,
+            headers,
+            data: part[split + 4..].to_vec(),
+        });
+        pos = end + next_delimiter.len();
+    }
+}
+
+fn encode_multipart(parts: &[FormPart], boundary: &str) -> Vec<u8> {
+    let mut out = Vec::new();
+    for part in parts {
+        out.extend_from_slice(format!("--{}\r\n", boundary).as_bytes());
+        out.extend_from_slice(&part.headers);
+        out.extend_from_slice(b"\r\n\r\n");
+        out.extend_from_slice(&part.data);
+        out.extend_from_slice(b"\r\n");
+    }
+    out.extend_from_slice(format!("--{}--\r\n", boundary).as_bytes());
+    out
+}
+
+/// 校验后的转写请求：表单字段保持原样，转发时仅替换 model（及 text 格式，见 `upstream_body`）
+#[derive(Debug, Clone, PartialEq)]
+pub struct TranscriptionRequest {
+    pub model: String,
+    pub response_format: String,
+    pub file_bytes: usize,
+    boundary: String,
+    parts: Vec<FormPart>,
+}
+
+impl TranscriptionRequest {
+    pub fn parse(content_type: &str, body: &[u8]) -> Result<Self, GatewayError> {
+        let boundary = boundary_of(content_type).ok_or_else(|| {
+            GatewayError::Config("request must be multipart/form-data with a boundary".into())
+        })?;
+        let parts = parse_multipart(body, &boundary)?;
+        let text = |name: &str| {
+            parts
+                .iter()
+                .find(|p| p.name == name && !p.is_file())
+                .map(|p| String::from_utf8_lossy(&p.data).trim().to_string())
+        };
+        let model = text("model")
+            .filter(|m| !m.is_empty())
+            .ok_or_else(|| GatewayError::Config("model is required".into()))?;
+        let response_format = text("response_format")
+            .filter(|f| !f.is_empty())
+            .unwrap_or_else(|| "json".to_string());
+        if !RESPONSE_FORMATS.contains(&response_format.as_str()) {
+            return Err(GatewayError::Config(format!(
+                "response_format must be one of {}",
+                RESPONSE_FORMATS.join(", ")
+            )));
+        }
+        let file_bytes = parts
+            .iter()
+            .find(|p| p.name == "file" && p.is_file())
+            .map(|p| p.data.len())
+            .filter(|len| *len > 0)
+            .ok_or_else(|| GatewayError::Config("file is required".into()))?;
+        Ok(Self {
+            model,
+            response_format,
+            file_bytes,
+            boundary,
+            parts,
+        })
+    }
+
+    /// 转发给上游的请求体：model 换成上游模型名；text 格式改为 json 以取得用量（时长），返回前再转回纯文本
+    fn upstream_body(&self, upstream_model: &str) -> Vec<u8> {
+        let parts: Vec<FormPart> = self
+            .parts
+            .iter()
+            .map(|p| match p.name.as_str() {
+                "model" => FormPart::text("model", upstream_model),
+                "response_format" if self.response_format == "text" => {
+                    FormPart::text("response_format", "json")
+                }
+                _ => p.clone(),
+            })
+            .collect();
+        encode_multipart(&parts, &self.boundary)
+    }
+
+    fn content_type(&self) -> String {
+        format!("multipart/form-data; boundary={}", self.boundary)
+    }
+
+    /// 写入请求日志详情的计费摘要
+    fn log_snapshot(
+        &self,
+        duration_seconds: Option<f64>,
+        price: Option<&AudioPriceRecord>,
+    ) -> String {
+        json!({
+            "model": self.model,
+            "response_format": self.response_format,
+            "file_bytes": self.file_bytes,
+            "duration_seconds": duration_seconds,
+            "price_per_minute": price.map(|p| p.price_per_minute),
+        })
+        .to_string()
+    }
+}
+
+/// 字幕时间戳（`HH:MM:SS,mmm`、`HH:MM:SS.mmm` 或 WebVTT 的 `MM:SS.mmm`）转为秒
+fn subtitle_timestamp_seconds(raw: &str) -> Option<f64> {
+    let raw = raw.replace(',', ".");
+    let fields: Vec<&str> = raw.split(':').collect();
+    let (h, m, s) = match fields.as_slice() {
+        [h, m, s] => (h.parse::<f64>().ok()?, m.parse::<f64>().ok()?, *s),
+        [m, s] => (0.0, m.parse::<f64>().ok()?, *s),
+        _ => return None,
+    };
+    Some(h * 3600.0 + m * 60.0 + s.parse::<f64>().ok()?)
+}
+
+/// 转写结果及其音频时长；时长来自 verbose_json 的 `duration`、json 的 `usage.seconds`
+/// 或字幕格式最后一条字幕的结束时间
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transcription {
+    pub content_type: String,
+    pub body: Vec<u8>,
+    pub duration_seconds: Option<f64>,
+}
+
+fn transcription_from_upstream(
+    response_format: &str,
+    content_type: String,
+    body: Vec<u8>,
+) -> Result<Transcription, GatewayError> {
+    if matches!(response_format, "srt" | "vtt") {
+        let duration_seconds = String::from_utf8_lossy(&body)
+            .lines()
+            .rev()
+            .find_map(|line| {
+                let (_, end) = line.split_once("-->")?;
+                subtitle_timestamp_seconds(end.split_whitespace().next()?)
+            });
+        return Ok(Transcription {
+            content_type,
+            body,
+            duration_seconds,
+        });
+    }
+    let raw: Value = serde_json::from_slice(&body)?;
+    let duration_seconds = raw.get("duration").and_then(Value::as_f64).or_else(|| {
+        let usage = raw.get("usage")?;
+        (usage.get("type").and_then(Value::as_str) == Some("duration"))
+            .then(|| usage.get("seconds").and_then(Value::as_f64))
+            .flatten()
+    });
+    if response_format == "text" {
+        let text = raw.get("text").and_then(Value::as_str).unwrap_or_default();
+        return Ok(Transcription {
+            content_type: "text/plain; charset=utf-8".into(),
+            body: format!("{}\n", text).into_bytes(),
+            duration_seconds,
+        });
+    }
+    Ok(Transcription {
+        content_type,
+        body,
+        duration_seconds,
+    })
+}
+
+/// 已通过检查、可直接调用上游的转写请求
+pub struct AdmittedTranscription {
+    pub request: TranscriptionRequest,
+    pub selected: SelectedProvider,
+    pub upstream_model: String,
+    pub price: Option<AudioPriceRecord>,
+}
+
+/// 调用上游前的检查：令牌与用户额度、限流、供应商选择（含密钥轮换）与单价查找
+pub async fn admit(
+    app_state: &AppState,
+    raw_client_token: &str,
+    request: TranscriptionRequest,
+) -> Result<AdmittedTranscription, GatewayError> {
+    let token = app_state
+        .token_store
+        .get_token(raw_client_token)
+        .await?
+        .ok_or_else(|| GatewayError::Config("invalid token".into()))?;
+    crate::server::sandbox::reject_sandbox_token(&token)?;
+    let mut trace = DecisionTrace::new(&request.model);
+    check_token_limits(app_state, &token, &request.model, &mut trace).await?;
+    app_state.runtime_settings.check_rate_limit(&token.id)?;
+    app_state.request_quota.try_acquire(&token, Utc::now())?;
+
+    let (selected, parsed_model) =
+        select_provider_for_model(app_state, &request.model, Some(&token.id)).await?;
+    if !selected.provider.api_type.supports_audio_transcriptions() {
+        return Err(GatewayError::Config(format!(
+            "audio transcription is not supported by provider '{}' ({:?})",
+            selected.provider.name, selected.provider.api_type
+        )));
+    }
+    let upstream_model = parsed_model.get_upstream_model_name().to_string();
+
+    let price = app_state
+        .log_store
+        .list_audio_prices(Some(&selected.provider.name))
+        .await?
+        .into_iter()
+        .find(|p| p.model == upstream_model);
+    if price.is_none() && !missing_price_allowed_for_chat(app_state) {
+        return Err(GatewayError::Config(format!(
+            "no audio price configured for {}/{}",
+            selected.provider.name, upstream_model
+        )));
+    }
+    Ok(AdmittedTranscription {
+        request,
+        selected,
+        upstream_model,
+        price,
+    })
+}
+
+/// 调用上游并按音频时长计费，成功与失败均写入请求日志
+pub async fn execute(
+    app_state: &AppState,
+    start_time: DateTime<Utc>,
+    raw_client_token: &str,
+    admitted: AdmittedTranscription,
+) -> Result<Transcription, GatewayError> {
+    let AdmittedTranscription {
+        request,
+        selected,
+        upstream_model,
+        price,
+    } = admitted;
+    let provider_name = selected.provider.name.clone();
+    let api_key = mask_key(&selected.api_key);
+    let body = request.upstream_body(&upstream_model);
+    app_state
+        .egress_meter
+        .record_request(&provider_name, &api_key, body.len() as i64);
+    let upstream_started_at = Utc::now();
+    let response = OpenAIProvider::audio_transcriptions(
+        &selected.provider.base_url,
+        &selected.api_key,
+        &selected.openai_account,
+        &request.content_type(),
+        body,
+    )
+    .await
+    .and_then(|(content_type, bytes)| {
+        app_state
+            .egress_meter
+            .record_response(&provider_name, &api_key, bytes.len() as i64);
+        transcription_from_upstream(&request.response_format, content_type, bytes)
+    });
+    let upstream_finished_at = Utc::now();
+    if let Err(e) = &response
+        && let Some(secs) = e.retry_after()
+    {
+        app_state
+            .load_balancer_state
+            .cool_down_key(&provider_name, &selected.api_key, secs);
+    }
+
+    let duration_seconds = response.as_ref().ok().and_then(|t| t.duration_seconds);
+    if response.is_ok() && duration_seconds.is_none() && price.is_some() {
+        tracing::warn!(
+            provider = %provider_name,
+            model = %upstream_model,
+            "upstream transcription response carries no duration; request not billed"
+        );
+    }
+    let amount_spent = price
+        .as_ref()
+        .zip(duration_seconds)
+        .map(|(p, secs)| p.price_per_minute * secs / 60.0);
+    app_state
+        .load_balancer_state
+        .record_key_usage(&provider_name, &api_key, None, amount_spent);
+    crate::server::provider_budget::record_spend(app_state, &provider_name, amount_spent).await;
+
+    let end_time = Utc::now();
+    let log = RequestLog {
+        id: None,
+        timestamp: start_time,
+        method: "POST".to_string(),
+        path: AUDIO_TRANSCRIPTIONS_PATH.to_string(),
+        request_type: REQ_TYPE_AUDIO_TRANSCRIPTION.to_string(),
+        requested_model: Some(request.model.clone()),
+        effective_model: Some(upstream_model.clone()),
+        model: Some(upstream_model),
+        provider: Some(provider_name.clone()),
+        api_key: Some(api_key.clone()),
+        client_token: Some(client_token_id_for_token(raw_client_token)),
+        user_id: None,
+        amount_spent,
+        status_code: match &response {
+            Ok(_) => 200,
+            Err(e) => e.status_code().as_u16(),
+        },
+        response_time_ms: (end_time - start_time).num_milliseconds(),
+        prompt_tokens: None,
+        completion_tokens: None,
+        total_tokens: None,
+        cached_tokens: None,
+        reasoning_tokens: None,
+        error_message: response.as_ref().err().map(|e| e.to_string()),
+        latency: LatencyBreakdown::from_marks(
+            start_time,
+            Some(upstream_started_at),
+            Some(upstream_finished_at),
+            end_time,
+        ),
+        profile: PromptProfile::default(),
+    };
+    // 与图片生成一致：按金额计费，订阅余额不在此扣减；先于请求日志入账
+    let counted = match amount_spent {
+        Some(delta) => {
+            crate::server::request_logging::count_token_usage(
+                app_state,
+                raw_client_token,
+                Some(delta),
+                None,
+            )
+            .await
+        }
+        None => None,
+    };
+    let usage_event = UsageEvent::from_request_log(&log);
+    let log_id = match crate::server::degraded::log_request(app_state.log_store.as_ref(), log).await
+    {
+        Ok(id) => Some(id),
+        Err(e) => {
+            tracing::error!("Failed to log request: {}", e);
+            None
+        }
+    };
+    if let Some(counted) = counted {
+        counted.logged(log_id).await;
+    }
+    usage_webhooks::enqueue_for_request(app_state, Some(raw_client_token), usage_event, log_id)
+        .await;
+    if let Some(request_log_id) = log_id {
+        let detail = RequestLogDetailRecord {
+            request_log_id,
+            request_payload_snapshot: Some(request.log_snapshot(duration_seconds, price.as_ref())),
+            response_preview: None,
+            upstream_status: Some(if response.is_ok() { 200 } else { 500 }),
+            fallback_triggered: None,
+            fallback_reason: None,
+            selected_provider: Some(provider_name),
+            selected_key_id: Some(api_key),
+            first_token_latency_ms: None,
+            param_policy_applied: None,
+            provider_override: None,
+            prompt_truncation: None,
+        };
+        if let Err(e) = app_state.log_store.upsert_request_log_detail(detail).await {
+            tracing::warn!("Failed to upsert request log detail: {}", e);
+        }
+    }
+
+    if amount_spent.is_some() {
+        crate::server::chat_pipeline::disable_token_if_over_limits(app_state, raw_client_token)
+            .await;
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BOUNDARY: &str = "xYzZY";
+
+    fn form(fields: &[(&str, &str)]) -> Vec<u8> {
+        let mut parts: Vec<FormPart> = fields
+            .iter()
+            .map(|(name, value)| FormPart::text(name, value))
+            .collect();
+        parts.push(FormPart {
+            name: "file".into(),
+            headers: b"Content-Disposition: form-data; name=\"file\"; filename=\"a.mp3\"\r\nContent-Type: audio/mpeg".to_vec(),
+            data: b"ID3\x00\r\n--not-a-boundary".to_vec(),
+        });
+        encode_multipart(&parts, BOUNDARY)
+    }
+
+    fn content_type() -> String {
+        format!("multipart/form-data; boundary=\"{}\"", BOUNDARY)
+    }
+
+    #[test]
+    fn parse_reads_fields_and_rewrites_upstream_form() {
+        let body = form(&[("model", "oa/whisper-1"), ("response_format", "text")]);
+        let req = TranscriptionRequest::parse(&content_type(), &body).unwrap();
+        assert_eq!(
+            (
+                req.model.as_str(),
+                req.response_format.as_str(),
+                req.file_bytes
+            ),
+            ("oa/whisper-1", "text", 22)
+        );
+
+        let upstream =
+            TranscriptionRequest::parse(&req.content_type(), &req.upstream_body("whisper-1"))
+                .unwrap();
+        assert_eq!(upstream.model, "whisper-1");
+        assert_eq!(upstream.response_format, "json");
+        assert_eq!(upstream.parts[2], req.parts[2]);
+
+        assert!(TranscriptionRequest::parse("application/json", &body).is_err());
+        assert!(TranscriptionRequest::parse(&content_type(), &form(&[])).is_err());
+        assert!(
+            TranscriptionRequest::parse(
+                &content_type(),
+                &form(&[("model", "m"), ("response_format", "xml")])
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn duration_comes_from_json_usage_or_subtitles() {
+        let verbose = transcription_from_upstream(
+            "verbose_json",
+            "application/json".into(),
+            br#"{"text":"hi","duration":12.5}"#.to_vec(),
+        )
+        .unwrap();
+        assert_eq!(verbose.duration_seconds, Some(12.5));
+
+        let text = transcription_from_upstream(
+            "text",
+            "application/json".into(),
+            br#"{"text":"hi","usage":{"type":"duration","seconds":7}}"#.to_vec(),
+        )
+        .unwrap();
+        assert_eq!(text.body, b"hi\n");
+        assert_eq!(text.duration_seconds, Some(7.0));
+
+        let srt =
+            "1\n00:00:00,000 --> 00:00:04,200\nhello\n\n2\n00:01:02,500 --> 00:01:05,250\nworld\n";
+        let srt = transcription_from_upstream("srt", "text/plain".into(), srt.into()).unwrap();
+        assert_eq!(srt.duration_seconds, Some(65.25));
+        let vtt = "WEBVTT\n\n00:01.000 --> 00:03.500 align:start\nhi\n";
+        let vtt = transcription_from_upstream("vtt", "text/vtt".into(), vtt.into()).unwrap();
+        assert_eq!(vtt.duration_seconds, Some(3.5));
+    }
+}
@@ -0,0 +1,198 @@
+use std::sync::Arc;
+
+use axum::body::{Body, HttpBody, to_bytes};
+use axum::extract::{Request, State};
+use axum::http::{HeaderMap, HeaderValue, header};
+use axum::middleware::Next;
+use axum::response::Response;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::error::GatewayError;
+use crate::server::AppState;
+
+pub const SERVICE_HEADER: &str = "x-gateway-service";
+pub const DOCS_HEADER: &str = "x-gateway-docs";
+pub const SUPPORT_HEADER: &str = "x-gateway-support";
+
+const FIELD_MAX_CHARS: usize = 256;
+// 仅改写小体积的 JSON 错误响应；更大的响应体原样透传
+const ERROR_BODY_MAX_BYTES: usize = 64 * 1024;
+
+/// 部署品牌信息：注入错误响应体、`/status` 与响应头（随运行期设置持久化）
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct Branding {
+    /// 对外展示的服务名称
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub service_name: Option<String>,
+    /// 文档地址（http/https）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub docs_url: Option<String>,
+    /// 支持联系方式（邮箱、工单地址等）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub support_contact: Option<String>,
+}
+
+fn normalize_field(field: &str, value: Option<String>) -> Result<Option<String>, GatewayError> {
+    let Some(value) = value
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+    else {
+        return Ok(None);
+    };
+    if value.chars().count() > FIELD_MAX_CHARS {
+        return Err(GatewayError::Config(format!(
+            "{} must be at most {} characters",
+            field, FIELD_MAX_CHARS
+        )));
+    }
+    // 需要作为响应头输出，不允许控制字符
+    if value.chars().any(char::is_control) {
+        return Err(GatewayError::Config(format!(
+            "{} must not contain control characters",
+            field
+        )));
+    }
+    Ok(Some(value))
+}
+
+impl Branding {
+    pub fn is_empty(&self) -> bool {
+        self.service_name.is_none() && self.docs_url.is_none() && self.support_contact.is_none()
+    }
+
+    /// 校验并规范化：去除首尾空白，空字符串视为未设置
+    pub fn validated(self) -> Result<Self, GatewayError> {
+        let docs_url = normalize_field("docs_url", self.docs_url)?;
+        if let Some(url) = docs_url.as_deref() {
+            let parsed = reqwest::Url::parse(url)
+                .map_err(|_| GatewayError::Config(format!("invalid docs_url: {}", url)))?;
+            if !matches!(parsed.scheme(), "http" | "https") {
+                return Err(GatewayError::Config(format!(
+                    "docs_url must be an http(s) URL: {}",
+                    url
+                )));
+            }
+        }
+        Ok(Self {
+            service_name: normalize_field("service_name", self.service_name)?,
+            docs_url,
+            support_contact: normalize_field("support_contact", self.support_contact)?,
+        })
+    }
+
+    fn apply_headers(&self, headers: &mut HeaderMap) {
+        for (name, value) in [
+            (SERVICE_HEADER, &self.service_name),
+            (DOCS_HEADER, &self.docs_url),
+            (SUPPORT_HEADER, &self.support_contact),
+        ] {
+            if let Some(value) = value.as_deref().and_then(|v| HeaderValue::from_str(v).ok()) {
+                headers.insert(name, value);
+            }
+        }
+    }
+
+    /// 在 JSON 对象错误体中补充品牌字段（不覆盖已有同名字段）
+    fn inject(&self, body: &[u8]) -> Option<Vec<u8>> {
+        let Ok(Value::Object(mut object)) = serde_json::from_slice::<Value>(body) else {
+            return None;
+        };
+        let Ok(Value::Object(fields)) = serde_json::to_value(self) else {
+            return None;
+        };
+        for (key, value) in fields {
+            object.entry(key).or_insert(value);
+        }
+        serde_json::to_vec(&Value::Object(object)).ok()
+    }
+}
+
+fn is_json(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("application/json"))
+}
+
+/// 为所有响应附加品牌响应头，并在 JSON 错误体中注入服务名称、文档地址与支持联系方式
+pub async fn brand_responses(
+    State(app_state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let response = next.run(request).await;
+    let branding = app_state.runtime_settings.snapshot().branding;
+    if branding.is_empty() {
+        return response;
+    }
+    let (mut parts, body) = response.into_parts();
+    branding.apply_headers(&mut parts.headers);
+    let is_error = parts.status.is_client_error() || parts.status.is_server_error();
+    let small = body
+        .size_hint()
+        .exact()
+        .is_some_and(|len| len <= ERROR_BODY_MAX_BYTES as u64);
+    if !is_error || !small || !is_json(&parts.headers) {
+        return Response::from_parts(parts, body);
+    }
+    let bytes = match to_bytes(body, ERROR_BODY_MAX_BYTES).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            tracing::warn!("Failed to buffer error response for branding: {}", e);
+            return Response::from_parts(parts, Body::empty());
+        }
+    };
+    match branding.inject(&bytes) {
+        Some(branded) => {
+            parts.headers.remove(header::CONTENT_LENGTH);
+            Response::from_parts(parts, Body::from(branded))
+        }
+        None => Response::from_parts(parts, Body::from(bytes)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validated_trims_and_rejects_bad_values() {
+        let branding = Branding {
+            service_name: Some("  Acme AI  ".into()),
+            docs_url: Some("https://docs.acme.example/api".into()),
+            support_contact: Some(" ".into()),
+        }
+        .validated()
+        .unwrap();
+        assert_eq!(branding.service_name.as_deref(), Some("Acme AI"));
+        assert_eq!(branding.support_contact, None);
+
+        let bad = Branding {
+            docs_url: Some("ftp://docs.acme.example".into()),
+            ..Default::default()
+        };
+        assert!(bad.validated().is_err());
+        let bad = Branding {
+            service_name: Some("Acme\nAI".into()),
+            ..Default::default()
+        };
+        assert!(bad.validated().is_err());
+    }
+
+    #[test]
+    fn inject_adds_fields_without_overwriting() {
+        let branding = Branding {
+            service_name: Some("Acme AI".into()),
+            support_contact: Some("support@acme.example".into()),
+            ..Default::default()
+        };
+        let body = br#"{"code":"not_found","message":"missing","service_name":"kept"}"#;
+        let branded: Value = serde_json::from_slice(&branding.inject(body).unwrap()).unwrap();
+        assert_eq!(branded["code"], "not_found");
+        assert_eq!(branded["service_name"], "kept");
+        assert_eq!(branded["support_contact"], "support@acme.example");
+        assert!(branded.get("docs_url").is_none());
+        assert!(branding.inject(b"[1,2]").is_none());
+    }
+}
@@ -0,0 +1,138 @@
+//! 面向 Agent 框架的额度提示响应头：在客户端 API 响应上附加剩余请求次数、剩余预算与限流窗口重置时间，
+//! 便于调用方规划工具调用。仅使用令牌记录与内存计数计算，不额外汇总用量。
+
+use std::sync::Arc;
+
+use axum::{
+    extract::{Request, State},
+    http::{HeaderMap, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use chrono::{DateTime, Utc};
+
+use crate::admin::ClientToken;
+use crate::server::AppState;
+use crate::server::request_quota::{RequestQuotaStatus, RequestUsage};
+use crate::server::request_signing::resolved_client_token;
+use crate::server::runtime_settings::RateLimitWindow;
+
+/// 当前分钟窗口的限流上限（与 OpenAI 的同名响应头含义一致）
+pub const RATE_LIMIT_HEADER: &str = "x-ratelimit-limit-requests";
+/// 当前分钟窗口内剩余的请求次数
+pub const RATE_REMAINING_HEADER: &str = "x-ratelimit-remaining-requests";
+/// 距离限流窗口重置的时间，如 `42s`
+pub const RATE_RESET_HEADER: &str = "x-ratelimit-reset-requests";
+/// 累计请求次数上限的剩余次数
+pub const REMAINING_REQUESTS_HEADER: &str = "x-gateway-remaining-requests";
+/// 每日（北京时间）请求次数上限的剩余次数
+pub const REMAINING_REQUESTS_TODAY_HEADER: &str = "x-gateway-remaining-requests-today";
+/// 令牌金额额度的剩余金额
+pub const REMAINING_BUDGET_HEADER: &str = "x-gateway-remaining-budget";
+
+/// 令牌的额度提示；未配置的限制不输出对应响应头
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BudgetHints {
+    pub rate_limit: Option<RateLimitWindow>,
+    pub remaining_requests: Option<i64>,
+    pub remaining_requests_today: Option<i64>,
+    pub remaining_budget: Option<f64>,
+}
+
+impl BudgetHints {
+    pub fn new(
+        token: &ClientToken,
+        usage: RequestUsage,
+        rate_limit: Option<RateLimitWindow>,
+    ) -> Self {
+        let quota = RequestQuotaStatus::new(token, usage);
+        Self {
+            rate_limit,
+            remaining_requests: quota.remaining_requests,
+            remaining_requests_today: quota.remaining_requests_today,
+            remaining_budget: token.max_amount.map(|m| (m - token.amount_spent).max(0.0)),
+        }
+    }
+
+    pub fn apply(&self, headers: &mut HeaderMap) {
+        let mut insert = |name: &'static str, value: String| {
+            if let Ok(value) = HeaderValue::from_str(&value) {
+                headers.insert(name, value);
+            }
+        };
+        if let Some(window) = self.rate_limit {
+            insert(RATE_LIMIT_HEADER, window.limit.to_string());
+            insert(RATE_REMAINING_HEADER, window.remaining.to_string());
+            insert(RATE_RESET_HEADER, format!("{}s", window.reset_after_secs));
+        }
+        if let Some(remaining) = self.remaining_requests {
+            insert(REMAINING_REQUESTS_HEADER, remaining.to_string());
+        }
+        if let Some(remaining) = self.remaining_requests_today {
+            insert(REMAINING_REQUESTS_TODAY_HEADER, remaining.to_string());
+        }
+        if let Some(remaining) = self.remaining_budget {
+            insert(REMAINING_BUDGET_HEADER, format!("{:.6}", remaining));
+        }
+    }
+}
+
+fn hints_for(app_state: &AppState, token: &ClientToken, now: DateTime<Utc>) -> BudgetHints {
+    BudgetHints::new(
+        token,
+        app_state.request_quota.usage(&token.id, now),
+        app_state.runtime_settings.rate_limit_window(&token.id, now),
+    )
+}
+
+/// `server.budget_hint_headers` 开启时，为携带客户端令牌的 API 响应附加额度提示头（含错误响应）
+pub async fn annotate(
+    State(app_state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if !app_state.config.server.budget_hint_headers
+        || !crate::server::request_signing::is_client_api_path(request.uri().path())
+    {
+        return next.run(request).await;
+    }
+    let token = resolved_client_token(&request).cloned();
+    let mut response = next.run(request).await;
+    // 令牌取自外层中间件（请求前的快照），剩余金额不含本次请求的消费；
+    // 限流与请求次数余量为内存计数，在响应之后读取仍会计入本次请求
+    if let Some(token) = token {
+        hints_for(&app_state, &token, Utc::now()).apply(response.headers_mut());
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_configured_limits_produce_headers() {
+        let hints = BudgetHints {
+            rate_limit: Some(RateLimitWindow {
+                limit: 60,
+                remaining: 59,
+                reset_after_secs: 42,
+            }),
+            remaining_requests: None,
+            remaining_requests_today: Some(9),
+            remaining_budget: Some(1.5),
+        };
+        let mut headers = HeaderMap::new();
+        hints.apply(&mut headers);
+        assert_eq!(headers[RATE_LIMIT_HEADER], "60");
+        assert_eq!(headers[RATE_REMAINING_HEADER], "59");
+        assert_eq!(headers[RATE_RESET_HEADER], "42s");
+        assert_eq!(headers[REMAINING_REQUESTS_TODAY_HEADER], "9");
+        assert_eq!(headers[REMAINING_BUDGET_HEADER], "1.500000");
+        assert!(!headers.contains_key(REMAINING_REQUESTS_HEADER));
+
+        let mut headers = HeaderMap::new();
+        BudgetHints::default().apply(&mut headers);
+        assert!(headers.is_empty());
+    }
+}
@@ -0,0 +1,334 @@
+use chrono::{DateTime, Utc};
+
+use crate::admin::ClientToken;
+use crate::error::GatewayError;
+use crate::logging::types::{PromptProfile, REQ_TYPE_CHAT_ONCE, REQ_TYPE_CHAT_STREAM};
+use crate::providers::adapters::runtime_streaming_unsupported_message;
+use crate::providers::capabilities::{self, Feature};
+use crate::providers::openai::ChatCompletionRequest;
+use crate::routing::SelectedProvider;
+use crate::server::AppState;
+use crate::server::chat_plan::{DecisionTrace, PlannedChatRequest, plan_chat_request};
+use crate::server::fault_injection::InjectedFault;
+use crate::server::model_parser::ParsedModel;
+use crate::server::payload_limits::{self, PromptSize};
+use crate::server::prompt_profile;
+use crate::server::prompt_truncation::{self, PromptTruncation};
+use crate::server::provider_override::ProviderOverride;
+
+/// 聊天请求的下游传输方式：预检查共用同一条流水线，仅分发阶段不同
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChatTransport {
+    /// 一次性 JSON 响应
+    NonStream,
+    /// SSE 流式响应
+    Sse,
+}
+
+impl ChatTransport {
+    pub fn for_request(request: &ChatCompletionRequest) -> Self {
+        if request.stream.unwrap_or(false) {
+            Self::Sse
+        } else {
+            Self::NonStream
+        }
+    }
+
+    pub fn is_stream(self) -> bool {
+        matches!(self, Self::Sse)
+    }
+
+    /// `/v1/chat/completions` 下该传输方式对应的请求类型
+    pub fn request_type(self) -> &'static str {
+        match self {
+            Self::NonStream => REQ_TYPE_CHAT_ONCE,
+            Self::Sse => REQ_TYPE_CHAT_STREAM,
+        }
+    }
+}
+
+/// 通过全部预检查的请求：已选定供应商/密钥，已应用参数策略，可直接分发到上游
+pub struct AdmittedChatRequest {
+    pub token: ClientToken,
+    pub selected: SelectedProvider,
+    pub parsed_model: ParsedModel,
+    pub requested_model: String,
+    pub upstream_model: String,
+    pub billing_model: String,
+    /// 已应用重定向与参数策略的请求（model 仍为调用方视角的名称）
+    pub request: ChatCompletionRequest,
+    pub top_k: Option<u32>,
+    pub param_policy_applied: Option<String>,
+    /// 请求指定的供应商/密钥（写入请求日志详情）
+    pub provider_override: Option<String>,
+    /// 超出上下文窗口时丢弃最早消息的记录
+    pub prompt_truncation: Option<PromptTruncation>,
+    /// 命中的故障注入（流式截断需在分发后生效）
+    pub fault: Option<InjectedFault>,
+    /// 请求中会被目标供应商丢弃的字段
+    pub dropped_features: Vec<Feature>,
+    /// 用户消息的语言与内容类别（未开启 prompt_profiling 时为空）
+    pub prompt_profile: PromptProfile,
+}
+
+/// 无副作用的准入检查结果：预演（/plan）、管理员测试请求与正式分发共用
+pub struct ChatAdmission {
+    pub planned: PlannedChatRequest,
+    pub param_policy_applied: Option<String>,
+    pub prompt_truncation: Option<PromptTruncation>,
+    pub dropped_features: Vec<Feature>,
+    pub prompt_size: PromptSize,
+}
+
+/// 不产生副作用的全部准入检查：令牌与模型检查、供应商选择、价格查找（plan_chat_request），
+/// 传输方式与字段能力、限流与请求次数余量、参数策略、提示截断、供应商提示大小上限与模型并发（仅 fail_fast）。
+/// 限流与次数配额只做预检不计数；每一步写入 trace，拒绝时 trace 最后一步即为失败的检查。
+pub async fn check_chat_admission(
+    app_state: &AppState,
+    request: &mut ChatCompletionRequest,
+    top_k: &mut Option<u32>,
+    token: &ClientToken,
+    transport: ChatTransport,
+    provider_override: Option<&ProviderOverride>,
+    trace: &mut DecisionTrace,
+) -> Result<ChatAdmission, GatewayError> {
+    let planned = plan_chat_request(app_state, request, token, provider_override, trace).await?;
+    let selected = &planned.selected;
+    let upstream_model = &planned.upstream_model;
+
+    if transport.is_stream() {
+        // 令牌级流式开关：关闭后仅允许非流式请求
+        if !token.allow_streaming {
+            return Err(trace.fail(
+                "streaming",
+                GatewayError::Forbidden(
+                    "streaming is disabled for this token; retry with stream=false".into(),
+                ),
+            ));
+        }
+        if let Some(message) = runtime_streaming_unsupported_message(selected.provider.api_type) {
+            return Err(trace.fail("streaming", GatewayError::Config(message)));
+        }
+        trace.pass("streaming", None);
+    }
+    if let Err(e) = crate::server::n_choices::check_request(
+        selected.provider.api_type,
+        request,
+        transport.is_stream(),
+    ) {
+        return Err(trace.fail("n_choices", e));
+    }
+    trace.pass("n_choices", None);
+    let dropped_features = match capabilities::check_request(
+        selected.provider.api_type,
+        request,
+        app_state.config.server.unsupported_features,
+    ) {
+        Ok(dropped) => dropped,
+        Err(e) => return Err(trace.fail("capabilities", e)),
+    };
+    trace.pass("capabilities", None);
+
+    if let Err(e) = app_state.runtime_settings.peek_rate_limit(&token.id) {
+        return Err(trace.fail("rate_limit", e));
+    }
+    trace.pass("rate_limit", None);
+    if let Err(e) = app_state.request_quota.check(token, Utc::now()) {
+        return Err(trace.fail("request_quota", e));
+    }
+    trace.pass("request_quota", None);
+
+    let param_policy_applied = match crate::server::param_policy::apply_param_policies(
+        app_state,
+        request,
+        top_k,
+        &token.id,
+        &selected.provider.name,
+        upstream_model,
+    )
+    .await
+    {
+        Ok(applied) => applied,
+        Err(e) => return Err(trace.fail("param_policy", e)),
+    };
+    trace.pass("param_policy", param_policy_applied.clone());
+    // 参数策略可能改写 max_tokens，截断需在其后按最终的输出预留计算
+    let prompt_truncation =
+        match prompt_truncation::apply(&app_state.config.server, token, upstream_model, request) {
+            Ok(truncation) => truncation,
+            Err(e) => return Err(trace.fail("prompt_truncation", e)),
+        };
+    trace.pass(
+        "prompt_truncation",
+        prompt_truncation
+            .as_ref()
+            .map(|t| format!("removed_messages={}", t.removed_messages)),
+    );
+    // 供应商提示大小上限按截断后的请求校验
+    let prompt_size = PromptSize::of(request);
+    if let Err(e) = payload_limits::check_prompt(&selected.provider, prompt_size) {
+        return Err(trace.fail("prompt_size", e));
+    }
+    trace.pass("prompt_size", Some(prompt_size.bytes.to_string()));
+    if let Err(e) = app_state
+        .model_concurrency
+        .check_available(&app_state.config.server, upstream_model)
+    {
+        return Err(trace.fail("model_concurrency", e));
+    }
+    trace.pass("model_concurrency", None);
+
+    Ok(ChatAdmission {
+        planned,
+        param_policy_applied,
+        prompt_truncation,
+        dropped_features,
+        prompt_size,
+    })
+}
+
+/// 非流式与流式请求共用的分发前流水线：先执行 check_chat_admission 的全部检查，
+/// 再执行有副作用的步骤（限流与次数配额计数、故障注入、提示大小统计）。
+/// 拒绝原因与已选中的供应商写入 trace，供调用方记录日志。
+#[allow(clippy::too_many_arguments)]
+pub async fn admit_chat_request(
+    app_state: &AppState,
+    start_time: DateTime<Utc>,
+    mut request: ChatCompletionRequest,
+    mut top_k: Option<u32>,
+    raw_client_token: &str,
+    path: &str,
+    transport: ChatTransport,
+    provider_override: Option<&ProviderOverride>,
+    trace: &mut DecisionTrace,
+) -> Result<AdmittedChatRequest, GatewayError> {
+    let requested_model = request.model.clone();
+    let token = app_state
+        .token_store
+        .get_token(raw_client_token)
+        .await?
+        .ok_or_else(|| GatewayError::Config("invalid token".into()))?;
+
+    let admission = match check_chat_admission(
+        app_state,
+        &mut request,
+        &mut top_k,
+        &token,
+        transport,
+        provider_override,
+        trace,
+    )
+    .await
+    {
+        Ok(admission) => admission,
+        Err(err) => {
+            match trace.steps.last().filter(|step| !step.passed) {
+                // 余额耗尽时停用该用户的全部令牌（预演不产生此副作用）
+                Some(step) if step.check == "user_balance" => {
+                    if let Some(user_id) = token.user_id.as_deref() {
+                        let _ = app_state
+                            .token_store
+                            .set_enabled_for_user(user_id, false)
+                            .await;
+                    }
+                }
+                Some(step) if step.check == "prompt_size" => {
+                    if let Some(provider) = trace.provider.as_deref() {
+                        app_state.payload_sizes.record_rejected(provider);
+                    }
+                }
+                _ => {}
+            }
+            return Err(err);
+        }
+    };
+    let ChatAdmission {
+        planned,
+        param_policy_applied,
+        prompt_truncation,
+        dropped_features,
+        prompt_size,
+    } = admission;
+    let selected = planned.selected;
+    let upstream_model = planned.upstream_model;
+
+    app_state.runtime_settings.check_rate_limit(&token.id)?;
+    app_state.request_quota.try_acquire(&token, Utc::now())?;
+
+    if !trace.price_found.unwrap_or(true) {
+        tracing::warn!(
+            provider = %selected.provider.name,
+            model = %upstream_model,
+            pricing_mode = ?app_state.config.server.pricing_mode,
+            "missing model price; continuing without billing amount"
+        );
+    }
+
+    let fault = app_state.fault_injector.pick(
+        &selected.provider.name,
+        &upstream_model,
+        &token.id,
+        transport.is_stream(),
+    );
+    if let Some(fault) = fault.as_ref() {
+        crate::server::fault_injection::apply_fault(
+            app_state,
+            fault,
+            start_time,
+            path,
+            &upstream_model,
+            &selected.provider.name,
+            Some(token.id.as_str()),
+        )
+        .await?;
+    }
+
+    let provider_name = &selected.provider.name;
+    if prompt_truncation.is_some() {
+        app_state.payload_sizes.record_truncated(provider_name);
+    }
+    app_state
+        .payload_sizes
+        .record_prompt(provider_name, prompt_size.bytes);
+    let prompt_profile = if app_state.config.server.prompt_profiling {
+        prompt_profile::detect(&request)
+    } else {
+        PromptProfile::default()
+    };
+
+    Ok(AdmittedChatRequest {
+        token,
+        selected,
+        parsed_model: planned.parsed_model,
+        requested_model,
+        upstream_model,
+        billing_model: planned.billing_model,
+        request,
+        top_k,
+        param_policy_applied,
+        provider_override: provider_override.map(ProviderOverride::log_value),
+        prompt_truncation,
+        fault,
+        dropped_features,
+        prompt_profile,
+    })
+}
+
+/// 请求完成后按最新用量停用超出金额或 token 上限的令牌
+pub async fn disable_token_if_over_limits(app_state: &AppState, raw_client_token: &str) {
+    let Ok(Some(updated)) = app_state.token_store.get_token(raw_client_token).await else {
+        return;
+    };
+    let over_amount = updated
+        .max_amount
+        .is_some_and(|max_amount| updated.amount_spent > max_amount);
+    let over_tokens = updated
+        .max_tokens
+        .is_some_and(|max_tokens| updated.total_tokens_spent > max_tokens);
+    if over_amount || over_tokens {
+        let _ = app_state
+            .token_store
+            .set_enabled(raw_client_token, false)
+            .await;
+    }
+}
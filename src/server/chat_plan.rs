@@ -0,0 +1,445 @@
+use chrono::Utc;
+use serde::Serialize;
+
+use crate::admin::ClientToken;
+use crate::error::GatewayError;
+use crate::providers::openai::{ChatCompletionRequest, Usage};
+use crate::routing::SelectedProvider;
+use crate::server::AppState;
+use crate::server::model_parser::ParsedModel;
+use crate::server::model_redirect::{
+    apply_model_redirects, apply_provider_model_redirects_to_parsed_model,
+};
+use crate::server::pricing::{missing_price_allowed_for_chat, resolve_model_pricing};
+use crate::server::provider_dispatch::select_provider_for_model;
+use crate::server::provider_override::ProviderOverride;
+use crate::server::util::mask_key;
+
+/// 单个检查步骤的结果（用于决策追踪输出）
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct DecisionStep {
+    pub check: String,
+    pub passed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+/// 聊天请求的决策追踪：记录重定向、令牌校验、供应商选择与价格查找过程
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DecisionTrace {
+    pub requested_model: String,
+    pub redirected_model: Option<String>,
+    pub provider: Option<String>,
+    pub key_id: Option<String>,
+    pub upstream_model: Option<String>,
+    pub billing_model: Option<String>,
+    pub price_found: Option<bool>,
+    pub steps: Vec<DecisionStep>,
+    pub rejection: Option<String>,
+}
+
+impl DecisionTrace {
+    pub fn new(requested_model: &str) -> Self {
+        Self {
+            requested_model: requested_model.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn pass(&mut self, check: &str, detail: Option<String>) {
+        self.steps.push(DecisionStep {
+            check: check.to_string(),
+            passed: true,
+            detail,
+        });
+    }
+
+    pub(crate) fn fail(&mut self, check: &str, err: GatewayError) -> GatewayError {
+        let message = err.to_string();
+        self.steps.push(DecisionStep {
+            check: check.to_string(),
+            passed: false,
+            detail: Some(message.clone()),
+        });
+        self.rejection = Some(message);
+        err
+    }
+}
+
+/// 规划结果：已选定的供应商/密钥与计费模型，调用方可直接据此发起上游请求
+pub struct PlannedChatRequest {
+    pub selected: SelectedProvider,
+    pub parsed_model: ParsedModel,
+    pub upstream_model: String,
+    pub billing_model: String,
+}
+
+/// 令牌与所属用户的额度、状态和模型白名单检查（聊天与图片生成共用）
+pub async fn check_token_limits(
+    app_state: &AppState,
+    token: &ClientToken,
+    model: &str,
+    trace: &mut DecisionTrace,
+) -> Result<(), GatewayError> {
+    if let Some(user_id) = token.user_id.as_deref() {
+        let user = app_state.user_store.get_user(user_id).await?;
+        let balance = user.as_ref().map(|item| item.balance).unwrap_or(0.0);
+        if balance <= 0.0 {
+            return Err(trace.fail(
+                "user_balance",
+                GatewayError::Config("余额不足：密钥已失效；充值/订阅后需手动启用密钥".into()),
+            ));
+        }
+        trace.pass("user_balance", Some(format!("balance={}", balance)));
+
+        // 用户级预算：汇总该用户名下全部令牌的消费，与令牌级预算同时生效
+        if let Some(status) = crate::server::user_budget::exceeded(app_state, user_id).await? {
+            return Err(trace.fail(
+                "user_budget",
+                GatewayError::Config(format!(
+                    "user budget exceeded: spent {:.4} of {:.4}",
+                    status.amount_spent,
+                    status.max_amount.unwrap_or_default()
+                )),
+            ));
+        }
+        trace.pass("user_budget", None);
+    }
+
+    if !token.enabled {
+        // 因预算耗尽被自动停用的令牌给出更明确的原因
+        let message = if token
+            .max_amount
+            .is_some_and(|max_amount| token.amount_spent >= max_amount)
+        {
+            "token budget exceeded"
+        } else {
+            "token disabled"
+        };
+        return Err(trace.fail("token_enabled", GatewayError::Config(message.into())));
+    }
+    trace.pass("token_enabled", None);
+
+    if let Some(expires_at) = token.expires_at
+        && Utc::now() > expires_at
+    {
+        return Err(trace.fail("token_expiry", GatewayError::Config("token expired".into())));
+    }
+    trace.pass("token_expiry", token.expires_at.map(|t| t.to_rfc3339()));
+
+    if let Some(max_tokens) = token.max_tokens
+        && token.total_tokens_spent >= max_tokens
+    {
+        return Err(trace.fail(
+            "token_max_tokens",
+            GatewayError::Config("token total usage exceeded".into()),
+        ));
+    }
+    trace.pass("token_max_tokens", None);
+
+    if let Some(max_amount) = token.max_amount
+        && token.amount_spent >= max_amount
+    {
+        return Err(trace.fail(
+            "token_max_amount",
+            GatewayError::Config("token budget exceeded".into()),
+        ));
+    }
+    trace.pass("token_max_amount", None);
+
+    if let Err(e) = crate::server::token_model_limits::enforce_model_allowed_for_token(token, model)
+    {
+        return Err(trace.fail("token_model_allowed", e));
+    }
+    trace.pass("token_model_allowed", None);
+    Ok(())
+}
+
+/// 按与真实请求相同的顺序执行重定向、令牌限制、供应商选择与价格查找，但不调用上游，
+/// 也不会产生任何副作用（例如余额不足时不会自动禁用令牌）。
+pub async fn plan_chat_request(
+    app_state: &AppState,
+    request: &mut ChatCompletionRequest,
+    token: &ClientToken,
+    provider_override: Option<&ProviderOverride>,
+    trace: &mut DecisionTrace,
+) -> Result<PlannedChatRequest, GatewayError> {
+    let before = request.model.clone();
+    apply_model_redirects(request);
+    if request.model != before {
+        trace.redirected_model = Some(request.model.clone());
+    }
+    let parsed_for_prefix = ParsedModel::parse(&request.model);
+    if let Some(provider_name) = parsed_for_prefix.provider_name.as_deref() {
+        let mut parsed = parsed_for_prefix.clone();
+        if let Some((from, to)) =
+            apply_provider_model_redirects_to_parsed_model(app_state, provider_name, &mut parsed)
+                .await?
+        {
+            return Err(trace.fail(
+                "model_redirect",
+                GatewayError::Config(format!(
+                    "model '{}' is redirected; use '{}' instead",
+                    from, to
+                )),
+            ));
+        }
+    }
+    trace.pass("model_redirect", trace.redirected_model.clone());
+
+    check_token_limits(app_state, token, &request.model, trace).await?;
+
+    let selection = match provider_override {
+        Some(pinned) => {
+            if !token.allow_provider_override {
+                return Err(trace.fail(
+                    "provider_override",
+                    GatewayError::Forbidden(
+                        "provider override is not enabled for this token".into(),
+                    ),
+                ));
+            }
+            trace.pass("provider_override", Some(pinned.log_value()));
+            crate::server::provider_override::select(app_state, pinned, &request.model).await
+        }
+        None => select_provider_for_model(app_state, &request.model, Some(&token.id)).await,
+    };
+    let (selected, parsed_model) = match selection {
+        Ok(v) => v,
+        Err(e) => return Err(trace.fail("provider_selection", e)),
+    };
+    let upstream_model = parsed_model.get_upstream_model_name().to_string();
+    trace.provider = Some(selected.provider.name.clone());
+    trace.key_id = Some(mask_key(&selected.api_key));
+    trace.upstream_model = Some(upstream_model.clone());
+    trace.pass("provider_selection", Some(selected.provider.name.clone()));
+
+    // 选中供应商后再次检查：该模型若是供应商重定向的源模型，则不允许直接调用
+    let mut parsed_for_redirect = parsed_model.clone();
+    if let Some((from, to)) = apply_provider_model_redirects_to_parsed_model(
+        app_state,
+        &selected.provider.name,
+        &mut parsed_for_redirect,
+    )
+    .await?
+    {
+        return Err(trace.fail(
+            "provider_model_redirect",
+            GatewayError::Config(format!(
+                "model '{}' is redirected; use '{}' instead",
+                from, to
+            )),
+        ));
+    }
+
+    if let Ok(Some(false)) = app_state
+        .log_store
+        .get_model_enabled(&selected.provider.name, &upstream_model)
+        .await
+    {
+        return Err(trace.fail(
+            "model_enabled",
+            GatewayError::Config("model is disabled".into()),
+        ));
+    }
+    trace.pass("model_enabled", None);
+
+    let resolved_pricing =
+        resolve_model_pricing(app_state, &selected.provider.name, &upstream_model, None).await?;
+    trace.billing_model = Some(resolved_pricing.billing_model.clone());
+    trace.price_found = Some(resolved_pricing.price_found);
+    if !resolved_pricing.price_found && !missing_price_allowed_for_chat(app_state) {
+        return Err(trace.fail(
+            "model_price",
+            GatewayError::Config("model price not set".into()),
+        ));
+    }
+    trace.pass("model_price", Some(resolved_pricing.billing_model.clone()));
+
+    Ok(PlannedChatRequest {
+        selected,
+        parsed_model,
+        upstream_model,
+        billing_model: resolved_pricing.billing_model,
+    })
+}
+
+/// 粗略估算 prompt tokens：按序列化后消息字符数 / 4 计算（不依赖分词器，仅用于预估）
+pub fn estimate_prompt_tokens(request: &ChatCompletionRequest) -> u32 {
+    let chars = serde_json::to_string(&request.messages)
+        .map(|s| s.chars().count())
+        .unwrap_or(0);
+    chars.div_ceil(4) as u32
+}
+
+/// 上游未返回 usage 时的估算值（本地运行时常见）：completion 同样按输出字符数 / 4 计算
+pub fn estimated_usage(prompt_tokens: u32, completion_chars: usize) -> Usage {
+    let completion_tokens = completion_chars.div_ceil(4) as u32;
+    Usage {
+        prompt_tokens,
+        completion_tokens,
+        total_tokens: prompt_tokens + completion_tokens,
+        prompt_tokens_details: None,
+        completion_tokens_details: None,
+    }
+}
+
+/// 预估费用：prompt 按估算值计，completion 按请求声明的 max_tokens 上限计（未声明则仅计 prompt）
+#[allow(deprecated)]
+pub async fn estimate_cost(
+    app_state: &AppState,
+    provider_name: &str,
+    billing_model: &str,
+    request: &ChatCompletionRequest,
+) -> Option<f64> {
+    let record = app_state
+        .log_store
+        .get_model_price(provider_name, billing_model)
+        .await
+        .ok()
+        .flatten()?;
+    let prompt = estimate_prompt_tokens(request) as f64;
+    let completion = request
+        .max_completion_tokens
+        .or(request.max_tokens)
+        .unwrap_or(0) as f64;
+    Some(
+        prompt * record.prompt_price_per_million / 1_000_000.0
+            + completion * record.completion_price_per_million / 1_000_000.0,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::admin::{CreateTokenPayload, TokenStore};
+    use crate::config::settings::{
+        BalanceStrategy, LoadBalancing, LoggingConfig, Provider, ProviderConfig, ProviderType,
+        ServerConfig,
+    };
+    use crate::logging::{DatabaseLogger, ModelPriceUpsert};
+    use std::sync::Arc;
+    use tempfile::tempdir;
+
+    async fn test_state() -> (tempfile::TempDir, Arc<AppState>, ClientToken) {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("plan.db");
+        let logger = Arc::new(
+            DatabaseLogger::new(db_path.to_str().unwrap())
+                .await
+                .unwrap(),
+        );
+        let settings = crate::config::Settings {
+            load_balancing: LoadBalancing {
+                strategy: BalanceStrategy::FirstAvailable,
+            },
+            server: ServerConfig::default(),
+            logging: LoggingConfig {
+                database_path: db_path.to_string_lossy().to_string(),
+                ..Default::default()
+            },
+        };
+        logger
+            .insert_provider(&Provider {
+                name: "p1".into(),
+                display_name: None,
+                collection: crate::config::settings::DEFAULT_PROVIDER_COLLECTION.into(),
+                api_type: ProviderType::OpenAI,
+                api_type_raw: None,
+                base_url: "http://127.0.0.1:9".into(),
+                api_keys: Vec::new(),
+                models_endpoint: None,
+                provider_config: ProviderConfig::default(),
+                enabled: true,
+                created_at: None,
+                updated_at: None,
+            })
+            .await
+            .unwrap();
+        logger
+            .add_provider_key("p1", "sk-plan-test-key", &settings.logging.key_log_strategy)
+            .await
+            .unwrap();
+        logger
+            .upsert_model_price(ModelPriceUpsert::manual(
+                "p1",
+                "m1",
+                1.0,
+                2.0,
+                Some("USD".into()),
+                None,
+            ))
+            .await
+            .unwrap();
+        let token = logger
+            .create_token(CreateTokenPayload {
+                name: Some("plan".into()),
+                model_blacklist: Some(vec!["p1/blocked".into()]),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        let app_state = crate::server::test_harness::app_state(settings, logger.clone());
+        (dir, app_state, token)
+    }
+
+    fn request_for(model: &str) -> ChatCompletionRequest {
+        serde_json::from_value(serde_json::json!({
+            "model": model,
+            "messages": [{"role": "user", "content": "hi"}]
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn plan_selects_provider_and_pricing() {
+        let (_dir, app_state, token) = test_state().await;
+        let mut request = request_for("p1/m1");
+        let mut trace = DecisionTrace::new("p1/m1");
+        let planned = plan_chat_request(&app_state, &mut request, &token, None, &mut trace)
+            .await
+            .unwrap();
+        assert_eq!(planned.selected.provider.name, "p1");
+        assert_eq!(planned.upstream_model, "m1");
+        assert_eq!(trace.price_found, Some(true));
+        assert_eq!(trace.key_id.as_deref(), Some("sk-p****-key"));
+        assert!(trace.rejection.is_none());
+        assert!(trace.steps.iter().all(|s| s.passed));
+    }
+
+    #[tokio::test]
+    async fn plan_records_rejection_step() {
+        let (_dir, app_state, token) = test_state().await;
+        let mut request = request_for("p1/blocked");
+        let mut trace = DecisionTrace::new("p1/blocked");
+        let err = plan_chat_request(&app_state, &mut request, &token, None, &mut trace)
+            .await
+            .err()
+            .unwrap();
+        assert!(matches!(err, GatewayError::Forbidden(_)));
+        let last = trace.steps.last().unwrap();
+        assert_eq!(last.check, "token_model_allowed");
+        assert!(!last.passed);
+        assert!(trace.provider.is_none());
+        assert!(trace.rejection.is_some());
+    }
+
+    #[tokio::test]
+    async fn estimate_cost_uses_prompt_estimate_and_max_tokens() {
+        let (_dir, app_state, _token) = test_state().await;
+        let mut request = request_for("p1/m1");
+        request.max_completion_tokens = Some(1000);
+        let prompt = estimate_prompt_tokens(&request);
+        assert!(prompt > 0);
+        let cost = estimate_cost(&app_state, "p1", "m1", &request)
+            .await
+            .unwrap();
+        let expected = prompt as f64 / 1_000_000.0 + 2.0 * 1000.0 / 1_000_000.0;
+        assert!((cost - expected).abs() < 1e-12);
+        assert!(
+            estimate_cost(&app_state, "p1", "missing", &request)
+                .await
+                .is_none()
+        );
+    }
+}
@@ -14,4 +14,10 @@ pub struct GatewayChatCompletionRequest {
     pub request: ChatCompletionRequest,
     /// Top-k sampling parameter (best-effort; currently only Anthropic path uses it).
     pub top_k: Option<u32>,
+    /// Pin the request to this provider, bypassing load balancing
+    /// (only for tokens with `allow_provider_override`; also accepted as `X-Gateway-Provider`).
+    pub provider: Option<String>,
+    /// Pin a specific key of `provider`, given as the raw key or its masked form
+    /// (also accepted as `X-Gateway-Provider-Key`).
+    pub provider_key: Option<String>,
 }
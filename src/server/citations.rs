@@ -0,0 +1,210 @@
+//! 联网搜索引用来源的统一表示：智谱 `web_search`、Perplexity `search_results` / `citations`
+//! 等供应商各自的字段，统一映射到 OpenAI 兼容的 `message.annotations`（流式为 `delta.annotations`），
+//! 条目类型为 `url_citation`，下游只需按 OpenAI 格式渲染来源。原始字段保持不变。
+
+use std::collections::HashSet;
+
+use serde_json::{Map, Value, json};
+
+/// 一条引用来源；`marker` 为正文中引用该来源的角标（如 `[1]`、`[ref_1]`）
+#[derive(Debug, Clone, PartialEq)]
+struct Source {
+    url: String,
+    title: Option<String>,
+    snippet: Option<String>,
+    marker: String,
+}
+
+fn text_field(v: &Value, key: &str) -> Option<String> {
+    v.get(key)
+        .and_then(Value::as_str)
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+}
+
+/// 从响应（或流式 chunk）顶层收集来源
+fn collect_sources(v: &Value) -> Vec<Source> {
+    // 智谱：web_search[{title, link, content, refer}]，正文以 [ref_n] 引用
+    if let Some(items) = v.get("web_search").and_then(Value::as_array) {
+        return items
+            .iter()
+            .enumerate()
+            .filter_map(|(i, item)| {
+                Some(Source {
+                    url: text_field(item, "link")?,
+                    title: text_field(item, "title"),
+                    snippet: text_field(item, "content"),
+                    marker: format!(
+                        "[{}]",
+                        text_field(item, "refer").unwrap_or_else(|| format!("ref_{}", i + 1))
+                    ),
+                })
+            })
+            .collect();
+    }
+    // Perplexity：search_results[{title, url}] 优先，旧版仅有 citations[url]；正文以 [n] 引用
+    if let Some(items) = v.get("search_results").and_then(Value::as_array) {
+        return items
+            .iter()
+            .enumerate()
+            .filter_map(|(i, item)| {
+                Some(Source {
+                    url: text_field(item, "url")?,
+                    title: text_field(item, "title"),
+                    snippet: text_field(item, "snippet"),
+                    marker: format!("[{}]", i + 1),
+                })
+            })
+            .collect();
+    }
+    if let Some(items) = v.get("citations").and_then(Value::as_array) {
+        return items
+            .iter()
+            .enumerate()
+            .filter_map(|(i, item)| {
+                Some(Source {
+                    url: item.as_str()?.to_string(),
+                    title: None,
+                    snippet: None,
+                    marker: format!("[{}]", i + 1),
+                })
+            })
+            .collect();
+    }
+    Vec::new()
+}
+
+/// 来源转为 url_citation；正文中找到角标时附上其字符区间
+fn annotation(source: &Source, content: &str) -> Value {
+    let mut citation = Map::new();
+    citation.insert("url".into(), json!(source.url));
+    if let Some(title) = &source.title {
+        citation.insert("title".into(), json!(title));
+    }
+    if let Some(snippet) = &source.snippet {
+        citation.insert("content".into(), json!(snippet));
+    }
+    if let Some(byte_pos) = content.find(&source.marker) {
+        let start = content[..byte_pos].chars().count();
+        citation.insert("start_index".into(), json!(start));
+        citation.insert(
+            "end_index".into(),
+            json!(start + source.marker.chars().count()),
+        );
+    }
+    json!({"type": "url_citation", "url_citation": citation})
+}
+
+fn annotate_choices(v: &mut Value, sources: &[Source], emitted: &mut HashSet<u64>) -> bool {
+    let Some(choices) = v.get_mut("choices").and_then(Value::as_array_mut) else {
+        return false;
+    };
+    let mut changed = false;
+    for choice in choices {
+        let index = choice.get("index").and_then(Value::as_u64).unwrap_or(0);
+        let key = if choice.get("delta").is_some() {
+            "delta"
+        } else {
+            "message"
+        };
+        let Some(msg) = choice.get_mut(key).and_then(Value::as_object_mut) else {
+            continue;
+        };
+        // 上游已返回 OpenAI 格式的 annotations（或本流已下发过）时不再重复
+        if msg.contains_key("annotations") || !emitted.insert(index) {
+            continue;
+        }
+        let content = msg
+            .get("content")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        let annotations: Vec<Value> = sources.iter().map(|s| annotation(s, content)).collect();
+        msg.insert("annotations".into(), Value::Array(annotations));
+        changed = true;
+    }
+    changed
+}
+
+/// 为完整响应补充 `choices[].message.annotations`；没有可识别的来源时不做改动
+pub fn normalize_response(v: &mut Value) {
+    let sources = collect_sources(v);
+    if !sources.is_empty() {
+        annotate_choices(v, &sources, &mut HashSet::new());
+    }
+}
+
+/// 流式响应的引用归一化：来源首次出现时写入对应 choice 的 `delta.annotations`，
+/// 之后重复携带来源的 chunk（Perplexity 每个 chunk 都带 citations）不再下发
+#[derive(Debug, Default)]
+pub struct StreamCitations {
+    emitted: HashSet<u64>,
+}
+
+impl StreamCitations {
+    /// 返回 chunk 是否被改写（未改写时调用方可原样转发上游数据）
+    pub fn normalize_chunk(&mut self, v: &mut Value) -> bool {
+        let sources = collect_sources(v);
+        !sources.is_empty() && annotate_choices(v, &sources, &mut self.emitted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zhipu_and_perplexity_sources_map_to_url_citations() {
+        let mut zhipu = json!({
+            "choices": [{"index": 0, "message": {"role": "assistant", "content": "结论见[ref_1]。"}}],
+            "web_search": [
+                {"title": "新闻", "link": "https://a.example", "content": "摘要", "refer": "ref_1"},
+                {"title": "无链接", "refer": "ref_2"}
+            ]
+        });
+        normalize_response(&mut zhipu);
+        assert_eq!(
+            zhipu["choices"][0]["message"]["annotations"],
+            json!([{"type": "url_citation", "url_citation": {
+                "url": "https://a.example", "title": "新闻", "content": "摘要",
+                "start_index": 3, "end_index": 10
+            }}])
+        );
+        assert!(zhipu.get("web_search").is_some());
+
+        let mut perplexity = json!({
+            "choices": [{"index": 0, "message": {"content": "Rust 1.0 shipped in 2015 [2]."}}],
+            "citations": ["https://one.example", "https://two.example"]
+        });
+        normalize_response(&mut perplexity);
+        let annotations = &perplexity["choices"][0]["message"]["annotations"];
+        assert_eq!(
+            annotations[1]["url_citation"]["url"],
+            json!("https://two.example")
+        );
+        assert_eq!(annotations[1]["url_citation"]["start_index"], json!(25));
+        assert!(annotations[0]["url_citation"].get("start_index").is_none());
+
+        let mut plain = json!({"choices": [{"index": 0, "message": {"content": "hi"}}]});
+        normalize_response(&mut plain);
+        assert!(plain["choices"][0]["message"].get("annotations").is_none());
+    }
+
+    #[test]
+    fn stream_emits_annotations_once_per_choice() {
+        let chunk = json!({
+            "choices": [{"index": 0, "delta": {"content": "a"}}],
+            "search_results": [{"title": "T", "url": "https://t.example"}]
+        });
+        let mut state = StreamCitations::default();
+        let mut first = chunk.clone();
+        assert!(state.normalize_chunk(&mut first));
+        assert_eq!(
+            first["choices"][0]["delta"]["annotations"][0]["url_citation"],
+            json!({"url": "https://t.example", "title": "T"})
+        );
+        let mut second = chunk.clone();
+        assert!(!state.normalize_chunk(&mut second));
+        assert_eq!(second, chunk);
+    }
+}
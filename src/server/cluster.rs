@@ -0,0 +1,255 @@
+//! 多实例部署（无 Redis）的缓存失效广播：
+//! 数据本身保存在共享的 Postgres 中，但各副本仍持有少量进程内状态
+//! （模型列表缓存、密钥轮询状态、预算告警记录、运行期设置快照）。
+//! 管理变更在本实例生效后，按静态配置的 cluster_peers 逐个 POST 失效事件，
+//! 收到事件的副本只在本地应用，不再转发。价格与令牌每次请求直接读取存储，无需广播。
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+use crate::config::settings::ServerConfig;
+use crate::error::GatewayError;
+use crate::server::AppState;
+use crate::server::request_signing::peer_signature_headers;
+use crate::server::tasks::TaskRegistry;
+
+/// 副本接收事件的路径（不带 /api 前缀）
+pub const EVENTS_PATH: &str = "/internal/cluster/events";
+const PEER_TIMEOUT_SECS: u64 = 5;
+
+/// 缓存失效事件
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum CacheEvent {
+    /// 供应商配置或其密钥变更
+    Provider { provider: String },
+    /// 供应商月度预算变更（重新允许阈值告警）
+    ProviderBudget { provider: String },
+    /// 维护窗口新增或取消，需从存储重新加载
+    MaintenanceWindows,
+    /// 运行期设置变更，需从存储重新加载
+    RuntimeSettings,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PeerMessage {
+    /// 发送方实例 ID，用于忽略自己发出的事件（peer 列表包含自身时）
+    pub origin: String,
+    pub event: CacheEvent,
+}
+
+/// 本实例的 peer 列表与签名密钥
+pub struct ClusterPeers {
+    instance_id: String,
+    peers: Vec<String>,
+    secret: Option<String>,
+}
+
+impl Default for ClusterPeers {
+    fn default() -> Self {
+        Self {
+            instance_id: hex::encode(rand::random::<[u8; 8]>()),
+            peers: Vec::new(),
+            secret: None,
+        }
+    }
+}
+
+impl ClusterPeers {
+    pub fn from_config(config: &ServerConfig) -> Self {
+        let secret = config
+            .cluster_secret
+            .as_deref()
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(str::to_string);
+        let peers = config
+            .cluster_peers
+            .iter()
+            .map(|p| p.trim().trim_end_matches('/').to_string())
+            .filter(|p| !p.is_empty())
+            .collect::<Vec<_>>();
+        if !peers.is_empty() && secret.is_none() {
+            tracing::warn!("cluster_peers configured without cluster_secret; broadcast disabled");
+        }
+        Self {
+            peers,
+            secret,
+            ..Default::default()
+        }
+    }
+
+    pub fn secret(&self) -> Option<&str> {
+        self.secret.as_deref()
+    }
+
+    pub fn instance_id(&self) -> &str {
+        &self.instance_id
+    }
+
+    /// 异步向所有 peer 发送事件（经任务登记表派发，关闭时等待在途投递）；
+    /// 失败只记录告警（对端会在下次变更或定期同步时收敛）
+    pub fn broadcast(&self, tasks: &Arc<TaskRegistry>, event: CacheEvent) {
+        let Some(secret) = self.secret.clone() else {
+            return;
+        };
+        if self.peers.is_empty() {
+            return;
+        }
+        let message = PeerMessage {
+            origin: self.instance_id.clone(),
+            event,
+        };
+        let body = match serde_json::to_vec(&message) {
+            Ok(body) => body,
+            Err(e) => {
+                tracing::warn!("Failed to encode cluster event: {}", e);
+                return;
+            }
+        };
+        for peer in self.peers.clone() {
+            let body = body.clone();
+            let secret = secret.clone();
+            tasks.spawn("cluster_broadcast", async move {
+                if let Err(e) = send_to_peer(&peer, &secret, body).await {
+                    tracing::warn!(peer = %peer, "cluster event delivery failed: {}", e);
+                }
+            });
+        }
+    }
+}
+
+async fn send_to_peer(peer: &str, secret: &str, body: Vec<u8>) -> Result<(), String> {
+    let url = format!("{}{}", peer, EVENTS_PATH);
+    let client = crate::http_client::client_for_url(&url).map_err(|e| e.to_string())?;
+    let mut request = client
+        .post(&url)
+        .timeout(Duration::from_secs(PEER_TIMEOUT_SECS))
+        .header(reqwest::header::CONTENT_TYPE, "application/json");
+    for (name, value) in peer_signature_headers(secret, "POST", EVENTS_PATH, &body) {
+        request = request.header(name, value);
+    }
+    let resp = request.body(body).send().await.map_err(|e| e.to_string())?;
+    if !resp.status().is_success() {
+        return Err(format!("peer responded with {}", resp.status()));
+    }
+    Ok(())
+}
+
+/// 在本实例应用失效事件
+pub async fn apply(app_state: &AppState, event: &CacheEvent) -> Result<(), GatewayError> {
+    match event {
+        CacheEvent::Provider { provider } => {
+            crate::server::handlers::provider_models_list::invalidate_cache_for_provider(provider)
+                .await;
+            crate::server::model_helpers::invalidate_upstream_models(provider).await;
+            app_state.load_balancer_state.forget_provider(provider);
+            crate::tls_pinning::sync(app_state).await?;
+        }
+        CacheEvent::ProviderBudget { provider } => {
+            app_state
+                .provider_spend
+                .reset_warnings(provider, Utc::now());
+        }
+        CacheEvent::MaintenanceWindows => crate::server::maintenance::refresh(app_state).await?,
+        CacheEvent::RuntimeSettings => app_state.runtime_settings.load().await?,
+    }
+    Ok(())
+}
+
+/// 本实例完成管理变更后调用：本地缓存由调用方处理，这里只通知其他副本
+pub fn publish(app_state: &AppState, event: CacheEvent) {
+    app_state.cluster.broadcast(&app_state.task_registry, event);
+}
+
+/// 供应商或其密钥变更后：清理本地缓存并通知其他副本
+pub async fn provider_changed(app_state: &AppState, provider: &str) {
+    let event = CacheEvent::Provider {
+        provider: provider.to_string(),
+    };
+    if let Err(e) = apply(app_state, &event).await {
+        tracing::warn!(provider, "failed to invalidate provider caches: {}", e);
+    }
+    publish(app_state, event);
+}
+
+/// 接收 peer 事件的共享实现，便于处理器与测试复用
+pub async fn receive(
+    app_state: &Arc<AppState>,
+    headers: &axum::http::HeaderMap,
+    body: &[u8],
+) -> Result<bool, GatewayError> {
+    let Some(secret) = app_state.cluster.secret() else {
+        return Err(GatewayError::NotFound("cluster is not configured".into()));
+    };
+    crate::server::request_signing::verify_peer_request(
+        &app_state.signature_nonces,
+        secret,
+        "POST",
+        EVENTS_PATH,
+        headers,
+        body,
+    )?;
+    let message: PeerMessage = serde_json::from_slice(body)
+        .map_err(|e| GatewayError::Config(format!("invalid cluster event: {}", e)))?;
+    if message.origin == app_state.cluster.instance_id() {
+        return Ok(false);
+    }
+    tracing::debug!(origin = %message.origin, event = ?message.event, "applying cluster event");
+    apply(app_state, &message.event).await?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_use_tagged_json() {
+        let message = PeerMessage {
+            origin: "a".into(),
+            event: CacheEvent::Provider {
+                provider: "openai".into(),
+            },
+        };
+        assert_eq!(
+            serde_json::to_value(&message).unwrap(),
+            serde_json::json!({"origin": "a", "event": {"kind": "provider", "provider": "openai"}})
+        );
+        let parsed: CacheEvent =
+            serde_json::from_value(serde_json::json!({"kind": "runtime_settings"})).unwrap();
+        assert_eq!(parsed, CacheEvent::RuntimeSettings);
+    }
+
+    #[test]
+    fn peer_signature_roundtrip_rejects_replay_and_wrong_secret() {
+        use crate::server::request_signing::{NonceCache, verify_peer_request};
+        let body = br#"{"origin":"a","event":{"kind":"runtime_settings"}}"#;
+        let mut headers = axum::http::HeaderMap::new();
+        for (name, value) in peer_signature_headers("secret", "POST", EVENTS_PATH, body) {
+            headers.insert(name, value.parse().unwrap());
+        }
+        let nonces = NonceCache::default();
+        let verify = |secret, body: &[u8]| {
+            verify_peer_request(&nonces, secret, "POST", EVENTS_PATH, &headers, body)
+        };
+        assert!(verify("other", body).is_err());
+        assert!(verify("secret", b"{}").is_err());
+        assert!(verify("secret", body).is_ok());
+        assert!(verify("secret", body).is_err());
+    }
+
+    #[test]
+    fn peers_require_secret() {
+        let config = ServerConfig {
+            cluster_peers: vec![" http://10.0.0.2:8000/ ".into(), "".into()],
+            ..Default::default()
+        };
+        let peers = ClusterPeers::from_config(&config);
+        assert_eq!(peers.peers, vec!["http://10.0.0.2:8000".to_string()]);
+        assert!(peers.secret().is_none());
+    }
+}
@@ -0,0 +1,611 @@
+//! SQLite 持续备份：按间隔用 `VACUUM INTO` 生成一致性快照，zstd 压缩后上传到
+//! S3 兼容存储或二级目录，并维护 `latest` 指针；`gateway db-restore` 据此恢复。
+
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use reqwest::Url;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::config::settings::{ReplicationConfig, S3ReplicaConfig};
+use crate::error::GatewayError;
+use crate::logging::DatabaseLogger;
+
+const LATEST_KEY: &str = "latest";
+const SNAPSHOT_DIR: &str = "snapshots";
+const SNAPSHOT_SUFFIX: &str = ".db.zst";
+const SQLITE_HEADER: &[u8] = b"SQLite format 3\0";
+
+/// S3 兼容存储（path-style，SigV4 签名）
+#[derive(Debug, Clone)]
+pub struct S3Target {
+    endpoint: Url,
+    bucket: String,
+    region: String,
+    prefix: String,
+    access_key_id: String,
+    secret_access_key: String,
+}
+
+#[derive(Debug, Clone)]
+pub enum ReplicaTarget {
+    Dir(PathBuf),
+    S3(S3Target),
+}
+
+fn non_empty(v: Option<String>) -> Option<String> {
+    v.map(|s| s.trim().to_string()).filter(|s| !s.is_empty())
+}
+
+impl S3Target {
+    fn from_config(cfg: &S3ReplicaConfig) -> Result<Self, GatewayError> {
+        let endpoint = Url::parse(cfg.endpoint.trim())
+            .ok()
+            .filter(|u| matches!(u.scheme(), "http" | "https") && u.host_str().is_some())
+            .ok_or_else(|| GatewayError::Config("replication.s3.endpoint 不是合法的 URL".into()))?;
+        let bucket = cfg.bucket.trim().to_string();
+        if bucket.is_empty() {
+            return Err(GatewayError::Config(
+                "replication.s3.bucket 不能为空".into(),
+            ));
+        }
+        let access_key_id = non_empty(cfg.access_key_id.clone())
+            .or_else(|| non_empty(std::env::var("GW_REPLICA_S3_ACCESS_KEY_ID").ok()))
+            .ok_or_else(|| {
+                GatewayError::Config(
+                    "replication.s3 缺少 access_key_id（或 GW_REPLICA_S3_ACCESS_KEY_ID）".into(),
+                )
+            })?;
+        let secret_access_key = non_empty(cfg.secret_access_key.clone())
+            .or_else(|| non_empty(std::env::var("GW_REPLICA_S3_SECRET_ACCESS_KEY").ok()))
+            .ok_or_else(|| {
+                GatewayError::Config(
+                    "replication.s3 缺少 secret_access_key（或 GW_REPLICA_S3_SECRET_ACCESS_KEY）"
+                        .into(),
+                )
+            })?;
+        Ok(Self {
+            endpoint,
+            bucket,
+            region: cfg.region.trim().to_string(),
+            prefix: cfg.prefix.trim().trim_matches('/').to_string(),
+            access_key_id,
+            secret_access_key,
+        })
+    }
+
+    fn object_url(&self, key: &str) -> Result<Url, GatewayError> {
+        let full_key = if self.prefix.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}/{}", self.prefix, key)
+        };
+        let base = self.endpoint.as_str().trim_end_matches('/');
+        Url::parse(&format!("{}/{}/{}", base, self.bucket, full_key))
+            .map_err(|e| GatewayError::Config(format!("invalid object url: {}", e)))
+    }
+
+    async fn send(
+        &self,
+        method: reqwest::Method,
+        key: &str,
+        body: Vec<u8>,
+    ) -> Result<reqwest::Response, GatewayError> {
+        let url = self.object_url(key)?;
+        let payload_hash = hex::encode(Sha256::digest(&body));
+        let signed = sign_v4(
+            method.as_str(),
+            &url,
+            &self.region,
+            &self.access_key_id,
+            &self.secret_access_key,
+            &payload_hash,
+            Utc::now(),
+        );
+        let client = crate::http_client::client_for_url(url.as_str())?;
+        let resp = client
+            .request(method, url)
+            .header("x-amz-date", signed.amz_date)
+            .header("x-amz-content-sha256", payload_hash)
+            .header("authorization", signed.authorization)
+            .body(body)
+            .send()
+            .await?;
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let text = resp.text().await.unwrap_or_default();
+            return Err(GatewayError::Config(format!(
+                "S3 {} {} failed: {} {}",
+                key,
+                status.as_u16(),
+                status.canonical_reason().unwrap_or(""),
+                text.chars().take(300).collect::<String>()
+            )));
+        }
+        Ok(resp)
+    }
+}
+
+struct SignedHeaders {
+    amz_date: String,
+    authorization: String,
+}
+
+fn hmac_sha256(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn signing_key(secret: &str, date: &str, region: &str, service: &str) -> Vec<u8> {
+    let k_date = hmac_sha256(format!("AWS4{}", secret).as_bytes(), date);
+    let k_region = hmac_sha256(&k_date, region);
+    let k_service = hmac_sha256(&k_region, service);
+    hmac_sha256(&k_service, "aws4_request")
+}
+
+/// AWS Signature V4（仅签 host / x-amz-content-sha256 / x-amz-date，无查询参数）
+fn sign_v4(
+    method: &str,
+    url: &Url,
+    region: &str,
+    access_key_id: &str,
+    secret_access_key: &str,
+    payload_hash: &str,
+    now: DateTime<Utc>,
+) -> SignedHeaders {
+    let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let date = now.format("%Y%m%d").to_string();
+    let host = match url.port() {
+        Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+        None => url.host_str().unwrap_or_default().to_string(),
+    };
+    let signed_headers = "host;x-amz-content-sha256;x-amz-date";
+    let canonical_request = format!(
+        "{}\n{}\n\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\n{}\n{}",
+        method,
+        url.path(),
+        host,
+        payload_hash,
+        amz_date,
+        signed_headers,
+        payload_hash
+    );
+    let scope = format!("{}/{}/s3/aws4_request", date, region);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date,
+        scope,
+        hex::encode(Sha256::digest(canonical_request.as_bytes()))
+    );
+    let signature = hex::encode(hmac_sha256(
+        &signing_key(secret_access_key, &date, region, "s3"),
+        &string_to_sign,
+    ));
+    SignedHeaders {
+        amz_date,
+        authorization: format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            access_key_id, scope, signed_headers, signature
+        ),
+    }
+}
+
+impl ReplicaTarget {
+    pub fn from_config(cfg: &ReplicationConfig) -> Result<Self, GatewayError> {
+        match (non_empty(cfg.path.clone()), cfg.s3.as_ref()) {
+            (Some(path), None) => Ok(ReplicaTarget::Dir(PathBuf::from(path))),
+            (None, Some(s3)) => Ok(ReplicaTarget::S3(S3Target::from_config(s3)?)),
+            _ => Err(GatewayError::Config(
+                "logging.replication 需要且只能配置 path 或 s3 之一".into(),
+            )),
+        }
+    }
+
+    /// 用于状态展示（不含凭据）
+    pub fn describe(&self) -> String {
+        match self {
+            ReplicaTarget::Dir(path) => path.display().to_string(),
+            ReplicaTarget::S3(s3) => format!("s3://{}/{}", s3.bucket, s3.prefix),
+        }
+    }
+
+    async fn put(&self, key: &str, body: Vec<u8>) -> Result<(), GatewayError> {
+        match self {
+            ReplicaTarget::Dir(root) => {
+                let dest = root.join(key);
+                if let Some(parent) = dest.parent() {
+                    tokio::fs::create_dir_all(parent).await?;
+                }
+                // 先写临时文件再改名，避免恢复时读到半个文件
+                let tmp = dest.with_extension("partial");
+                tokio::fs::write(&tmp, body).await?;
+                tokio::fs::rename(&tmp, &dest).await?;
+                Ok(())
+            }
+            ReplicaTarget::S3(s3) => s3.send(reqwest::Method::PUT, key, body).await.map(|_| ()),
+        }
+    }
+
+    async fn get(&self, key: &str) -> Result<Vec<u8>, GatewayError> {
+        match self {
+            ReplicaTarget::Dir(root) => Ok(tokio::fs::read(root.join(key)).await?),
+            ReplicaTarget::S3(s3) => Ok(s3
+                .send(reqwest::Method::GET, key, Vec::new())
+                .await?
+                .bytes()
+                .await?
+                .to_vec()),
+        }
+    }
+
+    /// 仅保留最近 `retain` 份快照（目录目标）；S3 依赖桶的生命周期规则
+    async fn prune(&self, retain: usize) -> Result<(), GatewayError> {
+        let ReplicaTarget::Dir(root) = self else {
+            return Ok(());
+        };
+        let mut names = Vec::new();
+        let mut entries = match tokio::fs::read_dir(root.join(SNAPSHOT_DIR)).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e.into()),
+        };
+        while let Some(entry) = entries.next_entry().await? {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name.ends_with(SNAPSHOT_SUFFIX) {
+                names.push(name);
+            }
+        }
+        // 文件名以 UTC 时间戳开头，字典序即时间序
+        names.sort();
+        let excess = names.len().saturating_sub(retain.max(1));
+        for name in names.into_iter().take(excess) {
+            tokio::fs::remove_file(root.join(SNAPSHOT_DIR).join(name)).await?;
+        }
+        Ok(())
+    }
+
+    /// 读取 `latest` 指针指向的快照键
+    pub async fn latest_key(&self) -> Result<String, GatewayError> {
+        let raw = self.get(LATEST_KEY).await?;
+        let key = String::from_utf8_lossy(&raw).trim().to_string();
+        if key.is_empty() {
+            return Err(GatewayError::NotFound(
+                "replica has no snapshots yet".into(),
+            ));
+        }
+        Ok(key)
+    }
+
+    /// 下载并解压快照，校验 SQLite 文件头
+    pub async fn fetch_snapshot(&self, key: &str) -> Result<Vec<u8>, GatewayError> {
+        let compressed = self.get(key).await?;
+        let data = tokio::task::spawn_blocking(move || zstd::decode_all(&compressed[..]))
+            .await
+            .map_err(|e| GatewayError::Config(e.to_string()))??;
+        if !data.starts_with(SQLITE_HEADER) {
+            return Err(GatewayError::Config(format!(
+                "{} is not a SQLite database snapshot",
+                key
+            )));
+        }
+        Ok(data)
+    }
+}
+
+/// 备份状态（/admin/db/status 展示）
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ReplicationStatus {
+    pub target: String,
+    pub interval_secs: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_snapshot_key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_snapshot_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_snapshot_bytes: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_checked_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error_at: Option<DateTime<Utc>>,
+    pub snapshots_shipped: u64,
+    /// 自上次快照后是否还有未备份的写入
+    pub pending_changes: bool,
+}
+
+static STATUS: OnceLock<Mutex<Option<ReplicationStatus>>> = OnceLock::new();
+
+fn status_cell() -> &'static Mutex<Option<ReplicationStatus>> {
+    STATUS.get_or_init(|| Mutex::new(None))
+}
+
+fn update_status(f: impl FnOnce(&mut ReplicationStatus)) {
+    let mut guard = status_cell().lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(status) = guard.as_mut() {
+        f(status);
+    }
+}
+
+/// 当前备份状态；未启用时为 None
+pub fn replication_status() -> Option<ReplicationStatus> {
+    status_cell()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+}
+
+pub struct Replicator {
+    logger: Arc<DatabaseLogger>,
+    target: ReplicaTarget,
+    scratch_path: PathBuf,
+    retain: usize,
+    /// 最近一次成功上传时的 total_changes
+    shipped_changes: Mutex<Option<u64>>,
+}
+
+impl Replicator {
+    pub fn new(
+        logger: Arc<DatabaseLogger>,
+        target: ReplicaTarget,
+        database_path: &str,
+        retain: usize,
+    ) -> Self {
+        Self {
+            logger,
+            target,
+            scratch_path: PathBuf::from(format!("{}.replica-tmp", database_path)),
+            retain,
+            shipped_changes: Mutex::new(None),
+        }
+    }
+
+    fn shipped(&self) -> Option<u64> {
+        *self
+            .shipped_changes
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 有变化时生成并上传一次快照；返回上传的快照键
+    pub async fn run_once(&self, now: DateTime<Utc>) -> Result<Option<String>, GatewayError> {
+        if self.shipped() == Some(self.logger.total_changes().await) {
+            return Ok(None);
+        }
+        remove_if_exists(&self.scratch_path).await?;
+        let changes = self.logger.snapshot_into(&self.scratch_path).await?;
+        let raw = tokio::fs::read(&self.scratch_path).await;
+        remove_if_exists(&self.scratch_path).await?;
+        let raw = raw?;
+        let compressed = tokio::task::spawn_blocking(move || zstd::encode_all(&raw[..], 3))
+            .await
+            .map_err(|e| GatewayError::Config(e.to_string()))??;
+        let size = compressed.len() as u64;
+        let key = format!(
+            "{}/{}{}",
+            SNAPSHOT_DIR,
+            now.format("%Y%m%dT%H%M%S%.3fZ"),
+            SNAPSHOT_SUFFIX
+        );
+        self.target.put(&key, compressed).await?;
+        self.target
+            .put(LATEST_KEY, key.clone().into_bytes())
+            .await?;
+        *self
+            .shipped_changes
+            .lock()
+            .unwrap_or_else(|e| e.into_inner()) = Some(changes);
+        if let Err(e) = self.target.prune(self.retain).await {
+            tracing::warn!("Failed to prune old database snapshots: {}", e);
+        }
+        update_status(|s| {
+            s.last_snapshot_key = Some(key.clone());
+            s.last_snapshot_at = Some(now);
+            s.last_snapshot_bytes = Some(size);
+            s.snapshots_shipped += 1;
+        });
+        Ok(Some(key))
+    }
+}
+
+async fn remove_if_exists(path: &Path) -> Result<(), GatewayError> {
+    match tokio::fs::remove_file(path).await {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// 启动备份任务：启动后立即快照一次，之后按间隔检查；关闭时再备份一次
+pub fn spawn_replication_task(
+    tasks: &Arc<crate::server::tasks::TaskRegistry>,
+    logger: Arc<DatabaseLogger>,
+    database_path: &str,
+    cfg: &ReplicationConfig,
+) -> Result<(), GatewayError> {
+    let target = ReplicaTarget::from_config(cfg)?;
+    let interval_secs = cfg.interval_secs.max(1);
+    *status_cell().lock().unwrap_or_else(|e| e.into_inner()) = Some(ReplicationStatus {
+        target: target.describe(),
+        interval_secs,
+        ..Default::default()
+    });
+    tracing::info!("SQLite replication enabled, target {}", target.describe());
+    let replicator = Replicator::new(logger.clone(), target, database_path, cfg.retain);
+    tasks.spawn_with("sqlite_replication", |mut ctx| async move {
+        let mut ticker = tokio::time::interval(std::time::Duration::from_secs(interval_secs));
+        loop {
+            let stopping = tokio::select! {
+                _ = ticker.tick() => false,
+                _ = ctx.cancelled() => true,
+            };
+            let now = Utc::now();
+            match replicator.run_once(now).await {
+                Ok(_) => update_status(|s| s.last_checked_at = Some(now)),
+                Err(e) => {
+                    tracing::warn!("SQLite replication failed: {}", e);
+                    update_status(|s| {
+                        s.last_error = Some(e.to_string());
+                        s.last_error_at = Some(now);
+                    });
+                    ctx.report_error(e);
+                }
+            }
+            let pending = replicator.shipped() != Some(logger.total_changes().await);
+            update_status(|s| s.pending_changes = pending);
+            if stopping {
+                break;
+            }
+        }
+    });
+    Ok(())
+}
+
+const RESTORE_USAGE: &str = "usage: gateway db-restore [options]
+
+restores the SQLite database from the replica configured in [logging.replication]
+
+options:
+  --snapshot <key>    snapshot key to restore (default: the latest snapshot)
+  --output <path>     where to write the database (default: logging.database_path)
+  --force             overwrite the output file if it exists";
+
+/// `gateway db-restore`：从备份目标下载快照并写入数据库文件（需在网关停止时执行）
+pub async fn restore_main(args: impl IntoIterator<Item = String>) -> Result<(), GatewayError> {
+    let mut snapshot = None;
+    let mut output = None;
+    let mut force = false;
+    let mut args = args.into_iter();
+    while let Some(flag) = args.next() {
+        match flag.as_str() {
+            "--snapshot" => snapshot = args.next(),
+            "--output" => output = args.next(),
+            "--force" => force = true,
+            "-h" | "--help" => {
+                println!("{}", RESTORE_USAGE);
+                return Ok(());
+            }
+            other => {
+                eprintln!("unknown option: {}\n\n{}", other, RESTORE_USAGE);
+                std::process::exit(2);
+            }
+        }
+    }
+    let config = crate::config::Settings::load()?;
+    let cfg =
+        config.logging.replication.as_ref().ok_or_else(|| {
+            GatewayError::Config("[logging.replication] is not configured".into())
+        })?;
+    let target = ReplicaTarget::from_config(cfg)?;
+    let output = PathBuf::from(output.unwrap_or_else(|| config.logging.database_path.clone()));
+    let key = restore(&target, snapshot.as_deref(), &output, force).await?;
+    println!(
+        "restored {} from {} to {}",
+        key,
+        target.describe(),
+        output.display()
+    );
+    Ok(())
+}
+
+/// 将快照写入 `output`（先写临时文件再改名）；返回恢复的快照键
+pub async fn restore(
+    target: &ReplicaTarget,
+    snapshot: Option<&str>,
+    output: &Path,
+    force: bool,
+) -> Result<String, GatewayError> {
+    if !force && tokio::fs::try_exists(output).await? {
+        return Err(GatewayError::Conflict(format!(
+            "{} already exists; pass --force to overwrite",
+            output.display()
+        )));
+    }
+    let key = match snapshot {
+        Some(key) => key.to_string(),
+        None => target.latest_key().await?,
+    };
+    let data = target.fetch_snapshot(&key).await?;
+    if let Some(parent) = output.parent().filter(|p| !p.as_os_str().is_empty()) {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    let tmp = output.with_extension("restore-tmp");
+    tokio::fs::write(&tmp, data).await?;
+    tokio::fs::rename(&tmp, output).await?;
+    // 旧的回滚日志会被 SQLite 视为未完成事务而回放到新文件上
+    for suffix in ["-journal", "-wal", "-shm"] {
+        remove_if_exists(&PathBuf::from(format!("{}{}", output.display(), suffix))).await?;
+    }
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::storage_traits::SettingsStore;
+    use tempfile::tempdir;
+
+    #[test]
+    fn signing_key_matches_aws_reference() {
+        // AWS 文档中的签名密钥派生示例
+        let key = signing_key(
+            "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+            "20120215",
+            "us-east-1",
+            "iam",
+        );
+        assert_eq!(
+            hex::encode(key),
+            "f4780e2d9f65fa895f9c67b32ce1baf0b0d8a43505a000a1a9e090d414db404d"
+        );
+    }
+
+    #[tokio::test]
+    async fn ships_changed_snapshots_and_restores_latest() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("gateway.db");
+        let db_path = db_path.to_str().unwrap();
+        let logger = Arc::new(DatabaseLogger::new(db_path).await.unwrap());
+        let replica = dir.path().join("replica");
+        let target = ReplicaTarget::Dir(replica.clone());
+        let replicator = Replicator::new(logger.clone(), target.clone(), db_path, 1);
+
+        let t0 = Utc::now();
+        let first = replicator.run_once(t0).await.unwrap().unwrap();
+        assert!(replicator.run_once(t0).await.unwrap().is_none());
+
+        logger.set_setting("replication_probe", "1").await.unwrap();
+        let second = replicator
+            .run_once(t0 + chrono::Duration::seconds(1))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_ne!(first, second);
+        assert!(!replica.join(&first).exists(), "old snapshot pruned");
+        assert_eq!(target.latest_key().await.unwrap(), second);
+
+        let restored = dir.path().join("restored.db");
+        assert_eq!(
+            restore(&target, None, &restored, false).await.unwrap(),
+            second
+        );
+        assert!(matches!(
+            restore(&target, None, &restored, false).await,
+            Err(GatewayError::Conflict(_))
+        ));
+        let copy = DatabaseLogger::new(restored.to_str().unwrap())
+            .await
+            .unwrap();
+        assert_eq!(
+            copy.get_setting("replication_probe")
+                .await
+                .unwrap()
+                .as_deref(),
+            Some("1")
+        );
+    }
+}
@@ -0,0 +1,66 @@
+use axum::http::HeaderMap;
+use chrono::{DateTime, Utc};
+
+use crate::error::GatewayError;
+use crate::logging::types::DebugCaptureRecord;
+use crate::server::AppState;
+
+/// 调试捕获请求头：取值 capture 时保存本次调用的完整请求/响应正文
+pub const DEBUG_HEADER: &str = "x-gateway-debug";
+/// 捕获成功时返回的日志 ID 响应头，可用于查询请求详情
+pub const LOG_ID_HEADER: &str = "x-gateway-log-id";
+const CAPTURE_VALUE: &str = "capture";
+
+/// 是否请求了调试捕获；未携带时返回 false，取值不是 capture 时报错
+pub fn capture_requested(headers: &HeaderMap) -> Result<bool, GatewayError> {
+    let Some(value) = headers.get(DEBUG_HEADER) else {
+        return Ok(false);
+    };
+    let value = value.to_str().map(str::trim).unwrap_or_default();
+    if value.eq_ignore_ascii_case(CAPTURE_VALUE) {
+        Ok(true)
+    } else {
+        Err(GatewayError::Config(format!(
+            "unsupported X-Gateway-Debug value; expected '{}'",
+            CAPTURE_VALUE
+        )))
+    }
+}
+
+/// 保存完整正文（不受 capture_body_max_bytes 限制），按 debug_capture_ttl_secs 过期；
+/// 写入失败仅记录告警，不影响本次响应
+pub async fn save(
+    app_state: &AppState,
+    request_log_id: i64,
+    request_body: Option<String>,
+    response_body: Option<String>,
+    now: DateTime<Utc>,
+) {
+    let ttl = chrono::Duration::seconds(app_state.config.server.debug_capture_ttl_secs as i64);
+    let record = DebugCaptureRecord {
+        request_log_id,
+        request_body,
+        response_body,
+        created_at: now,
+        expires_at: now + ttl,
+    };
+    if let Err(e) = app_state.log_store.save_debug_capture(record).await {
+        tracing::warn!(request_log_id, "failed to persist debug capture: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    #[test]
+    fn only_capture_value_is_accepted() {
+        let mut headers = HeaderMap::new();
+        assert!(!capture_requested(&headers).unwrap());
+        headers.insert(DEBUG_HEADER, HeaderValue::from_static(" Capture "));
+        assert!(capture_requested(&headers).unwrap());
+        headers.insert(DEBUG_HEADER, HeaderValue::from_static("trace"));
+        assert!(capture_requested(&headers).is_err());
+    }
+}
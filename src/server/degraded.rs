@@ -0,0 +1,571 @@
+//! 数据库不可用时的降级运行：令牌校验回退到最近一次成功读取的快照（不超过配置的时效），
+//! 写入失败或超时的请求日志进入磁盘回放队列（见 `log_queue`），数据库恢复后由后台任务按顺序回放；
+//! 管理端通过 `/admin/db/status` 与 `x-gateway-degraded` 响应头查看降级状态。
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use async_trait::async_trait;
+use axum::{extract::Request, http::HeaderValue, middleware::Next, response::Response};
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+
+use crate::admin::{
+    ClientToken, CreateTokenPayload, TokenAutoDisablePrefs, TokenNotificationRecord,
+    TokenRotationPrefs, TokenStore, UpdateTokenPayload,
+};
+use crate::config::settings::DegradedModeConfig;
+use crate::error::GatewayError;
+use crate::logging::types::RequestLog;
+use crate::server::log_queue::{LogQueueStats, LogReplayQueue, ReplayError};
+use crate::server::pagination::PageRequest;
+use crate::server::storage_traits::{RequestLogStore, SettingsStore};
+
+pub const DEGRADED_HEADER: &str = "x-gateway-degraded";
+
+/// 回放队列的检查间隔
+const REPLAY_INTERVAL_SECS: u64 = 30;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DegradedStatus {
+    pub degraded: bool,
+    pub since: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    /// 本次降级期间使用令牌快照通过校验的次数
+    pub token_snapshot_hits: u64,
+    pub token_snapshot_max_age_secs: u64,
+    pub log_queue: LogQueueStats,
+}
+
+#[derive(Debug, Default)]
+struct Outage {
+    since: Option<DateTime<Utc>>,
+    last_error: Option<String>,
+}
+
+pub struct DegradedMode {
+    config: DegradedModeConfig,
+    outage: Mutex<Outage>,
+    token_snapshot_hits: AtomicU64,
+    queue: LogReplayQueue,
+}
+
+impl DegradedMode {
+    pub fn new(config: DegradedModeConfig) -> Self {
+        let queue = LogReplayQueue::new(&config.log_spool_path, config.log_spool_max_bytes);
+        Self {
+            config,
+            outage: Mutex::new(Outage::default()),
+            token_snapshot_hits: AtomicU64::new(0),
+            queue,
+        }
+    }
+
+    fn outage(&self) -> std::sync::MutexGuard<'_, Outage> {
+        self.outage.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn is_degraded(&self) -> bool {
+        self.outage().since.is_some()
+    }
+
+    pub fn mark_failure(&self, op: &str, error: &dyn std::fmt::Display) {
+        let mut outage = self.outage();
+        if outage.since.is_none() {
+            tracing::warn!(op, error = %error, "database unavailable, entering degraded mode");
+            outage.since = Some(Utc::now());
+            self.token_snapshot_hits.store(0, Ordering::Relaxed);
+        }
+        outage.last_error = Some(format!("{}: {}", op, error));
+    }
+
+    pub fn mark_healthy(&self) {
+        let mut outage = self.outage();
+        if let Some(since) = outage.since.take() {
+            tracing::info!(since = %since, "database reachable again, leaving degraded mode");
+            outage.last_error = None;
+        }
+    }
+
+    /// 读写成功不代表队列已回放完，仍有待回放日志时保持降级状态
+    fn mark_healthy_if_idle(&self) {
+        if self.queue.depth() == 0 && self.is_degraded() {
+            self.mark_healthy();
+        }
+    }
+
+    pub fn status(&self) -> DegradedStatus {
+        let outage = self.outage();
+        DegradedStatus {
+            degraded: outage.since.is_some(),
+            since: outage.since,
+            last_error: outage.last_error.clone(),
+            token_snapshot_hits: self.token_snapshot_hits.load(Ordering::Relaxed),
+            token_snapshot_max_age_secs: self.config.token_snapshot_max_age_secs,
+            log_queue: self.queue.stats(),
+        }
+    }
+
+    pub fn queue(&self) -> &LogReplayQueue {
+        &self.queue
+    }
+
+    /// 回放队列；存储仍不可用时保持降级，队列清空后恢复
+    pub async fn replay(
+        &self,
+        store: &(dyn RequestLogStore + Send + Sync),
+    ) -> Result<Vec<i64>, ReplayError> {
+        let result = self.queue.replay(store).await;
+        match &result {
+            Err(ReplayError::Store { error, .. }) => self.mark_failure("replay_request_log", error),
+            Ok(ids) if !ids.is_empty() && self.queue.depth() == 0 => self.mark_healthy(),
+            _ => {}
+        }
+        result
+    }
+}
+
+static DEGRADED: OnceLock<Arc<DegradedMode>> = OnceLock::new();
+
+pub fn install(mode: Arc<DegradedMode>) {
+    let _ = DEGRADED.set(mode);
+}
+
+/// 未安装（如测试环境）时为 None，行为与未启用降级模式一致
+pub fn degraded_mode() -> Option<Arc<DegradedMode>> {
+    DEGRADED.get().cloned()
+}
+
+/// 写入请求日志；失败或超时时标记降级并放入回放队列，仍返回原错误（调用方拿不到日志 id）。
+/// 超时的写入可能仍在数据库中完成，回放时会出现一条重复日志
+pub async fn log_request(
+    store: &(dyn RequestLogStore + Send + Sync),
+    log: RequestLog,
+) -> rusqlite::Result<i64> {
+    let Some(mode) = degraded_mode() else {
+        return store.log_request(log).await;
+    };
+    let write = store.log_request(log.clone());
+    let result = match mode.config.log_write_timeout_ms {
+        0 => write.await,
+        ms => tokio::time::timeout(std::time::Duration::from_millis(ms), write)
+            .await
+            .unwrap_or_else(|_| {
+                Err(rusqlite::Error::SqliteFailure(
+                    rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_BUSY),
+                    Some(format!("request log write timed out after {}ms", ms)),
+                ))
+            }),
+    };
+    match result {
+        Ok(id) => {
+            mode.mark_healthy_if_idle();
+            Ok(id)
+        }
+        Err(e) => {
+            mode.mark_failure("log_request", &e);
+            let request_id = uuid::Uuid::new_v4().simple().to_string();
+            if let Err(queue_err) = mode.queue.enqueue(&request_id, &log).await {
+                tracing::error!("Failed to queue request log for replay: {}", queue_err);
+            }
+            Err(e)
+        }
+    }
+}
+
+/// 数据库恢复后回放队列中的请求日志；回放日志的令牌计数在请求时已入账，
+/// 其 ID 交给令牌用量缓冲在崩溃补记时排除
+pub fn spawn_replay_task(
+    tasks: &Arc<crate::server::tasks::TaskRegistry>,
+    mode: Arc<DegradedMode>,
+    log_store: Arc<dyn RequestLogStore + Send + Sync>,
+    settings: Arc<dyn SettingsStore + Send + Sync>,
+) {
+    tasks.spawn_with("log_queue_replay", |mut ctx| async move {
+        let mut ticker =
+            tokio::time::interval(std::time::Duration::from_secs(REPLAY_INTERVAL_SECS));
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {}
+                _ = ctx.cancelled() => break,
+            }
+            let result = mode.replay(log_store.as_ref()).await;
+            let replayed = match &result {
+                Ok(ids) | Err(ReplayError::Store { replayed: ids, .. }) => ids.as_slice(),
+                Err(_) => &[],
+            };
+            if let Err(e) =
+                crate::server::token_usage_buffer::note_replayed(settings.as_ref(), replayed).await
+            {
+                tracing::warn!("Failed to record replayed request log ids: {}", e);
+            }
+            match result {
+                Ok(ids) if ids.is_empty() => {}
+                Ok(ids) => tracing::info!("Replayed {} queued request logs", ids.len()),
+                // 数据库仍不可用，下个周期重试
+                Err(e @ ReplayError::Store { .. }) => {
+                    tracing::debug!("Request log replay deferred: {}", e)
+                }
+                Err(e) => {
+                    tracing::warn!("Request log queue replay failed: {}", e);
+                    ctx.report_error(e);
+                }
+            }
+        }
+    });
+}
+
+/// 降级期间在管理端响应上附加 `x-gateway-degraded: true`
+pub async fn annotate_admin_responses(request: Request, next: Next) -> Response {
+    let is_admin = {
+        let path = request.uri().path();
+        path.strip_prefix("/api")
+            .unwrap_or(path)
+            .starts_with("/admin/")
+    };
+    let mut response = next.run(request).await;
+    if is_admin && degraded_mode().is_some_and(|m| m.is_degraded()) {
+        response
+            .headers_mut()
+            .insert(DEGRADED_HEADER, HeaderValue::from_static("true"));
+    }
+    response
+}
+
+/// 令牌存储的快照层：成功读取的令牌按令牌值缓存，数据库读取失败时在时效内返回快照；
+/// 写入成功后清除相关快照，避免降级期间放行已停用/已轮换的令牌
+pub struct SnapshotTokenStore {
+    inner: Arc<dyn TokenStore + Send + Sync>,
+    mode: Arc<DegradedMode>,
+    snapshots: Mutex<HashMap<String, (ClientToken, DateTime<Utc>)>>,
+}
+
+impl SnapshotTokenStore {
+    pub fn new(inner: Arc<dyn TokenStore + Send + Sync>, mode: Arc<DegradedMode>) -> Self {
+        Self {
+            inner,
+            mode,
+            snapshots: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn snapshots(
+        &self,
+    ) -> std::sync::MutexGuard<'_, HashMap<String, (ClientToken, DateTime<Utc>)>> {
+        self.snapshots.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn remember(&self, token: &ClientToken) {
+        if self.mode.config.token_snapshot_max_age_secs == 0 {
+            return;
+        }
+        self.snapshots()
+            .insert(token.token.clone(), (token.clone(), Utc::now()));
+    }
+
+    fn forget(&self, matches: impl Fn(&ClientToken) -> bool) {
+        self.snapshots().retain(|_, (t, _)| !matches(t));
+    }
+
+    fn fresh(&self, find: impl Fn(&ClientToken) -> bool) -> Option<ClientToken> {
+        let max_age = self.mode.config.token_snapshot_max_age_secs;
+        if max_age == 0 {
+            return None;
+        }
+        let cutoff = Utc::now() - Duration::seconds(max_age as i64);
+        self.snapshots()
+            .values()
+            .find(|(t, at)| *at >= cutoff && find(t))
+            .map(|(t, _)| t.clone())
+    }
+
+    fn lookup(
+        &self,
+        op: &str,
+        result: Result<Option<ClientToken>, GatewayError>,
+        find: impl Fn(&ClientToken) -> bool,
+    ) -> Result<Option<ClientToken>, GatewayError> {
+        match result {
+            Ok(found) => {
+                self.mode.mark_healthy_if_idle();
+                if let Some(token) = &found {
+                    self.remember(token);
+                }
+                Ok(found)
+            }
+            Err(e) => {
+                self.mode.mark_failure(op, &e);
+                match self.fresh(find) {
+                    Some(token) => {
+                        self.mode
+                            .token_snapshot_hits
+                            .fetch_add(1, Ordering::Relaxed);
+                        Ok(Some(token))
+                    }
+                    None => Err(e),
+                }
+            }
+        }
+    }
+
+    /// 写入成功后按条件清除快照
+    fn after_write<T>(
+        &self,
+        result: Result<T, GatewayError>,
+        matches: impl Fn(&ClientToken) -> bool,
+    ) -> Result<T, GatewayError> {
+        if result.is_ok() {
+            self.forget(matches);
+        }
+        result
+    }
+}
+
+#[async_trait]
+impl TokenStore for SnapshotTokenStore {
+    async fn create_token(&self, payload: CreateTokenPayload) -> Result<ClientToken, GatewayError> {
+        self.inner.create_token(payload).await
+    }
+    async fn update_token(
+        &self,
+        token: &str,
+        payload: UpdateTokenPayload,
+    ) -> Result<Option<ClientToken>, GatewayError> {
+        let result = self.inner.update_token(token, payload).await;
+        self.after_write(result, |t| t.token == token)
+    }
+    async fn set_enabled(&self, token: &str, enabled: bool) -> Result<bool, GatewayError> {
+        let result = self.inner.set_enabled(token, enabled).await;
+        self.after_write(result, |t| t.token == token)
+    }
+    async fn set_enabled_for_user(
+        &self,
+        user_id: &str,
+        enabled: bool,
+    ) -> Result<u64, GatewayError> {
+        let result = self.inner.set_enabled_for_user(user_id, enabled).await;
+        self.after_write(result, |t| t.user_id.as_deref() == Some(user_id))
+    }
+    async fn get_token(&self, token: &str) -> Result<Option<ClientToken>, GatewayError> {
+        let result = self.inner.get_token(token).await;
+        self.lookup("get_token", result, |t| t.token == token)
+    }
+    async fn get_token_by_id(&self, id: &str) -> Result<Option<ClientToken>, GatewayError> {
+        let result = self.inner.get_token_by_id(id).await;
+        self.lookup("get_token_by_id", result, |t| t.id == id)
+    }
+    async fn get_token_by_id_scoped(
+        &self,
+        user_id: &str,
+        id: &str,
+    ) -> Result<Option<ClientToken>, GatewayError> {
+        self.inner.get_token_by_id_scoped(user_id, id).await
+    }
+    async fn list_tokens(&self) -> Result<Vec<ClientToken>, GatewayError> {
+        self.inner.list_tokens().await
+    }
+    async fn list_tokens_by_user(&self, user_id: &str) -> Result<Vec<ClientToken>, GatewayError> {
+        self.inner.list_tokens_by_user(user_id).await
+    }
+    async fn list_tokens_by_organization(
+        &self,
+        organization_id: &str,
+    ) -> Result<Vec<ClientToken>, GatewayError> {
+        self.inner
+            .list_tokens_by_organization(organization_id)
+            .await
+    }
+    async fn list_tokens_page(
+        &self,
+        organization_id: Option<&str>,
+        page: &PageRequest,
+    ) -> Result<(Vec<ClientToken>, u64), GatewayError> {
+        self.inner.list_tokens_page(organization_id, page).await
+    }
+    async fn list_child_tokens(&self, parent_id: &str) -> Result<Vec<ClientToken>, GatewayError> {
+        self.inner.list_child_tokens(parent_id).await
+    }
+    async fn add_amount_spent(&self, token: &str, delta: f64) -> Result<(), GatewayError> {
+        self.inner.add_amount_spent(token, delta).await
+    }
+    async fn add_usage_spent(
+        &self,
+        token: &str,
+        prompt: i64,
+        completion: i64,
+        total: i64,
+    ) -> Result<(), GatewayError> {
+        self.inner
+            .add_usage_spent(token, prompt, completion, total)
+            .await
+    }
+    async fn delete_token(&self, token: &str) -> Result<bool, GatewayError> {
+        let result = self.inner.delete_token(token).await;
+        self.after_write(result, |t| t.token == token)
+    }
+    async fn delete_token_by_id(&self, id: &str) -> Result<bool, GatewayError> {
+        let result = self.inner.delete_token_by_id(id).await;
+        self.after_write(result, |t| t.id == id)
+    }
+    async fn update_token_by_id(
+        &self,
+        id: &str,
+        payload: UpdateTokenPayload,
+    ) -> Result<Option<ClientToken>, GatewayError> {
+        let result = self.inner.update_token_by_id(id, payload).await;
+        self.after_write(result, |t| t.id == id)
+    }
+    async fn set_enabled_by_id(&self, id: &str, enabled: bool) -> Result<bool, GatewayError> {
+        let result = self.inner.set_enabled_by_id(id, enabled).await;
+        self.after_write(result, |t| t.id == id)
+    }
+    async fn get_notifications_opt_out(&self, id: &str) -> Result<bool, GatewayError> {
+        self.inner.get_notifications_opt_out(id).await
+    }
+    async fn set_notifications_opt_out(&self, id: &str, opt_out: bool) -> Result<(), GatewayError> {
+        self.inner.set_notifications_opt_out(id, opt_out).await
+    }
+    async fn record_token_notification(
+        &self,
+        record: &TokenNotificationRecord,
+    ) -> Result<(), GatewayError> {
+        self.inner.record_token_notification(record).await
+    }
+    async fn token_notification_sent(
+        &self,
+        id: &str,
+        kind: &str,
+        reference: &str,
+    ) -> Result<bool, GatewayError> {
+        self.inner
+            .token_notification_sent(id, kind, reference)
+            .await
+    }
+    async fn list_token_notifications(
+        &self,
+        id: &str,
+        limit: i64,
+    ) -> Result<Vec<TokenNotificationRecord>, GatewayError> {
+        self.inner.list_token_notifications(id, limit).await
+    }
+    async fn get_auto_disable_prefs(
+        &self,
+        id: &str,
+    ) -> Result<TokenAutoDisablePrefs, GatewayError> {
+        self.inner.get_auto_disable_prefs(id).await
+    }
+    async fn set_auto_disable_exempt(&self, id: &str, exempt: bool) -> Result<(), GatewayError> {
+        self.inner.set_auto_disable_exempt(id, exempt).await
+    }
+    async fn mark_auto_disabled(&self, id: &str, at: DateTime<Utc>) -> Result<(), GatewayError> {
+        let result = self.inner.mark_auto_disabled(id, at).await;
+        self.after_write(result, |t| t.id == id)
+    }
+    async fn get_rotation_prefs(&self, id: &str) -> Result<TokenRotationPrefs, GatewayError> {
+        self.inner.get_rotation_prefs(id).await
+    }
+    async fn set_rotation_exempt(&self, id: &str, exempt: bool) -> Result<(), GatewayError> {
+        self.inner.set_rotation_exempt(id, exempt).await
+    }
+    async fn rotate_token(
+        &self,
+        id: &str,
+        new_token: &str,
+        at: DateTime<Utc>,
+    ) -> Result<bool, GatewayError> {
+        let result = self.inner.rotate_token(id, new_token, at).await;
+        self.after_write(result, |t| t.id == id)
+    }
+    async fn import_token(&self, token: &ClientToken) -> Result<(), GatewayError> {
+        self.inner.import_token(token).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logging::DatabaseLogger;
+    use tempfile::tempdir;
+
+    fn mode(spool: &std::path::Path) -> Arc<DegradedMode> {
+        Arc::new(DegradedMode::new(DegradedModeConfig {
+            log_spool_path: spool.to_string_lossy().into_owned(),
+            ..Default::default()
+        }))
+    }
+
+    fn log(path: &str) -> RequestLog {
+        serde_json::from_value(serde_json::json!({
+            "timestamp": "2026-01-01T00:00:00Z",
+            "method": "POST",
+            "path": path,
+            "request_type": "chat_once",
+            "status_code": 200,
+            "response_time_ms": 12,
+            "latency": {},
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn stays_degraded_until_queued_logs_are_replayed() {
+        let dir = tempdir().unwrap();
+        let logger = DatabaseLogger::new(dir.path().join("gw.db").to_str().unwrap())
+            .await
+            .unwrap();
+        let spool = dir.path().join("spool/logs.jsonl");
+        let mode = mode(&spool);
+        mode.mark_failure("log_request", &"database is locked");
+        mode.queue().enqueue("r1", &log("/v1/a")).await.unwrap();
+        mode.queue().enqueue("r2", &log("/v1/b")).await.unwrap();
+        mode.mark_healthy_if_idle();
+        let status = mode.status();
+        assert!(status.degraded);
+        assert_eq!(status.log_queue.depth, 2);
+
+        assert_eq!(mode.replay(&logger).await.unwrap().len(), 2);
+        assert!(!spool.exists());
+        let status = mode.status();
+        assert!(!status.degraded);
+        assert_eq!(status.log_queue.replayed_total, 2);
+        assert_eq!(logger.get_recent_logs(10).await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn token_snapshot_serves_reads_during_outage_until_invalidated() {
+        let dir = tempdir().unwrap();
+        let logger = Arc::new(
+            DatabaseLogger::new(dir.path().join("gw.db").to_str().unwrap())
+                .await
+                .unwrap(),
+        );
+        let mode = mode(&dir.path().join("spool.jsonl"));
+        let store = SnapshotTokenStore::new(logger, mode.clone());
+        let created = store
+            .create_token(serde_json::from_value(serde_json::json!({"name": "svc"})).unwrap())
+            .await
+            .unwrap();
+        let value = created.token.clone();
+        let outage = || Err(GatewayError::Db(rusqlite::Error::InvalidQuery));
+
+        // 未读取过的令牌没有快照
+        assert!(
+            store
+                .lookup("get_token", outage(), |t| t.token == value)
+                .is_err()
+        );
+        assert!(store.get_token(&value).await.unwrap().is_some());
+        let hit = store.lookup("get_token", outage(), |t| t.token == value);
+        assert_eq!(hit.unwrap().unwrap().id, created.id);
+        assert!(mode.status().degraded);
+        assert_eq!(mode.status().token_snapshot_hits, 1);
+
+        store.set_enabled_by_id(&created.id, false).await.unwrap();
+        assert!(
+            store
+                .lookup("get_token", outage(), |t| t.token == value)
+                .is_err()
+        );
+    }
+}
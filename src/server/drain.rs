@@ -0,0 +1,305 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use axum::body::Body;
+use axum::extract::Request;
+use axum::http::header::CONTENT_TYPE;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use chrono::{DateTime, Utc};
+use futures_util::StreamExt;
+use serde::Serialize;
+use tokio::sync::watch;
+
+use crate::error::GatewayError;
+
+/// 排空期间每隔多久打印一次进度
+const PROGRESS_LOG_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct DrainStatus {
+    pub draining: bool,
+    pub started_at: Option<DateTime<Utc>>,
+    pub elapsed_ms: Option<u64>,
+    pub timeout_secs: Option<u64>,
+    /// 进行中的普通请求（不含流式响应）
+    pub in_flight_requests: u64,
+    /// 仍在输出的流式响应
+    pub in_flight_streams: u64,
+    /// 已超时或被管理员强制结束排空
+    pub force_completed: bool,
+}
+
+/// 排空结束的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DrainOutcome {
+    Idle,
+    TimedOut,
+    Forced,
+}
+
+struct DrainStart {
+    at: DateTime<Utc>,
+    instant: Instant,
+    timeout: Duration,
+}
+
+/// 进行中请求/流的计数器；收到关闭信号后进入排空阶段，拒绝新请求，
+/// 等待计数归零、超时或管理员强制结束
+pub struct DrainTracker {
+    requests: AtomicU64,
+    streams: AtomicU64,
+    started: Mutex<Option<DrainStart>>,
+    abandoned: watch::Sender<bool>,
+}
+
+impl Default for DrainTracker {
+    fn default() -> Self {
+        Self {
+            requests: AtomicU64::new(0),
+            streams: AtomicU64::new(0),
+            started: Mutex::new(None),
+            abandoned: watch::channel(false).0,
+        }
+    }
+}
+
+/// 计入进行中的请求，drop 时自动减计数
+pub struct InFlightGuard {
+    tracker: Arc<DrainTracker>,
+    stream: bool,
+}
+
+impl InFlightGuard {
+    /// 响应为流式时改记到流计数，直到响应体输出完毕
+    fn into_stream(mut self) -> Self {
+        if !self.stream {
+            self.tracker.streams.fetch_add(1, Ordering::SeqCst);
+            self.tracker.requests.fetch_sub(1, Ordering::SeqCst);
+            self.stream = true;
+        }
+        self
+    }
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        let counter = if self.stream {
+            &self.tracker.streams
+        } else {
+            &self.tracker.requests
+        };
+        counter.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+static TRACKER: OnceLock<Arc<DrainTracker>> = OnceLock::new();
+
+/// 进程级排空计数器（中间件、管理接口与 main 共用）
+pub fn drain_tracker() -> Arc<DrainTracker> {
+    TRACKER
+        .get_or_init(|| Arc::new(DrainTracker::default()))
+        .clone()
+}
+
+impl DrainTracker {
+    fn lock(&self) -> std::sync::MutexGuard<'_, Option<DrainStart>> {
+        self.started.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn enter(self: &Arc<Self>) -> InFlightGuard {
+        self.requests.fetch_add(1, Ordering::SeqCst);
+        InFlightGuard {
+            tracker: self.clone(),
+            stream: false,
+        }
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.lock().is_some()
+    }
+
+    fn in_flight(&self) -> u64 {
+        self.requests.load(Ordering::SeqCst) + self.streams.load(Ordering::SeqCst)
+    }
+
+    pub fn status(&self) -> DrainStatus {
+        let started = self.lock();
+        DrainStatus {
+            draining: started.is_some(),
+            started_at: started.as_ref().map(|s| s.at),
+            elapsed_ms: started
+                .as_ref()
+                .map(|s| s.instant.elapsed().as_millis() as u64),
+            timeout_secs: started.as_ref().map(|s| s.timeout.as_secs()),
+            in_flight_requests: self.requests.load(Ordering::SeqCst),
+            in_flight_streams: self.streams.load(Ordering::SeqCst),
+            force_completed: *self.abandoned.borrow(),
+        }
+    }
+
+    /// 强制结束排空：剩余请求随进程退出被中断；未处于排空阶段时返回 false
+    pub fn force(&self) -> bool {
+        if !self.is_draining() {
+            return false;
+        }
+        self.abandoned.send_replace(true);
+        true
+    }
+
+    /// 排空被强制结束或超时时完成（此时不应再等待剩余连接）
+    pub async fn abandoned(&self) {
+        let _ = self
+            .abandoned
+            .subscribe()
+            .wait_for(|abandoned| *abandoned)
+            .await;
+    }
+
+    /// 进入排空阶段并等待进行中的请求结束，定期打印剩余数量
+    pub async fn drain(&self, timeout: Duration) -> DrainOutcome {
+        let started = Instant::now();
+        self.lock().get_or_insert_with(|| DrainStart {
+            at: Utc::now(),
+            instant: started,
+            timeout,
+        });
+        let mut last_log = started;
+        let outcome = loop {
+            if *self.abandoned.borrow() {
+                break DrainOutcome::Forced;
+            }
+            if self.in_flight() == 0 {
+                break DrainOutcome::Idle;
+            }
+            if started.elapsed() >= timeout {
+                break DrainOutcome::TimedOut;
+            }
+            if last_log.elapsed() >= PROGRESS_LOG_INTERVAL {
+                last_log = Instant::now();
+                tracing::info!(
+                    "Draining: {} requests and {} streams in flight ({}s elapsed)",
+                    self.requests.load(Ordering::SeqCst),
+                    self.streams.load(Ordering::SeqCst),
+                    started.elapsed().as_secs()
+                );
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        };
+        match outcome {
+            DrainOutcome::Idle => {
+                tracing::info!("Drain completed in {}ms", started.elapsed().as_millis())
+            }
+            DrainOutcome::TimedOut | DrainOutcome::Forced => {
+                self.abandoned.send_replace(true);
+                tracing::warn!(
+                    "Drain {} after {}ms with {} requests and {} streams still in flight",
+                    if outcome == DrainOutcome::Forced {
+                        "force-completed"
+                    } else {
+                        "timed out"
+                    },
+                    started.elapsed().as_millis(),
+                    self.requests.load(Ordering::SeqCst),
+                    self.streams.load(Ordering::SeqCst)
+                );
+            }
+        }
+        outcome
+    }
+}
+
+/// 排空状态接口本身不计数，且排空期间仍可访问
+fn is_drain_endpoint(path: &str) -> bool {
+    let path = path.strip_prefix("/api").unwrap_or(path);
+    path == "/admin/drain-status" || path.starts_with("/admin/drain-status/")
+}
+
+/// 中间件：统计进行中的请求；流式响应的计数持续到响应体输出完毕。排空阶段拒绝新请求（503）
+pub async fn track_in_flight(req: Request, next: Next) -> Response {
+    if is_drain_endpoint(req.uri().path()) {
+        return next.run(req).await;
+    }
+    let tracker = drain_tracker();
+    if tracker.is_draining() {
+        return GatewayError::ShuttingDown("网关正在关闭，暂不接受新请求".into()).into_response();
+    }
+    let guard = tracker.enter();
+    let response = next.run(req).await;
+    let is_stream = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|ct| ct.starts_with("text/event-stream"));
+    if !is_stream {
+        return response;
+    }
+    let guard = guard.into_stream();
+    let (parts, body) = response.into_parts();
+    let body = Body::from_stream(body.into_data_stream().map(move |chunk| {
+        let _ = &guard;
+        chunk
+    }));
+    Response::from_parts(parts, body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn counts_requests_and_streams_until_idle() {
+        let tracker = Arc::new(DrainTracker::default());
+        assert!(!tracker.force());
+
+        let request = tracker.enter();
+        let stream = tracker.enter().into_stream();
+        let status = tracker.status();
+        assert!(!status.draining);
+        assert_eq!(
+            (status.in_flight_requests, status.in_flight_streams),
+            (1, 1)
+        );
+
+        let drainer = tracker.clone();
+        let drain = tokio::spawn(async move { drainer.drain(Duration::from_secs(5)).await });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        let status = tracker.status();
+        assert!(status.draining && status.started_at.is_some());
+        assert_eq!(status.timeout_secs, Some(5));
+
+        drop(request);
+        drop(stream);
+        assert_eq!(drain.await.unwrap(), DrainOutcome::Idle);
+        assert!(!tracker.status().force_completed);
+    }
+
+    #[tokio::test]
+    async fn force_and_timeout_abandon_remaining_requests() {
+        let tracker = Arc::new(DrainTracker::default());
+        let _stuck = tracker.enter();
+        assert_eq!(
+            tracker.drain(Duration::from_millis(60)).await,
+            DrainOutcome::TimedOut
+        );
+        assert!(tracker.status().force_completed);
+        tracker.abandoned().await;
+
+        let tracker = Arc::new(DrainTracker::default());
+        let _stuck = tracker.enter();
+        let drainer = tracker.clone();
+        let drain = tokio::spawn(async move { drainer.drain(Duration::from_secs(30)).await });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(tracker.force());
+        assert_eq!(drain.await.unwrap(), DrainOutcome::Forced);
+        assert_eq!(tracker.status().in_flight_requests, 1);
+    }
+
+    #[test]
+    fn drain_endpoints_are_exempt() {
+        assert!(is_drain_endpoint("/admin/drain-status"));
+        assert!(is_drain_endpoint("/api/admin/drain-status/force"));
+        assert!(!is_drain_endpoint("/v1/chat/completions"));
+    }
+}
@@ -0,0 +1,1052 @@
+//! SQLite → PostgreSQL 零停机迁移的双写模式：
+//! 令牌、供应商（含密钥与重定向）、组织、收藏与运行期设置的写入同时落到两个后端，
+//! 读取只走主后端；后台任务定期比对两端数据，管理员确认一致后可在运行期原子切换主后端。
+//! 用户、余额、会话与请求日志不双写，仍使用启动时的主后端，切换后需重启完成整体切换；
+//! 这些数据在旧主后端上存在未同步的写入（用户/余额有差异、请求日志水位领先）时拒绝切换，
+//! 需先回填到目标后端。会话只在状态中报告，不阻止切换（重启后需重新登录）。
+
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::admin::{
+    ClientToken, CreateTokenPayload, TokenAutoDisablePrefs, TokenNotificationRecord,
+    TokenRotationPrefs, TokenStore, UpdateTokenPayload,
+};
+use crate::config::settings::{KeyLogStrategy, MigrationBackend, Provider};
+use crate::error::GatewayError;
+use crate::routing::{KeyRotationStrategy, OpenAIAccountHeaders, ProviderKeyEntry};
+use crate::server::admin_notifications;
+use crate::server::pagination::PageRequest;
+use crate::server::storage_traits::{
+    BoxFuture, FavoriteKind, FavoritesStore, LoginStore, OrganizationStore,
+    ProviderKeyEntryWithCreatedAt, ProviderStore, RequestLogStore, SettingsStore,
+};
+use crate::users::UserStore;
+
+/// 差异列表最多返回的条目数
+const MAX_DIFF_SAMPLES: usize = 20;
+/// 不双写、始终使用启动时主后端的数据
+pub const NOT_DUAL_WRITTEN: &[&str] = &["users", "balances", "web_sessions", "request_logs"];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MirrorFailure {
+    pub op: &'static str,
+    pub error: String,
+    pub at: DateTime<Utc>,
+}
+
+/// 双写的运行期状态（主后端、镜像写失败统计、最近一次一致性检查）
+#[derive(Debug, Default)]
+pub struct MigrationState {
+    postgres_primary: AtomicBool,
+    mirror_failures: AtomicU64,
+    last_mirror_failure: Mutex<Option<MirrorFailure>>,
+    last_check: Mutex<Option<ConsistencyReport>>,
+    switched_at: Mutex<Option<DateTime<Utc>>>,
+}
+
+impl MigrationState {
+    pub fn new(primary: MigrationBackend) -> Self {
+        Self {
+            postgres_primary: AtomicBool::new(primary == MigrationBackend::Postgres),
+            ..Default::default()
+        }
+    }
+
+    pub fn primary(&self) -> MigrationBackend {
+        if self.postgres_primary.load(Ordering::SeqCst) {
+            MigrationBackend::Postgres
+        } else {
+            MigrationBackend::Sqlite
+        }
+    }
+
+    /// 镜像写失败不影响请求结果，只计数并记录最近一次错误（由一致性检查暴露差异）
+    fn mirrored<T, E: Display>(&self, op: &'static str, result: Result<T, E>) {
+        if let Err(e) = result {
+            self.mirror_failures.fetch_add(1, Ordering::Relaxed);
+            tracing::warn!(op, error = %e, "dual-write mirror failed");
+            *self
+                .last_mirror_failure
+                .lock()
+                .unwrap_or_else(|e| e.into_inner()) = Some(MirrorFailure {
+                op,
+                error: e.to_string(),
+                at: Utc::now(),
+            });
+        }
+    }
+
+    pub fn status(&self) -> MigrationStatus {
+        let switched_at = *self.switched_at.lock().unwrap_or_else(|e| e.into_inner());
+        MigrationStatus {
+            primary: self.primary(),
+            switched_at,
+            mirror_failures: self.mirror_failures.load(Ordering::Relaxed),
+            last_mirror_failure: self
+                .last_mirror_failure
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .clone(),
+            last_check: self
+                .last_check
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .clone(),
+            not_dual_written: NOT_DUAL_WRITTEN,
+            restart_required: switched_at.is_some(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MigrationStatus {
+    pub primary: MigrationBackend,
+    pub switched_at: Option<DateTime<Utc>>,
+    pub mirror_failures: u64,
+    pub last_mirror_failure: Option<MirrorFailure>,
+    pub last_check: Option<ConsistencyReport>,
+    /// 不双写的数据（运行期切换不影响，仍读写启动时的主后端）
+    pub not_dual_written: &'static [&'static str],
+    /// 运行期已切换主后端：需修改配置并重启，不双写的数据才会随之切换
+    pub restart_required: bool,
+}
+
+/// 同一组存储在两个后端上的实现；读取走主后端，写入先主后镜像
+pub struct Dual<T: ?Sized> {
+    state: Arc<MigrationState>,
+    sqlite: Arc<T>,
+    postgres: Arc<T>,
+}
+
+impl<T: ?Sized> Dual<T> {
+    pub fn new(state: Arc<MigrationState>, sqlite: Arc<T>, postgres: Arc<T>) -> Self {
+        Self {
+            state,
+            sqlite,
+            postgres,
+        }
+    }
+
+    /// (主, 镜像)；同一次写入只读取一次主后端标记，避免切换瞬间两次写到同一端
+    fn pair(&self) -> (&T, &T) {
+        match self.state.primary() {
+            MigrationBackend::Postgres => (&*self.postgres, &*self.sqlite),
+            MigrationBackend::Sqlite => (&*self.sqlite, &*self.postgres),
+        }
+    }
+}
+
+macro_rules! dual_read {
+    ($self:ident . $method:ident ( $($arg:expr),* )) => {
+        async move { $self.pair().0.$method($($arg),*).await }
+    };
+}
+
+macro_rules! dual_write {
+    ($self:ident . $method:ident ( $($arg:expr),* )) => {
+        async move {
+            let (primary, secondary) = $self.pair();
+            let out = primary.$method($($arg),*).await;
+            if out.is_ok() {
+                $self
+                    .state
+                    .mirrored(stringify!($method), secondary.$method($($arg),*).await);
+            }
+            out
+        }
+    };
+}
+
+type DualTokens = Dual<dyn TokenStore + Send + Sync>;
+
+#[async_trait]
+impl TokenStore for DualTokens {
+    async fn create_token(&self, payload: CreateTokenPayload) -> Result<ClientToken, GatewayError> {
+        let (primary, secondary) = self.pair();
+        let created = primary.create_token(payload).await?;
+        // 令牌值由后端随机生成，镜像端按原样导入以保持 id/token 一致
+        self.state
+            .mirrored("create_token", secondary.import_token(&created).await);
+        Ok(created)
+    }
+    async fn update_token(
+        &self,
+        token: &str,
+        payload: UpdateTokenPayload,
+    ) -> Result<Option<ClientToken>, GatewayError> {
+        dual_write!(self.update_token(token, payload.clone())).await
+    }
+    async fn set_enabled(&self, token: &str, enabled: bool) -> Result<bool, GatewayError> {
+        dual_write!(self.set_enabled(token, enabled)).await
+    }
+    async fn set_enabled_for_user(
+        &self,
+        user_id: &str,
+        enabled: bool,
+    ) -> Result<u64, GatewayError> {
+        dual_write!(self.set_enabled_for_user(user_id, enabled)).await
+    }
+    async fn get_token(&self, token: &str) -> Result<Option<ClientToken>, GatewayError> {
+        dual_read!(self.get_token(token)).await
+    }
+    async fn get_token_by_id(&self, id: &str) -> Result<Option<ClientToken>, GatewayError> {
+        dual_read!(self.get_token_by_id(id)).await
+    }
+    async fn get_token_by_id_scoped(
+        &self,
+        user_id: &str,
+        id: &str,
+    ) -> Result<Option<ClientToken>, GatewayError> {
+        dual_read!(self.get_token_by_id_scoped(user_id, id)).await
+    }
+    async fn list_tokens(&self) -> Result<Vec<ClientToken>, GatewayError> {
+        dual_read!(self.list_tokens()).await
+    }
+    async fn list_tokens_by_user(&self, user_id: &str) -> Result<Vec<ClientToken>, GatewayError> {
+        dual_read!(self.list_tokens_by_user(user_id)).await
+    }
+    async fn list_tokens_by_organization(
+        &self,
+        organization_id: &str,
+    ) -> Result<Vec<ClientToken>, GatewayError> {
+        dual_read!(self.list_tokens_by_organization(organization_id)).await
+    }
+    async fn list_tokens_page(
+        &self,
+        organization_id: Option<&str>,
+        page: &PageRequest,
+    ) -> Result<(Vec<ClientToken>, u64), GatewayError> {
+        dual_read!(self.list_tokens_page(organization_id, page)).await
+    }
+    async fn list_child_tokens(&self, parent_id: &str) -> Result<Vec<ClientToken>, GatewayError> {
+        dual_read!(self.list_child_tokens(parent_id)).await
+    }
+    async fn add_amount_spent(&self, token: &str, delta: f64) -> Result<(), GatewayError> {
+        dual_write!(self.add_amount_spent(token, delta)).await
+    }
+    async fn add_usage_spent(
+        &self,
+        token: &str,
+        prompt: i64,
+        completion: i64,
+        total: i64,
+    ) -> Result<(), GatewayError> {
+        dual_write!(self.add_usage_spent(token, prompt, completion, total)).await
+    }
+    async fn delete_token(&self, token: &str) -> Result<bool, GatewayError> {
+        dual_write!(self.delete_token(token)).await
+    }
+    async fn delete_token_by_id(&self, id: &str) -> Result<bool, GatewayError> {
+        dual_write!(self.delete_token_by_id(id)).await
+    }
+    async fn update_token_by_id(
+        &self,
+        id: &str,
+        payload: UpdateTokenPayload,
+    ) -> Result<Option<ClientToken>, GatewayError> {
+        dual_write!(self.update_token_by_id(id, payload.clone())).await
+    }
+    async fn set_enabled_by_id(&self, id: &str, enabled: bool) -> Result<bool, GatewayError> {
+        dual_write!(self.set_enabled_by_id(id, enabled)).await
+    }
+    async fn get_notifications_opt_out(&self, id: &str) -> Result<bool, GatewayError> {
+        dual_read!(self.get_notifications_opt_out(id)).await
+    }
+    async fn set_notifications_opt_out(&self, id: &str, opt_out: bool) -> Result<(), GatewayError> {
+        dual_write!(self.set_notifications_opt_out(id, opt_out)).await
+    }
+    async fn record_token_notification(
+        &self,
+        record: &TokenNotificationRecord,
+    ) -> Result<(), GatewayError> {
+        dual_write!(self.record_token_notification(record)).await
+    }
+    async fn token_notification_sent(
+        &self,
+        id: &str,
+        kind: &str,
+        reference: &str,
+    ) -> Result<bool, GatewayError> {
+        dual_read!(self.token_notification_sent(id, kind, reference)).await
+    }
+    async fn list_token_notifications(
+        &self,
+        id: &str,
+        limit: i64,
+    ) -> Result<Vec<TokenNotificationRecord>, GatewayError> {
+        dual_read!(self.list_token_notifications(id, limit)).await
+    }
+    async fn get_auto_disable_prefs(
+        &self,
+        id: &str,
+    ) -> Result<TokenAutoDisablePrefs, GatewayError> {
+        dual_read!(self.get_auto_disable_prefs(id)).await
+    }
+    async fn set_auto_disable_exempt(&self, id: &str, exempt: bool) -> Result<(), GatewayError> {
+        dual_write!(self.set_auto_disable_exempt(id, exempt)).await
+    }
+    async fn mark_auto_disabled(&self, id: &str, at: DateTime<Utc>) -> Result<(), GatewayError> {
+        dual_write!(self.mark_auto_disabled(id, at)).await
+    }
+    async fn get_rotation_prefs(&self, id: &str) -> Result<TokenRotationPrefs, GatewayError> {
+        dual_read!(self.get_rotation_prefs(id)).await
+    }
+    async fn set_rotation_exempt(&self, id: &str, exempt: bool) -> Result<(), GatewayError> {
+        dual_write!(self.set_rotation_exempt(id, exempt)).await
+    }
+    async fn rotate_token(
+        &self,
+        id: &str,
+        new_token: &str,
+        at: DateTime<Utc>,
+    ) -> Result<bool, GatewayError> {
+        dual_write!(self.rotate_token(id, new_token, at)).await
+    }
+    async fn import_token(&self, token: &ClientToken) -> Result<(), GatewayError> {
+        dual_write!(self.import_token(token)).await
+    }
+}
+
+impl ProviderStore for Dual<dyn ProviderStore + Send + Sync> {
+    fn insert_provider<'a>(
+        &'a self,
+        provider: &'a Provider,
+    ) -> BoxFuture<'a, rusqlite::Result<bool>> {
+        Box::pin(dual_write!(self.insert_provider(provider)))
+    }
+    fn upsert_provider<'a>(
+        &'a self,
+        provider: &'a Provider,
+    ) -> BoxFuture<'a, rusqlite::Result<()>> {
+        Box::pin(dual_write!(self.upsert_provider(provider)))
+    }
+    fn provider_exists<'a>(&'a self, name: &'a str) -> BoxFuture<'a, rusqlite::Result<bool>> {
+        Box::pin(dual_read!(self.provider_exists(name)))
+    }
+    fn get_provider<'a>(
+        &'a self,
+        name: &'a str,
+    ) -> BoxFuture<'a, rusqlite::Result<Option<Provider>>> {
+        Box::pin(dual_read!(self.get_provider(name)))
+    }
+    fn list_providers<'a>(&'a self) -> BoxFuture<'a, rusqlite::Result<Vec<Provider>>> {
+        Box::pin(dual_read!(self.list_providers()))
+    }
+    fn list_providers_page<'a>(
+        &'a self,
+        page: &'a PageRequest,
+    ) -> BoxFuture<'a, rusqlite::Result<(Vec<Provider>, u64)>> {
+        Box::pin(dual_read!(self.list_providers_page(page)))
+    }
+    fn delete_provider<'a>(&'a self, name: &'a str) -> BoxFuture<'a, rusqlite::Result<bool>> {
+        Box::pin(dual_write!(self.delete_provider(name)))
+    }
+    fn set_provider_enabled<'a>(
+        &'a self,
+        provider: &'a str,
+        enabled: bool,
+    ) -> BoxFuture<'a, rusqlite::Result<bool>> {
+        Box::pin(dual_write!(self.set_provider_enabled(provider, enabled)))
+    }
+    fn list_provider_collections<'a>(&'a self) -> BoxFuture<'a, rusqlite::Result<Vec<String>>> {
+        Box::pin(dual_read!(self.list_provider_collections()))
+    }
+    fn create_provider_collection<'a>(
+        &'a self,
+        name: &'a str,
+    ) -> BoxFuture<'a, rusqlite::Result<()>> {
+        Box::pin(dual_write!(self.create_provider_collection(name)))
+    }
+    fn get_provider_key_rotation_strategy<'a>(
+        &'a self,
+        provider: &'a str,
+    ) -> BoxFuture<'a, rusqlite::Result<KeyRotationStrategy>> {
+        Box::pin(dual_read!(
+            self.get_provider_key_rotation_strategy(provider)
+        ))
+    }
+    fn set_provider_key_rotation_strategy<'a>(
+        &'a self,
+        provider: &'a str,
+        strategy: KeyRotationStrategy,
+    ) -> BoxFuture<'a, rusqlite::Result<bool>> {
+        Box::pin(dual_write!(
+            self.set_provider_key_rotation_strategy(provider, strategy)
+        ))
+    }
+    fn get_provider_keys<'a>(
+        &'a self,
+        provider: &'a str,
+        strategy: &'a Option<KeyLogStrategy>,
+    ) -> BoxFuture<'a, rusqlite::Result<Vec<String>>> {
+        Box::pin(dual_read!(self.get_provider_keys(provider, strategy)))
+    }
+    fn add_provider_key<'a>(
+        &'a self,
+        provider: &'a str,
+        key: &'a str,
+        strategy: &'a Option<KeyLogStrategy>,
+    ) -> BoxFuture<'a, rusqlite::Result<()>> {
+        Box::pin(dual_write!(self.add_provider_key(provider, key, strategy)))
+    }
+    fn remove_provider_key<'a>(
+        &'a self,
+        provider: &'a str,
+        key: &'a str,
+        strategy: &'a Option<KeyLogStrategy>,
+    ) -> BoxFuture<'a, rusqlite::Result<bool>> {
+        Box::pin(dual_write!(
+            self.remove_provider_key(provider, key, strategy)
+        ))
+    }
+    fn list_provider_keys_raw<'a>(
+        &'a self,
+        provider: &'a str,
+        strategy: &'a Option<KeyLogStrategy>,
+    ) -> BoxFuture<'a, rusqlite::Result<Vec<ProviderKeyEntry>>> {
+        Box::pin(dual_read!(self.list_provider_keys_raw(provider, strategy)))
+    }
+    fn list_provider_keys_raw_with_created_at<'a>(
+        &'a self,
+        provider: &'a str,
+        strategy: &'a Option<KeyLogStrategy>,
+    ) -> BoxFuture<'a, rusqlite::Result<Vec<ProviderKeyEntryWithCreatedAt>>> {
+        Box::pin(dual_read!(
+            self.list_provider_keys_raw_with_created_at(provider, strategy)
+        ))
+    }
+    fn set_provider_key_weight<'a>(
+        &'a self,
+        provider: &'a str,
+        key: &'a str,
+        weight: u32,
+        strategy: &'a Option<KeyLogStrategy>,
+    ) -> BoxFuture<'a, rusqlite::Result<bool>> {
+        Box::pin(dual_write!(
+            self.set_provider_key_weight(provider, key, weight, strategy)
+        ))
+    }
+    fn set_provider_key_limits<'a>(
+        &'a self,
+        provider: &'a str,
+        key: &'a str,
+        spend_cap: Option<f64>,
+        rpm_limit: Option<u32>,
+        tpm_limit: Option<u32>,
+        strategy: &'a Option<KeyLogStrategy>,
+    ) -> BoxFuture<'a, rusqlite::Result<bool>> {
+        Box::pin(dual_write!(self.set_provider_key_limits(
+            provider, key, spend_cap, rpm_limit, tpm_limit, strategy
+        )))
+    }
+    fn set_provider_key_openai_headers<'a>(
+        &'a self,
+        provider: &'a str,
+        key: &'a str,
+        headers: &'a OpenAIAccountHeaders,
+        strategy: &'a Option<KeyLogStrategy>,
+    ) -> BoxFuture<'a, rusqlite::Result<bool>> {
+        Box::pin(dual_write!(self.set_provider_key_openai_headers(
+            provider, key, headers, strategy
+        )))
+    }
+    fn set_provider_key_labels<'a>(
+        &'a self,
+        provider: &'a str,
+        key: &'a str,
+        labels: &'a BTreeMap<String, String>,
+        strategy: &'a Option<KeyLogStrategy>,
+    ) -> BoxFuture<'a, rusqlite::Result<bool>> {
+        Box::pin(dual_write!(
+            self.set_provider_key_labels(provider, key, labels, strategy)
+        ))
+    }
+    fn set_provider_key_active<'a>(
+        &'a self,
+        provider: &'a str,
+        key: &'a str,
+        active: bool,
+        strategy: &'a Option<KeyLogStrategy>,
+    ) -> BoxFuture<'a, rusqlite::Result<bool>> {
+        Box::pin(dual_write!(
+            self.set_provider_key_active(provider, key, active, strategy)
+        ))
+    }
+    fn list_model_redirects<'a>(
+        &'a self,
+        provider: &'a str,
+    ) -> BoxFuture<'a, rusqlite::Result<Vec<(String, String)>>> {
+        Box::pin(dual_read!(self.list_model_redirects(provider)))
+    }
+    fn replace_model_redirects<'a>(
+        &'a self,
+        provider: &'a str,
+        redirects: &'a [(String, String)],
+        now: DateTime<Utc>,
+    ) -> BoxFuture<'a, rusqlite::Result<()>> {
+        Box::pin(dual_write!(
+            self.replace_model_redirects(provider, redirects, now)
+        ))
+    }
+    fn delete_model_redirect<'a>(
+        &'a self,
+        provider: &'a str,
+        source_model: &'a str,
+    ) -> BoxFuture<'a, rusqlite::Result<bool>> {
+        Box::pin(dual_write!(
+            self.delete_model_redirect(provider, source_model)
+        ))
+    }
+}
+
+impl OrganizationStore for Dual<dyn OrganizationStore + Send + Sync> {
+    fn list_organizations<'a>(&'a self) -> BoxFuture<'a, rusqlite::Result<Vec<String>>> {
+        Box::pin(dual_read!(self.list_organizations()))
+    }
+    fn create_organization<'a>(
+        &'a self,
+        organization_id: &'a str,
+    ) -> BoxFuture<'a, rusqlite::Result<()>> {
+        Box::pin(dual_write!(self.create_organization(organization_id)))
+    }
+    fn set_organization_admin<'a>(
+        &'a self,
+        organization_id: &'a str,
+        user_id: &'a str,
+    ) -> BoxFuture<'a, rusqlite::Result<()>> {
+        Box::pin(dual_write!(
+            self.set_organization_admin(organization_id, user_id)
+        ))
+    }
+    fn remove_organization_admin<'a>(
+        &'a self,
+        organization_id: &'a str,
+        user_id: &'a str,
+    ) -> BoxFuture<'a, rusqlite::Result<bool>> {
+        Box::pin(dual_write!(
+            self.remove_organization_admin(organization_id, user_id)
+        ))
+    }
+    fn list_organization_admins<'a>(
+        &'a self,
+        organization_id: &'a str,
+    ) -> BoxFuture<'a, rusqlite::Result<Vec<String>>> {
+        Box::pin(dual_read!(self.list_organization_admins(organization_id)))
+    }
+    fn get_admin_organization<'a>(
+        &'a self,
+        user_id: &'a str,
+    ) -> BoxFuture<'a, rusqlite::Result<Option<String>>> {
+        Box::pin(dual_read!(self.get_admin_organization(user_id)))
+    }
+}
+
+impl FavoritesStore for Dual<dyn FavoritesStore + Send + Sync> {
+    fn set_favorite<'a>(
+        &'a self,
+        kind: FavoriteKind,
+        target: &'a str,
+        favorite: bool,
+    ) -> BoxFuture<'a, rusqlite::Result<()>> {
+        Box::pin(dual_write!(self.set_favorite(kind, target, favorite)))
+    }
+    fn is_favorite<'a>(
+        &'a self,
+        kind: FavoriteKind,
+        target: &'a str,
+    ) -> BoxFuture<'a, rusqlite::Result<bool>> {
+        Box::pin(dual_read!(self.is_favorite(kind, target)))
+    }
+    fn list_favorites<'a>(
+        &'a self,
+        kind: FavoriteKind,
+    ) -> BoxFuture<'a, rusqlite::Result<Vec<String>>> {
+        Box::pin(dual_read!(self.list_favorites(kind)))
+    }
+}
+
+impl SettingsStore for Dual<dyn SettingsStore + Send + Sync> {
+    fn get_setting<'a>(&'a self, key: &'a str) -> BoxFuture<'a, rusqlite::Result<Option<String>>> {
+        Box::pin(dual_read!(self.get_setting(key)))
+    }
+    fn set_setting<'a>(
+        &'a self,
+        key: &'a str,
+        value: &'a str,
+    ) -> BoxFuture<'a, rusqlite::Result<()>> {
+        Box::pin(dual_write!(self.set_setting(key, value)))
+    }
+}
+
+/// 单个后端上参与一致性检查的存储
+#[derive(Clone)]
+pub struct BackendHandles {
+    pub tokens: Arc<dyn TokenStore + Send + Sync>,
+    pub providers: Arc<dyn ProviderStore + Send + Sync>,
+    pub organizations: Arc<dyn OrganizationStore + Send + Sync>,
+    pub users: Arc<dyn UserStore + Send + Sync>,
+    pub login: Arc<dyn LoginStore + Send + Sync>,
+    pub logs: Arc<dyn RequestLogStore + Send + Sync>,
+}
+
+/// 一类数据在两端的差异（以主键标识）
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SectionDiff {
+    /// 该类数据是否双写；未双写的数据仅作切换前的参考
+    pub dual_written: bool,
+    pub primary_count: usize,
+    pub secondary_count: usize,
+    pub missing_in_secondary: Vec<String>,
+    pub missing_in_primary: Vec<String>,
+    pub mismatched: Vec<String>,
+    /// 三类差异的总数（样例列表最多各 20 条）
+    pub divergent: usize,
+}
+
+fn diff_section(
+    dual_written: bool,
+    primary: &BTreeMap<String, String>,
+    secondary: &BTreeMap<String, String>,
+) -> SectionDiff {
+    let mut out = SectionDiff {
+        dual_written,
+        primary_count: primary.len(),
+        secondary_count: secondary.len(),
+        ..Default::default()
+    };
+    for (key, value) in primary {
+        match secondary.get(key) {
+            None => out.missing_in_secondary.push(key.clone()),
+            Some(other) if other != value => out.mismatched.push(key.clone()),
+            Some(_) => {}
+        }
+    }
+    for key in secondary.keys() {
+        if !primary.contains_key(key) {
+            out.missing_in_primary.push(key.clone());
+        }
+    }
+    out.divergent =
+        out.missing_in_secondary.len() + out.missing_in_primary.len() + out.mismatched.len();
+    for list in [
+        &mut out.missing_in_secondary,
+        &mut out.missing_in_primary,
+        &mut out.mismatched,
+    ] {
+        list.truncate(MAX_DIFF_SAMPLES);
+    }
+    out
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConsistencyReport {
+    pub checked_at: DateTime<Utc>,
+    pub primary: MigrationBackend,
+    /// 所有双写数据两端一致
+    pub consistent: bool,
+    /// 不双写的用户（含余额）与请求日志在目标后端上已补齐；否则切换会丢失这些写入
+    pub unmirrored_synced: bool,
+    pub tokens: SectionDiff,
+    pub providers: SectionDiff,
+    pub organizations: SectionDiff,
+    pub users: SectionDiff,
+    /// 仅供参考：会话不阻止切换，重启后未同步的会话需重新登录
+    pub web_sessions: SectionDiff,
+    pub request_logs: LogWatermark,
+}
+
+/// 请求日志只比较两端的最大 ID（日志只追加，水位相同即视为已补齐）
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LogWatermark {
+    pub primary_max_id: i64,
+    pub secondary_max_id: i64,
+}
+
+impl LogWatermark {
+    fn synced(&self) -> bool {
+        self.secondary_max_id >= self.primary_max_id
+    }
+}
+
+fn fingerprint<T: Serialize>(value: &T) -> String {
+    serde_json::to_string(value).unwrap_or_default()
+}
+
+/// 金额按 1e-9 取整，避免浮点累加的末位误差被当作差异
+fn token_fingerprint(t: &ClientToken) -> String {
+    let mut t = t.clone();
+    t.amount_spent = (t.amount_spent * 1e9).round() / 1e9;
+    fingerprint(&t)
+}
+
+struct Snapshot {
+    tokens: BTreeMap<String, String>,
+    providers: BTreeMap<String, String>,
+    organizations: BTreeMap<String, String>,
+    users: BTreeMap<String, String>,
+    web_sessions: BTreeMap<String, String>,
+    max_request_log_id: i64,
+}
+
+async fn snapshot(
+    h: &BackendHandles,
+    strategy: &Option<KeyLogStrategy>,
+) -> Result<Snapshot, GatewayError> {
+    let tokens = h
+        .tokens
+        .list_tokens()
+        .await?
+        .iter()
+        .map(|t| (t.id.clone(), token_fingerprint(t)))
+        .collect();
+    let mut providers = BTreeMap::new();
+    for mut p in h.providers.list_providers_with_keys(strategy).await? {
+        p.api_keys.sort();
+        let redirects = h.providers.list_model_redirects(&p.name).await?;
+        providers.insert(p.name.clone(), fingerprint(&(&p, redirects)));
+    }
+    let organizations = h
+        .organizations
+        .list_organizations()
+        .await?
+        .into_iter()
+        .map(|o| (o, String::new()))
+        .collect();
+    let users = h
+        .users
+        .list_users()
+        .await?
+        .iter()
+        .map(|u| (u.id.clone(), fingerprint(u)))
+        .collect();
+    // last_seen_at 随请求刷新，不参与比较
+    let web_sessions = h
+        .login
+        .list_web_sessions()
+        .await?
+        .into_iter()
+        .map(|s| {
+            let value = fingerprint(&(&s.user_id, s.expires_at, s.revoked));
+            (s.session_id, value)
+        })
+        .collect();
+    let max_request_log_id = h.logs.max_request_log_id().await?;
+    Ok(Snapshot {
+        tokens,
+        providers,
+        organizations,
+        users,
+        web_sessions,
+        max_request_log_id,
+    })
+}
+
+/// 双写模式的全局句柄（管理接口与后台检查共用）
+pub struct Migration {
+    pub state: Arc<MigrationState>,
+    sqlite: BackendHandles,
+    postgres: BackendHandles,
+    key_strategy: Option<KeyLogStrategy>,
+}
+
+impl Migration {
+    pub fn new(
+        state: Arc<MigrationState>,
+        sqlite: BackendHandles,
+        postgres: BackendHandles,
+        key_strategy: Option<KeyLogStrategy>,
+    ) -> Self {
+        Self {
+            state,
+            sqlite,
+            postgres,
+            key_strategy,
+        }
+    }
+
+    pub fn status(&self) -> MigrationStatus {
+        self.state.status()
+    }
+
+    /// 比对两端数据并记录为最近一次检查结果
+    pub async fn check(&self) -> Result<ConsistencyReport, GatewayError> {
+        let primary_backend = self.state.primary();
+        let (primary, secondary) = match primary_backend {
+            MigrationBackend::Sqlite => (&self.sqlite, &self.postgres),
+            MigrationBackend::Postgres => (&self.postgres, &self.sqlite),
+        };
+        let a = snapshot(primary, &self.key_strategy).await?;
+        let b = snapshot(secondary, &self.key_strategy).await?;
+        let tokens = diff_section(true, &a.tokens, &b.tokens);
+        let providers = diff_section(true, &a.providers, &b.providers);
+        let organizations = diff_section(true, &a.organizations, &b.organizations);
+        let users = diff_section(false, &a.users, &b.users);
+        let web_sessions = diff_section(false, &a.web_sessions, &b.web_sessions);
+        let request_logs = LogWatermark {
+            primary_max_id: a.max_request_log_id,
+            secondary_max_id: b.max_request_log_id,
+        };
+        let report = ConsistencyReport {
+            checked_at: Utc::now(),
+            primary: primary_backend,
+            consistent: tokens.divergent == 0
+                && providers.divergent == 0
+                && organizations.divergent == 0,
+            unmirrored_synced: users.divergent == 0 && request_logs.synced(),
+            tokens,
+            providers,
+            organizations,
+            users,
+            web_sessions,
+            request_logs,
+        };
+        if !report.consistent {
+            tracing::warn!(
+                tokens = report.tokens.divergent,
+                providers = report.providers.divergent,
+                organizations = report.organizations.divergent,
+                "dual-write consistency check found divergence"
+            );
+        }
+        *self
+            .state
+            .last_check
+            .lock()
+            .unwrap_or_else(|e| e.into_inner()) = Some(report.clone());
+        Ok(report)
+    }
+
+    /// 切换主后端：默认先做一次一致性检查，双写数据存在差异、或不双写的用户/余额/请求日志
+    /// 在目标后端上尚未补齐时拒绝（force 跳过检查）
+    pub async fn switch_primary(
+        &self,
+        target: MigrationBackend,
+        force: bool,
+    ) -> Result<MigrationStatus, GatewayError> {
+        if self.state.primary() != target {
+            if !force {
+                let report = self.check().await?;
+                if !report.consistent {
+                    return Err(GatewayError::Conflict(
+                        "backends have diverged; resolve the differences or pass force=true".into(),
+                    ));
+                }
+                if !report.unmirrored_synced {
+                    return Err(GatewayError::Conflict(format!(
+                        "users, balances and request logs are not dual-written and have writes missing from the target backend; backfill them or pass force=true (not dual-written: {})",
+                        NOT_DUAL_WRITTEN.join(", ")
+                    )));
+                }
+            }
+            self.state
+                .postgres_primary
+                .store(target == MigrationBackend::Postgres, Ordering::SeqCst);
+            *self
+                .state
+                .switched_at
+                .lock()
+                .unwrap_or_else(|e| e.into_inner()) = Some(Utc::now());
+            tracing::warn!(primary = ?target, "dual-write primary backend switched");
+        }
+        Ok(self.state.status())
+    }
+}
+
+static MIGRATION: OnceLock<Arc<Migration>> = OnceLock::new();
+
+pub fn install(migration: Arc<Migration>) {
+    let _ = MIGRATION.set(migration);
+}
+
+/// 双写模式未启用时为 None
+pub fn migration() -> Option<Arc<Migration>> {
+    MIGRATION.get().cloned()
+}
+
+/// 定期一致性检查
+pub fn spawn_check_task(
+    tasks: &Arc<crate::server::tasks::TaskRegistry>,
+    migration: Arc<Migration>,
+    log_store: Arc<dyn RequestLogStore + Send + Sync>,
+    interval_secs: u64,
+) {
+    if interval_secs == 0 {
+        return;
+    }
+    tasks.spawn_with("dual_write_consistency_check", |mut ctx| async move {
+        let mut ticker = tokio::time::interval(std::time::Duration::from_secs(interval_secs));
+        // 首次 tick 立即完成；启动时不检查，等待一个完整间隔
+        ticker.tick().await;
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {}
+                _ = ctx.cancelled() => break,
+            }
+            match migration.check().await {
+                Ok(report) if !report.consistent => {
+                    admin_notifications::notify(
+                        log_store.as_ref(),
+                        admin_notifications::NewNotification {
+                            kind: admin_notifications::KIND_MIGRATION_NEEDED,
+                            severity: admin_notifications::Severity::Critical,
+                            title: "Dual-write backends diverged".into(),
+                            message: format!(
+                                "Consistency check found divergence (tokens {}, providers {}, organizations {}); run a backfill before switching the primary backend.",
+                                report.tokens.divergent,
+                                report.providers.divergent,
+                                report.organizations.divergent
+                            ),
+                            reference: Some("dual_write".into()),
+                        },
+                    )
+                    .await;
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    tracing::warn!("Dual-write consistency check failed: {}", e);
+                    ctx.report_error(e);
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logging::DatabaseLogger;
+
+    fn map(entries: &[(&str, &str)]) -> BTreeMap<String, String> {
+        entries
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn diff_reports_missing_and_mismatched_keys() {
+        let primary = map(&[("a", "1"), ("b", "2"), ("c", "3")]);
+        let secondary = map(&[("a", "1"), ("b", "x"), ("d", "4")]);
+        let diff = diff_section(true, &primary, &secondary);
+        assert_eq!(diff.missing_in_secondary, vec!["c"]);
+        assert_eq!(diff.missing_in_primary, vec!["d"]);
+        assert_eq!(diff.mismatched, vec!["b"]);
+        assert_eq!(diff.divergent, 3);
+        assert_eq!(diff_section(true, &primary, &primary).divergent, 0);
+    }
+
+    fn handles(db: &Arc<DatabaseLogger>) -> BackendHandles {
+        BackendHandles {
+            tokens: db.clone(),
+            providers: db.clone(),
+            organizations: db.clone(),
+            users: db.clone(),
+            login: db.clone(),
+            logs: db.clone(),
+        }
+    }
+
+    #[tokio::test]
+    async fn writes_mirror_and_switch_requires_consistency() {
+        // 两个 SQLite 库分别充当两端后端
+        let dir = tempfile::tempdir().unwrap();
+        let a = Arc::new(
+            DatabaseLogger::new(dir.path().join("a.db").to_str().unwrap())
+                .await
+                .unwrap(),
+        );
+        let b = Arc::new(
+            DatabaseLogger::new(dir.path().join("b.db").to_str().unwrap())
+                .await
+                .unwrap(),
+        );
+        let state = Arc::new(MigrationState::new(MigrationBackend::Sqlite));
+        let tokens = DualTokens::new(state.clone(), a.clone(), b.clone());
+        let migration = Migration::new(state.clone(), handles(&a), handles(&b), None);
+
+        let created = tokens
+            .create_token(CreateTokenPayload {
+                name: Some("mirrored".into()),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        tokens.set_enabled(&created.token, false).await.unwrap();
+        let mirrored = b.get_token(&created.token).await.unwrap().unwrap();
+        assert_eq!(mirrored.id, created.id);
+        assert!(!mirrored.enabled);
+        assert!(migration.check().await.unwrap().consistent);
+
+        // 请求日志不双写：主后端领先时拒绝切换，补齐后放行
+        let log = crate::logging::RequestLog {
+            id: None,
+            timestamp: Utc::now(),
+            method: "POST".into(),
+            path: "/v1/chat/completions".into(),
+            request_type: "chat_once".into(),
+            requested_model: None,
+            effective_model: None,
+            model: None,
+            provider: None,
+            api_key: None,
+            client_token: None,
+            user_id: None,
+            amount_spent: None,
+            status_code: 200,
+            response_time_ms: 1,
+            prompt_tokens: None,
+            completion_tokens: None,
+            total_tokens: None,
+            cached_tokens: None,
+            reasoning_tokens: None,
+            error_message: None,
+            latency: Default::default(),
+            profile: Default::default(),
+        };
+        RequestLogStore::log_request(a.as_ref(), log.clone())
+            .await
+            .unwrap();
+        let report = migration.check().await.unwrap();
+        assert!(report.consistent);
+        assert!(!report.unmirrored_synced);
+        assert_eq!(report.request_logs.primary_max_id, 1);
+        assert!(matches!(
+            migration
+                .switch_primary(MigrationBackend::Postgres, false)
+                .await,
+            Err(GatewayError::Conflict(_))
+        ));
+        RequestLogStore::log_request(b.as_ref(), log).await.unwrap();
+        assert!(migration.check().await.unwrap().unmirrored_synced);
+        assert!(!state.status().restart_required);
+
+        // 绕过双写直接改一端，切换应被拒绝
+        a.set_enabled(&created.token, true).await.unwrap();
+        assert!(matches!(
+            migration
+                .switch_primary(MigrationBackend::Postgres, false)
+                .await,
+            Err(GatewayError::Conflict(_))
+        ));
+        assert_eq!(state.primary(), MigrationBackend::Sqlite);
+
+        let status = migration
+            .switch_primary(MigrationBackend::Postgres, true)
+            .await
+            .unwrap();
+        assert_eq!(status.primary, MigrationBackend::Postgres);
+        assert!(status.switched_at.is_some());
+        assert!(status.restart_required);
+        assert!(status.not_dual_written.contains(&"request_logs"));
+        tokens.set_enabled(&created.token, false).await.unwrap();
+        assert!(!a.get_token(&created.token).await.unwrap().unwrap().enabled);
+        assert_eq!(state.status().mirror_failures, 0);
+    }
+}
@@ -0,0 +1,1598 @@
+//! 端到端用例：经 HTTP 调用完整应用，覆盖鉴权、路由、流式、预算与故障转移路径
+
+use gateway::client::{
+    ChatCompletionRequest, ChatMessage, ClientError, CreateToken, GatewayClient,
+};
+use wiremock::matchers::{body_partial_json, header, method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+use crate::config::settings::PricingMode;
+use crate::server::test_harness::{
+    ADMIN_TOKEN, TestGateway, TestProvider, UPSTREAM_KEY, completion_body, mock_chat, sse,
+    stream_body,
+};
+
+fn ping(model: &str) -> ChatCompletionRequest {
+    ChatCompletionRequest::new(model, vec![ChatMessage::user("ping")])
+}
+
+fn status_of(err: ClientError) -> u16 {
+    err.status()
+        .unwrap_or_else(|| panic!("expected gateway error, got {err}"))
+}
+
+async fn single_provider(upstream: &MockServer) -> (TestGateway, GatewayClient) {
+    let gateway = TestGateway::builder()
+        .provider(TestProvider::openai("p1", upstream))
+        .price("p1", "m1", 1.0, 2.0)
+        .start()
+        .await;
+    let token = gateway.create_token(CreateToken::default()).await;
+    let client = gateway.client(&token.token);
+    (gateway, client)
+}
+
+#[tokio::test]
+async fn rejects_missing_and_unknown_credentials() {
+    let upstream = MockServer::start().await;
+    let (gateway, _) = single_provider(&upstream).await;
+
+    let err = gateway
+        .client("atk_unknown")
+        .chat_completion(&ping("m1"))
+        .await
+        .unwrap_err();
+    // 未知令牌按参数错误拒绝（与 /v1 其他错误一致）
+    assert!(matches!(&err, ClientError::Api { code, .. } if code == "config_error"));
+    assert_eq!(status_of(err), 400);
+    // 客户端令牌不能访问管理面
+    let token = gateway.create_token(CreateToken::default()).await;
+    let err = gateway
+        .client(&token.token)
+        .list_tokens()
+        .await
+        .unwrap_err();
+    assert_eq!(status_of(err), 401);
+    assert!(upstream.received_requests().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn routes_prefixed_model_to_named_provider_and_bills_usage() {
+    let first = MockServer::start().await;
+    let second = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .and(header(
+            "authorization",
+            format!("Bearer {}", UPSTREAM_KEY).as_str(),
+        ))
+        .and(body_partial_json(serde_json::json!({"model": "m2"})))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(completion_body("m2", "from p2", 1000, 500)),
+        )
+        .expect(1)
+        .mount(&second)
+        .await;
+    let gateway = TestGateway::builder()
+        .provider(TestProvider::openai("p1", &first))
+        .provider(TestProvider::openai("p2", &second))
+        .price("p2", "m2", 1.0, 2.0)
+        .start()
+        .await;
+    let token = gateway.create_token(CreateToken::default()).await;
+    let client = gateway.client(&token.token);
+
+    let reply = client.chat_completion(&ping("p2/m2")).await.unwrap();
+    assert_eq!(reply.text(), Some("from p2"));
+    assert!(first.received_requests().await.unwrap().is_empty());
+
+    let usage = client.token_usage(Some(5)).await.unwrap();
+    assert_eq!(usage.items[0].provider.as_deref(), Some("p2"));
+    assert_eq!(usage.total_tokens_spent, 1500);
+    // 1000 × 1.0 + 500 × 2.0（每百万 token）
+    assert!((usage.total_cost - 0.002).abs() < 1e-9);
+
+    let err = client.chat_completion(&ping("nope/m1")).await.unwrap_err();
+    assert_eq!(status_of(err), 404);
+}
+
+#[tokio::test]
+async fn streams_upstream_chunks_through_the_gateway() {
+    let upstream = MockServer::start().await;
+    mock_chat(&upstream, sse(stream_body("m1", &["hel", "lo"]))).await;
+    let (_gateway, client) = single_provider(&upstream).await;
+
+    let mut stream = client.chat_completion_stream(&ping("m1")).await.unwrap();
+    let mut text = String::new();
+    while let Some(chunk) = stream.next().await {
+        for choice in chunk.unwrap().choices {
+            text.push_str(choice.delta.content.as_deref().unwrap_or(""));
+        }
+    }
+    assert_eq!(text, "hello");
+    let sent = &upstream.received_requests().await.unwrap()[0];
+    let body: serde_json::Value = serde_json::from_slice(&sent.body).unwrap();
+    assert_eq!(body["stream"], true);
+}
+
+#[tokio::test]
+async fn captured_stream_is_reassembled_into_a_transcript() {
+    let upstream = MockServer::start().await;
+    mock_chat(&upstream, sse(stream_body("m1", &["hel", "lo"]))).await;
+    let gateway = TestGateway::builder()
+        .provider(TestProvider::openai("p1", &upstream))
+        .price("p1", "m1", 1.0, 2.0)
+        .configure(|settings| settings.server.capture_stream_chunks = true)
+        .start()
+        .await;
+    let token = gateway.create_token(CreateToken::default()).await;
+    let mut stream = gateway
+        .client(&token.token)
+        .chat_completion_stream(&ping("m1"))
+        .await
+        .unwrap();
+    while let Some(chunk) = stream.next().await {
+        chunk.unwrap();
+    }
+
+    let http = reqwest::Client::new();
+    let logs: serde_json::Value = http
+        .get(format!(
+            "{}/admin/logs/requests?request_type=chat_stream",
+            gateway.base_url
+        ))
+        .bearer_auth(ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let log_id = &logs["data"][0]["id"];
+    // 日志写入与捕获保存均为异步，短暂轮询
+    let mut transcript = serde_json::Value::Null;
+    for _ in 0..50 {
+        let resp = http
+            .get(format!(
+                "{}/admin/logs/{}/transcript",
+                gateway.base_url, log_id
+            ))
+            .bearer_auth(ADMIN_TOKEN)
+            .send()
+            .await
+            .unwrap();
+        if resp.status() == 200 {
+            transcript = resp.json().await.unwrap();
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    assert_eq!(transcript["choices"][0]["content"], "hello");
+    assert_eq!(transcript["choices"][0]["finish_reason"], "stop");
+    assert_eq!(transcript["usage"]["total_tokens"], 7);
+    assert_eq!(transcript["chunks"][3]["data"], "[DONE]");
+}
+
+#[tokio::test]
+async fn gemini_provider_translates_requests_and_streams() {
+    let upstream = MockServer::start().await;
+    let candidate = |text: &str, finish: Option<&str>| {
+        serde_json::json!({
+            "candidates": [{
+                "content": {"role": "model", "parts": [{"text": text}]},
+                "finishReason": finish,
+            }],
+            "usageMetadata": {"promptTokenCount": 3, "candidatesTokenCount": 2, "totalTokenCount": 5},
+        })
+    };
+    Mock::given(method("POST"))
+        .and(path("/v1beta/models/gemini-2.0-flash:generateContent"))
+        .and(query_param("key", UPSTREAM_KEY))
+        .and(body_partial_json(serde_json::json!({
+            "contents": [{"role": "user", "parts": [{"text": "ping"}]}],
+        })))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(candidate("hi from gemini", Some("STOP"))),
+        )
+        .mount(&upstream)
+        .await;
+    Mock::given(method("POST"))
+        .and(path(
+            "/v1beta/models/gemini-2.0-flash:streamGenerateContent",
+        ))
+        .and(query_param("key", UPSTREAM_KEY))
+        .respond_with(sse(format!(
+            "data: {}\n\ndata: {}\n\n",
+            candidate("hi from ", None),
+            candidate("gemini", Some("STOP")),
+        )))
+        .mount(&upstream)
+        .await;
+    let gateway = TestGateway::builder()
+        .provider(TestProvider::gemini("g1", &upstream))
+        .price("g1", "gemini-2.0-flash", 1.0, 1.0)
+        .start()
+        .await;
+    let token = gateway.create_token(CreateToken::default()).await;
+    let client = gateway.client(&token.token);
+
+    let reply = client
+        .chat_completion(&ping("gemini-2.0-flash"))
+        .await
+        .unwrap();
+    assert_eq!(reply.text(), Some("hi from gemini"));
+
+    let mut stream = client
+        .chat_completion_stream(&ping("gemini-2.0-flash"))
+        .await
+        .unwrap();
+    let mut text = String::new();
+    while let Some(chunk) = stream.next().await {
+        for choice in chunk.unwrap().choices {
+            text.push_str(choice.delta.content.as_deref().unwrap_or(""));
+        }
+    }
+    assert_eq!(text, "hi from gemini");
+}
+
+#[tokio::test]
+async fn anthropic_stream_events_are_converted_and_billed() {
+    let upstream = MockServer::start().await;
+    let events = [
+        (
+            "message_start",
+            serde_json::json!({"type": "message_start", "message": {
+                "id": "msg_e2e", "type": "message", "role": "assistant", "model": "claude-x",
+                "content": [], "usage": {"input_tokens": 1000, "output_tokens": 1}
+            }}),
+        ),
+        (
+            "content_block_start",
+            serde_json::json!({"type": "content_block_start", "index": 0,
+                "content_block": {"type": "text", "text": ""}}),
+        ),
+        (
+            "content_block_delta",
+            serde_json::json!({"type": "content_block_delta", "index": 0,
+                "delta": {"type": "text_delta", "text": "hi from "}}),
+        ),
+        (
+            "content_block_delta",
+            serde_json::json!({"type": "content_block_delta", "index": 0,
+                "delta": {"type": "text_delta", "text": "claude"}}),
+        ),
+        (
+            "content_block_stop",
+            serde_json::json!({"type": "content_block_stop", "index": 0}),
+        ),
+        (
+            "message_delta",
+            serde_json::json!({"type": "message_delta",
+                "delta": {"stop_reason": "end_turn"}, "usage": {"output_tokens": 500}}),
+        ),
+        ("message_stop", serde_json::json!({"type": "message_stop"})),
+    ];
+    let body: String = events
+        .iter()
+        .map(|(name, data)| format!("event: {}\ndata: {}\n\n", name, data))
+        .collect();
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .and(header("x-api-key", UPSTREAM_KEY))
+        .and(body_partial_json(serde_json::json!({"stream": true})))
+        .respond_with(sse(body))
+        .expect(1)
+        .mount(&upstream)
+        .await;
+    let gateway = TestGateway::builder()
+        .provider(TestProvider::anthropic("a1", &upstream))
+        .price("a1", "claude-x", 1.0, 2.0)
+        .start()
+        .await;
+    let token = gateway.create_token(CreateToken::default()).await;
+    let client = gateway.client(&token.token);
+
+    let mut stream = client
+        .chat_completion_stream(&ping("claude-x"))
+        .await
+        .unwrap();
+    let mut text = String::new();
+    let mut finish_reason = None;
+    while let Some(chunk) = stream.next().await {
+        for choice in chunk.unwrap().choices {
+            text.push_str(choice.delta.content.as_deref().unwrap_or(""));
+            finish_reason = finish_reason.or(choice.finish_reason);
+        }
+    }
+    assert_eq!(text, "hi from claude");
+    assert_eq!(finish_reason.as_deref(), Some("stop"));
+
+    let usage = client.token_usage(Some(5)).await.unwrap();
+    assert_eq!(usage.items[0].prompt_tokens, Some(1000));
+    assert_eq!(usage.items[0].completion_tokens, Some(500));
+    // 1000 × 1.0 + 500 × 2.0（每百万 token）
+    assert!((usage.total_cost - 0.002).abs() < 1e-9);
+}
+
+#[tokio::test]
+async fn budget_hint_headers_report_remaining_quota() {
+    let upstream = MockServer::start().await;
+    mock_chat(
+        &upstream,
+        ResponseTemplate::new(200).set_body_json(completion_body("m1", "ok", 1000, 500)),
+    )
+    .await;
+    let gateway = TestGateway::builder()
+        .provider(TestProvider::openai("p1", &upstream))
+        .price("p1", "m1", 1.0, 2.0)
+        .configure(|settings| settings.server.budget_hint_headers = true)
+        .start()
+        .await;
+    gateway
+        .state
+        .runtime_settings
+        .apply(crate::server::runtime_settings::RuntimeSettings {
+            rate_limit_per_minute: Some(10),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    let token = gateway
+        .create_token(CreateToken {
+            max_amount: Some(1.0),
+            ..Default::default()
+        })
+        .await;
+    let http = reqwest::Client::new();
+
+    let resp = http
+        .post(format!("{}/v1/chat/completions", gateway.base_url))
+        .bearer_auth(&token.token)
+        .json(&ping("m1"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    let headers = resp.headers();
+    assert_eq!(headers["x-ratelimit-limit-requests"], "10");
+    assert_eq!(headers["x-ratelimit-remaining-requests"], "9");
+    assert!(
+        headers["x-ratelimit-reset-requests"]
+            .to_str()
+            .unwrap()
+            .ends_with('s')
+    );
+    let budget: f64 = headers["x-gateway-remaining-budget"]
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!(budget > 0.99 && budget <= 1.0);
+    // 未设置请求次数上限时不返回对应提示
+    assert!(!headers.contains_key("x-gateway-remaining-requests"));
+
+    // 未携带令牌的请求不附加提示
+    let resp = http
+        .get(format!("{}/v1/models", gateway.base_url))
+        .send()
+        .await
+        .unwrap();
+    assert!(!resp.headers().contains_key("x-ratelimit-limit-requests"));
+}
+
+#[tokio::test]
+async fn exhausted_token_budget_blocks_further_requests() {
+    let upstream = MockServer::start().await;
+    mock_chat(
+        &upstream,
+        ResponseTemplate::new(200).set_body_json(completion_body("m1", "ok", 1_000_000, 0)),
+    )
+    .await;
+    let (gateway, _) = single_provider(&upstream).await;
+    let token = gateway
+        .create_token(CreateToken {
+            max_amount: Some(0.5),
+            ..Default::default()
+        })
+        .await;
+    let client = gateway.client(&token.token);
+
+    // 首个请求花费 1.0，超出 0.5 的预算后令牌被停用
+    client.chat_completion(&ping("m1")).await.unwrap();
+    let balance = gateway.admin().get_token(&token.id).await.unwrap();
+    assert!(!balance.enabled);
+    assert!(balance.amount_spent >= 1.0);
+    let err = client.chat_completion(&ping("m1")).await.unwrap_err();
+    assert!((400..500).contains(&status_of(err)));
+    assert_eq!(upstream.received_requests().await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn plan_runs_admission_checks_without_consuming_quota() {
+    let upstream = MockServer::start().await;
+    mock_chat(
+        &upstream,
+        ResponseTemplate::new(200).set_body_json(completion_body("m1", "ok", 3, 1)),
+    )
+    .await;
+    let (gateway, _) = single_provider(&upstream).await;
+    let token = gateway.create_token(CreateToken::default()).await;
+    let http = reqwest::Client::new();
+    http.put(format!("{}/admin/tokens/{}", gateway.base_url, token.id))
+        .bearer_auth(ADMIN_TOKEN)
+        .json(&serde_json::json!({ "max_requests": 1, "allow_streaming": false }))
+        .send()
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+    let plan = |body: serde_json::Value| {
+        let http = http.clone();
+        let url = format!("{}/v1/chat/completions/plan", gateway.base_url);
+        let raw_token = token.token.clone();
+        async move {
+            http.post(url)
+                .bearer_auth(raw_token)
+                .json(&body)
+                .send()
+                .await
+                .unwrap()
+                .json::<serde_json::Value>()
+                .await
+                .unwrap()
+        }
+    };
+    let messages = serde_json::json!([{ "role": "user", "content": "ping" }]);
+
+    let streamed =
+        plan(serde_json::json!({ "model": "m1", "messages": messages, "stream": true })).await;
+    assert_eq!(streamed["allowed"], false);
+    let last_step = streamed["trace"]["steps"]
+        .as_array()
+        .unwrap()
+        .last()
+        .unwrap()
+        .clone();
+    assert_eq!(last_step["check"], "streaming");
+
+    // 预演只检查次数余量，不计入请求
+    for _ in 0..2 {
+        let planned = plan(serde_json::json!({ "model": "m1", "messages": messages })).await;
+        assert_eq!(planned["allowed"], true, "{planned}");
+    }
+    gateway
+        .client(&token.token)
+        .chat_completion(&ping("m1"))
+        .await
+        .unwrap();
+    let exhausted = plan(serde_json::json!({ "model": "m1", "messages": messages })).await;
+    assert_eq!(exhausted["allowed"], false);
+    let rejection = exhausted["rejection"].as_str().unwrap();
+    assert!(
+        rejection.contains("token request quota exceeded"),
+        "{rejection}"
+    );
+}
+
+/// 创建有充足余额、用户级预算为 `max_amount` 的用户
+async fn create_budget_user(gateway: &TestGateway, max_amount: f64) -> crate::users::User {
+    let user = gateway
+        .state
+        .user_store
+        .create_user(crate::users::CreateUserPayload {
+            first_name: Some("Budget".into()),
+            last_name: Some("User".into()),
+            username: None,
+            email: "budget@example.com".into(),
+            phone_number: None,
+            password: None,
+            status: crate::users::UserStatus::Active,
+            role: crate::users::UserRole::Admin,
+            is_anonymous: false,
+        })
+        .await
+        .unwrap();
+    gateway
+        .state
+        .user_store
+        .add_balance(&user.id, 10_000_000.0)
+        .await
+        .unwrap();
+    reqwest::Client::new()
+        .put(format!(
+            "{}/admin/users/{}/budget",
+            gateway.base_url, user.id
+        ))
+        .bearer_auth(ADMIN_TOKEN)
+        .json(&serde_json::json!({ "max_amount": max_amount }))
+        .send()
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+    user
+}
+
+#[tokio::test]
+async fn user_budget_is_shared_across_tokens() {
+    let upstream = MockServer::start().await;
+    mock_chat(
+        &upstream,
+        ResponseTemplate::new(200).set_body_json(completion_body("m1", "ok", 1_000_000, 0)),
+    )
+    .await;
+    let (gateway, _) = single_provider(&upstream).await;
+    let user = create_budget_user(&gateway, 1.5).await;
+    let http = reqwest::Client::new();
+    let first = gateway
+        .create_token(CreateToken {
+            user_id: Some(user.id.clone()),
+            ..Default::default()
+        })
+        .await;
+    let second = gateway
+        .create_token(CreateToken {
+            user_id: Some(user.id.clone()),
+            ..Default::default()
+        })
+        .await;
+
+    // 每个请求花费 1.0：两个令牌各请求一次后合计 2.0，超出用户级预算 1.5
+    gateway
+        .client(&first.token)
+        .chat_completion(&ping("m1"))
+        .await
+        .unwrap();
+    gateway
+        .client(&second.token)
+        .chat_completion(&ping("m1"))
+        .await
+        .unwrap();
+    for token in [&first, &second] {
+        let err = gateway
+            .client(&token.token)
+            .chat_completion(&ping("m1"))
+            .await
+            .unwrap_err();
+        assert!((400..500).contains(&status_of(err)));
+    }
+    assert_eq!(upstream.received_requests().await.unwrap().len(), 2);
+
+    let balance: serde_json::Value = http
+        .get(format!("{}/v1/user/balance", gateway.base_url))
+        .bearer_auth(&first.token)
+        .send()
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(balance["user_id"], user.id.as_str());
+    assert_eq!(balance["max_amount"], 1.5);
+    assert_eq!(balance["remaining"], 0.0);
+    assert!(balance["amount_spent"].as_f64().unwrap() >= 2.0);
+    assert_eq!(balance["tokens"].as_array().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn user_budget_counts_child_token_spend_once() {
+    let upstream = MockServer::start().await;
+    mock_chat(
+        &upstream,
+        ResponseTemplate::new(200).set_body_json(completion_body("m1", "ok", 1_000_000, 0)),
+    )
+    .await;
+    let (gateway, _) = single_provider(&upstream).await;
+    let user = create_budget_user(&gateway, 1.5).await;
+    let parent = gateway
+        .create_token(CreateToken {
+            user_id: Some(user.id.clone()),
+            ..Default::default()
+        })
+        .await;
+    let http = reqwest::Client::new();
+    let child: serde_json::Value = http
+        .post(format!("{}/v1/token/exchange", gateway.base_url))
+        .bearer_auth(&parent.token)
+        .json(&serde_json::json!({}))
+        .send()
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let child_token = child["token"].as_str().unwrap();
+
+    // 子令牌花费 1.0 同时汇总到父令牌；用户级合计仍为 1.0，未超出预算 1.5
+    gateway
+        .client(child_token)
+        .chat_completion(&ping("m1"))
+        .await
+        .unwrap();
+    gateway
+        .client(&parent.token)
+        .chat_completion(&ping("m1"))
+        .await
+        .unwrap();
+    assert_eq!(upstream.received_requests().await.unwrap().len(), 2);
+
+    let balance: serde_json::Value = http
+        .get(format!("{}/v1/user/balance", gateway.base_url))
+        .bearer_auth(&parent.token)
+        .send()
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let spent = balance["amount_spent"].as_f64().unwrap();
+    assert!((2.0..2.01).contains(&spent), "spent {spent}");
+    let tokens = balance["tokens"].as_array().unwrap();
+    assert_eq!(tokens.len(), 1);
+    assert_eq!(tokens[0]["id"], parent.id.as_str());
+}
+
+#[tokio::test]
+async fn token_exchange_is_rate_limited_and_expired_children_are_pruned() {
+    let upstream = MockServer::start().await;
+    let (gateway, _) = single_provider(&upstream).await;
+    let parent = gateway.create_token(CreateToken::default()).await;
+    let http = reqwest::Client::new();
+    let exchange = || {
+        http.post(format!("{}/v1/token/exchange", gateway.base_url))
+            .bearer_auth(&parent.token)
+            .json(&serde_json::json!({}))
+            .send()
+    };
+    for _ in 0..crate::server::token_lineage::EXCHANGES_PER_MINUTE {
+        assert_eq!(exchange().await.unwrap().status(), 200);
+    }
+    assert_eq!(exchange().await.unwrap().status(), 429);
+
+    // 过期超过一天的交换子令牌被清理；管理端创建的长期子令牌保留
+    let store = &gateway.state.token_store;
+    let stale = store
+        .create_token(crate::admin::CreateTokenPayload {
+            parent_token_id: Some(parent.id.clone()),
+            expires_at: Some(crate::logging::time::to_beijing_string(
+                &(chrono::Utc::now() - chrono::Duration::days(2)),
+            )),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    let long_lived = store
+        .create_token(crate::admin::CreateTokenPayload {
+            parent_token_id: Some(parent.id.clone()),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    let pruned = crate::server::token_lineage::prune_expired_exchanged_tokens(
+        &gateway.state,
+        chrono::Utc::now(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(pruned, 1);
+    assert!(store.get_token_by_id(&stale.id).await.unwrap().is_none());
+    assert!(
+        store
+            .get_token_by_id(&long_lived.id)
+            .await
+            .unwrap()
+            .is_some()
+    );
+    assert_eq!(
+        store.list_child_tokens(&parent.id).await.unwrap().len(),
+        crate::server::token_lineage::EXCHANGES_PER_MINUTE + 1
+    );
+}
+
+#[tokio::test]
+async fn upstream_retry_after_is_propagated_and_cools_down_the_key() {
+    let upstream = MockServer::start().await;
+    mock_chat(
+        &upstream,
+        ResponseTemplate::new(429)
+            .insert_header("retry-after", "30")
+            .set_body_json(serde_json::json!({
+                "error": {"message": "slow down", "type": "rate_limit_error"}
+            })),
+    )
+    .await;
+    let (gateway, client) = single_provider(&upstream).await;
+    let token = gateway.create_token(CreateToken::default()).await;
+    let http = reqwest::Client::new();
+
+    let resp = http
+        .post(format!("{}/v1/chat/completions", gateway.base_url))
+        .bearer_auth(&token.token)
+        .json(&ping("m1"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 429);
+    assert_eq!(resp.headers()["retry-after"], "30");
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(body["code"], "rate_limited");
+    assert_eq!(body["message"], "slow down");
+
+    // 唯一的密钥仍在冷却期内：网关直接返回 429，不再请求上游
+    let err = client.chat_completion(&ping("m1")).await.unwrap_err();
+    assert_eq!(status_of(err), 429);
+    assert_eq!(upstream.received_requests().await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn local_provider_runs_without_key_and_estimates_missing_usage() {
+    let upstream = MockServer::start().await;
+    let mut body = completion_body("llama3", "pong from ollama", 0, 0);
+    body.as_object_mut().unwrap().remove("usage");
+    mock_chat(&upstream, ResponseTemplate::new(200).set_body_json(body)).await;
+    let gateway = TestGateway::builder()
+        .provider(TestProvider::local("ollama", &upstream))
+        .price("ollama", "llama3", 0.0, 0.0)
+        .start()
+        .await;
+    let token = gateway.create_token(CreateToken::default()).await;
+    let client = gateway.client(&token.token);
+
+    let resp = client
+        .chat_completion(&ping("ollama/llama3"))
+        .await
+        .unwrap();
+    assert_eq!(resp.text(), Some("pong from ollama"));
+    // 上游未返回 usage：按字符数估算，而不是记为 0
+    let usage = resp.usage.expect("estimated usage");
+    assert!(usage.prompt_tokens > 0);
+    assert!(usage.completion_tokens > 0);
+
+    let received = upstream.received_requests().await.unwrap();
+    assert_eq!(received.len(), 1);
+    assert!(!received[0].headers.contains_key("authorization"));
+}
+
+#[tokio::test]
+async fn upstream_errors_surface_and_disabled_provider_fails_over() {
+    let primary = MockServer::start().await;
+    let backup = MockServer::start().await;
+    mock_chat(
+        &primary,
+        ResponseTemplate::new(500).set_body_json(serde_json::json!({
+            "error": {"message": "boom", "type": "server_error", "code": "internal"}
+        })),
+    )
+    .await;
+    mock_chat(
+        &backup,
+        ResponseTemplate::new(200).set_body_json(completion_body("m1", "from backup", 3, 1)),
+    )
+    .await;
+    let gateway = TestGateway::builder()
+        .provider(TestProvider::openai("a-primary", &primary))
+        .provider(TestProvider::openai("b-backup", &backup))
+        .price("a-primary", "m1", 1.0, 1.0)
+        .price("b-backup", "m1", 1.0, 1.0)
+        .start()
+        .await;
+    let token = gateway.create_token(CreateToken::default()).await;
+    let client = gateway.client(&token.token);
+
+    // 上游错误信息透传给调用方
+    let err = client.chat_completion(&ping("m1")).await.unwrap_err();
+    assert!(matches!(&err, ClientError::Api { message, .. } if message.contains("boom")));
+
+    gateway
+        .admin()
+        .set_provider_enabled("a-primary", false)
+        .await
+        .unwrap();
+    let reply = client.chat_completion(&ping("m1")).await.unwrap();
+    assert_eq!(reply.text(), Some("from backup"));
+    assert_eq!(primary.received_requests().await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn missing_price_is_rejected_unless_pricing_mode_allows_it() {
+    let upstream = MockServer::start().await;
+    mock_chat(
+        &upstream,
+        ResponseTemplate::new(200).set_body_json(completion_body("m9", "free", 2, 2)),
+    )
+    .await;
+    let (strict, client) = single_provider(&upstream).await;
+    let err = client.chat_completion(&ping("m9")).await.unwrap_err();
+    assert_eq!(status_of(err), 400);
+    drop(strict);
+    assert!(upstream.received_requests().await.unwrap().is_empty());
+
+    let lenient = TestGateway::builder()
+        .provider(TestProvider::openai("p1", &upstream))
+        .configure(|settings| settings.server.pricing_mode = PricingMode::AllowMissing)
+        .start()
+        .await;
+    let token = lenient.create_token(CreateToken::default()).await;
+    let reply = lenient
+        .client(&token.token)
+        .chat_completion(&ping("m9"))
+        .await
+        .unwrap();
+    assert_eq!(reply.text(), Some("free"));
+    let logs = lenient
+        .state
+        .log_store
+        .get_logs_by_client_token(&token.id, 10)
+        .await
+        .unwrap();
+    let chat = logs
+        .iter()
+        .find(|log| log.path == "/v1/chat/completions")
+        .unwrap();
+    assert_eq!(chat.status_code, 200);
+    assert_eq!(chat.amount_spent, None);
+}
+
+#[tokio::test]
+async fn maintenance_window_moves_traffic_until_cancelled() {
+    let primary = MockServer::start().await;
+    let backup = MockServer::start().await;
+    for (upstream, text) in [(&primary, "from primary"), (&backup, "from backup")] {
+        mock_chat(
+            upstream,
+            ResponseTemplate::new(200).set_body_json(completion_body("m1", text, 3, 1)),
+        )
+        .await;
+    }
+    let gateway = TestGateway::builder()
+        .provider(TestProvider::openai("a-primary", &primary))
+        .provider(TestProvider::openai("b-backup", &backup))
+        .price("a-primary", "m1", 1.0, 1.0)
+        .price("b-backup", "m1", 1.0, 1.0)
+        .start()
+        .await;
+    let token = gateway.create_token(CreateToken::default()).await;
+    let client = gateway.client(&token.token);
+    let http = reqwest::Client::new();
+
+    let now = chrono::Utc::now();
+    let created: serde_json::Value = http
+        .post(format!("{}/admin/maintenance-windows", gateway.base_url))
+        .bearer_auth(ADMIN_TOKEN)
+        .json(&serde_json::json!({
+            "provider": "a-primary",
+            "starts_at": (now - chrono::Duration::minutes(1)).to_rfc3339(),
+            "ends_at": (now + chrono::Duration::hours(1)).to_rfc3339(),
+            "message": "database upgrade",
+        }))
+        .send()
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+
+    let status: serde_json::Value = http
+        .get(format!("{}/status", gateway.base_url))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(status["status"], "maintenance");
+    assert_eq!(status["maintenance"]["active"][0]["provider"], "a-primary");
+
+    let reply = client.chat_completion(&ping("m1")).await.unwrap();
+    assert_eq!(reply.text(), Some("from backup"));
+    let err = client
+        .chat_completion(&ping("a-primary/m1"))
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(&err, ClientError::Api { message, .. } if message.contains("database upgrade"))
+    );
+    assert!(primary.received_requests().await.unwrap().is_empty());
+
+    http.delete(format!(
+        "{}/admin/maintenance-windows/{}",
+        gateway.base_url, created["id"]
+    ))
+    .bearer_auth(ADMIN_TOKEN)
+    .send()
+    .await
+    .unwrap()
+    .error_for_status()
+    .unwrap();
+    let reply = client.chat_completion(&ping("a-primary/m1")).await.unwrap();
+    assert_eq!(reply.text(), Some("from primary"));
+}
+
+#[tokio::test]
+async fn branding_is_injected_into_errors_status_and_headers() {
+    let upstream = MockServer::start().await;
+    let (gateway, _) = single_provider(&upstream).await;
+    let http = reqwest::Client::new();
+
+    http.put(format!("{}/admin/branding", gateway.base_url))
+        .bearer_auth(ADMIN_TOKEN)
+        .json(&serde_json::json!({
+            "service_name": "Acme AI",
+            "docs_url": "https://docs.acme.example",
+            "support_contact": "support@acme.example",
+        }))
+        .send()
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+
+    let resp = http
+        .get(format!("{}/admin/tokens", gateway.base_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 401);
+    assert_eq!(resp.headers()["x-gateway-service"], "Acme AI");
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert!(body["code"].is_string());
+    assert_eq!(body["docs_url"], "https://docs.acme.example");
+    assert_eq!(body["support_contact"], "support@acme.example");
+
+    let status: serde_json::Value = http
+        .get(format!("{}/status", gateway.base_url))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(status["status"], "ok");
+    assert_eq!(status["branding"]["service_name"], "Acme AI");
+}
+
+#[tokio::test]
+async fn anthropic_messages_ingress_routes_to_openai_provider() {
+    let upstream = MockServer::start().await;
+    mock_chat(
+        &upstream,
+        ResponseTemplate::new(200).set_body_json(completion_body("m1", "pong", 1000, 500)),
+    )
+    .await;
+    let gateway = TestGateway::builder()
+        .provider(TestProvider::openai("p1", &upstream))
+        .price("p1", "m1", 1.0, 2.0)
+        .start()
+        .await;
+    let token = gateway.create_token(CreateToken::default()).await;
+    let http = reqwest::Client::new();
+    let request = serde_json::json!({
+        "model": "m1",
+        "max_tokens": 64,
+        "system": "be brief",
+        "messages": [{"role": "user", "content": "ping"}],
+    });
+
+    let resp = http
+        .post(format!("{}/v1/messages", gateway.base_url))
+        .header("x-api-key", &token.token)
+        .header("anthropic-version", "2023-06-01")
+        .json(&request)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(body["type"], "message");
+    assert_eq!(body["content"][0]["text"], "pong");
+    assert_eq!(body["stop_reason"], "end_turn");
+    assert_eq!(body["usage"]["input_tokens"], 1000);
+    assert_eq!(body["usage"]["output_tokens"], 500);
+
+    let sent = upstream.received_requests().await.unwrap();
+    let sent: serde_json::Value = serde_json::from_slice(&sent[0].body).unwrap();
+    assert_eq!(sent["messages"][0]["role"], "system");
+    assert_eq!(sent["messages"][1]["content"], "ping");
+
+    // 错误按 Anthropic 的错误体返回
+    let resp = http
+        .post(format!("{}/v1/messages", gateway.base_url))
+        .header("x-api-key", "invalid")
+        .json(&request)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 400);
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(body["type"], "error");
+    assert_eq!(body["error"]["type"], "invalid_request_error");
+    assert_eq!(body["error"]["message"], "invalid token");
+}
+
+#[tokio::test]
+async fn legacy_completions_forward_natively_or_adapt_to_chat() {
+    let upstream = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/completions"))
+        .and(body_partial_json(
+            serde_json::json!({"model": "m1", "prompt": "def fib(n):", "suffix": "\n"}),
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "id": "cmpl-1",
+            "object": "text_completion",
+            "created": 1,
+            "model": "m1",
+            "choices": [{"text": " return n", "index": 0, "logprobs": null, "finish_reason": "stop"}],
+            "usage": {"prompt_tokens": 1000, "completion_tokens": 500, "total_tokens": 1500}
+        })))
+        .expect(1)
+        .mount(&upstream)
+        .await;
+    mock_chat(&upstream, sse(stream_body("m1", &["ret", "urn n"]))).await;
+    let (gateway, _) = single_provider(&upstream).await;
+    let token = gateway.create_token(CreateToken::default()).await;
+    let http = reqwest::Client::new();
+
+    let resp = http
+        .post(format!("{}/v1/completions", gateway.base_url))
+        .bearer_auth(&token.token)
+        .json(&serde_json::json!({"model": "m1", "prompt": "def fib(n):", "suffix": "\n"}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(body["object"], "text_completion");
+    assert_eq!(body["choices"][0]["text"], " return n");
+    assert_eq!(body["usage"]["total_tokens"], 1500);
+
+    // 流式请求转为聊天调用，echo 由网关拼回
+    let text = http
+        .post(format!("{}/v1/completions", gateway.base_url))
+        .bearer_auth(&token.token)
+        .json(&serde_json::json!({"model": "m1", "prompt": "p:", "echo": true, "stream": true}))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    let streamed: String = text
+        .lines()
+        .filter_map(|line| line.strip_prefix("data: "))
+        .filter_map(|data| serde_json::from_str::<serde_json::Value>(data).ok())
+        .inspect(|chunk| assert_eq!(chunk["object"], "text_completion"))
+        .filter_map(|chunk| chunk["choices"][0]["text"].as_str().map(str::to_string))
+        .collect();
+    assert_eq!(streamed, "p:return n");
+    let sent = upstream.received_requests().await.unwrap();
+    let chat: serde_json::Value = serde_json::from_slice(&sent[1].body).unwrap();
+    assert_eq!(chat["messages"][0]["content"], "p:");
+
+    let usage = gateway
+        .client(&token.token)
+        .token_usage(Some(5))
+        .await
+        .unwrap();
+    assert!(usage.total_tokens_spent >= 1500);
+    // 两次请求都以旧版入口记录日志；流式日志在流结束后异步写入
+    let mut paths = Vec::new();
+    for _ in 0..50 {
+        paths = gateway
+            .state
+            .log_store
+            .get_logs_by_client_token(&token.id, 10)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|log| log.path)
+            .collect();
+        if paths.len() == 2 {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    assert_eq!(paths, ["/v1/completions", "/v1/completions"]);
+}
+
+#[tokio::test]
+async fn heatmap_counts_requests_by_weekday_and_hour() {
+    use chrono::{Datelike, Timelike};
+
+    let upstream = MockServer::start().await;
+    mock_chat(
+        &upstream,
+        ResponseTemplate::new(200).set_body_json(completion_body("m1", "pong", 10, 5)),
+    )
+    .await;
+    let (gateway, client) = single_provider(&upstream).await;
+    client.chat_completion(&ping("m1")).await.unwrap();
+    client.chat_completion(&ping("m1")).await.unwrap();
+
+    let now = chrono::Utc::now().with_timezone(&crate::logging::time::BEIJING_OFFSET);
+    let index = now.weekday().num_days_from_sunday() as usize * 24 + now.hour() as usize;
+    let http = reqwest::Client::new();
+    let mut heatmap = serde_json::Value::Null;
+    // 日志异步写入，短暂轮询
+    for _ in 0..50 {
+        heatmap = http
+            .get(format!(
+                "{}/admin/metrics/heatmap?model=m1",
+                gateway.base_url
+            ))
+            .bearer_auth(ADMIN_TOKEN)
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        if heatmap["total_requests"] == 2 {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    assert_eq!(heatmap["cells"].as_array().unwrap().len(), 7 * 24);
+    assert_eq!(heatmap["total_requests"], 2);
+    assert_eq!(heatmap["cells"][index]["requests"], 2);
+    assert_eq!(heatmap["max_requests"], 2);
+
+    let other: serde_json::Value = http
+        .get(format!(
+            "{}/admin/metrics/heatmap?model=m2",
+            gateway.base_url
+        ))
+        .bearer_auth(ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(other["total_requests"], 0);
+}
+
+#[tokio::test]
+async fn image_generations_bill_per_image_and_enforce_token_budget() {
+    let openai = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/images/generations"))
+        .and(body_partial_json(
+            serde_json::json!({"model": "dall-e-3", "n": 2, "quality": "hd"}),
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "created": 1,
+            "data": [{"url": "https://img/1.png"}, {"url": "https://img/2.png"}]
+        })))
+        .expect(1)
+        .mount(&openai)
+        .await;
+    let zhipu = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/api/paas/v4/images/generations"))
+        .and(body_partial_json(
+            serde_json::json!({"model": "cogview-3", "user_id": "u1"}),
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "created": 1,
+            "data": [{"url": "https://cogview/1.png"}]
+        })))
+        .expect(2)
+        .mount(&zhipu)
+        .await;
+    let gateway = TestGateway::builder()
+        .provider(TestProvider::openai("oa", &openai))
+        .provider(TestProvider {
+            name: "zp".into(),
+            api_type: crate::config::settings::ProviderType::Zhipu,
+            base_url: zhipu.uri(),
+            provider_config: Default::default(),
+        })
+        .start()
+        .await;
+    let http = reqwest::Client::new();
+    for (provider, price) in [
+        (
+            "oa",
+            serde_json::json!({"model": "dall-e-3", "price_per_image": 0.04}),
+        ),
+        (
+            "oa",
+            serde_json::json!({"model": "dall-e-3", "size": "1024x1024", "quality": "hd", "price_per_image": 0.08}),
+        ),
+        (
+            "zp",
+            serde_json::json!({"model": "cogview-3", "price_per_image": 0.1}),
+        ),
+    ] {
+        let resp = http
+            .put(format!(
+                "{}/admin/image-prices/{}",
+                gateway.base_url, provider
+            ))
+            .bearer_auth(ADMIN_TOKEN)
+            .json(&price)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 200);
+    }
+    let token = gateway
+        .create_token(CreateToken {
+            max_amount: Some(0.5),
+            ..Default::default()
+        })
+        .await;
+    let generate = |body: serde_json::Value| {
+        http.post(format!("{}/v1/images/generations", gateway.base_url))
+            .bearer_auth(&token.token)
+            .json(&body)
+            .send()
+    };
+
+    let resp = generate(
+        serde_json::json!({"model": "oa/dall-e-3", "prompt": "a cat", "n": 2, "quality": "hd"}),
+    )
+    .await
+    .unwrap();
+    assert_eq!(resp.status(), 200);
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(body["data"].as_array().unwrap().len(), 2);
+
+    // CogView 单次一张：网关按 n 拆分调用并合并结果
+    let resp = generate(
+        serde_json::json!({"model": "zp/cogview-3", "prompt": "a dog", "n": 2, "user": "u1"}),
+    )
+    .await
+    .unwrap();
+    assert_eq!(resp.status(), 200);
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(body["data"].as_array().unwrap().len(), 2);
+    let sent = zhipu.received_requests().await.unwrap();
+    let upstream_body: serde_json::Value = serde_json::from_slice(&sent[0].body).unwrap();
+    assert!(upstream_body.get("n").is_none());
+
+    let spent = gateway.admin().get_token(&token.id).await.unwrap();
+    assert!((spent.amount_spent - 0.36).abs() < 1e-9);
+
+    // 预估 0.36 + 2 × 0.08 超出 0.5 的预算，不调用上游
+    let resp = generate(
+        serde_json::json!({"model": "oa/dall-e-3", "prompt": "a cat", "n": 2, "quality": "hd"}),
+    )
+    .await
+    .unwrap();
+    assert_eq!(resp.status(), 400);
+    // 未配置单价的模型在严格定价模式下拒绝
+    let resp = generate(serde_json::json!({"model": "oa/gpt-image-1", "prompt": "a cat"}))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 400);
+}
+
+#[tokio::test]
+async fn sandbox_tokens_cannot_generate_images() {
+    let openai = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/images/generations"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "created": 1,
+            "data": [{"url": "https://img/1.png"}]
+        })))
+        .expect(0)
+        .mount(&openai)
+        .await;
+    let gateway = TestGateway::builder()
+        .provider(TestProvider::openai("oa", &openai))
+        .start()
+        .await;
+    let http = reqwest::Client::new();
+    let token: serde_json::Value = http
+        .post(format!("{}/admin/tokens", gateway.base_url))
+        .bearer_auth(ADMIN_TOKEN)
+        .json(&serde_json::json!({"name": "sandbox", "sandbox": true}))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+
+    let resp = http
+        .post(format!("{}/v1/images/generations", gateway.base_url))
+        .bearer_auth(token["token"].as_str().unwrap())
+        .json(&serde_json::json!({"model": "oa/dall-e-3", "prompt": "a cat"}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 403);
+}
+
+#[tokio::test]
+async fn labeled_keys_serve_matching_models_and_fall_back_when_disabled() {
+    let upstream = MockServer::start().await;
+    mock_chat(
+        &upstream,
+        ResponseTemplate::new(200).set_body_json(completion_body("m1", "ok", 1, 1)),
+    )
+    .await;
+    let mut provider = TestProvider::openai("p1", &upstream);
+    provider.provider_config.key_label_rules = vec![crate::routing::KeyLabelRule {
+        labels: [("region".to_string(), "eu".to_string())].into(),
+        models: vec!["m1".into()],
+        ..Default::default()
+    }];
+    let gateway = TestGateway::builder()
+        .provider(provider)
+        .price("p1", "m1", 1.0, 1.0)
+        .start()
+        .await;
+    let http = reqwest::Client::new();
+    let admin = |method: reqwest::Method, suffix: &str, body: serde_json::Value| {
+        http.request(
+            method,
+            format!("{}/providers/p1/keys{}", gateway.base_url, suffix),
+        )
+        .bearer_auth(ADMIN_TOKEN)
+        .json(&body)
+        .send()
+    };
+    let resp = admin(
+        reqwest::Method::POST,
+        "",
+        serde_json::json!({"key": "sk-eu"}),
+    )
+    .await
+    .unwrap();
+    assert!(resp.status().is_success());
+    let resp = admin(
+        reqwest::Method::PATCH,
+        "/labels",
+        serde_json::json!({"key": "sk-eu", "labels": {"region": "eu"}}),
+    )
+    .await
+    .unwrap();
+    assert_eq!(resp.status(), 200);
+    // 非法标签名被拒绝
+    let resp = admin(
+        reqwest::Method::PATCH,
+        "/labels",
+        serde_json::json!({"key": "sk-eu", "labels": {"bad name": "x"}}),
+    )
+    .await
+    .unwrap();
+    assert_eq!(resp.status(), 400);
+
+    let token = gateway.create_token(CreateToken::default()).await;
+    let client = gateway.client(&token.token);
+    for _ in 0..3 {
+        client.chat_completion(&ping("m1")).await.unwrap();
+    }
+    let auth_of =
+        |req: &wiremock::Request| req.headers["authorization"].to_str().unwrap().to_string();
+    let received = upstream.received_requests().await.unwrap();
+    assert!(received.iter().all(|r| auth_of(r) == "Bearer sk-eu"));
+
+    // 带标签的密钥不可用时回退到其余密钥
+    let resp = admin(
+        reqwest::Method::POST,
+        "/toggle",
+        serde_json::json!({"key": "sk-eu", "active": false}),
+    )
+    .await
+    .unwrap();
+    assert!(resp.status().is_success());
+    client.chat_completion(&ping("m1")).await.unwrap();
+    let received = upstream.received_requests().await.unwrap();
+    assert_eq!(
+        auth_of(received.last().unwrap()),
+        format!("Bearer {}", UPSTREAM_KEY)
+    );
+}
+
+#[tokio::test]
+async fn audio_transcriptions_forward_multipart_and_bill_by_duration() {
+    let upstream = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/audio/transcriptions"))
+        .and(header(
+            "authorization",
+            format!("Bearer {}", UPSTREAM_KEY).as_str(),
+        ))
+        .and(wiremock::matchers::body_string_contains("whisper-1\r\n"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "text": "hello world",
+            "usage": {"type": "duration", "seconds": 90}
+        })))
+        .expect(1)
+        .mount(&upstream)
+        .await;
+    let gateway = TestGateway::builder()
+        .provider(TestProvider::openai("oa", &upstream))
+        .start()
+        .await;
+    let http = reqwest::Client::new();
+    let resp = http
+        .put(format!("{}/admin/audio-prices/oa", gateway.base_url))
+        .bearer_auth(ADMIN_TOKEN)
+        .json(&serde_json::json!({"model": "whisper-1", "price_per_minute": 0.006}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    let token = gateway.create_token(CreateToken::default()).await;
+    let form = |model: &str| {
+        format!(
+            "--b0\r\nContent-Disposition: form-data; name=\"model\"\r\n\r\n{model}\r\n\
+             --b0\r\nContent-Disposition: form-data; name=\"response_format\"\r\n\r\ntext\r\n\
+             --b0\r\nContent-Disposition: form-data; name=\"file\"; filename=\"a.wav\"\r\n\
+             Content-Type: audio/wav\r\n\r\nRIFF....WAVE\r\n--b0--\r\n"
+        )
+    };
+    let transcribe = |model: &str| {
+        http.post(format!("{}/v1/audio/transcriptions", gateway.base_url))
+            .bearer_auth(&token.token)
+            .header("content-type", "multipart/form-data; boundary=b0")
+            .body(form(model))
+            .send()
+    };
+
+    let resp = transcribe("oa/whisper-1").await.unwrap();
+    assert_eq!(resp.status(), 200);
+    assert!(
+        resp.headers()["content-type"]
+            .to_str()
+            .unwrap()
+            .starts_with("text/plain")
+    );
+    assert_eq!(resp.text().await.unwrap(), "hello world\n");
+    let sent = upstream.received_requests().await.unwrap();
+    let sent_body = String::from_utf8_lossy(&sent[0].body).to_string();
+    assert!(sent_body.contains("name=\"response_format\"\r\n\r\njson\r\n"));
+    assert!(sent_body.contains("RIFF....WAVE"));
+
+    // 90 秒 × 0.006/分钟
+    let spent = gateway.admin().get_token(&token.id).await.unwrap();
+    assert!((spent.amount_spent - 0.009).abs() < 1e-9);
+
+    // 未配置单价的模型在严格定价模式下拒绝，不调用上游
+    let resp = transcribe("oa/gpt-4o-transcribe").await.unwrap();
+    assert_eq!(resp.status(), 400);
+
+    // 沙箱令牌不访问真实上游
+    let sandbox: serde_json::Value = http
+        .post(format!("{}/admin/tokens", gateway.base_url))
+        .bearer_auth(ADMIN_TOKEN)
+        .json(&serde_json::json!({"name": "sandbox", "sandbox": true}))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let resp = http
+        .post(format!("{}/v1/audio/transcriptions", gateway.base_url))
+        .bearer_auth(sandbox["token"].as_str().unwrap())
+        .header("content-type", "multipart/form-data; boundary=b0")
+        .body(form("oa/whisper-1"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 403);
+}
+
+#[tokio::test]
+async fn token_detail_includes_activity_timeline() {
+    let upstream = MockServer::start().await;
+    mock_chat(
+        &upstream,
+        ResponseTemplate::new(200).set_body_json(completion_body("m1", "ok", 3, 1)),
+    )
+    .await;
+    let (gateway, _) = single_provider(&upstream).await;
+    let token = gateway.create_token(CreateToken::default()).await;
+    let client = gateway.client(&token.token);
+    client.chat_completion(&ping("m1")).await.unwrap();
+    client.chat_completion(&ping("m1")).await.unwrap();
+    // 未配置价格的模型被拒绝，计入最近错误
+    client.chat_completion(&ping("m2")).await.unwrap_err();
+
+    let detail = gateway.admin().get_token(&token.id).await.unwrap();
+    let activity = &detail.extra["activity"];
+    assert_eq!(activity["window_days"], 30);
+    let daily = activity["daily"].as_array().unwrap();
+    assert_eq!(daily.len(), 30);
+    let today = &daily[29];
+    assert_eq!(today["requests"], 3);
+    assert_eq!(today["errors"], 1);
+    assert!(today["amount_spent"].as_f64().unwrap() > 0.0);
+    assert_eq!(daily[0]["requests"], 0);
+    assert_eq!(activity["top_models"][0]["model"], "m1");
+    assert_eq!(activity["top_models"][0]["requests"], 2);
+    let errors = activity["recent_errors"].as_array().unwrap();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0]["status_code"], 400);
+    assert!(activity["last_used_at"].is_string());
+    assert_eq!(detail.extra["usage_count"], 3);
+}
+
+#[tokio::test]
+async fn audio_speech_streams_audio_and_bills_by_characters() {
+    let upstream = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/audio/speech"))
+        .and(wiremock::matchers::body_partial_json(
+            serde_json::json!({"model": "tts-1", "voice": "alloy", "input": "你好，world"}),
+        ))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-type", "audio/mpeg")
+                .set_body_bytes(b"ID3\x03fake-mp3-frames".to_vec()),
+        )
+        .expect(1)
+        .mount(&upstream)
+        .await;
+    let gateway = TestGateway::builder()
+        .provider(TestProvider::openai("oa", &upstream))
+        .start()
+        .await;
+    let http = reqwest::Client::new();
+    let resp = http
+        .put(format!("{}/admin/speech-prices/oa", gateway.base_url))
+        .bearer_auth(ADMIN_TOKEN)
+        .json(&serde_json::json!({"model": "tts-1", "price_per_million_chars": 15.0}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    let token = gateway.create_token(CreateToken::default()).await;
+    let speak = |model: &str| {
+        http.post(format!("{}/v1/audio/speech", gateway.base_url))
+            .bearer_auth(&token.token)
+            .json(&serde_json::json!({"model": model, "voice": "alloy", "input": "你好，world"}))
+            .send()
+    };
+
+    let resp = speak("oa/tts-1").await.unwrap();
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.headers()["content-type"], "audio/mpeg");
+    assert_eq!(
+        resp.bytes().await.unwrap().as_ref(),
+        b"ID3\x03fake-mp3-frames"
+    );
+
+    // 8 个字符 × 15/百万字符
+    let spent = gateway.admin().get_token(&token.id).await.unwrap();
+    assert!((spent.amount_spent - 0.00012).abs() < 1e-12);
+
+    // 未配置单价的模型在严格定价模式下拒绝，不调用上游
+    let resp = speak("oa/tts-1-hd").await.unwrap();
+    assert_eq!(resp.status(), 400);
+
+    // 沙箱令牌不访问真实上游
+    let sandbox: serde_json::Value = http
+        .post(format!("{}/admin/tokens", gateway.base_url))
+        .bearer_auth(ADMIN_TOKEN)
+        .json(&serde_json::json!({"name": "sandbox", "sandbox": true}))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let resp = http
+        .post(format!("{}/v1/audio/speech", gateway.base_url))
+        .bearer_auth(sandbox["token"].as_str().unwrap())
+        .json(&serde_json::json!({"model": "oa/tts-1", "voice": "alloy", "input": "你好，world"}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 403);
+}
@@ -0,0 +1,263 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use axum::body::Body;
+use axum::response::Response;
+use chrono::Utc;
+use futures_util::StreamExt;
+use serde::Serialize;
+
+use crate::logging::time::BEIJING_OFFSET;
+use crate::logging::types::ProviderEgressDaily;
+use crate::server::storage_traits::RequestLogStore;
+
+/// 内存计数写入 provider_egress_daily 的间隔
+const FLUSH_INTERVAL_SECS: u64 = 60;
+
+type EgressKey = (String, String, String);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct EgressCounters {
+    requests: i64,
+    request_bytes: i64,
+    response_bytes: i64,
+}
+
+/// 上游流量计量：按 (北京时间日期, 供应商, 脱敏密钥) 在内存中累加请求/响应字节数，
+/// 由后台任务定期合并进按天汇总表
+#[derive(Default)]
+pub struct EgressMeter {
+    pending: Mutex<HashMap<EgressKey, EgressCounters>>,
+}
+
+fn today() -> String {
+    Utc::now()
+        .with_timezone(&BEIJING_OFFSET)
+        .format("%Y-%m-%d")
+        .to_string()
+}
+
+/// 请求/响应体序列化为 JSON 后的字节数（转换协议的供应商按 OpenAI 形态近似计量）
+pub fn json_len<T: Serialize>(value: &T) -> i64 {
+    serde_json::to_vec(value).map_or(0, |bytes| bytes.len() as i64)
+}
+
+impl EgressMeter {
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<EgressKey, EgressCounters>> {
+        self.pending.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn add(&self, day: String, provider: &str, api_key: &str, delta: EgressCounters) {
+        let mut pending = self.lock();
+        let entry = pending
+            .entry((day, provider.to_string(), api_key.to_string()))
+            .or_default();
+        entry.requests += delta.requests;
+        entry.request_bytes += delta.request_bytes;
+        entry.response_bytes += delta.response_bytes;
+    }
+
+    /// 记录一次发往上游的请求
+    pub fn record_request(&self, provider: &str, api_key: &str, request_bytes: i64) {
+        self.add(
+            today(),
+            provider,
+            api_key,
+            EgressCounters {
+                requests: 1,
+                request_bytes,
+                response_bytes: 0,
+            },
+        );
+    }
+
+    /// 记录从上游收到的响应字节（流式响应按分片多次调用）
+    pub fn record_response(&self, provider: &str, api_key: &str, response_bytes: i64) {
+        self.add(
+            today(),
+            provider,
+            api_key,
+            EgressCounters {
+                response_bytes,
+                ..Default::default()
+            },
+        );
+    }
+
+    /// 尚未写入数据库的计数
+    pub fn snapshot(&self) -> Vec<ProviderEgressDaily> {
+        to_rows(self.lock().clone())
+    }
+
+    /// 取出并清空尚未写入数据库的计数
+    pub fn take_pending(&self) -> Vec<ProviderEgressDaily> {
+        to_rows(std::mem::take(&mut *self.lock()))
+    }
+
+    /// 写库失败时放回计数，下次再试
+    pub fn restore(&self, rows: Vec<ProviderEgressDaily>) {
+        for row in rows {
+            self.add(
+                row.day,
+                &row.provider,
+                &row.api_key,
+                EgressCounters {
+                    requests: row.requests,
+                    request_bytes: row.request_bytes,
+                    response_bytes: row.response_bytes,
+                },
+            );
+        }
+    }
+
+    /// 包装流式响应：按实际转发的分片字节数计入响应流量
+    pub fn meter_stream_response(
+        self: &Arc<Self>,
+        response: Response,
+        provider: String,
+        api_key: String,
+    ) -> Response {
+        let meter = self.clone();
+        let (parts, body) = response.into_parts();
+        let stream = body.into_data_stream().map(move |chunk| {
+            if let Ok(bytes) = &chunk {
+                meter.record_response(&provider, &api_key, bytes.len() as i64);
+            }
+            chunk
+        });
+        Response::from_parts(parts, Body::from_stream(stream))
+    }
+
+    async fn flush(&self, log_store: &(dyn RequestLogStore + Send + Sync)) -> rusqlite::Result<()> {
+        let rows = self.take_pending();
+        if rows.is_empty() {
+            return Ok(());
+        }
+        if let Err(e) = log_store.add_provider_egress(rows.clone()).await {
+            self.restore(rows);
+            return Err(e);
+        }
+        Ok(())
+    }
+}
+
+fn to_rows(pending: HashMap<EgressKey, EgressCounters>) -> Vec<ProviderEgressDaily> {
+    let mut rows: Vec<ProviderEgressDaily> = pending
+        .into_iter()
+        .map(|((day, provider, api_key), c)| ProviderEgressDaily {
+            day,
+            provider,
+            api_key,
+            requests: c.requests,
+            request_bytes: c.request_bytes,
+            response_bytes: c.response_bytes,
+        })
+        .collect();
+    rows.sort_by(|a, b| (&a.day, &a.provider, &a.api_key).cmp(&(&b.day, &b.provider, &b.api_key)));
+    rows
+}
+
+/// 将未写库的计数叠加到已存储的按天汇总上（用于管理端实时查看）
+pub fn merge_rows(
+    stored: Vec<ProviderEgressDaily>,
+    pending: Vec<ProviderEgressDaily>,
+) -> Vec<ProviderEgressDaily> {
+    let mut merged: HashMap<EgressKey, EgressCounters> = HashMap::new();
+    for row in stored.into_iter().chain(pending) {
+        let entry = merged
+            .entry((row.day, row.provider, row.api_key))
+            .or_default();
+        entry.requests += row.requests;
+        entry.request_bytes += row.request_bytes;
+        entry.response_bytes += row.response_bytes;
+    }
+    to_rows(merged)
+}
+
+/// 定期把内存计数写入 provider_egress_daily；关闭时最后写一次
+pub fn spawn_flush_task(
+    tasks: &Arc<crate::server::tasks::TaskRegistry>,
+    meter: Arc<EgressMeter>,
+    log_store: Arc<dyn RequestLogStore + Send + Sync>,
+) {
+    tasks.spawn_with("egress_flush", |mut ctx| async move {
+        let mut ticker = tokio::time::interval(std::time::Duration::from_secs(FLUSH_INTERVAL_SECS));
+        loop {
+            let stopping = tokio::select! {
+                _ = ticker.tick() => false,
+                _ = ctx.cancelled() => true,
+            };
+            if let Err(e) = meter.flush(log_store.as_ref()).await {
+                tracing::warn!("Failed to flush provider egress counters: {}", e);
+                ctx.report_error(e);
+            }
+            if stopping {
+                break;
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logging::DatabaseLogger;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn accumulates_and_flushes_daily_aggregates() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("egress.db");
+        let logger = DatabaseLogger::new(db_path.to_str().unwrap())
+            .await
+            .unwrap();
+        let meter = Arc::new(EgressMeter::default());
+
+        meter.record_request("openai", "sk-a****1234", 120);
+        meter.record_response("openai", "sk-a****1234", 800);
+        meter.record_request("openai", "sk-a****1234", 80);
+        meter.record_request("anthropic", "****", 50);
+        let response = Response::new(Body::from("data: hello\n\n"));
+        let metered = meter.meter_stream_response(response, "anthropic".into(), "****".into());
+        let body = axum::body::to_bytes(metered.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(body.len(), 13);
+
+        meter.flush(&logger).await.unwrap();
+        assert!(meter.snapshot().is_empty());
+        meter.record_request("openai", "sk-a****1234", 10);
+        meter.flush(&logger).await.unwrap();
+
+        let day = today();
+        let rows = logger.list_provider_egress(&day, &day).await.unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(
+            (
+                rows[0].provider.as_str(),
+                rows[0].requests,
+                rows[0].response_bytes
+            ),
+            ("anthropic", 1, 13)
+        );
+        assert_eq!(
+            (
+                rows[1].requests,
+                rows[1].request_bytes,
+                rows[1].response_bytes
+            ),
+            (3, 210, 800)
+        );
+        assert!(
+            logger
+                .list_provider_egress("2000-01-01", "2000-01-02")
+                .await
+                .unwrap()
+                .is_empty()
+        );
+
+        meter.record_response("openai", "sk-a****1234", 5);
+        let merged = merge_rows(rows, meter.snapshot());
+        assert_eq!(merged[1].response_bytes, 805);
+    }
+}
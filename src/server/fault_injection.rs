@@ -0,0 +1,273 @@
+use std::sync::RwLock;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use axum::body::Body;
+use axum::response::Response;
+use chrono::{DateTime, Utc};
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+
+use crate::error::GatewayError;
+use crate::logging::types::REQ_TYPE_CHAT_FAULT_INJECTED;
+use crate::server::AppState;
+
+const LATENCY_MAX_MS: u64 = 120_000;
+
+/// 故障注入规则（仅保存在内存中，重启后自动关闭；用于验证客户端重试逻辑）
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct FaultInjectionRule {
+    #[serde(default)]
+    pub enabled: bool,
+    /// 命中过滤条件的请求中注入故障的百分比（1-100）
+    #[serde(default)]
+    pub percent: u8,
+    /// 调用上游前额外等待的毫秒数
+    #[serde(default)]
+    pub latency_ms: Option<u64>,
+    /// 直接返回的错误状态码（仅支持 429 / 500），不访问上游
+    #[serde(default)]
+    pub error_status: Option<u16>,
+    /// 流式请求仅转发前 N 个分片后断开（不发送 [DONE]）
+    #[serde(default)]
+    pub truncate_after_chunks: Option<u32>,
+    /// 过滤条件：为空表示不限制
+    #[serde(default)]
+    pub provider: Option<String>,
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub token_id: Option<String>,
+}
+
+/// 单次请求命中的故障
+#[derive(Debug, Clone, PartialEq)]
+pub struct InjectedFault {
+    pub latency_ms: Option<u64>,
+    pub error_status: Option<u16>,
+    pub truncate_after_chunks: Option<u32>,
+}
+
+impl InjectedFault {
+    /// 日志中的故障描述，统一以 `[fault-injection]` 开头便于检索
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(ms) = self.latency_ms {
+            parts.push(format!("latency {}ms", ms));
+        }
+        if let Some(status) = self.error_status {
+            parts.push(format!("error {}", status));
+        }
+        if let Some(n) = self.truncate_after_chunks {
+            parts.push(format!("truncate stream after {} chunks", n));
+        }
+        format!("[fault-injection] {}", parts.join(", "))
+    }
+
+    pub fn error(&self) -> Option<GatewayError> {
+        self.error_status
+            .map(|status| GatewayError::FaultInjected(status, self.describe()))
+    }
+}
+
+fn normalize_filter(value: Option<String>) -> Option<String> {
+    value
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+impl FaultInjectionRule {
+    pub fn validated(mut self) -> Result<Self, GatewayError> {
+        self.provider = normalize_filter(self.provider);
+        self.model = normalize_filter(self.model);
+        self.token_id = normalize_filter(self.token_id);
+        if !self.enabled {
+            return Ok(self);
+        }
+        if self.percent == 0 || self.percent > 100 {
+            return Err(GatewayError::Config(
+                "percent must be between 1 and 100".into(),
+            ));
+        }
+        if let Some(status) = self.error_status
+            && !matches!(status, 429 | 500)
+        {
+            return Err(GatewayError::Config(
+                "error_status must be 429 or 500".into(),
+            ));
+        }
+        if self.latency_ms.is_some_and(|ms| ms > LATENCY_MAX_MS) {
+            return Err(GatewayError::Config(format!(
+                "latency_ms must not exceed {}",
+                LATENCY_MAX_MS
+            )));
+        }
+        if self.truncate_after_chunks == Some(0) {
+            return Err(GatewayError::Config(
+                "truncate_after_chunks must be at least 1".into(),
+            ));
+        }
+        if self.latency_ms.is_none()
+            && self.error_status.is_none()
+            && self.truncate_after_chunks.is_none()
+        {
+            return Err(GatewayError::Config(
+                "at least one of latency_ms / error_status / truncate_after_chunks is required"
+                    .into(),
+            ));
+        }
+        Ok(self)
+    }
+
+    fn matches(&self, provider: &str, model: &str, token_id: &str) -> bool {
+        self.provider.as_deref().is_none_or(|p| p == provider)
+            && self.model.as_deref().is_none_or(|m| m == model)
+            && self.token_id.as_deref().is_none_or(|t| t == token_id)
+    }
+}
+
+/// 故障注入器：内存中的当前规则 + 已注入次数统计
+#[derive(Default)]
+pub struct FaultInjector {
+    current: RwLock<FaultInjectionRule>,
+    injected: AtomicU64,
+}
+
+impl FaultInjector {
+    pub fn snapshot(&self) -> FaultInjectionRule {
+        self.current
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    pub fn injected_total(&self) -> u64 {
+        self.injected.load(Ordering::Relaxed)
+    }
+
+    pub fn set(&self, rule: FaultInjectionRule) -> Result<FaultInjectionRule, GatewayError> {
+        let rule = rule.validated()?;
+        *self.current.write().unwrap_or_else(|e| e.into_inner()) = rule.clone();
+        Ok(rule)
+    }
+
+    /// 按规则与采样比例决定本次请求是否注入故障；非流式请求忽略截断
+    pub fn pick(
+        &self,
+        provider: &str,
+        model: &str,
+        token_id: &str,
+        stream: bool,
+    ) -> Option<InjectedFault> {
+        let rule = self.snapshot();
+        if !rule.enabled || !rule.matches(provider, model, token_id) {
+            return None;
+        }
+        if rand::random_range(0..100u8) >= rule.percent {
+            return None;
+        }
+        let fault = InjectedFault {
+            latency_ms: rule.latency_ms,
+            error_status: rule.error_status,
+            truncate_after_chunks: rule.truncate_after_chunks.filter(|_| stream),
+        };
+        if fault.latency_ms.is_none()
+            && fault.error_status.is_none()
+            && fault.truncate_after_chunks.is_none()
+        {
+            return None;
+        }
+        self.injected.fetch_add(1, Ordering::Relaxed);
+        Some(fault)
+    }
+}
+
+/// 执行注入的延迟，并以 chat_fault_injected 类型单独记录一条日志；
+/// 若规则要求返回错误则返回对应的 GatewayError，调用方不应再访问上游
+#[allow(clippy::too_many_arguments)]
+pub async fn apply_fault(
+    app_state: &AppState,
+    fault: &InjectedFault,
+    start_time: DateTime<Utc>,
+    path: &str,
+    model: &str,
+    provider: &str,
+    client_token_log_id: Option<&str>,
+) -> Result<(), GatewayError> {
+    if let Some(ms) = fault.latency_ms {
+        tokio::time::sleep(std::time::Duration::from_millis(ms)).await;
+    }
+    tracing::warn!(provider, model, "{}", fault.describe());
+    crate::server::request_logging::log_simple_request(
+        app_state,
+        start_time,
+        "POST",
+        path,
+        REQ_TYPE_CHAT_FAULT_INJECTED,
+        Some(model.to_string()),
+        Some(provider.to_string()),
+        client_token_log_id,
+        fault.error_status.unwrap_or(200),
+        Some(fault.describe()),
+    )
+    .await;
+    match fault.error() {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+/// 仅保留响应体的前 N 个分片，模拟上游中途断流
+pub fn truncate_stream_response(response: Response, chunks: u32) -> Response {
+    let (parts, body) = response.into_parts();
+    let stream = body.into_data_stream().take(chunks as usize);
+    Response::from_parts(parts, Body::from_stream(stream))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validated_rejects_unsupported_faults() {
+        let rule = FaultInjectionRule {
+            enabled: true,
+            percent: 50,
+            error_status: Some(503),
+            ..Default::default()
+        };
+        assert!(rule.validated().is_err());
+        let rule = FaultInjectionRule {
+            enabled: true,
+            percent: 50,
+            ..Default::default()
+        };
+        assert!(rule.validated().is_err());
+        let disabled = FaultInjectionRule {
+            provider: Some("  ".into()),
+            ..Default::default()
+        }
+        .validated()
+        .unwrap();
+        assert_eq!(disabled.provider, None);
+    }
+
+    #[test]
+    fn pick_respects_filters_and_stream_only_truncation() {
+        let injector = FaultInjector::default();
+        injector
+            .set(FaultInjectionRule {
+                enabled: true,
+                percent: 100,
+                truncate_after_chunks: Some(1),
+                provider: Some("openai".into()),
+                ..Default::default()
+            })
+            .unwrap();
+        assert!(injector.pick("other", "gpt-4o", "t1", true).is_none());
+        assert!(injector.pick("openai", "gpt-4o", "t1", false).is_none());
+        let fault = injector.pick("openai", "gpt-4o", "t1", true).unwrap();
+        assert_eq!(fault.truncate_after_chunks, Some(1));
+        assert!(fault.describe().starts_with("[fault-injection]"));
+        assert_eq!(injector.injected_total(), 1);
+    }
+}
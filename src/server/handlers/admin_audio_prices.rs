@@ -0,0 +1,218 @@
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::sync::Arc;
+
+use super::auth::{AdminIdentity, require_superadmin};
+use crate::error::GatewayError;
+use crate::logging::types::{
+    AudioPriceRecord, ProviderOpLog, REQ_TYPE_AUDIO_PRICE_DELETE, REQ_TYPE_AUDIO_PRICE_LIST,
+    REQ_TYPE_AUDIO_PRICE_SET,
+};
+use crate::server::AppState;
+use crate::server::request_logging::log_simple_request;
+use crate::server::util::{bearer_token, token_for_log};
+
+#[derive(Debug, Deserialize)]
+pub struct AudioPriceListQuery {
+    #[serde(default)]
+    pub provider: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AudioPricePayload {
+    pub model: String,
+    pub price_per_minute: f64,
+    #[serde(default)]
+    pub currency: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AudioPriceKeyQuery {
+    pub model: String,
+}
+
+async fn log_admin_call<T>(
+    app_state: &AppState,
+    start_time: DateTime<Utc>,
+    method: &str,
+    path: &str,
+    request_type: &str,
+    provided_token: Option<&str>,
+    result: &Result<T, GatewayError>,
+) {
+    let (code, err) = match result {
+        Ok(_) => (200, None),
+        Err(e) => (e.status_code().as_u16(), Some(e.to_string())),
+    };
+    log_simple_request(
+        app_state,
+        start_time,
+        method,
+        path,
+        request_type,
+        None,
+        None,
+        token_for_log(provided_token),
+        code,
+        err,
+    )
+    .await;
+}
+
+async fn log_price_op(
+    app_state: &AppState,
+    start_time: DateTime<Utc>,
+    operation: &str,
+    provider: &str,
+    actor: &AdminIdentity,
+    details: Option<String>,
+) {
+    let _ = app_state
+        .log_store
+        .log_provider_op(ProviderOpLog {
+            id: None,
+            timestamp: start_time,
+            operation: operation.to_string(),
+            provider: Some(provider.to_string()),
+            details,
+            actor: Some(actor.actor()),
+        })
+        .await;
+}
+
+/// 语音转写单价列表（可按供应商过滤）
+pub async fn list_audio_prices(
+    Query(query): Query<AudioPriceListQuery>,
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<AudioPriceRecord>>, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    let result = async {
+        require_superadmin(&headers, &app_state).await?;
+        Ok(app_state
+            .log_store
+            .list_audio_prices(query.provider.as_deref())
+            .await?)
+    }
+    .await;
+    log_admin_call(
+        &app_state,
+        start_time,
+        "GET",
+        "/admin/audio-prices",
+        REQ_TYPE_AUDIO_PRICE_LIST,
+        provided_token.as_deref(),
+        &result,
+    )
+    .await;
+    result.map(Json)
+}
+
+/// 设置供应商某个转写模型的每分钟单价（按实际音频秒数折算）
+pub async fn set_audio_price(
+    Path(provider): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(payload): Json<AudioPricePayload>,
+) -> Result<Json<AudioPriceRecord>, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    let result = async {
+        let identity = require_superadmin(&headers, &app_state).await?;
+        if payload.model.trim().is_empty() {
+            return Err(GatewayError::Config("model is required".into()));
+        }
+        if !payload.price_per_minute.is_finite() || payload.price_per_minute < 0.0 {
+            return Err(GatewayError::Config(
+                "price_per_minute must be a non-negative number".into(),
+            ));
+        }
+        if app_state.providers.get_provider(&provider).await?.is_none() {
+            return Err(GatewayError::NotFound(format!(
+                "Provider '{}' not found",
+                provider
+            )));
+        }
+        let record = AudioPriceRecord {
+            provider: provider.clone(),
+            model: payload.model.trim().to_string(),
+            price_per_minute: payload.price_per_minute,
+            currency: payload.currency,
+            updated_at: start_time,
+        };
+        app_state
+            .log_store
+            .upsert_audio_price(record.clone())
+            .await?;
+        log_price_op(
+            &app_state,
+            start_time,
+            REQ_TYPE_AUDIO_PRICE_SET,
+            &provider,
+            &identity,
+            serde_json::to_string(&record).ok(),
+        )
+        .await;
+        Ok(record)
+    }
+    .await;
+    log_admin_call(
+        &app_state,
+        start_time,
+        "PUT",
+        &format!("/admin/audio-prices/{}", provider),
+        REQ_TYPE_AUDIO_PRICE_SET,
+        provided_token.as_deref(),
+        &result,
+    )
+    .await;
+    result.map(Json)
+}
+
+pub async fn delete_audio_price(
+    Path(provider): Path<String>,
+    Query(query): Query<AudioPriceKeyQuery>,
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<StatusCode, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    let result = async {
+        let identity = require_superadmin(&headers, &app_state).await?;
+        if !app_state
+            .log_store
+            .delete_audio_price(&provider, &query.model)
+            .await?
+        {
+            return Err(GatewayError::NotFound("audio price not found".into()));
+        }
+        log_price_op(
+            &app_state,
+            start_time,
+            REQ_TYPE_AUDIO_PRICE_DELETE,
+            &provider,
+            &identity,
+            Some(query.model.clone()),
+        )
+        .await;
+        Ok(StatusCode::NO_CONTENT)
+    }
+    .await;
+    log_admin_call(
+        &app_state,
+        start_time,
+        "DELETE",
+        &format!("/admin/audio-prices/{}", provider),
+        REQ_TYPE_AUDIO_PRICE_DELETE,
+        provided_token.as_deref(),
+        &result,
+    )
+    .await;
+    result
+}
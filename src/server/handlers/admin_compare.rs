@@ -0,0 +1,318 @@
+use axum::{Json, extract::State, http::HeaderMap};
+use chrono::Utc;
+use futures_util::future::join_all;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use super::auth::require_superadmin;
+use crate::error::GatewayError;
+use crate::logging::types::REQ_TYPE_ADMIN_COMPARE;
+use crate::providers::openai::types::RawAndTypedChatCompletion;
+use crate::providers::openai::usage::resolved_usage;
+use crate::server::AppState;
+use crate::server::chat_request::GatewayChatCompletionRequest;
+use crate::server::model_redirect::apply_provider_model_redirects_to_parsed_model;
+use crate::server::pricing::resolve_model_pricing;
+use crate::server::provider_dispatch::{
+    call_provider_with_parsed_model, select_provider_for_model,
+};
+use crate::server::request_lab::build_request_payload_snapshot;
+use crate::server::request_logging::{ChatLogContext, log_chat_request, log_simple_request};
+use crate::server::response_text::response_summary;
+use crate::server::util::{bearer_token, mask_key, token_for_log};
+
+const PATH: &str = "/admin/compare";
+const MAX_TARGETS: usize = 8;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CompareTarget {
+    pub provider: String,
+    pub model: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AdminComparePayload {
+    pub targets: Vec<CompareTarget>,
+    pub request: GatewayChatCompletionRequest,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AdminCompareItem {
+    pub provider: String,
+    pub model: String,
+    pub upstream_model: Option<String>,
+    pub success: bool,
+    pub request_id: Option<i64>,
+    pub response_time_ms: i64,
+    pub prompt_tokens: Option<u32>,
+    pub completion_tokens: Option<u32>,
+    pub total_tokens: Option<u32>,
+    /// 按模型价格估算的费用；未设置价格时为空
+    pub cost: Option<f64>,
+    pub output_summary: Option<String>,
+    pub response: Option<serde_json::Value>,
+    pub error_message: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AdminCompareResponse {
+    pub items: Vec<AdminCompareItem>,
+}
+
+fn validate_targets(targets: &[CompareTarget]) -> Result<(), GatewayError> {
+    if targets.is_empty() {
+        return Err(GatewayError::Config("targets 不能为空".into()));
+    }
+    if targets.len() > MAX_TARGETS {
+        return Err(GatewayError::Config(format!(
+            "targets 数量不能超过 {}",
+            MAX_TARGETS
+        )));
+    }
+    if targets
+        .iter()
+        .any(|t| t.provider.trim().is_empty() || t.model.trim().is_empty())
+    {
+        return Err(GatewayError::Config("provider 与 model 均不能为空".into()));
+    }
+    Ok(())
+}
+
+async fn log_failure(
+    app_state: &Arc<AppState>,
+    start_time: chrono::DateTime<Utc>,
+    provided_token: Option<&str>,
+    e: &GatewayError,
+) {
+    log_simple_request(
+        app_state,
+        start_time,
+        "POST",
+        PATH,
+        REQ_TYPE_ADMIN_COMPARE,
+        None,
+        None,
+        provided_token,
+        e.status_code().as_u16(),
+        Some(e.to_string()),
+    )
+    .await;
+}
+
+async fn usage_cost(
+    app_state: &AppState,
+    provider: &str,
+    billing_model: &str,
+    dual: &RawAndTypedChatCompletion,
+) -> Option<f64> {
+    let usage = resolved_usage(&dual.raw, &dual.typed)?;
+    let price = app_state
+        .log_store
+        .get_model_price(provider, billing_model)
+        .await
+        .ok()??;
+    Some(
+        (usage.prompt_tokens as f64 * price.prompt_price_per_million
+            + usage.completion_tokens as f64 * price.completion_price_per_million)
+            / 1_000_000.0,
+    )
+}
+
+fn failed_item(
+    target: &CompareTarget,
+    start: chrono::DateTime<Utc>,
+    e: &GatewayError,
+) -> AdminCompareItem {
+    AdminCompareItem {
+        provider: target.provider.clone(),
+        model: target.model.clone(),
+        upstream_model: None,
+        success: false,
+        request_id: None,
+        response_time_ms: (Utc::now() - start).num_milliseconds(),
+        prompt_tokens: None,
+        completion_tokens: None,
+        total_tokens: None,
+        cost: None,
+        output_summary: None,
+        response: None,
+        error_message: Some(e.to_string()),
+    }
+}
+
+async fn run_target(
+    app_state: &AppState,
+    target: CompareTarget,
+    base: &GatewayChatCompletionRequest,
+    snapshot: &str,
+) -> AdminCompareItem {
+    let start_time = Utc::now();
+    let requested_model = format!("{}/{}", target.provider.trim(), target.model.trim());
+    let (selected, mut parsed_model) =
+        match select_provider_for_model(app_state, &requested_model, None).await {
+            Ok(v) => v,
+            Err(e) => return failed_item(&target, start_time, &e),
+        };
+    if let Err(e) = apply_provider_model_redirects_to_parsed_model(
+        app_state,
+        &selected.provider.name,
+        &mut parsed_model,
+    )
+    .await
+    {
+        return failed_item(&target, start_time, &e);
+    }
+    let upstream_model = parsed_model.get_upstream_model_name().to_string();
+    let billing_model = match resolve_model_pricing(
+        app_state,
+        &selected.provider.name,
+        &upstream_model,
+        None,
+    )
+    .await
+    {
+        Ok(pricing) => pricing.billing_model,
+        Err(e) => return failed_item(&target, start_time, &e),
+    };
+
+    let mut request = base.request.clone();
+    request.model = requested_model.clone();
+    let upstream_started_at = Utc::now();
+    let response = call_provider_with_parsed_model(
+        app_state,
+        &selected,
+        &request,
+        &parsed_model,
+        base.top_k,
+        None,
+    )
+    .await;
+    let upstream_finished_at = Utc::now();
+    let response: Result<RawAndTypedChatCompletion, GatewayError> = match response {
+        Ok(dual) if dual.raw.get("error").is_some() && dual.raw.get("choices").is_none() => Err(
+            GatewayError::Config(format!("upstream returned error payload: {}", dual.raw)),
+        ),
+        other => other,
+    };
+
+    // client_token 传 None：对比请求只记录在管理员名下，不计入任何客户端令牌
+    let logged = log_chat_request(
+        app_state,
+        start_time,
+        &billing_model,
+        &requested_model,
+        &upstream_model,
+        &selected.provider.name,
+        &selected.api_key,
+        None,
+        &response,
+        ChatLogContext {
+            path: PATH.to_string(),
+            request_type: REQ_TYPE_ADMIN_COMPARE.to_string(),
+            request_payload_snapshot: Some(snapshot.to_string()),
+            upstream_status: Some(if response.is_ok() { 200 } else { 500 }),
+            selected_provider: Some(selected.provider.name.clone()),
+            selected_key_id: Some(mask_key(&selected.api_key)),
+            first_token_latency_ms: None,
+            param_policy_applied: None,
+            provider_override: None,
+            prompt_truncation: None,
+            debug_capture: false,
+            upstream_started_at: Some(upstream_started_at),
+            upstream_finished_at: Some(upstream_finished_at),
+            prompt_profile: Default::default(),
+        },
+    )
+    .await;
+
+    match response {
+        Ok(dual) => {
+            let usage = resolved_usage(&dual.raw, &dual.typed);
+            AdminCompareItem {
+                provider: target.provider,
+                model: target.model,
+                upstream_model: Some(upstream_model),
+                success: true,
+                request_id: logged.log_id,
+                response_time_ms: logged.response_time_ms,
+                prompt_tokens: usage.as_ref().map(|u| u.prompt_tokens),
+                completion_tokens: usage.as_ref().map(|u| u.completion_tokens),
+                total_tokens: usage.as_ref().map(|u| u.total_tokens),
+                cost: usage_cost(app_state, &selected.provider.name, &billing_model, &dual).await,
+                output_summary: response_summary(&dual, 600),
+                response: Some(dual.raw),
+                error_message: None,
+            }
+        }
+        Err(e) => AdminCompareItem {
+            upstream_model: Some(upstream_model),
+            request_id: logged.log_id,
+            response_time_ms: logged.response_time_ms,
+            ..failed_item(&target, start_time, &e)
+        },
+    }
+}
+
+/// 管理员将同一请求并行发送到多个供应商/模型，返回响应、耗时与费用对比。
+/// 请求不关联任何客户端令牌，因此不会扣减令牌或用户额度。
+pub async fn admin_compare(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(mut payload): Json<AdminComparePayload>,
+) -> Result<Json<AdminCompareResponse>, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    if let Err(e) = require_superadmin(&headers, &app_state).await {
+        log_failure(&app_state, start_time, provided_token.as_deref(), &e).await;
+        return Err(e);
+    }
+    // 对比端点只走非流式路径
+    payload.request.request.stream = Some(false);
+    let snapshot = validate_targets(&payload.targets).and_then(|_| {
+        build_request_payload_snapshot(&payload.request.request, payload.request.top_k)
+    });
+    let snapshot = match snapshot {
+        Ok(s) => s,
+        Err(e) => {
+            log_failure(
+                &app_state,
+                start_time,
+                token_for_log(provided_token.as_deref()),
+                &e,
+            )
+            .await;
+            return Err(e);
+        }
+    };
+
+    let items = join_all(
+        payload
+            .targets
+            .iter()
+            .cloned()
+            .map(|target| run_target(&app_state, target, &payload.request, &snapshot)),
+    )
+    .await;
+    Ok(Json(AdminCompareResponse { items }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_targets_rejects_empty_and_oversized_lists() {
+        assert!(validate_targets(&[]).is_err());
+        let target = CompareTarget {
+            provider: "p1".into(),
+            model: "m1".into(),
+        };
+        assert!(validate_targets(std::slice::from_ref(&target)).is_ok());
+        assert!(validate_targets(&vec![target.clone(); MAX_TARGETS + 1]).is_err());
+        let blank = CompareTarget {
+            provider: " ".into(),
+            model: "m1".into(),
+        };
+        assert!(validate_targets(&[blank]).is_err());
+    }
+}
@@ -0,0 +1,214 @@
+use axum::{Json, extract::State, http::HeaderMap};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use super::auth::require_superadmin;
+use crate::config::settings::MigrationBackend;
+use crate::error::GatewayError;
+use crate::server::AppState;
+use crate::server::db_replication::{ReplicationStatus, replication_status};
+use crate::server::degraded::{DegradedStatus, degraded_mode};
+use crate::server::dual_write::{ConsistencyReport, Migration, MigrationStatus, migration};
+use crate::server::request_logging::log_simple_request;
+use crate::server::util::{bearer_token, token_for_log};
+
+#[derive(Debug, Serialize)]
+pub struct DbStatus {
+    /// sqlite | postgres
+    pub backend: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub database_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size_bytes: Option<u64>,
+    /// SQLite 持续备份状态；未启用时为 null
+    pub replication: Option<ReplicationStatus>,
+    /// 双写迁移状态；未启用时省略
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub migration: Option<MigrationStatus>,
+    /// 数据库不可用时的降级状态（令牌快照、暂存日志）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub degraded: Option<DegradedStatus>,
+}
+
+/// 数据库后端与备份状态
+pub async fn get_db_status(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<DbStatus>, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    let result: Result<DbStatus, GatewayError> = async {
+        require_superadmin(&headers, &app_state).await?;
+        let logging = &app_state.config.logging;
+        if let Some(m) = migration() {
+            return Ok(DbStatus {
+                backend: "dual_write",
+                database_path: Some(logging.database_path.clone()),
+                size_bytes: None,
+                replication: None,
+                migration: Some(m.status()),
+                degraded: degraded_mode().map(|d| d.status()),
+            });
+        }
+        if logging.pg_url.is_some() {
+            return Ok(DbStatus {
+                backend: "postgres",
+                database_path: None,
+                size_bytes: None,
+                replication: None,
+                migration: None,
+                degraded: degraded_mode().map(|d| d.status()),
+            });
+        }
+        let size_bytes = tokio::fs::metadata(&logging.database_path)
+            .await
+            .ok()
+            .map(|m| m.len());
+        Ok(DbStatus {
+            backend: "sqlite",
+            database_path: Some(logging.database_path.clone()),
+            size_bytes,
+            replication: replication_status(),
+            migration: None,
+            degraded: degraded_mode().map(|d| d.status()),
+        })
+    }
+    .await;
+    let (code, err) = match &result {
+        Ok(_) => (200, None),
+        Err(e) => (e.status_code().as_u16(), Some(e.to_string())),
+    };
+    log_simple_request(
+        &app_state,
+        start_time,
+        "GET",
+        "/admin/db/status",
+        "admin_db_status",
+        None,
+        None,
+        token_for_log(provided_token.as_deref()),
+        code,
+        err,
+    )
+    .await;
+    result.map(Json)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SwitchPrimaryRequest {
+    pub primary: MigrationBackend,
+    /// 跳过切换前的一致性检查
+    #[serde(default)]
+    pub force: bool,
+}
+
+fn enabled_migration() -> Result<Arc<Migration>, GatewayError> {
+    migration().ok_or_else(|| GatewayError::NotFound("dual-write migration is not enabled".into()))
+}
+
+async fn log_migration_request<T>(
+    app_state: &AppState,
+    start_time: chrono::DateTime<Utc>,
+    method: &str,
+    path: &str,
+    req_type: &str,
+    provided_token: Option<&str>,
+    result: &Result<T, GatewayError>,
+) {
+    let (code, err) = match result {
+        Ok(_) => (200, None),
+        Err(e) => (e.status_code().as_u16(), Some(e.to_string())),
+    };
+    log_simple_request(
+        app_state,
+        start_time,
+        method,
+        path,
+        req_type,
+        None,
+        None,
+        token_for_log(provided_token),
+        code,
+        err,
+    )
+    .await;
+}
+
+/// 双写迁移状态（主后端、镜像写失败、最近一次一致性检查）
+pub async fn get_migration_status(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<MigrationStatus>, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    let result: Result<MigrationStatus, GatewayError> = async {
+        require_superadmin(&headers, &app_state).await?;
+        Ok(enabled_migration()?.status())
+    }
+    .await;
+    log_migration_request(
+        &app_state,
+        start_time,
+        "GET",
+        "/admin/db/migration",
+        "admin_db_migration_status",
+        provided_token.as_deref(),
+        &result,
+    )
+    .await;
+    result.map(Json)
+}
+
+/// 立即执行一次一致性检查
+pub async fn check_migration(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<ConsistencyReport>, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    let result: Result<ConsistencyReport, GatewayError> = async {
+        require_superadmin(&headers, &app_state).await?;
+        enabled_migration()?.check().await
+    }
+    .await;
+    log_migration_request(
+        &app_state,
+        start_time,
+        "POST",
+        "/admin/db/migration/check",
+        "admin_db_migration_check",
+        provided_token.as_deref(),
+        &result,
+    )
+    .await;
+    result.map(Json)
+}
+
+/// 运行期切换双写的主后端；两端存在差异时返回 409，除非 force=true
+pub async fn switch_migration_primary(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(req): Json<SwitchPrimaryRequest>,
+) -> Result<Json<MigrationStatus>, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    let result: Result<MigrationStatus, GatewayError> = async {
+        require_superadmin(&headers, &app_state).await?;
+        enabled_migration()?
+            .switch_primary(req.primary, req.force)
+            .await
+    }
+    .await;
+    log_migration_request(
+        &app_state,
+        start_time,
+        "POST",
+        "/admin/db/migration/switch",
+        "admin_db_migration_switch",
+        provided_token.as_deref(),
+        &result,
+    )
+    .await;
+    result.map(Json)
+}
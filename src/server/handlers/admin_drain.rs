@@ -0,0 +1,99 @@
+use axum::{Json, extract::State, http::HeaderMap};
+use chrono::Utc;
+use std::sync::Arc;
+
+use super::auth::require_superadmin;
+use crate::error::GatewayError;
+use crate::server::AppState;
+use crate::server::drain::{DrainStatus, drain_tracker};
+use crate::server::request_logging::log_simple_request;
+use crate::server::util::{bearer_token, token_for_log};
+
+/// 关闭排空进度：进行中的请求/流数量与已排空时长（未关闭时 draining=false）
+pub async fn get_drain_status(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<DrainStatus>, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    if let Err(e) = require_superadmin(&headers, &app_state).await {
+        log_simple_request(
+            &app_state,
+            start_time,
+            "GET",
+            "/admin/drain-status",
+            "admin_drain_status",
+            None,
+            None,
+            provided_token.as_deref(),
+            e.status_code().as_u16(),
+            Some(e.to_string()),
+        )
+        .await;
+        return Err(e);
+    }
+    log_simple_request(
+        &app_state,
+        start_time,
+        "GET",
+        "/admin/drain-status",
+        "admin_drain_status",
+        None,
+        None,
+        token_for_log(provided_token.as_deref()),
+        200,
+        None,
+    )
+    .await;
+    Ok(Json(drain_tracker().status()))
+}
+
+/// 强制结束排空：不再等待剩余请求，进程立即退出（仅在关闭排空阶段可用）
+pub async fn force_drain(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<DrainStatus>, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    if let Err(e) = require_superadmin(&headers, &app_state).await {
+        log_simple_request(
+            &app_state,
+            start_time,
+            "POST",
+            "/admin/drain-status/force",
+            "admin_drain_force",
+            None,
+            None,
+            provided_token.as_deref(),
+            e.status_code().as_u16(),
+            Some(e.to_string()),
+        )
+        .await;
+        return Err(e);
+    }
+    let tracker = drain_tracker();
+    let result = if tracker.force() {
+        tracing::warn!("Drain force-completed by admin");
+        Ok(tracker.status())
+    } else {
+        Err(GatewayError::Conflict("gateway is not draining".into()))
+    };
+    let (code, err) = match &result {
+        Ok(_) => (200, None),
+        Err(e) => (e.status_code().as_u16(), Some(e.to_string())),
+    };
+    log_simple_request(
+        &app_state,
+        start_time,
+        "POST",
+        "/admin/drain-status/force",
+        "admin_drain_force",
+        None,
+        None,
+        token_for_log(provided_token.as_deref()),
+        code,
+        err,
+    )
+    .await;
+    result.map(Json)
+}
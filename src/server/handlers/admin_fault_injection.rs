@@ -0,0 +1,114 @@
+use axum::{Json, extract::State, http::HeaderMap};
+use chrono::Utc;
+use serde::Serialize;
+use std::sync::Arc;
+
+use super::auth::require_superadmin;
+use crate::error::GatewayError;
+use crate::server::AppState;
+use crate::server::fault_injection::FaultInjectionRule;
+use crate::server::request_logging::log_simple_request;
+use crate::server::util::{bearer_token, token_for_log};
+
+#[derive(Debug, Serialize)]
+pub struct FaultInjectionOut {
+    pub rule: FaultInjectionRule,
+    /// 自启动（或进程内）以来累计注入的故障次数
+    pub injected_total: u64,
+}
+
+fn fault_injection_out(app_state: &AppState) -> FaultInjectionOut {
+    FaultInjectionOut {
+        rule: app_state.fault_injector.snapshot(),
+        injected_total: app_state.fault_injector.injected_total(),
+    }
+}
+
+pub async fn get_fault_injection(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<FaultInjectionOut>, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    if let Err(e) = require_superadmin(&headers, &app_state).await {
+        let code = e.status_code().as_u16();
+        log_simple_request(
+            &app_state,
+            start_time,
+            "GET",
+            "/admin/fault-injection",
+            "admin_fault_injection_get",
+            None,
+            None,
+            provided_token.as_deref(),
+            code,
+            Some(e.to_string()),
+        )
+        .await;
+        return Err(e);
+    }
+    log_simple_request(
+        &app_state,
+        start_time,
+        "GET",
+        "/admin/fault-injection",
+        "admin_fault_injection_get",
+        None,
+        None,
+        token_for_log(provided_token.as_deref()),
+        200,
+        None,
+    )
+    .await;
+    Ok(Json(fault_injection_out(&app_state)))
+}
+
+/// 设置故障注入规则（立即生效，仅保存在内存中）；提交 `{"enabled": false}` 即可关闭
+pub async fn put_fault_injection(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(rule): Json<FaultInjectionRule>,
+) -> Result<Json<FaultInjectionOut>, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    if let Err(e) = require_superadmin(&headers, &app_state).await {
+        let code = e.status_code().as_u16();
+        log_simple_request(
+            &app_state,
+            start_time,
+            "PUT",
+            "/admin/fault-injection",
+            "admin_fault_injection_put",
+            None,
+            None,
+            provided_token.as_deref(),
+            code,
+            Some(e.to_string()),
+        )
+        .await;
+        return Err(e);
+    }
+
+    let result = app_state.fault_injector.set(rule);
+    let (code, err) = match &result {
+        Ok(rule) => {
+            tracing::warn!(enabled = rule.enabled, "fault injection rule updated");
+            (200, None)
+        }
+        Err(e) => (e.status_code().as_u16(), Some(e.to_string())),
+    };
+    log_simple_request(
+        &app_state,
+        start_time,
+        "PUT",
+        "/admin/fault-injection",
+        "admin_fault_injection_put",
+        None,
+        None,
+        token_for_log(provided_token.as_deref()),
+        code,
+        err,
+    )
+    .await;
+    result.map(|_| Json(fault_injection_out(&app_state)))
+}
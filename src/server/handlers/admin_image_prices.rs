@@ -0,0 +1,240 @@
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::sync::Arc;
+
+use super::auth::{AdminIdentity, require_superadmin};
+use crate::error::GatewayError;
+use crate::logging::types::{
+    ImagePriceRecord, ProviderOpLog, REQ_TYPE_IMAGE_PRICE_DELETE, REQ_TYPE_IMAGE_PRICE_LIST,
+    REQ_TYPE_IMAGE_PRICE_SET,
+};
+use crate::server::AppState;
+use crate::server::image_generation::IMAGE_PRICE_WILDCARD;
+use crate::server::request_logging::log_simple_request;
+use crate::server::util::{bearer_token, token_for_log};
+
+#[derive(Debug, Deserialize)]
+pub struct ImagePriceListQuery {
+    #[serde(default)]
+    pub provider: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ImagePricePayload {
+    pub model: String,
+    /// 为空时匹配任意尺寸
+    #[serde(default)]
+    pub size: Option<String>,
+    /// 为空时匹配任意质量档位
+    #[serde(default)]
+    pub quality: Option<String>,
+    pub price_per_image: f64,
+    #[serde(default)]
+    pub currency: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ImagePriceKeyQuery {
+    pub model: String,
+    #[serde(default)]
+    pub size: Option<String>,
+    #[serde(default)]
+    pub quality: Option<String>,
+}
+
+fn key_part(value: Option<String>) -> String {
+    value
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+        .unwrap_or_else(|| IMAGE_PRICE_WILDCARD.to_string())
+}
+
+async fn log_admin_call<T>(
+    app_state: &AppState,
+    start_time: DateTime<Utc>,
+    method: &str,
+    path: &str,
+    request_type: &str,
+    provided_token: Option<&str>,
+    result: &Result<T, GatewayError>,
+) {
+    let (code, err) = match result {
+        Ok(_) => (200, None),
+        Err(e) => (e.status_code().as_u16(), Some(e.to_string())),
+    };
+    log_simple_request(
+        app_state,
+        start_time,
+        method,
+        path,
+        request_type,
+        None,
+        None,
+        token_for_log(provided_token),
+        code,
+        err,
+    )
+    .await;
+}
+
+async fn log_price_op(
+    app_state: &AppState,
+    start_time: DateTime<Utc>,
+    operation: &str,
+    provider: &str,
+    actor: &AdminIdentity,
+    details: Option<String>,
+) {
+    let _ = app_state
+        .log_store
+        .log_provider_op(ProviderOpLog {
+            id: None,
+            timestamp: start_time,
+            operation: operation.to_string(),
+            provider: Some(provider.to_string()),
+            details,
+            actor: Some(actor.actor()),
+        })
+        .await;
+}
+
+/// 图片单价列表（可按供应商过滤）
+pub async fn list_image_prices(
+    Query(query): Query<ImagePriceListQuery>,
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<ImagePriceRecord>>, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    let result = async {
+        require_superadmin(&headers, &app_state).await?;
+        Ok(app_state
+            .log_store
+            .list_image_prices(query.provider.as_deref())
+            .await?)
+    }
+    .await;
+    log_admin_call(
+        &app_state,
+        start_time,
+        "GET",
+        "/admin/image-prices",
+        REQ_TYPE_IMAGE_PRICE_LIST,
+        provided_token.as_deref(),
+        &result,
+    )
+    .await;
+    result.map(Json)
+}
+
+/// 设置供应商某个图片模型的单价；size/quality 省略时作为该模型的兜底价格
+pub async fn set_image_price(
+    Path(provider): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(payload): Json<ImagePricePayload>,
+) -> Result<Json<ImagePriceRecord>, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    let result = async {
+        let identity = require_superadmin(&headers, &app_state).await?;
+        if payload.model.trim().is_empty() {
+            return Err(GatewayError::Config("model is required".into()));
+        }
+        if !payload.price_per_image.is_finite() || payload.price_per_image < 0.0 {
+            return Err(GatewayError::Config(
+                "price_per_image must be a non-negative number".into(),
+            ));
+        }
+        if app_state.providers.get_provider(&provider).await?.is_none() {
+            return Err(GatewayError::NotFound(format!(
+                "Provider '{}' not found",
+                provider
+            )));
+        }
+        let record = ImagePriceRecord {
+            provider: provider.clone(),
+            model: payload.model.trim().to_string(),
+            size: key_part(payload.size),
+            quality: key_part(payload.quality),
+            price_per_image: payload.price_per_image,
+            currency: payload.currency,
+            updated_at: start_time,
+        };
+        app_state
+            .log_store
+            .upsert_image_price(record.clone())
+            .await?;
+        log_price_op(
+            &app_state,
+            start_time,
+            REQ_TYPE_IMAGE_PRICE_SET,
+            &provider,
+            &identity,
+            serde_json::to_string(&record).ok(),
+        )
+        .await;
+        Ok(record)
+    }
+    .await;
+    log_admin_call(
+        &app_state,
+        start_time,
+        "PUT",
+        &format!("/admin/image-prices/{}", provider),
+        REQ_TYPE_IMAGE_PRICE_SET,
+        provided_token.as_deref(),
+        &result,
+    )
+    .await;
+    result.map(Json)
+}
+
+pub async fn delete_image_price(
+    Path(provider): Path<String>,
+    Query(query): Query<ImagePriceKeyQuery>,
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<StatusCode, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    let result = async {
+        let identity = require_superadmin(&headers, &app_state).await?;
+        let size = key_part(query.size);
+        let quality = key_part(query.quality);
+        if !app_state
+            .log_store
+            .delete_image_price(&provider, &query.model, &size, &quality)
+            .await?
+        {
+            return Err(GatewayError::NotFound("image price not found".into()));
+        }
+        log_price_op(
+            &app_state,
+            start_time,
+            REQ_TYPE_IMAGE_PRICE_DELETE,
+            &provider,
+            &identity,
+            Some(format!("{} {} {}", query.model, size, quality)),
+        )
+        .await;
+        Ok(StatusCode::NO_CONTENT)
+    }
+    .await;
+    log_admin_call(
+        &app_state,
+        start_time,
+        "DELETE",
+        &format!("/admin/image-prices/{}", provider),
+        REQ_TYPE_IMAGE_PRICE_DELETE,
+        provided_token.as_deref(),
+        &result,
+    )
+    .await;
+    result
+}
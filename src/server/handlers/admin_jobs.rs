@@ -0,0 +1,178 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::HeaderMap,
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::sync::Arc;
+
+use super::auth::require_superadmin;
+use crate::error::GatewayError;
+use crate::server::AppState;
+use crate::server::jobs::{JobInfo, JobScheduler, job_scheduler};
+use crate::server::request_logging::log_simple_request;
+use crate::server::util::{bearer_token, token_for_log};
+
+#[derive(Debug, Serialize)]
+pub struct JobsOut {
+    pub jobs: Vec<JobInfo>,
+}
+
+async fn log_admin_call<T>(
+    app_state: &AppState,
+    start_time: DateTime<Utc>,
+    method: &str,
+    path: &str,
+    request_type: &str,
+    provided_token: Option<&str>,
+    result: &Result<T, GatewayError>,
+) {
+    let (code, err) = match result {
+        Ok(_) => (200, None),
+        Err(e) => (e.status_code().as_u16(), Some(e.to_string())),
+    };
+    log_simple_request(
+        app_state,
+        start_time,
+        method,
+        path,
+        request_type,
+        None,
+        None,
+        token_for_log(provided_token),
+        code,
+        err,
+    )
+    .await;
+}
+
+fn scheduler() -> Result<Arc<JobScheduler>, GatewayError> {
+    job_scheduler().ok_or_else(|| GatewayError::NotFound("job scheduler is not running".into()))
+}
+
+/// 全部定时任务及其计划、最近运行结果与下一次执行时间
+pub async fn list_jobs(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<JobsOut>, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    let result = async {
+        require_superadmin(&headers, &app_state).await?;
+        Ok(JobsOut {
+            jobs: scheduler()?.list(),
+        })
+    }
+    .await;
+    log_admin_call(
+        &app_state,
+        start_time,
+        "GET",
+        "/admin/jobs",
+        "admin_jobs_list",
+        provided_token.as_deref(),
+        &result,
+    )
+    .await;
+    result.map(Json)
+}
+
+pub async fn get_job(
+    Path(name): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<JobInfo>, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    let result = async {
+        require_superadmin(&headers, &app_state).await?;
+        scheduler()?.get(&name)
+    }
+    .await;
+    log_admin_call(
+        &app_state,
+        start_time,
+        "GET",
+        &format!("/admin/jobs/{}", name),
+        "admin_job_get",
+        provided_token.as_deref(),
+        &result,
+    )
+    .await;
+    result.map(Json)
+}
+
+/// 立即运行一次；任务正在运行时返回 409
+pub async fn run_job(
+    Path(name): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<JobInfo>, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    let result = async {
+        require_superadmin(&headers, &app_state).await?;
+        let info = scheduler()?.trigger(&name)?;
+        tracing::info!("Job {} triggered by admin", name);
+        Ok(info)
+    }
+    .await;
+    log_admin_call(
+        &app_state,
+        start_time,
+        "POST",
+        &format!("/admin/jobs/{}/run", name),
+        "admin_job_run",
+        provided_token.as_deref(),
+        &result,
+    )
+    .await;
+    result.map(Json)
+}
+
+async fn set_paused(
+    app_state: Arc<AppState>,
+    headers: HeaderMap,
+    name: String,
+    paused: bool,
+) -> Result<Json<JobInfo>, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    let action = if paused { "pause" } else { "resume" };
+    let result = async {
+        require_superadmin(&headers, &app_state).await?;
+        let info = scheduler()?.set_paused(&name, paused).await?;
+        tracing::info!("Job {} {}d by admin", name, action);
+        Ok(info)
+    }
+    .await;
+    log_admin_call(
+        &app_state,
+        start_time,
+        "POST",
+        &format!("/admin/jobs/{}/{}", name, action),
+        &format!("admin_job_{}", action),
+        provided_token.as_deref(),
+        &result,
+    )
+    .await;
+    result.map(Json)
+}
+
+/// 暂停后不再按计划运行（仍可手动运行），状态持久化，重启后保持
+pub async fn pause_job(
+    Path(name): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<JobInfo>, GatewayError> {
+    set_paused(app_state, headers, name, true).await
+}
+
+pub async fn resume_job(
+    Path(name): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<JobInfo>, GatewayError> {
+    set_paused(app_state, headers, name, false).await
+}
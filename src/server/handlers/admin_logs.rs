@@ -2,19 +2,23 @@ use std::sync::Arc;
 
 use axum::{
     Json,
-    extract::{Query, State},
+    extract::{Path, Query, State},
     http::HeaderMap,
 };
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
-use super::auth::{AdminIdentity, require_superadmin};
+use super::auth::{AdminIdentity, ensure_admin, require_superadmin};
 use crate::error::GatewayError;
+use crate::logging::types::LogColumns;
+use crate::logging::types::ProviderOpLog;
 use crate::logging::types::RequestLog;
 use crate::logging::types::RequestLogDetailRecord;
 use crate::server::AppState;
+use crate::server::log_fields::{FieldSelection, LogFields, Projected};
 use crate::server::model_display::format_model_display_name;
 use crate::server::request_logging::log_simple_request;
+use crate::server::stream_transcript::{self, Transcript};
 
 const MAX_LOG_LIMIT: usize = 1000;
 const DEFAULT_LOG_LIMIT: usize = 200;
@@ -33,6 +37,19 @@ pub struct OpsQuery {
     pub provider: Option<String>,
 }
 
+/// `GET /admin/providers/{provider}/ops` 的查询参数；since/until 为 RFC3339 时间，区间左闭右开
+#[derive(Debug, Deserialize, Default)]
+pub struct ProviderOpsQuery {
+    #[serde(default)]
+    pub limit: Option<usize>,
+    #[serde(default)]
+    pub cursor: Option<i64>,
+    #[serde(default)]
+    pub since: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub until: Option<DateTime<Utc>>,
+}
+
 #[derive(Debug, Deserialize, Default)]
 pub struct LogsQuery {
     #[serde(default)]
@@ -55,6 +72,12 @@ pub struct LogsQuery {
     pub method: Option<String>,
     #[serde(default)]
     pub path: Option<String>,
+    /// 逗号分隔的输出字段；为空时返回全部字段
+    #[serde(default)]
+    pub fields: Option<String>,
+    /// 组织委派管理员可见的令牌 ID 集合（由服务端填充，不接受查询参数）
+    #[serde(skip)]
+    pub token_scope: Option<std::collections::HashSet<String>>,
 }
 
 #[derive(Debug, Serialize)]
@@ -88,10 +111,56 @@ pub struct RequestLogEntry {
     pub replayable: bool,
 }
 
+impl LogFields for RequestLogEntry {
+    const FIELDS: &'static [&'static str] = &[
+        "id",
+        "timestamp",
+        "method",
+        "path",
+        "request_type",
+        "requested_model",
+        "effective_model",
+        "requested_model_display",
+        "effective_model_display",
+        "model_display",
+        "provider",
+        "api_key",
+        "client_token_id",
+        "client_token_name",
+        "username",
+        "amount_spent",
+        "amount_spent_currency",
+        "status_code",
+        "response_time_ms",
+        "prompt_tokens",
+        "completion_tokens",
+        "total_tokens",
+        "cached_tokens",
+        "reasoning_tokens",
+        "error_message",
+        "success",
+        "replayable",
+    ];
+}
+
+/// 筛选条件依赖的列：带筛选时这些列必须读取，即使不在输出字段中
+const FILTER_COLUMNS: &[&str] = &[
+    "request_type",
+    "method",
+    "path",
+    "provider",
+    "requested_model",
+    "effective_model",
+    "model",
+    "client_token",
+    "api_key",
+    "status_code",
+];
+
 #[derive(Debug, Serialize)]
 pub struct RequestLogsResponse {
     pub total: usize,
-    pub data: Vec<RequestLogEntry>,
+    pub data: Projected<RequestLogEntry>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub next_cursor: Option<i64>,
 }
@@ -102,6 +171,8 @@ pub struct ChatCompletionsQuery {
     pub limit: Option<usize>,
     #[serde(default)]
     pub cursor: Option<i64>,
+    #[serde(default)]
+    pub fields: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -111,6 +182,20 @@ pub struct OperationLogEntry {
     pub operation: String,
     pub provider: Option<String>,
     pub details: Option<String>,
+    pub actor: Option<String>,
+}
+
+impl From<&ProviderOpLog> for OperationLogEntry {
+    fn from(log: &ProviderOpLog) -> Self {
+        Self {
+            id: log.id,
+            timestamp: log.timestamp.to_rfc3339(),
+            operation: log.operation.clone(),
+            provider: log.provider.clone(),
+            details: log.details.clone(),
+            actor: log.actor.clone(),
+        }
+    }
 }
 
 #[derive(Debug, Serialize)]
@@ -248,6 +333,10 @@ fn filter_logs<'a>(logs: &'a [RequestLog], query: &LogsQuery) -> Vec<&'a Request
                 .unwrap_or(false),
             None => true,
         })
+        .filter(|log| match query.token_scope.as_ref() {
+            Some(scope) => log.client_token.as_ref().is_some_and(|t| scope.contains(t)),
+            None => true,
+        })
         .filter(|log| match query.api_key.as_ref() {
             Some(api_key) => log.api_key.as_ref().map(|k| k == api_key).unwrap_or(false),
             None => true,
@@ -271,13 +360,26 @@ fn is_replayable_detail(detail: Option<&RequestLogDetailRecord>) -> bool {
 pub async fn list_request_logs(
     State(app_state): State<Arc<AppState>>,
     headers: HeaderMap,
-    Query(query): Query<LogsQuery>,
+    Query(mut query): Query<LogsQuery>,
 ) -> Result<Json<RequestLogsResponse>, GatewayError> {
-    let identity = require_superadmin(&headers, &app_state).await?;
+    let identity = ensure_admin(&headers, &app_state).await?;
+    if let Some(org) = identity.organization_scope() {
+        query.token_scope = Some(
+            app_state
+                .token_store
+                .list_tokens_by_organization(org)
+                .await?
+                .into_iter()
+                .map(|t| t.id)
+                .collect(),
+        );
+    }
     let limit = query
         .limit
         .unwrap_or(DEFAULT_LOG_LIMIT)
         .clamp(1, MAX_LOG_LIMIT);
+    let fields = FieldSelection::parse::<RequestLogEntry>(query.fields.as_deref())?;
+    let mut columns = fields.columns();
 
     // 若存在任何筛选条件，则分批读取并筛选，直到凑满 limit 或无更多数据
     let has_filters = query.request_type.is_some()
@@ -287,16 +389,19 @@ pub async fn list_request_logs(
         || query.api_key.is_some()
         || query.status.is_some()
         || query.method.is_some()
-        || query.path.is_some();
+        || query.path.is_some()
+        || query.token_scope.is_some();
 
     let (raw_logs, next_cursor) = if has_filters {
+        columns.extend(FILTER_COLUMNS.iter().copied());
         let (logs, next_cur) =
-            get_logs_matching_query(&app_state, limit as i32, query.cursor, &query).await?;
+            get_logs_matching_query(&app_state, limit as i32, query.cursor, &query, &columns)
+                .await?;
         (logs, next_cur)
     } else {
         let logs = app_state
             .log_store
-            .get_recent_logs_with_cursor(limit as i32, query.cursor)
+            .get_logs_with_columns(limit as i32, query.cursor, None, &columns)
             .await
             .map_err(GatewayError::Db)?;
         let next_cur = logs
@@ -377,7 +482,8 @@ pub async fn list_request_logs(
         .unwrap_or_default();
     let mut replayable_by_id: std::collections::HashMap<i64, bool> =
         std::collections::HashMap::new();
-    for log in &filtered {
+    // 未请求 replayable 时跳过逐条读取日志详情
+    for log in filtered.iter().filter(|_| fields.includes("replayable")) {
         let replayable = if log.request_type.starts_with("chat_") {
             if let Some(log_id) = log.id {
                 let detail = app_state
@@ -479,6 +585,7 @@ pub async fn list_request_logs(
             }
         })
         .collect();
+    let data = Projected::new(data, fields);
 
     // next_cursor 已在上方计算，避免重复绑定
 
@@ -513,10 +620,16 @@ pub async fn list_chat_completion_logs(
         .limit
         .unwrap_or(DEFAULT_LOG_LIMIT)
         .clamp(1, MAX_LOG_LIMIT) as i32;
+    let fields = FieldSelection::parse::<RequestLogEntry>(query.fields.as_deref())?;
 
     let raw_logs = app_state
         .log_store
-        .get_logs_by_method_path("POST", "/v1/chat/completions", limit, query.cursor)
+        .get_logs_with_columns(
+            limit,
+            query.cursor,
+            Some(("POST", "/v1/chat/completions")),
+            &fields.columns(),
+        )
         .await
         .map_err(GatewayError::Db)?;
 
@@ -647,6 +760,7 @@ pub async fn list_chat_completion_logs(
             }
         })
         .collect();
+    let data = Projected::new(data, fields);
 
     let next_cursor = raw_logs
         .last()
@@ -684,6 +798,7 @@ async fn get_logs_matching_query(
     limit: i32,
     cursor: Option<i64>,
     query: &LogsQuery,
+    columns: &LogColumns,
 ) -> Result<(Vec<RequestLog>, Option<i64>), GatewayError> {
     let page_size = limit.max(100); // 至少读取 100 条以提高命中率
     let mut acc: Vec<RequestLog> = Vec::with_capacity(limit as usize);
@@ -692,7 +807,7 @@ async fn get_logs_matching_query(
     loop {
         let batch = app_state
             .log_store
-            .get_recent_logs_with_cursor(page_size, next)
+            .get_logs_with_columns(page_size, next, None, columns)
             .await
             .map_err(GatewayError::Db)?;
         if batch.is_empty() {
@@ -754,14 +869,8 @@ pub async fn list_operation_logs(
         .collect::<Vec<_>>();
 
     let data = filtered
-        .iter()
-        .map(|log| OperationLogEntry {
-            id: log.id,
-            timestamp: log.timestamp.to_rfc3339(),
-            operation: log.operation.clone(),
-            provider: log.provider.clone(),
-            details: log.details.clone(),
-        })
+        .into_iter()
+        .map(OperationLogEntry::from)
         .collect::<Vec<_>>();
 
     let next_cursor = raw_logs
@@ -789,3 +898,93 @@ pub async fn list_operation_logs(
         next_cursor,
     }))
 }
+
+/// 单个供应商的操作日志（含操作者），按时间倒序，cursor 为上一页返回的 next_cursor
+pub async fn list_provider_ops(
+    Path(provider): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(query): Query<ProviderOpsQuery>,
+) -> Result<Json<OperationLogsResponse>, GatewayError> {
+    let identity = require_superadmin(&headers, &app_state).await?;
+    if let (Some(since), Some(until)) = (query.since, query.until)
+        && since >= until
+    {
+        return Err(GatewayError::Config(
+            "since must be earlier than until".into(),
+        ));
+    }
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_LOG_LIMIT)
+        .clamp(1, MAX_LOG_LIMIT);
+    let logs = app_state
+        .log_store
+        .get_provider_ops_logs_for_provider(
+            &provider,
+            query.since,
+            query.until,
+            limit as i32,
+            query.cursor,
+        )
+        .await
+        .map_err(GatewayError::Db)?;
+    let next_cursor = logs
+        .last()
+        .and_then(|log| log.id)
+        .filter(|_| logs.len() == limit);
+    let data = logs.iter().map(OperationLogEntry::from).collect::<Vec<_>>();
+
+    log_simple_request(
+        &app_state,
+        Utc::now(),
+        "GET",
+        &format!("/admin/providers/{}/ops", provider),
+        "admin_provider_ops",
+        None,
+        Some(provider),
+        Some(identity_label(&identity)),
+        200,
+        None,
+    )
+    .await;
+
+    Ok(Json(OperationLogsResponse {
+        total: data.len(),
+        data,
+        next_cursor,
+    }))
+}
+
+/// 流式请求的回复重建：按捕获的分片还原最终消息（含工具调用）与每块到达时间，需开启 capture_stream_chunks
+pub async fn get_request_transcript(
+    Path(id): Path<i64>,
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<Transcript>, GatewayError> {
+    let identity = require_superadmin(&headers, &app_state).await?;
+    let result = app_state
+        .log_store
+        .get_stream_capture(id)
+        .await
+        .map_err(GatewayError::Db)?
+        .ok_or_else(|| GatewayError::NotFound(format!("no stream capture for request log {}", id)));
+    let (code, err) = match &result {
+        Ok(_) => (200, None),
+        Err(e) => (e.status_code().as_u16(), Some(e.to_string())),
+    };
+    log_simple_request(
+        &app_state,
+        Utc::now(),
+        "GET",
+        &format!("/admin/logs/{}/transcript", id),
+        "admin_logs_transcript",
+        None,
+        None,
+        Some(identity_label(&identity)),
+        code,
+        err,
+    )
+    .await;
+    result.map(|capture| Json(stream_transcript::reconstruct(capture)))
+}
@@ -0,0 +1,262 @@
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use super::auth::require_superadmin;
+use crate::error::GatewayError;
+use crate::logging::time::parse_datetime_string;
+use crate::logging::types::{
+    MaintenanceWindowRecord, ProviderOpLog, REQ_TYPE_MAINTENANCE_WINDOW_CREATE,
+    REQ_TYPE_MAINTENANCE_WINDOW_DELETE, REQ_TYPE_MAINTENANCE_WINDOW_LIST,
+};
+use crate::server::AppState;
+use crate::server::branding::Branding;
+use crate::server::cluster::{self, CacheEvent};
+use crate::server::maintenance::{self, MaintenanceStatus};
+use crate::server::request_logging::log_simple_request;
+use crate::server::util::{bearer_token, token_for_log};
+
+const MAX_MESSAGE_LEN: usize = 500;
+
+#[derive(Debug, Deserialize)]
+pub struct MaintenanceWindowQuery {
+    /// 同时返回已结束的窗口
+    #[serde(default)]
+    pub include_past: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MaintenanceWindowPayload {
+    pub provider: String,
+    /// RFC3339 时间
+    pub starts_at: String,
+    pub ends_at: String,
+    #[serde(default)]
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GatewayStatus {
+    pub status: &'static str,
+    pub maintenance: MaintenanceStatus,
+    #[serde(skip_serializing_if = "Branding::is_empty")]
+    pub branding: Branding,
+}
+
+async fn log_admin_call<T>(
+    app_state: &AppState,
+    start_time: DateTime<Utc>,
+    method: &str,
+    path: &str,
+    request_type: &str,
+    provided_token: Option<&str>,
+    result: &Result<T, GatewayError>,
+) {
+    let (code, err) = match result {
+        Ok(_) => (200, None),
+        Err(e) => (e.status_code().as_u16(), Some(e.to_string())),
+    };
+    log_simple_request(
+        app_state,
+        start_time,
+        method,
+        path,
+        request_type,
+        None,
+        None,
+        token_for_log(provided_token),
+        code,
+        err,
+    )
+    .await;
+}
+
+fn parse_time(field: &str, raw: &str) -> Result<DateTime<Utc>, GatewayError> {
+    parse_datetime_string(raw.trim())
+        .map_err(|_| GatewayError::Config(format!("{} must be an RFC3339 timestamp", field)))
+}
+
+/// 窗口变更后立即刷新本实例路由，并通知其他副本
+async fn apply_change(app_state: &AppState) -> Result<(), GatewayError> {
+    maintenance::refresh(app_state).await?;
+    cluster::publish(app_state, CacheEvent::MaintenanceWindows);
+    Ok(())
+}
+
+/// 维护窗口列表（按开始时间升序）；默认只含进行中与即将开始的窗口
+pub async fn list_maintenance_windows(
+    Query(query): Query<MaintenanceWindowQuery>,
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<MaintenanceWindowRecord>>, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    let result = async {
+        require_superadmin(&headers, &app_state).await?;
+        let ending_after = if query.include_past {
+            DateTime::<Utc>::UNIX_EPOCH
+        } else {
+            start_time
+        };
+        Ok(app_state
+            .log_store
+            .list_maintenance_windows(ending_after)
+            .await?)
+    }
+    .await;
+    log_admin_call(
+        &app_state,
+        start_time,
+        "GET",
+        "/admin/maintenance-windows",
+        REQ_TYPE_MAINTENANCE_WINDOW_LIST,
+        provided_token.as_deref(),
+        &result,
+    )
+    .await;
+    result.map(Json)
+}
+
+/// 计划一个维护窗口；开始时间已过时立即生效
+pub async fn create_maintenance_window(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(payload): Json<MaintenanceWindowPayload>,
+) -> Result<Json<MaintenanceWindowRecord>, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    let result = async {
+        let identity = require_superadmin(&headers, &app_state).await?;
+        let provider = payload.provider.trim().to_string();
+        if app_state.providers.get_provider(&provider).await?.is_none() {
+            return Err(GatewayError::NotFound(format!(
+                "Provider '{}' not found",
+                provider
+            )));
+        }
+        let starts_at = parse_time("starts_at", &payload.starts_at)?;
+        let ends_at = parse_time("ends_at", &payload.ends_at)?;
+        if ends_at <= starts_at {
+            return Err(GatewayError::Config(
+                "ends_at must be later than starts_at".into(),
+            ));
+        }
+        if ends_at <= start_time {
+            return Err(GatewayError::Config("ends_at must be in the future".into()));
+        }
+        let message = payload
+            .message
+            .map(|m| m.trim().to_string())
+            .filter(|m| !m.is_empty());
+        if message
+            .as_deref()
+            .is_some_and(|m| m.chars().count() > MAX_MESSAGE_LEN)
+        {
+            return Err(GatewayError::Config(format!(
+                "message may contain at most {} characters",
+                MAX_MESSAGE_LEN
+            )));
+        }
+        let mut record = MaintenanceWindowRecord {
+            id: 0,
+            provider: provider.clone(),
+            starts_at,
+            ends_at,
+            message,
+            created_by: Some(identity.actor()),
+            created_at: start_time,
+        };
+        record.id = app_state
+            .log_store
+            .insert_maintenance_window(record.clone())
+            .await?;
+        let _ = app_state
+            .log_store
+            .log_provider_op(ProviderOpLog {
+                id: None,
+                timestamp: start_time,
+                operation: REQ_TYPE_MAINTENANCE_WINDOW_CREATE.to_string(),
+                provider: Some(provider),
+                details: serde_json::to_string(&record).ok(),
+                actor: Some(identity.actor()),
+            })
+            .await;
+        apply_change(&app_state).await?;
+        Ok(record)
+    }
+    .await;
+    log_admin_call(
+        &app_state,
+        start_time,
+        "POST",
+        "/admin/maintenance-windows",
+        REQ_TYPE_MAINTENANCE_WINDOW_CREATE,
+        provided_token.as_deref(),
+        &result,
+    )
+    .await;
+    result.map(Json)
+}
+
+/// 取消维护窗口；进行中的窗口取消后立即恢复路由
+pub async fn delete_maintenance_window(
+    Path(id): Path<i64>,
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<StatusCode, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    let result = async {
+        let identity = require_superadmin(&headers, &app_state).await?;
+        if !app_state.log_store.delete_maintenance_window(id).await? {
+            return Err(GatewayError::NotFound(
+                "maintenance window not found".into(),
+            ));
+        }
+        let _ = app_state
+            .log_store
+            .log_provider_op(ProviderOpLog {
+                id: None,
+                timestamp: start_time,
+                operation: REQ_TYPE_MAINTENANCE_WINDOW_DELETE.to_string(),
+                provider: None,
+                details: Some(serde_json::json!({ "id": id }).to_string()),
+                actor: Some(identity.actor()),
+            })
+            .await;
+        apply_change(&app_state).await?;
+        Ok(StatusCode::NO_CONTENT)
+    }
+    .await;
+    log_admin_call(
+        &app_state,
+        start_time,
+        "DELETE",
+        &format!("/admin/maintenance-windows/{}", id),
+        REQ_TYPE_MAINTENANCE_WINDOW_DELETE,
+        provided_token.as_deref(),
+        &result,
+    )
+    .await;
+    result
+}
+
+/// 公开状态：进行中与即将开始的维护窗口，以及部署品牌信息
+pub async fn gateway_status(State(app_state): State<Arc<AppState>>) -> Json<GatewayStatus> {
+    let maintenance = app_state.maintenance.status(Utc::now());
+    let status = if maintenance.active.is_empty() {
+        "ok"
+    } else {
+        "maintenance"
+    };
+    Json(GatewayStatus {
+        status,
+        maintenance,
+        branding: app_state.runtime_settings.snapshot().branding,
+    })
+}
@@ -14,11 +14,17 @@ use crate::admin::ClientToken;
 use crate::config::settings::Provider;
 use crate::error::GatewayError;
 use crate::logging::time::BEIJING_OFFSET;
-use crate::logging::types::RequestLog;
+use crate::logging::types::{
+    MaintenanceWindowRecord, ProviderEgressDaily, RequestHeatmapCell, RequestLog,
+};
 use crate::routing::ProviderKeyEntry;
 use crate::server::AppState;
+use crate::server::in_flight::ProviderInFlight;
+use crate::server::lenient_request::RequestDeviationSnapshot;
+use crate::server::model_concurrency::ModelInFlight;
 use crate::server::model_display::{format_model_display_name, provider_display_name};
 use crate::server::request_logging::log_simple_request;
+use crate::server::semantic_cache::SemanticCacheStats;
 
 const DEFAULT_WINDOW_MINUTES: i64 = 60;
 const DEFAULT_INTERVAL_MINUTES: i64 = 5;
@@ -29,6 +35,8 @@ const TARGET_METHOD: &str = "POST";
 const TARGET_PATH: &str = "/v1/chat/completions";
 const DEFAULT_COST_WINDOW_MINUTES: i64 = 24 * 60;
 const DEFAULT_COST_INTERVAL_MINUTES: i64 = 60;
+const DEFAULT_EGRESS_DAYS: i64 = 7;
+const MAX_EGRESS_DAYS: i64 = 366;
 
 #[derive(Debug, Deserialize)]
 pub struct MetricsQuery {
@@ -62,6 +70,13 @@ pub struct MetricsSummary {
     pub average_latency_ms: f64,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub p95_latency_ms: Option<f64>,
+    /// 网关自身开销（上游调用前 + 上游返回后的处理耗时），仅统计带分段耗时的请求
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub average_gateway_overhead_ms: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub p95_gateway_overhead_ms: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub average_upstream_ms: Option<f64>,
     pub total_amount_spent: f64,
     pub total_tokens: u64,
     pub prompt_tokens_spent: u64,
@@ -75,6 +90,15 @@ pub struct MetricsSummary {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub end_date: Option<String>,
     pub available_dates: Vec<String>,
+    /// 配置了并发上限的模型当前进行中/排队的请求数（本实例实时值，不受时间窗口影响）
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub model_in_flight: Vec<ModelInFlight>,
+    /// 各供应商当前进行中的上游请求/流式响应数（本实例实时值）
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub provider_in_flight: Vec<ProviderInFlight>,
+    /// 进行中与即将开始的供应商维护窗口
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub maintenance_windows: Vec<MaintenanceWindowRecord>,
 }
 
 #[derive(Debug, Serialize)]
@@ -264,8 +288,14 @@ fn aggregate_summary(
     let mut provider_counts: HashMap<String, usize> = HashMap::new();
     let mut model_counts: HashMap<String, usize> = HashMap::new();
     let mut clients: HashMap<String, ()> = HashMap::new();
+    let mut overheads: Vec<i64> = Vec::new();
+    let mut upstream_total: i64 = 0;
 
     for log in logs {
+        if let Some(overhead) = log.latency.overhead_ms() {
+            overheads.push(overhead);
+            upstream_total += log.latency.upstream_ms.unwrap_or(0);
+        }
         if log.status_code < 400 {
             success_requests += 1;
         } else {
@@ -319,6 +349,20 @@ fn aggregate_summary(
         latencies.get(pos).map(|v| *v as f64)
     };
 
+    overheads.sort();
+    let (average_gateway_overhead_ms, p95_gateway_overhead_ms, average_upstream_ms) =
+        if overheads.is_empty() {
+            (None, None, None)
+        } else {
+            let n = overheads.len();
+            let pos = (((n as f64) * 0.95).ceil() as usize).clamp(1, n) - 1;
+            (
+                Some(overheads.iter().sum::<i64>() as f64 / n as f64),
+                Some(overheads[pos] as f64),
+                Some(upstream_total as f64 / n as f64),
+            )
+        };
+
     let top_providers = top_items(provider_counts, 5);
     let top_models = top_items(model_counts, 5);
 
@@ -330,6 +374,9 @@ fn aggregate_summary(
         error_rate,
         average_latency_ms: avg_latency,
         p95_latency_ms,
+        average_gateway_overhead_ms,
+        p95_gateway_overhead_ms,
+        average_upstream_ms,
         total_amount_spent: total_amount,
         total_tokens,
         prompt_tokens_spent,
@@ -341,6 +388,9 @@ fn aggregate_summary(
         start_date,
         end_date,
         available_dates,
+        model_in_flight: Vec::new(),
+        provider_in_flight: Vec::new(),
+        maintenance_windows: Vec::new(),
     }
 }
 
@@ -461,6 +511,29 @@ fn log_model_label(log: &RequestLog, providers_by_id: &HashMap<String, Provider>
     normalize_model_label(log.provider.as_deref(), model, providers_by_id)
 }
 
+/// 分布/序列指标的分组维度；language/category 需开启 prompt_profiling
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MetricsGroupBy {
+    #[default]
+    Model,
+    Language,
+    Category,
+}
+
+fn log_group_label(
+    log: &RequestLog,
+    group_by: MetricsGroupBy,
+    providers_by_id: &HashMap<String, Provider>,
+) -> String {
+    let profile_label = match group_by {
+        MetricsGroupBy::Model => return log_model_label(log, providers_by_id),
+        MetricsGroupBy::Language => log.profile.language.as_deref(),
+        MetricsGroupBy::Category => log.profile.category.as_deref(),
+    };
+    profile_label.unwrap_or("unknown").to_string()
+}
+
 fn spent_tokens(log: &RequestLog) -> u64 {
     log.total_tokens.map(|v| v as u64).unwrap_or_else(|| {
         log.prompt_tokens.unwrap_or(0) as u64 + log.completion_tokens.unwrap_or(0) as u64
@@ -489,6 +562,8 @@ pub struct ModelsDistributionQuery {
     pub limit: Option<usize>,
     #[serde(default)]
     pub sort_by: Option<ModelsDistributionSortBy>,
+    #[serde(default)]
+    pub group_by: Option<MetricsGroupBy>,
 }
 
 #[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
@@ -521,11 +596,12 @@ fn build_models_distribution_items(
     logs: &[&RequestLog],
     limit: usize,
     sort_by: ModelsDistributionSortBy,
+    group_by: MetricsGroupBy,
     providers_by_id: &HashMap<String, Provider>,
 ) -> Vec<ModelCountItem> {
     let mut aggregates: HashMap<String, ModelDistributionAggregate> = HashMap::new();
     for log in logs {
-        let label = log_model_label(log, providers_by_id);
+        let label = log_group_label(log, group_by, providers_by_id);
         let entry = aggregates.entry(label).or_default();
         entry.count += 1;
         entry.amount_spent += log.amount_spent.unwrap_or(0.0);
@@ -594,7 +670,9 @@ pub async fn models_distribution(
 
     let limit = q.limit.unwrap_or(8).max(1);
     let sort_by = q.sort_by.unwrap_or(ModelsDistributionSortBy::Calls);
-    let items = build_models_distribution_items(&filtered, limit, sort_by, &providers_by_id);
+    let group_by = q.group_by.unwrap_or_default();
+    let items =
+        build_models_distribution_items(&filtered, limit, sort_by, group_by, &providers_by_id);
 
     log_simple_request(
         &app_state,
@@ -624,6 +702,8 @@ pub struct SeriesModelCostQuery {
     pub interval_minutes: Option<i64>,
     #[serde(default)]
     pub limit: Option<usize>,
+    #[serde(default)]
+    pub group_by: Option<MetricsGroupBy>,
 }
 
 #[derive(Debug, Serialize, Clone)]
@@ -653,6 +733,7 @@ fn build_model_cost_series(
     until: DateTime<Utc>,
     interval_minutes: i64,
     limit: usize,
+    group_by: MetricsGroupBy,
     providers_by_id: &HashMap<String, Provider>,
 ) -> MetricsSeriesModelCost {
     let interval_minutes = interval_minutes.max(1);
@@ -680,7 +761,7 @@ fn build_model_cost_series(
         if idx >= buckets.max(1) {
             continue;
         }
-        let label = log_model_label(log, providers_by_id);
+        let label = log_group_label(log, group_by, providers_by_id);
         let tokens = spent_tokens(log);
         let entry = bucket_maps[idx]
             .entry(label.clone())
@@ -767,6 +848,7 @@ pub async fn series_model_cost(
         until,
         interval_minutes,
         limit,
+        q.group_by.unwrap_or_default(),
         &providers_by_id,
     );
 
@@ -797,6 +879,8 @@ pub struct SeriesModelsQuery {
     pub start_date: Option<String>,
     #[serde(default)]
     pub end_date: Option<String>,
+    #[serde(default)]
+    pub group_by: Option<MetricsGroupBy>,
 }
 
 #[derive(Debug, Serialize)]
@@ -856,6 +940,7 @@ pub async fn series_models(
         .map(|provider| (provider.name.clone(), provider))
         .collect();
 
+    let group_by = q.group_by.unwrap_or_default();
     let interval = Duration::minutes(interval_minutes);
     let total_minutes = (until - since).num_minutes().max(interval_minutes);
     let buckets = ((total_minutes + interval_minutes - 1) / interval_minutes) as usize;
@@ -867,7 +952,7 @@ pub async fn series_models(
         let mut model_counts: HashMap<String, usize> = HashMap::new();
         for log in &filtered {
             if log.timestamp >= bucket_start && log.timestamp < bucket_end {
-                let label = log_model_label(log, &providers_by_id);
+                let label = log_group_label(log, group_by, &providers_by_id);
                 *model_counts.entry(label).or_insert(0) += 1;
             }
         }
@@ -940,7 +1025,7 @@ pub async fn summary(
         .into_iter()
         .map(|provider| (provider.name.clone(), provider))
         .collect();
-    let summary = aggregate_summary(
+    let mut summary = aggregate_summary(
         &filtered,
         window_minutes,
         start_date.clone(),
@@ -948,6 +1033,11 @@ pub async fn summary(
         available_dates.clone(),
         &providers_by_id,
     );
+    summary.model_in_flight = app_state
+        .model_concurrency
+        .snapshot(&app_state.config.server);
+    summary.provider_in_flight = app_state.in_flight.snapshot();
+    summary.maintenance_windows = app_state.maintenance.pending();
 
     log_simple_request(
         &app_state,
@@ -1069,9 +1159,292 @@ pub async fn resource_health(
     }))
 }
 
+#[derive(Debug, Deserialize)]
+pub struct EgressQuery {
+    #[serde(default)]
+    pub start_date: Option<String>,
+    #[serde(default)]
+    pub end_date: Option<String>,
+    #[serde(default)]
+    pub provider: Option<String>,
+}
+
+#[derive(Debug, Serialize, Default, PartialEq)]
+pub struct EgressProviderTotal {
+    pub provider: String,
+    pub requests: i64,
+    pub request_bytes: i64,
+    pub response_bytes: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MetricsEgress {
+    pub start_date: String,
+    pub end_date: String,
+    /// 按天、供应商、密钥的明细（含尚未写库的计数）
+    pub daily: Vec<ProviderEgressDaily>,
+    /// 区间内按供应商汇总（按总字节数降序）
+    pub providers: Vec<EgressProviderTotal>,
+    pub generated_at: String,
+}
+
+/// 解析日期区间（北京时间，闭区间）；缺省为截至今天的最近 7 天
+fn resolve_egress_range(
+    today: NaiveDate,
+    start: Option<&str>,
+    end: Option<&str>,
+) -> Result<(NaiveDate, NaiveDate), GatewayError> {
+    let parse = |value: &str| {
+        parse_date(value).ok_or_else(|| {
+            GatewayError::Config(format!("invalid date '{}', expected YYYY-MM-DD", value))
+        })
+    };
+    let end_date = end.map(parse).transpose()?.unwrap_or(today);
+    let start_date = start
+        .map(parse)
+        .transpose()?
+        .unwrap_or(end_date - Duration::days(DEFAULT_EGRESS_DAYS - 1));
+    if start_date > end_date {
+        return Err(GatewayError::Config(
+            "start_date must not be after end_date".into(),
+        ));
+    }
+    if (end_date - start_date).num_days() >= MAX_EGRESS_DAYS {
+        return Err(GatewayError::Config(format!(
+            "date range must not exceed {} days",
+            MAX_EGRESS_DAYS
+        )));
+    }
+    Ok((start_date, end_date))
+}
+
+fn build_egress_totals(daily: &[ProviderEgressDaily]) -> Vec<EgressProviderTotal> {
+    let mut by_provider: HashMap<&str, EgressProviderTotal> = HashMap::new();
+    for row in daily {
+        let total =
+            by_provider
+                .entry(row.provider.as_str())
+                .or_insert_with(|| EgressProviderTotal {
+                    provider: row.provider.clone(),
+                    ..Default::default()
+                });
+        total.requests += row.requests;
+        total.request_bytes += row.request_bytes;
+        total.response_bytes += row.response_bytes;
+    }
+    let mut totals: Vec<EgressProviderTotal> = by_provider.into_values().collect();
+    totals.sort_by(|a, b| {
+        (b.request_bytes + b.response_bytes)
+            .cmp(&(a.request_bytes + a.response_bytes))
+            .then_with(|| a.provider.cmp(&b.provider))
+    });
+    totals
+}
+
+#[derive(Debug, Deserialize)]
+pub struct HeatmapQuery {
+    #[serde(default)]
+    pub start_date: Option<String>,
+    #[serde(default)]
+    pub end_date: Option<String>,
+    /// 计费模型（实际调用的上游模型名）
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub provider: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MetricsHeatmap {
+    pub start_date: String,
+    pub end_date: String,
+    /// 7 × 24 个格子，按 weekday（0 为周日）、hour 排序，无请求的格子计 0
+    pub cells: Vec<RequestHeatmapCell>,
+    pub total_requests: u64,
+    /// 单格最大请求数，便于前端确定色阶
+    pub max_requests: u64,
+    pub generated_at: String,
+}
+
+/// SQL 只返回有请求的格子，这里补齐为完整的 7 × 24 网格
+fn fill_heatmap(rows: Vec<RequestHeatmapCell>) -> Vec<RequestHeatmapCell> {
+    let mut cells: Vec<RequestHeatmapCell> = (0..7u8)
+        .flat_map(|weekday| {
+            (0..24u8).map(move |hour| RequestHeatmapCell {
+                weekday,
+                hour,
+                ..Default::default()
+            })
+        })
+        .collect();
+    for row in rows {
+        if let Some(cell) = cells.get_mut(row.weekday as usize * 24 + row.hour as usize) {
+            cell.requests += row.requests;
+            cell.errors += row.errors;
+        }
+    }
+    cells
+}
+
+/// 聊天请求按星期 × 小时（北京时间）的分布热力图，汇总在数据库中完成，用于容量规划
+pub async fn heatmap(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(q): Query<HeatmapQuery>,
+) -> Result<Json<MetricsHeatmap>, GatewayError> {
+    let identity = require_superadmin(&headers, &app_state).await?;
+    let today = Utc::now().with_timezone(&BEIJING_OFFSET).date_naive();
+    let (start, end) = resolve_egress_range(today, q.start_date.as_deref(), q.end_date.as_deref())?;
+    let non_empty = |v: &Option<String>| {
+        v.as_deref()
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+    };
+    let model = non_empty(&q.model);
+    let provider = non_empty(&q.provider);
+
+    let rows = app_state
+        .log_store
+        .request_heatmap(
+            start_of_day_utc(start),
+            end_of_day_exclusive_utc(end),
+            model.as_deref(),
+            provider.as_deref(),
+        )
+        .await
+        .map_err(GatewayError::Db)?;
+    let cells = fill_heatmap(rows);
+
+    log_simple_request(
+        &app_state,
+        Utc::now(),
+        "GET",
+        "/admin/metrics/heatmap",
+        "admin_metrics_heatmap",
+        None,
+        None,
+        Some(identity_label(&identity)),
+        200,
+        None,
+    )
+    .await;
+
+    Ok(Json(MetricsHeatmap {
+        start_date: start.format("%Y-%m-%d").to_string(),
+        end_date: end.format("%Y-%m-%d").to_string(),
+        total_requests: cells.iter().map(|c| c.requests).sum(),
+        max_requests: cells.iter().map(|c| c.requests).max().unwrap_or(0),
+        cells,
+        generated_at: Utc::now().to_rfc3339(),
+    }))
+}
+
+/// 按供应商/密钥的上游流量（请求与响应字节数，含流式响应），按天汇总
+pub async fn egress(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(q): Query<EgressQuery>,
+) -> Result<Json<MetricsEgress>, GatewayError> {
+    let identity = require_superadmin(&headers, &app_state).await?;
+    let today = Utc::now().with_timezone(&BEIJING_OFFSET).date_naive();
+    let (start, end) = resolve_egress_range(today, q.start_date.as_deref(), q.end_date.as_deref())?;
+    let start_date = start.format("%Y-%m-%d").to_string();
+    let end_date = end.format("%Y-%m-%d").to_string();
+
+    let stored = app_state
+        .log_store
+        .list_provider_egress(&start_date, &end_date)
+        .await
+        .map_err(GatewayError::Db)?;
+    let provider = q
+        .provider
+        .as_deref()
+        .map(str::trim)
+        .filter(|p| !p.is_empty());
+    let daily: Vec<ProviderEgressDaily> =
+        crate::server::egress::merge_rows(stored, app_state.egress_meter.snapshot())
+            .into_iter()
+            .filter(|row| row.day >= start_date && row.day <= end_date)
+            .filter(|row| provider.is_none_or(|p| p == row.provider))
+            .collect();
+    let providers = build_egress_totals(&daily);
+
+    log_simple_request(
+        &app_state,
+        Utc::now(),
+        "GET",
+        "/admin/metrics/egress",
+        "admin_metrics_egress",
+        None,
+        None,
+        Some(identity_label(&identity)),
+        200,
+        None,
+    )
+    .await;
+
+    Ok(Json(MetricsEgress {
+        start_date,
+        end_date,
+        daily,
+        providers,
+        generated_at: Utc::now().to_rfc3339(),
+    }))
+}
+
+/// 语义响应缓存的命中统计（本实例，自启动起累计）
+pub async fn semantic_cache(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<SemanticCacheStats>, GatewayError> {
+    let identity = require_superadmin(&headers, &app_state).await?;
+    log_simple_request(
+        &app_state,
+        Utc::now(),
+        "GET",
+        "/admin/metrics/semantic-cache",
+        "admin_metrics_semantic_cache",
+        None,
+        None,
+        Some(identity_label(&identity)),
+        200,
+        None,
+    )
+    .await;
+    Ok(Json(
+        app_state
+            .semantic_cache
+            .stats(app_state.config.server.semantic_cache.is_some()),
+    ))
+}
+
+/// 聊天请求体偏离 OpenAI 规范的统计（本实例，自启动起累计），用于定位需要修正的客户端
+pub async fn request_deviations(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<RequestDeviationSnapshot>, GatewayError> {
+    let identity = require_superadmin(&headers, &app_state).await?;
+    log_simple_request(
+        &app_state,
+        Utc::now(),
+        "GET",
+        "/admin/metrics/request-deviations",
+        "admin_metrics_request_deviations",
+        None,
+        None,
+        Some(identity_label(&identity)),
+        200,
+        None,
+    )
+    .await;
+    Ok(Json(app_state.request_deviations.snapshot()))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::logging::types::LatencyBreakdown;
 
     fn mk_log(
         ts: DateTime<Utc>,
@@ -1104,6 +1477,8 @@ mod tests {
             cached_tokens: None,
             reasoning_tokens: None,
             error_message: None,
+            latency: Default::default(),
+            profile: Default::default(),
         }
     }
 
@@ -1116,6 +1491,27 @@ mod tests {
         assert_eq!(spent_tokens(&b), 10);
     }
 
+    #[test]
+    fn summary_reports_gateway_overhead_separately() {
+        let base = Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap();
+        let ms = chrono::Duration::milliseconds;
+        let mut a = mk_log(base, "p", "m", None, None, None, None);
+        a.latency = LatencyBreakdown::from_marks(
+            base,
+            Some(base + ms(5)),
+            Some(base + ms(105)),
+            base + ms(110),
+        );
+        let mut b = mk_log(base, "p", "m", None, None, None, None);
+        b.latency = LatencyBreakdown::from_marks(base, Some(base + ms(20)), None, base + ms(320));
+        // 无上游调用的请求不参与开销统计
+        let c = mk_log(base, "p", "m", None, None, None, None);
+        let summary = aggregate_summary(&[&a, &b, &c], 60, None, None, vec![], &HashMap::new());
+        assert_eq!(summary.average_gateway_overhead_ms, Some(15.0));
+        assert_eq!(summary.p95_gateway_overhead_ms, Some(20.0));
+        assert_eq!(summary.average_upstream_ms, Some(200.0));
+    }
+
     #[test]
     fn build_model_cost_series_buckets_and_limit_work() {
         let providers_by_id = HashMap::new();
@@ -1171,7 +1567,15 @@ mod tests {
         ];
         let refs: Vec<&RequestLog> = logs.iter().collect();
 
-        let out = build_model_cost_series(&refs, since, until, 60, 1, &providers_by_id);
+        let out = build_model_cost_series(
+            &refs,
+            since,
+            until,
+            60,
+            1,
+            MetricsGroupBy::Model,
+            &providers_by_id,
+        );
         assert_eq!(out.points.len(), 2);
         assert_eq!(out.points[0].items.len(), 1);
         assert_eq!(out.points[1].items.len(), 0);
@@ -1211,6 +1615,7 @@ mod tests {
             &refs,
             5,
             ModelsDistributionSortBy::Calls,
+            MetricsGroupBy::Model,
             &providers_by_id,
         );
 
@@ -1271,6 +1676,7 @@ mod tests {
             &refs,
             1,
             ModelsDistributionSortBy::AmountSpent,
+            MetricsGroupBy::Model,
             &providers_by_id,
         );
 
@@ -1283,4 +1689,111 @@ mod tests {
             }]
         );
     }
+
+    #[test]
+    fn build_models_distribution_groups_by_prompt_profile() {
+        let providers_by_id = HashMap::new();
+        let base = Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap();
+        let mut logs = [
+            mk_log(base, "openai", "gpt-4o", None, None, None, Some(0.5)),
+            mk_log(base, "anthropic", "claude", None, None, None, Some(0.25)),
+            mk_log(base, "openai", "gpt-4o", None, None, None, None),
+        ];
+        logs[0].profile.language = Some("zh".into());
+        logs[1].profile.language = Some("zh".into());
+        logs[0].profile.category = Some("code".into());
+        let refs: Vec<&RequestLog> = logs.iter().collect();
+
+        let by_language = build_models_distribution_items(
+            &refs,
+            5,
+            ModelsDistributionSortBy::Calls,
+            MetricsGroupBy::Language,
+            &providers_by_id,
+        );
+        assert_eq!(
+            by_language,
+            vec![
+                ModelCountItem {
+                    name: "zh".into(),
+                    count: 2,
+                    amount_spent: 0.75,
+                },
+                ModelCountItem {
+                    name: "unknown".into(),
+                    count: 1,
+                    amount_spent: 0.0,
+                },
+            ]
+        );
+        let by_category = build_models_distribution_items(
+            &refs,
+            1,
+            ModelsDistributionSortBy::Calls,
+            MetricsGroupBy::Category,
+            &providers_by_id,
+        );
+        assert_eq!(by_category[0].name, "unknown");
+        assert_eq!(by_category[0].count, 2);
+    }
+
+    #[test]
+    fn heatmap_is_filled_to_full_week_grid() {
+        let cells = fill_heatmap(vec![
+            RequestHeatmapCell {
+                weekday: 1,
+                hour: 9,
+                requests: 5,
+                errors: 1,
+            },
+            RequestHeatmapCell {
+                weekday: 6,
+                hour: 23,
+                requests: 2,
+                errors: 0,
+            },
+        ]);
+        assert_eq!(cells.len(), 7 * 24);
+        assert_eq!((cells[0].weekday, cells[0].hour), (0, 0));
+        assert_eq!((cells[33].weekday, cells[33].hour), (1, 9));
+        assert_eq!((cells[33].requests, cells[33].errors), (5, 1));
+        assert_eq!(cells[167].requests, 2);
+        assert_eq!(cells.iter().map(|c| c.requests).sum::<u64>(), 7);
+    }
+
+    #[test]
+    fn egress_range_defaults_and_totals_by_provider() {
+        let today = NaiveDate::from_ymd_opt(2026, 3, 10).unwrap();
+        let (start, end) = resolve_egress_range(today, None, None).unwrap();
+        assert_eq!(
+            (start.to_string(), end.to_string()),
+            ("2026-03-04".into(), "2026-03-10".into())
+        );
+        assert!(resolve_egress_range(today, Some("2026-03-09"), Some("2026-03-01")).is_err());
+        assert!(resolve_egress_range(today, Some("yesterday"), None).is_err());
+
+        let row = |day: &str, provider: &str, key: &str, req: i64, resp: i64| ProviderEgressDaily {
+            day: day.into(),
+            provider: provider.into(),
+            api_key: key.into(),
+            requests: 1,
+            request_bytes: req,
+            response_bytes: resp,
+        };
+        let totals = build_egress_totals(&[
+            row("2026-03-09", "openai", "sk-a****0001", 100, 1000),
+            row("2026-03-10", "openai", "sk-b****0002", 50, 500),
+            row("2026-03-10", "anthropic", "****", 10, 20000),
+        ]);
+        assert_eq!(totals[0].provider, "anthropic");
+        assert_eq!(
+            totals[1],
+            EgressProviderTotal {
+                provider: "openai".into(),
+                requests: 2,
+                request_bytes: 150,
+                response_bytes: 1500,
+            }
+        );
+    }
 }
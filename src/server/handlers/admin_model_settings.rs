@@ -93,23 +93,26 @@ pub async fn upsert_model_enabled(
 ) -> Result<Response, GatewayError> {
     let start_time = Utc::now();
     let provided = bearer_token(&headers);
-    if let Err(e) = require_superadmin(&headers, &app_state).await {
-        let code = e.status_code().as_u16();
-        log_simple_request(
-            &app_state,
-            start_time,
-            "POST",
-            "/admin/models/enabled",
-            "admin_model_enabled_upsert",
-            Some(payload.model.clone()),
-            Some(payload.provider.clone()),
-            provided.as_deref(),
-            code,
-            Some("auth failed".into()),
-        )
-        .await;
-        return Err(e);
-    }
+    let identity = match require_superadmin(&headers, &app_state).await {
+        Ok(identity) => identity,
+        Err(e) => {
+            let code = e.status_code().as_u16();
+            log_simple_request(
+                &app_state,
+                start_time,
+                "POST",
+                "/admin/models/enabled",
+                "admin_model_enabled_upsert",
+                Some(payload.model.clone()),
+                Some(payload.provider.clone()),
+                provided.as_deref(),
+                code,
+                Some("auth failed".into()),
+            )
+            .await;
+            return Err(e);
+        }
+    };
 
     if !app_state
         .providers
@@ -153,6 +156,7 @@ pub async fn upsert_model_enabled(
                 })
                 .to_string(),
             ),
+            actor: Some(identity.actor()),
         })
         .await;
 
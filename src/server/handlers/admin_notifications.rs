@@ -0,0 +1,183 @@
+use axum::{
+    Json,
+    extract::{Query, State},
+    http::HeaderMap,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use super::auth::require_superadmin;
+use crate::error::GatewayError;
+use crate::logging::types::{
+    AdminNotificationRecord, REQ_TYPE_ADMIN_NOTIFICATIONS_LIST, REQ_TYPE_ADMIN_NOTIFICATIONS_READ,
+    REQ_TYPE_ADMIN_NOTIFICATIONS_UNREAD_COUNT,
+};
+use crate::server::AppState;
+use crate::server::request_logging::log_simple_request;
+use crate::server::util::{bearer_token, token_for_log};
+
+const NOTIFICATIONS_DEFAULT_LIMIT: i32 = 50;
+const NOTIFICATIONS_MAX_LIMIT: i32 = 500;
+
+#[derive(Debug, Deserialize)]
+pub struct NotificationsQuery {
+    /// 仅返回未读通知
+    #[serde(default)]
+    pub unread: bool,
+    pub limit: Option<i32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct NotificationsResponse {
+    /// 未读总数（供 TUI / 前端角标使用，不受 limit 影响）
+    pub unread_count: u64,
+    pub items: Vec<AdminNotificationRecord>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MarkReadPayload {
+    /// 省略时标记全部未读通知
+    pub ids: Option<Vec<i64>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MarkReadResponse {
+    pub marked: u64,
+    pub unread_count: u64,
+}
+
+async fn log_admin_call(
+    app_state: &AppState,
+    start_time: DateTime<Utc>,
+    method: &str,
+    path: &str,
+    request_type: &str,
+    provided_token: Option<&str>,
+    result: Result<(), &GatewayError>,
+) {
+    let (code, err) = match result {
+        Ok(()) => (200, None),
+        Err(e) => (e.status_code().as_u16(), Some(e.to_string())),
+    };
+    log_simple_request(
+        app_state,
+        start_time,
+        method,
+        path,
+        request_type,
+        None,
+        None,
+        token_for_log(provided_token),
+        code,
+        err,
+    )
+    .await;
+}
+
+/// 管理员通知列表（最新在前），附带未读总数
+pub async fn list_notifications(
+    Query(query): Query<NotificationsQuery>,
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<NotificationsResponse>, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    let result = async {
+        require_superadmin(&headers, &app_state).await?;
+        let limit = query
+            .limit
+            .unwrap_or(NOTIFICATIONS_DEFAULT_LIMIT)
+            .clamp(1, NOTIFICATIONS_MAX_LIMIT);
+        let items = app_state
+            .log_store
+            .list_admin_notifications(query.unread, limit)
+            .await?;
+        let unread_count = app_state
+            .log_store
+            .count_unread_admin_notifications()
+            .await?;
+        Ok(NotificationsResponse {
+            unread_count,
+            items,
+        })
+    }
+    .await;
+    log_admin_call(
+        &app_state,
+        start_time,
+        "GET",
+        "/admin/notifications",
+        REQ_TYPE_ADMIN_NOTIFICATIONS_LIST,
+        provided_token.as_deref(),
+        result.as_ref().map(|_| ()),
+    )
+    .await;
+    result.map(Json)
+}
+
+/// 未读通知数（角标轮询用）
+pub async fn unread_count(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<serde_json::Value>, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    let result = async {
+        require_superadmin(&headers, &app_state).await?;
+        let unread_count = app_state
+            .log_store
+            .count_unread_admin_notifications()
+            .await?;
+        Ok(serde_json::json!({ "unread_count": unread_count }))
+    }
+    .await;
+    log_admin_call(
+        &app_state,
+        start_time,
+        "GET",
+        "/admin/notifications/unread-count",
+        REQ_TYPE_ADMIN_NOTIFICATIONS_UNREAD_COUNT,
+        provided_token.as_deref(),
+        result.as_ref().map(|_| ()),
+    )
+    .await;
+    result.map(Json)
+}
+
+/// 将指定通知（或全部未读通知）标记为已读
+pub async fn mark_read(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(payload): Json<MarkReadPayload>,
+) -> Result<Json<MarkReadResponse>, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    let result = async {
+        require_superadmin(&headers, &app_state).await?;
+        let marked = app_state
+            .log_store
+            .mark_admin_notifications_read(payload.ids, Utc::now())
+            .await?;
+        let unread_count = app_state
+            .log_store
+            .count_unread_admin_notifications()
+            .await?;
+        Ok(MarkReadResponse {
+            marked,
+            unread_count,
+        })
+    }
+    .await;
+    log_admin_call(
+        &app_state,
+        start_time,
+        "POST",
+        "/admin/notifications/read",
+        REQ_TYPE_ADMIN_NOTIFICATIONS_READ,
+        provided_token.as_deref(),
+        result.as_ref().map(|_| ()),
+    )
+    .await;
+    result.map(Json)
+}
@@ -0,0 +1,232 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use super::auth::require_superadmin;
+use crate::error::GatewayError;
+use crate::logging::types::ParamPolicyRecord;
+use crate::server::AppState;
+use crate::server::param_policy::{ParamPolicy, ParamPolicyRules};
+use crate::server::request_logging::log_simple_request;
+use crate::server::util::{bearer_token, token_for_log};
+
+#[derive(Debug, Deserialize)]
+pub struct ParamPolicyPayload {
+    /// 为空表示所有模型
+    #[serde(default)]
+    pub model: Option<String>,
+    /// 为空表示所有令牌
+    #[serde(default)]
+    pub token_id: Option<String>,
+    pub rules: ParamPolicyRules,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+async fn log_admin_call<T>(
+    app_state: &AppState,
+    start_time: DateTime<Utc>,
+    method: &str,
+    path: &str,
+    request_type: &str,
+    provided_token: Option<&str>,
+    result: &Result<T, GatewayError>,
+) {
+    let (code, err) = match result {
+        Ok(_) => (200, None),
+        Err(e) => (e.status_code().as_u16(), Some(e.to_string())),
+    };
+    log_simple_request(
+        app_state,
+        start_time,
+        method,
+        path,
+        request_type,
+        None,
+        None,
+        token_for_log(provided_token),
+        code,
+        err,
+    )
+    .await;
+}
+
+async fn validated_record(
+    app_state: &AppState,
+    id: String,
+    payload: ParamPolicyPayload,
+    created_at: DateTime<Utc>,
+) -> Result<ParamPolicyRecord, GatewayError> {
+    payload.rules.validate()?;
+    let model = payload
+        .model
+        .map(|m| m.trim().to_string())
+        .filter(|m| !m.is_empty());
+    let token_id = payload
+        .token_id
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty());
+    if let Some(token_id) = token_id.as_deref()
+        && app_state
+            .token_store
+            .get_token_by_id(token_id)
+            .await?
+            .is_none()
+    {
+        return Err(GatewayError::NotFound(format!(
+            "token '{}' not found",
+            token_id
+        )));
+    }
+    Ok(ParamPolicyRecord {
+        id,
+        model,
+        token_id,
+        rules_json: serde_json::to_string(&payload.rules)
+            .map_err(|e| GatewayError::Config(e.to_string()))?,
+        enabled: payload.enabled,
+        created_at,
+        updated_at: Utc::now(),
+    })
+}
+
+async fn find_policy(app_state: &AppState, id: &str) -> Result<ParamPolicyRecord, GatewayError> {
+    app_state
+        .log_store
+        .list_param_policies()
+        .await?
+        .into_iter()
+        .find(|p| p.id == id)
+        .ok_or_else(|| GatewayError::NotFound("param policy not found".into()))
+}
+
+/// 全部参数策略（按创建时间，即应用顺序）
+pub async fn list_param_policies(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<ParamPolicy>>, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    let result = async {
+        require_superadmin(&headers, &app_state).await?;
+        let records = app_state.log_store.list_param_policies().await?;
+        records
+            .into_iter()
+            .map(ParamPolicy::from_record)
+            .collect::<Result<Vec<_>, _>>()
+    }
+    .await;
+    log_admin_call(
+        &app_state,
+        start_time,
+        "GET",
+        "/admin/param-policies",
+        "admin_param_policies_list",
+        provided_token.as_deref(),
+        &result,
+    )
+    .await;
+    result.map(Json)
+}
+
+pub async fn create_param_policy(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(payload): Json<ParamPolicyPayload>,
+) -> Result<(StatusCode, Json<ParamPolicy>), GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    let result = async {
+        require_superadmin(&headers, &app_state).await?;
+        let id = format!("pp_{}", Uuid::new_v4().simple());
+        let record = validated_record(&app_state, id, payload, start_time).await?;
+        app_state
+            .log_store
+            .upsert_param_policy(record.clone())
+            .await?;
+        ParamPolicy::from_record(record)
+    }
+    .await;
+    log_admin_call(
+        &app_state,
+        start_time,
+        "POST",
+        "/admin/param-policies",
+        "admin_param_policy_create",
+        provided_token.as_deref(),
+        &result,
+    )
+    .await;
+    result.map(|p| (StatusCode::CREATED, Json(p)))
+}
+
+pub async fn update_param_policy(
+    Path(id): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(payload): Json<ParamPolicyPayload>,
+) -> Result<Json<ParamPolicy>, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    let result = async {
+        require_superadmin(&headers, &app_state).await?;
+        let existing = find_policy(&app_state, &id).await?;
+        let record =
+            validated_record(&app_state, existing.id, payload, existing.created_at).await?;
+        app_state
+            .log_store
+            .upsert_param_policy(record.clone())
+            .await?;
+        ParamPolicy::from_record(record)
+    }
+    .await;
+    log_admin_call(
+        &app_state,
+        start_time,
+        "PUT",
+        &format!("/admin/param-policies/{}", id),
+        "admin_param_policy_update",
+        provided_token.as_deref(),
+        &result,
+    )
+    .await;
+    result.map(Json)
+}
+
+pub async fn delete_param_policy(
+    Path(id): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<StatusCode, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    let result = async {
+        require_superadmin(&headers, &app_state).await?;
+        if !app_state.log_store.delete_param_policy(&id).await? {
+            return Err(GatewayError::NotFound("param policy not found".into()));
+        }
+        Ok(StatusCode::NO_CONTENT)
+    }
+    .await;
+    log_admin_call(
+        &app_state,
+        start_time,
+        "DELETE",
+        &format!("/admin/param-policies/{}", id),
+        "admin_param_policy_delete",
+        provided_token.as_deref(),
+        &result,
+    )
+    .await;
+    result
+}
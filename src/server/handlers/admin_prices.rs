@@ -13,10 +13,12 @@ use crate::logging::types::ProviderOpLog;
 use crate::logging::{ModelPriceSource, ModelPriceStatus, ModelPriceUpsert};
 use crate::server::AppState;
 use crate::server::model_types;
+use crate::server::pagination::{Listing, PageQuery};
 use crate::server::pricing::{
-    ModelPriceView, derive_model_price_view, model_price_view_from_record,
-    normalized_price_metadata,
+    MODEL_PRICE_SORT_FIELDS, ModelPriceView, compare_model_price_views, derive_model_price_view,
+    model_price_view_from_record, normalized_price_metadata,
 };
+use crate::server::pricing_bulk::{BulkPriceReport, BulkPriceRequest};
 use crate::server::pricing_sync::{PricingSyncReport, PricingSyncRequest};
 use crate::server::request_logging::log_simple_request;
 use chrono::Utc;
@@ -167,34 +169,38 @@ pub async fn upsert_model_price(
         .and_then(|v| v.to_str().ok())
         .and_then(|s| s.strip_prefix("Bearer "))
         .map(|s| s.to_string());
-    if let Err(e) = require_superadmin(&headers, &app_state).await {
-        // audit + request logs on failure
-        let _ = app_state
-            .log_store
-            .log_provider_op(ProviderOpLog {
-                id: None,
-                timestamp: start_time,
-                operation: "model_price_upsert".to_string(),
-                provider: Some(payload.provider.clone()),
-                details: Some(e.to_string()),
-            })
+    let identity = match require_superadmin(&headers, &app_state).await {
+        Ok(identity) => identity,
+        Err(e) => {
+            // audit + request logs on failure
+            let _ = app_state
+                .log_store
+                .log_provider_op(ProviderOpLog {
+                    id: None,
+                    timestamp: start_time,
+                    operation: "model_price_upsert".to_string(),
+                    provider: Some(payload.provider.clone()),
+                    details: Some(e.to_string()),
+                    actor: None,
+                })
+                .await;
+            let code = e.status_code().as_u16();
+            log_simple_request(
+                &app_state,
+                start_time,
+                "POST",
+                "/admin/model-prices",
+                "model_price_upsert",
+                Some(payload.model.clone()),
+                Some(payload.provider.clone()),
+                provided_token.as_deref(),
+                code,
+                Some("auth failed".into()),
+            )
             .await;
-        let code = e.status_code().as_u16();
-        log_simple_request(
-            &app_state,
-            start_time,
-            "POST",
-            "/admin/model-prices",
-            "model_price_upsert",
-            Some(payload.model.clone()),
-            Some(payload.provider.clone()),
-            provided_token.as_deref(),
-            code,
-            Some("auth failed".into()),
-        )
-        .await;
-        return Err(e);
-    }
+            return Err(e);
+        }
+    };
     if let Err(ge) = ensure_provider_exists(&app_state, &payload.provider).await {
         let code = ge.status_code().as_u16();
         log_simple_request(
@@ -286,6 +292,7 @@ pub async fn upsert_model_price(
                 })
                 .to_string(),
             ),
+            actor: Some(identity.actor()),
         })
         .await;
     log_simple_request(
@@ -319,33 +326,37 @@ pub async fn sync_model_prices(
         .and_then(|v| v.to_str().ok())
         .and_then(|s| s.strip_prefix("Bearer "))
         .map(|s| s.to_string());
-    if let Err(e) = require_superadmin(&headers, &app_state).await {
-        let _ = app_state
-            .log_store
-            .log_provider_op(ProviderOpLog {
-                id: None,
-                timestamp: start_time,
-                operation: "model_price_sync".to_string(),
-                provider: payload.provider.clone(),
-                details: Some(e.to_string()),
-            })
+    let identity = match require_superadmin(&headers, &app_state).await {
+        Ok(identity) => identity,
+        Err(e) => {
+            let _ = app_state
+                .log_store
+                .log_provider_op(ProviderOpLog {
+                    id: None,
+                    timestamp: start_time,
+                    operation: "model_price_sync".to_string(),
+                    provider: payload.provider.clone(),
+                    details: Some(e.to_string()),
+                    actor: None,
+                })
+                .await;
+            let code = e.status_code().as_u16();
+            log_simple_request(
+                &app_state,
+                start_time,
+                "POST",
+                "/admin/model-prices/sync",
+                "model_price_sync",
+                None,
+                payload.provider.clone(),
+                provided_token.as_deref(),
+                code,
+                Some("auth failed".into()),
+            )
             .await;
-        let code = e.status_code().as_u16();
-        log_simple_request(
-            &app_state,
-            start_time,
-            "POST",
-            "/admin/model-prices/sync",
-            "model_price_sync",
-            None,
-            payload.provider.clone(),
-            provided_token.as_deref(),
-            code,
-            Some("auth failed".into()),
-        )
-        .await;
-        return Err(e);
-    }
+            return Err(e);
+        }
+    };
 
     let report = crate::server::pricing_sync::sync_model_prices(
         &app_state,
@@ -369,6 +380,7 @@ pub async fn sync_model_prices(
                     operation: "model_price_sync".into(),
                     provider: payload.provider.clone(),
                     details: Some(serde_json::to_string(&report).unwrap_or_else(|_| "{}".into())),
+                    actor: Some(identity.actor()),
                 })
                 .await;
             log_simple_request(
@@ -396,6 +408,7 @@ pub async fn sync_model_prices(
                     operation: "model_price_sync".into(),
                     provider: payload.provider.clone(),
                     details: Some(err.to_string()),
+                    actor: Some(identity.actor()),
                 })
                 .await;
             log_simple_request(
@@ -428,23 +441,26 @@ pub async fn sync_single_model_price(
         .and_then(|v| v.to_str().ok())
         .and_then(|s| s.strip_prefix("Bearer "))
         .map(|s| s.to_string());
-    if let Err(e) = require_superadmin(&headers, &app_state).await {
-        let code = e.status_code().as_u16();
-        log_simple_request(
-            &app_state,
-            start_time,
-            "POST",
-            &format!("/admin/model-prices/{}/{}/sync", provider, model),
-            "model_price_sync_single",
-            Some(model.clone()),
-            Some(provider.clone()),
-            provided_token.as_deref(),
-            code,
-            Some("auth failed".into()),
-        )
-        .await;
-        return Err(e);
-    }
+    let identity = match require_superadmin(&headers, &app_state).await {
+        Ok(identity) => identity,
+        Err(e) => {
+            let code = e.status_code().as_u16();
+            log_simple_request(
+                &app_state,
+                start_time,
+                "POST",
+                &format!("/admin/model-prices/{}/{}/sync", provider, model),
+                "model_price_sync_single",
+                Some(model.clone()),
+                Some(provider.clone()),
+                provided_token.as_deref(),
+                code,
+                Some("auth failed".into()),
+            )
+            .await;
+            return Err(e);
+        }
+    };
     ensure_provider_exists(&app_state, &provider).await?;
     ensure_cached_model_exists(&app_state, &provider, &model).await?;
 
@@ -495,11 +511,73 @@ pub async fn sync_single_model_price(
             operation: "model_price_sync_single".into(),
             provider: Some(provider),
             details: Some(serde_json::to_string(&response).unwrap_or_else(|_| "{}".into())),
+            actor: Some(identity.actor()),
         })
         .await;
     Ok(Json(response))
 }
 
+/// 批量调整价格（按百分比或跨供应商复制），dry_run 时只返回受影响的行
+pub async fn bulk_update_model_prices(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(payload): Json<BulkPriceRequest>,
+) -> Result<Json<BulkPriceReport>, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = headers
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|s| s.strip_prefix("Bearer "))
+        .map(|s| s.to_string());
+    let log_provider = payload.log_provider();
+    let result: Result<BulkPriceReport, GatewayError> = async {
+        let identity = require_superadmin(&headers, &app_state).await?;
+        let dry_run = payload.dry_run;
+        let details = serde_json::to_value(&payload).unwrap_or_default();
+        let report = crate::server::pricing_bulk::run(&app_state, payload).await?;
+        if !dry_run {
+            let _ = app_state
+                .log_store
+                .log_provider_op(ProviderOpLog {
+                    id: None,
+                    timestamp: start_time,
+                    operation: "model_price_bulk".into(),
+                    provider: log_provider.clone(),
+                    details: Some(
+                        serde_json::json!({
+                            "request": details,
+                            "affected": report.affected,
+                            "changes": report.changes,
+                        })
+                        .to_string(),
+                    ),
+                    actor: Some(identity.actor()),
+                })
+                .await;
+        }
+        Ok(report)
+    }
+    .await;
+    let (code, err) = match &result {
+        Ok(_) => (200, None),
+        Err(e) => (e.status_code().as_u16(), Some(e.to_string())),
+    };
+    log_simple_request(
+        &app_state,
+        start_time,
+        "POST",
+        "/admin/model-prices/bulk",
+        "model_price_bulk",
+        None,
+        log_provider,
+        provided_token.as_deref(),
+        code,
+        err,
+    )
+    .await;
+    result.map(Json)
+}
+
 #[derive(Debug, Deserialize)]
 pub struct ListQuery {
     pub provider: Option<String>,
@@ -509,7 +587,8 @@ pub async fn list_model_prices(
     State(app_state): State<Arc<AppState>>,
     headers: HeaderMap,
     Query(q): Query<ListQuery>,
-) -> Result<Json<Vec<ModelPriceView>>, GatewayError> {
+    Query(page): Query<PageQuery>,
+) -> Result<Json<Listing<ModelPriceView>>, GatewayError> {
     let start_time = Utc::now();
     let provided_token = headers
         .get(axum::http::header::AUTHORIZATION)
@@ -533,6 +612,7 @@ pub async fn list_model_prices(
         .await;
         return Err(e);
     }
+    let page = page.resolve(&MODEL_PRICE_SORT_FIELDS)?;
     let items = app_state
         .log_store
         .list_model_prices(q.provider.as_deref())
@@ -556,7 +636,12 @@ pub async fn list_model_prices(
             .entry((model.provider.clone(), model.id.clone()))
             .or_insert_with(|| derive_model_price_view(&model.provider, &model.id, None));
     }
-    let out: Vec<_> = by_key.into_values().collect();
+    // 价格记录与缓存模型合并后才能得到完整列表，因此在内存中分页
+    let out = Listing::from_vec(
+        by_key.into_values().collect(),
+        page.as_ref(),
+        compare_model_price_views,
+    );
     log_simple_request(
         &app_state,
         start_time,
@@ -637,6 +722,135 @@ pub async fn get_model_price(
     }
 }
 
+#[derive(Debug, Deserialize, Default)]
+pub struct PriceEstimatePayload {
+    pub provider: String,
+    pub model: String,
+    #[serde(default)]
+    pub prompt_tokens: Option<u32>,
+    #[serde(default)]
+    pub completion_tokens: Option<u32>,
+    #[serde(default)]
+    pub cached_tokens: Option<u32>,
+    /// 可选：示例 Chat Completions 请求体，未给出 prompt_tokens 时据此估算
+    #[serde(default)]
+    pub sample_request: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PriceEstimateResponse {
+    pub provider: String,
+    pub model: String,
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+    pub cached_tokens: u32,
+    /// prompt_tokens 是否由 sample_request 估算得出
+    pub prompt_tokens_estimated: bool,
+    pub price: ModelPriceView,
+    pub prompt_cost: Option<f64>,
+    pub completion_cost: Option<f64>,
+    pub total_cost: Option<f64>,
+}
+
+/// 按当前价格试算费用（不发起上游请求、不计费），用于启用模型前核对价格配置
+#[allow(deprecated)]
+pub async fn estimate_model_price(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(payload): Json<PriceEstimatePayload>,
+) -> Result<Json<PriceEstimateResponse>, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = headers
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|s| s.strip_prefix("Bearer "))
+        .map(|s| s.to_string());
+    if let Err(e) = require_superadmin(&headers, &app_state).await {
+        let code = e.status_code().as_u16();
+        log_simple_request(
+            &app_state,
+            start_time,
+            "POST",
+            "/admin/pricing/estimate",
+            "model_price_estimate",
+            Some(payload.model.clone()),
+            Some(payload.provider.clone()),
+            provided_token.as_deref(),
+            code,
+            Some("auth failed".into()),
+        )
+        .await;
+        return Err(e);
+    }
+
+    let sample = payload
+        .sample_request
+        .map(serde_json::from_value::<crate::providers::openai::ChatCompletionRequest>)
+        .transpose()
+        .map_err(|e| GatewayError::Config(format!("invalid sample_request: {}", e)))?;
+    let (prompt_tokens, prompt_tokens_estimated) = match (payload.prompt_tokens, sample.as_ref()) {
+        (Some(n), _) => (n, false),
+        (None, Some(req)) => (crate::server::chat_plan::estimate_prompt_tokens(req), true),
+        (None, None) => (0, false),
+    };
+    let completion_tokens = payload
+        .completion_tokens
+        .or_else(|| {
+            sample
+                .as_ref()
+                .and_then(|req| req.max_completion_tokens.or(req.max_tokens))
+        })
+        .unwrap_or(0);
+    let cached_tokens = payload.cached_tokens.unwrap_or(0);
+    if cached_tokens > prompt_tokens {
+        return Err(GatewayError::Config(
+            "cached_tokens cannot exceed prompt_tokens".into(),
+        ));
+    }
+
+    let record = app_state
+        .log_store
+        .get_model_price(&payload.provider, &payload.model)
+        .await
+        .map_err(GatewayError::Db)?;
+    // 价格表暂无独立的缓存命中单价，计费时 cached_tokens 与普通 prompt tokens 同价；试算保持一致
+    let (prompt_cost, completion_cost) = match record.as_ref() {
+        Some(r) => (
+            Some(prompt_tokens as f64 * r.prompt_price_per_million / 1_000_000.0),
+            Some(completion_tokens as f64 * r.completion_price_per_million / 1_000_000.0),
+        ),
+        None => (None, None),
+    };
+    let total_cost = prompt_cost.zip(completion_cost).map(|(p, c)| p + c);
+    let price = derive_model_price_view(&payload.provider, &payload.model, record);
+
+    log_simple_request(
+        &app_state,
+        start_time,
+        "POST",
+        "/admin/pricing/estimate",
+        "model_price_estimate",
+        Some(payload.model.clone()),
+        Some(payload.provider.clone()),
+        provided_token.as_deref(),
+        200,
+        None,
+    )
+    .await;
+    Ok(Json(PriceEstimateResponse {
+        provider: payload.provider,
+        model: payload.model,
+        prompt_tokens,
+        completion_tokens,
+        cached_tokens,
+        prompt_tokens_estimated,
+        price,
+        prompt_cost,
+        completion_cost,
+        total_cost,
+    }))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -647,7 +861,6 @@ mod tests {
     };
     use crate::logging::DatabaseLogger;
     use crate::providers::openai::Model;
-    use crate::server::login::LoginManager;
     use crate::server::storage_traits::{AdminPublicKeyRecord, LoginStore, TuiSessionRecord};
     use axum::http::{HeaderValue, header::AUTHORIZATION};
     use chrono::Duration;
@@ -783,6 +996,7 @@ mod tests {
                 enabled: true,
                 created_at: now,
                 last_used_at: None,
+                expires_at: None,
             })
             .await
             .unwrap();
@@ -800,22 +1014,7 @@ mod tests {
             .await
             .unwrap();
 
-        let app_state = Arc::new(AppState {
-            config: settings,
-            load_balancer_state: Arc::new(crate::routing::LoadBalancerState::default()),
-            log_store: logger.clone(),
-            model_cache: logger.clone(),
-            providers: logger.clone(),
-            token_store: logger.clone(),
-            favorites_store: logger.clone(),
-            organizations: logger.clone(),
-            login_manager: Arc::new(LoginManager::new(logger.clone())),
-            user_store: logger.clone(),
-            refresh_token_store: logger.clone(),
-            password_reset_token_store: logger.clone(),
-            balance_store: logger.clone(),
-            subscription_store: logger,
-        });
+        let app_state = crate::server::test_harness::app_state(settings, logger.clone());
 
         Harness {
             _dir: dir,
@@ -923,15 +1122,23 @@ mod tests {
         let h = harness().await;
         let headers = auth_headers(&h.token);
 
-        let Json(items) = list_model_prices(
+        let Json(Listing::Page(page)) = list_model_prices(
             State(h.state),
             headers,
             Query(ListQuery {
                 provider: Some("p1".into()),
             }),
+            Query(PageQuery {
+                limit: Some(10),
+                ..Default::default()
+            }),
         )
         .await
-        .unwrap();
+        .unwrap() else {
+            panic!("paged list should return an envelope");
+        };
+        assert_eq!(page.total, 1);
+        let items = page.items;
 
         assert_eq!(items.len(), 1);
         assert_eq!(items[0].provider, "p1");
@@ -1055,4 +1262,76 @@ mod tests {
         assert_eq!(response.price.prompt_price_per_million, Some(75.0));
         assert_eq!(response.price.synced_at, None);
     }
+
+    #[tokio::test]
+    async fn admin_estimate_uses_current_price_and_sample_request() {
+        let h = harness().await;
+        h.state
+            .log_store
+            .upsert_model_price(ModelPriceUpsert::manual(
+                "p1",
+                "m1",
+                2.0,
+                8.0,
+                Some("USD".into()),
+                Some("chat".into()),
+            ))
+            .await
+            .unwrap();
+
+        let Json(resp) = estimate_model_price(
+            State(h.state.clone()),
+            auth_headers(&h.token),
+            Json(PriceEstimatePayload {
+                provider: "p1".into(),
+                model: "m1".into(),
+                prompt_tokens: Some(1_000_000),
+                completion_tokens: Some(500_000),
+                cached_tokens: Some(200_000),
+                sample_request: None,
+            }),
+        )
+        .await
+        .unwrap();
+        assert_eq!(resp.prompt_cost, Some(2.0));
+        assert_eq!(resp.completion_cost, Some(4.0));
+        assert_eq!(resp.total_cost, Some(6.0));
+        assert!(!resp.prompt_tokens_estimated);
+
+        let Json(resp) = estimate_model_price(
+            State(h.state.clone()),
+            auth_headers(&h.token),
+            Json(PriceEstimatePayload {
+                provider: "p1".into(),
+                model: "m1".into(),
+                sample_request: Some(serde_json::json!({
+                    "model": "m1",
+                    "messages": [{"role": "user", "content": "hello world"}],
+                    "max_tokens": 100
+                })),
+                ..Default::default()
+            }),
+        )
+        .await
+        .unwrap();
+        assert!(resp.prompt_tokens_estimated);
+        assert!(resp.prompt_tokens > 0);
+        assert_eq!(resp.completion_tokens, 100);
+        assert!(resp.total_cost.unwrap() > 0.0);
+
+        let Json(missing) = estimate_model_price(
+            State(h.state.clone()),
+            auth_headers(&h.token),
+            Json(PriceEstimatePayload {
+                provider: "p1".into(),
+                model: "unpriced".into(),
+                prompt_tokens: Some(10),
+                ..Default::default()
+            }),
+        )
+        .await
+        .unwrap();
+        assert_eq!(missing.price.status, ModelPriceStatus::Missing);
+        assert_eq!(missing.total_cost, None);
+    }
 }
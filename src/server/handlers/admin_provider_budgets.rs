@@ -0,0 +1,261 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::sync::Arc;
+
+use super::auth::{AdminIdentity, require_superadmin};
+use crate::error::GatewayError;
+use crate::logging::types::{
+    ProviderBudgetRecord, ProviderOpLog, REQ_TYPE_PROVIDER_BUDGET_DELETE,
+    REQ_TYPE_PROVIDER_BUDGET_LIST, REQ_TYPE_PROVIDER_BUDGET_SET,
+};
+use crate::server::AppState;
+use crate::server::cluster::{self, CacheEvent};
+use crate::server::provider_budget::{DEFAULT_WARN_THRESHOLDS, ProviderBudgetStatus};
+use crate::server::request_logging::log_simple_request;
+use crate::server::util::{bearer_token, token_for_log};
+
+const MAX_WARN_THRESHOLDS: usize = 10;
+
+#[derive(Debug, Deserialize)]
+pub struct ProviderBudgetPayload {
+    pub monthly_budget: f64,
+    /// 预算百分比（1-100）；为空时使用 80 与 100
+    #[serde(default)]
+    pub warn_thresholds: Option<Vec<u8>>,
+    #[serde(default = "default_stop_routing")]
+    pub stop_routing_when_exhausted: bool,
+}
+
+fn default_stop_routing() -> bool {
+    true
+}
+
+fn normalize_warn_thresholds(thresholds: Option<Vec<u8>>) -> Result<Vec<u8>, GatewayError> {
+    let mut thresholds = thresholds.unwrap_or_else(|| DEFAULT_WARN_THRESHOLDS.to_vec());
+    if thresholds.len() > MAX_WARN_THRESHOLDS {
+        return Err(GatewayError::Config(format!(
+            "warn_thresholds may contain at most {} values",
+            MAX_WARN_THRESHOLDS
+        )));
+    }
+    if thresholds.iter().any(|t| !(1..=100).contains(t)) {
+        return Err(GatewayError::Config(
+            "warn_thresholds must be percentages between 1 and 100".into(),
+        ));
+    }
+    thresholds.sort_unstable();
+    thresholds.dedup();
+    Ok(thresholds)
+}
+
+async fn log_admin_call<T>(
+    app_state: &AppState,
+    start_time: DateTime<Utc>,
+    method: &str,
+    path: &str,
+    request_type: &str,
+    provided_token: Option<&str>,
+    result: &Result<T, GatewayError>,
+) {
+    let (code, err) = match result {
+        Ok(_) => (200, None),
+        Err(e) => (e.status_code().as_u16(), Some(e.to_string())),
+    };
+    log_simple_request(
+        app_state,
+        start_time,
+        method,
+        path,
+        request_type,
+        None,
+        None,
+        token_for_log(provided_token),
+        code,
+        err,
+    )
+    .await;
+}
+
+async fn log_budget_op(
+    app_state: &AppState,
+    start_time: DateTime<Utc>,
+    operation: &str,
+    provider: &str,
+    actor: &AdminIdentity,
+    details: Option<String>,
+) {
+    let _ = app_state
+        .log_store
+        .log_provider_op(ProviderOpLog {
+            id: None,
+            timestamp: start_time,
+            operation: operation.to_string(),
+            provider: Some(provider.to_string()),
+            details,
+            actor: Some(actor.actor()),
+        })
+        .await;
+}
+
+/// 全部供应商预算及本月花费
+pub async fn list_provider_budgets(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<ProviderBudgetStatus>>, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    let result = async {
+        require_superadmin(&headers, &app_state).await?;
+        let now = Utc::now();
+        let budgets = app_state.log_store.list_provider_budgets().await?;
+        Ok(budgets
+            .into_iter()
+            .map(|b| {
+                let spent = app_state.provider_spend.spent(&b.provider, now);
+                ProviderBudgetStatus::new(b, spent, now)
+            })
+            .collect::<Vec<_>>())
+    }
+    .await;
+    log_admin_call(
+        &app_state,
+        start_time,
+        "GET",
+        "/admin/provider-budgets",
+        REQ_TYPE_PROVIDER_BUDGET_LIST,
+        provided_token.as_deref(),
+        &result,
+    )
+    .await;
+    result.map(Json)
+}
+
+/// 设置或调整供应商月度预算，月中调整立即生效（并允许重新触发告警）
+pub async fn set_provider_budget(
+    Path(provider): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(payload): Json<ProviderBudgetPayload>,
+) -> Result<Json<ProviderBudgetStatus>, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    let result = async {
+        let identity = require_superadmin(&headers, &app_state).await?;
+        if !payload.monthly_budget.is_finite() || payload.monthly_budget <= 0.0 {
+            return Err(GatewayError::Config(
+                "monthly_budget must be a positive number".into(),
+            ));
+        }
+        if app_state.providers.get_provider(&provider).await?.is_none() {
+            return Err(GatewayError::NotFound(format!(
+                "Provider '{}' not found",
+                provider
+            )));
+        }
+        let record = ProviderBudgetRecord {
+            provider: provider.clone(),
+            monthly_budget: payload.monthly_budget,
+            warn_thresholds: normalize_warn_thresholds(payload.warn_thresholds)?,
+            stop_routing_when_exhausted: payload.stop_routing_when_exhausted,
+            updated_at: start_time,
+        };
+        app_state
+            .log_store
+            .upsert_provider_budget(record.clone())
+            .await?;
+        app_state
+            .provider_spend
+            .reset_warnings(&provider, start_time);
+        cluster::publish(
+            &app_state,
+            CacheEvent::ProviderBudget {
+                provider: provider.clone(),
+            },
+        );
+        log_budget_op(
+            &app_state,
+            start_time,
+            REQ_TYPE_PROVIDER_BUDGET_SET,
+            &provider,
+            &identity,
+            serde_json::to_string(&record).ok(),
+        )
+        .await;
+        let spent = app_state.provider_spend.spent(&provider, start_time);
+        Ok(ProviderBudgetStatus::new(record, spent, start_time))
+    }
+    .await;
+    log_admin_call(
+        &app_state,
+        start_time,
+        "PUT",
+        &format!("/admin/provider-budgets/{}", provider),
+        REQ_TYPE_PROVIDER_BUDGET_SET,
+        provided_token.as_deref(),
+        &result,
+    )
+    .await;
+    result.map(Json)
+}
+
+pub async fn delete_provider_budget(
+    Path(provider): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<StatusCode, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    let result = async {
+        let identity = require_superadmin(&headers, &app_state).await?;
+        if !app_state
+            .log_store
+            .delete_provider_budget(&provider)
+            .await?
+        {
+            return Err(GatewayError::NotFound("provider budget not found".into()));
+        }
+        log_budget_op(
+            &app_state,
+            start_time,
+            REQ_TYPE_PROVIDER_BUDGET_DELETE,
+            &provider,
+            &identity,
+            None,
+        )
+        .await;
+        Ok(StatusCode::NO_CONTENT)
+    }
+    .await;
+    log_admin_call(
+        &app_state,
+        start_time,
+        "DELETE",
+        &format!("/admin/provider-budgets/{}", provider),
+        REQ_TYPE_PROVIDER_BUDGET_DELETE,
+        provided_token.as_deref(),
+        &result,
+    )
+    .await;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn warn_thresholds_default_sort_and_validate() {
+        assert_eq!(normalize_warn_thresholds(None).unwrap(), vec![80, 100]);
+        assert_eq!(
+            normalize_warn_thresholds(Some(vec![90, 50, 90])).unwrap(),
+            vec![50, 90]
+        );
+        assert!(normalize_warn_thresholds(Some(vec![0])).is_err());
+        assert!(normalize_warn_thresholds(Some(vec![101])).is_err());
+    }
+}
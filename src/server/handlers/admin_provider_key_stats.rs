@@ -301,7 +301,6 @@ mod tests {
     };
     use crate::logging::DatabaseLogger;
     use crate::logging::types::RequestLog;
-    use crate::server::login::LoginManager;
     use crate::server::storage_traits::{AdminPublicKeyRecord, LoginStore, TuiSessionRecord};
     use axum::http::{HeaderValue, header::AUTHORIZATION};
     use chrono::Utc;
@@ -346,6 +345,7 @@ mod tests {
                 enabled: true,
                 created_at: now,
                 last_used_at: None,
+                expires_at: None,
             })
             .await
             .unwrap();
@@ -363,22 +363,7 @@ mod tests {
             .await
             .unwrap();
 
-        let app_state = Arc::new(AppState {
-            config: settings,
-            load_balancer_state: Arc::new(crate::routing::LoadBalancerState::default()),
-            log_store: logger.clone(),
-            model_cache: logger.clone(),
-            providers: logger.clone(),
-            token_store: logger.clone(),
-            favorites_store: logger.clone(),
-            organizations: logger.clone(),
-            login_manager: Arc::new(LoginManager::new(logger.clone())),
-            user_store: logger.clone(),
-            refresh_token_store: logger.clone(),
-            password_reset_token_store: logger.clone(),
-            balance_store: logger.clone(),
-            subscription_store: logger.clone(),
-        });
+        let app_state = crate::server::test_harness::app_state(settings, logger.clone());
 
         let mut headers = HeaderMap::new();
         headers.insert(
@@ -452,6 +437,8 @@ mod tests {
                 cached_tokens: None,
                 reasoning_tokens: None,
                 error_message: None,
+                latency: Default::default(),
+                profile: Default::default(),
             },
             RequestLog {
                 id: None,
@@ -475,6 +462,8 @@ mod tests {
                 cached_tokens: None,
                 reasoning_tokens: None,
                 error_message: Some("err".into()),
+                latency: Default::default(),
+                profile: Default::default(),
             },
         ];
         for mut log in logs {
@@ -570,6 +559,8 @@ mod tests {
                 cached_tokens: None,
                 reasoning_tokens: None,
                 error_message: None,
+                latency: Default::default(),
+                profile: Default::default(),
             };
             log.api_key = log.api_key.as_deref().map(mask_key);
             state.log_store.log_request(log).await.unwrap();
@@ -605,6 +596,8 @@ mod tests {
             cached_tokens: None,
             reasoning_tokens: None,
             error_message: None,
+            latency: Default::default(),
+            profile: Default::default(),
         };
         log.api_key = log.api_key.as_deref().map(mask_key);
         state.log_store.log_request(log).await.unwrap();
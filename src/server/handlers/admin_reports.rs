@@ -0,0 +1,293 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use super::auth::require_superadmin;
+use crate::error::GatewayError;
+use crate::logging::types::MetricsReportRecord;
+use crate::server::AppState;
+use crate::server::metrics_reports::{self, MetricsReport, period_duration};
+use crate::server::notifications::{CHANNEL_EMAIL, CHANNEL_WEBHOOK};
+use crate::server::request_logging::log_simple_request;
+use crate::server::util::{bearer_token, token_for_log};
+
+const NAME_MAX_LEN: usize = 128;
+const TEMPLATE_MAX_LEN: usize = 16 * 1024;
+
+#[derive(Debug, Deserialize)]
+pub struct MetricsReportPayload {
+    pub name: String,
+    /// daily | weekly
+    pub period: String,
+    /// webhook | email
+    pub channel: String,
+    pub target: String,
+    /// 为空时使用内置模板
+    #[serde(default)]
+    pub template: String,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+#[derive(Debug, Serialize)]
+pub struct TestSendResponse {
+    pub delivered: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub text: String,
+    pub report: MetricsReport,
+}
+
+async fn log_admin_call<T>(
+    app_state: &AppState,
+    start_time: DateTime<Utc>,
+    method: &str,
+    path: &str,
+    request_type: &str,
+    provided_token: Option<&str>,
+    result: &Result<T, GatewayError>,
+) {
+    let (code, err) = match result {
+        Ok(_) => (200, None),
+        Err(e) => (e.status_code().as_u16(), Some(e.to_string())),
+    };
+    log_simple_request(
+        app_state,
+        start_time,
+        method,
+        path,
+        request_type,
+        None,
+        None,
+        token_for_log(provided_token),
+        code,
+        err,
+    )
+    .await;
+}
+
+async fn validated_record(
+    id: String,
+    payload: MetricsReportPayload,
+    existing: Option<&MetricsReportRecord>,
+) -> Result<MetricsReportRecord, GatewayError> {
+    let name = payload.name.trim().to_string();
+    if name.is_empty() || name.chars().count() > NAME_MAX_LEN {
+        return Err(GatewayError::Config(format!(
+            "name 不能为空且不超过 {} 个字符",
+            NAME_MAX_LEN
+        )));
+    }
+    let period = payload.period.trim().to_ascii_lowercase();
+    if period_duration(&period).is_none() {
+        return Err(GatewayError::Config("period 仅支持 daily 或 weekly".into()));
+    }
+    let target = payload.target.trim().to_string();
+    let channel = payload.channel.trim().to_ascii_lowercase();
+    match channel.as_str() {
+        CHANNEL_WEBHOOK => {
+            crate::server::ssrf::validate_outbound_base_url(&target)
+                .await
+                .map_err(|e| GatewayError::Config(format!("target 无效: {}", e)))?;
+        }
+        CHANNEL_EMAIL => {
+            if !target.contains('@') {
+                return Err(GatewayError::Config("target 需为邮箱地址".into()));
+            }
+        }
+        _ => {
+            return Err(GatewayError::Config(
+                "channel 仅支持 webhook 或 email".into(),
+            ));
+        }
+    }
+    if payload.template.len() > TEMPLATE_MAX_LEN {
+        return Err(GatewayError::Config(format!(
+            "template 不能超过 {} 字节",
+            TEMPLATE_MAX_LEN
+        )));
+    }
+    let now = Utc::now();
+    Ok(MetricsReportRecord {
+        id,
+        name,
+        period,
+        channel,
+        target,
+        template: payload.template,
+        enabled: payload.enabled,
+        last_sent_at: existing.and_then(|r| r.last_sent_at),
+        created_at: existing.map(|r| r.created_at).unwrap_or(now),
+        updated_at: now,
+    })
+}
+
+async fn find_report(app_state: &AppState, id: &str) -> Result<MetricsReportRecord, GatewayError> {
+    app_state
+        .log_store
+        .list_metrics_reports()
+        .await?
+        .into_iter()
+        .find(|r| r.id == id)
+        .ok_or_else(|| GatewayError::NotFound("metrics report not found".into()))
+}
+
+/// 全部定时报表
+pub async fn list_reports(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<MetricsReportRecord>>, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    let result = async {
+        require_superadmin(&headers, &app_state).await?;
+        Ok(app_state.log_store.list_metrics_reports().await?)
+    }
+    .await;
+    log_admin_call(
+        &app_state,
+        start_time,
+        "GET",
+        "/admin/reports",
+        "admin_reports_list",
+        provided_token.as_deref(),
+        &result,
+    )
+    .await;
+    result.map(Json)
+}
+
+pub async fn create_report(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(payload): Json<MetricsReportPayload>,
+) -> Result<(StatusCode, Json<MetricsReportRecord>), GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    let result = async {
+        require_superadmin(&headers, &app_state).await?;
+        let id = format!("rpt_{}", Uuid::new_v4().simple());
+        let record = validated_record(id, payload, None).await?;
+        app_state
+            .log_store
+            .upsert_metrics_report(record.clone())
+            .await?;
+        Ok(record)
+    }
+    .await;
+    log_admin_call(
+        &app_state,
+        start_time,
+        "POST",
+        "/admin/reports",
+        "admin_report_create",
+        provided_token.as_deref(),
+        &result,
+    )
+    .await;
+    result.map(|r| (StatusCode::CREATED, Json(r)))
+}
+
+pub async fn update_report(
+    Path(id): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(payload): Json<MetricsReportPayload>,
+) -> Result<Json<MetricsReportRecord>, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    let result = async {
+        require_superadmin(&headers, &app_state).await?;
+        let existing = find_report(&app_state, &id).await?;
+        let record = validated_record(existing.id.clone(), payload, Some(&existing)).await?;
+        app_state
+            .log_store
+            .upsert_metrics_report(record.clone())
+            .await?;
+        Ok(record)
+    }
+    .await;
+    log_admin_call(
+        &app_state,
+        start_time,
+        "PUT",
+        &format!("/admin/reports/{}", id),
+        "admin_report_update",
+        provided_token.as_deref(),
+        &result,
+    )
+    .await;
+    result.map(Json)
+}
+
+pub async fn delete_report(
+    Path(id): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<StatusCode, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    let result = async {
+        require_superadmin(&headers, &app_state).await?;
+        if !app_state.log_store.delete_metrics_report(&id).await? {
+            return Err(GatewayError::NotFound("metrics report not found".into()));
+        }
+        Ok(StatusCode::NO_CONTENT)
+    }
+    .await;
+    log_admin_call(
+        &app_state,
+        start_time,
+        "DELETE",
+        &format!("/admin/reports/{}", id),
+        "admin_report_delete",
+        provided_token.as_deref(),
+        &result,
+    )
+    .await;
+    result
+}
+
+/// 立即按当前数据渲染并发送一次（不影响定时发送进度），返回渲染结果与投递状态
+pub async fn test_send_report(
+    Path(id): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<TestSendResponse>, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    let result = async {
+        require_superadmin(&headers, &app_state).await?;
+        let record = find_report(&app_state, &id).await?;
+        let report = metrics_reports::build_report(&app_state, &record.period, start_time).await?;
+        let delivery = metrics_reports::send_report(&record, &report).await;
+        Ok(TestSendResponse {
+            delivered: delivery.is_ok(),
+            error: delivery.err(),
+            text: metrics_reports::render_report(&record.template, &record.name, &report),
+            report,
+        })
+    }
+    .await;
+    log_admin_call(
+        &app_state,
+        start_time,
+        "POST",
+        &format!("/admin/reports/{}/test-send", id),
+        "admin_report_test_send",
+        provided_token.as_deref(),
+        &result,
+    )
+    .await;
+    result.map(Json)
+}
@@ -0,0 +1,146 @@
+use std::convert::Infallible;
+use std::sync::Arc;
+
+use axum::{
+    Json,
+    extract::{Query, State},
+    http::HeaderMap,
+    response::{IntoResponse, Response, Sse, sse::Event},
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::error::RecvError;
+
+use super::auth::require_superadmin;
+use crate::error::GatewayError;
+use crate::logging::ring_buffer::{ServerLogEntry, ServerLogFilter, server_log_buffer};
+use crate::server::AppState;
+use crate::server::request_logging::log_simple_request;
+use crate::server::util::{bearer_token, token_for_log};
+
+const MAX_LIMIT: usize = 2000;
+const DEFAULT_LIMIT: usize = 200;
+
+#[derive(Debug, Deserialize, Default)]
+pub struct ServerLogsQuery {
+    #[serde(default)]
+    pub level: Option<String>,
+    #[serde(default)]
+    pub module: Option<String>,
+    #[serde(default)]
+    pub limit: Option<usize>,
+    /// 为 true 时以 SSE 持续推送新日志（先推送最近 limit 条）
+    #[serde(default)]
+    pub tail: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ServerLogsResponse {
+    pub data: Vec<ServerLogEntry>,
+}
+
+fn entry_event(entry: &ServerLogEntry) -> Event {
+    Event::default()
+        .id(entry.seq.to_string())
+        .data(serde_json::to_string(entry).unwrap_or_default())
+}
+
+pub async fn list_server_logs(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(query): Query<ServerLogsQuery>,
+) -> Result<Response, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    let checked = require_superadmin(&headers, &app_state)
+        .await
+        .and_then(|_| {
+            ServerLogFilter::parse(query.level.as_deref(), query.module.as_deref())
+                .map_err(GatewayError::Config)
+        });
+    let filter = match checked {
+        Ok(filter) => filter,
+        Err(e) => {
+            let code = e.status_code().as_u16();
+            log_simple_request(
+                &app_state,
+                start_time,
+                "GET",
+                "/admin/server-logs",
+                "admin_server_logs",
+                None,
+                None,
+                provided_token.as_deref(),
+                code,
+                Some(e.to_string()),
+            )
+            .await;
+            return Err(e);
+        }
+    };
+    log_simple_request(
+        &app_state,
+        start_time,
+        "GET",
+        "/admin/server-logs",
+        "admin_server_logs",
+        None,
+        None,
+        token_for_log(provided_token.as_deref()),
+        200,
+        None,
+    )
+    .await;
+
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+    let buffer = server_log_buffer();
+    if !query.tail {
+        return Ok(Json(ServerLogsResponse {
+            data: buffer.recent(&filter, limit),
+        })
+        .into_response());
+    }
+
+    // 先订阅再取快照，避免两者之间的日志丢失；用 seq 去重
+    let mut rx = buffer.subscribe();
+    let backlog = buffer.recent(&filter, limit);
+    let (tx, out_rx) = tokio::sync::mpsc::unbounded_channel::<Event>();
+    tokio::spawn(async move {
+        let mut last_seq = 0;
+        for entry in &backlog {
+            last_seq = entry.seq;
+            if tx.send(entry_event(entry)).is_err() {
+                return;
+            }
+        }
+        loop {
+            match rx.recv().await {
+                Ok(entry) => {
+                    if entry.seq <= last_seq || !filter.matches(&entry) {
+                        continue;
+                    }
+                    last_seq = entry.seq;
+                    if tx.send(entry_event(&entry)).is_err() {
+                        return;
+                    }
+                }
+                Err(RecvError::Lagged(skipped)) => {
+                    let ev = Event::default()
+                        .event("lagged")
+                        .data(format!("{{\"skipped\":{}}}", skipped));
+                    if tx.send(ev).is_err() {
+                        return;
+                    }
+                }
+                Err(RecvError::Closed) => return,
+            }
+        }
+    });
+    let stream = futures_util::StreamExt::map(
+        tokio_stream::wrappers::UnboundedReceiverStream::new(out_rx),
+        Ok::<_, Infallible>,
+    );
+    Ok(Sse::new(stream)
+        .keep_alive(axum::response::sse::KeepAlive::default())
+        .into_response())
+}
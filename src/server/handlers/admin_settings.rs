@@ -0,0 +1,310 @@
+use axum::{Json, extract::State, http::HeaderMap};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use super::auth::require_superadmin;
+use crate::error::GatewayError;
+use crate::server::AppState;
+use crate::server::branding::Branding;
+use crate::server::request_logging::log_simple_request;
+use crate::server::runtime_settings::{RuntimeSettings, SettingChange};
+use crate::server::util::{bearer_token, token_for_log};
+
+#[derive(Debug, Serialize)]
+pub struct RuntimeSettingsOut {
+    pub settings: RuntimeSettings,
+    /// 当前实际生效的日志过滤指令（未通过设置覆盖时来自 RUST_LOG）
+    pub effective_log_level: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PutRuntimeSettingsPayload {
+    #[serde(flatten)]
+    pub settings: RuntimeSettings,
+    /// 为 false 时仅校验并返回差异预览，不落库
+    #[serde(default)]
+    pub confirm: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PutRuntimeSettingsResponse {
+    pub applied: bool,
+    pub changes: Vec<SettingChange>,
+    pub settings: RuntimeSettings,
+}
+
+pub async fn get_settings(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<RuntimeSettingsOut>, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    if let Err(e) = require_superadmin(&headers, &app_state).await {
+        let code = e.status_code().as_u16();
+        log_simple_request(
+            &app_state,
+            start_time,
+            "GET",
+            "/admin/settings",
+            "admin_settings_get",
+            None,
+            None,
+            provided_token.as_deref(),
+            code,
+            Some(e.to_string()),
+        )
+        .await;
+        return Err(e);
+    }
+    log_simple_request(
+        &app_state,
+        start_time,
+        "GET",
+        "/admin/settings",
+        "admin_settings_get",
+        None,
+        None,
+        token_for_log(provided_token.as_deref()),
+        200,
+        None,
+    )
+    .await;
+    Ok(Json(RuntimeSettingsOut {
+        settings: app_state.runtime_settings.snapshot(),
+        effective_log_level: crate::logging::level::current_directives(),
+    }))
+}
+
+pub async fn put_settings(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(payload): Json<PutRuntimeSettingsPayload>,
+) -> Result<Json<PutRuntimeSettingsResponse>, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    if let Err(e) = require_superadmin(&headers, &app_state).await {
+        let code = e.status_code().as_u16();
+        log_simple_request(
+            &app_state,
+            start_time,
+            "PUT",
+            "/admin/settings",
+            "admin_settings_put",
+            None,
+            None,
+            provided_token.as_deref(),
+            code,
+            Some(e.to_string()),
+        )
+        .await;
+        return Err(e);
+    }
+
+    let result =
+        match payload.settings.validated() {
+            Ok(next) if !payload.confirm => Ok(PutRuntimeSettingsResponse {
+                applied: false,
+                changes: app_state.runtime_settings.snapshot().diff(&next),
+                settings: next,
+            }),
+            Ok(next) => app_state.runtime_settings.apply(next).await.map(|changes| {
+                PutRuntimeSettingsResponse {
+                    applied: true,
+                    changes,
+                    settings: app_state.runtime_settings.snapshot(),
+                }
+            }),
+            Err(e) => Err(e),
+        };
+    if let Ok(resp) = &result
+        && resp.applied
+        && !resp.changes.is_empty()
+    {
+        crate::server::cluster::publish(
+            &app_state,
+            crate::server::cluster::CacheEvent::RuntimeSettings,
+        );
+    }
+    let (code, err) = match &result {
+        Ok(_) => (200, None),
+        Err(e) => (e.status_code().as_u16(), Some(e.to_string())),
+    };
+    log_simple_request(
+        &app_state,
+        start_time,
+        "PUT",
+        "/admin/settings",
+        "admin_settings_put",
+        None,
+        None,
+        token_for_log(provided_token.as_deref()),
+        code,
+        err,
+    )
+    .await;
+    result.map(Json)
+}
+
+pub async fn get_branding(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<Branding>, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    let result = require_superadmin(&headers, &app_state)
+        .await
+        .map(|_| app_state.runtime_settings.snapshot().branding);
+    let (code, err) = match &result {
+        Ok(_) => (200, None),
+        Err(e) => (e.status_code().as_u16(), Some(e.to_string())),
+    };
+    log_simple_request(
+        &app_state,
+        start_time,
+        "GET",
+        "/admin/branding",
+        "admin_branding_get",
+        None,
+        None,
+        token_for_log(provided_token.as_deref()),
+        code,
+        err,
+    )
+    .await;
+    result.map(Json)
+}
+
+/// 整体替换品牌信息（未提供的字段视为清除），立即生效并同步到集群其他实例
+pub async fn put_branding(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(payload): Json<Branding>,
+) -> Result<Json<Branding>, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    let result = async {
+        require_superadmin(&headers, &app_state).await?;
+        let next = RuntimeSettings {
+            branding: payload,
+            ..app_state.runtime_settings.snapshot()
+        };
+        let changes = app_state.runtime_settings.apply(next).await?;
+        if !changes.is_empty() {
+            crate::server::cluster::publish(
+                &app_state,
+                crate::server::cluster::CacheEvent::RuntimeSettings,
+            );
+        }
+        Ok::<_, GatewayError>(app_state.runtime_settings.snapshot().branding)
+    }
+    .await;
+    let (code, err) = match &result {
+        Ok(_) => (200, None),
+        Err(e) => (e.status_code().as_u16(), Some(e.to_string())),
+    };
+    log_simple_request(
+        &app_state,
+        start_time,
+        "PUT",
+        "/admin/branding",
+        "admin_branding_put",
+        None,
+        None,
+        token_for_log(provided_token.as_deref()),
+        code,
+        err,
+    )
+    .await;
+    result.map(Json)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PutLogLevelsPayload {
+    /// 模块路径 -> 级别，例如 `{"server::streaming": "debug"}`；整体替换，空对象表示清除覆盖
+    #[serde(default)]
+    pub modules: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LogLevelsOut {
+    pub modules: BTreeMap<String, String>,
+    pub effective_log_level: Option<String>,
+}
+
+fn log_levels_snapshot() -> LogLevelsOut {
+    LogLevelsOut {
+        modules: crate::logging::level::module_levels(),
+        effective_log_level: crate::logging::level::current_directives(),
+    }
+}
+
+pub async fn get_log_levels(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<LogLevelsOut>, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    let result = require_superadmin(&headers, &app_state)
+        .await
+        .map(|_| log_levels_snapshot());
+    let (code, err) = match &result {
+        Ok(_) => (200, None),
+        Err(e) => (e.status_code().as_u16(), Some(e.to_string())),
+    };
+    log_simple_request(
+        &app_state,
+        start_time,
+        "GET",
+        "/admin/logging/level",
+        "admin_log_level_get",
+        None,
+        None,
+        token_for_log(provided_token.as_deref()),
+        code,
+        err,
+    )
+    .await;
+    result.map(Json)
+}
+
+/// 运行期按模块调整日志级别（仅对当前实例生效，重启后恢复），便于排障时临时打开 debug 日志
+pub async fn put_log_levels(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(payload): Json<PutLogLevelsPayload>,
+) -> Result<Json<LogLevelsOut>, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    let result = match require_superadmin(&headers, &app_state).await {
+        Ok(identity) => crate::logging::level::set_module_levels(payload.modules).map(|()| {
+            let out = log_levels_snapshot();
+            tracing::info!(
+                actor = %identity.actor(),
+                modules = ?out.modules,
+                "log levels updated"
+            );
+            out
+        }),
+        Err(e) => Err(e),
+    };
+    let (code, err) = match &result {
+        Ok(_) => (200, None),
+        Err(e) => (e.status_code().as_u16(), Some(e.to_string())),
+    };
+    log_simple_request(
+        &app_state,
+        start_time,
+        "PUT",
+        "/admin/logging/level",
+        "admin_log_level_put",
+        None,
+        None,
+        token_for_log(provided_token.as_deref()),
+        code,
+        err,
+    )
+    .await;
+    result.map(Json)
+}
@@ -0,0 +1,224 @@
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::{HeaderMap, header},
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use super::auth::require_superadmin;
+use crate::error::GatewayError;
+use crate::logging::time::to_iso8601_utc_string;
+use crate::server::AppState;
+use crate::server::request_logging::log_simple_request;
+use crate::server::statements::{self, GenerateReport, Statement, UsageLine};
+use crate::server::util::{bearer_token, token_for_log};
+
+async fn log_admin_call<T>(
+    app_state: &AppState,
+    start_time: DateTime<Utc>,
+    method: &str,
+    path: &str,
+    request_type: &str,
+    provided_token: Option<&str>,
+    result: &Result<T, GatewayError>,
+) {
+    let (code, err) = match result {
+        Ok(_) => (200, None),
+        Err(e) => (e.status_code().as_u16(), Some(e.to_string())),
+    };
+    log_simple_request(
+        app_state,
+        start_time,
+        method,
+        path,
+        request_type,
+        None,
+        None,
+        token_for_log(provided_token),
+        code,
+        err,
+    )
+    .await;
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StatementListQuery {
+    /// token | organization
+    pub scope: Option<String>,
+    pub subject_id: Option<String>,
+    /// YYYY-MM
+    pub period: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StatementSummary {
+    pub id: String,
+    pub scope: String,
+    pub subject_id: String,
+    pub subject_name: String,
+    pub period: String,
+    #[serde(flatten)]
+    pub totals: UsageLine,
+    pub created_at: String,
+}
+
+impl From<Statement> for StatementSummary {
+    fn from(s: Statement) -> Self {
+        Self {
+            id: s.id,
+            scope: s.scope,
+            subject_id: s.subject_id,
+            subject_name: s.subject_name,
+            period: s.period,
+            totals: s.totals,
+            created_at: to_iso8601_utc_string(&s.created_at),
+        }
+    }
+}
+
+pub async fn list_statements(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(q): Query<StatementListQuery>,
+) -> Result<Json<Vec<StatementSummary>>, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    let result = async {
+        require_superadmin(&headers, &app_state).await?;
+        let records = app_state
+            .log_store
+            .list_statements(
+                q.scope.as_deref(),
+                q.subject_id.as_deref(),
+                q.period.as_deref(),
+            )
+            .await
+            .map_err(GatewayError::Db)?;
+        records
+            .iter()
+            .map(|r| statements::parse_record(r).map(StatementSummary::from))
+            .collect::<Result<Vec<_>, _>>()
+    }
+    .await;
+    log_admin_call(
+        &app_state,
+        start_time,
+        "GET",
+        "/admin/statements",
+        "admin_statements_list",
+        provided_token.as_deref(),
+        &result,
+    )
+    .await;
+    result.map(Json)
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct GenerateStatementsPayload {
+    /// YYYY-MM，缺省为上一个自然月
+    #[serde(default)]
+    pub period: Option<String>,
+}
+
+pub async fn generate_statements(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(payload): Json<GenerateStatementsPayload>,
+) -> Result<Json<GenerateReport>, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    let result = async {
+        require_superadmin(&headers, &app_state).await?;
+        let now = Utc::now();
+        let period = payload
+            .period
+            .unwrap_or_else(|| statements::previous_period(now));
+        statements::generate_for_period(&app_state, &period, now).await
+    }
+    .await;
+    log_admin_call(
+        &app_state,
+        start_time,
+        "POST",
+        "/admin/statements/generate",
+        "admin_statements_generate",
+        provided_token.as_deref(),
+        &result,
+    )
+    .await;
+    result.map(Json)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StatementFormatQuery {
+    /// json（默认）| csv | pdf
+    pub format: Option<String>,
+}
+
+pub async fn get_statement(
+    Path(id): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(q): Query<StatementFormatQuery>,
+) -> Result<Response, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    let result = async {
+        require_superadmin(&headers, &app_state).await?;
+        let record = app_state
+            .log_store
+            .get_statement(&id)
+            .await
+            .map_err(GatewayError::Db)?
+            .ok_or_else(|| GatewayError::NotFound("statement not found".into()))?;
+        let statement = statements::parse_record(&record)?;
+        let export = match q.format.as_deref().unwrap_or("json") {
+            "json" => None,
+            "csv" => Some((
+                "csv",
+                "text/csv; charset=utf-8",
+                statements::to_csv(&statement).into_bytes(),
+            )),
+            "pdf" => Some(("pdf", "application/pdf", statements::to_pdf(&statement))),
+            other => {
+                return Err(GatewayError::Config(format!(
+                    "unsupported format '{}', expected json, csv or pdf",
+                    other
+                )));
+            }
+        };
+        Ok((statement, export))
+    }
+    .await;
+    log_admin_call(
+        &app_state,
+        start_time,
+        "GET",
+        "/admin/statements/{id}",
+        "admin_statements_get",
+        provided_token.as_deref(),
+        &result,
+    )
+    .await;
+    let (statement, export) = result?;
+    Ok(match export {
+        None => Json(statement).into_response(),
+        Some((ext, content_type, body)) => {
+            let disposition = format!(
+                "attachment; filename=\"statement-{}-{}-{}.{}\"",
+                statement.scope, statement.subject_id, statement.period, ext
+            );
+            (
+                [
+                    (header::CONTENT_TYPE, content_type.to_string()),
+                    (header::CONTENT_DISPOSITION, disposition),
+                ],
+                body,
+            )
+                .into_response()
+        }
+    })
+}
@@ -13,7 +13,7 @@ fn identity_updated_by(identity: &AdminIdentity) -> Option<String> {
     match identity {
         AdminIdentity::Jwt(claims) => Some(claims.sub.clone()),
         AdminIdentity::TuiSession(s) => Some(s.fingerprint.clone()),
-        AdminIdentity::WebSession(s) => s.fingerprint.clone(),
+        AdminIdentity::WebSession(s) => s.user_id.clone().or_else(|| s.fingerprint.clone()),
     }
 }
 
@@ -0,0 +1,114 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::HeaderMap,
+};
+use chrono::Utc;
+use serde::Serialize;
+use std::sync::Arc;
+
+use super::auth::require_superadmin;
+use crate::error::GatewayError;
+use crate::server::AppState;
+use crate::server::request_logging::log_simple_request;
+use crate::server::tasks::TaskInfo;
+use crate::server::util::{bearer_token, token_for_log};
+
+#[derive(Debug, Serialize)]
+pub struct TasksOut {
+    pub running: Vec<TaskInfo>,
+    /// 最近失败或被取消的任务（最新在前）
+    pub recent: Vec<TaskInfo>,
+}
+
+pub async fn list_tasks(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<TasksOut>, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    if let Err(e) = require_superadmin(&headers, &app_state).await {
+        log_simple_request(
+            &app_state,
+            start_time,
+            "GET",
+            "/admin/tasks",
+            "admin_tasks_list",
+            None,
+            None,
+            provided_token.as_deref(),
+            e.status_code().as_u16(),
+            Some(e.to_string()),
+        )
+        .await;
+        return Err(e);
+    }
+    let out = TasksOut {
+        running: app_state.task_registry.list_running(),
+        recent: app_state.task_registry.list_recent(),
+    };
+    log_simple_request(
+        &app_state,
+        start_time,
+        "GET",
+        "/admin/tasks",
+        "admin_tasks_list",
+        None,
+        None,
+        token_for_log(provided_token.as_deref()),
+        200,
+        None,
+    )
+    .await;
+    Ok(Json(out))
+}
+
+pub async fn cancel_task(
+    Path(id): Path<u64>,
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<serde_json::Value>, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    let path = format!("/admin/tasks/{}", id);
+    if let Err(e) = require_superadmin(&headers, &app_state).await {
+        log_simple_request(
+            &app_state,
+            start_time,
+            "DELETE",
+            &path,
+            "admin_task_cancel",
+            None,
+            None,
+            provided_token.as_deref(),
+            e.status_code().as_u16(),
+            Some(e.to_string()),
+        )
+        .await;
+        return Err(e);
+    }
+    let result = if app_state.task_registry.cancel(id) {
+        tracing::info!("Background task {} cancelled by admin", id);
+        Ok(serde_json::json!({ "success": true, "id": id }))
+    } else {
+        Err(GatewayError::NotFound("task not found".into()))
+    };
+    let (code, err) = match &result {
+        Ok(_) => (200, None),
+        Err(e) => (e.status_code().as_u16(), Some(e.to_string())),
+    };
+    log_simple_request(
+        &app_state,
+        start_time,
+        "DELETE",
+        &path,
+        "admin_task_cancel",
+        None,
+        None,
+        token_for_log(provided_token.as_deref()),
+        code,
+        err,
+    )
+    .await;
+    result.map(Json)
+}
@@ -0,0 +1,235 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::HeaderMap,
+};
+use chrono::Utc;
+use serde::Serialize;
+use std::sync::Arc;
+
+use super::auth::require_superadmin;
+use crate::error::GatewayError;
+use crate::logging::types::REQ_TYPE_ADMIN_TOKEN_TEST;
+use crate::providers::openai::types::RawAndTypedChatCompletion;
+use crate::server::AppState;
+use crate::server::chat_pipeline::{ChatAdmission, ChatTransport, check_chat_admission};
+use crate::server::chat_plan::DecisionTrace;
+use crate::server::chat_request::GatewayChatCompletionRequest;
+use crate::server::model_concurrency::FairShare;
+use crate::server::prompt_truncation::PromptTruncation;
+use crate::server::provider_dispatch::call_provider_with_parsed_model;
+use crate::server::request_lab::build_request_payload_snapshot;
+use crate::server::request_logging::{ChatLogContext, log_chat_request, log_simple_request};
+use crate::server::util::{bearer_token, mask_key, token_for_log};
+
+const PATH: &str = "/admin/tokens/{id}/test-request";
+
+#[derive(Debug, Serialize)]
+pub struct TokenTestRequestResponse {
+    pub token_id: String,
+    pub success: bool,
+    pub trace: DecisionTrace,
+    pub request_id: Option<i64>,
+    pub response_time_ms: Option<i64>,
+    pub response: Option<serde_json::Value>,
+    pub error_message: Option<String>,
+}
+
+async fn log_failure(
+    app_state: &Arc<AppState>,
+    start_time: chrono::DateTime<Utc>,
+    model: Option<String>,
+    provided_token: Option<&str>,
+    e: &GatewayError,
+) {
+    log_simple_request(
+        app_state,
+        start_time,
+        "POST",
+        PATH,
+        REQ_TYPE_ADMIN_TOKEN_TEST,
+        model,
+        None,
+        provided_token,
+        e.status_code().as_u16(),
+        Some(e.to_string()),
+    )
+    .await;
+}
+
+/// 管理员以指定令牌的身份发起一次非流式聊天请求：执行与正式请求相同的无副作用准入检查，
+/// 但费用记入管理员（不扣减该令牌/用户的额度），并返回完整的决策追踪。
+pub async fn token_test_request(
+    Path(id): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(gateway_req): Json<GatewayChatCompletionRequest>,
+) -> Result<Json<TokenTestRequestResponse>, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    let mut top_k = gateway_req.top_k;
+    let mut request = gateway_req.request;
+    let requested_model = request.model.clone();
+    if let Err(e) = require_superadmin(&headers, &app_state).await {
+        log_failure(
+            &app_state,
+            start_time,
+            Some(requested_model),
+            provided_token.as_deref(),
+            &e,
+        )
+        .await;
+        return Err(e);
+    }
+
+    let token = match app_state.token_store.get_token_by_id(&id).await? {
+        Some(t) => t,
+        None => {
+            let ge = GatewayError::NotFound("token not found".into());
+            log_failure(
+                &app_state,
+                start_time,
+                Some(requested_model),
+                token_for_log(provided_token.as_deref()),
+                &ge,
+            )
+            .await;
+            return Err(ge);
+        }
+    };
+
+    // 调试端点只走非流式路径，便于一次性返回追踪与响应
+    request.stream = Some(false);
+    let snapshot = build_request_payload_snapshot(&request, top_k)?;
+    let mut trace = DecisionTrace::new(&requested_model);
+    let admission = match check_chat_admission(
+        &app_state,
+        &mut request,
+        &mut top_k,
+        &token,
+        ChatTransport::NonStream,
+        None,
+        &mut trace,
+    )
+    .await
+    {
+        Ok(admission) => admission,
+        Err(e) => {
+            trace.rejection.get_or_insert_with(|| e.to_string());
+            log_failure(
+                &app_state,
+                start_time,
+                Some(requested_model),
+                token_for_log(provided_token.as_deref()),
+                &e,
+            )
+            .await;
+            return Ok(Json(TokenTestRequestResponse {
+                token_id: token.id,
+                success: false,
+                trace,
+                request_id: None,
+                response_time_ms: None,
+                response: None,
+                error_message: Some(e.to_string()),
+            }));
+        }
+    };
+
+    let ChatAdmission {
+        planned,
+        param_policy_applied,
+        prompt_truncation,
+        ..
+    } = admission;
+    // 与正式请求一样占用模型并发名额，测试请求不会绕过上游的并发上限
+    let concurrency_permit = match app_state
+        .model_concurrency
+        .acquire(
+            &app_state.config.server,
+            &planned.upstream_model,
+            &FairShare::for_token(&app_state.config.server, &token),
+        )
+        .await
+    {
+        Ok(permit) => permit,
+        Err(e) => {
+            log_failure(
+                &app_state,
+                start_time,
+                Some(requested_model),
+                token_for_log(provided_token.as_deref()),
+                &e,
+            )
+            .await;
+            return Err(e);
+        }
+    };
+    let upstream_started_at = Utc::now();
+    let response = call_provider_with_parsed_model(
+        &app_state,
+        &planned.selected,
+        &request,
+        &planned.parsed_model,
+        top_k,
+        None,
+    )
+    .await;
+    let upstream_finished_at = Utc::now();
+    drop(concurrency_permit);
+    let response_for_log: Result<RawAndTypedChatCompletion, GatewayError> = match &response {
+        Ok(dual) if dual.raw.get("error").is_some() && dual.raw.get("choices").is_none() => Err(
+            GatewayError::Config(format!("upstream returned error payload: {}", dual.raw)),
+        ),
+        Ok(dual) => Ok(dual.clone()),
+        Err(err) => Err(GatewayError::Config(err.to_string())),
+    };
+
+    // client_token 传 None：本次请求不计入该令牌/用户的额度，仅记录在管理员名下
+    let logged = log_chat_request(
+        &app_state,
+        start_time,
+        &planned.billing_model,
+        &requested_model,
+        &planned.upstream_model,
+        &planned.selected.provider.name,
+        &planned.selected.api_key,
+        None,
+        &response_for_log,
+        ChatLogContext {
+            path: PATH.to_string(),
+            request_type: REQ_TYPE_ADMIN_TOKEN_TEST.to_string(),
+            request_payload_snapshot: Some(snapshot),
+            upstream_status: Some(if response.is_ok() { 200 } else { 500 }),
+            selected_provider: Some(planned.selected.provider.name.clone()),
+            selected_key_id: Some(mask_key(&planned.selected.api_key)),
+            first_token_latency_ms: None,
+            param_policy_applied,
+            provider_override: None,
+            prompt_truncation: prompt_truncation.as_ref().map(PromptTruncation::log_value),
+            debug_capture: false,
+            upstream_started_at: Some(upstream_started_at),
+            upstream_finished_at: Some(upstream_finished_at),
+            prompt_profile: Default::default(),
+        },
+    )
+    .await;
+
+    let (success, response, error_message) = match response_for_log {
+        Ok(dual) => (true, Some(dual.raw), None),
+        Err(e) => (
+            false,
+            response.ok().map(|dual| dual.raw),
+            Some(e.to_string()),
+        ),
+    };
+    Ok(Json(TokenTestRequestResponse {
+        token_id: token.id,
+        success,
+        trace,
+        request_id: logged.log_id,
+        response_time_ms: Some(logged.response_time_ms),
+        response,
+        error_message,
+    }))
+}
@@ -0,0 +1,206 @@
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::HeaderMap,
+};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::sync::Arc;
+
+use super::auth::require_superadmin;
+use crate::error::GatewayError;
+use crate::logging::types::UsageWebhookDeadLetter;
+use crate::server::AppState;
+use crate::server::request_logging::log_simple_request;
+use crate::server::usage_webhooks;
+use crate::server::util::{bearer_token, token_for_log};
+
+const DEAD_LETTER_DEFAULT_LIMIT: i32 = 100;
+const DEAD_LETTER_MAX_LIMIT: i32 = 1000;
+
+#[derive(Debug, Deserialize)]
+pub struct DeadLetterQuery {
+    pub limit: Option<i32>,
+}
+
+async fn log_admin_call(
+    app_state: &AppState,
+    start_time: DateTime<Utc>,
+    method: &str,
+    path: &str,
+    request_type: &str,
+    provided_token: Option<&str>,
+    result: Result<(), &GatewayError>,
+) {
+    let (code, err) = match result {
+        Ok(()) => (200, None),
+        Err(e) => (e.status_code().as_u16(), Some(e.to_string())),
+    };
+    log_simple_request(
+        app_state,
+        start_time,
+        method,
+        path,
+        request_type,
+        None,
+        None,
+        token_for_log(provided_token),
+        code,
+        err,
+    )
+    .await;
+}
+
+/// 重试耗尽的用量 Webhook 投递记录（最新在前）
+pub async fn list_dead_letters(
+    Query(query): Query<DeadLetterQuery>,
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<UsageWebhookDeadLetter>>, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    if let Err(e) = require_superadmin(&headers, &app_state).await {
+        log_simple_request(
+            &app_state,
+            start_time,
+            "GET",
+            "/admin/usage-webhooks/dead-letters",
+            "admin_usage_webhook_dead_letters_list",
+            None,
+            None,
+            provided_token.as_deref(),
+            e.status_code().as_u16(),
+            Some(e.to_string()),
+        )
+        .await;
+        return Err(e);
+    }
+    let limit = query
+        .limit
+        .unwrap_or(DEAD_LETTER_DEFAULT_LIMIT)
+        .clamp(1, DEAD_LETTER_MAX_LIMIT);
+    let result = app_state
+        .log_store
+        .list_usage_webhook_dead_letters(limit)
+        .await
+        .map_err(GatewayError::from);
+    log_admin_call(
+        &app_state,
+        start_time,
+        "GET",
+        "/admin/usage-webhooks/dead-letters",
+        "admin_usage_webhook_dead_letters_list",
+        provided_token.as_deref(),
+        result.as_ref().map(|_| ()),
+    )
+    .await;
+    result.map(Json)
+}
+
+/// 重新投递一条死信：使用令牌当前明文签名；令牌已删除时返回 404
+pub async fn retry_dead_letter(
+    Path(id): Path<i64>,
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<serde_json::Value>, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    let path = format!("/admin/usage-webhooks/dead-letters/{}/retry", id);
+    if let Err(e) = require_superadmin(&headers, &app_state).await {
+        log_simple_request(
+            &app_state,
+            start_time,
+            "POST",
+            &path,
+            "admin_usage_webhook_dead_letter_retry",
+            None,
+            None,
+            provided_token.as_deref(),
+            e.status_code().as_u16(),
+            Some(e.to_string()),
+        )
+        .await;
+        return Err(e);
+    }
+    let result = async {
+        let letter = app_state
+            .log_store
+            .get_usage_webhook_dead_letter(id)
+            .await?
+            .ok_or_else(|| GatewayError::NotFound("dead letter not found".into()))?;
+        let token = app_state
+            .token_store
+            .get_token_by_id(&letter.token_id)
+            .await?
+            .ok_or_else(|| GatewayError::NotFound("token not found".into()))?;
+        let secret = token.usage_webhook_secret.ok_or_else(|| {
+            GatewayError::Config(
+                "token has no usage webhook secret; set usage_webhook_url first".into(),
+            )
+        })?;
+        app_state
+            .log_store
+            .delete_usage_webhook_dead_letter(id)
+            .await?;
+        usage_webhooks::requeue_dead_letter(&app_state, letter, secret);
+        Ok(serde_json::json!({ "success": true, "id": id }))
+    }
+    .await;
+    log_admin_call(
+        &app_state,
+        start_time,
+        "POST",
+        &path,
+        "admin_usage_webhook_dead_letter_retry",
+        provided_token.as_deref(),
+        result.as_ref().map(|_| ()),
+    )
+    .await;
+    result.map(Json)
+}
+
+pub async fn delete_dead_letter(
+    Path(id): Path<i64>,
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<serde_json::Value>, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    let path = format!("/admin/usage-webhooks/dead-letters/{}", id);
+    if let Err(e) = require_superadmin(&headers, &app_state).await {
+        log_simple_request(
+            &app_state,
+            start_time,
+            "DELETE",
+            &path,
+            "admin_usage_webhook_dead_letter_delete",
+            None,
+            None,
+            provided_token.as_deref(),
+            e.status_code().as_u16(),
+            Some(e.to_string()),
+        )
+        .await;
+        return Err(e);
+    }
+    let result = match app_state
+        .log_store
+        .delete_usage_webhook_dead_letter(id)
+        .await
+    {
+        Ok(true) => Ok(serde_json::json!({ "success": true, "id": id })),
+        Ok(false) => Err(GatewayError::NotFound("dead letter not found".into())),
+        Err(e) => Err(GatewayError::from(e)),
+    };
+    log_admin_call(
+        &app_state,
+        start_time,
+        "DELETE",
+        &path,
+        "admin_usage_webhook_dead_letter_delete",
+        provided_token.as_deref(),
+        result.as_ref().map(|_| ()),
+    )
+    .await;
+    result.map(Json)
+}
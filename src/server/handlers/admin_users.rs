@@ -1,6 +1,6 @@
 use axum::{
     Json,
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::{HeaderMap, header},
     response::IntoResponse,
 };
@@ -10,7 +10,9 @@ use std::sync::Arc;
 use super::auth::require_superadmin;
 use crate::error::GatewayError;
 use crate::server::AppState;
+use crate::server::pagination::{Listing, PageQuery, SortFields, SortOrder};
 use crate::server::request_logging::log_simple_request;
+use crate::server::user_budget::{self, UserBudgetPayload, UserBudgetStatus};
 use crate::server::util::{bearer_token, token_for_log};
 use crate::users::{CreateUserPayload, UpdateUserPayload, User};
 use chrono::Utc;
@@ -48,10 +50,27 @@ impl From<User> for UserOut {
     }
 }
 
+const USER_SORT_FIELDS: SortFields = SortFields {
+    allowed: &["created_at", "username", "email", "balance"],
+    default: "created_at",
+    default_order: SortOrder::Desc,
+};
+
+fn compare_users(sort: &str, a: &UserOut, b: &UserOut) -> std::cmp::Ordering {
+    let primary = match sort {
+        "username" => a.username.cmp(&b.username),
+        "email" => a.email.cmp(&b.email),
+        "balance" => a.balance.total_cmp(&b.balance),
+        _ => a.created_at.cmp(&b.created_at),
+    };
+    primary.then_with(|| a.id.cmp(&b.id))
+}
+
 pub async fn list_users(
     State(app_state): State<Arc<AppState>>,
     headers: HeaderMap,
-) -> Result<Json<Vec<UserOut>>, GatewayError> {
+    Query(page): Query<PageQuery>,
+) -> Result<Json<Listing<UserOut>>, GatewayError> {
     let start_time = Utc::now();
     let provided_token = bearer_token(&headers);
     if let Err(e) = require_superadmin(&headers, &app_state).await {
@@ -72,6 +91,7 @@ pub async fn list_users(
         return Err(e);
     }
 
+    let page = page.resolve(&USER_SORT_FIELDS)?;
     let users = app_state.user_store.list_users().await?;
     for u in users.iter() {
         if u.balance <= 0.0 {
@@ -81,7 +101,12 @@ pub async fn list_users(
                 .await;
         }
     }
-    let users = users.into_iter().map(UserOut::from).collect();
+    // 余额检查需要遍历全部用户，分页在内存中完成
+    let users = Listing::from_vec(
+        users.into_iter().map(UserOut::from).collect(),
+        page.as_ref(),
+        compare_users,
+    );
     log_simple_request(
         &app_state,
         start_time,
@@ -260,6 +285,78 @@ pub async fn update_user(
     }
 }
 
+pub async fn get_user_budget(
+    Path(id): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<UserBudgetStatus>, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    let result = async {
+        require_superadmin(&headers, &app_state).await?;
+        if app_state.user_store.get_user(&id).await?.is_none() {
+            return Err(GatewayError::NotFound("user not found".into()));
+        }
+        user_budget::status(&app_state, &id).await
+    }
+    .await;
+    let (code, err) = match &result {
+        Ok(_) => (200, None),
+        Err(e) => (e.status_code().as_u16(), Some(e.to_string())),
+    };
+    log_simple_request(
+        &app_state,
+        start_time,
+        "GET",
+        &format!("/admin/users/{}/budget", id),
+        "admin_users_budget_get",
+        None,
+        None,
+        token_for_log(provided_token.as_deref()),
+        code,
+        err,
+    )
+    .await;
+    result.map(Json)
+}
+
+pub async fn put_user_budget(
+    Path(id): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(payload): Json<UserBudgetPayload>,
+) -> Result<Json<UserBudgetStatus>, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    let result = async {
+        require_superadmin(&headers, &app_state).await?;
+        let max_amount = payload.validated()?;
+        if !app_state.user_store.set_budget(&id, max_amount).await? {
+            return Err(GatewayError::NotFound("user not found".into()));
+        }
+        user_budget::status(&app_state, &id).await
+    }
+    .await;
+    let (code, err) = match &result {
+        Ok(_) => (200, None),
+        Err(e) => (e.status_code().as_u16(), Some(e.to_string())),
+    };
+    log_simple_request(
+        &app_state,
+        start_time,
+        "PUT",
+        &format!("/admin/users/{}/budget", id),
+        "admin_users_budget_update",
+        None,
+        None,
+        token_for_log(provided_token.as_deref()),
+        code,
+        err,
+    )
+    .await;
+    result.map(Json)
+}
+
 pub async fn delete_user(
     Path(id): Path<String>,
     State(app_state): State<Arc<AppState>>,
@@ -408,7 +505,6 @@ mod tests {
     use crate::config::BalanceStrategy;
     use crate::config::settings::{LoadBalancing, LoggingConfig, ServerConfig};
     use crate::logging::DatabaseLogger;
-    use crate::server::login::LoginManager;
     use crate::server::storage_traits::{AdminPublicKeyRecord, LoginStore, TuiSessionRecord};
     use crate::users::{UserRole, UserStatus};
     use axum::http::{HeaderValue, header::AUTHORIZATION};
@@ -454,6 +550,7 @@ mod tests {
                 enabled: true,
                 created_at: now,
                 last_used_at: None,
+                expires_at: None,
             })
             .await
             .unwrap();
@@ -471,25 +568,7 @@ mod tests {
             .await
             .unwrap();
 
-        let refresh_token_store = logger.clone();
-        let password_reset_token_store = logger.clone();
-
-        let app_state = Arc::new(AppState {
-            config: settings,
-            load_balancer_state: Arc::new(crate::routing::LoadBalancerState::default()),
-            log_store: logger.clone(),
-            model_cache: logger.clone(),
-            providers: logger.clone(),
-            token_store: logger.clone(),
-            favorites_store: logger.clone(),
-            organizations: logger.clone(),
-            login_manager: Arc::new(LoginManager::new(logger.clone())),
-            user_store: logger.clone(),
-            refresh_token_store,
-            password_reset_token_store,
-            balance_store: logger.clone(),
-            subscription_store: logger.clone(),
-        });
+        let app_state = crate::server::test_harness::app_state(settings, logger.clone());
 
         Harness {
             _dir: dir,
@@ -510,7 +589,12 @@ mod tests {
     #[tokio::test]
     async fn admin_users_requires_admin_auth() {
         let h = harness().await;
-        let res = list_users(State(h.state), HeaderMap::new()).await;
+        let res = list_users(
+            State(h.state),
+            HeaderMap::new(),
+            Query(PageQuery::default()),
+        )
+        .await;
         assert!(res.is_err());
     }
 
@@ -539,7 +623,13 @@ mod tests {
         assert_eq!(code, axum::http::StatusCode::CREATED);
         assert_eq!(created.email, "bob@example.com");
 
-        let Json(list) = list_users(State(h.state), headers).await.unwrap();
+        let Json(Listing::All(list)) =
+            list_users(State(h.state), headers, Query(PageQuery::default()))
+                .await
+                .unwrap()
+        else {
+            panic!("unpaged list should return an array");
+        };
         assert_eq!(list.len(), 1);
         assert_eq!(list[0].id, created.id);
     }
@@ -572,26 +662,17 @@ mod tests {
             .state
             .token_store
             .create_token(CreateTokenPayload {
-                id: None,
                 user_id: Some(created.id.clone()),
                 name: Some("t1".into()),
-                token: None,
-                allowed_models: None,
-                model_blacklist: None,
-                max_tokens: None,
-                max_amount: None,
-                enabled: true,
-                expires_at: None,
-                remark: None,
-                organization_id: None,
-                ip_whitelist: None,
-                ip_blacklist: None,
+                ..Default::default()
             })
             .await
             .unwrap();
         assert!(tok.enabled);
 
-        let _ = list_users(State(h.state.clone()), headers).await.unwrap();
+        let _ = list_users(State(h.state.clone()), headers, Query(PageQuery::default()))
+            .await
+            .unwrap();
 
         let refreshed = h
             .state
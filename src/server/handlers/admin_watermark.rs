@@ -0,0 +1,92 @@
+use axum::{Json, extract::State, http::HeaderMap};
+use chrono::{TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use super::auth::require_superadmin;
+use crate::error::GatewayError;
+use crate::server::AppState;
+use crate::server::request_logging::log_simple_request;
+use crate::server::util::{bearer_token, token_for_log};
+use crate::server::watermark::{Watermark, token_hash};
+
+#[derive(Debug, Deserialize)]
+pub struct TraceWatermarkRequest {
+    /// 响应头 `X-Gateway-Watermark` 或响应体 `gateway_watermark` 的值
+    pub watermark: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TracedToken {
+    pub id: String,
+    pub name: String,
+    pub user_id: Option<String>,
+    pub organization_id: Option<String>,
+    pub enabled: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WatermarkTrace {
+    pub request_id: String,
+    pub token_hash: String,
+    pub issued_at: String,
+    /// 令牌已删除时为 null
+    pub token: Option<TracedToken>,
+}
+
+/// 校验水印签名并追溯到签发令牌
+pub async fn trace_watermark(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(req): Json<TraceWatermarkRequest>,
+) -> Result<Json<WatermarkTrace>, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    let result: Result<WatermarkTrace, GatewayError> = async {
+        require_superadmin(&headers, &app_state).await?;
+        let watermark = Watermark::verify(&req.watermark)?;
+        let token = app_state
+            .token_store
+            .list_tokens()
+            .await?
+            .into_iter()
+            .find(|t| token_hash(&t.id) == watermark.token_hash)
+            .map(|t| TracedToken {
+                id: t.id,
+                name: t.name,
+                user_id: t.user_id,
+                organization_id: t.organization_id,
+                enabled: t.enabled,
+            });
+        let issued_at = Utc
+            .timestamp_opt(watermark.issued_at, 0)
+            .single()
+            .map(|t| t.to_rfc3339())
+            .unwrap_or_default();
+        Ok(WatermarkTrace {
+            request_id: watermark.request_id,
+            token_hash: watermark.token_hash,
+            issued_at,
+            token,
+        })
+    }
+    .await;
+    let (code, err) = match &result {
+        Ok(_) => (200, None),
+        Err(e) => (e.status_code().as_u16(), Some(e.to_string())),
+    };
+    log_simple_request(
+        &app_state,
+        start_time,
+        "POST",
+        "/admin/watermarks/trace",
+        "admin_watermark_trace",
+        None,
+        None,
+        token_for_log(provided_token.as_deref()),
+        code,
+        err,
+    )
+    .await;
+    result.map(Json)
+}
@@ -0,0 +1,780 @@
+use axum::{
+    Json,
+    body::{Body, Bytes},
+    extract::{Request, State},
+    http::{HeaderMap, HeaderValue, StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use futures_util::StreamExt;
+use serde_json::{Map, Value, json};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use super::chat::chat_completions;
+use crate::error::GatewayError;
+use crate::server::AppState;
+use crate::server::chat_request::GatewayChatCompletionRequest;
+
+/// Anthropic SDK 的密钥请求头
+const ANTHROPIC_API_KEY_HEADER: &str = "x-api-key";
+/// Messages API 路径（挂载于 /api 之下）
+pub const MESSAGES_PATH: &str = "/v1/messages";
+
+/// Anthropic SDK 通过 `x-api-key` 请求头传递密钥；
+/// 在签名与父令牌校验之前改写为 `Authorization: Bearer <client-token>`
+pub async fn anthropic_api_key_auth(mut request: Request, next: Next) -> Response {
+    let path = request.uri().path();
+    if path.strip_prefix("/api").unwrap_or(path) == MESSAGES_PATH
+        && !request.headers().contains_key(header::AUTHORIZATION)
+        && let Some(key) = request
+            .headers()
+            .get(ANTHROPIC_API_KEY_HEADER)
+            .and_then(|v| v.to_str().ok())
+        && let Ok(bearer) = HeaderValue::from_str(&format!("Bearer {}", key.trim()))
+    {
+        request.headers_mut().insert(header::AUTHORIZATION, bearer);
+    }
+    next.run(request).await
+}
+
+/// 字符串或 text block 数组中的文本
+fn text_of(content: &Value) -> String {
+    match content {
+        Value::String(text) => text.clone(),
+        Value::Array(blocks) => blocks
+            .iter()
+            .filter(|b| b.get("type").and_then(Value::as_str) == Some("text"))
+            .filter_map(|b| b.get("text").and_then(Value::as_str))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    }
+}
+
+fn image_part(source: &Value) -> Option<Value> {
+    let url = match source.get("type").and_then(Value::as_str) {
+        Some("base64") => format!(
+            "data:{};base64,{}",
+            source
+                .get("media_type")
+                .and_then(Value::as_str)
+                .unwrap_or("image/png"),
+            source.get("data").and_then(Value::as_str)?
+        ),
+        Some("url") => source.get("url").and_then(Value::as_str)?.to_string(),
+        _ => return None,
+    };
+    Some(json!({"type": "image_url", "image_url": {"url": url}}))
+}
+
+/// Anthropic messages 转为 OpenAI messages：
+/// - tool_use 转为 assistant 的 tool_calls，tool_result 转为 tool 消息（ID 原样沿用）
+/// - thinking / redacted_thinking 属于上一轮的思考内容，不再回传上游
+fn convert_messages(body: &Value) -> Result<Vec<Value>, GatewayError> {
+    let mut messages = Vec::new();
+    if let Some(system) = body.get("system") {
+        let system = text_of(system);
+        if !system.is_empty() {
+            messages.push(json!({"role": "system", "content": system}));
+        }
+    }
+    let input = body
+        .get("messages")
+        .and_then(Value::as_array)
+        .ok_or_else(|| GatewayError::Config("messages is required".into()))?;
+    for message in input {
+        let role = message
+            .get("role")
+            .and_then(Value::as_str)
+            .unwrap_or("user");
+        let content = message.get("content").unwrap_or(&Value::Null);
+        let Some(blocks) = content.as_array() else {
+            messages.push(json!({"role": role, "content": text_of(content)}));
+            continue;
+        };
+
+        if role == "assistant" {
+            let tool_calls: Vec<Value> = blocks
+                .iter()
+                .filter(|b| b.get("type").and_then(Value::as_str) == Some("tool_use"))
+                .map(|b| {
+                    json!({
+                        "id": b.get("id").cloned().unwrap_or(Value::Null),
+                        "type": "function",
+                        "function": {
+                            "name": b.get("name").cloned().unwrap_or(Value::Null),
+                            "arguments": b.get("input").cloned().unwrap_or_else(|| json!({})).to_string(),
+                        }
+                    })
+                })
+                .collect();
+            let text = text_of(content);
+            let mut msg = json!({"role": "assistant", "content": text});
+            if !tool_calls.is_empty() {
+                if text.is_empty() {
+                    msg["content"] = Value::Null;
+                }
+                msg["tool_calls"] = Value::Array(tool_calls);
+            }
+            messages.push(msg);
+            continue;
+        }
+
+        let mut user_parts = Vec::new();
+        for block in blocks {
+            match block.get("type").and_then(Value::as_str) {
+                Some("text") => {
+                    if let Some(text) = block.get("text").and_then(Value::as_str) {
+                        user_parts.push(json!({"type": "text", "text": text}));
+                    }
+                }
+                Some("image") => {
+                    if let Some(part) = block.get("source").and_then(image_part) {
+                        user_parts.push(part);
+                    }
+                }
+                Some("tool_result") => {
+                    let mut output = text_of(block.get("content").unwrap_or(&Value::Null));
+                    if block.get("is_error").and_then(Value::as_bool) == Some(true) {
+                        output = format!("[tool error] {}", output);
+                    }
+                    messages.push(json!({
+                        "role": "tool",
+                        "tool_call_id": block.get("tool_use_id").cloned().unwrap_or(Value::Null),
+                        "content": output
+                    }));
+                }
+                _ => {}
+            }
+        }
+        match user_parts.as_slice() {
+            [] => {}
+            [only] if only["type"] == "text" => {
+                messages.push(json!({"role": "user", "content": only["text"]}));
+            }
+            _ => messages.push(json!({"role": "user", "content": user_parts})),
+        }
+    }
+    Ok(messages)
+}
+
+/// 自定义工具转为 function；web_search 等服务端工具没有 input_schema，无法转换，直接丢弃
+fn convert_tools(body: &Value, req: &mut Map<String, Value>) {
+    let tools: Vec<Value> = body
+        .get("tools")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter(|t| t.get("input_schema").is_some())
+        .map(|t| {
+            let mut function = json!({
+                "name": t.get("name").cloned().unwrap_or(Value::Null),
+                "parameters": t["input_schema"].clone(),
+            });
+            if let Some(description) = t.get("description") {
+                function["description"] = description.clone();
+            }
+            json!({"type": "function", "function": function})
+        })
+        .collect();
+    if tools.is_empty() {
+        return;
+    }
+    req.insert("tools".into(), Value::Array(tools));
+    let Some(choice) = body.get("tool_choice") else {
+        return;
+    };
+    let mapped = match choice.get("type").and_then(Value::as_str) {
+        Some("any") => json!("required"),
+        Some("none") => json!("none"),
+        Some("tool") => json!({"type": "function", "function": {"name": choice.get("name")}}),
+        _ => json!("auto"),
+    };
+    req.insert("tool_choice".into(), mapped);
+    if choice
+        .get("disable_parallel_tool_use")
+        .and_then(Value::as_bool)
+        == Some(true)
+    {
+        req.insert("parallel_tool_calls".into(), json!(false));
+    }
+}
+
+/// extended thinking 的 budget_tokens 映射为 reasoning_effort（Anthropic 供应商再按档位换回预算）
+fn reasoning_effort(budget_tokens: u64) -> &'static str {
+    match budget_tokens {
+        0..=2048 => "low",
+        2049..=8192 => "medium",
+        _ => "high",
+    }
+}
+
+/// Anthropic Messages 请求转为网关请求
+fn anthropic_request_to_gateway(
+    body: &Value,
+) -> Result<GatewayChatCompletionRequest, GatewayError> {
+    let model = body
+        .get("model")
+        .and_then(Value::as_str)
+        .ok_or_else(|| GatewayError::Config("model is required".into()))?;
+    let mut req = Map::new();
+    req.insert("model".into(), json!(model));
+    req.insert("messages".into(), Value::Array(convert_messages(body)?));
+    if body.get("stream").and_then(Value::as_bool) == Some(true) {
+        req.insert("stream".into(), json!(true));
+        req.insert("stream_options".into(), json!({"include_usage": true}));
+    }
+    for (source, target) in [
+        ("temperature", "temperature"),
+        ("top_p", "top_p"),
+        ("stop_sequences", "stop"),
+    ] {
+        if let Some(v) = body.get(source) {
+            req.insert(target.into(), v.clone());
+        }
+    }
+    let mut max_tokens = body.get("max_tokens").and_then(Value::as_u64);
+    if let Some(thinking) = body.get("thinking")
+        && thinking.get("type").and_then(Value::as_str) == Some("enabled")
+    {
+        let budget = thinking
+            .get("budget_tokens")
+            .and_then(Value::as_u64)
+            .unwrap_or(0);
+        req.insert("reasoning_effort".into(), json!(reasoning_effort(budget)));
+        // Anthropic 的 max_tokens 包含思考预算，网关的 max_tokens 仅计可见输出
+        max_tokens = max_tokens.map(|m| if m > budget { m - budget } else { m });
+    }
+    if let Some(max_tokens) = max_tokens {
+        req.insert("max_tokens".into(), json!(max_tokens));
+    }
+    if let Some(user) = body.pointer("/metadata/user_id") {
+        req.insert("user".into(), user.clone());
+    }
+    convert_tools(body, &mut req);
+
+    let mut request: GatewayChatCompletionRequest = serde_json::from_value(Value::Object(req))
+        .map_err(|e| GatewayError::Config(format!("invalid messages request: {}", e)))?;
+    request.top_k = body.get("top_k").and_then(Value::as_u64).map(|k| k as u32);
+    Ok(request)
+}
+
+fn stop_reason(finish_reason: &str) -> &'static str {
+    match finish_reason {
+        "length" => "max_tokens",
+        "tool_calls" | "function_call" => "tool_use",
+        "content_filter" => "refusal",
+        _ => "end_turn",
+    }
+}
+
+fn usage_of(usage: Option<&Value>) -> Value {
+    let count = |key: &str| usage.and_then(|u| u.get(key)).cloned().unwrap_or(json!(0));
+    json!({
+        "input_tokens": count("prompt_tokens"),
+        "output_tokens": count("completion_tokens"),
+    })
+}
+
+fn tool_use_block(id: &Value, name: &Value, arguments: &str) -> Value {
+    let input = serde_json::from_str::<Value>(arguments).unwrap_or_else(|_| json!({}));
+    json!({"type": "tool_use", "id": id, "name": name, "input": input})
+}
+
+/// 网关（OpenAI 格式）完整响应转为 Anthropic Message
+fn gateway_response_to_anthropic(v: &Value) -> Value {
+    let choice = v.pointer("/choices/0").cloned().unwrap_or(Value::Null);
+    let msg = choice.get("message").cloned().unwrap_or(Value::Null);
+    let mut content = Vec::new();
+    if let Some(reasoning) = msg.get("reasoning_content").and_then(Value::as_str)
+        && !reasoning.is_empty()
+    {
+        content.push(json!({"type": "thinking", "thinking": reasoning, "signature": ""}));
+    }
+    if let Some(text) = msg.get("content").and_then(Value::as_str)
+        && !text.is_empty()
+    {
+        content.push(json!({"type": "text", "text": text}));
+    }
+    for call in msg
+        .get("tool_calls")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+    {
+        content.push(tool_use_block(
+            call.get("id").unwrap_or(&Value::Null),
+            call.pointer("/function/name").unwrap_or(&Value::Null),
+            call.pointer("/function/arguments")
+                .and_then(Value::as_str)
+                .unwrap_or("{}"),
+        ));
+    }
+    json!({
+        "id": v.get("id").cloned().unwrap_or(Value::Null),
+        "type": "message",
+        "role": "assistant",
+        "model": v.get("model").cloned().unwrap_or(Value::Null),
+        "content": content,
+        "stop_reason": choice
+            .get("finish_reason")
+            .and_then(Value::as_str)
+            .map(stop_reason),
+        "stop_sequence": Value::Null,
+        "usage": usage_of(v.get("usage").filter(|u| !u.is_null())),
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BlockKind {
+    Thinking,
+    Text,
+    /// OpenAI tool_calls 下标
+    ToolUse(u64),
+}
+
+/// 流式转换状态：OpenAI chunk 转为 Anthropic 事件序列
+/// （message_start → content_block_* → message_delta → message_stop），
+/// 同一时刻只打开一个 content block，类型切换时先关闭上一个
+#[derive(Default)]
+struct AnthropicStream {
+    started: bool,
+    finished: bool,
+    open: Option<(usize, BlockKind)>,
+    next_index: usize,
+    tool_blocks: HashMap<u64, usize>,
+    stop_reason: Option<&'static str>,
+    usage: Option<Value>,
+}
+
+impl AnthropicStream {
+    fn start(&mut self, chunk: &Value, events: &mut Vec<(&'static str, Value)>) {
+        if self.started {
+            return;
+        }
+        self.started = true;
+        events.push((
+            "message_start",
+            json!({"type": "message_start", "message": {
+                "id": chunk.get("id").cloned().unwrap_or(Value::Null),
+                "type": "message",
+                "role": "assistant",
+                "model": chunk.get("model").cloned().unwrap_or(Value::Null),
+                "content": [],
+                "stop_reason": Value::Null,
+                "stop_sequence": Value::Null,
+                "usage": {"input_tokens": 0, "output_tokens": 0},
+            }}),
+        ));
+    }
+
+    fn close(&mut self, events: &mut Vec<(&'static str, Value)>) {
+        if let Some((index, _)) = self.open.take() {
+            events.push((
+                "content_block_stop",
+                json!({"type": "content_block_stop", "index": index}),
+            ));
+        }
+    }
+
+    /// 确保指定类型的 block 处于打开状态，返回其下标
+    fn open(
+        &mut self,
+        kind: BlockKind,
+        block: Value,
+        events: &mut Vec<(&'static str, Value)>,
+    ) -> usize {
+        if let Some((index, open)) = self.open
+            && open == kind
+        {
+            return index;
+        }
+        self.close(events);
+        let index = self.next_index;
+        self.next_index += 1;
+        self.open = Some((index, kind));
+        events.push((
+            "content_block_start",
+            json!({"type": "content_block_start", "index": index, "content_block": block}),
+        ));
+        index
+    }
+
+    fn delta(index: usize, delta: Value) -> (&'static str, Value) {
+        (
+            "content_block_delta",
+            json!({"type": "content_block_delta", "index": index, "delta": delta}),
+        )
+    }
+
+    fn convert_chunk(&mut self, chunk: &Value) -> Vec<(&'static str, Value)> {
+        let mut events = Vec::new();
+        self.start(chunk, &mut events);
+        if let Some(usage) = chunk.get("usage").filter(|u| !u.is_null()) {
+            self.usage = Some(usage.clone());
+        }
+        // n>1 时只转换第一个候选（Anthropic 不支持多候选）
+        let Some(choice) = chunk
+            .get("choices")
+            .and_then(Value::as_array)
+            .and_then(|choices| {
+                choices
+                    .iter()
+                    .find(|c| c["index"].as_u64().unwrap_or(0) == 0)
+            })
+        else {
+            return events;
+        };
+        let delta = choice.get("delta").unwrap_or(&Value::Null);
+        if let Some(thinking) = delta.get("reasoning_content").and_then(Value::as_str)
+            && !thinking.is_empty()
+        {
+            let index = self.open(
+                BlockKind::Thinking,
+                json!({"type": "thinking", "thinking": ""}),
+                &mut events,
+            );
+            events.push(Self::delta(
+                index,
+                json!({"type": "thinking_delta", "thinking": thinking}),
+            ));
+        }
+        if let Some(text) = delta.get("content").and_then(Value::as_str)
+            && !text.is_empty()
+        {
+            let index = self.open(
+                BlockKind::Text,
+                json!({"type": "text", "text": ""}),
+                &mut events,
+            );
+            events.push(Self::delta(
+                index,
+                json!({"type": "text_delta", "text": text}),
+            ));
+        }
+        for call in delta
+            .get("tool_calls")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+        {
+            let tool = call.get("index").and_then(Value::as_u64).unwrap_or(0);
+            let index = match self.tool_blocks.get(&tool) {
+                Some(&index) => index,
+                None => {
+                    let index = self.open(
+                        BlockKind::ToolUse(tool),
+                        json!({
+                            "type": "tool_use",
+                            "id": call.get("id").cloned().unwrap_or(Value::Null),
+                            "name": call.pointer("/function/name").cloned().unwrap_or(Value::Null),
+                            "input": {},
+                        }),
+                        &mut events,
+                    );
+                    self.tool_blocks.insert(tool, index);
+                    index
+                }
+            };
+            if let Some(arguments) = call.pointer("/function/arguments").and_then(Value::as_str)
+                && !arguments.is_empty()
+            {
+                events.push(Self::delta(
+                    index,
+                    json!({"type": "input_json_delta", "partial_json": arguments}),
+                ));
+            }
+        }
+        if let Some(reason) = choice.get("finish_reason").and_then(Value::as_str) {
+            self.close(&mut events);
+            self.stop_reason = Some(stop_reason(reason));
+        }
+        events
+    }
+
+    /// 收到 [DONE] 时补齐 message_delta（含最终 usage）与 message_stop
+    fn finish(&mut self) -> Vec<(&'static str, Value)> {
+        let mut events = Vec::new();
+        if self.finished {
+            return events;
+        }
+        self.finished = true;
+        self.start(&Value::Null, &mut events);
+        self.close(&mut events);
+        events.push((
+            "message_delta",
+            json!({
+                "type": "message_delta",
+                "delta": {
+                    "stop_reason": self.stop_reason.unwrap_or("end_turn"),
+                    "stop_sequence": Value::Null,
+                },
+                "usage": usage_of(self.usage.as_ref()),
+            }),
+        ));
+        events.push(("message_stop", json!({"type": "message_stop"})));
+        events
+    }
+
+    fn convert_frame(&mut self, frame: &str) -> Vec<(&'static str, Value)> {
+        let mut events = Vec::new();
+        for data in frame
+            .lines()
+            .filter_map(|line| line.strip_prefix("data:"))
+            .map(str::trim)
+        {
+            if self.finished {
+                break;
+            }
+            if data == "[DONE]" {
+                events.extend(self.finish());
+            } else if let Some(message) = data.strip_prefix("error:") {
+                self.finished = true;
+                events.push((
+                    "error",
+                    json!({"type": "error", "error": {
+                        "type": "api_error",
+                        "message": message.trim(),
+                    }}),
+                ));
+            } else if let Ok(chunk) = serde_json::from_str::<Value>(data) {
+                events.extend(self.convert_chunk(&chunk));
+            }
+        }
+        events
+    }
+}
+
+/// 网关 SSE 流转为 Anthropic 事件流（`event:` 行 + `data:` 行）
+fn anthropic_stream_response(response: Response) -> Response {
+    let (parts, body) = response.into_parts();
+    let mut pending: Vec<u8> = Vec::new();
+    let mut state = AnthropicStream::default();
+    let converted = body.into_data_stream().map(move |chunk| {
+        chunk.map(|bytes| {
+            pending.extend_from_slice(&bytes);
+            let mut out = String::new();
+            while let Some(pos) = pending.windows(2).position(|w| w == b"\n\n") {
+                let frame: Vec<u8> = pending.drain(..pos + 2).collect();
+                for (event, data) in state.convert_frame(&String::from_utf8_lossy(&frame[..pos])) {
+                    out.push_str(&format!("event: {}\ndata: {}\n\n", event, data));
+                }
+            }
+            Bytes::from(out)
+        })
+    });
+    Response::from_parts(parts, Body::from_stream(converted))
+}
+
+fn anthropic_error_type(code: StatusCode) -> &'static str {
+    match code.as_u16() {
+        400 | 422 => "invalid_request_error",
+        401 => "authentication_error",
+        402 | 403 => "permission_error",
+        404 => "not_found_error",
+        413 => "request_too_large",
+        429 => "rate_limit_error",
+        503 | 529 => "overloaded_error",
+        _ => "api_error",
+    }
+}
+
+/// 非流式响应与错误体转为 Anthropic 格式；错误为 `{type: "error", error: {type, message}}`
+async fn anthropic_json_response(response: Response) -> Response {
+    let (mut parts, body) = response.into_parts();
+    let bytes = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(e) => return GatewayError::Config(e.to_string()).into_response(),
+    };
+    let Ok(v) = serde_json::from_slice::<Value>(&bytes) else {
+        return Response::from_parts(parts, Body::from(bytes));
+    };
+    let out = if parts.status.is_success() {
+        gateway_response_to_anthropic(&v)
+    } else {
+        json!({"type": "error", "error": {
+            "type": anthropic_error_type(parts.status),
+            "message": v.get("message").cloned().unwrap_or_else(|| json!(v.to_string())),
+        }})
+    };
+    parts.headers.remove(header::CONTENT_LENGTH);
+    Response::from_parts(parts, Body::from(out.to_string()))
+}
+
+/// Anthropic 原生协议入口 `/v1/messages`：
+/// 请求转为网关格式后走与 `/v1/chat/completions` 相同的链路（准入、计费、日志一致），
+/// Anthropic 供应商仍以 Messages 接口请求上游，其他供应商按 OpenAI 兼容协议转发；
+/// 响应（含 SSE 事件流与错误体）再转回 Anthropic 格式。密钥为 Client Token，可通过 `x-api-key` 或 Bearer 传递
+pub async fn messages(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(body): Json<Value>,
+) -> Response {
+    let gateway_req = match anthropic_request_to_gateway(&body) {
+        Ok(req) => req,
+        Err(e) => return anthropic_json_response(e.into_response()).await,
+    };
+    let stream = gateway_req.request.stream.unwrap_or(false);
+    let response = chat_completions(State(app_state), headers, Json(gateway_req))
+        .await
+        .unwrap_or_else(IntoResponse::into_response);
+    if stream && response.status().is_success() {
+        anthropic_stream_response(response)
+    } else {
+        anthropic_json_response(response).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_request_maps_to_chat_request() {
+        let req = anthropic_request_to_gateway(&json!({
+            "model": "claude-sonnet-4",
+            "max_tokens": 5120,
+            "system": [{"type": "text", "text": "be brief"}],
+            "top_k": 5,
+            "thinking": {"type": "enabled", "budget_tokens": 4096},
+            "metadata": {"user_id": "u-1"},
+            "messages": [
+                {"role": "user", "content": [
+                    {"type": "text", "text": "weather?"},
+                    {"type": "image", "source": {"type": "base64", "media_type": "image/jpeg", "data": "AAAA"}}
+                ]},
+                {"role": "assistant", "content": [
+                    {"type": "thinking", "thinking": "hmm", "signature": "sig"},
+                    {"type": "tool_use", "id": "toolu_1", "name": "get_weather", "input": {"city": "SF"}}
+                ]},
+                {"role": "user", "content": [
+                    {"type": "tool_result", "tool_use_id": "toolu_1", "content": [{"type": "text", "text": "20C"}]}
+                ]}
+            ],
+            "tools": [
+                {"name": "get_weather", "description": "weather", "input_schema": {"type": "object"}},
+                {"type": "web_search_20250305", "name": "web_search"}
+            ],
+            "tool_choice": {"type": "tool", "name": "get_weather", "disable_parallel_tool_use": true}
+        }))
+        .unwrap();
+        assert_eq!(req.top_k, Some(5));
+        let v = serde_json::to_value(&req.request).unwrap();
+        assert_eq!(v["max_tokens"], json!(1024));
+        assert_eq!(v["reasoning_effort"], json!("medium"));
+        assert_eq!(v["user"], json!("u-1"));
+        assert_eq!(v["tools"].as_array().unwrap().len(), 1);
+        assert_eq!(
+            v.pointer("/tool_choice/function/name"),
+            Some(&json!("get_weather"))
+        );
+        assert_eq!(v["parallel_tool_calls"], json!(false));
+        let messages = v["messages"].as_array().unwrap();
+        assert_eq!(
+            messages[0],
+            json!({"role": "system", "content": "be brief"})
+        );
+        assert_eq!(
+            messages[1].pointer("/content/1/image_url/url"),
+            Some(&json!("data:image/jpeg;base64,AAAA"))
+        );
+        assert_eq!(
+            messages[2].pointer("/tool_calls/0/id"),
+            Some(&json!("toolu_1"))
+        );
+        assert_eq!(
+            messages[2].pointer("/tool_calls/0/function/arguments"),
+            Some(&json!("{\"city\":\"SF\"}"))
+        );
+        assert_eq!(messages[3]["role"], json!("tool"));
+        assert_eq!(messages[3]["tool_call_id"], json!("toolu_1"));
+        assert_eq!(messages[3]["content"], json!("20C"));
+    }
+
+    #[test]
+    fn chat_response_and_errors_map_to_anthropic_shape() {
+        let out = gateway_response_to_anthropic(&json!({
+            "id": "chatcmpl-1",
+            "model": "m1",
+            "choices": [{"index": 0, "finish_reason": "tool_calls", "message": {
+                "role": "assistant",
+                "content": "checking",
+                "tool_calls": [{"id": "c1", "type": "function",
+                    "function": {"name": "f", "arguments": "{\"a\":1}"}}]
+            }}],
+            "usage": {"prompt_tokens": 3, "completion_tokens": 2, "total_tokens": 5}
+        }));
+        assert_eq!(out["type"], json!("message"));
+        assert_eq!(out["stop_reason"], json!("tool_use"));
+        assert_eq!(
+            out["content"],
+            json!([
+                {"type": "text", "text": "checking"},
+                {"type": "tool_use", "id": "c1", "name": "f", "input": {"a": 1}}
+            ])
+        );
+        assert_eq!(out["usage"], json!({"input_tokens": 3, "output_tokens": 2}));
+        assert_eq!(
+            anthropic_error_type(StatusCode::TOO_MANY_REQUESTS),
+            "rate_limit_error"
+        );
+    }
+
+    #[tokio::test]
+    async fn stream_emits_anthropic_event_sequence() {
+        let upstream = concat!(
+            "data: {\"id\":\"c1\",\"model\":\"m1\",\"choices\":[{\"index\":0,\"delta\":{\"role\":\"assistant\"}}]}\n\n",
+            "data: {\"id\":\"c1\",\"model\":\"m1\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"he\"}}]}\n\n",
+            "data: {\"id\":\"c1\",\"model\":\"m1\",\"choices\":[{\"index\":0,\"delta\":{\"tool_calls\":[{\"index\":0,\"id\":\"call_1\",\"function\":{\"name\":\"f\",\"arguments\":\"{\\\"a\\\"\"}}]}}]}\n\n",
+            "data: {\"id\":\"c1\",\"model\":\"m1\",\"choices\":[{\"index\":0,\"delta\":{\"tool_calls\":[{\"index\":0,\"function\":{\"arguments\":\":1}\"}}]},\"finish_reason\":\"tool_calls\"}]}\n\n",
+            "data: {\"id\":\"c1\",\"model\":\"m1\",\"choices\":[],\"usage\":{\"prompt_tokens\":7,\"completion_tokens\":4,\"total_tokens\":11}}\n\n",
+            "data: [DONE]\n\n",
+        );
+        let response = Response::new(Body::from(upstream));
+        let body =
+            axum::body::to_bytes(anthropic_stream_response(response).into_body(), usize::MAX)
+                .await
+                .unwrap();
+        let text = String::from_utf8(body.to_vec()).unwrap();
+        let events: Vec<(String, Value)> = text
+            .split("\n\n")
+            .filter(|frame| !frame.is_empty())
+            .map(|frame| {
+                let (event, data) = frame.split_once('\n').unwrap();
+                (
+                    event.strip_prefix("event: ").unwrap().to_string(),
+                    serde_json::from_str(data.strip_prefix("data: ").unwrap()).unwrap(),
+                )
+            })
+            .collect();
+        let names: Vec<&str> = events.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "message_start",
+                "content_block_start",
+                "content_block_delta",
+                "content_block_stop",
+                "content_block_start",
+                "content_block_delta",
+                "content_block_delta",
+                "content_block_stop",
+                "message_delta",
+                "message_stop",
+            ]
+        );
+        assert_eq!(
+            events[2].1["delta"],
+            json!({"type": "text_delta", "text": "he"})
+        );
+        assert_eq!(events[4].1["index"], json!(1));
+        assert_eq!(events[4].1["content_block"]["id"], json!("call_1"));
+        assert_eq!(events[6].1["delta"]["partial_json"], json!(":1}"));
+        assert_eq!(events[8].1["delta"]["stop_reason"], json!("tool_use"));
+        assert_eq!(
+            events[8].1["usage"],
+            json!({"input_tokens": 7, "output_tokens": 4})
+        );
+    }
+}
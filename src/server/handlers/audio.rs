@@ -0,0 +1,117 @@
+use axum::{
+    Json,
+    body::Bytes,
+    extract::State,
+    http::{HeaderMap, header},
+    response::{IntoResponse, Response},
+};
+use chrono::Utc;
+use serde_json::Value;
+use std::sync::Arc;
+
+use crate::error::GatewayError;
+use crate::logging::types::{REQ_TYPE_AUDIO_SPEECH, REQ_TYPE_AUDIO_TRANSCRIPTION};
+use crate::server::AppState;
+use crate::server::audio_speech::{self, AUDIO_SPEECH_PATH, SpeechRequest};
+use crate::server::audio_transcription::{self, AUDIO_TRANSCRIPTIONS_PATH, TranscriptionRequest};
+use crate::server::request_logging::log_simple_request;
+use crate::server::util::bearer_token;
+
+/// OpenAI 兼容的语音转写入口（Whisper）：multipart 表单原样转发，按音频时长计价
+pub async fn transcriptions(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Response, GatewayError> {
+    let start_time = Utc::now();
+    let client_token = bearer_token(&headers);
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+    let parsed = TranscriptionRequest::parse(content_type, &body);
+    let requested_model = parsed.as_ref().ok().map(|r| r.model.clone());
+    let admitted = async {
+        let token = client_token
+            .as_deref()
+            .ok_or_else(|| GatewayError::Config("missing bearer token".into()))?;
+        audio_transcription::admit(&app_state, token, parsed?).await
+    }
+    .await;
+    let admitted = match admitted {
+        Ok(admitted) => admitted,
+        Err(e) => {
+            let client_token_id = client_token
+                .as_deref()
+                .map(crate::admin::client_token_id_for_token);
+            log_simple_request(
+                &app_state,
+                start_time,
+                "POST",
+                AUDIO_TRANSCRIPTIONS_PATH,
+                REQ_TYPE_AUDIO_TRANSCRIPTION,
+                requested_model,
+                None,
+                client_token_id.as_deref(),
+                e.status_code().as_u16(),
+                Some(e.to_string()),
+            )
+            .await;
+            return Err(e);
+        }
+    };
+    let token = client_token.unwrap_or_default();
+    let transcription =
+        audio_transcription::execute(&app_state, start_time, &token, admitted).await?;
+    Ok((
+        [(header::CONTENT_TYPE, transcription.content_type)],
+        transcription.body,
+    )
+        .into_response())
+}
+
+/// OpenAI 兼容的语音合成入口（TTS）：音频以流的形式原样返回，按输入字符数计价
+pub async fn speech(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(body): Json<Value>,
+) -> Result<Response, GatewayError> {
+    let start_time = Utc::now();
+    let requested_model = body
+        .get("model")
+        .and_then(Value::as_str)
+        .map(str::to_string);
+    let client_token = bearer_token(&headers);
+    let admitted = async {
+        let token = client_token
+            .as_deref()
+            .ok_or_else(|| GatewayError::Config("missing bearer token".into()))?;
+        let request = SpeechRequest::parse(body)?;
+        audio_speech::admit(&app_state, token, request).await
+    }
+    .await;
+    let admitted = match admitted {
+        Ok(admitted) => admitted,
+        Err(e) => {
+            let client_token_id = client_token
+                .as_deref()
+                .map(crate::admin::client_token_id_for_token);
+            log_simple_request(
+                &app_state,
+                start_time,
+                "POST",
+                AUDIO_SPEECH_PATH,
+                REQ_TYPE_AUDIO_SPEECH,
+                requested_model,
+                None,
+                client_token_id.as_deref(),
+                e.status_code().as_u16(),
+                Some(e.to_string()),
+            )
+            .await;
+            return Err(e);
+        }
+    };
+    let token = client_token.unwrap_or_default();
+    audio_speech::execute(&app_state, start_time, &token, admitted).await
+}
@@ -33,6 +33,9 @@ pub struct AccessTokenClaims {
     pub exp: i64,
     #[serde(default)]
     pub iat: Option<i64>,
+    /// 组织委派管理员的组织 ID（仅非超级管理员且被委派时存在）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub org_id: Option<String>,
 }
 
 fn bearer_token(headers: &HeaderMap) -> Option<String> {
@@ -206,11 +209,60 @@ pub async fn require_superadmin(
     Err(GatewayError::Unauthorized("管理员身份认证失败".into()))
 }
 
+impl AdminIdentity {
+    /// 组织委派管理员的作用域；超级管理员/TUI/Web 会话返回 None（不限组织）
+    pub fn organization_scope(&self) -> Option<&str> {
+        match self {
+            AdminIdentity::Jwt(claims) => claims.org_id.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// 审计日志中的操作者标识，如 `jwt:admin@example.com`、`tui_session:<fingerprint>`
+    pub fn actor(&self) -> String {
+        match self {
+            AdminIdentity::Jwt(claims) => format!("jwt:{}", claims.email),
+            AdminIdentity::TuiSession(session) => format!("tui_session:{}", session.fingerprint),
+            // 会话 ID 即 Cookie 凭据，不写入日志
+            AdminIdentity::WebSession(session) => match (&session.user_id, &session.fingerprint) {
+                (Some(user_id), _) => format!("web_session:user:{}", user_id),
+                (None, Some(fingerprint)) => format!("web_session:{}", fingerprint),
+                (None, None) => "web_session".to_string(),
+            },
+        }
+    }
+}
+
+/// 超级管理员，或被委派管理某个组织的用户（JWT 携带 org_id，且委派关系仍然有效）。
+/// 调用方需通过 `organization_scope()` 将操作限制在该组织内。
 pub async fn ensure_admin(
     headers: &HeaderMap,
     app_state: &AppState,
 ) -> Result<AdminIdentity, GatewayError> {
-    require_superadmin(headers, app_state).await
+    let err = match require_superadmin(headers, app_state).await {
+        Ok(identity) => return Ok(identity),
+        Err(e) => e,
+    };
+    let Some(token) = bearer_token(headers).filter(|t| t.split('.').count() == 3) else {
+        return Err(err);
+    };
+    let Some(secret) = jwt_secret_optional() else {
+        return Err(err);
+    };
+    let mut claims = validate_access_token_with_secret(&token, &secret)?;
+    let Some(claimed_org) = claims.org_id.as_deref() else {
+        return Err(err);
+    };
+    let delegated = app_state
+        .organizations
+        .get_admin_organization(&claims.sub)
+        .await
+        .map_err(GatewayError::Db)?;
+    if delegated.as_deref() != Some(claimed_org) {
+        return Err(GatewayError::Forbidden("permission denied".into()));
+    }
+    claims.org_id = delegated;
+    Ok(AdminIdentity::Jwt(claims))
 }
 
 // 校验 Client Token（外部调用 `/v1/*` 的 API Token）：
@@ -90,6 +90,22 @@ fn permissions_from_env_or_default(role: Option<UserRole>) -> Vec<String> {
     default_permissions_for_role(role)
 }
 
+/// 非超级管理员若被委派管理某个组织，则在 AccessToken 中携带 org_id
+async fn delegated_org_for(
+    app_state: &AppState,
+    user_id: &str,
+    role: UserRole,
+) -> AppResult<Option<String>> {
+    if matches!(role, UserRole::Superadmin) {
+        return Ok(None);
+    }
+    app_state
+        .organizations
+        .get_admin_organization(user_id)
+        .await
+        .map_err(GatewayError::Db)
+}
+
 fn db_user_to_auth_user(claims: &AccessTokenClaims, user: crate::users::User) -> AuthUser {
     let name = {
         let first = user.first_name.trim();
@@ -135,25 +151,33 @@ pub async fn login(
         return Err(GatewayError::Unauthorized("invalid credentials".into()));
     }
 
+    let db_user = app_state
+        .user_store
+        .get_user(&user.id)
+        .await?
+        .ok_or_else(|| GatewayError::Unauthorized("invalid credentials".into()))?;
+    issue_login(&app_state, db_user).await.map(Json)
+}
+
+/// 为已通过校验的用户签发 AccessToken 与 RefreshToken
+pub(super) async fn issue_login(
+    app_state: &AppState,
+    db_user: crate::users::User,
+) -> AppResult<LoginResponse> {
     let now = Utc::now();
     let exp = now + Duration::seconds(jwt_ttl_secs() as i64);
-    let role = user.role.as_str().to_string();
+    let org_id = delegated_org_for(app_state, &db_user.id, db_user.role).await?;
     let claims = AccessTokenClaims {
-        sub: user.id,
-        email: user.email,
-        permissions: permissions_from_env_or_default(Some(user.role)),
-        role,
+        sub: db_user.id.clone(),
+        email: db_user.email.clone(),
+        permissions: permissions_from_env_or_default(Some(db_user.role)),
+        role: db_user.role.as_str().to_string(),
         jti: Some(Uuid::new_v4().to_string()),
         exp: exp.timestamp(),
         iat: Some(now.timestamp()),
+        org_id,
     };
-
     let token = issue_access_token(&claims)?;
-    let db_user = app_state
-        .user_store
-        .get_user(&claims.sub)
-        .await?
-        .ok_or_else(|| GatewayError::Unauthorized("invalid credentials".into()))?;
 
     let refresh_token = issue_refresh_token();
     let refresh_hash = hash_refresh_token(&refresh_token);
@@ -172,13 +196,13 @@ pub async fn login(
         })
         .await?;
 
-    Ok(Json(LoginResponse {
+    Ok(LoginResponse {
         access_token: token,
         refresh_token,
         expires_at: exp.to_rfc3339(),
         refresh_expires_at: refresh_exp.to_rfc3339(),
         user: db_user_to_auth_user(&claims, db_user),
-    }))
+    })
 }
 
 pub async fn me(
@@ -431,6 +455,24 @@ pub struct RefreshResponse {
     pub refresh_expires_at: String,
 }
 
+async fn refresh_reuse_detected(
+    app_state: &AppState,
+    user_id: &str,
+    now: chrono::DateTime<Utc>,
+) -> GatewayError {
+    let revoked = app_state
+        .refresh_token_store
+        .revoke_all_refresh_tokens_for_user(user_id, now)
+        .await
+        .unwrap_or(0);
+    tracing::warn!(
+        user_id = %user_id,
+        revoked,
+        "refresh token reuse detected; all refresh tokens revoked"
+    );
+    GatewayError::Unauthorized("refresh token reuse detected".into())
+}
+
 pub async fn refresh(
     State(app_state): State<Arc<AppState>>,
     Json(payload): Json<RefreshRequest>,
@@ -450,6 +492,10 @@ pub async fn refresh(
         return Err(GatewayError::Unauthorized("invalid refresh token".into()));
     };
     if stored.revoked_at.is_some() {
+        // 已轮换过的令牌再次出现：说明令牌可能泄露，撤销该用户的整条刷新令牌链
+        if stored.replaced_by_id.is_some() {
+            return Err(refresh_reuse_detected(&app_state, &stored.user_id, now).await);
+        }
         return Err(GatewayError::Unauthorized("invalid refresh token".into()));
     }
     if stored.expires_at <= now {
@@ -461,7 +507,8 @@ pub async fn refresh(
         .revoke_refresh_token(&token_hash, now)
         .await?;
     if !revoked {
-        return Err(GatewayError::Unauthorized("invalid refresh token".into()));
+        // 并发请求抢先完成了轮换，同样按重用处理
+        return Err(refresh_reuse_detected(&app_state, &stored.user_id, now).await);
     }
 
     let Some(user) = app_state.user_store.get_user(&stored.user_id).await? else {
@@ -470,6 +517,7 @@ pub async fn refresh(
 
     let exp = now + Duration::seconds(jwt_ttl_secs() as i64);
     let role = user.role.as_str().to_string();
+    let org_id = delegated_org_for(&app_state, &user.id, user.role).await?;
     let claims = AccessTokenClaims {
         sub: user.id,
         email: user.email,
@@ -478,6 +526,7 @@ pub async fn refresh(
         jti: Some(Uuid::new_v4().to_string()),
         exp: exp.timestamp(),
         iat: Some(now.timestamp()),
+        org_id,
     };
     let access_token = issue_access_token(&claims)?;
 
@@ -510,3 +559,110 @@ pub async fn refresh(
         refresh_expires_at: refresh_exp.to_rfc3339(),
     }))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::BalanceStrategy;
+    use crate::config::settings::{LoadBalancing, LoggingConfig, ServerConfig};
+    use crate::logging::DatabaseLogger;
+    use tempfile::tempdir;
+
+    async fn test_state(dir: &tempfile::TempDir) -> Arc<AppState> {
+        let db_path = dir.path().join("test.db");
+        let settings = crate::config::Settings {
+            load_balancing: LoadBalancing {
+                strategy: BalanceStrategy::FirstAvailable,
+            },
+            server: ServerConfig::default(),
+            logging: LoggingConfig {
+                database_path: db_path.to_str().unwrap().to_string(),
+                ..Default::default()
+            },
+        };
+        let logger = Arc::new(
+            DatabaseLogger::new(&settings.logging.database_path)
+                .await
+                .unwrap(),
+        );
+        crate::server::test_harness::app_state(settings, logger.clone())
+    }
+
+    #[tokio::test]
+    async fn reused_refresh_token_revokes_whole_family() {
+        unsafe {
+            std::env::set_var("GW_JWT_SECRET", "testsecret");
+        }
+        let dir = tempdir().unwrap();
+        let state = test_state(&dir).await;
+        let user = state
+            .user_store
+            .create_user(CreateUserPayload {
+                first_name: None,
+                last_name: None,
+                username: Some("refresh-user".into()),
+                email: "refresh@example.com".into(),
+                phone_number: None,
+                password: None,
+                status: UserStatus::Active,
+                role: UserRole::Manager,
+                is_anonymous: false,
+            })
+            .await
+            .unwrap();
+        let now = Utc::now();
+        let original = issue_refresh_token();
+        state
+            .refresh_token_store
+            .create_refresh_token(RefreshTokenRecord {
+                id: Uuid::new_v4().to_string(),
+                user_id: user.id.clone(),
+                token_hash: hash_refresh_token(&original),
+                created_at: now,
+                expires_at: now + Duration::hours(1),
+                revoked_at: None,
+                replaced_by_id: None,
+                last_used_at: None,
+            })
+            .await
+            .unwrap();
+
+        let Json(rotated) = refresh(
+            State(state.clone()),
+            Json(RefreshRequest {
+                refresh_token: original.clone(),
+            }),
+        )
+        .await
+        .unwrap();
+        assert_ne!(rotated.refresh_token, original);
+
+        // 旧令牌被重放：拒绝并连带撤销轮换出的新令牌
+        let err = refresh(
+            State(state.clone()),
+            Json(RefreshRequest {
+                refresh_token: original,
+            }),
+        )
+        .await
+        .unwrap_err();
+        assert!(err.to_string().contains("reuse"));
+        let successor = state
+            .refresh_token_store
+            .get_refresh_token_by_hash(&hash_refresh_token(&rotated.refresh_token))
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(successor.revoked_at.is_some());
+        assert!(
+            refresh(
+                State(state),
+                Json(RefreshRequest {
+                    refresh_token: rotated.refresh_token,
+                }),
+            )
+            .await
+            .is_err()
+        );
+    }
+}
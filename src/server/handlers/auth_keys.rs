@@ -14,7 +14,7 @@ use crate::server::login::LoginManager;
 use crate::server::storage_traits::AdminPublicKeyRecord;
 use base64::Engine;
 use base64::engine::general_purpose::STANDARD as B64_STANDARD;
-use chrono::Utc;
+use chrono::{DateTime, Duration, Utc};
 
 #[derive(Debug, Serialize)]
 pub struct AdminKeyOut {
@@ -23,6 +23,22 @@ pub struct AdminKeyOut {
     pub enabled: bool,
     pub created_at: String,
     pub last_used_at: Option<String>,
+    pub expires_at: Option<String>,
+    pub expired: bool,
+}
+
+impl AdminKeyOut {
+    fn from_record(k: AdminPublicKeyRecord, now: DateTime<Utc>) -> Self {
+        Self {
+            expired: k.is_expired(now),
+            fingerprint: k.fingerprint,
+            comment: k.comment,
+            enabled: k.enabled,
+            created_at: k.created_at.to_rfc3339(),
+            last_used_at: k.last_used_at.map(|v| v.to_rfc3339()),
+            expires_at: k.expires_at.map(|v| v.to_rfc3339()),
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -32,6 +48,43 @@ pub struct AddKeyPayload {
     pub comment: Option<String>,
     #[serde(default)]
     pub enabled: Option<bool>,
+    /// 过期时间；未指定时按 admin_key_max_age_days 计算
+    #[serde(default)]
+    pub expires_at: Option<String>,
+}
+
+/// 延期：指定新的过期时间，或在当前过期时间（已过期则从现在）基础上顺延天数；
+/// 两者均未指定表示改为永不过期
+#[derive(Debug, Deserialize)]
+pub struct ExtendKeyPayload {
+    #[serde(default)]
+    pub expires_at: Option<String>,
+    #[serde(default)]
+    pub extend_days: Option<u32>,
+}
+
+fn parse_expires_at(raw: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>, GatewayError> {
+    let expires_at = crate::logging::time::parse_datetime_string(raw.trim())?;
+    if expires_at <= now {
+        return Err(GatewayError::Config("expires_at 必须晚于当前时间".into()));
+    }
+    Ok(expires_at)
+}
+
+async fn find_key(
+    app: &AppState,
+    fingerprint: &str,
+) -> Result<(AdminPublicKeyRecord, usize), GatewayError> {
+    let now = Utc::now();
+    let keys = app.login_manager.list_admin_keys().await?;
+    let active = keys
+        .iter()
+        .filter(|k| k.enabled && !k.is_expired(now))
+        .count();
+    keys.into_iter()
+        .find(|k| k.fingerprint == fingerprint)
+        .map(|k| (k, active))
+        .ok_or_else(|| GatewayError::NotFound("fingerprint not found".into()))
 }
 
 pub async fn list_keys(
@@ -40,15 +93,10 @@ pub async fn list_keys(
 ) -> Result<Json<Vec<AdminKeyOut>>, GatewayError> {
     require_superadmin(&headers, &app).await?;
     let keys = app.login_manager.list_admin_keys().await?;
+    let now = Utc::now();
     let out = keys
         .into_iter()
-        .map(|k| AdminKeyOut {
-            fingerprint: k.fingerprint,
-            comment: k.comment,
-            enabled: k.enabled,
-            created_at: k.created_at.to_rfc3339(),
-            last_used_at: k.last_used_at.map(|v| v.to_rfc3339()),
-        })
+        .map(|k| AdminKeyOut::from_record(k, now))
         .collect();
     Ok(Json(out))
 }
@@ -72,22 +120,77 @@ pub async fn add_key(
     )
     .map_err(|_| GatewayError::Config("公钥解析失败".into()))?;
     let fp = LoginManager::fingerprint_for_public_key(&vk.to_bytes());
+    let now = Utc::now();
+    let expires_at = match payload.expires_at.as_deref() {
+        Some(raw) => Some(parse_expires_at(raw, now)?),
+        None => app
+            .config
+            .server
+            .admin_key_max_age_days
+            .map(|days| now + Duration::days(days as i64)),
+    };
     let rec = AdminPublicKeyRecord {
-        fingerprint: fp.clone(),
+        fingerprint: fp,
         public_key: raw,
         comment: payload.comment.clone(),
         enabled: payload.enabled.unwrap_or(true),
-        created_at: Utc::now(),
+        created_at: now,
         last_used_at: None,
+        expires_at,
     };
     app.login_manager.add_admin_key(&rec).await?;
-    Ok(Json(AdminKeyOut {
-        fingerprint: fp,
-        comment: rec.comment,
-        enabled: rec.enabled,
-        created_at: rec.created_at.to_rfc3339(),
-        last_used_at: None,
-    }))
+    Ok(Json(AdminKeyOut::from_record(rec, now)))
+}
+
+pub async fn extend_key(
+    State(app): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+    Path(fingerprint): Path<String>,
+    Json(payload): Json<ExtendKeyPayload>,
+) -> Result<Json<AdminKeyOut>, GatewayError> {
+    require_superadmin(&headers, &app).await?;
+    let now = Utc::now();
+    let (mut key, _) = find_key(&app, &fingerprint).await?;
+    key.expires_at = match (payload.expires_at.as_deref(), payload.extend_days) {
+        (Some(_), Some(_)) => {
+            return Err(GatewayError::Config(
+                "expires_at 与 extend_days 只能指定一个".into(),
+            ));
+        }
+        (Some(raw), None) => Some(parse_expires_at(raw, now)?),
+        (None, Some(0)) => return Err(GatewayError::Config("extend_days 必须大于 0".into())),
+        (None, Some(days)) => {
+            let base = key.expires_at.filter(|exp| *exp > now).unwrap_or(now);
+            Some(base + Duration::days(days as i64))
+        }
+        (None, None) => None,
+    };
+    app.login_manager
+        .set_admin_key_status(&fingerprint, key.enabled, key.expires_at)
+        .await?;
+    Ok(Json(AdminKeyOut::from_record(key, now)))
+}
+
+/// 退役：立即停用并标记过期，吊销该公钥的 TUI 会话（保留记录便于审计）
+pub async fn retire_key(
+    State(app): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+    Path(fingerprint): Path<String>,
+) -> Result<Json<AdminKeyOut>, GatewayError> {
+    require_superadmin(&headers, &app).await?;
+    let now = Utc::now();
+    let (mut key, active) = find_key(&app, &fingerprint).await?;
+    if key.enabled && !key.is_expired(now) && active <= 1 {
+        return Err(GatewayError::Config(
+            "不能退役最后一把可用的管理员密钥".into(),
+        ));
+    }
+    key.enabled = false;
+    key.expires_at = Some(key.expires_at.map_or(now, |exp| exp.min(now)));
+    app.login_manager
+        .set_admin_key_status(&fingerprint, false, key.expires_at)
+        .await?;
+    Ok(Json(AdminKeyOut::from_record(key, now)))
 }
 
 pub async fn delete_key(
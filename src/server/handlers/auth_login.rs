@@ -3,7 +3,7 @@ use std::sync::Arc;
 use axum::{
     Json,
     body::Bytes,
-    extract::State,
+    extract::{Path, State},
     http::{HeaderMap, HeaderValue, StatusCode},
     response::IntoResponse,
 };
@@ -11,9 +11,16 @@ use serde::{Deserialize, Serialize};
 
 use super::auth::{AdminIdentity, SESSION_COOKIE, require_superadmin};
 use crate::{
+    config::settings::SameSitePolicy,
     error::{GatewayError, Result as AppResult},
     refresh_tokens::hash_refresh_token,
-    server::{AppState, login::LoginCodeEntry},
+    server::{
+        AppState,
+        login::{
+            LoginCodeEntry, LoginManager, PasswordAccount, PasswordLoginOutcome, SecondFactor,
+        },
+    },
+    users::{UserAuthRecord, UserRole, UserStatus},
 };
 
 #[derive(Debug, Deserialize)]
@@ -96,20 +103,33 @@ fn parse_cookie(headers: &HeaderMap, name: &str) -> Option<String> {
     None
 }
 
-fn set_session_cookie(session_id: &str, secure: bool) -> HeaderValue {
-    // For dev, we use SameSite=Lax to allow on same-origin (e.g., via Vite proxy)
-    let mut v = format!(
-        "{}={}; Path=/; HttpOnly; SameSite=Lax",
-        SESSION_COOKIE, session_id
-    );
+/// 会话 Cookie 的公共属性：SameSite/Secure 取自配置；SameSite=None 时浏览器要求必须 Secure
+fn cookie_attributes(app: &AppState, headers: &HeaderMap) -> String {
+    let same_site = app.config.server.session_cookie_same_site;
+    let secure = same_site == SameSitePolicy::None
+        || app
+            .config
+            .server
+            .session_cookie_secure
+            .unwrap_or_else(|| is_secure(headers));
+    let mut v = format!("Path=/; HttpOnly; SameSite={}", same_site.as_str());
     if secure {
         v.push_str("; Secure");
     }
+    v
+}
+
+fn set_session_cookie(session_id: &str, attributes: &str, max_age_secs: i64) -> HeaderValue {
+    let v = format!(
+        "{}={}; Max-Age={}; {}",
+        SESSION_COOKIE, session_id, max_age_secs, attributes
+    );
     HeaderValue::from_str(&v).unwrap_or(HeaderValue::from_static(""))
 }
 
-fn clear_session_cookie() -> HeaderValue {
-    HeaderValue::from_static("gw_session=deleted; Path=/; Max-Age=0; HttpOnly; SameSite=Lax")
+fn clear_session_cookie(attributes: &str) -> HeaderValue {
+    let v = format!("{}=deleted; Max-Age=0; {}", SESSION_COOKIE, attributes);
+    HeaderValue::from_str(&v).unwrap_or(HeaderValue::from_static(""))
 }
 
 fn is_secure(headers: &HeaderMap) -> bool {
@@ -220,12 +240,16 @@ pub async fn redeem_code(
         .status(StatusCode::NO_CONTENT)
         .body(axum::body::Body::empty())
         .unwrap();
-    let secure = is_secure(&headers);
+    let attributes = cookie_attributes(&app, &headers);
     resp.headers_mut().insert(
         axum::http::header::SET_COOKIE,
-        set_session_cookie(&sess.id, secure),
+        set_session_cookie(
+            &sess.id,
+            &attributes,
+            app.login_manager.web_session_ttl().num_seconds(),
+        ),
     );
-    tracing::info!(secure_cookie = secure, session_id_preview = %sess.id.get(0..6).unwrap_or(""), "redeem success, session issued");
+    tracing::info!(cookie = %attributes, session_id_preview = %sess.id.get(0..6).unwrap_or(""), "redeem success, session issued");
     Ok(resp)
 }
 
@@ -293,7 +317,260 @@ pub async fn logout(
         .status(StatusCode::NO_CONTENT)
         .body(axum::body::Body::empty())
         .unwrap();
-    resp.headers_mut()
-        .insert(axum::http::header::SET_COOKIE, clear_session_cookie());
+    resp.headers_mut().insert(
+        axum::http::header::SET_COOKIE,
+        clear_session_cookie(&cookie_attributes(&app, &headers)),
+    );
+    Ok(resp)
+}
+
+#[derive(Debug, Serialize)]
+pub struct WebSessionOut {
+    /// 会话句柄（会话 ID 的哈希前缀），用于撤销
+    pub id: String,
+    pub fingerprint: Option<String>,
+    /// 账号密码登录的用户 ID
+    pub user_id: Option<String>,
+    pub created_at: String,
+    pub expires_at: String,
+    pub last_seen_at: Option<String>,
+    /// 是否为当前请求所使用的会话
+    pub current: bool,
+}
+
+pub async fn list_web_sessions(
+    State(app): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> AppResult<Json<Vec<WebSessionOut>>> {
+    require_superadmin(&headers, &app).await?;
+    let current = parse_cookie(&headers, SESSION_COOKIE);
+    let list = app
+        .login_manager
+        .list_web_sessions()
+        .await?
+        .into_iter()
+        .map(|r| WebSessionOut {
+            id: LoginManager::web_session_handle(&r.session_id),
+            current: current.as_deref() == Some(r.session_id.as_str()),
+            fingerprint: r.fingerprint,
+            user_id: r.user_id,
+            created_at: r.created_at.to_rfc3339(),
+            expires_at: r.expires_at.to_rfc3339(),
+            last_seen_at: r.last_seen_at.map(|t| t.to_rfc3339()),
+        })
+        .collect();
+    Ok(Json(list))
+}
+
+/// 撤销指定 Web 会话；撤销的是当前会话时同时清除 Cookie
+pub async fn revoke_web_session(
+    State(app): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> AppResult<impl IntoResponse> {
+    require_superadmin(&headers, &app).await?;
+    if !app.login_manager.revoke_session_by_handle(&id).await? {
+        return Err(GatewayError::NotFound("session not found".into()));
+    }
+    tracing::info!(session_handle = %id, "web session revoked");
+    let mut resp = axum::response::Response::builder()
+        .status(StatusCode::NO_CONTENT)
+        .body(axum::body::Body::empty())
+        .unwrap();
+    if parse_cookie(&headers, SESSION_COOKIE)
+        .is_some_and(|sid| LoginManager::web_session_handle(&sid) == id)
+    {
+        resp.headers_mut().insert(
+            axum::http::header::SET_COOKIE,
+            clear_session_cookie(&cookie_attributes(&app, &headers)),
+        );
+    }
     Ok(resp)
 }
+
+#[derive(Debug, Deserialize)]
+pub struct PasswordLoginPayload {
+    /// 用户名或邮箱
+    pub login: String,
+    pub password: String,
+    #[serde(default)]
+    pub totp_code: Option<String>,
+    #[serde(default)]
+    pub recovery_code: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TotpActivatePayload {
+    pub login: String,
+    pub password: String,
+    pub code: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PasswordLoginResponse {
+    /// ok / enrollment_required
+    pub status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub totp_secret: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub otpauth_url: Option<String>,
+    /// 仅在完成 TOTP 绑定时返回一次
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recovery_codes: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recovery_codes_remaining: Option<usize>,
+}
+
+fn ensure_password_login_enabled(app: &AppState) -> AppResult<()> {
+    if !app.config.server.admin_password_login {
+        return Err(GatewayError::Forbidden("password login is disabled".into()));
+    }
+    Ok(())
+}
+
+/// 只有启用状态的超级管理员可以通过账号密码获得 Web 会话；其他情况统一按凭据错误处理
+async fn password_admin(app: &AppState, login: &str) -> AppResult<UserAuthRecord> {
+    let invalid = || GatewayError::Unauthorized("invalid credentials".into());
+    let login = login.trim();
+    let email = if login.contains('@') {
+        login.to_string()
+    } else {
+        app.user_store
+            .get_user_by_username(login)
+            .await?
+            .ok_or_else(invalid)?
+            .email
+    };
+    let auth = app
+        .user_store
+        .get_auth_by_email(&email)
+        .await?
+        .ok_or_else(invalid)?;
+    let user = app
+        .user_store
+        .get_user(&auth.id)
+        .await?
+        .ok_or_else(invalid)?;
+    if !matches!(auth.role, UserRole::Superadmin) || !matches!(user.status, UserStatus::Active) {
+        return Err(invalid());
+    }
+    Ok(auth)
+}
+
+fn session_response(
+    app: &AppState,
+    headers: &HeaderMap,
+    session_id: &str,
+    body: PasswordLoginResponse,
+) -> axum::response::Response {
+    let mut resp = Json(body).into_response();
+    resp.headers_mut().insert(
+        axum::http::header::SET_COOKIE,
+        set_session_cookie(
+            session_id,
+            &cookie_attributes(app, headers),
+            app.login_manager.web_session_ttl().num_seconds(),
+        ),
+    );
+    resp
+}
+
+pub async fn password_login(
+    State(app): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(payload): Json<PasswordLoginPayload>,
+) -> AppResult<axum::response::Response> {
+    ensure_password_login_enabled(&app)?;
+    let auth = password_admin(&app, &payload.login).await?;
+    let factor = match (
+        payload.totp_code.as_deref(),
+        payload.recovery_code.as_deref(),
+    ) {
+        (Some(code), _) if !code.trim().is_empty() => SecondFactor::Totp(code),
+        (_, Some(code)) if !code.trim().is_empty() => SecondFactor::RecoveryCode(code),
+        _ => SecondFactor::None,
+    };
+    let account = PasswordAccount {
+        user_id: &auth.id,
+        account: &auth.email,
+        password_hash: auth.password_hash.as_deref(),
+    };
+    match app
+        .login_manager
+        .password_login(account, &payload.password, factor)
+        .await?
+    {
+        PasswordLoginOutcome::EnrollmentRequired {
+            secret,
+            otpauth_url,
+        } => Ok(Json(PasswordLoginResponse {
+            status: "enrollment_required",
+            totp_secret: Some(secret),
+            otpauth_url: Some(otpauth_url),
+            recovery_codes: None,
+            recovery_codes_remaining: None,
+        })
+        .into_response()),
+        PasswordLoginOutcome::Authenticated {
+            session,
+            recovery_codes_remaining,
+        } => {
+            tracing::info!(user_id = %auth.id, "password login success, session issued");
+            Ok(session_response(
+                &app,
+                &headers,
+                &session.id,
+                PasswordLoginResponse {
+                    status: "ok",
+                    totp_secret: None,
+                    otpauth_url: None,
+                    recovery_codes: None,
+                    recovery_codes_remaining: Some(recovery_codes_remaining),
+                },
+            ))
+        }
+    }
+}
+
+pub async fn activate_totp(
+    State(app): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(payload): Json<TotpActivatePayload>,
+) -> AppResult<axum::response::Response> {
+    ensure_password_login_enabled(&app)?;
+    let auth = password_admin(&app, &payload.login).await?;
+    let account = PasswordAccount {
+        user_id: &auth.id,
+        account: &auth.email,
+        password_hash: auth.password_hash.as_deref(),
+    };
+    let (session, codes) = app
+        .login_manager
+        .activate_totp(account, &payload.password, &payload.code)
+        .await?;
+    tracing::info!(user_id = %auth.id, "totp enrolled, session issued");
+    Ok(session_response(
+        &app,
+        &headers,
+        &session.id,
+        PasswordLoginResponse {
+            status: "ok",
+            totp_secret: None,
+            otpauth_url: None,
+            recovery_codes_remaining: Some(codes.len()),
+            recovery_codes: Some(codes),
+        },
+    ))
+}
+
+/// 超级管理员为丢失认证器的用户重置 TOTP；下次登录需重新绑定
+pub async fn reset_password_mfa(
+    State(app): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(user_id): Path<String>,
+) -> AppResult<Json<serde_json::Value>> {
+    require_superadmin(&headers, &app).await?;
+    let reset = app.login_manager.reset_admin_mfa(&user_id).await?;
+    tracing::info!(user_id = %user_id, reset, "password login mfa reset");
+    Ok(Json(serde_json::json!({ "reset": reset })))
+}
@@ -31,6 +31,10 @@ pub struct VerifyResp {
     pub token: String,
     pub expires_at: String,
     pub fingerprint: String,
+    pub key_expires_at: Option<String>,
+    /// 公钥即将到期时的轮换提醒，供 TUI 弹出提示
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_expiry_warning: Option<String>,
 }
 
 pub async fn challenge(
@@ -72,9 +76,16 @@ pub async fn verify(
             payload.signature.trim(),
         )
         .await?;
+    let key_expiry_warning = crate::server::scheduler::admin_key_expiry_warning(
+        session.key_expires_at,
+        chrono::Utc::now(),
+        app.config.server.admin_key_expiry_notice_days,
+    );
     Ok(Json(VerifyResp {
         token: session.token,
         expires_at: session.expires_at.to_rfc3339(),
         fingerprint: session.fingerprint,
+        key_expires_at: session.key_expires_at.map(|v| v.to_rfc3339()),
+        key_expiry_warning,
     }))
 }
@@ -8,7 +8,10 @@ use std::sync::Arc;
 
 use super::auth::require_superadmin;
 use crate::error::GatewayError;
-use crate::logging::types::{REQ_TYPE_PROVIDER_CACHE_DELETE, REQ_TYPE_PROVIDER_CACHE_UPDATE};
+use crate::logging::types::{
+    REQ_TYPE_PROVIDER_CACHE_DELETE, REQ_TYPE_PROVIDER_CACHE_RECONCILE,
+    REQ_TYPE_PROVIDER_CACHE_UPDATE,
+};
 use crate::providers::openai::{Model, ModelListResponse};
 use crate::server::AppState;
 use crate::server::model_cache::{cache_models_for_provider, get_cached_models_for_provider};
@@ -29,6 +32,12 @@ pub struct CacheUpdatePayload {
     pub replace: Option<bool>, // selected + include 时覆盖
 }
 
+#[derive(Debug, Deserialize, Default)]
+pub struct CacheReconcilePayload {
+    #[serde(default)]
+    pub grace_hours: Option<u32>, // 下线模型保留时长，默认 24 小时
+}
+
 #[derive(Debug, Deserialize, Default)]
 pub struct CacheListQuery {
     #[serde(default)]
@@ -116,6 +125,7 @@ pub async fn update_provider_cache(
                 operation: REQ_TYPE_PROVIDER_CACHE_UPDATE.to_string(),
                 provider: Some(provider_name.clone()),
                 details: Some(e.to_string()),
+                actor: None,
             })
             .await;
         let code = e.status_code().as_u16();
@@ -170,6 +180,7 @@ pub async fn update_provider_cache(
         .map_err(GatewayError::Db)?
         .first()
         .cloned()
+        .or_else(|| provider.api_type.allows_keyless().then(String::new))
     {
         Some(k) => k,
         None => {
@@ -402,6 +413,7 @@ pub async fn delete_provider_cache(
                 operation: REQ_TYPE_PROVIDER_CACHE_DELETE.to_string(),
                 provider: Some(provider_name.clone()),
                 details: Some(e.to_string()),
+                actor: None,
             })
             .await;
         let code = e.status_code().as_u16();
@@ -506,3 +518,133 @@ pub async fn delete_provider_cache(
     }
     Ok(resp)
 }
+
+const DEFAULT_RECONCILE_GRACE_HOURS: u32 = 24;
+
+#[derive(Debug, Serialize)]
+pub struct CacheReconcileResponse {
+    provider: String,
+    grace_hours: u32,
+    #[serde(flatten)]
+    diff: crate::server::model_cache::ModelCacheDiff,
+}
+
+// 对账上游模型列表与缓存：报告新增/下线模型，并清理超出宽限期的下线条目
+pub async fn reconcile_provider_cache(
+    Path(provider_name): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+    payload: Option<Json<CacheReconcilePayload>>,
+) -> Result<Json<CacheReconcileResponse>, GatewayError> {
+    let start_time = Utc::now();
+    let path = format!("/models/{}/cache/reconcile", provider_name);
+    let provided_token = bearer_token(&headers);
+    let identity = match require_superadmin(&headers, &app_state).await {
+        Ok(identity) => identity,
+        Err(e) => {
+            let code = e.status_code().as_u16();
+            log_simple_request(
+                &app_state,
+                start_time,
+                "POST",
+                &path,
+                REQ_TYPE_PROVIDER_CACHE_RECONCILE,
+                None,
+                Some(provider_name),
+                provided_token.as_deref(),
+                code,
+                Some(e.to_string()),
+            )
+            .await;
+            return Err(e);
+        }
+    };
+    let grace_hours = payload
+        .and_then(|Json(p)| p.grace_hours)
+        .unwrap_or(DEFAULT_RECONCILE_GRACE_HOURS);
+
+    let result = async {
+        let provider = app_state
+            .providers
+            .get_provider(&provider_name)
+            .await
+            .map_err(GatewayError::Db)?
+            .ok_or_else(|| {
+                GatewayError::NotFound(format!("Provider '{}' not found", provider_name))
+            })?;
+        let api_key = app_state
+            .providers
+            .get_provider_keys(&provider_name, &app_state.config.logging.key_log_strategy)
+            .await
+            .map_err(GatewayError::Db)?
+            .first()
+            .cloned()
+            .or_else(|| provider.api_type.allows_keyless().then(String::new))
+            .ok_or(crate::routing::load_balancer::BalanceError::NoApiKeysAvailable)?;
+        let upstream = fetch_provider_models(&provider, &api_key).await?;
+        crate::server::model_cache::reconcile_models_for_provider(
+            &app_state,
+            &provider_name,
+            &upstream,
+            chrono::Duration::hours(grace_hours as i64),
+        )
+        .await
+        .map_err(GatewayError::Db)
+    }
+    .await;
+
+    let diff = match result {
+        Ok(diff) => diff,
+        Err(ge) => {
+            let code = ge.status_code().as_u16();
+            log_simple_request(
+                &app_state,
+                start_time,
+                "POST",
+                &path,
+                REQ_TYPE_PROVIDER_CACHE_RECONCILE,
+                None,
+                Some(provider_name),
+                provided_token.as_deref(),
+                code,
+                Some(ge.to_string()),
+            )
+            .await;
+            return Err(ge);
+        }
+    };
+
+    let _ = app_state
+        .log_store
+        .log_provider_op(crate::logging::types::ProviderOpLog {
+            id: None,
+            timestamp: start_time,
+            operation: REQ_TYPE_PROVIDER_CACHE_RECONCILE.to_string(),
+            provider: Some(provider_name.clone()),
+            details: serde_json::to_string(&serde_json::json!({
+                "grace_hours": grace_hours,
+                "diff": &diff,
+            }))
+            .ok(),
+            actor: Some(identity.actor()),
+        })
+        .await;
+    log_simple_request(
+        &app_state,
+        start_time,
+        "POST",
+        &path,
+        REQ_TYPE_PROVIDER_CACHE_RECONCILE,
+        None,
+        Some(provider_name.clone()),
+        token_for_log(provided_token.as_deref()),
+        200,
+        None,
+    )
+    .await;
+    Ok(Json(CacheReconcileResponse {
+        provider: provider_name,
+        grace_hours,
+        diff,
+    }))
+}
@@ -5,13 +5,20 @@ use axum::{
     response::{IntoResponse, Response},
 };
 use chrono::Utc;
+use serde::Serialize;
 use std::sync::Arc;
 
 use crate::error::GatewayError;
 use crate::server::AppState;
+use crate::server::chat_pipeline::{ChatTransport, check_chat_admission};
+use crate::server::chat_plan::{DecisionTrace, estimate_cost, estimate_prompt_tokens};
 use crate::server::chat_request::GatewayChatCompletionRequest;
+use crate::server::idempotency::{IDEMPOTENT_REPLAYED_HEADER, IdempotencyOutcome};
+use crate::server::legacy_completions::{LEGACY_COMPLETIONS_PATH, LegacyFields};
+use crate::server::provider_override::ProviderOverride;
 use crate::server::request_lab::{build_request_payload_snapshot, execute_logged_chat_request};
 use crate::server::streaming::stream_chat_completions;
+use crate::server::util::bearer_token;
 
 fn error_payload_to_chat_completion(
     provider: &str,
@@ -45,17 +52,58 @@ pub async fn chat_completions(
     headers: HeaderMap,
     Json(gateway_req): Json<GatewayChatCompletionRequest>,
 ) -> Result<Response, GatewayError> {
-    let top_k = gateway_req.top_k;
-    let request = gateway_req.request;
-    if request.stream.unwrap_or(false) {
+    serve_chat_completions(app_state, headers, gateway_req, None).await
+}
+
+/// `/v1/chat/completions` 与旧版 `/v1/completions` 共用的处理流程；
+/// legacy 为旧版补全入口的独有字段，此时日志与计费记录在 `/v1/completions` 下
+pub(super) async fn serve_chat_completions(
+    app_state: Arc<AppState>,
+    headers: HeaderMap,
+    gateway_req: GatewayChatCompletionRequest,
+    legacy: Option<LegacyFields>,
+) -> Result<Response, GatewayError> {
+    let path = if legacy.is_some() {
+        LEGACY_COMPLETIONS_PATH
+    } else {
+        "/v1/chat/completions"
+    };
+    let GatewayChatCompletionRequest {
+        request,
+        top_k,
+        provider,
+        provider_key,
+    } = gateway_req;
+    if let Some(response) =
+        crate::server::sandbox::try_sandbox_response(&app_state, &headers, &request).await?
+    {
+        return Ok(response);
+    }
+    let transport = ChatTransport::for_request(&request);
+    let debug_capture = crate::server::debug_capture::capture_requested(&headers)?;
+    // 流式日志在流结束后才写入，无法在响应头中返回日志 ID
+    if debug_capture && transport.is_stream() {
+        return Err(GatewayError::Config(
+            "X-Gateway-Debug: capture is only supported with stream=false".into(),
+        ));
+    }
+    if transport.is_stream() {
         let response = stream_chat_completions(
             State(app_state),
             headers,
-            Json(GatewayChatCompletionRequest { request, top_k }),
+            Json(GatewayChatCompletionRequest {
+                request,
+                top_k,
+                provider,
+                provider_key,
+            }),
+            path,
         )
         .await?;
         Ok(response.into_response())
     } else {
+        let provider_override =
+            ProviderOverride::from_request(&headers, provider.as_deref(), provider_key.as_deref())?;
         let start_time = Utc::now();
         let requested_model = request.model.clone();
         let client_token = headers
@@ -75,8 +123,8 @@ pub async fn chat_completions(
                     &app_state,
                     start_time,
                     "POST",
-                    "/v1/chat/completions",
-                    crate::logging::types::REQ_TYPE_CHAT_ONCE,
+                    path,
+                    transport.request_type(),
                     Some(requested_model),
                     None,
                     None,
@@ -89,15 +137,48 @@ pub async fn chat_completions(
         };
 
         let snapshot = build_request_payload_snapshot(&request, top_k)?;
+        // Idempotency-Key：命中未过期的成功响应时直接回放，不访问上游、不重复计费
+        let idempotency = match crate::server::idempotency::idempotency_key(&headers)? {
+            Some(key) => {
+                let token_id = crate::admin::client_token_id_for_token(token_str);
+                match crate::server::idempotency::claim(&app_state, &token_id, &key, &snapshot)
+                    .await?
+                {
+                    IdempotencyOutcome::Replay(body) => {
+                        crate::server::request_logging::log_simple_request(
+                            &app_state,
+                            start_time,
+                            "POST",
+                            path,
+                            crate::logging::types::REQ_TYPE_CHAT_IDEMPOTENT_REPLAY,
+                            Some(requested_model),
+                            None,
+                            Some(token_id.as_str()),
+                            200,
+                            None,
+                        )
+                        .await;
+                        return Ok(
+                            ([(IDEMPOTENT_REPLAYED_HEADER, "true")], Json(body)).into_response()
+                        );
+                    }
+                    IdempotencyOutcome::Claimed(claim) => Some(claim),
+                }
+            }
+            None => None,
+        };
         let executed = match execute_logged_chat_request(
             &app_state,
             start_time,
             request,
             top_k,
             token_str,
-            "/v1/chat/completions",
-            crate::logging::types::REQ_TYPE_CHAT_ONCE,
+            path,
+            transport.request_type(),
             Some(snapshot),
+            debug_capture,
+            provider_override.as_ref(),
+            legacy.as_ref(),
         )
         .await
         {
@@ -108,8 +189,8 @@ pub async fn chat_completions(
                     &app_state,
                     start_time,
                     "POST",
-                    "/v1/chat/completions",
-                    crate::logging::types::REQ_TYPE_CHAT_ONCE,
+                    path,
+                    transport.request_type(),
                     Some(requested_model),
                     None,
                     client_token_log_id.as_deref(),
@@ -121,22 +202,169 @@ pub async fn chat_completions(
             }
         };
 
-        if let Some(body) = executed.upstream_error_body {
+        let mut response = if let Some(body) = executed.upstream_error_body {
             let v = error_payload_to_chat_completion(
                 &executed.provider_name,
                 &executed.effective_model,
                 &body,
             );
-            return Ok(Json(v).into_response());
+            Json(v).into_response()
+        } else {
+            let mut dual = executed.response?;
+            if let Some(claim) = idempotency {
+                claim.complete(&app_state, &dual.raw, Utc::now()).await;
+            }
+            if let Some(encoded) = executed.watermark.as_deref() {
+                crate::server::watermark::annotate_json(&mut dual.raw, encoded);
+            }
+            Json(dual.raw).into_response()
+        };
+        if let Some(encoded) = executed.watermark.as_deref() {
+            crate::server::watermark::insert_header(&mut response, encoded);
         }
-
-        match executed.response {
-            Ok(dual) => Ok(Json(dual.raw).into_response()),
-            Err(err) => Err(err),
+        if let Some(hit) = executed.semantic_cache_hit {
+            response.headers_mut().insert(
+                crate::server::semantic_cache::SEMANTIC_CACHE_HEADER,
+                axum::http::HeaderValue::from_static(if hit { "hit" } else { "miss" }),
+            );
+        }
+        if let Some(truncation) = executed.prompt_truncation.as_ref() {
+            response.headers_mut().insert(
+                crate::server::prompt_truncation::TRUNCATED_HEADER,
+                axum::http::HeaderValue::from(truncation.removed_messages),
+            );
         }
+        crate::providers::capabilities::insert_header(&mut response, &executed.dropped_features);
+        if debug_capture && let Some(log_id) = executed.logged.log_id {
+            response.headers_mut().insert(
+                crate::server::debug_capture::LOG_ID_HEADER,
+                axum::http::HeaderValue::from(log_id),
+            );
+        }
+        Ok(response)
     }
 }
 
+#[derive(Debug, Serialize)]
+pub struct ChatPlanResponse {
+    pub allowed: bool,
+    pub requested_model: String,
+    pub provider: Option<String>,
+    pub key_id: Option<String>,
+    pub upstream_model: Option<String>,
+    pub billing_model: Option<String>,
+    pub estimated_prompt_tokens: u32,
+    pub estimated_cost: Option<f64>,
+    pub rejection: Option<String>,
+    pub trace: DecisionTrace,
+}
+
+// 试运行：执行与正式请求相同的无副作用准入检查（令牌、供应商选择、价格、限流余量、参数策略、提示大小等），但不调用上游
+pub async fn chat_completions_plan(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(gateway_req): Json<GatewayChatCompletionRequest>,
+) -> Result<Json<ChatPlanResponse>, GatewayError> {
+    let start_time = Utc::now();
+    let provider_override = ProviderOverride::from_request(
+        &headers,
+        gateway_req.provider.as_deref(),
+        gateway_req.provider_key.as_deref(),
+    )?;
+    let mut top_k = gateway_req.top_k;
+    let mut request = gateway_req.request;
+    let requested_model = request.model.clone();
+    let client_token = bearer_token(&headers);
+    let client_token_log_id = client_token
+        .as_deref()
+        .map(crate::admin::client_token_id_for_token);
+    let token = match client_token.as_deref() {
+        Some(tok) => app_state.token_store.get_token(tok).await?,
+        None => None,
+    };
+    let token = match token {
+        Some(token) => token,
+        None => {
+            let ge = if client_token.is_some() {
+                GatewayError::Config("invalid token".into())
+            } else {
+                GatewayError::Config("missing bearer token".into())
+            };
+            crate::server::request_logging::log_simple_request(
+                &app_state,
+                start_time,
+                "POST",
+                "/v1/chat/completions/plan",
+                crate::logging::types::REQ_TYPE_CHAT_PLAN,
+                Some(requested_model),
+                None,
+                client_token_log_id.as_deref(),
+                ge.status_code().as_u16(),
+                Some(ge.to_string()),
+            )
+            .await;
+            return Err(ge);
+        }
+    };
+
+    let estimated_prompt_tokens = estimate_prompt_tokens(&request);
+    let mut trace = DecisionTrace::new(&requested_model);
+    let transport = ChatTransport::for_request(&request);
+    let planned = check_chat_admission(
+        &app_state,
+        &mut request,
+        &mut top_k,
+        &token,
+        transport,
+        provider_override.as_ref(),
+        &mut trace,
+    )
+    .await;
+    let estimated_cost = match planned.as_ref() {
+        Ok(admission) => {
+            estimate_cost(
+                &app_state,
+                &admission.planned.selected.provider.name,
+                &admission.planned.billing_model,
+                &request,
+            )
+            .await
+        }
+        Err(_) => None,
+    };
+    let rejection = planned
+        .as_ref()
+        .err()
+        .map(|e| trace.rejection.clone().unwrap_or_else(|| e.to_string()));
+
+    crate::server::request_logging::log_simple_request(
+        &app_state,
+        start_time,
+        "POST",
+        "/v1/chat/completions/plan",
+        crate::logging::types::REQ_TYPE_CHAT_PLAN,
+        Some(requested_model.clone()),
+        trace.provider.clone(),
+        client_token_log_id.as_deref(),
+        200,
+        rejection.clone(),
+    )
+    .await;
+
+    Ok(Json(ChatPlanResponse {
+        allowed: planned.is_ok(),
+        requested_model,
+        provider: trace.provider.clone(),
+        key_id: trace.key_id.clone(),
+        upstream_model: trace.upstream_model.clone(),
+        billing_model: trace.billing_model.clone(),
+        estimated_prompt_tokens,
+        estimated_cost,
+        rejection,
+        trace,
+    }))
+}
+
 #[cfg(test)]
 mod tests {
     use super::error_payload_to_chat_completion;
@@ -147,7 +375,6 @@ mod tests {
     };
     use crate::logging::{DatabaseLogger, ModelPriceUpsert};
     use crate::server::AppState;
-    use crate::server::login::LoginManager;
     use crate::users::{CreateUserPayload, UserRole, UserStatus, UserStore};
     use axum::body::to_bytes;
     use axum::extract::State;
@@ -750,9 +977,21 @@ mod tests {
             }
         }
 
+        // 语义缓存测试用：提到 weather 的输入向量相同，其余输入与之正交
+        async fn embeddings_handler(Json(body): Json<Value>) -> Json<Value> {
+            let input = body["input"].as_str().unwrap_or_default();
+            let embedding = if input.contains("weather") {
+                json!([1.0, 0.0])
+            } else {
+                json!([0.0, 1.0])
+            };
+            Json(json!({"object": "list", "data": [{"index": 0, "embedding": embedding}]}))
+        }
+
         let captured = Arc::new(Mutex::new(Vec::new()));
         let app = Router::new()
             .route("/v1/chat/completions", post(handler))
+            .route("/v1/embeddings", post(embeddings_handler))
             .with_state(captured.clone());
         let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
         let addr = listener.local_addr().unwrap();
@@ -971,40 +1210,13 @@ mod tests {
 
         let token = logger
             .create_token(CreateTokenPayload {
-                id: None,
-                user_id: None,
                 name: Some(format!("{provider_name}-token")),
-                token: None,
-                allowed_models: None,
-                model_blacklist: None,
-                max_tokens: None,
-                max_amount: None,
-                enabled: true,
-                expires_at: None,
-                remark: None,
-                organization_id: None,
-                ip_whitelist: None,
-                ip_blacklist: None,
+                ..Default::default()
             })
             .await
             .unwrap();
 
-        let app_state = Arc::new(AppState {
-            config: settings,
-            load_balancer_state: Arc::new(crate::routing::LoadBalancerState::default()),
-            log_store: logger.clone(),
-            model_cache: logger.clone(),
-            providers: logger.clone(),
-            token_store: logger.clone(),
-            favorites_store: logger.clone(),
-            organizations: logger.clone(),
-            login_manager: Arc::new(LoginManager::new(logger.clone())),
-            user_store: logger.clone(),
-            refresh_token_store: logger.clone(),
-            password_reset_token_store: logger.clone(),
-            balance_store: logger.clone(),
-            subscription_store: logger.clone(),
-        });
+        let app_state = crate::server::test_harness::app_state(settings, logger.clone());
 
         (dir, app_state, token.token)
     }
@@ -1055,6 +1267,8 @@ mod tests {
             Json(super::GatewayChatCompletionRequest {
                 request,
                 top_k: None,
+                provider: None,
+                provider_key: None,
             }),
         )
         .await?;
@@ -1090,6 +1304,8 @@ mod tests {
             Json(super::GatewayChatCompletionRequest {
                 request,
                 top_k: None,
+                provider: None,
+                provider_key: None,
             }),
         )
         .await?;
@@ -1603,6 +1819,111 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn mock_provider_type_serves_chat_and_stream_in_process() {
+        let (_dir, app_state, token) = test_app_state_with_provider(
+            "local-mock",
+            ProviderType::Mock,
+            "http://mock.invalid",
+            ProviderConfig {
+                mock_reply: Some("canned mock reply".into()),
+                mock_completion_tokens: Some(3),
+                ..Default::default()
+            },
+            "mock-chat",
+        )
+        .await;
+
+        let payload =
+            invoke_chat_and_parse_json(app_state.clone(), &token, "local-mock/mock-chat", false)
+                .await
+                .unwrap();
+        assert_eq!(
+            payload["choices"][0]["message"]["content"],
+            json!("canned mock reply")
+        );
+        assert_eq!(payload["usage"]["completion_tokens"], json!(3));
+
+        let (_, body) =
+            invoke_chat_and_collect_text(app_state.clone(), &token, "local-mock/mock-chat", true)
+                .await
+                .unwrap();
+        assert_eq!(collect_stream_content(&body), "canned mock reply");
+        assert_eq!(stream_data_lines(&body).last().copied(), Some("[DONE]"));
+
+        let logs = app_state.log_store.get_request_logs(5, None).await.unwrap();
+        assert_eq!(logs.len(), 2);
+        assert!(logs.iter().all(|log| log.status_code == 200));
+        assert!(logs.iter().all(|log| log.completion_tokens == Some(3)));
+    }
+
+    #[tokio::test]
+    async fn n_choices_fan_out_for_providers_without_native_n() {
+        let (_dir, app_state, token) = test_app_state_with_provider(
+            "local-mock",
+            ProviderType::Mock,
+            "http://mock.invalid",
+            ProviderConfig {
+                mock_reply: Some("canned mock reply".into()),
+                mock_completion_tokens: Some(3),
+                ..Default::default()
+            },
+            "mock-chat",
+        )
+        .await;
+        let call = |stream: bool| {
+            let app_state = app_state.clone();
+            let token = token.clone();
+            async move {
+                let mut headers = HeaderMap::new();
+                headers.insert(
+                    AUTHORIZATION,
+                    HeaderValue::from_str(&format!("Bearer {token}")).unwrap(),
+                );
+                let request = serde_json::from_value(json!({
+                    "model": "local-mock/mock-chat",
+                    "messages": [{"role":"user","content":"hello"}],
+                    "n": 3,
+                    "stream": stream
+                }))
+                .unwrap();
+                super::chat_completions(
+                    State(app_state),
+                    headers,
+                    Json(super::GatewayChatCompletionRequest {
+                        request,
+                        top_k: None,
+                        provider: None,
+                        provider_key: None,
+                    }),
+                )
+                .await
+            }
+        };
+
+        let response = call(false).await.unwrap();
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let payload: Value = serde_json::from_slice(&bytes).unwrap();
+        let indices: Vec<_> = payload["choices"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|choice| choice["index"].as_u64().unwrap())
+            .collect();
+        assert_eq!(indices, vec![0, 1, 2]);
+        assert_eq!(payload["usage"]["completion_tokens"], json!(9));
+
+        let err = call(true).await.unwrap_err();
+        assert!(
+            matches!(err, crate::error::GatewayError::Config(_)),
+            "{err}"
+        );
+
+        let logs = app_state.log_store.get_request_logs(5, None).await.unwrap();
+        let ok = logs.iter().find(|log| log.status_code == 200).unwrap();
+        assert_eq!(ok.completion_tokens, Some(9));
+    }
+
     #[tokio::test]
     async fn mock_runtime_360_zhinao_chat() {
         let (base_url, captured) = spawn_mock_openai_compat_server().await;
@@ -2029,6 +2350,475 @@ mod tests {
         assert!(call.body.get("stream_options").is_none());
     }
 
+    #[tokio::test]
+    async fn token_model_blacklist_applies_to_stream_and_non_stream() {
+        let (base_url, captured) = spawn_mock_openai_compat_server().await;
+        let (_dir, app_state, token) = test_app_state_with_provider(
+            "blacklist-both",
+            ProviderType::OpenAI,
+            &base_url,
+            ProviderConfig::default(),
+            "m1",
+        )
+        .await;
+        app_state
+            .token_store
+            .update_token(
+                &token,
+                serde_json::from_value(json!({ "model_blacklist": ["blacklist-both/m1"] }))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        for stream in [false, true] {
+            let err =
+                invoke_chat_and_parse_json(app_state.clone(), &token, "blacklist-both/m1", stream)
+                    .await
+                    .unwrap_err();
+            assert!(
+                matches!(err, crate::error::GatewayError::Forbidden(_)),
+                "stream={stream}: {err}"
+            );
+        }
+        assert!(captured.lock().await.is_empty());
+        let logs = app_state.log_store.get_request_logs(5, None).await.unwrap();
+        assert_eq!(logs.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn debug_capture_header_requires_permission_and_returns_log_id() {
+        let (base_url, _captured) = spawn_mock_openai_compat_server().await;
+        let (_dir, app_state, token) = test_app_state_with_provider(
+            "debug-capture",
+            ProviderType::OpenAI,
+            &base_url,
+            ProviderConfig::default(),
+            "m1",
+        )
+        .await;
+        let call = |stream: bool| {
+            let app_state = app_state.clone();
+            let token = token.clone();
+            async move {
+                let mut headers = HeaderMap::new();
+                headers.insert(
+                    AUTHORIZATION,
+                    HeaderValue::from_str(&format!("Bearer {token}")).unwrap(),
+                );
+                headers.insert(
+                    crate::server::debug_capture::DEBUG_HEADER,
+                    HeaderValue::from_static("capture"),
+                );
+                let request = serde_json::from_value(json!({
+                    "model": "debug-capture/m1",
+                    "messages": [{"role":"user","content":"hello"}],
+                    "stream": stream
+                }))
+                .unwrap();
+                super::chat_completions(
+                    State(app_state),
+                    headers,
+                    Json(super::GatewayChatCompletionRequest {
+                        request,
+                        top_k: None,
+                        provider: None,
+                        provider_key: None,
+                    }),
+                )
+                .await
+            }
+        };
+
+        let err = call(false).await.unwrap_err();
+        assert!(
+            matches!(err, crate::error::GatewayError::Forbidden(_)),
+            "{err}"
+        );
+
+        app_state
+            .token_store
+            .update_token(
+                &token,
+                serde_json::from_value(json!({ "allow_debug_capture": true })).unwrap(),
+            )
+            .await
+            .unwrap();
+        let err = call(true).await.unwrap_err();
+        assert!(
+            matches!(err, crate::error::GatewayError::Config(_)),
+            "{err}"
+        );
+
+        let response = call(false).await.unwrap();
+        let log_id: i64 = response
+            .headers()
+            .get(crate::server::debug_capture::LOG_ID_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse().ok())
+            .expect("log id header");
+        let capture = app_state
+            .log_store
+            .get_debug_capture(log_id)
+            .await
+            .unwrap()
+            .expect("debug capture");
+        assert!(capture.request_body.unwrap().contains("hello"));
+        assert!(capture.response_body.unwrap().contains("choices"));
+        assert!(capture.expires_at > capture.created_at);
+
+        app_state
+            .log_store
+            .purge_expired_debug_captures(capture.expires_at)
+            .await
+            .unwrap();
+        assert!(
+            app_state
+                .log_store
+                .get_debug_capture(log_id)
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn provider_override_requires_permission_and_pins_key() {
+        let (base_url, captured) = spawn_mock_openai_compat_server().await;
+        let (_dir, app_state, token) = test_app_state_with_provider(
+            "pinned",
+            ProviderType::OpenAI,
+            &base_url,
+            ProviderConfig::default(),
+            "m1",
+        )
+        .await;
+        app_state
+            .providers
+            .add_provider_key(
+                "pinned",
+                "other-upstream-key",
+                &app_state.config.logging.key_log_strategy,
+            )
+            .await
+            .unwrap();
+        let call = || {
+            let app_state = app_state.clone();
+            let token = token.clone();
+            async move {
+                let mut headers = HeaderMap::new();
+                headers.insert(
+                    AUTHORIZATION,
+                    HeaderValue::from_str(&format!("Bearer {token}")).unwrap(),
+                );
+                headers.insert(
+                    crate::server::provider_override::PROVIDER_KEY_HEADER,
+                    HeaderValue::from_static("othe****-key"),
+                );
+                let request = serde_json::from_value(json!({
+                    "model": "m1",
+                    "messages": [{"role":"user","content":"hello"}]
+                }))
+                .unwrap();
+                super::chat_completions(
+                    State(app_state),
+                    headers,
+                    Json(super::GatewayChatCompletionRequest {
+                        request,
+                        top_k: None,
+                        provider: Some("pinned".into()),
+                        provider_key: None,
+                    }),
+                )
+                .await
+            }
+        };
+
+        let err = call().await.unwrap_err();
+        assert!(
+            matches!(err, crate::error::GatewayError::Forbidden(_)),
+            "{err}"
+        );
+        assert!(captured.lock().await.is_empty());
+
+        app_state
+            .token_store
+            .update_token(
+                &token,
+                serde_json::from_value(json!({ "allow_provider_override": true })).unwrap(),
+            )
+            .await
+            .unwrap();
+        call().await.unwrap();
+        {
+            let captured = captured.lock().await;
+            assert_eq!(captured.len(), 1);
+            assert_eq!(
+                captured[0].headers.get("authorization").map(String::as_str),
+                Some("Bearer other-upstream-key")
+            );
+            assert_eq!(captured[0].body["model"], "m1");
+        }
+
+        let log = app_state
+            .log_store
+            .get_recent_logs_with_cursor(1, None)
+            .await
+            .unwrap()
+            .remove(0);
+        assert_eq!(log.provider.as_deref(), Some("pinned"));
+        let detail = app_state
+            .log_store
+            .get_request_log_detail(log.id.unwrap())
+            .await
+            .unwrap()
+            .expect("log detail");
+        assert_eq!(
+            detail.provider_override.as_deref(),
+            Some(r#"{"provider":"pinned","key":"othe****-key"}"#)
+        );
+    }
+
+    #[tokio::test]
+    async fn auto_truncate_prompt_drops_oldest_messages() {
+        let (base_url, captured) = spawn_mock_openai_compat_server().await;
+        let (_dir, mut app_state, token) = test_app_state_with_provider(
+            "truncating",
+            ProviderType::OpenAI,
+            &base_url,
+            ProviderConfig::default(),
+            "m1",
+        )
+        .await;
+        Arc::get_mut(&mut app_state)
+            .unwrap()
+            .config
+            .server
+            .model_context_windows
+            .insert("m1".into(), 64);
+        app_state
+            .token_store
+            .update_token(
+                &token,
+                serde_json::from_value(json!({ "auto_truncate_prompt": true })).unwrap(),
+            )
+            .await
+            .unwrap();
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {token}")).unwrap(),
+        );
+        let long = "x".repeat(400);
+        let request = serde_json::from_value(json!({
+            "model": "m1",
+            "messages": [
+                {"role": "system", "content": "be brief"},
+                {"role": "user", "content": long},
+                {"role": "assistant", "content": long},
+                {"role": "user", "content": "latest question"}
+            ]
+        }))
+        .unwrap();
+        let response = super::chat_completions(
+            State(app_state.clone()),
+            headers,
+            Json(super::GatewayChatCompletionRequest {
+                request,
+                top_k: None,
+                provider: None,
+                provider_key: None,
+            }),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            response
+                .headers()
+                .get(crate::server::prompt_truncation::TRUNCATED_HEADER)
+                .and_then(|v| v.to_str().ok()),
+            Some("2")
+        );
+        {
+            let captured = captured.lock().await;
+            let messages = captured[0].body["messages"].as_array().unwrap();
+            assert_eq!(messages.len(), 2);
+            assert_eq!(messages[1]["content"], "latest question");
+        }
+
+        let log = app_state
+            .log_store
+            .get_recent_logs_with_cursor(1, None)
+            .await
+            .unwrap()
+            .remove(0);
+        let detail = app_state
+            .log_store
+            .get_request_log_detail(log.id.unwrap())
+            .await
+            .unwrap()
+            .expect("log detail");
+        let truncation: Value =
+            serde_json::from_str(detail.prompt_truncation.as_deref().unwrap()).unwrap();
+        assert_eq!(truncation["removed_messages"], 2);
+        assert_eq!(truncation["context_window"], 64);
+    }
+
+    #[tokio::test]
+    async fn model_concurrency_limit_fails_fast_when_full() {
+        let (base_url, captured) = spawn_mock_openai_compat_server().await;
+        let (_dir, mut app_state, token) = test_app_state_with_provider(
+            "limited",
+            ProviderType::OpenAI,
+            &base_url,
+            ProviderConfig::default(),
+            "m1",
+        )
+        .await;
+        Arc::get_mut(&mut app_state)
+            .unwrap()
+            .config
+            .server
+            .model_concurrency
+            .insert(
+                "m1".into(),
+                serde_json::from_value(json!({"max_in_flight": 1, "mode": "fail_fast"})).unwrap(),
+            );
+
+        let call = || {
+            let mut headers = HeaderMap::new();
+            headers.insert(
+                AUTHORIZATION,
+                HeaderValue::from_str(&format!("Bearer {token}")).unwrap(),
+            );
+            let request = serde_json::from_value(json!({
+                "model": "m1",
+                "messages": [{"role": "user", "content": "hi"}]
+            }))
+            .unwrap();
+            super::chat_completions(
+                State(app_state.clone()),
+                headers,
+                Json(super::GatewayChatCompletionRequest {
+                    request,
+                    top_k: None,
+                    provider: None,
+                    provider_key: None,
+                }),
+            )
+        };
+
+        let held = app_state
+            .model_concurrency
+            .acquire(
+                &app_state.config.server,
+                "m1",
+                &crate::server::model_concurrency::FairShare {
+                    key: "holder".into(),
+                    weight: 1,
+                },
+            )
+            .await
+            .unwrap();
+        let err = call().await.unwrap_err();
+        assert_eq!(err.status_code(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(captured.lock().await.is_empty());
+
+        drop(held);
+        let response = call().await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(captured.lock().await.len(), 1);
+        let snapshot = app_state
+            .model_concurrency
+            .snapshot(&app_state.config.server);
+        assert_eq!(snapshot[0].in_flight, 0);
+    }
+
+    #[tokio::test]
+    async fn semantic_cache_serves_similar_prompts_without_upstream_call() {
+        let (base_url, captured) = spawn_mock_openai_compat_server().await;
+        let (_dir, mut app_state, token) = test_app_state_with_provider(
+            "cached",
+            ProviderType::OpenAI,
+            &base_url,
+            ProviderConfig::default(),
+            "m1",
+        )
+        .await;
+        Arc::get_mut(&mut app_state)
+            .unwrap()
+            .config
+            .server
+            .semantic_cache = Some(crate::config::settings::SemanticCacheConfig {
+            embedding_provider: "cached".into(),
+            embedding_model: "embed-1".into(),
+            similarity_threshold: 0.95,
+            ttl_secs: 60,
+            max_entries: 10,
+        });
+        app_state
+            .token_store
+            .update_token(
+                &token,
+                serde_json::from_value(json!({ "semantic_cache": true })).unwrap(),
+            )
+            .await
+            .unwrap();
+        let call = |content: &'static str| {
+            let app_state = app_state.clone();
+            let token = token.clone();
+            async move {
+                let mut headers = HeaderMap::new();
+                headers.insert(
+                    AUTHORIZATION,
+                    HeaderValue::from_str(&format!("Bearer {token}")).unwrap(),
+                );
+                let request = serde_json::from_value(json!({
+                    "model": "m1",
+                    "messages": [{"role": "user", "content": content}]
+                }))
+                .unwrap();
+                let response = super::chat_completions(
+                    State(app_state),
+                    headers,
+                    Json(super::GatewayChatCompletionRequest {
+                        request,
+                        top_k: None,
+                        provider: None,
+                        provider_key: None,
+                    }),
+                )
+                .await
+                .unwrap();
+                response
+                    .headers()
+                    .get(crate::server::semantic_cache::SEMANTIC_CACHE_HEADER)
+                    .and_then(|v| v.to_str().ok())
+                    .map(str::to_string)
+            }
+        };
+
+        assert_eq!(call("what's the weather").await.as_deref(), Some("miss"));
+        assert_eq!(call("how is the weather").await.as_deref(), Some("hit"));
+        assert_eq!(captured.lock().await.len(), 1);
+        assert_eq!(call("hello").await.as_deref(), Some("miss"));
+        assert_eq!(captured.lock().await.len(), 2);
+
+        let stats = app_state.semantic_cache.stats(true);
+        assert_eq!((stats.hits, stats.misses, stats.entries), (1, 2, 2));
+        let logs = app_state
+            .log_store
+            .get_recent_logs_with_cursor(3, None)
+            .await
+            .unwrap();
+        assert!(
+            logs.iter()
+                .any(|log| log.request_type
+                    == crate::logging::types::REQ_TYPE_CHAT_SEMANTIC_CACHE_HIT)
+        );
+    }
+
     #[tokio::test]
     async fn missing_price_strict_mode_rejects_non_stream_chat() {
         let (base_url, captured) = spawn_mock_openai_compat_server().await;
@@ -2106,6 +2896,386 @@ mod tests {
         assert_eq!(logs[0].total_tokens, Some(10));
     }
 
+    #[tokio::test]
+    async fn idempotency_key_replays_response_without_rebilling() {
+        let (base_url, captured) = spawn_mock_openai_compat_server().await;
+        let (_dir, app_state, token) = test_app_state_with_provider(
+            "idem-target",
+            ProviderType::OpenAI,
+            &base_url,
+            ProviderConfig::default(),
+            "m1",
+        )
+        .await;
+        let invoke = |content: &'static str| {
+            let app_state = app_state.clone();
+            let token = token.clone();
+            async move {
+                let mut headers = HeaderMap::new();
+                headers.insert(
+                    AUTHORIZATION,
+                    HeaderValue::from_str(&format!("Bearer {token}")).unwrap(),
+                );
+                headers.insert("idempotency-key", HeaderValue::from_static("retry-1"));
+                let request = serde_json::from_value(json!({
+                    "model": "idem-target/m1",
+                    "messages": [{"role": "user", "content": content}],
+                }))
+                .unwrap();
+                super::chat_completions(
+                    State(app_state),
+                    headers,
+                    Json(super::GatewayChatCompletionRequest {
+                        request,
+                        top_k: None,
+                        provider: None,
+                        provider_key: None,
+                    }),
+                )
+                .await
+            }
+        };
+
+        let first = invoke("hello").await.unwrap();
+        assert!(
+            first
+                .headers()
+                .get(super::IDEMPOTENT_REPLAYED_HEADER)
+                .is_none()
+        );
+        let first: Value =
+            serde_json::from_slice(&to_bytes(first.into_body(), usize::MAX).await.unwrap())
+                .unwrap();
+        let replay = invoke("hello").await.unwrap();
+        assert_eq!(
+            replay
+                .headers()
+                .get(super::IDEMPOTENT_REPLAYED_HEADER)
+                .unwrap(),
+            "true"
+        );
+        let replay: Value =
+            serde_json::from_slice(&to_bytes(replay.into_body(), usize::MAX).await.unwrap())
+                .unwrap();
+        assert_eq!(replay, first);
+        let mismatch = invoke("different").await.unwrap_err();
+        assert_eq!(mismatch.status_code(), axum::http::StatusCode::BAD_REQUEST);
+        assert_eq!(captured.lock().await.len(), 1);
+
+        let updated = app_state
+            .token_store
+            .get_token(&token)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(updated.total_tokens_spent, 10);
+        let logs = app_state.log_store.get_request_logs(5, None).await.unwrap();
+        assert_eq!(logs.len(), 2);
+        assert!(logs.iter().any(|log| {
+            log.request_type == crate::logging::types::REQ_TYPE_CHAT_IDEMPOTENT_REPLAY
+                && log.amount_spent.is_none()
+        }));
+    }
+
+    #[tokio::test]
+    async fn usage_webhook_receives_signed_payload_after_completion() {
+        let (base_url, _captured) = spawn_mock_openai_compat_server().await;
+        let (_dir, app_state, token) = test_app_state_with_provider(
+            "hook-target",
+            ProviderType::OpenAI,
+            &base_url,
+            ProviderConfig::default(),
+            "m1",
+        )
+        .await;
+
+        let (hook_tx, mut hook_rx) = tokio::sync::mpsc::unbounded_channel::<(HeaderMap, String)>();
+        let hook_app = Router::new()
+            .route(
+                "/hook",
+                post(
+                    |State(tx): State<tokio::sync::mpsc::UnboundedSender<(HeaderMap, String)>>,
+                     headers: HeaderMap,
+                     body: String| async move {
+                        let _ = tx.send((headers, body));
+                        StatusCode::NO_CONTENT
+                    },
+                ),
+            )
+            .with_state(hook_tx);
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let hook_url = format!("http://{}/hook", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            axum::serve(listener, hook_app).await.unwrap();
+        });
+        let secret = crate::server::usage_webhooks::generate_secret();
+        let mut patch: crate::admin::UpdateTokenPayload =
+            serde_json::from_value(json!({ "usage_webhook_url": hook_url })).unwrap();
+        patch.usage_webhook_secret = Some(Some(secret.clone()));
+        app_state
+            .token_store
+            .update_token(&token, patch)
+            .await
+            .unwrap()
+            .unwrap();
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {token}")).unwrap(),
+        );
+        let request = serde_json::from_value(json!({
+            "model": "hook-target/m1",
+            "messages": [{"role": "user", "content": "hello"}],
+        }))
+        .unwrap();
+        super::chat_completions(
+            State(app_state.clone()),
+            headers,
+            Json(super::GatewayChatCompletionRequest {
+                request,
+                top_k: None,
+                provider: None,
+                provider_key: None,
+            }),
+        )
+        .await
+        .unwrap();
+
+        let (hook_headers, body) =
+            tokio::time::timeout(std::time::Duration::from_secs(5), hook_rx.recv())
+                .await
+                .unwrap()
+                .unwrap();
+        let timestamp: i64 = hook_headers
+            .get(crate::server::usage_webhooks::TIMESTAMP_HEADER)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert_eq!(
+            hook_headers
+                .get(crate::server::usage_webhooks::SIGNATURE_HEADER)
+                .unwrap()
+                .to_str()
+                .unwrap(),
+            crate::server::usage_webhooks::sign_payload(&secret, timestamp, &body)
+        );
+        assert_ne!(secret, token);
+        let payload: Value = serde_json::from_str(&body).unwrap();
+        let logs = app_state.log_store.get_request_logs(5, None).await.unwrap();
+        assert_eq!(payload["request_id"], json!(logs[0].id.unwrap()));
+        assert_eq!(payload["model"], "hook-target/m1");
+        assert_eq!(payload["total_tokens"], 10);
+        assert_eq!(payload["status_code"], 200);
+        assert!(payload["latency_ms"].is_i64());
+    }
+
+    #[tokio::test]
+    async fn signed_requests_authenticate_and_reject_replays() {
+        use crate::server::request_signing;
+        use tower::ServiceExt;
+
+        let (base_url, _captured) = spawn_mock_openai_compat_server().await;
+        let (_dir, app_state, token) = test_app_state_with_provider(
+            "signed",
+            ProviderType::OpenAI,
+            &base_url,
+            ProviderConfig::default(),
+            "m1",
+        )
+        .await;
+        let secret = "k".repeat(32);
+        let patch =
+            serde_json::from_value(json!({ "signing_secret": secret, "require_signature": true }))
+                .unwrap();
+        let token_id = app_state
+            .token_store
+            .update_token(&token, patch)
+            .await
+            .unwrap()
+            .unwrap()
+            .id;
+        let app = Router::new()
+            .route("/v1/chat/completions", post(super::chat_completions))
+            .layer(axum::middleware::from_fn_with_state(
+                app_state.clone(),
+                request_signing::enforce,
+            ))
+            .with_state(app_state.clone());
+
+        let body = json!({
+            "model": "signed/m1",
+            "messages": [{"role": "user", "content": "hello"}],
+        })
+        .to_string();
+        let timestamp = chrono::Utc::now().timestamp();
+        let signature = request_signing::sign_request(
+            &secret,
+            &request_signing::canonical_request(
+                "POST",
+                "/v1/chat/completions",
+                timestamp,
+                "nonce-1",
+                body.as_bytes(),
+            ),
+        );
+        let signed = || {
+            axum::http::Request::builder()
+                .method("POST")
+                .uri("/v1/chat/completions")
+                .header(CONTENT_TYPE, "application/json")
+                .header(request_signing::TOKEN_ID_HEADER, &token_id)
+                .header(request_signing::TIMESTAMP_HEADER, timestamp.to_string())
+                .header(request_signing::NONCE_HEADER, "nonce-1")
+                .header(request_signing::SIGNATURE_HEADER, &signature)
+                .body(axum::body::Body::from(body.clone()))
+                .unwrap()
+        };
+
+        let resp = app.clone().oneshot(signed()).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let replay = app.clone().oneshot(signed()).await.unwrap();
+        assert_eq!(replay.status(), StatusCode::UNAUTHORIZED);
+
+        // 开启 require_signature 后直接使用 Bearer Token 被拒绝
+        let bearer = axum::http::Request::builder()
+            .method("POST")
+            .uri("/v1/chat/completions")
+            .header(CONTENT_TYPE, "application/json")
+            .header(AUTHORIZATION, format!("Bearer {token}"))
+            .body(axum::body::Body::from(body.clone()))
+            .unwrap();
+        let resp = app.clone().oneshot(bearer).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+        // 篡改请求体后签名失效
+        let tampered = axum::http::Request::builder()
+            .method("POST")
+            .uri("/v1/chat/completions")
+            .header(CONTENT_TYPE, "application/json")
+            .header(request_signing::TOKEN_ID_HEADER, &token_id)
+            .header(request_signing::TIMESTAMP_HEADER, timestamp.to_string())
+            .header(request_signing::NONCE_HEADER, "nonce-2")
+            .header(request_signing::SIGNATURE_HEADER, &signature)
+            .body(axum::body::Body::from(body.replace("hello", "bye")))
+            .unwrap();
+        let resp = app.oneshot(tampered).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn exchanged_token_usage_rolls_up_to_parent() {
+        let (base_url, _captured) = spawn_mock_openai_compat_server().await;
+        let (_dir, app_state, parent) = test_app_state_with_provider(
+            "exch",
+            ProviderType::OpenAI,
+            &base_url,
+            ProviderConfig::default(),
+            "m1",
+        )
+        .await;
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {parent}")).unwrap(),
+        );
+        let req = serde_json::from_value(json!({
+            "allowed_models": ["exch/m1"],
+            "ttl_secs": 300,
+        }))
+        .unwrap();
+        let Json(child) = super::super::token_exchange::exchange_token(
+            State(app_state.clone()),
+            headers,
+            Json(req),
+        )
+        .await
+        .unwrap();
+        assert_eq!(child.allowed_models, Some(vec!["exch/m1".to_string()]));
+        assert!(child.expires_at.is_some());
+
+        let body = invoke_chat_and_parse_json(app_state.clone(), &child.token, "exch/m1", false)
+            .await
+            .unwrap();
+        assert_eq!(body["usage"]["total_tokens"], 10);
+
+        let child_row = app_state
+            .token_store
+            .get_token(&child.token)
+            .await
+            .unwrap()
+            .unwrap();
+        let parent_row = app_state
+            .token_store
+            .get_token(&parent)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            child_row.parent_token_id.as_deref(),
+            Some(parent_row.id.as_str())
+        );
+        assert_eq!(parent_row.total_tokens_spent, child_row.total_tokens_spent);
+        assert!(child_row.amount_spent > 0.0);
+        assert!((parent_row.amount_spent - child_row.amount_spent).abs() < 1e-12);
+    }
+
+    #[tokio::test]
+    async fn sandbox_token_never_hits_upstream_and_costs_nothing() {
+        let (base_url, captured) = spawn_mock_openai_compat_server().await;
+        let (_dir, app_state, token) = test_app_state_with_provider(
+            "sandbox-target",
+            ProviderType::OpenAI,
+            &base_url,
+            ProviderConfig::default(),
+            "m1",
+        )
+        .await;
+        let patch: crate::admin::UpdateTokenPayload =
+            serde_json::from_value(json!({"sandbox": true})).unwrap();
+        app_state
+            .token_store
+            .update_token(&token, patch)
+            .await
+            .unwrap();
+
+        let payload =
+            invoke_chat_and_parse_json(app_state.clone(), &token, "sandbox-target/m1", false)
+                .await
+                .unwrap();
+        assert_eq!(
+            payload["choices"][0]["message"]["content"],
+            json!("[sandbox] hello")
+        );
+        let (_, body) =
+            invoke_chat_and_collect_text(app_state.clone(), &token, "sandbox-target/m1", true)
+                .await
+                .unwrap();
+        assert_eq!(collect_stream_content(&body), "[sandbox] hello");
+        assert_eq!(stream_data_lines(&body).last().copied(), Some("[DONE]"));
+        assert!(captured.lock().await.is_empty());
+
+        let updated = app_state
+            .token_store
+            .get_token(&token)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(updated.amount_spent, 0.0);
+        assert_eq!(updated.total_tokens_spent, 0);
+
+        let logs = app_state.log_store.get_request_logs(5, None).await.unwrap();
+        assert_eq!(logs.len(), 2);
+        assert!(logs.iter().all(|log| {
+            log.request_type == crate::logging::types::REQ_TYPE_CHAT_SANDBOX
+                && log.provider.as_deref() == Some(crate::server::sandbox::SANDBOX_PROVIDER)
+                && log.amount_spent == Some(0.0)
+        }));
+    }
+
     #[tokio::test]
     async fn user_balance_depleted_rejects_chat_and_disables_tokens() {
         let dir = tempdir().unwrap();
@@ -2117,22 +3287,7 @@ mod tests {
         );
 
         let settings = test_settings(db_path.to_string_lossy().to_string());
-        let app_state = Arc::new(AppState {
-            config: settings,
-            load_balancer_state: Arc::new(crate::routing::LoadBalancerState::default()),
-            log_store: logger.clone(),
-            model_cache: logger.clone(),
-            providers: logger.clone(),
-            token_store: logger.clone(),
-            favorites_store: logger.clone(),
-            organizations: logger.clone(),
-            login_manager: Arc::new(LoginManager::new(logger.clone())),
-            user_store: logger.clone(),
-            refresh_token_store: logger.clone(),
-            password_reset_token_store: logger.clone(),
-            balance_store: logger.clone(),
-            subscription_store: logger.clone(),
-        });
+        let app_state = crate::server::test_harness::app_state(settings, logger.clone());
 
         let user = logger
             .create_user(CreateUserPayload {
@@ -2151,40 +3306,18 @@ mod tests {
 
         let t1 = logger
             .create_token(CreateTokenPayload {
-                id: None,
                 user_id: Some(user.id.clone()),
                 name: Some("t1".into()),
-                token: None,
-                allowed_models: None,
-                model_blacklist: None,
-                max_tokens: None,
-                max_amount: None,
-                enabled: true,
-                expires_at: None,
-                remark: None,
-                organization_id: None,
-                ip_whitelist: None,
-                ip_blacklist: None,
+                ..Default::default()
             })
             .await
             .unwrap();
 
         let _t2 = logger
             .create_token(CreateTokenPayload {
-                id: None,
                 user_id: Some(user.id.clone()),
                 name: Some("t2".into()),
-                token: None,
-                allowed_models: None,
-                model_blacklist: None,
-                max_tokens: None,
-                max_amount: None,
-                enabled: true,
-                expires_at: None,
-                remark: None,
-                organization_id: None,
-                ip_whitelist: None,
-                ip_blacklist: None,
+                ..Default::default()
             })
             .await
             .unwrap();
@@ -2209,6 +3342,8 @@ mod tests {
             Json(super::GatewayChatCompletionRequest {
                 request: req,
                 top_k: None,
+                provider: None,
+                provider_key: None,
             }),
         )
         .await
@@ -1,11 +1,12 @@
 use axum::{
     Json,
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::HeaderMap,
 };
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
+use crate::server::pagination::{Listing, Page, PageQuery, SortFields, SortOrder};
 use crate::server::storage_traits::FavoriteKind;
 use crate::server::util::{bearer_token, token_for_log};
 use crate::{
@@ -36,7 +37,39 @@ pub struct ClientTokenOut {
     pub organization_id: Option<String>,
     pub ip_whitelist: Option<Vec<String>>,
     pub ip_blacklist: Option<Vec<String>>,
+    pub allow_streaming: bool,
+    pub sandbox: bool,
+    pub strip_reasoning: bool,
+    pub usage_webhook_url: Option<String>,
+    /// 用量 Webhook 签名密钥：设置 usage_webhook_url 时由网关生成
+    pub usage_webhook_secret: Option<String>,
+    pub signing_secret: Option<String>,
+    pub require_signature: bool,
+    pub allow_debug_capture: bool,
+    pub allow_provider_override: bool,
+    pub auto_truncate_prompt: bool,
+    pub semantic_cache: bool,
+    pub allow_login_codes: bool,
+    pub max_requests: Option<i64>,
+    pub max_requests_per_day: Option<i64>,
+    pub watermark_responses: bool,
+    pub queue_weight: Option<i64>,
+    pub parent_token_id: Option<String>,
     pub is_favorite: bool,
+    /// 仅详情接口返回
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub activity: Option<TokenActivityOut>,
+}
+
+/// 令牌详情中的活动概览（供 TUI 详情弹窗与 Web 控制台一次取全）
+#[derive(Debug, Serialize)]
+pub struct TokenActivityOut {
+    pub window_days: i64,
+    pub last_used_at: Option<String>,
+    /// 最近 window_days 天（北京时间，含今天）逐日汇总，无请求的日期补零
+    pub daily: Vec<TokenActivityDay>,
+    pub top_models: Vec<TokenModelUsage>,
+    pub recent_errors: Vec<TokenRecentError>,
 }
 
 impl From<ClientToken> for ClientTokenOut {
@@ -65,14 +98,95 @@ impl From<ClientToken> for ClientTokenOut {
             organization_id: t.organization_id,
             ip_whitelist: t.ip_whitelist,
             ip_blacklist: t.ip_blacklist,
+            allow_streaming: t.allow_streaming,
+            sandbox: t.sandbox,
+            strip_reasoning: t.strip_reasoning,
+            usage_webhook_url: t.usage_webhook_url,
+            usage_webhook_secret: t.usage_webhook_secret,
+            signing_secret: t.signing_secret,
+            require_signature: t.require_signature,
+            allow_debug_capture: t.allow_debug_capture,
+            allow_provider_override: t.allow_provider_override,
+            auto_truncate_prompt: t.auto_truncate_prompt,
+            semantic_cache: t.semantic_cache,
+            allow_login_codes: t.allow_login_codes,
+            max_requests: t.max_requests,
+            max_requests_per_day: t.max_requests_per_day,
+            watermark_responses: t.watermark_responses,
+            queue_weight: t.queue_weight,
+            parent_token_id: t.parent_token_id,
             is_favorite: false,
+            activity: None,
         }
     }
 }
 
-use super::auth::require_superadmin;
+use super::auth::{AdminIdentity, ensure_admin, require_superadmin};
+use crate::logging::time::BEIJING_OFFSET;
+use crate::logging::types::{TokenActivity, TokenActivityDay, TokenModelUsage, TokenRecentError};
+use crate::server::model_concurrency;
 use crate::server::request_logging::log_simple_request;
-use chrono::Utc;
+use crate::server::request_quota;
+use crate::server::usage_webhooks;
+use chrono::{Duration, NaiveDate, TimeZone, Utc};
+
+const ACTIVITY_WINDOW_DAYS: i64 = 30;
+const ACTIVITY_TOP_MODELS: u32 = 5;
+const ACTIVITY_RECENT_ERRORS: u32 = 10;
+
+/// 按日期补齐 [today - window_days + 1, today] 的逐日序列
+fn fill_activity_days(
+    days: Vec<TokenActivityDay>,
+    today: NaiveDate,
+    window_days: i64,
+) -> Vec<TokenActivityDay> {
+    let mut by_date: std::collections::HashMap<String, TokenActivityDay> =
+        days.into_iter().map(|d| (d.date.clone(), d)).collect();
+    (0..window_days)
+        .rev()
+        .map(|offset| {
+            let date = (today - Duration::days(offset))
+                .format("%Y-%m-%d")
+                .to_string();
+            by_date.remove(&date).unwrap_or(TokenActivityDay {
+                date,
+                ..Default::default()
+            })
+        })
+        .collect()
+}
+
+async fn token_activity(
+    app_state: &AppState,
+    token_id: &str,
+) -> Result<TokenActivityOut, GatewayError> {
+    let today = Utc::now().with_timezone(&BEIJING_OFFSET).date_naive();
+    let first_day = today - Duration::days(ACTIVITY_WINDOW_DAYS - 1);
+    let since = BEIJING_OFFSET
+        .from_local_datetime(&first_day.and_hms_opt(0, 0, 0).unwrap())
+        .single()
+        .map(|t| t.with_timezone(&Utc))
+        .unwrap_or_else(Utc::now);
+    let TokenActivity {
+        last_used_at,
+        daily,
+        top_models,
+        recent_errors,
+    } = app_state
+        .log_store
+        .token_activity(token_id, since, ACTIVITY_TOP_MODELS, ACTIVITY_RECENT_ERRORS)
+        .await
+        .map_err(GatewayError::Db)?;
+    Ok(TokenActivityOut {
+        window_days: ACTIVITY_WINDOW_DAYS,
+        last_used_at: last_used_at
+            .as_ref()
+            .map(crate::logging::time::to_iso8601_utc_string),
+        daily: fill_activity_days(daily, today, ACTIVITY_WINDOW_DAYS),
+        top_models,
+        recent_errors,
+    })
+}
 
 fn validate_client_token_name(name: &str) -> Result<String, GatewayError> {
     let trimmed = name.trim();
@@ -92,6 +206,9 @@ const REMARK_MAX_LEN: usize = 1024;
 const ORGANIZATION_ID_MAX_LEN: usize = 128;
 const IP_LIST_MAX_LEN: usize = 200;
 const IP_ITEM_MAX_LEN: usize = 64;
+const USAGE_WEBHOOK_URL_MAX_LEN: usize = 2048;
+const SIGNING_SECRET_MIN_LEN: usize = 32;
+const SIGNING_SECRET_MAX_LEN: usize = 256;
 const DEFAULT_ORGANIZATION_ID: &str = "default";
 
 fn normalize_optional_string(
@@ -128,6 +245,50 @@ fn normalize_optional_string_patch(
     }
 }
 
+/// 用量 Webhook 地址：去空白 + 长度校验 + SSRF 校验（不允许指向内网）
+async fn normalize_usage_webhook_url(v: Option<String>) -> Result<Option<String>, GatewayError> {
+    let Some(url) = normalize_optional_string("usage_webhook_url", v, USAGE_WEBHOOK_URL_MAX_LEN)?
+    else {
+        return Ok(None);
+    };
+    crate::server::ssrf::validate_outbound_base_url(&url)
+        .await
+        .map_err(|e| GatewayError::Config(format!("usage_webhook_url 无效: {}", e)))?;
+    Ok(Some(url))
+}
+
+/// 请求签名密钥：去空白后需为 32~256 位可见 ASCII 字符
+fn normalize_signing_secret(v: Option<String>) -> Result<Option<String>, GatewayError> {
+    let Some(secret) = v.map(|s| s.trim().to_string()).filter(|s| !s.is_empty()) else {
+        return Ok(None);
+    };
+    if !secret.chars().all(|c| c.is_ascii_graphic()) {
+        return Err(GatewayError::Config(
+            "signing_secret 只能包含可见 ASCII 字符".into(),
+        ));
+    }
+    let len = secret.len();
+    if !(SIGNING_SECRET_MIN_LEN..=SIGNING_SECRET_MAX_LEN).contains(&len) {
+        return Err(GatewayError::Config(format!(
+            "signing_secret 长度需在 {} 到 {} 之间",
+            SIGNING_SECRET_MIN_LEN, SIGNING_SECRET_MAX_LEN
+        )));
+    }
+    Ok(Some(secret))
+}
+
+fn ensure_signature_secret(
+    require_signature: bool,
+    signing_secret: Option<&str>,
+) -> Result<(), GatewayError> {
+    if require_signature && signing_secret.is_none() {
+        return Err(GatewayError::Config(
+            "require_signature 需要先设置 signing_secret".into(),
+        ));
+    }
+    Ok(())
+}
+
 fn normalize_ip_list(
     field: &str,
     v: Option<Vec<String>>,
@@ -176,29 +337,103 @@ fn normalize_ip_list_patch(
     }
 }
 
+/// 组织委派管理员只能访问本组织的令牌；越权访问统一按不存在处理
+async fn ensure_token_in_scope(
+    app_state: &AppState,
+    identity: &AdminIdentity,
+    id: &str,
+) -> Result<(), GatewayError> {
+    let Some(org) = identity.organization_scope() else {
+        return Ok(());
+    };
+    match app_state.token_store.get_token_by_id(id).await? {
+        Some(t) if t.organization_id.as_deref() == Some(org) => Ok(()),
+        _ => Err(GatewayError::NotFound("token not found".into())),
+    }
+}
+
+/// 仅超级管理员可开启的令牌能力；组织委派管理员设置这些字段时拒绝
+fn ensure_privileged_flags_allowed(
+    identity: &AdminIdentity,
+    flags: &[(&str, bool)],
+) -> Result<(), GatewayError> {
+    if identity.organization_scope().is_none() {
+        return Ok(());
+    }
+    match flags.iter().find(|(_, enabled)| *enabled) {
+        Some((name, _)) => Err(GatewayError::Forbidden(format!(
+            "only superadmins can set {}",
+            name
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// 用户属于组织：是该组织的委派管理员，或已持有该组织内的令牌
+async fn ensure_user_in_organization(
+    app_state: &AppState,
+    org: &str,
+    user_id: &str,
+) -> Result<(), GatewayError> {
+    let admin_of = app_state
+        .organizations
+        .get_admin_organization(user_id)
+        .await
+        .map_err(GatewayError::Db)?;
+    if admin_of.as_deref() == Some(org) {
+        return Ok(());
+    }
+    let owned = app_state.token_store.list_tokens_by_user(user_id).await?;
+    if owned
+        .iter()
+        .any(|t| t.organization_id.as_deref() == Some(org))
+    {
+        return Ok(());
+    }
+    Err(GatewayError::Forbidden(
+        "user_id is not a member of this organization".into(),
+    ))
+}
+
+const TOKEN_SORT_FIELDS: SortFields = SortFields {
+    allowed: &[
+        "created_at",
+        "name",
+        "expires_at",
+        "amount_spent",
+        "total_tokens_spent",
+    ],
+    default: "created_at",
+    default_order: SortOrder::Desc,
+};
+
 pub async fn list_tokens(
     State(app_state): State<Arc<AppState>>,
     headers: HeaderMap,
-) -> Result<Json<Vec<ClientTokenOut>>, GatewayError> {
+    Query(page): Query<PageQuery>,
+) -> Result<Json<Listing<ClientTokenOut>>, GatewayError> {
     let start_time = Utc::now();
     let provided_token = bearer_token(&headers);
-    if let Err(e) = require_superadmin(&headers, &app_state).await {
-        let code = e.status_code().as_u16();
-        log_simple_request(
-            &app_state,
-            start_time,
-            "GET",
-            "/admin/tokens",
-            "client_tokens_list",
-            None,
-            None,
-            provided_token.as_deref(),
-            code,
-            Some(e.to_string()),
-        )
-        .await;
-        return Err(e);
-    }
+    let identity = match ensure_admin(&headers, &app_state).await {
+        Ok(identity) => identity,
+        Err(e) => {
+            let code = e.status_code().as_u16();
+            log_simple_request(
+                &app_state,
+                start_time,
+                "GET",
+                "/admin/tokens",
+                "client_tokens_list",
+                None,
+                None,
+                provided_token.as_deref(),
+                code,
+                Some(e.to_string()),
+            )
+            .await;
+            return Err(e);
+        }
+    };
     use std::collections::HashMap;
     let usage_counts: HashMap<String, i64> = app_state
         .log_store
@@ -214,20 +449,35 @@ pub async fn list_tokens(
         .map_err(GatewayError::Db)?
         .into_iter()
         .collect();
-    let tokens = app_state
-        .token_store
-        .list_tokens()
-        .await?
-        .into_iter()
-        .map(|token| {
-            let mut out = ClientTokenOut::from(token.clone());
-            if let Some(count) = usage_counts.get(&token.id) {
-                out.usage_count = *count;
-            }
-            out.is_favorite = favorites.contains(&token.id);
-            out
-        })
-        .collect();
+    let to_out = |token: ClientToken| {
+        let mut out = ClientTokenOut::from(token.clone());
+        if let Some(count) = usage_counts.get(&token.id) {
+            out.usage_count = *count;
+        }
+        out.is_favorite = favorites.contains(&token.id);
+        out
+    };
+    let tokens = match page.resolve(&TOKEN_SORT_FIELDS)? {
+        Some(page) => {
+            let (tokens, total) = app_state
+                .token_store
+                .list_tokens_page(identity.organization_scope(), &page)
+                .await?;
+            Listing::Page(Page::new(tokens, total, &page).map(to_out))
+        }
+        None => {
+            let tokens = match identity.organization_scope() {
+                Some(org) => {
+                    app_state
+                        .token_store
+                        .list_tokens_by_organization(org)
+                        .await?
+                }
+                None => app_state.token_store.list_tokens().await?,
+            };
+            Listing::All(tokens.into_iter().map(to_out).collect())
+        }
+    };
     log_simple_request(
         &app_state,
         start_time,
@@ -251,24 +501,36 @@ pub async fn get_token(
 ) -> Result<Json<ClientTokenOut>, GatewayError> {
     let start_time = Utc::now();
     let provided_token = bearer_token(&headers);
-    if let Err(e) = require_superadmin(&headers, &app_state).await {
-        let code = e.status_code().as_u16();
-        log_simple_request(
-            &app_state,
-            start_time,
-            "GET",
-            "/admin/tokens/{id}",
-            "client_tokens_get",
-            None,
-            None,
-            provided_token.as_deref(),
-            code,
-            Some(e.to_string()),
-        )
-        .await;
-        return Err(e);
-    }
-    match app_state.token_store.get_token_by_id(&id).await? {
+    let identity = match ensure_admin(&headers, &app_state).await {
+        Ok(identity) => identity,
+        Err(e) => {
+            let code = e.status_code().as_u16();
+            log_simple_request(
+                &app_state,
+                start_time,
+                "GET",
+                "/admin/tokens/{id}",
+                "client_tokens_get",
+                None,
+                None,
+                provided_token.as_deref(),
+                code,
+                Some(e.to_string()),
+            )
+            .await;
+            return Err(e);
+        }
+    };
+    let token = app_state
+        .token_store
+        .get_token_by_id(&id)
+        .await?
+        .filter(|t| {
+            identity
+                .organization_scope()
+                .is_none_or(|org| t.organization_id.as_deref() == Some(org))
+        });
+    match token {
         Some(t) => {
             let mut out = ClientTokenOut::from(t.clone());
             out.is_favorite = app_state
@@ -283,7 +545,8 @@ pub async fn get_token(
                 .map_err(GatewayError::Db)?
                 .into_iter()
                 .collect();
-            out.usage_count = usage_counts.get(&t.token).copied().unwrap_or(0);
+            out.usage_count = usage_counts.get(&t.id).copied().unwrap_or(0);
+            out.activity = Some(token_activity(&app_state, &t.id).await?);
             Ok(Json(out))
         }
         None => {
@@ -307,6 +570,44 @@ pub async fn get_token(
     }
 }
 
+/// 令牌及其全部后代的用量汇总（分销商层级报表）
+pub async fn get_token_hierarchy(
+    Path(id): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<crate::server::token_lineage::HierarchyUsageNode>, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    let identity = match ensure_admin(&headers, &app_state).await {
+        Ok(identity) => identity,
+        Err(e) => {
+            let code = e.status_code().as_u16();
+            log_simple_request(
+                &app_state,
+                start_time,
+                "GET",
+                "/admin/tokens/{id}/hierarchy",
+                "client_tokens_hierarchy",
+                None,
+                None,
+                provided_token.as_deref(),
+                code,
+                Some(e.to_string()),
+            )
+            .await;
+            return Err(e);
+        }
+    };
+    ensure_token_in_scope(&app_state, &identity, &id).await?;
+    let root = app_state
+        .token_store
+        .get_token_by_id(&id)
+        .await?
+        .ok_or_else(|| GatewayError::NotFound("token not found".into()))?;
+    let tree = crate::server::token_lineage::hierarchy_usage(&app_state, &root).await?;
+    Ok(Json(tree))
+}
+
 pub async fn create_token(
     State(app_state): State<Arc<AppState>>,
     headers: HeaderMap,
@@ -314,23 +615,26 @@ pub async fn create_token(
 ) -> Result<(axum::http::StatusCode, Json<ClientTokenOut>), GatewayError> {
     let start_time = Utc::now();
     let provided_token = bearer_token(&headers);
-    if let Err(e) = require_superadmin(&headers, &app_state).await {
-        let code = e.status_code().as_u16();
-        log_simple_request(
-            &app_state,
-            start_time,
-            "POST",
-            "/admin/tokens",
-            "client_tokens_create",
-            None,
-            None,
-            provided_token.as_deref(),
-            code,
-            Some(e.to_string()),
-        )
-        .await;
-        return Err(e);
-    }
+    let identity = match ensure_admin(&headers, &app_state).await {
+        Ok(identity) => identity,
+        Err(e) => {
+            let code = e.status_code().as_u16();
+            log_simple_request(
+                &app_state,
+                start_time,
+                "POST",
+                "/admin/tokens",
+                "client_tokens_create",
+                None,
+                None,
+                provided_token.as_deref(),
+                code,
+                Some(e.to_string()),
+            )
+            .await;
+            return Err(e);
+        }
+    };
     if payload.id.is_some() {
         return Err(GatewayError::Config("不允许传入 id".into()));
     }
@@ -344,11 +648,42 @@ pub async fn create_token(
         payload.organization_id,
         ORGANIZATION_ID_MAX_LEN,
     )?;
+    if let Some(org) = identity.organization_scope() {
+        if payload
+            .organization_id
+            .as_deref()
+            .is_some_and(|requested| requested != org)
+        {
+            return Err(GatewayError::Forbidden(
+                "organization admins can only create tokens in their own organization".into(),
+            ));
+        }
+        payload.organization_id = Some(org.to_string());
+    }
     if payload.organization_id.is_none() {
         payload.organization_id = Some(DEFAULT_ORGANIZATION_ID.to_string());
     }
+    ensure_privileged_flags_allowed(
+        &identity,
+        &[
+            ("allow_provider_override", payload.allow_provider_override),
+            ("allow_login_codes", payload.allow_login_codes),
+            ("allow_debug_capture", payload.allow_debug_capture),
+            ("sandbox", payload.sandbox),
+        ],
+    )?;
+    request_quota::validate_limit("max_requests", payload.max_requests)?;
+    request_quota::validate_limit("max_requests_per_day", payload.max_requests_per_day)?;
+    model_concurrency::validate_queue_weight(payload.queue_weight)?;
     payload.ip_whitelist = normalize_ip_list("ip_whitelist", payload.ip_whitelist)?;
     payload.ip_blacklist = normalize_ip_list("ip_blacklist", payload.ip_blacklist)?;
+    payload.usage_webhook_url = normalize_usage_webhook_url(payload.usage_webhook_url).await?;
+    payload.usage_webhook_secret = payload
+        .usage_webhook_url
+        .as_ref()
+        .map(|_| usage_webhooks::generate_secret());
+    payload.signing_secret = normalize_signing_secret(payload.signing_secret)?;
+    ensure_signature_secret(payload.require_signature, payload.signing_secret.as_deref())?;
     payload.allowed_models = crate::server::token_model_limits::normalize_model_list(
         "allowed_models",
         payload.allowed_models,
@@ -376,6 +711,9 @@ pub async fn create_token(
         if exists.is_none() {
             return Err(GatewayError::Config("user_id 不存在".into()));
         }
+        if let Some(org) = identity.organization_scope() {
+            ensure_user_in_organization(&app_state, org, user_id).await?;
+        }
         payload.user_id = Some(user_id.to_string());
     } else {
         payload.user_id = None;
@@ -392,6 +730,20 @@ pub async fn create_token(
         &payload.model_blacklist,
     )
     .await?;
+    if let Some(parent_id) = payload
+        .parent_token_id
+        .take()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+    {
+        ensure_token_in_scope(&app_state, &identity, &parent_id).await?;
+        let parent = app_state
+            .token_store
+            .get_token_by_id(&parent_id)
+            .await?
+            .ok_or_else(|| GatewayError::NotFound("parent token not found".into()))?;
+        crate::server::token_lineage::attach_to_parent(&app_state, &parent, &mut payload).await?;
+    }
     let t = app_state
         .token_store
         .create_token(CreateTokenPayload {
@@ -432,27 +784,34 @@ pub async fn toggle_token(
 ) -> Result<Json<serde_json::Value>, GatewayError> {
     let start_time = Utc::now();
     let provided_token = bearer_token(&headers);
-    if let Err(e) = require_superadmin(&headers, &app_state).await {
-        let code = e.status_code().as_u16();
-        log_simple_request(
-            &app_state,
-            start_time,
-            "POST",
-            "/admin/tokens/{id}/toggle",
-            "client_tokens_toggle",
-            None,
-            None,
-            provided_token.as_deref(),
-            code,
-            Some(e.to_string()),
-        )
-        .await;
-        return Err(e);
-    }
+    let identity = match ensure_admin(&headers, &app_state).await {
+        Ok(identity) => identity,
+        Err(e) => {
+            let code = e.status_code().as_u16();
+            log_simple_request(
+                &app_state,
+                start_time,
+                "POST",
+                "/admin/tokens/{id}/toggle",
+                "client_tokens_toggle",
+                None,
+                None,
+                provided_token.as_deref(),
+                code,
+                Some(e.to_string()),
+            )
+            .await;
+            return Err(e);
+        }
+    };
+    ensure_token_in_scope(&app_state, &identity, &id).await?;
     let ok = app_state
         .token_store
         .set_enabled_by_id(&id, payload.enabled)
         .await?;
+    if ok && !payload.enabled {
+        crate::server::token_lineage::cascade_disable(&app_state, &id).await?;
+    }
     if ok {
         log_simple_request(
             &app_state,
@@ -593,24 +952,28 @@ pub async fn delete_token(
 ) -> Result<axum::http::StatusCode, GatewayError> {
     let start_time = Utc::now();
     let provided_token = bearer_token(&headers);
-    if let Err(e) = require_superadmin(&headers, &app_state).await {
-        let code = e.status_code().as_u16();
-        log_simple_request(
-            &app_state,
-            start_time,
-            "DELETE",
-            "/admin/tokens/{id}",
-            "client_tokens_delete",
-            None,
-            None,
-            provided_token.as_deref(),
-            code,
-            Some(e.to_string()),
-        )
-        .await;
-        return Err(e);
-    }
-    let deleted = app_state.token_store.delete_token_by_id(&id).await?;
+    let identity = match ensure_admin(&headers, &app_state).await {
+        Ok(identity) => identity,
+        Err(e) => {
+            let code = e.status_code().as_u16();
+            log_simple_request(
+                &app_state,
+                start_time,
+                "DELETE",
+                "/admin/tokens/{id}",
+                "client_tokens_delete",
+                None,
+                None,
+                provided_token.as_deref(),
+                code,
+                Some(e.to_string()),
+            )
+            .await;
+            return Err(e);
+        }
+    };
+    ensure_token_in_scope(&app_state, &identity, &id).await?;
+    let deleted = crate::server::token_lineage::delete_with_descendants(&app_state, &id).await?;
     if deleted {
         log_simple_request(
             &app_state,
@@ -654,23 +1017,26 @@ pub async fn update_token(
 ) -> Result<Json<ClientTokenOut>, GatewayError> {
     let start_time = Utc::now();
     let provided_token = bearer_token(&headers);
-    if let Err(e) = require_superadmin(&headers, &app_state).await {
-        let code = e.status_code().as_u16();
-        log_simple_request(
-            &app_state,
-            start_time,
-            "PUT",
-            "/admin/tokens/{id}",
-            "client_tokens_update",
-            None,
-            None,
-            provided_token.as_deref(),
-            code,
-            Some(e.to_string()),
-        )
-        .await;
-        return Err(e);
-    }
+    let identity = match ensure_admin(&headers, &app_state).await {
+        Ok(identity) => identity,
+        Err(e) => {
+            let code = e.status_code().as_u16();
+            log_simple_request(
+                &app_state,
+                start_time,
+                "PUT",
+                "/admin/tokens/{id}",
+                "client_tokens_update",
+                None,
+                None,
+                provided_token.as_deref(),
+                code,
+                Some(e.to_string()),
+            )
+            .await;
+            return Err(e);
+        }
+    };
     if payload.id.is_some() {
         return Err(GatewayError::Config("不允许修改 id".into()));
     }
@@ -691,8 +1057,80 @@ pub async fn update_token(
             Some(None) => Some(Some(DEFAULT_ORGANIZATION_ID.to_string())),
         };
     }
+    ensure_token_in_scope(&app_state, &identity, &id).await?;
+    if let (Some(org), Some(Some(requested))) = (
+        identity.organization_scope(),
+        payload.organization_id.as_ref(),
+    ) && requested != org
+    {
+        return Err(GatewayError::Forbidden(
+            "organization admins cannot move tokens to another organization".into(),
+        ));
+    }
+    ensure_privileged_flags_allowed(
+        &identity,
+        &[
+            (
+                "allow_provider_override",
+                payload.allow_provider_override == Some(true),
+            ),
+            ("allow_login_codes", payload.allow_login_codes == Some(true)),
+            (
+                "allow_debug_capture",
+                payload.allow_debug_capture == Some(true),
+            ),
+            ("sandbox", payload.sandbox == Some(true)),
+        ],
+    )?;
+    request_quota::validate_limit("max_requests", payload.max_requests.flatten())?;
+    request_quota::validate_limit(
+        "max_requests_per_day",
+        payload.max_requests_per_day.flatten(),
+    )?;
+    model_concurrency::validate_queue_weight(payload.queue_weight.flatten())?;
     payload.ip_whitelist = normalize_ip_list_patch("ip_whitelist", payload.ip_whitelist)?;
     payload.ip_blacklist = normalize_ip_list_patch("ip_blacklist", payload.ip_blacklist)?;
+    payload.usage_webhook_url = match payload.usage_webhook_url {
+        Some(v) => Some(normalize_usage_webhook_url(v).await?),
+        None => None,
+    };
+    payload.usage_webhook_secret = match payload.usage_webhook_url.as_ref() {
+        // 清空地址时一并作废密钥；重新设置地址时沿用已有密钥，没有则生成
+        Some(None) => Some(None),
+        Some(Some(_)) => {
+            let current = app_state
+                .token_store
+                .get_token_by_id(&id)
+                .await?
+                .ok_or_else(|| GatewayError::NotFound("token not found".into()))?;
+            match current.usage_webhook_secret {
+                Some(_) => None,
+                None => Some(Some(usage_webhooks::generate_secret())),
+            }
+        }
+        None => None,
+    };
+    payload.signing_secret = payload
+        .signing_secret
+        .map(normalize_signing_secret)
+        .transpose()?;
+    if payload.signing_secret.is_some() || payload.require_signature.is_some() {
+        let current = app_state
+            .token_store
+            .get_token_by_id(&id)
+            .await?
+            .ok_or_else(|| GatewayError::NotFound("token not found".into()))?;
+        let next_secret = match payload.signing_secret.as_ref() {
+            Some(v) => v.as_deref(),
+            None => current.signing_secret.as_deref(),
+        };
+        ensure_signature_secret(
+            payload
+                .require_signature
+                .unwrap_or(current.require_signature),
+            next_secret,
+        )?;
+    }
     payload.allowed_models = crate::server::token_model_limits::normalize_model_list_patch(
         "allowed_models",
         payload.allowed_models,
@@ -752,6 +1190,9 @@ pub async fn update_token(
         .await?
     {
         Some(t) => {
+            if !t.enabled {
+                crate::server::token_lineage::cascade_disable(&app_state, &t.id).await?;
+            }
             log_simple_request(
                 &app_state,
                 start_time,
@@ -794,7 +1235,6 @@ mod tests {
     use crate::config::BalanceStrategy;
     use crate::config::settings::{LoadBalancing, LoggingConfig, ServerConfig};
     use crate::logging::DatabaseLogger;
-    use crate::server::login::LoginManager;
     use crate::server::storage_traits::{AdminPublicKeyRecord, LoginStore, TuiSessionRecord};
     use crate::users::CreateUserPayload;
     use axum::http::{HeaderValue, header::AUTHORIZATION};
@@ -840,6 +1280,7 @@ mod tests {
                 enabled: true,
                 created_at: now,
                 last_used_at: None,
+                expires_at: None,
             })
             .await
             .unwrap();
@@ -857,25 +1298,7 @@ mod tests {
             .await
             .unwrap();
 
-        let refresh_token_store = logger.clone();
-        let password_reset_token_store = logger.clone();
-
-        let app_state = Arc::new(AppState {
-            config: settings,
-            load_balancer_state: Arc::new(crate::routing::LoadBalancerState::default()),
-            log_store: logger.clone(),
-            model_cache: logger.clone(),
-            providers: logger.clone(),
-            token_store: logger.clone(),
-            favorites_store: logger.clone(),
-            organizations: logger.clone(),
-            login_manager: Arc::new(LoginManager::new(logger.clone())),
-            user_store: logger.clone(),
-            refresh_token_store,
-            password_reset_token_store,
-            balance_store: logger.clone(),
-            subscription_store: logger.clone(),
-        });
+        let app_state = crate::server::test_harness::app_state(settings, logger.clone());
 
         Harness {
             _dir: dir,
@@ -902,16 +1325,8 @@ mod tests {
             State(h.state.clone()),
             headers.clone(),
             Json(CreateTokenPayload {
-                id: None,
-                user_id: None,
                 name: Some("  my-token  ".into()),
-                token: None,
-                allowed_models: None,
-                model_blacklist: None,
-                max_tokens: None,
                 max_amount: Some(10.0),
-                enabled: true,
-                expires_at: None,
                 remark: Some("  hello  ".into()),
                 organization_id: Some("  org-1  ".into()),
                 ip_whitelist: Some(vec![
@@ -921,6 +1336,7 @@ mod tests {
                     "2001:db8::/32".into(),
                 ]),
                 ip_blacklist: Some(vec![" 2.2.2.2 ".into()]),
+                ..Default::default()
             }),
         )
         .await
@@ -955,9 +1371,15 @@ mod tests {
         assert_eq!(fetched.ip_whitelist, created.ip_whitelist);
         assert_eq!(fetched.ip_blacklist, created.ip_blacklist);
 
-        let Json(listed) = list_tokens(State(h.state.clone()), headers.clone())
-            .await
-            .unwrap();
+        let Json(Listing::All(listed)) = list_tokens(
+            State(h.state.clone()),
+            headers.clone(),
+            Query(PageQuery::default()),
+        )
+        .await
+        .unwrap() else {
+            panic!("unpaged list should return an array");
+        };
         let listed_one = listed
             .into_iter()
             .find(|t| t.id == created.id)
@@ -1023,19 +1445,8 @@ mod tests {
             headers.clone(),
             Json(CreateTokenPayload {
                 id: Some("client-id".into()),
-                user_id: None,
                 name: Some("name".into()),
-                token: None,
-                allowed_models: None,
-                model_blacklist: None,
-                max_tokens: None,
-                max_amount: None,
-                enabled: true,
-                expires_at: None,
-                remark: None,
-                organization_id: None,
-                ip_whitelist: None,
-                ip_blacklist: None,
+                ..Default::default()
             }),
         )
         .await
@@ -1046,20 +1457,8 @@ mod tests {
             State(h.state),
             headers,
             Json(CreateTokenPayload {
-                id: None,
-                user_id: None,
                 name: Some("   ".into()),
-                token: None,
-                allowed_models: None,
-                model_blacklist: None,
-                max_tokens: None,
-                max_amount: None,
-                enabled: true,
-                expires_at: None,
-                remark: None,
-                organization_id: None,
-                ip_whitelist: None,
-                ip_blacklist: None,
+                ..Default::default()
             }),
         )
         .await
@@ -1067,6 +1466,69 @@ mod tests {
         assert!(matches!(err, GatewayError::Config(_)));
     }
 
+    #[tokio::test]
+    async fn usage_webhook_secret_is_generated_independently_of_token() {
+        let h = harness().await;
+        let headers = auth_headers(&h.token);
+
+        let (_, Json(created)) = create_token(
+            State(h.state.clone()),
+            headers.clone(),
+            Json(CreateTokenPayload {
+                name: Some("hooked".into()),
+                usage_webhook_url: Some("https://93.184.216.34/usage".into()),
+                ..Default::default()
+            }),
+        )
+        .await
+        .unwrap();
+        let secret = created.usage_webhook_secret.clone().unwrap();
+        assert!(secret.starts_with("whsec_"));
+        assert_ne!(secret, created.token);
+
+        // 更换地址沿用原密钥
+        let patch: UpdateTokenPayload = serde_json::from_value(serde_json::json!({
+            "usage_webhook_url": "https://93.184.216.34/v2/usage",
+            "usage_webhook_secret": "whsec_client_supplied",
+        }))
+        .unwrap();
+        let Json(updated) = update_token(
+            Path(created.id.clone()),
+            State(h.state.clone()),
+            headers.clone(),
+            Json(patch),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            updated.usage_webhook_secret.as_deref(),
+            Some(secret.as_str())
+        );
+
+        // 清空地址时密钥一并作废，重新设置后签发新密钥
+        let patch: UpdateTokenPayload =
+            serde_json::from_value(serde_json::json!({ "usage_webhook_url": null })).unwrap();
+        let Json(cleared) = update_token(
+            Path(created.id.clone()),
+            State(h.state.clone()),
+            headers.clone(),
+            Json(patch),
+        )
+        .await
+        .unwrap();
+        assert_eq!(cleared.usage_webhook_secret, None);
+        let patch: UpdateTokenPayload = serde_json::from_value(
+            serde_json::json!({ "usage_webhook_url": "https://93.184.216.34/usage" }),
+        )
+        .unwrap();
+        let Json(rearmed) = update_token(Path(created.id), State(h.state), headers, Json(patch))
+            .await
+            .unwrap();
+        let rearmed_secret = rearmed.usage_webhook_secret.unwrap();
+        assert!(rearmed_secret.starts_with("whsec_"));
+        assert_ne!(rearmed_secret, secret);
+    }
+
     #[tokio::test]
     async fn client_tokens_create_validates_user_id_exists_when_provided() {
         let h = harness().await;
@@ -1076,20 +1538,9 @@ mod tests {
             State(h.state.clone()),
             headers.clone(),
             Json(CreateTokenPayload {
-                id: None,
                 user_id: Some("no-such-user".into()),
                 name: Some("name".into()),
-                token: None,
-                allowed_models: None,
-                model_blacklist: None,
-                max_tokens: None,
-                max_amount: None,
-                enabled: true,
-                expires_at: None,
-                remark: None,
-                organization_id: None,
-                ip_whitelist: None,
-                ip_blacklist: None,
+                ..Default::default()
             }),
         )
         .await
@@ -1117,20 +1568,9 @@ mod tests {
             State(h.state.clone()),
             headers,
             Json(CreateTokenPayload {
-                id: None,
                 user_id: Some(user.id.clone()),
                 name: Some("name".into()),
-                token: None,
-                allowed_models: None,
-                model_blacklist: None,
-                max_tokens: None,
-                max_amount: None,
-                enabled: true,
-                expires_at: None,
-                remark: None,
-                organization_id: None,
-                ip_whitelist: None,
-                ip_blacklist: None,
+                ..Default::default()
             }),
         )
         .await
@@ -1166,20 +1606,10 @@ mod tests {
             State(h.state.clone()),
             headers.clone(),
             Json(CreateTokenPayload {
-                id: None,
                 user_id: Some(user.id.clone()),
                 name: Some("name".into()),
-                token: None,
-                allowed_models: None,
-                model_blacklist: None,
-                max_tokens: None,
                 max_amount: Some(10.0),
-                enabled: true,
-                expires_at: None,
-                remark: None,
-                organization_id: None,
-                ip_whitelist: None,
-                ip_blacklist: None,
+                ..Default::default()
             }),
         )
         .await
@@ -1190,20 +1620,9 @@ mod tests {
             State(h.state.clone()),
             headers.clone(),
             Json(CreateTokenPayload {
-                id: None,
                 user_id: Some(user.id.clone()),
                 name: Some("name-ok".into()),
-                token: None,
-                allowed_models: None,
-                model_blacklist: None,
-                max_tokens: None,
-                max_amount: None,
-                enabled: true,
-                expires_at: None,
-                remark: None,
-                organization_id: None,
-                ip_whitelist: None,
-                ip_blacklist: None,
+                ..Default::default()
             }),
         )
         .await
@@ -1224,4 +1643,366 @@ mod tests {
         .unwrap_err();
         assert!(matches!(err, GatewayError::Config(_)));
     }
+
+    fn token_payload(name: &str, organization_id: Option<&str>) -> CreateTokenPayload {
+        serde_json::from_value(serde_json::json!({
+            "name": name,
+            "organization_id": organization_id,
+        }))
+        .unwrap()
+    }
+
+    /// 创建一个被委派管理 `org` 的用户，返回该用户及其 AccessToken 请求头
+    async fn org_admin(h: &Harness, name: &str, org: &str) -> (crate::users::User, HeaderMap) {
+        let user = h
+            .state
+            .user_store
+            .create_user(CreateUserPayload {
+                first_name: None,
+                last_name: None,
+                username: Some(name.into()),
+                email: format!("{}@example.com", name),
+                phone_number: None,
+                password: None,
+                status: crate::users::UserStatus::Active,
+                role: crate::users::UserRole::Admin,
+                is_anonymous: false,
+            })
+            .await
+            .unwrap();
+        h.state
+            .organizations
+            .create_organization(org)
+            .await
+            .unwrap();
+        h.state
+            .organizations
+            .set_organization_admin(org, &user.id)
+            .await
+            .unwrap();
+        let now = Utc::now();
+        let claims = crate::server::handlers::auth::AccessTokenClaims {
+            sub: user.id.clone(),
+            email: user.email.clone(),
+            role: "admin".into(),
+            permissions: Vec::new(),
+            jti: None,
+            exp: (now + Duration::minutes(30)).timestamp(),
+            iat: Some(now.timestamp()),
+            org_id: Some(org.into()),
+        };
+        let jwt = crate::server::handlers::auth::issue_access_token(&claims).unwrap();
+        (user, auth_headers(&jwt))
+    }
+
+    #[tokio::test]
+    async fn organization_admin_only_manages_own_organization_tokens() {
+        unsafe {
+            std::env::set_var("GW_JWT_SECRET", "testsecret");
+        }
+        let h = harness().await;
+        let admin_headers = auth_headers(&h.token);
+        let (_, Json(foreign)) = create_token(
+            State(h.state.clone()),
+            admin_headers.clone(),
+            Json(token_payload("foreign", Some("org-b"))),
+        )
+        .await
+        .unwrap();
+
+        let (user, org_headers) = org_admin(&h, "org-admin", "org-a").await;
+
+        let (_, Json(own)) = create_token(
+            State(h.state.clone()),
+            org_headers.clone(),
+            Json(token_payload("own", None)),
+        )
+        .await
+        .unwrap();
+        assert_eq!(own.organization_id.as_deref(), Some("org-a"));
+        let err = create_token(
+            State(h.state.clone()),
+            org_headers.clone(),
+            Json(token_payload("escape", Some("org-b"))),
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(err, GatewayError::Forbidden(_)));
+
+        let Json(Listing::Page(listed)) = list_tokens(
+            State(h.state.clone()),
+            org_headers.clone(),
+            Query(PageQuery {
+                limit: Some(10),
+                ..Default::default()
+            }),
+        )
+        .await
+        .unwrap() else {
+            panic!("paged list should return an envelope");
+        };
+        assert_eq!(listed.total, 1);
+        assert_eq!(
+            listed
+                .items
+                .iter()
+                .map(|t| t.id.as_str())
+                .collect::<Vec<_>>(),
+            vec![own.id.as_str()]
+        );
+        let err = delete_token(
+            Path(foreign.id.clone()),
+            State(h.state.clone()),
+            org_headers.clone(),
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(err, GatewayError::NotFound(_)));
+
+        // 撤销委派后旧 AccessToken 立即失效
+        h.state
+            .organizations
+            .remove_organization_admin("org-a", &user.id)
+            .await
+            .unwrap();
+        let err = list_tokens(
+            State(h.state.clone()),
+            org_headers,
+            Query(PageQuery::default()),
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(err, GatewayError::Forbidden(_)));
+    }
+    #[tokio::test]
+    async fn organization_admin_cannot_bind_outsiders_or_grant_privileged_flags() {
+        unsafe {
+            std::env::set_var("GW_JWT_SECRET", "testsecret");
+        }
+        let h = harness().await;
+        let (_, org_headers) = org_admin(&h, "org-a-admin", "org-a").await;
+        let (outsider, _) = org_admin(&h, "org-b-admin", "org-b").await;
+        let create = |v: serde_json::Value| {
+            create_token(
+                State(h.state.clone()),
+                org_headers.clone(),
+                Json(serde_json::from_value(v).unwrap()),
+            )
+        };
+
+        let err = create(serde_json::json!({ "name": "bind", "user_id": outsider.id }))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, GatewayError::Forbidden(_)));
+        for flag in [
+            "allow_provider_override",
+            "allow_login_codes",
+            "allow_debug_capture",
+            "sandbox",
+        ] {
+            let err = create(serde_json::json!({ "name": "flag", flag: true }))
+                .await
+                .unwrap_err();
+            assert!(matches!(err, GatewayError::Forbidden(_)), "{}", flag);
+        }
+
+        // 超级管理员不受限制
+        let (_, Json(t)) = create_token(
+            State(h.state.clone()),
+            auth_headers(&h.token),
+            Json(
+                serde_json::from_value(serde_json::json!({
+                    "name": "root",
+                    "user_id": outsider.id,
+                    "organization_id": "org-a",
+                    "allow_login_codes": true,
+                }))
+                .unwrap(),
+            ),
+        )
+        .await
+        .unwrap();
+        assert!(t.allow_login_codes);
+        // 已持有本组织令牌的用户可被组织管理员绑定
+        let (_, Json(bound)) =
+            create(serde_json::json!({ "name": "bind", "user_id": outsider.id }))
+                .await
+                .unwrap();
+        assert_eq!(bound.user_id.as_deref(), Some(outsider.id.as_str()));
+    }
+
+    #[tokio::test]
+    async fn child_tokens_cascade_and_roll_up_through_hierarchy() {
+        let h = harness().await;
+        let headers = auth_headers(&h.token);
+        let create =
+            |v: serde_json::Value| -> CreateTokenPayload { serde_json::from_value(v).unwrap() };
+
+        let (_, Json(parent)) = create_token(
+            State(h.state.clone()),
+            headers.clone(),
+            Json(create(
+                serde_json::json!({ "name": "reseller", "max_amount": 10.0 }),
+            )),
+        )
+        .await
+        .unwrap();
+        // 分销商用自己的令牌为客户创建子令牌
+        let (_, Json(child)) = super::super::token_children::create_child(
+            State(h.state.clone()),
+            auth_headers(&parent.token),
+            Json(
+                serde_json::from_value(
+                    serde_json::json!({ "name": "customer", "max_amount": 2.0 }),
+                )
+                .unwrap(),
+            ),
+        )
+        .await
+        .unwrap();
+        assert_eq!(child.parent_token_id.as_deref(), Some(parent.id.as_str()));
+        assert_eq!(child.organization_id, parent.organization_id);
+        let err = super::super::token_children::create_child(
+            State(h.state.clone()),
+            auth_headers(&parent.token),
+            Json(serde_json::from_value(serde_json::json!({ "max_amount": 20.0 })).unwrap()),
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(err, GatewayError::Config(_)));
+
+        let (_, Json(grandchild)) = create_token(
+            State(h.state.clone()),
+            headers.clone(),
+            Json(create(
+                serde_json::json!({ "name": "end-user", "parent_token_id": child.id }),
+            )),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            grandchild.parent_token_id.as_deref(),
+            Some(child.id.as_str())
+        );
+
+        h.state
+            .token_store
+            .add_amount_spent(&grandchild.token, 0.5)
+            .await
+            .unwrap();
+        crate::server::token_lineage::roll_up_to_parents(
+            &h.state,
+            &grandchild.token,
+            Some(0.5),
+            Some((10, 20, 30)),
+        )
+        .await;
+        let Json(tree) = get_token_hierarchy(
+            Path(parent.id.clone()),
+            State(h.state.clone()),
+            headers.clone(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(tree.amount_spent, 0.5);
+        assert_eq!(tree.own_amount_spent, 0.0);
+        assert_eq!(tree.total_tokens_spent, 30);
+        assert_eq!(tree.children[0].id, child.id);
+        assert_eq!(tree.children[0].children[0].id, grandchild.id);
+        assert_eq!(tree.children[0].children[0].own_amount_spent, 0.5);
+
+        let _ = toggle_token(
+            Path(parent.id.clone()),
+            State(h.state.clone()),
+            headers.clone(),
+            Json(TogglePayload { enabled: false }),
+        )
+        .await
+        .unwrap();
+        for id in [&child.id, &grandchild.id] {
+            let t = h
+                .state
+                .token_store
+                .get_token_by_id(id)
+                .await
+                .unwrap()
+                .unwrap();
+            assert!(!t.enabled);
+        }
+
+        delete_token(Path(parent.id.clone()), State(h.state.clone()), headers)
+            .await
+            .unwrap();
+        for id in [&parent.id, &child.id, &grandchild.id] {
+            assert!(
+                h.state
+                    .token_store
+                    .get_token_by_id(id)
+                    .await
+                    .unwrap()
+                    .is_none()
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn child_token_requests_require_active_ancestors() {
+        let h = harness().await;
+        let headers = auth_headers(&h.token);
+        let (_, Json(parent)) = create_token(
+            State(h.state.clone()),
+            headers.clone(),
+            Json(serde_json::from_value(serde_json::json!({ "name": "reseller" })).unwrap()),
+        )
+        .await
+        .unwrap();
+        let (_, Json(child)) = create_token(
+            State(h.state.clone()),
+            headers,
+            Json(
+                serde_json::from_value(serde_json::json!({ "parent_token_id": parent.id }))
+                    .unwrap(),
+            ),
+        )
+        .await
+        .unwrap();
+        let child = h
+            .state
+            .token_store
+            .get_token(&child.token)
+            .await
+            .unwrap()
+            .unwrap();
+        crate::server::token_lineage::ensure_ancestors_active(&h.state, &child)
+            .await
+            .unwrap();
+        // 直接改库停用父令牌（不经过级联），子令牌仍应被拒绝
+        h.state
+            .token_store
+            .set_enabled_by_id(&parent.id, false)
+            .await
+            .unwrap();
+        let err = crate::server::token_lineage::ensure_ancestors_active(&h.state, &child)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, GatewayError::Unauthorized(_)));
+    }
+
+    #[test]
+    fn signing_secret_requires_long_visible_ascii() {
+        assert_eq!(
+            super::normalize_signing_secret(Some("  ".into())).unwrap(),
+            None
+        );
+        assert!(super::normalize_signing_secret(Some("short".into())).is_err());
+        assert!(super::normalize_signing_secret(Some(format!("{} x", "a".repeat(32)))).is_err());
+        let secret = "a".repeat(32);
+        assert_eq!(
+            super::normalize_signing_secret(Some(format!(" {secret} "))).unwrap(),
+            Some(secret.clone())
+        );
+        assert!(super::ensure_signature_secret(true, None).is_err());
+        assert!(super::ensure_signature_secret(true, Some(&secret)).is_ok());
+        assert!(super::ensure_signature_secret(false, None).is_ok());
+    }
 }
@@ -0,0 +1,17 @@
+use std::sync::Arc;
+
+use axum::{Json, body::Bytes, extract::State, http::HeaderMap};
+
+use crate::error::GatewayError;
+use crate::server::AppState;
+use crate::server::cluster;
+
+/// 接收其他副本广播的缓存失效事件（cluster_secret 签名）
+pub async fn receive_event(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<serde_json::Value>, GatewayError> {
+    let applied = cluster::receive(&app_state, &headers, &body).await?;
+    Ok(Json(serde_json::json!({ "applied": applied })))
+}
@@ -0,0 +1,651 @@
+use axum::{
+    Json,
+    body::{Body, Bytes},
+    extract::{Path, Query, Request, State},
+    http::{HeaderMap, HeaderValue, StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use futures_util::StreamExt;
+use serde::Deserialize;
+use serde_json::{Map, Value, json};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::Arc;
+
+use super::chat::chat_completions;
+use crate::error::GatewayError;
+use crate::server::AppState;
+use crate::server::chat_request::GatewayChatCompletionRequest;
+
+/// Gemini SDK 的密钥请求头
+const GOOGLE_API_KEY_HEADER: &str = "x-goog-api-key";
+
+/// Gemini SDK 通过 `x-goog-api-key` 请求头或 `?key=` 查询参数传递密钥；
+/// 在签名与父令牌校验之前改写为 `Authorization: Bearer <client-token>`
+pub async fn google_api_key_auth(mut request: Request, next: Next) -> Response {
+    let path = request.uri().path();
+    let path = path.strip_prefix("/api").unwrap_or(path);
+    if path.starts_with("/v1beta/")
+        && !request.headers().contains_key(header::AUTHORIZATION)
+        && let Some(key) = request
+            .headers()
+            .get(GOOGLE_API_KEY_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string)
+            .or_else(|| query_param(request.uri().query(), "key"))
+        && let Ok(bearer) = HeaderValue::from_str(&format!("Bearer {}", key.trim()))
+    {
+        request.headers_mut().insert(header::AUTHORIZATION, bearer);
+    }
+    next.run(request).await
+}
+
+/// 密钥仅含 URL 安全字符，无需百分号解码
+fn query_param(query: Option<&str>, name: &str) -> Option<String> {
+    query?
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(k, _)| *k == name)
+        .map(|(_, v)| v.to_string())
+        .filter(|v| !v.is_empty())
+}
+
+/// 同时接受 camelCase 与 snake_case 字段（Gemini REST 两种写法均合法）
+fn field<'a>(obj: &'a Value, camel: &str, snake: &str) -> Option<&'a Value> {
+    obj.get(camel).or_else(|| obj.get(snake))
+}
+
+fn text_of(parts: &[Value]) -> String {
+    parts
+        .iter()
+        .filter_map(|p| p.get("text").and_then(Value::as_str))
+        .collect::<Vec<_>>()
+        .join("")
+}
+
+/// Gemini schema 的类型名为大写（OBJECT/STRING），转为 JSON Schema 的小写
+fn lowercase_schema_types(schema: &mut Value) {
+    match schema {
+        Value::Object(obj) => {
+            if let Some(Value::String(t)) = obj.get_mut("type") {
+                *t = t.to_lowercase();
+            }
+            for v in obj.values_mut() {
+                lowercase_schema_types(v);
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(lowercase_schema_types),
+        _ => {}
+    }
+}
+
+/// Gemini 没有工具调用 ID：按函数名生成，functionResponse 按出现顺序对应同名调用
+#[derive(Default)]
+struct ToolCallIds {
+    next: usize,
+    pending: HashMap<String, VecDeque<String>>,
+}
+
+impl ToolCallIds {
+    fn call(&mut self, name: &str) -> String {
+        self.next += 1;
+        let id = format!("call_{}_{}", self.next, name);
+        self.pending
+            .entry(name.to_string())
+            .or_default()
+            .push_back(id.clone());
+        id
+    }
+
+    fn response(&mut self, name: &str) -> String {
+        self.pending
+            .get_mut(name)
+            .and_then(VecDeque::pop_front)
+            .unwrap_or_else(|| format!("call_{}", name))
+    }
+}
+
+fn convert_contents(body: &Value) -> Result<Vec<Value>, GatewayError> {
+    let mut messages = Vec::new();
+    if let Some(system) = field(body, "systemInstruction", "system_instruction")
+        .and_then(|s| s.get("parts"))
+        .and_then(Value::as_array)
+    {
+        messages.push(json!({"role": "system", "content": text_of(system)}));
+    }
+    let contents = body
+        .get("contents")
+        .and_then(Value::as_array)
+        .ok_or_else(|| GatewayError::Config("contents is required".into()))?;
+    let mut ids = ToolCallIds::default();
+    for content in contents {
+        let parts = content
+            .get("parts")
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        if content.get("role").and_then(Value::as_str) == Some("model") {
+            let tool_calls: Vec<Value> = parts
+                .iter()
+                .filter_map(|p| field(p, "functionCall", "function_call"))
+                .map(|call| {
+                    let name = call.get("name").and_then(Value::as_str).unwrap_or_default();
+                    let args = call.get("args").cloned().unwrap_or_else(|| json!({}));
+                    json!({
+                        "id": ids.call(name),
+                        "type": "function",
+                        "function": {"name": name, "arguments": args.to_string()}
+                    })
+                })
+                .collect();
+            let mut msg = json!({"role": "assistant", "content": text_of(parts)});
+            if !tool_calls.is_empty() {
+                msg["tool_calls"] = Value::Array(tool_calls);
+            }
+            messages.push(msg);
+            continue;
+        }
+
+        let mut user_parts = Vec::new();
+        for part in parts {
+            if let Some(text) = part.get("text").and_then(Value::as_str) {
+                user_parts.push(json!({"type": "text", "text": text}));
+            } else if let Some(data) = field(part, "inlineData", "inline_data") {
+                let mime = field(data, "mimeType", "mime_type")
+                    .and_then(Value::as_str)
+                    .unwrap_or("image/png");
+                let data = data.get("data").and_then(Value::as_str).unwrap_or_default();
+                user_parts.push(json!({
+                    "type": "image_url",
+                    "image_url": {"url": format!("data:{};base64,{}", mime, data)}
+                }));
+            } else if let Some(resp) = field(part, "functionResponse", "function_response") {
+                let name = resp.get("name").and_then(Value::as_str).unwrap_or_default();
+                let output = resp.get("response").cloned().unwrap_or(Value::Null);
+                messages.push(json!({
+                    "role": "tool",
+                    "tool_call_id": ids.response(name),
+                    "content": output.to_string()
+                }));
+            }
+        }
+        match user_parts.as_slice() {
+            [] => {}
+            [only] if only["type"] == "text" => {
+                messages.push(json!({"role": "user", "content": only["text"]}));
+            }
+            _ => messages.push(json!({"role": "user", "content": user_parts})),
+        }
+    }
+    Ok(messages)
+}
+
+fn convert_tools(body: &Value, req: &mut Map<String, Value>) {
+    let tools: Vec<Value> = body
+        .get("tools")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|t| field(t, "functionDeclarations", "function_declarations"))
+        .filter_map(Value::as_array)
+        .flatten()
+        .map(|decl| {
+            let mut function = decl.clone();
+            if let Some(params) = function.get_mut("parameters") {
+                lowercase_schema_types(params);
+            }
+            json!({"type": "function", "function": function})
+        })
+        .collect();
+    if tools.is_empty() {
+        return;
+    }
+    req.insert("tools".into(), Value::Array(tools));
+    let Some(config) = field(body, "toolConfig", "tool_config")
+        .and_then(|c| field(c, "functionCallingConfig", "function_calling_config"))
+    else {
+        return;
+    };
+    let allowed = field(config, "allowedFunctionNames", "allowed_function_names")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    let choice = match config.get("mode").and_then(Value::as_str) {
+        Some("NONE") => json!("none"),
+        Some("ANY") => match allowed {
+            [name] => json!({"type": "function", "function": {"name": name}}),
+            _ => json!("required"),
+        },
+        _ => json!("auto"),
+    };
+    req.insert("tool_choice".into(), choice);
+}
+
+/// Gemini generateContent 请求转为网关请求
+fn gemini_request_to_gateway(
+    model: &str,
+    body: &Value,
+    stream: bool,
+) -> Result<GatewayChatCompletionRequest, GatewayError> {
+    let mut req = Map::new();
+    req.insert("model".into(), json!(model));
+    req.insert("messages".into(), Value::Array(convert_contents(body)?));
+    if stream {
+        req.insert("stream".into(), json!(true));
+        req.insert("stream_options".into(), json!({"include_usage": true}));
+    }
+    let mut top_k = None;
+    if let Some(config) = field(body, "generationConfig", "generation_config") {
+        for (camel, snake, target) in [
+            ("temperature", "temperature", "temperature"),
+            ("topP", "top_p", "top_p"),
+            ("maxOutputTokens", "max_output_tokens", "max_tokens"),
+            ("stopSequences", "stop_sequences", "stop"),
+            ("candidateCount", "candidate_count", "n"),
+            ("presencePenalty", "presence_penalty", "presence_penalty"),
+            ("frequencyPenalty", "frequency_penalty", "frequency_penalty"),
+            ("seed", "seed", "seed"),
+        ] {
+            if let Some(v) = field(config, camel, snake) {
+                req.insert(target.into(), v.clone());
+            }
+        }
+        if field(config, "responseMimeType", "response_mime_type").and_then(Value::as_str)
+            == Some("application/json")
+        {
+            req.insert("response_format".into(), json!({"type": "json_object"}));
+        }
+        top_k = field(config, "topK", "top_k").and_then(Value::as_u64);
+    }
+    convert_tools(body, &mut req);
+
+    let mut request: GatewayChatCompletionRequest = serde_json::from_value(Value::Object(req))
+        .map_err(|e| GatewayError::Config(format!("invalid generateContent request: {}", e)))?;
+    request.top_k = top_k.map(|k| k as u32);
+    Ok(request)
+}
+
+fn finish_reason(reason: &str) -> &'static str {
+    match reason {
+        "length" => "MAX_TOKENS",
+        "content_filter" => "SAFETY",
+        _ => "STOP",
+    }
+}
+
+fn usage_metadata(usage: &Value) -> Value {
+    json!({
+        "promptTokenCount": usage.get("prompt_tokens").cloned().unwrap_or(json!(0)),
+        "candidatesTokenCount": usage.get("completion_tokens").cloned().unwrap_or(json!(0)),
+        "totalTokenCount": usage.get("total_tokens").cloned().unwrap_or(json!(0)),
+    })
+}
+
+fn function_call_part(name: &str, arguments: &str) -> Value {
+    let args = serde_json::from_str::<Value>(arguments).unwrap_or_else(|_| json!({}));
+    json!({"functionCall": {"name": name, "args": args}})
+}
+
+/// 网关（OpenAI 格式）完整响应转为 GenerateContentResponse
+fn gateway_response_to_gemini(v: &Value) -> Value {
+    let candidates: Vec<Value> = v
+        .get("choices")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .map(|choice| {
+            let msg = choice.get("message").cloned().unwrap_or(Value::Null);
+            let mut parts = Vec::new();
+            if let Some(text) = msg.get("content").and_then(Value::as_str)
+                && !text.is_empty()
+            {
+                parts.push(json!({"text": text}));
+            }
+            for call in msg
+                .get("tool_calls")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+            {
+                parts.push(function_call_part(
+                    call.pointer("/function/name")
+                        .and_then(Value::as_str)
+                        .unwrap_or_default(),
+                    call.pointer("/function/arguments")
+                        .and_then(Value::as_str)
+                        .unwrap_or("{}"),
+                ));
+            }
+            let mut candidate = json!({
+                "content": {"role": "model", "parts": parts},
+                "index": choice.get("index").cloned().unwrap_or(json!(0)),
+            });
+            if let Some(reason) = choice.get("finish_reason").and_then(Value::as_str) {
+                candidate["finishReason"] = json!(finish_reason(reason));
+            }
+            candidate
+        })
+        .collect();
+    let mut out = json!({"candidates": candidates});
+    if let Some(usage) = v.get("usage").filter(|u| !u.is_null()) {
+        out["usageMetadata"] = usage_metadata(usage);
+    }
+    if let Some(model) = v.get("model") {
+        out["modelVersion"] = model.clone();
+    }
+    if let Some(id) = v.get("id") {
+        out["responseId"] = id.clone();
+    }
+    out
+}
+
+/// 流式转换状态：工具调用参数按分片到达，累积到 finish_reason 时一次性输出
+#[derive(Default)]
+struct GeminiStream {
+    tool_calls: BTreeMap<u64, (String, String)>,
+}
+
+impl GeminiStream {
+    /// 单个 OpenAI chunk 转为 GenerateContentResponse；无可输出内容时返回 None
+    fn convert_chunk(&mut self, chunk: &Value) -> Option<Value> {
+        let mut candidates = Vec::new();
+        for choice in chunk
+            .get("choices")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+        {
+            let delta = choice.get("delta").cloned().unwrap_or(Value::Null);
+            let mut parts = Vec::new();
+            if let Some(text) = delta.get("content").and_then(Value::as_str)
+                && !text.is_empty()
+            {
+                parts.push(json!({"text": text}));
+            }
+            for call in delta
+                .get("tool_calls")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+            {
+                let index = call.get("index").and_then(Value::as_u64).unwrap_or(0);
+                let entry = self.tool_calls.entry(index).or_default();
+                if let Some(name) = call.pointer("/function/name").and_then(Value::as_str) {
+                    entry.0.push_str(name);
+                }
+                if let Some(args) = call.pointer("/function/arguments").and_then(Value::as_str) {
+                    entry.1.push_str(args);
+                }
+            }
+            let reason = choice.get("finish_reason").and_then(Value::as_str);
+            if reason.is_some() {
+                for (name, args) in std::mem::take(&mut self.tool_calls).into_values() {
+                    parts.push(function_call_part(&name, &args));
+                }
+            }
+            if parts.is_empty() && reason.is_none() {
+                continue;
+            }
+            let mut candidate = json!({
+                "content": {"role": "model", "parts": parts},
+                "index": choice.get("index").cloned().unwrap_or(json!(0)),
+            });
+            if let Some(reason) = reason {
+                candidate["finishReason"] = json!(finish_reason(reason));
+            }
+            candidates.push(candidate);
+        }
+        let usage = chunk.get("usage").filter(|u| !u.is_null());
+        if candidates.is_empty() && usage.is_none() {
+            return None;
+        }
+        let mut out = json!({"candidates": candidates});
+        if let Some(usage) = usage {
+            out["usageMetadata"] = usage_metadata(usage);
+        }
+        if let Some(model) = chunk.get("model") {
+            out["modelVersion"] = model.clone();
+        }
+        Some(out)
+    }
+
+    fn convert_frame(&mut self, frame: &str) -> Vec<Value> {
+        frame
+            .lines()
+            .filter_map(|line| line.strip_prefix("data:"))
+            .filter_map(|data| serde_json::from_str::<Value>(data.trim()).ok())
+            .filter_map(|chunk| self.convert_chunk(&chunk))
+            .collect()
+    }
+}
+
+/// 网关 SSE 流转为 Gemini 流：`alt=sse` 时输出 SSE（无 [DONE]），否则输出流式 JSON 数组
+fn gemini_stream_response(response: Response, sse: bool) -> Response {
+    let (mut parts, body) = response.into_parts();
+    let mut pending: Vec<u8> = Vec::new();
+    let mut state = GeminiStream::default();
+    let mut first = true;
+    let converted = body.into_data_stream().map(move |chunk| {
+        chunk.map(|bytes| {
+            pending.extend_from_slice(&bytes);
+            let mut out = String::new();
+            while let Some(pos) = pending.windows(2).position(|w| w == b"\n\n") {
+                let frame: Vec<u8> = pending.drain(..pos + 2).collect();
+                for v in state.convert_frame(&String::from_utf8_lossy(&frame[..pos])) {
+                    if sse {
+                        out.push_str(&format!("data: {}\r\n\r\n", v));
+                    } else {
+                        out.push_str(if first { "[" } else { ",\r\n" });
+                        out.push_str(&v.to_string());
+                    }
+                    first = false;
+                }
+            }
+            Bytes::from(out)
+        })
+    });
+    if sse {
+        return Response::from_parts(parts, Body::from_stream(converted));
+    }
+    parts.headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/json"),
+    );
+    // 首个元素输出 "["；没有任何元素时在结尾补齐，保证输出为合法数组
+    let opened = Arc::new(std::sync::atomic::AtomicBool::new(false));
+    let seen = opened.clone();
+    let converted = converted.map(move |chunk| {
+        if chunk.as_ref().is_ok_and(|b| !b.is_empty()) {
+            seen.store(true, std::sync::atomic::Ordering::Relaxed);
+        }
+        chunk
+    });
+    let closing = futures_util::stream::once(async move {
+        let close = if opened.load(std::sync::atomic::Ordering::Relaxed) {
+            "]"
+        } else {
+            "[]"
+        };
+        Ok(Bytes::from_static(close.as_bytes()))
+    });
+    Response::from_parts(parts, Body::from_stream(converted.chain(closing)))
+}
+
+fn google_status(code: StatusCode) -> &'static str {
+    match code.as_u16() {
+        400 => "INVALID_ARGUMENT",
+        401 => "UNAUTHENTICATED",
+        402 | 403 => "PERMISSION_DENIED",
+        404 => "NOT_FOUND",
+        409 => "ABORTED",
+        429 => "RESOURCE_EXHAUSTED",
+        503 => "UNAVAILABLE",
+        504 => "DEADLINE_EXCEEDED",
+        _ => "INTERNAL",
+    }
+}
+
+/// 非流式响应与错误体转为 Gemini 格式；错误为 `{error: {code, message, status}}`
+async fn gemini_json_response(response: Response) -> Response {
+    let (mut parts, body) = response.into_parts();
+    let bytes = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(e) => return GatewayError::Config(e.to_string()).into_response(),
+    };
+    let Ok(v) = serde_json::from_slice::<Value>(&bytes) else {
+        return Response::from_parts(parts, Body::from(bytes));
+    };
+    let out = if parts.status.is_success() {
+        gateway_response_to_gemini(&v)
+    } else {
+        json!({"error": {
+            "code": parts.status.as_u16(),
+            "message": v.get("message").cloned().unwrap_or_else(|| json!(v.to_string())),
+            "status": google_status(parts.status),
+        }})
+    };
+    parts.headers.remove(header::CONTENT_LENGTH);
+    Response::from_parts(parts, Body::from(out.to_string()))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GeminiQuery {
+    /// `sse` 时流式响应使用 SSE
+    pub alt: Option<String>,
+}
+
+/// Gemini 原生协议入口 `/v1beta/models/{model}:generateContent` 与 `:streamGenerateContent`：
+/// 请求转为网关格式后走与 `/v1/chat/completions` 相同的链路（可路由到任意供应商），
+/// 响应再转回 GenerateContentResponse。密钥为 Client Token，可通过 `x-goog-api-key`、
+/// `?key=` 或 Bearer 传递
+pub async fn generate_content(
+    State(app_state): State<Arc<AppState>>,
+    Path(model_action): Path<String>,
+    Query(query): Query<GeminiQuery>,
+    headers: HeaderMap,
+    Json(body): Json<Value>,
+) -> Response {
+    let (model, stream) = match model_action.rsplit_once(':') {
+        Some((model, "generateContent")) => (model, false),
+        Some((model, "streamGenerateContent")) => (model, true),
+        _ => {
+            let err = GatewayError::NotFound(format!("unsupported method '{}'", model_action));
+            return gemini_json_response(err.into_response()).await;
+        }
+    };
+    let gateway_req = match gemini_request_to_gateway(model, &body, stream) {
+        Ok(req) => req,
+        Err(e) => return gemini_json_response(e.into_response()).await,
+    };
+    let response = chat_completions(State(app_state), headers, Json(gateway_req))
+        .await
+        .unwrap_or_else(IntoResponse::into_response);
+    if stream && response.status().is_success() {
+        gemini_stream_response(response, query.alt.as_deref() == Some("sse"))
+    } else {
+        gemini_json_response(response).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generate_content_request_maps_to_chat_request() {
+        let req = gemini_request_to_gateway(
+            "gemini-1.5-pro",
+            &json!({
+                "systemInstruction": {"parts": [{"text": "be brief"}]},
+                "contents": [
+                    {"role": "user", "parts": [
+                        {"text": "weather?"},
+                        {"inlineData": {"mimeType": "image/jpeg", "data": "AAAA"}}
+                    ]},
+                    {"role": "model", "parts": [
+                        {"functionCall": {"name": "get_weather", "args": {"city": "SF"}}}
+                    ]},
+                    {"role": "user", "parts": [
+                        {"functionResponse": {"name": "get_weather", "response": {"temp": 20}}}
+                    ]}
+                ],
+                "generationConfig": {"temperature": 0.2, "maxOutputTokens": 64, "topK": 5},
+                "tools": [{"functionDeclarations": [{
+                    "name": "get_weather",
+                    "parameters": {"type": "OBJECT", "properties": {"city": {"type": "STRING"}}}
+                }]}],
+                "toolConfig": {"functionCallingConfig": {"mode": "ANY"}}
+            }),
+            false,
+        )
+        .unwrap();
+        assert_eq!(req.top_k, Some(5));
+        let v = serde_json::to_value(&req.request).unwrap();
+        assert_eq!(v["model"], json!("gemini-1.5-pro"));
+        assert_eq!(v["max_tokens"], json!(64));
+        assert_eq!(v["tool_choice"], json!("required"));
+        assert_eq!(
+            v.pointer("/tools/0/function/parameters/properties/city/type"),
+            Some(&json!("string"))
+        );
+        let messages = v["messages"].as_array().unwrap();
+        assert_eq!(messages[0]["role"], json!("system"));
+        assert_eq!(
+            messages[1].pointer("/content/1/image_url/url"),
+            Some(&json!("data:image/jpeg;base64,AAAA"))
+        );
+        let call_id = messages[2].pointer("/tool_calls/0/id").unwrap();
+        assert_eq!(messages[3]["role"], json!("tool"));
+        assert_eq!(&messages[3]["tool_call_id"], call_id);
+    }
+
+    #[test]
+    fn chat_response_maps_to_generate_content_response() {
+        let out = gateway_response_to_gemini(&json!({
+            "id": "chatcmpl-1",
+            "model": "m1",
+            "choices": [{"index": 0, "finish_reason": "length", "message": {
+                "role": "assistant",
+                "content": "hi",
+                "tool_calls": [{"id": "c1", "type": "function",
+                    "function": {"name": "f", "arguments": "{\"a\":1}"}}]
+            }}],
+            "usage": {"prompt_tokens": 3, "completion_tokens": 2, "total_tokens": 5}
+        }));
+        assert_eq!(out["candidates"][0]["finishReason"], json!("MAX_TOKENS"));
+        assert_eq!(
+            out["candidates"][0]["content"]["parts"],
+            json!([{"text": "hi"}, {"functionCall": {"name": "f", "args": {"a": 1}}}])
+        );
+        assert_eq!(out["usageMetadata"]["totalTokenCount"], json!(5));
+    }
+
+    #[tokio::test]
+    async fn stream_accumulates_tool_calls_and_emits_json_array() {
+        let upstream = concat!(
+            "data: {\"choices\":[{\"index\":0,\"delta\":{\"content\":\"he\"}}]}\n\n",
+            "data: {\"choices\":[{\"index\":0,\"delta\":{\"tool_calls\":[{\"index\":0,\"function\":{\"name\":\"f\",\"arguments\":\"{\\\"a\\\"\"}}]}}]}\n\n",
+            "data: {\"choices\":[{\"index\":0,\"delta\":{\"tool_calls\":[{\"index\":0,\"function\":{\"arguments\":\":1}\"}}]},\"finish_reason\":\"tool_calls\"}]}\n\n",
+            "data: [DONE]\n\n",
+        );
+        let response = Response::new(Body::from(upstream));
+        let body = axum::body::to_bytes(
+            gemini_stream_response(response, false).into_body(),
+            usize::MAX,
+        )
+        .await
+        .unwrap();
+        let v: Value = serde_json::from_slice(&body).unwrap();
+        let items = v.as_array().unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!(
+            items[0]["candidates"][0]["content"]["parts"][0]["text"],
+            json!("he")
+        );
+        assert_eq!(
+            items[1]["candidates"][0]["content"]["parts"][0],
+            json!({"functionCall": {"name": "f", "args": {"a": 1}}})
+        );
+        assert_eq!(items[1]["candidates"][0]["finishReason"], json!("STOP"));
+    }
+}
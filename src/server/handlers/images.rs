@@ -0,0 +1,59 @@
+use axum::{Json, extract::State, http::HeaderMap};
+use chrono::Utc;
+use serde_json::Value;
+use std::sync::Arc;
+
+use crate::error::GatewayError;
+use crate::logging::types::REQ_TYPE_IMAGE_GENERATION;
+use crate::server::AppState;
+use crate::server::image_generation::{self, IMAGE_GENERATIONS_PATH, ImageRequest};
+use crate::server::request_logging::log_simple_request;
+use crate::server::util::bearer_token;
+
+/// OpenAI 兼容的图片生成入口：按张计价，调用上游前按令牌金额预算预估拦截
+pub async fn image_generations(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(body): Json<Value>,
+) -> Result<Json<Value>, GatewayError> {
+    let start_time = Utc::now();
+    let requested_model = body
+        .get("model")
+        .and_then(Value::as_str)
+        .map(str::to_string);
+    let client_token = bearer_token(&headers);
+    let admitted = async {
+        let token = client_token
+            .as_deref()
+            .ok_or_else(|| GatewayError::Config("missing bearer token".into()))?;
+        let request = ImageRequest::parse(body)?;
+        image_generation::admit(&app_state, token, request).await
+    }
+    .await;
+    let admitted = match admitted {
+        Ok(admitted) => admitted,
+        Err(e) => {
+            let client_token_id = client_token
+                .as_deref()
+                .map(crate::admin::client_token_id_for_token);
+            log_simple_request(
+                &app_state,
+                start_time,
+                "POST",
+                IMAGE_GENERATIONS_PATH,
+                REQ_TYPE_IMAGE_GENERATION,
+                requested_model,
+                None,
+                client_token_id.as_deref(),
+                e.status_code().as_u16(),
+                Some(e.to_string()),
+            )
+            .await;
+            return Err(e);
+        }
+    };
+    let token = client_token.unwrap_or_default();
+    image_generation::execute(&app_state, start_time, &token, admitted)
+        .await
+        .map(Json)
+}
@@ -13,6 +13,7 @@ use crate::error::GatewayError;
 use crate::logging::types::RequestLog;
 use crate::logging::types::RequestLogDetailRecord;
 use crate::server::AppState;
+use crate::server::log_fields::{FieldSelection, LogFields, Projected};
 use crate::server::model_display::format_model_display_name;
 
 const MAX_LOG_LIMIT: usize = 1000;
@@ -31,6 +32,9 @@ pub struct MyLogsQuery {
     pub status: Option<String>, // success | failed | error
     #[serde(default)]
     pub filter: Option<String>,
+    /// 逗号分隔的输出字段；为空时返回全部字段
+    #[serde(default)]
+    pub fields: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -59,10 +63,37 @@ pub struct MyRequestLogEntry {
     pub replayable: bool,
 }
 
+impl LogFields for MyRequestLogEntry {
+    const FIELDS: &'static [&'static str] = &[
+        "id",
+        "timestamp",
+        "method",
+        "path",
+        "request_type",
+        "requested_model",
+        "effective_model",
+        "requested_model_display",
+        "effective_model_display",
+        "model_display",
+        "client_token_id",
+        "client_token_name",
+        "amount_spent",
+        "amount_spent_currency",
+        "status_code",
+        "response_time_ms",
+        "prompt_tokens",
+        "completion_tokens",
+        "total_tokens",
+        "error_message",
+        "success",
+        "replayable",
+    ];
+}
+
 #[derive(Debug, Serialize)]
 pub struct MyRequestLogsResponse {
     pub total: usize,
-    pub data: Vec<MyRequestLogEntry>,
+    pub data: Projected<MyRequestLogEntry>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub next_cursor: Option<i64>,
 }
@@ -197,6 +228,18 @@ pub async fn list_my_request_logs(
         .limit
         .unwrap_or(DEFAULT_LOG_LIMIT)
         .clamp(1, MAX_LOG_LIMIT);
+    let fields = FieldSelection::parse::<MyRequestLogEntry>(query.fields.as_deref())?;
+    // 归属判断与筛选条件依赖的列总是读取
+    let mut columns = fields.columns();
+    columns.extend([
+        "user_id",
+        "client_token",
+        "request_type",
+        "status_code",
+        "requested_model",
+        "effective_model",
+        "model",
+    ]);
 
     let tokens = app_state.token_store.list_tokens_by_user(&user_id).await?;
     let token_ids: HashSet<String> = tokens.iter().map(|t| t.id.clone()).collect();
@@ -231,7 +274,7 @@ pub async fn list_my_request_logs(
     loop {
         let batch = app_state
             .log_store
-            .get_recent_logs_with_cursor(page_size, next)
+            .get_logs_with_columns(page_size, next, None, &columns)
             .await
             .map_err(GatewayError::Db)?;
         if batch.is_empty() {
@@ -274,7 +317,7 @@ pub async fn list_my_request_logs(
 
     let next_cursor = out.last().and_then(|l| l.id).filter(|_| out.len() == limit);
     let mut replayable_by_id: HashMap<i64, bool> = HashMap::new();
-    for log in &out {
+    for log in out.iter().filter(|_| fields.includes("replayable")) {
         let replayable = if log.request_type.starts_with("chat_") {
             if let Some(log_id) = log.id {
                 let detail = app_state
@@ -355,7 +398,7 @@ pub async fn list_my_request_logs(
 
     Ok(Json(MyRequestLogsResponse {
         total: data.len(),
-        data,
+        data: Projected::new(data, fields),
         next_cursor,
     }))
 }
@@ -12,6 +12,7 @@ use crate::error::GatewayError;
 use crate::logging::types::{REQ_TYPE_CHAT_ONCE, REQ_TYPE_CHAT_STREAM};
 use crate::server::AppState;
 use crate::server::request_logging::log_simple_request;
+use crate::server::request_quota::RequestQuotaStatus;
 use crate::server::util::{bearer_token, token_for_log};
 
 #[derive(Debug, Deserialize)]
@@ -105,6 +106,7 @@ pub async fn my_token_balance(
             "total_tokens_spent": t.total_tokens_spent,
             "usage_count": usage_count,
             "max_tokens": t.max_tokens,
+            "requests": RequestQuotaStatus::new(&t, app_state.request_quota.usage(&t.id, start_time)),
         })));
     }
 
@@ -132,6 +134,7 @@ pub async fn my_token_balance(
                 "total_tokens_spent": t.total_tokens_spent,
                 "usage_count": usage_counts.get(&t.id).copied().unwrap_or(0),
                 "max_tokens": t.max_tokens,
+                "requests": RequestQuotaStatus::new(&t, app_state.request_quota.usage(&t.id, start_time)),
                 "enabled": t.enabled,
                 "expires_at": t.expires_at.as_ref().map(crate::logging::time::to_iso8601_utc_string),
                 "created_at": crate::logging::time::to_iso8601_utc_string(&t.created_at),
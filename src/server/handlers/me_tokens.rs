@@ -227,20 +227,14 @@ pub async fn create_my_token(
     let created = app_state
         .token_store
         .create_token(CreateTokenPayload {
-            id: None,
             user_id: Some(claims.sub.clone()),
             name,
-            token: None,
             allowed_models,
             model_blacklist,
             max_tokens: payload.max_tokens,
-            max_amount: None,
             enabled: payload.enabled,
             expires_at: payload.expires_at,
-            remark: None,
-            organization_id: None,
-            ip_whitelist: None,
-            ip_blacklist: None,
+            ..Default::default()
         })
         .await?;
 
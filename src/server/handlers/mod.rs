@@ -6,13 +6,35 @@ use std::sync::Arc;
 
 use crate::server::AppState;
 
+mod admin_audio_prices;
+mod admin_compare;
+mod admin_db;
+mod admin_drain;
+mod admin_fault_injection;
+mod admin_image_prices;
+mod admin_jobs;
 mod admin_logs;
+mod admin_maintenance;
 mod admin_metrics;
 mod admin_model_settings;
+mod admin_notifications;
+mod admin_param_policies;
 mod admin_prices;
+mod admin_provider_budgets;
 mod admin_provider_key_stats;
+mod admin_reports;
+mod admin_server_logs;
+mod admin_settings;
+mod admin_speech_prices;
+mod admin_statements;
 mod admin_subscription;
+mod admin_tasks;
+mod admin_token_test;
+mod admin_usage_webhooks;
 mod admin_users;
+mod admin_watermark;
+pub(crate) mod anthropic_ingress;
+mod audio;
 pub(crate) mod auth;
 mod auth_jwt;
 mod auth_keys;
@@ -23,6 +45,9 @@ mod auth_tui_admin;
 mod cache;
 mod chat;
 mod client_tokens;
+mod cluster_events;
+pub(crate) mod gemini_ingress;
+mod images;
 mod me_balance;
 mod me_logs;
 mod me_token_info;
@@ -31,24 +56,50 @@ mod model_prices;
 mod model_redirects;
 mod models;
 mod organizations;
+mod pricing_catalog;
+mod prometheus;
+mod provider_capabilities;
+mod provider_diagnose;
 mod provider_keys;
 mod provider_model_test;
-mod provider_models_list;
+pub(crate) mod provider_models_list;
+mod provider_tls;
 mod providers;
 mod subscription;
+mod text_completions;
+mod token_auto_disable;
+mod token_children;
+mod token_exchange;
 mod token_info;
+mod token_login_codes;
+mod token_notifications;
+mod token_rotation;
+mod zhipu_ingress;
 
 pub fn routes() -> Router<Arc<AppState>> {
     Router::new()
         // Auth for Web
         .route("/auth/tui/challenge", post(auth_tui::challenge))
         .route("/auth/tui/verify", post(auth_tui::verify))
+        // Cluster peer events (HMAC signed with cluster_secret)
+        .route(
+            crate::server::cluster::EVENTS_PATH,
+            post(cluster_events::receive_event),
+        )
         // Admin key management
         .route(
             "/auth/keys",
             get(auth_keys::list_keys).post(auth_keys::add_key),
         )
         .route("/auth/keys/{fingerprint}", delete(auth_keys::delete_key))
+        .route(
+            "/auth/keys/{fingerprint}/extend",
+            post(auth_keys::extend_key),
+        )
+        .route(
+            "/auth/keys/{fingerprint}/retire",
+            post(auth_keys::retire_key),
+        )
         // TUI sessions management
         .route("/auth/tui/sessions", get(auth_tui_admin::list_tui_sessions))
         .route(
@@ -74,15 +125,73 @@ pub fn routes() -> Router<Arc<AppState>> {
             post(auth_password_reset::reset_password),
         )
         .route("/auth/code/redeem", post(auth_login::redeem_code))
+        .route(
+            "/auth/user-code/redeem",
+            post(token_login_codes::redeem_user_login_code),
+        )
         .route("/auth/session", get(auth_login::get_session))
         .route("/auth/logout", post(auth_login::logout))
+        .route("/auth/password/login", post(auth_login::password_login))
+        .route(
+            "/auth/password/totp/activate",
+            post(auth_login::activate_totp),
+        )
+        .route(
+            "/auth/password/mfa/{user_id}/reset",
+            post(auth_login::reset_password_mfa),
+        )
+        .route("/auth/sessions", get(auth_login::list_web_sessions))
+        .route(
+            "/auth/sessions/{id}",
+            delete(auth_login::revoke_web_session),
+        )
         .route("/v1/chat/completions", post(chat::chat_completions))
+        // 智谱原生协议入口；挂载在 /api 下即为智谱的 /api/paas/v4 路径
+        .route(
+            "/paas/v4/chat/completions",
+            post(zhipu_ingress::zhipu_chat_completions),
+        )
+        // Gemini 原生协议入口：{model}:generateContent / {model}:streamGenerateContent
+        .route(
+            "/v1beta/models/{model_action}",
+            post(gemini_ingress::generate_content),
+        )
+        // Anthropic 原生协议入口：Messages API
+        .route(
+            anthropic_ingress::MESSAGES_PATH,
+            post(anthropic_ingress::messages),
+        )
+        // 旧版文本补全：prompt 转为聊天消息，或原样转发给支持 /v1/completions 的上游
+        .route("/v1/completions", post(text_completions::completions))
+        // 图片生成：按 image_prices 中的单价按张计费
+        .route("/v1/images/generations", post(images::image_generations))
+        // 语音转写：multipart 表单转发，按 audio_prices 中的每分钟单价按时长计费
+        .route(
+            crate::server::audio_transcription::AUDIO_TRANSCRIPTIONS_PATH,
+            post(audio::transcriptions).layer(axum::extract::DefaultBodyLimit::max(
+                crate::server::audio_transcription::MAX_AUDIO_UPLOAD_BYTES,
+            )),
+        )
+        // 语音合成：音频流式返回，按 speech_prices 中的每百万字符单价按输入字符数计费
+        .route(
+            crate::server::audio_speech::AUDIO_SPEECH_PATH,
+            post(audio::speech),
+        )
+        .route(
+            "/v1/chat/completions/plan",
+            post(chat::chat_completions_plan),
+        )
+        .route("/status", get(admin_maintenance::gateway_status))
         .route("/v1/models", get(models::list_models))
         .route("/models/{provider}", get(models::list_provider_models))
         .route(
             "/models/{provider}/cache",
             post(cache::update_provider_cache).delete(cache::delete_provider_cache),
         )
+        .route(
+            "/models/{provider}/cache/reconcile",
+            post(cache::reconcile_provider_cache),
+        )
         .route("/admin/models/cache", get(cache::list_cached_models))
         .route(
             "/admin/models/enabled",
@@ -112,6 +221,18 @@ pub fn routes() -> Router<Arc<AppState>> {
             "/providers/{provider}/keys/weight",
             axum::routing::patch(provider_keys::patch_provider_key_weight),
         )
+        .route(
+            "/providers/{provider}/keys/limits",
+            axum::routing::patch(provider_keys::patch_provider_key_limits),
+        )
+        .route(
+            "/providers/{provider}/keys/openai-headers",
+            axum::routing::patch(provider_keys::patch_provider_key_openai_headers),
+        )
+        .route(
+            "/providers/{provider}/keys/labels",
+            axum::routing::patch(provider_keys::patch_provider_key_labels),
+        )
         .route(
             "/providers/{provider}/keys/batch",
             post(provider_keys::add_provider_keys_batch)
@@ -135,6 +256,14 @@ pub fn routes() -> Router<Arc<AppState>> {
             "/providers/collections",
             get(providers::list_provider_collections).post(providers::create_provider_collection),
         )
+        .route(
+            "/providers/collections/{collection}",
+            get(providers::get_provider_collection),
+        )
+        .route(
+            "/providers/collections/{collection}/providers",
+            post(providers::assign_providers_to_collection),
+        )
         .route(
             "/providers",
             get(providers::list_providers).post(providers::create_provider),
@@ -171,14 +300,184 @@ pub fn routes() -> Router<Arc<AppState>> {
             "/admin/tokens/{id}/toggle",
             post(client_tokens::toggle_token),
         )
+        .route(
+            "/admin/tokens/{id}/hierarchy",
+            get(client_tokens::get_token_hierarchy),
+        )
         .route(
             "/admin/tokens/{id}/favorite",
             post(client_tokens::set_token_favorite),
         )
+        .route(
+            "/admin/tokens/{id}/auto-disable",
+            get(token_auto_disable::get_auto_disable).put(token_auto_disable::set_auto_disable),
+        )
+        .route(
+            "/admin/tokens/{id}/rotation",
+            get(token_rotation::get_rotation).put(token_rotation::set_rotation),
+        )
+        .route(
+            "/admin/tokens/{id}/notifications",
+            get(token_notifications::get_token_notifications)
+                .put(token_notifications::set_token_notifications),
+        )
+        .route(
+            "/admin/tokens/{id}/test-request",
+            post(admin_token_test::token_test_request),
+        )
+        .route("/admin/compare", post(admin_compare::admin_compare))
+        .route(
+            "/admin/server-logs",
+            get(admin_server_logs::list_server_logs),
+        )
+        .route("/admin/tasks", get(admin_tasks::list_tasks))
+        .route("/admin/tasks/{id}", delete(admin_tasks::cancel_task))
+        .route("/admin/jobs", get(admin_jobs::list_jobs))
+        .route("/admin/jobs/{name}", get(admin_jobs::get_job))
+        .route("/admin/jobs/{name}/run", post(admin_jobs::run_job))
+        .route("/admin/jobs/{name}/pause", post(admin_jobs::pause_job))
+        .route("/admin/jobs/{name}/resume", post(admin_jobs::resume_job))
+        .route("/admin/db/status", get(admin_db::get_db_status))
+        .route(
+            "/admin/watermarks/trace",
+            post(admin_watermark::trace_watermark),
+        )
+        .route("/admin/db/migration", get(admin_db::get_migration_status))
+        .route("/admin/db/migration/check", post(admin_db::check_migration))
+        .route(
+            "/admin/db/migration/switch",
+            post(admin_db::switch_migration_primary),
+        )
+        .route("/admin/drain-status", get(admin_drain::get_drain_status))
+        .route("/admin/drain-status/force", post(admin_drain::force_drain))
+        .route(
+            "/admin/settings",
+            get(admin_settings::get_settings).put(admin_settings::put_settings),
+        )
+        .route(
+            "/admin/branding",
+            get(admin_settings::get_branding).put(admin_settings::put_branding),
+        )
+        .route(
+            "/admin/logging/level",
+            get(admin_settings::get_log_levels).put(admin_settings::put_log_levels),
+        )
+        .route(
+            "/admin/fault-injection",
+            get(admin_fault_injection::get_fault_injection)
+                .put(admin_fault_injection::put_fault_injection),
+        )
+        .route(
+            "/admin/param-policies",
+            get(admin_param_policies::list_param_policies)
+                .post(admin_param_policies::create_param_policy),
+        )
+        .route(
+            "/admin/param-policies/{id}",
+            put(admin_param_policies::update_param_policy)
+                .delete(admin_param_policies::delete_param_policy),
+        )
+        .route(
+            "/admin/reports",
+            get(admin_reports::list_reports).post(admin_reports::create_report),
+        )
+        .route(
+            "/admin/reports/{id}",
+            put(admin_reports::update_report).delete(admin_reports::delete_report),
+        )
+        .route("/admin/statements", get(admin_statements::list_statements))
+        .route(
+            "/admin/statements/generate",
+            post(admin_statements::generate_statements),
+        )
+        .route(
+            "/admin/statements/{id}",
+            get(admin_statements::get_statement),
+        )
+        .route(
+            "/admin/reports/{id}/test-send",
+            post(admin_reports::test_send_report),
+        )
+        .route(
+            "/admin/provider-budgets",
+            get(admin_provider_budgets::list_provider_budgets),
+        )
+        .route(
+            "/admin/maintenance-windows",
+            get(admin_maintenance::list_maintenance_windows)
+                .post(admin_maintenance::create_maintenance_window),
+        )
+        .route(
+            "/admin/maintenance-windows/{id}",
+            delete(admin_maintenance::delete_maintenance_window),
+        )
+        .route(
+            "/admin/provider-budgets/{provider}",
+            put(admin_provider_budgets::set_provider_budget)
+                .delete(admin_provider_budgets::delete_provider_budget),
+        )
+        .route(
+            "/admin/image-prices",
+            get(admin_image_prices::list_image_prices),
+        )
+        .route(
+            "/admin/image-prices/{provider}",
+            put(admin_image_prices::set_image_price).delete(admin_image_prices::delete_image_price),
+        )
+        .route(
+            "/admin/audio-prices",
+            get(admin_audio_prices::list_audio_prices),
+        )
+        .route(
+            "/admin/audio-prices/{provider}",
+            put(admin_audio_prices::set_audio_price).delete(admin_audio_prices::delete_audio_price),
+        )
+        .route(
+            "/admin/speech-prices",
+            get(admin_speech_prices::list_speech_prices),
+        )
+        .route(
+            "/admin/speech-prices/{provider}",
+            put(admin_speech_prices::set_speech_price)
+                .delete(admin_speech_prices::delete_speech_price),
+        )
+        .route(
+            "/admin/notifications",
+            get(admin_notifications::list_notifications),
+        )
+        .route(
+            "/admin/notifications/unread-count",
+            get(admin_notifications::unread_count),
+        )
+        .route(
+            "/admin/notifications/read",
+            post(admin_notifications::mark_read),
+        )
+        .route(
+            "/admin/usage-webhooks/dead-letters",
+            get(admin_usage_webhooks::list_dead_letters),
+        )
+        .route(
+            "/admin/usage-webhooks/dead-letters/{id}",
+            delete(admin_usage_webhooks::delete_dead_letter),
+        )
+        .route(
+            "/admin/usage-webhooks/dead-letters/{id}/retry",
+            post(admin_usage_webhooks::retry_dead_letter),
+        )
         .route(
             "/admin/organizations",
             get(organizations::list_organizations).post(organizations::create_organization),
         )
+        .route(
+            "/admin/organizations/{id}/admins",
+            get(organizations::list_organization_admins),
+        )
+        .route(
+            "/admin/organizations/{id}/admins/{user_id}",
+            put(organizations::add_organization_admin)
+                .delete(organizations::remove_organization_admin),
+        )
         .route(
             "/admin/users",
             get(admin_users::list_users).post(admin_users::create_user),
@@ -190,6 +489,10 @@ pub fn routes() -> Router<Arc<AppState>> {
                 .put(admin_users::update_user)
                 .delete(admin_users::delete_user),
         )
+        .route(
+            "/admin/users/{id}/budget",
+            get(admin_users::get_user_budget).put(admin_users::put_user_budget),
+        )
         .route(
             "/admin/subscription/plans/draft",
             get(admin_subscription::get_draft_plans).put(admin_subscription::put_draft_plans),
@@ -202,10 +505,18 @@ pub fn routes() -> Router<Arc<AppState>> {
             "/admin/model-prices",
             post(admin_prices::upsert_model_price).get(admin_prices::list_model_prices),
         )
+        .route(
+            "/admin/pricing/estimate",
+            post(admin_prices::estimate_model_price),
+        )
         .route(
             "/admin/model-prices/sync",
             post(admin_prices::sync_model_prices),
         )
+        .route(
+            "/admin/model-prices/bulk",
+            post(admin_prices::bulk_update_model_prices),
+        )
         .route(
             "/admin/model-prices/{provider}/{model}",
             get(admin_prices::get_model_price),
@@ -214,8 +525,14 @@ pub fn routes() -> Router<Arc<AppState>> {
             "/admin/model-prices/{provider}/{model}/sync",
             post(admin_prices::sync_single_model_price),
         )
+        .route("/metrics", get(prometheus::metrics))
         .route("/admin/metrics/summary", get(admin_metrics::summary))
         .route("/admin/metrics/series", get(admin_metrics::series))
+        .route("/admin/metrics/egress", get(admin_metrics::egress))
+        .route(
+            "/admin/metrics/semantic-cache",
+            get(admin_metrics::semantic_cache),
+        )
         .route(
             "/admin/metrics/models-distribution",
             get(admin_metrics::models_distribution),
@@ -232,11 +549,36 @@ pub fn routes() -> Router<Arc<AppState>> {
             "/admin/metrics/series-models",
             get(admin_metrics::series_models),
         )
+        .route(
+            "/admin/metrics/request-deviations",
+            get(admin_metrics::request_deviations),
+        )
+        .route("/admin/metrics/heatmap", get(admin_metrics::heatmap))
         .route(
             "/admin/providers/{provider}/keys/stats",
             get(admin_provider_key_stats::provider_key_stats),
         )
+        .route(
+            "/admin/providers/{provider}/diagnose",
+            post(provider_diagnose::diagnose_provider),
+        )
+        .route(
+            "/admin/providers/{provider}/tls-certificate",
+            get(provider_tls::get_provider_tls_certificate),
+        )
+        .route(
+            "/admin/providers/{provider}/capabilities",
+            get(provider_capabilities::get_provider_capabilities),
+        )
+        .route(
+            "/admin/providers/{provider}/ops",
+            get(admin_logs::list_provider_ops),
+        )
         .route("/admin/logs/requests", get(admin_logs::list_request_logs))
+        .route(
+            "/admin/logs/{id}/transcript",
+            get(admin_logs::get_request_transcript),
+        )
         .route(
             "/admin/requests/{id}",
             get(crate::server::request_lab::get_admin_request_detail),
@@ -262,6 +604,11 @@ pub fn routes() -> Router<Arc<AppState>> {
                 .delete(me_tokens::delete_my_token),
         )
         .route("/me/tokens/{id}/toggle", post(me_tokens::toggle_my_token))
+        .route(
+            "/me/tokens/{id}/notifications",
+            get(token_notifications::get_my_token_notifications)
+                .put(token_notifications::set_my_token_notifications),
+        )
         .route("/me/token/balance", get(me_token_info::my_token_balance))
         .route("/me/token/usage", get(me_token_info::my_token_usage))
         .route("/me/logs/requests", get(me_logs::list_my_request_logs))
@@ -322,6 +669,30 @@ pub fn routes() -> Router<Arc<AppState>> {
         )
         .route("/subscription/plans", get(subscription::list_plans))
         .route("/subscription/purchase", post(subscription::purchase_plan))
+        .route("/v1/pricing", get(pricing_catalog::pricing_catalog))
         .route("/v1/token/balance", get(token_info::token_balance))
+        .route("/v1/user/balance", get(token_info::user_balance))
         .route("/v1/token/usage", get(token_info::token_usage))
+        .route("/v1/token/exchange", post(token_exchange::exchange_token))
+        .route("/v1/token/rotate", post(token_rotation::rotate_token))
+        .route(
+            "/v1/token/login-codes",
+            post(token_login_codes::create_user_login_code),
+        )
+        .route(
+            "/v1/token/children",
+            get(token_children::list_children).post(token_children::create_child),
+        )
+        .route(
+            "/v1/token/children/{id}",
+            delete(token_children::delete_child),
+        )
+        .route(
+            "/v1/token/children/{id}/toggle",
+            post(token_children::toggle_child),
+        )
+        .route(
+            "/v1/token/hierarchy/usage",
+            get(token_children::hierarchy_usage),
+        )
 }
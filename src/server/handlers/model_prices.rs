@@ -9,7 +9,10 @@ use std::sync::Arc;
 
 use crate::error::GatewayError;
 use crate::server::AppState;
-use crate::server::pricing::{ModelPriceView, model_price_view_from_record};
+use crate::server::pagination::{Listing, Page, PageQuery};
+use crate::server::pricing::{
+    MODEL_PRICE_SORT_FIELDS, ModelPriceView, model_price_view_from_record,
+};
 use crate::server::request_logging::log_simple_request;
 use crate::server::util::bearer_token;
 
@@ -22,18 +25,30 @@ pub async fn list_model_prices(
     State(app_state): State<Arc<AppState>>,
     headers: HeaderMap,
     Query(q): Query<ListQuery>,
-) -> Result<Json<Vec<ModelPriceView>>, GatewayError> {
+    Query(page): Query<PageQuery>,
+) -> Result<Json<Listing<ModelPriceView>>, GatewayError> {
     let start_time = Utc::now();
     let provided = bearer_token(&headers);
-    let items = app_state
-        .log_store
-        .list_model_prices(q.provider.as_deref())
-        .await
-        .map_err(GatewayError::Db)?;
-    let out: Vec<_> = items
-        .into_iter()
-        .map(model_price_view_from_record)
-        .collect();
+    let out = match page.resolve(&MODEL_PRICE_SORT_FIELDS)? {
+        Some(page) => {
+            let (items, total) = app_state
+                .log_store
+                .list_model_prices_page(q.provider.as_deref(), &page)
+                .await
+                .map_err(GatewayError::Db)?;
+            Listing::Page(Page::new(items, total, &page).map(model_price_view_from_record))
+        }
+        None => Listing::All(
+            app_state
+                .log_store
+                .list_model_prices(q.provider.as_deref())
+                .await
+                .map_err(GatewayError::Db)?
+                .into_iter()
+                .map(model_price_view_from_record)
+                .collect(),
+        ),
+    };
     log_simple_request(
         &app_state,
         start_time,
@@ -85,8 +100,8 @@ mod tests {
         .await
         .unwrap();
 
-        let state = Arc::new(crate::server::AppState {
-            config: crate::config::Settings {
+        let state = crate::server::test_harness::app_state(
+            crate::config::Settings {
                 load_balancing: crate::config::settings::LoadBalancing {
                     strategy: crate::config::BalanceStrategy::FirstAvailable,
                 },
@@ -96,32 +111,26 @@ mod tests {
                     ..Default::default()
                 },
             },
-            load_balancer_state: Arc::new(crate::routing::LoadBalancerState::default()),
-            log_store: Arc::new(logger.clone()),
-            model_cache: Arc::new(logger.clone()),
-            providers: Arc::new(logger.clone()),
-            token_store: Arc::new(logger.clone()),
-            favorites_store: Arc::new(logger.clone()),
-            organizations: Arc::new(logger.clone()),
-            login_manager: Arc::new(crate::server::login::LoginManager::new(Arc::new(
-                logger.clone(),
-            ))),
-            user_store: Arc::new(logger.clone()),
-            refresh_token_store: Arc::new(logger.clone()),
-            password_reset_token_store: Arc::new(logger.clone()),
-            balance_store: Arc::new(logger.clone()),
-            subscription_store: Arc::new(logger),
-        });
+            Arc::new(logger.clone()),
+        );
 
-        let Json(items) = list_model_prices(
+        let Json(Listing::Page(page)) = list_model_prices(
             State(state),
             HeaderMap::new(),
             Query(ListQuery {
                 provider: Some("p1".into()),
             }),
+            Query(PageQuery {
+                limit: Some(10),
+                ..Default::default()
+            }),
         )
         .await
-        .unwrap();
+        .unwrap() else {
+            panic!("paged list should return an envelope");
+        };
+        assert_eq!(page.total, 1);
+        let items = page.items;
 
         assert_eq!(items.len(), 1);
         assert_eq!(items[0].source, Some(ModelPriceSource::Auto));
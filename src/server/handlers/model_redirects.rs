@@ -93,33 +93,37 @@ pub async fn list_model_redirects(
 ) -> Result<Response, GatewayError> {
     let start_time = Utc::now();
     let provided_token = bearer_token(&headers);
-    if let Err(e) = require_superadmin(&headers, &app_state).await {
-        let _ = app_state
-            .log_store
-            .log_provider_op(ProviderOpLog {
-                id: None,
-                timestamp: start_time,
-                operation: REQ_TYPE_PROVIDER_MODEL_REDIRECTS_LIST.to_string(),
-                provider: Some(provider_name.clone()),
-                details: Some(e.to_string()),
-            })
+    let identity = match require_superadmin(&headers, &app_state).await {
+        Ok(identity) => identity,
+        Err(e) => {
+            let _ = app_state
+                .log_store
+                .log_provider_op(ProviderOpLog {
+                    id: None,
+                    timestamp: start_time,
+                    operation: REQ_TYPE_PROVIDER_MODEL_REDIRECTS_LIST.to_string(),
+                    provider: Some(provider_name.clone()),
+                    details: Some(e.to_string()),
+                    actor: None,
+                })
+                .await;
+            let code = e.status_code().as_u16();
+            log_simple_request(
+                &app_state,
+                start_time,
+                "GET",
+                &format!("/providers/{}/model-redirects", provider_name),
+                REQ_TYPE_PROVIDER_MODEL_REDIRECTS_LIST,
+                None,
+                Some(provider_name),
+                provided_token.as_deref(),
+                code,
+                Some("auth failed".into()),
+            )
             .await;
-        let code = e.status_code().as_u16();
-        log_simple_request(
-            &app_state,
-            start_time,
-            "GET",
-            &format!("/providers/{}/model-redirects", provider_name),
-            REQ_TYPE_PROVIDER_MODEL_REDIRECTS_LIST,
-            None,
-            Some(provider_name),
-            provided_token.as_deref(),
-            code,
-            Some("auth failed".into()),
-        )
-        .await;
-        return Err(e);
-    }
+            return Err(e);
+        }
+    };
 
     if !app_state
         .providers
@@ -148,6 +152,7 @@ pub async fn list_model_redirects(
             operation: REQ_TYPE_PROVIDER_MODEL_REDIRECTS_LIST.to_string(),
             provider: Some(provider_name.clone()),
             details: Some(serde_json::json!({"count": redirects.len()}).to_string()),
+            actor: Some(identity.actor()),
         })
         .await;
     log_simple_request(
@@ -175,33 +180,37 @@ pub async fn replace_model_redirects(
 ) -> Result<Response, GatewayError> {
     let start_time = Utc::now();
     let provided_token = bearer_token(&headers);
-    if let Err(e) = require_superadmin(&headers, &app_state).await {
-        let _ = app_state
-            .log_store
-            .log_provider_op(ProviderOpLog {
-                id: None,
-                timestamp: start_time,
-                operation: REQ_TYPE_PROVIDER_MODEL_REDIRECTS_SET.to_string(),
-                provider: Some(provider_name.clone()),
-                details: Some(e.to_string()),
-            })
+    let identity = match require_superadmin(&headers, &app_state).await {
+        Ok(identity) => identity,
+        Err(e) => {
+            let _ = app_state
+                .log_store
+                .log_provider_op(ProviderOpLog {
+                    id: None,
+                    timestamp: start_time,
+                    operation: REQ_TYPE_PROVIDER_MODEL_REDIRECTS_SET.to_string(),
+                    provider: Some(provider_name.clone()),
+                    details: Some(e.to_string()),
+                    actor: None,
+                })
+                .await;
+            let code = e.status_code().as_u16();
+            log_simple_request(
+                &app_state,
+                start_time,
+                "PUT",
+                &format!("/providers/{}/model-redirects", provider_name),
+                REQ_TYPE_PROVIDER_MODEL_REDIRECTS_SET,
+                None,
+                Some(provider_name),
+                provided_token.as_deref(),
+                code,
+                Some("auth failed".into()),
+            )
             .await;
-        let code = e.status_code().as_u16();
-        log_simple_request(
-            &app_state,
-            start_time,
-            "PUT",
-            &format!("/providers/{}/model-redirects", provider_name),
-            REQ_TYPE_PROVIDER_MODEL_REDIRECTS_SET,
-            None,
-            Some(provider_name),
-            provided_token.as_deref(),
-            code,
-            Some("auth failed".into()),
-        )
-        .await;
-        return Err(e);
-    }
+            return Err(e);
+        }
+    };
 
     if !app_state
         .providers
@@ -237,6 +246,7 @@ pub async fn replace_model_redirects(
             operation: REQ_TYPE_PROVIDER_MODEL_REDIRECTS_SET.to_string(),
             provider: Some(provider_name.clone()),
             details: Some(serde_json::json!({"count": pairs.len()}).to_string()),
+            actor: Some(identity.actor()),
         })
         .await;
     log_simple_request(
@@ -268,33 +278,37 @@ pub async fn delete_model_redirect(
 ) -> Result<Response, GatewayError> {
     let start_time = Utc::now();
     let provided_token = bearer_token(&headers);
-    if let Err(e) = require_superadmin(&headers, &app_state).await {
-        let _ = app_state
-            .log_store
-            .log_provider_op(ProviderOpLog {
-                id: None,
-                timestamp: start_time,
-                operation: REQ_TYPE_PROVIDER_MODEL_REDIRECTS_DELETE.to_string(),
-                provider: Some(provider_name.clone()),
-                details: Some(e.to_string()),
-            })
+    let identity = match require_superadmin(&headers, &app_state).await {
+        Ok(identity) => identity,
+        Err(e) => {
+            let _ = app_state
+                .log_store
+                .log_provider_op(ProviderOpLog {
+                    id: None,
+                    timestamp: start_time,
+                    operation: REQ_TYPE_PROVIDER_MODEL_REDIRECTS_DELETE.to_string(),
+                    provider: Some(provider_name.clone()),
+                    details: Some(e.to_string()),
+                    actor: None,
+                })
+                .await;
+            let code = e.status_code().as_u16();
+            log_simple_request(
+                &app_state,
+                start_time,
+                "DELETE",
+                &format!("/providers/{}/model-redirects", provider_name),
+                REQ_TYPE_PROVIDER_MODEL_REDIRECTS_DELETE,
+                None,
+                Some(provider_name),
+                provided_token.as_deref(),
+                code,
+                Some("auth failed".into()),
+            )
             .await;
-        let code = e.status_code().as_u16();
-        log_simple_request(
-            &app_state,
-            start_time,
-            "DELETE",
-            &format!("/providers/{}/model-redirects", provider_name),
-            REQ_TYPE_PROVIDER_MODEL_REDIRECTS_DELETE,
-            None,
-            Some(provider_name),
-            provided_token.as_deref(),
-            code,
-            Some("auth failed".into()),
-        )
-        .await;
-        return Err(e);
-    }
+            return Err(e);
+        }
+    };
 
     if !app_state
         .providers
@@ -329,6 +343,7 @@ pub async fn delete_model_redirect(
             details: Some(
                 serde_json::json!({"source_model": source_model, "deleted": deleted}).to_string(),
             ),
+            actor: Some(identity.actor()),
         })
         .await;
     log_simple_request(
@@ -16,7 +16,7 @@ use crate::providers::openai::ModelListResponse;
 use crate::server::AppState;
 use crate::server::model_cache::{get_cached_models_all, get_cached_models_for_provider};
 use crate::server::model_display::{format_model_display_name, format_provider_model_display_name};
-use crate::server::model_helpers::fetch_provider_models;
+use crate::server::model_helpers::fetch_provider_models_cached;
 use crate::server::pricing::derive_model_price_view;
 use crate::server::request_logging::log_simple_request;
 use crate::server::util::{bearer_token, token_for_log};
@@ -613,6 +613,9 @@ pub async fn list_my_models(
 pub(super) struct ProviderModelsQuery {
     #[serde(default)]
     refresh: Option<bool>,
+    /// 跳过上游模型列表的短期缓存，强制重新拉取（隐含 refresh）
+    #[serde(default)]
+    force_refresh: Option<bool>,
 }
 
 pub async fn list_provider_models(
@@ -681,9 +684,11 @@ pub async fn list_provider_models(
 
     // GET 不再执行任何缓存变更。
     // - 无 refresh：仅返回缓存
-    // - refresh=true：拉取上游并返回，但不落库
+    // - refresh=true：拉取上游并返回，但不落库（上游结果短期缓存，见 fetch_provider_models_cached）
+    // - force_refresh=true：跳过上游缓存强制拉取
+    let force_refresh = params.force_refresh == Some(true);
 
-    if params.refresh != Some(true) {
+    if params.refresh != Some(true) && !force_refresh {
         let cached_models = get_cached_models_for_provider(&app_state, &provider_name).await?;
         let disabled = app_state
             .log_store
@@ -792,6 +797,7 @@ pub async fn list_provider_models(
         .map_err(GatewayError::Db)?
         .first()
         .cloned()
+        .or_else(|| provider.api_type.allows_keyless().then(String::new))
     {
         Some(k) => k,
         None => {
@@ -815,26 +821,27 @@ pub async fn list_provider_models(
         }
     };
 
-    let mut upstream_models = match fetch_provider_models(&provider, &api_key).await {
-        Ok(models) => models,
-        Err(e) => {
-            let code = e.status_code().as_u16();
-            log_simple_request(
-                &app_state,
-                start_time,
-                "GET",
-                &full_path,
-                REQ_TYPE_PROVIDER_MODELS_LIST,
-                None,
-                Some(provider_name.clone()),
-                provided_token.as_deref(),
-                code,
-                Some(e.to_string()),
-            )
-            .await;
-            return Err(e);
-        }
-    };
+    let (mut upstream_models, cache_status) =
+        match fetch_provider_models_cached(&provider, &api_key, force_refresh).await {
+            Ok(fetched) => fetched,
+            Err(e) => {
+                let code = e.status_code().as_u16();
+                log_simple_request(
+                    &app_state,
+                    start_time,
+                    "GET",
+                    &full_path,
+                    REQ_TYPE_PROVIDER_MODELS_LIST,
+                    None,
+                    Some(provider_name.clone()),
+                    provided_token.as_deref(),
+                    code,
+                    Some(e.to_string()),
+                )
+                .await;
+                return Err(e);
+            }
+        };
     let provider_map = std::iter::once((provider.name.clone(), provider.clone())).collect();
     for model in &mut upstream_models {
         model.display_name = Some(format_provider_model_display_name(
@@ -858,10 +865,14 @@ pub async fn list_provider_models(
     )
     .await;
 
-    let resp = Json(ModelListResponse {
+    let mut resp = Json(ModelListResponse {
         object: "list".into(),
         data: upstream_models,
     })
     .into_response();
+    resp.headers_mut().insert(
+        "x-models-cache",
+        axum::http::HeaderValue::from_static(cache_status.as_str()),
+    );
     Ok(resp)
 }
@@ -1,4 +1,8 @@
-use axum::{Json, extract::State, http::HeaderMap};
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::HeaderMap,
+};
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
@@ -8,6 +12,7 @@ use crate::error::GatewayError;
 use crate::server::AppState;
 use crate::server::request_logging::log_simple_request;
 use crate::server::util::{bearer_token, token_for_log};
+use crate::users::UserRole;
 
 #[derive(Debug, Serialize, PartialEq, Eq)]
 pub struct OrganizationOut {
@@ -139,3 +144,205 @@ pub async fn create_organization(
         }),
     ))
 }
+
+#[derive(Debug, Serialize)]
+pub struct OrganizationAdminsOut {
+    pub organization_id: String,
+    pub user_ids: Vec<String>,
+}
+
+async fn reject_unless_superadmin(
+    app_state: &AppState,
+    headers: &HeaderMap,
+    start_time: chrono::DateTime<Utc>,
+    method: &str,
+    request_type: &str,
+) -> Result<(), GatewayError> {
+    if let Err(e) = require_superadmin(headers, app_state).await {
+        let code = e.status_code().as_u16();
+        log_simple_request(
+            app_state,
+            start_time,
+            method,
+            "/admin/organizations/{id}/admins",
+            request_type,
+            None,
+            None,
+            bearer_token(headers).as_deref(),
+            code,
+            Some(e.to_string()),
+        )
+        .await;
+        return Err(e);
+    }
+    Ok(())
+}
+
+async fn ensure_organization_exists(
+    app_state: &AppState,
+    organization_id: &str,
+) -> Result<(), GatewayError> {
+    let exists = app_state
+        .organizations
+        .list_organizations()
+        .await
+        .map_err(GatewayError::Db)?
+        .iter()
+        .any(|o| o == organization_id);
+    if !exists {
+        return Err(GatewayError::NotFound("organization not found".into()));
+    }
+    Ok(())
+}
+
+pub async fn list_organization_admins(
+    Path(id): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<OrganizationAdminsOut>, GatewayError> {
+    let start_time = Utc::now();
+    reject_unless_superadmin(
+        &app_state,
+        &headers,
+        start_time,
+        "GET",
+        "organization_admins_list",
+    )
+    .await?;
+    let organization_id = normalize_organization_id(&id)?;
+    let user_ids = app_state
+        .organizations
+        .list_organization_admins(&organization_id)
+        .await
+        .map_err(GatewayError::Db)?;
+    log_simple_request(
+        &app_state,
+        start_time,
+        "GET",
+        "/admin/organizations/{id}/admins",
+        "organization_admins_list",
+        None,
+        None,
+        token_for_log(bearer_token(&headers).as_deref()),
+        200,
+        None,
+    )
+    .await;
+    Ok(Json(OrganizationAdminsOut {
+        organization_id,
+        user_ids,
+    }))
+}
+
+/// 委派用户管理该组织：之后该用户登录获得的 AccessToken 携带 org_id，
+/// 可通过令牌管理与请求日志接口管理/查看本组织数据。每个用户最多委派一个组织。
+pub async fn add_organization_admin(
+    Path((id, user_id)): Path<(String, String)>,
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<OrganizationAdminsOut>, GatewayError> {
+    let start_time = Utc::now();
+    reject_unless_superadmin(
+        &app_state,
+        &headers,
+        start_time,
+        "PUT",
+        "organization_admins_add",
+    )
+    .await?;
+    let organization_id = normalize_organization_id(&id)?;
+    let result = async {
+        ensure_organization_exists(&app_state, &organization_id).await?;
+        let user = app_state
+            .user_store
+            .get_user(user_id.trim())
+            .await?
+            .ok_or_else(|| GatewayError::NotFound("user not found".into()))?;
+        if matches!(user.role, UserRole::Superadmin) {
+            return Err(GatewayError::Config(
+                "超级管理员无需委派组织管理权限".into(),
+            ));
+        }
+        app_state
+            .organizations
+            .set_organization_admin(&organization_id, &user.id)
+            .await
+            .map_err(GatewayError::Db)?;
+        app_state
+            .organizations
+            .list_organization_admins(&organization_id)
+            .await
+            .map_err(GatewayError::Db)
+    }
+    .await;
+    let (code, err) = match &result {
+        Ok(_) => (200, None),
+        Err(e) => (e.status_code().as_u16(), Some(e.to_string())),
+    };
+    log_simple_request(
+        &app_state,
+        start_time,
+        "PUT",
+        "/admin/organizations/{id}/admins",
+        "organization_admins_add",
+        None,
+        None,
+        token_for_log(bearer_token(&headers).as_deref()),
+        code,
+        err,
+    )
+    .await;
+    result.map(|user_ids| {
+        Json(OrganizationAdminsOut {
+            organization_id,
+            user_ids,
+        })
+    })
+}
+
+pub async fn remove_organization_admin(
+    Path((id, user_id)): Path<(String, String)>,
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<axum::http::StatusCode, GatewayError> {
+    let start_time = Utc::now();
+    reject_unless_superadmin(
+        &app_state,
+        &headers,
+        start_time,
+        "DELETE",
+        "organization_admins_remove",
+    )
+    .await?;
+    let organization_id = normalize_organization_id(&id)?;
+    let removed = app_state
+        .organizations
+        .remove_organization_admin(&organization_id, user_id.trim())
+        .await
+        .map_err(GatewayError::Db)?;
+    let (code, err) = if removed {
+        (204, None)
+    } else {
+        (404, Some("organization admin not found".to_string()))
+    };
+    log_simple_request(
+        &app_state,
+        start_time,
+        "DELETE",
+        "/admin/organizations/{id}/admins",
+        "organization_admins_remove",
+        None,
+        None,
+        token_for_log(bearer_token(&headers).as_deref()),
+        code,
+        err,
+    )
+    .await;
+    if removed {
+        Ok(axum::http::StatusCode::NO_CONTENT)
+    } else {
+        Err(GatewayError::NotFound(
+            "organization admin not found".into(),
+        ))
+    }
+}
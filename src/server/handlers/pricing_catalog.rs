@@ -0,0 +1,228 @@
+use axum::{Json, extract::State, http::HeaderMap};
+use chrono::Utc;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
+
+use super::auth::ensure_client_token;
+use crate::error::GatewayError;
+use crate::logging::types::REQ_TYPE_PRICING_CATALOG;
+use crate::logging::{ModelPriceRecord, ModelPriceStatus};
+use crate::server::AppState;
+use crate::server::model_cache::get_cached_models_all;
+use crate::server::model_types::model_types_for_response;
+use crate::server::request_logging::log_simple_request;
+use crate::server::token_model_limits::enforce_model_allowed_for_token;
+use crate::server::util::bearer_token;
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct CatalogEntry {
+    /// 隐藏供应商时为模型名，否则为 `{provider}/{model}`
+    pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    pub model: String,
+    pub input_price_per_million: f64,
+    pub output_price_per_million: f64,
+    pub currency: Option<String>,
+    pub model_type: Option<String>,
+    pub model_types: Option<Vec<String>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PricingCatalog {
+    pub object: &'static str,
+    pub data: Vec<CatalogEntry>,
+}
+
+fn marked_up(price: f64, markup: f64) -> f64 {
+    (price * markup * 1_000_000.0).round() / 1_000_000.0
+}
+
+/// 由可访问模型（`provider/model`）与成本价生成对外价目；无价格的模型不展示。
+/// 隐藏供应商时同名模型合并为一条，取较高价格，避免对外报价低于实际成本
+pub(crate) fn build_catalog(
+    models: &[String],
+    prices: &HashMap<(String, String), ModelPriceRecord>,
+    markup: f64,
+    hide_providers: bool,
+) -> Vec<CatalogEntry> {
+    let mut out: BTreeMap<String, CatalogEntry> = BTreeMap::new();
+    for full_id in models {
+        let Some((provider, model)) = full_id.split_once('/') else {
+            continue;
+        };
+        let Some(price) = prices.get(&(provider.to_string(), model.to_string())) else {
+            continue;
+        };
+        if matches!(price.status, ModelPriceStatus::Missing) {
+            continue;
+        }
+        let (model_type, model_types) = model_types_for_response(price.model_type.as_deref());
+        let entry = CatalogEntry {
+            id: if hide_providers {
+                model.to_string()
+            } else {
+                full_id.clone()
+            },
+            provider: (!hide_providers).then(|| provider.to_string()),
+            model: model.to_string(),
+            input_price_per_million: marked_up(price.prompt_price_per_million, markup),
+            output_price_per_million: marked_up(price.completion_price_per_million, markup),
+            currency: price.currency.clone(),
+            model_type,
+            model_types,
+        };
+        match out.get_mut(&entry.id) {
+            Some(existing) => {
+                existing.input_price_per_million = existing
+                    .input_price_per_million
+                    .max(entry.input_price_per_million);
+                existing.output_price_per_million = existing
+                    .output_price_per_million
+                    .max(entry.output_price_per_million);
+            }
+            None => {
+                out.insert(entry.id.clone(), entry);
+            }
+        }
+    }
+    out.into_values().collect()
+}
+
+/// 面向下游产品的价目表：仅包含调用令牌可访问的模型，价格为成本价乘以配置的倍率
+pub async fn pricing_catalog(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<PricingCatalog>, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    let result: Result<PricingCatalog, GatewayError> = async {
+        let tok = ensure_client_token(&headers, &app_state).await?;
+        let token = app_state
+            .token_store
+            .get_token(&tok)
+            .await?
+            .ok_or_else(|| GatewayError::Unauthorized("invalid token".into()))?;
+        let enabled_providers: HashSet<String> = app_state
+            .providers
+            .list_providers()
+            .await
+            .map_err(GatewayError::Db)?
+            .into_iter()
+            .filter(|p| p.enabled)
+            .map(|p| p.name)
+            .collect();
+        let disabled_models: HashSet<String> = app_state
+            .log_store
+            .list_model_enabled(None)
+            .await
+            .map_err(GatewayError::Db)?
+            .into_iter()
+            .filter(|(_, _, enabled)| !*enabled)
+            .map(|(provider, model, _)| format!("{}/{}", provider, model))
+            .collect();
+        let models: Vec<String> = get_cached_models_all(&app_state)
+            .await
+            .map_err(GatewayError::Db)?
+            .into_iter()
+            .map(|m| m.id)
+            .filter(|id| {
+                id.split_once('/')
+                    .is_some_and(|(p, _)| enabled_providers.contains(p))
+                    && !disabled_models.contains(id)
+                    && enforce_model_allowed_for_token(&token, id).is_ok()
+            })
+            .collect();
+        let prices: HashMap<(String, String), ModelPriceRecord> = app_state
+            .log_store
+            .list_model_prices(None)
+            .await
+            .map_err(GatewayError::Db)?
+            .into_iter()
+            .map(crate::server::pricing::normalize_model_price_record)
+            .map(|r| ((r.provider.clone(), r.model.clone()), r))
+            .collect();
+        let settings = app_state.runtime_settings.snapshot();
+        Ok(PricingCatalog {
+            object: "list",
+            data: build_catalog(
+                &models,
+                &prices,
+                settings.pricing_markup.unwrap_or(1.0),
+                settings.pricing_hide_providers,
+            ),
+        })
+    }
+    .await;
+    let (code, err) = match &result {
+        Ok(_) => (200, None),
+        Err(e) => (e.status_code().as_u16(), Some(e.to_string())),
+    };
+    log_simple_request(
+        &app_state,
+        start_time,
+        "GET",
+        "/v1/pricing",
+        REQ_TYPE_PRICING_CATALOG,
+        None,
+        None,
+        provided_token.as_deref(),
+        code,
+        err,
+    )
+    .await;
+    result.map(Json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logging::ModelPriceSource;
+
+    fn price(provider: &str, model: &str, prompt: f64, completion: f64) -> ModelPriceRecord {
+        ModelPriceRecord {
+            provider: provider.into(),
+            model: model.into(),
+            prompt_price_per_million: prompt,
+            completion_price_per_million: completion,
+            currency: Some("USD".into()),
+            model_type: Some("chat".into()),
+            source: ModelPriceSource::Manual,
+            status: ModelPriceStatus::Active,
+            synced_at: None,
+            expires_at: None,
+        }
+    }
+
+    #[test]
+    fn catalog_applies_markup_and_merges_hidden_providers() {
+        let prices: HashMap<(String, String), ModelPriceRecord> = [
+            price("a", "gpt-4o", 2.0, 8.0),
+            price("b", "gpt-4o", 2.5, 7.0),
+            price("a", "unlisted", 1.0, 1.0),
+        ]
+        .into_iter()
+        .map(|r| ((r.provider.clone(), r.model.clone()), r))
+        .collect();
+        let models = vec![
+            "a/gpt-4o".to_string(),
+            "b/gpt-4o".to_string(),
+            "a/no-price".to_string(),
+        ];
+
+        let visible = build_catalog(&models, &prices, 1.5, false);
+        assert_eq!(visible.len(), 2);
+        assert_eq!(visible[0].id, "a/gpt-4o");
+        assert_eq!(visible[0].provider.as_deref(), Some("a"));
+        assert_eq!(visible[0].input_price_per_million, 3.0);
+        assert_eq!(visible[0].output_price_per_million, 12.0);
+
+        let hidden = build_catalog(&models, &prices, 1.0, true);
+        assert_eq!(hidden.len(), 1);
+        assert_eq!(hidden[0].id, "gpt-4o");
+        assert_eq!(hidden[0].provider, None);
+        assert_eq!(hidden[0].input_price_per_million, 2.5);
+        assert_eq!(hidden[0].output_price_per_million, 8.0);
+    }
+}
@@ -0,0 +1,346 @@
+use axum::{
+    extract::State,
+    http::{HeaderMap, header},
+    response::IntoResponse,
+};
+use std::fmt::Write;
+use std::sync::Arc;
+
+use super::auth::require_superadmin;
+use crate::error::GatewayError;
+use crate::server::AppState;
+use crate::server::degraded::degraded_mode;
+use crate::server::in_flight::ProviderInFlight;
+use crate::server::log_queue::LogQueueStats;
+use crate::server::model_concurrency::ModelInFlight;
+use crate::server::payload_limits::{ProviderPayloadSizes, SIZE_BUCKETS, SizeHistogram};
+use crate::server::util::bearer_token;
+
+/// 逐字节比较，耗时与内容无关
+fn token_matches(provided: &str, expected: &str) -> bool {
+    provided.len() == expected.len()
+        && provided
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn gauge<T>(
+    out: &mut String,
+    name: &str,
+    help: &str,
+    label: &str,
+    items: &[T],
+    value: impl Fn(&T) -> (&str, usize),
+) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} gauge", name);
+    for item in items {
+        let (label_value, v) = value(item);
+        let _ = writeln!(
+            out,
+            "{}{{{}=\"{}\"}} {}",
+            name,
+            label,
+            escape_label(label_value),
+            v
+        );
+    }
+}
+
+fn scalar(out: &mut String, name: &str, kind: &str, help: &str, value: impl std::fmt::Display) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+    let _ = writeln!(out, "{} {}", name, value);
+}
+
+fn log_queue(out: &mut String, stats: &LogQueueStats) {
+    scalar(
+        out,
+        "gateway_log_queue_depth",
+        "gauge",
+        "Request logs waiting in the on-disk replay queue.",
+        stats.depth,
+    );
+    scalar(
+        out,
+        "gateway_log_queue_replay_lag_seconds",
+        "gauge",
+        "Age of the oldest queued request log; 0 when the queue is empty.",
+        stats.replay_lag_secs.unwrap_or(0),
+    );
+    scalar(
+        out,
+        "gateway_log_queue_replayed_total",
+        "counter",
+        "Queued request logs written to the store after recovery.",
+        stats.replayed_total,
+    );
+    scalar(
+        out,
+        "gateway_log_queue_duplicates_total",
+        "counter",
+        "Queued request logs skipped because their request id was already queued or replayed.",
+        stats.duplicates_total,
+    );
+    scalar(
+        out,
+        "gateway_log_queue_dropped_total",
+        "counter",
+        "Request logs dropped because the replay queue was full.",
+        stats.dropped_total,
+    );
+}
+
+fn histogram(
+    out: &mut String,
+    name: &str,
+    help: &str,
+    sizes: &[ProviderPayloadSizes],
+    value: impl Fn(&ProviderPayloadSizes) -> &SizeHistogram,
+) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} histogram", name);
+    for item in sizes {
+        let h = value(item);
+        let provider = escape_label(&item.provider);
+        for (i, bound) in SIZE_BUCKETS.iter().enumerate() {
+            let _ = writeln!(
+                out,
+                "{}_bucket{{provider=\"{}\",le=\"{}\"}} {}",
+                name,
+                provider,
+                bound,
+                h.buckets.get(i).copied().unwrap_or(0)
+            );
+        }
+        let _ = writeln!(
+            out,
+            "{}_bucket{{provider=\"{}\",le=\"+Inf\"}} {}",
+            name, provider, h.count
+        );
+        let _ = writeln!(out, "{}_sum{{provider=\"{}\"}} {}", name, provider, h.sum);
+        let _ = writeln!(
+            out,
+            "{}_count{{provider=\"{}\"}} {}",
+            name, provider, h.count
+        );
+    }
+}
+
+fn payload_sizes(out: &mut String, sizes: &[ProviderPayloadSizes]) {
+    histogram(
+        out,
+        "gateway_provider_prompt_bytes",
+        "Serialized prompt size of requests admitted for dispatch.",
+        sizes,
+        |p| &p.prompt_bytes,
+    );
+    histogram(
+        out,
+        "gateway_provider_response_bytes",
+        "Upstream response size forwarded to clients.",
+        sizes,
+        |p| &p.response_bytes,
+    );
+    let _ = writeln!(
+        out,
+        "# HELP gateway_provider_prompt_rejected_total Requests rejected for exceeding the provider prompt size limit."
+    );
+    let _ = writeln!(out, "# TYPE gateway_provider_prompt_rejected_total counter");
+    for p in sizes {
+        let _ = writeln!(
+            out,
+            "gateway_provider_prompt_rejected_total{{provider=\"{}\"}} {}",
+            escape_label(&p.provider),
+            p.rejected
+        );
+    }
+    let _ = writeln!(
+        out,
+        "# HELP gateway_provider_prompt_truncated_total Requests whose prompt was truncated to fit the model context window."
+    );
+    let _ = writeln!(
+        out,
+        "# TYPE gateway_provider_prompt_truncated_total counter"
+    );
+    for p in sizes {
+        let _ = writeln!(
+            out,
+            "gateway_provider_prompt_truncated_total{{provider=\"{}\"}} {}",
+            escape_label(&p.provider),
+            p.truncated
+        );
+    }
+}
+
+/// Prometheus 文本格式的实时指标（本实例）
+pub(crate) fn render(
+    providers: &[ProviderInFlight],
+    models: &[ModelInFlight],
+    sizes: &[ProviderPayloadSizes],
+    queue: Option<&LogQueueStats>,
+) -> String {
+    let mut out = String::new();
+    gauge(
+        &mut out,
+        "gateway_provider_in_flight_requests",
+        "Upstream requests currently in flight, including streams.",
+        "provider",
+        providers,
+        |p| (&p.provider, p.in_flight),
+    );
+    gauge(
+        &mut out,
+        "gateway_provider_in_flight_streams",
+        "Streaming responses currently in flight.",
+        "provider",
+        providers,
+        |p| (&p.provider, p.streams),
+    );
+    gauge(
+        &mut out,
+        "gateway_provider_saturated",
+        "Whether in-flight requests are at or above the alert threshold.",
+        "provider",
+        providers,
+        |p| (&p.provider, p.saturated_since.is_some() as usize),
+    );
+    gauge(
+        &mut out,
+        "gateway_model_in_flight",
+        "Generations in flight for models with a concurrency limit.",
+        "model",
+        models,
+        |m| (&m.model, m.in_flight),
+    );
+    gauge(
+        &mut out,
+        "gateway_model_queued",
+        "Requests waiting for a model concurrency slot.",
+        "model",
+        models,
+        |m| (&m.model, m.queued),
+    );
+    gauge(
+        &mut out,
+        "gateway_model_max_in_flight",
+        "Configured model concurrency limit.",
+        "model",
+        models,
+        |m| (&m.model, m.max_in_flight as usize),
+    );
+    gauge(
+        &mut out,
+        "gateway_model_queue_oldest_wait_ms",
+        "Wait time of the longest-queued request for a model concurrency slot.",
+        "model",
+        models,
+        |m| (&m.model, m.oldest_wait_ms as usize),
+    );
+    let _ = writeln!(
+        out,
+        "# HELP gateway_model_queue_timeouts_total Requests rejected after waiting too long for a model concurrency slot."
+    );
+    let _ = writeln!(out, "# TYPE gateway_model_queue_timeouts_total counter");
+    for m in models {
+        let _ = writeln!(
+            out,
+            "gateway_model_queue_timeouts_total{{model=\"{}\"}} {}",
+            escape_label(&m.model),
+            m.queue_timeouts
+        );
+    }
+    payload_sizes(&mut out, sizes);
+    if let Some(stats) = queue {
+        log_queue(&mut out, stats);
+    }
+    out
+}
+
+/// 抓取频繁，不写入请求日志
+pub async fn metrics(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, GatewayError> {
+    match app_state.config.server.metrics_token.as_deref() {
+        Some(expected) if !expected.is_empty() => {
+            if !bearer_token(&headers).is_some_and(|t| token_matches(&t, expected)) {
+                return Err(GatewayError::Unauthorized("invalid metrics token".into()));
+            }
+        }
+        _ => {
+            require_superadmin(&headers, &app_state).await?;
+        }
+    }
+    let queue = degraded_mode().map(|m| m.queue().stats());
+    let body = render(
+        &app_state.in_flight.snapshot(),
+        &app_state
+            .model_concurrency
+            .snapshot(&app_state.config.server),
+        &app_state.payload_sizes.snapshot(),
+        queue.as_ref(),
+    );
+    Ok((
+        [(
+            header::CONTENT_TYPE,
+            "text/plain; version=0.0.4; charset=utf-8",
+        )],
+        body,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_gauges_with_escaped_labels() {
+        let providers = vec![ProviderInFlight {
+            provider: "a\"b".into(),
+            in_flight: 3,
+            streams: 1,
+            saturated_since: None,
+        }];
+        let queue = LogQueueStats {
+            depth: 4,
+            replay_lag_secs: Some(90),
+            ..Default::default()
+        };
+        let sizes = crate::server::payload_limits::PayloadSizeStats::default();
+        sizes.record_prompt("a\"b", 2000);
+        sizes.record_rejected("a\"b");
+        let text = render(&providers, &[], &sizes.snapshot(), Some(&queue));
+        assert!(text.contains("# TYPE gateway_provider_in_flight_requests gauge\n"));
+        assert!(text.contains("gateway_provider_in_flight_requests{provider=\"a\\\"b\"} 3\n"));
+        assert!(text.contains("gateway_provider_in_flight_streams{provider=\"a\\\"b\"} 1\n"));
+        assert!(text.contains("gateway_provider_saturated{provider=\"a\\\"b\"} 0\n"));
+        assert!(text.contains("gateway_log_queue_depth 4\n"));
+        assert!(
+            text.contains(
+                "gateway_provider_prompt_bytes_bucket{provider=\"a\\\"b\",le=\"1024\"} 0\n"
+            )
+        );
+        assert!(
+            text.contains(
+                "gateway_provider_prompt_bytes_bucket{provider=\"a\\\"b\",le=\"4096\"} 1\n"
+            )
+        );
+        assert!(text.contains("gateway_provider_prompt_bytes_sum{provider=\"a\\\"b\"} 2000\n"));
+        assert!(text.contains("gateway_provider_response_bytes_count{provider=\"a\\\"b\"} 0\n"));
+        assert!(text.contains("gateway_provider_prompt_rejected_total{provider=\"a\\\"b\"} 1\n"));
+        assert!(text.contains("gateway_log_queue_replay_lag_seconds 90\n"));
+        assert!(text.contains("# TYPE gateway_log_queue_replayed_total counter\n"));
+        assert!(token_matches("secret", "secret"));
+        assert!(!token_matches("secreT", "secret"));
+    }
+}
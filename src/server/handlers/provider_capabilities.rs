@@ -0,0 +1,98 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::HeaderMap,
+};
+use chrono::Utc;
+use serde::Serialize;
+use std::sync::Arc;
+
+use super::auth::require_superadmin;
+use crate::config::settings::{ProviderType, UnsupportedFeaturePolicy};
+use crate::error::GatewayError;
+use crate::logging::types::REQ_TYPE_PROVIDER_CAPABILITIES;
+use crate::providers::adapters::{ApiVersionRule, api_version_spec, effective_api_version};
+use crate::providers::capabilities::{self, FeatureSupport};
+use crate::server::AppState;
+use crate::server::request_logging::log_simple_request;
+use crate::server::util::{bearer_token, token_for_log};
+
+#[derive(Debug, Serialize)]
+pub struct ApiVersionInfo {
+    /// 实际发送给上游的版本
+    pub effective: Option<String>,
+    pub default: Option<&'static str>,
+    /// 可选版本；日期格式版本（Azure）为空
+    pub supported: Vec<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<&'static str>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProviderCapabilitiesResponse {
+    pub provider: String,
+    pub api_type: ProviderType,
+    /// 该类型不接受 api_version 配置时为 null
+    pub api_version: Option<ApiVersionInfo>,
+    pub unsupported_features: UnsupportedFeaturePolicy,
+    pub features: Vec<FeatureSupport>,
+}
+
+/// 供应商在请求转换后保留的字段与使用的上游 API 版本
+pub async fn get_provider_capabilities(
+    Path(provider_name): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<ProviderCapabilitiesResponse>, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    let result: Result<ProviderCapabilitiesResponse, GatewayError> = async {
+        require_superadmin(&headers, &app_state).await?;
+        let provider = app_state
+            .providers
+            .get_provider(&provider_name)
+            .await?
+            .ok_or_else(|| {
+                GatewayError::NotFound(format!("Provider '{}' not found", provider_name))
+            })?;
+        let api_version = api_version_spec(provider.api_type).map(|spec| {
+            let (supported, format) = match spec.rule {
+                ApiVersionRule::OneOf(versions) => (versions.to_vec(), None),
+                ApiVersionRule::Dated => (Vec::new(), Some("YYYY-MM-DD[-preview]")),
+            };
+            ApiVersionInfo {
+                effective: effective_api_version(provider.api_type, &provider.provider_config)
+                    .map(str::to_string),
+                default: spec.default,
+                supported,
+                format,
+            }
+        });
+        Ok(ProviderCapabilitiesResponse {
+            api_type: provider.api_type,
+            api_version,
+            unsupported_features: app_state.config.server.unsupported_features,
+            features: capabilities::report(provider.api_type),
+            provider: provider.name,
+        })
+    }
+    .await;
+    let (code, err) = match &result {
+        Ok(_) => (200, None),
+        Err(e) => (e.status_code().as_u16(), Some(e.to_string())),
+    };
+    log_simple_request(
+        &app_state,
+        start_time,
+        "GET",
+        &format!("/admin/providers/{}/capabilities", provider_name),
+        REQ_TYPE_PROVIDER_CAPABILITIES,
+        None,
+        Some(provider_name.clone()),
+        token_for_log(provided_token.as_deref()),
+        code,
+        err,
+    )
+    .await;
+    result.map(Json)
+}
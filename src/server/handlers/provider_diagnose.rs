@@ -0,0 +1,344 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::HeaderMap,
+};
+use chrono::Utc;
+use reqwest::Url;
+use serde::Serialize;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::net::TcpStream;
+
+use super::auth::require_superadmin;
+use super::provider_model_test::resolve_models_url;
+use crate::config::settings::Provider;
+use crate::error::GatewayError;
+use crate::logging::types::REQ_TYPE_PROVIDER_DIAGNOSE;
+use crate::providers::adapters::{ListModelsRequest, adapter_for};
+use crate::server::AppState;
+use crate::server::request_logging::log_simple_request;
+use crate::server::ssrf::{is_disallowed_host, is_disallowed_ip};
+use crate::server::util::{bearer_token, token_for_log};
+
+/// 每个阶段的超时
+const STAGE_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Serialize)]
+pub struct DiagnoseStage {
+    /// dns | tcp | tls | probe
+    pub stage: &'static str,
+    /// ok | failed | skipped
+    pub status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub elapsed_ms: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl DiagnoseStage {
+    fn ok(stage: &'static str, elapsed: Duration, detail: impl Into<String>) -> Self {
+        Self {
+            stage,
+            status: "ok",
+            elapsed_ms: Some(elapsed.as_secs_f64() * 1000.0),
+            detail: Some(detail.into()),
+            error: None,
+        }
+    }
+
+    fn failed(stage: &'static str, elapsed: Option<Duration>, error: impl Into<String>) -> Self {
+        Self {
+            stage,
+            status: "failed",
+            elapsed_ms: elapsed.map(|d| d.as_secs_f64() * 1000.0),
+            detail: None,
+            error: Some(error.into()),
+        }
+    }
+
+    fn skipped(stage: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            stage,
+            status: "skipped",
+            elapsed_ms: None,
+            detail: Some(detail.into()),
+            error: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProviderDiagnosis {
+    pub provider: String,
+    pub base_url: String,
+    /// 所有已执行的阶段均成功
+    pub success: bool,
+    pub stages: Vec<DiagnoseStage>,
+}
+
+async fn timed<T, E: std::fmt::Display>(
+    fut: impl Future<Output = Result<T, E>>,
+) -> (Duration, Result<T, String>) {
+    let t0 = Instant::now();
+    let out = match tokio::time::timeout(STAGE_TIMEOUT, fut).await {
+        Ok(Ok(v)) => Ok(v),
+        Ok(Err(e)) => Err(e.to_string()),
+        Err(_) => Err(format!("timed out after {}s", STAGE_TIMEOUT.as_secs())),
+    };
+    (t0.elapsed(), out)
+}
+
+/// DNS 解析；与出站 SSRF 校验一致，解析到本机/内网地址视为失败
+async fn resolve_stage(host: &str, port: u16) -> (DiagnoseStage, Vec<SocketAddr>) {
+    if is_disallowed_host(host) {
+        return (
+            DiagnoseStage::failed("dns", None, "base_url 不允许指向本机/内网"),
+            Vec::new(),
+        );
+    }
+    let (elapsed, res) = timed(tokio::net::lookup_host((host, port))).await;
+    let addrs: Vec<SocketAddr> = match res {
+        Ok(addrs) => addrs.collect(),
+        Err(e) => return (DiagnoseStage::failed("dns", Some(elapsed), e), Vec::new()),
+    };
+    if addrs.is_empty() {
+        return (
+            DiagnoseStage::failed("dns", Some(elapsed), "no addresses resolved"),
+            Vec::new(),
+        );
+    }
+    if addrs.iter().any(|a| is_disallowed_ip(a.ip())) {
+        return (
+            DiagnoseStage::failed("dns", Some(elapsed), "base_url 不允许指向本机/内网"),
+            Vec::new(),
+        );
+    }
+    let listed = addrs
+        .iter()
+        .map(|a| a.ip().to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    (DiagnoseStage::ok("dns", elapsed, listed), addrs)
+}
+
+/// 依次尝试解析到的地址，返回第一个建立成功的连接
+async fn connect_stage(addrs: &[SocketAddr]) -> (DiagnoseStage, Option<TcpStream>) {
+    let t0 = Instant::now();
+    let mut errors = Vec::new();
+    for addr in addrs {
+        let (_, res) = timed(TcpStream::connect(addr)).await;
+        match res {
+            Ok(stream) => {
+                return (
+                    DiagnoseStage::ok("tcp", t0.elapsed(), addr.to_string()),
+                    Some(stream),
+                );
+            }
+            Err(e) => errors.push(format!("{}: {}", addr, e)),
+        }
+    }
+    (
+        DiagnoseStage::failed("tcp", Some(t0.elapsed()), errors.join("; ")),
+        None,
+    )
+}
+
+async fn tls_stage(host: &str, stream: TcpStream) -> DiagnoseStage {
+    let connector = match tokio_native_tls::native_tls::TlsConnector::new() {
+        Ok(c) => tokio_native_tls::TlsConnector::from(c),
+        Err(e) => return DiagnoseStage::failed("tls", None, e.to_string()),
+    };
+    let (elapsed, res) = timed(connector.connect(host, stream)).await;
+    match res {
+        Ok(_) => DiagnoseStage::ok("tls", elapsed, "handshake completed, certificate verified"),
+        Err(e) => DiagnoseStage::failed("tls", Some(elapsed), e),
+    }
+}
+
+/// 使用首个可用密钥请求模型列表，验证鉴权与接口可达
+async fn probe_stage(app_state: &AppState, provider: &Provider, base_url: &Url) -> DiagnoseStage {
+    let capabilities = provider.api_type.capabilities();
+    let models_endpoint = provider
+        .models_endpoint
+        .as_deref()
+        .map(str::trim)
+        .filter(|v| !v.is_empty());
+    let probeable = capabilities.supports_auto_model_discovery
+        || (capabilities.supports_models_endpoint && models_endpoint.is_some());
+    if !probeable {
+        return DiagnoseStage::skipped("probe", "provider type has no models endpoint to probe");
+    }
+    let Some(adapter) = adapter_for(provider.api_type) else {
+        return DiagnoseStage::skipped("probe", "provider type is not supported");
+    };
+    let api_key = match app_state
+        .providers
+        .get_provider_keys(&provider.name, &app_state.config.logging.key_log_strategy)
+        .await
+    {
+        Ok(keys) => keys.into_iter().find(|k| !k.trim().is_empty()),
+        Err(e) => return DiagnoseStage::failed("probe", None, e.to_string()),
+    };
+    let Some(api_key) = api_key else {
+        return DiagnoseStage::skipped("probe", "no available api key");
+    };
+    let models_url = match resolve_models_url(base_url, models_endpoint).await {
+        Ok(url) => url,
+        Err((kind, message)) => {
+            return DiagnoseStage::failed("probe", None, message.unwrap_or(kind));
+        }
+    };
+    let (elapsed, res) = timed(adapter.list_models(ListModelsRequest {
+        models_url: &models_url,
+        api_key: &api_key,
+    }))
+    .await;
+    match res {
+        Ok(models) => DiagnoseStage::ok(
+            "probe",
+            elapsed,
+            format!("GET {} returned {} models", models_url.path(), models.len()),
+        ),
+        Err(e) => DiagnoseStage::failed("probe", Some(elapsed), e),
+    }
+}
+
+/// 逐阶段诊断上游连通性：DNS 解析、TCP 连接、TLS 握手与带鉴权的探测请求；
+/// 前一阶段失败时后续阶段标记为 skipped
+async fn diagnose(app_state: &AppState, provider: &Provider) -> ProviderDiagnosis {
+    let mut stages = Vec::new();
+    let url = match Url::parse(provider.base_url.trim()) {
+        Ok(url) if matches!(url.scheme(), "http" | "https") && url.host_str().is_some() => url,
+        _ => {
+            stages.push(DiagnoseStage::failed(
+                "dns",
+                None,
+                "base_url 不是合法的 http/https URL",
+            ));
+            return finish(provider, stages);
+        }
+    };
+    let host = url.host_str().unwrap_or_default().to_string();
+    let port = url.port_or_known_default().unwrap_or(443);
+    let is_https = url.scheme() == "https";
+
+    let (dns, addrs) = resolve_stage(&host, port).await;
+    stages.push(dns);
+    let stream = if addrs.is_empty() {
+        None
+    } else {
+        let (tcp, stream) = connect_stage(&addrs).await;
+        stages.push(tcp);
+        stream
+    };
+    let transport_ok = match stream {
+        Some(stream) if is_https => {
+            let tls = tls_stage(&host, stream).await;
+            let ok = tls.status == "ok";
+            stages.push(tls);
+            ok
+        }
+        Some(_) => {
+            stages.push(DiagnoseStage::skipped("tls", "base_url uses plain http"));
+            true
+        }
+        None => false,
+    };
+    if transport_ok {
+        stages.push(probe_stage(app_state, provider, &url).await);
+    }
+    finish(provider, stages)
+}
+
+fn finish(provider: &Provider, mut stages: Vec<DiagnoseStage>) -> ProviderDiagnosis {
+    for stage in ["dns", "tcp", "tls", "probe"] {
+        if !stages.iter().any(|s| s.stage == stage) {
+            stages.push(DiagnoseStage::skipped(stage, "previous stage failed"));
+        }
+    }
+    ProviderDiagnosis {
+        provider: provider.name.clone(),
+        base_url: provider.base_url.clone(),
+        success: stages.iter().all(|s| s.status != "failed"),
+        stages,
+    }
+}
+
+pub async fn diagnose_provider(
+    Path(provider_name): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<ProviderDiagnosis>, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    let result: Result<ProviderDiagnosis, GatewayError> = async {
+        require_superadmin(&headers, &app_state).await?;
+        let provider = app_state
+            .providers
+            .get_provider(&provider_name)
+            .await?
+            .ok_or_else(|| {
+                GatewayError::NotFound(format!("Provider '{}' not found", provider_name))
+            })?;
+        Ok(diagnose(&app_state, &provider).await)
+    }
+    .await;
+    let (code, err) = match &result {
+        Ok(d) if !d.success => (
+            200,
+            d.stages
+                .iter()
+                .find(|s| s.status == "failed")
+                .map(|s| format!("{}: {}", s.stage, s.error.as_deref().unwrap_or_default())),
+        ),
+        Ok(_) => (200, None),
+        Err(e) => (e.status_code().as_u16(), Some(e.to_string())),
+    };
+    log_simple_request(
+        &app_state,
+        start_time,
+        "POST",
+        &format!("/admin/providers/{}/diagnose", provider_name),
+        REQ_TYPE_PROVIDER_DIAGNOSE,
+        None,
+        Some(provider_name.clone()),
+        token_for_log(provided_token.as_deref()),
+        code,
+        err,
+    )
+    .await;
+    result.map(Json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn dns_stage_rejects_internal_targets() {
+        let (stage, addrs) = resolve_stage("localhost", 443).await;
+        assert_eq!(stage.status, "failed");
+        assert!(addrs.is_empty());
+
+        let (stage, addrs) = resolve_stage("127.0.0.1", 443).await;
+        assert_eq!(stage.status, "failed");
+        assert!(addrs.is_empty());
+    }
+
+    #[tokio::test]
+    async fn tcp_stage_reports_each_failed_address() {
+        // 绑定后立即释放端口，确保连接被拒绝
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+        let (stage, stream) = connect_stage(&[addr]).await;
+        assert!(stream.is_none());
+        assert_eq!(stage.status, "failed");
+        assert!(stage.error.unwrap().contains(&addr.to_string()));
+    }
+}
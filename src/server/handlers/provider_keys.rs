@@ -5,18 +5,21 @@ use axum::{
 };
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::sync::Arc;
 
 use super::auth::require_superadmin;
-use super::provider_models_list::invalidate_cache_for_provider;
 use crate::error::GatewayError;
 use crate::logging::types::{
     ProviderOpLog, REQ_TYPE_PROVIDER_KEY_ADD, REQ_TYPE_PROVIDER_KEY_CONFIG_GET,
-    REQ_TYPE_PROVIDER_KEY_CONFIG_SET, REQ_TYPE_PROVIDER_KEY_DELETE, REQ_TYPE_PROVIDER_KEY_LIST,
-    REQ_TYPE_PROVIDER_KEY_TOGGLE, REQ_TYPE_PROVIDER_KEY_WEIGHT_SET,
+    REQ_TYPE_PROVIDER_KEY_CONFIG_SET, REQ_TYPE_PROVIDER_KEY_DELETE,
+    REQ_TYPE_PROVIDER_KEY_LABELS_SET, REQ_TYPE_PROVIDER_KEY_LIMITS_SET, REQ_TYPE_PROVIDER_KEY_LIST,
+    REQ_TYPE_PROVIDER_KEY_OPENAI_HEADERS_SET, REQ_TYPE_PROVIDER_KEY_TOGGLE,
+    REQ_TYPE_PROVIDER_KEY_WEIGHT_SET,
 };
-use crate::routing::KeyRotationStrategy;
+use crate::routing::{KeyRotationStrategy, OpenAIAccountHeaders};
 use crate::server::AppState;
+use crate::server::pagination::{PageQuery, SortFields, SortOrder};
 use crate::server::request_logging::log_simple_request;
 use crate::server::util::{key_display_hint, mask_key};
 
@@ -53,6 +56,39 @@ pub(super) struct KeyWeightPayload {
     weight: u32,
 }
 
+#[derive(Debug, Deserialize)]
+pub(super) struct KeyLimitsPayload {
+    key: String,
+    /// 滚动 24 小时花费上限；为空表示不限制
+    #[serde(default)]
+    spend_cap: Option<f64>,
+    /// 每分钟请求上限；为空表示不限制
+    #[serde(default)]
+    rpm_limit: Option<u32>,
+    /// 每分钟 token 上限；为空表示不限制
+    #[serde(default)]
+    tpm_limit: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct KeyOpenAIHeadersPayload {
+    key: String,
+    /// OpenAI-Organization 头；为空表示不发送
+    #[serde(default)]
+    openai_organization: Option<String>,
+    /// OpenAI-Project 头；为空表示不发送
+    #[serde(default)]
+    openai_project: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct KeyLabelsPayload {
+    key: String,
+    /// 整体替换密钥的标签；空对象表示清除
+    #[serde(default)]
+    labels: BTreeMap<String, String>,
+}
+
 #[derive(Debug, Serialize)]
 struct BatchResult {
     key: String,
@@ -67,6 +103,13 @@ struct ProviderKeyFullEntry {
     masked: String,
     active: bool,
     weight: u32,
+    spend_cap: Option<f64>,
+    rpm_limit: Option<u32>,
+    tpm_limit: Option<u32>,
+    #[serde(flatten)]
+    openai_account: OpenAIAccountHeaders,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    labels: BTreeMap<String, String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -90,34 +133,38 @@ pub async fn add_provider_key(
         .and_then(|s| s.strip_prefix("Bearer "))
         .map(|s| s.to_string());
     // 鉴权失败也要记录操作日志与请求日志
-    if let Err(e) = require_superadmin(&headers, &app_state).await {
-        let start_time = chrono::Utc::now();
-        let _ = app_state
-            .log_store
-            .log_provider_op(ProviderOpLog {
-                id: None,
-                timestamp: start_time,
-                operation: REQ_TYPE_PROVIDER_KEY_ADD.to_string(),
-                provider: Some(provider_name.clone()),
-                details: Some(e.to_string()),
-            })
+    let identity = match require_superadmin(&headers, &app_state).await {
+        Ok(identity) => identity,
+        Err(e) => {
+            let start_time = chrono::Utc::now();
+            let _ = app_state
+                .log_store
+                .log_provider_op(ProviderOpLog {
+                    id: None,
+                    timestamp: start_time,
+                    operation: REQ_TYPE_PROVIDER_KEY_ADD.to_string(),
+                    provider: Some(provider_name.clone()),
+                    details: Some(e.to_string()),
+                    actor: None,
+                })
+                .await;
+            let code = e.status_code().as_u16();
+            log_simple_request(
+                &app_state,
+                start_time,
+                "POST",
+                &format!("/providers/{}/keys", provider_name),
+                REQ_TYPE_PROVIDER_KEY_ADD,
+                None,
+                Some(provider_name),
+                provided_token.as_deref(),
+                code,
+                Some("auth failed".into()),
+            )
             .await;
-        let code = e.status_code().as_u16();
-        log_simple_request(
-            &app_state,
-            start_time,
-            "POST",
-            &format!("/providers/{}/keys", provider_name),
-            REQ_TYPE_PROVIDER_KEY_ADD,
-            None,
-            Some(provider_name),
-            provided_token.as_deref(),
-            code,
-            Some("auth failed".into()),
-        )
-        .await;
-        return Err(e);
-    }
+            return Err(e);
+        }
+    };
     if !app_state
         .providers
         .provider_exists(&provider_name)
@@ -138,7 +185,7 @@ pub async fn add_provider_key(
         )
         .await
         .map_err(GatewayError::Db)?;
-    invalidate_cache_for_provider(&provider_name).await;
+    crate::server::cluster::provider_changed(&app_state, &provider_name).await;
 
     let start_time = Utc::now();
     // provider ops audit log with masked/plain/none display
@@ -152,6 +199,7 @@ pub async fn add_provider_key(
             operation: REQ_TYPE_PROVIDER_KEY_ADD.to_string(),
             provider: Some(provider_name.clone()),
             details,
+            actor: Some(identity.actor()),
         })
         .await;
     log_simple_request(
@@ -187,34 +235,38 @@ pub async fn toggle_provider_key(
         .and_then(|s| s.strip_prefix("Bearer "))
         .map(|s| s.to_string());
 
-    if let Err(e) = require_superadmin(&headers, &app_state).await {
-        let start_time = chrono::Utc::now();
-        let _ = app_state
-            .log_store
-            .log_provider_op(ProviderOpLog {
-                id: None,
-                timestamp: start_time,
-                operation: REQ_TYPE_PROVIDER_KEY_TOGGLE.to_string(),
-                provider: Some(provider_name.clone()),
-                details: Some(e.to_string()),
-            })
+    let identity = match require_superadmin(&headers, &app_state).await {
+        Ok(identity) => identity,
+        Err(e) => {
+            let start_time = chrono::Utc::now();
+            let _ = app_state
+                .log_store
+                .log_provider_op(ProviderOpLog {
+                    id: None,
+                    timestamp: start_time,
+                    operation: REQ_TYPE_PROVIDER_KEY_TOGGLE.to_string(),
+                    provider: Some(provider_name.clone()),
+                    details: Some(e.to_string()),
+                    actor: None,
+                })
+                .await;
+            let code = e.status_code().as_u16();
+            log_simple_request(
+                &app_state,
+                start_time,
+                "POST",
+                &format!("/providers/{}/keys/toggle", provider_name),
+                REQ_TYPE_PROVIDER_KEY_TOGGLE,
+                None,
+                Some(provider_name),
+                provided_token.as_deref(),
+                code,
+                Some("auth failed".into()),
+            )
             .await;
-        let code = e.status_code().as_u16();
-        log_simple_request(
-            &app_state,
-            start_time,
-            "POST",
-            &format!("/providers/{}/keys/toggle", provider_name),
-            REQ_TYPE_PROVIDER_KEY_TOGGLE,
-            None,
-            Some(provider_name),
-            provided_token.as_deref(),
-            code,
-            Some("auth failed".into()),
-        )
-        .await;
-        return Err(e);
-    }
+            return Err(e);
+        }
+    };
 
     if !app_state
         .providers
@@ -251,11 +303,12 @@ pub async fn toggle_provider_key(
             operation: REQ_TYPE_PROVIDER_KEY_TOGGLE.to_string(),
             provider: Some(provider_name.clone()),
             details,
+            actor: Some(identity.actor()),
         })
         .await;
 
     if updated {
-        invalidate_cache_for_provider(&provider_name).await;
+        crate::server::cluster::provider_changed(&app_state, &provider_name).await;
         log_simple_request(
             &app_state,
             start_time,
@@ -304,34 +357,38 @@ pub async fn add_provider_keys_batch(
         .and_then(|s| s.strip_prefix("Bearer "))
         .map(|s| s.to_string());
 
-    if let Err(e) = require_superadmin(&headers, &app_state).await {
-        let start_time = chrono::Utc::now();
-        let _ = app_state
-            .log_store
-            .log_provider_op(ProviderOpLog {
-                id: None,
-                timestamp: start_time,
-                operation: REQ_TYPE_PROVIDER_KEY_ADD.to_string(),
-                provider: Some(provider_name.clone()),
-                details: Some(e.to_string()),
-            })
+    let identity = match require_superadmin(&headers, &app_state).await {
+        Ok(identity) => identity,
+        Err(e) => {
+            let start_time = chrono::Utc::now();
+            let _ = app_state
+                .log_store
+                .log_provider_op(ProviderOpLog {
+                    id: None,
+                    timestamp: start_time,
+                    operation: REQ_TYPE_PROVIDER_KEY_ADD.to_string(),
+                    provider: Some(provider_name.clone()),
+                    details: Some(e.to_string()),
+                    actor: None,
+                })
+                .await;
+            let code = e.status_code().as_u16();
+            log_simple_request(
+                &app_state,
+                start_time,
+                "POST",
+                &format!("/providers/{}/keys/batch", provider_name),
+                REQ_TYPE_PROVIDER_KEY_ADD,
+                None,
+                Some(provider_name.clone()),
+                provided_token.as_deref(),
+                code,
+                Some("auth failed".into()),
+            )
             .await;
-        let code = e.status_code().as_u16();
-        log_simple_request(
-            &app_state,
-            start_time,
-            "POST",
-            &format!("/providers/{}/keys/batch", provider_name),
-            REQ_TYPE_PROVIDER_KEY_ADD,
-            None,
-            Some(provider_name.clone()),
-            provided_token.as_deref(),
-            code,
-            Some("auth failed".into()),
-        )
-        .await;
-        return Err(e);
-    }
+            return Err(e);
+        }
+    };
 
     if !app_state
         .providers
@@ -384,7 +441,7 @@ pub async fn add_provider_keys_batch(
     }
 
     if success > 0 {
-        invalidate_cache_for_provider(&provider_name).await;
+        crate::server::cluster::provider_changed(&app_state, &provider_name).await;
     }
 
     let detail = serde_json::json!({
@@ -400,6 +457,7 @@ pub async fn add_provider_keys_batch(
             operation: REQ_TYPE_PROVIDER_KEY_ADD.to_string(),
             provider: Some(provider_name.clone()),
             details: Some(detail),
+            actor: Some(identity.actor()),
         })
         .await;
 
@@ -440,34 +498,38 @@ pub async fn delete_provider_key(
         .and_then(|v| v.to_str().ok())
         .and_then(|s| s.strip_prefix("Bearer "))
         .map(|s| s.to_string());
-    if let Err(e) = require_superadmin(&headers, &app_state).await {
-        let start_time = chrono::Utc::now();
-        let _ = app_state
-            .log_store
-            .log_provider_op(ProviderOpLog {
-                id: None,
-                timestamp: start_time,
-                operation: REQ_TYPE_PROVIDER_KEY_DELETE.to_string(),
-                provider: Some(provider_name.clone()),
-                details: Some(e.to_string()),
-            })
+    let identity = match require_superadmin(&headers, &app_state).await {
+        Ok(identity) => identity,
+        Err(e) => {
+            let start_time = chrono::Utc::now();
+            let _ = app_state
+                .log_store
+                .log_provider_op(ProviderOpLog {
+                    id: None,
+                    timestamp: start_time,
+                    operation: REQ_TYPE_PROVIDER_KEY_DELETE.to_string(),
+                    provider: Some(provider_name.clone()),
+                    details: Some(e.to_string()),
+                    actor: None,
+                })
+                .await;
+            let code = e.status_code().as_u16();
+            log_simple_request(
+                &app_state,
+                start_time,
+                "DELETE",
+                &format!("/providers/{}/keys", provider_name),
+                REQ_TYPE_PROVIDER_KEY_DELETE,
+                None,
+                Some(provider_name),
+                provided_token.as_deref(),
+                code,
+                Some("auth failed".into()),
+            )
             .await;
-        let code = e.status_code().as_u16();
-        log_simple_request(
-            &app_state,
-            start_time,
-            "DELETE",
-            &format!("/providers/{}/keys", provider_name),
-            REQ_TYPE_PROVIDER_KEY_DELETE,
-            None,
-            Some(provider_name),
-            provided_token.as_deref(),
-            code,
-            Some("auth failed".into()),
-        )
-        .await;
-        return Err(e);
-    }
+            return Err(e);
+        }
+    };
     if !app_state
         .providers
         .provider_exists(&provider_name)
@@ -501,10 +563,11 @@ pub async fn delete_provider_key(
             operation: REQ_TYPE_PROVIDER_KEY_DELETE.to_string(),
             provider: Some(provider_name.clone()),
             details,
+            actor: Some(identity.actor()),
         })
         .await;
     if deleted {
-        invalidate_cache_for_provider(&provider_name).await;
+        crate::server::cluster::provider_changed(&app_state, &provider_name).await;
         log_simple_request(
             &app_state,
             start_time,
@@ -552,34 +615,38 @@ pub async fn delete_provider_keys_batch(
         .and_then(|v| v.to_str().ok())
         .and_then(|s| s.strip_prefix("Bearer "))
         .map(|s| s.to_string());
-    if let Err(e) = require_superadmin(&headers, &app_state).await {
-        let start_time = chrono::Utc::now();
-        let _ = app_state
-            .log_store
-            .log_provider_op(ProviderOpLog {
-                id: None,
-                timestamp: start_time,
-                operation: REQ_TYPE_PROVIDER_KEY_DELETE.to_string(),
-                provider: Some(provider_name.clone()),
-                details: Some(e.to_string()),
-            })
+    let identity = match require_superadmin(&headers, &app_state).await {
+        Ok(identity) => identity,
+        Err(e) => {
+            let start_time = chrono::Utc::now();
+            let _ = app_state
+                .log_store
+                .log_provider_op(ProviderOpLog {
+                    id: None,
+                    timestamp: start_time,
+                    operation: REQ_TYPE_PROVIDER_KEY_DELETE.to_string(),
+                    provider: Some(provider_name.clone()),
+                    details: Some(e.to_string()),
+                    actor: None,
+                })
+                .await;
+            let code = e.status_code().as_u16();
+            log_simple_request(
+                &app_state,
+                start_time,
+                "DELETE",
+                &format!("/providers/{}/keys/batch", provider_name),
+                REQ_TYPE_PROVIDER_KEY_DELETE,
+                None,
+                Some(provider_name.clone()),
+                provided_token.as_deref(),
+                code,
+                Some("auth failed".into()),
+            )
             .await;
-        let code = e.status_code().as_u16();
-        log_simple_request(
-            &app_state,
-            start_time,
-            "DELETE",
-            &format!("/providers/{}/keys/batch", provider_name),
-            REQ_TYPE_PROVIDER_KEY_DELETE,
-            None,
-            Some(provider_name.clone()),
-            provided_token.as_deref(),
-            code,
-            Some("auth failed".into()),
-        )
-        .await;
-        return Err(e);
-    }
+            return Err(e);
+        }
+    };
     if !app_state
         .providers
         .provider_exists(&provider_name)
@@ -651,11 +718,12 @@ pub async fn delete_provider_keys_batch(
             operation: REQ_TYPE_PROVIDER_KEY_DELETE.to_string(),
             provider: Some(provider_name.clone()),
             details: Some(detail),
+            actor: Some(identity.actor()),
         })
         .await;
 
     if removed > 0 {
-        invalidate_cache_for_provider(&provider_name).await;
+        crate::server::cluster::provider_changed(&app_state, &provider_name).await;
     }
 
     log_simple_request(
@@ -684,9 +752,24 @@ pub async fn delete_provider_keys_batch(
         .into_response())
 }
 
+/// 密钥列表按存储顺序（position）排序，原始列表还可按权重排序；
+/// 搜索与解密后才能得到结果，因此在内存中分页
+const KEY_SORT_FIELDS: SortFields = SortFields {
+    allowed: &["position"],
+    default: "position",
+    default_order: SortOrder::Asc,
+};
+
+const RAW_KEY_SORT_FIELDS: SortFields = SortFields {
+    allowed: &["position", "weight"],
+    default: "position",
+    default_order: SortOrder::Asc,
+};
+
 pub async fn list_provider_keys(
     Path(provider_name): Path<String>,
     Query(query): Query<KeysQuery>,
+    Query(page): Query<PageQuery>,
     State(app_state): State<Arc<AppState>>,
     headers: axum::http::HeaderMap,
 ) -> Result<Response, GatewayError> {
@@ -695,34 +778,38 @@ pub async fn list_provider_keys(
         .and_then(|v| v.to_str().ok())
         .and_then(|s| s.strip_prefix("Bearer "))
         .map(|s| s.to_string());
-    if let Err(e) = require_superadmin(&headers, &app_state).await {
-        let start_time = chrono::Utc::now();
-        let _ = app_state
-            .log_store
-            .log_provider_op(ProviderOpLog {
-                id: None,
-                timestamp: start_time,
-                operation: REQ_TYPE_PROVIDER_KEY_LIST.to_string(),
-                provider: Some(provider_name.clone()),
-                details: Some(e.to_string()),
-            })
+    let identity = match require_superadmin(&headers, &app_state).await {
+        Ok(identity) => identity,
+        Err(e) => {
+            let start_time = chrono::Utc::now();
+            let _ = app_state
+                .log_store
+                .log_provider_op(ProviderOpLog {
+                    id: None,
+                    timestamp: start_time,
+                    operation: REQ_TYPE_PROVIDER_KEY_LIST.to_string(),
+                    provider: Some(provider_name.clone()),
+                    details: Some(e.to_string()),
+                    actor: None,
+                })
+                .await;
+            let code = e.status_code().as_u16();
+            log_simple_request(
+                &app_state,
+                start_time,
+                "GET",
+                &format!("/providers/{}/keys", provider_name),
+                REQ_TYPE_PROVIDER_KEY_LIST,
+                None,
+                Some(provider_name),
+                provided_token.as_deref(),
+                code,
+                Some("auth failed".into()),
+            )
             .await;
-        let code = e.status_code().as_u16();
-        log_simple_request(
-            &app_state,
-            start_time,
-            "GET",
-            &format!("/providers/{}/keys", provider_name),
-            REQ_TYPE_PROVIDER_KEY_LIST,
-            None,
-            Some(provider_name),
-            provided_token.as_deref(),
-            code,
-            Some("auth failed".into()),
-        )
-        .await;
-        return Err(e);
-    }
+            return Err(e);
+        }
+    };
     if !app_state
         .providers
         .provider_exists(&provider_name)
@@ -734,6 +821,7 @@ pub async fn list_provider_keys(
             provider_name
         )));
     }
+    let page = page.resolve(&KEY_SORT_FIELDS)?;
     let start_time = Utc::now();
     let keys = app_state
         .providers
@@ -762,6 +850,7 @@ pub async fn list_provider_keys(
             operation: REQ_TYPE_PROVIDER_KEY_LIST.to_string(),
             provider: Some(provider_name.clone()),
             details: None,
+            actor: Some(identity.actor()),
         })
         .await;
     log_simple_request(
@@ -778,16 +867,22 @@ pub async fn list_provider_keys(
     )
     .await;
 
-    Ok((
-        axum::http::StatusCode::OK,
-        Json(serde_json::json!({ "keys": filtered })),
-    )
-        .into_response())
+    let body = match page {
+        Some(page) => serde_json::to_value(
+            page.paginate(filtered.into_iter().enumerate().collect(), |_, a, b| {
+                a.0.cmp(&b.0)
+            })
+            .map(|(_, key)| key),
+        )?,
+        None => serde_json::json!({ "keys": filtered }),
+    };
+    Ok((axum::http::StatusCode::OK, Json(body)).into_response())
 }
 
 pub async fn list_provider_keys_raw(
     Path(provider_name): Path<String>,
     Query(query): Query<KeysQuery>,
+    Query(page): Query<PageQuery>,
     State(app_state): State<Arc<AppState>>,
     headers: axum::http::HeaderMap,
 ) -> Result<Response, GatewayError> {
@@ -796,34 +891,38 @@ pub async fn list_provider_keys_raw(
         .and_then(|v| v.to_str().ok())
         .and_then(|s| s.strip_prefix("Bearer "))
         .map(|s| s.to_string());
-    if let Err(e) = require_superadmin(&headers, &app_state).await {
-        let start_time = chrono::Utc::now();
-        let _ = app_state
-            .log_store
-            .log_provider_op(ProviderOpLog {
-                id: None,
-                timestamp: start_time,
-                operation: REQ_TYPE_PROVIDER_KEY_LIST.to_string(),
-                provider: Some(provider_name.clone()),
-                details: Some(e.to_string()),
-            })
+    let identity = match require_superadmin(&headers, &app_state).await {
+        Ok(identity) => identity,
+        Err(e) => {
+            let start_time = chrono::Utc::now();
+            let _ = app_state
+                .log_store
+                .log_provider_op(ProviderOpLog {
+                    id: None,
+                    timestamp: start_time,
+                    operation: REQ_TYPE_PROVIDER_KEY_LIST.to_string(),
+                    provider: Some(provider_name.clone()),
+                    details: Some(e.to_string()),
+                    actor: None,
+                })
+                .await;
+            let code = e.status_code().as_u16();
+            log_simple_request(
+                &app_state,
+                start_time,
+                "GET",
+                &format!("/providers/{}/keys/raw", provider_name),
+                REQ_TYPE_PROVIDER_KEY_LIST,
+                None,
+                Some(provider_name),
+                provided_token.as_deref(),
+                code,
+                Some("auth failed".into()),
+            )
             .await;
-        let code = e.status_code().as_u16();
-        log_simple_request(
-            &app_state,
-            start_time,
-            "GET",
-            &format!("/providers/{}/keys/raw", provider_name),
-            REQ_TYPE_PROVIDER_KEY_LIST,
-            None,
-            Some(provider_name),
-            provided_token.as_deref(),
-            code,
-            Some("auth failed".into()),
-        )
-        .await;
-        return Err(e);
-    }
+            return Err(e);
+        }
+    };
     if !app_state
         .providers
         .provider_exists(&provider_name)
@@ -835,6 +934,7 @@ pub async fn list_provider_keys_raw(
             provider_name
         )));
     }
+    let page = page.resolve(&RAW_KEY_SORT_FIELDS)?;
     let start_time = Utc::now();
     let keys = app_state
         .providers
@@ -858,6 +958,11 @@ pub async fn list_provider_keys_raw(
             value: entry.value,
             active: entry.active,
             weight: entry.weight,
+            spend_cap: entry.spend_cap,
+            rpm_limit: entry.rpm_limit,
+            tpm_limit: entry.tpm_limit,
+            openai_account: entry.openai_account,
+            labels: entry.labels,
         })
         .collect();
 
@@ -869,6 +974,7 @@ pub async fn list_provider_keys_raw(
             operation: REQ_TYPE_PROVIDER_KEY_LIST.to_string(),
             provider: Some(provider_name.clone()),
             details: Some("raw".into()),
+            actor: Some(identity.actor()),
         })
         .await;
 
@@ -886,14 +992,23 @@ pub async fn list_provider_keys_raw(
     )
     .await;
 
-    Ok((
-        axum::http::StatusCode::OK,
-        Json(serde_json::json!({
+    let body = match page {
+        Some(page) => serde_json::to_value(
+            page.paginate(
+                entries.into_iter().enumerate().collect(),
+                |sort, a, b| match sort {
+                    "weight" => a.1.weight.cmp(&b.1.weight).then(a.0.cmp(&b.0)),
+                    _ => a.0.cmp(&b.0),
+                },
+            )
+            .map(|(_, entry)| entry),
+        )?,
+        None => serde_json::json!({
             "keys": entries,
             "total": total,
-        })),
-    )
-        .into_response())
+        }),
+    };
+    Ok((axum::http::StatusCode::OK, Json(body)).into_response())
 }
 
 pub async fn get_provider_keys_config(
@@ -907,34 +1022,38 @@ pub async fn get_provider_keys_config(
         .and_then(|v| v.to_str().ok())
         .and_then(|s| s.strip_prefix("Bearer "))
         .map(|s| s.to_string());
-    if let Err(e) = require_superadmin(&headers, &app_state).await {
-        let start_time = chrono::Utc::now();
-        let _ = app_state
-            .log_store
-            .log_provider_op(ProviderOpLog {
-                id: None,
-                timestamp: start_time,
-                operation: REQ_TYPE_PROVIDER_KEY_CONFIG_GET.to_string(),
-                provider: Some(provider_name.clone()),
-                details: Some(e.to_string()),
-            })
+    let identity = match require_superadmin(&headers, &app_state).await {
+        Ok(identity) => identity,
+        Err(e) => {
+            let start_time = chrono::Utc::now();
+            let _ = app_state
+                .log_store
+                .log_provider_op(ProviderOpLog {
+                    id: None,
+                    timestamp: start_time,
+                    operation: REQ_TYPE_PROVIDER_KEY_CONFIG_GET.to_string(),
+                    provider: Some(provider_name.clone()),
+                    details: Some(e.to_string()),
+                    actor: None,
+                })
+                .await;
+            let code = e.status_code().as_u16();
+            log_simple_request(
+                &app_state,
+                start_time,
+                "GET",
+                &format!("/providers/{}/keys/config", provider_name),
+                REQ_TYPE_PROVIDER_KEY_CONFIG_GET,
+                None,
+                Some(provider_name),
+                provided_token.as_deref(),
+                code,
+                Some("auth failed".into()),
+            )
             .await;
-        let code = e.status_code().as_u16();
-        log_simple_request(
-            &app_state,
-            start_time,
-            "GET",
-            &format!("/providers/{}/keys/config", provider_name),
-            REQ_TYPE_PROVIDER_KEY_CONFIG_GET,
-            None,
-            Some(provider_name),
-            provided_token.as_deref(),
-            code,
-            Some("auth failed".into()),
-        )
-        .await;
-        return Err(e);
-    }
+            return Err(e);
+        }
+    };
     if !app_state
         .providers
         .provider_exists(&provider_name)
@@ -975,6 +1094,11 @@ pub async fn get_provider_keys_config(
             value: entry.value,
             active: entry.active,
             weight: entry.weight,
+            spend_cap: entry.spend_cap,
+            rpm_limit: entry.rpm_limit,
+            tpm_limit: entry.tpm_limit,
+            openai_account: entry.openai_account,
+            labels: entry.labels,
         })
         .collect();
 
@@ -986,6 +1110,7 @@ pub async fn get_provider_keys_config(
             KeyRotationStrategy::Random,
             KeyRotationStrategy::WeightedSequential,
             KeyRotationStrategy::WeightedRandom,
+            KeyRotationStrategy::UsageWeighted,
         ],
         keys: entries,
         total,
@@ -999,6 +1124,7 @@ pub async fn get_provider_keys_config(
             operation: REQ_TYPE_PROVIDER_KEY_CONFIG_GET.to_string(),
             provider: Some(provider_name.clone()),
             details: None,
+            actor: Some(identity.actor()),
         })
         .await;
 
@@ -1030,34 +1156,38 @@ pub async fn put_provider_keys_config(
         .and_then(|v| v.to_str().ok())
         .and_then(|s| s.strip_prefix("Bearer "))
         .map(|s| s.to_string());
-    if let Err(e) = require_superadmin(&headers, &app_state).await {
-        let start_time = chrono::Utc::now();
-        let _ = app_state
-            .log_store
-            .log_provider_op(ProviderOpLog {
-                id: None,
-                timestamp: start_time,
-                operation: REQ_TYPE_PROVIDER_KEY_CONFIG_SET.to_string(),
-                provider: Some(provider_name.clone()),
-                details: Some(e.to_string()),
-            })
+    let identity = match require_superadmin(&headers, &app_state).await {
+        Ok(identity) => identity,
+        Err(e) => {
+            let start_time = chrono::Utc::now();
+            let _ = app_state
+                .log_store
+                .log_provider_op(ProviderOpLog {
+                    id: None,
+                    timestamp: start_time,
+                    operation: REQ_TYPE_PROVIDER_KEY_CONFIG_SET.to_string(),
+                    provider: Some(provider_name.clone()),
+                    details: Some(e.to_string()),
+                    actor: None,
+                })
+                .await;
+            let code = e.status_code().as_u16();
+            log_simple_request(
+                &app_state,
+                start_time,
+                "PUT",
+                &format!("/providers/{}/keys/config", provider_name),
+                REQ_TYPE_PROVIDER_KEY_CONFIG_SET,
+                None,
+                Some(provider_name),
+                provided_token.as_deref(),
+                code,
+                Some("auth failed".into()),
+            )
             .await;
-        let code = e.status_code().as_u16();
-        log_simple_request(
-            &app_state,
-            start_time,
-            "PUT",
-            &format!("/providers/{}/keys/config", provider_name),
-            REQ_TYPE_PROVIDER_KEY_CONFIG_SET,
-            None,
-            Some(provider_name),
-            provided_token.as_deref(),
-            code,
-            Some("auth failed".into()),
-        )
-        .await;
-        return Err(e);
-    }
+            return Err(e);
+        }
+    };
     if !app_state
         .providers
         .provider_exists(&provider_name)
@@ -1090,10 +1220,12 @@ pub async fn put_provider_keys_config(
                 })
                 .to_string(),
             ),
+            actor: Some(identity.actor()),
         })
         .await;
 
     if updated {
+        crate::server::cluster::provider_changed(&app_state, &provider_name).await;
         log_simple_request(
             &app_state,
             start_time,
@@ -1141,19 +1273,87 @@ pub async fn patch_provider_key_weight(
         .and_then(|v| v.to_str().ok())
         .and_then(|s| s.strip_prefix("Bearer "))
         .map(|s| s.to_string());
-    if let Err(e) = require_superadmin(&headers, &app_state).await {
-        let start_time = chrono::Utc::now();
-        let _ = app_state
-            .log_store
-            .log_provider_op(ProviderOpLog {
-                id: None,
-                timestamp: start_time,
-                operation: REQ_TYPE_PROVIDER_KEY_WEIGHT_SET.to_string(),
-                provider: Some(provider_name.clone()),
-                details: Some(e.to_string()),
-            })
+    let identity = match require_superadmin(&headers, &app_state).await {
+        Ok(identity) => identity,
+        Err(e) => {
+            let start_time = chrono::Utc::now();
+            let _ = app_state
+                .log_store
+                .log_provider_op(ProviderOpLog {
+                    id: None,
+                    timestamp: start_time,
+                    operation: REQ_TYPE_PROVIDER_KEY_WEIGHT_SET.to_string(),
+                    provider: Some(provider_name.clone()),
+                    details: Some(e.to_string()),
+                    actor: None,
+                })
+                .await;
+            let code = e.status_code().as_u16();
+            log_simple_request(
+                &app_state,
+                start_time,
+                "PATCH",
+                &format!("/providers/{}/keys/weight", provider_name),
+                REQ_TYPE_PROVIDER_KEY_WEIGHT_SET,
+                None,
+                Some(provider_name),
+                provided_token.as_deref(),
+                code,
+                Some("auth failed".into()),
+            )
             .await;
-        let code = e.status_code().as_u16();
+            return Err(e);
+        }
+    };
+    if !app_state
+        .providers
+        .provider_exists(&provider_name)
+        .await
+        .map_err(GatewayError::Db)?
+    {
+        return Err(GatewayError::NotFound(format!(
+            "Provider '{}' not found",
+            provider_name
+        )));
+    }
+    if payload.weight < 1 {
+        return Err(GatewayError::Config("weight must be >= 1".into()));
+    }
+
+    let updated = app_state
+        .providers
+        .set_provider_key_weight(
+            &provider_name,
+            &payload.key,
+            payload.weight,
+            &app_state.config.logging.key_log_strategy,
+        )
+        .await
+        .map_err(GatewayError::Db)?;
+
+    let start_time = Utc::now();
+    let key_hint = key_display_hint(&app_state.config.logging.key_log_strategy, &payload.key);
+    let details = key_hint.map(|v| {
+        serde_json::json!({
+            "key": v,
+            "weight": payload.weight,
+        })
+        .to_string()
+    });
+    let _ = app_state
+        .log_store
+        .log_provider_op(ProviderOpLog {
+            id: None,
+            timestamp: start_time,
+            operation: REQ_TYPE_PROVIDER_KEY_WEIGHT_SET.to_string(),
+            provider: Some(provider_name.clone()),
+            details,
+            actor: Some(identity.actor()),
+        })
+        .await;
+
+    if updated {
+        crate::server::cluster::provider_changed(&app_state, &provider_name).await;
         log_simple_request(
             &app_state,
             start_time,
@@ -1163,12 +1363,76 @@ pub async fn patch_provider_key_weight(
             None,
             Some(provider_name),
             provided_token.as_deref(),
-            code,
-            Some("auth failed".into()),
+            200,
+            None,
         )
         .await;
-        return Err(e);
+        Ok((
+            axum::http::StatusCode::OK,
+            Json(serde_json::json!({ "success": true })),
+        )
+            .into_response())
+    } else {
+        log_simple_request(
+            &app_state,
+            start_time,
+            "PATCH",
+            &format!("/providers/{}/keys/weight", provider_name),
+            REQ_TYPE_PROVIDER_KEY_WEIGHT_SET,
+            None,
+            Some(provider_name.clone()),
+            provided_token.as_deref(),
+            404,
+            Some("key not found".into()),
+        )
+        .await;
+        Err(GatewayError::NotFound("key not found".into()))
     }
+}
+
+pub async fn patch_provider_key_limits(
+    Path(provider_name): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+    Json(payload): Json<KeyLimitsPayload>,
+) -> Result<Response, GatewayError> {
+    let provided_token = headers
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|s| s.strip_prefix("Bearer "))
+        .map(|s| s.to_string());
+    let identity = match require_superadmin(&headers, &app_state).await {
+        Ok(identity) => identity,
+        Err(e) => {
+            let start_time = chrono::Utc::now();
+            let _ = app_state
+                .log_store
+                .log_provider_op(ProviderOpLog {
+                    id: None,
+                    timestamp: start_time,
+                    operation: REQ_TYPE_PROVIDER_KEY_LIMITS_SET.to_string(),
+                    provider: Some(provider_name.clone()),
+                    details: Some(e.to_string()),
+                    actor: None,
+                })
+                .await;
+            let code = e.status_code().as_u16();
+            log_simple_request(
+                &app_state,
+                start_time,
+                "PATCH",
+                &format!("/providers/{}/keys/limits", provider_name),
+                REQ_TYPE_PROVIDER_KEY_LIMITS_SET,
+                None,
+                Some(provider_name),
+                provided_token.as_deref(),
+                code,
+                Some("auth failed".into()),
+            )
+            .await;
+            return Err(e);
+        }
+    };
     if !app_state
         .providers
         .provider_exists(&provider_name)
@@ -1180,16 +1444,27 @@ pub async fn patch_provider_key_weight(
             provider_name
         )));
     }
-    if payload.weight < 1 {
-        return Err(GatewayError::Config("weight must be >= 1".into()));
+    if payload
+        .spend_cap
+        .is_some_and(|cap| !cap.is_finite() || cap <= 0.0)
+    {
+        return Err(GatewayError::Config("spend_cap must be > 0".into()));
+    }
+    if payload.rpm_limit == Some(0) {
+        return Err(GatewayError::Config("rpm_limit must be >= 1".into()));
+    }
+    if payload.tpm_limit == Some(0) {
+        return Err(GatewayError::Config("tpm_limit must be >= 1".into()));
     }
 
     let updated = app_state
         .providers
-        .set_provider_key_weight(
+        .set_provider_key_limits(
             &provider_name,
             &payload.key,
-            payload.weight,
+            payload.spend_cap,
+            payload.rpm_limit,
+            payload.tpm_limit,
             &app_state.config.logging.key_log_strategy,
         )
         .await
@@ -1200,7 +1475,9 @@ pub async fn patch_provider_key_weight(
     let details = key_hint.map(|v| {
         serde_json::json!({
             "key": v,
-            "weight": payload.weight,
+            "spend_cap": payload.spend_cap,
+            "rpm_limit": payload.rpm_limit,
+            "tpm_limit": payload.tpm_limit,
         })
         .to_string()
     });
@@ -1209,19 +1486,21 @@ pub async fn patch_provider_key_weight(
         .log_provider_op(ProviderOpLog {
             id: None,
             timestamp: start_time,
-            operation: REQ_TYPE_PROVIDER_KEY_WEIGHT_SET.to_string(),
+            operation: REQ_TYPE_PROVIDER_KEY_LIMITS_SET.to_string(),
             provider: Some(provider_name.clone()),
             details,
+            actor: Some(identity.actor()),
         })
         .await;
 
     if updated {
+        crate::server::cluster::provider_changed(&app_state, &provider_name).await;
         log_simple_request(
             &app_state,
             start_time,
             "PATCH",
-            &format!("/providers/{}/keys/weight", provider_name),
-            REQ_TYPE_PROVIDER_KEY_WEIGHT_SET,
+            &format!("/providers/{}/keys/limits", provider_name),
+            REQ_TYPE_PROVIDER_KEY_LIMITS_SET,
             None,
             Some(provider_name),
             provided_token.as_deref(),
@@ -1239,8 +1518,326 @@ pub async fn patch_provider_key_weight(
             &app_state,
             start_time,
             "PATCH",
-            &format!("/providers/{}/keys/weight", provider_name),
-            REQ_TYPE_PROVIDER_KEY_WEIGHT_SET,
+            &format!("/providers/{}/keys/limits", provider_name),
+            REQ_TYPE_PROVIDER_KEY_LIMITS_SET,
+            None,
+            Some(provider_name.clone()),
+            provided_token.as_deref(),
+            404,
+            Some("key not found".into()),
+        )
+        .await;
+        Err(GatewayError::NotFound("key not found".into()))
+    }
+}
+
+/// 去除首尾空白，空串视为未设置；值必须是合法的 HTTP 头
+fn normalize_openai_header(
+    name: &str,
+    value: Option<String>,
+) -> Result<Option<String>, GatewayError> {
+    let Some(value) = value
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+    else {
+        return Ok(None);
+    };
+    if axum::http::HeaderValue::from_str(&value).is_err() {
+        return Err(GatewayError::Config(format!(
+            "{} is not a valid header value",
+            name
+        )));
+    }
+    Ok(Some(value))
+}
+
+pub async fn patch_provider_key_openai_headers(
+    Path(provider_name): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+    Json(payload): Json<KeyOpenAIHeadersPayload>,
+) -> Result<Response, GatewayError> {
+    let provided_token = headers
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|s| s.strip_prefix("Bearer "))
+        .map(|s| s.to_string());
+    let path = format!("/providers/{}/keys/openai-headers", provider_name);
+    let identity = match require_superadmin(&headers, &app_state).await {
+        Ok(identity) => identity,
+        Err(e) => {
+            let start_time = chrono::Utc::now();
+            let _ = app_state
+                .log_store
+                .log_provider_op(ProviderOpLog {
+                    id: None,
+                    timestamp: start_time,
+                    operation: REQ_TYPE_PROVIDER_KEY_OPENAI_HEADERS_SET.to_string(),
+                    provider: Some(provider_name.clone()),
+                    details: Some(e.to_string()),
+                    actor: None,
+                })
+                .await;
+            let code = e.status_code().as_u16();
+            log_simple_request(
+                &app_state,
+                start_time,
+                "PATCH",
+                &path,
+                REQ_TYPE_PROVIDER_KEY_OPENAI_HEADERS_SET,
+                None,
+                Some(provider_name),
+                provided_token.as_deref(),
+                code,
+                Some("auth failed".into()),
+            )
+            .await;
+            return Err(e);
+        }
+    };
+    if !app_state
+        .providers
+        .provider_exists(&provider_name)
+        .await
+        .map_err(GatewayError::Db)?
+    {
+        return Err(GatewayError::NotFound(format!(
+            "Provider '{}' not found",
+            provider_name
+        )));
+    }
+    let account = OpenAIAccountHeaders {
+        openai_organization: normalize_openai_header(
+            "openai_organization",
+            payload.openai_organization,
+        )?,
+        openai_project: normalize_openai_header("openai_project", payload.openai_project)?,
+    };
+
+    let updated = app_state
+        .providers
+        .set_provider_key_openai_headers(
+            &provider_name,
+            &payload.key,
+            &account,
+            &app_state.config.logging.key_log_strategy,
+        )
+        .await
+        .map_err(GatewayError::Db)?;
+
+    let start_time = Utc::now();
+    let key_hint = key_display_hint(&app_state.config.logging.key_log_strategy, &payload.key);
+    let details = key_hint.map(|v| {
+        serde_json::json!({
+            "key": v,
+            "openai_organization": account.openai_organization,
+            "openai_project": account.openai_project,
+        })
+        .to_string()
+    });
+    let _ = app_state
+        .log_store
+        .log_provider_op(ProviderOpLog {
+            id: None,
+            timestamp: start_time,
+            operation: REQ_TYPE_PROVIDER_KEY_OPENAI_HEADERS_SET.to_string(),
+            provider: Some(provider_name.clone()),
+            details,
+            actor: Some(identity.actor()),
+        })
+        .await;
+
+    if updated {
+        crate::server::cluster::provider_changed(&app_state, &provider_name).await;
+        log_simple_request(
+            &app_state,
+            start_time,
+            "PATCH",
+            &path,
+            REQ_TYPE_PROVIDER_KEY_OPENAI_HEADERS_SET,
+            None,
+            Some(provider_name),
+            provided_token.as_deref(),
+            200,
+            None,
+        )
+        .await;
+        Ok((
+            axum::http::StatusCode::OK,
+            Json(serde_json::json!({ "success": true })),
+        )
+            .into_response())
+    } else {
+        log_simple_request(
+            &app_state,
+            start_time,
+            "PATCH",
+            &path,
+            REQ_TYPE_PROVIDER_KEY_OPENAI_HEADERS_SET,
+            None,
+            Some(provider_name.clone()),
+            provided_token.as_deref(),
+            404,
+            Some("key not found".into()),
+        )
+        .await;
+        Err(GatewayError::NotFound("key not found".into()))
+    }
+}
+
+const MAX_KEY_LABELS: usize = 16;
+const MAX_KEY_LABEL_LEN: usize = 64;
+
+/// 标签名限字母、数字与 `_`、`-`、`.`；值去除首尾空白后不能为空
+fn normalize_key_labels(
+    labels: BTreeMap<String, String>,
+) -> Result<BTreeMap<String, String>, GatewayError> {
+    if labels.len() > MAX_KEY_LABELS {
+        return Err(GatewayError::Config(format!(
+            "a key may have at most {} labels",
+            MAX_KEY_LABELS
+        )));
+    }
+    let mut out = BTreeMap::new();
+    for (name, value) in labels {
+        let name = name.trim().to_string();
+        let value = value.trim().to_string();
+        if name.is_empty()
+            || name.len() > MAX_KEY_LABEL_LEN
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+        {
+            return Err(GatewayError::Config(format!(
+                "invalid label name '{}': use up to {} letters, digits, '_', '-' or '.'",
+                name, MAX_KEY_LABEL_LEN
+            )));
+        }
+        if value.is_empty()
+            || value.len() > MAX_KEY_LABEL_LEN
+            || value.chars().any(char::is_control)
+        {
+            return Err(GatewayError::Config(format!(
+                "invalid value for label '{}'",
+                name
+            )));
+        }
+        out.insert(name, value);
+    }
+    Ok(out)
+}
+
+/// 设置密钥的分组标签（如 region=eu、tier=paid），供 `key_label_rules` 路由规则匹配
+pub async fn patch_provider_key_labels(
+    Path(provider_name): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+    Json(payload): Json<KeyLabelsPayload>,
+) -> Result<Response, GatewayError> {
+    let provided_token = headers
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|s| s.strip_prefix("Bearer "))
+        .map(|s| s.to_string());
+    let path = format!("/providers/{}/keys/labels", provider_name);
+    let identity = match require_superadmin(&headers, &app_state).await {
+        Ok(identity) => identity,
+        Err(e) => {
+            let start_time = chrono::Utc::now();
+            let _ = app_state
+                .log_store
+                .log_provider_op(ProviderOpLog {
+                    id: None,
+                    timestamp: start_time,
+                    operation: REQ_TYPE_PROVIDER_KEY_LABELS_SET.to_string(),
+                    provider: Some(provider_name.clone()),
+                    details: Some(e.to_string()),
+                    actor: None,
+                })
+                .await;
+            let code = e.status_code().as_u16();
+            log_simple_request(
+                &app_state,
+                start_time,
+                "PATCH",
+                &path,
+                REQ_TYPE_PROVIDER_KEY_LABELS_SET,
+                None,
+                Some(provider_name),
+                provided_token.as_deref(),
+                code,
+                Some("auth failed".into()),
+            )
+            .await;
+            return Err(e);
+        }
+    };
+    if !app_state
+        .providers
+        .provider_exists(&provider_name)
+        .await
+        .map_err(GatewayError::Db)?
+    {
+        return Err(GatewayError::NotFound(format!(
+            "Provider '{}' not found",
+            provider_name
+        )));
+    }
+    let labels = normalize_key_labels(payload.labels)?;
+
+    let updated = app_state
+        .providers
+        .set_provider_key_labels(
+            &provider_name,
+            &payload.key,
+            &labels,
+            &app_state.config.logging.key_log_strategy,
+        )
+        .await
+        .map_err(GatewayError::Db)?;
+
+    let start_time = Utc::now();
+    let key_hint = key_display_hint(&app_state.config.logging.key_log_strategy, &payload.key);
+    let details = key_hint.map(|v| serde_json::json!({ "key": v, "labels": labels }).to_string());
+    let _ = app_state
+        .log_store
+        .log_provider_op(ProviderOpLog {
+            id: None,
+            timestamp: start_time,
+            operation: REQ_TYPE_PROVIDER_KEY_LABELS_SET.to_string(),
+            provider: Some(provider_name.clone()),
+            details,
+            actor: Some(identity.actor()),
+        })
+        .await;
+
+    if updated {
+        crate::server::cluster::provider_changed(&app_state, &provider_name).await;
+        log_simple_request(
+            &app_state,
+            start_time,
+            "PATCH",
+            &path,
+            REQ_TYPE_PROVIDER_KEY_LABELS_SET,
+            None,
+            Some(provider_name),
+            provided_token.as_deref(),
+            200,
+            None,
+        )
+        .await;
+        Ok((
+            axum::http::StatusCode::OK,
+            Json(serde_json::json!({ "success": true })),
+        )
+            .into_response())
+    } else {
+        log_simple_request(
+            &app_state,
+            start_time,
+            "PATCH",
+            &path,
+            REQ_TYPE_PROVIDER_KEY_LABELS_SET,
             None,
             Some(provider_name.clone()),
             provided_token.as_deref(),
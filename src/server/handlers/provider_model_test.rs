@@ -120,7 +120,7 @@ fn map_model_discovery_error(err: GatewayError) -> (String, Option<String>) {
     }
 }
 
-async fn resolve_models_url(
+pub(super) async fn resolve_models_url(
     base_url: &reqwest::Url,
     models_endpoint: Option<&str>,
 ) -> Result<reqwest::Url, (String, Option<String>)> {
@@ -428,6 +428,7 @@ pub async fn test_provider_model(
 
     if api_key.trim().is_empty()
         && !provider_uses_inline_credentials(provider.api_type, &provider.provider_config)
+        && !provider.api_type.allows_keyless()
     {
         let resp = Json(ProviderModelTestResponse {
             success: false,
@@ -552,7 +553,10 @@ pub async fn test_provider_model_draft(
     }
 
     let api_key = payload.api_key.unwrap_or_default().trim().to_string();
-    if api_key.is_empty() && !provider_uses_inline_credentials(api_type, &payload.provider_config) {
+    if api_key.is_empty()
+        && !provider_uses_inline_credentials(api_type, &payload.provider_config)
+        && !api_type.allows_keyless()
+    {
         let resp = Json(failure_response(
             "configuration_required",
             "api_key 不能为空",
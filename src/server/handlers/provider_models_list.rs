@@ -76,7 +76,7 @@ fn fingerprint_api_key(api_key: &str) -> Option<String> {
     Some(format!("sha256:{}", &hex[..16]))
 }
 
-pub(super) async fn invalidate_cache_for_provider(provider: &str) {
+pub(crate) async fn invalidate_cache_for_provider(provider: &str) {
     let trimmed = provider.trim();
     if trimmed.is_empty() {
         return;
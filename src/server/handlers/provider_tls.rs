@@ -0,0 +1,84 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::HeaderMap,
+};
+use chrono::Utc;
+use serde::Serialize;
+use std::sync::Arc;
+
+use super::auth::require_superadmin;
+use crate::error::GatewayError;
+use crate::logging::types::REQ_TYPE_PROVIDER_TLS_CERTIFICATE;
+use crate::server::AppState;
+use crate::server::request_logging::log_simple_request;
+use crate::server::ssrf::validate_outbound_base_url;
+use crate::server::util::{bearer_token, token_for_log};
+use crate::tls_pinning::{PeerCertificate, fetch_peer_certificate};
+
+#[derive(Debug, Serialize)]
+pub struct ProviderTlsCertificate {
+    pub provider: String,
+    pub base_url: String,
+    pub certificate: PeerCertificate,
+    /// 当前配置的 pin
+    pub tls_pins: Vec<String>,
+    /// 上游证书是否命中已配置的 pin；未配置 pin 时为 null
+    pub pin_matches: Option<bool>,
+}
+
+/// 获取上游当前证书的 SPKI pin 与指纹，用于配置或核对 `tls_pins`
+pub async fn get_provider_tls_certificate(
+    Path(provider_name): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<ProviderTlsCertificate>, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    let result: Result<ProviderTlsCertificate, GatewayError> = async {
+        require_superadmin(&headers, &app_state).await?;
+        let provider = app_state
+            .providers
+            .get_provider(&provider_name)
+            .await?
+            .ok_or_else(|| {
+                GatewayError::NotFound(format!("Provider '{}' not found", provider_name))
+            })?;
+        let url = validate_outbound_base_url(&provider.base_url).await?;
+        if url.scheme() != "https" {
+            return Err(GatewayError::Config(
+                "base_url uses plain http, no certificate to fetch".into(),
+            ));
+        }
+        let certificate = fetch_peer_certificate(&url).await?;
+        let tls_pins = provider.provider_config.tls_pins().to_vec();
+        let pin_matches = (!tls_pins.is_empty())
+            .then(|| tls_pins.iter().any(|p| p.trim() == certificate.spki_pin));
+        Ok(ProviderTlsCertificate {
+            provider: provider.name,
+            base_url: provider.base_url,
+            certificate,
+            tls_pins,
+            pin_matches,
+        })
+    }
+    .await;
+    let (code, err) = match &result {
+        Ok(_) => (200, None),
+        Err(e) => (e.status_code().as_u16(), Some(e.to_string())),
+    };
+    log_simple_request(
+        &app_state,
+        start_time,
+        "GET",
+        &format!("/admin/providers/{}/tls-certificate", provider_name),
+        REQ_TYPE_PROVIDER_TLS_CERTIFICATE,
+        None,
+        Some(provider_name.clone()),
+        token_for_log(provided_token.as_deref()),
+        code,
+        err,
+    )
+    .await;
+    result.map(Json)
+}
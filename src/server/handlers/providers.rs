@@ -1,6 +1,6 @@
 use axum::{
     Json,
-    extract::{Path, State},
+    extract::{Path, Query, State},
     response::{IntoResponse, Response},
 };
 use serde::{Deserialize, Serialize};
@@ -13,11 +13,13 @@ use crate::config::settings::{
 };
 use crate::error::GatewayError;
 use crate::logging::types::{
-    ProviderOpLog, REQ_TYPE_PROVIDER_CREATE, REQ_TYPE_PROVIDER_DELETE,
-    REQ_TYPE_PROVIDER_ENABLED_SET, REQ_TYPE_PROVIDER_FAVORITE_SET, REQ_TYPE_PROVIDER_GET,
-    REQ_TYPE_PROVIDER_LIST, REQ_TYPE_PROVIDER_UPDATE,
+    ProviderOpLog, REQ_TYPE_PROVIDER_COLLECTION_ASSIGN, REQ_TYPE_PROVIDER_CREATE,
+    REQ_TYPE_PROVIDER_DELETE, REQ_TYPE_PROVIDER_ENABLED_SET, REQ_TYPE_PROVIDER_FAVORITE_SET,
+    REQ_TYPE_PROVIDER_GET, REQ_TYPE_PROVIDER_LIST, REQ_TYPE_PROVIDER_UPDATE,
 };
+use crate::providers::adapters::validate_api_version;
 use crate::server::AppState;
+use crate::server::pagination::{Listing, Page, PageQuery, SortFields, SortOrder};
 use crate::server::request_logging::log_simple_request;
 use crate::server::storage_traits::FavoriteKind;
 use crate::server::util::{bearer_token, mask_key, token_for_log};
@@ -108,11 +110,19 @@ impl ProviderOut {
     }
 }
 
+const PROVIDER_SORT_FIELDS: SortFields = SortFields {
+    allowed: &["name", "collection", "api_type", "created_at", "updated_at"],
+    default: "name",
+    default_order: SortOrder::Asc,
+};
+
 pub async fn list_providers(
     State(app_state): State<Arc<AppState>>,
     headers: axum::http::HeaderMap,
-) -> Result<Json<Vec<ProviderOut>>, GatewayError> {
-    require_superadmin(&headers, &app_state).await?;
+    Query(page): Query<PageQuery>,
+) -> Result<Json<Listing<ProviderOut>>, GatewayError> {
+    let identity = require_superadmin(&headers, &app_state).await?;
+    let page = page.resolve(&PROVIDER_SORT_FIELDS)?;
     let start_time = Utc::now();
     let provided_token = bearer_token(&headers);
 
@@ -136,18 +146,39 @@ pub async fn list_providers(
         .into_iter()
         .collect();
 
-    let providers = app_state
-        .providers
-        .list_providers_with_keys(&app_state.config.logging.key_log_strategy)
-        .await
-        .map_err(GatewayError::Db)?
-        .into_iter()
-        .map(|p| {
-            let count = cached_counts.get(&p.name).copied().unwrap_or(0);
-            let is_favorite = favorites.contains(&p.name);
-            ProviderOut::from_provider(p, count, is_favorite)
-        })
-        .collect();
+    let to_out = |p: Provider| {
+        let count = cached_counts.get(&p.name).copied().unwrap_or(0);
+        let is_favorite = favorites.contains(&p.name);
+        ProviderOut::from_provider(p, count, is_favorite)
+    };
+    let strategy = &app_state.config.logging.key_log_strategy;
+    let providers = match page {
+        Some(page) => {
+            let (mut providers, total) = app_state
+                .providers
+                .list_providers_page(&page)
+                .await
+                .map_err(GatewayError::Db)?;
+            for p in &mut providers {
+                p.api_keys = app_state
+                    .providers
+                    .get_provider_keys(&p.name, strategy)
+                    .await
+                    .map_err(GatewayError::Db)?;
+            }
+            Listing::Page(Page::new(providers, total, &page).map(to_out))
+        }
+        None => Listing::All(
+            app_state
+                .providers
+                .list_providers_with_keys(strategy)
+                .await
+                .map_err(GatewayError::Db)?
+                .into_iter()
+                .map(to_out)
+                .collect(),
+        ),
+    };
     // audit log
     let _ = app_state
         .log_store
@@ -157,6 +188,7 @@ pub async fn list_providers(
             operation: REQ_TYPE_PROVIDER_LIST.to_string(),
             provider: None,
             details: None,
+            actor: Some(identity.actor()),
         })
         .await;
     // request log
@@ -182,7 +214,7 @@ pub async fn get_provider(
     State(app_state): State<Arc<AppState>>,
     headers: axum::http::HeaderMap,
 ) -> Result<Json<ProviderOut>, GatewayError> {
-    require_superadmin(&headers, &app_state).await?;
+    let identity = require_superadmin(&headers, &app_state).await?;
     let start_time = Utc::now();
     let provided_token = bearer_token(&headers);
     match app_state
@@ -216,6 +248,7 @@ pub async fn get_provider(
                     operation: REQ_TYPE_PROVIDER_GET.to_string(),
                     provider: Some(name.clone()),
                     details: None,
+                    actor: Some(identity.actor()),
                 })
                 .await;
             let token_log = token_for_log(provided_token.as_deref());
@@ -266,12 +299,14 @@ pub async fn create_provider(
     headers: axum::http::HeaderMap,
     Json(payload): Json<ProviderCreatePayload>,
 ) -> Result<Json<ProviderOut>, GatewayError> {
-    require_superadmin(&headers, &app_state).await?;
+    let identity = require_superadmin(&headers, &app_state).await?;
     let start_time = Utc::now();
     let provided_token = bearer_token(&headers);
     if payload.name.trim().is_empty() {
         return Err(GatewayError::Config("name cannot be empty".into()));
     }
+    validate_api_version(payload.api_type, &payload.provider_config)?;
+    crate::tls_pinning::validate_pins(&payload.base_url, &payload.provider_config)?;
     if app_state
         .providers
         .provider_exists(&payload.name)
@@ -320,6 +355,7 @@ pub async fn create_provider(
                     operation: REQ_TYPE_PROVIDER_CREATE.to_string(),
                     provider: Some(p.name.clone()),
                     details: Some(format!("error: {}", e)),
+                    actor: Some(identity.actor()),
                 })
                 .await;
             let ge = GatewayError::Db(e);
@@ -344,6 +380,7 @@ pub async fn create_provider(
     if !inserted {
         return Err(GatewayError::Config("provider already exists".into()));
     }
+    crate::server::cluster::provider_changed(&app_state, &p.name).await;
     app_state
         .providers
         .create_provider_collection(&p.collection)
@@ -365,6 +402,7 @@ pub async fn create_provider(
                 }))
                 .unwrap_or_default(),
             ),
+            actor: Some(identity.actor()),
         })
         .await;
     let token_for_log = provided_token.as_deref();
@@ -395,7 +433,9 @@ pub async fn update_provider(
     headers: axum::http::HeaderMap,
     Json(payload): Json<ProviderUpdatePayload>,
 ) -> Result<Json<ProviderOut>, GatewayError> {
-    require_superadmin(&headers, &app_state).await?;
+    let identity = require_superadmin(&headers, &app_state).await?;
+    validate_api_version(payload.api_type, &payload.provider_config)?;
+    crate::tls_pinning::validate_pins(&payload.base_url, &payload.provider_config)?;
     let start_time = Utc::now();
     let provided_token = bearer_token(&headers);
     let existed = app_state
@@ -470,6 +510,7 @@ pub async fn update_provider(
         .get_provider_keys(&name, &app_state.config.logging.key_log_strategy)
         .await
         .map_err(GatewayError::Db)?;
+    crate::server::cluster::provider_changed(&app_state, &name).await;
     let _ = app_state
         .log_store
         .log_provider_op(ProviderOpLog {
@@ -486,6 +527,7 @@ pub async fn update_provider(
                 }))
                 .unwrap_or_default(),
             ),
+            actor: Some(identity.actor()),
         })
         .await;
     let token_log = token_for_log(provided_token.as_deref());
@@ -565,6 +607,155 @@ pub async fn create_provider_collection(
     Ok(Json(ProviderCollectionOut { name }))
 }
 
+#[derive(Debug, Deserialize)]
+pub struct ProviderCollectionAssignPayload {
+    pub providers: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProviderCollectionDetailOut {
+    pub name: String,
+    pub providers: Vec<String>,
+}
+
+async fn collection_detail(
+    app_state: &AppState,
+    name: String,
+) -> Result<ProviderCollectionDetailOut, GatewayError> {
+    let providers = app_state
+        .providers
+        .list_providers()
+        .await
+        .map_err(GatewayError::Db)?
+        .into_iter()
+        .filter(|p| normalize_collection(Some(p.collection.clone())) == name)
+        .map(|p| p.name)
+        .collect();
+    Ok(ProviderCollectionDetailOut { name, providers })
+}
+
+pub async fn get_provider_collection(
+    Path(collection): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+) -> Result<Json<ProviderCollectionDetailOut>, GatewayError> {
+    require_superadmin(&headers, &app_state).await?;
+    let name = normalize_collection(Some(collection));
+    let known = name == DEFAULT_PROVIDER_COLLECTION
+        || app_state
+            .providers
+            .list_provider_collections()
+            .await
+            .map_err(GatewayError::Db)?
+            .contains(&name);
+    if !known {
+        return Err(GatewayError::NotFound(format!(
+            "Collection '{}' not found",
+            name
+        )));
+    }
+    Ok(Json(collection_detail(&app_state, name).await?))
+}
+
+/// 将供应商移入指定合集（合集不存在时自动创建）；请求模型可使用 "<合集>/<模型>" 在合集内负载均衡
+pub async fn assign_providers_to_collection(
+    Path(collection): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+    Json(payload): Json<ProviderCollectionAssignPayload>,
+) -> Result<Json<ProviderCollectionDetailOut>, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    let path = format!("/providers/collections/{}/providers", collection);
+    let identity = match require_superadmin(&headers, &app_state).await {
+        Ok(identity) => identity,
+        Err(e) => {
+            log_simple_request(
+                &app_state,
+                start_time,
+                "POST",
+                &path,
+                REQ_TYPE_PROVIDER_COLLECTION_ASSIGN,
+                None,
+                None,
+                provided_token.as_deref(),
+                e.status_code().as_u16(),
+                Some(e.to_string()),
+            )
+            .await;
+            return Err(e);
+        }
+    };
+
+    let result = async {
+        let name = normalize_collection(Some(collection.clone()));
+        let mut targets = Vec::with_capacity(payload.providers.len());
+        for provider_name in &payload.providers {
+            let provider = app_state
+                .providers
+                .get_provider(provider_name)
+                .await
+                .map_err(GatewayError::Db)?
+                .ok_or_else(|| {
+                    GatewayError::NotFound(format!("Provider '{}' not found", provider_name))
+                })?;
+            targets.push(provider);
+        }
+        app_state
+            .providers
+            .create_provider_collection(&name)
+            .await
+            .map_err(GatewayError::Db)?;
+        for mut provider in targets {
+            if provider.collection == name {
+                continue;
+            }
+            provider.collection = name.clone();
+            provider.updated_at = Some(start_time);
+            app_state
+                .providers
+                .upsert_provider(&provider)
+                .await
+                .map_err(GatewayError::Db)?;
+        }
+        let _ = app_state
+            .log_store
+            .log_provider_op(ProviderOpLog {
+                id: None,
+                timestamp: start_time,
+                operation: REQ_TYPE_PROVIDER_COLLECTION_ASSIGN.to_string(),
+                provider: None,
+                details: Some(
+                    serde_json::json!({ "collection": name, "providers": payload.providers })
+                        .to_string(),
+                ),
+                actor: Some(identity.actor()),
+            })
+            .await;
+        collection_detail(&app_state, name).await
+    }
+    .await;
+
+    let (code, err) = match &result {
+        Ok(_) => (200, None),
+        Err(e) => (e.status_code().as_u16(), Some(e.to_string())),
+    };
+    log_simple_request(
+        &app_state,
+        start_time,
+        "POST",
+        &path,
+        REQ_TYPE_PROVIDER_COLLECTION_ASSIGN,
+        None,
+        None,
+        token_for_log(provided_token.as_deref()),
+        code,
+        err,
+    )
+    .await;
+    result.map(Json)
+}
+
 #[derive(Debug, Deserialize)]
 pub struct ProviderFavoritePayload {
     pub favorite: bool,
@@ -683,34 +874,38 @@ pub async fn toggle_provider(
     let provided_token = bearer_token(&headers);
     let path = format!("/providers/{}/toggle", name);
 
-    if let Err(e) = require_superadmin(&headers, &app_state).await {
-        let _ = app_state
-            .log_store
-            .log_provider_op(ProviderOpLog {
-                id: None,
-                timestamp: start_time,
-                operation: REQ_TYPE_PROVIDER_ENABLED_SET.to_string(),
-                provider: Some(name.clone()),
-                details: Some(e.to_string()),
-            })
+    let identity = match require_superadmin(&headers, &app_state).await {
+        Ok(identity) => identity,
+        Err(e) => {
+            let _ = app_state
+                .log_store
+                .log_provider_op(ProviderOpLog {
+                    id: None,
+                    timestamp: start_time,
+                    operation: REQ_TYPE_PROVIDER_ENABLED_SET.to_string(),
+                    provider: Some(name.clone()),
+                    details: Some(e.to_string()),
+                    actor: None,
+                })
+                .await;
+            let code = e.status_code().as_u16();
+            let token_log = token_for_log(provided_token.as_deref());
+            log_simple_request(
+                &app_state,
+                start_time,
+                "POST",
+                &path,
+                REQ_TYPE_PROVIDER_ENABLED_SET,
+                None,
+                Some(name.clone()),
+                token_log,
+                code,
+                Some("auth failed".into()),
+            )
             .await;
-        let code = e.status_code().as_u16();
-        let token_log = token_for_log(provided_token.as_deref());
-        log_simple_request(
-            &app_state,
-            start_time,
-            "POST",
-            &path,
-            REQ_TYPE_PROVIDER_ENABLED_SET,
-            None,
-            Some(name.clone()),
-            token_log,
-            code,
-            Some("auth failed".into()),
-        )
-        .await;
-        return Err(e);
-    }
+            return Err(e);
+        }
+    };
 
     if !app_state
         .providers
@@ -755,10 +950,12 @@ pub async fn toggle_provider(
                 serde_json::to_string(&serde_json::json!({ "enabled": payload.enabled }))
                     .unwrap_or_default(),
             ),
+            actor: Some(identity.actor()),
         })
         .await;
 
     if updated {
+        crate::server::cluster::provider_changed(&app_state, &name).await;
         let token_log = token_for_log(provided_token.as_deref());
         log_simple_request(
             &app_state,
@@ -806,7 +1003,6 @@ mod tests {
     use crate::config::BalanceStrategy;
     use crate::config::settings::{LoadBalancing, LoggingConfig, ServerConfig};
     use crate::logging::DatabaseLogger;
-    use crate::server::login::LoginManager;
     use crate::server::storage_traits::{AdminPublicKeyRecord, LoginStore, TuiSessionRecord};
     use axum::http::HeaderMap;
     use axum::http::{HeaderValue, header::AUTHORIZATION};
@@ -852,6 +1048,7 @@ mod tests {
                 enabled: true,
                 created_at: now,
                 last_used_at: None,
+                expires_at: None,
             })
             .await
             .unwrap();
@@ -869,25 +1066,7 @@ mod tests {
             .await
             .unwrap();
 
-        let refresh_token_store = logger.clone();
-        let password_reset_token_store = logger.clone();
-
-        let app_state = Arc::new(AppState {
-            config: settings,
-            load_balancer_state: Arc::new(crate::routing::LoadBalancerState::default()),
-            log_store: logger.clone(),
-            model_cache: logger.clone(),
-            providers: logger.clone(),
-            token_store: logger.clone(),
-            favorites_store: logger.clone(),
-            organizations: logger.clone(),
-            login_manager: Arc::new(LoginManager::new(logger.clone())),
-            user_store: logger.clone(),
-            refresh_token_store,
-            password_reset_token_store,
-            balance_store: logger.clone(),
-            subscription_store: logger.clone(),
-        });
+        let app_state = crate::server::test_harness::app_state(settings, logger.clone());
 
         Harness {
             _dir: dir,
@@ -960,6 +1139,75 @@ mod tests {
         assert_eq!(fetched.created_at.as_deref(), Some(created_at.as_str()));
     }
 
+    #[tokio::test]
+    async fn providers_can_be_assigned_to_collection_and_routed_by_collection_prefix() {
+        let h = harness().await;
+        let headers = auth_headers(&h.token);
+        for name in ["cheap", "premium"] {
+            let _ = create_provider(
+                State(h.state.clone()),
+                headers.clone(),
+                Json(ProviderCreatePayload {
+                    name: name.into(),
+                    display_name: None,
+                    collection: None,
+                    api_type: ProviderType::OpenAI,
+                    base_url: "http://example.com".into(),
+                    models_endpoint: None,
+                    provider_config: ProviderConfig::default(),
+                }),
+            )
+            .await
+            .unwrap();
+            h.state
+                .providers
+                .add_provider_key(name, &format!("sk-{}-key", name), &None)
+                .await
+                .unwrap();
+        }
+
+        let Json(detail) = assign_providers_to_collection(
+            Path("premium-pool".into()),
+            State(h.state.clone()),
+            headers.clone(),
+            Json(ProviderCollectionAssignPayload {
+                providers: vec!["premium".into()],
+            }),
+        )
+        .await
+        .unwrap();
+        assert_eq!(detail.providers, vec!["premium".to_string()]);
+
+        let err = assign_providers_to_collection(
+            Path("premium-pool".into()),
+            State(h.state.clone()),
+            headers.clone(),
+            Json(ProviderCollectionAssignPayload {
+                providers: vec!["missing".into()],
+            }),
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(err, GatewayError::NotFound(_)));
+
+        let Json(detail) =
+            get_provider_collection(Path("premium-pool".into()), State(h.state.clone()), headers)
+                .await
+                .unwrap();
+        assert_eq!(detail.providers, vec!["premium".to_string()]);
+
+        let (selected, parsed) = crate::server::provider_dispatch::select_provider_for_model(
+            &h.state,
+            "premium-pool/gpt-4o",
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(selected.provider.name, "premium");
+        assert_eq!(parsed.provider_name.as_deref(), Some("premium"));
+        assert_eq!(parsed.model_name, "gpt-4o");
+    }
+
     #[test]
     fn create_payload_provider_config_accepts_missing_and_null() {
         let missing: ProviderCreatePayload = serde_json::from_value(serde_json::json!({
@@ -1010,7 +1258,7 @@ pub async fn delete_provider(
     State(app_state): State<Arc<AppState>>,
     headers: axum::http::HeaderMap,
 ) -> Result<Json<serde_json::Value>, GatewayError> {
-    require_superadmin(&headers, &app_state).await?;
+    let identity = require_superadmin(&headers, &app_state).await?;
     let start_time = Utc::now();
     let provided_token = bearer_token(&headers);
     let deleted = app_state
@@ -1019,6 +1267,7 @@ pub async fn delete_provider(
         .await
         .map_err(GatewayError::Db)?;
     if deleted {
+        crate::server::cluster::provider_changed(&app_state, &name).await;
         let _ = app_state
             .log_store
             .log_provider_op(ProviderOpLog {
@@ -1027,6 +1276,7 @@ pub async fn delete_provider(
                 operation: REQ_TYPE_PROVIDER_DELETE.to_string(),
                 provider: Some(name.clone()),
                 details: None,
+                actor: Some(identity.actor()),
             })
             .await;
         let token_log = token_for_log(provided_token.as_deref());
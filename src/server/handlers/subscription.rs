@@ -45,9 +45,11 @@ async fn log_recharge_request(
         cached_tokens: None,
         reasoning_tokens: None,
         error_message,
+        latency: Default::default(),
+        profile: Default::default(),
     };
 
-    if let Err(e) = app_state.log_store.log_request(log).await {
+    if let Err(e) = crate::server::degraded::log_request(app_state.log_store.as_ref(), log).await {
         tracing::error!("Failed to log recharge request: {}", e);
     }
 }
@@ -224,8 +226,6 @@ mod tests {
     use crate::admin::{CreateTokenPayload, TokenStore};
     use crate::config::settings::{BalanceStrategy, LoadBalancing, LoggingConfig, ServerConfig};
     use crate::logging::DatabaseLogger;
-    use crate::server::AppState;
-    use crate::server::login::LoginManager;
     use crate::subscription::SubscriptionPlan;
     use crate::users::{CreateUserPayload, UserRole, UserStatus, UserStore};
     use axum::body::Body;
@@ -265,22 +265,7 @@ mod tests {
                 .unwrap(),
         );
 
-        let app_state = Arc::new(AppState {
-            config: settings,
-            load_balancer_state: Arc::new(crate::routing::LoadBalancerState::default()),
-            log_store: logger.clone(),
-            model_cache: logger.clone(),
-            providers: logger.clone(),
-            token_store: logger.clone(),
-            favorites_store: logger.clone(),
-            organizations: logger.clone(),
-            login_manager: Arc::new(LoginManager::new(logger.clone())),
-            user_store: logger.clone(),
-            refresh_token_store: logger.clone(),
-            password_reset_token_store: logger.clone(),
-            balance_store: logger.clone(),
-            subscription_store: logger.clone(),
-        });
+        let app_state = crate::server::test_harness::app_state(settings, logger.clone());
 
         let user = logger
             .create_user(CreateUserPayload {
@@ -299,20 +284,10 @@ mod tests {
 
         let created_token = logger
             .create_token(CreateTokenPayload {
-                id: None,
                 user_id: Some(user.id.clone()),
                 name: Some("t1".into()),
-                token: None,
-                allowed_models: None,
-                model_blacklist: None,
-                max_tokens: None,
-                max_amount: None,
                 enabled: false,
-                expires_at: None,
-                remark: None,
-                organization_id: None,
-                ip_whitelist: None,
-                ip_blacklist: None,
+                ..Default::default()
             })
             .await
             .unwrap();
@@ -345,6 +320,7 @@ mod tests {
             jti: None,
             exp: (now + Duration::minutes(30)).timestamp(),
             iat: Some(now.timestamp()),
+            org_id: None,
         };
         let access_token = super::super::auth::issue_access_token(&claims).unwrap();
 
@@ -411,6 +387,7 @@ mod tests {
             jti: None,
             exp: (now + Duration::minutes(30)).timestamp(),
             iat: Some(now.timestamp()),
+            org_id: None,
         };
         let admin_access_token = super::super::auth::issue_access_token(&admin_claims).unwrap();
         let mut admin_headers = HeaderMap::new();
@@ -457,22 +434,7 @@ mod tests {
                 .unwrap(),
         );
 
-        let app_state = Arc::new(AppState {
-            config: settings,
-            load_balancer_state: Arc::new(crate::routing::LoadBalancerState::default()),
-            log_store: logger.clone(),
-            model_cache: logger.clone(),
-            providers: logger.clone(),
-            token_store: logger.clone(),
-            favorites_store: logger.clone(),
-            organizations: logger.clone(),
-            login_manager: Arc::new(LoginManager::new(logger.clone())),
-            user_store: logger.clone(),
-            refresh_token_store: logger.clone(),
-            password_reset_token_store: logger.clone(),
-            balance_store: logger.clone(),
-            subscription_store: logger.clone(),
-        });
+        let app_state = crate::server::test_harness::app_state(settings, logger.clone());
 
         let routes = crate::server::handlers::routes();
         let app = axum::Router::new()
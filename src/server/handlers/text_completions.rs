@@ -0,0 +1,288 @@
+use axum::{
+    Json,
+    body::{Body, Bytes},
+    extract::State,
+    http::{HeaderMap, header},
+    response::{IntoResponse, Response},
+};
+use futures_util::StreamExt;
+use serde_json::{Map, Value, json};
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use super::chat::serve_chat_completions;
+use crate::error::GatewayError;
+use crate::server::AppState;
+use crate::server::chat_request::GatewayChatCompletionRequest;
+use crate::server::legacy_completions::{LEGACY_ONLY_FIELDS, LegacyFields};
+
+const TEXT_COMPLETION_OBJECT: &str = "text_completion";
+
+struct LegacyRequest {
+    request: GatewayChatCompletionRequest,
+    /// prompt、suffix 等独有字段，供原生转发使用
+    fields: Map<String, Value>,
+    /// echo=true 时的 prompt（聊天转换路径下由网关拼回输出开头）
+    echo: Option<String>,
+}
+
+/// prompt 只接受单条文本（字符串或单元素数组）；批量与 token 数组无法映射为一轮对话
+fn prompt_text(prompt: Option<&Value>) -> Result<String, GatewayError> {
+    match prompt {
+        None | Some(Value::Null) => Ok(String::new()),
+        Some(Value::String(s)) => Ok(s.clone()),
+        Some(Value::Array(items)) => match items.as_slice() {
+            [] => Ok(String::new()),
+            [Value::String(s)] => Ok(s.clone()),
+            items if items.iter().all(Value::is_string) => Err(GatewayError::Config(
+                "batched prompts are not supported; send one prompt per request".into(),
+            )),
+            _ => Err(GatewayError::Config(
+                "token-array prompts are not supported; send the prompt as text".into(),
+            )),
+        },
+        Some(_) => Err(GatewayError::Config("prompt must be a string".into())),
+    }
+}
+
+/// 旧版补全请求转为网关聊天请求：prompt 作为唯一一条 user 消息，
+/// max_tokens、temperature、stop、n 等同名参数原样沿用；suffix、best_of 等仅在原生转发时生效
+fn legacy_request_to_gateway(mut body: Value) -> Result<LegacyRequest, GatewayError> {
+    let obj = body
+        .as_object_mut()
+        .ok_or_else(|| GatewayError::Config("request body must be a JSON object".into()))?;
+    let prompt = prompt_text(obj.get("prompt"))?;
+    let echo = obj.get("echo").and_then(Value::as_bool).unwrap_or(false);
+    let mut fields = Map::new();
+    for field in LEGACY_ONLY_FIELDS {
+        if let Some(value) = obj.remove(field) {
+            fields.insert(field.to_string(), value);
+        }
+    }
+    fields.insert("prompt".into(), Value::String(prompt.clone()));
+    obj.insert(
+        "messages".into(),
+        json!([{"role": "user", "content": prompt.clone()}]),
+    );
+
+    let request = serde_json::from_value(body)
+        .map_err(|e| GatewayError::Config(format!("invalid completion request: {}", e)))?;
+    Ok(LegacyRequest {
+        request,
+        fields,
+        echo: echo.then_some(prompt),
+    })
+}
+
+/// 网关响应（完整响应或流式 chunk）转为文本补全格式；错误体原样保留。
+/// 原生转发的响应（object 已是 text_completion）已含 echo 与 logprobs，不再拼接
+fn gateway_response_to_text_completion(
+    v: &mut Value,
+    echo: Option<&str>,
+    echoed: &mut HashSet<u64>,
+) {
+    let Some(obj) = v.as_object_mut() else {
+        return;
+    };
+    let native = obj.get("object").and_then(Value::as_str) == Some(TEXT_COMPLETION_OBJECT);
+    let Some(choices) = obj.get_mut("choices").and_then(Value::as_array_mut) else {
+        return;
+    };
+    for choice in choices.iter_mut() {
+        let index = choice.get("index").and_then(Value::as_u64).unwrap_or(0);
+        let mut text = choice
+            .pointer("/message/content")
+            .or_else(|| choice.pointer("/delta/content"))
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string();
+        if !native
+            && let Some(prompt) = echo
+            && echoed.insert(index)
+        {
+            text.insert_str(0, prompt);
+        }
+        let logprobs = if native {
+            choice.get("logprobs").cloned().unwrap_or(Value::Null)
+        } else {
+            Value::Null
+        };
+        *choice = json!({
+            "text": text,
+            "index": index,
+            "logprobs": logprobs,
+            "finish_reason": choice.get("finish_reason").cloned().unwrap_or(Value::Null),
+        });
+    }
+    obj.insert("object".into(), json!(TEXT_COMPLETION_OBJECT));
+}
+
+fn text_completion_sse_frame(frame: &str, echo: Option<&str>, echoed: &mut HashSet<u64>) -> String {
+    frame
+        .split('\n')
+        .map(|line| {
+            if let Some(data) = line.strip_prefix("data:")
+                && let Ok(mut v) = serde_json::from_str::<Value>(data.trim_start())
+            {
+                gateway_response_to_text_completion(&mut v, echo, echoed);
+                return format!("data: {}", v);
+            }
+            line.to_string()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// 按 SSE 帧（空行分隔）缓冲并逐帧转换 data 行
+fn text_completion_stream_response(response: Response, echo: Option<String>) -> Response {
+    let (parts, body) = response.into_parts();
+    let mut pending: Vec<u8> = Vec::new();
+    let mut echoed = HashSet::new();
+    let stream = body.into_data_stream().map(move |chunk| {
+        chunk.map(|bytes| {
+            pending.extend_from_slice(&bytes);
+            let mut out = String::new();
+            while let Some(pos) = pending.windows(2).position(|w| w == b"\n\n") {
+                let frame: Vec<u8> = pending.drain(..pos + 2).collect();
+                out.push_str(&text_completion_sse_frame(
+                    &String::from_utf8_lossy(&frame[..pos]),
+                    echo.as_deref(),
+                    &mut echoed,
+                ));
+                out.push_str("\n\n");
+            }
+            Bytes::from(out)
+        })
+    });
+    Response::from_parts(parts, Body::from_stream(stream))
+}
+
+async fn text_completion_json_response(response: Response, echo: Option<&str>) -> Response {
+    let (mut parts, body) = response.into_parts();
+    let bytes = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(e) => return GatewayError::Config(e.to_string()).into_response(),
+    };
+    let Ok(mut v) = serde_json::from_slice::<Value>(&bytes) else {
+        return Response::from_parts(parts, Body::from(bytes));
+    };
+    gateway_response_to_text_completion(&mut v, echo, &mut HashSet::new());
+    parts.headers.remove(header::CONTENT_LENGTH);
+    Response::from_parts(parts, Body::from(v.to_string()))
+}
+
+/// 旧版文本补全入口 `/v1/completions`（老版本 SDK 与部分代码补全工具仍在使用）：
+/// 请求走与 `/v1/chat/completions` 相同的链路（鉴权、路由、计费、日志），
+/// 非流式请求分发到支持原生接口的供应商（OpenAI、本地运行时）时直接转发 prompt，
+/// 其余情况把 prompt 转为一条 user 消息调用聊天接口，响应（含 SSE 流）再转回文本补全格式
+pub async fn completions(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(body): Json<Value>,
+) -> Response {
+    let legacy = match legacy_request_to_gateway(body) {
+        Ok(legacy) => legacy,
+        Err(e) => return e.into_response(),
+    };
+    let stream = legacy.request.request.stream.unwrap_or(false);
+    let response = serve_chat_completions(
+        app_state,
+        headers,
+        legacy.request,
+        Some(LegacyFields::new(legacy.fields)),
+    )
+    .await
+    .unwrap_or_else(IntoResponse::into_response);
+    if stream && response.status().is_success() {
+        text_completion_stream_response(response, legacy.echo)
+    } else {
+        text_completion_json_response(response, legacy.echo.as_deref()).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn legacy_request_maps_prompt_to_user_message() {
+        let legacy = legacy_request_to_gateway(json!({
+            "model": "gpt-3.5-turbo-instruct",
+            "prompt": ["def fib(n):"],
+            "suffix": "\n",
+            "echo": true,
+            "logprobs": 2,
+            "max_tokens": 16,
+            "stop": ["\n\n"]
+        }))
+        .unwrap();
+        let v = serde_json::to_value(&legacy.request.request).unwrap();
+        assert_eq!(
+            v["messages"],
+            json!([{"role": "user", "content": "def fib(n):"}])
+        );
+        assert_eq!(v["max_tokens"], json!(16));
+        assert_eq!(v["stop"], json!(["\n\n"]));
+        assert!(v.get("suffix").is_none());
+        assert_eq!(legacy.fields["prompt"], json!("def fib(n):"));
+        assert_eq!(legacy.fields["logprobs"], json!(2));
+        assert_eq!(legacy.echo.as_deref(), Some("def fib(n):"));
+
+        assert!(legacy_request_to_gateway(json!({"model": "m", "prompt": ["a", "b"]})).is_err());
+        assert!(legacy_request_to_gateway(json!({"model": "m", "prompt": [1, 2]})).is_err());
+    }
+
+    #[test]
+    fn chat_response_converts_with_echo_and_native_keeps_logprobs() {
+        let mut v = json!({
+            "id": "chatcmpl-1",
+            "object": "chat.completion",
+            "model": "m",
+            "choices": [{"index": 0, "finish_reason": "stop",
+                         "message": {"role": "assistant", "content": " return n"}}],
+            "usage": {"prompt_tokens": 3, "completion_tokens": 2, "total_tokens": 5}
+        });
+        gateway_response_to_text_completion(&mut v, Some("def f(n):"), &mut HashSet::new());
+        assert_eq!(v["object"], json!("text_completion"));
+        assert_eq!(
+            v["choices"][0],
+            json!({"text": "def f(n): return n", "index": 0, "logprobs": null, "finish_reason": "stop"})
+        );
+        assert_eq!(v["usage"]["total_tokens"], json!(5));
+
+        let mut native = json!({
+            "object": "text_completion",
+            "choices": [{"index": 0, "finish_reason": "length", "logprobs": {"tokens": ["a"]},
+                         "message": {"role": "assistant", "content": "echoed a"}}]
+        });
+        gateway_response_to_text_completion(&mut native, Some("echoed"), &mut HashSet::new());
+        assert_eq!(native["choices"][0]["text"], json!("echoed a"));
+        assert_eq!(native["choices"][0]["logprobs"], json!({"tokens": ["a"]}));
+
+        let mut err = json!({"code": "invalid_request", "message": "invalid token"});
+        gateway_response_to_text_completion(&mut err, None, &mut HashSet::new());
+        assert_eq!(
+            err,
+            json!({"code": "invalid_request", "message": "invalid token"})
+        );
+    }
+
+    #[test]
+    fn stream_frames_echo_prompt_once_per_choice() {
+        let mut echoed = HashSet::new();
+        let chunk = "data: {\"object\":\"chat.completion.chunk\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"x\"}}]}";
+        let first = text_completion_sse_frame(chunk, Some("p:"), &mut echoed);
+        let second = text_completion_sse_frame(chunk, Some("p:"), &mut echoed);
+        let text = |frame: &str| {
+            serde_json::from_str::<Value>(frame.strip_prefix("data: ").unwrap()).unwrap()["choices"]
+                [0]["text"]
+                .clone()
+        };
+        assert_eq!(text(&first), json!("p:x"));
+        assert_eq!(text(&second), json!("x"));
+        assert_eq!(
+            text_completion_sse_frame("data: [DONE]", None, &mut echoed),
+            "data: [DONE]"
+        );
+    }
+}
@@ -0,0 +1,123 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::HeaderMap,
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use super::auth::require_superadmin;
+use crate::error::GatewayError;
+use crate::server::AppState;
+use crate::server::request_logging::log_simple_request;
+use crate::server::util::{bearer_token, token_for_log};
+
+const PATH: &str = "/admin/tokens/{id}/auto-disable";
+
+#[derive(Debug, Serialize)]
+pub struct TokenAutoDisableOut {
+    pub token_id: String,
+    pub enabled: bool,
+    pub exempt: bool,
+    pub disabled_at: Option<String>,
+    /// 全局策略：连续多少天无请求即停用；为空表示策略未启用
+    pub inactive_days: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetAutoDisablePayload {
+    pub exempt: bool,
+}
+
+async fn handle(
+    app_state: Arc<AppState>,
+    headers: HeaderMap,
+    id: String,
+    update: Option<SetAutoDisablePayload>,
+) -> Result<Json<TokenAutoDisableOut>, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    let (method, op) = if update.is_some() {
+        ("PUT", "token_auto_disable_set")
+    } else {
+        ("GET", "token_auto_disable_get")
+    };
+    if let Err(e) = require_superadmin(&headers, &app_state).await {
+        log_simple_request(
+            &app_state,
+            start_time,
+            method,
+            PATH,
+            op,
+            None,
+            None,
+            provided_token.as_deref(),
+            e.status_code().as_u16(),
+            Some(e.to_string()),
+        )
+        .await;
+        return Err(e);
+    }
+
+    let result = async {
+        let token = app_state
+            .token_store
+            .get_token_by_id(&id)
+            .await?
+            .ok_or_else(|| GatewayError::NotFound("token not found".into()))?;
+        if let Some(payload) = update {
+            app_state
+                .token_store
+                .set_auto_disable_exempt(&id, payload.exempt)
+                .await?;
+        }
+        let prefs = app_state.token_store.get_auto_disable_prefs(&id).await?;
+        Ok::<_, GatewayError>(TokenAutoDisableOut {
+            token_id: token.id,
+            enabled: token.enabled,
+            exempt: prefs.exempt,
+            disabled_at: prefs
+                .disabled_at
+                .as_ref()
+                .map(crate::logging::time::to_iso8601_utc_string),
+            inactive_days: app_state.config.server.inactive_token_disable_days,
+        })
+    }
+    .await;
+    let (code, err) = match &result {
+        Ok(_) => (200, None),
+        Err(e) => (e.status_code().as_u16(), Some(e.to_string())),
+    };
+    log_simple_request(
+        &app_state,
+        start_time,
+        method,
+        PATH,
+        op,
+        None,
+        None,
+        token_for_log(provided_token.as_deref()),
+        code,
+        err,
+    )
+    .await;
+    result.map(Json)
+}
+
+pub async fn get_auto_disable(
+    Path(id): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<TokenAutoDisableOut>, GatewayError> {
+    handle(app_state, headers, id, None).await
+}
+
+pub async fn set_auto_disable(
+    Path(id): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(payload): Json<SetAutoDisablePayload>,
+) -> Result<Json<TokenAutoDisableOut>, GatewayError> {
+    handle(app_state, headers, id, Some(payload)).await
+}
@@ -0,0 +1,295 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::HeaderMap,
+};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::sync::Arc;
+
+use super::auth::ensure_client_token;
+use super::client_tokens::ClientTokenOut;
+use crate::admin::{ClientToken, CreateTokenPayload};
+use crate::error::GatewayError;
+use crate::server::AppState;
+use crate::server::request_logging::log_simple_request;
+use crate::server::token_lineage::{self, HierarchyUsageNode};
+use crate::server::token_model_limits::{enforce_model_allowed_for_token, normalize_model_list};
+use crate::server::util::bearer_token;
+
+/// 分销商为终端客户创建子令牌：未指定的限制继承父令牌
+#[derive(Debug, Deserialize)]
+pub struct ChildTokenRequest {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub allowed_models: Option<Vec<String>>,
+    /// 为空时直接消耗父令牌额度
+    #[serde(default)]
+    pub max_amount: Option<f64>,
+    #[serde(default)]
+    pub expires_at: Option<String>, // 北京时间字符串，可选
+    #[serde(default)]
+    pub remark: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChildTogglePayload {
+    pub enabled: bool,
+}
+
+async fn log_call<T>(
+    app_state: &AppState,
+    start_time: DateTime<Utc>,
+    method: &str,
+    path: &str,
+    request_type: &str,
+    provided_token: Option<&str>,
+    result: &Result<T, GatewayError>,
+) {
+    let (code, err) = match result {
+        Ok(_) => (200, None),
+        Err(e) => (e.status_code().as_u16(), Some(e.to_string())),
+    };
+    log_simple_request(
+        app_state,
+        start_time,
+        method,
+        path,
+        request_type,
+        None,
+        None,
+        provided_token,
+        code,
+        err,
+    )
+    .await;
+}
+
+async fn caller_token(
+    headers: &HeaderMap,
+    app_state: &AppState,
+) -> Result<ClientToken, GatewayError> {
+    let tok = ensure_client_token(headers, app_state).await?;
+    app_state
+        .token_store
+        .get_token(&tok)
+        .await?
+        .ok_or_else(|| GatewayError::Unauthorized("invalid token".into()))
+}
+
+/// 只允许管理自己的直接子令牌；他人的令牌一律按不存在处理
+async fn owned_child(
+    app_state: &AppState,
+    caller: &ClientToken,
+    id: &str,
+) -> Result<ClientToken, GatewayError> {
+    match app_state.token_store.get_token_by_id(id).await? {
+        Some(t) if t.parent_token_id.as_deref() == Some(caller.id.as_str()) => Ok(t),
+        _ => Err(GatewayError::NotFound("token not found".into())),
+    }
+}
+
+fn child_payload(
+    parent: &ClientToken,
+    req: ChildTokenRequest,
+) -> Result<CreateTokenPayload, GatewayError> {
+    let (allowed_models, model_blacklist) =
+        match normalize_model_list("allowed_models", req.allowed_models)? {
+            Some(models) => {
+                for m in &models {
+                    enforce_model_allowed_for_token(parent, m)?;
+                }
+                (Some(models), None)
+            }
+            None => (
+                parent.allowed_models.clone(),
+                parent.model_blacklist.clone(),
+            ),
+        };
+    if let Some(amount) = req.max_amount
+        && (!amount.is_finite() || amount <= 0.0)
+    {
+        return Err(GatewayError::Config("max_amount must be positive".into()));
+    }
+    let name = req
+        .name
+        .map(|n| n.trim().to_string())
+        .filter(|n| !n.is_empty());
+    if name
+        .as_deref()
+        .is_some_and(|n| n.chars().count() > 64 || n.chars().any(|c| c.is_control()))
+    {
+        return Err(GatewayError::Config(
+            "name must be at most 64 characters without control characters".into(),
+        ));
+    }
+    if req
+        .remark
+        .as_deref()
+        .is_some_and(|r| r.chars().count() > 1024)
+    {
+        return Err(GatewayError::Config(
+            "remark must be at most 1024 characters".into(),
+        ));
+    }
+    if parent.user_id.is_some() && req.max_amount.is_some() {
+        return Err(GatewayError::Config(
+            "user-bound tokens cannot set max_amount on child tokens".into(),
+        ));
+    }
+    Ok(CreateTokenPayload {
+        name,
+        allowed_models,
+        model_blacklist,
+        max_amount: req.max_amount,
+        expires_at: req.expires_at.filter(|s| !s.trim().is_empty()),
+        remark: req.remark,
+        ip_whitelist: parent.ip_whitelist.clone(),
+        ip_blacklist: parent.ip_blacklist.clone(),
+        allow_streaming: parent.allow_streaming,
+        sandbox: parent.sandbox,
+        strip_reasoning: parent.strip_reasoning,
+        ..Default::default()
+    })
+}
+
+pub async fn list_children(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<ClientTokenOut>>, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    let result = async {
+        let caller = caller_token(&headers, &app_state).await?;
+        let children = app_state.token_store.list_child_tokens(&caller.id).await?;
+        Ok(children.into_iter().map(ClientTokenOut::from).collect())
+    }
+    .await;
+    log_call(
+        &app_state,
+        start_time,
+        "GET",
+        "/v1/token/children",
+        "token_children_list",
+        provided_token.as_deref(),
+        &result,
+    )
+    .await;
+    result.map(Json)
+}
+
+pub async fn create_child(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(req): Json<ChildTokenRequest>,
+) -> Result<(axum::http::StatusCode, Json<ClientTokenOut>), GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    let result = async {
+        let caller = caller_token(&headers, &app_state).await?;
+        let mut payload = child_payload(&caller, req)?;
+        token_lineage::attach_to_parent(&app_state, &caller, &mut payload).await?;
+        let t = app_state.token_store.create_token(payload).await?;
+        Ok(ClientTokenOut::from(t))
+    }
+    .await;
+    log_call(
+        &app_state,
+        start_time,
+        "POST",
+        "/v1/token/children",
+        "token_children_create",
+        provided_token.as_deref(),
+        &result,
+    )
+    .await;
+    result.map(|t| (axum::http::StatusCode::CREATED, Json(t)))
+}
+
+/// 停用子令牌时级联停用其全部后代
+pub async fn toggle_child(
+    Path(id): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(payload): Json<ChildTogglePayload>,
+) -> Result<Json<serde_json::Value>, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    let result = async {
+        let caller = caller_token(&headers, &app_state).await?;
+        let child = owned_child(&app_state, &caller, &id).await?;
+        app_state
+            .token_store
+            .set_enabled_by_id(&child.id, payload.enabled)
+            .await?;
+        if !payload.enabled {
+            token_lineage::cascade_disable(&app_state, &child.id).await?;
+        }
+        Ok(serde_json::json!({ "status": "ok" }))
+    }
+    .await;
+    log_call(
+        &app_state,
+        start_time,
+        "POST",
+        "/v1/token/children/{id}/toggle",
+        "token_children_toggle",
+        provided_token.as_deref(),
+        &result,
+    )
+    .await;
+    result.map(Json)
+}
+
+pub async fn delete_child(
+    Path(id): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<axum::http::StatusCode, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    let result = async {
+        let caller = caller_token(&headers, &app_state).await?;
+        let child = owned_child(&app_state, &caller, &id).await?;
+        token_lineage::delete_with_descendants(&app_state, &child.id).await?;
+        Ok(axum::http::StatusCode::NO_CONTENT)
+    }
+    .await;
+    log_call(
+        &app_state,
+        start_time,
+        "DELETE",
+        "/v1/token/children/{id}",
+        "token_children_delete",
+        provided_token.as_deref(),
+        &result,
+    )
+    .await;
+    result
+}
+
+/// 调用方令牌及全部后代的用量汇总
+pub async fn hierarchy_usage(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<HierarchyUsageNode>, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    let result = async {
+        let caller = caller_token(&headers, &app_state).await?;
+        token_lineage::hierarchy_usage(&app_state, &caller).await
+    }
+    .await;
+    log_call(
+        &app_state,
+        start_time,
+        "GET",
+        "/v1/token/hierarchy/usage",
+        "token_hierarchy_usage",
+        provided_token.as_deref(),
+        &result,
+    )
+    .await;
+    result.map(Json)
+}
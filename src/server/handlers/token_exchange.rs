@@ -0,0 +1,75 @@
+use axum::{Json, extract::State, http::HeaderMap};
+use chrono::Utc;
+use serde::Serialize;
+use std::sync::Arc;
+
+use super::auth::ensure_client_token;
+use crate::error::GatewayError;
+use crate::server::AppState;
+use crate::server::request_logging::log_simple_request;
+use crate::server::token_lineage::{
+    TokenExchangeRequest, child_token_payload, ensure_can_add_child, ensure_exchange_rate,
+};
+use crate::server::util::bearer_token;
+
+#[derive(Debug, Serialize)]
+pub struct TokenExchangeResponse {
+    pub id: String,
+    pub token: String,
+    pub parent_token_id: String,
+    pub allowed_models: Option<Vec<String>>,
+    pub max_amount: Option<f64>,
+    pub expires_at: Option<String>,
+}
+
+/// 用长期令牌换取短期、收窄权限的子令牌（适合下发给浏览器端），用量向父令牌汇总；
+/// 按父令牌每分钟限流，过期的子令牌由定时任务清理
+pub async fn exchange_token(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(req): Json<TokenExchangeRequest>,
+) -> Result<Json<TokenExchangeResponse>, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    let result: Result<TokenExchangeResponse, GatewayError> = async {
+        let tok = ensure_client_token(&headers, &app_state).await?;
+        let parent = app_state
+            .token_store
+            .get_token(&tok)
+            .await?
+            .ok_or_else(|| GatewayError::Unauthorized("invalid token".into()))?;
+        ensure_can_add_child(&app_state, &parent).await?;
+        ensure_exchange_rate(&app_state, &parent).await?;
+        let payload = child_token_payload(&parent, req)?;
+        let child = app_state.token_store.create_token(payload).await?;
+        Ok(TokenExchangeResponse {
+            id: child.id,
+            token: child.token,
+            parent_token_id: parent.id,
+            allowed_models: child.allowed_models,
+            max_amount: child.max_amount,
+            expires_at: child
+                .expires_at
+                .map(|t| crate::logging::time::to_iso8601_utc_string(&t)),
+        })
+    }
+    .await;
+    let (code, err) = match &result {
+        Ok(_) => (200, None),
+        Err(e) => (e.status_code().as_u16(), Some(e.to_string())),
+    };
+    log_simple_request(
+        &app_state,
+        start_time,
+        "POST",
+        "/v1/token/exchange",
+        "token_exchange",
+        None,
+        None,
+        provided_token.as_deref(),
+        code,
+        err,
+    )
+    .await;
+    result.map(Json)
+}
@@ -10,6 +10,8 @@ use crate::error::GatewayError;
 use crate::logging::types::{REQ_TYPE_CHAT_ONCE, REQ_TYPE_CHAT_STREAM};
 use crate::server::AppState;
 use crate::server::request_logging::log_simple_request;
+use crate::server::request_quota::RequestQuotaStatus;
+use crate::server::user_budget::{self, UserBudgetStatus};
 use chrono::Utc;
 
 fn bearer(headers: &HeaderMap) -> Option<String> {
@@ -79,6 +81,9 @@ pub async fn token_balance(
         .unwrap_or(0);
     let max_tokens = token_row.as_ref().and_then(|t| t.max_tokens);
     let remaining = max_amount.map(|m| (m - spent).max(0.0));
+    let requests = token_row
+        .as_ref()
+        .map(|t| RequestQuotaStatus::new(t, app_state.request_quota.usage(&t.id, start_time)));
     log_simple_request(
         &app_state,
         start_time,
@@ -99,9 +104,48 @@ pub async fn token_balance(
         "remaining": remaining,
         "total_tokens_spent": total_tokens_spent,
         "max_tokens": max_tokens,
+        "requests": requests,
     })))
 }
 
+/// 用户级预算：汇总当前令牌所属用户名下全部令牌的消费
+pub async fn user_balance(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<UserBudgetStatus>, GatewayError> {
+    let start_time = Utc::now();
+    let provided = bearer(&headers);
+    let result = async {
+        let token = ensure_active_token(&headers, &app_state).await?;
+        let user_id = app_state
+            .token_store
+            .get_token(&token)
+            .await?
+            .and_then(|t| t.user_id)
+            .ok_or_else(|| GatewayError::NotFound("token is not bound to a user".into()))?;
+        user_budget::status(&app_state, &user_id).await
+    }
+    .await;
+    let (code, err) = match &result {
+        Ok(_) => (200, None),
+        Err(e) => (e.status_code().as_u16(), Some(e.to_string())),
+    };
+    log_simple_request(
+        &app_state,
+        start_time,
+        "GET",
+        "/v1/user/balance",
+        "user_balance",
+        None,
+        None,
+        provided.as_deref(),
+        code,
+        err,
+    )
+    .await;
+    result.map(Json)
+}
+
 #[derive(Debug, Deserialize)]
 pub struct UsageQuery {
     #[serde(default)]
@@ -0,0 +1,327 @@
+use axum::{Json, extract::State, http::HeaderMap};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use super::auth::ensure_client_token;
+use super::auth_jwt::{LoginResponse, issue_login};
+use crate::admin::ClientToken;
+use crate::error::GatewayError;
+use crate::server::AppState;
+use crate::server::request_logging::log_simple_request;
+use crate::server::util::bearer_token;
+use crate::users::{UserRole, UserStatus};
+
+const DEFAULT_CODE_TTL_SECS: u64 = 300;
+const MIN_CODE_TTL_SECS: u64 = 30;
+const MAX_CODE_TTL_SECS: u64 = 900;
+
+#[derive(Debug, Deserialize)]
+pub struct CreateUserLoginCodeRequest {
+    pub user_id: String,
+    #[serde(default)]
+    pub ttl_secs: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UserLoginCodeResponse {
+    pub code: String,
+    pub user_id: String,
+    pub expires_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RedeemUserLoginCodeRequest {
+    pub code: String,
+}
+
+/// 令牌只能为自己的所有者，或由它签发的子令牌的所有者签发登录码；
+/// 组织（含默认组织）成员关系不授予签发权限，避免跨租户冒用
+async fn ensure_token_can_sign_in(
+    app_state: &AppState,
+    token: &ClientToken,
+    user_id: &str,
+) -> Result<(), GatewayError> {
+    if token.user_id.as_deref() == Some(user_id) {
+        return Ok(());
+    }
+    let children = app_state.token_store.list_child_tokens(&token.id).await?;
+    if children
+        .iter()
+        .any(|t| t.user_id.as_deref() == Some(user_id))
+    {
+        return Ok(());
+    }
+    Err(GatewayError::Forbidden(
+        "user is not managed by this token".into(),
+    ))
+}
+
+/// 具备 `allow_login_codes` 能力的令牌为其终端用户签发一次性、短时效的 Web 控制台登录码
+/// （供合作方门户嵌入登录）；每个令牌按分钟限流，签发记录写入请求日志
+pub async fn create_user_login_code(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(req): Json<CreateUserLoginCodeRequest>,
+) -> Result<Json<UserLoginCodeResponse>, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    let result: Result<UserLoginCodeResponse, GatewayError> = async {
+        let tok = ensure_client_token(&headers, &app_state).await?;
+        let token = app_state
+            .token_store
+            .get_token(&tok)
+            .await?
+            .ok_or_else(|| GatewayError::Unauthorized("invalid token".into()))?;
+        if !token.allow_login_codes {
+            return Err(GatewayError::Forbidden(
+                "token is not allowed to create login codes".into(),
+            ));
+        }
+        let user_id = req.user_id.trim();
+        ensure_token_can_sign_in(&app_state, &token, user_id).await?;
+        let user = app_state
+            .user_store
+            .get_user(user_id)
+            .await?
+            .ok_or_else(|| GatewayError::NotFound("user not found".into()))?;
+        if matches!(user.role, UserRole::Superadmin | UserRole::Admin) {
+            return Err(GatewayError::Forbidden(
+                "login codes cannot be issued for administrators".into(),
+            ));
+        }
+        if !matches!(user.status, UserStatus::Active) {
+            return Err(GatewayError::Forbidden("user is not active".into()));
+        }
+        let ttl_secs = req
+            .ttl_secs
+            .unwrap_or(DEFAULT_CODE_TTL_SECS)
+            .clamp(MIN_CODE_TTL_SECS, MAX_CODE_TTL_SECS);
+        let entry = app_state
+            .login_manager
+            .generate_user_code(&token.id, &user.id, ttl_secs)
+            .await?;
+        tracing::info!(
+            token_id = %token.id,
+            user_id = %user.id,
+            ttl_secs,
+            "user login code issued by client token"
+        );
+        Ok(UserLoginCodeResponse {
+            code: entry.code,
+            user_id: user.id,
+            expires_at: entry.expires_at.to_rfc3339(),
+        })
+    }
+    .await;
+    let (code, err) = match &result {
+        Ok(_) => (200, None),
+        Err(e) => (e.status_code().as_u16(), Some(e.to_string())),
+    };
+    log_simple_request(
+        &app_state,
+        start_time,
+        "POST",
+        "/v1/token/login-codes",
+        "token_login_code",
+        None,
+        None,
+        provided_token.as_deref(),
+        code,
+        err,
+    )
+    .await;
+    result.map(Json)
+}
+
+/// 兑换令牌签发的登录码，返回与 `/auth/login` 相同的 AccessToken/RefreshToken
+pub async fn redeem_user_login_code(
+    State(app_state): State<Arc<AppState>>,
+    Json(req): Json<RedeemUserLoginCodeRequest>,
+) -> Result<Json<LoginResponse>, GatewayError> {
+    let Some(record) = app_state
+        .login_manager
+        .redeem_user_code(req.code.trim())
+        .await?
+    else {
+        return Err(GatewayError::Unauthorized("invalid or expired code".into()));
+    };
+    let user = app_state
+        .user_store
+        .get_user(&record.user_id)
+        .await?
+        .filter(|u| matches!(u.status, UserStatus::Active))
+        .ok_or_else(|| GatewayError::Unauthorized("invalid or expired code".into()))?;
+    tracing::info!(
+        token_id = %record.token_id,
+        user_id = %user.id,
+        "user login code redeemed"
+    );
+    issue_login(&app_state, user).await.map(Json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::admin::CreateTokenPayload;
+    use crate::config::BalanceStrategy;
+    use crate::config::settings::{LoadBalancing, LoggingConfig, ServerConfig};
+    use crate::logging::DatabaseLogger;
+    use crate::users::CreateUserPayload;
+    use tempfile::tempdir;
+
+    async fn test_state(dir: &tempfile::TempDir) -> Arc<AppState> {
+        let db_path = dir.path().join("test.db");
+        let settings = crate::config::Settings {
+            load_balancing: LoadBalancing {
+                strategy: BalanceStrategy::FirstAvailable,
+            },
+            server: ServerConfig::default(),
+            logging: LoggingConfig {
+                database_path: db_path.to_str().unwrap().to_string(),
+                ..Default::default()
+            },
+        };
+        let logger = Arc::new(
+            DatabaseLogger::new(&settings.logging.database_path)
+                .await
+                .unwrap(),
+        );
+        crate::server::test_harness::app_state(settings, logger.clone())
+    }
+
+    async fn create_user(state: &AppState, name: &str, role: UserRole) -> String {
+        state
+            .user_store
+            .create_user(CreateUserPayload {
+                first_name: None,
+                last_name: None,
+                username: Some(name.into()),
+                email: format!("{}@example.com", name),
+                phone_number: None,
+                password: None,
+                status: UserStatus::Active,
+                role,
+                is_anonymous: false,
+            })
+            .await
+            .unwrap()
+            .id
+    }
+
+    async fn create_token(state: &AppState, user_id: &str, allow_login_codes: bool) -> String {
+        state
+            .token_store
+            .create_token(CreateTokenPayload {
+                user_id: Some(user_id.into()),
+                allow_login_codes,
+                ..Default::default()
+            })
+            .await
+            .unwrap()
+            .token
+    }
+
+    fn bearer(token: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            axum::http::header::AUTHORIZATION,
+            format!("Bearer {}", token).parse().unwrap(),
+        );
+        headers
+    }
+
+    async fn mint(
+        state: &Arc<AppState>,
+        token: &str,
+        user_id: &str,
+    ) -> Result<UserLoginCodeResponse, GatewayError> {
+        create_user_login_code(
+            State(state.clone()),
+            bearer(token),
+            Json(CreateUserLoginCodeRequest {
+                user_id: user_id.into(),
+                ttl_secs: None,
+            }),
+        )
+        .await
+        .map(|Json(resp)| resp)
+    }
+
+    #[tokio::test]
+    async fn scoped_token_mints_single_use_login_codes() {
+        unsafe {
+            std::env::set_var("GW_JWT_SECRET", "testsecret");
+        }
+        let dir = tempdir().unwrap();
+        let state = test_state(&dir).await;
+        // 首个用户会被提升为超级管理员
+        let admin_id = create_user(&state, "portal-admin", UserRole::Admin).await;
+        let user_id = create_user(&state, "portal-user", UserRole::Manager).await;
+        let allowed = create_token(&state, &user_id, true).await;
+        let plain = create_token(&state, &user_id, false).await;
+        let admin_token = create_token(&state, &admin_id, true).await;
+
+        assert!(matches!(
+            mint(&state, &plain, &user_id).await,
+            Err(GatewayError::Forbidden(_))
+        ));
+        assert!(matches!(
+            mint(&state, &allowed, &admin_id).await,
+            Err(GatewayError::Forbidden(_))
+        ));
+        assert!(matches!(
+            mint(&state, &admin_token, &admin_id).await,
+            Err(GatewayError::Forbidden(_))
+        ));
+
+        let issued = mint(&state, &allowed, &user_id).await.unwrap();
+        let redeem = |code: String| {
+            redeem_user_login_code(
+                State(state.clone()),
+                Json(RedeemUserLoginCodeRequest { code }),
+            )
+        };
+        let Json(login) = redeem(issued.code.clone()).await.unwrap();
+        assert_eq!(login.user.id, user_id);
+        assert_eq!(login.user.role, "manager");
+        assert!(redeem(issued.code).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn org_membership_does_not_grant_login_codes() {
+        let dir = tempdir().unwrap();
+        let state = test_state(&dir).await;
+        create_user(&state, "root", UserRole::Admin).await;
+        let tenant_a = create_user(&state, "tenant-a", UserRole::Manager).await;
+        let tenant_b = create_user(&state, "tenant-b", UserRole::Manager).await;
+        let end_user = create_user(&state, "end-user", UserRole::Manager).await;
+        let create = |user_id: String, parent: Option<String>| {
+            let state = state.clone();
+            async move {
+                state
+                    .token_store
+                    .create_token(CreateTokenPayload {
+                        user_id: Some(user_id),
+                        organization_id: Some("default".into()),
+                        allow_login_codes: true,
+                        parent_token_id: parent,
+                        ..Default::default()
+                    })
+                    .await
+                    .unwrap()
+            }
+        };
+        let token_a = create(tenant_a.clone(), None).await;
+        create(tenant_b.clone(), None).await;
+        create(end_user.clone(), Some(token_a.id.clone())).await;
+
+        // 同在默认组织的无关用户不能被签发
+        assert!(matches!(
+            mint(&state, &token_a.token, &tenant_b).await,
+            Err(GatewayError::Forbidden(_))
+        ));
+        // 由该令牌签发的子令牌所属用户可以
+        assert!(mint(&state, &token_a.token, &end_user).await.is_ok());
+    }
+}
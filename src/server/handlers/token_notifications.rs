@@ -0,0 +1,181 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::HeaderMap,
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use super::auth::{require_superadmin, require_user};
+use crate::admin::TokenNotificationRecord;
+use crate::error::GatewayError;
+use crate::server::AppState;
+use crate::server::request_logging::log_simple_request;
+use crate::server::util::{bearer_token, token_for_log};
+
+const HISTORY_LIMIT: i64 = 50;
+
+#[derive(Debug, Serialize)]
+pub struct TokenNotificationsOut {
+    pub token_id: String,
+    pub opt_out: bool,
+    pub history: Vec<TokenNotificationRecord>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetNotificationsPayload {
+    pub opt_out: bool,
+}
+
+enum Caller {
+    Admin,
+    User(String),
+}
+
+async fn authorize(
+    app_state: &AppState,
+    headers: &HeaderMap,
+    admin: bool,
+) -> Result<Caller, GatewayError> {
+    if admin {
+        require_superadmin(headers, app_state).await?;
+        Ok(Caller::Admin)
+    } else {
+        require_user(headers).map(|claims| Caller::User(claims.sub))
+    }
+}
+
+async fn ensure_token_visible(
+    app_state: &AppState,
+    caller: &Caller,
+    id: &str,
+) -> Result<(), GatewayError> {
+    let found = match caller {
+        Caller::Admin => app_state.token_store.get_token_by_id(id).await?,
+        Caller::User(user_id) => {
+            app_state
+                .token_store
+                .get_token_by_id_scoped(user_id, id)
+                .await?
+        }
+    };
+    match found {
+        Some(_) => Ok(()),
+        None => Err(GatewayError::NotFound("token not found".into())),
+    }
+}
+
+async fn load(app_state: &AppState, id: String) -> Result<TokenNotificationsOut, GatewayError> {
+    let opt_out = app_state.token_store.get_notifications_opt_out(&id).await?;
+    let history = app_state
+        .token_store
+        .list_token_notifications(&id, HISTORY_LIMIT)
+        .await?;
+    Ok(TokenNotificationsOut {
+        token_id: id,
+        opt_out,
+        history,
+    })
+}
+
+async fn handle(
+    app_state: Arc<AppState>,
+    headers: HeaderMap,
+    id: String,
+    admin: bool,
+    update: Option<SetNotificationsPayload>,
+) -> Result<Json<TokenNotificationsOut>, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    let (method, op) = if update.is_some() {
+        ("PUT", "token_notifications_set")
+    } else {
+        ("GET", "token_notifications_get")
+    };
+    let path = if admin {
+        "/admin/tokens/{id}/notifications"
+    } else {
+        "/me/tokens/{id}/notifications"
+    };
+
+    let caller = match authorize(&app_state, &headers, admin).await {
+        Ok(c) => c,
+        Err(e) => {
+            log_simple_request(
+                &app_state,
+                start_time,
+                method,
+                path,
+                op,
+                None,
+                None,
+                provided_token.as_deref(),
+                e.status_code().as_u16(),
+                Some(e.to_string()),
+            )
+            .await;
+            return Err(e);
+        }
+    };
+
+    let result = async {
+        ensure_token_visible(&app_state, &caller, &id).await?;
+        if let Some(payload) = update {
+            app_state
+                .token_store
+                .set_notifications_opt_out(&id, payload.opt_out)
+                .await?;
+        }
+        load(&app_state, id).await
+    }
+    .await;
+    let (code, err) = match &result {
+        Ok(_) => (200, None),
+        Err(e) => (e.status_code().as_u16(), Some(e.to_string())),
+    };
+    let token_log = if admin {
+        token_for_log(provided_token.as_deref())
+    } else {
+        provided_token.as_deref()
+    };
+    log_simple_request(
+        &app_state, start_time, method, path, op, None, None, token_log, code, err,
+    )
+    .await;
+    result.map(Json)
+}
+
+pub async fn get_token_notifications(
+    Path(id): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<TokenNotificationsOut>, GatewayError> {
+    handle(app_state, headers, id, true, None).await
+}
+
+pub async fn set_token_notifications(
+    Path(id): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(payload): Json<SetNotificationsPayload>,
+) -> Result<Json<TokenNotificationsOut>, GatewayError> {
+    handle(app_state, headers, id, true, Some(payload)).await
+}
+
+pub async fn get_my_token_notifications(
+    Path(id): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<TokenNotificationsOut>, GatewayError> {
+    handle(app_state, headers, id, false, None).await
+}
+
+pub async fn set_my_token_notifications(
+    Path(id): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(payload): Json<SetNotificationsPayload>,
+) -> Result<Json<TokenNotificationsOut>, GatewayError> {
+    handle(app_state, headers, id, false, Some(payload)).await
+}
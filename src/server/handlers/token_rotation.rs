@@ -0,0 +1,170 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::HeaderMap,
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use super::auth::{ensure_client_token, require_superadmin};
+use crate::error::GatewayError;
+use crate::logging::time::to_iso8601_utc_string;
+use crate::server::AppState;
+use crate::server::request_logging::log_simple_request;
+use crate::server::token_rotation::{
+    ROTATE_PATH, RotationStatus, generate_token_value, status_for_token,
+};
+use crate::server::util::{bearer_token, token_for_log};
+
+const ADMIN_PATH: &str = "/admin/tokens/{id}/rotation";
+
+#[derive(Debug, Serialize)]
+pub struct TokenRotateResponse {
+    pub id: String,
+    /// 新令牌值；旧值立即失效
+    pub token: String,
+    pub rotated_at: String,
+}
+
+/// 客户端自助轮换：用当前令牌换一个新值，令牌 id、额度与用量保持不变
+pub async fn rotate_token(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<TokenRotateResponse>, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    let result: Result<TokenRotateResponse, GatewayError> = async {
+        let tok = ensure_client_token(&headers, &app_state).await?;
+        let token = app_state
+            .token_store
+            .get_token(&tok)
+            .await?
+            .ok_or_else(|| GatewayError::Unauthorized("invalid token".into()))?;
+        let new_token = generate_token_value();
+        let now = Utc::now();
+        if !app_state
+            .token_store
+            .rotate_token(&token.id, &new_token, now)
+            .await?
+        {
+            return Err(GatewayError::Unauthorized("invalid token".into()));
+        }
+        Ok(TokenRotateResponse {
+            id: token.id,
+            token: new_token,
+            rotated_at: to_iso8601_utc_string(&now),
+        })
+    }
+    .await;
+    let (code, err) = match &result {
+        Ok(_) => (200, None),
+        Err(e) => (e.status_code().as_u16(), Some(e.to_string())),
+    };
+    log_simple_request(
+        &app_state,
+        start_time,
+        "POST",
+        ROTATE_PATH,
+        "token_rotation",
+        None,
+        None,
+        provided_token.as_deref(),
+        code,
+        err,
+    )
+    .await;
+    result.map(Json)
+}
+
+#[derive(Debug, Serialize)]
+pub struct TokenRotationOut {
+    pub token_id: String,
+    pub exempt: bool,
+    pub rotated_at: Option<String>,
+    /// 全局策略：签发后多少天要求轮换；为空表示策略未启用
+    pub rotation_days: Option<u32>,
+    pub grace_days: Option<u32>,
+    #[serde(flatten)]
+    pub status: RotationStatus,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetRotationPayload {
+    pub exempt: bool,
+}
+
+async fn handle(
+    app_state: Arc<AppState>,
+    headers: HeaderMap,
+    id: String,
+    update: Option<SetRotationPayload>,
+) -> Result<Json<TokenRotationOut>, GatewayError> {
+    let start_time = Utc::now();
+    let provided_token = bearer_token(&headers);
+    let (method, op) = if update.is_some() {
+        ("PUT", "token_rotation_set")
+    } else {
+        ("GET", "token_rotation_get")
+    };
+    let result = async {
+        require_superadmin(&headers, &app_state).await?;
+        let token = app_state
+            .token_store
+            .get_token_by_id(&id)
+            .await?
+            .ok_or_else(|| GatewayError::NotFound("token not found".into()))?;
+        if let Some(payload) = update {
+            app_state
+                .token_store
+                .set_rotation_exempt(&id, payload.exempt)
+                .await?;
+        }
+        let prefs = app_state.token_store.get_rotation_prefs(&id).await?;
+        let settings = app_state.runtime_settings.snapshot();
+        Ok::<_, GatewayError>(TokenRotationOut {
+            status: status_for_token(&settings, &token, &prefs, Utc::now()),
+            token_id: token.id,
+            exempt: prefs.exempt,
+            rotated_at: prefs.rotated_at.as_ref().map(to_iso8601_utc_string),
+            rotation_days: settings.token_rotation_days,
+            grace_days: settings.token_rotation_grace_days,
+        })
+    }
+    .await;
+    let (code, err) = match &result {
+        Ok(_) => (200, None),
+        Err(e) => (e.status_code().as_u16(), Some(e.to_string())),
+    };
+    log_simple_request(
+        &app_state,
+        start_time,
+        method,
+        ADMIN_PATH,
+        op,
+        None,
+        None,
+        token_for_log(provided_token.as_deref()),
+        code,
+        err,
+    )
+    .await;
+    result.map(Json)
+}
+
+pub async fn get_rotation(
+    Path(id): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<TokenRotationOut>, GatewayError> {
+    handle(app_state, headers, id, None).await
+}
+
+pub async fn set_rotation(
+    Path(id): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(payload): Json<SetRotationPayload>,
+) -> Result<Json<TokenRotationOut>, GatewayError> {
+    handle(app_state, headers, id, Some(payload)).await
+}
@@ -0,0 +1,271 @@
+use axum::{
+    Json,
+    body::{Body, Bytes},
+    extract::State,
+    http::{HeaderMap, header},
+    response::{IntoResponse, Response},
+};
+use futures_util::StreamExt;
+use serde_json::{Value, json};
+use std::sync::Arc;
+
+use super::chat::chat_completions;
+use crate::error::GatewayError;
+use crate::server::AppState;
+use crate::server::chat_request::GatewayChatCompletionRequest;
+
+/// 智谱专有、OpenAI 请求中没有对应项的字段
+const ZHIPU_ONLY_FIELDS: [&str; 5] = [
+    "do_sample",
+    "request_id",
+    "user_id",
+    "sensitive_word_check",
+    "meta",
+];
+
+/// 智谱请求转为网关请求，返回调用方传入的 request_id（响应中原样回显）：
+/// - do_sample=false 映射为 temperature=0
+/// - user_id 映射为 user
+/// - 内置 web_search 工具映射为 web_search_options，其余非 function 工具（retrieval 等）丢弃
+/// - 不带 data: 前缀的纯 base64 图片补齐为 data URL
+fn zhipu_request_to_gateway(
+    mut body: Value,
+) -> Result<(GatewayChatCompletionRequest, Option<String>), GatewayError> {
+    let obj = body
+        .as_object_mut()
+        .ok_or_else(|| GatewayError::Config("request body must be a JSON object".into()))?;
+    let request_id = obj
+        .get("request_id")
+        .and_then(Value::as_str)
+        .map(str::to_string);
+    if obj.get("do_sample") == Some(&Value::Bool(false)) {
+        obj.insert("temperature".into(), json!(0.0));
+    }
+    if let Some(user_id) = obj.get("user_id").cloned() {
+        obj.entry("user").or_insert(user_id);
+    }
+    for field in ZHIPU_ONLY_FIELDS {
+        obj.remove(field);
+    }
+
+    let mut web_search = false;
+    if let Some(tools) = obj.get_mut("tools").and_then(Value::as_array_mut) {
+        tools.retain(|tool| match tool.get("type").and_then(Value::as_str) {
+            Some("function") | None => true,
+            Some("web_search") => {
+                web_search |= tool
+                    .pointer("/web_search/enable")
+                    .and_then(Value::as_bool)
+                    .unwrap_or(true);
+                false
+            }
+            Some(_) => false,
+        });
+        if tools.is_empty() {
+            obj.remove("tools");
+            obj.remove("tool_choice");
+        }
+    }
+    if web_search {
+        obj.insert("web_search_options".into(), json!({}));
+    }
+
+    if let Some(messages) = obj.get_mut("messages").and_then(Value::as_array_mut) {
+        for part in messages
+            .iter_mut()
+            .filter_map(|m| m.get_mut("content").and_then(Value::as_array_mut))
+            .flatten()
+        {
+            if let Some(url) = part.pointer_mut("/image_url/url")
+                && let Some(s) = url.as_str()
+                && !s.starts_with("http://")
+                && !s.starts_with("https://")
+                && !s.starts_with("data:")
+            {
+                *url = Value::String(format!("data:image/png;base64,{}", s));
+            }
+        }
+    }
+
+    let request = serde_json::from_value(body)
+        .map_err(|e| GatewayError::Config(format!("invalid chat request: {}", e)))?;
+    Ok((request, request_id))
+}
+
+/// 网关响应（完整响应或流式 chunk）转为智谱格式
+fn gateway_response_to_zhipu(v: &mut Value, request_id: Option<&str>) {
+    let Some(obj) = v.as_object_mut() else {
+        return;
+    };
+    // 网关错误体 {code, message} 转为智谱的 {error: {code, message}}
+    if !obj.contains_key("choices")
+        && let (Some(code), Some(message)) = (obj.remove("code"), obj.remove("message"))
+    {
+        obj.insert("error".into(), json!({"code": code, "message": message}));
+        return;
+    }
+    for field in ["object", "system_fingerprint", "service_tier"] {
+        obj.remove(field);
+    }
+    let request_id = request_id
+        .map(str::to_string)
+        .or_else(|| obj.get("id").and_then(Value::as_str).map(str::to_string));
+    if let Some(request_id) = request_id {
+        obj.insert("request_id".into(), Value::String(request_id));
+    }
+    let Some(choices) = obj.get_mut("choices").and_then(Value::as_array_mut) else {
+        return;
+    };
+    for choice in choices {
+        if choice.get("finish_reason").and_then(Value::as_str) == Some("content_filter") {
+            choice["finish_reason"] = json!("sensitive");
+        }
+        if let Some(choice) = choice.as_object_mut() {
+            choice.remove("logprobs");
+        }
+    }
+}
+
+fn zhipu_sse_frame(frame: &str, request_id: Option<&str>) -> String {
+    frame
+        .split('\n')
+        .map(|line| {
+            if let Some(data) = line.strip_prefix("data:")
+                && let Ok(mut v) = serde_json::from_str::<Value>(data.trim_start())
+            {
+                gateway_response_to_zhipu(&mut v, request_id);
+                return format!("data: {}", v);
+            }
+            line.to_string()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// 按 SSE 帧（空行分隔）缓冲并逐帧转换 data 行
+fn zhipu_stream_response(response: Response, request_id: Option<String>) -> Response {
+    let (parts, body) = response.into_parts();
+    let mut pending: Vec<u8> = Vec::new();
+    let stream = body.into_data_stream().map(move |chunk| {
+        chunk.map(|bytes| {
+            pending.extend_from_slice(&bytes);
+            let mut out = String::new();
+            while let Some(pos) = pending.windows(2).position(|w| w == b"\n\n") {
+                let frame: Vec<u8> = pending.drain(..pos + 2).collect();
+                out.push_str(&zhipu_sse_frame(
+                    &String::from_utf8_lossy(&frame[..pos]),
+                    request_id.as_deref(),
+                ));
+                out.push_str("\n\n");
+            }
+            Bytes::from(out)
+        })
+    });
+    Response::from_parts(parts, Body::from_stream(stream))
+}
+
+async fn zhipu_json_response(response: Response, request_id: Option<&str>) -> Response {
+    let (mut parts, body) = response.into_parts();
+    let bytes = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(e) => return GatewayError::Config(e.to_string()).into_response(),
+    };
+    let Ok(mut v) = serde_json::from_slice::<Value>(&bytes) else {
+        return Response::from_parts(parts, Body::from(bytes));
+    };
+    gateway_response_to_zhipu(&mut v, request_id);
+    parts.headers.remove(header::CONTENT_LENGTH);
+    Response::from_parts(parts, Body::from(v.to_string()))
+}
+
+/// 智谱原生协议入口 `/api/paas/v4/chat/completions`：
+/// 请求转为网关格式后走与 `/v1/chat/completions` 相同的链路（可路由到任意供应商），
+/// 响应（含 SSE 流与错误体）再转回智谱格式。鉴权使用 Client Token：
+/// 智谱 SDK 需关闭 JWT 签名，直接以 `Authorization: Bearer <client-token>` 发送
+pub async fn zhipu_chat_completions(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(body): Json<Value>,
+) -> Response {
+    let (gateway_req, request_id) = match zhipu_request_to_gateway(body) {
+        Ok(converted) => converted,
+        Err(e) => return zhipu_json_response(e.into_response(), None).await,
+    };
+    let stream = gateway_req.request.stream.unwrap_or(false);
+    let response = chat_completions(State(app_state), headers, Json(gateway_req))
+        .await
+        .unwrap_or_else(IntoResponse::into_response);
+    if stream && response.status().is_success() {
+        zhipu_stream_response(response, request_id)
+    } else {
+        zhipu_json_response(response, request_id.as_deref()).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zhipu_request_maps_native_fields() {
+        let (req, request_id) = zhipu_request_to_gateway(json!({
+            "model": "glm-4",
+            "request_id": "req-1",
+            "user_id": "u-1",
+            "do_sample": false,
+            "messages": [{"role": "user", "content": [
+                {"type": "text", "text": "hi"},
+                {"type": "image_url", "image_url": {"url": "aGVsbG8="}}
+            ]}],
+            "tools": [
+                {"type": "web_search", "web_search": {"enable": true}},
+                {"type": "retrieval", "retrieval": {"knowledge_id": "k"}}
+            ]
+        }))
+        .unwrap();
+        assert_eq!(request_id.as_deref(), Some("req-1"));
+        let v = serde_json::to_value(&req.request).unwrap();
+        assert_eq!(v["temperature"], json!(0.0));
+        assert_eq!(v["user"], json!("u-1"));
+        assert!(v.get("tools").is_none());
+        assert!(v.get("web_search_options").is_some());
+        assert_eq!(
+            v.pointer("/messages/0/content/1/image_url/url"),
+            Some(&json!("data:image/png;base64,aGVsbG8="))
+        );
+    }
+
+    #[test]
+    fn gateway_response_and_errors_convert_to_zhipu_shape() {
+        let mut v = json!({
+            "id": "chatcmpl-1",
+            "object": "chat.completion",
+            "created": 1,
+            "model": "glm-4",
+            "choices": [{"index": 0, "finish_reason": "content_filter",
+                         "message": {"role": "assistant", "content": ""}}]
+        });
+        gateway_response_to_zhipu(&mut v, Some("req-1"));
+        assert!(v.get("object").is_none());
+        assert_eq!(v["request_id"], json!("req-1"));
+        assert_eq!(v["choices"][0]["finish_reason"], json!("sensitive"));
+
+        let mut err = json!({"code": "unauthorized", "message": "invalid token"});
+        gateway_response_to_zhipu(&mut err, None);
+        assert_eq!(
+            err,
+            json!({"error": {"code": "unauthorized", "message": "invalid token"}})
+        );
+    }
+
+    #[test]
+    fn stream_frames_keep_done_marker() {
+        let frame = zhipu_sse_frame(
+            "data: {\"id\":\"c1\",\"object\":\"chat.completion.chunk\",\"choices\":[]}",
+            None,
+        );
+        let v: Value = serde_json::from_str(frame.strip_prefix("data: ").unwrap()).unwrap();
+        assert_eq!(v, json!({"id": "c1", "request_id": "c1", "choices": []}));
+        assert_eq!(zhipu_sse_frame("data: [DONE]", None), "data: [DONE]");
+    }
+}
@@ -0,0 +1,176 @@
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+
+use axum::http::HeaderMap;
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use sha2::Digest;
+
+use crate::error::GatewayError;
+use crate::logging::types::StoredIdempotentResponse;
+use crate::server::AppState;
+
+/// 幂等键请求头（仅对非流式 /v1/chat/completions 生效）
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+/// 回放缓存响应时附带的响应头
+pub const IDEMPOTENT_REPLAYED_HEADER: &str = "idempotent-replayed";
+const IDEMPOTENCY_KEY_MAX_LEN: usize = 255;
+
+/// 读取并校验 Idempotency-Key；未携带时返回 None
+pub fn idempotency_key(headers: &HeaderMap) -> Result<Option<String>, GatewayError> {
+    let Some(value) = headers.get(IDEMPOTENCY_KEY_HEADER) else {
+        return Ok(None);
+    };
+    let key = value
+        .to_str()
+        .map_err(|_| GatewayError::Config("Idempotency-Key must be visible ASCII".into()))?
+        .trim();
+    if key.is_empty() || key.len() > IDEMPOTENCY_KEY_MAX_LEN {
+        return Err(GatewayError::Config(format!(
+            "Idempotency-Key must be 1-{} characters",
+            IDEMPOTENCY_KEY_MAX_LEN
+        )));
+    }
+    Ok(Some(key.to_string()))
+}
+
+/// 请求体指纹：同一幂等键只能用于同一请求，避免误把不同请求的结果回放给调用方
+pub fn request_fingerprint(request_snapshot: &str) -> String {
+    let mut hasher = sha2::Sha256::new();
+    hasher.update(request_snapshot.as_bytes());
+    hex::encode(hasher.finalize())
+}
+
+/// 进行中的 (token_id, key)：同一键的并发重试直接返回 409，避免重复调用上游与重复计费
+#[derive(Default)]
+pub struct InFlightKeys {
+    keys: Mutex<HashSet<(String, String)>>,
+}
+
+impl InFlightKeys {
+    fn try_begin(self: &Arc<Self>, token_id: &str, key: &str) -> Option<InFlightGuard> {
+        let entry = (token_id.to_string(), key.to_string());
+        let mut keys = self.keys.lock().unwrap_or_else(|e| e.into_inner());
+        if !keys.insert(entry.clone()) {
+            return None;
+        }
+        Some(InFlightGuard {
+            owner: self.clone(),
+            entry,
+        })
+    }
+}
+
+struct InFlightGuard {
+    owner: Arc<InFlightKeys>,
+    entry: (String, String),
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.owner
+            .keys
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&self.entry);
+    }
+}
+
+pub enum IdempotencyOutcome {
+    /// 已有未过期的成功响应：直接回放，不再访问上游、不再计费
+    Replay(Value),
+    /// 首次请求（或已过期）：执行完成后调用 `complete` 持久化结果
+    Claimed(IdempotencyClaim),
+}
+
+pub struct IdempotencyClaim {
+    token_id: String,
+    key: String,
+    fingerprint: String,
+    _guard: InFlightGuard,
+}
+
+/// 占用幂等键：先登记进行中（防并发重复执行），再查询持久化的历史结果
+pub async fn claim(
+    app_state: &AppState,
+    token_id: &str,
+    key: &str,
+    request_snapshot: &str,
+) -> Result<IdempotencyOutcome, GatewayError> {
+    let guard = app_state
+        .idempotency_in_flight
+        .try_begin(token_id, key)
+        .ok_or_else(|| {
+            GatewayError::Conflict(
+                "a request with this Idempotency-Key is still in progress".into(),
+            )
+        })?;
+    let fingerprint = request_fingerprint(request_snapshot);
+    if let Some(stored) = app_state
+        .log_store
+        .get_idempotent_response(token_id, key)
+        .await?
+    {
+        if stored.request_fingerprint != fingerprint {
+            return Err(GatewayError::Config(
+                "Idempotency-Key was already used with a different request body".into(),
+            ));
+        }
+        return Ok(IdempotencyOutcome::Replay(serde_json::from_str(
+            &stored.response_json,
+        )?));
+    }
+    Ok(IdempotencyOutcome::Claimed(IdempotencyClaim {
+        token_id: token_id.to_string(),
+        key: key.to_string(),
+        fingerprint,
+        _guard: guard,
+    }))
+}
+
+impl IdempotencyClaim {
+    /// 持久化成功响应（按配置的 TTL 过期）；写入失败仅记录告警，不影响本次响应
+    pub async fn complete(self, app_state: &AppState, response: &Value, now: DateTime<Utc>) {
+        let ttl = chrono::Duration::seconds(app_state.config.server.idempotency_ttl_secs as i64);
+        let record = StoredIdempotentResponse {
+            token_id: self.token_id.clone(),
+            idempotency_key: self.key.clone(),
+            request_fingerprint: self.fingerprint.clone(),
+            response_json: response.to_string(),
+            created_at: now,
+            expires_at: now + ttl,
+        };
+        if let Err(e) = app_state.log_store.save_idempotent_response(record).await {
+            tracing::warn!(key = %self.key, "failed to persist idempotent response: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    #[test]
+    fn idempotency_key_is_trimmed_and_length_checked() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(idempotency_key(&headers).unwrap(), None);
+        headers.insert(IDEMPOTENCY_KEY_HEADER, HeaderValue::from_static(" abc "));
+        assert_eq!(idempotency_key(&headers).unwrap().as_deref(), Some("abc"));
+        headers.insert(
+            IDEMPOTENCY_KEY_HEADER,
+            HeaderValue::from_str(&"k".repeat(IDEMPOTENCY_KEY_MAX_LEN + 1)).unwrap(),
+        );
+        assert!(idempotency_key(&headers).is_err());
+    }
+
+    #[test]
+    fn in_flight_guard_releases_key_on_drop() {
+        let keys = Arc::new(InFlightKeys::default());
+        let guard = keys.try_begin("t1", "k1").unwrap();
+        assert!(keys.try_begin("t1", "k1").is_none());
+        assert!(keys.try_begin("t2", "k1").is_some());
+        drop(guard);
+        assert!(keys.try_begin("t1", "k1").is_some());
+    }
+}
@@ -0,0 +1,398 @@
+use chrono::{DateTime, Utc};
+use serde_json::{Value, json};
+
+use crate::admin::client_token_id_for_token;
+use crate::config::ProviderType;
+use crate::error::GatewayError;
+use crate::logging::RequestLog;
+use crate::logging::types::{
+    ImagePriceRecord, LatencyBreakdown, PromptProfile, REQ_TYPE_IMAGE_GENERATION,
+    RequestLogDetailRecord,
+};
+use crate::providers::openai::OpenAIProvider;
+use crate::routing::SelectedProvider;
+use crate::server::AppState;
+use crate::server::chat_plan::{DecisionTrace, check_token_limits};
+use crate::server::pricing::missing_price_allowed_for_chat;
+use crate::server::provider_dispatch::select_provider_for_model;
+use crate::server::usage_webhooks::{self, UsageEvent};
+use crate::server::util::mask_key;
+
+pub const IMAGE_GENERATIONS_PATH: &str = "/v1/images/generations";
+/// 请求未指定 size/quality 时按 OpenAI 默认值计价
+pub const DEFAULT_IMAGE_SIZE: &str = "1024x1024";
+pub const DEFAULT_IMAGE_QUALITY: &str = "standard";
+/// 单价表中匹配任意 size/quality 的取值
+pub const IMAGE_PRICE_WILDCARD: &str = "*";
+pub const MAX_IMAGES_PER_REQUEST: u32 = 10;
+
+/// 校验后的图片生成请求；`body` 为待转发的原始请求体
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageRequest {
+    pub model: String,
+    pub n: u32,
+    pub size: String,
+    pub quality: String,
+    pub body: Value,
+}
+
+impl ImageRequest {
+    pub fn parse(body: Value) -> Result<Self, GatewayError> {
+        let obj = body
+            .as_object()
+            .ok_or_else(|| GatewayError::Config("request body must be a JSON object".into()))?;
+        let model = obj
+            .get("model")
+            .and_then(Value::as_str)
+            .filter(|m| !m.trim().is_empty())
+            .ok_or_else(|| GatewayError::Config("model is required".into()))?
+            .to_string();
+        if obj
+            .get("prompt")
+            .and_then(Value::as_str)
+            .is_none_or(|p| p.trim().is_empty())
+        {
+            return Err(GatewayError::Config(
+                "prompt must be a non-empty string".into(),
+            ));
+        }
+        let n = match obj.get("n") {
+            None | Some(Value::Null) => 1,
+            Some(v) => v
+                .as_u64()
+                .filter(|n| (1..=MAX_IMAGES_PER_REQUEST as u64).contains(n))
+                .ok_or_else(|| {
+                    GatewayError::Config(format!(
+                        "n must be an integer between 1 and {}",
+                        MAX_IMAGES_PER_REQUEST
+                    ))
+                })? as u32,
+        };
+        let text = |field: &str, default: &str| -> Result<String, GatewayError> {
+            match obj.get(field) {
+                None | Some(Value::Null) => Ok(default.to_string()),
+                Some(Value::String(s)) => Ok(s.clone()),
+                Some(_) => Err(GatewayError::Config(format!("{} must be a string", field))),
+            }
+        };
+        Ok(Self {
+            size: text("size", DEFAULT_IMAGE_SIZE)?,
+            quality: text("quality", DEFAULT_IMAGE_QUALITY)?,
+            model,
+            n,
+            body,
+        })
+    }
+
+    /// 写入请求日志详情的计费摘要
+    fn log_snapshot(&self, images: u32, price: Option<&ImagePriceRecord>) -> String {
+        json!({
+            "model": self.model,
+            "n": self.n,
+            "images": images,
+            "size": self.size,
+            "quality": self.quality,
+            "price_per_image": price.map(|p| p.price_per_image),
+        })
+        .to_string()
+    }
+}
+
+/// 按 (model, size, quality) 查找单价：精确匹配优先，其次 size、quality 为通配
+pub fn match_image_price<'a>(
+    prices: &'a [ImagePriceRecord],
+    model: &str,
+    size: &str,
+    quality: &str,
+) -> Option<&'a ImagePriceRecord> {
+    prices
+        .iter()
+        .filter(|p| p.model == model)
+        .filter(|p| p.size == size || p.size == IMAGE_PRICE_WILDCARD)
+        .filter(|p| p.quality == quality || p.quality == IMAGE_PRICE_WILDCARD)
+        .max_by_key(|p| (p.size == size, p.quality == quality))
+}
+
+/// 仅 OpenAI 与智谱 CogView 已接入图片生成
+fn ensure_image_capable(api_type: ProviderType, provider: &str) -> Result<(), GatewayError> {
+    match api_type {
+        ProviderType::OpenAI | ProviderType::Zhipu => Ok(()),
+        other => Err(GatewayError::Config(format!(
+            "image generation is not supported by provider '{}' ({:?})",
+            provider, other
+        ))),
+    }
+}
+
+/// 智谱单次只生成一张：按 n 并发调用后合并 data
+async fn call_upstream(
+    selected: &SelectedProvider,
+    body: &Value,
+    n: u32,
+) -> Result<Value, GatewayError> {
+    let provider = &selected.provider;
+    match provider.api_type {
+        ProviderType::Zhipu => {
+            let calls = (0..n).map(|_| {
+                crate::providers::zhipu::image_generations(
+                    &provider.base_url,
+                    &selected.api_key,
+                    body,
+                )
+            });
+            let mut merged: Option<Value> = None;
+            for result in futures_util::future::join_all(calls).await {
+                let mut raw = result?;
+                match merged.as_mut() {
+                    None => merged = Some(raw),
+                    Some(acc) => {
+                        let extra = raw
+                            .get_mut("data")
+                            .and_then(Value::as_array_mut)
+                            .map(std::mem::take)
+                            .unwrap_or_default();
+                        if let Some(data) = acc.get_mut("data").and_then(Value::as_array_mut) {
+                            data.extend(extra);
+                        }
+                    }
+                }
+            }
+            Ok(merged.unwrap_or_else(|| json!({"data": []})))
+        }
+        _ => {
+            OpenAIProvider::image_generations(
+                &provider.base_url,
+                &selected.api_key,
+                &selected.openai_account,
+                body,
+            )
+            .await
+        }
+    }
+}
+
+/// 已通过检查、可直接调用上游的图片生成请求
+pub struct AdmittedImageRequest {
+    pub request: ImageRequest,
+    pub selected: SelectedProvider,
+    pub upstream_model: String,
+    pub price: Option<ImagePriceRecord>,
+}
+
+/// 调用上游前的检查：令牌与用户额度（含按张预估的金额预算）、限流、供应商选择与单价查找
+pub async fn admit(
+    app_state: &AppState,
+    raw_client_token: &str,
+    request: ImageRequest,
+) -> Result<AdmittedImageRequest, GatewayError> {
+    let token = app_state
+        .token_store
+        .get_token(raw_client_token)
+        .await?
+        .ok_or_else(|| GatewayError::Config("invalid token".into()))?;
+    crate::server::sandbox::reject_sandbox_token(&token)?;
+    let mut trace = DecisionTrace::new(&request.model);
+    check_token_limits(app_state, &token, &request.model, &mut trace).await?;
+    app_state.runtime_settings.check_rate_limit(&token.id)?;
+    app_state.request_quota.try_acquire(&token, Utc::now())?;
+
+    let (selected, parsed_model) =
+        select_provider_for_model(app_state, &request.model, Some(&token.id)).await?;
+    ensure_image_capable(selected.provider.api_type, &selected.provider.name)?;
+    let upstream_model = parsed_model.get_upstream_model_name().to_string();
+
+    let prices = app_state
+        .log_store
+        .list_image_prices(Some(&selected.provider.name))
+        .await?;
+    let price =
+        match_image_price(&prices, &upstream_model, &request.size, &request.quality).cloned();
+    if price.is_none() && !missing_price_allowed_for_chat(app_state) {
+        return Err(GatewayError::Config(format!(
+            "no image price configured for {}/{} (size {}, quality {})",
+            selected.provider.name, upstream_model, request.size, request.quality
+        )));
+    }
+    // 令牌金额预算按本次最多生成的张数预估，避免一次请求大幅超支
+    if let (Some(max_amount), Some(price)) = (token.max_amount, price.as_ref())
+        && token.amount_spent + price.price_per_image * request.n as f64 > max_amount
+    {
+        return Err(GatewayError::Config("token budget exceeded".into()));
+    }
+    Ok(AdmittedImageRequest {
+        request,
+        selected,
+        upstream_model,
+        price,
+    })
+}
+
+/// 调用上游并按实际返回的张数计费，成功与失败均写入请求日志
+pub async fn execute(
+    app_state: &AppState,
+    start_time: DateTime<Utc>,
+    raw_client_token: &str,
+    admitted: AdmittedImageRequest,
+) -> Result<Value, GatewayError> {
+    let AdmittedImageRequest {
+        request,
+        selected,
+        upstream_model,
+        price,
+    } = admitted;
+    let provider_name = selected.provider.name.clone();
+    let mut body = request.body.clone();
+    if let Some(obj) = body.as_object_mut() {
+        obj.insert("model".into(), Value::String(upstream_model.clone()));
+    }
+    let upstream_started_at = Utc::now();
+    let response = call_upstream(&selected, &body, request.n).await;
+    let upstream_finished_at = Utc::now();
+
+    let images = response
+        .as_ref()
+        .ok()
+        .and_then(|raw| raw.get("data").and_then(Value::as_array))
+        .map(|data| data.len() as u32)
+        .unwrap_or(0);
+    let amount_spent = price.as_ref().map(|p| p.price_per_image * images as f64);
+    let api_key = mask_key(&selected.api_key);
+    app_state
+        .load_balancer_state
+        .record_key_usage(&provider_name, &api_key, None, amount_spent);
+    crate::server::provider_budget::record_spend(app_state, &provider_name, amount_spent).await;
+
+    let end_time = Utc::now();
+    let log = RequestLog {
+        id: None,
+        timestamp: start_time,
+        method: "POST".to_string(),
+        path: IMAGE_GENERATIONS_PATH.to_string(),
+        request_type: REQ_TYPE_IMAGE_GENERATION.to_string(),
+        requested_model: Some(request.model.clone()),
+        effective_model: Some(upstream_model.clone()),
+        model: Some(upstream_model),
+        provider: Some(provider_name.clone()),
+        api_key: Some(api_key.clone()),
+        client_token: Some(client_token_id_for_token(raw_client_token)),
+        user_id: None,
+        amount_spent,
+        status_code: match &response {
+            Ok(_) => 200,
+            Err(e) => e.status_code().as_u16(),
+        },
+        response_time_ms: (end_time - start_time).num_milliseconds(),
+        prompt_tokens: None,
+        completion_tokens: None,
+        total_tokens: None,
+        cached_tokens: None,
+        reasoning_tokens: None,
+        error_message: response.as_ref().err().map(|e| e.to_string()),
+        latency: LatencyBreakdown::from_marks(
+            start_time,
+            Some(upstream_started_at),
+            Some(upstream_finished_at),
+            end_time,
+        ),
+        profile: PromptProfile::default(),
+    };
+    // 图片按金额计费；订阅余额以 token 为单位，不在此扣减；先于请求日志入账
+    let counted = match amount_spent {
+        Some(delta) => {
+            crate::server::request_logging::count_token_usage(
+                app_state,
+                raw_client_token,
+                Some(delta),
+                None,
+            )
+            .await
+        }
+        None => None,
+    };
+    let usage_event = UsageEvent::from_request_log(&log);
+    let log_id = match crate::server::degraded::log_request(app_state.log_store.as_ref(), log).await
+    {
+        Ok(id) => Some(id),
+        Err(e) => {
+            tracing::error!("Failed to log request: {}", e);
+            None
+        }
+    };
+    if let Some(counted) = counted {
+        counted.logged(log_id).await;
+    }
+    usage_webhooks::enqueue_for_request(app_state, Some(raw_client_token), usage_event, log_id)
+        .await;
+    if let Some(request_log_id) = log_id {
+        let detail = RequestLogDetailRecord {
+            request_log_id,
+            request_payload_snapshot: Some(request.log_snapshot(images, price.as_ref())),
+            response_preview: None,
+            upstream_status: Some(if response.is_ok() { 200 } else { 500 }),
+            fallback_triggered: None,
+            fallback_reason: None,
+            selected_provider: Some(provider_name),
+            selected_key_id: Some(api_key),
+            first_token_latency_ms: None,
+            param_policy_applied: None,
+            provider_override: None,
+            prompt_truncation: None,
+        };
+        if let Err(e) = app_state.log_store.upsert_request_log_detail(detail).await {
+            tracing::warn!("Failed to upsert request log detail: {}", e);
+        }
+    }
+
+    if amount_spent.is_some() {
+        crate::server::chat_pipeline::disable_token_if_over_limits(app_state, raw_client_token)
+            .await;
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn price(model: &str, size: &str, quality: &str, price_per_image: f64) -> ImagePriceRecord {
+        ImagePriceRecord {
+            provider: "p1".into(),
+            model: model.into(),
+            size: size.into(),
+            quality: quality.into(),
+            price_per_image,
+            currency: None,
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn parse_applies_defaults_and_validates() {
+        let req = ImageRequest::parse(json!({"model": "dall-e-3", "prompt": "a cat"})).unwrap();
+        assert_eq!(
+            (req.n, req.size.as_str(), req.quality.as_str()),
+            (1, "1024x1024", "standard")
+        );
+        assert!(ImageRequest::parse(json!({"model": "m", "prompt": ""})).is_err());
+        assert!(ImageRequest::parse(json!({"model": "m", "prompt": "x", "n": 0})).is_err());
+        assert!(ImageRequest::parse(json!({"model": "m", "prompt": "x", "n": 11})).is_err());
+        assert!(ImageRequest::parse(json!({"prompt": "x"})).is_err());
+    }
+
+    #[test]
+    fn exact_price_beats_wildcards() {
+        let prices = vec![
+            price("dall-e-3", "*", "*", 0.04),
+            price("dall-e-3", "1024x1024", "hd", 0.08),
+            price("dall-e-3", "*", "hd", 0.06),
+            price("cogview-3", "*", "*", 0.1),
+        ];
+        let hit = |size, quality| {
+            match_image_price(&prices, "dall-e-3", size, quality).map(|p| p.price_per_image)
+        };
+        assert_eq!(hit("1024x1024", "hd"), Some(0.08));
+        assert_eq!(hit("1792x1024", "hd"), Some(0.06));
+        assert_eq!(hit("1024x1024", "standard"), Some(0.04));
+        assert!(match_image_price(&prices, "gpt-image-1", "1024x1024", "hd").is_none());
+    }
+}
@@ -0,0 +1,295 @@
+//! 各供应商进行中的上游请求与流式响应数（本实例），经 /metrics 与管理端指标概览暴露；
+//! 进行中请求数持续达到阈值时通过通知 Webhook 发出饱和告警，回落后发送恢复通知。
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use axum::body::Body;
+use axum::response::Response;
+use chrono::{DateTime, Duration, Utc};
+use futures_util::StreamExt;
+use serde::Serialize;
+
+use crate::config::settings::InFlightAlertConfig;
+use crate::server::AppState;
+use crate::server::admin_notifications;
+
+/// 饱和检测的采样间隔
+const SAMPLE_INTERVAL_SECS: u64 = 5;
+
+pub const ALERT_SATURATED: &str = "provider_saturated";
+pub const ALERT_RECOVERED: &str = "provider_saturation_recovered";
+
+#[derive(Default)]
+struct ProviderGauge {
+    requests: AtomicUsize,
+    streams: AtomicUsize,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct SaturationState {
+    since: Option<DateTime<Utc>>,
+    alerted: bool,
+}
+
+#[derive(Default)]
+pub struct InFlightTracker {
+    gauges: Mutex<HashMap<String, Arc<ProviderGauge>>>,
+    saturation: Mutex<HashMap<String, SaturationState>>,
+}
+
+/// 一次进行中的上游调用，释放时计数减一
+pub struct InFlightGuard {
+    gauge: Arc<ProviderGauge>,
+    stream: bool,
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.gauge.requests.fetch_sub(1, Ordering::SeqCst);
+        if self.stream {
+            self.gauge.streams.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ProviderInFlight {
+    pub provider: String,
+    /// 进行中的上游请求（含流式）
+    pub in_flight: usize,
+    pub streams: usize,
+    /// 持续达到告警阈值的起始时间
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub saturated_since: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SaturationAlert {
+    pub kind: &'static str,
+    pub provider: String,
+    pub in_flight: usize,
+    pub streams: usize,
+    pub threshold: u32,
+    pub since: DateTime<Utc>,
+}
+
+impl InFlightTracker {
+    fn gauge(&self, provider: &str) -> Arc<ProviderGauge> {
+        let mut gauges = self.gauges.lock().unwrap_or_else(|e| e.into_inner());
+        gauges.entry(provider.to_string()).or_default().clone()
+    }
+
+    /// 记录一次发往上游的调用；流式调用需配合 [`hold_during_stream`] 持有到响应体结束
+    pub fn start(&self, provider: &str, stream: bool) -> InFlightGuard {
+        let gauge = self.gauge(provider);
+        gauge.requests.fetch_add(1, Ordering::SeqCst);
+        if stream {
+            gauge.streams.fetch_add(1, Ordering::SeqCst);
+        }
+        InFlightGuard { gauge, stream }
+    }
+
+    /// 出现过请求的供应商及其当前计数（按供应商名排序）
+    pub fn snapshot(&self) -> Vec<ProviderInFlight> {
+        let gauges = self.gauges.lock().unwrap_or_else(|e| e.into_inner());
+        let saturation = self.saturation.lock().unwrap_or_else(|e| e.into_inner());
+        let mut out = gauges
+            .iter()
+            .map(|(provider, gauge)| ProviderInFlight {
+                provider: provider.clone(),
+                in_flight: gauge.requests.load(Ordering::SeqCst),
+                streams: gauge.streams.load(Ordering::SeqCst),
+                saturated_since: saturation.get(provider).and_then(|s| s.since),
+            })
+            .collect::<Vec<_>>();
+        out.sort_by(|a, b| a.provider.cmp(&b.provider));
+        out
+    }
+
+    /// 采样一次：进行中请求数达到阈值并维持 sustain_secs 后产生一次饱和告警，
+    /// 回落到阈值以下时重置，已告警的供应商产生恢复通知
+    pub fn observe(
+        &self,
+        config: &InFlightAlertConfig,
+        now: DateTime<Utc>,
+    ) -> Vec<SaturationAlert> {
+        let snapshot = self.snapshot();
+        let mut saturation = self.saturation.lock().unwrap_or_else(|e| e.into_inner());
+        let mut alerts = Vec::new();
+        for current in snapshot {
+            let threshold = config.threshold_for(&current.provider);
+            let state = saturation.entry(current.provider.clone()).or_default();
+            let alert = |kind, since| SaturationAlert {
+                kind,
+                provider: current.provider.clone(),
+                in_flight: current.in_flight,
+                streams: current.streams,
+                threshold,
+                since,
+            };
+            if threshold > 0 && current.in_flight >= threshold as usize {
+                let since = *state.since.get_or_insert(now);
+                if !state.alerted && now - since >= Duration::seconds(config.sustain_secs as i64) {
+                    state.alerted = true;
+                    alerts.push(alert(ALERT_SATURATED, since));
+                }
+            } else {
+                if let Some(since) = state.since
+                    && state.alerted
+                {
+                    alerts.push(alert(ALERT_RECOVERED, since));
+                }
+                *state = SaturationState::default();
+            }
+        }
+        alerts
+    }
+}
+
+/// 流式响应：计数保持到响应体输出完毕（或客户端断开）
+pub fn hold_during_stream(response: Response, guard: InFlightGuard) -> Response {
+    let (parts, body) = response.into_parts();
+    let body = Body::from_stream(body.into_data_stream().map(move |chunk| {
+        let _ = &guard;
+        chunk
+    }));
+    Response::from_parts(parts, body)
+}
+
+async fn deliver(app_state: &AppState, alert: &SaturationAlert) {
+    match alert.kind {
+        ALERT_SATURATED => tracing::warn!(
+            provider = %alert.provider,
+            in_flight = alert.in_flight,
+            threshold = alert.threshold,
+            "provider in-flight requests saturated"
+        ),
+        _ => tracing::info!(provider = %alert.provider, "provider saturation recovered"),
+    }
+    if alert.kind == ALERT_SATURATED {
+        admin_notifications::notify(
+            app_state.log_store.as_ref(),
+            admin_notifications::NewNotification {
+                kind: admin_notifications::KIND_PROVIDER_UNHEALTHY,
+                severity: admin_notifications::Severity::Warning,
+                title: format!("Provider {} saturated", alert.provider),
+                message: format!(
+                    "{} in-flight requests (threshold {}) since {}.",
+                    alert.in_flight,
+                    alert.threshold,
+                    crate::logging::time::to_iso8601_utc_string(&alert.since)
+                ),
+                reference: Some(alert.provider.clone()),
+            },
+        )
+        .await;
+    }
+    let Some(url) = app_state
+        .config
+        .server
+        .notification_webhook_url
+        .as_deref()
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+    else {
+        return;
+    };
+    let body = serde_json::json!({
+        "kind": alert.kind,
+        "provider": alert.provider,
+        "in_flight": alert.in_flight,
+        "streams": alert.streams,
+        "threshold": alert.threshold,
+        "since": crate::logging::time::to_iso8601_utc_string(&alert.since),
+        "sent_at": crate::logging::time::to_iso8601_utc_string(&Utc::now()),
+    });
+    if let Err(e) = crate::server::notifications::post_webhook_json(url, &body).await {
+        tracing::warn!("saturation alert webhook failed: {}", e);
+    }
+}
+
+/// 配置了 in_flight_alerts 时定期采样并发送饱和告警
+pub fn spawn_alert_task(app_state: Arc<AppState>) {
+    let Some(config) = app_state.config.server.in_flight_alerts.clone() else {
+        return;
+    };
+    let tasks = app_state.task_registry.clone();
+    tasks.spawn_with("in_flight_alerts", |mut ctx| async move {
+        let mut ticker =
+            tokio::time::interval(std::time::Duration::from_secs(SAMPLE_INTERVAL_SECS));
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {}
+                _ = ctx.cancelled() => break,
+            }
+            for alert in app_state.in_flight.observe(&config, Utc::now()) {
+                deliver(&app_state, &alert).await;
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn guards_track_requests_and_streams() {
+        let tracker = InFlightTracker::default();
+        let a = tracker.start("openai", false);
+        let b = tracker.start("openai", true);
+        let stream = hold_during_stream(Response::new(Body::from("data: x\n\n")), b);
+        let snapshot = tracker.snapshot();
+        assert_eq!((snapshot[0].in_flight, snapshot[0].streams), (2, 1));
+
+        drop(a);
+        axum::body::to_bytes(stream.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let snapshot = tracker.snapshot();
+        assert_eq!((snapshot[0].in_flight, snapshot[0].streams), (0, 0));
+    }
+
+    #[test]
+    fn saturation_alerts_after_sustained_period_and_recovers() {
+        let tracker = InFlightTracker::default();
+        let config = InFlightAlertConfig {
+            threshold: 2,
+            sustain_secs: 30,
+            providers: HashMap::from([("local".to_string(), 0)]),
+        };
+        let t0 = DateTime::parse_from_rfc3339("2026-01-01T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let at = |secs| t0 + Duration::seconds(secs);
+        let held = vec![
+            tracker.start("openai", false),
+            tracker.start("openai", true),
+            tracker.start("local", false),
+            tracker.start("local", false),
+        ];
+
+        assert!(tracker.observe(&config, at(0)).is_empty());
+        assert!(tracker.observe(&config, at(20)).is_empty());
+        let alerts = tracker.observe(&config, at(30));
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].kind, ALERT_SATURATED);
+        assert_eq!(alerts[0].provider, "openai");
+        assert_eq!(alerts[0].since, at(0));
+        // 持续饱和只告警一次；阈值为 0 的供应商不告警
+        assert!(tracker.observe(&config, at(60)).is_empty());
+
+        drop(held);
+        let alerts = tracker.observe(&config, at(65));
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].kind, ALERT_RECOVERED);
+        assert!(
+            tracker
+                .snapshot()
+                .iter()
+                .all(|p| p.saturated_since.is_none())
+        );
+    }
+}
@@ -0,0 +1,585 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Notify;
+
+use crate::error::GatewayError;
+use crate::server::storage_traits::{BoxFuture, SettingsStore};
+use crate::server::tasks::TaskRegistry;
+
+/// gateway_settings 中保存各定时任务运行状态的键
+const JOB_STATE_KEY: &str = "scheduled_jobs";
+/// 调度循环最长休眠时间，避免系统时间调整后长时间错过执行
+const MAX_SLEEP_SECS: i64 = 60;
+/// 向后查找下一次执行时间的范围
+const LOOKAHEAD_DAYS: i64 = 366 * 5;
+
+/// 五段式 cron 表达式（分 时 日 月 周，按 UTC 计算），支持 `*`、`a-b`、`*/n`、`a-b/n`、逗号列表，
+/// 以及 `@hourly` / `@daily` / `@weekly` / `@monthly`。周字段 0 与 7 均表示周日；
+/// 日与周同时受限时满足任一即可（与 crontab 一致）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronSchedule {
+    expr: String,
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    days_restricted: bool,
+    weekdays_restricted: bool,
+}
+
+fn invalid(expr: &str, reason: impl std::fmt::Display) -> GatewayError {
+    GatewayError::Config(format!("invalid cron expression '{}': {}", expr, reason))
+}
+
+fn parse_field(expr: &str, field: &str, min: u32, max: u32) -> Result<u64, GatewayError> {
+    let number = |raw: &str| {
+        raw.parse::<u32>()
+            .map_err(|_| invalid(expr, format!("'{}' is not a number", raw)))
+    };
+    let mut bits = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => match number(step)? {
+                0 => return Err(invalid(expr, "step must be positive")),
+                step => (range, step),
+            },
+            None => (part, 1),
+        };
+        let (lo, hi) = if range == "*" {
+            (min, max)
+        } else if let Some((lo, hi)) = range.split_once('-') {
+            (number(lo)?, number(hi)?)
+        } else if step > 1 {
+            (number(range)?, max)
+        } else {
+            let value = number(range)?;
+            (value, value)
+        };
+        if lo < min || hi > max || lo > hi {
+            return Err(invalid(
+                expr,
+                format!("'{}' is outside {}-{}", part, min, max),
+            ));
+        }
+        for value in (lo..=hi).step_by(step as usize) {
+            bits |= 1 << value;
+        }
+    }
+    Ok(bits)
+}
+
+fn has(bits: u64, value: u32) -> bool {
+    bits & (1 << value) != 0
+}
+
+fn midnight(date: NaiveDate) -> DateTime<Utc> {
+    date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc()
+}
+
+impl CronSchedule {
+    pub fn parse(expr: &str) -> Result<Self, GatewayError> {
+        let trimmed = expr.trim();
+        let expanded = match trimmed {
+            "@hourly" => "0 * * * *",
+            "@daily" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            other => other,
+        };
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        let &[minute, hour, day, month, weekday] = fields.as_slice() else {
+            return Err(invalid(trimmed, "expected 5 fields"));
+        };
+        let mut weekdays = parse_field(trimmed, weekday, 0, 7)?;
+        if has(weekdays, 7) {
+            weekdays = (weekdays & !(1 << 7)) | 1;
+        }
+        Ok(Self {
+            expr: trimmed.to_string(),
+            minutes: parse_field(trimmed, minute, 0, 59)?,
+            hours: parse_field(trimmed, hour, 0, 23)?,
+            days: parse_field(trimmed, day, 1, 31)?,
+            months: parse_field(trimmed, month, 1, 12)?,
+            weekdays,
+            days_restricted: !day.starts_with('*'),
+            weekdays_restricted: !weekday.starts_with('*'),
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.expr
+    }
+
+    fn day_matches(&self, t: DateTime<Utc>) -> bool {
+        let day = has(self.days, t.day());
+        let weekday = has(self.weekdays, t.weekday().num_days_from_sunday());
+        if self.days_restricted && self.weekdays_restricted {
+            day || weekday
+        } else {
+            day && weekday
+        }
+    }
+
+    /// `after` 之后（不含）的下一次执行时间；表达式永远无法满足（如 2 月 30 日）时返回 None
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let limit = after + Duration::days(LOOKAHEAD_DAYS);
+        let mut t = after.with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+        while t <= limit {
+            if !has(self.months, t.month()) {
+                let (year, month) = match t.month() {
+                    12 => (t.year() + 1, 1),
+                    m => (t.year(), m + 1),
+                };
+                t = midnight(NaiveDate::from_ymd_opt(year, month, 1)?);
+            } else if !self.day_matches(t) {
+                t = midnight(t.date_naive().succ_opt()?);
+            } else if !has(self.hours, t.hour()) {
+                t = t.with_minute(0)? + Duration::hours(1);
+            } else if !has(self.minutes, t.minute()) {
+                t += Duration::minutes(1);
+            } else {
+                return Some(t);
+            }
+        }
+        None
+    }
+}
+
+/// 任务执行体：返回本次处理的条目数
+pub type JobRun = Arc<dyn Fn() -> BoxFuture<'static, Result<usize, GatewayError>> + Send + Sync>;
+
+/// 持久化的任务运行状态
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct JobRunState {
+    #[serde(default)]
+    pub paused: bool,
+    #[serde(default)]
+    pub last_run_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub last_duration_ms: Option<i64>,
+    /// success / failed
+    #[serde(default)]
+    pub last_outcome: Option<String>,
+    #[serde(default)]
+    pub last_error: Option<String>,
+    /// 最近一次成功运行处理的条目数
+    #[serde(default)]
+    pub last_processed: Option<usize>,
+    /// 暂停时为空
+    #[serde(default)]
+    pub next_run_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub run_count: u64,
+    #[serde(default)]
+    pub failure_count: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct JobInfo {
+    pub name: String,
+    pub description: String,
+    pub schedule: String,
+    pub running: bool,
+    #[serde(flatten)]
+    pub state: JobRunState,
+}
+
+struct Job {
+    name: String,
+    description: String,
+    schedule: CronSchedule,
+    run: JobRun,
+}
+
+#[derive(Default)]
+struct JobSlot {
+    state: JobRunState,
+    running: bool,
+}
+
+/// 定时任务调度器：按 cron 表达式触发已登记的任务，运行状态持久化在 gateway_settings 中。
+/// 每次运行作为 `job:<name>` 登记到 TaskRegistry，关闭时随其他后台任务一起等待或取消
+pub struct JobScheduler {
+    jobs: Vec<Job>,
+    slots: Mutex<HashMap<String, JobSlot>>,
+    store: Arc<dyn SettingsStore + Send + Sync>,
+    tasks: Arc<TaskRegistry>,
+    wake: Notify,
+}
+
+impl JobScheduler {
+    pub fn new(store: Arc<dyn SettingsStore + Send + Sync>, tasks: Arc<TaskRegistry>) -> Self {
+        Self {
+            jobs: Vec::new(),
+            slots: Mutex::new(HashMap::new()),
+            store,
+            tasks,
+            wake: Notify::new(),
+        }
+    }
+
+    /// 登记任务；同名任务后登记者覆盖先登记者
+    pub fn add(
+        &mut self,
+        name: &str,
+        description: &str,
+        schedule: CronSchedule,
+        run: JobRun,
+    ) -> &mut Self {
+        self.jobs.retain(|job| job.name != name);
+        self.jobs.push(Job {
+            name: name.to_string(),
+            description: description.to_string(),
+            schedule,
+            run,
+        });
+        self
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, JobSlot>> {
+        self.slots.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn job(&self, name: &str) -> Result<&Job, GatewayError> {
+        self.jobs
+            .iter()
+            .find(|job| job.name == name)
+            .ok_or_else(|| GatewayError::NotFound(format!("job '{}' not found", name)))
+    }
+
+    /// 读取持久化状态并计算下一次执行时间；停机期间错过的执行会在启动后立即补跑一次
+    pub async fn load(&self) -> Result<(), GatewayError> {
+        let persisted: HashMap<String, JobRunState> =
+            match self.store.get_setting(JOB_STATE_KEY).await? {
+                Some(raw) => serde_json::from_str(&raw).unwrap_or_else(|e| {
+                    tracing::warn!("Ignoring unreadable scheduled job state: {}", e);
+                    HashMap::new()
+                }),
+                None => HashMap::new(),
+            };
+        let now = Utc::now();
+        let mut slots = self.lock();
+        for job in &self.jobs {
+            let mut state = persisted.get(&job.name).cloned().unwrap_or_default();
+            state.next_run_at = match state.next_run_at {
+                _ if state.paused => None,
+                Some(missed) if missed <= now => Some(missed),
+                _ => job.schedule.next_after(now),
+            };
+            slots.insert(
+                job.name.clone(),
+                JobSlot {
+                    state,
+                    running: false,
+                },
+            );
+        }
+        Ok(())
+    }
+
+    async fn save(&self) {
+        let snapshot: HashMap<String, JobRunState> = self
+            .lock()
+            .iter()
+            .map(|(name, slot)| (name.clone(), slot.state.clone()))
+            .collect();
+        let result = match serde_json::to_string(&snapshot) {
+            Ok(raw) => self
+                .store
+                .set_setting(JOB_STATE_KEY, &raw)
+                .await
+                .map_err(GatewayError::from),
+            Err(e) => Err(e.into()),
+        };
+        if let Err(e) = result {
+            tracing::warn!("Failed to persist scheduled job state: {}", e);
+        }
+    }
+
+    fn info(&self, job: &Job, slots: &HashMap<String, JobSlot>) -> JobInfo {
+        let slot = slots.get(&job.name);
+        JobInfo {
+            name: job.name.clone(),
+            description: job.description.clone(),
+            schedule: job.schedule.as_str().to_string(),
+            running: slot.is_some_and(|s| s.running),
+            state: slot.map(|s| s.state.clone()).unwrap_or_default(),
+        }
+    }
+
+    /// 全部任务（按登记顺序）
+    pub fn list(&self) -> Vec<JobInfo> {
+        let slots = self.lock();
+        self.jobs.iter().map(|job| self.info(job, &slots)).collect()
+    }
+
+    pub fn get(&self, name: &str) -> Result<JobInfo, GatewayError> {
+        let job = self.job(name)?;
+        Ok(self.info(job, &self.lock()))
+    }
+
+    /// 立即运行一次（不影响计划时间，暂停中的任务也可手动运行）；任务正在运行时返回冲突
+    pub fn trigger(self: &Arc<Self>, name: &str) -> Result<JobInfo, GatewayError> {
+        self.job(name)?;
+        if !self.launch(name) {
+            return Err(GatewayError::Conflict(format!(
+                "job '{}' is already running",
+                name
+            )));
+        }
+        self.get(name)
+    }
+
+    /// 暂停或恢复任务；恢复后从当前时间起计算下一次执行
+    pub async fn set_paused(&self, name: &str, paused: bool) -> Result<JobInfo, GatewayError> {
+        let job = self.job(name)?;
+        {
+            let mut slots = self.lock();
+            let slot = slots.entry(job.name.clone()).or_default();
+            if slot.state.paused != paused {
+                slot.state.paused = paused;
+                slot.state.next_run_at = if paused {
+                    None
+                } else {
+                    job.schedule.next_after(Utc::now())
+                };
+            }
+        }
+        self.save().await;
+        self.wake.notify_one();
+        self.get(name)
+    }
+
+    /// 标记为运行中并启动；已在运行时返回 false
+    fn launch(self: &Arc<Self>, name: &str) -> bool {
+        {
+            let mut slots = self.lock();
+            let slot = slots.entry(name.to_string()).or_default();
+            if slot.running {
+                return false;
+            }
+            slot.running = true;
+        }
+        let scheduler = self.clone();
+        let name = name.to_string();
+        self.tasks.spawn(format!("job:{}", name), async move {
+            scheduler.execute(&name).await;
+        });
+        true
+    }
+
+    async fn execute(&self, name: &str) {
+        let Ok(job) = self.job(name) else {
+            return;
+        };
+        let started_at = Utc::now();
+        let result = (job.run)().await;
+        let finished_at = Utc::now();
+        {
+            let mut slots = self.lock();
+            let slot = slots.entry(job.name.clone()).or_default();
+            let state = &mut slot.state;
+            slot.running = false;
+            state.last_run_at = Some(started_at);
+            state.last_duration_ms = Some((finished_at - started_at).num_milliseconds());
+            state.run_count += 1;
+            match result {
+                Ok(processed) => {
+                    state.last_outcome = Some("success".into());
+                    state.last_error = None;
+                    state.last_processed = Some(processed);
+                    if processed > 0 {
+                        tracing::info!("Job {} processed {} items", job.name, processed);
+                    }
+                }
+                Err(e) => {
+                    tracing::warn!("Job {} failed: {}", job.name, e);
+                    state.last_outcome = Some("failed".into());
+                    state.last_error = Some(e.to_string());
+                    state.failure_count += 1;
+                }
+            }
+            if !state.paused {
+                state.next_run_at = job.schedule.next_after(finished_at);
+            }
+        }
+        self.save().await;
+        self.wake.notify_one();
+    }
+
+    /// 启动到期的任务，返回最近一次计划执行时间
+    fn run_due(self: &Arc<Self>, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let due: Vec<String> = {
+            let slots = self.lock();
+            slots
+                .iter()
+                .filter(|(_, slot)| !slot.running && !slot.state.paused)
+                .filter(|(_, slot)| slot.state.next_run_at.is_some_and(|at| at <= now))
+                .map(|(name, _)| name.clone())
+                .collect()
+        };
+        for name in due {
+            self.launch(&name);
+        }
+        self.lock()
+            .values()
+            .filter(|slot| !slot.running)
+            .filter_map(|slot| slot.state.next_run_at)
+            .min()
+    }
+
+    /// 启动调度循环（收到关闭信号后不再触发新的运行）
+    pub fn spawn(self: &Arc<Self>) {
+        let scheduler = self.clone();
+        self.tasks.spawn_with("job_scheduler", |mut ctx| async move {
+            loop {
+                let now = Utc::now();
+                let wait = scheduler
+                    .run_due(now)
+                    .map(|at| (at - now).num_seconds().clamp(0, MAX_SLEEP_SECS))
+                    .unwrap_or(MAX_SLEEP_SECS);
+                tokio::select! {
+                    _ = tokio::time::sleep(std::time::Duration::from_secs(wait as u64 + 1)) => {}
+                    _ = scheduler.wake.notified() => {}
+                    _ = ctx.cancelled() => break,
+                }
+            }
+        });
+    }
+}
+
+static SCHEDULER: OnceLock<Arc<JobScheduler>> = OnceLock::new();
+
+pub fn install(scheduler: Arc<JobScheduler>) {
+    let _ = SCHEDULER.set(scheduler);
+}
+
+/// 未安装（如测试环境）时为 None
+pub fn job_scheduler() -> Option<Arc<JobScheduler>> {
+    SCHEDULER.get().cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn at(raw: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(raw)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn cron_expressions_compute_next_run() {
+        let next = |expr: &str, after: &str| {
+            CronSchedule::parse(expr)
+                .unwrap()
+                .next_after(at(after))
+                .map(|t| t.to_rfc3339())
+        };
+        let expect = |raw: &str| Some(at(raw).to_rfc3339());
+        assert_eq!(
+            next("*/15 * * * *", "2026-01-01T10:07:30Z"),
+            expect("2026-01-01T10:15:00Z")
+        );
+        assert_eq!(
+            next("@hourly", "2026-01-01T10:00:00Z"),
+            expect("2026-01-01T11:00:00Z")
+        );
+        assert_eq!(
+            next("30 2 * * 1-5", "2026-01-02T03:00:00Z"),
+            expect("2026-01-05T02:30:00Z")
+        );
+        assert_eq!(
+            next("0 0 1 * *", "2026-12-15T00:00:00Z"),
+            expect("2027-01-01T00:00:00Z")
+        );
+        // 日与周同时受限：满足任一即可
+        assert_eq!(
+            next("0 0 13 * 5", "2026-01-01T00:00:00Z"),
+            expect("2026-01-02T00:00:00Z")
+        );
+        assert_eq!(next("0 0 30 2 *", "2026-01-01T00:00:00Z"), None);
+        for bad in [
+            "* * * *",
+            "60 * * * *",
+            "*/0 * * * *",
+            "5-1 * * * *",
+            "a * * * *",
+        ] {
+            assert!(CronSchedule::parse(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[tokio::test]
+    async fn scheduler_runs_persists_and_pauses_jobs() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = crate::logging::DatabaseLogger::new(dir.path().join("jobs.db").to_str().unwrap())
+            .await
+            .unwrap();
+        let store: Arc<dyn SettingsStore + Send + Sync> = Arc::new(db);
+        let runs = Arc::new(AtomicUsize::new(0));
+        let build = |store: Arc<dyn SettingsStore + Send + Sync>| {
+            let runs = runs.clone();
+            let mut scheduler = JobScheduler::new(store, Arc::new(TaskRegistry::default()));
+            scheduler.add(
+                "count",
+                "counts runs",
+                CronSchedule::parse("0 0 1 1 *").unwrap(),
+                Arc::new(move || {
+                    let runs = runs.clone();
+                    Box::pin(async move { Ok(runs.fetch_add(1, Ordering::SeqCst) + 1) })
+                }),
+            );
+            Arc::new(scheduler)
+        };
+
+        let scheduler = build(store.clone());
+        scheduler.load().await.unwrap();
+        let info = scheduler.get("count").unwrap();
+        assert!(info.state.next_run_at.unwrap() > Utc::now());
+        assert!(matches!(
+            scheduler.get("missing"),
+            Err(GatewayError::NotFound(_))
+        ));
+
+        scheduler.trigger("count").unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        let info = scheduler.get("count").unwrap();
+        assert!(!info.running);
+        assert_eq!(info.state.run_count, 1);
+        assert_eq!(info.state.last_outcome.as_deref(), Some("success"));
+        assert_eq!(info.state.last_processed, Some(1));
+
+        let info = scheduler.set_paused("count", true).await.unwrap();
+        assert!(info.state.paused);
+        assert!(info.state.next_run_at.is_none());
+
+        // 重启后恢复运行记录与暂停状态
+        let restarted = build(store.clone());
+        restarted.load().await.unwrap();
+        let info = restarted.get("count").unwrap();
+        assert!(info.state.paused);
+        assert_eq!(info.state.run_count, 1);
+        let info = restarted.set_paused("count", false).await.unwrap();
+        assert!(info.state.next_run_at.is_some());
+
+        // 停机期间错过的执行在启动后补跑
+        let mut state = restarted.get("count").unwrap().state;
+        state.next_run_at = Some(Utc::now() - Duration::minutes(5));
+        let raw = serde_json::to_string(&HashMap::from([("count".to_string(), state)])).unwrap();
+        store.set_setting(JOB_STATE_KEY, &raw).await.unwrap();
+        let catch_up = build(store);
+        catch_up.load().await.unwrap();
+        catch_up.run_due(Utc::now());
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        let info = catch_up.get("count").unwrap();
+        assert_eq!(info.state.run_count, 2);
+        assert!(info.state.next_run_at.unwrap() > Utc::now());
+    }
+}
@@ -0,0 +1,73 @@
+use serde_json::{Map, Value};
+
+use crate::config::ProviderType;
+use crate::providers::openai::ChatCompletionRequest;
+
+/// 旧版文本补全入口；经此入口的请求以该路径记录日志与计费
+pub const LEGACY_COMPLETIONS_PATH: &str = "/v1/completions";
+
+/// 旧版文本补全独有、聊天请求中没有对应项的字段
+pub const LEGACY_ONLY_FIELDS: [&str; 5] = ["prompt", "suffix", "echo", "logprobs", "best_of"];
+
+/// `/v1/completions` 请求的原始独有字段，由入口随请求显式传到分发阶段
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LegacyFields(Map<String, Value>);
+
+impl LegacyFields {
+    pub fn new(fields: Map<String, Value>) -> Self {
+        Self(fields)
+    }
+
+    /// 供应商支持原生 `/v1/completions` 时返回原生请求体：
+    /// 以（已重定向模型名、已套用参数策略的）聊天请求为基础，去掉 messages 后补回 prompt 等独有字段。
+    /// 流式请求始终走聊天转换
+    pub fn native_request(
+        &self,
+        provider_type: ProviderType,
+        request: &ChatCompletionRequest,
+    ) -> Option<Value> {
+        if !provider_type.supports_legacy_completions() || request.stream.unwrap_or(false) {
+            return None;
+        }
+        let mut body = serde_json::to_value(request).ok()?;
+        let obj = body.as_object_mut()?;
+        obj.remove("messages");
+        obj.extend(self.0.clone());
+        Some(body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn request() -> ChatCompletionRequest {
+        serde_json::from_value(json!({
+            "model": "gpt-3.5-turbo-instruct",
+            "messages": [{"role": "user", "content": "def fib(n):"}],
+            "max_tokens": 32
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn native_request_only_for_capable_providers() {
+        let mut fields = Map::new();
+        fields.insert("prompt".into(), json!("def fib(n):"));
+        fields.insert("suffix".into(), json!("\n\nprint(fib(10))"));
+        let legacy = LegacyFields::new(fields);
+        let body = legacy
+            .native_request(ProviderType::OpenAI, &request())
+            .unwrap();
+        assert!(body.get("messages").is_none());
+        assert_eq!(body["prompt"], json!("def fib(n):"));
+        assert_eq!(body["suffix"], json!("\n\nprint(fib(10))"));
+        assert_eq!(body["max_tokens"], json!(32));
+        assert!(
+            legacy
+                .native_request(ProviderType::Anthropic, &request())
+                .is_none()
+        );
+    }
+}
@@ -0,0 +1,488 @@
+//! 聊天请求宽松解析：部分客户端发送的请求体与 OpenAI 规范略有出入（数字/布尔值写成字符串、
+//! 携带未知字段），直接反序列化只会得到笼统的错误。本中间件先把常见偏差改写为规范形式并记录警告
+//! （响应头 `X-Gateway-Request-Warnings`），无法改写时按字段路径返回精确的 400 错误；
+//! 各字段的偏差次数可在 `/admin/metrics/request-deviations` 查看，用于推动客户端修正。
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use axum::body::{Body, Bytes};
+use axum::extract::{Request, State};
+use axum::http::{HeaderValue, Method, header};
+use axum::middleware::Next;
+use axum::response::Response;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::error::GatewayError;
+use crate::providers::openai::ChatCompletionRequest;
+use crate::server::AppState;
+use crate::server::util::bearer_token;
+
+/// 响应头：本次请求被改写或忽略的字段，形如 `temperature:string_number, foo:unknown_field`
+pub const REQUEST_WARNINGS_HEADER: &str = "x-gateway-request-warnings";
+
+/// 与 axum `Json` 提取器的默认上限一致
+const BODY_MAX_BYTES: usize = 2 * 1024 * 1024;
+
+const LENIENT_PATHS: &[&str] = &["/v1/chat/completions", "/v1/chat/completions/plan"];
+
+const FLOAT_FIELDS: &[&str] = &[
+    "temperature",
+    "top_p",
+    "frequency_penalty",
+    "presence_penalty",
+];
+const INT_FIELDS: &[&str] = &[
+    "max_tokens",
+    "max_completion_tokens",
+    "n",
+    "seed",
+    "top_logprobs",
+    "top_k",
+];
+const BOOL_FIELDS: &[&str] = &["stream", "logprobs", "parallel_tool_calls", "store"];
+
+/// 规范字段与网关扩展字段；其余顶层字段会被忽略
+const KNOWN_FIELDS: &[&str] = &[
+    "messages",
+    "model",
+    "store",
+    "reasoning_effort",
+    "metadata",
+    "frequency_penalty",
+    "logit_bias",
+    "logprobs",
+    "top_logprobs",
+    "max_tokens",
+    "max_completion_tokens",
+    "n",
+    "modalities",
+    "prediction",
+    "audio",
+    "presence_penalty",
+    "response_format",
+    "seed",
+    "service_tier",
+    "stop",
+    "stream",
+    "stream_options",
+    "temperature",
+    "top_p",
+    "tools",
+    "tool_choice",
+    "parallel_tool_calls",
+    "user",
+    "web_search_options",
+    "function_call",
+    "functions",
+    "top_k",
+    "provider",
+    "provider_key",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeviationKind {
+    /// 数值字段写成了字符串（已转换）
+    StringNumber,
+    /// 布尔字段写成了字符串（已转换）
+    StringBoolean,
+    /// 未知的顶层字段（已忽略）
+    UnknownField,
+}
+
+impl DeviationKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::StringNumber => "string_number",
+            Self::StringBoolean => "string_boolean",
+            Self::UnknownField => "unknown_field",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Deviation {
+    pub field: String,
+    pub kind: DeviationKind,
+}
+
+#[derive(Debug)]
+pub struct LenientChatRequest {
+    pub deviations: Vec<Deviation>,
+    /// 改写后的请求体；没有需要转换的字段时为 None（原样转发）
+    pub rewritten: Option<Value>,
+}
+
+/// 解析失败：`field` 为出错字段的路径（如 `messages[0].role`）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidField {
+    pub field: String,
+    pub message: String,
+}
+
+impl From<InvalidField> for GatewayError {
+    fn from(e: InvalidField) -> Self {
+        GatewayError::Config(format!("invalid field `{}`: {}", e.field, e.message))
+    }
+}
+
+fn coerce_number(value: &str, integer: bool) -> Option<Value> {
+    let value = value.trim();
+    if integer {
+        value.parse::<i64>().ok().map(Value::from)
+    } else {
+        value
+            .parse::<f64>()
+            .ok()
+            .filter(|v| v.is_finite())
+            .map(Value::from)
+    }
+}
+
+fn coerce_fields(map: &mut Map<String, Value>, deviations: &mut Vec<Deviation>) -> bool {
+    let mut changed = false;
+    let numeric = FLOAT_FIELDS
+        .iter()
+        .map(|f| (*f, false))
+        .chain(INT_FIELDS.iter().map(|f| (*f, true)));
+    for (field, integer) in numeric {
+        if let Some(Value::String(raw)) = map.get(field)
+            && let Some(number) = coerce_number(raw, integer)
+        {
+            map.insert(field.to_string(), number);
+            deviations.push(Deviation {
+                field: field.to_string(),
+                kind: DeviationKind::StringNumber,
+            });
+            changed = true;
+        }
+    }
+    for field in BOOL_FIELDS {
+        let Some(Value::String(raw)) = map.get(*field) else {
+            continue;
+        };
+        let parsed = match raw.trim().to_ascii_lowercase().as_str() {
+            "true" => true,
+            "false" => false,
+            _ => continue,
+        };
+        map.insert(field.to_string(), Value::Bool(parsed));
+        deviations.push(Deviation {
+            field: field.to_string(),
+            kind: DeviationKind::StringBoolean,
+        });
+        changed = true;
+    }
+    changed
+}
+
+fn invalid(err: serde_path_to_error::Error<serde_json::Error>) -> InvalidField {
+    let path = err.path().to_string();
+    InvalidField {
+        field: if path == "." { "body".into() } else { path },
+        message: err.into_inner().to_string(),
+    }
+}
+
+/// `GatewayChatCompletionRequest` 中的网关扩展字段
+#[derive(serde::Deserialize)]
+#[allow(dead_code)]
+struct GatewayExtras {
+    top_k: Option<u32>,
+    provider: Option<String>,
+    provider_key: Option<String>,
+}
+
+/// 宽松解析聊天请求体：先转换常见偏差，再按规范类型严格反序列化
+pub fn parse(body: &[u8]) -> Result<LenientChatRequest, InvalidField> {
+    let value: Value = serde_json::from_slice(body).map_err(|e| InvalidField {
+        field: "body".into(),
+        message: e.to_string(),
+    })?;
+    let Value::Object(mut map) = value else {
+        return Err(InvalidField {
+            field: "body".into(),
+            message: "expected a JSON object".into(),
+        });
+    };
+    let mut deviations = Vec::new();
+    let changed = coerce_fields(&mut map, &mut deviations);
+    let mut unknown: Vec<&String> = map
+        .keys()
+        .filter(|k| !KNOWN_FIELDS.contains(&k.as_str()))
+        .collect();
+    unknown.sort();
+    deviations.extend(unknown.into_iter().map(|field| Deviation {
+        field: field.clone(),
+        kind: DeviationKind::UnknownField,
+    }));
+
+    let value = Value::Object(map);
+    // 按规范类型校验；分开反序列化是因为 `GatewayChatCompletionRequest` 的 flatten 会丢失出错字段的路径
+    serde_path_to_error::deserialize::<_, ChatCompletionRequest>(&value).map_err(invalid)?;
+    serde_path_to_error::deserialize::<_, GatewayExtras>(&value).map_err(invalid)?;
+    Ok(LenientChatRequest {
+        deviations,
+        rewritten: changed.then_some(value),
+    })
+}
+
+fn warnings_header(deviations: &[Deviation]) -> Option<HeaderValue> {
+    let text = deviations
+        .iter()
+        .map(|d| format!("{}:{}", d.field, d.kind.as_str()))
+        .collect::<Vec<_>>()
+        .join(", ");
+    HeaderValue::from_str(&text).ok()
+}
+
+/// `POST /v1/chat/completions`（及 `/plan`）请求体的宽松解析中间件
+pub async fn normalize_chat_body(
+    State(app_state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Result<Response, GatewayError> {
+    let path = request.uri().path();
+    if request.method() != Method::POST
+        || !LENIENT_PATHS.contains(&path.strip_prefix("/api").unwrap_or(path))
+    {
+        return Ok(next.run(request).await);
+    }
+    let token_id =
+        bearer_token(request.headers()).map(|t| crate::admin::client_token_id_for_token(&t));
+    let (mut parts, body) = request.into_parts();
+    let bytes: Bytes = axum::body::to_bytes(body, BODY_MAX_BYTES)
+        .await
+        .map_err(|_| GatewayError::PayloadTooLarge("request body too large".into()))?;
+    let parsed = match parse(&bytes) {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            app_state
+                .request_deviations
+                .record_rejected(&err.field, token_id.as_deref());
+            return Err(err.into());
+        }
+    };
+    if parsed.deviations.is_empty() {
+        return Ok(next
+            .run(Request::from_parts(parts, Body::from(bytes)))
+            .await);
+    }
+    app_state
+        .request_deviations
+        .record(&parsed.deviations, token_id.as_deref());
+    tracing::debug!(
+        token_id = token_id.as_deref().unwrap_or("-"),
+        deviations = ?parsed.deviations,
+        "chat request deviates from the OpenAI schema"
+    );
+    let body = match parsed.rewritten {
+        Some(value) => {
+            parts.headers.remove(header::CONTENT_LENGTH);
+            Body::from(serde_json::to_vec(&value).unwrap_or_else(|_| bytes.to_vec()))
+        }
+        None => Body::from(bytes),
+    };
+    let mut response = next.run(Request::from_parts(parts, body)).await;
+    if let Some(value) = warnings_header(&parsed.deviations) {
+        response
+            .headers_mut()
+            .insert(REQUEST_WARNINGS_HEADER, value);
+    }
+    Ok(response)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DeviationCount {
+    pub field: String,
+    pub kind: DeviationKind,
+    pub count: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RejectedFieldCount {
+    pub field: String,
+    pub count: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TokenDeviationCount {
+    pub token_id: String,
+    /// 被改写或忽略字段的请求数
+    pub deviating_requests: u64,
+    /// 因字段无法解析而被拒绝的请求数
+    pub rejected_requests: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RequestDeviationSnapshot {
+    pub deviations: Vec<DeviationCount>,
+    pub rejected: Vec<RejectedFieldCount>,
+    pub tokens: Vec<TokenDeviationCount>,
+}
+
+#[derive(Default)]
+struct DeviationCounters {
+    fields: HashMap<(String, DeviationKind), u64>,
+    rejected: HashMap<String, u64>,
+    /// 令牌 ID -> (偏差请求数, 拒绝请求数)
+    tokens: HashMap<String, (u64, u64)>,
+}
+
+/// 请求体偏差统计（本实例，启动以来累计）
+#[derive(Default)]
+pub struct RequestDeviationStats {
+    counters: Mutex<DeviationCounters>,
+}
+
+impl RequestDeviationStats {
+    pub fn record(&self, deviations: &[Deviation], token_id: Option<&str>) {
+        let mut counters = self.counters.lock().unwrap_or_else(|e| e.into_inner());
+        for d in deviations {
+            *counters
+                .fields
+                .entry((d.field.clone(), d.kind))
+                .or_insert(0) += 1;
+        }
+        if let Some(token_id) = token_id {
+            counters.tokens.entry(token_id.to_string()).or_default().0 += 1;
+        }
+    }
+
+    pub fn record_rejected(&self, field: &str, token_id: Option<&str>) {
+        let mut counters = self.counters.lock().unwrap_or_else(|e| e.into_inner());
+        *counters.rejected.entry(field.to_string()).or_insert(0) += 1;
+        if let Some(token_id) = token_id {
+            counters.tokens.entry(token_id.to_string()).or_default().1 += 1;
+        }
+    }
+
+    /// 各列表按次数降序
+    pub fn snapshot(&self) -> RequestDeviationSnapshot {
+        let counters = self.counters.lock().unwrap_or_else(|e| e.into_inner());
+        let mut deviations: Vec<_> = counters
+            .fields
+            .iter()
+            .map(|((field, kind), count)| DeviationCount {
+                field: field.clone(),
+                kind: *kind,
+                count: *count,
+            })
+            .collect();
+        deviations.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.field.cmp(&b.field)));
+        let mut rejected: Vec<_> = counters
+            .rejected
+            .iter()
+            .map(|(field, count)| RejectedFieldCount {
+                field: field.clone(),
+                count: *count,
+            })
+            .collect();
+        rejected.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.field.cmp(&b.field)));
+        let mut tokens: Vec<_> = counters
+            .tokens
+            .iter()
+            .map(|(token_id, (deviating, rejected))| TokenDeviationCount {
+                token_id: token_id.clone(),
+                deviating_requests: *deviating,
+                rejected_requests: *rejected,
+            })
+            .collect();
+        tokens.sort_by(|a, b| {
+            (b.deviating_requests + b.rejected_requests)
+                .cmp(&(a.deviating_requests + a.rejected_requests))
+                .then_with(|| a.token_id.cmp(&b.token_id))
+        });
+        RequestDeviationSnapshot {
+            deviations,
+            rejected,
+            tokens,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn coerces_string_numbers_and_booleans() {
+        let parsed = parse(
+            br#"{"model":"m","messages":[{"role":"user","content":"hi"}],
+                "temperature":"0.5","max_tokens":" 64 ","stream":"False","top_k":"5","foo":1}"#,
+        )
+        .unwrap();
+        let summary: Vec<_> = parsed
+            .deviations
+            .iter()
+            .map(|d| (d.field.as_str(), d.kind))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("temperature", DeviationKind::StringNumber),
+                ("max_tokens", DeviationKind::StringNumber),
+                ("top_k", DeviationKind::StringNumber),
+                ("stream", DeviationKind::StringBoolean),
+                ("foo", DeviationKind::UnknownField),
+            ]
+        );
+        let rewritten = parsed.rewritten.clone().unwrap();
+        assert_eq!(rewritten["temperature"], 0.5);
+        assert_eq!(rewritten["max_tokens"], 64);
+        assert_eq!(rewritten["stream"], false);
+        assert_eq!(rewritten["top_k"], 5);
+        assert_eq!(
+            warnings_header(&parsed.deviations).unwrap(),
+            "temperature:string_number, max_tokens:string_number, top_k:string_number, stream:string_boolean, foo:unknown_field"
+        );
+    }
+
+    #[test]
+    fn conformant_requests_pass_through_unchanged() {
+        let parsed =
+            parse(br#"{"model":"m","messages":[{"role":"user","content":"hi"}],"top_p":1}"#)
+                .unwrap();
+        assert!(parsed.deviations.is_empty());
+        assert!(parsed.rewritten.is_none());
+    }
+
+    #[test]
+    fn reports_the_failing_field_path() {
+        let err =
+            parse(br#"{"model":"m","messages":[{"role":"robot","content":"hi"}]}"#).unwrap_err();
+        assert_eq!(err.field, "messages[0].role");
+        let err = parse(br#"{"model":"m","messages":[],"temperature":"warm"}"#).unwrap_err();
+        assert_eq!(err.field, "temperature");
+        let err = parse(br#"{"messages":[]}"#).unwrap_err();
+        assert_eq!(err.field, "body");
+        assert!(err.message.contains("model"));
+        let err = parse(b"[1]").unwrap_err();
+        assert_eq!(err.field, "body");
+    }
+
+    #[test]
+    fn stats_count_fields_and_tokens() {
+        let stats = RequestDeviationStats::default();
+        let deviation = Deviation {
+            field: "temperature".into(),
+            kind: DeviationKind::StringNumber,
+        };
+        stats.record(std::slice::from_ref(&deviation), Some("t1"));
+        stats.record(&[deviation], None);
+        stats.record_rejected("messages[0]", Some("t1"));
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot.deviations[0].count, 2);
+        assert_eq!(snapshot.rejected[0].field, "messages[0]");
+        assert_eq!(
+            snapshot.tokens,
+            vec![TokenDeviationCount {
+                token_id: "t1".into(),
+                deviating_requests: 1,
+                rejected_requests: 1,
+            }]
+        );
+    }
+}
@@ -0,0 +1,171 @@
+use std::ops::Deref;
+
+use serde::{Serialize, Serializer, ser::SerializeSeq};
+
+use crate::error::GatewayError;
+use crate::logging::types::LogColumns;
+
+/// 可通过 `fields` 查询参数选择输出字段的日志条目
+pub trait LogFields: Serialize {
+    const FIELDS: &'static [&'static str];
+}
+
+/// `fields=id,timestamp,status_code` 形式的字段选择；为空表示返回全部字段
+#[derive(Debug, Clone, Default)]
+pub struct FieldSelection(Option<Vec<&'static str>>);
+
+impl FieldSelection {
+    pub fn parse<T: LogFields>(raw: Option<&str>) -> Result<Self, GatewayError> {
+        let Some(raw) = raw.map(str::trim).filter(|s| !s.is_empty()) else {
+            return Ok(Self(None));
+        };
+        let mut fields: Vec<&'static str> = Vec::new();
+        for name in raw.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            let Some(field) = T::FIELDS.iter().find(|f| **f == name) else {
+                return Err(GatewayError::Config(format!("unknown log field: {}", name)));
+            };
+            if !fields.contains(field) {
+                fields.push(field);
+            }
+        }
+        Ok(Self(Some(fields)))
+    }
+
+    pub fn includes(&self, field: &str) -> bool {
+        self.0.as_ref().is_none_or(|f| f.contains(&field))
+    }
+
+    /// 生成所选字段所依赖的数据库列
+    pub fn columns(&self) -> LogColumns {
+        match &self.0 {
+            None => LogColumns::all(),
+            Some(fields) => LogColumns::only(
+                fields
+                    .iter()
+                    .flat_map(|f| source_columns(f).iter().copied()),
+            ),
+        }
+    }
+}
+
+/// 输出字段 -> 计算该字段需要读取的 request_logs 列
+fn source_columns(field: &str) -> &'static [&'static str] {
+    const MODEL_COLUMNS: &[&str] = &["requested_model", "effective_model", "model", "provider"];
+    match field {
+        "method" => &["method"],
+        "path" => &["path"],
+        "request_type" => &["request_type"],
+        "requested_model"
+        | "effective_model"
+        | "requested_model_display"
+        | "effective_model_display"
+        | "model_display" => MODEL_COLUMNS,
+        "provider" => &["provider"],
+        "api_key" => &["api_key"],
+        "client_token_id" | "client_token_name" | "username" => &["client_token", "user_id"],
+        "amount_spent" => &["amount_spent"],
+        "amount_spent_currency" => &[
+            "request_type",
+            "requested_model",
+            "effective_model",
+            "model",
+            "provider",
+        ],
+        "status_code" | "success" => &["status_code"],
+        "response_time_ms" => &["response_time_ms"],
+        "prompt_tokens" => &["prompt_tokens"],
+        "completion_tokens" => &["completion_tokens"],
+        "total_tokens" => &["total_tokens"],
+        "cached_tokens" => &["cached_tokens"],
+        "reasoning_tokens" => &["reasoning_tokens"],
+        "error_message" => &["error_message"],
+        "replayable" => &["request_type"],
+        _ => &[],
+    }
+}
+
+/// 按字段选择序列化的列表；在代码中仍可像切片一样访问完整条目
+#[derive(Debug)]
+pub struct Projected<T> {
+    items: Vec<T>,
+    fields: FieldSelection,
+}
+
+impl<T> Projected<T> {
+    pub fn new(items: Vec<T>, fields: FieldSelection) -> Self {
+        Self { items, fields }
+    }
+}
+
+impl<T> Deref for Projected<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        &self.items
+    }
+}
+
+impl<T: Serialize> Serialize for Projected<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let Some(fields) = self.fields.0.as_ref() else {
+            return self.items.serialize(serializer);
+        };
+        let mut seq = serializer.serialize_seq(Some(self.items.len()))?;
+        for item in &self.items {
+            let mut value = serde_json::to_value(item).map_err(serde::ser::Error::custom)?;
+            if let Some(obj) = value.as_object_mut() {
+                obj.retain(|k, _| fields.contains(&k.as_str()));
+            }
+            seq.serialize_element(&value)?;
+        }
+        seq.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize)]
+    struct Entry {
+        id: i64,
+        status_code: u16,
+        error_message: Option<String>,
+    }
+
+    impl LogFields for Entry {
+        const FIELDS: &'static [&'static str] = &["id", "status_code", "error_message"];
+    }
+
+    #[test]
+    fn selection_prunes_output_and_columns() {
+        let fields = FieldSelection::parse::<Entry>(Some(" status_code,id,status_code ")).unwrap();
+        assert!(fields.includes("id") && !fields.includes("error_message"));
+        let columns = fields.columns();
+        assert!(columns.contains("status_code") && columns.contains("timestamp"));
+        assert!(!columns.contains("error_message") && !columns.contains("api_key"));
+        assert!(columns.select_list().contains("NULL AS error_message"));
+
+        let out = Projected::new(
+            vec![Entry {
+                id: 1,
+                status_code: 200,
+                error_message: None,
+            }],
+            fields,
+        );
+        assert_eq!(
+            serde_json::to_value(&out).unwrap(),
+            serde_json::json!([{ "id": 1, "status_code": 200 }])
+        );
+        assert_eq!(out[0].id, 1);
+    }
+
+    #[test]
+    fn empty_selection_returns_everything_and_unknown_fields_are_rejected() {
+        let all = FieldSelection::parse::<Entry>(None).unwrap();
+        assert!(all.includes("error_message"));
+        assert_eq!(all.columns(), LogColumns::all());
+        assert!(FieldSelection::parse::<Entry>(Some("id,token")).is_err());
+    }
+}
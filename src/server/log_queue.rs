@@ -0,0 +1,405 @@
+//! 请求日志的磁盘回放队列：写库失败（数据库不可用、写入超时）的日志以 JSON Lines 追加到本地文件，
+//! 存储恢复后按写入顺序回放。每条记录带请求 id：入队时同一 id 只保留一份，
+//! 回放成功的 id 先记入旁路文件（`*.done`）再压缩队列，进程在回放中途退出后重启不会重复写入。
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+
+use crate::logging::types::RequestLog;
+use crate::server::storage_traits::RequestLogStore;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct QueuedLog {
+    request_id: String,
+    enqueued_at: DateTime<Utc>,
+    log: RequestLog,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LogQueueStats {
+    /// 队列中待回放的日志数
+    pub depth: u64,
+    pub oldest_enqueued_at: Option<DateTime<Utc>>,
+    /// 最早一条待回放日志已等待的秒数
+    pub replay_lag_secs: Option<i64>,
+    pub last_replay_at: Option<DateTime<Utc>>,
+    /// 最近一次回放中最早那条日志从入队到写入的耗时（秒）
+    pub last_replay_lag_secs: Option<i64>,
+    /// 以下为累计计数
+    pub replayed_total: u64,
+    pub duplicates_total: u64,
+    /// 队列文件超过上限而丢弃的日志数
+    pub dropped_total: u64,
+}
+
+#[derive(Debug, Default)]
+struct QueueState {
+    /// 首次访问时从队列文件恢复
+    loaded: bool,
+    ids: HashSet<String>,
+    oldest: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Default)]
+struct LastReplay {
+    at: Option<DateTime<Utc>>,
+    lag_secs: Option<i64>,
+}
+
+pub struct LogReplayQueue {
+    path: PathBuf,
+    max_bytes: u64,
+    /// 串行化队列文件的追加、回放与恢复
+    state: tokio::sync::Mutex<QueueState>,
+    depth: AtomicU64,
+    oldest: Mutex<Option<DateTime<Utc>>>,
+    last_replay: Mutex<LastReplay>,
+    replayed: AtomicU64,
+    duplicates: AtomicU64,
+    dropped: AtomicU64,
+}
+
+fn read_entries(content: &str) -> Vec<(&str, Option<QueuedLog>)> {
+    content
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| (l, serde_json::from_str(l).ok()))
+        .collect()
+}
+
+async fn read_optional(path: &Path) -> std::io::Result<String> {
+    match tokio::fs::read_to_string(path).await {
+        Ok(content) => Ok(content),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(String::new()),
+        Err(e) => Err(e),
+    }
+}
+
+async fn remove_optional(path: &Path) -> std::io::Result<()> {
+    match tokio::fs::remove_file(path).await {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+impl LogReplayQueue {
+    pub fn new(path: impl Into<PathBuf>, max_bytes: u64) -> Self {
+        Self {
+            path: path.into(),
+            max_bytes,
+            state: tokio::sync::Mutex::new(QueueState::default()),
+            depth: AtomicU64::new(0),
+            oldest: Mutex::new(None),
+            last_replay: Mutex::new(LastReplay::default()),
+            replayed: AtomicU64::new(0),
+            duplicates: AtomicU64::new(0),
+            dropped: AtomicU64::new(0),
+        }
+    }
+
+    fn done_path(&self) -> PathBuf {
+        self.path.with_extension("done")
+    }
+
+    fn set_oldest(&self, state: &QueueState) {
+        *self.oldest.lock().unwrap_or_else(|e| e.into_inner()) = state.oldest;
+    }
+
+    /// 从队列文件恢复 id 集合与深度（排除旁路文件中已回放的 id）
+    async fn ensure_loaded(&self, state: &mut QueueState) -> std::io::Result<()> {
+        if state.loaded {
+            return Ok(());
+        }
+        let content = read_optional(&self.path).await?;
+        let done = read_optional(&self.done_path()).await?;
+        let done: HashSet<&str> = done.lines().collect();
+        for entry in read_entries(&content).into_iter().filter_map(|(_, e)| e) {
+            if done.contains(entry.request_id.as_str()) {
+                continue;
+            }
+            state.oldest = Some(match state.oldest {
+                Some(t) => t.min(entry.enqueued_at),
+                None => entry.enqueued_at,
+            });
+            state.ids.insert(entry.request_id);
+        }
+        state.loaded = true;
+        self.depth.store(state.ids.len() as u64, Ordering::Relaxed);
+        self.set_oldest(state);
+        Ok(())
+    }
+
+    pub fn depth(&self) -> u64 {
+        self.depth.load(Ordering::Relaxed)
+    }
+
+    /// 追加一条日志；同一 request_id 已在队列中时跳过并返回 false，超过大小上限时丢弃
+    pub async fn enqueue(&self, request_id: &str, log: &RequestLog) -> std::io::Result<bool> {
+        let mut state = self.state.lock().await;
+        self.ensure_loaded(&mut state).await?;
+        if state.ids.contains(request_id) {
+            self.duplicates.fetch_add(1, Ordering::Relaxed);
+            return Ok(false);
+        }
+        let entry = QueuedLog {
+            request_id: request_id.to_string(),
+            enqueued_at: Utc::now(),
+            log: log.clone(),
+        };
+        let mut line = serde_json::to_vec(&entry)?;
+        line.push(b'\n');
+        let size = tokio::fs::metadata(&self.path).await.map_or(0, |m| m.len());
+        if size + line.len() as u64 > self.max_bytes {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+            return Err(std::io::Error::other("request log queue is full"));
+        }
+        if let Some(dir) = self.path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            tokio::fs::create_dir_all(dir).await?;
+        }
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await?;
+        file.write_all(&line).await?;
+        file.flush().await?;
+        state.ids.insert(entry.request_id);
+        state.oldest.get_or_insert(entry.enqueued_at);
+        self.depth.store(state.ids.len() as u64, Ordering::Relaxed);
+        self.set_oldest(&state);
+        Ok(true)
+    }
+
+    /// 按写入顺序回放；遇到写入失败即停止并返回该错误，已回放与未回放的部分都不会丢失。
+    /// 返回本次写入存储的日志 ID（不含跳过的重复项）
+    pub async fn replay(
+        &self,
+        store: &(dyn RequestLogStore + Send + Sync),
+    ) -> Result<Vec<i64>, ReplayError> {
+        let mut state = self.state.lock().await;
+        self.ensure_loaded(&mut state).await?;
+        let content = read_optional(&self.path).await?;
+        let entries = read_entries(&content);
+        if entries.is_empty() {
+            return Ok(Vec::new());
+        }
+        let done_path = self.done_path();
+        let done_content = read_optional(&done_path).await?;
+        let mut seen: HashSet<String> = done_content.lines().map(str::to_string).collect();
+        let mut done = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&done_path)
+            .await?;
+
+        let now = Utc::now();
+        let mut consumed = 0;
+        let mut replayed = Vec::new();
+        let mut first_lag = None;
+        let mut failure = None;
+        for (_, entry) in &entries {
+            let Some(entry) = entry else {
+                tracing::warn!("Skipping unreadable queued request log");
+                consumed += 1;
+                continue;
+            };
+            if !seen.insert(entry.request_id.clone()) {
+                self.duplicates.fetch_add(1, Ordering::Relaxed);
+                consumed += 1;
+                continue;
+            }
+            match store.log_request(entry.log.clone()).await {
+                Ok(id) => replayed.push(id),
+                Err(e) => {
+                    failure = Some(e);
+                    break;
+                }
+            }
+            done.write_all(format!("{}\n", entry.request_id).as_bytes())
+                .await?;
+            done.flush().await?;
+            first_lag.get_or_insert((now - entry.enqueued_at).num_seconds().max(0));
+            consumed += 1;
+        }
+        drop(done);
+
+        // 先压缩队列文件再清除旁路文件；两步之间退出时旁路文件仍能阻止重复回放
+        let rest = &entries[consumed..];
+        if rest.is_empty() {
+            remove_optional(&self.path).await?;
+        } else if consumed > 0 {
+            let body: String = rest.iter().map(|(line, _)| format!("{}\n", line)).collect();
+            let tmp = self.path.with_extension("tmp");
+            tokio::fs::write(&tmp, body).await?;
+            tokio::fs::rename(&tmp, &self.path).await?;
+        }
+        if consumed > 0 {
+            remove_optional(&done_path).await?;
+        }
+
+        state.ids = rest
+            .iter()
+            .filter_map(|(_, e)| e.as_ref().map(|e| e.request_id.clone()))
+            .collect();
+        state.oldest = rest
+            .iter()
+            .filter_map(|(_, e)| e.as_ref().map(|e| e.enqueued_at))
+            .min();
+        self.depth.store(state.ids.len() as u64, Ordering::Relaxed);
+        self.set_oldest(&state);
+        if !replayed.is_empty() {
+            self.replayed
+                .fetch_add(replayed.len() as u64, Ordering::Relaxed);
+            let mut last = self.last_replay.lock().unwrap_or_else(|e| e.into_inner());
+            last.at = Some(now);
+            last.lag_secs = first_lag;
+        }
+        match failure {
+            Some(e) => Err(ReplayError::Store { replayed, error: e }),
+            None => Ok(replayed),
+        }
+    }
+
+    pub fn stats(&self) -> LogQueueStats {
+        let oldest = *self.oldest.lock().unwrap_or_else(|e| e.into_inner());
+        let last = self.last_replay.lock().unwrap_or_else(|e| e.into_inner());
+        LogQueueStats {
+            depth: self.depth(),
+            oldest_enqueued_at: oldest,
+            replay_lag_secs: oldest.map(|t| (Utc::now() - t).num_seconds().max(0)),
+            last_replay_at: last.at,
+            last_replay_lag_secs: last.lag_secs,
+            replayed_total: self.replayed.load(Ordering::Relaxed),
+            duplicates_total: self.duplicates.load(Ordering::Relaxed),
+            dropped_total: self.dropped.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(std::io::Error),
+    /// 存储仍不可用；replayed 为失败前已写入的日志 ID
+    Store {
+        replayed: Vec<i64>,
+        error: rusqlite::Error,
+    },
+}
+
+impl From<std::io::Error> for ReplayError {
+    fn from(e: std::io::Error) -> Self {
+        ReplayError::Io(e)
+    }
+}
+
+impl std::fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReplayError::Io(e) => write!(f, "request log queue I/O error: {}", e),
+            ReplayError::Store { replayed, error } => write!(
+                f,
+                "request log store unavailable after replaying {} logs: {}",
+                replayed.len(),
+                error
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logging::DatabaseLogger;
+    use tempfile::tempdir;
+
+    fn log(path: &str) -> RequestLog {
+        serde_json::from_value(serde_json::json!({
+            "timestamp": "2026-01-01T00:00:00Z",
+            "method": "POST",
+            "path": path,
+            "request_type": "chat_once",
+            "status_code": 200,
+            "response_time_ms": 12,
+            "latency": {},
+        }))
+        .unwrap()
+    }
+
+    async fn logged_paths(logger: &DatabaseLogger) -> Vec<String> {
+        logger
+            .get_recent_logs(10)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|l| l.path)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn replays_in_order_and_skips_duplicate_request_ids() {
+        let dir = tempdir().unwrap();
+        let logger = DatabaseLogger::new(dir.path().join("gw.db").to_str().unwrap())
+            .await
+            .unwrap();
+        let path = dir.path().join("queue/logs.jsonl");
+        let queue = LogReplayQueue::new(&path, 1 << 20);
+        assert!(queue.enqueue("r1", &log("/v1/a")).await.unwrap());
+        assert!(!queue.enqueue("r1", &log("/v1/a")).await.unwrap());
+        assert!(queue.enqueue("r2", &log("/v1/b")).await.unwrap());
+        let stats = queue.stats();
+        assert_eq!(stats.depth, 2);
+        assert_eq!(stats.duplicates_total, 1);
+        assert!(stats.replay_lag_secs.is_some());
+
+        // 重启后从文件恢复深度
+        let queue = LogReplayQueue::new(&path, 1 << 20);
+        assert!(!queue.enqueue("r2", &log("/v1/b")).await.unwrap());
+        assert_eq!(queue.depth(), 2);
+
+        assert_eq!(queue.replay(&logger).await.unwrap().len(), 2);
+        assert!(!path.exists());
+        assert!(!path.with_extension("done").exists());
+        let stats = queue.stats();
+        assert_eq!(stats.depth, 0);
+        assert_eq!(stats.replayed_total, 2);
+        assert!(stats.replay_lag_secs.is_none());
+        assert!(stats.last_replay_at.is_some());
+        assert_eq!(logged_paths(&logger).await, vec!["/v1/b", "/v1/a"]);
+    }
+
+    #[tokio::test]
+    async fn ids_recorded_as_replayed_are_not_written_again() {
+        let dir = tempdir().unwrap();
+        let logger = DatabaseLogger::new(dir.path().join("gw.db").to_str().unwrap())
+            .await
+            .unwrap();
+        let path = dir.path().join("logs.jsonl");
+        let queue = LogReplayQueue::new(&path, 1 << 20);
+        queue.enqueue("r1", &log("/v1/a")).await.unwrap();
+        queue.enqueue("r2", &log("/v1/b")).await.unwrap();
+        // 模拟上次回放写入 r1 后、压缩队列前退出
+        std::fs::write(path.with_extension("done"), "r1\n").unwrap();
+
+        let queue = LogReplayQueue::new(&path, 1 << 20);
+        assert_eq!(queue.replay(&logger).await.unwrap().len(), 1);
+        assert_eq!(queue.stats().duplicates_total, 1);
+        assert_eq!(logged_paths(&logger).await, vec!["/v1/b"]);
+    }
+
+    #[tokio::test]
+    async fn drops_logs_beyond_size_limit() {
+        let dir = tempdir().unwrap();
+        let queue = LogReplayQueue::new(dir.path().join("logs.jsonl"), 64);
+        assert!(queue.enqueue("r1", &log("/v1/a")).await.is_err());
+        assert_eq!(queue.stats().dropped_total, 1);
+        assert_eq!(queue.depth(), 0);
+    }
+}
@@ -12,8 +12,10 @@ use tokio::sync::RwLock;
 
 use crate::error::GatewayError;
 use crate::server::storage_traits::{
-    AdminPublicKeyRecord, LoginCodeRecord, LoginStore, TuiSessionRecord, WebSessionRecord,
+    AdminMfaRecord, AdminPublicKeyRecord, LoginCodeRecord, LoginStore, TuiSessionRecord,
+    UserLoginCodeRecord, WebSessionRecord,
 };
+use crate::server::totp;
 
 const CODE_COOLDOWN_SECS: i64 = 5;
 const TUI_SESSION_TTL_HOURS: i64 = 12;
@@ -23,6 +25,18 @@ const SIGNING_PREFIX: &[u8] = b"gateway-auth:";
 const CHALLENGE_NONCE_LEN: usize = 32;
 const TUI_TOKEN_LEN: usize = 64;
 const WEB_SESSION_ID_LEN: usize = 56;
+/// 空闲计时的写入粒度，避免每个请求都更新数据库
+const WEB_SESSION_TOUCH_INTERVAL_SECS: i64 = 60;
+const WEB_SESSION_HANDLE_LEN: usize = 16;
+/// 账号密码登录：连续失败次数达到上限后锁定一段时间
+const MAX_FAILED_PASSWORD_LOGINS: u32 = 5;
+const PASSWORD_LOCKOUT_MINUTES: i64 = 15;
+const RECOVERY_CODE_COUNT: usize = 10;
+const RECOVERY_CODE_LEN: usize = 10;
+const TOTP_ISSUER: &str = "AI Gateway";
+/// 令牌为终端用户签发的登录码：长度与每个令牌每分钟的签发上限
+const USER_CODE_LEN: usize = 40;
+const USER_CODES_PER_MINUTE: u64 = 10;
 
 #[derive(Debug, Clone)]
 pub struct LoginCodeEntry {
@@ -53,8 +67,9 @@ pub struct SessionEntry {
     pub created_at: DateTime<Utc>,
     #[allow(dead_code)]
     pub expires_at: DateTime<Utc>,
-    #[allow(dead_code)]
     pub fingerprint: Option<String>,
+    /// 账号密码登录的用户 ID
+    pub user_id: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -62,6 +77,8 @@ pub struct TuiSession {
     pub token: String,
     pub fingerprint: String,
     pub expires_at: DateTime<Utc>,
+    /// 登录所用公钥的过期时间，供客户端提示轮换
+    pub key_expires_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Clone)]
@@ -71,16 +88,45 @@ pub struct TuiChallenge {
     pub expires_at: DateTime<Utc>,
 }
 
+/// 账号密码登录的第二因素
+#[derive(Debug, Clone, Copy)]
+pub enum SecondFactor<'a> {
+    None,
+    Totp(&'a str),
+    RecoveryCode(&'a str),
+}
+
+#[derive(Debug, Clone)]
+pub enum PasswordLoginOutcome {
+    /// 尚未绑定 TOTP：返回待确认的密钥，需调用激活接口完成绑定后才能登录
+    EnrollmentRequired { secret: String, otpauth_url: String },
+    Authenticated {
+        session: SessionEntry,
+        recovery_codes_remaining: usize,
+    },
+}
+
+/// 账号密码登录所需的用户信息（由调用方从 user_store 查出）
+#[derive(Debug, Clone, Copy)]
+pub struct PasswordAccount<'a> {
+    pub user_id: &'a str,
+    pub account: &'a str,
+    pub password_hash: Option<&'a str>,
+}
+
 struct ChallengeEntry {
     fingerprint: String,
     public_key: Vec<u8>,
     nonce: Vec<u8>,
     expires_at: DateTime<Utc>,
+    key_expires_at: Option<DateTime<Utc>>,
 }
 
 pub struct LoginManager {
     store: Arc<dyn LoginStore + Send + Sync>,
     challenges: Arc<RwLock<HashMap<String, ChallengeEntry>>>,
+    web_session_ttl: Duration,
+    web_session_idle: Option<Duration>,
 }
 
 impl LoginManager {
@@ -88,7 +134,39 @@ impl LoginManager {
         Self {
             store,
             challenges: Arc::new(RwLock::new(HashMap::new())),
+            web_session_ttl: Duration::hours(WEB_SESSION_TTL_HOURS),
+            web_session_idle: None,
+        }
+    }
+
+    /// Web 会话的绝对有效期与空闲超时（秒）；idle_secs 为 0 表示不启用空闲超时
+    pub fn with_web_session_timeouts(mut self, absolute_secs: u64, idle_secs: u64) -> Self {
+        if absolute_secs > 0 {
+            self.web_session_ttl = Duration::seconds(absolute_secs.min(i64::MAX as u64) as i64);
         }
+        self.web_session_idle =
+            (idle_secs > 0).then(|| Duration::seconds(idle_secs.min(i64::MAX as u64) as i64));
+        self
+    }
+
+    pub fn web_session_ttl(&self) -> Duration {
+        self.web_session_ttl
+    }
+
+    /// 会话 ID 即 Cookie 明文，不能对外展示；管理接口使用其哈希前缀作为句柄
+    pub fn web_session_handle(session_id: &str) -> String {
+        let mut handle = Self::hash_code(session_id);
+        handle.truncate(WEB_SESSION_HANDLE_LEN);
+        handle
+    }
+
+    fn web_session_active(&self, record: &WebSessionRecord, now: DateTime<Utc>) -> bool {
+        if record.revoked || now > record.expires_at {
+            return false;
+        }
+        let last_seen = record.last_seen_at.unwrap_or(record.created_at);
+        self.web_session_idle
+            .is_none_or(|idle| now - last_seen <= idle)
     }
 
     pub async fn list_admin_keys(&self) -> Result<Vec<AdminPublicKeyRecord>, GatewayError> {
@@ -102,6 +180,31 @@ impl LoginManager {
             .map_err(GatewayError::Db)
     }
 
+    /// 延期或退役管理员公钥；退役时同时吊销该公钥签发的 TUI 会话
+    pub async fn set_admin_key_status(
+        &self,
+        fingerprint: &str,
+        enabled: bool,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> Result<bool, GatewayError> {
+        let updated = self
+            .store
+            .update_admin_key_status(fingerprint, enabled, expires_at)
+            .await
+            .map_err(GatewayError::Db)?;
+        if updated && (!enabled || expires_at.is_some_and(|exp| exp <= Utc::now())) {
+            for session in self.list_tui_sessions(Some(fingerprint)).await? {
+                if !session.revoked {
+                    self.store
+                        .revoke_tui_session(&session.session_id)
+                        .await
+                        .map_err(GatewayError::Db)?;
+                }
+            }
+        }
+        Ok(updated)
+    }
+
     pub async fn delete_admin_key(&self, fingerprint: &str) -> Result<bool, GatewayError> {
         self.store
             .delete_admin_key(fingerprint)
@@ -161,6 +264,9 @@ impl LoginManager {
         if !key.enabled {
             return Err(GatewayError::Config("管理员公钥已禁用".into()));
         }
+        if key.is_expired(Utc::now()) {
+            return Err(GatewayError::Config("管理员公钥已过期".into()));
+        }
         Ok(key)
     }
 
@@ -183,6 +289,7 @@ impl LoginManager {
                     public_key: key.public_key.clone(),
                     nonce: nonce.clone(),
                     expires_at,
+                    key_expires_at: key.expires_at,
                 },
             );
         }
@@ -237,7 +344,9 @@ impl LoginManager {
             .map_err(|_| GatewayError::Config("签名验证失败".into()))?;
 
         let now = Utc::now();
-        let expires_at = now + Duration::hours(TUI_SESSION_TTL_HOURS);
+        // 会话不超过公钥的过期时间
+        let expires_at = (now + Duration::hours(TUI_SESSION_TTL_HOURS))
+            .min(challenge.key_expires_at.unwrap_or(DateTime::<Utc>::MAX_UTC));
         let token = Self::random_string(TUI_TOKEN_LEN);
         let session = TuiSessionRecord {
             session_id: token.clone(),
@@ -259,6 +368,7 @@ impl LoginManager {
             token,
             fingerprint: fingerprint.to_string(),
             expires_at,
+            key_expires_at: challenge.key_expires_at,
         })
     }
 
@@ -384,6 +494,57 @@ impl LoginManager {
         }))
     }
 
+    /// 客户端令牌为终端用户签发一次性登录码（按令牌限流）
+    pub async fn generate_user_code(
+        &self,
+        token_id: &str,
+        user_id: &str,
+        ttl_secs: u64,
+    ) -> Result<LoginCodeEntry, GatewayError> {
+        let now = Utc::now();
+        let recent = self
+            .store
+            .count_user_login_codes_since(token_id, now - Duration::minutes(1))
+            .await
+            .map_err(GatewayError::Db)?;
+        if recent >= USER_CODES_PER_MINUTE {
+            return Err(GatewayError::RateLimited(
+                "too many login codes issued by this token, try again later".into(),
+            ));
+        }
+        let code = Self::random_string(USER_CODE_LEN);
+        let expires_at = now + Duration::seconds(ttl_secs as i64);
+        self.store
+            .insert_user_login_code(&UserLoginCodeRecord {
+                code_hash: Self::hash_code(&code),
+                user_id: user_id.to_string(),
+                token_id: token_id.to_string(),
+                created_at: now,
+                expires_at,
+                used_at: None,
+            })
+            .await
+            .map_err(GatewayError::Db)?;
+        Ok(LoginCodeEntry {
+            code,
+            expires_at,
+            max_uses: 1,
+            uses: 0,
+            disabled: false,
+            created_at: now,
+        })
+    }
+
+    pub async fn redeem_user_code(
+        &self,
+        code: &str,
+    ) -> Result<Option<UserLoginCodeRecord>, GatewayError> {
+        self.store
+            .redeem_user_login_code(&Self::hash_code(code), Utc::now())
+            .await
+            .map_err(GatewayError::Db)
+    }
+
     pub async fn redeem(&self, code: &str) -> Result<Option<SessionEntry>, GatewayError> {
         let now = Utc::now();
         let hash = Self::hash_code(code);
@@ -395,26 +556,41 @@ impl LoginManager {
         let Some(record) = record else {
             return Ok(None);
         };
+        self.create_web_session(Some(record.fingerprint), Some(record.code_hash), None, now)
+            .await
+            .map(Some)
+    }
+
+    async fn create_web_session(
+        &self,
+        fingerprint: Option<String>,
+        issued_by_code: Option<String>,
+        user_id: Option<String>,
+        now: DateTime<Utc>,
+    ) -> Result<SessionEntry, GatewayError> {
         let session_id = Self::random_string(WEB_SESSION_ID_LEN);
-        let expires_at = now + Duration::hours(WEB_SESSION_TTL_HOURS);
+        let expires_at = now + self.web_session_ttl;
         let web_record = WebSessionRecord {
             session_id: session_id.clone(),
-            fingerprint: Some(record.fingerprint.clone()),
+            fingerprint: fingerprint.clone(),
             created_at: now,
             expires_at,
             revoked: false,
-            issued_by_code: Some(record.code_hash.clone()),
+            issued_by_code,
+            last_seen_at: Some(now),
+            user_id: user_id.clone(),
         };
         self.store
             .insert_web_session(&web_record)
             .await
             .map_err(GatewayError::Db)?;
-        Ok(Some(SessionEntry {
+        Ok(SessionEntry {
             id: session_id,
             created_at: now,
             expires_at,
-            fingerprint: Some(record.fingerprint),
-        }))
+            fingerprint,
+            user_id,
+        })
     }
 
     pub async fn get_session(&self, id: &str) -> Result<Option<SessionEntry>, GatewayError> {
@@ -432,7 +608,9 @@ impl LoginManager {
         if record.revoked {
             return Ok(None);
         }
-        if Utc::now() > record.expires_at {
+        let now = Utc::now();
+        // 超过绝对有效期或空闲超时：直接撤销
+        if !self.web_session_active(&record, now) {
             let _ = self
                 .store
                 .revoke_web_session(id)
@@ -440,11 +618,19 @@ impl LoginManager {
                 .map_err(GatewayError::Db)?;
             return Ok(None);
         }
+        let last_seen = record.last_seen_at.unwrap_or(record.created_at);
+        if now - last_seen >= Duration::seconds(WEB_SESSION_TOUCH_INTERVAL_SECS) {
+            self.store
+                .touch_web_session(id, now)
+                .await
+                .map_err(GatewayError::Db)?;
+        }
         Ok(Some(SessionEntry {
             id: record.session_id,
             created_at: record.created_at,
             expires_at: record.expires_at,
             fingerprint: record.fingerprint,
+            user_id: record.user_id,
         }))
     }
 
@@ -454,4 +640,477 @@ impl LoginManager {
             .await
             .map_err(GatewayError::Db)
     }
+
+    /// 仍然有效的 Web 会话（已过期/空闲超时的不返回）
+    pub async fn list_web_sessions(&self) -> Result<Vec<WebSessionRecord>, GatewayError> {
+        let now = Utc::now();
+        let sessions = self
+            .store
+            .list_web_sessions()
+            .await
+            .map_err(GatewayError::Db)?;
+        Ok(sessions
+            .into_iter()
+            .filter(|s| self.web_session_active(s, now))
+            .collect())
+    }
+
+    fn normalize_recovery_code(code: &str) -> String {
+        code.chars()
+            .filter(|c| c.is_ascii_alphanumeric())
+            .map(|c| c.to_ascii_lowercase())
+            .collect()
+    }
+
+    fn generate_recovery_codes() -> Vec<String> {
+        (0..RECOVERY_CODE_COUNT)
+            .map(|_| {
+                let raw = Self::random_string(RECOVERY_CODE_LEN).to_ascii_lowercase();
+                format!(
+                    "{}-{}",
+                    &raw[..RECOVERY_CODE_LEN / 2],
+                    &raw[RECOVERY_CODE_LEN / 2..]
+                )
+            })
+            .collect()
+    }
+
+    async fn load_mfa(
+        &self,
+        user_id: &str,
+        now: DateTime<Utc>,
+    ) -> Result<AdminMfaRecord, GatewayError> {
+        let record = self
+            .store
+            .get_admin_mfa(user_id)
+            .await
+            .map_err(GatewayError::Db)?
+            .unwrap_or_else(|| AdminMfaRecord {
+                user_id: user_id.to_string(),
+                totp_secret: None,
+                totp_enabled: false,
+                recovery_code_hashes: Vec::new(),
+                last_totp_step: None,
+                failed_attempts: 0,
+                locked_until: None,
+                updated_at: now,
+            });
+        if record.locked_until.is_some_and(|until| until > now) {
+            return Err(GatewayError::RateLimited(
+                "登录失败次数过多，账号已临时锁定，请稍后再试".into(),
+            ));
+        }
+        Ok(record)
+    }
+
+    async fn save_mfa(
+        &self,
+        record: &mut AdminMfaRecord,
+        now: DateTime<Utc>,
+    ) -> Result<(), GatewayError> {
+        record.updated_at = now;
+        self.store
+            .upsert_admin_mfa(record)
+            .await
+            .map_err(GatewayError::Db)
+    }
+
+    /// 记录一次失败；达到上限时锁定并清零计数。返回给调用方的错误不区分失败原因
+    async fn register_failure(
+        &self,
+        mut record: AdminMfaRecord,
+        now: DateTime<Utc>,
+        err: GatewayError,
+    ) -> GatewayError {
+        record.failed_attempts += 1;
+        if record.failed_attempts >= MAX_FAILED_PASSWORD_LOGINS {
+            record.failed_attempts = 0;
+            record.locked_until = Some(now + Duration::minutes(PASSWORD_LOCKOUT_MINUTES));
+            tracing::warn!(user_id = %record.user_id, "password login locked after repeated failures");
+        }
+        match self.save_mfa(&mut record, now).await {
+            Ok(()) => err,
+            Err(e) => e,
+        }
+    }
+
+    fn password_matches(
+        account: &PasswordAccount<'_>,
+        password: &str,
+    ) -> Result<bool, GatewayError> {
+        match account.password_hash {
+            Some(hash) => crate::users::verify_password(password, hash),
+            None => Ok(false),
+        }
+    }
+
+    /// 账号密码 + TOTP 登录，成功后签发 Web 会话；与公钥挑战、登录码流程相互独立
+    pub async fn password_login(
+        &self,
+        account: PasswordAccount<'_>,
+        password: &str,
+        factor: SecondFactor<'_>,
+    ) -> Result<PasswordLoginOutcome, GatewayError> {
+        let now = Utc::now();
+        let mut record = self.load_mfa(account.user_id, now).await?;
+        if !Self::password_matches(&account, password)? {
+            let err = GatewayError::Unauthorized("invalid credentials".into());
+            return Err(self.register_failure(record, now, err).await);
+        }
+        if !record.totp_enabled {
+            // 强制绑定 TOTP：重复登录复用同一个待确认密钥，避免扫码后又被替换
+            let secret = match record.totp_secret.clone() {
+                Some(secret) => secret,
+                None => {
+                    let secret = totp::generate_secret();
+                    record.totp_secret = Some(secret.clone());
+                    self.save_mfa(&mut record, now).await?;
+                    secret
+                }
+            };
+            return Ok(PasswordLoginOutcome::EnrollmentRequired {
+                otpauth_url: totp::otpauth_url(TOTP_ISSUER, account.account, &secret),
+                secret,
+            });
+        }
+        let verified = match factor {
+            SecondFactor::None => {
+                return Err(GatewayError::Unauthorized(
+                    "verification code required".into(),
+                ));
+            }
+            SecondFactor::Totp(code) => record
+                .totp_secret
+                .as_deref()
+                .and_then(|secret| totp::verify(secret, code, now.timestamp()))
+                .filter(|step| record.last_totp_step.is_none_or(|last| *step > last))
+                .map(|step| record.last_totp_step = Some(step))
+                .is_some(),
+            SecondFactor::RecoveryCode(code) => {
+                let hash = Self::hash_code(&Self::normalize_recovery_code(code));
+                let before = record.recovery_code_hashes.len();
+                record.recovery_code_hashes.retain(|h| *h != hash);
+                record.recovery_code_hashes.len() < before
+            }
+        };
+        if !verified {
+            let err = GatewayError::Unauthorized("invalid verification code".into());
+            return Err(self.register_failure(record, now, err).await);
+        }
+        record.failed_attempts = 0;
+        record.locked_until = None;
+        self.save_mfa(&mut record, now).await?;
+        let session = self
+            .create_web_session(None, None, Some(account.user_id.to_string()), now)
+            .await?;
+        Ok(PasswordLoginOutcome::Authenticated {
+            session,
+            recovery_codes_remaining: record.recovery_code_hashes.len(),
+        })
+    }
+
+    /// 用待确认密钥生成的验证码完成 TOTP 绑定；返回新会话与一次性恢复码（仅此一次明文返回）
+    pub async fn activate_totp(
+        &self,
+        account: PasswordAccount<'_>,
+        password: &str,
+        code: &str,
+    ) -> Result<(SessionEntry, Vec<String>), GatewayError> {
+        let now = Utc::now();
+        let mut record = self.load_mfa(account.user_id, now).await?;
+        if !Self::password_matches(&account, password)? {
+            let err = GatewayError::Unauthorized("invalid credentials".into());
+            return Err(self.register_failure(record, now, err).await);
+        }
+        let Some(secret) = record.totp_secret.clone().filter(|_| !record.totp_enabled) else {
+            return Err(GatewayError::Config("no pending TOTP enrollment".into()));
+        };
+        let Some(step) = totp::verify(&secret, code, now.timestamp()) else {
+            let err = GatewayError::Unauthorized("invalid verification code".into());
+            return Err(self.register_failure(record, now, err).await);
+        };
+        let codes = Self::generate_recovery_codes();
+        record.totp_enabled = true;
+        record.last_totp_step = Some(step);
+        record.recovery_code_hashes = codes
+            .iter()
+            .map(|c| Self::hash_code(&Self::normalize_recovery_code(c)))
+            .collect();
+        record.failed_attempts = 0;
+        record.locked_until = None;
+        self.save_mfa(&mut record, now).await?;
+        let session = self
+            .create_web_session(None, None, Some(account.user_id.to_string()), now)
+            .await?;
+        Ok((session, codes))
+    }
+
+    /// 清除用户的 TOTP 绑定、恢复码与锁定状态（丢失认证器时由超级管理员操作）
+    pub async fn reset_admin_mfa(&self, user_id: &str) -> Result<bool, GatewayError> {
+        self.store
+            .delete_admin_mfa(user_id)
+            .await
+            .map_err(GatewayError::Db)
+    }
+
+    /// 按句柄撤销 Web 会话；句柄不存在时返回 false
+    pub async fn revoke_session_by_handle(&self, handle: &str) -> Result<bool, GatewayError> {
+        let sessions = self
+            .store
+            .list_web_sessions()
+            .await
+            .map_err(GatewayError::Db)?;
+        let Some(target) = sessions
+            .into_iter()
+            .find(|s| Self::web_session_handle(&s.session_id) == handle)
+        else {
+            return Ok(false);
+        };
+        self.revoke_session(&target.session_id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logging::DatabaseLogger;
+    use tempfile::tempdir;
+
+    fn web_session(
+        id: &str,
+        created_at: DateTime<Utc>,
+        last_seen_at: DateTime<Utc>,
+    ) -> WebSessionRecord {
+        WebSessionRecord {
+            session_id: id.to_string(),
+            fingerprint: Some("fp".into()),
+            created_at,
+            expires_at: created_at + Duration::hours(1),
+            revoked: false,
+            issued_by_code: None,
+            last_seen_at: Some(last_seen_at),
+            user_id: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn web_sessions_expire_on_idle_and_absolute_timeouts() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("test.db");
+        let logger = Arc::new(
+            DatabaseLogger::new(db_path.to_str().unwrap())
+                .await
+                .unwrap(),
+        );
+        let manager = LoginManager::new(logger.clone()).with_web_session_timeouts(3600, 600);
+        let now = Utc::now();
+
+        let idle = web_session(
+            "idle",
+            now - Duration::minutes(30),
+            now - Duration::minutes(20),
+        );
+        let expired = web_session(
+            "expired",
+            now - Duration::hours(2),
+            now - Duration::minutes(1),
+        );
+        let active = web_session(
+            "active",
+            now - Duration::minutes(5),
+            now - Duration::minutes(5),
+        );
+        for s in [&idle, &expired, &active] {
+            logger.insert_web_session(s).await.unwrap();
+        }
+
+        assert_eq!(
+            manager
+                .list_web_sessions()
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|s| s.session_id)
+                .collect::<Vec<_>>(),
+            vec!["active".to_string()]
+        );
+        assert!(manager.get_session("idle").await.unwrap().is_none());
+        assert!(manager.get_session("expired").await.unwrap().is_none());
+        assert!(
+            logger
+                .get_web_session("idle")
+                .await
+                .unwrap()
+                .unwrap()
+                .revoked
+        );
+
+        // 有效请求刷新空闲计时
+        assert!(manager.get_session("active").await.unwrap().is_some());
+        let touched = logger.get_web_session("active").await.unwrap().unwrap();
+        assert!(touched.last_seen_at.unwrap() > active.last_seen_at.unwrap());
+
+        let handle = LoginManager::web_session_handle("active");
+        assert_eq!(handle.len(), WEB_SESSION_HANDLE_LEN);
+        assert!(!manager.revoke_session_by_handle("unknown").await.unwrap());
+        assert!(manager.revoke_session_by_handle(&handle).await.unwrap());
+        assert!(manager.get_session("active").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn expired_or_retired_admin_keys_cannot_log_in() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("test.db");
+        let logger = Arc::new(
+            DatabaseLogger::new(db_path.to_str().unwrap())
+                .await
+                .unwrap(),
+        );
+        let manager = LoginManager::new(logger.clone());
+        let now = Utc::now();
+        let key = AdminPublicKeyRecord {
+            fingerprint: "fp".into(),
+            public_key: vec![0u8; ed25519_dalek::PUBLIC_KEY_LENGTH],
+            comment: None,
+            enabled: true,
+            created_at: now,
+            last_used_at: None,
+            expires_at: Some(now - Duration::minutes(1)),
+        };
+        manager.add_admin_key(&key).await.unwrap();
+        let err = manager.issue_challenge("fp").await.err().unwrap();
+        assert!(err.to_string().contains("管理员公钥已过期"), "{err}");
+
+        assert!(
+            manager
+                .set_admin_key_status("fp", true, Some(now + Duration::days(30)))
+                .await
+                .unwrap()
+        );
+        manager.issue_challenge("fp").await.unwrap();
+        logger
+            .create_tui_session(&TuiSessionRecord {
+                session_id: "tui-1".into(),
+                fingerprint: "fp".into(),
+                issued_at: now,
+                expires_at: now + Duration::hours(1),
+                revoked: false,
+                last_code_at: None,
+            })
+            .await
+            .unwrap();
+
+        // 退役：停用并吊销已签发的会话
+        manager
+            .set_admin_key_status("fp", false, Some(now))
+            .await
+            .unwrap();
+        assert!(manager.validate_tui_token("tui-1").await.unwrap().is_none());
+        assert!(manager.issue_challenge("fp").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn password_login_requires_totp_enrollment_recovery_codes_and_locks_out() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("test.db");
+        let logger = Arc::new(
+            DatabaseLogger::new(db_path.to_str().unwrap())
+                .await
+                .unwrap(),
+        );
+        let manager = LoginManager::new(logger.clone());
+        let hash = crate::users::hash_password("correct horse").unwrap();
+        let account = PasswordAccount {
+            user_id: "u1",
+            account: "admin@example.com",
+            password_hash: Some(&hash),
+        };
+
+        let PasswordLoginOutcome::EnrollmentRequired {
+            secret,
+            otpauth_url,
+        } = manager
+            .password_login(account, "correct horse", SecondFactor::None)
+            .await
+            .unwrap()
+        else {
+            panic!("enrollment should be required first");
+        };
+        assert!(otpauth_url.contains(&secret));
+        assert!(!otpauth_url.contains("algorithm="));
+        let now = Utc::now().timestamp();
+        let (session, codes) = manager
+            .activate_totp(account, "correct horse", &totp::code_for(&secret, now))
+            .await
+            .unwrap();
+        let restored = manager.get_session(&session.id).await.unwrap().unwrap();
+        assert_eq!(restored.user_id.as_deref(), Some("u1"));
+        assert_eq!(codes.len(), RECOVERY_CODE_COUNT);
+
+        // 激活时用过的验证码不能再次登录；恢复码只能使用一次
+        assert!(
+            manager
+                .password_login(
+                    account,
+                    "correct horse",
+                    SecondFactor::Totp(&totp::code_for(&secret, now))
+                )
+                .await
+                .is_err()
+        );
+        let outcome = manager
+            .password_login(
+                account,
+                "correct horse",
+                SecondFactor::RecoveryCode(&codes[0].to_uppercase()),
+            )
+            .await
+            .unwrap();
+        let PasswordLoginOutcome::Authenticated {
+            session,
+            recovery_codes_remaining: 9,
+        } = outcome
+        else {
+            panic!("recovery code should authenticate");
+        };
+        assert_eq!(session.user_id.as_deref(), Some("u1"));
+        assert!(
+            manager
+                .password_login(
+                    account,
+                    "correct horse",
+                    SecondFactor::RecoveryCode(&codes[0])
+                )
+                .await
+                .is_err()
+        );
+
+        for _ in 1..MAX_FAILED_PASSWORD_LOGINS {
+            assert!(matches!(
+                manager
+                    .password_login(account, "wrong", SecondFactor::None)
+                    .await,
+                Err(GatewayError::Unauthorized(_))
+            ));
+        }
+        // 第 5 次失败（含上面的恢复码重放）后锁定，正确凭据也被拒绝
+        assert!(matches!(
+            manager
+                .password_login(
+                    account,
+                    "correct horse",
+                    SecondFactor::RecoveryCode(&codes[1])
+                )
+                .await,
+            Err(GatewayError::RateLimited(_))
+        ));
+        assert!(manager.reset_admin_mfa("u1").await.unwrap());
+        assert!(matches!(
+            manager
+                .password_login(account, "correct horse", SecondFactor::None)
+                .await
+                .unwrap(),
+            PasswordLoginOutcome::EnrollmentRequired { .. }
+        ));
+    }
 }
@@ -0,0 +1,259 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::error::GatewayError;
+use crate::logging::types::{
+    MaintenanceWindowRecord, ProviderOpLog, REQ_TYPE_PROVIDER_MAINTENANCE_END,
+    REQ_TYPE_PROVIDER_MAINTENANCE_START,
+};
+use crate::server::AppState;
+
+/// 检查维护窗口开始/结束的间隔
+const TICK_SECS: u64 = 30;
+
+/// 维护窗口的对外视图（状态接口不暴露创建人）
+#[derive(Debug, Clone, Serialize)]
+pub struct PublicMaintenanceWindow {
+    pub provider: String,
+    pub starts_at: String,
+    pub ends_at: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+impl From<&MaintenanceWindowRecord> for PublicMaintenanceWindow {
+    fn from(window: &MaintenanceWindowRecord) -> Self {
+        Self {
+            provider: window.provider.clone(),
+            starts_at: crate::logging::time::to_iso8601_utc_string(&window.starts_at),
+            ends_at: crate::logging::time::to_iso8601_utc_string(&window.ends_at),
+            message: window.message.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MaintenanceStatus {
+    pub active: Vec<PublicMaintenanceWindow>,
+    pub upcoming: Vec<PublicMaintenanceWindow>,
+}
+
+/// 路由状态变化：维护开始或结束的供应商
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Transitions {
+    started: Vec<String>,
+    ended: Vec<String>,
+}
+
+#[derive(Default)]
+struct ScheduleState {
+    /// 尚未结束的窗口（按开始时间升序）
+    windows: Vec<MaintenanceWindowRecord>,
+    /// 当前处于维护中的供应商 -> 生效的窗口
+    active: HashMap<String, MaintenanceWindowRecord>,
+}
+
+/// 维护窗口调度：窗口开始时将供应商移出路由，结束时恢复
+#[derive(Default)]
+pub struct MaintenanceSchedule {
+    state: Mutex<ScheduleState>,
+}
+
+impl MaintenanceSchedule {
+    fn lock(&self) -> std::sync::MutexGuard<'_, ScheduleState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 供应商当前生效的维护窗口（路由据此跳过该供应商）
+    pub fn active_window(&self, provider: &str) -> Option<MaintenanceWindowRecord> {
+        self.lock().active.get(provider).cloned()
+    }
+
+    /// 尚未结束的全部窗口（进行中与即将开始）
+    pub fn pending(&self) -> Vec<MaintenanceWindowRecord> {
+        self.lock().windows.clone()
+    }
+
+    pub fn status(&self, now: DateTime<Utc>) -> MaintenanceStatus {
+        let state = self.lock();
+        let (active, upcoming): (Vec<_>, Vec<_>) = state
+            .windows
+            .iter()
+            .filter(|w| w.ends_at > now)
+            .partition(|w| w.starts_at <= now);
+        MaintenanceStatus {
+            active: active.into_iter().map(Into::into).collect(),
+            upcoming: upcoming.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    fn replace(&self, windows: Vec<MaintenanceWindowRecord>, now: DateTime<Utc>) -> Transitions {
+        let mut state = self.lock();
+        state.windows = windows;
+        Self::apply_locked(&mut state, now)
+    }
+
+    fn apply(&self, now: DateTime<Utc>) -> Transitions {
+        Self::apply_locked(&mut self.lock(), now)
+    }
+
+    fn apply_locked(state: &mut ScheduleState, now: DateTime<Utc>) -> Transitions {
+        state.windows.retain(|w| w.ends_at > now);
+        let mut active: HashMap<String, MaintenanceWindowRecord> = HashMap::new();
+        for window in state.windows.iter().filter(|w| w.starts_at <= now) {
+            // 同一供应商的重叠窗口取最晚结束的一个
+            match active.get(&window.provider) {
+                Some(current) if current.ends_at >= window.ends_at => {}
+                _ => {
+                    active.insert(window.provider.clone(), window.clone());
+                }
+            }
+        }
+        let mut transitions = Transitions::default();
+        for provider in active.keys() {
+            if !state.active.contains_key(provider) {
+                transitions.started.push(provider.clone());
+            }
+        }
+        for provider in state.active.keys() {
+            if !active.contains_key(provider) {
+                transitions.ended.push(provider.clone());
+            }
+        }
+        transitions.started.sort();
+        transitions.ended.sort();
+        state.active = active;
+        transitions
+    }
+}
+
+/// 从存储重新加载维护窗口并立即应用（管理变更与集群事件后调用）
+pub async fn refresh(app_state: &AppState) -> Result<(), GatewayError> {
+    let now = Utc::now();
+    let windows = app_state.log_store.list_maintenance_windows(now).await?;
+    let transitions = app_state.maintenance.replace(windows, now);
+    record_transitions(app_state, transitions, now).await;
+    Ok(())
+}
+
+async fn record_transitions(app_state: &AppState, transitions: Transitions, now: DateTime<Utc>) {
+    for (providers, operation) in [
+        (transitions.started, REQ_TYPE_PROVIDER_MAINTENANCE_START),
+        (transitions.ended, REQ_TYPE_PROVIDER_MAINTENANCE_END),
+    ] {
+        for provider in providers {
+            let window = app_state.maintenance.active_window(&provider);
+            tracing::info!(provider = %provider, operation, "provider maintenance routing change");
+            let details = window.as_ref().map(|w| {
+                serde_json::json!({
+                    "window_id": w.id,
+                    "ends_at": crate::logging::time::to_iso8601_utc_string(&w.ends_at),
+                    "message": w.message,
+                })
+                .to_string()
+            });
+            let _ = app_state
+                .log_store
+                .log_provider_op(ProviderOpLog {
+                    id: None,
+                    timestamp: now,
+                    operation: operation.to_string(),
+                    provider: Some(provider),
+                    details,
+                    actor: None,
+                })
+                .await;
+        }
+    }
+}
+
+/// 后台定期从存储加载窗口并按当前时间切换路由（启动时立即执行一次）
+pub fn spawn_scheduler_task(app_state: Arc<AppState>) {
+    let tasks = app_state.task_registry.clone();
+    tasks.spawn_with("maintenance_windows", |mut ctx| async move {
+        let mut ticker = tokio::time::interval(std::time::Duration::from_secs(TICK_SECS));
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {}
+                _ = ctx.cancelled() => break,
+            }
+            if let Err(e) = refresh(&app_state).await {
+                // 存储不可用时仍按已缓存的窗口切换
+                tracing::warn!("Failed to load maintenance windows: {}", e);
+                let now = Utc::now();
+                let transitions = app_state.maintenance.apply(now);
+                record_transitions(&app_state, transitions, now).await;
+                ctx.report_error(e);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+
+    fn window(id: i64, provider: &str, start: i64, end: i64) -> MaintenanceWindowRecord {
+        let base = Utc.with_ymd_and_hms(2026, 10, 16, 0, 0, 0).unwrap();
+        MaintenanceWindowRecord {
+            id,
+            provider: provider.into(),
+            starts_at: base + Duration::hours(start),
+            ends_at: base + Duration::hours(end),
+            message: Some("upgrade".into()),
+            created_by: None,
+            created_at: base,
+        }
+    }
+
+    fn at(hour: i64) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 10, 16, 0, 0, 0).unwrap() + Duration::hours(hour)
+    }
+
+    #[test]
+    fn windows_start_and_end_routing_changes() {
+        let schedule = MaintenanceSchedule::default();
+        let windows = vec![window(1, "openai", 2, 4), window(2, "claude", 3, 5)];
+        assert_eq!(schedule.replace(windows, at(1)), Transitions::default());
+        assert!(schedule.active_window("openai").is_none());
+        assert_eq!(schedule.status(at(1)).upcoming.len(), 2);
+
+        let t = schedule.apply(at(2));
+        assert_eq!(t.started, vec!["openai"]);
+        assert_eq!(schedule.active_window("openai").unwrap().id, 1);
+
+        let t = schedule.apply(at(4));
+        assert_eq!(t.started, vec!["claude"]);
+        assert_eq!(t.ended, vec!["openai"]);
+        let status = schedule.status(at(4));
+        assert_eq!(status.active.len(), 1);
+        assert!(status.upcoming.is_empty());
+
+        let t = schedule.apply(at(5));
+        assert_eq!(t.ended, vec!["claude"]);
+        assert!(schedule.pending().is_empty());
+    }
+
+    #[test]
+    fn overlapping_windows_keep_provider_in_maintenance() {
+        let schedule = MaintenanceSchedule::default();
+        let windows = vec![window(1, "openai", 0, 2), window(2, "openai", 1, 3)];
+        assert_eq!(schedule.replace(windows, at(1)).started, vec!["openai"]);
+        assert_eq!(schedule.active_window("openai").unwrap().id, 2);
+        assert_eq!(schedule.apply(at(2)), Transitions::default());
+        assert_eq!(schedule.apply(at(3)).ended, vec!["openai"]);
+    }
+
+    #[test]
+    fn cancelling_active_window_restores_routing() {
+        let schedule = MaintenanceSchedule::default();
+        schedule.replace(vec![window(1, "openai", 0, 3)], at(1));
+        let t = schedule.replace(Vec::new(), at(1));
+        assert_eq!(t.ended, vec!["openai"]);
+        assert!(schedule.active_window("openai").is_none());
+    }
+}
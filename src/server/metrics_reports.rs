@@ -0,0 +1,430 @@
+//! 定时指标报表：按日/周汇总花费（供应商/模型/组织）、错误摘要与用量最高的令牌，
+//! 用数据库中保存的模板渲染后通过 Webhook 或邮件发送。
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+
+use crate::error::GatewayError;
+use crate::logging::RequestLog;
+use crate::logging::types::MetricsReportRecord;
+use crate::server::AppState;
+use crate::server::notifications::{
+    CHANNEL_EMAIL, CHANNEL_WEBHOOK, email_configured, escape_html, post_webhook_json,
+    send_email_html,
+};
+
+pub const PERIOD_DAILY: &str = "daily";
+pub const PERIOD_WEEKLY: &str = "weekly";
+
+const LOG_PAGE_SIZE: i32 = 1000;
+/// 单次报表最多扫描的日志条数，避免超大窗口拖慢定时任务
+const MAX_REPORT_LOGS: usize = 200_000;
+const TOP_N: usize = 5;
+
+pub const DEFAULT_TEMPLATE: &str = "{{name}} ({{period}} report)
+Window: {{since}} - {{until}}
+Requests: {{total_requests}} ({{error_requests}} errors)
+Spend: {{total_amount_spent}}
+
+Spend by provider:
+{{spend_by_provider}}
+
+Spend by model:
+{{spend_by_model}}
+
+Spend by organization:
+{{spend_by_organization}}
+
+Top tokens:
+{{top_tokens}}
+
+Error highlights:
+{{error_highlights}}";
+
+pub fn period_duration(period: &str) -> Option<Duration> {
+    match period {
+        PERIOD_DAILY => Some(Duration::days(1)),
+        PERIOD_WEEKLY => Some(Duration::weeks(1)),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct SpendItem {
+    pub name: String,
+    pub requests: u64,
+    pub amount: f64,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ErrorHighlight {
+    pub message: String,
+    pub count: u64,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct MetricsReport {
+    pub period: String,
+    pub since: DateTime<Utc>,
+    pub until: DateTime<Utc>,
+    pub total_requests: u64,
+    pub error_requests: u64,
+    pub total_amount_spent: f64,
+    pub spend_by_provider: Vec<SpendItem>,
+    pub spend_by_model: Vec<SpendItem>,
+    pub spend_by_organization: Vec<SpendItem>,
+    /// 令牌以名称展示，不含令牌明文
+    pub top_tokens: Vec<SpendItem>,
+    pub error_highlights: Vec<ErrorHighlight>,
+}
+
+/// 令牌 ID -> (名称, 组织 ID)
+type TokenDirectory = HashMap<String, (String, Option<String>)>;
+
+fn add_spend(map: &mut HashMap<String, SpendItem>, key: &str, amount: f64) {
+    let item = map.entry(key.to_string()).or_insert_with(|| SpendItem {
+        name: key.to_string(),
+        ..Default::default()
+    });
+    item.requests += 1;
+    item.amount += amount;
+}
+
+/// 按花费降序（同额按请求数、名称）截取前 limit 项
+fn ranked(map: HashMap<String, SpendItem>, limit: usize) -> Vec<SpendItem> {
+    let mut items: Vec<_> = map.into_values().collect();
+    items.sort_by(|a, b| {
+        b.amount
+            .total_cmp(&a.amount)
+            .then(b.requests.cmp(&a.requests))
+            .then(a.name.cmp(&b.name))
+    });
+    items.truncate(limit);
+    items
+}
+
+/// 错误信息取首行并截断，使同类错误能够归并
+fn error_key(log: &RequestLog) -> String {
+    let message = log
+        .error_message
+        .as_deref()
+        .and_then(|m| m.lines().next())
+        .map(str::trim)
+        .filter(|m| !m.is_empty())
+        .unwrap_or("(no message)");
+    let message: String = message.chars().take(120).collect();
+    format!("HTTP {}: {}", log.status_code, message)
+}
+
+fn aggregate(
+    period: &str,
+    since: DateTime<Utc>,
+    until: DateTime<Utc>,
+    logs: &[RequestLog],
+    tokens: &TokenDirectory,
+) -> MetricsReport {
+    let mut report = MetricsReport {
+        period: period.to_string(),
+        since,
+        until,
+        total_requests: 0,
+        error_requests: 0,
+        total_amount_spent: 0.0,
+        spend_by_provider: Vec::new(),
+        spend_by_model: Vec::new(),
+        spend_by_organization: Vec::new(),
+        top_tokens: Vec::new(),
+        error_highlights: Vec::new(),
+    };
+    let (mut providers, mut models, mut orgs, mut top_tokens) = (
+        HashMap::new(),
+        HashMap::new(),
+        HashMap::new(),
+        HashMap::new(),
+    );
+    let mut errors: HashMap<String, u64> = HashMap::new();
+    for log in logs
+        .iter()
+        .filter(|l| l.timestamp >= since && l.timestamp < until)
+    {
+        report.total_requests += 1;
+        let amount = log.amount_spent.unwrap_or(0.0);
+        report.total_amount_spent += amount;
+        if log.status_code >= 400 {
+            report.error_requests += 1;
+            *errors.entry(error_key(log)).or_insert(0) += 1;
+        }
+        if let Some(provider) = log.provider.as_deref().filter(|p| !p.is_empty()) {
+            add_spend(&mut providers, provider, amount);
+        }
+        if let Some(model) = log
+            .effective_model
+            .as_deref()
+            .or(log.model.as_deref())
+            .filter(|m| !m.is_empty())
+        {
+            add_spend(&mut models, model, amount);
+        }
+        let Some(token_id) = log.client_token.as_deref() else {
+            continue;
+        };
+        let (token_name, org) = match tokens.get(token_id) {
+            Some((name, org)) => (name.as_str(), org.as_deref()),
+            None => (token_id, None),
+        };
+        add_spend(&mut top_tokens, token_name, amount);
+        if let Some(org) = org {
+            add_spend(&mut orgs, org, amount);
+        }
+    }
+    report.spend_by_provider = ranked(providers, usize::MAX);
+    report.spend_by_model = ranked(models, usize::MAX);
+    report.spend_by_organization = ranked(orgs, usize::MAX);
+    report.top_tokens = ranked(top_tokens, TOP_N);
+    let mut errors: Vec<_> = errors
+        .into_iter()
+        .map(|(message, count)| ErrorHighlight { message, count })
+        .collect();
+    errors.sort_by(|a, b| b.count.cmp(&a.count).then(a.message.cmp(&b.message)));
+    errors.truncate(TOP_N);
+    report.error_highlights = errors;
+    report
+}
+
+/// 从最新日志向前翻页，直到覆盖 since
+async fn logs_since(
+    app_state: &AppState,
+    since: DateTime<Utc>,
+) -> Result<Vec<RequestLog>, GatewayError> {
+    let mut out = Vec::new();
+    let mut cursor = None;
+    while out.len() < MAX_REPORT_LOGS {
+        let page = app_state
+            .log_store
+            .get_recent_logs_with_cursor(LOG_PAGE_SIZE, cursor)
+            .await
+            .map_err(GatewayError::Db)?;
+        let Some(last) = page.last() else {
+            break;
+        };
+        let reached_since = last.timestamp < since;
+        cursor = last.id;
+        out.extend(page);
+        if reached_since || cursor.is_none() {
+            break;
+        }
+    }
+    Ok(out)
+}
+
+/// 生成截至 until 的最近一个周期的报表
+pub async fn build_report(
+    app_state: &AppState,
+    period: &str,
+    until: DateTime<Utc>,
+) -> Result<MetricsReport, GatewayError> {
+    let span = period_duration(period)
+        .ok_or_else(|| GatewayError::Config(format!("unsupported report period '{}'", period)))?;
+    let since = until - span;
+    let logs = logs_since(app_state, since).await?;
+    let tokens = app_state
+        .token_store
+        .list_tokens()
+        .await?
+        .into_iter()
+        .map(|t| (t.id, (t.name, t.organization_id)))
+        .collect();
+    Ok(aggregate(period, since, until, &logs, &tokens))
+}
+
+fn spend_lines(items: &[SpendItem]) -> String {
+    if items.is_empty() {
+        return "- (none)".to_string();
+    }
+    items
+        .iter()
+        .map(|i| format!("- {}: {:.4} ({} requests)", i.name, i.amount, i.requests))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// 用 `{{placeholder}}` 模板渲染报表正文；未知占位原样保留
+pub fn render_report(template: &str, name: &str, report: &MetricsReport) -> String {
+    let template = if template.trim().is_empty() {
+        DEFAULT_TEMPLATE
+    } else {
+        template
+    };
+    let errors = if report.error_highlights.is_empty() {
+        "- (none)".to_string()
+    } else {
+        report
+            .error_highlights
+            .iter()
+            .map(|e| format!("- {} x{}", e.message, e.count))
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+    let values = [
+        ("name", name.to_string()),
+        ("period", report.period.clone()),
+        (
+            "since",
+            crate::logging::time::to_iso8601_utc_string(&report.since),
+        ),
+        (
+            "until",
+            crate::logging::time::to_iso8601_utc_string(&report.until),
+        ),
+        ("total_requests", report.total_requests.to_string()),
+        ("error_requests", report.error_requests.to_string()),
+        (
+            "total_amount_spent",
+            format!("{:.4}", report.total_amount_spent),
+        ),
+        ("spend_by_provider", spend_lines(&report.spend_by_provider)),
+        ("spend_by_model", spend_lines(&report.spend_by_model)),
+        (
+            "spend_by_organization",
+            spend_lines(&report.spend_by_organization),
+        ),
+        ("top_tokens", spend_lines(&report.top_tokens)),
+        ("error_highlights", errors),
+    ];
+    let mut out = template.to_string();
+    for (key, value) in values {
+        out = out.replace(&format!("{{{{{}}}}}", key), &value);
+    }
+    out
+}
+
+/// 渲染并发送一份报表（不更新 last_sent_at）
+pub async fn send_report(
+    record: &MetricsReportRecord,
+    report: &MetricsReport,
+) -> Result<(), String> {
+    let text = render_report(&record.template, &record.name, report);
+    let subject = format!("{} ({} report)", record.name, report.period);
+    match record.channel.as_str() {
+        CHANNEL_WEBHOOK => {
+            let body = serde_json::json!({
+                "kind": "metrics_report",
+                "report_id": record.id,
+                "name": record.name,
+                "subject": subject,
+                "text": text,
+                "report": report,
+            });
+            post_webhook_json(&record.target, &body).await
+        }
+        CHANNEL_EMAIL => {
+            if !email_configured() {
+                return Err("RESEND_API_KEY not configured".into());
+            }
+            let html = format!("<pre>{}</pre>", escape_html(&text));
+            send_email_html(&record.target, &subject, &html).await
+        }
+        other => Err(format!("unsupported report channel '{}'", other)),
+    }
+}
+
+fn is_due(record: &MetricsReportRecord, now: DateTime<Utc>) -> bool {
+    let Some(span) = period_duration(&record.period) else {
+        return false;
+    };
+    record.enabled && record.last_sent_at.unwrap_or(record.created_at) + span <= now
+}
+
+/// 发送所有到期的报表；返回成功发送的数量。单个报表失败不影响其他报表，下个周期重试
+pub async fn send_due_reports(
+    app_state: &AppState,
+    now: DateTime<Utc>,
+) -> Result<usize, GatewayError> {
+    let records = app_state
+        .log_store
+        .list_metrics_reports()
+        .await
+        .map_err(GatewayError::Db)?;
+    let mut sent = 0;
+    for record in records.iter().filter(|r| is_due(r, now)) {
+        let report = build_report(app_state, &record.period, now).await?;
+        match send_report(record, &report).await {
+            Ok(()) => {
+                app_state
+                    .log_store
+                    .mark_metrics_report_sent(&record.id, now)
+                    .await
+                    .map_err(GatewayError::Db)?;
+                sent += 1;
+            }
+            Err(e) => tracing::warn!(report = %record.id, "metrics report delivery failed: {}", e),
+        }
+    }
+    Ok(sent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log(minutes_ago: i64, provider: &str, token: &str, amount: f64, status: u16) -> RequestLog {
+        RequestLog {
+            id: None,
+            timestamp: Utc::now() - Duration::minutes(minutes_ago),
+            method: "POST".into(),
+            path: "/v1/chat/completions".into(),
+            request_type: "chat_once".into(),
+            requested_model: None,
+            effective_model: Some("gpt-4o".into()),
+            model: None,
+            provider: Some(provider.into()),
+            api_key: None,
+            client_token: Some(token.into()),
+            user_id: None,
+            amount_spent: Some(amount),
+            status_code: status,
+            response_time_ms: 10,
+            prompt_tokens: None,
+            completion_tokens: None,
+            total_tokens: None,
+            cached_tokens: None,
+            reasoning_tokens: None,
+            error_message: (status >= 400).then(|| "upstream timeout\ndetails".to_string()),
+            latency: Default::default(),
+            profile: Default::default(),
+        }
+    }
+
+    #[test]
+    fn aggregates_window_and_renders_template() {
+        let now = Utc::now();
+        let logs = vec![
+            log(10, "openai", "ct_a", 1.5, 200),
+            log(20, "openai", "ct_b", 0.5, 200),
+            log(30, "anthropic", "ct_a", 0.0, 502),
+            // 窗口之外
+            log(60 * 30, "openai", "ct_a", 9.0, 200),
+        ];
+        let tokens = TokenDirectory::from([
+            ("ct_a".into(), ("partner".into(), Some("org-1".into()))),
+            ("ct_b".into(), ("internal".into(), None)),
+        ]);
+        let report = aggregate(PERIOD_DAILY, now - Duration::days(1), now, &logs, &tokens);
+        assert_eq!((report.total_requests, report.error_requests), (3, 1));
+        assert!((report.total_amount_spent - 2.0).abs() < 1e-9);
+        assert_eq!(report.spend_by_provider[0].name, "openai");
+        assert_eq!(report.spend_by_provider[0].requests, 2);
+        assert_eq!(report.spend_by_organization[0].name, "org-1");
+        assert_eq!(report.top_tokens[0].name, "partner");
+        assert_eq!(
+            report.error_highlights[0].message,
+            "HTTP 502: upstream timeout"
+        );
+
+        let text = render_report("{{name}}: {{total_requests}} {{unknown}}", "Ops", &report);
+        assert_eq!(text, "Ops: 3 {{unknown}}");
+        assert!(render_report("", "Ops", &report).contains("- openai: 2.0000 (2 requests)"));
+    }
+}
@@ -1,30 +1,86 @@
+pub(crate) mod admin_notifications;
+pub(crate) mod app_state_builder;
+pub(crate) mod audio_speech;
+pub(crate) mod audio_transcription;
+pub(crate) mod branding;
+pub(crate) mod budget_hints;
+pub(crate) mod chat_pipeline;
+pub(crate) mod chat_plan;
 pub(crate) mod chat_request;
+pub(crate) mod citations;
+pub(crate) mod cluster;
+pub(crate) mod db_replication;
+pub(crate) mod debug_capture;
+pub(crate) mod degraded;
+pub(crate) mod drain;
+pub(crate) mod dual_write;
+#[cfg(test)]
+mod e2e_tests;
+pub(crate) mod egress;
+pub(crate) mod fault_injection;
 pub mod handlers;
+pub(crate) mod idempotency;
+pub(crate) mod image_generation;
+pub(crate) mod in_flight;
+pub(crate) mod jobs;
+pub(crate) mod legacy_completions;
+pub(crate) mod lenient_request;
+pub(crate) mod log_fields;
+pub(crate) mod log_queue;
 pub mod login;
+pub(crate) mod maintenance;
+pub(crate) mod metrics_reports;
 pub(crate) mod model_cache;
+pub(crate) mod model_concurrency;
 pub(crate) mod model_display;
 pub(crate) mod model_helpers;
 pub(crate) mod model_parser;
 pub(crate) mod model_redirect;
 pub(crate) mod model_types;
+pub(crate) mod n_choices;
+pub(crate) mod notifications;
+pub(crate) mod pagination;
+pub(crate) mod param_policy;
+pub(crate) mod payload_limits;
 pub(crate) mod pricing;
+pub(crate) mod pricing_bulk;
 pub(crate) mod pricing_sync;
+pub(crate) mod prompt_profile;
+pub(crate) mod prompt_truncation;
+pub(crate) mod provider_budget;
 pub(crate) mod provider_dispatch;
+pub(crate) mod provider_override;
 pub(crate) mod request_lab;
 pub(crate) mod request_logging;
+pub(crate) mod request_quota;
+pub(crate) mod request_signing;
 pub(crate) mod response_text;
+pub(crate) mod runtime_settings;
+pub(crate) mod sandbox;
+pub(crate) mod scheduler;
+pub(crate) mod semantic_cache;
 pub(crate) mod ssrf;
+pub(crate) mod statements;
 pub(crate) mod storage_traits;
+pub(crate) mod stream_transcript;
 pub(crate) mod streaming;
+pub(crate) mod tasks;
+#[cfg(test)]
+pub(crate) mod test_harness;
+pub(crate) mod token_lineage;
 pub(crate) mod token_model_limits;
+pub(crate) mod token_rotation;
+pub(crate) mod token_usage_buffer;
+pub(crate) mod totp;
+pub(crate) mod usage_webhooks;
+pub(crate) mod user_budget;
 pub(crate) mod util;
+pub(crate) mod watermark;
 
-use crate::admin::{PgTokenStore, TokenStore};
+use crate::admin::TokenStore;
 use crate::balance::BalanceStore;
 use crate::config::Settings;
 use crate::error::{GatewayError, Result as AppResult};
-use crate::logging::DatabaseLogger;
-use crate::logging::postgres_store::PgLogStore;
 use crate::password_reset_tokens::PasswordResetTokenStore;
 use crate::refresh_tokens::RefreshTokenStore;
 use crate::routing::LoadBalancerState;
@@ -43,21 +99,6 @@ use rand::Rng;
 use std::path::PathBuf;
 use std::sync::Arc;
 
-type StoreTuple = (
-    Arc<dyn RequestLogStore + Send + Sync>,
-    Arc<dyn ModelCache + Send + Sync>,
-    Arc<dyn ProviderStore + Send + Sync>,
-    Arc<dyn TokenStore + Send + Sync>,
-    Arc<dyn FavoritesStore + Send + Sync>,
-    Arc<dyn OrganizationStore + Send + Sync>,
-    Arc<dyn LoginStore + Send + Sync>,
-    Arc<dyn UserStore + Send + Sync>,
-    Arc<dyn RefreshTokenStore + Send + Sync>,
-    Arc<dyn PasswordResetTokenStore + Send + Sync>,
-    Arc<dyn BalanceStore + Send + Sync>,
-    Arc<dyn SubscriptionStore + Send + Sync>,
-);
-
 #[derive(Clone)]
 pub struct AppState {
     pub config: Settings,
@@ -74,110 +115,85 @@ pub struct AppState {
     pub password_reset_token_store: Arc<dyn PasswordResetTokenStore + Send + Sync>,
     pub balance_store: Arc<dyn BalanceStore + Send + Sync>,
     pub subscription_store: Arc<dyn SubscriptionStore + Send + Sync>,
+    pub runtime_settings: Arc<runtime_settings::RuntimeSettingsManager>,
+    pub task_registry: Arc<tasks::TaskRegistry>,
+    pub fault_injector: Arc<fault_injection::FaultInjector>,
+    pub idempotency_in_flight: Arc<idempotency::InFlightKeys>,
+    pub usage_webhooks: Arc<usage_webhooks::UsageWebhookQueue>,
+    pub signature_nonces: Arc<request_signing::NonceCache>,
+    pub egress_meter: Arc<egress::EgressMeter>,
+    pub provider_spend: Arc<provider_budget::ProviderSpendTracker>,
+    pub cluster: Arc<cluster::ClusterPeers>,
+    pub semantic_cache: Arc<semantic_cache::SemanticCache>,
+    pub model_concurrency: Arc<model_concurrency::ModelConcurrency>,
+    pub in_flight: Arc<in_flight::InFlightTracker>,
+    pub request_quota: Arc<request_quota::RequestQuotaCounter>,
+    pub payload_sizes: Arc<payload_limits::PayloadSizeStats>,
+    pub maintenance: Arc<maintenance::MaintenanceSchedule>,
+    pub request_deviations: Arc<lenient_request::RequestDeviationStats>,
+    /// 令牌用量延迟写入的计费区段；未启用延迟写入时为 None
+    pub usage_sections: Option<Arc<token_usage_buffer::UsageSections>>,
 }
 
 /// 创建 HTTP 应用：
-/// - 根据配置选择日志/缓存/令牌存储（Postgres 或本地 SQLite）
-/// - 确保存在至少一把管理员登录密钥（首次启动自动生成并落盘提示）
+/// - 经 [`app_state_builder::AppStateBuilder`] 打开并体检存储、装配全局状态
 /// - 构建带全局状态和 CORS 中间件的 Axum 路由
 pub async fn create_app(config: Settings) -> AppResult<Router> {
-    // Admin identity token generated per boot
-    // Choose stores based on Postgres availability
-    let (
-        log_store_arc,
-        model_cache_arc,
-        provider_store_arc,
-        token_store,
-        favorites_store_arc,
-        organizations_store_arc,
-        login_store_arc,
-        user_store_arc,
-        refresh_token_store_arc,
-        password_reset_token_store_arc,
-        balance_store_arc,
-        subscription_store_arc,
-    ): StoreTuple = if let Some(pg_url) = &config.logging.pg_url {
-        // Strict Postgres-only mode (no SQLite fallback)
-        let pool_size = config.logging.pg_pool_size.unwrap_or(4);
-        let pglog = PgLogStore::connect(pg_url, &config.logging.pg_schema, pool_size).await?;
-        tracing::info!("Using PostgreSQL for logs and cache");
-        let log_cache = Arc::new(pglog);
-        let ts = PgTokenStore::connect(pg_url, config.logging.pg_schema.as_deref()).await?;
-        (
-            log_cache.clone(),
-            log_cache.clone(),
-            log_cache.clone(),
-            Arc::new(ts),
-            log_cache.clone(),
-            log_cache.clone(),
-            log_cache.clone(),
-            log_cache.clone(),
-            log_cache.clone(),
-            log_cache.clone(),
-            log_cache.clone(),
-            log_cache.clone(),
-        )
-    } else {
-        let db_logger = Arc::new(DatabaseLogger::new(&config.logging.database_path).await?);
-        (
-            db_logger.clone(),
-            db_logger.clone(),
-            db_logger.clone(),
-            db_logger.clone(),
-            db_logger.clone(),
-            db_logger.clone(),
-            db_logger.clone(),
-            db_logger.clone(),
-            db_logger.clone(),
-            db_logger.clone(),
-            db_logger.clone(),
-            db_logger.clone(),
-        )
-    };
-
-    if std::env::var("GATEWAY_BOOTSTRAP_CODE")
-        .ok()
-        .filter(|v| !v.is_empty())
-        .is_none()
-    {
-        tracing::warn!("GATEWAY_BOOTSTRAP_CODE not set; /auth/register will be disabled");
-    }
-
-    if let Some((fingerprint, path)) = ensure_initial_admin_key(login_store_arc.clone()).await? {
-        tracing::warn!(
-            "新管理员密钥已生成；指纹={}，私钥已写入 {}，请立即妥善备份并加载至 TUI 配置。",
-            fingerprint,
-            path.display()
-        );
-        tracing::warn!(
-            "该密钥仅首次生成，后续启动会复用现有密钥，如需轮换请通过 TUI 管理途径重置。"
-        );
-    }
-
-    let app_state = AppState {
-        config,
-        load_balancer_state: Arc::new(LoadBalancerState::default()),
-        log_store: log_store_arc,
-        model_cache: model_cache_arc,
-        providers: provider_store_arc,
-        token_store,
-        favorites_store: favorites_store_arc,
-        organizations: organizations_store_arc,
-        login_manager: Arc::new(login::LoginManager::new(login_store_arc.clone())),
-        user_store: user_store_arc,
-        refresh_token_store: refresh_token_store_arc,
-        password_reset_token_store: password_reset_token_store_arc,
-        balance_store: balance_store_arc,
-        subscription_store: subscription_store_arc,
-    };
+    let app_state = app_state_builder::AppStateBuilder::new(config)
+        .build()
+        .await?;
+    Ok(build_router(app_state))
+}
 
+/// 组装完整的路由与中间件栈（create_app 与端到端测试共用）
+pub(crate) fn build_router(app_state: Arc<AppState>) -> Router {
     // Backward/forward compatibility:
     // Serve the same API both at `/` and under `/api/*` (useful for reverse proxies).
     let routes = handlers::routes();
-    let mut app = Router::new()
+    let app = Router::new()
         .merge(routes.clone())
         .nest("/api", routes)
-        .with_state(Arc::new(app_state));
+        // 宽松解析在最内层：签名校验需要原始请求体
+        .layer(axum::middleware::from_fn_with_state(
+            app_state.clone(),
+            lenient_request::normalize_chat_body,
+        ))
+        // 额度提示需读取签名/Gemini 入口换出的 Bearer Token，位于二者内层
+        .layer(axum::middleware::from_fn_with_state(
+            app_state.clone(),
+            budget_hints::annotate,
+        ))
+        // 注意顺序：签名中间件在外层，先把签名请求换成 Bearer Token 再校验轮换策略与父令牌链
+        .layer(axum::middleware::from_fn_with_state(
+            app_state.clone(),
+            token_rotation::enforce,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            app_state.clone(),
+            token_lineage::enforce_active_ancestors,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            app_state.clone(),
+            request_signing::enforce,
+        ))
+        // Gemini SDK 的 x-goog-api-key / ?key= 需在签名与父令牌校验前换成 Bearer
+        .layer(axum::middleware::from_fn(
+            handlers::gemini_ingress::google_api_key_auth,
+        ))
+        // Anthropic SDK 的 x-api-key 同理
+        .layer(axum::middleware::from_fn(
+            handlers::anthropic_ingress::anthropic_api_key_auth,
+        ))
+        .layer(axum::middleware::from_fn(
+            degraded::annotate_admin_responses,
+        ))
+        .layer(axum::middleware::from_fn(drain::track_in_flight))
+        // 品牌信息在最外层注入，覆盖所有中间件产生的错误响应
+        .layer(axum::middleware::from_fn_with_state(
+            app_state.clone(),
+            branding::brand_responses,
+        ))
+        .with_state(app_state.clone());
 
     // CORS（开发环境便于前端联调；生产应收敛来源并仅 HTTPS）
     use axum::http::{Method, header};
@@ -192,12 +208,16 @@ pub async fn create_app(config: Settings) -> AppResult<Router> {
             Method::OPTIONS,
         ])
         .allow_headers([header::CONTENT_TYPE, header::AUTHORIZATION])
-        // 反射请求来源（便于 dev server 代理转发携带 Cookie）
-        .allow_origin(AllowOrigin::mirror_request())
+        // 未配置来源白名单时反射请求来源（便于 dev server 代理转发携带 Cookie）；
+        // 白名单可通过 /admin/settings 在运行期调整
+        .allow_origin(AllowOrigin::predicate(move |origin, _| {
+            origin
+                .to_str()
+                .map(|o| app_state.runtime_settings.origin_allowed(o))
+                .unwrap_or(false)
+        }))
         .allow_credentials(true);
-    app = app.layer(cors);
-
-    Ok(app)
+    app.layer(cors)
 }
 
 async fn ensure_initial_admin_key(
@@ -224,6 +244,7 @@ async fn ensure_initial_admin_key(
         enabled: true,
         created_at: Utc::now(),
         last_used_at: None,
+        expires_at: None,
     };
     login_store
         .insert_admin_key(&record)
@@ -1,3 +1,4 @@
+use crate::logging::types::CachedModel;
 use crate::providers::openai::Model;
 use crate::server::AppState;
 
@@ -77,3 +78,130 @@ pub async fn remove_models_for_provider(
         .remove_cached_models(provider_name, model_ids)
         .await
 }
+
+/// 上游模型列表与缓存的差异报告（用于 reconcile）
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize)]
+pub struct ModelCacheDiff {
+    /// 上游存在但未缓存（仅报告，不自动加入，避免覆盖 selected 模式下的人工挑选）
+    pub added: Vec<String>,
+    /// 缓存中存在但上游已下线
+    pub removed: Vec<String>,
+    /// removed 中已超过宽限期、需要清理的条目
+    pub pruned: Vec<String>,
+    /// removed 中仍在宽限期内、暂时保留的条目
+    pub pending: Vec<String>,
+}
+
+/// 对比上游与缓存：cached_at 表示最近一次在上游出现的时间，
+/// 缺失超过 grace 的缓存条目归入 pruned，其余归入 pending
+pub fn diff_cached_models(
+    cached: &[CachedModel],
+    upstream: &[Model],
+    now: chrono::DateTime<chrono::Utc>,
+    grace: chrono::Duration,
+) -> ModelCacheDiff {
+    use std::collections::HashSet;
+    let upstream_ids: HashSet<&str> = upstream.iter().map(|m| m.id.as_str()).collect();
+    let cached_ids: HashSet<&str> = cached.iter().map(|m| m.id.as_str()).collect();
+
+    let mut diff = ModelCacheDiff {
+        added: upstream
+            .iter()
+            .filter(|m| !cached_ids.contains(m.id.as_str()))
+            .map(|m| m.id.clone())
+            .collect(),
+        ..Default::default()
+    };
+    for m in cached
+        .iter()
+        .filter(|m| !upstream_ids.contains(m.id.as_str()))
+    {
+        diff.removed.push(m.id.clone());
+        if now - m.cached_at >= grace {
+            diff.pruned.push(m.id.clone());
+        } else {
+            diff.pending.push(m.id.clone());
+        }
+    }
+    diff.added.sort();
+    diff.added.dedup();
+    diff.removed.sort();
+    diff.pruned.sort();
+    diff.pending.sort();
+    diff
+}
+
+/// 对账某供应商的模型缓存：刷新仍在上游的条目、清理超出宽限期的下线条目
+pub async fn reconcile_models_for_provider(
+    app_state: &AppState,
+    provider_name: &str,
+    upstream: &[Model],
+    grace: chrono::Duration,
+) -> rusqlite::Result<ModelCacheDiff> {
+    let cached = app_state
+        .model_cache
+        .get_cached_models(Some(provider_name))
+        .await?;
+    let diff = diff_cached_models(&cached, upstream, chrono::Utc::now(), grace);
+
+    let still_listed: Vec<Model> = upstream
+        .iter()
+        .filter(|m| cached.iter().any(|c| c.id == m.id))
+        .cloned()
+        .collect();
+    if !still_listed.is_empty() {
+        cache_models_for_provider_append(app_state, provider_name, &still_listed).await?;
+    }
+    // remove_cached_models 在 ids 为空时会清空整个供应商，需显式跳过
+    if !diff.pruned.is_empty() {
+        remove_models_for_provider(app_state, provider_name, &diff.pruned).await?;
+    }
+    Ok(diff)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, Utc};
+
+    fn cached(id: &str, cached_at: chrono::DateTime<Utc>) -> CachedModel {
+        CachedModel {
+            id: id.into(),
+            provider: "p".into(),
+            object: "model".into(),
+            created: 0,
+            owned_by: "p".into(),
+            cached_at,
+        }
+    }
+
+    fn upstream(id: &str) -> Model {
+        Model {
+            id: id.into(),
+            object: "model".into(),
+            created: 0,
+            owned_by: "p".into(),
+            display_name: None,
+        }
+    }
+
+    #[test]
+    fn diff_splits_removed_models_by_grace_period() {
+        let now = Utc::now();
+        let cache = vec![
+            cached("kept", now - Duration::days(10)),
+            cached("gone-old", now - Duration::hours(48)),
+            cached("gone-recent", now - Duration::hours(2)),
+        ];
+        let list = vec![upstream("kept"), upstream("new")];
+
+        let diff = diff_cached_models(&cache, &list, now, Duration::hours(24));
+        assert_eq!(diff.added, vec!["new".to_string()]);
+        assert_eq!(
+            diff.removed,
+            vec!["gone-old".to_string(), "gone-recent".to_string()]
+        );
+        assert_eq!(diff.pruned, vec!["gone-old".to_string()]);
+        assert_eq!(diff.pending, vec!["gone-recent".to_string()]);
+    }
+}
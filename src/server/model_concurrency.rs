@@ -0,0 +1,560 @@
+//! 按上游模型限制同时进行的生成数（自托管模型通常只能承受少量并发）。
+//! 达到上限时按配置排队等待空位（超时返回 429）或直接返回 429；
+//! 流式请求的占位持续到响应体输出完毕。
+//! 排队请求按令牌（或组织）分组，空位按令牌的 queue_weight 加权轮转分配，
+//! 避免单个令牌的大量请求占满队列。
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::body::Body;
+use axum::response::Response;
+use futures_util::StreamExt;
+use serde::Serialize;
+use tokio::sync::oneshot;
+
+use crate::admin::ClientToken;
+use crate::config::settings::{
+    FairQueueKey, ModelConcurrencyConfig, ModelConcurrencyMode, ServerConfig,
+};
+use crate::error::GatewayError;
+
+fn concurrency_limit_reached(upstream_model: &str, limit: &ModelConcurrencyConfig) -> GatewayError {
+    GatewayError::RateLimited(format!(
+        "model '{}' is at its concurrency limit ({} in flight)",
+        upstream_model, limit.max_in_flight
+    ))
+}
+
+/// 令牌 queue_weight 的上限
+pub const MAX_QUEUE_WEIGHT: i64 = 100;
+
+pub fn validate_queue_weight(value: Option<i64>) -> Result<(), GatewayError> {
+    if value.is_some_and(|v| !(1..=MAX_QUEUE_WEIGHT).contains(&v)) {
+        return Err(GatewayError::Config(format!(
+            "queue_weight must be between 1 and {}",
+            MAX_QUEUE_WEIGHT
+        )));
+    }
+    Ok(())
+}
+
+/// 排队时的调度单位：同一单位的请求按到达顺序排队，不同单位按权重轮流获得空位
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FairShare {
+    pub key: String,
+    pub weight: u32,
+}
+
+impl FairShare {
+    /// 按组织分组时，组织的权重取最近排队请求所用令牌的权重
+    pub fn for_token(config: &ServerConfig, token: &ClientToken) -> Self {
+        let org = match config.fair_queue_key {
+            FairQueueKey::Organization => token
+                .organization_id
+                .as_deref()
+                .filter(|org| !org.is_empty()),
+            FairQueueKey::Token => None,
+        };
+        Self {
+            key: org.map_or_else(
+                || format!("token:{}", token.id),
+                |org| format!("org:{}", org),
+            ),
+            weight: token
+                .queue_weight
+                .map_or(1, |w| w.clamp(1, MAX_QUEUE_WEIGHT) as u32),
+        }
+    }
+}
+
+struct Waiter {
+    id: u64,
+    enqueued_at: Instant,
+    tx: oneshot::Sender<ModelPermit>,
+}
+
+struct Flow {
+    key: String,
+    weight: u32,
+    /// 本轮剩余可连续获得的空位数
+    credits: u32,
+    waiters: VecDeque<Waiter>,
+}
+
+/// 加权轮转队列：队首单位连续获得 weight 个空位后移到队尾
+#[derive(Default)]
+struct FairQueue {
+    flows: VecDeque<Flow>,
+    len: usize,
+}
+
+impl FairQueue {
+    fn push(&mut self, share: &FairShare, waiter: Waiter) {
+        self.len += 1;
+        if let Some(flow) = self.flows.iter_mut().find(|f| f.key == share.key) {
+            flow.weight = share.weight.max(1);
+            flow.waiters.push_back(waiter);
+            return;
+        }
+        self.flows.push_back(Flow {
+            key: share.key.clone(),
+            weight: share.weight.max(1),
+            credits: share.weight.max(1),
+            waiters: VecDeque::from([waiter]),
+        });
+    }
+
+    fn pop(&mut self) -> Option<Waiter> {
+        let mut flow = self.flows.pop_front()?;
+        let waiter = flow.waiters.pop_front()?;
+        self.len -= 1;
+        flow.credits = flow.credits.saturating_sub(1);
+        if !flow.waiters.is_empty() {
+            if flow.credits == 0 {
+                flow.credits = flow.weight;
+                self.flows.push_back(flow);
+            } else {
+                self.flows.push_front(flow);
+            }
+        }
+        Some(waiter)
+    }
+
+    /// 移除放弃等待的请求；已被唤醒（不在队列中）时返回 false
+    fn remove(&mut self, key: &str, id: u64) -> bool {
+        let Some(idx) = self.flows.iter().position(|f| f.key == key) else {
+            return false;
+        };
+        let flow = &mut self.flows[idx];
+        let Some(pos) = flow.waiters.iter().position(|w| w.id == id) else {
+            return false;
+        };
+        flow.waiters.remove(pos);
+        self.len -= 1;
+        if flow.waiters.is_empty() {
+            self.flows.remove(idx);
+        }
+        true
+    }
+}
+
+#[derive(Default)]
+struct SlotState {
+    in_flight: usize,
+    queue: FairQueue,
+    next_waiter_id: u64,
+}
+
+struct ModelSlot {
+    max_in_flight: u32,
+    state: Mutex<SlotState>,
+    queue_timeouts: AtomicU64,
+}
+
+impl ModelSlot {
+    /// 归还名额：有排队请求时直接转交给轮到的请求
+    fn release(self: &Arc<Self>) {
+        loop {
+            let waiter = {
+                let mut state = self.state.lock().unwrap();
+                match state.queue.pop() {
+                    Some(waiter) => waiter,
+                    None => {
+                        state.in_flight = state.in_flight.saturating_sub(1);
+                        return;
+                    }
+                }
+            };
+            match waiter.tx.send(ModelPermit::new(self.clone())) {
+                Ok(()) => return,
+                // 等待方已放弃：收回名额转交下一位
+                Err(mut permit) => permit.slot = None,
+            }
+        }
+    }
+}
+
+/// 各模型的并发占位（本实例）
+#[derive(Default)]
+pub struct ModelConcurrency {
+    slots: Mutex<HashMap<String, Arc<ModelSlot>>>,
+}
+
+/// 一次上游调用占用的并发名额，释放时归还
+pub struct ModelPermit {
+    slot: Option<Arc<ModelSlot>>,
+}
+
+impl ModelPermit {
+    fn new(slot: Arc<ModelSlot>) -> Self {
+        Self { slot: Some(slot) }
+    }
+}
+
+impl Drop for ModelPermit {
+    fn drop(&mut self) {
+        if let Some(slot) = self.slot.take() {
+            slot.release();
+        }
+    }
+}
+
+/// 排队登记：等待被取消（如客户端断开或超时）时移出队列
+struct QueuedGuard<'a> {
+    slot: &'a ModelSlot,
+    key: &'a str,
+    id: u64,
+}
+
+impl Drop for QueuedGuard<'_> {
+    fn drop(&mut self) {
+        self.slot
+            .state
+            .lock()
+            .unwrap()
+            .queue
+            .remove(self.key, self.id);
+    }
+}
+
+/// 某一调度单位当前排队的请求
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct QueuedShare {
+    pub key: String,
+    pub weight: u32,
+    pub queued: usize,
+    /// 该单位最早排队请求已等待的毫秒数
+    pub oldest_wait_ms: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ModelInFlight {
+    pub model: String,
+    pub max_in_flight: u32,
+    pub in_flight: usize,
+    pub queued: usize,
+    pub mode: ModelConcurrencyMode,
+    /// 当前排队最久的请求已等待的毫秒数（饥饿指标）
+    pub oldest_wait_ms: u64,
+    /// 启动以来排队超时被拒绝的请求数
+    pub queue_timeouts: u64,
+    pub waiting: Vec<QueuedShare>,
+}
+
+impl ModelConcurrency {
+    fn slot(&self, model: &str, limit: &ModelConcurrencyConfig) -> Arc<ModelSlot> {
+        let mut slots = self.slots.lock().unwrap();
+        slots
+            .entry(model.to_string())
+            .or_insert_with(|| {
+                Arc::new(ModelSlot {
+                    max_in_flight: limit.max_in_flight,
+                    state: Mutex::new(SlotState::default()),
+                    queue_timeouts: AtomicU64::new(0),
+                })
+            })
+            .clone()
+    }
+
+    /// 不占用名额的预检：仅 fail_fast 模式在已满（或有人排队）时拒绝，排队模式总是放行
+    pub fn check_available(
+        &self,
+        config: &ServerConfig,
+        upstream_model: &str,
+    ) -> Result<(), GatewayError> {
+        let Some(limit) = config
+            .model_concurrency
+            .get(upstream_model)
+            .filter(|limit| {
+                limit.max_in_flight > 0 && limit.mode == ModelConcurrencyMode::FailFast
+            })
+        else {
+            return Ok(());
+        };
+        let slot = self.slot(upstream_model, limit);
+        let state = slot.state.lock().unwrap();
+        if state.queue.len == 0 && state.in_flight < slot.max_in_flight as usize {
+            return Ok(());
+        }
+        Err(concurrency_limit_reached(upstream_model, limit))
+    }
+
+    /// 为上游模型申请并发名额；未配置上限时返回 None
+    pub async fn acquire(
+        &self,
+        config: &ServerConfig,
+        upstream_model: &str,
+        share: &FairShare,
+    ) -> Result<Option<ModelPermit>, GatewayError> {
+        let Some(limit) = config
+            .model_concurrency
+            .get(upstream_model)
+            .filter(|limit| limit.max_in_flight > 0)
+        else {
+            return Ok(None);
+        };
+        let slot = self.slot(upstream_model, limit);
+        let busy = || concurrency_limit_reached(upstream_model, limit);
+        let (rx, id) = {
+            let mut state = slot.state.lock().unwrap();
+            // 有人排队时新请求也要排队，空位由轮转决定归属
+            if state.queue.len == 0 && state.in_flight < slot.max_in_flight as usize {
+                state.in_flight += 1;
+                return Ok(Some(ModelPermit::new(slot.clone())));
+            }
+            if limit.mode == ModelConcurrencyMode::FailFast {
+                return Err(busy());
+            }
+            let (tx, rx) = oneshot::channel();
+            let id = state.next_waiter_id;
+            state.next_waiter_id += 1;
+            state.queue.push(
+                share,
+                Waiter {
+                    id,
+                    enqueued_at: Instant::now(),
+                    tx,
+                },
+            );
+            (rx, id)
+        };
+        let _queued = QueuedGuard {
+            slot: &slot,
+            key: &share.key,
+            id,
+        };
+        match tokio::time::timeout(Duration::from_secs(limit.queue_timeout_secs), rx).await {
+            Ok(Ok(permit)) => Ok(Some(permit)),
+            _ => {
+                slot.queue_timeouts.fetch_add(1, Ordering::Relaxed);
+                tracing::warn!(
+                    model = upstream_model,
+                    queue = %share.key,
+                    timeout_secs = limit.queue_timeout_secs,
+                    "timed out waiting for a model concurrency slot"
+                );
+                Err(busy())
+            }
+        }
+    }
+
+    /// 已配置上限的模型及其当前进行中/排队的请求数（按模型名排序）
+    pub fn snapshot(&self, config: &ServerConfig) -> Vec<ModelInFlight> {
+        let slots = self.slots.lock().unwrap();
+        let now = Instant::now();
+        let mut models = config
+            .model_concurrency
+            .iter()
+            .filter(|(_, limit)| limit.max_in_flight > 0)
+            .map(|(model, limit)| {
+                let mut status = ModelInFlight {
+                    model: model.clone(),
+                    max_in_flight: limit.max_in_flight,
+                    in_flight: 0,
+                    queued: 0,
+                    mode: limit.mode,
+                    oldest_wait_ms: 0,
+                    queue_timeouts: 0,
+                    waiting: Vec::new(),
+                };
+                if let Some(slot) = slots.get(model) {
+                    let state = slot.state.lock().unwrap();
+                    status.in_flight = state.in_flight;
+                    status.queued = state.queue.len;
+                    status.queue_timeouts = slot.queue_timeouts.load(Ordering::Relaxed);
+                    status.waiting = state
+                        .queue
+                        .flows
+                        .iter()
+                        .map(|flow| QueuedShare {
+                            key: flow.key.clone(),
+                            weight: flow.weight,
+                            queued: flow.waiters.len(),
+                            oldest_wait_ms: flow.waiters.front().map_or(0, |w| {
+                                now.duration_since(w.enqueued_at).as_millis() as u64
+                            }),
+                        })
+                        .collect();
+                    status.oldest_wait_ms = status
+                        .waiting
+                        .iter()
+                        .map(|w| w.oldest_wait_ms)
+                        .max()
+                        .unwrap_or(0);
+                }
+                status
+            })
+            .collect::<Vec<_>>();
+        models.sort_by(|a, b| a.model.cmp(&b.model));
+        models
+    }
+}
+
+/// 流式响应：名额保持到响应体输出完毕（或客户端断开）
+pub fn hold_during_stream(response: Response, permit: ModelPermit) -> Response {
+    let (parts, body) = response.into_parts();
+    let body = Body::from_stream(body.into_data_stream().map(move |chunk| {
+        let _ = &permit;
+        chunk
+    }));
+    Response::from_parts(parts, body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(mode: ModelConcurrencyMode) -> ServerConfig {
+        let mut config = ServerConfig::default();
+        config.model_concurrency.insert(
+            "local-llm".into(),
+            ModelConcurrencyConfig {
+                max_in_flight: 1,
+                mode,
+                queue_timeout_secs: 1,
+            },
+        );
+        config
+    }
+
+    fn share(key: &str) -> FairShare {
+        FairShare {
+            key: key.into(),
+            weight: 1,
+        }
+    }
+
+    #[tokio::test]
+    async fn fail_fast_rejects_when_full() {
+        let config = config(ModelConcurrencyMode::FailFast);
+        let limits = ModelConcurrency::default();
+        assert!(
+            limits
+                .acquire(&config, "other", &share("a"))
+                .await
+                .unwrap()
+                .is_none()
+        );
+
+        let permit = limits
+            .acquire(&config, "local-llm", &share("a"))
+            .await
+            .unwrap();
+        assert!(permit.is_some());
+        assert_eq!(limits.snapshot(&config)[0].in_flight, 1);
+        assert!(matches!(
+            limits.acquire(&config, "local-llm", &share("a")).await,
+            Err(GatewayError::RateLimited(_))
+        ));
+
+        drop(permit);
+        assert_eq!(limits.snapshot(&config)[0].in_flight, 0);
+        assert!(
+            limits
+                .acquire(&config, "local-llm", &share("a"))
+                .await
+                .unwrap()
+                .is_some()
+        );
+    }
+
+    #[tokio::test]
+    async fn queued_requests_wait_for_a_free_slot() {
+        let config = Arc::new(config(ModelConcurrencyMode::Queue));
+        let limits = Arc::new(ModelConcurrency::default());
+        let first = limits
+            .acquire(&config, "local-llm", &share("a"))
+            .await
+            .unwrap();
+
+        let waiter = {
+            let (config, limits) = (config.clone(), limits.clone());
+            tokio::spawn(async move {
+                limits
+                    .acquire(&config, "local-llm", &share("a"))
+                    .await
+                    .map(|p| p.is_some())
+            })
+        };
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(limits.snapshot(&config)[0].queued, 1);
+
+        drop(first);
+        assert!(waiter.await.unwrap().unwrap());
+        let status = &limits.snapshot(&config)[0];
+        assert_eq!((status.in_flight, status.queued), (0, 0));
+    }
+
+    #[tokio::test]
+    async fn queue_times_out() {
+        let config = config(ModelConcurrencyMode::Queue);
+        let limits = ModelConcurrency::default();
+        let _held = limits
+            .acquire(&config, "local-llm", &share("a"))
+            .await
+            .unwrap();
+        assert!(matches!(
+            limits.acquire(&config, "local-llm", &share("a")).await,
+            Err(GatewayError::RateLimited(_))
+        ));
+        let status = &limits.snapshot(&config)[0];
+        assert_eq!((status.queued, status.queue_timeouts), (0, 1));
+    }
+
+    #[tokio::test]
+    async fn free_slots_rotate_between_queues_by_weight() {
+        let config = Arc::new(config(ModelConcurrencyMode::Queue));
+        let limits = Arc::new(ModelConcurrency::default());
+        let held = limits
+            .acquire(&config, "local-llm", &share("a"))
+            .await
+            .unwrap();
+        let order = Arc::new(Mutex::new(Vec::new()));
+
+        let mut waiters = Vec::new();
+        for (key, weight) in [
+            ("heavy", 1),
+            ("heavy", 1),
+            ("heavy", 1),
+            ("light", 2),
+            ("light", 2),
+        ] {
+            let (config, limits, order) = (config.clone(), limits.clone(), order.clone());
+            let share = FairShare {
+                key: key.into(),
+                weight,
+            };
+            waiters.push(tokio::spawn(async move {
+                let permit = limits.acquire(&config, "local-llm", &share).await.unwrap();
+                order.lock().unwrap().push(share.key);
+                drop(permit);
+            }));
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let status = &limits.snapshot(&config)[0];
+        assert_eq!(status.queued, 5);
+        assert_eq!(
+            status
+                .waiting
+                .iter()
+                .map(|w| (w.key.as_str(), w.weight, w.queued))
+                .collect::<Vec<_>>(),
+            vec![("heavy", 1, 3), ("light", 2, 2)]
+        );
+        assert!(status.oldest_wait_ms >= 40);
+
+        drop(held);
+        for waiter in waiters {
+            waiter.await.unwrap();
+        }
+        assert_eq!(
+            *order.lock().unwrap(),
+            vec!["heavy", "light", "light", "heavy", "heavy"]
+        );
+        let status = &limits.snapshot(&config)[0];
+        assert_eq!((status.in_flight, status.queued), (0, 0));
+    }
+}
@@ -1,28 +1,224 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use reqwest::StatusCode;
+use reqwest::header::{ETAG, HeaderMap, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use sha2::{Digest, Sha256};
+use tokio::sync::Mutex;
+
 use crate::config::ProviderType;
 use crate::error::{GatewayError, Result as AppResult};
-use crate::providers::openai::{Model, ModelListResponse, OpenAIProvider};
+use crate::providers::local;
+use crate::providers::openai::client::bearer_auth;
+use crate::providers::openai::{Model, OpenAIProvider};
 use crate::providers::{adapters::ListModelsRequest, adapters::adapter_for};
 
+/// 上游模型列表缓存的有效期；过期后携带 ETag / Last-Modified 向上游条件请求重新校验
+const UPSTREAM_MODELS_TTL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+struct UpstreamModelsKey {
+    provider: String,
+    base_url: String,
+    models_endpoint: Option<String>,
+    auth_fingerprint: String,
+}
+
+/// 上游响应的缓存校验信息
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Validators {
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+impl Validators {
+    fn from_headers(headers: &HeaderMap) -> Self {
+        let get = |name| {
+            headers
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string)
+        };
+        Self {
+            etag: get(ETAG),
+            last_modified: get(LAST_MODIFIED),
+        }
+    }
+
+    fn apply(&self, mut request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        if let Some(etag) = &self.etag {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &self.last_modified {
+            request = request.header(IF_MODIFIED_SINCE, last_modified);
+        }
+        request
+    }
+}
+
+#[derive(Debug, Clone)]
+struct UpstreamModelsEntry {
+    at: Instant,
+    models: Vec<Model>,
+    validators: Validators,
+}
+
+enum UpstreamModels {
+    Modified(Vec<Model>, Validators),
+    NotModified,
+}
+
+/// 本次模型列表的来源，通过 `x-models-cache` 响应头返回
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModelsCacheStatus {
+    /// 缓存仍在有效期内，未请求上游
+    Hit,
+    /// 缓存过期，上游返回 304，沿用缓存并续期
+    Revalidated,
+    /// 无可用缓存，已从上游拉取
+    Miss,
+    /// force_refresh：跳过缓存直接拉取
+    Bypass,
+}
+
+impl ModelsCacheStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ModelsCacheStatus::Hit => "hit",
+            ModelsCacheStatus::Revalidated => "revalidated",
+            ModelsCacheStatus::Miss => "miss",
+            ModelsCacheStatus::Bypass => "bypass",
+        }
+    }
+}
+
+static UPSTREAM_MODELS_CACHE: OnceLock<Mutex<HashMap<UpstreamModelsKey, UpstreamModelsEntry>>> =
+    OnceLock::new();
+
+fn cache() -> &'static Mutex<HashMap<UpstreamModelsKey, UpstreamModelsEntry>> {
+    UPSTREAM_MODELS_CACHE.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn cache_key(provider: &crate::config::Provider, api_key: &str) -> UpstreamModelsKey {
+    let digest = Sha256::digest(api_key.trim().as_bytes());
+    UpstreamModelsKey {
+        provider: provider.name.clone(),
+        base_url: provider.base_url.trim().trim_end_matches('/').to_string(),
+        models_endpoint: provider
+            .models_endpoint
+            .as_deref()
+            .map(|s| s.trim().to_string()),
+        auth_fingerprint: hex::encode(&digest[..8]),
+    }
+}
+
+/// 供应商配置或密钥变更后清除其上游模型列表缓存
+pub(crate) async fn invalidate_upstream_models(provider: &str) {
+    cache()
+        .lock()
+        .await
+        .retain(|key, _| key.provider != provider.trim());
+}
+
 // 获取指定 Provider 的模型列表，并在需要时通过自定义端点获取
 pub async fn fetch_provider_models(
     provider: &crate::config::Provider,
     api_key: &str,
 ) -> AppResult<Vec<Model>> {
+    match fetch_upstream_models(provider, api_key, &Validators::default()).await? {
+        UpstreamModels::Modified(models, _) => Ok(models),
+        UpstreamModels::NotModified => Err(GatewayError::Config(
+            "upstream returned 304 for an unconditional models request".into(),
+        )),
+    }
+}
+
+/// 带短期缓存的上游模型列表：有效期内直接返回缓存，过期后条件请求重新校验；
+/// force_refresh 跳过缓存直接拉取
+pub async fn fetch_provider_models_cached(
+    provider: &crate::config::Provider,
+    api_key: &str,
+    force_refresh: bool,
+) -> AppResult<(Vec<Model>, ModelsCacheStatus)> {
+    let key = cache_key(provider, api_key);
+    let cached = cache().lock().await.get(&key).cloned();
+    let cached = cached.filter(|_| !force_refresh);
+    if let Some(entry) = cached
+        .as_ref()
+        .filter(|e| e.at.elapsed() <= UPSTREAM_MODELS_TTL)
+    {
+        return Ok((entry.models.clone(), ModelsCacheStatus::Hit));
+    }
+
+    let validators = cached
+        .as_ref()
+        .map(|e| e.validators.clone())
+        .unwrap_or_default();
+    let (models, validators, status) =
+        match fetch_upstream_models(provider, api_key, &validators).await? {
+            UpstreamModels::NotModified => match cached {
+                Some(entry) => (
+                    entry.models,
+                    entry.validators,
+                    ModelsCacheStatus::Revalidated,
+                ),
+                None => {
+                    return Err(GatewayError::Config(
+                        "upstream returned 304 for an unconditional models request".into(),
+                    ));
+                }
+            },
+            UpstreamModels::Modified(models, validators) => {
+                let status = if force_refresh {
+                    ModelsCacheStatus::Bypass
+                } else {
+                    ModelsCacheStatus::Miss
+                };
+                (models, validators, status)
+            }
+        };
+    cache().lock().await.insert(
+        key,
+        UpstreamModelsEntry {
+            at: Instant::now(),
+            models: models.clone(),
+            validators,
+        },
+    );
+    Ok((models, status))
+}
+
+async fn fetch_upstream_models(
+    provider: &crate::config::Provider,
+    api_key: &str,
+    validators: &Validators,
+) -> AppResult<UpstreamModels> {
     if let Some(models_endpoint) = &provider.models_endpoint {
         let full_url = format!(
             "{}{}",
             provider.base_url.trim_end_matches('/'),
             models_endpoint
         );
-        let response = fetch_models_from_endpoint(&full_url, api_key, provider.api_type).await?;
-        Ok(response.data)
+        fetch_models_from_endpoint(&full_url, api_key, provider, validators).await
     } else {
-        if matches!(
+        if provider.api_type == ProviderType::Local {
+            // Ollama 提供 /api/tags；vLLM / LM Studio 没有该接口时回退到 OpenAI 兼容的 /v1/models
+            let tags = bearer_request(&local::tags_url(&provider.base_url), api_key)?;
+            match send_models_request(validators.apply(tags), &provider.name).await {
+                Err(GatewayError::NotFound(_)) => {
+                    let url = OpenAIProvider::models_url(&provider.base_url);
+                    let request = bearer_request(&url, api_key)?;
+                    send_models_request(validators.apply(request), &provider.name).await
+                }
+                other => other,
+            }
+        } else if matches!(
             provider.api_type,
             ProviderType::OpenAI | ProviderType::Doubao
         ) {
-            let response = OpenAIProvider::list_models(&provider.base_url, api_key).await?;
-            Ok(response.data)
+            let request = bearer_request(&OpenAIProvider::models_url(&provider.base_url), api_key)?;
+            send_models_request(validators.apply(request), &provider.name).await
         } else if !provider
             .api_type
             .capabilities()
@@ -48,6 +244,7 @@ pub async fn fetch_provider_models(
                     .into(),
                 )
             })?;
+            // 适配器不暴露响应头，无法条件请求，过期后整体重新拉取
             let data = adapter
                 .list_models(ListModelsRequest {
                     models_url: &models_url,
@@ -63,17 +260,24 @@ pub async fn fetch_provider_models(
                     display_name: None,
                 })
                 .collect();
-            Ok(data)
+            Ok(UpstreamModels::Modified(data, Validators::default()))
         }
     }
 }
 
-// 从指定 URL 获取模型列表（OpenAI 兼容响应）
+fn bearer_request(url: &str, api_key: &str) -> AppResult<reqwest::RequestBuilder> {
+    let client = crate::http_client::client_for_url(url)?;
+    Ok(bearer_auth(client.get(url), api_key).header("Content-Type", "application/json"))
+}
+
+// 从指定 URL 获取模型列表（OpenAI 兼容响应或 Ollama `/api/tags`）
 async fn fetch_models_from_endpoint(
     url: &str,
     api_key: &str,
-    provider_type: ProviderType,
-) -> Result<ModelListResponse, GatewayError> {
+    provider: &crate::config::Provider,
+    validators: &Validators,
+) -> AppResult<UpstreamModels> {
+    let provider_type = provider.api_type;
     let client = crate::http_client::client_for_url(url)?;
     let adapter = adapter_for(provider_type).ok_or_else(|| {
         GatewayError::Config(
@@ -96,7 +300,115 @@ async fn fetch_models_from_endpoint(
             request = request.header(name, value);
         }
     }
+    send_models_request(validators.apply(request), &provider.name).await
+}
+
+async fn send_models_request(
+    request: reqwest::RequestBuilder,
+    owned_by: &str,
+) -> AppResult<UpstreamModels> {
     let response = request.send().await?;
+    let status = response.status();
+    if status == StatusCode::NOT_MODIFIED {
+        return Ok(UpstreamModels::NotModified);
+    }
+    if status == StatusCode::NOT_FOUND {
+        return Err(GatewayError::NotFound(format!(
+            "upstream models endpoint not found: {}",
+            response.url()
+        )));
+    }
+    let validators = Validators::from_headers(response.headers());
+    let bytes = response.error_for_status()?.bytes().await?;
+    let models = local::parse_model_list(&bytes, owned_by)
+        .ok_or_else(|| GatewayError::Config("解析上游模型列表失败（非 OpenAI 兼容响应）".into()))?;
+    Ok(UpstreamModels::Modified(models, validators))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::settings::{Provider, ProviderConfig};
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn provider(name: &str, base_url: &str) -> Provider {
+        Provider {
+            name: name.into(),
+            display_name: None,
+            collection: crate::config::settings::DEFAULT_PROVIDER_COLLECTION.into(),
+            api_type: ProviderType::OpenAI,
+            api_type_raw: None,
+            base_url: base_url.into(),
+            api_keys: Vec::new(),
+            models_endpoint: None,
+            provider_config: ProviderConfig::default(),
+            enabled: true,
+            created_at: None,
+            updated_at: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn cached_models_expire_into_conditional_revalidation() {
+        let upstream = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/models"))
+            .and(header("if-none-match", "\"v1\""))
+            .respond_with(ResponseTemplate::new(304))
+            .with_priority(1)
+            .mount(&upstream)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/models"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("etag", "\"v1\"")
+                    .set_body_json(serde_json::json!({
+                        "object": "list",
+                        "data": [{"id": "m1", "object": "model", "created": 1, "owned_by": "up"}]
+                    })),
+            )
+            .mount(&upstream)
+            .await;
+        let provider = provider("models-cache-test", &upstream.uri());
+
+        let (models, status) = fetch_provider_models_cached(&provider, "sk-a", false)
+            .await
+            .unwrap();
+        assert_eq!(status, ModelsCacheStatus::Miss);
+        assert_eq!(models[0].id, "m1");
+        let (_, status) = fetch_provider_models_cached(&provider, "sk-a", false)
+            .await
+            .unwrap();
+        assert_eq!(status, ModelsCacheStatus::Hit);
+        assert_eq!(upstream.received_requests().await.unwrap().len(), 1);
 
-    Ok(response.json::<ModelListResponse>().await?)
+        // 过期后携带 If-None-Match 重新校验，上游 304 时沿用缓存
+        for entry in cache().lock().await.values_mut() {
+            entry.at = Instant::now() - UPSTREAM_MODELS_TTL - Duration::from_secs(1);
+        }
+        let (models, status) = fetch_provider_models_cached(&provider, "sk-a", false)
+            .await
+            .unwrap();
+        assert_eq!(status, ModelsCacheStatus::Revalidated);
+        assert_eq!(models[0].id, "m1");
+
+        let (_, status) = fetch_provider_models_cached(&provider, "sk-a", true)
+            .await
+            .unwrap();
+        assert_eq!(status, ModelsCacheStatus::Bypass);
+        // 换密钥后不命中旧缓存
+        let (_, status) = fetch_provider_models_cached(&provider, "sk-b", false)
+            .await
+            .unwrap();
+        assert_eq!(status, ModelsCacheStatus::Miss);
+        assert_eq!(upstream.received_requests().await.unwrap().len(), 4);
+
+        invalidate_upstream_models("models-cache-test").await;
+        let (_, status) = fetch_provider_models_cached(&provider, "sk-a", false)
+            .await
+            .unwrap();
+        assert_eq!(status, ModelsCacheStatus::Miss);
+    }
 }
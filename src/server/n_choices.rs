@@ -0,0 +1,195 @@
+use async_openai::types::{CompletionTokensDetails, PromptTokensDetails};
+use serde_json::Value;
+
+use crate::config::ProviderType;
+use crate::error::GatewayError;
+use crate::providers::openai::types::RawAndTypedChatCompletion;
+use crate::providers::openai::usage::resolved_usage;
+use crate::providers::openai::{ChatCompletionRequest, Usage};
+
+/// 供应商不支持原生 n 时，单次请求最多拆分的上游调用数
+pub const MAX_FANOUT_CHOICES: u8 = 8;
+
+/// 请求的候选数（未指定时为 1）
+pub fn requested(request: &ChatCompletionRequest) -> u8 {
+    request.n.unwrap_or(1)
+}
+
+/// 需要由网关拆分为多次上游调用（每次 n=1）再合并
+pub fn needs_fanout(provider_type: ProviderType, request: &ChatCompletionRequest) -> bool {
+    requested(request) > 1 && !provider_type.supports_native_n_choices()
+}
+
+/// 分发前校验 n：不支持原生 n 的供应商只能以非流式拆分调用，且受 MAX_FANOUT_CHOICES 限制
+pub fn check_request(
+    provider_type: ProviderType,
+    request: &ChatCompletionRequest,
+    stream: bool,
+) -> Result<(), GatewayError> {
+    let n = requested(request);
+    if n == 0 {
+        return Err(GatewayError::Config("n must be at least 1".into()));
+    }
+    if !needs_fanout(provider_type, request) {
+        return Ok(());
+    }
+    if stream {
+        return Err(GatewayError::Config(format!(
+            "provider type '{}' does not support n>1 with stream=true; retry with stream=false",
+            provider_type.as_str()
+        )));
+    }
+    if n > MAX_FANOUT_CHOICES {
+        return Err(GatewayError::Config(format!(
+            "provider type '{}' supports at most n={} (gateway fan-out)",
+            provider_type.as_str(),
+            MAX_FANOUT_CHOICES
+        )));
+    }
+    Ok(())
+}
+
+fn add_opt(a: Option<u32>, b: Option<u32>) -> Option<u32> {
+    match (a, b) {
+        (None, None) => None,
+        (a, b) => Some(a.unwrap_or(0) + b.unwrap_or(0)),
+    }
+}
+
+fn add_usage(total: Usage, next: &Usage) -> Usage {
+    let cached = add_opt(
+        total
+            .prompt_tokens_details
+            .as_ref()
+            .and_then(|d| d.cached_tokens),
+        next.prompt_tokens_details
+            .as_ref()
+            .and_then(|d| d.cached_tokens),
+    );
+    let reasoning = add_opt(
+        total
+            .completion_tokens_details
+            .as_ref()
+            .and_then(|d| d.reasoning_tokens),
+        next.completion_tokens_details
+            .as_ref()
+            .and_then(|d| d.reasoning_tokens),
+    );
+    Usage {
+        prompt_tokens: total.prompt_tokens + next.prompt_tokens,
+        completion_tokens: total.completion_tokens + next.completion_tokens,
+        total_tokens: total.total_tokens + next.total_tokens,
+        prompt_tokens_details: cached.map(|cached_tokens| PromptTokensDetails {
+            cached_tokens: Some(cached_tokens),
+            ..Default::default()
+        }),
+        completion_tokens_details: reasoning.map(|reasoning_tokens| CompletionTokensDetails {
+            reasoning_tokens: Some(reasoning_tokens),
+            ..Default::default()
+        }),
+    }
+}
+
+/// 合并拆分调用的响应：choices 按调用顺序重新编号，usage 求和（按合计计费）；
+/// 任一上游返回错误载荷时原样返回该载荷；responses 不能为空
+pub fn merge_fanout(responses: Vec<RawAndTypedChatCompletion>) -> RawAndTypedChatCompletion {
+    if let Some(error) = responses
+        .iter()
+        .find(|dual| dual.raw.get("error").is_some() && dual.raw.get("choices").is_none())
+    {
+        return error.clone();
+    }
+    let usage = responses
+        .iter()
+        .filter_map(|dual| resolved_usage(&dual.raw, &dual.typed))
+        .reduce(|total, next| add_usage(total, &next));
+
+    let mut responses = responses.into_iter();
+    let mut merged = responses
+        .next()
+        .expect("fan-out yields at least one response");
+    let mut raw_choices = take_raw_choices(&mut merged.raw);
+    for mut dual in responses {
+        raw_choices.extend(take_raw_choices(&mut dual.raw));
+        merged.typed.choices.extend(dual.typed.choices);
+    }
+    for (index, choice) in raw_choices.iter_mut().enumerate() {
+        if let Some(choice) = choice.as_object_mut() {
+            choice.insert("index".into(), Value::from(index));
+        }
+    }
+    for (index, choice) in merged.typed.choices.iter_mut().enumerate() {
+        choice.index = index as u32;
+    }
+    if let Some(raw) = merged.raw.as_object_mut() {
+        raw.insert("choices".into(), Value::Array(raw_choices));
+        if let Some(usage) = usage.as_ref()
+            && let Ok(value) = serde_json::to_value(usage)
+        {
+            raw.insert("usage".into(), value);
+        }
+    }
+    merged.typed.usage = usage;
+    merged
+}
+
+fn take_raw_choices(raw: &mut Value) -> Vec<Value> {
+    match raw.get_mut("choices").map(Value::take) {
+        Some(Value::Array(choices)) => choices,
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn dual(content: &str, prompt: u32, completion: u32) -> RawAndTypedChatCompletion {
+        let raw = json!({
+            "id": "chatcmpl-test",
+            "object": "chat.completion",
+            "created": 1,
+            "model": "m1",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": content},
+                "finish_reason": "stop"
+            }],
+            "usage": {
+                "prompt_tokens": prompt,
+                "completion_tokens": completion,
+                "total_tokens": prompt + completion
+            }
+        });
+        RawAndTypedChatCompletion {
+            typed: serde_json::from_value(raw.clone()).unwrap(),
+            raw,
+        }
+    }
+
+    #[test]
+    fn fanout_is_limited_to_non_stream_requests() {
+        let request: ChatCompletionRequest = serde_json::from_value(json!({
+            "model": "m1",
+            "messages": [{"role": "user", "content": "hi"}],
+            "n": 2
+        }))
+        .unwrap();
+        assert!(check_request(ProviderType::OpenAI, &request, true).is_ok());
+        assert!(check_request(ProviderType::Anthropic, &request, false).is_ok());
+        assert!(check_request(ProviderType::Anthropic, &request, true).is_err());
+    }
+
+    #[test]
+    fn merged_choices_are_reindexed_and_usage_summed() {
+        let merged = merge_fanout(vec![dual("a", 10, 2), dual("b", 10, 3)]);
+        let choices = merged.raw["choices"].as_array().unwrap();
+        assert_eq!(choices.len(), 2);
+        assert_eq!(choices[1]["index"], 1);
+        assert_eq!(choices[1]["message"]["content"], "b");
+        assert_eq!(merged.typed.choices[1].index, 1);
+        assert_eq!(merged.raw["usage"]["prompt_tokens"], 20);
+        assert_eq!(merged.typed.usage.unwrap().total_tokens, 25);
+    }
+}
@@ -0,0 +1,138 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use resend_rs::{Resend, types::CreateEmailBaseOptions};
+use serde::Serialize;
+
+use crate::admin::TokenNotificationRecord;
+use crate::config::settings::ServerConfig;
+
+pub const CHANNEL_WEBHOOK: &str = "webhook";
+pub const CHANNEL_EMAIL: &str = "email";
+
+const WEBHOOK_TIMEOUT_SECS: u64 = 10;
+
+/// 一条发往令牌所有者的通知
+#[derive(Debug, Clone, Serialize)]
+pub struct TokenNotification {
+    pub kind: &'static str,
+    pub token_id: String,
+    pub token_name: String,
+    pub user_id: Option<String>,
+    /// 去重依据（同一 kind + reference 只成功发送一次）
+    pub reference: String,
+    pub subject: String,
+    pub message: String,
+}
+
+fn env_non_empty(name: &'static str) -> Option<String> {
+    std::env::var(name)
+        .ok()
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+/// POST JSON 到 Webhook，非 2xx 视为失败
+pub async fn post_webhook_json(url: &str, body: &serde_json::Value) -> Result<(), String> {
+    let client = crate::http_client::client_for_url(url).map_err(|e| e.to_string())?;
+    let resp = client
+        .post(url)
+        .timeout(Duration::from_secs(WEBHOOK_TIMEOUT_SECS))
+        .json(body)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    if !resp.status().is_success() {
+        return Err(format!("webhook responded with {}", resp.status()));
+    }
+    Ok(())
+}
+
+async fn send_webhook(url: &str, notification: &TokenNotification) -> Result<(), String> {
+    let body = serde_json::json!({
+        "kind": notification.kind,
+        "token_id": notification.token_id,
+        "token_name": notification.token_name,
+        "user_id": notification.user_id,
+        "subject": notification.subject,
+        "message": notification.message,
+        "sent_at": crate::logging::time::to_iso8601_utc_string(&Utc::now()),
+    });
+    post_webhook_json(url, &body).await
+}
+
+pub fn escape_html(raw: &str) -> String {
+    raw.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// 是否已配置邮件发送（Resend）
+pub fn email_configured() -> bool {
+    env_non_empty("RESEND_API_KEY").is_some()
+}
+
+/// 通过 Resend 发送一封 HTML 邮件
+pub async fn send_email_html(to: &str, subject: &str, html: &str) -> Result<(), String> {
+    let from = env_non_empty("RESEND_FROM").ok_or("RESEND_FROM not configured")?;
+    let resend = Resend::default();
+    let email = CreateEmailBaseOptions::new(from, [to.to_string()], subject).with_html(html);
+    resend
+        .emails
+        .send(email)
+        .await
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+async fn send_email(to: &str, notification: &TokenNotification) -> Result<(), String> {
+    let html = format!("<p>{}</p>", escape_html(&notification.message));
+    send_email_html(to, &notification.subject, &html).await
+}
+
+/// 通过所有已配置的渠道发送通知，返回每个渠道的发送记录。
+/// 未配置任何渠道时返回空列表（调用方据此决定是否稍后重试）。
+pub async fn deliver(
+    config: &ServerConfig,
+    notification: &TokenNotification,
+    owner_email: Option<&str>,
+) -> Vec<TokenNotificationRecord> {
+    let mut out = Vec::new();
+    let record = |channel: &str, recipient: Option<String>, result: Result<(), String>| {
+        TokenNotificationRecord {
+            token_id: notification.token_id.clone(),
+            kind: notification.kind.to_string(),
+            reference: notification.reference.clone(),
+            channel: channel.to_string(),
+            recipient,
+            success: result.is_ok(),
+            error: result.err(),
+            sent_at: Utc::now(),
+        }
+    };
+
+    if let Some(url) = config
+        .notification_webhook_url
+        .as_deref()
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+    {
+        let result = send_webhook(url, notification).await;
+        if let Err(e) = &result {
+            tracing::warn!("token notification webhook failed: {}", e);
+        }
+        out.push(record(CHANNEL_WEBHOOK, Some(url.to_string()), result));
+    }
+
+    if let Some(to) = owner_email.map(str::trim).filter(|s| !s.is_empty())
+        && email_configured()
+    {
+        let result = send_email(to, notification).await;
+        if let Err(e) = &result {
+            tracing::warn!("token notification email failed: {}", e);
+        }
+        out.push(record(CHANNEL_EMAIL, Some(to.to_string()), result));
+    }
+    out
+}
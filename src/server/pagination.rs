@@ -0,0 +1,261 @@
+//! 管理端列表接口的统一分页约定：
+//! `?limit=&cursor=&sort=&order=asc|desc`，返回 `{items, total, limit, next_cursor}`。
+//! 未携带任何分页参数时仍返回完整数组，兼容现有客户端。
+
+use std::cmp::Ordering;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::GatewayError;
+
+pub const DEFAULT_PAGE_LIMIT: u32 = 50;
+pub const MAX_PAGE_LIMIT: u32 = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+impl SortOrder {
+    fn as_sql(self) -> &'static str {
+        match self {
+            SortOrder::Asc => "ASC",
+            SortOrder::Desc => "DESC",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PageQuery {
+    #[serde(default)]
+    pub limit: Option<u32>,
+    /// 上一页返回的 next_cursor（不透明字符串）
+    #[serde(default)]
+    pub cursor: Option<String>,
+    #[serde(default)]
+    pub sort: Option<String>,
+    #[serde(default)]
+    pub order: Option<SortOrder>,
+}
+
+/// 端点允许的排序字段（即列名）与默认排序
+pub struct SortFields {
+    pub allowed: &'static [&'static str],
+    pub default: &'static str,
+    pub default_order: SortOrder,
+}
+
+/// 校验后的分页参数；sort 一定来自端点白名单，可直接拼入 SQL
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PageRequest {
+    pub limit: u32,
+    pub offset: u64,
+    pub sort: &'static str,
+    pub order: SortOrder,
+}
+
+impl PageQuery {
+    fn is_empty(&self) -> bool {
+        self.limit.is_none() && self.cursor.is_none() && self.sort.is_none() && self.order.is_none()
+    }
+
+    /// 未携带分页参数时返回 None（调用方返回完整数组）
+    pub fn resolve(&self, fields: &SortFields) -> Result<Option<PageRequest>, GatewayError> {
+        if self.is_empty() {
+            return Ok(None);
+        }
+        let sort = match self.sort.as_deref().map(str::trim) {
+            None | Some("") => fields.default,
+            Some(name) => fields
+                .allowed
+                .iter()
+                .copied()
+                .find(|field| *field == name)
+                .ok_or_else(|| {
+                    GatewayError::Config(format!(
+                        "unsupported sort field '{}'; expected one of: {}",
+                        name,
+                        fields.allowed.join(", ")
+                    ))
+                })?,
+        };
+        let offset = match self.cursor.as_deref().filter(|c| !c.is_empty()) {
+            None => 0,
+            Some(cursor) => decode_cursor(cursor)?,
+        };
+        Ok(Some(PageRequest {
+            limit: self
+                .limit
+                .unwrap_or(DEFAULT_PAGE_LIMIT)
+                .clamp(1, MAX_PAGE_LIMIT),
+            offset,
+            sort,
+            order: self.order.unwrap_or(fields.default_order),
+        }))
+    }
+}
+
+fn encode_cursor(offset: u64) -> String {
+    format!("o{}", offset)
+}
+
+fn decode_cursor(cursor: &str) -> Result<u64, GatewayError> {
+    cursor
+        .strip_prefix('o')
+        .and_then(|n| n.parse().ok())
+        .ok_or_else(|| GatewayError::Config("invalid cursor".into()))
+}
+
+impl PageRequest {
+    /// `ORDER BY <sort> <order>, <tiebreak...> ASC LIMIT n OFFSET m`（SQLite 与 Postgres 通用）；
+    /// tiebreak 为唯一键列，保证翻页顺序稳定
+    pub fn sql_tail(&self, tiebreak: &[&str]) -> String {
+        let mut order_by = format!("{} {}", self.sort, self.order.as_sql());
+        for column in tiebreak.iter().filter(|c| **c != self.sort) {
+            order_by.push_str(&format!(", {} ASC", column));
+        }
+        format!(
+            "ORDER BY {} LIMIT {} OFFSET {}",
+            order_by, self.limit, self.offset
+        )
+    }
+
+    /// 内存中排序并截取一页；用于由多个来源合并而成、无法直接下推到 SQL 的列表
+    pub fn paginate<T>(
+        &self,
+        mut items: Vec<T>,
+        compare: impl Fn(&str, &T, &T) -> Ordering,
+    ) -> Page<T> {
+        items.sort_by(|a, b| {
+            let ordering = compare(self.sort, a, b);
+            match self.order {
+                SortOrder::Asc => ordering,
+                SortOrder::Desc => ordering.reverse(),
+            }
+        });
+        let total = items.len() as u64;
+        let items = items
+            .into_iter()
+            .skip(self.offset as usize)
+            .take(self.limit as usize)
+            .collect();
+        Page::new(items, total, self)
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub total: u64,
+    pub limit: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+}
+
+impl<T> Page<T> {
+    pub fn new(items: Vec<T>, total: u64, request: &PageRequest) -> Self {
+        let end = request.offset + items.len() as u64;
+        Self {
+            next_cursor: (!items.is_empty() && end < total).then(|| encode_cursor(end)),
+            items,
+            total,
+            limit: request.limit,
+        }
+    }
+
+    pub fn map<U>(self, f: impl FnMut(T) -> U) -> Page<U> {
+        Page {
+            items: self.items.into_iter().map(f).collect(),
+            total: self.total,
+            limit: self.limit,
+            next_cursor: self.next_cursor,
+        }
+    }
+}
+
+/// 列表响应：未分页时为数组，分页时为统一信封
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum Listing<T> {
+    All(Vec<T>),
+    Page(Page<T>),
+}
+
+impl<T> Listing<T> {
+    /// 对已完整加载的列表按请求分页（无分页参数时原样返回）
+    pub fn from_vec(
+        items: Vec<T>,
+        page: Option<&PageRequest>,
+        compare: impl Fn(&str, &T, &T) -> Ordering,
+    ) -> Self {
+        match page {
+            None => Listing::All(items),
+            Some(page) => Listing::Page(page.paginate(items, compare)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIELDS: SortFields = SortFields {
+        allowed: &["name", "created_at"],
+        default: "created_at",
+        default_order: SortOrder::Desc,
+    };
+
+    fn query(limit: Option<u32>, cursor: Option<&str>, sort: Option<&str>) -> PageQuery {
+        PageQuery {
+            limit,
+            cursor: cursor.map(str::to_string),
+            sort: sort.map(str::to_string),
+            order: None,
+        }
+    }
+
+    #[test]
+    fn resolve_applies_defaults_and_whitelist() {
+        assert_eq!(PageQuery::default().resolve(&FIELDS).unwrap(), None);
+        let page = query(Some(10_000), None, None)
+            .resolve(&FIELDS)
+            .unwrap()
+            .unwrap();
+        assert_eq!(page.limit, MAX_PAGE_LIMIT);
+        assert_eq!((page.sort, page.order), ("created_at", SortOrder::Desc));
+        assert_eq!(
+            page.sql_tail(&["id"]),
+            "ORDER BY created_at DESC, id ASC LIMIT 500 OFFSET 0"
+        );
+        assert!(
+            query(None, None, Some("token; DROP TABLE x"))
+                .resolve(&FIELDS)
+                .is_err()
+        );
+        assert!(query(None, Some("12"), None).resolve(&FIELDS).is_err());
+    }
+
+    #[test]
+    fn cursor_walks_through_all_items() {
+        let names = vec!["c", "a", "e", "b", "d"];
+        let compare = |_: &str, a: &&str, b: &&str| a.cmp(b);
+        let mut seen = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let mut q = query(Some(2), cursor.as_deref(), Some("name"));
+            q.order = Some(SortOrder::Asc);
+            let page = q.resolve(&FIELDS).unwrap().unwrap();
+            let page = page.paginate(names.clone(), compare);
+            assert_eq!(page.total, 5);
+            seen.extend(page.items);
+            match page.next_cursor {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+        assert_eq!(seen, vec!["a", "b", "c", "d", "e"]);
+    }
+}
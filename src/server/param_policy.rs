@@ -0,0 +1,507 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::config::settings::{ModelParamRules, ServerConfig};
+use crate::error::GatewayError;
+use crate::logging::types::ParamPolicyRecord;
+use crate::providers::openai::ChatCompletionRequest;
+use crate::server::AppState;
+
+/// 不允许被策略改写的字段：改写后请求无法正确路由或解析
+const PROTECTED_PARAMS: &[&str] = &["model", "messages", "stream", "stream_options"];
+const MAX_POLICY_PARAMS: usize = 32;
+
+/// 数值参数的允许范围（闭区间），超出时收紧到边界
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ParamBounds {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ParamPolicyRules {
+    /// 参数名 -> 允许范围，如 {"temperature": {"max": 1.0}}
+    #[serde(default)]
+    pub clamp: BTreeMap<String, ParamBounds>,
+    /// 转发前移除的参数，如 ["logprobs", "top_logprobs"]
+    #[serde(default)]
+    pub strip: Vec<String>,
+}
+
+impl ParamPolicyRules {
+    pub fn validate(&self) -> Result<(), GatewayError> {
+        if self.clamp.is_empty() && self.strip.is_empty() {
+            return Err(GatewayError::Config(
+                "policy must clamp or strip at least one parameter".into(),
+            ));
+        }
+        if self.clamp.len() + self.strip.len() > MAX_POLICY_PARAMS {
+            return Err(GatewayError::Config(format!(
+                "policy may cover at most {} parameters",
+                MAX_POLICY_PARAMS
+            )));
+        }
+        for name in self.clamp.keys().chain(self.strip.iter()) {
+            if name.trim().is_empty() || PROTECTED_PARAMS.contains(&name.as_str()) {
+                return Err(GatewayError::Config(format!(
+                    "parameter '{}' cannot be governed by a policy",
+                    name
+                )));
+            }
+        }
+        for (name, bounds) in &self.clamp {
+            let finite = |v: Option<f64>| v.is_none_or(f64::is_finite);
+            if (bounds.min.is_none() && bounds.max.is_none())
+                || !finite(bounds.min)
+                || !finite(bounds.max)
+                || matches!((bounds.min, bounds.max), (Some(lo), Some(hi)) if lo > hi)
+            {
+                return Err(GatewayError::Config(format!(
+                    "invalid clamp bounds for '{}'",
+                    name
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 单条改写记录，写入请求日志详情（param_policy_applied）
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ParamModification {
+    pub policy_id: String,
+    pub param: String,
+    /// clamped | stripped | renamed（renamed 时 to 为新参数名）
+    pub action: &'static str,
+    pub from: serde_json::Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ParamPolicy {
+    pub id: String,
+    pub model: Option<String>,
+    pub token_id: Option<String>,
+    pub rules: ParamPolicyRules,
+    pub enabled: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl ParamPolicy {
+    pub fn from_record(record: ParamPolicyRecord) -> Result<Self, GatewayError> {
+        let rules = serde_json::from_str(&record.rules_json).map_err(|e| {
+            GatewayError::Config(format!(
+                "param policy {} has invalid rules: {}",
+                record.id, e
+            ))
+        })?;
+        Ok(Self {
+            id: record.id,
+            model: record.model,
+            token_id: record.token_id,
+            rules,
+            enabled: record.enabled,
+            created_at: record.created_at,
+            updated_at: record.updated_at,
+        })
+    }
+
+    /// 模型可写上游模型名或 provider/model；令牌按 ID 匹配
+    pub fn matches(&self, token_id: &str, provider: &str, upstream_model: &str) -> bool {
+        let model_ok = self.model.as_deref().is_none_or(|m| {
+            m == upstream_model
+                || m.split_once('/')
+                    .is_some_and(|(p, rest)| p == provider && rest == upstream_model)
+        });
+        let token_ok = self.token_id.as_deref().is_none_or(|t| t == token_id);
+        self.enabled && model_ok && token_ok
+    }
+}
+
+fn clamp_value(value: &serde_json::Value, bounds: &ParamBounds) -> Option<serde_json::Value> {
+    let current = value.as_f64()?;
+    let mut clamped = current;
+    if let Some(hi) = bounds.max {
+        clamped = clamped.min(hi);
+    }
+    if let Some(lo) = bounds.min {
+        clamped = clamped.max(lo);
+    }
+    if clamped == current {
+        return None;
+    }
+    // 整数参数（如 max_tokens）保持整数：上限向下取整，下限向上取整
+    if value.is_i64() || value.is_u64() {
+        let rounded = if clamped < current {
+            clamped.floor()
+        } else {
+            clamped.ceil()
+        };
+        return Some(serde_json::json!(rounded as i64));
+    }
+    serde_json::Number::from_f64(clamped).map(serde_json::Value::Number)
+}
+
+/// 按顺序对请求参数应用策略，返回所做的改写
+fn apply_rules(
+    params: &mut serde_json::Map<String, serde_json::Value>,
+    policy_id: &str,
+    rules: &ParamPolicyRules,
+) -> Vec<ParamModification> {
+    let mut out = Vec::new();
+    for name in &rules.strip {
+        if let Some(from) = params.remove(name).filter(|v| !v.is_null()) {
+            out.push(ParamModification {
+                policy_id: policy_id.to_string(),
+                param: name.clone(),
+                action: "stripped",
+                from,
+                to: None,
+            });
+        }
+    }
+    for (name, bounds) in &rules.clamp {
+        let Some(value) = params.get_mut(name) else {
+            continue;
+        };
+        if let Some(to) = clamp_value(value, bounds) {
+            let from = std::mem::replace(value, to.clone());
+            out.push(ParamModification {
+                policy_id: policy_id.to_string(),
+                param: name.clone(),
+                action: "clamped",
+                from,
+                to: Some(to),
+            });
+        }
+    }
+    out
+}
+
+/// 推理模型不接受的采样参数
+const REASONING_UNSUPPORTED_PARAMS: &[&str] = &[
+    "temperature",
+    "top_p",
+    "presence_penalty",
+    "frequency_penalty",
+    "logprobs",
+    "top_logprobs",
+    "logit_bias",
+];
+
+/// o 系列推理模型：o1、o3-mini、o4-mini 等（可带 provider/ 前缀）
+fn is_reasoning_model(upstream_model: &str) -> bool {
+    let name = upstream_model.rsplit('/').next().unwrap_or(upstream_model);
+    let mut chars = name.chars();
+    matches!(chars.next(), Some('o' | 'O')) && chars.next().is_some_and(|c| c.is_ascii_digit())
+}
+
+/// 上游模型适用的参数转换规则及其来源标识；显式配置优先于内置推理模型规则
+fn model_param_rules(
+    config: &ServerConfig,
+    upstream_model: &str,
+) -> Option<(String, ModelParamRules)> {
+    if let Some(rules) = config.model_param_rules.get(upstream_model) {
+        return Some((format!("model_rules:{}", upstream_model), rules.clone()));
+    }
+    if config.reasoning_param_rules && is_reasoning_model(upstream_model) {
+        let rules = ModelParamRules {
+            strip: REASONING_UNSUPPORTED_PARAMS
+                .iter()
+                .map(|p| p.to_string())
+                .collect(),
+            rename: [(
+                "max_tokens".to_string(),
+                "max_completion_tokens".to_string(),
+            )]
+            .into(),
+        };
+        return Some(("builtin:reasoning".to_string(), rules));
+    }
+    None
+}
+
+/// 移除/改名上游不支持的参数；改名目标已存在时直接移除原参数
+fn apply_model_rules(
+    params: &mut serde_json::Map<String, serde_json::Value>,
+    source: &str,
+    rules: &ModelParamRules,
+) -> Vec<ParamModification> {
+    let governed = |name: &str| !PROTECTED_PARAMS.contains(&name);
+    let mut out = Vec::new();
+    for name in rules.strip.iter().filter(|n| governed(n)) {
+        if let Some(from) = params.remove(name).filter(|v| !v.is_null()) {
+            out.push(ParamModification {
+                policy_id: source.to_string(),
+                param: name.clone(),
+                action: "stripped",
+                from,
+                to: None,
+            });
+        }
+    }
+    for (name, target) in &rules.rename {
+        if !governed(name) || !governed(target) || name == target {
+            continue;
+        }
+        let Some(from) = params.remove(name).filter(|v| !v.is_null()) else {
+            continue;
+        };
+        let target_set = params.get(target).is_some_and(|v| !v.is_null());
+        if !target_set {
+            params.insert(target.clone(), from.clone());
+        }
+        out.push(ParamModification {
+            policy_id: source.to_string(),
+            param: name.clone(),
+            action: if target_set { "stripped" } else { "renamed" },
+            from,
+            to: (!target_set).then(|| serde_json::Value::String(target.clone())),
+        });
+    }
+    out
+}
+
+/// 先应用参数策略（按客户端参数名编写），再应用模型参数转换
+fn apply_policies_to_request(
+    policies: &[ParamPolicy],
+    model_rules: Option<&(String, ModelParamRules)>,
+    request: &mut ChatCompletionRequest,
+    top_k: &mut Option<u32>,
+) -> Result<Vec<ParamModification>, GatewayError> {
+    if policies.is_empty() && model_rules.is_none() {
+        return Ok(Vec::new());
+    }
+    let mut value = serde_json::to_value(&*request)
+        .map_err(|e| GatewayError::Config(format!("failed to apply param policy: {}", e)))?;
+    let Some(params) = value.as_object_mut() else {
+        return Ok(Vec::new());
+    };
+    // top_k 不在 OpenAI 请求体中，临时并入以便统一处理
+    if let Some(k) = *top_k {
+        params.insert("top_k".into(), serde_json::json!(k));
+    }
+    let mut modifications: Vec<ParamModification> = policies
+        .iter()
+        .flat_map(|p| apply_rules(params, &p.id, &p.rules))
+        .collect();
+    if let Some((source, rules)) = model_rules {
+        modifications.extend(apply_model_rules(params, source, rules));
+    }
+    if modifications.is_empty() {
+        return Ok(modifications);
+    }
+    *top_k = params
+        .remove("top_k")
+        .and_then(|v| v.as_u64())
+        .map(|k| k as u32);
+    *request = serde_json::from_value(value)
+        .map_err(|e| GatewayError::Config(format!("failed to apply param policy: {}", e)))?;
+    Ok(modifications)
+}
+
+/// 转发前应用所有匹配的参数策略及模型参数转换；有改写时返回 JSON 记录供写入日志
+pub async fn apply_param_policies(
+    app_state: &AppState,
+    request: &mut ChatCompletionRequest,
+    top_k: &mut Option<u32>,
+    token_id: &str,
+    provider: &str,
+    upstream_model: &str,
+) -> Result<Option<String>, GatewayError> {
+    let policies: Vec<ParamPolicy> = app_state
+        .log_store
+        .list_param_policies()
+        .await?
+        .into_iter()
+        .filter_map(|record| match ParamPolicy::from_record(record) {
+            Ok(p) => Some(p),
+            Err(e) => {
+                tracing::warn!("Skipping param policy: {}", e);
+                None
+            }
+        })
+        .filter(|p| p.matches(token_id, provider, upstream_model))
+        .collect();
+    let model_rules = model_param_rules(&app_state.config.server, upstream_model);
+    let modifications = apply_policies_to_request(&policies, model_rules.as_ref(), request, top_k)?;
+    if modifications.is_empty() {
+        return Ok(None);
+    }
+    tracing::info!(
+        provider,
+        model = upstream_model,
+        changes = modifications.len(),
+        "param policy modified request"
+    );
+    Ok(serde_json::to_string(&modifications).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn rules(raw: serde_json::Value) -> ParamPolicyRules {
+        serde_json::from_value(raw).unwrap()
+    }
+
+    #[test]
+    fn clamps_and_strips_parameters() {
+        let mut params = json!({
+            "temperature": 1.7,
+            "max_tokens": 9000,
+            "logprobs": true,
+            "top_p": 0.5
+        })
+        .as_object()
+        .unwrap()
+        .clone();
+        let r = rules(json!({
+            "clamp": {"temperature": {"max": 1.0}, "max_tokens": {"max": 4096.5}, "top_p": {"max": 0.9}},
+            "strip": ["logprobs", "seed"]
+        }));
+        let changes = apply_rules(&mut params, "pp_1", &r);
+        assert_eq!(params.get("temperature"), Some(&json!(1.0)));
+        assert_eq!(params.get("max_tokens"), Some(&json!(4096)));
+        assert_eq!(params.get("top_p"), Some(&json!(0.5)));
+        assert!(!params.contains_key("logprobs"));
+        let summary: Vec<_> = changes
+            .iter()
+            .map(|c| (c.param.as_str(), c.action))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("logprobs", "stripped"),
+                ("max_tokens", "clamped"),
+                ("temperature", "clamped")
+            ]
+        );
+    }
+
+    #[test]
+    fn policies_rewrite_typed_request_and_top_k() {
+        let mut request: ChatCompletionRequest = serde_json::from_value(json!({
+            "model": "gpt-4o",
+            "messages": [{"role": "user", "content": "hi"}],
+            "temperature": 1.8,
+            "logprobs": true,
+            "top_logprobs": 5
+        }))
+        .unwrap();
+        let mut top_k = Some(200);
+        let now = Utc::now();
+        let policy = ParamPolicy {
+            id: "pp_1".into(),
+            model: None,
+            token_id: None,
+            rules: rules(json!({
+                "clamp": {"temperature": {"max": 1.0}, "top_k": {"max": 40}},
+                "strip": ["logprobs", "top_logprobs"]
+            })),
+            enabled: true,
+            created_at: now,
+            updated_at: now,
+        };
+        let changes = apply_policies_to_request(&[policy], None, &mut request, &mut top_k).unwrap();
+        assert_eq!(changes.len(), 4);
+        assert_eq!(request.temperature, Some(1.0));
+        assert_eq!(request.logprobs, None);
+        assert_eq!(request.top_logprobs, None);
+        assert_eq!(request.model, "gpt-4o");
+        assert_eq!(top_k, Some(40));
+    }
+
+    #[test]
+    fn rules_validation_and_matching() {
+        assert!(rules(json!({})).validate().is_err());
+        assert!(rules(json!({"strip": ["model"]})).validate().is_err());
+        assert!(
+            rules(json!({"clamp": {"temperature": {"min": 1.0, "max": 0.5}}}))
+                .validate()
+                .is_err()
+        );
+        assert!(
+            rules(json!({"clamp": {"temperature": {}}}))
+                .validate()
+                .is_err()
+        );
+        assert!(
+            rules(json!({"clamp": {"temperature": {"max": 1.0}}, "strip": ["logprobs"]}))
+                .validate()
+                .is_ok()
+        );
+
+        let now = Utc::now();
+        let policy = ParamPolicy {
+            id: "pp_1".into(),
+            model: Some("openai/gpt-4o".into()),
+            token_id: None,
+            rules: ParamPolicyRules::default(),
+            enabled: true,
+            created_at: now,
+            updated_at: now,
+        };
+        assert!(policy.matches("atk_1", "openai", "gpt-4o"));
+        assert!(!policy.matches("atk_1", "azure", "gpt-4o"));
+        let scoped = ParamPolicy {
+            model: Some("gpt-4o".into()),
+            token_id: Some("atk_2".into()),
+            ..policy
+        };
+        assert!(scoped.matches("atk_2", "azure", "gpt-4o"));
+        assert!(!scoped.matches("atk_1", "azure", "gpt-4o"));
+    }
+
+    #[test]
+    fn reasoning_models_drop_sampling_params_and_rename_max_tokens() {
+        let mut config = ServerConfig::default();
+        assert!(is_reasoning_model("o3-mini"));
+        assert!(is_reasoning_model("openai/o1"));
+        assert!(!is_reasoning_model("gpt-4o"));
+        assert!(!is_reasoning_model("omni-moderation-latest"));
+        assert!(model_param_rules(&config, "gpt-4o").is_none());
+
+        let mut request: ChatCompletionRequest = serde_json::from_value(json!({
+            "model": "o3-mini",
+            "messages": [{"role": "user", "content": "hi"}],
+            "temperature": 0.2,
+            "top_p": 0.9,
+            "max_tokens": 512
+        }))
+        .unwrap();
+        let rules = model_param_rules(&config, "o3-mini");
+        let changes =
+            apply_policies_to_request(&[], rules.as_ref(), &mut request, &mut None).unwrap();
+        assert_eq!(request.temperature, None);
+        assert_eq!(request.top_p, None);
+        assert!(serde_json::to_value(&request).unwrap()["max_tokens"].is_null());
+        assert_eq!(request.max_completion_tokens, Some(512));
+        assert_eq!(changes.len(), 3);
+        assert!(changes.iter().all(|c| c.policy_id == "builtin:reasoning"));
+
+        // 显式配置替代内置规则；关闭内置规则后不再改写
+        config.model_param_rules.insert(
+            "o3-mini".into(),
+            ModelParamRules {
+                strip: vec!["top_p".into()],
+                rename: Default::default(),
+            },
+        );
+        let (source, rules) = model_param_rules(&config, "o3-mini").unwrap();
+        assert_eq!(
+            (source.as_str(), rules.strip.len()),
+            ("model_rules:o3-mini", 1)
+        );
+        config.model_param_rules.clear();
+        config.reasoning_param_rules = false;
+        assert!(model_param_rules(&config, "o3-mini").is_none());
+    }
+}
@@ -0,0 +1,255 @@
+//! 供应商级提示大小上限：`provider_config.max_prompt_bytes` / `max_prompt_tokens` 在分发前校验，
+//! 超出时直接返回 413 并说明上限，避免上游以含糊的错误拒绝。
+//! 同时按供应商统计提示与响应大小分布、超限拒绝与自动截断次数（本实例），经 /metrics 暴露。
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use axum::body::Body;
+use axum::response::Response;
+use futures_util::StreamExt;
+use serde::Serialize;
+
+use crate::config::settings::Provider;
+use crate::error::GatewayError;
+use crate::providers::openai::ChatCompletionRequest;
+use crate::server::chat_plan::estimate_prompt_tokens;
+
+/// 大小分布的桶上界（字节）：1 KiB 起按 4 倍递增至 16 MiB
+pub const SIZE_BUCKETS: [u64; 8] = [
+    1 << 10,
+    1 << 12,
+    1 << 14,
+    1 << 16,
+    1 << 18,
+    1 << 20,
+    1 << 22,
+    1 << 24,
+];
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct SizeHistogram {
+    /// 与 SIZE_BUCKETS 一一对应的累计计数（≤ 上界）
+    pub buckets: Vec<u64>,
+    pub sum: u64,
+    pub count: u64,
+}
+
+impl SizeHistogram {
+    fn observe(&mut self, bytes: u64) {
+        if self.buckets.is_empty() {
+            self.buckets = vec![0; SIZE_BUCKETS.len()];
+        }
+        for (bucket, bound) in self.buckets.iter_mut().zip(SIZE_BUCKETS) {
+            if bytes <= bound {
+                *bucket += 1;
+            }
+        }
+        self.sum += bytes;
+        self.count += 1;
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ProviderPayloadSizes {
+    pub provider: String,
+    pub prompt_bytes: SizeHistogram,
+    pub response_bytes: SizeHistogram,
+    /// 超出提示大小上限被拒绝的请求数
+    pub rejected: u64,
+    /// 因超出上下文窗口而自动截断的请求数
+    pub truncated: u64,
+}
+
+#[derive(Default)]
+pub struct PayloadSizeStats {
+    providers: Mutex<HashMap<String, ProviderPayloadSizes>>,
+}
+
+impl PayloadSizeStats {
+    fn update(&self, provider: &str, f: impl FnOnce(&mut ProviderPayloadSizes)) {
+        let mut providers = self.providers.lock().unwrap_or_else(|e| e.into_inner());
+        let entry = providers
+            .entry(provider.to_string())
+            .or_insert_with(|| ProviderPayloadSizes {
+                provider: provider.to_string(),
+                ..Default::default()
+            });
+        f(entry);
+    }
+
+    pub fn record_prompt(&self, provider: &str, bytes: u64) {
+        self.update(provider, |p| p.prompt_bytes.observe(bytes));
+    }
+
+    pub fn record_response(&self, provider: &str, bytes: u64) {
+        self.update(provider, |p| p.response_bytes.observe(bytes));
+    }
+
+    pub fn record_rejected(&self, provider: &str) {
+        self.update(provider, |p| p.rejected += 1);
+    }
+
+    pub fn record_truncated(&self, provider: &str) {
+        self.update(provider, |p| p.truncated += 1);
+    }
+
+    /// 包装流式响应：累计实际转发的字节数，响应体结束（或客户端断开）时计入一次响应大小
+    pub fn measure_stream_response(
+        self: &Arc<Self>,
+        response: Response,
+        provider: String,
+    ) -> Response {
+        let mut counter = StreamBytes {
+            stats: self.clone(),
+            provider,
+            bytes: 0,
+        };
+        let (parts, body) = response.into_parts();
+        let stream = body.into_data_stream().map(move |chunk| {
+            if let Ok(bytes) = &chunk {
+                counter.add(bytes.len());
+            }
+            chunk
+        });
+        Response::from_parts(parts, Body::from_stream(stream))
+    }
+
+    /// 出现过请求的供应商（按供应商名排序）
+    pub fn snapshot(&self) -> Vec<ProviderPayloadSizes> {
+        let providers = self.providers.lock().unwrap_or_else(|e| e.into_inner());
+        let mut out: Vec<_> = providers.values().cloned().collect();
+        out.sort_by(|a, b| a.provider.cmp(&b.provider));
+        out
+    }
+}
+
+struct StreamBytes {
+    stats: Arc<PayloadSizeStats>,
+    provider: String,
+    bytes: u64,
+}
+
+impl StreamBytes {
+    fn add(&mut self, len: usize) {
+        self.bytes += len as u64;
+    }
+}
+
+impl Drop for StreamBytes {
+    fn drop(&mut self) {
+        self.stats.record_response(&self.provider, self.bytes);
+    }
+}
+
+/// 提示（messages 序列化后）的字节数与估算 token 数
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PromptSize {
+    pub bytes: u64,
+    pub estimated_tokens: u32,
+}
+
+impl PromptSize {
+    pub fn of(request: &ChatCompletionRequest) -> Self {
+        Self {
+            bytes: serde_json::to_vec(&request.messages).map_or(0, |v| v.len() as u64),
+            estimated_tokens: estimate_prompt_tokens(request),
+        }
+    }
+}
+
+/// 校验提示是否超出供应商配置的上限；未配置或配置为 0 时不限制
+pub fn check_prompt(provider: &Provider, size: PromptSize) -> Result<(), GatewayError> {
+    let config = &provider.provider_config;
+    if let Some(max) = config.max_prompt_bytes.filter(|max| *max > 0)
+        && size.bytes > max
+    {
+        return Err(GatewayError::PayloadTooLarge(format!(
+            "prompt is {} bytes, exceeding the {}-byte limit of provider '{}'; shorten the messages or choose another provider",
+            size.bytes, max, provider.name
+        )));
+    }
+    if let Some(max) = config.max_prompt_tokens.filter(|max| *max > 0)
+        && size.estimated_tokens > max
+    {
+        return Err(GatewayError::PayloadTooLarge(format!(
+            "prompt is ~{} tokens, exceeding the {}-token limit of provider '{}'; shorten the messages or choose another provider",
+            size.estimated_tokens, max, provider.name
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn provider(config: serde_json::Value) -> Provider {
+        serde_json::from_value(json!({
+            "name": "p1",
+            "api_type": "openai",
+            "base_url": "https://api.example.com/v1",
+            "api_keys": [],
+            "provider_config": config,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn rejects_prompts_over_configured_limits() {
+        let request: ChatCompletionRequest = serde_json::from_value(json!({
+            "model": "m1",
+            "messages": [{"role": "user", "content": "x".repeat(400)}],
+        }))
+        .unwrap();
+        let size = PromptSize::of(&request);
+        assert!(size.bytes > 400);
+        assert!(size.estimated_tokens > 100);
+
+        assert!(check_prompt(&provider(json!({})), size).is_ok());
+        assert!(check_prompt(&provider(json!({"max_prompt_bytes": 0})), size).is_ok());
+        let err = check_prompt(&provider(json!({"max_prompt_bytes": 256})), size).unwrap_err();
+        assert_eq!(err.status_code(), axum::http::StatusCode::PAYLOAD_TOO_LARGE);
+        assert!(err.to_string().contains("256-byte limit of provider 'p1'"));
+        let err = check_prompt(&provider(json!({"max_prompt_tokens": 50})), size).unwrap_err();
+        assert!(err.to_string().contains("50-token limit"));
+        assert!(check_prompt(&provider(json!({"max_prompt_tokens": 4096})), size).is_ok());
+    }
+
+    #[test]
+    fn histograms_are_cumulative_per_provider() {
+        let stats = PayloadSizeStats::default();
+        stats.record_prompt("b", 100);
+        stats.record_prompt("b", 5000);
+        stats.record_response("b", 1 << 25);
+        stats.record_rejected("a");
+        stats.record_truncated("b");
+
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot[0].provider, "a");
+        assert_eq!(snapshot[0].rejected, 1);
+        let b = &snapshot[1];
+        assert_eq!(b.prompt_bytes.buckets[..3], [1, 1, 2]);
+        assert_eq!(b.prompt_bytes.sum, 5100);
+        assert_eq!(b.prompt_bytes.count, 2);
+        assert_eq!(b.response_bytes.buckets, vec![0; SIZE_BUCKETS.len()]);
+        assert_eq!(b.response_bytes.count, 1);
+        assert_eq!(b.truncated, 1);
+    }
+
+    #[tokio::test]
+    async fn stream_response_size_is_recorded_once_body_ends() {
+        let stats = Arc::new(PayloadSizeStats::default());
+        let response = Response::new(Body::from("data: hello\n\n"));
+        let measured = stats.measure_stream_response(response, "p1".into());
+        assert!(stats.snapshot().is_empty());
+        let body = axum::body::to_bytes(measured.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(body.len(), 13);
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot[0].response_bytes.count, 1);
+        assert_eq!(snapshot[0].response_bytes.sum, 13);
+    }
+}
@@ -1,3 +1,4 @@
+use std::cmp::Ordering;
 use std::collections::{HashMap, HashSet};
 
 use crate::error::GatewayError;
@@ -7,6 +8,7 @@ use serde::Serialize;
 
 use super::AppState;
 use super::model_types;
+use super::pagination::{SortFields, SortOrder};
 
 pub(crate) struct ResolvedModelPricing {
     pub billing_model: String,
@@ -76,6 +78,44 @@ pub(crate) fn normalized_price_metadata(
     (source, status, synced_at, expires_at)
 }
 
+/// 价格列表的排序字段；相同值按 (provider, model) 排列
+pub(crate) const MODEL_PRICE_SORT_FIELDS: SortFields = SortFields {
+    allowed: &[
+        "provider",
+        "model",
+        "prompt_price_per_million",
+        "completion_price_per_million",
+        "synced_at",
+    ],
+    default: "provider",
+    default_order: SortOrder::Asc,
+};
+
+/// 与 MODEL_PRICE_SORT_FIELDS 对应的内存排序（用于合并了缓存模型的管理端列表）
+pub(crate) fn compare_model_price_views(
+    sort: &str,
+    a: &ModelPriceView,
+    b: &ModelPriceView,
+) -> Ordering {
+    let price = |x: Option<f64>, y: Option<f64>| {
+        x.unwrap_or(f64::NEG_INFINITY)
+            .total_cmp(&y.unwrap_or(f64::NEG_INFINITY))
+    };
+    let primary = match sort {
+        "model" => a.model.cmp(&b.model),
+        "prompt_price_per_million" => price(a.prompt_price_per_million, b.prompt_price_per_million),
+        "completion_price_per_million" => price(
+            a.completion_price_per_million,
+            b.completion_price_per_million,
+        ),
+        "synced_at" => a.synced_at.cmp(&b.synced_at),
+        _ => Ordering::Equal,
+    };
+    primary
+        .then_with(|| a.provider.cmp(&b.provider))
+        .then_with(|| a.model.cmp(&b.model))
+}
+
 pub(crate) fn model_price_view_from_record(record: ModelPriceRecord) -> ModelPriceView {
     let record = normalize_model_price_record(record);
     let (model_type, model_types) =
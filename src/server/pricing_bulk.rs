@@ -0,0 +1,377 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::GatewayError;
+use crate::logging::{ModelPriceRecord, ModelPriceStatus, ModelPriceUpsert};
+
+use super::AppState;
+
+/// 单次调整的百分比上限（+1000% 即 11 倍）
+const MAX_ADJUST_PERCENT: f64 = 1000.0;
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum PriceField {
+    #[default]
+    Both,
+    Prompt,
+    Completion,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(tag = "operation", rename_all = "snake_case")]
+pub(crate) enum BulkPriceOperation {
+    /// 按百分比调整已有价格；provider/model_prefix 为空表示不限
+    Adjust {
+        #[serde(default)]
+        provider: Option<String>,
+        #[serde(default)]
+        model_prefix: Option<String>,
+        percent: f64,
+        #[serde(default)]
+        field: PriceField,
+    },
+    /// 将源供应商的价格复制到目标供应商的同名模型（仅限目标已缓存的模型）
+    Copy {
+        source_provider: String,
+        target_provider: String,
+        /// 为 false 时跳过目标已有价格的模型
+        #[serde(default)]
+        overwrite: bool,
+    },
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub(crate) struct BulkPriceRequest {
+    #[serde(flatten)]
+    pub operation: BulkPriceOperation,
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+impl BulkPriceRequest {
+    /// 用于日志的供应商（调整时为筛选供应商，复制时为目标供应商）
+    pub fn log_provider(&self) -> Option<String> {
+        match &self.operation {
+            BulkPriceOperation::Adjust { provider, .. } => provider.clone(),
+            BulkPriceOperation::Copy {
+                target_provider, ..
+            } => Some(target_provider.clone()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub(crate) struct BulkPriceChange {
+    pub provider: String,
+    pub model: String,
+    pub old_prompt_price_per_million: Option<f64>,
+    pub old_completion_price_per_million: Option<f64>,
+    pub prompt_price_per_million: f64,
+    pub completion_price_per_million: f64,
+    pub currency: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub(crate) struct BulkPriceSkip {
+    pub provider: String,
+    pub model: String,
+    pub reason: &'static str,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct BulkPriceReport {
+    pub dry_run: bool,
+    /// 受影响（将被/已被写入）的行数
+    pub affected: usize,
+    pub changes: Vec<BulkPriceChange>,
+    pub skipped: Vec<BulkPriceSkip>,
+}
+
+/// 价格保留 6 位小数，避免百分比运算引入浮点噪声
+fn round_price(value: f64) -> f64 {
+    (value * 1_000_000.0).round() / 1_000_000.0
+}
+
+fn change_for(old: Option<&ModelPriceRecord>, new: &ModelPriceUpsert) -> BulkPriceChange {
+    BulkPriceChange {
+        provider: new.provider.clone(),
+        model: new.model.clone(),
+        old_prompt_price_per_million: old.map(|r| r.prompt_price_per_million),
+        old_completion_price_per_million: old.map(|r| r.completion_price_per_million),
+        prompt_price_per_million: new.prompt_price_per_million,
+        completion_price_per_million: new.completion_price_per_million,
+        currency: new.currency.clone(),
+    }
+}
+
+/// 按百分比调整价格；结果均记为手动价格，避免被自动同步覆盖
+pub(crate) fn plan_adjust(
+    records: &[ModelPriceRecord],
+    model_prefix: Option<&str>,
+    percent: f64,
+    field: PriceField,
+) -> Result<Vec<ModelPriceUpsert>, GatewayError> {
+    if !percent.is_finite() || percent <= -100.0 || percent > MAX_ADJUST_PERCENT {
+        return Err(GatewayError::Config(format!(
+            "percent must be greater than -100 and at most {}",
+            MAX_ADJUST_PERCENT
+        )));
+    }
+    let factor = 1.0 + percent / 100.0;
+    let scale = |value: f64, applies: bool| {
+        if applies {
+            round_price(value * factor)
+        } else {
+            value
+        }
+    };
+    Ok(records
+        .iter()
+        .filter(|r| !matches!(r.status, ModelPriceStatus::Missing))
+        .filter(|r| model_prefix.is_none_or(|p| r.model.starts_with(p)))
+        .filter_map(|r| {
+            let prompt = scale(r.prompt_price_per_million, field != PriceField::Completion);
+            let completion = scale(r.completion_price_per_million, field != PriceField::Prompt);
+            // 价格不变（如 0 价格）的行不计入变更
+            (prompt != r.prompt_price_per_million || completion != r.completion_price_per_million)
+                .then(|| {
+                    ModelPriceUpsert::manual(
+                        r.provider.clone(),
+                        r.model.clone(),
+                        prompt,
+                        completion,
+                        r.currency.clone(),
+                        r.model_type.clone(),
+                    )
+                })
+        })
+        .collect())
+}
+
+/// 复制价格：目标未缓存的模型跳过；overwrite 为 false 时目标已有价格的模型也跳过
+pub(crate) fn plan_copy(
+    source: &[ModelPriceRecord],
+    target: &[ModelPriceRecord],
+    target_provider: &str,
+    target_models: &HashSet<String>,
+    overwrite: bool,
+) -> (Vec<ModelPriceUpsert>, Vec<BulkPriceSkip>) {
+    let existing: HashMap<&str, &ModelPriceRecord> = target
+        .iter()
+        .filter(|r| !matches!(r.status, ModelPriceStatus::Missing))
+        .map(|r| (r.model.as_str(), r))
+        .collect();
+    let mut out = Vec::new();
+    let mut skipped = Vec::new();
+    for record in source
+        .iter()
+        .filter(|r| !matches!(r.status, ModelPriceStatus::Missing))
+    {
+        let skip = |reason| BulkPriceSkip {
+            provider: target_provider.to_string(),
+            model: record.model.clone(),
+            reason,
+        };
+        if !target_models.contains(&record.model) {
+            skipped.push(skip("model not found under target provider"));
+            continue;
+        }
+        let current = existing.get(record.model.as_str());
+        if current.is_some() && !overwrite {
+            skipped.push(skip("target price already set"));
+            continue;
+        }
+        out.push(ModelPriceUpsert::manual(
+            target_provider,
+            record.model.clone(),
+            record.prompt_price_per_million,
+            record.completion_price_per_million,
+            record.currency.clone(),
+            current
+                .and_then(|r| r.model_type.clone())
+                .or_else(|| record.model_type.clone()),
+        ));
+    }
+    (out, skipped)
+}
+
+async fn ensure_provider(app_state: &AppState, provider: &str) -> Result<(), GatewayError> {
+    if app_state
+        .providers
+        .provider_exists(provider)
+        .await
+        .map_err(GatewayError::Db)?
+    {
+        Ok(())
+    } else {
+        Err(GatewayError::NotFound(format!(
+            "provider '{}' not found",
+            provider
+        )))
+    }
+}
+
+/// 计算批量变更；非 dry_run 时在单个事务中写入
+pub(crate) async fn run(
+    app_state: &AppState,
+    request: BulkPriceRequest,
+) -> Result<BulkPriceReport, GatewayError> {
+    let store = &app_state.log_store;
+    // existing：写入前的价格，用于展示变更前后对比
+    let (upserts, skipped, existing) = match &request.operation {
+        BulkPriceOperation::Adjust {
+            provider,
+            model_prefix,
+            percent,
+            field,
+        } => {
+            let provider = provider.as_deref().map(str::trim).filter(|p| !p.is_empty());
+            if let Some(p) = provider {
+                ensure_provider(app_state, p).await?;
+            }
+            let records = store
+                .list_model_prices(provider)
+                .await
+                .map_err(GatewayError::Db)?;
+            let prefix = model_prefix.as_deref().filter(|p| !p.is_empty());
+            let upserts = plan_adjust(&records, prefix, *percent, *field)?;
+            (upserts, Vec::new(), records)
+        }
+        BulkPriceOperation::Copy {
+            source_provider,
+            target_provider,
+            overwrite,
+        } => {
+            if source_provider == target_provider {
+                return Err(GatewayError::Config(
+                    "source_provider and target_provider must differ".into(),
+                ));
+            }
+            ensure_provider(app_state, source_provider).await?;
+            ensure_provider(app_state, target_provider).await?;
+            let source = store
+                .list_model_prices(Some(source_provider))
+                .await
+                .map_err(GatewayError::Db)?;
+            let target = store
+                .list_model_prices(Some(target_provider))
+                .await
+                .map_err(GatewayError::Db)?;
+            let target_models: HashSet<String> =
+                super::model_cache::get_cached_models_for_provider(app_state, target_provider)
+                    .await
+                    .map_err(GatewayError::Db)?
+                    .into_iter()
+                    .map(|m| m.id)
+                    .collect();
+            let (upserts, skipped) = plan_copy(
+                &source,
+                &target,
+                target_provider,
+                &target_models,
+                *overwrite,
+            );
+            (upserts, skipped, target)
+        }
+    };
+
+    let old: HashMap<(&str, &str), &ModelPriceRecord> = existing
+        .iter()
+        .map(|r| ((r.provider.as_str(), r.model.as_str()), r))
+        .collect();
+    let changes: Vec<BulkPriceChange> = upserts
+        .iter()
+        .map(|u| {
+            change_for(
+                old.get(&(u.provider.as_str(), u.model.as_str())).copied(),
+                u,
+            )
+        })
+        .collect();
+    if !request.dry_run && !upserts.is_empty() {
+        store
+            .upsert_model_prices(upserts)
+            .await
+            .map_err(GatewayError::Db)?;
+    }
+    Ok(BulkPriceReport {
+        dry_run: request.dry_run,
+        affected: changes.len(),
+        changes,
+        skipped,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logging::ModelPriceSource;
+
+    fn record(provider: &str, model: &str, prompt: f64, completion: f64) -> ModelPriceRecord {
+        ModelPriceRecord {
+            provider: provider.into(),
+            model: model.into(),
+            prompt_price_per_million: prompt,
+            completion_price_per_million: completion,
+            currency: Some("USD".into()),
+            model_type: Some("chat".into()),
+            source: ModelPriceSource::Manual,
+            status: ModelPriceStatus::Active,
+            synced_at: None,
+            expires_at: None,
+        }
+    }
+
+    #[test]
+    fn adjust_scales_selected_fields_and_filters_by_prefix() {
+        let mut missing = record("anthropic", "claude-x", 0.0, 0.0);
+        missing.status = ModelPriceStatus::Missing;
+        let records = vec![
+            record("anthropic", "claude-a", 3.0, 15.0),
+            record("anthropic", "other", 1.0, 1.0),
+            missing,
+        ];
+        let plan = plan_adjust(&records, Some("claude"), 10.0, PriceField::Both).unwrap();
+        assert_eq!(plan.len(), 1);
+        assert_eq!(plan[0].prompt_price_per_million, 3.3);
+        assert_eq!(plan[0].completion_price_per_million, 16.5);
+
+        let plan = plan_adjust(&records, None, -50.0, PriceField::Completion).unwrap();
+        assert_eq!(plan.len(), 2);
+        assert_eq!(plan[0].prompt_price_per_million, 3.0);
+        assert_eq!(plan[0].completion_price_per_million, 7.5);
+
+        assert!(plan_adjust(&records, None, -100.0, PriceField::Both).is_err());
+        assert!(plan_adjust(&records, None, f64::NAN, PriceField::Both).is_err());
+    }
+
+    #[test]
+    fn copy_skips_uncached_and_existing_unless_overwrite() {
+        let source = vec![
+            record("a", "m1", 1.0, 2.0),
+            record("a", "m2", 3.0, 4.0),
+            record("a", "m3", 5.0, 6.0),
+        ];
+        let target = vec![record("b", "m2", 9.0, 9.0)];
+        let cached: HashSet<String> = ["m1".to_string(), "m2".to_string()].into();
+
+        let (plan, skipped) = plan_copy(&source, &target, "b", &cached, false);
+        assert_eq!(plan.len(), 1);
+        assert_eq!(plan[0].provider, "b");
+        assert_eq!(plan[0].model, "m1");
+        assert_eq!(
+            skipped.iter().map(|s| s.reason).collect::<Vec<_>>(),
+            vec![
+                "target price already set",
+                "model not found under target provider"
+            ]
+        );
+
+        let (plan, skipped) = plan_copy(&source, &target, "b", &cached, true);
+        assert_eq!(plan.len(), 2);
+        assert_eq!(plan[1].prompt_price_per_million, 3.0);
+        assert_eq!(skipped.len(), 1);
+    }
+}
@@ -758,7 +758,6 @@ mod tests {
     use crate::logging::{DatabaseLogger, ModelPriceSource, ModelPriceStatus, ModelPriceUpsert};
     use crate::providers::openai::Model;
     use crate::server::AppState;
-    use crate::server::login::LoginManager;
     use chrono::{Duration, Utc};
     use std::sync::Arc;
     use tempfile::tempdir;
@@ -925,22 +924,7 @@ mod tests {
             .await
             .unwrap();
 
-        let state = Arc::new(AppState {
-            config: settings,
-            load_balancer_state: Arc::new(crate::routing::LoadBalancerState::default()),
-            log_store: logger.clone(),
-            model_cache: logger.clone(),
-            providers: logger.clone(),
-            token_store: logger.clone(),
-            favorites_store: logger.clone(),
-            organizations: logger.clone(),
-            login_manager: Arc::new(LoginManager::new(logger.clone())),
-            user_store: logger.clone(),
-            refresh_token_store: logger.clone(),
-            password_reset_token_store: logger.clone(),
-            balance_store: logger.clone(),
-            subscription_store: logger,
-        });
+        let state = crate::server::test_harness::app_state(settings, logger.clone());
 
         Harness { _dir: dir, state }
     }
@@ -0,0 +1,212 @@
+//! 提示画像（可选，`server.prompt_profiling`）：按字符脚本粗略识别用户消息的语言，
+//! 并按代码特征区分 code/prose。只把标签写入请求日志，不保存原文。
+
+use serde_json::Value;
+
+use crate::logging::types::PromptProfile;
+use crate::providers::openai::ChatCompletionRequest;
+
+/// 参与识别的最大字符数，避免超长提示拖慢请求
+const MAX_SAMPLE_CHARS: usize = 4000;
+/// 非空行中“像代码”的行占比达到该值时视为代码
+const CODE_LINE_RATIO: f64 = 0.4;
+
+pub fn detect(request: &ChatCompletionRequest) -> PromptProfile {
+    let text = user_text(request);
+    PromptProfile {
+        language: detect_language(&text).map(str::to_string),
+        category: detect_category(&text).map(str::to_string),
+    }
+}
+
+/// 拼接所有 user 消息的文本部分（截断到 MAX_SAMPLE_CHARS）
+fn user_text(request: &ChatCompletionRequest) -> String {
+    let messages = serde_json::to_value(&request.messages).unwrap_or(Value::Null);
+    let mut text = String::new();
+    for message in messages.as_array().into_iter().flatten() {
+        if message.get("role").and_then(Value::as_str) != Some("user") {
+            continue;
+        }
+        match message.get("content") {
+            Some(Value::String(content)) => push_text(&mut text, content),
+            Some(Value::Array(parts)) => {
+                for part in parts {
+                    if let Some(content) = part.get("text").and_then(Value::as_str) {
+                        push_text(&mut text, content);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    text.chars().take(MAX_SAMPLE_CHARS).collect()
+}
+
+fn push_text(buf: &mut String, text: &str) {
+    if !buf.is_empty() {
+        buf.push('\n');
+    }
+    buf.push_str(text);
+}
+
+#[derive(Default)]
+struct ScriptCounts {
+    han: usize,
+    kana: usize,
+    hangul: usize,
+    cyrillic: usize,
+    arabic: usize,
+    latin: usize,
+    other: usize,
+}
+
+fn detect_language(text: &str) -> Option<&'static str> {
+    let mut counts = ScriptCounts::default();
+    for c in text.chars().filter(|c| c.is_alphabetic()) {
+        match c as u32 {
+            0x3040..=0x30FF | 0x31F0..=0x31FF => counts.kana += 1,
+            0xAC00..=0xD7AF | 0x1100..=0x11FF | 0x3130..=0x318F => counts.hangul += 1,
+            0x4E00..=0x9FFF | 0x3400..=0x4DBF | 0xF900..=0xFAFF => counts.han += 1,
+            0x0400..=0x04FF => counts.cyrillic += 1,
+            0x0600..=0x06FF | 0x0750..=0x077F => counts.arabic += 1,
+            _ if c.is_ascii_alphabetic() || ('\u{00C0}'..='\u{024F}').contains(&c) => {
+                counts.latin += 1
+            }
+            _ => counts.other += 1,
+        }
+    }
+    // 日文夹杂汉字、韩文偶有汉字：出现足量假名/谚文时优先判定
+    let cjk = counts.han + counts.kana + counts.hangul;
+    if cjk > 0 && cjk * 5 >= counts.latin {
+        if counts.kana * 10 >= cjk {
+            return Some("ja");
+        }
+        if counts.hangul >= counts.han {
+            return Some("ko");
+        }
+        return Some("zh");
+    }
+    [
+        (counts.latin, "latin"),
+        (counts.cyrillic, "cyrillic"),
+        (counts.arabic, "arabic"),
+        (counts.other, "other"),
+    ]
+    .into_iter()
+    .filter(|(n, _)| *n > 0)
+    .max_by_key(|(n, _)| *n)
+    .map(|(_, label)| label)
+}
+
+fn detect_category(text: &str) -> Option<&'static str> {
+    if text.trim().is_empty() {
+        return None;
+    }
+    if text.contains("```") {
+        return Some("code");
+    }
+    let lines: Vec<&str> = text
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .collect();
+    let code_lines = lines.iter().filter(|l| looks_like_code(l)).count();
+    if lines.len() >= 2 && code_lines as f64 / lines.len() as f64 >= CODE_LINE_RATIO {
+        Some("code")
+    } else {
+        Some("prose")
+    }
+}
+
+fn looks_like_code(line: &str) -> bool {
+    const PREFIXES: &[&str] = &[
+        "fn ",
+        "pub ",
+        "def ",
+        "class ",
+        "import ",
+        "from ",
+        "use ",
+        "let ",
+        "const ",
+        "var ",
+        "function ",
+        "return ",
+        "if (",
+        "for (",
+        "while (",
+        "#include",
+        "package ",
+        "SELECT ",
+        "//",
+        "/*",
+    ];
+    PREFIXES.iter().any(|p| line.starts_with(p))
+        || line.ends_with(';')
+        || line.ends_with('{')
+        || line == "}"
+        || line.contains("=>")
+        || line.contains("->")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(messages: Value) -> ChatCompletionRequest {
+        serde_json::from_value(serde_json::json!({ "model": "m", "messages": messages })).unwrap()
+    }
+
+    fn profile(content: &str) -> PromptProfile {
+        detect(&request(serde_json::json!([
+            { "role": "system", "content": "You are a helpful assistant." },
+            { "role": "user", "content": content }
+        ])))
+    }
+
+    #[test]
+    fn detects_language_from_user_messages() {
+        assert_eq!(
+            profile("请帮我总结这段文字").language.as_deref(),
+            Some("zh")
+        );
+        assert_eq!(
+            profile("この文章を要約してください").language.as_deref(),
+            Some("ja")
+        );
+        assert_eq!(
+            profile("이 글을 요약해 주세요").language.as_deref(),
+            Some("ko")
+        );
+        assert_eq!(
+            profile("Привет, как дела?").language.as_deref(),
+            Some("cyrillic")
+        );
+        assert_eq!(
+            profile("Summarize this text").language.as_deref(),
+            Some("latin")
+        );
+        assert_eq!(profile("12345").language, None);
+    }
+
+    #[test]
+    fn detects_code_versus_prose() {
+        assert_eq!(
+            profile("Tell me a story about a cat.").category.as_deref(),
+            Some("prose")
+        );
+        assert_eq!(
+            profile("Why does this fail?\n```rust\nlet x = 1;\n```")
+                .category
+                .as_deref(),
+            Some("code")
+        );
+        assert_eq!(
+            profile("fn main() {\n    println!(\"hi\");\n}")
+                .category
+                .as_deref(),
+            Some("code")
+        );
+        assert_eq!(profile("").category, None);
+    }
+}
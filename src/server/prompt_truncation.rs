@@ -0,0 +1,200 @@
+use async_openai::types::ChatCompletionRequestMessage;
+use serde::Serialize;
+
+use crate::admin::ClientToken;
+use crate::config::settings::ServerConfig;
+use crate::error::GatewayError;
+use crate::providers::openai::ChatCompletionRequest;
+use crate::server::chat_plan::estimate_prompt_tokens;
+
+/// 响应头：本次请求因超出上下文窗口被丢弃的最早消息条数
+pub const TRUNCATED_HEADER: &str = "x-gateway-prompt-truncated";
+
+/// 一次自动截断的结果，写入请求日志详情（prompt_truncation）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PromptTruncation {
+    pub context_window: u32,
+    /// 为输出预留的 token（max_completion_tokens / max_tokens）
+    pub reserved_completion_tokens: u32,
+    pub removed_messages: usize,
+    pub prompt_tokens_before: u32,
+    pub prompt_tokens_after: u32,
+}
+
+impl PromptTruncation {
+    pub fn log_value(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+/// 上游模型的上下文窗口；未配置时不截断
+pub fn context_window(config: &ServerConfig, upstream_model: &str) -> Option<u32> {
+    config
+        .model_context_windows
+        .get(upstream_model)
+        .copied()
+        .filter(|window| *window > 0)
+}
+
+fn is_pinned(message: &ChatCompletionRequestMessage) -> bool {
+    matches!(
+        message,
+        ChatCompletionRequestMessage::System(_) | ChatCompletionRequestMessage::Developer(_)
+    )
+}
+
+/// 最早一条可丢弃的消息：system/developer 指令与最后一条消息始终保留
+fn oldest_removable(messages: &[ChatCompletionRequestMessage]) -> Option<usize> {
+    let last = messages.len().checked_sub(1)?;
+    messages[..last].iter().position(|m| !is_pinned(m))
+}
+
+/// 提示估算值超过（上下文窗口 - 输出预留）时，从最早的对话消息开始丢弃，直到落入窗口；
+/// 丢弃后开头残留的 tool 结果（其 tool_calls 已被丢弃）一并移除。
+/// 只保留指令与最后一条消息仍超出时返回错误，而不是交给上游失败。
+#[allow(deprecated)]
+pub fn truncate_request(
+    request: &mut ChatCompletionRequest,
+    context_window: u32,
+) -> Result<Option<PromptTruncation>, GatewayError> {
+    let reserved = request
+        .max_completion_tokens
+        .or(request.max_tokens)
+        .unwrap_or(0);
+    let budget = context_window.saturating_sub(reserved);
+    let before = estimate_prompt_tokens(request);
+    if before <= budget {
+        return Ok(None);
+    }
+    let mut removed = 0;
+    while estimate_prompt_tokens(request) > budget {
+        let Some(index) = oldest_removable(&request.messages) else {
+            return Err(GatewayError::Config(format!(
+                "prompt (~{} tokens) exceeds the context window of model '{}' ({} tokens, {} reserved for completion) even after dropping earlier messages",
+                estimate_prompt_tokens(request),
+                request.model,
+                context_window,
+                reserved
+            )));
+        };
+        request.messages.remove(index);
+        removed += 1;
+    }
+    while let Some(index) = oldest_removable(&request.messages)
+        && matches!(
+            request.messages[index],
+            ChatCompletionRequestMessage::Tool(_)
+        )
+    {
+        request.messages.remove(index);
+        removed += 1;
+    }
+    Ok(Some(PromptTruncation {
+        context_window,
+        reserved_completion_tokens: reserved,
+        removed_messages: removed,
+        prompt_tokens_before: before,
+        prompt_tokens_after: estimate_prompt_tokens(request),
+    }))
+}
+
+/// 令牌开启 auto_truncate_prompt 且上游模型配置了上下文窗口时截断请求
+pub fn apply(
+    config: &ServerConfig,
+    token: &ClientToken,
+    upstream_model: &str,
+    request: &mut ChatCompletionRequest,
+) -> Result<Option<PromptTruncation>, GatewayError> {
+    if !token.auto_truncate_prompt {
+        return Ok(None);
+    }
+    let Some(window) = context_window(config, upstream_model) else {
+        return Ok(None);
+    };
+    let truncation = truncate_request(request, window)?;
+    if let Some(truncation) = truncation.as_ref() {
+        tracing::info!(
+            token_id = %token.id,
+            model = upstream_model,
+            removed_messages = truncation.removed_messages,
+            prompt_tokens_before = truncation.prompt_tokens_before,
+            prompt_tokens_after = truncation.prompt_tokens_after,
+            context_window = window,
+            "truncated prompt to fit the model context window"
+        );
+    }
+    Ok(truncation)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn request(messages: serde_json::Value, max_tokens: Option<u32>) -> ChatCompletionRequest {
+        serde_json::from_value(json!({
+            "model": "m1",
+            "messages": messages,
+            "max_completion_tokens": max_tokens,
+        }))
+        .unwrap()
+    }
+
+    fn roles(request: &ChatCompletionRequest) -> Vec<String> {
+        serde_json::to_value(&request.messages)
+            .unwrap()
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|m| m["role"].as_str().unwrap().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn drops_oldest_turns_and_keeps_instructions() {
+        let long = "x".repeat(400);
+        let mut req = request(
+            json!([
+                {"role": "system", "content": "be brief"},
+                {"role": "user", "content": long},
+                {"role": "assistant", "content": long},
+                {"role": "user", "content": "latest question"}
+            ]),
+            Some(50),
+        );
+        let fits = estimate_prompt_tokens(&req);
+        assert_eq!(truncate_request(&mut req.clone(), fits + 50).unwrap(), None);
+
+        let truncation = truncate_request(&mut req, 150).unwrap().unwrap();
+        assert_eq!(truncation.removed_messages, 2);
+        assert_eq!(truncation.reserved_completion_tokens, 50);
+        assert!(truncation.prompt_tokens_after <= 100);
+        assert_eq!(roles(&req), vec!["system", "user"]);
+    }
+
+    #[test]
+    fn orphaned_tool_results_are_removed() {
+        let long = "x".repeat(400);
+        let mut req = request(
+            json!([
+                {"role": "assistant", "content": null, "tool_calls": [{
+                    "id": "call_1",
+                    "type": "function",
+                    "function": {"name": "lookup", "arguments": long}
+                }]},
+                {"role": "tool", "tool_call_id": "call_1", "content": "42"},
+                {"role": "user", "content": "thanks"}
+            ]),
+            None,
+        );
+        let truncation = truncate_request(&mut req, 60).unwrap().unwrap();
+        assert_eq!(truncation.removed_messages, 2);
+        assert_eq!(roles(&req), vec!["user"]);
+    }
+
+    #[test]
+    fn fails_when_last_message_alone_is_too_long() {
+        let mut req = request(json!([{"role": "user", "content": "x".repeat(4000)}]), None);
+        assert!(truncate_request(&mut req, 100).is_err());
+    }
+}
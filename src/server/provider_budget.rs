@@ -0,0 +1,291 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Datelike, TimeZone, Utc};
+use serde::Serialize;
+
+use crate::logging::time::BEIJING_OFFSET;
+use crate::logging::types::{
+    ProviderBudgetRecord, ProviderOpLog, REQ_TYPE_PROVIDER_BUDGET_THRESHOLD,
+};
+use crate::server::AppState;
+use crate::server::admin_notifications;
+use crate::server::storage_traits::RequestLogStore;
+
+/// 未指定时的告警阈值（预算百分比）
+pub const DEFAULT_WARN_THRESHOLDS: &[u8] = &[80, 100];
+/// 从请求日志重新汇总本月花费的间隔（多实例部署时各实例据此对齐）
+const SYNC_INTERVAL_SECS: u64 = 300;
+
+/// 当前自然月（北京时间），如 2026-10
+pub fn month_of(now: DateTime<Utc>) -> String {
+    now.with_timezone(&BEIJING_OFFSET)
+        .format("%Y-%m")
+        .to_string()
+}
+
+/// now 所在自然月（北京时间）的起始时刻
+pub fn month_start(now: DateTime<Utc>) -> DateTime<Utc> {
+    let local = now.with_timezone(&BEIJING_OFFSET);
+    BEIJING_OFFSET
+        .with_ymd_and_hms(local.year(), local.month(), 1, 0, 0, 0)
+        .single()
+        .map(|t| t.with_timezone(&Utc))
+        .unwrap_or(now)
+}
+
+#[derive(Default)]
+struct SpendState {
+    month: String,
+    spend: HashMap<String, f64>,
+    /// 本月已告警过的 (供应商, 阈值)
+    warned: HashSet<(String, u8)>,
+}
+
+/// 各供应商本月累计花费：请求完成时在内存中累加，并由后台任务定期按请求日志校准
+#[derive(Default)]
+pub struct ProviderSpendTracker {
+    state: Mutex<SpendState>,
+}
+
+impl ProviderSpendTracker {
+    /// 跨月时清零花费与告警记录
+    fn lock_month(&self, now: DateTime<Utc>) -> std::sync::MutexGuard<'_, SpendState> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let month = month_of(now);
+        if state.month != month {
+            *state = SpendState {
+                month,
+                ..Default::default()
+            };
+        }
+        state
+    }
+
+    /// 累加一次花费，返回该供应商本月累计
+    pub fn add(&self, provider: &str, amount: f64, now: DateTime<Utc>) -> f64 {
+        let mut state = self.lock_month(now);
+        let total = state.spend.entry(provider.to_string()).or_default();
+        *total += amount;
+        *total
+    }
+
+    pub fn spent(&self, provider: &str, now: DateTime<Utc>) -> f64 {
+        self.lock_month(now)
+            .spend
+            .get(provider)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// 用请求日志的汇总值覆盖内存计数（告警记录保留）
+    pub fn replace(&self, totals: HashMap<String, f64>, now: DateTime<Utc>) {
+        self.lock_month(now).spend = totals;
+    }
+
+    /// 返回本次新越过的阈值（每月每个阈值只返回一次）
+    fn take_crossed(
+        &self,
+        provider: &str,
+        thresholds: &[u8],
+        used_percent: f64,
+        now: DateTime<Utc>,
+    ) -> Vec<u8> {
+        let mut state = self.lock_month(now);
+        thresholds
+            .iter()
+            .copied()
+            .filter(|t| used_percent >= f64::from(*t))
+            .filter(|t| state.warned.insert((provider.to_string(), *t)))
+            .collect()
+    }
+
+    /// 管理员调整预算后允许重新告警
+    pub fn reset_warnings(&self, provider: &str, now: DateTime<Utc>) {
+        self.lock_month(now).warned.retain(|(p, _)| p != provider);
+    }
+}
+
+/// 预算及其本月使用情况
+#[derive(Debug, Clone, Serialize)]
+pub struct ProviderBudgetStatus {
+    pub provider: String,
+    pub month: String,
+    pub monthly_budget: f64,
+    pub warn_thresholds: Vec<u8>,
+    pub stop_routing_when_exhausted: bool,
+    pub spent: f64,
+    pub remaining: f64,
+    pub used_percent: f64,
+    pub exhausted: bool,
+    pub updated_at: String,
+}
+
+impl ProviderBudgetStatus {
+    pub fn new(record: ProviderBudgetRecord, spent: f64, now: DateTime<Utc>) -> Self {
+        Self {
+            month: month_of(now),
+            monthly_budget: record.monthly_budget,
+            warn_thresholds: record.warn_thresholds,
+            stop_routing_when_exhausted: record.stop_routing_when_exhausted,
+            spent,
+            remaining: (record.monthly_budget - spent).max(0.0),
+            used_percent: used_percent(spent, record.monthly_budget),
+            exhausted: spent >= record.monthly_budget,
+            updated_at: crate::logging::time::to_iso8601_utc_string(&record.updated_at),
+            provider: record.provider,
+        }
+    }
+}
+
+fn used_percent(spent: f64, budget: f64) -> f64 {
+    if budget > 0.0 {
+        spent / budget * 100.0
+    } else {
+        100.0
+    }
+}
+
+/// 计入一次请求的花费；越过告警阈值时打印告警并写入供应商操作日志
+pub async fn record_spend(app_state: &AppState, provider: &str, amount: Option<f64>) {
+    let Some(amount) = amount.filter(|v| v.is_finite() && *v > 0.0) else {
+        return;
+    };
+    let now = Utc::now();
+    let spent = app_state.provider_spend.add(provider, amount, now);
+    let budget = match app_state.log_store.get_provider_budget(provider).await {
+        Ok(Some(budget)) => budget,
+        Ok(None) => return,
+        Err(e) => {
+            tracing::warn!("Failed to load provider budget: {}", e);
+            return;
+        }
+    };
+    let used = used_percent(spent, budget.monthly_budget);
+    for threshold in
+        app_state
+            .provider_spend
+            .take_crossed(provider, &budget.warn_thresholds, used, now)
+    {
+        tracing::warn!(
+            provider,
+            threshold,
+            spent,
+            monthly_budget = budget.monthly_budget,
+            "provider monthly spend crossed budget threshold"
+        );
+        let details = serde_json::json!({
+            "month": month_of(now),
+            "threshold_percent": threshold,
+            "spent": spent,
+            "monthly_budget": budget.monthly_budget,
+            "stop_routing_when_exhausted": budget.stop_routing_when_exhausted,
+        });
+        let _ = app_state
+            .log_store
+            .log_provider_op(ProviderOpLog {
+                id: None,
+                timestamp: now,
+                operation: REQ_TYPE_PROVIDER_BUDGET_THRESHOLD.to_string(),
+                provider: Some(provider.to_string()),
+                details: Some(details.to_string()),
+                actor: None,
+            })
+            .await;
+        let month = month_of(now);
+        admin_notifications::notify(
+            app_state.log_store.as_ref(),
+            admin_notifications::NewNotification {
+                kind: admin_notifications::KIND_PROVIDER_BUDGET,
+                severity: if threshold >= 100 {
+                    admin_notifications::Severity::Critical
+                } else {
+                    admin_notifications::Severity::Warning
+                },
+                title: format!("Provider {} reached {}% of budget", provider, threshold),
+                message: format!(
+                    "Spent {:.4} of {:.4} in {}.",
+                    spent, budget.monthly_budget, month
+                ),
+                reference: Some(format!("{}:{}:{}", provider, month, threshold)),
+            },
+        )
+        .await;
+    }
+}
+
+/// 预算已耗尽且配置为停止路由的供应商
+pub async fn exhausted_providers(app_state: &AppState) -> HashSet<String> {
+    let budgets = match app_state.log_store.list_provider_budgets().await {
+        Ok(budgets) => budgets,
+        Err(e) => {
+            tracing::warn!("Failed to load provider budgets: {}", e);
+            return HashSet::new();
+        }
+    };
+    let now = Utc::now();
+    budgets
+        .into_iter()
+        .filter(|b| b.stop_routing_when_exhausted)
+        .filter(|b| app_state.provider_spend.spent(&b.provider, now) >= b.monthly_budget)
+        .map(|b| b.provider)
+        .collect()
+}
+
+/// 后台定期按请求日志汇总本月花费（启动时立即执行一次）
+pub fn spawn_sync_task(
+    tasks: &Arc<crate::server::tasks::TaskRegistry>,
+    tracker: Arc<ProviderSpendTracker>,
+    log_store: Arc<dyn RequestLogStore + Send + Sync>,
+) {
+    tasks.spawn_with("provider_budget_sync", |mut ctx| async move {
+        let mut ticker = tokio::time::interval(std::time::Duration::from_secs(SYNC_INTERVAL_SECS));
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {}
+                _ = ctx.cancelled() => break,
+            }
+            let now = Utc::now();
+            match log_store.sum_provider_spend_since(month_start(now)).await {
+                Ok(totals) => tracker.replace(totals, now),
+                Err(e) => {
+                    tracing::warn!("Failed to sync provider monthly spend: {}", e);
+                    ctx.report_error(e);
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn month_start_uses_beijing_calendar() {
+        // 北京时间 2026-11-01 02:00 仍是 UTC 10 月 31 日
+        let now = Utc.with_ymd_and_hms(2026, 10, 31, 18, 0, 0).unwrap();
+        assert_eq!(month_of(now), "2026-11");
+        assert_eq!(
+            month_start(now),
+            Utc.with_ymd_and_hms(2026, 10, 31, 16, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn thresholds_fire_once_per_month() {
+        let tracker = ProviderSpendTracker::default();
+        let now = Utc.with_ymd_and_hms(2026, 10, 10, 0, 0, 0).unwrap();
+        assert_eq!(tracker.add("p", 8.5, now), 8.5);
+        assert_eq!(tracker.take_crossed("p", &[80, 100], 85.0, now), vec![80]);
+        assert!(tracker.take_crossed("p", &[80, 100], 90.0, now).is_empty());
+        assert_eq!(tracker.take_crossed("p", &[80, 100], 100.0, now), vec![100]);
+
+        tracker.reset_warnings("p", now);
+        assert_eq!(tracker.take_crossed("p", &[80], 85.0, now), vec![80]);
+
+        let next_month = Utc.with_ymd_and_hms(2026, 11, 10, 0, 0, 0).unwrap();
+        assert_eq!(tracker.spent("p", next_month), 0.0);
+        assert_eq!(tracker.take_crossed("p", &[80], 85.0, next_month), vec![80]);
+    }
+}
@@ -1,12 +1,20 @@
 use crate::config::ProviderType;
 use crate::error::GatewayError;
-use crate::providers::adapters::{ChatCompletionsRequest, runtime_chat_completions};
+use crate::providers::adapters::{
+    ANTHROPIC_DEFAULT_API_VERSION, ChatCompletionsRequest, effective_api_version,
+    runtime_chat_completions,
+};
 use crate::providers::anthropic::AnthropicProvider;
 use crate::providers::openai::{ChatCompletionRequest, OpenAIProvider, RawAndTypedChatCompletion};
 use crate::providers::zhipu;
-use crate::routing::{LoadBalancer, SelectedProvider, load_balancer::BalanceError};
+use crate::routing::{
+    KeyRotationStrategy, KeyRoute, LoadBalancer, OpenAIAccountHeaders, ProviderKeyEntry,
+    SelectedProvider, key_labels, load_balancer::BalanceError,
+};
 use crate::server::AppState;
+use crate::server::legacy_completions::LegacyFields;
 use crate::server::model_parser::ParsedModel;
+use crate::server::util::mask_key;
 
 fn provider_uses_inline_credentials(provider: &crate::config::Provider) -> bool {
     match provider.api_type {
@@ -17,12 +25,26 @@ fn provider_uses_inline_credentials(provider: &crate::config::Provider) -> bool
     }
 }
 
+/// 无鉴权的本地运行时：没有可用密钥时以空密钥调度，请求不携带认证头
+fn provider_runs_keyless(provider: &crate::config::Provider, keys: &[ProviderKeyEntry]) -> bool {
+    provider.api_type.allows_keyless()
+        && !keys
+            .iter()
+            .any(|k| k.active && !k.value.is_empty() && k.weight >= 1)
+}
+
 // 基于请求的模型名称选择合适的供应商
+/// token_id 为发起请求的客户端令牌，用于匹配供应商的密钥标签路由规则
 pub async fn select_provider_for_model(
     app_state: &AppState,
     model_name: &str,
+    token_id: Option<&str>,
 ) -> Result<(SelectedProvider, ParsedModel), GatewayError> {
     let parsed_model = ParsedModel::parse(model_name);
+    let route = KeyRoute {
+        token_id,
+        model: Some(parsed_model.model_name.as_str()),
+    };
 
     // 如果解析出了供应商前缀，尝试直接匹配该供应商（从数据库读取）
     if let Some(provider_name) = &parsed_model.provider_name {
@@ -33,36 +55,18 @@ pub async fn select_provider_for_model(
             .ok()
             .flatten()
         {
-            if !provider.enabled {
-                return Err(GatewayError::Forbidden(format!(
-                    "Provider '{}' is disabled",
-                    provider_name
-                )));
-            }
-            let keys = app_state
-                .providers
-                .list_provider_keys_raw(provider_name, &app_state.config.logging.key_log_strategy)
-                .await
-                .unwrap_or_default();
-            let strategy = app_state
-                .providers
-                .get_provider_key_rotation_strategy(provider_name)
+            let selected = select_named_provider(app_state, provider, None, route).await?;
+            return Ok((selected, parsed_model));
+        } else if provider_collection_exists(app_state, provider_name).await {
+            // 前缀为供应商合集：在合集内按负载均衡策略选择供应商
+            let selected = select_provider_in_collection(app_state, Some(provider_name), route)
                 .await
-                .unwrap_or_default();
-            let api_key = if provider_uses_inline_credentials(&provider) {
-                String::new()
-            } else {
-                let api_key = app_state.load_balancer_state.select_provider_key(
-                    provider_name,
-                    strategy,
-                    &keys,
-                )?;
-                if api_key.is_empty() {
-                    return Err(GatewayError::from(BalanceError::NoApiKeysAvailable));
-                }
-                api_key
+                .map_err(GatewayError::from)?;
+            let parsed_model = ParsedModel {
+                provider_name: Some(selected.provider.name.clone()),
+                model_name: parsed_model.model_name,
             };
-            return Ok((SelectedProvider { provider, api_key }, parsed_model));
+            return Ok((selected, parsed_model));
         } else {
             // 指定供应商不存在
             return Err(GatewayError::NotFound(format!(
@@ -73,14 +77,137 @@ pub async fn select_provider_for_model(
     }
 
     // 没有指定供应商前缀，使用负载均衡策略选择
-    let selected = select_provider(app_state)
+    let selected = select_provider(app_state, route)
         .await
         .map_err(GatewayError::from)?;
     Ok((selected, parsed_model))
 }
 
+/// 直接使用指定的供应商（不经过供应商间的负载均衡）。
+/// pinned_key 为密钥原文或其掩码（与请求日志中的 selected_key_id 一致）；为空时按密钥轮换策略选择
+pub async fn select_named_provider(
+    app_state: &AppState,
+    provider: crate::config::Provider,
+    pinned_key: Option<&str>,
+    route: KeyRoute<'_>,
+) -> Result<SelectedProvider, GatewayError> {
+    let provider_name = &provider.name;
+    if !provider.enabled {
+        return Err(GatewayError::Forbidden(format!(
+            "Provider '{}' is disabled",
+            provider_name
+        )));
+    }
+    if crate::server::provider_budget::exhausted_providers(app_state)
+        .await
+        .contains(provider_name)
+    {
+        return Err(GatewayError::Forbidden(format!(
+            "Provider '{}' has exhausted its monthly budget",
+            provider_name
+        )));
+    }
+    if let Some(window) = app_state.maintenance.active_window(provider_name) {
+        return Err(GatewayError::Forbidden(match window.message {
+            Some(message) => format!(
+                "Provider '{}' is under maintenance: {}",
+                provider_name, message
+            ),
+            None => format!("Provider '{}' is under maintenance", provider_name),
+        }));
+    }
+    let keys = app_state
+        .providers
+        .list_provider_keys_raw(provider_name, &app_state.config.logging.key_log_strategy)
+        .await
+        .unwrap_or_default();
+    let api_key = if let Some(pinned) = pinned_key {
+        let matched: Vec<&ProviderKeyEntry> = keys
+            .iter()
+            .filter(|e| e.active && !e.value.is_empty())
+            .filter(|e| e.value == pinned || mask_key(&e.value) == pinned)
+            .collect();
+        match matched.as_slice() {
+            [entry] => entry.value.clone(),
+            [] => {
+                return Err(GatewayError::NotFound(format!(
+                    "No active key '{}' for provider '{}'",
+                    mask_key(pinned),
+                    provider_name
+                )));
+            }
+            _ => {
+                return Err(GatewayError::Config(format!(
+                    "Key '{}' matches several keys of provider '{}'; pass the full key",
+                    mask_key(pinned),
+                    provider_name
+                )));
+            }
+        }
+    } else if provider_uses_inline_credentials(&provider) || provider_runs_keyless(&provider, &keys)
+    {
+        String::new()
+    } else {
+        let strategy = app_state
+            .providers
+            .get_provider_key_rotation_strategy(provider_name)
+            .await
+            .unwrap_or_default();
+        let api_key = select_key_for_route(app_state, &provider, strategy, &keys, route)?;
+        if api_key.is_empty() {
+            return Err(GatewayError::from(BalanceError::NoApiKeysAvailable));
+        }
+        api_key
+    };
+    let openai_account = OpenAIAccountHeaders::for_key(&keys, &api_key);
+    Ok(SelectedProvider {
+        provider,
+        api_key,
+        openai_account,
+    })
+}
+
+/// 命中密钥标签路由规则时先在匹配的密钥中轮换，它们均不可用（停用、限流、超出花费上限）时回落到全部密钥
+fn select_key_for_route(
+    app_state: &AppState,
+    provider: &crate::config::Provider,
+    strategy: KeyRotationStrategy,
+    keys: &[ProviderKeyEntry],
+    route: KeyRoute<'_>,
+) -> Result<String, BalanceError> {
+    let balancer = &app_state.load_balancer_state;
+    if let Some(preferred) =
+        key_labels::preferred_keys(&provider.provider_config.key_label_rules, keys, route)
+        && let Ok(api_key) = balancer.select_provider_key(&provider.name, strategy, &preferred)
+    {
+        return Ok(api_key);
+    }
+    balancer.select_provider_key(&provider.name, strategy, keys)
+}
+
+async fn provider_collection_exists(app_state: &AppState, name: &str) -> bool {
+    app_state
+        .providers
+        .list_provider_collections()
+        .await
+        .map(|cols| cols.iter().any(|c| c == name))
+        .unwrap_or(false)
+}
+
 // 基于数据库中可用的供应商进行选择（替代文件配置）
-pub async fn select_provider(app_state: &AppState) -> Result<SelectedProvider, BalanceError> {
+pub async fn select_provider(
+    app_state: &AppState,
+    route: KeyRoute<'_>,
+) -> Result<SelectedProvider, BalanceError> {
+    select_provider_in_collection(app_state, None, route).await
+}
+
+// 同上，collection 不为空时仅在该合集的供应商中选择
+pub async fn select_provider_in_collection(
+    app_state: &AppState,
+    collection: Option<&str>,
+    route: KeyRoute<'_>,
+) -> Result<SelectedProvider, BalanceError> {
     let providers = app_state
         .providers
         .list_providers()
@@ -91,6 +218,8 @@ pub async fn select_provider(app_state: &AppState) -> Result<SelectedProvider, B
         return Err(BalanceError::NoProvidersAvailable);
     }
 
+    // 月度预算耗尽或处于维护窗口的供应商不参与选择，流量回落到其他供应商
+    let exhausted = crate::server::provider_budget::exhausted_providers(app_state).await;
     let mut candidates: Vec<crate::config::Provider> = Vec::new();
     let mut keys_by_provider: std::collections::HashMap<
         String,
@@ -98,7 +227,11 @@ pub async fn select_provider(app_state: &AppState) -> Result<SelectedProvider, B
     > = std::collections::HashMap::new();
 
     for p in providers {
-        if !p.enabled {
+        if !p.enabled
+            || collection.is_some_and(|c| c != p.collection)
+            || exhausted.contains(&p.name)
+            || app_state.maintenance.active_window(&p.name).is_some()
+        {
             continue;
         }
         let keys = app_state
@@ -109,7 +242,7 @@ pub async fn select_provider(app_state: &AppState) -> Result<SelectedProvider, B
         let has_active = keys
             .iter()
             .any(|k| k.active && !k.value.is_empty() && k.weight >= 1);
-        if has_active || provider_uses_inline_credentials(&p) {
+        if has_active || provider_uses_inline_credentials(&p) || p.api_type.allows_keyless() {
             keys_by_provider.insert(p.name.clone(), keys);
             candidates.push(p);
         }
@@ -132,52 +265,118 @@ pub async fn select_provider(app_state: &AppState) -> Result<SelectedProvider, B
         .get_provider_key_rotation_strategy(&provider.name)
         .await
         .unwrap_or_default();
-    let api_key = if provider_uses_inline_credentials(&provider) {
-        String::new()
-    } else {
-        app_state
-            .load_balancer_state
-            .select_provider_key(&provider.name, strategy, &keys)?
-    };
+    let api_key =
+        if provider_uses_inline_credentials(&provider) || provider_runs_keyless(&provider, &keys) {
+            String::new()
+        } else {
+            select_key_for_route(app_state, &provider, strategy, &keys, route)?
+        };
 
-    Ok(SelectedProvider { provider, api_key })
+    let openai_account = OpenAIAccountHeaders::for_key(&keys, &api_key);
+    Ok(SelectedProvider {
+        provider,
+        api_key,
+        openai_account,
+    })
 }
 
-// 根据选中的供应商和解析的模型调用对应的聊天补全接口
+// 根据选中的供应商和解析的模型调用对应的聊天补全接口，并计入该供应商/密钥的上游流量；
+// legacy 为旧版补全入口的独有字段，供应商支持时改走原生 /v1/completions
 pub async fn call_provider_with_parsed_model(
+    app_state: &AppState,
     selected: &SelectedProvider,
     request: &ChatCompletionRequest,
     parsed_model: &ParsedModel,
     top_k: Option<u32>,
+    legacy: Option<&LegacyFields>,
 ) -> Result<RawAndTypedChatCompletion, GatewayError> {
     // 创建一个新的请求，使用实际的模型名称
     let mut modified_request = request.clone();
     modified_request.model = parsed_model.get_upstream_model_name().to_string();
 
+    if crate::server::n_choices::needs_fanout(selected.provider.api_type, &modified_request) {
+        // 上游不支持原生 n：拆分为 n 次并发调用后合并，任一失败则整体失败
+        let n = crate::server::n_choices::requested(&modified_request);
+        modified_request.n = None;
+        let calls =
+            (0..n).map(|_| dispatch_metered(app_state, selected, &modified_request, top_k, legacy));
+        let responses = futures_util::future::join_all(calls)
+            .await
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()?;
+        return Ok(crate::server::n_choices::merge_fanout(responses));
+    }
+    dispatch_metered(app_state, selected, &modified_request, top_k, legacy).await
+}
+
+async fn dispatch_metered(
+    app_state: &AppState,
+    selected: &SelectedProvider,
+    modified_request: &ChatCompletionRequest,
+    top_k: Option<u32>,
+    legacy: Option<&LegacyFields>,
+) -> Result<RawAndTypedChatCompletion, GatewayError> {
+    let masked_key = crate::server::util::mask_key(&selected.api_key);
+    app_state.egress_meter.record_request(
+        &selected.provider.name,
+        &masked_key,
+        crate::server::egress::json_len(modified_request),
+    );
+    let mut response = dispatch_to_provider(selected, modified_request, top_k, legacy).await;
+    if let Err(e) = &response
+        && let Some(secs) = e.retry_after()
+    {
+        // 上游 429 + Retry-After：冷却该密钥，窗口结束前不再选中
+        app_state.load_balancer_state.cool_down_key(
+            &selected.provider.name,
+            &selected.api_key,
+            secs,
+        );
+    }
+    if let Ok(dual) = &mut response {
+        crate::server::citations::normalize_response(&mut dual.raw);
+        let response_bytes = crate::server::egress::json_len(&dual.raw);
+        app_state.egress_meter.record_response(
+            &selected.provider.name,
+            &masked_key,
+            response_bytes,
+        );
+        app_state
+            .payload_sizes
+            .record_response(&selected.provider.name, response_bytes as u64);
+    }
+    response
+}
+
+async fn dispatch_to_provider(
+    selected: &SelectedProvider,
+    modified_request: &ChatCompletionRequest,
+    top_k: Option<u32>,
+    legacy: Option<&LegacyFields>,
+) -> Result<RawAndTypedChatCompletion, GatewayError> {
     match selected.provider.api_type {
-        ProviderType::Anthropic => {
-            call_anthropic_provider(selected, &modified_request, top_k).await
-        }
-        ProviderType::Zhipu => call_zhipu_provider(selected, &modified_request).await,
+        ProviderType::Anthropic => call_anthropic_provider(selected, modified_request, top_k).await,
+        ProviderType::Zhipu => call_zhipu_provider(selected, modified_request).await,
         ProviderType::AzureOpenAI
         | ProviderType::GoogleGemini
         | ProviderType::Cohere
         | ProviderType::AwsClaude
         | ProviderType::BaiduErnie
-        | ProviderType::VertexAI => {
+        | ProviderType::VertexAI
+        | ProviderType::Mock => {
             runtime_chat_completions(
                 selected.provider.api_type,
                 ChatCompletionsRequest {
                     base_url: &selected.provider.base_url,
                     api_key: &selected.api_key,
                     provider_config: &selected.provider.provider_config,
-                    request: &modified_request,
+                    request: modified_request,
                 },
             )
             .await
         }
         provider_type if provider_type.capabilities().openai_compatible => {
-            call_openai_provider(selected, &modified_request).await
+            call_openai_provider(selected, modified_request, legacy).await
         }
         provider_type => Err(GatewayError::Config(
             format!(
@@ -192,8 +391,49 @@ pub async fn call_provider_with_parsed_model(
 async fn call_openai_provider(
     selected: &SelectedProvider,
     request: &ChatCompletionRequest,
+    legacy: Option<&LegacyFields>,
 ) -> Result<RawAndTypedChatCompletion, GatewayError> {
-    OpenAIProvider::chat_completions(&selected.provider.base_url, &selected.api_key, request).await
+    let native_body =
+        legacy.and_then(|legacy| legacy.native_request(selected.provider.api_type, request));
+    let mut response = match native_body {
+        Some(body) => {
+            OpenAIProvider::text_completions(
+                &selected.provider.base_url,
+                &selected.api_key,
+                &selected.openai_account,
+                &body,
+            )
+            .await?
+        }
+        None => {
+            OpenAIProvider::chat_completions(
+                &selected.provider.base_url,
+                &selected.api_key,
+                &selected.openai_account,
+                request,
+            )
+            .await?
+        }
+    };
+    if selected.provider.api_type == ProviderType::Local && response.typed.usage.is_none() {
+        // 本地运行时可能不返回 usage：按请求与输出文本估算，保证计费与配额仍然生效
+        let completion_chars =
+            crate::server::response_text::extract_response_text(&response.raw, &response.typed)
+                .map(|text| text.chars().count())
+                .unwrap_or(0);
+        let usage = crate::server::chat_plan::estimated_usage(
+            crate::server::chat_plan::estimate_prompt_tokens(request),
+            completion_chars,
+        );
+        if let Some(obj) = response.raw.as_object_mut() {
+            obj.insert(
+                "usage".to_string(),
+                serde_json::to_value(&usage).unwrap_or_default(),
+            );
+        }
+        response.typed.usage = Some(usage);
+    }
+    Ok(response)
 }
 
 async fn call_anthropic_provider(
@@ -204,9 +444,13 @@ async fn call_anthropic_provider(
     let anthropic_request =
         AnthropicProvider::convert_openai_to_anthropic_with_top_k(request, top_k);
 
+    let api_version =
+        effective_api_version(ProviderType::Anthropic, &selected.provider.provider_config)
+            .unwrap_or(ANTHROPIC_DEFAULT_API_VERSION);
     let anthropic_response = AnthropicProvider::chat_completions(
         &selected.provider.base_url,
         &selected.api_key,
+        api_version,
         &anthropic_request,
     )
     .await?;
@@ -234,8 +478,5 @@ async fn call_zhipu_provider(
     selected: &SelectedProvider,
     request: &ChatCompletionRequest,
 ) -> Result<RawAndTypedChatCompletion, GatewayError> {
-    let adapted = zhipu::adapt_openai_request_for_zhipu(request.clone());
-    let resp =
-        zhipu::chat_completions(&selected.provider.base_url, &selected.api_key, &adapted).await?;
-    Ok(resp)
+    zhipu::chat_completions(&selected.provider.base_url, &selected.api_key, request).await
 }
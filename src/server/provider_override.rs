@@ -0,0 +1,160 @@
+use axum::http::HeaderMap;
+use serde::Serialize;
+
+use crate::error::GatewayError;
+use crate::routing::SelectedProvider;
+use crate::server::AppState;
+use crate::server::model_parser::ParsedModel;
+use crate::server::provider_dispatch::select_named_provider;
+use crate::server::util::mask_key;
+
+/// 指定供应商的请求头，等价于请求体中的 provider 字段
+pub const PROVIDER_HEADER: &str = "x-gateway-provider";
+/// 指定密钥的请求头（密钥原文或其掩码），等价于请求体中的 provider_key 字段
+pub const PROVIDER_KEY_HEADER: &str = "x-gateway-provider-key";
+
+/// 特权令牌对单次请求指定的供应商/密钥：绕过负载均衡，但鉴权与计费照常
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProviderOverride {
+    pub provider: String,
+    pub key: Option<String>,
+}
+
+#[derive(Serialize)]
+struct OverrideLog<'a> {
+    provider: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    key: Option<String>,
+}
+
+fn header_value(headers: &HeaderMap, name: &str) -> Result<Option<String>, GatewayError> {
+    let Some(value) = headers.get(name) else {
+        return Ok(None);
+    };
+    let value = value
+        .to_str()
+        .map_err(|_| GatewayError::Config(format!("invalid {} header", name)))?
+        .trim();
+    Ok((!value.is_empty()).then(|| value.to_string()))
+}
+
+/// 请求体字段与请求头同时给出时必须一致
+fn merge(
+    field: &str,
+    body: Option<&str>,
+    header: Option<String>,
+) -> Result<Option<String>, GatewayError> {
+    let body = body.map(str::trim).filter(|v| !v.is_empty());
+    match (body, header) {
+        (Some(b), Some(h)) if b != h => Err(GatewayError::Config(format!(
+            "{} in body conflicts with the request header",
+            field
+        ))),
+        (Some(b), _) => Ok(Some(b.to_string())),
+        (None, h) => Ok(h),
+    }
+}
+
+impl ProviderOverride {
+    /// 解析请求体中的 provider/provider_key 字段与 X-Gateway-Provider(-Key) 请求头；
+    /// 均未指定时返回 None
+    pub fn from_request(
+        headers: &HeaderMap,
+        provider: Option<&str>,
+        provider_key: Option<&str>,
+    ) -> Result<Option<Self>, GatewayError> {
+        let provider = merge(
+            "provider",
+            provider,
+            header_value(headers, PROVIDER_HEADER)?,
+        )?;
+        let key = merge(
+            "provider_key",
+            provider_key,
+            header_value(headers, PROVIDER_KEY_HEADER)?,
+        )?;
+        match (provider, key) {
+            (Some(provider), key) => Ok(Some(Self { provider, key })),
+            (None, Some(_)) => Err(GatewayError::Config(
+                "provider_key requires provider to be set".into(),
+            )),
+            (None, None) => Ok(None),
+        }
+    }
+
+    /// 写入请求日志详情的记录；密钥只保留掩码
+    pub fn log_value(&self) -> String {
+        serde_json::to_string(&OverrideLog {
+            provider: &self.provider,
+            key: self.key.as_deref().map(mask_key),
+        })
+        .unwrap_or_default()
+    }
+}
+
+/// 按指定的供应商/密钥选择上游；模型名带有其他供应商前缀时视为冲突
+pub async fn select(
+    app_state: &AppState,
+    pinned: &ProviderOverride,
+    model_name: &str,
+) -> Result<(SelectedProvider, ParsedModel), GatewayError> {
+    let parsed = ParsedModel::parse(model_name);
+    if let Some(prefix) = parsed.provider_name.as_deref()
+        && prefix != pinned.provider
+    {
+        return Err(GatewayError::Config(format!(
+            "model prefix '{}' conflicts with provider override '{}'",
+            prefix, pinned.provider
+        )));
+    }
+    let provider = app_state
+        .providers
+        .get_provider(&pinned.provider)
+        .await?
+        .ok_or_else(|| {
+            GatewayError::NotFound(format!("Provider '{}' not found", pinned.provider))
+        })?;
+    let selected = select_named_provider(
+        app_state,
+        provider,
+        pinned.key.as_deref(),
+        Default::default(),
+    )
+    .await?;
+    Ok((
+        selected,
+        ParsedModel {
+            provider_name: Some(pinned.provider.clone()),
+            model_name: parsed.model_name,
+        },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    #[test]
+    fn body_and_headers_are_merged() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(
+            ProviderOverride::from_request(&headers, None, None).unwrap(),
+            None
+        );
+
+        headers.insert(PROVIDER_HEADER, HeaderValue::from_static("azure-eu"));
+        let parsed =
+            ProviderOverride::from_request(&headers, Some("azure-eu"), Some("sk-abcdefgh1234"))
+                .unwrap()
+                .unwrap();
+        assert_eq!(parsed.provider, "azure-eu");
+        assert_eq!(
+            parsed.log_value(),
+            r#"{"provider":"azure-eu","key":"sk-a****1234"}"#
+        );
+
+        assert!(ProviderOverride::from_request(&headers, Some("openai"), None).is_err());
+        assert!(ProviderOverride::from_request(&HeaderMap::new(), None, Some("sk-1")).is_err());
+    }
+}
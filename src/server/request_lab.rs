@@ -14,28 +14,32 @@ use crate::admin::ClientToken;
 use crate::error::GatewayError;
 use crate::logging::RequestLog;
 use crate::logging::types::{
-    REQ_TYPE_CHAT_COMPARE, REQ_TYPE_CHAT_REPLAY, RequestLabExperimentConfig,
-    RequestLogDetailRecord, StoredCompareRun, StoredRequestLabSnapshot, StoredRequestLabSource,
-    StoredRequestLabTemplate,
+    DebugCaptureRecord, REQ_TYPE_CHAT_COMPARE, REQ_TYPE_CHAT_ONCE, REQ_TYPE_CHAT_REPLAY,
+    REQ_TYPE_CHAT_SEMANTIC_CACHE_HIT, RequestLabExperimentConfig, RequestLogDetailRecord,
+    StoredCompareRun, StoredRequestLabSnapshot, StoredRequestLabSource, StoredRequestLabTemplate,
 };
+use crate::providers::capabilities::Feature;
 use crate::providers::openai::ChatCompletionRequest;
 use crate::providers::openai::types::RawAndTypedChatCompletion;
 use crate::providers::openai::usage::resolved_usage;
 use crate::server::AppState;
+use crate::server::chat_pipeline::{
+    AdmittedChatRequest, ChatTransport, admit_chat_request, disable_token_if_over_limits,
+};
+use crate::server::chat_plan::DecisionTrace;
 use crate::server::handlers::auth::{
     AccessTokenClaims, AdminIdentity, require_superadmin, require_user,
 };
-use crate::server::model_redirect::{
-    apply_model_redirects, apply_provider_model_redirects_to_parsed_model,
-};
-use crate::server::pricing::{missing_price_allowed_for_chat, resolve_model_pricing};
-use crate::server::provider_dispatch::{
-    call_provider_with_parsed_model, select_provider_for_model,
-};
+use crate::server::legacy_completions::LegacyFields;
+use crate::server::model_concurrency::FairShare;
+use crate::server::prompt_truncation::PromptTruncation;
+use crate::server::provider_dispatch::call_provider_with_parsed_model;
+use crate::server::provider_override::ProviderOverride;
 use crate::server::request_logging::{
     ChatLogContext, LoggedChatRequest, log_chat_request, log_simple_request,
 };
 use crate::server::response_text;
+use crate::server::semantic_cache::{self, Lookup};
 use crate::users::UserRole;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -384,6 +388,15 @@ pub struct RequestDetailResponse {
     pub selected_provider: Option<String>,
     pub selected_key_id: Option<String>,
     pub first_token_latency_ms: Option<i64>,
+    /// 参数策略对该请求所做的改写
+    #[serde(default)]
+    pub param_policy_applied: Option<serde_json::Value>,
+    /// 请求指定的供应商/密钥（绕过负载均衡）
+    #[serde(default)]
+    pub provider_override: Option<serde_json::Value>,
+    /// 因超出上下文窗口被丢弃的最早消息
+    #[serde(default)]
+    pub prompt_truncation: Option<serde_json::Value>,
     pub error_message: Option<String>,
     pub source_request_summary: SourceRequestSummary,
     #[serde(default)]
@@ -394,6 +407,9 @@ pub struct RequestDetailResponse {
     pub template_applied: bool,
     #[serde(default)]
     pub template_name: Option<String>,
+    /// X-Gateway-Debug: capture 捕获的完整正文（过期后为空）
+    #[serde(default)]
+    pub debug_capture: Option<DebugCaptureRecord>,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -422,6 +438,13 @@ pub struct ExecutedChatRequest {
     pub response: Result<RawAndTypedChatCompletion, GatewayError>,
     pub upstream_error_body: Option<serde_json::Value>,
     pub logged: LoggedChatRequest,
+    pub prompt_truncation: Option<PromptTruncation>,
+    /// 语义缓存：Some(true) 命中，Some(false) 已查询未命中，None 未使用
+    pub semantic_cache_hit: Option<bool>,
+    /// 令牌开启响应水印时的签名水印
+    pub watermark: Option<String>,
+    /// 请求中被目标供应商丢弃的字段
+    pub dropped_features: Vec<Feature>,
 }
 
 fn is_superadmin(claims: &AccessTokenClaims) -> bool {
@@ -958,6 +981,18 @@ fn detail_response(
             .as_ref()
             .and_then(|item| item.selected_key_id.clone()),
         first_token_latency_ms: detail.as_ref().and_then(|item| item.first_token_latency_ms),
+        param_policy_applied: detail
+            .as_ref()
+            .and_then(|item| item.param_policy_applied.as_deref())
+            .and_then(|raw| serde_json::from_str(raw).ok()),
+        provider_override: detail
+            .as_ref()
+            .and_then(|item| item.provider_override.as_deref())
+            .and_then(|raw| serde_json::from_str(raw).ok()),
+        prompt_truncation: detail
+            .as_ref()
+            .and_then(|item| item.prompt_truncation.as_deref())
+            .and_then(|raw| serde_json::from_str(raw).ok()),
         error_message: log.error_message,
         source_request_summary,
         system_prompt,
@@ -965,6 +1000,7 @@ fn detail_response(
         locked_fields: request_locked_fields(),
         template_applied: false,
         template_name: None,
+        debug_capture: None,
     })
 }
 
@@ -1033,6 +1069,7 @@ fn gateway_error_detail(err: &GatewayError) -> String {
         | GatewayError::Config(message)
         | GatewayError::NotFound(message)
         | GatewayError::RateLimited(message)
+        | GatewayError::UpstreamRateLimited(_, message)
         | GatewayError::Unauthorized(message)
         | GatewayError::Forbidden(message) => message.clone(),
         _ => err.to_string(),
@@ -1448,95 +1485,120 @@ fn snapshot_matches_keyword(snapshot: &StoredRequestLabSnapshot, keyword: &str)
             .any(|value| value.to_lowercase().contains(&keyword))
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn execute_logged_chat_request(
     app_state: &Arc<AppState>,
     start_time: DateTime<Utc>,
-    mut request: ChatCompletionRequest,
+    request: ChatCompletionRequest,
     top_k: Option<u32>,
     raw_client_token: &str,
     path: &str,
     request_type: &str,
     request_payload_snapshot: Option<String>,
+    debug_capture: bool,
+    provider_override: Option<&ProviderOverride>,
+    legacy: Option<&LegacyFields>,
 ) -> Result<ExecutedChatRequest, GatewayError> {
-    let requested_model = request.model.clone();
-    apply_model_redirects(&mut request);
-    let parsed_for_prefix = crate::server::model_parser::ParsedModel::parse(&request.model);
-    if let Some(provider_name) = parsed_for_prefix.provider_name.as_deref() {
-        let mut parsed = parsed_for_prefix.clone();
-        if let Some((from, to)) =
-            apply_provider_model_redirects_to_parsed_model(app_state, provider_name, &mut parsed)
-                .await?
-        {
-            return Err(GatewayError::Config(format!(
-                "model '{}' is redirected; use '{}' instead",
-                from, to
-            )));
-        }
-    }
-
-    let token = app_state
-        .token_store
-        .get_token(raw_client_token)
-        .await?
-        .ok_or_else(|| GatewayError::Config("invalid token".into()))?;
-
-    if let Some(user_id) = token.user_id.as_deref() {
-        let user = app_state.user_store.get_user(user_id).await?;
-        let balance = user.as_ref().map(|item| item.balance).unwrap_or(0.0);
-        if balance <= 0.0 {
-            let _ = app_state
-                .token_store
-                .set_enabled_for_user(user_id, false)
-                .await;
-            return Err(GatewayError::Config(
-                "余额不足：密钥已失效；充值/订阅后需手动启用密钥".into(),
-            ));
-        }
-    }
-
-    if !token.enabled {
-        if let Some(max_amount) = token.max_amount
-            && let Ok(spent) = app_state
-                .log_store
-                .sum_spent_amount_by_client_token(&token.id)
-                .await
-            && spent >= max_amount
-        {
-            return Err(GatewayError::Config("token budget exceeded".into()));
-        }
-        return Err(GatewayError::Config("token disabled".into()));
-    }
-
-    if let Some(expires_at) = token.expires_at
-        && Utc::now() > expires_at
-    {
-        return Err(GatewayError::Config("token expired".into()));
+    let mut trace = DecisionTrace::new(&request.model);
+    let AdmittedChatRequest {
+        token,
+        selected,
+        parsed_model,
+        requested_model,
+        upstream_model,
+        billing_model,
+        request,
+        top_k,
+        param_policy_applied,
+        provider_override,
+        prompt_truncation,
+        dropped_features,
+        prompt_profile,
+        ..
+    } = admit_chat_request(
+        app_state,
+        start_time,
+        request,
+        top_k,
+        raw_client_token,
+        path,
+        ChatTransport::NonStream,
+        provider_override,
+        &mut trace,
+    )
+    .await?;
+    if debug_capture && !token.allow_debug_capture {
+        return Err(GatewayError::Forbidden(
+            "debug capture is not enabled for this token".into(),
+        ));
     }
 
-    if let Some(max_tokens) = token.max_tokens
-        && token.total_tokens_spent >= max_tokens
+    // 语义缓存只用于 /v1/chat/completions 的普通非流式请求；旧版补全、回放、对比与调试捕获始终访问上游
+    let semantic = if token.semantic_cache
+        && request_type == REQ_TYPE_CHAT_ONCE
+        && legacy.is_none()
+        && !debug_capture
     {
-        return Err(GatewayError::Config("token total usage exceeded".into()));
-    }
-
-    let (selected, parsed_model) = select_provider_for_model(app_state, &request.model).await?;
-    let upstream_model = parsed_model.get_upstream_model_name().to_string();
-
-    if let Ok(Some(false)) = app_state
-        .log_store
-        .get_model_enabled(&selected.provider.name, &upstream_model)
-        .await
-    {
-        return Err(GatewayError::Config("model is disabled".into()));
-    }
-
-    let resolved_pricing =
-        resolve_model_pricing(app_state, &selected.provider.name, &upstream_model, None).await?;
-    if !resolved_pricing.price_found && !missing_price_allowed_for_chat(app_state) {
-        return Err(GatewayError::Config("model price not set".into()));
-    }
+        semantic_cache::lookup(app_state, &token.id, &request, top_k).await
+    } else {
+        None
+    };
+    let semantic_key = match semantic {
+        Some(Lookup::Hit(mut response)) => {
+            log_simple_request(
+                app_state,
+                start_time,
+                "POST",
+                path,
+                REQ_TYPE_CHAT_SEMANTIC_CACHE_HIT,
+                Some(requested_model),
+                Some(selected.provider.name.clone()),
+                Some(token.id.as_str()),
+                200,
+                None,
+            )
+            .await;
+            if token.strip_reasoning {
+                response_text::strip_reasoning_fields(&mut response.raw);
+            }
+            return Ok(ExecutedChatRequest {
+                effective_model: upstream_model,
+                provider_name: selected.provider.name,
+                response: Ok(*response),
+                upstream_error_body: None,
+                logged: LoggedChatRequest::default(),
+                prompt_truncation,
+                semantic_cache_hit: Some(true),
+                watermark: issue_watermark(&token),
+                dropped_features,
+            });
+        }
+        Some(Lookup::Miss(key)) => Some(key),
+        None => None,
+    };
 
-    let response = call_provider_with_parsed_model(&selected, &request, &parsed_model, top_k).await;
+    let concurrency_permit = app_state
+        .model_concurrency
+        .acquire(
+            &app_state.config.server,
+            &upstream_model,
+            &FairShare::for_token(&app_state.config.server, &token),
+        )
+        .await?;
+    let in_flight = app_state.in_flight.start(&selected.provider.name, false);
+    let upstream_started_at = Utc::now();
+    let mut response = call_provider_with_parsed_model(
+        app_state,
+        &selected,
+        &request,
+        &parsed_model,
+        top_k,
+        legacy,
+    )
+    .await;
+    let upstream_finished_at = Utc::now();
+    drop(in_flight);
+    drop(concurrency_permit);
     let upstream_error_body = response
         .as_ref()
         .ok()
@@ -1558,7 +1620,7 @@ pub async fn execute_logged_chat_request(
     let logged = log_chat_request(
         app_state,
         start_time,
-        &resolved_pricing.billing_model,
+        &billing_model,
         &requested_model,
         &upstream_model,
         &selected.provider.name,
@@ -1573,27 +1635,32 @@ pub async fn execute_logged_chat_request(
             selected_provider: Some(selected.provider.name.clone()),
             selected_key_id: Some(crate::server::util::mask_key(&selected.api_key)),
             first_token_latency_ms: None,
+            param_policy_applied,
+            provider_override,
+            prompt_truncation: prompt_truncation.as_ref().map(PromptTruncation::log_value),
+            debug_capture,
+            upstream_started_at: Some(upstream_started_at),
+            upstream_finished_at: Some(upstream_finished_at),
+            prompt_profile,
         },
     )
     .await;
 
-    if let Ok(Some(updated)) = app_state.token_store.get_token(raw_client_token).await {
-        if let Some(max_amount) = updated.max_amount
-            && updated.amount_spent > max_amount
-        {
-            let _ = app_state
-                .token_store
-                .set_enabled(raw_client_token, false)
-                .await;
-        }
-        if let Some(max_tokens) = updated.max_tokens
-            && updated.total_tokens_spent > max_tokens
-        {
-            let _ = app_state
-                .token_store
-                .set_enabled(raw_client_token, false)
-                .await;
-        }
+    disable_token_if_over_limits(app_state, raw_client_token).await;
+
+    let semantic_cache_hit = semantic_key.is_some().then_some(false);
+    if let Some(key) = semantic_key
+        && upstream_error_body.is_none()
+        && let Ok(dual) = response.as_ref()
+    {
+        semantic_cache::store(app_state, &token.id, key, dual);
+    }
+
+    // 日志保留完整响应；返回给调用方前按令牌策略剥离思考内容
+    if token.strip_reasoning
+        && let Ok(dual) = response.as_mut()
+    {
+        response_text::strip_reasoning_fields(&mut dual.raw);
     }
 
     Ok(ExecutedChatRequest {
@@ -1602,9 +1669,20 @@ pub async fn execute_logged_chat_request(
         response,
         upstream_error_body,
         logged,
+        prompt_truncation,
+        semantic_cache_hit,
+        watermark: issue_watermark(&token),
+        dropped_features,
     })
 }
 
+fn issue_watermark(token: &ClientToken) -> Option<String> {
+    if !token.watermark_responses {
+        return None;
+    }
+    crate::server::watermark::Watermark::issue(&token.id, Utc::now()).encode()
+}
+
 fn replay_response(
     source_request_id: i64,
     requested_model: String,
@@ -1672,7 +1750,13 @@ pub async fn get_my_request_detail(
         None,
     )
     .await;
-    Ok(Json(detail_response(log, detail, token_name, username)?))
+    let mut response = detail_response(log, detail, token_name, username)?;
+    response.debug_capture = app_state
+        .log_store
+        .get_debug_capture(request_id)
+        .await
+        .map_err(GatewayError::Db)?;
+    Ok(Json(response))
 }
 
 pub async fn get_admin_request_detail(
@@ -1711,7 +1795,13 @@ pub async fn get_admin_request_detail(
         None,
     )
     .await;
-    Ok(Json(detail_response(log, detail, token_name, username)?))
+    let mut response = detail_response(log, detail, token_name, username)?;
+    response.debug_capture = app_state
+        .log_store
+        .get_debug_capture(request_id)
+        .await
+        .map_err(GatewayError::Db)?;
+    Ok(Json(response))
 }
 
 pub async fn replay_my_request(
@@ -1742,6 +1832,9 @@ pub async fn replay_my_request(
         &format!("/me/requests/{request_id}/replay"),
         REQ_TYPE_CHAT_REPLAY,
         Some(snapshot_json),
+        false,
+        None,
+        None,
     )
     .await?;
     Ok(Json(replay_response(request_id, requested_model, &result)))
@@ -2224,6 +2317,9 @@ pub async fn create_compare(
                         "/me/compare",
                         REQ_TYPE_CHAT_COMPARE,
                         Some(snapshot_json),
+                        false,
+                        None,
+                        None,
                     )
                     .await;
                     let item = match executed {
@@ -2360,7 +2456,6 @@ mod tests {
     };
     use crate::server::AppState;
     use crate::server::handlers::auth::{AccessTokenClaims, issue_access_token};
-    use crate::server::login::LoginManager;
     use crate::server::storage_traits::RequestLogStore;
     use crate::users::{CreateUserPayload, UserRole, UserStatus};
     use axum::http::{HeaderMap, HeaderValue, header::AUTHORIZATION};
@@ -2400,22 +2495,7 @@ mod tests {
                 .unwrap(),
         );
 
-        Arc::new(AppState {
-            config: settings,
-            load_balancer_state: Arc::new(crate::routing::LoadBalancerState::default()),
-            log_store: logger.clone(),
-            model_cache: logger.clone(),
-            providers: logger.clone(),
-            token_store: logger.clone(),
-            favorites_store: logger.clone(),
-            organizations: logger.clone(),
-            login_manager: Arc::new(LoginManager::new(logger.clone())),
-            user_store: logger.clone(),
-            refresh_token_store: logger.clone(),
-            password_reset_token_store: logger.clone(),
-            balance_store: logger.clone(),
-            subscription_store: logger.clone(),
-        })
+        crate::server::test_harness::app_state(settings, logger.clone())
     }
 
     fn ensure_test_jwt_secret() {
@@ -2435,6 +2515,7 @@ mod tests {
             jti: None,
             exp: (Utc::now() + Duration::hours(1)).timestamp(),
             iat: Some(Utc::now().timestamp()),
+            org_id: None,
         };
         let token = issue_access_token(&claims).unwrap();
         let mut headers = HeaderMap::new();
@@ -2465,20 +2546,9 @@ mod tests {
         let token = app_state
             .token_store
             .create_token(CreateTokenPayload {
-                id: None,
                 user_id: Some(user.id.clone()),
                 name: Some("Lab Token".into()),
-                token: None,
-                allowed_models: None,
-                model_blacklist: None,
-                max_tokens: None,
-                max_amount: None,
-                enabled: true,
-                expires_at: None,
-                remark: None,
-                organization_id: None,
-                ip_whitelist: None,
-                ip_blacklist: None,
+                ..Default::default()
             })
             .await
             .unwrap();
@@ -2507,6 +2577,8 @@ mod tests {
                 cached_tokens: None,
                 reasoning_tokens: None,
                 error_message: None,
+                latency: Default::default(),
+                profile: Default::default(),
             })
             .await
             .unwrap();
@@ -2540,6 +2612,9 @@ mod tests {
                 selected_provider: Some("openai".into()),
                 selected_key_id: Some("sk-****".into()),
                 first_token_latency_ms: Some(66),
+                param_policy_applied: None,
+                provider_override: None,
+                prompt_truncation: None,
             })
             .await
             .unwrap();
@@ -2727,6 +2802,8 @@ mod tests {
             cached_tokens: None,
             reasoning_tokens: None,
             error_message: None,
+            latency: Default::default(),
+            profile: Default::default(),
         };
         let detail = RequestLogDetailRecord {
             request_log_id: 42,
@@ -2738,6 +2815,9 @@ mod tests {
             selected_provider: Some("openai".into()),
             selected_key_id: Some("sk-****".into()),
             first_token_latency_ms: Some(88),
+            param_policy_applied: None,
+            provider_override: None,
+            prompt_truncation: None,
         };
 
         let response = detail_response(
@@ -2778,6 +2858,8 @@ mod tests {
             cached_tokens: None,
             reasoning_tokens: None,
             error_message: None,
+            latency: Default::default(),
+            profile: Default::default(),
         };
         let detail = RequestLogDetailRecord {
             request_log_id: 77,
@@ -2799,6 +2881,9 @@ mod tests {
             selected_provider: Some("openai".into()),
             selected_key_id: Some("sk-****".into()),
             first_token_latency_ms: Some(45),
+            param_policy_applied: None,
+            provider_override: None,
+            prompt_truncation: None,
         };
         let compare = super::CompareResponse {
             id: "cmp_live".into(),
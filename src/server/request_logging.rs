@@ -2,11 +2,16 @@ use crate::admin::client_token_id_for_token;
 use crate::balance::BalanceTransactionKind;
 use crate::error::GatewayError;
 use crate::logging::RequestLog;
-use crate::logging::types::{REQ_TYPE_CHAT_ONCE, RequestLogDetailRecord};
+use crate::logging::payload_archive;
+use crate::logging::types::{
+    LatencyBreakdown, PromptProfile, REQ_TYPE_CHAT_ONCE, RequestLogDetailRecord,
+};
 use crate::providers::openai::types::RawAndTypedChatCompletion;
 use crate::providers::openai::usage::resolved_usage;
 use crate::server::AppState;
 use crate::server::response_text;
+use crate::server::token_usage_buffer::{CountedUsage, UsageSection, usage_section};
+use crate::server::usage_webhooks::{self, UsageEvent};
 use crate::server::util::mask_key;
 use chrono::{DateTime, Utc};
 
@@ -19,6 +24,17 @@ pub struct ChatLogContext {
     pub selected_provider: Option<String>,
     pub selected_key_id: Option<String>,
     pub first_token_latency_ms: Option<i64>,
+    pub param_policy_applied: Option<String>,
+    pub provider_override: Option<String>,
+    /// 自动截断超长对话的记录（JSON 对象）
+    pub prompt_truncation: Option<String>,
+    /// X-Gateway-Debug: capture：另存完整请求/响应正文
+    pub debug_capture: bool,
+    /// 上游调用的起止时间，用于拆分网关开销与上游耗时
+    pub upstream_started_at: Option<DateTime<Utc>>,
+    pub upstream_finished_at: Option<DateTime<Utc>>,
+    /// 提示语言/内容类别（仅在开启 prompt_profiling 时填充）
+    pub prompt_profile: PromptProfile,
 }
 
 #[derive(Debug, Clone, Default)]
@@ -80,16 +96,27 @@ pub async fn log_chat_request(
         }
         Err(_) => None,
     };
+    // 供密钥轮换跟踪每个上游 key 的 tpm 窗口与剩余额度
+    if let Some(masked) = api_key.as_deref() {
+        app_state.load_balancer_state.record_key_usage(
+            provider_name,
+            masked,
+            usage.as_ref().map(|u| u.total_tokens),
+            amount_spent,
+        );
+    }
+    crate::server::provider_budget::record_spend(app_state, provider_name, amount_spent).await;
 
+    let path = if context.path.is_empty() {
+        "/v1/chat/completions".to_string()
+    } else {
+        context.path.clone()
+    };
     let log = RequestLog {
         id: None,
         timestamp: start_time,
         method: "POST".to_string(),
-        path: if context.path.is_empty() {
-            "/v1/chat/completions".to_string()
-        } else {
-            context.path.clone()
-        },
+        path: path.clone(),
         request_type: if context.request_type.is_empty() {
             REQ_TYPE_CHAT_ONCE.to_string()
         } else {
@@ -121,20 +148,69 @@ pub async fn log_chat_request(
                 .and_then(|details| details.reasoning_tokens)
         }),
         error_message: response.as_ref().err().map(|e| e.to_string()),
+        latency: LatencyBreakdown::from_marks(
+            start_time,
+            context.upstream_started_at,
+            context.upstream_finished_at,
+            end_time,
+        ),
+        profile: context.prompt_profile.clone(),
     };
 
-    let log_id = match app_state.log_store.log_request(log).await {
+    // 增量更新 client_tokens：金额与 tokens（仅当有 usage/金额 与 Client Token 时）；先于请求日志入账
+    let counted = match client_token {
+        Some(tok) => {
+            count_token_usage(
+                app_state,
+                tok,
+                amount_spent,
+                usage.as_ref().map(|u| {
+                    (
+                        u.prompt_tokens as i64,
+                        u.completion_tokens as i64,
+                        u.total_tokens as i64,
+                    )
+                }),
+            )
+            .await
+        }
+        None => None,
+    };
+    let usage_event = UsageEvent::from_request_log(&log);
+    let log_id = match crate::server::degraded::log_request(app_state.log_store.as_ref(), log).await
+    {
         Ok(id) => Some(id),
         Err(e) => {
             tracing::error!("Failed to log request: {}", e);
             None
         }
     };
+    if let Some(counted) = counted {
+        counted.logged(log_id).await;
+    }
+    usage_webhooks::enqueue_for_request(app_state, client_token, usage_event, log_id).await;
 
     if let Some(request_log_id) = log_id {
+        if context.debug_capture {
+            let response_body = match response {
+                Ok(dual) => dual.raw.to_string(),
+                Err(e) => e.to_string(),
+            };
+            crate::server::debug_capture::save(
+                app_state,
+                request_log_id,
+                context.request_payload_snapshot.clone(),
+                Some(response_body),
+                end_time,
+            )
+            .await;
+        }
         let detail = RequestLogDetailRecord {
             request_log_id,
-            request_payload_snapshot: context.request_payload_snapshot,
+            request_payload_snapshot: payload_archive::cap_captured_body(
+                context.request_payload_snapshot,
+                app_state.config.server.capture_body_max_bytes,
+            ),
             response_preview: response_preview(response),
             upstream_status: context.upstream_status.or(Some(if response.is_ok() {
                 200
@@ -150,38 +226,19 @@ pub async fn log_chat_request(
                 .selected_key_id
                 .or_else(|| Some(mask_key(api_key_raw))),
             first_token_latency_ms: context.first_token_latency_ms,
+            param_policy_applied: context.param_policy_applied,
+            provider_override: context.provider_override,
+            prompt_truncation: context.prompt_truncation,
         };
         if let Err(e) = app_state.log_store.upsert_request_log_detail(detail).await {
             tracing::warn!("Failed to upsert request log detail: {}", e);
         }
     }
 
-    // 增量更新 client_tokens：金额与 tokens（仅当有 usage/金额 与 Client Token 时）
     if let Some(tok) = client_token {
-        // 1) update money spent (for statistics) when pricing is available
-        if let Some(delta) = amount_spent {
-            if let Err(e) = app_state.token_store.add_amount_spent(tok, delta).await {
-                tracing::warn!("Failed to update token spent: {}", e);
-            }
-        }
+        let tokens_used = usage.as_ref().map(|u| u.total_tokens as i64);
 
-        // 2) update token usage counters + compute tokens used for subscription billing
-        let mut tokens_used: Option<i64> = None;
-        if let Some(u) = usage.as_ref() {
-            let prompt = u.prompt_tokens as i64;
-            let completion = u.completion_tokens as i64;
-            let total = u.total_tokens as i64;
-            tokens_used = Some(total);
-            if let Err(e) = app_state
-                .token_store
-                .add_usage_spent(tok, prompt, completion, total)
-                .await
-            {
-                tracing::warn!("Failed to update token tokens: {}", e);
-            }
-        }
-
-        // 3) subscription billing: user-bound tokens deduct from user.balance (unit: tokens)
+        // subscription billing: user-bound tokens deduct from user.balance (unit: tokens)
         if let Some(total_tokens) = tokens_used.filter(|v| *v > 0) {
             if let Ok(Some(t)) = app_state.token_store.get_token(tok).await {
                 if let Some(user_id) = t.user_id.as_deref() {
@@ -194,7 +251,7 @@ pub async fn log_chat_request(
                         Ok(Some(new_balance)) => {
                             let meta = serde_json::json!({
                                 "client_token_id": t.id,
-                                "path": "/v1/chat/completions",
+                                "path": path,
                                 "total_tokens": total_tokens,
                                 "amount_spent": amount_spent,
                             })
@@ -235,6 +292,33 @@ pub async fn log_chat_request(
     }
 }
 
+/// 令牌计数入账：金额、tokens，以及令牌交换签发的子令牌向各级父令牌的汇总。
+/// 须在写入请求日志之前调用（批量写库先读日志水位再取计数），
+/// 返回值在日志写入后交给 [`CountedUsage::logged`]；未启用延迟写入时返回 None
+pub async fn count_token_usage<'a>(
+    app_state: &'a AppState,
+    tok: &str,
+    amount_spent: Option<f64>,
+    usage: Option<(i64, i64, i64)>,
+) -> Option<CountedUsage<'a>> {
+    let usage_section = usage_section(app_state).await;
+    if let Some(delta) = amount_spent
+        && let Err(e) = app_state.token_store.add_amount_spent(tok, delta).await
+    {
+        tracing::warn!("Failed to update token spent: {}", e);
+    }
+    if let Some((prompt, completion, total)) = usage
+        && let Err(e) = app_state
+            .token_store
+            .add_usage_spent(tok, prompt, completion, total)
+            .await
+    {
+        tracing::warn!("Failed to update token tokens: {}", e);
+    }
+    crate::server::token_lineage::roll_up_to_parents(app_state, tok, amount_spent, usage).await;
+    usage_section.map(UsageSection::close)
+}
+
 // 记录普通请求（不含 tokens）
 #[allow(clippy::too_many_arguments)]
 pub async fn log_simple_request(
@@ -274,9 +358,11 @@ pub async fn log_simple_request(
         cached_tokens: None,
         reasoning_tokens: None,
         error_message,
+        latency: LatencyBreakdown::default(),
+        profile: Default::default(),
     };
 
-    if let Err(e) = app_state.log_store.log_request(log).await {
+    if let Err(e) = crate::server::degraded::log_request(app_state.log_store.as_ref(), log).await {
         tracing::error!("Failed to log request: {}", e);
     }
 }
@@ -288,8 +374,6 @@ mod tests {
     use crate::balance::BalanceStore;
     use crate::config::settings::{BalanceStrategy, LoadBalancing, LoggingConfig, ServerConfig};
     use crate::logging::DatabaseLogger;
-    use crate::server::AppState;
-    use crate::server::login::LoginManager;
     use crate::users::{CreateUserPayload, UserRole, UserStatus, UserStore};
     use std::sync::Arc;
     use tempfile::tempdir;
@@ -319,22 +403,7 @@ mod tests {
             },
         };
 
-        let app_state = AppState {
-            config: settings,
-            load_balancer_state: Arc::new(crate::routing::LoadBalancerState::default()),
-            log_store: logger.clone(),
-            model_cache: logger.clone(),
-            providers: logger.clone(),
-            token_store: logger.clone(),
-            favorites_store: logger.clone(),
-            organizations: logger.clone(),
-            login_manager: Arc::new(LoginManager::new(logger.clone())),
-            user_store: logger.clone(),
-            refresh_token_store: logger.clone(),
-            password_reset_token_store: logger.clone(),
-            balance_store: logger.clone(),
-            subscription_store: logger.clone(),
-        };
+        let app_state = crate::server::test_harness::app_state(settings, logger.clone());
 
         // model pricing needed for amount_spent
         logger
@@ -351,20 +420,8 @@ mod tests {
 
         let created = logger
             .create_token(CreateTokenPayload {
-                id: None,
-                user_id: None,
                 name: Some("t1".into()),
-                token: None,
-                allowed_models: None,
-                model_blacklist: None,
-                max_tokens: None,
-                max_amount: None,
-                enabled: true,
-                expires_at: None,
-                remark: None,
-                organization_id: None,
-                ip_whitelist: None,
-                ip_blacklist: None,
+                ..Default::default()
             })
             .await
             .unwrap();
@@ -441,22 +498,7 @@ mod tests {
             },
         };
 
-        let app_state = AppState {
-            config: settings,
-            load_balancer_state: Arc::new(crate::routing::LoadBalancerState::default()),
-            log_store: logger.clone(),
-            model_cache: logger.clone(),
-            providers: logger.clone(),
-            token_store: logger.clone(),
-            favorites_store: logger.clone(),
-            organizations: logger.clone(),
-            login_manager: Arc::new(LoginManager::new(logger.clone())),
-            user_store: logger.clone(),
-            refresh_token_store: logger.clone(),
-            password_reset_token_store: logger.clone(),
-            balance_store: logger.clone(),
-            subscription_store: logger.clone(),
-        };
+        let app_state = crate::server::test_harness::app_state(settings, logger.clone());
 
         logger
             .upsert_model_price(crate::logging::ModelPriceUpsert::manual(
@@ -489,20 +531,9 @@ mod tests {
 
         let created = logger
             .create_token(CreateTokenPayload {
-                id: None,
                 user_id: Some(user.id.clone()),
                 name: Some("t1".into()),
-                token: None,
-                allowed_models: None,
-                model_blacklist: None,
-                max_tokens: None,
-                max_amount: None,
-                enabled: true,
-                expires_at: None,
-                remark: None,
-                organization_id: None,
-                ip_whitelist: None,
-                ip_blacklist: None,
+                ..Default::default()
             })
             .await
             .unwrap();
@@ -572,22 +603,7 @@ mod tests {
             },
         };
 
-        let app_state = AppState {
-            config: settings,
-            load_balancer_state: Arc::new(crate::routing::LoadBalancerState::default()),
-            log_store: logger.clone(),
-            model_cache: logger.clone(),
-            providers: logger.clone(),
-            token_store: logger.clone(),
-            favorites_store: logger.clone(),
-            organizations: logger.clone(),
-            login_manager: Arc::new(LoginManager::new(logger.clone())),
-            user_store: logger.clone(),
-            refresh_token_store: logger.clone(),
-            password_reset_token_store: logger.clone(),
-            balance_store: logger.clone(),
-            subscription_store: logger.clone(),
-        };
+        let app_state = crate::server::test_harness::app_state(settings, logger.clone());
 
         logger
             .upsert_model_price(crate::logging::ModelPriceUpsert::manual(
@@ -603,20 +619,8 @@ mod tests {
 
         let created = logger
             .create_token(CreateTokenPayload {
-                id: None,
-                user_id: None,
                 name: Some("t1".into()),
-                token: None,
-                allowed_models: None,
-                model_blacklist: None,
-                max_tokens: None,
-                max_amount: None,
-                enabled: true,
-                expires_at: None,
-                remark: None,
-                organization_id: None,
-                ip_whitelist: None,
-                ip_blacklist: None,
+                ..Default::default()
             })
             .await
             .unwrap();
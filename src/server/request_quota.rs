@@ -0,0 +1,315 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::admin::ClientToken;
+use crate::error::GatewayError;
+use crate::logging::time::BEIJING_OFFSET;
+use crate::logging::types::TokenRequestCount;
+use crate::server::storage_traits::RequestLogStore;
+
+/// 内存计数写入 token_request_counts 并回读汇总的间隔（多实例部署时各实例据此对齐）
+const SYNC_INTERVAL_SECS: u64 = 60;
+
+/// 北京时间日期，如 2026-10-16
+fn day_of(now: DateTime<Utc>) -> String {
+    now.with_timezone(&BEIJING_OFFSET)
+        .format("%Y-%m-%d")
+        .to_string()
+}
+
+/// 令牌的请求次数用量
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct RequestUsage {
+    pub total: i64,
+    pub today: i64,
+}
+
+/// 令牌请求次数额度的使用情况（用于余额/令牌信息接口）
+#[derive(Debug, Clone, Serialize)]
+pub struct RequestQuotaStatus {
+    pub requests_total: i64,
+    pub requests_today: i64,
+    pub max_requests: Option<i64>,
+    pub max_requests_per_day: Option<i64>,
+    pub remaining_requests: Option<i64>,
+    pub remaining_requests_today: Option<i64>,
+}
+
+impl RequestQuotaStatus {
+    pub fn new(token: &ClientToken, usage: RequestUsage) -> Self {
+        Self {
+            requests_total: usage.total,
+            requests_today: usage.today,
+            max_requests: token.max_requests,
+            max_requests_per_day: token.max_requests_per_day,
+            remaining_requests: token.max_requests.map(|m| (m - usage.total).max(0)),
+            remaining_requests_today: token.max_requests_per_day.map(|m| (m - usage.today).max(0)),
+        }
+    }
+}
+
+#[derive(Default)]
+struct QuotaState {
+    /// 最近一次从存储回读的汇总：token_id -> 用量（today 对应 synced_day）
+    synced: HashMap<String, RequestUsage>,
+    synced_day: String,
+    /// 尚未写入存储的计数：(day, token_id) -> 次数
+    pending: HashMap<(String, String), i64>,
+}
+
+impl QuotaState {
+    fn usage(&self, token_id: &str, day: &str) -> RequestUsage {
+        let mut usage = self.synced.get(token_id).copied().unwrap_or_default();
+        if self.synced_day != day {
+            usage.today = 0;
+        }
+        for ((d, id), n) in &self.pending {
+            if id == token_id {
+                usage.total += n;
+                if d == day {
+                    usage.today += n;
+                }
+            }
+        }
+        usage
+    }
+
+    fn add(&mut self, rows: &[TokenRequestCount], sign: i64) {
+        for row in rows {
+            let entry = self.synced.entry(row.token_id.clone()).or_default();
+            entry.total += sign * row.requests;
+            if row.day == self.synced_day {
+                entry.today += sign * row.requests;
+            }
+        }
+    }
+}
+
+fn check_usage(token: &ClientToken, usage: RequestUsage) -> Result<(), GatewayError> {
+    if token.max_requests.is_some_and(|max| usage.total >= max) {
+        return Err(GatewayError::Config("token request quota exceeded".into()));
+    }
+    if token
+        .max_requests_per_day
+        .is_some_and(|max| usage.today >= max)
+    {
+        return Err(GatewayError::RateLimited(
+            "token daily request quota exceeded".into(),
+        ));
+    }
+    Ok(())
+}
+
+/// 客户端令牌请求次数计数：准入时在内存中校验并累加，
+/// 由后台任务定期写入按天汇总表并回读全部实例的累计值
+#[derive(Default)]
+pub struct RequestQuotaCounter {
+    state: Mutex<QuotaState>,
+}
+
+impl RequestQuotaCounter {
+    fn lock(&self) -> std::sync::MutexGuard<'_, QuotaState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn usage(&self, token_id: &str, now: DateTime<Utc>) -> RequestUsage {
+        self.lock().usage(token_id, &day_of(now))
+    }
+
+    /// 是否已达到累计/每日上限，不计入请求
+    pub fn check(&self, token: &ClientToken, now: DateTime<Utc>) -> Result<(), GatewayError> {
+        check_usage(token, self.usage(&token.id, now))
+    }
+
+    /// 未超出累计/每日上限时计入一次请求
+    pub fn try_acquire(&self, token: &ClientToken, now: DateTime<Utc>) -> Result<(), GatewayError> {
+        let day = day_of(now);
+        let mut state = self.lock();
+        check_usage(token, state.usage(&token.id, &day))?;
+        *state.pending.entry((day, token.id.clone())).or_default() += 1;
+        Ok(())
+    }
+
+    /// 取出未写库的计数，并先并入已同步的汇总，避免写库与回读之间出现少计
+    fn take_pending(&self) -> Vec<TokenRequestCount> {
+        let mut state = self.lock();
+        let rows: Vec<TokenRequestCount> = std::mem::take(&mut state.pending)
+            .into_iter()
+            .map(|((day, token_id), requests)| TokenRequestCount {
+                day,
+                token_id,
+                requests,
+            })
+            .collect();
+        state.add(&rows, 1);
+        rows
+    }
+
+    /// 写库失败时放回计数，下次再试
+    fn restore(&self, rows: Vec<TokenRequestCount>) {
+        let mut state = self.lock();
+        state.add(&rows, -1);
+        for row in rows {
+            *state.pending.entry((row.day, row.token_id)).or_default() += row.requests;
+        }
+    }
+
+    async fn sync(
+        &self,
+        log_store: &(dyn RequestLogStore + Send + Sync),
+        now: DateTime<Utc>,
+    ) -> rusqlite::Result<()> {
+        let rows = self.take_pending();
+        if !rows.is_empty()
+            && let Err(e) = log_store.add_token_request_counts(rows.clone()).await
+        {
+            self.restore(rows);
+            return Err(e);
+        }
+        let day = day_of(now);
+        let totals = log_store.sum_token_request_counts(&day).await?;
+        let mut state = self.lock();
+        state.synced = totals
+            .into_iter()
+            .map(|(id, (total, today))| (id, RequestUsage { total, today }))
+            .collect();
+        state.synced_day = day;
+        Ok(())
+    }
+}
+
+/// 定期写入并回读请求次数（启动时立即回读一次）；关闭时最后写一次
+pub fn spawn_sync_task(
+    tasks: &Arc<crate::server::tasks::TaskRegistry>,
+    counter: Arc<RequestQuotaCounter>,
+    log_store: Arc<dyn RequestLogStore + Send + Sync>,
+) {
+    tasks.spawn_with("request_quota_sync", |mut ctx| async move {
+        let mut ticker = tokio::time::interval(std::time::Duration::from_secs(SYNC_INTERVAL_SECS));
+        loop {
+            let stopping = tokio::select! {
+                _ = ticker.tick() => false,
+                _ = ctx.cancelled() => true,
+            };
+            if let Err(e) = counter.sync(log_store.as_ref(), Utc::now()).await {
+                tracing::warn!("Failed to sync token request counts: {}", e);
+                ctx.report_error(e);
+            }
+            if stopping {
+                break;
+            }
+        }
+    });
+}
+
+/// 请求次数上限需为正整数
+pub fn validate_limit(field: &str, value: Option<i64>) -> Result<(), GatewayError> {
+    if value.is_some_and(|v| v <= 0) {
+        return Err(GatewayError::Config(format!(
+            "{} must be a positive integer",
+            field
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logging::DatabaseLogger;
+    use chrono::TimeZone;
+    use tempfile::tempdir;
+
+    fn token(max_requests: Option<i64>, max_requests_per_day: Option<i64>) -> ClientToken {
+        ClientToken {
+            id: "atk_quota".into(),
+            user_id: None,
+            name: "quota".into(),
+            token: "tok".into(),
+            allowed_models: None,
+            model_blacklist: None,
+            max_tokens: None,
+            max_amount: None,
+            enabled: true,
+            expires_at: None,
+            created_at: Utc::now(),
+            amount_spent: 0.0,
+            prompt_tokens_spent: 0,
+            completion_tokens_spent: 0,
+            total_tokens_spent: 0,
+            remark: None,
+            organization_id: None,
+            ip_whitelist: None,
+            ip_blacklist: None,
+            allow_streaming: true,
+            sandbox: false,
+            strip_reasoning: false,
+            usage_webhook_url: None,
+            signing_secret: None,
+            require_signature: false,
+            parent_token_id: None,
+            allow_debug_capture: false,
+            allow_provider_override: false,
+            auto_truncate_prompt: false,
+            semantic_cache: false,
+            allow_login_codes: false,
+            max_requests,
+            max_requests_per_day,
+            watermark_responses: false,
+            queue_weight: None,
+            usage_webhook_secret: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn enforces_daily_and_total_limits_across_syncs() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("quota.db");
+        let logger = DatabaseLogger::new(db_path.to_str().unwrap())
+            .await
+            .unwrap();
+        let counter = RequestQuotaCounter::default();
+        // 北京时间 10-16 23:00 与次日 01:00
+        let day1 = Utc.with_ymd_and_hms(2026, 10, 16, 15, 0, 0).unwrap();
+        let day2 = Utc.with_ymd_and_hms(2026, 10, 16, 17, 0, 0).unwrap();
+        let limited = token(Some(3), Some(2));
+
+        counter.try_acquire(&limited, day1).unwrap();
+        counter.sync(&logger, day1).await.unwrap();
+        counter.try_acquire(&limited, day1).unwrap();
+        assert!(matches!(
+            counter.try_acquire(&limited, day1),
+            Err(GatewayError::RateLimited(_))
+        ));
+        assert_eq!(
+            counter.usage(&limited.id, day1),
+            RequestUsage { total: 2, today: 2 }
+        );
+
+        counter.sync(&logger, day2).await.unwrap();
+        assert_eq!(
+            counter.usage(&limited.id, day2),
+            RequestUsage { total: 2, today: 0 }
+        );
+        counter.try_acquire(&limited, day2).unwrap();
+        assert!(matches!(
+            counter.try_acquire(&limited, day2),
+            Err(GatewayError::Config(_))
+        ));
+
+        // 新实例从存储回读累计值
+        counter.sync(&logger, day2).await.unwrap();
+        let other = RequestQuotaCounter::default();
+        other.sync(&logger, day2).await.unwrap();
+        assert_eq!(
+            other.usage(&limited.id, day2),
+            RequestUsage { total: 3, today: 1 }
+        );
+        let status = RequestQuotaStatus::new(&limited, other.usage(&limited.id, day2));
+        assert_eq!(status.remaining_requests, Some(0));
+        assert_eq!(status.remaining_requests_today, Some(1));
+    }
+}
@@ -0,0 +1,324 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use axum::{
+    body::{Body, Bytes},
+    extract::{Request, State},
+    http::{HeaderMap, HeaderValue, header::AUTHORIZATION},
+    middleware::Next,
+    response::Response,
+};
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+
+use crate::admin::ClientToken;
+use crate::error::GatewayError;
+use crate::server::AppState;
+use crate::server::util::bearer_token;
+
+/// 签名认证请求头：令牌 ID（不是令牌明文）
+pub const TOKEN_ID_HEADER: &str = "x-gateway-token-id";
+/// Unix 秒；与服务器时间相差超过 MAX_CLOCK_SKEW_SECS 的请求直接拒绝
+pub const TIMESTAMP_HEADER: &str = "x-gateway-timestamp";
+/// 每个请求唯一的随机串，窗口期内重复出现视为重放
+pub const NONCE_HEADER: &str = "x-gateway-nonce";
+/// `sha256=<hex(HMAC-SHA256(signing_secret, canonical_request))>`
+pub const SIGNATURE_HEADER: &str = "x-gateway-signature";
+
+const MAX_CLOCK_SKEW_SECS: i64 = 300;
+const NONCE_MAX_LEN: usize = 128;
+/// 与 axum Json 提取器默认上限一致
+const SIGNED_BODY_MAX_BYTES: usize = 2 * 1024 * 1024;
+/// 集群内部请求在 nonce 缓存中的命名空间（令牌 ID 不会以冒号开头）
+const PEER_NONCE_SCOPE: &str = ":cluster";
+
+/// 待签名串：`METHOD\npath?query\ntimestamp\nnonce\nhex(sha256(body))`
+pub fn canonical_request(
+    method: &str,
+    path_and_query: &str,
+    timestamp: i64,
+    nonce: &str,
+    body: &[u8],
+) -> String {
+    format!(
+        "{}\n{}\n{}\n{}\n{}",
+        method.to_ascii_uppercase(),
+        path_and_query,
+        timestamp,
+        nonce,
+        hex::encode(Sha256::digest(body))
+    )
+}
+
+pub(crate) fn sign_request(secret: &str, canonical: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(canonical.as_bytes());
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// 常量时间比较签名，避免时序侧信道
+fn signature_matches(secret: &str, canonical: &str, provided: &str) -> bool {
+    let Some(sig) = provided
+        .strip_prefix("sha256=")
+        .and_then(|h| hex::decode(h).ok())
+    else {
+        return false;
+    };
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(canonical.as_bytes());
+    mac.verify_slice(&sig).is_ok()
+}
+
+/// 已使用的 (token_id, nonce)，保留到时间窗口结束；超出窗口的请求已被时间戳校验拒绝
+#[derive(Default)]
+pub struct NonceCache {
+    seen: Mutex<HashMap<(String, String), i64>>,
+}
+
+impl NonceCache {
+    /// 首次出现返回 true；重复出现（重放）返回 false
+    fn check_and_insert(&self, token_id: &str, nonce: &str, now: i64) -> bool {
+        let mut seen = self.seen.lock().unwrap_or_else(|e| e.into_inner());
+        seen.retain(|_, expires_at| *expires_at >= now);
+        let key = (token_id.to_string(), nonce.to_string());
+        if seen.contains_key(&key) {
+            return false;
+        }
+        seen.insert(key, now + 2 * MAX_CLOCK_SKEW_SECS);
+        true
+    }
+}
+
+fn header_str<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+}
+
+/// 使用 Client Token 的调用方接口：OpenAI 兼容的 /v1/* 与智谱原生入口
+pub(crate) fn is_client_api_path(path: &str) -> bool {
+    let path = path.strip_prefix("/api").unwrap_or(path);
+    path.starts_with("/v1/") || path.starts_with("/paas/v4/") || path.starts_with("/v1beta/")
+}
+
+/// 生成集群内部请求的签名头（时间戳、nonce、签名），密钥为 cluster_secret
+pub(crate) fn peer_signature_headers(
+    secret: &str,
+    method: &str,
+    path: &str,
+    body: &[u8],
+) -> [(&'static str, String); 3] {
+    let timestamp = Utc::now().timestamp();
+    let nonce = hex::encode(rand::random::<[u8; 16]>());
+    let signature = sign_request(
+        secret,
+        &canonical_request(method, path, timestamp, &nonce, body),
+    );
+    [
+        (TIMESTAMP_HEADER, timestamp.to_string()),
+        (NONCE_HEADER, nonce),
+        (SIGNATURE_HEADER, signature),
+    ]
+}
+
+/// 校验集群内部请求：与客户端签名使用相同的待签名串、时间窗口与 nonce 防重放
+pub(crate) fn verify_peer_request(
+    nonces: &NonceCache,
+    secret: &str,
+    method: &str,
+    path: &str,
+    headers: &HeaderMap,
+    body: &[u8],
+) -> Result<(), GatewayError> {
+    let unauthorized = |msg: &str| GatewayError::Unauthorized(msg.to_string());
+    let signature = header_str(headers, SIGNATURE_HEADER)
+        .ok_or_else(|| unauthorized("missing request signature"))?;
+    let timestamp = header_str(headers, TIMESTAMP_HEADER)
+        .and_then(|v| v.parse::<i64>().ok())
+        .ok_or_else(|| unauthorized("missing or invalid signature timestamp"))?;
+    let nonce = header_str(headers, NONCE_HEADER)
+        .filter(|n| n.len() <= NONCE_MAX_LEN)
+        .ok_or_else(|| unauthorized("missing or invalid signature nonce"))?;
+    let now = Utc::now().timestamp();
+    if (now - timestamp).abs() > MAX_CLOCK_SKEW_SECS {
+        return Err(unauthorized("signature timestamp outside allowed window"));
+    }
+    let canonical = canonical_request(method, path, timestamp, nonce, body);
+    if !signature_matches(secret, &canonical, signature) {
+        return Err(unauthorized("invalid signature"));
+    }
+    if !nonces.check_and_insert(PEER_NONCE_SCOPE, nonce, now) {
+        return Err(unauthorized("replayed request"));
+    }
+    Ok(())
+}
+
+/// 校验签名请求，成功时返回令牌（其明文供后续按 Bearer Token 走原有鉴权流程）
+pub async fn verify_signed_request(
+    app_state: &AppState,
+    method: &str,
+    path_and_query: &str,
+    headers: &HeaderMap,
+    body: &[u8],
+) -> Result<ClientToken, GatewayError> {
+    let unauthorized = |msg: &str| GatewayError::Unauthorized(msg.to_string());
+    let token_id = header_str(headers, TOKEN_ID_HEADER)
+        .ok_or_else(|| unauthorized("missing signature token id"))?;
+    let signature = header_str(headers, SIGNATURE_HEADER)
+        .ok_or_else(|| unauthorized("missing request signature"))?;
+    let timestamp = header_str(headers, TIMESTAMP_HEADER)
+        .and_then(|v| v.parse::<i64>().ok())
+        .ok_or_else(|| unauthorized("missing or invalid signature timestamp"))?;
+    let nonce = header_str(headers, NONCE_HEADER)
+        .filter(|n| n.len() <= NONCE_MAX_LEN)
+        .ok_or_else(|| unauthorized("missing or invalid signature nonce"))?;
+
+    let now = Utc::now().timestamp();
+    if (now - timestamp).abs() > MAX_CLOCK_SKEW_SECS {
+        return Err(unauthorized("signature timestamp outside allowed window"));
+    }
+    let token = app_state
+        .token_store
+        .get_token_by_id(token_id)
+        .await?
+        .ok_or_else(|| unauthorized("invalid signature"))?;
+    let Some(secret) = token.signing_secret.as_deref() else {
+        return Err(unauthorized(
+            "request signing is not enabled for this token",
+        ));
+    };
+    let canonical = canonical_request(method, path_and_query, timestamp, nonce, body);
+    if !signature_matches(secret, &canonical, signature) {
+        return Err(unauthorized("invalid signature"));
+    }
+    // 签名通过后再记录 nonce，避免未签名的伪造请求占用合法 nonce
+    if !app_state
+        .signature_nonces
+        .check_and_insert(&token.id, nonce, now)
+    {
+        return Err(unauthorized("replayed request"));
+    }
+    Ok(token)
+}
+
+/// `/v1/*` 签名认证中间件：
+/// - 携带签名头：校验签名/时间戳/nonce 后注入 `Authorization: Bearer <token>`
+/// - 仅携带 Bearer Token：令牌开启 require_signature 时拒绝
+///
+/// 两种情况下查到的令牌都写入请求扩展 [`ResolvedClientToken`]
+pub async fn enforce(
+    State(app_state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Result<Response, GatewayError> {
+    if !is_client_api_path(request.uri().path()) {
+        return Ok(next.run(request).await);
+    }
+    if request.headers().contains_key(SIGNATURE_HEADER) {
+        let (mut parts, body) = request.into_parts();
+        let bytes: Bytes = axum::body::to_bytes(body, SIGNED_BODY_MAX_BYTES)
+            .await
+            .map_err(|_| GatewayError::Config("request body too large".into()))?;
+        let path_and_query = parts
+            .uri
+            .path_and_query()
+            .map(|p| p.as_str())
+            .unwrap_or("/");
+        let token = verify_signed_request(
+            &app_state,
+            parts.method.as_str(),
+            path_and_query,
+            &parts.headers,
+            &bytes,
+        )
+        .await?;
+        let bearer = HeaderValue::from_str(&format!("Bearer {}", token.token))
+            .map_err(|_| GatewayError::Unauthorized("invalid token".into()))?;
+        parts.headers.insert(AUTHORIZATION, bearer);
+        parts.extensions.insert(ResolvedClientToken(token));
+        return Ok(next
+            .run(Request::from_parts(parts, Body::from(bytes)))
+            .await);
+    }
+    let mut request = request;
+    if let Some(tok) = bearer_token(request.headers())
+        && let Some(t) = app_state.token_store.get_token(&tok).await?
+    {
+        if t.require_signature {
+            return Err(GatewayError::Unauthorized(
+                "this token requires signed requests".into(),
+            ));
+        }
+        request.extensions_mut().insert(ResolvedClientToken(t));
+    }
+    Ok(next.run(request).await)
+}
+
+/// 签名中间件查到的客户端令牌，经请求扩展交给内层中间件（轮换、父令牌链、额度提示），
+/// 使每个 `/v1/*` 请求在中间件栈中只查一次令牌
+#[derive(Debug, Clone)]
+pub struct ResolvedClientToken(pub ClientToken);
+
+/// 外层签名中间件已解析的令牌；未携带或未知令牌时为空
+pub fn resolved_client_token(request: &Request) -> Option<&ClientToken> {
+    request
+        .extensions()
+        .get::<ResolvedClientToken>()
+        .map(|resolved| &resolved.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signature_covers_method_path_timestamp_nonce_and_body() {
+        let secret = "s".repeat(32);
+        let base = canonical_request("post", "/v1/chat/completions", 100, "n1", b"{}");
+        assert!(base.starts_with("POST\n/v1/chat/completions\n100\nn1\n"));
+        let sig = sign_request(&secret, &base);
+        assert!(signature_matches(&secret, &base, &sig));
+        for other in [
+            canonical_request("GET", "/v1/chat/completions", 100, "n1", b"{}"),
+            canonical_request("POST", "/v1/models", 100, "n1", b"{}"),
+            canonical_request("POST", "/v1/chat/completions", 101, "n1", b"{}"),
+            canonical_request("POST", "/v1/chat/completions", 100, "n2", b"{}"),
+            canonical_request("POST", "/v1/chat/completions", 100, "n1", b"{ }"),
+        ] {
+            assert!(!signature_matches(&secret, &other, &sig));
+        }
+        assert!(!signature_matches(&"t".repeat(32), &base, &sig));
+        assert!(!signature_matches(&secret, &base, "sha256=zz"));
+        assert!(!signature_matches(
+            &secret,
+            &base,
+            sig.trim_start_matches("sha256=")
+        ));
+    }
+
+    #[test]
+    fn nonce_cache_rejects_replays_until_window_expires() {
+        let cache = NonceCache::default();
+        assert!(cache.check_and_insert("tok", "n1", 1_000));
+        assert!(!cache.check_and_insert("tok", "n1", 1_001));
+        assert!(cache.check_and_insert("other", "n1", 1_001));
+        assert!(cache.check_and_insert("tok", "n1", 1_000 + 2 * MAX_CLOCK_SKEW_SECS + 1));
+    }
+
+    #[test]
+    fn only_client_api_paths_are_guarded() {
+        assert!(is_client_api_path("/v1/chat/completions"));
+        assert!(is_client_api_path("/api/v1/models"));
+        assert!(is_client_api_path("/api/paas/v4/chat/completions"));
+        assert!(is_client_api_path(
+            "/v1beta/models/gemini-pro:generateContent"
+        ));
+        assert!(!is_client_api_path("/admin/tokens"));
+        assert!(!is_client_api_path("/api/admin/tokens"));
+    }
+}
@@ -210,6 +210,23 @@ pub(crate) fn preview_from_stream_text(text: String, max_len: usize) -> Option<S
     normalize_output_text(text).map(|text| truncate(text, max_len))
 }
 
+/// 剥离 choices[].message / choices[].delta 中的思考内容（令牌开启 strip_reasoning 时使用），返回是否有改动
+pub(crate) fn strip_reasoning_fields(raw: &mut Value) -> bool {
+    let Some(choices) = raw.get_mut("choices").and_then(|v| v.as_array_mut()) else {
+        return false;
+    };
+    let mut stripped = false;
+    for choice in choices {
+        for key in ["message", "delta"] {
+            if let Some(obj) = choice.get_mut(key).and_then(|v| v.as_object_mut()) {
+                stripped |= obj.remove("reasoning_content").is_some();
+                stripped |= obj.remove("reasoning").is_some();
+            }
+        }
+    }
+    stripped
+}
+
 pub(crate) fn stream_chunk_preview_fragment(raw: &Value) -> Option<String> {
     if let Some(event_type) = raw.get("type").and_then(|value| value.as_str())
         && (event_type.starts_with("response.output_text")
@@ -236,11 +253,16 @@ pub(crate) fn stream_chunk_preview_fragment(raw: &Value) -> Option<String> {
         }
     }
 
-    let delta = raw
+    // n>1 时各 choice 的增量交错到达，预览只取第一个候选
+    let choice = raw
         .get("choices")
         .and_then(|value| value.as_array())
-        .and_then(|choices| choices.first())
-        .and_then(|choice| choice.get("delta"));
+        .and_then(|choices| {
+            choices
+                .iter()
+                .find(|choice| choice.get("index").and_then(Value::as_u64).unwrap_or(0) == 0)
+        });
+    let delta = choice.and_then(|choice| choice.get("delta"));
 
     if let Some(text) = delta
         .and_then(|delta| delta.get("content"))
@@ -256,16 +278,17 @@ pub(crate) fn stream_chunk_preview_fragment(raw: &Value) -> Option<String> {
         return Some(text);
     }
 
-    raw.get("choices")
-        .and_then(|value| value.as_array())
-        .and_then(|choices| choices.first())
+    choice
         .and_then(|choice| choice.get("text"))
         .and_then(|value| join_stream_fragments(collect_stream_fragments(value)))
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{extract_response_text, response_summary, stream_chunk_preview_fragment};
+    use super::{
+        extract_response_text, response_summary, stream_chunk_preview_fragment,
+        strip_reasoning_fields,
+    };
     use crate::providers::openai::types::RawAndTypedChatCompletion;
     use serde_json::json;
 
@@ -430,6 +453,20 @@ mod tests {
         );
     }
 
+    #[test]
+    fn strip_reasoning_fields_removes_message_and_delta_reasoning() {
+        let mut raw = json!({
+            "choices": [
+                {"message": {"content": "answer", "reasoning_content": "thinking"}},
+                {"delta": {"reasoning_content": "step-1"}}
+            ]
+        });
+        assert!(strip_reasoning_fields(&mut raw));
+        assert_eq!(raw["choices"][0]["message"], json!({"content": "answer"}));
+        assert_eq!(raw["choices"][1]["delta"], json!({}));
+        assert!(!strip_reasoning_fields(&mut raw));
+    }
+
     #[test]
     fn extracts_stream_chunk_preview_fragment_from_reasoning_content() {
         let chunk = json!({
@@ -0,0 +1,456 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::error::GatewayError;
+use crate::server::branding::Branding;
+use crate::server::storage_traits::{RequestLogStore, SettingsStore};
+
+const RUNTIME_SETTINGS_KEY: &str = "runtime_settings";
+const RATE_LIMIT_MAX: u32 = 100_000;
+const RETENTION_DAYS_MAX: u32 = 3650;
+const CORS_ORIGINS_MAX: usize = 64;
+const PRICING_MARKUP_MAX: f64 = 100.0;
+
+/// 运行期可调整的网关设置（持久化在 gateway_settings 表中，无需重启即可生效）
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct RuntimeSettings {
+    /// 允许的 CORS 来源；为空表示反射请求来源（默认行为）
+    #[serde(default)]
+    pub cors_allowed_origins: Vec<String>,
+    /// 每个客户端令牌每分钟允许的聊天请求数；为空表示不限制
+    #[serde(default)]
+    pub rate_limit_per_minute: Option<u32>,
+    /// 请求日志与供应商操作日志保留天数；为空表示永久保留
+    #[serde(default)]
+    pub log_retention_days: Option<u32>,
+    /// 日志过滤指令（EnvFilter 语法）；为空表示沿用 RUST_LOG
+    #[serde(default)]
+    pub log_level: Option<String>,
+    /// 客户端令牌签发（或上次轮换）超过该天数即要求轮换；为空表示不启用
+    #[serde(default)]
+    pub token_rotation_days: Option<u32>,
+    /// 到期后仅通过响应头提醒的宽限天数，超过后拒绝请求；为空视为 0
+    #[serde(default)]
+    pub token_rotation_grace_days: Option<u32>,
+    /// `/v1/pricing` 对外价格倍率（成本价 × 倍率）；为空视为 1.0
+    #[serde(default)]
+    pub pricing_markup: Option<f64>,
+    /// `/v1/pricing` 是否隐藏供应商名称（同名模型合并，取最高价）
+    #[serde(default)]
+    pub pricing_hide_providers: bool,
+    /// 部署品牌信息（服务名称、文档地址、支持联系方式）
+    #[serde(default)]
+    pub branding: Branding,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct SettingChange {
+    pub field: &'static str,
+    pub old: serde_json::Value,
+    pub new: serde_json::Value,
+}
+
+fn normalize_origin(raw: &str) -> Result<String, GatewayError> {
+    let trimmed = raw.trim().trim_end_matches('/');
+    if trimmed.is_empty() {
+        return Err(GatewayError::Config(
+            "cors_allowed_origins 不能包含空字符串".into(),
+        ));
+    }
+    let url = reqwest::Url::parse(trimmed)
+        .map_err(|_| GatewayError::Config(format!("invalid CORS origin: {}", raw)))?;
+    if !matches!(url.scheme(), "http" | "https") || url.host_str().is_none() {
+        return Err(GatewayError::Config(format!(
+            "invalid CORS origin: {}",
+            raw
+        )));
+    }
+    if url.path() != "/" || url.query().is_some() {
+        return Err(GatewayError::Config(format!(
+            "CORS origin must not contain a path: {}",
+            raw
+        )));
+    }
+    Ok(url.origin().ascii_serialization())
+}
+
+impl RuntimeSettings {
+    /// 校验并规范化（去重、去尾斜杠等），返回可直接保存的设置
+    pub fn validated(mut self) -> Result<Self, GatewayError> {
+        if self.cors_allowed_origins.len() > CORS_ORIGINS_MAX {
+            return Err(GatewayError::Config(format!(
+                "cors_allowed_origins 数量不能超过 {}",
+                CORS_ORIGINS_MAX
+            )));
+        }
+        let mut origins: Vec<String> = Vec::new();
+        for raw in &self.cors_allowed_origins {
+            let origin = normalize_origin(raw)?;
+            if !origins.contains(&origin) {
+                origins.push(origin);
+            }
+        }
+        self.cors_allowed_origins = origins;
+
+        if let Some(limit) = self.rate_limit_per_minute
+            && (limit == 0 || limit > RATE_LIMIT_MAX)
+        {
+            return Err(GatewayError::Config(format!(
+                "rate_limit_per_minute must be between 1 and {}",
+                RATE_LIMIT_MAX
+            )));
+        }
+        if let Some(days) = self.log_retention_days
+            && (days == 0 || days > RETENTION_DAYS_MAX)
+        {
+            return Err(GatewayError::Config(format!(
+                "log_retention_days must be between 1 and {}",
+                RETENTION_DAYS_MAX
+            )));
+        }
+        if let Some(days) = self.token_rotation_days
+            && (days == 0 || days > RETENTION_DAYS_MAX)
+        {
+            return Err(GatewayError::Config(format!(
+                "token_rotation_days must be between 1 and {}",
+                RETENTION_DAYS_MAX
+            )));
+        }
+        if self
+            .token_rotation_grace_days
+            .is_some_and(|days| days > RETENTION_DAYS_MAX)
+        {
+            return Err(GatewayError::Config(format!(
+                "token_rotation_grace_days must be at most {}",
+                RETENTION_DAYS_MAX
+            )));
+        }
+        if let Some(markup) = self.pricing_markup
+            && (!markup.is_finite() || markup <= 0.0 || markup > PRICING_MARKUP_MAX)
+        {
+            return Err(GatewayError::Config(format!(
+                "pricing_markup must be greater than 0 and at most {}",
+                PRICING_MARKUP_MAX
+            )));
+        }
+        self.log_level = self
+            .log_level
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty());
+        if let Some(level) = self.log_level.as_deref() {
+            crate::logging::level::validate_directives(level)?;
+        }
+        self.branding = self.branding.validated()?;
+        Ok(self)
+    }
+
+    /// 计算与另一份设置的差异（用于应用前的确认预览）
+    pub fn diff(&self, next: &RuntimeSettings) -> Vec<SettingChange> {
+        fn push<T: Serialize + PartialEq>(
+            out: &mut Vec<SettingChange>,
+            field: &'static str,
+            old: &T,
+            new: &T,
+        ) {
+            if old != new {
+                out.push(SettingChange {
+                    field,
+                    old: serde_json::to_value(old).unwrap_or_default(),
+                    new: serde_json::to_value(new).unwrap_or_default(),
+                });
+            }
+        }
+        let mut out = Vec::new();
+        push(
+            &mut out,
+            "cors_allowed_origins",
+            &self.cors_allowed_origins,
+            &next.cors_allowed_origins,
+        );
+        push(
+            &mut out,
+            "rate_limit_per_minute",
+            &self.rate_limit_per_minute,
+            &next.rate_limit_per_minute,
+        );
+        push(
+            &mut out,
+            "log_retention_days",
+            &self.log_retention_days,
+            &next.log_retention_days,
+        );
+        push(&mut out, "log_level", &self.log_level, &next.log_level);
+        push(
+            &mut out,
+            "token_rotation_days",
+            &self.token_rotation_days,
+            &next.token_rotation_days,
+        );
+        push(
+            &mut out,
+            "token_rotation_grace_days",
+            &self.token_rotation_grace_days,
+            &next.token_rotation_grace_days,
+        );
+        push(
+            &mut out,
+            "pricing_markup",
+            &self.pricing_markup,
+            &next.pricing_markup,
+        );
+        push(
+            &mut out,
+            "pricing_hide_providers",
+            &self.pricing_hide_providers,
+            &next.pricing_hide_providers,
+        );
+        push(&mut out, "branding", &self.branding, &next.branding);
+        out
+    }
+}
+
+/// 运行期设置管理：内存快照 + 持久化 + 基于快照的限流/CORS 判定
+pub struct RuntimeSettingsManager {
+    store: Arc<dyn SettingsStore + Send + Sync>,
+    current: RwLock<RuntimeSettings>,
+    // token_id -> (分钟窗口, 已用次数)
+    rate_windows: Mutex<HashMap<String, (i64, u32)>>,
+}
+
+impl RuntimeSettingsManager {
+    pub fn new(store: Arc<dyn SettingsStore + Send + Sync>) -> Self {
+        Self {
+            store,
+            current: RwLock::new(RuntimeSettings::default()),
+            rate_windows: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 启动时从存储加载；损坏或非法的记录会被忽略并回退默认值
+    pub async fn load(&self) -> Result<(), GatewayError> {
+        let raw = self.store.get_setting(RUNTIME_SETTINGS_KEY).await?;
+        let Some(raw) = raw else { return Ok(()) };
+        match serde_json::from_str::<RuntimeSettings>(&raw).map(RuntimeSettings::validated) {
+            Ok(Ok(settings)) => {
+                if let Some(level) = settings.log_level.as_deref()
+                    && let Err(e) = crate::logging::level::set_directives(level)
+                {
+                    tracing::warn!("Failed to apply stored log level: {}", e);
+                }
+                *self.current.write().unwrap_or_else(|e| e.into_inner()) = settings;
+            }
+            Ok(Err(e)) => tracing::warn!("Ignoring invalid stored runtime settings: {}", e),
+            Err(e) => tracing::warn!("Ignoring malformed stored runtime settings: {}", e),
+        }
+        Ok(())
+    }
+
+    pub fn snapshot(&self) -> RuntimeSettings {
+        self.current
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// 持久化并立即生效，返回变更列表
+    pub async fn apply(&self, next: RuntimeSettings) -> Result<Vec<SettingChange>, GatewayError> {
+        let next = next.validated()?;
+        let changes = self.snapshot().diff(&next);
+        if changes.is_empty() {
+            return Ok(changes);
+        }
+        let encoded = serde_json::to_string(&next)?;
+        self.store
+            .set_setting(RUNTIME_SETTINGS_KEY, &encoded)
+            .await?;
+        if changes.iter().any(|c| c.field == "log_level") {
+            let directives = next
+                .log_level
+                .clone()
+                .or_else(|| std::env::var("RUST_LOG").ok())
+                .unwrap_or_else(|| "info".to_string());
+            crate::logging::level::set_directives(&directives)?;
+        }
+        if changes.iter().any(|c| c.field == "rate_limit_per_minute") {
+            self.rate_windows
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .clear();
+        }
+        *self.current.write().unwrap_or_else(|e| e.into_inner()) = next;
+        Ok(changes)
+    }
+
+    pub fn origin_allowed(&self, origin: &str) -> bool {
+        let current = self.current.read().unwrap_or_else(|e| e.into_inner());
+        current.cors_allowed_origins.is_empty()
+            || current
+                .cors_allowed_origins
+                .iter()
+                .any(|o| o.eq_ignore_ascii_case(origin))
+    }
+
+    /// 按客户端令牌做固定窗口（每分钟）限流
+    pub fn check_rate_limit(&self, token_id: &str) -> Result<(), GatewayError> {
+        let Some(limit) = self.snapshot().rate_limit_per_minute else {
+            return Ok(());
+        };
+        let window = Utc::now().timestamp() / 60;
+        let mut windows = self.rate_windows.lock().unwrap_or_else(|e| e.into_inner());
+        let entry = windows.entry(token_id.to_string()).or_insert((window, 0));
+        if entry.0 != window {
+            *entry = (window, 0);
+        }
+        if entry.1 >= limit {
+            return Err(rate_limit_exceeded(limit));
+        }
+        entry.1 += 1;
+        if windows.len() > 10_000 {
+            windows.retain(|_, (w, _)| *w == window);
+        }
+        Ok(())
+    }
+
+    /// 当前分钟窗口是否已用尽，不计入请求
+    pub fn peek_rate_limit(&self, token_id: &str) -> Result<(), GatewayError> {
+        match self.rate_limit_window(token_id, Utc::now()) {
+            Some(window) if window.remaining == 0 => Err(rate_limit_exceeded(window.limit)),
+            _ => Ok(()),
+        }
+    }
+
+    /// 令牌在当前分钟窗口内的限流余量（未启用限流时为空），不计入请求
+    pub fn rate_limit_window(&self, token_id: &str, now: DateTime<Utc>) -> Option<RateLimitWindow> {
+        let limit = self.snapshot().rate_limit_per_minute?;
+        let window = now.timestamp() / 60;
+        let used = self
+            .rate_windows
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(token_id)
+            .filter(|(w, _)| *w == window)
+            .map(|(_, used)| *used)
+            .unwrap_or(0);
+        Some(RateLimitWindow {
+            limit,
+            remaining: limit.saturating_sub(used),
+            reset_after_secs: ((window + 1) * 60 - now.timestamp()) as u64,
+        })
+    }
+}
+
+fn rate_limit_exceeded(limit: u32) -> GatewayError {
+    GatewayError::RateLimited(format!(
+        "rate limit exceeded: {} requests per minute",
+        limit
+    ))
+}
+
+/// 固定窗口限流的当前状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimitWindow {
+    pub limit: u32,
+    pub remaining: u32,
+    /// 距离窗口重置的秒数
+    pub reset_after_secs: u64,
+}
+
+/// 按保留期清理请求日志与供应商操作日志，并清理已过期的调试捕获；返回删除的日志条数
+pub async fn purge_expired_logs(
+    manager: &RuntimeSettingsManager,
+    log_store: &(dyn RequestLogStore + Send + Sync),
+) -> Result<usize, GatewayError> {
+    let now = Utc::now();
+    if let Err(e) = log_store.purge_expired_debug_captures(now).await {
+        tracing::warn!("Failed to purge expired debug captures: {}", e);
+    }
+    let Some(days) = manager.snapshot().log_retention_days else {
+        return Ok(0);
+    };
+    let cutoff = now - Duration::days(days as i64);
+    let requests = log_store.purge_request_logs_before(cutoff).await?;
+    let ops = log_store.purge_provider_ops_logs_before(cutoff).await?;
+    Ok(requests as usize + ops as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logging::DatabaseLogger;
+    use tempfile::tempdir;
+
+    #[test]
+    fn validated_normalizes_origins_and_rejects_bad_values() {
+        let settings = RuntimeSettings {
+            cors_allowed_origins: vec![
+                "https://a.example.com/".into(),
+                "https://a.example.com".into(),
+            ],
+            log_level: Some("  ".into()),
+            ..Default::default()
+        }
+        .validated()
+        .unwrap();
+        assert_eq!(settings.cors_allowed_origins, vec!["https://a.example.com"]);
+        assert_eq!(settings.log_level, None);
+
+        let bad = RuntimeSettings {
+            cors_allowed_origins: vec!["https://a.example.com/path".into()],
+            ..Default::default()
+        };
+        assert!(bad.validated().is_err());
+        let bad = RuntimeSettings {
+            rate_limit_per_minute: Some(0),
+            ..Default::default()
+        };
+        assert!(bad.validated().is_err());
+        let bad = RuntimeSettings {
+            token_rotation_days: Some(0),
+            ..Default::default()
+        };
+        assert!(bad.validated().is_err());
+        let bad = RuntimeSettings {
+            pricing_markup: Some(0.0),
+            ..Default::default()
+        };
+        assert!(bad.validated().is_err());
+    }
+
+    #[tokio::test]
+    async fn apply_persists_and_enforces_rate_limit() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("settings.db");
+        let logger = Arc::new(
+            DatabaseLogger::new(db_path.to_str().unwrap())
+                .await
+                .unwrap(),
+        );
+        let manager = RuntimeSettingsManager::new(logger.clone());
+        let changes = manager
+            .apply(RuntimeSettings {
+                rate_limit_per_minute: Some(2),
+                log_retention_days: Some(30),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(changes.len(), 2);
+
+        manager.check_rate_limit("t1").unwrap();
+        manager.check_rate_limit("t1").unwrap();
+        let err = manager.check_rate_limit("t1").unwrap_err();
+        assert!(matches!(err, GatewayError::RateLimited(_)));
+        manager.check_rate_limit("t2").unwrap();
+        let window = manager.rate_limit_window("t1", Utc::now()).unwrap();
+        assert_eq!((window.limit, window.remaining), (2, 0));
+        assert!((1..=60).contains(&window.reset_after_secs));
+
+        let reloaded = RuntimeSettingsManager::new(logger);
+        reloaded.load().await.unwrap();
+        assert_eq!(reloaded.snapshot().log_retention_days, Some(30));
+        assert!(reloaded.origin_allowed("https://anything.example"));
+    }
+}
@@ -0,0 +1,179 @@
+use std::convert::Infallible;
+
+use axum::http::HeaderMap;
+use axum::response::sse::{Event, Sse};
+use axum::response::{IntoResponse, Json, Response};
+use chrono::{DateTime, Utc};
+use serde_json::{Value, json};
+
+use crate::admin::ClientToken;
+use crate::error::GatewayError;
+use crate::logging::types::{REQ_TYPE_CHAT_SANDBOX, RequestLog};
+use crate::providers::openai::ChatCompletionRequest;
+use crate::server::AppState;
+use crate::server::chat_plan::estimate_prompt_tokens;
+use crate::server::util::bearer_token;
+
+/// 沙箱请求在日志中记录的 provider 名
+pub const SANDBOX_PROVIDER: &str = "sandbox";
+
+/// 沙箱令牌的请求不经过上游：返回固定回复（或回显），费用记 0，并以 chat_sandbox 类型记录日志。
+/// 非沙箱令牌（或已停用/过期的沙箱令牌）返回 None，交由常规流程处理与拒绝。
+pub async fn try_sandbox_response(
+    app_state: &AppState,
+    headers: &HeaderMap,
+    request: &ChatCompletionRequest,
+) -> Result<Option<Response>, GatewayError> {
+    let Some(token_str) = bearer_token(headers) else {
+        return Ok(None);
+    };
+    let Some(token) = app_state.token_store.get_token(&token_str).await? else {
+        return Ok(None);
+    };
+    let now = Utc::now();
+    if !token.sandbox || !token.enabled || token.expires_at.is_some_and(|exp| now > exp) {
+        return Ok(None);
+    }
+    crate::server::token_model_limits::enforce_model_allowed_for_token(&token, &request.model)?;
+
+    let reply = sandbox_reply_text(app_state.config.server.sandbox_reply.as_deref(), request);
+    let prompt_tokens = estimate_prompt_tokens(request);
+    let completion_tokens = reply.chars().count().div_ceil(4) as u32;
+    log_sandbox_request(
+        app_state,
+        now,
+        &token,
+        &request.model,
+        prompt_tokens,
+        completion_tokens,
+    )
+    .await;
+
+    let completion = build_completion(&request.model, &reply, prompt_tokens, completion_tokens);
+    if !request.stream.unwrap_or(false) {
+        return Ok(Some(Json(completion).into_response()));
+    }
+    let events: Vec<Result<Event, Infallible>> = stream_chunks(&completion)
+        .into_iter()
+        .map(|chunk| Ok(Event::default().data(chunk.to_string())))
+        .chain(std::iter::once(Ok(Event::default().data("[DONE]"))))
+        .collect();
+    Ok(Some(Sse::new(tokio_stream::iter(events)).into_response()))
+}
+
+/// 沙箱令牌不访问真实上游；没有模拟回复的接口（图片、音频）在调用上游前直接拒绝
+pub fn reject_sandbox_token(token: &ClientToken) -> Result<(), GatewayError> {
+    if token.sandbox {
+        return Err(GatewayError::Forbidden(
+            "sandbox tokens can only call chat completions".into(),
+        ));
+    }
+    Ok(())
+}
+
+/// 配置了固定回复时直接使用；否则回显最后一条用户消息
+fn sandbox_reply_text(configured: Option<&str>, request: &ChatCompletionRequest) -> String {
+    if let Some(reply) = configured.map(str::trim).filter(|s| !s.is_empty()) {
+        return reply.to_string();
+    }
+    let messages = serde_json::to_value(&request.messages).unwrap_or(Value::Null);
+    let last_user = messages
+        .as_array()
+        .and_then(|list| {
+            list.iter()
+                .rev()
+                .find(|m| m.get("role").and_then(Value::as_str) == Some("user"))
+        })
+        .and_then(|m| m.get("content"));
+    let text = match last_user {
+        Some(Value::String(s)) => s.clone(),
+        Some(Value::Array(parts)) => parts
+            .iter()
+            .filter_map(|p| p.get("text").and_then(Value::as_str))
+            .collect::<Vec<_>>()
+            .join(""),
+        _ => String::new(),
+    };
+    format!("[sandbox] {}", text)
+}
+
+fn build_completion(model: &str, reply: &str, prompt_tokens: u32, completion_tokens: u32) -> Value {
+    json!({
+        "id": format!("chatcmpl-sandbox-{}", Utc::now().timestamp_millis()),
+        "object": "chat.completion",
+        "created": Utc::now().timestamp().max(0),
+        "model": model,
+        "choices": [{
+            "index": 0,
+            "message": {"role": "assistant", "content": reply},
+            "finish_reason": "stop"
+        }],
+        "usage": {
+            "prompt_tokens": prompt_tokens,
+            "completion_tokens": completion_tokens,
+            "total_tokens": prompt_tokens + completion_tokens
+        }
+    })
+}
+
+/// 将完整回复拆成 OpenAI 兼容的流式 chunk：内容块 + 结束块（携带 usage）
+fn stream_chunks(completion: &Value) -> Vec<Value> {
+    let base = |choices: Value| {
+        json!({
+            "id": completion["id"],
+            "object": "chat.completion.chunk",
+            "created": completion["created"],
+            "model": completion["model"],
+            "choices": choices,
+        })
+    };
+    let content = completion["choices"][0]["message"]["content"].clone();
+    let mut last = base(json!([{"index": 0, "delta": {}, "finish_reason": "stop"}]));
+    last["usage"] = completion["usage"].clone();
+    vec![
+        base(json!([{
+            "index": 0,
+            "delta": {"role": "assistant", "content": content},
+            "finish_reason": null
+        }])),
+        last,
+    ]
+}
+
+async fn log_sandbox_request(
+    app_state: &AppState,
+    start_time: DateTime<Utc>,
+    token: &ClientToken,
+    model: &str,
+    prompt_tokens: u32,
+    completion_tokens: u32,
+) {
+    let log = RequestLog {
+        id: None,
+        timestamp: start_time,
+        method: "POST".to_string(),
+        path: "/v1/chat/completions".to_string(),
+        request_type: REQ_TYPE_CHAT_SANDBOX.to_string(),
+        requested_model: Some(model.to_string()),
+        effective_model: Some(model.to_string()),
+        model: Some(model.to_string()),
+        provider: Some(SANDBOX_PROVIDER.to_string()),
+        api_key: None,
+        client_token: Some(token.id.clone()),
+        user_id: token.user_id.clone(),
+        amount_spent: Some(0.0),
+        status_code: 200,
+        response_time_ms: (Utc::now() - start_time).num_milliseconds(),
+        prompt_tokens: Some(prompt_tokens),
+        completion_tokens: Some(completion_tokens),
+        total_tokens: Some(prompt_tokens + completion_tokens),
+        cached_tokens: None,
+        reasoning_tokens: None,
+        error_message: None,
+        latency: Default::default(),
+        profile: Default::default(),
+    };
+    if let Err(e) = crate::server::degraded::log_request(app_state.log_store.as_ref(), log).await {
+        tracing::warn!("Failed to log sandbox request: {}", e);
+    }
+}
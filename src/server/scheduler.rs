@@ -0,0 +1,473 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Duration, Utc};
+
+use crate::admin::{ClientToken, TokenAutoDisablePrefs};
+use crate::error::GatewayError;
+use crate::logging::types::ProviderOpLog;
+use crate::server::AppState;
+use crate::server::jobs::{CronSchedule, JobScheduler};
+use crate::server::notifications::{TokenNotification, deliver};
+use crate::server::storage_traits::SettingsStore;
+
+pub const NOTIFY_TOKEN_EXPIRING: &str = "token_expiring";
+pub const NOTIFY_TOKEN_AUTO_DISABLED: &str = "token_auto_disabled";
+/// 管理员公钥到期提醒（通知记录以公钥指纹作为 token_id）
+pub const NOTIFY_ADMIN_KEY_EXPIRING: &str = "admin_key_expiring";
+/// 审计日志（provider_ops_logs）中的操作名
+pub const OP_TOKEN_AUTO_DISABLE: &str = "token_auto_disable";
+
+pub const JOB_TOKEN_EXPIRY_NOTICES: &str = "token_expiry_notices";
+pub const JOB_ADMIN_KEY_EXPIRY_NOTICES: &str = "admin_key_expiry_notices";
+pub const JOB_INACTIVE_TOKEN_DISABLE: &str = "inactive_token_disable";
+pub const JOB_METRICS_REPORTS: &str = "metrics_reports";
+pub const JOB_MONTHLY_STATEMENTS: &str = "monthly_statements";
+pub const JOB_LOG_RETENTION: &str = "log_retention";
+pub const JOB_EXCHANGED_TOKEN_PRUNE: &str = "exchanged_token_prune";
+
+/// 内置定时任务：(名称, 说明, 默认 cron 表达式)；可通过 `server.job_schedules` 覆盖表达式
+pub const BUILTIN_JOBS: &[(&str, &str, &str)] = &[
+    (
+        JOB_TOKEN_EXPIRY_NOTICES,
+        "Notify owners of client tokens that expire soon",
+        "0 * * * *",
+    ),
+    (
+        JOB_ADMIN_KEY_EXPIRY_NOTICES,
+        "Notify the webhook about admin keys that expire soon",
+        "0 * * * *",
+    ),
+    (
+        JOB_INACTIVE_TOKEN_DISABLE,
+        "Disable client tokens without requests for inactive_token_disable_days",
+        "0 * * * *",
+    ),
+    (
+        JOB_METRICS_REPORTS,
+        "Send scheduled metrics reports that are due",
+        "0 * * * *",
+    ),
+    (
+        JOB_MONTHLY_STATEMENTS,
+        "Generate statements for the last closed billing period",
+        "0 * * * *",
+    ),
+    (
+        JOB_LOG_RETENTION,
+        "Purge logs older than log_retention_days and expired debug captures",
+        "15 * * * *",
+    ),
+    (
+        JOB_EXCHANGED_TOKEN_PRUNE,
+        "Delete short-lived exchanged tokens that expired more than a day ago",
+        "30 * * * *",
+    ),
+];
+
+async fn run_builtin_job(app_state: &AppState, name: &str) -> Result<usize, GatewayError> {
+    let now = Utc::now();
+    match name {
+        JOB_TOKEN_EXPIRY_NOTICES => notify_expiring_tokens(app_state, now).await,
+        JOB_ADMIN_KEY_EXPIRY_NOTICES => notify_expiring_admin_keys(app_state, now).await,
+        JOB_INACTIVE_TOKEN_DISABLE => disable_inactive_tokens(app_state, now).await,
+        JOB_METRICS_REPORTS => {
+            crate::server::metrics_reports::send_due_reports(app_state, now).await
+        }
+        JOB_MONTHLY_STATEMENTS => {
+            crate::server::statements::generate_closed_period(app_state, now).await
+        }
+        JOB_LOG_RETENTION => {
+            crate::server::runtime_settings::purge_expired_logs(
+                &app_state.runtime_settings,
+                app_state.log_store.as_ref(),
+            )
+            .await
+        }
+        JOB_EXCHANGED_TOKEN_PRUNE => {
+            crate::server::token_lineage::prune_expired_exchanged_tokens(app_state, now).await
+        }
+        other => Err(GatewayError::NotFound(format!("job '{}' not found", other))),
+    }
+}
+
+/// 登记内置定时任务并启动调度器（令牌与管理员公钥到期提醒、闲置令牌自动停用、定时指标报表、
+/// 月度账单、日志保留期清理、过期交换令牌清理）；`server.job_schedules` 中的表达式无效时启动失败
+pub async fn start_job_scheduler(
+    app_state: Arc<AppState>,
+    store: Arc<dyn SettingsStore + Send + Sync>,
+) -> Result<(), GatewayError> {
+    let overrides = &app_state.config.server.job_schedules;
+    for name in overrides.keys() {
+        if !BUILTIN_JOBS.iter().any(|(job, _, _)| job == name) {
+            tracing::warn!("Ignoring schedule for unknown job '{}'", name);
+        }
+    }
+    let mut scheduler = JobScheduler::new(store, app_state.task_registry.clone());
+    for &(name, description, default_schedule) in BUILTIN_JOBS {
+        let expr = overrides
+            .get(name)
+            .map(String::as_str)
+            .unwrap_or(default_schedule);
+        let app_state = app_state.clone();
+        scheduler.add(
+            name,
+            description,
+            CronSchedule::parse(expr)?,
+            Arc::new(move || {
+                let app_state = app_state.clone();
+                Box::pin(async move { run_builtin_job(&app_state, name).await })
+            }),
+        );
+    }
+    let scheduler = Arc::new(scheduler);
+    scheduler.load().await?;
+    scheduler.spawn();
+    crate::server::jobs::install(scheduler);
+    Ok(())
+}
+
+/// 已启用、尚未过期且在 notice_days 天内到期的令牌；子令牌随父令牌到期，不单独提醒
+fn tokens_expiring_within(
+    tokens: &[ClientToken],
+    now: DateTime<Utc>,
+    notice_days: u32,
+) -> Vec<&ClientToken> {
+    let horizon = now + Duration::days(notice_days as i64);
+    tokens
+        .iter()
+        .filter(|t| t.enabled && t.parent_token_id.is_none())
+        .filter(|t| matches!(t.expires_at, Some(exp) if exp > now && exp <= horizon))
+        .collect()
+}
+
+fn expiry_notification(token: &ClientToken, expires_at: DateTime<Utc>) -> TokenNotification {
+    let expires = crate::logging::time::to_beijing_string(&expires_at);
+    TokenNotification {
+        kind: NOTIFY_TOKEN_EXPIRING,
+        token_id: token.id.clone(),
+        token_name: token.name.clone(),
+        user_id: token.user_id.clone(),
+        reference: crate::logging::time::to_iso8601_utc_string(&expires_at),
+        subject: format!("API token \"{}\" expires soon", token.name),
+        message: format!(
+            "Your API token \"{}\" ({}) will expire at {} (UTC+8). Renew or replace it to avoid failed requests.",
+            token.name, token.id, expires
+        ),
+    }
+}
+
+/// 查找即将到期的令牌并向所有者发送提醒；返回成功发送的通知数。
+/// 同一令牌的同一到期时间只会成功提醒一次；退订的令牌会被跳过。
+pub async fn notify_expiring_tokens(
+    app_state: &AppState,
+    now: DateTime<Utc>,
+) -> Result<usize, GatewayError> {
+    let Some(notice_days) = app_state.config.server.token_expiry_notice_days else {
+        return Ok(0);
+    };
+    let tokens = app_state.token_store.list_tokens().await?;
+    let mut sent = 0;
+    for token in tokens_expiring_within(&tokens, now, notice_days) {
+        let Some(expires_at) = token.expires_at else {
+            continue;
+        };
+        let notification = expiry_notification(token, expires_at);
+        let store = &app_state.token_store;
+        if store
+            .token_notification_sent(&token.id, notification.kind, &notification.reference)
+            .await?
+            || store.get_notifications_opt_out(&token.id).await?
+        {
+            continue;
+        }
+        let owner_email = match token.user_id.as_deref() {
+            Some(uid) => app_state.user_store.get_user(uid).await?.map(|u| u.email),
+            None => None,
+        };
+        let records = deliver(
+            &app_state.config.server,
+            &notification,
+            owner_email.as_deref(),
+        )
+        .await;
+        if records.is_empty() {
+            tracing::debug!(
+                "No notification channel configured; skipped expiry notice for token {}",
+                token.id
+            );
+            continue;
+        }
+        for record in &records {
+            if record.success {
+                sent += 1;
+            }
+            store.record_token_notification(record).await?;
+        }
+    }
+    Ok(sent)
+}
+
+/// 公钥在 notice_days 天内到期时返回轮换提醒（TUI 登录响应与 Webhook 共用）
+pub fn admin_key_expiry_warning(
+    expires_at: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+    notice_days: Option<u32>,
+) -> Option<String> {
+    let (expires_at, notice_days) = (expires_at?, notice_days?);
+    if expires_at <= now || expires_at > now + Duration::days(notice_days as i64) {
+        return None;
+    }
+    Some(format!(
+        "Admin key expires at {} (UTC+8); add a replacement key and retire this one before then.",
+        crate::logging::time::to_beijing_string(&expires_at)
+    ))
+}
+
+/// 向通知 Webhook 提醒即将到期的已启用管理员公钥；返回成功发送的通知数。
+/// 同一公钥的同一到期时间只会成功提醒一次（延期后会再次提醒）。
+pub async fn notify_expiring_admin_keys(
+    app_state: &AppState,
+    now: DateTime<Utc>,
+) -> Result<usize, GatewayError> {
+    let notice_days = app_state.config.server.admin_key_expiry_notice_days;
+    if notice_days.is_none() {
+        return Ok(0);
+    }
+    let store = &app_state.token_store;
+    let mut sent = 0;
+    for key in app_state.login_manager.list_admin_keys().await? {
+        if !key.enabled {
+            continue;
+        }
+        let Some(message) = admin_key_expiry_warning(key.expires_at, now, notice_days) else {
+            continue;
+        };
+        let Some(expires_at) = key.expires_at else {
+            continue;
+        };
+        let name = key
+            .comment
+            .clone()
+            .unwrap_or_else(|| key.fingerprint.chars().take(16).collect());
+        let notification = TokenNotification {
+            kind: NOTIFY_ADMIN_KEY_EXPIRING,
+            token_id: key.fingerprint.clone(),
+            token_name: name.clone(),
+            user_id: None,
+            reference: crate::logging::time::to_iso8601_utc_string(&expires_at),
+            subject: format!("Admin key \"{}\" expires soon", name),
+            message,
+        };
+        if store
+            .token_notification_sent(&key.fingerprint, notification.kind, &notification.reference)
+            .await?
+        {
+            continue;
+        }
+        for record in deliver(&app_state.config.server, &notification, None).await {
+            if record.success {
+                sent += 1;
+            }
+            store.record_token_notification(&record).await?;
+        }
+    }
+    Ok(sent)
+}
+
+/// 令牌最近一次“活跃”时间：创建、最近请求、最近一次被自动停用（重新启用后重新计时）中的最大值
+fn last_activity(
+    token: &ClientToken,
+    last_request_at: Option<DateTime<Utc>>,
+    prefs: &TokenAutoDisablePrefs,
+) -> DateTime<Utc> {
+    [Some(token.created_at), last_request_at, prefs.disabled_at]
+        .into_iter()
+        .flatten()
+        .max()
+        .unwrap_or(token.created_at)
+}
+
+/// 停用连续 inactive_days 天没有请求的已启用令牌（豁免令牌除外），
+/// 写入审计日志并通知所有者；返回停用的令牌数。
+pub async fn disable_inactive_tokens(
+    app_state: &AppState,
+    now: DateTime<Utc>,
+) -> Result<usize, GatewayError> {
+    let Some(inactive_days) = app_state.config.server.inactive_token_disable_days else {
+        return Ok(0);
+    };
+    let cutoff = now - Duration::days(inactive_days as i64);
+    let last_requests = app_state
+        .log_store
+        .last_request_at_by_client_token()
+        .await
+        .map_err(GatewayError::Db)?;
+    let store = &app_state.token_store;
+    let mut disabled = 0;
+    for token in store.list_tokens().await? {
+        if !token.enabled {
+            continue;
+        }
+        let prefs = store.get_auto_disable_prefs(&token.id).await?;
+        let last_active = last_activity(&token, last_requests.get(&token.id).copied(), &prefs);
+        if prefs.exempt || last_active > cutoff {
+            continue;
+        }
+        if !store.set_enabled_by_id(&token.id, false).await? {
+            continue;
+        }
+        store.mark_auto_disabled(&token.id, now).await?;
+        disabled += 1;
+
+        let details = serde_json::json!({
+            "token_id": token.id,
+            "user_id": token.user_id,
+            "last_active_at": crate::logging::time::to_iso8601_utc_string(&last_active),
+            "inactive_days": inactive_days,
+        });
+        if let Err(e) = app_state
+            .log_store
+            .log_provider_op(ProviderOpLog {
+                id: None,
+                timestamp: now,
+                operation: OP_TOKEN_AUTO_DISABLE.to_string(),
+                provider: None,
+                details: Some(details.to_string()),
+                actor: None,
+            })
+            .await
+        {
+            tracing::warn!("Failed to write audit log for token {}: {}", token.id, e);
+        }
+
+        if store.get_notifications_opt_out(&token.id).await? {
+            continue;
+        }
+        let notification = TokenNotification {
+            kind: NOTIFY_TOKEN_AUTO_DISABLED,
+            token_id: token.id.clone(),
+            token_name: token.name.clone(),
+            user_id: token.user_id.clone(),
+            reference: crate::logging::time::to_iso8601_utc_string(&now),
+            subject: format!("API token \"{}\" was disabled", token.name),
+            message: format!(
+                "Your API token \"{}\" ({}) had no requests for {} days and has been disabled. Re-enable it if you still need it.",
+                token.name, token.id, inactive_days
+            ),
+        };
+        let owner_email = match token.user_id.as_deref() {
+            Some(uid) => app_state.user_store.get_user(uid).await?.map(|u| u.email),
+            None => None,
+        };
+        for record in deliver(
+            &app_state.config.server,
+            &notification,
+            owner_email.as_deref(),
+        )
+        .await
+        {
+            store.record_token_notification(&record).await?;
+        }
+    }
+    Ok(disabled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(id: &str, enabled: bool, expires_at: Option<DateTime<Utc>>) -> ClientToken {
+        ClientToken {
+            id: id.into(),
+            user_id: None,
+            name: id.into(),
+            token: format!("tok-{}", id),
+            allowed_models: None,
+            model_blacklist: None,
+            max_tokens: None,
+            max_amount: None,
+            enabled,
+            expires_at,
+            created_at: Utc::now(),
+            amount_spent: 0.0,
+            prompt_tokens_spent: 0,
+            completion_tokens_spent: 0,
+            total_tokens_spent: 0,
+            remark: None,
+            organization_id: None,
+            ip_whitelist: None,
+            ip_blacklist: None,
+            allow_streaming: true,
+            sandbox: false,
+            strip_reasoning: false,
+            usage_webhook_url: None,
+            signing_secret: None,
+            require_signature: false,
+            parent_token_id: None,
+            allow_debug_capture: false,
+            allow_provider_override: false,
+            auto_truncate_prompt: false,
+            semantic_cache: false,
+            allow_login_codes: false,
+            max_requests: None,
+            max_requests_per_day: None,
+            watermark_responses: false,
+            queue_weight: None,
+            usage_webhook_secret: None,
+        }
+    }
+
+    #[test]
+    fn expiring_filter_skips_expired_disabled_and_far_future_tokens() {
+        let now = Utc::now();
+        let tokens = vec![
+            token("soon", true, Some(now + Duration::days(2))),
+            token("expired", true, Some(now - Duration::hours(1))),
+            token("disabled", false, Some(now + Duration::days(1))),
+            token("later", true, Some(now + Duration::days(30))),
+            token("forever", true, None),
+            ClientToken {
+                parent_token_id: Some("soon".into()),
+                ..token("child", true, Some(now + Duration::minutes(15)))
+            },
+        ];
+        let due: Vec<_> = tokens_expiring_within(&tokens, now, 7)
+            .into_iter()
+            .map(|t| t.id.as_str())
+            .collect();
+        assert_eq!(due, vec!["soon"]);
+    }
+
+    #[test]
+    fn admin_key_warning_only_inside_notice_window() {
+        let now = Utc::now();
+        let warn = |exp: Option<DateTime<Utc>>, days| admin_key_expiry_warning(exp, now, days);
+        assert!(warn(Some(now + Duration::days(3)), Some(7)).is_some());
+        assert!(warn(Some(now + Duration::days(30)), Some(7)).is_none());
+        assert!(warn(Some(now - Duration::hours(1)), Some(7)).is_none());
+        assert!(warn(Some(now + Duration::days(3)), None).is_none());
+        assert!(warn(None, Some(7)).is_none());
+    }
+
+    #[test]
+    fn last_activity_uses_latest_of_creation_request_and_previous_disable() {
+        let now = Utc::now();
+        let mut t = token("t", true, None);
+        t.created_at = now - Duration::days(90);
+        let prefs = TokenAutoDisablePrefs::default();
+        assert_eq!(last_activity(&t, None, &prefs), t.created_at);
+
+        let last_request = now - Duration::days(40);
+        assert_eq!(last_activity(&t, Some(last_request), &prefs), last_request);
+
+        // 被自动停用后又被重新启用：从停用时间起重新计算闲置期
+        let prefs = TokenAutoDisablePrefs {
+            exempt: false,
+            disabled_at: Some(now - Duration::days(1)),
+        };
+        assert_eq!(
+            last_activity(&t, Some(last_request), &prefs),
+            now - Duration::days(1)
+        );
+    }
+}
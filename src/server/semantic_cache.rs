@@ -0,0 +1,371 @@
+//! 语义响应缓存（可选）：用配置的 embedding 模型向量化提示，同一令牌、同一模型与参数下
+//! 余弦相似度达到阈值的请求直接返回缓存的响应，不访问上游、不计费。
+//! 向量与响应只保存在本进程内存中（按 TTL 与条目上限淘汰）；仅用于非流式请求。
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use sha2::Digest;
+
+use crate::config::settings::SemanticCacheConfig;
+use crate::error::GatewayError;
+use crate::providers::openai::types::RawAndTypedChatCompletion;
+use crate::providers::openai::{ChatCompletionRequest, OpenAIProvider};
+use crate::server::AppState;
+use crate::server::provider_dispatch::select_named_provider;
+
+/// 响应头：hit 表示命中缓存，miss 表示已查询但未命中
+pub const SEMANTIC_CACHE_HEADER: &str = "x-gateway-semantic-cache";
+
+struct Entry {
+    token_id: String,
+    scope: String,
+    vector: Vec<f32>,
+    response: RawAndTypedChatCompletion,
+    expires_at: DateTime<Utc>,
+}
+
+#[derive(Default)]
+pub struct SemanticCache {
+    entries: Mutex<VecDeque<Entry>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    embedding_errors: AtomicU64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SemanticCacheStats {
+    pub enabled: bool,
+    pub entries: usize,
+    pub hits: u64,
+    pub misses: u64,
+    pub hit_rate: f64,
+    pub embedding_errors: u64,
+}
+
+/// 查询前计算好的缓存键；未命中时在上游成功返回后写入
+pub struct CacheKey {
+    scope: String,
+    vector: Vec<f32>,
+}
+
+pub enum Lookup {
+    Hit(Box<RawAndTypedChatCompletion>),
+    Miss(CacheKey),
+}
+
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() || a.is_empty() {
+        return 0.0;
+    }
+    let (mut dot, mut norm_a, mut norm_b) = (0.0f32, 0.0f32, 0.0f32);
+    for (x, y) in a.iter().zip(b) {
+        dot += x * y;
+        norm_a += x * x;
+        norm_b += y * y;
+    }
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+    dot / (norm_a.sqrt() * norm_b.sqrt())
+}
+
+/// 缓存作用域：模型与除 messages 外的全部参数的指纹，温度、工具等不同的请求互不命中
+pub fn scope_key(request: &ChatCompletionRequest, top_k: Option<u32>) -> String {
+    let mut value = serde_json::to_value(request).unwrap_or(Value::Null);
+    if let Some(obj) = value.as_object_mut() {
+        obj.remove("messages");
+        obj.remove("stream");
+        obj.remove("stream_options");
+        obj.insert("top_k".into(), top_k.into());
+    }
+    let mut hasher = sha2::Sha256::new();
+    hasher.update(value.to_string().as_bytes());
+    hex::encode(hasher.finalize())
+}
+
+/// 用于向量化的提示文本：每条消息一行 "role: 文本"（多模态消息只取文本部分）
+pub fn prompt_text(request: &ChatCompletionRequest) -> String {
+    let messages = serde_json::to_value(&request.messages).unwrap_or(Value::Null);
+    let mut lines = Vec::new();
+    for message in messages.as_array().into_iter().flatten() {
+        let role = message.get("role").and_then(Value::as_str).unwrap_or("");
+        let text = match message.get("content") {
+            Some(Value::String(text)) => text.clone(),
+            Some(Value::Array(parts)) => parts
+                .iter()
+                .filter_map(|part| part.get("text").and_then(Value::as_str))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            _ => message
+                .get("tool_calls")
+                .map(Value::to_string)
+                .unwrap_or_default(),
+        };
+        lines.push(format!("{}: {}", role, text));
+    }
+    lines.join("\n")
+}
+
+impl SemanticCache {
+    fn find(
+        &self,
+        token_id: &str,
+        key: &CacheKey,
+        threshold: f32,
+        now: DateTime<Utc>,
+    ) -> Option<(RawAndTypedChatCompletion, f32)> {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.retain(|entry| entry.expires_at > now);
+        let found = entries
+            .iter()
+            .filter(|entry| entry.token_id == token_id && entry.scope == key.scope)
+            .map(|entry| (entry, cosine_similarity(&entry.vector, &key.vector)))
+            .filter(|(_, similarity)| *similarity >= threshold)
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(entry, similarity)| (entry.response.clone(), similarity));
+        let counter = if found.is_some() {
+            &self.hits
+        } else {
+            &self.misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        found
+    }
+
+    fn insert(
+        &self,
+        token_id: &str,
+        key: CacheKey,
+        response: RawAndTypedChatCompletion,
+        config: &SemanticCacheConfig,
+        now: DateTime<Utc>,
+    ) {
+        if config.max_entries == 0 {
+            return;
+        }
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        while entries.len() >= config.max_entries {
+            entries.pop_front();
+        }
+        entries.push_back(Entry {
+            token_id: token_id.to_string(),
+            scope: key.scope,
+            vector: key.vector,
+            response,
+            expires_at: now + Duration::seconds(config.ttl_secs.min(i64::MAX as u64) as i64),
+        });
+    }
+
+    pub fn stats(&self, enabled: bool) -> SemanticCacheStats {
+        let hits = self.hits.load(Ordering::Relaxed);
+        let misses = self.misses.load(Ordering::Relaxed);
+        let lookups = hits + misses;
+        SemanticCacheStats {
+            enabled,
+            entries: self.entries.lock().unwrap_or_else(|e| e.into_inner()).len(),
+            hits,
+            misses,
+            hit_rate: if lookups > 0 {
+                hits as f64 / lookups as f64
+            } else {
+                0.0
+            },
+            embedding_errors: self.embedding_errors.load(Ordering::Relaxed),
+        }
+    }
+}
+
+async fn embed(
+    app_state: &AppState,
+    config: &SemanticCacheConfig,
+    input: &str,
+) -> Result<Vec<f32>, GatewayError> {
+    let provider = app_state
+        .providers
+        .get_provider(&config.embedding_provider)
+        .await?
+        .ok_or_else(|| {
+            GatewayError::NotFound(format!(
+                "Provider '{}' not found",
+                config.embedding_provider
+            ))
+        })?;
+    if !provider.api_type.capabilities().openai_compatible {
+        return Err(GatewayError::Config(format!(
+            "embedding provider '{}' is not OpenAI-compatible",
+            provider.name
+        )));
+    }
+    let selected = select_named_provider(app_state, provider, None, Default::default()).await?;
+    OpenAIProvider::embeddings(
+        &selected.provider.base_url,
+        &selected.api_key,
+        &selected.openai_account,
+        &config.embedding_model,
+        input,
+    )
+    .await
+}
+
+/// 查询缓存；未配置语义缓存或向量化失败时返回 None（按正常请求处理）
+pub async fn lookup(
+    app_state: &AppState,
+    token_id: &str,
+    request: &ChatCompletionRequest,
+    top_k: Option<u32>,
+) -> Option<Lookup> {
+    let config = app_state.config.server.semantic_cache.as_ref()?;
+    let vector = match embed(app_state, config, &prompt_text(request)).await {
+        Ok(vector) => vector,
+        Err(e) => {
+            app_state
+                .semantic_cache
+                .embedding_errors
+                .fetch_add(1, Ordering::Relaxed);
+            tracing::warn!("semantic cache embedding failed: {}", e);
+            return None;
+        }
+    };
+    let key = CacheKey {
+        scope: scope_key(request, top_k),
+        vector,
+    };
+    match app_state
+        .semantic_cache
+        .find(token_id, &key, config.similarity_threshold, Utc::now())
+    {
+        Some((response, similarity)) => {
+            tracing::debug!(token_id, similarity, "semantic cache hit");
+            Some(Lookup::Hit(Box::new(response)))
+        }
+        None => Some(Lookup::Miss(key)),
+    }
+}
+
+/// 未命中的请求在上游成功返回后写入缓存
+pub fn store(
+    app_state: &AppState,
+    token_id: &str,
+    key: CacheKey,
+    response: &RawAndTypedChatCompletion,
+) {
+    if let Some(config) = app_state.config.server.semantic_cache.as_ref() {
+        app_state
+            .semantic_cache
+            .insert(token_id, key, response.clone(), config, Utc::now());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn request(value: Value) -> ChatCompletionRequest {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn response(content: &str) -> RawAndTypedChatCompletion {
+        let raw = json!({
+            "id": "chatcmpl-1",
+            "object": "chat.completion",
+            "created": 1,
+            "model": "m1",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": content},
+                "finish_reason": "stop"
+            }]
+        });
+        RawAndTypedChatCompletion {
+            typed: serde_json::from_value(raw.clone()).unwrap(),
+            raw,
+        }
+    }
+
+    fn config(max_entries: usize) -> SemanticCacheConfig {
+        SemanticCacheConfig {
+            embedding_provider: "openai".into(),
+            embedding_model: "text-embedding-3-small".into(),
+            similarity_threshold: 0.9,
+            ttl_secs: 60,
+            max_entries,
+        }
+    }
+
+    #[test]
+    fn cosine_similarity_handles_mismatch_and_zero() {
+        assert!((cosine_similarity(&[1.0, 0.0], &[2.0, 0.0]) - 1.0).abs() < 1e-6);
+        assert_eq!(cosine_similarity(&[1.0, 0.0], &[0.0, 1.0]), 0.0);
+        assert_eq!(cosine_similarity(&[1.0], &[1.0, 0.0]), 0.0);
+        assert_eq!(cosine_similarity(&[0.0, 0.0], &[1.0, 0.0]), 0.0);
+    }
+
+    #[test]
+    fn scope_ignores_messages_but_not_parameters() {
+        let a = request(json!({"model": "m1", "messages": [{"role": "user", "content": "a"}]}));
+        let b = request(
+            json!({"model": "m1", "messages": [{"role": "user", "content": "b"}], "stream": false}),
+        );
+        let c = request(json!({"model": "m1", "messages": [], "temperature": 0.2}));
+        assert_eq!(scope_key(&a, None), scope_key(&b, None));
+        assert_ne!(scope_key(&a, None), scope_key(&c, None));
+        assert_ne!(scope_key(&a, None), scope_key(&a, Some(5)));
+        assert_eq!(prompt_text(&a), "user: a");
+    }
+
+    #[test]
+    fn lookup_matches_by_token_scope_and_threshold() {
+        let cache = SemanticCache::default();
+        let now = Utc::now();
+        let key = |vector: Vec<f32>| CacheKey {
+            scope: "s".into(),
+            vector,
+        };
+        cache.insert(
+            "t1",
+            key(vec![1.0, 0.0]),
+            response("cached"),
+            &config(10),
+            now,
+        );
+
+        let (hit, similarity) = cache.find("t1", &key(vec![0.99, 0.05]), 0.9, now).unwrap();
+        assert_eq!(hit.raw["choices"][0]["message"]["content"], "cached");
+        assert!(similarity > 0.9);
+        assert!(cache.find("t2", &key(vec![1.0, 0.0]), 0.9, now).is_none());
+        assert!(cache.find("t1", &key(vec![0.0, 1.0]), 0.9, now).is_none());
+        assert!(
+            cache
+                .find("t1", &key(vec![1.0, 0.0]), 0.9, now + Duration::seconds(61))
+                .is_none()
+        );
+
+        let stats = cache.stats(true);
+        assert_eq!((stats.hits, stats.misses, stats.entries), (1, 3, 0));
+    }
+
+    #[test]
+    fn oldest_entries_are_evicted() {
+        let cache = SemanticCache::default();
+        let now = Utc::now();
+        for i in 0..3 {
+            let key = CacheKey {
+                scope: "s".into(),
+                vector: vec![1.0, i as f32],
+            };
+            cache.insert("t1", key, response("r"), &config(2), now);
+        }
+        assert_eq!(cache.stats(true).entries, 2);
+        let oldest = CacheKey {
+            scope: "s".into(),
+            vector: vec![1.0, 0.0],
+        };
+        assert!(cache.find("t1", &oldest, 0.999, now).is_none());
+    }
+}
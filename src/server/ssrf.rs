@@ -2,7 +2,7 @@ use crate::error::GatewayError;
 use reqwest::Url;
 use std::net::{IpAddr, Ipv4Addr};
 
-fn is_disallowed_ip(ip: IpAddr) -> bool {
+pub(crate) fn is_disallowed_ip(ip: IpAddr) -> bool {
     match ip {
         IpAddr::V4(v4) => {
             v4.is_private()
@@ -27,7 +27,7 @@ fn is_v4_shared(ip: Ipv4Addr) -> bool {
     a == 100 && (64..=127).contains(&b)
 }
 
-fn is_disallowed_host(domain: &str) -> bool {
+pub(crate) fn is_disallowed_host(domain: &str) -> bool {
     let d = domain.trim().trim_end_matches('.').to_ascii_lowercase();
     d == "localhost" || d.ends_with(".localhost") || d.ends_with(".local")
 }
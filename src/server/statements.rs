@@ -0,0 +1,553 @@
+//! 月度账单：按北京时间自然月汇总每个令牌、每个组织的请求数、Token 用量与花费
+//! （按模型、按日拆分），生成后以不可变记录保存，可导出 CSV / PDF 作为开票依据。
+
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{DateTime, Datelike, NaiveDate, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::error::GatewayError;
+use crate::logging::time::BEIJING_OFFSET;
+use crate::logging::types::{StatementRecord, TokenUsageDaily};
+use crate::server::AppState;
+
+pub const SCOPE_TOKEN: &str = "token";
+pub const SCOPE_ORGANIZATION: &str = "organization";
+
+/// 单页 PDF 的行数
+const PDF_LINES_PER_PAGE: usize = 60;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct UsageLine {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub day: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    pub requests: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub total_tokens: u64,
+    pub cost: f64,
+}
+
+impl UsageLine {
+    fn add(&mut self, row: &TokenUsageDaily) {
+        self.requests += row.requests;
+        self.prompt_tokens += row.prompt_tokens;
+        self.completion_tokens += row.completion_tokens;
+        self.total_tokens += row.total_tokens;
+        self.cost += row.cost;
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Statement {
+    pub id: String,
+    pub scope: String,
+    pub subject_id: String,
+    pub subject_name: String,
+    pub period: String,
+    pub since: DateTime<Utc>,
+    pub until: DateTime<Utc>,
+    pub totals: UsageLine,
+    pub by_model: Vec<UsageLine>,
+    pub by_day: Vec<UsageLine>,
+    /// 按 (日期, 模型) 的明细
+    pub lines: Vec<UsageLine>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GenerateReport {
+    pub period: String,
+    pub created: usize,
+    /// 已存在的账单保持不变
+    pub existing: usize,
+}
+
+/// 解析 `YYYY-MM`，返回该月（北京时间）的 [since, until)
+pub fn period_bounds(period: &str) -> Result<(DateTime<Utc>, DateTime<Utc>), GatewayError> {
+    let invalid = || GatewayError::Config(format!("invalid period '{}', expected YYYY-MM", period));
+    let (y, m) = period.split_once('-').ok_or_else(invalid)?;
+    if y.len() != 4 || m.len() != 2 {
+        return Err(invalid());
+    }
+    let year: i32 = y.parse().map_err(|_| invalid())?;
+    let month: u32 = m.parse().map_err(|_| invalid())?;
+    let start = NaiveDate::from_ymd_opt(year, month, 1).ok_or_else(invalid)?;
+    let next = if month == 12 {
+        NaiveDate::from_ymd_opt(year + 1, 1, 1)
+    } else {
+        NaiveDate::from_ymd_opt(year, month + 1, 1)
+    }
+    .ok_or_else(invalid)?;
+    let to_utc = |d: NaiveDate| {
+        BEIJING_OFFSET
+            .from_local_datetime(&d.and_hms_opt(0, 0, 0).unwrap_or_default())
+            .single()
+            .map(|dt| dt.with_timezone(&Utc))
+            .ok_or_else(invalid)
+    };
+    Ok((to_utc(start)?, to_utc(next)?))
+}
+
+/// now 所在月份的上一个自然月（北京时间）
+pub fn previous_period(now: DateTime<Utc>) -> String {
+    let local = now.with_timezone(&BEIJING_OFFSET);
+    let (year, month) = if local.month() == 1 {
+        (local.year() - 1, 12)
+    } else {
+        (local.year(), local.month() - 1)
+    };
+    format!("{:04}-{:02}", year, month)
+}
+
+/// 账单 ID 由 (scope, subject, period) 确定，重复生成不会产生新记录
+pub fn statement_id(scope: &str, subject_id: &str, period: &str) -> String {
+    let digest = Sha256::digest(format!("{}\n{}\n{}", scope, subject_id, period));
+    format!("stmt_{}", &hex::encode(digest)[..24])
+}
+
+fn ordered(map: BTreeMap<String, UsageLine>) -> Vec<UsageLine> {
+    map.into_values().collect()
+}
+
+/// 由按日/模型汇总的用量生成账单；tokens 为 令牌 ID -> (名称, 组织 ID)。
+/// 组织归属按生成时令牌所属组织计算；无用量的令牌/组织不生成账单
+pub fn build_statements(
+    period: &str,
+    since: DateTime<Utc>,
+    until: DateTime<Utc>,
+    rows: &[TokenUsageDaily],
+    tokens: &HashMap<String, (String, Option<String>)>,
+    now: DateTime<Utc>,
+) -> Vec<Statement> {
+    #[derive(Default)]
+    struct Acc {
+        totals: UsageLine,
+        by_model: BTreeMap<String, UsageLine>,
+        by_day: BTreeMap<String, UsageLine>,
+        lines: BTreeMap<(String, String), UsageLine>,
+    }
+    impl Acc {
+        fn add(&mut self, row: &TokenUsageDaily) {
+            self.totals.add(row);
+            self.by_model
+                .entry(row.model.clone())
+                .or_insert_with(|| UsageLine {
+                    model: Some(row.model.clone()),
+                    ..Default::default()
+                })
+                .add(row);
+            self.by_day
+                .entry(row.day.clone())
+                .or_insert_with(|| UsageLine {
+                    day: Some(row.day.clone()),
+                    ..Default::default()
+                })
+                .add(row);
+            self.lines
+                .entry((row.day.clone(), row.model.clone()))
+                .or_insert_with(|| UsageLine {
+                    day: Some(row.day.clone()),
+                    model: Some(row.model.clone()),
+                    ..Default::default()
+                })
+                .add(row);
+        }
+    }
+
+    let mut subjects: BTreeMap<(&'static str, String), Acc> = BTreeMap::new();
+    for row in rows {
+        subjects
+            .entry((SCOPE_TOKEN, row.client_token.clone()))
+            .or_default()
+            .add(row);
+        if let Some((_, Some(org))) = tokens.get(&row.client_token) {
+            subjects
+                .entry((SCOPE_ORGANIZATION, org.clone()))
+                .or_default()
+                .add(row);
+        }
+    }
+    subjects
+        .into_iter()
+        .map(|((scope, subject_id), acc)| {
+            let subject_name = match scope {
+                SCOPE_TOKEN => tokens
+                    .get(&subject_id)
+                    .map(|(name, _)| name.clone())
+                    .unwrap_or_else(|| subject_id.clone()),
+                _ => subject_id.clone(),
+            };
+            Statement {
+                id: statement_id(scope, &subject_id, period),
+                scope: scope.to_string(),
+                subject_id,
+                subject_name,
+                period: period.to_string(),
+                since,
+                until,
+                totals: acc.totals,
+                by_model: ordered(acc.by_model),
+                by_day: ordered(acc.by_day),
+                lines: acc.lines.into_values().collect(),
+                created_at: now,
+            }
+        })
+        .collect()
+}
+
+/// 生成指定月份的账单（该月须已结束）；已存在的账单不会被覆盖
+pub async fn generate_for_period(
+    app_state: &AppState,
+    period: &str,
+    now: DateTime<Utc>,
+) -> Result<GenerateReport, GatewayError> {
+    let (since, until) = period_bounds(period)?;
+    if until > now {
+        return Err(GatewayError::Config(format!(
+            "period '{}' has not ended yet",
+            period
+        )));
+    }
+    let rows = app_state
+        .log_store
+        .aggregate_token_usage_daily(since, until)
+        .await
+        .map_err(GatewayError::Db)?;
+    let tokens = app_state
+        .token_store
+        .list_tokens()
+        .await?
+        .into_iter()
+        .map(|t| (t.id, (t.name, t.organization_id)))
+        .collect();
+    let mut report = GenerateReport {
+        period: period.to_string(),
+        created: 0,
+        existing: 0,
+    };
+    for statement in build_statements(period, since, until, &rows, &tokens, now) {
+        let record = StatementRecord {
+            id: statement.id.clone(),
+            scope: statement.scope.clone(),
+            subject_id: statement.subject_id.clone(),
+            period: statement.period.clone(),
+            body: serde_json::to_string(&statement)?,
+            created_at: now,
+        };
+        if app_state
+            .log_store
+            .insert_statement(record)
+            .await
+            .map_err(GatewayError::Db)?
+        {
+            report.created += 1;
+        } else {
+            report.existing += 1;
+        }
+    }
+    Ok(report)
+}
+
+/// 定时任务：上个月的账单尚未生成时生成；返回新生成的数量
+pub async fn generate_closed_period(
+    app_state: &AppState,
+    now: DateTime<Utc>,
+) -> Result<usize, GatewayError> {
+    let period = previous_period(now);
+    let existing = app_state
+        .log_store
+        .list_statements(None, None, Some(&period))
+        .await
+        .map_err(GatewayError::Db)?;
+    if !existing.is_empty() {
+        return Ok(0);
+    }
+    Ok(generate_for_period(app_state, &period, now).await?.created)
+}
+
+pub fn parse_record(record: &StatementRecord) -> Result<Statement, GatewayError> {
+    Ok(serde_json::from_str(&record.body)?)
+}
+
+fn escape_csv_field(field: &str) -> String {
+    if field.contains(',') || field.contains('"') || field.contains('\n') {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// CSV：每行一个 (日期, 模型) 明细，末行为合计
+pub fn to_csv(statement: &Statement) -> String {
+    let mut out =
+        String::from("day,model,requests,prompt_tokens,completion_tokens,total_tokens,cost\n");
+    let row = |line: &UsageLine, day: &str, model: &str| {
+        format!(
+            "{},{},{},{},{},{},{:.6}\n",
+            escape_csv_field(day),
+            escape_csv_field(model),
+            line.requests,
+            line.prompt_tokens,
+            line.completion_tokens,
+            line.total_tokens,
+            line.cost
+        )
+    };
+    for line in &statement.lines {
+        out.push_str(&row(
+            line,
+            line.day.as_deref().unwrap_or_default(),
+            line.model.as_deref().unwrap_or_default(),
+        ));
+    }
+    out.push_str(&row(&statement.totals, "total", ""));
+    out
+}
+
+fn statement_text_lines(statement: &Statement) -> Vec<String> {
+    let fmt_line = |label: &str, l: &UsageLine| {
+        format!(
+            "{:<40} {:>8} {:>12} {:>14.4}",
+            label, l.requests, l.total_tokens, l.cost
+        )
+    };
+    let header = format!(
+        "{:<40} {:>8} {:>12} {:>14}",
+        "", "Requests", "Tokens", "Cost"
+    );
+    let mut lines = vec![
+        format!("Statement {}", statement.id),
+        format!(
+            "{}: {} ({})",
+            statement.scope, statement.subject_name, statement.subject_id
+        ),
+        format!("Period: {} (UTC+8)", statement.period),
+        String::new(),
+        header.clone(),
+        fmt_line("Total", &statement.totals),
+        String::new(),
+        "By model".to_string(),
+        header.clone(),
+    ];
+    for l in &statement.by_model {
+        lines.push(fmt_line(l.model.as_deref().unwrap_or_default(), l));
+    }
+    lines.push(String::new());
+    lines.push("By day".to_string());
+    lines.push(header);
+    for l in &statement.by_day {
+        lines.push(fmt_line(l.day.as_deref().unwrap_or_default(), l));
+    }
+    lines
+}
+
+/// 一行文本的绘制指令：ASCII 片段用 Courier（F1，保持列对齐），
+/// 其余字符切换到预置 CJK 字体（F2，UniGB-UTF16-H 编码，十六进制 UTF-16BE 字符串）；
+/// ASCII 控制字符以 `?` 代替
+fn pdf_text_ops(text: &str) -> String {
+    let mut out = String::new();
+    let mut ascii = String::new();
+    let mut wide: Vec<u16> = Vec::new();
+    let flush_ascii = |out: &mut String, ascii: &mut String| {
+        if !ascii.is_empty() {
+            out.push_str(&format!("/F1 9 Tf ({}) Tj ", ascii));
+            ascii.clear();
+        }
+    };
+    let flush_wide = |out: &mut String, wide: &mut Vec<u16>| {
+        if !wide.is_empty() {
+            let hex: String = wide.iter().map(|u| format!("{:04X}", u)).collect();
+            out.push_str(&format!("/F2 9 Tf <{}> Tj ", hex));
+            wide.clear();
+        }
+    };
+    for c in text.chars() {
+        if c.is_ascii() {
+            flush_wide(&mut out, &mut wide);
+            match c {
+                '\\' | '(' | ')' => {
+                    ascii.push('\\');
+                    ascii.push(c);
+                }
+                ' '..='~' => ascii.push(c),
+                _ => ascii.push('?'),
+            }
+        } else {
+            flush_ascii(&mut out, &mut ascii);
+            let mut buf = [0u16; 2];
+            wide.extend_from_slice(c.encode_utf16(&mut buf));
+        }
+    }
+    flush_ascii(&mut out, &mut ascii);
+    flush_wide(&mut out, &mut wide);
+    out
+}
+
+/// 生成不依赖外部库的简单文本 PDF（A4，ASCII 用等宽字体、中文用预置 CJK 字体，自动分页）
+pub fn to_pdf(statement: &Statement) -> Vec<u8> {
+    let lines = statement_text_lines(statement);
+    let pages: Vec<&[String]> = lines.chunks(PDF_LINES_PER_PAGE).collect();
+    // 对象编号：1 目录，2 页面树，3 Courier，4-6 CJK 字体（Type0 / CIDFont / 字体描述），
+    // 之后每页依次为页面对象与内容流
+    let mut objects: Vec<String> = Vec::new();
+    let page_ids: Vec<usize> = (0..pages.len()).map(|i| 7 + i * 2).collect();
+    objects.push("<< /Type /Catalog /Pages 2 0 R >>".to_string());
+    objects.push(format!(
+        "<< /Type /Pages /Kids [{}] /Count {} >>",
+        page_ids
+            .iter()
+            .map(|id| format!("{} 0 R", id))
+            .collect::<Vec<_>>()
+            .join(" "),
+        pages.len()
+    ));
+    objects.push("<< /Type /Font /Subtype /Type1 /BaseFont /Courier >>".to_string());
+    // Adobe 预置的简体中文字体与 Unicode CMap，阅读器自带或按需替换，无需嵌入字形
+    objects.push(
+        "<< /Type /Font /Subtype /Type0 /BaseFont /STSong-Light-UniGB-UTF16-H /Encoding /UniGB-UTF16-H /DescendantFonts [5 0 R] >>"
+            .to_string(),
+    );
+    objects.push(
+        "<< /Type /Font /Subtype /CIDFontType0 /BaseFont /STSong-Light /CIDSystemInfo << /Registry (Adobe) /Ordering (GB1) /Supplement 4 >> /FontDescriptor 6 0 R /DW 1000 >>"
+            .to_string(),
+    );
+    objects.push(
+        "<< /Type /FontDescriptor /FontName /STSong-Light /Flags 6 /FontBBox [-25 -254 1000 880] /ItalicAngle 0 /Ascent 880 /Descent -120 /CapHeight 880 /StemV 93 >>"
+            .to_string(),
+    );
+    for (page, id) in pages.iter().zip(&page_ids) {
+        let mut content = String::from("BT 11 TL 36 806 Td\n");
+        for line in page.iter() {
+            content.push_str(&format!("{}T*\n", pdf_text_ops(line)));
+        }
+        content.push_str("ET");
+        objects.push(format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 595 842] /Resources << /Font << /F1 3 0 R /F2 4 0 R >> >> /Contents {} 0 R >>",
+            id + 1
+        ));
+        objects.push(format!(
+            "<< /Length {} >>\nstream\n{}\nendstream",
+            content.len(),
+            content
+        ));
+    }
+
+    let mut out = String::from("%PDF-1.4\n");
+    let mut offsets = Vec::with_capacity(objects.len());
+    for (i, body) in objects.iter().enumerate() {
+        offsets.push(out.len());
+        out.push_str(&format!("{} 0 obj\n{}\nendobj\n", i + 1, body));
+    }
+    let xref = out.len();
+    out.push_str(&format!(
+        "xref\n0 {}\n0000000000 65535 f \n",
+        objects.len() + 1
+    ));
+    for offset in offsets {
+        out.push_str(&format!("{:010} 00000 n \n", offset));
+    }
+    out.push_str(&format!(
+        "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+        objects.len() + 1,
+        xref
+    ));
+    out.into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage(token: &str, day: &str, model: &str, requests: u64, cost: f64) -> TokenUsageDaily {
+        TokenUsageDaily {
+            client_token: token.into(),
+            day: day.into(),
+            model: model.into(),
+            requests,
+            prompt_tokens: requests * 10,
+            completion_tokens: requests * 5,
+            total_tokens: requests * 15,
+            cost,
+        }
+    }
+
+    #[test]
+    fn period_bounds_use_beijing_calendar_month() {
+        let (since, until) = period_bounds("2026-12").unwrap();
+        assert_eq!(since.to_rfc3339(), "2026-11-30T16:00:00+00:00");
+        assert_eq!(until.to_rfc3339(), "2026-12-31T16:00:00+00:00");
+        assert!(period_bounds("2026-13").is_err());
+        assert!(period_bounds("202601").is_err());
+        let jan = DateTime::parse_from_rfc3339("2026-01-01T02:00:00+08:00")
+            .unwrap()
+            .with_timezone(&Utc);
+        assert_eq!(previous_period(jan), "2025-12");
+    }
+
+    #[test]
+    fn builds_token_and_organization_statements() {
+        let (since, until) = period_bounds("2026-09").unwrap();
+        let rows = vec![
+            usage("ct_a", "2026-09-01", "gpt-4o", 2, 1.0),
+            usage("ct_a", "2026-09-02", "gpt-4o", 1, 0.5),
+            usage("ct_b", "2026-09-02", "claude", 3, 2.0),
+            usage("ct_c", "2026-09-03", "gpt-4o", 1, 0.25),
+        ];
+        let tokens = HashMap::from([
+            (
+                "ct_a".to_string(),
+                ("partner".to_string(), Some("org-1".to_string())),
+            ),
+            (
+                "ct_b".to_string(),
+                ("batch".to_string(), Some("org-1".to_string())),
+            ),
+            ("ct_c".to_string(), ("solo".to_string(), None)),
+        ]);
+        let statements = build_statements("2026-09", since, until, &rows, &tokens, Utc::now());
+        assert_eq!(statements.len(), 4);
+
+        let org = statements
+            .iter()
+            .find(|s| s.scope == SCOPE_ORGANIZATION)
+            .unwrap();
+        assert_eq!(org.subject_id, "org-1");
+        assert_eq!(org.totals.requests, 6);
+        assert!((org.totals.cost - 3.5).abs() < 1e-9);
+        assert_eq!(org.by_model.len(), 2);
+        assert_eq!(org.by_day.len(), 2);
+        assert_eq!(org.lines.len(), 3);
+
+        let a = statements.iter().find(|s| s.subject_id == "ct_a").unwrap();
+        assert_eq!(a.subject_name, "partner");
+        assert_eq!(a.id, statement_id(SCOPE_TOKEN, "ct_a", "2026-09"));
+        let csv = to_csv(a);
+        assert!(csv.contains("2026-09-01,gpt-4o,2,20,10,30,1.000000"));
+        assert!(csv.ends_with("total,,3,30,15,45,1.500000\n"));
+
+        let pdf = String::from_utf8(to_pdf(a)).unwrap();
+        assert!(pdf.starts_with("%PDF-1.4"));
+        assert!(pdf.contains("/F1 9 Tf (Period: 2026-09 \\(UTC+8\\)) Tj"));
+        assert!(pdf.trim_end().ends_with("%%EOF"));
+    }
+
+    #[test]
+    fn pdf_renders_chinese_names_with_cjk_font() {
+        let (since, until) = period_bounds("2026-09").unwrap();
+        let rows = vec![usage("ct_a", "2026-09-01", "gpt-4o", 1, 0.5)];
+        let tokens = HashMap::from([("ct_a".to_string(), ("张三的令牌".to_string(), None))]);
+        let statements = build_statements("2026-09", since, until, &rows, &tokens, Utc::now());
+        let pdf = String::from_utf8(to_pdf(&statements[0])).unwrap();
+        assert!(!pdf.contains('?'));
+        assert!(pdf.contains("/Encoding /UniGB-UTF16-H"));
+        // 张三的令牌 -> UTF-16BE
+        assert!(pdf.contains("/F2 9 Tf <5F204E0976844EE4724C> Tj"));
+        assert!(pdf.contains(
+            "/F1 9 Tf (token: ) Tj /F2 9 Tf <5F204E0976844EE4724C> Tj /F1 9 Tf ( \\(ct_a\\)) Tj"
+        ));
+    }
+}
@@ -1,14 +1,21 @@
+use std::collections::BTreeMap;
 use std::future::Future;
 use std::pin::Pin;
 
 use crate::config::settings::{KeyLogStrategy, Provider};
 use crate::logging::types::{
-    ModelPriceRecord, ModelPriceUpsert, ProviderOpLog, RequestLogDetailRecord, StoredCompareRun,
-    StoredRequestLabSnapshot, StoredRequestLabSource, StoredRequestLabTemplate,
+    AdminNotificationRecord, AudioPriceRecord, DebugCaptureRecord, ImagePriceRecord, LogColumns,
+    MaintenanceWindowRecord, MetricsReportRecord, ModelPriceRecord, ModelPriceUpsert,
+    ParamPolicyRecord, ProviderBudgetRecord, ProviderEgressDaily, ProviderOpLog,
+    RequestHeatmapCell, RequestLogDetailRecord, SpeechPriceRecord, StatementRecord,
+    StoredCompareRun, StoredIdempotentResponse, StoredRequestLabSnapshot, StoredRequestLabSource,
+    StoredRequestLabTemplate, StreamCaptureRecord, TokenActivity, TokenRequestCount,
+    TokenUsageDaily, TokenUsageDelta, UsageWebhookDeadLetter,
 };
 use crate::logging::{CachedModel, DatabaseLogger, ProviderKeyStatsAgg, RequestLog};
 use crate::providers::openai::Model;
-use crate::routing::{KeyRotationStrategy, ProviderKeyEntry};
+use crate::routing::{KeyRotationStrategy, OpenAIAccountHeaders, ProviderKeyEntry};
+use crate::server::pagination::PageRequest;
 use chrono::{DateTime, Utc};
 
 pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
@@ -17,6 +24,13 @@ type DateRangeFuture<'a> = BoxFuture<'a, rusqlite::Result<Option<(DateTime<Utc>,
 type ModelPriceFuture<'a> = BoxFuture<'a, rusqlite::Result<Option<ModelPriceRecord>>>;
 type ModelPriceListFuture<'a> = BoxFuture<'a, rusqlite::Result<Vec<ModelPriceRecord>>>;
 type ModelEnabledGetFuture<'a> = BoxFuture<'a, rusqlite::Result<Option<bool>>>;
+type LastRequestMapFuture<'a> =
+    BoxFuture<'a, rusqlite::Result<std::collections::HashMap<String, DateTime<Utc>>>>;
+type ProviderSpendFuture<'a> =
+    BoxFuture<'a, rusqlite::Result<std::collections::HashMap<String, f64>>>;
+/// token_id -> (累计请求数, 指定日期的请求数)
+type TokenRequestCountsFuture<'a> =
+    BoxFuture<'a, rusqlite::Result<std::collections::HashMap<String, (i64, i64)>>>;
 type ModelEnabledListFuture<'a> = BoxFuture<'a, rusqlite::Result<Vec<(String, String, bool)>>>;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -62,16 +76,17 @@ pub trait RequestLogStore: Send + Sync {
         limit: i32,
         cursor: Option<i64>,
     ) -> BoxFuture<'a, rusqlite::Result<Vec<RequestLog>>>;
-    #[allow(dead_code)]
-    fn get_request_logs<'a>(
+    /// 只读取 columns 中的列（其余列为占位值）；method_path 为 Some 时仅返回该接口的日志
+    fn get_logs_with_columns<'a>(
         &'a self,
         limit: i32,
         cursor: Option<i64>,
+        method_path: Option<(&'a str, &'a str)>,
+        columns: &'a LogColumns,
     ) -> BoxFuture<'a, rusqlite::Result<Vec<RequestLog>>>;
-    fn get_logs_by_method_path<'a>(
+    #[allow(dead_code)]
+    fn get_request_logs<'a>(
         &'a self,
-        method: &'a str,
-        path: &'a str,
         limit: i32,
         cursor: Option<i64>,
     ) -> BoxFuture<'a, rusqlite::Result<Vec<RequestLog>>>;
@@ -93,6 +108,223 @@ pub trait RequestLogStore: Send + Sync {
         &'a self,
         id: &'a str,
     ) -> BoxFuture<'a, rusqlite::Result<Option<StoredCompareRun>>>;
+    fn get_idempotent_response<'a>(
+        &'a self,
+        token_id: &'a str,
+        idempotency_key: &'a str,
+    ) -> BoxFuture<'a, rusqlite::Result<Option<StoredIdempotentResponse>>>;
+    fn save_idempotent_response<'a>(
+        &'a self,
+        record: StoredIdempotentResponse,
+    ) -> BoxFuture<'a, rusqlite::Result<()>>;
+    fn save_debug_capture<'a>(
+        &'a self,
+        record: DebugCaptureRecord,
+    ) -> BoxFuture<'a, rusqlite::Result<()>>;
+    /// 已过期的捕获返回 None
+    fn get_debug_capture<'a>(
+        &'a self,
+        request_log_id: i64,
+    ) -> BoxFuture<'a, rusqlite::Result<Option<DebugCaptureRecord>>>;
+    fn purge_expired_debug_captures<'a>(
+        &'a self,
+        now: DateTime<Utc>,
+    ) -> BoxFuture<'a, rusqlite::Result<u64>>;
+    fn save_stream_capture<'a>(
+        &'a self,
+        record: StreamCaptureRecord,
+    ) -> BoxFuture<'a, rusqlite::Result<()>>;
+    fn get_stream_capture<'a>(
+        &'a self,
+        request_log_id: i64,
+    ) -> BoxFuture<'a, rusqlite::Result<Option<StreamCaptureRecord>>>;
+    fn insert_usage_webhook_dead_letter<'a>(
+        &'a self,
+        letter: UsageWebhookDeadLetter,
+    ) -> BoxFuture<'a, rusqlite::Result<i64>>;
+    fn list_usage_webhook_dead_letters<'a>(
+        &'a self,
+        limit: i32,
+    ) -> BoxFuture<'a, rusqlite::Result<Vec<UsageWebhookDeadLetter>>>;
+    fn get_usage_webhook_dead_letter<'a>(
+        &'a self,
+        id: i64,
+    ) -> BoxFuture<'a, rusqlite::Result<Option<UsageWebhookDeadLetter>>>;
+    fn delete_usage_webhook_dead_letter<'a>(
+        &'a self,
+        id: i64,
+    ) -> BoxFuture<'a, rusqlite::Result<bool>>;
+    fn insert_admin_notification<'a>(
+        &'a self,
+        notification: AdminNotificationRecord,
+    ) -> BoxFuture<'a, rusqlite::Result<Option<i64>>>;
+    fn list_admin_notifications<'a>(
+        &'a self,
+        unread_only: bool,
+        limit: i32,
+    ) -> BoxFuture<'a, rusqlite::Result<Vec<AdminNotificationRecord>>>;
+    fn count_unread_admin_notifications<'a>(&'a self) -> BoxFuture<'a, rusqlite::Result<u64>>;
+    fn mark_admin_notifications_read<'a>(
+        &'a self,
+        ids: Option<Vec<i64>>,
+        at: DateTime<Utc>,
+    ) -> BoxFuture<'a, rusqlite::Result<u64>>;
+    fn insert_maintenance_window<'a>(
+        &'a self,
+        window: MaintenanceWindowRecord,
+    ) -> BoxFuture<'a, rusqlite::Result<i64>>;
+    /// 结束时间晚于 ending_after 的维护窗口（按开始时间升序）
+    fn list_maintenance_windows<'a>(
+        &'a self,
+        ending_after: DateTime<Utc>,
+    ) -> BoxFuture<'a, rusqlite::Result<Vec<MaintenanceWindowRecord>>>;
+    fn delete_maintenance_window<'a>(&'a self, id: i64) -> BoxFuture<'a, rusqlite::Result<bool>>;
+    fn list_param_policies<'a>(&'a self)
+    -> BoxFuture<'a, rusqlite::Result<Vec<ParamPolicyRecord>>>;
+    fn upsert_param_policy<'a>(
+        &'a self,
+        policy: ParamPolicyRecord,
+    ) -> BoxFuture<'a, rusqlite::Result<()>>;
+    fn delete_param_policy<'a>(&'a self, id: &'a str) -> BoxFuture<'a, rusqlite::Result<bool>>;
+    fn list_metrics_reports<'a>(
+        &'a self,
+    ) -> BoxFuture<'a, rusqlite::Result<Vec<MetricsReportRecord>>>;
+    fn upsert_metrics_report<'a>(
+        &'a self,
+        report: MetricsReportRecord,
+    ) -> BoxFuture<'a, rusqlite::Result<()>>;
+    fn delete_metrics_report<'a>(&'a self, id: &'a str) -> BoxFuture<'a, rusqlite::Result<bool>>;
+    fn mark_metrics_report_sent<'a>(
+        &'a self,
+        id: &'a str,
+        sent_at: DateTime<Utc>,
+    ) -> BoxFuture<'a, rusqlite::Result<()>>;
+    /// 汇总 [since, until) 内各客户端令牌按日、按模型的用量
+    fn aggregate_token_usage_daily<'a>(
+        &'a self,
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+    ) -> BoxFuture<'a, rusqlite::Result<Vec<TokenUsageDaily>>>;
+    /// 账单只插入不更新；id 已存在时返回 false
+    fn insert_statement<'a>(
+        &'a self,
+        statement: StatementRecord,
+    ) -> BoxFuture<'a, rusqlite::Result<bool>>;
+    fn get_statement<'a>(
+        &'a self,
+        id: &'a str,
+    ) -> BoxFuture<'a, rusqlite::Result<Option<StatementRecord>>>;
+    /// 按条件列出账单（period 降序）
+    fn list_statements<'a>(
+        &'a self,
+        scope: Option<&'a str>,
+        subject_id: Option<&'a str>,
+        period: Option<&'a str>,
+    ) -> BoxFuture<'a, rusqlite::Result<Vec<StatementRecord>>>;
+    fn list_provider_budgets<'a>(
+        &'a self,
+    ) -> BoxFuture<'a, rusqlite::Result<Vec<ProviderBudgetRecord>>>;
+    fn get_provider_budget<'a>(
+        &'a self,
+        provider: &'a str,
+    ) -> BoxFuture<'a, rusqlite::Result<Option<ProviderBudgetRecord>>>;
+    fn upsert_provider_budget<'a>(
+        &'a self,
+        budget: ProviderBudgetRecord,
+    ) -> BoxFuture<'a, rusqlite::Result<()>>;
+    fn delete_provider_budget<'a>(
+        &'a self,
+        provider: &'a str,
+    ) -> BoxFuture<'a, rusqlite::Result<bool>>;
+    /// 图片单价列表（可限定供应商）
+    fn list_image_prices<'a>(
+        &'a self,
+        provider: Option<&'a str>,
+    ) -> BoxFuture<'a, rusqlite::Result<Vec<ImagePriceRecord>>>;
+    fn upsert_image_price<'a>(
+        &'a self,
+        price: ImagePriceRecord,
+    ) -> BoxFuture<'a, rusqlite::Result<()>>;
+    fn delete_image_price<'a>(
+        &'a self,
+        provider: &'a str,
+        model: &'a str,
+        size: &'a str,
+        quality: &'a str,
+    ) -> BoxFuture<'a, rusqlite::Result<bool>>;
+    /// 语音转写单价列表（可限定供应商）
+    fn list_audio_prices<'a>(
+        &'a self,
+        provider: Option<&'a str>,
+    ) -> BoxFuture<'a, rusqlite::Result<Vec<AudioPriceRecord>>>;
+    fn upsert_audio_price<'a>(
+        &'a self,
+        price: AudioPriceRecord,
+    ) -> BoxFuture<'a, rusqlite::Result<()>>;
+    fn delete_audio_price<'a>(
+        &'a self,
+        provider: &'a str,
+        model: &'a str,
+    ) -> BoxFuture<'a, rusqlite::Result<bool>>;
+    /// 语音合成单价列表（可限定供应商）
+    fn list_speech_prices<'a>(
+        &'a self,
+        provider: Option<&'a str>,
+    ) -> BoxFuture<'a, rusqlite::Result<Vec<SpeechPriceRecord>>>;
+    fn upsert_speech_price<'a>(
+        &'a self,
+        price: SpeechPriceRecord,
+    ) -> BoxFuture<'a, rusqlite::Result<()>>;
+    fn delete_speech_price<'a>(
+        &'a self,
+        provider: &'a str,
+        model: &'a str,
+    ) -> BoxFuture<'a, rusqlite::Result<bool>>;
+    /// since 之后各供应商的累计花费（amount_spent 之和）
+    fn sum_provider_spend_since<'a>(&'a self, since: DateTime<Utc>) -> ProviderSpendFuture<'a>;
+    /// 将计数累加到对应的按天汇总行（不存在则插入）
+    fn add_provider_egress<'a>(
+        &'a self,
+        rows: Vec<ProviderEgressDaily>,
+    ) -> BoxFuture<'a, rusqlite::Result<()>>;
+    /// 读取 [since_day, until_day] 范围内的按天汇总（按日期、供应商、密钥排序）
+    fn list_provider_egress<'a>(
+        &'a self,
+        since_day: &'a str,
+        until_day: &'a str,
+    ) -> BoxFuture<'a, rusqlite::Result<Vec<ProviderEgressDaily>>>;
+    /// 将令牌请求次数累加到对应的按天汇总行（不存在则插入）
+    fn add_token_request_counts<'a>(
+        &'a self,
+        rows: Vec<TokenRequestCount>,
+    ) -> BoxFuture<'a, rusqlite::Result<()>>;
+    /// 各令牌的累计请求数与 day 当天的请求数
+    fn sum_token_request_counts<'a>(&'a self, day: &'a str) -> TokenRequestCountsFuture<'a>;
+    /// 当前最大的请求日志 ID（无日志时为 0）
+    fn max_request_log_id<'a>(&'a self) -> BoxFuture<'a, rusqlite::Result<i64>>;
+    /// 日志 ID 位于 (after_id, until_id] 的请求按客户端令牌汇总的金额与 tokens（跳过 excluded_ids）
+    fn sum_token_usage_between<'a>(
+        &'a self,
+        after_id: i64,
+        until_id: i64,
+        excluded_ids: &'a [i64],
+    ) -> BoxFuture<'a, rusqlite::Result<Vec<TokenUsageDelta>>>;
+    /// [since, until) 内聊天请求按星期 × 小时汇总（可按计费模型、供应商过滤），只返回有请求的格子
+    fn request_heatmap<'a>(
+        &'a self,
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+        model: Option<&'a str>,
+        provider: Option<&'a str>,
+    ) -> BoxFuture<'a, rusqlite::Result<Vec<RequestHeatmapCell>>>;
+    /// 令牌活动汇总：since 之后的按日请求/花费与常用模型，以及全部日志中的最后使用时间与最近错误
+    fn token_activity<'a>(
+        &'a self,
+        client_token: &'a str,
+        since: DateTime<Utc>,
+        top_models: u32,
+        recent_errors: u32,
+    ) -> BoxFuture<'a, rusqlite::Result<TokenActivity>>;
     fn upsert_request_lab_source<'a>(
         &'a self,
         source: StoredRequestLabSource,
@@ -176,6 +408,12 @@ pub trait RequestLogStore: Send + Sync {
         since: Option<DateTime<Utc>>,
         until: Option<DateTime<Utc>>,
     ) -> BoxFuture<'a, rusqlite::Result<Vec<ProviderKeyStatsAgg>>>;
+    fn purge_request_logs_before<'a>(
+        &'a self,
+        cutoff: DateTime<Utc>,
+    ) -> BoxFuture<'a, rusqlite::Result<u64>>;
+    /// 每个客户端令牌（按令牌 ID）最近一次请求的时间
+    fn last_request_at_by_client_token<'a>(&'a self) -> LastRequestMapFuture<'a>;
     // provider ops audit log
     fn log_provider_op<'a>(&'a self, op: ProviderOpLog) -> BoxFuture<'a, rusqlite::Result<i64>>;
     fn get_provider_ops_logs<'a>(
@@ -183,13 +421,36 @@ pub trait RequestLogStore: Send + Sync {
         limit: i32,
         cursor: Option<i64>,
     ) -> BoxFuture<'a, rusqlite::Result<Vec<ProviderOpLog>>>;
+    fn get_provider_ops_logs_for_provider<'a>(
+        &'a self,
+        provider: &'a str,
+        since: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+        limit: i32,
+        cursor: Option<i64>,
+    ) -> BoxFuture<'a, rusqlite::Result<Vec<ProviderOpLog>>>;
+    fn purge_provider_ops_logs_before<'a>(
+        &'a self,
+        cutoff: DateTime<Utc>,
+    ) -> BoxFuture<'a, rusqlite::Result<u64>>;
     // pricing & billing
     fn upsert_model_price<'a>(
         &'a self,
         price: ModelPriceUpsert,
     ) -> BoxFuture<'a, rusqlite::Result<()>>;
+    /// 批量写入价格：全部成功或全部不生效
+    fn upsert_model_prices<'a>(
+        &'a self,
+        prices: Vec<ModelPriceUpsert>,
+    ) -> BoxFuture<'a, rusqlite::Result<()>>;
     fn get_model_price<'a>(&'a self, provider: &'a str, model: &'a str) -> ModelPriceFuture<'a>;
     fn list_model_prices<'a>(&'a self, provider: Option<&'a str>) -> ModelPriceListFuture<'a>;
+    /// 分页查询价格（可限定供应商），返回当前页与总数
+    fn list_model_prices_page<'a>(
+        &'a self,
+        provider: Option<&'a str>,
+        page: &'a PageRequest,
+    ) -> BoxFuture<'a, rusqlite::Result<(Vec<ModelPriceRecord>, u64)>>;
     fn sum_spent_amount_by_client_token<'a>(
         &'a self,
         token: &'a str,
@@ -247,6 +508,11 @@ pub trait ProviderStore: Send + Sync {
         name: &'a str,
     ) -> BoxFuture<'a, rusqlite::Result<Option<Provider>>>;
     fn list_providers<'a>(&'a self) -> BoxFuture<'a, rusqlite::Result<Vec<Provider>>>;
+    /// 分页查询供应商（不含密钥），返回当前页与总数
+    fn list_providers_page<'a>(
+        &'a self,
+        page: &'a PageRequest,
+    ) -> BoxFuture<'a, rusqlite::Result<(Vec<Provider>, u64)>>;
     fn delete_provider<'a>(&'a self, name: &'a str) -> BoxFuture<'a, rusqlite::Result<bool>>;
     fn set_provider_enabled<'a>(
         &'a self,
@@ -309,6 +575,34 @@ pub trait ProviderStore: Send + Sync {
         strategy: &'a Option<KeyLogStrategy>,
     ) -> BoxFuture<'a, rusqlite::Result<bool>>;
 
+    /// 设置密钥的 24 小时花费上限与每分钟请求/token 上限
+    fn set_provider_key_limits<'a>(
+        &'a self,
+        provider: &'a str,
+        key: &'a str,
+        spend_cap: Option<f64>,
+        rpm_limit: Option<u32>,
+        tpm_limit: Option<u32>,
+        strategy: &'a Option<KeyLogStrategy>,
+    ) -> BoxFuture<'a, rusqlite::Result<bool>>;
+
+    fn set_provider_key_openai_headers<'a>(
+        &'a self,
+        provider: &'a str,
+        key: &'a str,
+        headers: &'a OpenAIAccountHeaders,
+        strategy: &'a Option<KeyLogStrategy>,
+    ) -> BoxFuture<'a, rusqlite::Result<bool>>;
+
+    /// 设置密钥的分组标签（整体替换，空表示清除）
+    fn set_provider_key_labels<'a>(
+        &'a self,
+        provider: &'a str,
+        key: &'a str,
+        labels: &'a BTreeMap<String, String>,
+        strategy: &'a Option<KeyLogStrategy>,
+    ) -> BoxFuture<'a, rusqlite::Result<bool>>;
+
     fn set_provider_key_active<'a>(
         &'a self,
         provider: &'a str,
@@ -348,12 +642,41 @@ pub trait ProviderStore: Send + Sync {
     ) -> BoxFuture<'a, rusqlite::Result<bool>>;
 }
 
+/// 通用键值配置存储（运行期可调的网关设置等）
+pub trait SettingsStore: Send + Sync {
+    fn get_setting<'a>(&'a self, key: &'a str) -> BoxFuture<'a, rusqlite::Result<Option<String>>>;
+    fn set_setting<'a>(
+        &'a self,
+        key: &'a str,
+        value: &'a str,
+    ) -> BoxFuture<'a, rusqlite::Result<()>>;
+}
+
 pub trait OrganizationStore: Send + Sync {
     fn list_organizations<'a>(&'a self) -> BoxFuture<'a, rusqlite::Result<Vec<String>>>;
     fn create_organization<'a>(
         &'a self,
         organization_id: &'a str,
     ) -> BoxFuture<'a, rusqlite::Result<()>>;
+    // 组织委派管理员
+    fn set_organization_admin<'a>(
+        &'a self,
+        organization_id: &'a str,
+        user_id: &'a str,
+    ) -> BoxFuture<'a, rusqlite::Result<()>>;
+    fn remove_organization_admin<'a>(
+        &'a self,
+        organization_id: &'a str,
+        user_id: &'a str,
+    ) -> BoxFuture<'a, rusqlite::Result<bool>>;
+    fn list_organization_admins<'a>(
+        &'a self,
+        organization_id: &'a str,
+    ) -> BoxFuture<'a, rusqlite::Result<Vec<String>>>;
+    fn get_admin_organization<'a>(
+        &'a self,
+        user_id: &'a str,
+    ) -> BoxFuture<'a, rusqlite::Result<Option<String>>>;
 }
 
 #[derive(Debug, Clone)]
@@ -364,6 +687,14 @@ pub struct AdminPublicKeyRecord {
     pub enabled: bool,
     pub created_at: DateTime<Utc>,
     pub last_used_at: Option<DateTime<Utc>>,
+    /// 过期后拒绝签发登录挑战；为空表示永不过期
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl AdminPublicKeyRecord {
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.is_some_and(|exp| exp <= now)
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -395,6 +726,18 @@ pub struct LoginCodeRecord {
     pub hint: Option<String>,
 }
 
+/// 客户端令牌为终端用户签发的一次性登录码（兑换后得到该用户的 AccessToken）
+#[derive(Debug, Clone)]
+pub struct UserLoginCodeRecord {
+    pub code_hash: String,
+    pub user_id: String,
+    /// 签发该登录码的令牌 ID（审计用）
+    pub token_id: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub used_at: Option<DateTime<Utc>>,
+}
+
 #[derive(Debug, Clone)]
 pub struct WebSessionRecord {
     pub session_id: String,
@@ -403,6 +746,26 @@ pub struct WebSessionRecord {
     pub expires_at: DateTime<Utc>,
     pub revoked: bool,
     pub issued_by_code: Option<String>,
+    /// 最近一次携带该会话的请求时间，用于空闲超时
+    pub last_seen_at: Option<DateTime<Utc>>,
+    /// 账号密码登录签发的会话所属用户；公钥/登录码会话为 None
+    pub user_id: Option<String>,
+}
+
+/// 账号密码登录的二次验证与锁定状态（按用户）
+#[derive(Debug, Clone)]
+pub struct AdminMfaRecord {
+    pub user_id: String,
+    /// Base32 TOTP 密钥；totp_enabled 为 false 时表示待确认的注册密钥
+    pub totp_secret: Option<String>,
+    pub totp_enabled: bool,
+    /// 恢复码的 SHA-256 哈希，使用一次即移除
+    pub recovery_code_hashes: Vec<String>,
+    /// 最近一次成功使用的 TOTP 步长，防止同一验证码重复使用
+    pub last_totp_step: Option<i64>,
+    pub failed_attempts: u32,
+    pub locked_until: Option<DateTime<Utc>>,
+    pub updated_at: DateTime<Utc>,
 }
 
 pub trait LoginStore: Send + Sync {
@@ -420,6 +783,13 @@ pub trait LoginStore: Send + Sync {
         when: DateTime<Utc>,
     ) -> BoxFuture<'a, rusqlite::Result<()>>;
     fn list_admin_keys<'a>(&'a self) -> BoxFuture<'a, rusqlite::Result<Vec<AdminPublicKeyRecord>>>;
+    /// 更新启用状态与过期时间；密钥不存在时返回 false
+    fn update_admin_key_status<'a>(
+        &'a self,
+        fingerprint: &'a str,
+        enabled: bool,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> BoxFuture<'a, rusqlite::Result<bool>>;
     fn delete_admin_key<'a>(
         &'a self,
         fingerprint: &'a str,
@@ -464,6 +834,22 @@ pub trait LoginStore: Send + Sync {
         &'a self,
         session_id: &'a str,
     ) -> BoxFuture<'a, rusqlite::Result<Option<LoginCodeRecord>>>;
+    fn insert_user_login_code<'a>(
+        &'a self,
+        code: &'a UserLoginCodeRecord,
+    ) -> BoxFuture<'a, rusqlite::Result<()>>;
+    /// 令牌自 since 起签发的登录码数量（限流用）
+    fn count_user_login_codes_since<'a>(
+        &'a self,
+        token_id: &'a str,
+        since: DateTime<Utc>,
+    ) -> BoxFuture<'a, rusqlite::Result<u64>>;
+    /// 核销未过期且未使用的登录码
+    fn redeem_user_login_code<'a>(
+        &'a self,
+        code_hash: &'a str,
+        now: DateTime<Utc>,
+    ) -> BoxFuture<'a, rusqlite::Result<Option<UserLoginCodeRecord>>>;
 
     fn insert_web_session<'a>(
         &'a self,
@@ -477,6 +863,23 @@ pub trait LoginStore: Send + Sync {
         &'a self,
         session_id: &'a str,
     ) -> BoxFuture<'a, rusqlite::Result<bool>>;
+    fn touch_web_session<'a>(
+        &'a self,
+        session_id: &'a str,
+        when: DateTime<Utc>,
+    ) -> BoxFuture<'a, rusqlite::Result<()>>;
+    /// 未撤销的 Web 会话（含已过期但尚未清理的），按创建时间倒序
+    fn list_web_sessions<'a>(&'a self) -> BoxFuture<'a, rusqlite::Result<Vec<WebSessionRecord>>>;
+
+    fn get_admin_mfa<'a>(
+        &'a self,
+        user_id: &'a str,
+    ) -> BoxFuture<'a, rusqlite::Result<Option<AdminMfaRecord>>>;
+    fn upsert_admin_mfa<'a>(
+        &'a self,
+        record: &'a AdminMfaRecord,
+    ) -> BoxFuture<'a, rusqlite::Result<()>>;
+    fn delete_admin_mfa<'a>(&'a self, user_id: &'a str) -> BoxFuture<'a, rusqlite::Result<bool>>;
 }
 
 // 现有的 DatabaseLogger 作为两种接口的默认实现
@@ -493,25 +896,25 @@ impl RequestLogStore for DatabaseLogger {
         Box::pin(async move { self.get_recent_logs_with_cursor(limit, cursor).await })
     }
 
-    fn get_request_logs<'a>(
+    fn get_logs_with_columns<'a>(
         &'a self,
         limit: i32,
         cursor: Option<i64>,
+        method_path: Option<(&'a str, &'a str)>,
+        columns: &'a LogColumns,
     ) -> BoxFuture<'a, rusqlite::Result<Vec<RequestLog>>> {
-        Box::pin(async move { self.get_request_logs(limit, cursor).await })
+        Box::pin(async move {
+            self.get_logs_with_columns(limit, cursor, method_path, columns)
+                .await
+        })
     }
 
-    fn get_logs_by_method_path<'a>(
+    fn get_request_logs<'a>(
         &'a self,
-        method: &'a str,
-        path: &'a str,
         limit: i32,
         cursor: Option<i64>,
     ) -> BoxFuture<'a, rusqlite::Result<Vec<RequestLog>>> {
-        Box::pin(async move {
-            self.get_logs_by_method_path(method, path, limit, cursor)
-                .await
-        })
+        Box::pin(async move { self.get_request_logs(limit, cursor).await })
     }
 
     fn get_request_log_by_id<'a>(
@@ -549,6 +952,372 @@ impl RequestLogStore for DatabaseLogger {
         Box::pin(async move { self.get_compare_run(id).await })
     }
 
+    fn get_idempotent_response<'a>(
+        &'a self,
+        token_id: &'a str,
+        idempotency_key: &'a str,
+    ) -> BoxFuture<'a, rusqlite::Result<Option<StoredIdempotentResponse>>> {
+        Box::pin(async move {
+            self.get_idempotent_response(token_id, idempotency_key)
+                .await
+        })
+    }
+
+    fn save_idempotent_response<'a>(
+        &'a self,
+        record: StoredIdempotentResponse,
+    ) -> BoxFuture<'a, rusqlite::Result<()>> {
+        Box::pin(async move { self.save_idempotent_response(record).await })
+    }
+
+    fn save_debug_capture<'a>(
+        &'a self,
+        record: DebugCaptureRecord,
+    ) -> BoxFuture<'a, rusqlite::Result<()>> {
+        Box::pin(async move { self.save_debug_capture(record).await })
+    }
+
+    fn get_debug_capture<'a>(
+        &'a self,
+        request_log_id: i64,
+    ) -> BoxFuture<'a, rusqlite::Result<Option<DebugCaptureRecord>>> {
+        Box::pin(async move { self.get_debug_capture(request_log_id).await })
+    }
+
+    fn purge_expired_debug_captures<'a>(
+        &'a self,
+        now: DateTime<Utc>,
+    ) -> BoxFuture<'a, rusqlite::Result<u64>> {
+        Box::pin(async move { self.purge_expired_debug_captures(now).await })
+    }
+
+    fn save_stream_capture<'a>(
+        &'a self,
+        record: StreamCaptureRecord,
+    ) -> BoxFuture<'a, rusqlite::Result<()>> {
+        Box::pin(async move { self.save_stream_capture(record).await })
+    }
+
+    fn get_stream_capture<'a>(
+        &'a self,
+        request_log_id: i64,
+    ) -> BoxFuture<'a, rusqlite::Result<Option<StreamCaptureRecord>>> {
+        Box::pin(async move { self.get_stream_capture(request_log_id).await })
+    }
+
+    fn insert_usage_webhook_dead_letter<'a>(
+        &'a self,
+        letter: UsageWebhookDeadLetter,
+    ) -> BoxFuture<'a, rusqlite::Result<i64>> {
+        Box::pin(async move { self.insert_usage_webhook_dead_letter(letter).await })
+    }
+
+    fn list_usage_webhook_dead_letters<'a>(
+        &'a self,
+        limit: i32,
+    ) -> BoxFuture<'a, rusqlite::Result<Vec<UsageWebhookDeadLetter>>> {
+        Box::pin(async move { self.list_usage_webhook_dead_letters(limit).await })
+    }
+
+    fn get_usage_webhook_dead_letter<'a>(
+        &'a self,
+        id: i64,
+    ) -> BoxFuture<'a, rusqlite::Result<Option<UsageWebhookDeadLetter>>> {
+        Box::pin(async move { self.get_usage_webhook_dead_letter(id).await })
+    }
+
+    fn delete_usage_webhook_dead_letter<'a>(
+        &'a self,
+        id: i64,
+    ) -> BoxFuture<'a, rusqlite::Result<bool>> {
+        Box::pin(async move { self.delete_usage_webhook_dead_letter(id).await })
+    }
+
+    fn insert_admin_notification<'a>(
+        &'a self,
+        notification: AdminNotificationRecord,
+    ) -> BoxFuture<'a, rusqlite::Result<Option<i64>>> {
+        Box::pin(async move { self.insert_admin_notification(notification).await })
+    }
+
+    fn list_admin_notifications<'a>(
+        &'a self,
+        unread_only: bool,
+        limit: i32,
+    ) -> BoxFuture<'a, rusqlite::Result<Vec<AdminNotificationRecord>>> {
+        Box::pin(async move { self.list_admin_notifications(unread_only, limit).await })
+    }
+
+    fn count_unread_admin_notifications<'a>(&'a self) -> BoxFuture<'a, rusqlite::Result<u64>> {
+        Box::pin(async move { self.count_unread_admin_notifications().await })
+    }
+
+    fn mark_admin_notifications_read<'a>(
+        &'a self,
+        ids: Option<Vec<i64>>,
+        at: DateTime<Utc>,
+    ) -> BoxFuture<'a, rusqlite::Result<u64>> {
+        Box::pin(async move { self.mark_admin_notifications_read(ids, at).await })
+    }
+
+    fn insert_maintenance_window<'a>(
+        &'a self,
+        window: MaintenanceWindowRecord,
+    ) -> BoxFuture<'a, rusqlite::Result<i64>> {
+        Box::pin(async move { self.insert_maintenance_window(window).await })
+    }
+
+    fn list_maintenance_windows<'a>(
+        &'a self,
+        ending_after: DateTime<Utc>,
+    ) -> BoxFuture<'a, rusqlite::Result<Vec<MaintenanceWindowRecord>>> {
+        Box::pin(async move { self.list_maintenance_windows(ending_after).await })
+    }
+
+    fn delete_maintenance_window<'a>(&'a self, id: i64) -> BoxFuture<'a, rusqlite::Result<bool>> {
+        Box::pin(async move { self.delete_maintenance_window(id).await })
+    }
+
+    fn list_param_policies<'a>(
+        &'a self,
+    ) -> BoxFuture<'a, rusqlite::Result<Vec<ParamPolicyRecord>>> {
+        Box::pin(async move { self.list_param_policies().await })
+    }
+
+    fn upsert_param_policy<'a>(
+        &'a self,
+        policy: ParamPolicyRecord,
+    ) -> BoxFuture<'a, rusqlite::Result<()>> {
+        Box::pin(async move { self.upsert_param_policy(policy).await })
+    }
+
+    fn delete_param_policy<'a>(&'a self, id: &'a str) -> BoxFuture<'a, rusqlite::Result<bool>> {
+        Box::pin(async move { self.delete_param_policy(id).await })
+    }
+
+    fn list_metrics_reports<'a>(
+        &'a self,
+    ) -> BoxFuture<'a, rusqlite::Result<Vec<MetricsReportRecord>>> {
+        Box::pin(async move { self.list_metrics_reports().await })
+    }
+
+    fn upsert_metrics_report<'a>(
+        &'a self,
+        report: MetricsReportRecord,
+    ) -> BoxFuture<'a, rusqlite::Result<()>> {
+        Box::pin(async move { self.upsert_metrics_report(report).await })
+    }
+
+    fn delete_metrics_report<'a>(&'a self, id: &'a str) -> BoxFuture<'a, rusqlite::Result<bool>> {
+        Box::pin(async move { self.delete_metrics_report(id).await })
+    }
+
+    fn mark_metrics_report_sent<'a>(
+        &'a self,
+        id: &'a str,
+        sent_at: DateTime<Utc>,
+    ) -> BoxFuture<'a, rusqlite::Result<()>> {
+        Box::pin(async move { self.mark_metrics_report_sent(id, sent_at).await })
+    }
+
+    fn aggregate_token_usage_daily<'a>(
+        &'a self,
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+    ) -> BoxFuture<'a, rusqlite::Result<Vec<TokenUsageDaily>>> {
+        Box::pin(async move { self.aggregate_token_usage_daily(since, until).await })
+    }
+
+    fn insert_statement<'a>(
+        &'a self,
+        statement: StatementRecord,
+    ) -> BoxFuture<'a, rusqlite::Result<bool>> {
+        Box::pin(async move { self.insert_statement(statement).await })
+    }
+
+    fn get_statement<'a>(
+        &'a self,
+        id: &'a str,
+    ) -> BoxFuture<'a, rusqlite::Result<Option<StatementRecord>>> {
+        Box::pin(async move { self.get_statement(id).await })
+    }
+
+    fn list_statements<'a>(
+        &'a self,
+        scope: Option<&'a str>,
+        subject_id: Option<&'a str>,
+        period: Option<&'a str>,
+    ) -> BoxFuture<'a, rusqlite::Result<Vec<StatementRecord>>> {
+        Box::pin(async move { self.list_statements(scope, subject_id, period).await })
+    }
+
+    fn list_provider_budgets<'a>(
+        &'a self,
+    ) -> BoxFuture<'a, rusqlite::Result<Vec<ProviderBudgetRecord>>> {
+        Box::pin(async move { self.list_provider_budgets().await })
+    }
+
+    fn get_provider_budget<'a>(
+        &'a self,
+        provider: &'a str,
+    ) -> BoxFuture<'a, rusqlite::Result<Option<ProviderBudgetRecord>>> {
+        Box::pin(async move { self.get_provider_budget(provider).await })
+    }
+
+    fn upsert_provider_budget<'a>(
+        &'a self,
+        budget: ProviderBudgetRecord,
+    ) -> BoxFuture<'a, rusqlite::Result<()>> {
+        Box::pin(async move { self.upsert_provider_budget(budget).await })
+    }
+
+    fn delete_provider_budget<'a>(
+        &'a self,
+        provider: &'a str,
+    ) -> BoxFuture<'a, rusqlite::Result<bool>> {
+        Box::pin(async move { self.delete_provider_budget(provider).await })
+    }
+
+    fn list_image_prices<'a>(
+        &'a self,
+        provider: Option<&'a str>,
+    ) -> BoxFuture<'a, rusqlite::Result<Vec<ImagePriceRecord>>> {
+        Box::pin(async move { self.list_image_prices(provider).await })
+    }
+
+    fn upsert_image_price<'a>(
+        &'a self,
+        price: ImagePriceRecord,
+    ) -> BoxFuture<'a, rusqlite::Result<()>> {
+        Box::pin(async move { self.upsert_image_price(price).await })
+    }
+
+    fn delete_image_price<'a>(
+        &'a self,
+        provider: &'a str,
+        model: &'a str,
+        size: &'a str,
+        quality: &'a str,
+    ) -> BoxFuture<'a, rusqlite::Result<bool>> {
+        Box::pin(async move {
+            self.delete_image_price(provider, model, size, quality)
+                .await
+        })
+    }
+
+    fn list_audio_prices<'a>(
+        &'a self,
+        provider: Option<&'a str>,
+    ) -> BoxFuture<'a, rusqlite::Result<Vec<AudioPriceRecord>>> {
+        Box::pin(async move { self.list_audio_prices(provider).await })
+    }
+
+    fn upsert_audio_price<'a>(
+        &'a self,
+        price: AudioPriceRecord,
+    ) -> BoxFuture<'a, rusqlite::Result<()>> {
+        Box::pin(async move { self.upsert_audio_price(price).await })
+    }
+
+    fn delete_audio_price<'a>(
+        &'a self,
+        provider: &'a str,
+        model: &'a str,
+    ) -> BoxFuture<'a, rusqlite::Result<bool>> {
+        Box::pin(async move { self.delete_audio_price(provider, model).await })
+    }
+
+    fn list_speech_prices<'a>(
+        &'a self,
+        provider: Option<&'a str>,
+    ) -> BoxFuture<'a, rusqlite::Result<Vec<SpeechPriceRecord>>> {
+        Box::pin(async move { self.list_speech_prices(provider).await })
+    }
+
+    fn upsert_speech_price<'a>(
+        &'a self,
+        price: SpeechPriceRecord,
+    ) -> BoxFuture<'a, rusqlite::Result<()>> {
+        Box::pin(async move { self.upsert_speech_price(price).await })
+    }
+
+    fn delete_speech_price<'a>(
+        &'a self,
+        provider: &'a str,
+        model: &'a str,
+    ) -> BoxFuture<'a, rusqlite::Result<bool>> {
+        Box::pin(async move { self.delete_speech_price(provider, model).await })
+    }
+
+    fn sum_provider_spend_since<'a>(&'a self, since: DateTime<Utc>) -> ProviderSpendFuture<'a> {
+        Box::pin(async move { self.sum_provider_spend_since(since).await })
+    }
+
+    fn add_provider_egress<'a>(
+        &'a self,
+        rows: Vec<ProviderEgressDaily>,
+    ) -> BoxFuture<'a, rusqlite::Result<()>> {
+        Box::pin(async move { self.add_provider_egress(rows).await })
+    }
+
+    fn list_provider_egress<'a>(
+        &'a self,
+        since_day: &'a str,
+        until_day: &'a str,
+    ) -> BoxFuture<'a, rusqlite::Result<Vec<ProviderEgressDaily>>> {
+        Box::pin(async move { self.list_provider_egress(since_day, until_day).await })
+    }
+
+    fn add_token_request_counts<'a>(
+        &'a self,
+        rows: Vec<TokenRequestCount>,
+    ) -> BoxFuture<'a, rusqlite::Result<()>> {
+        Box::pin(async move { self.add_token_request_counts(rows).await })
+    }
+
+    fn sum_token_request_counts<'a>(&'a self, day: &'a str) -> TokenRequestCountsFuture<'a> {
+        Box::pin(async move { self.sum_token_request_counts(day).await })
+    }
+
+    fn max_request_log_id<'a>(&'a self) -> BoxFuture<'a, rusqlite::Result<i64>> {
+        Box::pin(async move { self.max_request_log_id().await })
+    }
+
+    fn sum_token_usage_between<'a>(
+        &'a self,
+        after_id: i64,
+        until_id: i64,
+        excluded_ids: &'a [i64],
+    ) -> BoxFuture<'a, rusqlite::Result<Vec<TokenUsageDelta>>> {
+        Box::pin(async move {
+            self.sum_token_usage_between(after_id, until_id, excluded_ids)
+                .await
+        })
+    }
+
+    fn request_heatmap<'a>(
+        &'a self,
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+        model: Option<&'a str>,
+        provider: Option<&'a str>,
+    ) -> BoxFuture<'a, rusqlite::Result<Vec<RequestHeatmapCell>>> {
+        Box::pin(async move { self.request_heatmap(since, until, model, provider).await })
+    }
+
+    fn token_activity<'a>(
+        &'a self,
+        client_token: &'a str,
+        since: DateTime<Utc>,
+        top_models: u32,
+        recent_errors: u32,
+    ) -> BoxFuture<'a, rusqlite::Result<TokenActivity>> {
+        Box::pin(async move {
+            self.token_activity(client_token, since, top_models, recent_errors)
+                .await
+        })
+    }
+
     fn upsert_request_lab_source<'a>(
         &'a self,
         source: StoredRequestLabSource,
@@ -694,6 +1463,17 @@ impl RequestLogStore for DatabaseLogger {
         })
     }
 
+    fn purge_request_logs_before<'a>(
+        &'a self,
+        cutoff: DateTime<Utc>,
+    ) -> BoxFuture<'a, rusqlite::Result<u64>> {
+        Box::pin(async move { self.purge_request_logs_before(cutoff).await })
+    }
+
+    fn last_request_at_by_client_token<'a>(&'a self) -> LastRequestMapFuture<'a> {
+        Box::pin(async move { self.last_request_at_by_client_token().await })
+    }
+
     fn log_provider_op<'a>(&'a self, op: ProviderOpLog) -> BoxFuture<'a, rusqlite::Result<i64>> {
         Box::pin(async move { self.log_provider_op(op).await })
     }
@@ -706,6 +1486,27 @@ impl RequestLogStore for DatabaseLogger {
         Box::pin(async move { self.get_provider_ops_logs(limit, cursor).await })
     }
 
+    fn get_provider_ops_logs_for_provider<'a>(
+        &'a self,
+        provider: &'a str,
+        since: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+        limit: i32,
+        cursor: Option<i64>,
+    ) -> BoxFuture<'a, rusqlite::Result<Vec<ProviderOpLog>>> {
+        Box::pin(async move {
+            self.get_provider_ops_logs_for_provider(provider, since, until, limit, cursor)
+                .await
+        })
+    }
+
+    fn purge_provider_ops_logs_before<'a>(
+        &'a self,
+        cutoff: DateTime<Utc>,
+    ) -> BoxFuture<'a, rusqlite::Result<u64>> {
+        Box::pin(async move { self.purge_provider_ops_logs_before(cutoff).await })
+    }
+
     fn upsert_model_price<'a>(
         &'a self,
         price: ModelPriceUpsert,
@@ -713,6 +1514,13 @@ impl RequestLogStore for DatabaseLogger {
         Box::pin(async move { self.upsert_model_price(price).await })
     }
 
+    fn upsert_model_prices<'a>(
+        &'a self,
+        prices: Vec<ModelPriceUpsert>,
+    ) -> BoxFuture<'a, rusqlite::Result<()>> {
+        Box::pin(async move { self.upsert_model_prices(prices).await })
+    }
+
     fn get_model_price<'a>(&'a self, provider: &'a str, model: &'a str) -> ModelPriceFuture<'a> {
         Box::pin(async move { self.get_model_price(provider, model).await })
     }
@@ -720,6 +1528,13 @@ impl RequestLogStore for DatabaseLogger {
     fn list_model_prices<'a>(&'a self, provider: Option<&'a str>) -> ModelPriceListFuture<'a> {
         Box::pin(async move { self.list_model_prices(provider).await })
     }
+    fn list_model_prices_page<'a>(
+        &'a self,
+        provider: Option<&'a str>,
+        page: &'a PageRequest,
+    ) -> BoxFuture<'a, rusqlite::Result<(Vec<ModelPriceRecord>, u64)>> {
+        Box::pin(async move { self.list_model_prices_page(provider, page).await })
+    }
 
     fn sum_spent_amount_by_client_token<'a>(
         &'a self,
@@ -808,6 +1623,12 @@ impl ProviderStore for DatabaseLogger {
     fn list_providers<'a>(&'a self) -> BoxFuture<'a, rusqlite::Result<Vec<Provider>>> {
         Box::pin(async move { self.list_providers().await })
     }
+    fn list_providers_page<'a>(
+        &'a self,
+        page: &'a PageRequest,
+    ) -> BoxFuture<'a, rusqlite::Result<(Vec<Provider>, u64)>> {
+        Box::pin(async move { self.list_providers_page(page).await })
+    }
     fn delete_provider<'a>(&'a self, name: &'a str) -> BoxFuture<'a, rusqlite::Result<bool>> {
         Box::pin(async move { self.delete_provider(name).await })
     }
@@ -903,6 +1724,47 @@ impl ProviderStore for DatabaseLogger {
         })
     }
 
+    fn set_provider_key_limits<'a>(
+        &'a self,
+        provider: &'a str,
+        key: &'a str,
+        spend_cap: Option<f64>,
+        rpm_limit: Option<u32>,
+        tpm_limit: Option<u32>,
+        strategy: &'a Option<KeyLogStrategy>,
+    ) -> BoxFuture<'a, rusqlite::Result<bool>> {
+        Box::pin(async move {
+            self.set_provider_key_limits(provider, key, spend_cap, rpm_limit, tpm_limit, strategy)
+                .await
+        })
+    }
+
+    fn set_provider_key_openai_headers<'a>(
+        &'a self,
+        provider: &'a str,
+        key: &'a str,
+        headers: &'a OpenAIAccountHeaders,
+        strategy: &'a Option<KeyLogStrategy>,
+    ) -> BoxFuture<'a, rusqlite::Result<bool>> {
+        Box::pin(async move {
+            self.set_provider_key_openai_headers(provider, key, headers, strategy)
+                .await
+        })
+    }
+
+    fn set_provider_key_labels<'a>(
+        &'a self,
+        provider: &'a str,
+        key: &'a str,
+        labels: &'a BTreeMap<String, String>,
+        strategy: &'a Option<KeyLogStrategy>,
+    ) -> BoxFuture<'a, rusqlite::Result<bool>> {
+        Box::pin(async move {
+            self.set_provider_key_labels(provider, key, labels, strategy)
+                .await
+        })
+    }
+
     fn set_provider_key_active<'a>(
         &'a self,
         provider: &'a str,
@@ -952,4 +1814,37 @@ impl OrganizationStore for DatabaseLogger {
     ) -> BoxFuture<'a, rusqlite::Result<()>> {
         Box::pin(async move { self.create_organization(organization_id).await })
     }
+
+    fn set_organization_admin<'a>(
+        &'a self,
+        organization_id: &'a str,
+        user_id: &'a str,
+    ) -> BoxFuture<'a, rusqlite::Result<()>> {
+        Box::pin(async move { self.set_organization_admin(organization_id, user_id).await })
+    }
+
+    fn remove_organization_admin<'a>(
+        &'a self,
+        organization_id: &'a str,
+        user_id: &'a str,
+    ) -> BoxFuture<'a, rusqlite::Result<bool>> {
+        Box::pin(async move {
+            self.remove_organization_admin(organization_id, user_id)
+                .await
+        })
+    }
+
+    fn list_organization_admins<'a>(
+        &'a self,
+        organization_id: &'a str,
+    ) -> BoxFuture<'a, rusqlite::Result<Vec<String>>> {
+        Box::pin(async move { self.list_organization_admins(organization_id).await })
+    }
+
+    fn get_admin_organization<'a>(
+        &'a self,
+        user_id: &'a str,
+    ) -> BoxFuture<'a, rusqlite::Result<Option<String>>> {
+        Box::pin(async move { self.get_admin_organization(user_id).await })
+    }
 }
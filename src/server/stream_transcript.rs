@@ -0,0 +1,476 @@
+//! 流式响应逐块捕获（`server.capture_stream_chunks`）：记录转发给调用方的每个 SSE data 分片及到达时间，
+//! 并在管理端按分片还原用户实际收到的完整回复（含推理内容与工具调用）
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use axum::body::Body;
+use axum::response::Response;
+use chrono::{DateTime, Utc};
+use futures_util::StreamExt;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::logging::types::{StreamCaptureRecord, StreamChunk};
+use crate::server::AppState;
+use crate::server::storage_traits::RequestLogStore;
+use crate::server::tasks::TaskRegistry;
+
+#[derive(Default)]
+struct RecorderState {
+    chunks: Vec<StreamChunk>,
+    bytes: usize,
+    truncated: bool,
+    finished: bool,
+    log_id: Option<i64>,
+}
+
+/// 单次流式请求的分片记录器：流结束且请求日志写入后（两者先后不定）保存捕获
+pub struct StreamRecorder {
+    log_store: Arc<dyn RequestLogStore + Send + Sync>,
+    /// 保存经任务登记表派发，关闭时等待尚未写完的捕获
+    tasks: Arc<TaskRegistry>,
+    started_at: DateTime<Utc>,
+    max_bytes: usize,
+    state: Mutex<RecorderState>,
+}
+
+impl fmt::Debug for StreamRecorder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StreamRecorder")
+            .field("started_at", &self.started_at)
+            .field("max_bytes", &self.max_bytes)
+            .finish_non_exhaustive()
+    }
+}
+
+impl StreamRecorder {
+    /// 未开启 capture_stream_chunks 时返回 None
+    pub fn start(app_state: &AppState, started_at: DateTime<Utc>) -> Option<Arc<Self>> {
+        let server = &app_state.config.server;
+        server.capture_stream_chunks.then(|| {
+            Arc::new(Self {
+                log_store: app_state.log_store.clone(),
+                tasks: app_state.task_registry.clone(),
+                started_at,
+                max_bytes: server.capture_body_max_bytes,
+                state: Mutex::new(RecorderState::default()),
+            })
+        })
+    }
+
+    fn state(&self) -> MutexGuard<'_, RecorderState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn push(&self, data: String) {
+        let offset_ms = (Utc::now() - self.started_at).num_milliseconds();
+        let mut state = self.state();
+        if state.truncated {
+            return;
+        }
+        if self.max_bytes > 0 && state.bytes + data.len() > self.max_bytes {
+            state.truncated = true;
+            return;
+        }
+        state.bytes += data.len();
+        state.chunks.push(StreamChunk { offset_ms, data });
+    }
+
+    /// 记录一个 SSE 帧中的 data 内容（多行 data 按换行拼接）；心跳等无 data 的帧忽略
+    fn push_frame(&self, frame: &str) {
+        let data = frame
+            .lines()
+            .filter_map(|line| line.strip_prefix("data:"))
+            .map(|data| data.strip_prefix(' ').unwrap_or(data))
+            .collect::<Vec<_>>();
+        if !data.is_empty() {
+            self.push(data.join("\n"));
+        }
+    }
+
+    fn finish(&self) {
+        let mut state = self.state();
+        state.finished = true;
+        self.save_if_ready(state);
+    }
+
+    /// 请求日志写入后关联日志 ID
+    pub fn attach_log(&self, log_id: i64) {
+        let mut state = self.state();
+        state.log_id = Some(log_id);
+        self.save_if_ready(state);
+    }
+
+    fn save_if_ready(&self, mut state: MutexGuard<'_, RecorderState>) {
+        if !state.finished {
+            return;
+        }
+        let Some(request_log_id) = state.log_id.take() else {
+            return;
+        };
+        let record = StreamCaptureRecord {
+            request_log_id,
+            chunks: std::mem::take(&mut state.chunks),
+            truncated: state.truncated,
+        };
+        drop(state);
+        let log_store = self.log_store.clone();
+        self.tasks.spawn("stream_capture_save", async move {
+            if let Err(e) = log_store.save_stream_capture(record).await {
+                tracing::warn!(request_log_id, "Failed to persist stream capture: {}", e);
+            }
+        });
+    }
+}
+
+struct FinishOnDrop(Arc<StreamRecorder>);
+
+impl Drop for FinishOnDrop {
+    fn drop(&mut self) {
+        self.0.finish();
+    }
+}
+
+/// 包装流式响应体：按 SSE 帧（空行分隔）记录转发内容；响应体结束或调用方断开时视为流结束
+pub fn record_stream_response(response: Response, recorder: Arc<StreamRecorder>) -> Response {
+    let (parts, body) = response.into_parts();
+    let guard = FinishOnDrop(recorder);
+    let mut pending: Vec<u8> = Vec::new();
+    let stream = body.into_data_stream().map(move |chunk| {
+        if let Ok(bytes) = &chunk {
+            pending.extend_from_slice(bytes);
+            while let Some(pos) = pending.windows(2).position(|w| w == b"\n\n") {
+                let frame: Vec<u8> = pending.drain(..pos + 2).collect();
+                guard.0.push_frame(&String::from_utf8_lossy(&frame[..pos]));
+            }
+        }
+        chunk
+    });
+    Response::from_parts(parts, Body::from_stream(stream))
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct TranscriptToolCall {
+    pub index: u64,
+    pub id: Option<String>,
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+    pub name: Option<String>,
+    pub arguments: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct TranscriptChoice {
+    pub index: u64,
+    pub role: Option<String>,
+    pub content: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub reasoning_content: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<TranscriptToolCall>,
+    pub finish_reason: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TranscriptChunk {
+    /// 相对请求开始的毫秒数
+    pub offset_ms: i64,
+    /// 与上一分片的间隔（首个分片为首字延迟）
+    pub gap_ms: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reasoning_content: Option<String>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub tool_call: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finish_reason: Option<String>,
+    /// 转发给调用方的原始 data 内容
+    pub data: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Transcript {
+    pub request_log_id: i64,
+    pub chunk_count: usize,
+    /// 捕获超过 capture_body_max_bytes 被截断，重建内容不完整
+    pub truncated: bool,
+    pub first_chunk_ms: Option<i64>,
+    pub last_chunk_ms: Option<i64>,
+    pub choices: Vec<TranscriptChoice>,
+    pub usage: Option<Value>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<String>,
+    pub chunks: Vec<TranscriptChunk>,
+}
+
+fn str_field(value: &Value, key: &str) -> Option<String> {
+    value.get(key).and_then(Value::as_str).map(str::to_string)
+}
+
+fn merge_tool_call(calls: &mut Vec<TranscriptToolCall>, delta: &Value) {
+    let index = delta["index"]
+        .as_u64()
+        .unwrap_or(calls.len().saturating_sub(1) as u64);
+    let position = match calls.iter().position(|c| c.index == index) {
+        Some(position) => position,
+        None => {
+            calls.push(TranscriptToolCall {
+                index,
+                id: None,
+                kind: None,
+                name: None,
+                arguments: String::new(),
+            });
+            calls.len() - 1
+        }
+    };
+    let call = &mut calls[position];
+    if let Some(id) = str_field(delta, "id") {
+        call.id = Some(id);
+    }
+    if let Some(kind) = str_field(delta, "type") {
+        call.kind = Some(kind);
+    }
+    let function = &delta["function"];
+    if let Some(name) = str_field(function, "name") {
+        call.name.get_or_insert_with(String::new).push_str(&name);
+    }
+    if let Some(arguments) = function.get("arguments").and_then(Value::as_str) {
+        call.arguments.push_str(arguments);
+    }
+}
+
+/// 按 OpenAI chat.completion.chunk 语义合并各分片的 delta，得到每个 choice 的最终消息
+pub fn reconstruct(capture: StreamCaptureRecord) -> Transcript {
+    let mut choices: BTreeMap<u64, TranscriptChoice> = BTreeMap::new();
+    let mut usage = None;
+    let mut errors = Vec::new();
+    let mut chunks = Vec::with_capacity(capture.chunks.len());
+    let mut previous_ms = 0;
+    for chunk in capture.chunks {
+        let mut entry = TranscriptChunk {
+            offset_ms: chunk.offset_ms,
+            gap_ms: chunk.offset_ms - previous_ms,
+            content: None,
+            reasoning_content: None,
+            tool_call: false,
+            finish_reason: None,
+            data: chunk.data,
+        };
+        previous_ms = chunk.offset_ms;
+        if let Some(message) = entry.data.strip_prefix("error:") {
+            errors.push(message.trim().to_string());
+        } else if let Ok(value) = serde_json::from_str::<Value>(&entry.data) {
+            if let Some(error) = value.get("error") {
+                errors.push(str_field(error, "message").unwrap_or_else(|| error.to_string()));
+            }
+            if value.get("usage").is_some_and(|u| !u.is_null()) {
+                usage = value.get("usage").cloned();
+            }
+            for raw in value["choices"].as_array().into_iter().flatten() {
+                let index = raw["index"].as_u64().unwrap_or(0);
+                let choice = choices.entry(index).or_insert_with(|| TranscriptChoice {
+                    index,
+                    ..Default::default()
+                });
+                let delta = &raw["delta"];
+                if let Some(role) = str_field(delta, "role") {
+                    choice.role = Some(role);
+                }
+                if let Some(text) = str_field(delta, "content") {
+                    choice.content.push_str(&text);
+                    entry
+                        .content
+                        .get_or_insert_with(String::new)
+                        .push_str(&text);
+                }
+                if let Some(text) = str_field(delta, "reasoning_content") {
+                    choice.reasoning_content.push_str(&text);
+                    entry
+                        .reasoning_content
+                        .get_or_insert_with(String::new)
+                        .push_str(&text);
+                }
+                for call in delta["tool_calls"].as_array().into_iter().flatten() {
+                    merge_tool_call(&mut choice.tool_calls, call);
+                    entry.tool_call = true;
+                }
+                if let Some(reason) = str_field(raw, "finish_reason") {
+                    choice.finish_reason = Some(reason.clone());
+                    entry.finish_reason = Some(reason);
+                }
+            }
+        }
+        chunks.push(entry);
+    }
+    Transcript {
+        request_log_id: capture.request_log_id,
+        chunk_count: chunks.len(),
+        truncated: capture.truncated,
+        first_chunk_ms: chunks.first().map(|c| c.offset_ms),
+        last_chunk_ms: chunks.last().map(|c| c.offset_ms),
+        choices: choices.into_values().collect(),
+        usage,
+        errors,
+        chunks,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn chunk(offset_ms: i64, data: Value) -> StreamChunk {
+        StreamChunk {
+            offset_ms,
+            data: data.to_string(),
+        }
+    }
+
+    #[test]
+    fn reconstructs_content_and_tool_calls_with_timing() {
+        let capture = StreamCaptureRecord {
+            request_log_id: 7,
+            truncated: false,
+            chunks: vec![
+                chunk(
+                    120,
+                    json!({"choices":[{"index":0,"delta":{"role":"assistant","content":"Hel"}}]}),
+                ),
+                chunk(
+                    150,
+                    json!({"choices":[{"index":0,"delta":{"content":"lo"}}]}),
+                ),
+                chunk(
+                    180,
+                    json!({"choices":[{"index":0,"delta":{"tool_calls":[
+                        {"index":0,"id":"call_1","type":"function","function":{"name":"get_weather","arguments":"{\"ci"}}
+                    ]}}]}),
+                ),
+                chunk(
+                    200,
+                    json!({"choices":[{"index":0,"delta":{"tool_calls":[
+                        {"index":0,"function":{"arguments":"ty\":\"Paris\"}"}}
+                    ]},"finish_reason":"tool_calls"}]}),
+                ),
+                chunk(210, json!({"choices":[],"usage":{"total_tokens":12}})),
+                StreamChunk {
+                    offset_ms: 210,
+                    data: "[DONE]".into(),
+                },
+            ],
+        };
+        let transcript = reconstruct(capture);
+        assert_eq!(transcript.chunk_count, 6);
+        assert_eq!(transcript.first_chunk_ms, Some(120));
+        let choice = &transcript.choices[0];
+        assert_eq!(choice.role.as_deref(), Some("assistant"));
+        assert_eq!(choice.content, "Hello");
+        assert_eq!(choice.finish_reason.as_deref(), Some("tool_calls"));
+        assert_eq!(
+            choice.tool_calls,
+            vec![TranscriptToolCall {
+                index: 0,
+                id: Some("call_1".into()),
+                kind: Some("function".into()),
+                name: Some("get_weather".into()),
+                arguments: "{\"city\":\"Paris\"}".into(),
+            }]
+        );
+        assert_eq!(transcript.usage, Some(json!({"total_tokens":12})));
+        assert_eq!(transcript.chunks[1].gap_ms, 30);
+        assert_eq!(transcript.chunks[1].content.as_deref(), Some("lo"));
+        assert!(transcript.chunks[2].tool_call);
+    }
+
+    #[test]
+    fn error_frames_are_collected() {
+        let transcript = reconstruct(StreamCaptureRecord {
+            request_log_id: 1,
+            truncated: true,
+            chunks: vec![StreamChunk {
+                offset_ms: 5,
+                data: "error: upstream closed".into(),
+            }],
+        });
+        assert_eq!(transcript.errors, vec!["upstream closed"]);
+        assert!(transcript.truncated);
+    }
+
+    #[tokio::test]
+    async fn shutdown_waits_for_pending_capture_save() {
+        use crate::config::BalanceStrategy;
+        use crate::config::settings::{LoadBalancing, LoggingConfig, ServerConfig, Settings};
+        use crate::logging::DatabaseLogger;
+
+        let dir = tempfile::tempdir().unwrap();
+        let settings = Settings {
+            load_balancing: LoadBalancing {
+                strategy: BalanceStrategy::FirstAvailable,
+            },
+            server: ServerConfig {
+                capture_stream_chunks: true,
+                ..Default::default()
+            },
+            logging: LoggingConfig {
+                database_path: dir.path().join("t.db").to_string_lossy().to_string(),
+                ..Default::default()
+            },
+        };
+        let logger = Arc::new(
+            DatabaseLogger::new(&settings.logging.database_path)
+                .await
+                .unwrap(),
+        );
+        let app_state = crate::server::test_harness::app_state(settings, logger);
+        let log_id = app_state
+            .log_store
+            .log_request(crate::logging::RequestLog {
+                id: None,
+                timestamp: Utc::now(),
+                method: "POST".into(),
+                path: "/v1/chat/completions".into(),
+                request_type: "chat_stream".into(),
+                requested_model: None,
+                effective_model: None,
+                model: None,
+                provider: None,
+                api_key: None,
+                client_token: None,
+                user_id: None,
+                amount_spent: None,
+                status_code: 200,
+                response_time_ms: 1,
+                prompt_tokens: None,
+                completion_tokens: None,
+                total_tokens: None,
+                cached_tokens: None,
+                reasoning_tokens: None,
+                error_message: None,
+                latency: Default::default(),
+                profile: Default::default(),
+            })
+            .await
+            .unwrap();
+        let recorder = StreamRecorder::start(&app_state, Utc::now()).unwrap();
+        recorder.push_frame(&format!("data: {}", json!({"choices": []})));
+        recorder.finish();
+        recorder.attach_log(log_id);
+
+        app_state
+            .task_registry
+            .shutdown(std::time::Duration::from_secs(5))
+            .await;
+        assert!(app_state.task_registry.list_running().is_empty());
+        let capture = app_state
+            .log_store
+            .get_stream_capture(log_id)
+            .await
+            .unwrap();
+        assert_eq!(capture.unwrap().chunks.len(), 1);
+    }
+}
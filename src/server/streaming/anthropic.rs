@@ -1,7 +1,12 @@
-use std::{convert::Infallible, sync::Arc};
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    sync::{Arc, Mutex},
+};
 
 use axum::response::{IntoResponse, Response, Sse};
 use chrono::{DateTime, Utc};
+use futures_util::StreamExt;
 use serde_json::{Value, json};
 
 use crate::error::GatewayError;
@@ -11,10 +16,244 @@ use crate::server::AppState;
 use crate::server::response_text;
 use crate::server::util::mask_key;
 
-/// Anthropic streaming (best-effort):
-/// - Anthropic upstream streaming is not implemented here yet.
-/// - We call upstream in non-stream mode, then emit an OpenAI-compatible SSE stream containing
-///   the full assistant message as a single delta chunk, followed by a finish chunk and `[DONE]`.
+use super::native::SseMessageDecoder;
+
+/// Anthropic Messages SSE 事件到 OpenAI `chat.completion.chunk` 的增量转换状态：
+/// - message_start：记录 id/model/输入 tokens，输出 assistant 角色块
+/// - content_block_start/delta：文本、思考（reasoning_content）与工具调用参数增量
+/// - message_delta：结束原因与累计输出 tokens；message_stop 时追加 usage 块
+#[derive(Debug, Default)]
+struct AnthropicStreamState {
+    id: String,
+    model: String,
+    created: u64,
+    input_tokens: u64,
+    output_tokens: u64,
+    reasoning_chars: usize,
+    /// Anthropic content block 下标 → OpenAI tool_calls 下标
+    tool_indexes: HashMap<u64, u32>,
+}
+
+#[derive(Debug, Default)]
+struct StreamStep {
+    chunks: Vec<Value>,
+    done: bool,
+}
+
+fn finish_reason(stop_reason: &str) -> &'static str {
+    match stop_reason {
+        "max_tokens" => "length",
+        "tool_use" => "tool_calls",
+        "refusal" => "content_filter",
+        _ => "stop",
+    }
+}
+
+impl AnthropicStreamState {
+    fn new(model: String) -> Self {
+        Self {
+            id: format!("chatcmpl-{}", Utc::now().timestamp_millis()),
+            model,
+            created: Utc::now().timestamp().max(0) as u64,
+            ..Default::default()
+        }
+    }
+
+    fn chunk(&self, delta: Value, finish_reason: Value) -> Value {
+        json!({
+            "id": self.id,
+            "object": "chat.completion.chunk",
+            "created": self.created,
+            "model": self.model,
+            "choices": [{
+                "index": 0,
+                "delta": delta,
+                "finish_reason": finish_reason,
+            }],
+        })
+    }
+
+    fn delta(&self, delta: Value) -> Value {
+        self.chunk(delta, Value::Null)
+    }
+
+    fn tool_call_delta(&self, index: u32, call: Value) -> Value {
+        let mut call = call;
+        call["index"] = json!(index);
+        self.delta(json!({ "tool_calls": [call] }))
+    }
+
+    /// 与非流式一致：思考 tokens 已计入 output_tokens，按思考文本字符数 / 4 估算并以其为上限
+    fn usage(&self) -> Usage {
+        let prompt_tokens = self.input_tokens as u32;
+        let completion_tokens = self.output_tokens as u32;
+        Usage {
+            prompt_tokens,
+            completion_tokens,
+            total_tokens: prompt_tokens + completion_tokens,
+            prompt_tokens_details: None,
+            completion_tokens_details: (self.reasoning_chars > 0).then(|| {
+                async_openai::types::CompletionTokensDetails {
+                    reasoning_tokens: Some(
+                        (self.reasoning_chars.div_ceil(4) as u32).min(completion_tokens),
+                    ),
+                    ..Default::default()
+                }
+            }),
+        }
+    }
+
+    fn push(&mut self, event_name: &str, data: &str) -> Result<StreamStep, String> {
+        let value: Value = serde_json::from_str(data)
+            .map_err(|err| format!("Anthropic 流式事件解析失败：{err}"))?;
+        let event_type = value
+            .get("type")
+            .and_then(Value::as_str)
+            .unwrap_or(event_name);
+        let mut step = StreamStep::default();
+
+        match event_type {
+            "message_start" => {
+                let message = value.get("message").unwrap_or(&Value::Null);
+                if let Some(id) = message.get("id").and_then(Value::as_str) {
+                    self.id = id.to_string();
+                }
+                if let Some(model) = message.get("model").and_then(Value::as_str) {
+                    self.model = model.to_string();
+                }
+                if let Some(usage) = message.get("usage") {
+                    self.input_tokens = usage
+                        .get("input_tokens")
+                        .and_then(Value::as_u64)
+                        .unwrap_or(0);
+                    self.output_tokens = usage
+                        .get("output_tokens")
+                        .and_then(Value::as_u64)
+                        .unwrap_or(0);
+                }
+                step.chunks.push(self.delta(json!({"role": "assistant"})));
+            }
+            "content_block_start" => {
+                let index = value.get("index").and_then(Value::as_u64).unwrap_or(0);
+                let block = value.get("content_block").unwrap_or(&Value::Null);
+                match block.get("type").and_then(Value::as_str) {
+                    Some("tool_use") => {
+                        let tool_index = self.tool_indexes.len() as u32;
+                        self.tool_indexes.insert(index, tool_index);
+                        step.chunks.push(self.tool_call_delta(
+                            tool_index,
+                            json!({
+                                "id": block.get("id").cloned().unwrap_or(Value::Null),
+                                "type": "function",
+                                "function": {
+                                    "name": block.get("name").cloned().unwrap_or(Value::Null),
+                                    "arguments": "",
+                                },
+                            }),
+                        ));
+                    }
+                    Some("redacted_thinking") => {
+                        step.chunks
+                            .push(self.delta(json!({"reasoning_content": "[redacted_thinking]"})));
+                    }
+                    Some("text") => {
+                        if let Some(text) = block
+                            .get("text")
+                            .and_then(Value::as_str)
+                            .filter(|text| !text.is_empty())
+                        {
+                            step.chunks.push(self.delta(json!({"content": text})));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            "content_block_delta" => {
+                let index = value.get("index").and_then(Value::as_u64).unwrap_or(0);
+                let delta = value.get("delta").unwrap_or(&Value::Null);
+                match delta.get("type").and_then(Value::as_str) {
+                    Some("text_delta") => {
+                        if let Some(text) = delta
+                            .get("text")
+                            .and_then(Value::as_str)
+                            .filter(|text| !text.is_empty())
+                        {
+                            step.chunks.push(self.delta(json!({"content": text})));
+                        }
+                    }
+                    Some("thinking_delta") => {
+                        if let Some(thinking) = delta
+                            .get("thinking")
+                            .and_then(Value::as_str)
+                            .filter(|text| !text.is_empty())
+                        {
+                            self.reasoning_chars += thinking.chars().count();
+                            step.chunks
+                                .push(self.delta(json!({"reasoning_content": thinking})));
+                        }
+                    }
+                    Some("input_json_delta") => {
+                        if let (Some(tool_index), Some(partial)) = (
+                            self.tool_indexes.get(&index).copied(),
+                            delta.get("partial_json").and_then(Value::as_str),
+                        ) && !partial.is_empty()
+                        {
+                            step.chunks.push(self.tool_call_delta(
+                                tool_index,
+                                json!({"function": {"arguments": partial}}),
+                            ));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            "message_delta" => {
+                if let Some(usage) = value.get("usage") {
+                    if let Some(input) = usage.get("input_tokens").and_then(Value::as_u64) {
+                        self.input_tokens = input;
+                    }
+                    if let Some(output) = usage.get("output_tokens").and_then(Value::as_u64) {
+                        self.output_tokens = output;
+                    }
+                }
+                if let Some(reason) = value
+                    .get("delta")
+                    .and_then(|delta| delta.get("stop_reason"))
+                    .and_then(Value::as_str)
+                {
+                    step.chunks
+                        .push(self.chunk(json!({}), json!(finish_reason(reason))));
+                }
+            }
+            "message_stop" => {
+                step.chunks.push(json!({
+                    "id": self.id,
+                    "object": "chat.completion.chunk",
+                    "created": self.created,
+                    "model": self.model,
+                    "choices": [],
+                    "usage": self.usage(),
+                }));
+                step.done = true;
+            }
+            "error" => {
+                let message = value
+                    .get("error")
+                    .and_then(|error| error.get("message"))
+                    .and_then(Value::as_str)
+                    .unwrap_or("Anthropic 流式返回错误事件。");
+                return Err(message.to_string());
+            }
+            _ => {}
+        }
+
+        Ok(step)
+    }
+}
+
+/// Anthropic 流式聊天：以 `stream: true` 请求 Messages 接口，
+/// 将 SSE 事件逐个转换为 OpenAI 兼容的 chunk 透传给调用方，
+/// 并按 message_start/message_delta 中的 usage 记录日志与计费
 #[allow(clippy::too_many_arguments)]
 pub async fn stream_anthropic_chat(
     app_state: Arc<AppState>,
@@ -25,159 +264,100 @@ pub async fn stream_anthropic_chat(
     base_url: String,
     provider_name: String,
     api_key: String,
+    api_version: String,
     client_token: Option<String>,
     mut upstream_req: ChatCompletionRequest,
     top_k: Option<u32>,
     log_context: super::common::StreamLogContext,
 ) -> Result<Response, GatewayError> {
-    // Ensure we don't accidentally request upstream SSE.
-    upstream_req.stream = Some(false);
+    upstream_req.stream = Some(true);
     upstream_req.stream_options = None;
+    let params = AnthropicProvider::convert_openai_to_anthropic_with_top_k(&upstream_req, top_k);
+    let response =
+        AnthropicProvider::stream_messages(&base_url, &api_key, &api_version, &params).await?;
 
     let (tx, rx) = tokio::sync::mpsc::unbounded_channel::<axum::response::sse::Event>();
     let api_key_ref = Some(mask_key(&api_key));
-    let app_state_clone = app_state.clone();
-    let client_token_for_task = client_token.clone();
+    let usage_cell: Arc<Mutex<Option<Usage>>> = Arc::new(Mutex::new(None));
+    let preview_cell: Arc<Mutex<String>> = Arc::new(Mutex::new(String::new()));
 
-    tokio::spawn(async move {
+    let tasks = app_state.task_registry.clone();
+    tasks.spawn_fallible("stream_anthropic", async move {
         let mut log_context = log_context;
-        let params =
-            AnthropicProvider::convert_openai_to_anthropic_with_top_k(&upstream_req, top_k);
-
-        let resp = AnthropicProvider::chat_completions(&base_url, &api_key, &params).await;
-
-        match resp {
-            Ok(ok) => {
-                let openai_resp = AnthropicProvider::convert_anthropic_to_openai(&ok);
-                let reasoning = AnthropicProvider::extract_reasoning_content(&ok);
-                let usage: Option<Usage> = openai_resp.usage.clone();
-
-                let content = openai_resp
-                    .choices
-                    .first()
-                    .and_then(|c| c.message.content.clone())
-                    .unwrap_or_default();
-
-                let created = Utc::now().timestamp().max(0) as u64;
-                let id = openai_resp.id.clone();
-
-                let mut delta = serde_json::Map::new();
-                delta.insert("role".to_string(), Value::String("assistant".to_string()));
-                if !content.is_empty() {
-                    delta.insert("content".to_string(), Value::String(content.clone()));
-                }
-                if let Some(r) = reasoning.as_deref()
-                    && !r.is_empty()
-                {
-                    delta.insert(
-                        "reasoning_content".to_string(),
-                        Value::String(r.to_string()),
-                    );
-                }
+        let mut state = AnthropicStreamState::new(effective_model.clone());
+        let mut decoder = SseMessageDecoder::default();
+        let mut stream = response.bytes_stream();
+        let send = |value: &Value| {
+            tx.send(axum::response::sse::Event::default().data(value.to_string()))
+                .is_ok()
+        };
 
-                let chunk1 = json!({
-                    "id": id,
-                    "object": "chat.completion.chunk",
-                    "created": created,
-                    "model": effective_model,
-                    "choices": [{
-                        "index": 0,
-                        "delta": Value::Object(delta),
-                        "finish_reason": Value::Null
-                    }]
-                });
-
-                let mut chunk2 = json!({
-                    "id": openai_resp.id,
-                    "object": "chat.completion.chunk",
-                    "created": created,
-                    "model": openai_resp.model,
-                    "choices": [{
-                        "index": 0,
-                        "delta": {},
-                        "finish_reason": "stop"
-                    }]
-                });
-                if let Some(u) = usage.clone()
-                    && let Ok(v) = serde_json::to_value(u)
-                {
-                    chunk2["usage"] = v;
+        let outcome: Result<(), String> = async {
+            while let Some(item) = stream.next().await {
+                let chunk = item.map_err(|err| format!("Anthropic 流式读取失败：{err}"))?;
+                for message in decoder.push_bytes(&chunk) {
+                    let step = state.push(&message.event, &message.data)?;
+                    *usage_cell.lock().unwrap() = Some(state.usage());
+                    if !step.chunks.is_empty() {
+                        super::common::record_first_token_latency(&mut log_context, start_time);
+                    }
+                    for chunk in &step.chunks {
+                        super::common::append_response_preview_fragment(
+                            &preview_cell,
+                            response_text::stream_chunk_preview_fragment(chunk),
+                        );
+                        if !send(chunk) {
+                            return Ok(());
+                        }
+                    }
+                    if step.done {
+                        return Ok(());
+                    }
                 }
+            }
+            Ok(())
+        }
+        .await;
 
-                super::common::record_first_token_latency(&mut log_context, start_time);
-                let _ = tx.send(axum::response::sse::Event::default().data(chunk1.to_string()));
-                let _ = tx.send(axum::response::sse::Event::default().data(chunk2.to_string()));
+        let usage_snapshot = usage_cell.lock().unwrap().clone();
+        let log_context = super::common::context_with_stream_preview(&log_context, &preview_cell);
+        match &outcome {
+            Ok(()) => {
                 let _ = tx.send(axum::response::sse::Event::default().data("[DONE]"));
-
-                tokio::spawn({
-                    let app = app_state_clone.clone();
-                    let billing_model = model_with_prefix.clone();
-                    let requested_model = requested_model.clone();
-                    let effective_model = effective_model.clone();
-                    let provider = provider_name.clone();
-                    let api_key = api_key_ref.clone();
-                    let ct = client_token_for_task.clone();
-                    let response_preview = response_text::preview_from_stream_text(
-                        if content.is_empty() {
-                            reasoning.unwrap_or_default()
-                        } else {
-                            content.clone()
-                        },
-                        1200,
-                    );
-                    let log_context = super::common::context_with_response_preview(
-                        &log_context,
-                        response_preview,
-                    );
-                    async move {
-                        super::common::log_stream_success(
-                            app,
-                            start_time,
-                            billing_model,
-                            requested_model,
-                            effective_model,
-                            provider,
-                            api_key,
-                            ct,
-                            usage,
-                            log_context,
-                        )
-                        .await;
-                    }
-                });
+                super::common::log_stream_success(
+                    app_state,
+                    start_time,
+                    model_with_prefix,
+                    requested_model,
+                    effective_model,
+                    provider_name,
+                    api_key_ref,
+                    client_token,
+                    usage_snapshot,
+                    log_context,
+                )
+                .await;
             }
-            Err(e) => {
-                let msg = e.to_string();
-                tokio::spawn({
-                    let app = app_state_clone.clone();
-                    let billing_model = model_with_prefix.clone();
-                    let requested_model = requested_model.clone();
-                    let effective_model = effective_model.clone();
-                    let provider = provider_name.clone();
-                    let api_key = api_key_ref.clone();
-                    let ct = client_token_for_task.clone();
-                    let msg = msg.clone();
-                    async move {
-                        super::common::log_stream_error(
-                            app,
-                            start_time,
-                            billing_model,
-                            requested_model,
-                            effective_model,
-                            provider,
-                            api_key,
-                            ct,
-                            msg,
-                            log_context.clone(),
-                        )
-                        .await;
-                    }
-                });
-                let _ =
-                    tx.send(axum::response::sse::Event::default().data(format!("error: {}", msg)));
-                let _ = tx.send(axum::response::sse::Event::default().data("[DONE]"));
+            Err(message) => {
+                let _ = tx
+                    .send(axum::response::sse::Event::default().data(format!("error: {message}")));
+                super::common::log_stream_error(
+                    app_state,
+                    start_time,
+                    model_with_prefix,
+                    requested_model,
+                    effective_model,
+                    provider_name,
+                    api_key_ref,
+                    client_token,
+                    message.clone(),
+                    log_context,
+                )
+                .await;
             }
         }
+
+        outcome
     });
 
     let out_stream = tokio_stream::StreamExt::map(
@@ -188,3 +368,126 @@ pub async fn stream_anthropic_chat(
         .keep_alive(axum::response::sse::KeepAlive::default())
         .into_response())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn push_all(state: &mut AnthropicStreamState, events: &[(&str, Value)]) -> Vec<Value> {
+        events
+            .iter()
+            .flat_map(|(name, data)| state.push(name, &data.to_string()).unwrap().chunks)
+            .collect()
+    }
+
+    #[test]
+    fn converts_anthropic_events_into_openai_chunks_with_usage() {
+        let mut state = AnthropicStreamState::new("claude".into());
+        let chunks = push_all(
+            &mut state,
+            &[
+                (
+                    "message_start",
+                    json!({"type": "message_start", "message": {
+                        "id": "msg_1", "model": "claude-sonnet-4",
+                        "usage": {"input_tokens": 25, "output_tokens": 1}
+                    }}),
+                ),
+                (
+                    "content_block_start",
+                    json!({"type": "content_block_start", "index": 0,
+                        "content_block": {"type": "thinking", "thinking": ""}}),
+                ),
+                (
+                    "content_block_delta",
+                    json!({"type": "content_block_delta", "index": 0,
+                        "delta": {"type": "thinking_delta", "thinking": "hmm"}}),
+                ),
+                (
+                    "content_block_delta",
+                    json!({"type": "content_block_delta", "index": 1,
+                        "delta": {"type": "text_delta", "text": "Hello"}}),
+                ),
+                (
+                    "content_block_start",
+                    json!({"type": "content_block_start", "index": 2,
+                        "content_block": {"type": "tool_use", "id": "toolu_1", "name": "lookup", "input": {}}}),
+                ),
+                (
+                    "content_block_delta",
+                    json!({"type": "content_block_delta", "index": 2,
+                        "delta": {"type": "input_json_delta", "partial_json": "{\"q\":"}}),
+                ),
+                ("ping", json!({"type": "ping"})),
+                (
+                    "message_delta",
+                    json!({"type": "message_delta",
+                        "delta": {"stop_reason": "tool_use"}, "usage": {"output_tokens": 15}}),
+                ),
+            ],
+        );
+
+        assert_eq!(chunks[0]["id"], "msg_1");
+        assert_eq!(chunks[0]["model"], "claude-sonnet-4");
+        assert_eq!(chunks[0]["choices"][0]["delta"]["role"], "assistant");
+        assert_eq!(chunks[1]["choices"][0]["delta"]["reasoning_content"], "hmm");
+        assert_eq!(chunks[2]["choices"][0]["delta"]["content"], "Hello");
+        let call = &chunks[3]["choices"][0]["delta"]["tool_calls"][0];
+        assert_eq!(call["index"], 0);
+        assert_eq!(call["id"], "toolu_1");
+        assert_eq!(call["function"]["name"], "lookup");
+        let args = &chunks[4]["choices"][0]["delta"]["tool_calls"][0];
+        assert_eq!(args["index"], 0);
+        assert_eq!(args["function"]["arguments"], "{\"q\":");
+        assert_eq!(chunks[5]["choices"][0]["finish_reason"], "tool_calls");
+        assert_eq!(chunks.len(), 6);
+
+        let stop = state
+            .push("message_stop", r#"{"type":"message_stop"}"#)
+            .unwrap();
+        assert!(stop.done);
+        let usage = &stop.chunks[0]["usage"];
+        assert_eq!(usage["prompt_tokens"], 25);
+        assert_eq!(usage["completion_tokens"], 15);
+        assert_eq!(usage["total_tokens"], 40);
+        assert_eq!(usage["completion_tokens_details"]["reasoning_tokens"], 1);
+    }
+
+    #[test]
+    fn error_events_fail_the_stream() {
+        let mut state = AnthropicStreamState::new("claude".into());
+        let err = state
+            .push(
+                "error",
+                r#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#,
+            )
+            .unwrap_err();
+        assert_eq!(err, "Overloaded");
+        assert!(state.push("message_start", "not json").is_err());
+    }
+
+    #[test]
+    fn multibyte_text_split_across_network_chunks_is_preserved() {
+        let frame = format!(
+            "event: content_block_delta\ndata: {}\n\n",
+            json!({"type": "content_block_delta", "index": 0,
+                "delta": {"type": "text_delta", "text": "你好，世界"}})
+        );
+        let bytes = frame.as_bytes();
+        // 在“你”的三个字节中间切开
+        let split = frame.find('你').unwrap() + 1;
+        let mut decoder = SseMessageDecoder::default();
+        assert!(decoder.push_bytes(&bytes[..split]).is_empty());
+        let messages = decoder.push_bytes(&bytes[split..]);
+        assert_eq!(messages.len(), 1);
+
+        let mut state = AnthropicStreamState::new("claude".into());
+        let step = state.push(&messages[0].event, &messages[0].data).unwrap();
+        let content = step
+            .chunks
+            .iter()
+            .filter_map(|c| c["choices"][0]["delta"]["content"].as_str())
+            .collect::<String>();
+        assert_eq!(content, "你好，世界");
+    }
+}
@@ -1,21 +1,39 @@
 use std::sync::{Arc, Mutex};
 
+use axum::body::{Body, Bytes};
+use axum::response::Response;
 use chrono::{DateTime, Utc};
+use futures_util::StreamExt;
 
 use crate::balance::BalanceTransactionKind;
 use crate::logging::RequestLog;
-use crate::logging::types::{REQ_TYPE_CHAT_STREAM, RequestLogDetailRecord};
+use crate::logging::payload_archive;
+use crate::logging::types::{
+    LatencyBreakdown, PromptProfile, REQ_TYPE_CHAT_STREAM, RequestLogDetailRecord,
+};
 use crate::providers::openai::Usage;
 use crate::server::AppState;
 use crate::server::response_text;
+use crate::server::stream_transcript::StreamRecorder;
+use crate::server::usage_webhooks::{self, UsageEvent};
 
 const STREAM_RESPONSE_PREVIEW_MAX_LEN: usize = 1200;
 
 #[derive(Debug, Clone, Default)]
 pub(super) struct StreamLogContext {
+    /// 日志与计费记录的入口路径（`/v1/chat/completions` 或旧版 `/v1/completions`）
+    pub path: String,
     pub request_payload_snapshot: Option<String>,
     pub response_preview: Option<String>,
     pub first_token_latency_ms: Option<i64>,
+    pub param_policy_applied: Option<String>,
+    pub provider_override: Option<String>,
+    pub prompt_truncation: Option<String>,
+    /// 开始向上游发送请求的时间；流式请求的上游耗时持续到流结束
+    pub upstream_started_at: Option<DateTime<Utc>>,
+    pub prompt_profile: PromptProfile,
+    /// 开启 capture_stream_chunks 时的分片记录器，日志写入后关联日志 ID
+    pub transcript: Option<Arc<StreamRecorder>>,
 }
 
 async fn upsert_stream_log_detail(
@@ -28,7 +46,10 @@ async fn upsert_stream_log_detail(
 ) {
     let detail = RequestLogDetailRecord {
         request_log_id,
-        request_payload_snapshot: context.request_payload_snapshot.clone(),
+        request_payload_snapshot: payload_archive::cap_captured_body(
+            context.request_payload_snapshot.clone(),
+            app_state.config.server.capture_body_max_bytes,
+        ),
         response_preview: context.response_preview.clone(),
         upstream_status: Some(i64::from(status_code)),
         fallback_triggered: None,
@@ -36,10 +57,16 @@ async fn upsert_stream_log_detail(
         selected_provider: Some(provider.to_string()),
         selected_key_id: api_key.map(str::to_string),
         first_token_latency_ms: context.first_token_latency_ms,
+        param_policy_applied: context.param_policy_applied.clone(),
+        provider_override: context.provider_override.clone(),
+        prompt_truncation: context.prompt_truncation.clone(),
     };
     if let Err(error) = app_state.log_store.upsert_request_log_detail(detail).await {
         tracing::warn!("Failed to upsert streaming request log detail: {}", error);
     }
+    if let Some(recorder) = context.transcript.as_ref() {
+        recorder.attach_log(request_log_id);
+    }
 }
 
 pub(super) fn append_response_preview_fragment(
@@ -104,7 +131,7 @@ pub(super) async fn log_stream_error(
         id: None,
         timestamp: start_time,
         method: "POST".to_string(),
-        path: "/v1/chat/completions".to_string(),
+        path: context.path.clone(),
         request_type: REQ_TYPE_CHAT_STREAM.to_string(),
         requested_model: Some(requested_model),
         effective_model: Some(effective_model),
@@ -122,8 +149,17 @@ pub(super) async fn log_stream_error(
         cached_tokens: None,
         reasoning_tokens: None,
         error_message: Some(error_message),
+        latency: LatencyBreakdown::from_marks(
+            start_time,
+            context.upstream_started_at,
+            None,
+            end_time,
+        ),
+        profile: context.prompt_profile.clone(),
     };
-    match app_state.log_store.log_request(log).await {
+    let usage_event = UsageEvent::from_request_log(&log);
+    let log_id = match crate::server::degraded::log_request(app_state.log_store.as_ref(), log).await
+    {
         Ok(log_id) => {
             upsert_stream_log_detail(
                 &app_state,
@@ -134,11 +170,15 @@ pub(super) async fn log_stream_error(
                 &context,
             )
             .await;
+            Some(log_id)
         }
         Err(e) => {
             tracing::error!("Failed to log streaming error: {}", e);
+            None
         }
-    }
+    };
+    usage_webhooks::enqueue_for_request(&app_state, client_token.as_deref(), usage_event, log_id)
+        .await;
 }
 
 // 统一的流式成功日志记录函数
@@ -192,6 +232,16 @@ pub(super) async fn log_stream_success(
     } else {
         None
     };
+    // 供密钥轮换跟踪每个上游 key 的 tpm 窗口与剩余额度
+    if let Some(masked) = api_key.as_deref() {
+        app_state.load_balancer_state.record_key_usage(
+            &provider,
+            masked,
+            usage.as_ref().map(|u| u.total_tokens),
+            amount_spent,
+        );
+    }
+    crate::server::provider_budget::record_spend(&app_state, &provider, amount_spent).await;
 
     let client_token_id = client_token
         .as_deref()
@@ -200,7 +250,7 @@ pub(super) async fn log_stream_success(
         id: None,
         timestamp: start_time,
         method: "POST".to_string(),
-        path: "/v1/chat/completions".to_string(),
+        path: context.path.clone(),
         request_type: REQ_TYPE_CHAT_STREAM.to_string(),
         requested_model: Some(requested_model),
         effective_model: Some(effective_model),
@@ -218,8 +268,36 @@ pub(super) async fn log_stream_success(
         cached_tokens: cached,
         reasoning_tokens: reasoning,
         error_message: None,
+        latency: LatencyBreakdown::from_marks(
+            start_time,
+            context.upstream_started_at,
+            None,
+            end_time,
+        ),
+        profile: context.prompt_profile.clone(),
     };
-    match app_state.log_store.log_request(log).await {
+    // 增量更新 client_tokens：金额与 tokens（仅当有 Client Token 时）；先于请求日志入账
+    let counted = match client_token.as_deref() {
+        Some(tok) => {
+            crate::server::request_logging::count_token_usage(
+                &app_state,
+                tok,
+                amount_spent,
+                usage.as_ref().map(|u| {
+                    (
+                        u.prompt_tokens as i64,
+                        u.completion_tokens as i64,
+                        u.total_tokens as i64,
+                    )
+                }),
+            )
+            .await
+        }
+        None => None,
+    };
+    let usage_event = UsageEvent::from_request_log(&log);
+    let log_id = match crate::server::degraded::log_request(app_state.log_store.as_ref(), log).await
+    {
         Ok(log_id) => {
             upsert_stream_log_detail(
                 &app_state,
@@ -230,32 +308,20 @@ pub(super) async fn log_stream_success(
                 &context,
             )
             .await;
+            Some(log_id)
         }
         Err(e) => {
             tracing::error!("Failed to log streaming request: {}", e);
+            None
         }
+    };
+    if let Some(counted) = counted {
+        counted.logged(log_id).await;
     }
+    usage_webhooks::enqueue_for_request(&app_state, client_token.as_deref(), usage_event, log_id)
+        .await;
 
-    // 增量更新 client_tokens：金额与 tokens（仅当有 Client Token 时）
     if let Some(tok) = client_token.as_deref() {
-        if let Some(delta) = amount_spent
-            && let Err(e) = app_state.token_store.add_amount_spent(tok, delta).await
-        {
-            tracing::warn!("Failed to update token spent: {}", e);
-        }
-        if let Some(u) = usage.as_ref() {
-            let prompt = u.prompt_tokens as i64;
-            let completion = u.completion_tokens as i64;
-            let total = u.total_tokens as i64;
-            if let Err(e) = app_state
-                .token_store
-                .add_usage_spent(tok, prompt, completion, total)
-                .await
-            {
-                tracing::warn!("Failed to update token tokens: {}", e);
-            }
-        }
-
         // 订阅计费：绑定用户 token 只扣 user.balance（单位：tokens），不扣金额
         if let Some(u) = usage.as_ref()
             && let Ok(Some(t)) = app_state.token_store.get_token(tok).await
@@ -272,7 +338,7 @@ pub(super) async fn log_stream_success(
                     Ok(Some(new_balance)) => {
                         let meta = serde_json::json!({
                             "client_token_id": t.id,
-                            "path": "/v1/chat/completions",
+                            "path": context.path,
                             "total_tokens": total_tokens,
                             "amount_spent": amount_spent,
                         })
@@ -321,6 +387,44 @@ pub(super) async fn log_stream_success(
 }
 
 // Extract Usage from a JSON value if fields are present (lenient across providers)
+/// 令牌开启 strip_reasoning 时改写流式响应：按 SSE 帧（空行分隔）缓冲，
+/// 去掉 data 行 JSON 中的 reasoning_content 后原样转发其余内容
+pub(super) fn strip_reasoning_stream_response(response: Response) -> Response {
+    let (parts, body) = response.into_parts();
+    let mut pending: Vec<u8> = Vec::new();
+    let stream = body.into_data_stream().map(move |chunk| {
+        chunk.map(|bytes| {
+            pending.extend_from_slice(&bytes);
+            let mut out = String::new();
+            while let Some(pos) = pending.windows(2).position(|w| w == b"\n\n") {
+                let frame: Vec<u8> = pending.drain(..pos + 2).collect();
+                out.push_str(&strip_reasoning_sse_frame(&String::from_utf8_lossy(
+                    &frame[..pos],
+                )));
+                out.push_str("\n\n");
+            }
+            Bytes::from(out)
+        })
+    });
+    Response::from_parts(parts, Body::from_stream(stream))
+}
+
+fn strip_reasoning_sse_frame(frame: &str) -> String {
+    frame
+        .split('\n')
+        .map(|line| {
+            if let Some(data) = line.strip_prefix("data:")
+                && let Ok(mut v) = serde_json::from_str::<serde_json::Value>(data.trim_start())
+                && response_text::strip_reasoning_fields(&mut v)
+            {
+                return format!("data: {}", v);
+            }
+            line.to_string()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
 pub(super) fn parse_usage_from_value(v: &serde_json::Value) -> Option<Usage> {
     use async_openai::types::{CompletionTokensDetails, PromptTokensDetails};
     let u = v.get("usage")?;
@@ -386,11 +490,31 @@ mod tests {
     use crate::balance::BalanceStore;
     use crate::config::settings::{BalanceStrategy, LoadBalancing, LoggingConfig, ServerConfig};
     use crate::logging::DatabaseLogger;
-    use crate::server::login::LoginManager;
     use crate::users::{CreateUserPayload, UserRole, UserStatus, UserStore};
     use std::sync::Arc;
     use tempfile::tempdir;
 
+    #[tokio::test]
+    async fn strip_reasoning_stream_response_rewrites_split_frames() {
+        let chunks = vec![
+            Ok::<_, std::io::Error>(Bytes::from("data: {\"choices\":[{\"delta\":{\"reasoning_")),
+            Ok(Bytes::from(
+                "content\":\"x\",\"content\":\"hi\"}}]}\n\ndata: [DONE]\n\n",
+            )),
+        ];
+        let response = Response::new(Body::from_stream(futures_util::stream::iter(chunks)));
+        let body = axum::body::to_bytes(
+            strip_reasoning_stream_response(response).into_body(),
+            usize::MAX,
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(body.to_vec()).unwrap(),
+            "data: {\"choices\":[{\"delta\":{\"content\":\"hi\"}}]}\n\ndata: [DONE]\n\n"
+        );
+    }
+
     fn test_settings(db_path: String) -> crate::config::Settings {
         crate::config::Settings {
             load_balancing: LoadBalancing {
@@ -415,22 +539,7 @@ mod tests {
         );
         let settings = test_settings(db_path.to_string_lossy().to_string());
 
-        let app_state = Arc::new(AppState {
-            config: settings,
-            load_balancer_state: Arc::new(crate::routing::LoadBalancerState::default()),
-            log_store: logger.clone(),
-            model_cache: logger.clone(),
-            providers: logger.clone(),
-            token_store: logger.clone(),
-            favorites_store: logger.clone(),
-            organizations: logger.clone(),
-            login_manager: Arc::new(LoginManager::new(logger.clone())),
-            user_store: logger.clone(),
-            refresh_token_store: logger.clone(),
-            password_reset_token_store: logger.clone(),
-            balance_store: logger.clone(),
-            subscription_store: logger.clone(),
-        });
+        let app_state = crate::server::test_harness::app_state(settings, logger.clone());
 
         let user = logger
             .create_user(CreateUserPayload {
@@ -450,20 +559,9 @@ mod tests {
 
         let token = logger
             .create_token(CreateTokenPayload {
-                id: None,
                 user_id: Some(user.id.clone()),
                 name: Some("t1".into()),
-                token: None,
-                allowed_models: None,
-                model_blacklist: None,
-                max_tokens: None,
-                max_amount: None,
-                enabled: true,
-                expires_at: None,
-                remark: None,
-                organization_id: None,
-                ip_whitelist: None,
-                ip_blacklist: None,
+                ..Default::default()
             })
             .await
             .unwrap();
@@ -509,39 +607,12 @@ mod tests {
         );
         let settings = test_settings(db_path.to_string_lossy().to_string());
 
-        let app_state = Arc::new(AppState {
-            config: settings,
-            load_balancer_state: Arc::new(crate::routing::LoadBalancerState::default()),
-            log_store: logger.clone(),
-            model_cache: logger.clone(),
-            providers: logger.clone(),
-            token_store: logger.clone(),
-            favorites_store: logger.clone(),
-            organizations: logger.clone(),
-            login_manager: Arc::new(LoginManager::new(logger.clone())),
-            user_store: logger.clone(),
-            refresh_token_store: logger.clone(),
-            password_reset_token_store: logger.clone(),
-            balance_store: logger.clone(),
-            subscription_store: logger.clone(),
-        });
+        let app_state = crate::server::test_harness::app_state(settings, logger.clone());
 
         let token = logger
             .create_token(CreateTokenPayload {
-                id: None,
-                user_id: None,
                 name: Some("t1".into()),
-                token: None,
-                allowed_models: None,
-                model_blacklist: None,
-                max_tokens: None,
-                max_amount: None,
-                enabled: true,
-                expires_at: None,
-                remark: None,
-                organization_id: None,
-                ip_whitelist: None,
-                ip_blacklist: None,
+                ..Default::default()
             })
             .await
             .unwrap();
@@ -573,9 +644,16 @@ mod tests {
                 completion_tokens_details: None,
             }),
             StreamLogContext {
+                path: "/v1/chat/completions".into(),
                 request_payload_snapshot: Some(snapshot.clone()),
                 response_preview: Some("hello world".into()),
                 first_token_latency_ms: Some(123),
+                param_policy_applied: None,
+                provider_override: None,
+                prompt_truncation: None,
+                upstream_started_at: None,
+                prompt_profile: Default::default(),
+                transcript: None,
             },
         )
         .await;
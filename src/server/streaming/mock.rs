@@ -0,0 +1,127 @@
+use std::{convert::Infallible, sync::Arc};
+
+use axum::response::{IntoResponse, Response, Sse};
+use chrono::{DateTime, Utc};
+use serde_json::{Value, json};
+
+use crate::config::settings::ProviderConfig;
+use crate::error::GatewayError;
+use crate::providers::adapters::mock_chat_completion;
+use crate::providers::openai::ChatCompletionRequest;
+use crate::server::AppState;
+use crate::server::response_text;
+use crate::server::util::mask_key;
+
+/// Mock streaming：在进程内生成完整回复后按词切分为多个 delta chunk，
+/// 最后发送携带 usage 的结束 chunk 与 `[DONE]`，日志与计费走常规流式链路。
+#[allow(clippy::too_many_arguments)]
+pub async fn stream_mock_chat(
+    app_state: Arc<AppState>,
+    start_time: DateTime<Utc>,
+    model_with_prefix: String,
+    requested_model: String,
+    effective_model: String,
+    provider_name: String,
+    api_key: String,
+    client_token: Option<String>,
+    upstream_req: ChatCompletionRequest,
+    provider_config: ProviderConfig,
+    log_context: super::common::StreamLogContext,
+) -> Result<Response, GatewayError> {
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel::<axum::response::sse::Event>();
+    let api_key_ref = Some(mask_key(&api_key));
+
+    let tasks = app_state.task_registry.clone();
+    tasks.spawn("stream_mock", async move {
+        let mut log_context = log_context;
+        match mock_chat_completion(&provider_config, &upstream_req).await {
+            Ok(ok) => {
+                let content = ok
+                    .typed
+                    .choices
+                    .first()
+                    .and_then(|c| c.message.content.clone())
+                    .unwrap_or_default();
+                let usage = ok.typed.usage.clone();
+                let created = Utc::now().timestamp().max(0) as u64;
+                let chunk = |delta: Value, finish_reason: Value| {
+                    json!({
+                        "id": ok.typed.id,
+                        "object": "chat.completion.chunk",
+                        "created": created,
+                        "model": effective_model,
+                        "choices": [{
+                            "index": 0,
+                            "delta": delta,
+                            "finish_reason": finish_reason
+                        }]
+                    })
+                };
+
+                super::common::record_first_token_latency(&mut log_context, start_time);
+                for (i, piece) in content.split_inclusive(' ').enumerate() {
+                    let delta = if i == 0 {
+                        json!({"role": "assistant", "content": piece})
+                    } else {
+                        json!({"content": piece})
+                    };
+                    let _ = tx.send(
+                        axum::response::sse::Event::default()
+                            .data(chunk(delta, Value::Null).to_string()),
+                    );
+                }
+                let mut last = chunk(json!({}), json!("stop"));
+                if let Some(v) = usage.as_ref().and_then(|u| serde_json::to_value(u).ok()) {
+                    last["usage"] = v;
+                }
+                let _ = tx.send(axum::response::sse::Event::default().data(last.to_string()));
+                let _ = tx.send(axum::response::sse::Event::default().data("[DONE]"));
+
+                let log_context = super::common::context_with_response_preview(
+                    &log_context,
+                    response_text::preview_from_stream_text(content, 1200),
+                );
+                super::common::log_stream_success(
+                    app_state,
+                    start_time,
+                    model_with_prefix,
+                    requested_model,
+                    effective_model,
+                    provider_name,
+                    api_key_ref,
+                    client_token,
+                    usage,
+                    log_context,
+                )
+                .await;
+            }
+            Err(e) => {
+                let msg = e.to_string();
+                let _ =
+                    tx.send(axum::response::sse::Event::default().data(format!("error: {}", msg)));
+                let _ = tx.send(axum::response::sse::Event::default().data("[DONE]"));
+                super::common::log_stream_error(
+                    app_state,
+                    start_time,
+                    model_with_prefix,
+                    requested_model,
+                    effective_model,
+                    provider_name,
+                    api_key_ref,
+                    client_token,
+                    msg,
+                    log_context,
+                )
+                .await;
+            }
+        }
+    });
+
+    let out_stream = tokio_stream::StreamExt::map(
+        tokio_stream::wrappers::UnboundedReceiverStream::new(rx),
+        Ok::<_, Infallible>,
+    );
+    Ok(Sse::new(out_stream)
+        .keep_alive(axum::response::sse::KeepAlive::default())
+        .into_response())
+}
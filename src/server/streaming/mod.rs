@@ -10,33 +10,45 @@ use std::sync::Arc;
 use crate::error::GatewayError;
 use crate::providers::adapters::runtime_streaming_unsupported_message;
 use crate::server::AppState;
-use crate::server::chat_request::GatewayChatCompletionRequest;
-use crate::server::model_redirect::{
-    apply_model_redirects, apply_provider_model_redirects_to_parsed_model,
+use crate::server::chat_pipeline::{
+    AdmittedChatRequest, ChatTransport, admit_chat_request, disable_token_if_over_limits,
 };
-use crate::server::pricing::{missing_price_allowed_for_chat, resolve_model_pricing};
-use crate::server::provider_dispatch::select_provider_for_model;
+use crate::server::chat_plan::DecisionTrace;
+use crate::server::chat_request::GatewayChatCompletionRequest;
+use crate::server::model_concurrency::FairShare;
+use crate::server::prompt_truncation::PromptTruncation;
+use crate::server::provider_override::ProviderOverride;
 use crate::server::request_lab::build_request_payload_snapshot;
+use crate::server::stream_transcript::{self, StreamRecorder};
+use crate::server::watermark::{self, Watermark};
 
 mod anthropic;
 mod common;
+mod mock;
 mod native;
 mod openai;
 mod zhipu;
 
 /// Chat Completions 流式入口：
 /// - 仅接受 `stream=true` 的请求，否则直接报错
-/// - 应用模型重定向后，根据模型选择具体 Provider，并校验令牌额度/过期/模型白名单
+/// - 预检查（重定向、令牌、供应商选择、价格、故障注入、参数策略）与非流式共用 `admit_chat_request`
 /// - 按 Provider 类型分发到对应的流式实现（OpenAI/Zhipu/原生协议族），并统一返回 SSE 响应
 pub async fn stream_chat_completions(
     State(app_state): State<Arc<AppState>>,
     headers: HeaderMap,
     Json(gateway_req): Json<GatewayChatCompletionRequest>,
+    path: &str,
 ) -> Result<Response, GatewayError> {
     let top_k = gateway_req.top_k;
+    let provider_override = ProviderOverride::from_request(
+        &headers,
+        gateway_req.provider.as_deref(),
+        gateway_req.provider_key.as_deref(),
+    )?;
     let snapshot = build_request_payload_snapshot(&gateway_req.request, top_k)?;
-    let mut request = gateway_req.request;
-    if !request.stream.unwrap_or(false) {
+    let request = gateway_req.request;
+    let transport = ChatTransport::for_request(&request);
+    if !transport.is_stream() {
         return Err(GatewayError::Config(
             "stream=false for streaming endpoint".into(),
         ));
@@ -44,123 +56,59 @@ pub async fn stream_chat_completions(
 
     let start_time = Utc::now();
     let requested_model = request.model.clone();
-    apply_model_redirects(&mut request);
-    let parsed_for_prefix = crate::server::model_parser::ParsedModel::parse(&request.model);
-    if let Some(p) = parsed_for_prefix.provider_name.as_deref() {
-        let mut parsed = parsed_for_prefix.clone();
-        if let Some((from, to)) =
-            apply_provider_model_redirects_to_parsed_model(&app_state, p, &mut parsed).await?
-        {
-            return Err(GatewayError::Config(format!(
-                "model '{}' is redirected; use '{}' instead",
-                from, to
-            )));
-        }
-    }
-    let (selected, mut parsed_model) =
-        select_provider_for_model(&app_state, &request.model).await?;
-
-    // 若该模型在 provider redirects 中作为 source，则不允许第三方直接调用（避免 source/target 重复可用）
-    let mut parsed_for_redirect_check = parsed_model.clone();
-    if let Some((from, to)) = apply_provider_model_redirects_to_parsed_model(
-        &app_state,
-        &selected.provider.name,
-        &mut parsed_for_redirect_check,
-    )
-    .await?
-    {
-        let ge = GatewayError::Config(format!(
-            "model '{}' is redirected; use '{}' instead",
-            from, to
-        ));
+    // Extract required gateway token from Authorization header
+    let client_token = headers
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|s| s.strip_prefix("Bearer "))
+        .map(|s| s.to_string());
+    let client_token_log_id = client_token
+        .as_deref()
+        .map(crate::admin::client_token_id_for_token);
+    let Some(token_str) = client_token.as_deref() else {
+        let ge = GatewayError::Config("missing bearer token".into());
         let code = ge.status_code().as_u16();
         crate::server::request_logging::log_simple_request(
             &app_state,
             start_time,
             "POST",
-            "/v1/chat/completions",
-            crate::logging::types::REQ_TYPE_CHAT_STREAM,
-            Some(from),
-            Some(selected.provider.name.clone()),
+            path,
+            transport.request_type(),
+            Some(requested_model),
+            None,
             None,
             code,
             Some(ge.to_string()),
         )
         .await;
         return Err(ge);
-    }
+    };
 
-    let mut redirected_from_for_price: Option<String> = None;
-    if let Some((from, to)) = apply_provider_model_redirects_to_parsed_model(
+    let mut trace = DecisionTrace::new(&requested_model);
+    let admitted = match admit_chat_request(
         &app_state,
-        &selected.provider.name,
-        &mut parsed_model,
+        start_time,
+        request,
+        top_k,
+        token_str,
+        path,
+        transport,
+        provider_override.as_ref(),
+        &mut trace,
     )
-    .await?
+    .await
     {
-        tracing::info!(
-            provider = %selected.provider.name,
-            source_model = %from,
-            target_model = %to,
-            "已应用 provider 维度模型重定向"
-        );
-        redirected_from_for_price = Some(from.clone());
-        request.model = if parsed_model.provider_name.is_some() {
-            format!("{}/{}", selected.provider.name, parsed_model.model_name)
-        } else {
-            parsed_model.model_name.clone()
-        };
-    }
-
-    // Build upstream request with real model id
-    let mut upstream_req = request.clone();
-    upstream_req.model = parsed_model.get_upstream_model_name().to_string();
-
-    // Extract required gateway token from Authorization header
-    let client_token = headers
-        .get(axum::http::header::AUTHORIZATION)
-        .and_then(|v| v.to_str().ok())
-        .and_then(|s| s.strip_prefix("Bearer "))
-        .map(|s| s.to_string());
-    let client_token_log_id = client_token
-        .as_deref()
-        .map(crate::admin::client_token_id_for_token);
-    let token_str = match client_token.as_deref() {
-        Some(tok) => tok,
-        None => {
-            let ge = GatewayError::Config("missing bearer token".into());
-            let code = ge.status_code().as_u16();
-            crate::server::request_logging::log_simple_request(
-                &app_state,
-                start_time,
-                "POST",
-                "/v1/chat/completions",
-                crate::logging::types::REQ_TYPE_CHAT_STREAM,
-                Some(upstream_req.model.clone()),
-                Some(selected.provider.name.clone()),
-                None,
-                code,
-                Some(ge.to_string()),
-            )
-            .await;
-            return Err(ge);
-        }
-    };
-
-    let token_record = app_state.token_store.get_token(token_str).await?;
-    let token = match token_record {
-        Some(t) => t,
-        None => {
-            let ge = GatewayError::Config("invalid token".into());
+        Ok(admitted) => admitted,
+        Err(ge) => {
             let code = ge.status_code().as_u16();
             crate::server::request_logging::log_simple_request(
                 &app_state,
                 start_time,
                 "POST",
-                "/v1/chat/completions",
-                crate::logging::types::REQ_TYPE_CHAT_STREAM,
-                Some(upstream_req.model.clone()),
-                Some(selected.provider.name.clone()),
+                path,
+                transport.request_type(),
+                Some(trace.upstream_model.unwrap_or(requested_model)),
+                trace.provider,
                 client_token_log_id.as_deref(),
                 code,
                 Some(ge.to_string()),
@@ -169,178 +117,62 @@ pub async fn stream_chat_completions(
             return Err(ge);
         }
     };
-
-    if let Some(user_id) = token.user_id.as_deref() {
-        let user = app_state.user_store.get_user(user_id).await?;
-        let balance = user.as_ref().map(|u| u.balance).unwrap_or(0.0);
-        if balance <= 0.0 {
-            let _ = app_state
-                .token_store
-                .set_enabled_for_user(user_id, false)
-                .await;
-            let ge = GatewayError::Config("余额不足：密钥已失效；充值/订阅后需手动启用密钥".into());
-            let code = ge.status_code().as_u16();
-            crate::server::request_logging::log_simple_request(
-                &app_state,
-                start_time,
-                "POST",
-                "/v1/chat/completions",
-                crate::logging::types::REQ_TYPE_CHAT_STREAM,
-                Some(upstream_req.model.clone()),
-                Some(selected.provider.name.clone()),
-                client_token_log_id.as_deref(),
-                code,
-                Some(ge.to_string()),
-            )
-            .await;
-            return Err(ge);
-        }
-    }
-
-    if !token.enabled {
-        if let Some(max_amount) = token.max_amount
-            && let Ok(spent) = app_state
-                .log_store
-                .sum_spent_amount_by_client_token(&token.id)
-                .await
-            && spent >= max_amount
-        {
-            let ge = GatewayError::Config("token budget exceeded".into());
-            let code = ge.status_code().as_u16();
+    let AdmittedChatRequest {
+        token,
+        selected,
+        upstream_model,
+        billing_model,
+        request: mut upstream_req,
+        top_k,
+        param_policy_applied,
+        provider_override,
+        prompt_truncation: truncation,
+        fault,
+        dropped_features,
+        prompt_profile,
+        ..
+    } = admitted;
+    let prompt_truncation = truncation.as_ref().map(PromptTruncation::log_value);
+    let concurrency_permit = match app_state
+        .model_concurrency
+        .acquire(
+            &app_state.config.server,
+            &upstream_model,
+            &FairShare::for_token(&app_state.config.server, &token),
+        )
+        .await
+    {
+        Ok(permit) => permit,
+        Err(ge) => {
             crate::server::request_logging::log_simple_request(
                 &app_state,
                 start_time,
                 "POST",
-                "/v1/chat/completions",
-                crate::logging::types::REQ_TYPE_CHAT_STREAM,
-                Some(upstream_req.model.clone()),
+                path,
+                transport.request_type(),
+                Some(upstream_model),
                 Some(selected.provider.name.clone()),
                 client_token_log_id.as_deref(),
-                code,
+                ge.status_code().as_u16(),
                 Some(ge.to_string()),
             )
             .await;
             return Err(ge);
         }
-        let ge = GatewayError::Config("token disabled".into());
-        let code = ge.status_code().as_u16();
-        crate::server::request_logging::log_simple_request(
-            &app_state,
-            start_time,
-            "POST",
-            "/v1/chat/completions",
-            crate::logging::types::REQ_TYPE_CHAT_STREAM,
-            Some(upstream_req.model.clone()),
-            Some(selected.provider.name.clone()),
-            client_token_log_id.as_deref(),
-            code,
-            Some(ge.to_string()),
-        )
-        .await;
-        return Err(ge);
-    }
-
-    if let Some(exp) = token.expires_at
-        && chrono::Utc::now() > exp
-    {
-        return Err(GatewayError::Config("token expired".into()));
-    }
-
-    crate::server::token_model_limits::enforce_model_allowed_for_token(&token, &request.model)?;
-
-    if let Some(max_tokens) = token.max_tokens
-        && token.total_tokens_spent >= max_tokens
-    {
-        let ge = GatewayError::Config("token tokens exceeded".into());
-        let code = ge.status_code().as_u16();
-        crate::server::request_logging::log_simple_request(
-            &app_state,
-            start_time,
-            "POST",
-            "/v1/chat/completions",
-            crate::logging::types::REQ_TYPE_CHAT_STREAM,
-            Some(upstream_req.model.clone()),
-            Some(selected.provider.name.clone()),
-            client_token_log_id.as_deref(),
-            code,
-            Some(ge.to_string()),
-        )
-        .await;
-        return Err(ge);
-    }
-
-    if let Some(max_amount) = token.max_amount
-        && let Ok(spent) = app_state
-            .log_store
-            .sum_spent_amount_by_client_token(&token.id)
-            .await
-        && spent > max_amount
-    {
-        return Err(GatewayError::Config("token budget exceeded".into()));
-    }
+    };
+    // Build upstream request with real model id
+    upstream_req.model = upstream_model;
 
-    let upstream_model_for_check = parsed_model.get_upstream_model_name().to_string();
-    if let Ok(Some(false)) = app_state
-        .log_store
-        .get_model_enabled(&selected.provider.name, &upstream_model_for_check)
-        .await
-    {
-        let ge = GatewayError::Config("model is disabled".into());
-        let code = ge.status_code().as_u16();
-        crate::server::request_logging::log_simple_request(
-            &app_state,
-            start_time,
-            "POST",
-            "/v1/chat/completions",
-            crate::logging::types::REQ_TYPE_CHAT_STREAM,
-            Some(upstream_req.model.clone()),
-            Some(selected.provider.name.clone()),
-            client_token_log_id.as_deref(),
-            code,
-            Some(ge.to_string()),
-        )
-        .await;
-        return Err(ge);
-    }
-    let resolved_pricing = resolve_model_pricing(
-        &app_state,
+    let egress_key = crate::server::util::mask_key(&selected.api_key);
+    app_state.egress_meter.record_request(
         &selected.provider.name,
-        &upstream_model_for_check,
-        redirected_from_for_price.as_deref(),
-    )
-    .await?;
-    if !resolved_pricing.price_found && !missing_price_allowed_for_chat(&app_state) {
-        let ge = GatewayError::Config("model price not set".into());
-        let code = ge.status_code().as_u16();
-        crate::server::request_logging::log_simple_request(
-            &app_state,
-            start_time,
-            "POST",
-            "/v1/chat/completions",
-            crate::logging::types::REQ_TYPE_CHAT_STREAM,
-            Some(upstream_req.model.clone()),
-            Some(selected.provider.name.clone()),
-            client_token_log_id.as_deref(),
-            code,
-            Some(ge.to_string()),
-        )
-        .await;
-        return Err(ge);
-    }
-    if !resolved_pricing.price_found {
-        tracing::warn!(
-            provider = %selected.provider.name,
-            model = %upstream_model_for_check,
-            pricing_mode = ?app_state.config.server.pricing_mode,
-            "missing model price; continuing without billing amount"
-        );
-    }
-    let billing_model = resolved_pricing.billing_model;
-
-    if let Some(message) = runtime_streaming_unsupported_message(selected.provider.api_type) {
-        return Err(GatewayError::Config(message));
-    }
+        &egress_key,
+        crate::server::egress::json_len(&upstream_req),
+    );
 
+    let in_flight = app_state.in_flight.start(&selected.provider.name, true);
+    let upstream_started_at = Utc::now();
+    let transcript = StreamRecorder::start(&app_state, start_time);
     let response = match selected.provider.api_type {
         crate::config::ProviderType::Anthropic => anthropic::stream_anthropic_chat(
             app_state.clone(),
@@ -351,13 +183,26 @@ pub async fn stream_chat_completions(
             selected.provider.base_url.clone(),
             selected.provider.name.clone(),
             selected.api_key.clone(),
+            crate::providers::adapters::effective_api_version(
+                crate::config::ProviderType::Anthropic,
+                &selected.provider.provider_config,
+            )
+            .unwrap_or(crate::providers::adapters::ANTHROPIC_DEFAULT_API_VERSION)
+            .to_string(),
             client_token.clone(),
             upstream_req,
             top_k,
             common::StreamLogContext {
+                path: path.to_string(),
                 request_payload_snapshot: Some(snapshot.clone()),
                 response_preview: None,
                 first_token_latency_ms: None,
+                param_policy_applied: param_policy_applied.clone(),
+                provider_override: provider_override.clone(),
+                prompt_truncation: prompt_truncation.clone(),
+                upstream_started_at: Some(upstream_started_at),
+                prompt_profile: prompt_profile.clone(),
+                transcript: transcript.clone(),
             },
         )
         .await
@@ -374,9 +219,16 @@ pub async fn stream_chat_completions(
             client_token.clone(),
             upstream_req,
             common::StreamLogContext {
+                path: path.to_string(),
                 request_payload_snapshot: Some(snapshot.clone()),
                 response_preview: None,
                 first_token_latency_ms: None,
+                param_policy_applied: param_policy_applied.clone(),
+                provider_override: provider_override.clone(),
+                prompt_truncation: prompt_truncation.clone(),
+                upstream_started_at: Some(upstream_started_at),
+                prompt_profile: prompt_profile.clone(),
+                transcript: transcript.clone(),
             },
         )
         .await
@@ -396,14 +248,47 @@ pub async fn stream_chat_completions(
                 upstream_req,
                 selected.provider.provider_config.clone(),
                 common::StreamLogContext {
+                    path: path.to_string(),
                     request_payload_snapshot: Some(snapshot.clone()),
                     response_preview: None,
                     first_token_latency_ms: None,
+                    param_policy_applied: param_policy_applied.clone(),
+                    provider_override: provider_override.clone(),
+                    prompt_truncation: prompt_truncation.clone(),
+                    upstream_started_at: Some(upstream_started_at),
+                    prompt_profile: prompt_profile.clone(),
+                    transcript: transcript.clone(),
                 },
             )
             .await
             .map(IntoResponse::into_response)
         }
+        crate::config::ProviderType::Mock => mock::stream_mock_chat(
+            app_state.clone(),
+            start_time,
+            billing_model.clone(),
+            requested_model.clone(),
+            upstream_req.model.clone(),
+            selected.provider.name.clone(),
+            selected.api_key.clone(),
+            client_token.clone(),
+            upstream_req,
+            selected.provider.provider_config.clone(),
+            common::StreamLogContext {
+                path: path.to_string(),
+                request_payload_snapshot: Some(snapshot.clone()),
+                response_preview: None,
+                first_token_latency_ms: None,
+                param_policy_applied: param_policy_applied.clone(),
+                provider_override: provider_override.clone(),
+                prompt_truncation: prompt_truncation.clone(),
+                upstream_started_at: Some(upstream_started_at),
+                prompt_profile: prompt_profile.clone(),
+                transcript: transcript.clone(),
+            },
+        )
+        .await
+        .map(IntoResponse::into_response),
         provider_type if provider_type.capabilities().openai_compatible => {
             openai::stream_openai_chat(
                 app_state.clone(),
@@ -414,13 +299,22 @@ pub async fn stream_chat_completions(
                 selected.provider.base_url.clone(),
                 selected.provider.name.clone(),
                 selected.api_key.clone(),
+                selected.openai_account.clone(),
                 client_token.clone(),
                 upstream_req,
                 common::StreamLogContext {
+                    path: path.to_string(),
                     request_payload_snapshot: Some(snapshot.clone()),
                     response_preview: None,
                     first_token_latency_ms: None,
+                    param_policy_applied: param_policy_applied.clone(),
+                    provider_override: provider_override.clone(),
+                    prompt_truncation: prompt_truncation.clone(),
+                    upstream_started_at: Some(upstream_started_at),
+                    prompt_profile: prompt_profile.clone(),
+                    transcript: transcript.clone(),
                 },
+                provider_type == crate::config::ProviderType::Local,
             )
             .await
             .map(IntoResponse::into_response)
@@ -443,9 +337,16 @@ pub async fn stream_chat_completions(
             upstream_req,
             selected.provider.provider_config.clone(),
             common::StreamLogContext {
+                path: path.to_string(),
                 request_payload_snapshot: Some(snapshot),
                 response_preview: None,
                 first_token_latency_ms: None,
+                param_policy_applied: param_policy_applied.clone(),
+                provider_override: provider_override.clone(),
+                prompt_truncation: prompt_truncation.clone(),
+                upstream_started_at: Some(upstream_started_at),
+                prompt_profile: prompt_profile.clone(),
+                transcript: transcript.clone(),
             },
         )
         .await
@@ -459,21 +360,66 @@ pub async fn stream_chat_completions(
             }),
         )),
     };
-
-    if let Some(tok) = client_token.as_deref()
-        && let Some(t) = app_state.token_store.get_token(tok).await?
+    if let Err(e) = &response
+        && let Some(secs) = e.retry_after()
     {
-        if let Some(max_amount) = t.max_amount
-            && t.amount_spent > max_amount
-        {
-            let _ = app_state.token_store.set_enabled(tok, false).await;
-        }
-        if let Some(max_tokens) = t.max_tokens
-            && t.total_tokens_spent > max_tokens
-        {
-            let _ = app_state.token_store.set_enabled(tok, false).await;
+        app_state.load_balancer_state.cool_down_key(
+            &selected.provider.name,
+            &selected.api_key,
+            secs,
+        );
+    }
+    // 上游响应流量按转发给调用方的分片计量（截断/剥离前）
+    let response = response.map(|r| {
+        app_state
+            .egress_meter
+            .meter_stream_response(r, selected.provider.name.clone(), egress_key)
+    });
+    let response = response.map(|r| {
+        app_state
+            .payload_sizes
+            .measure_stream_response(r, selected.provider.name.clone())
+    });
+    let response = match fault.and_then(|f| f.truncate_after_chunks) {
+        Some(chunks) => {
+            response.map(|r| crate::server::fault_injection::truncate_stream_response(r, chunks))
         }
+        None => response,
+    };
+    let mut response = if token.strip_reasoning {
+        response.map(common::strip_reasoning_stream_response)
+    } else {
+        response
+    };
+    if token.watermark_responses
+        && let Some(encoded) = Watermark::issue(&token.id, Utc::now()).encode()
+    {
+        response = response.map(|r| {
+            let mut r = watermark::watermark_stream_response(r, encoded.clone());
+            watermark::insert_header(&mut r, &encoded);
+            r
+        });
+    }
+    // 在所有改写之后捕获，记录的是调用方实际收到的内容
+    if let Some(recorder) = transcript {
+        response = response.map(|r| stream_transcript::record_stream_response(r, recorder));
     }
+    if let Some(permit) = concurrency_permit {
+        response =
+            response.map(|r| crate::server::model_concurrency::hold_during_stream(r, permit));
+    }
+    response = response.map(|r| crate::server::in_flight::hold_during_stream(r, in_flight));
+    if let (Ok(response), Some(truncation)) = (response.as_mut(), truncation.as_ref()) {
+        response.headers_mut().insert(
+            crate::server::prompt_truncation::TRUNCATED_HEADER,
+            axum::http::HeaderValue::from(truncation.removed_messages),
+        );
+    }
+    if let Ok(response) = response.as_mut() {
+        crate::providers::capabilities::insert_header(response, &dropped_features);
+    }
+
+    disable_token_if_over_limits(&app_state, token_str).await;
 
     response
 }
@@ -488,7 +434,6 @@ mod tests {
     };
     use crate::logging::{DatabaseLogger, ModelPriceUpsert};
     use crate::providers::openai::ChatCompletionRequest;
-    use crate::server::login::LoginManager;
     use crate::users::{CreateUserPayload, UserRole, UserStatus, UserStore};
     use axum::Json;
     use axum::body::to_bytes;
@@ -602,40 +547,13 @@ mod tests {
 
         let token = logger
             .create_token(CreateTokenPayload {
-                id: None,
-                user_id: None,
                 name: Some("stream-token".into()),
-                token: None,
-                allowed_models: None,
-                model_blacklist: None,
-                max_tokens: None,
-                max_amount: None,
-                enabled: true,
-                expires_at: None,
-                remark: None,
-                organization_id: None,
-                ip_whitelist: None,
-                ip_blacklist: None,
+                ..Default::default()
             })
             .await
             .unwrap();
 
-        let app_state = Arc::new(AppState {
-            config: settings,
-            load_balancer_state: Arc::new(crate::routing::LoadBalancerState::default()),
-            log_store: logger.clone(),
-            model_cache: logger.clone(),
-            providers: logger.clone(),
-            token_store: logger.clone(),
-            favorites_store: logger.clone(),
-            organizations: logger.clone(),
-            login_manager: Arc::new(LoginManager::new(logger.clone())),
-            user_store: logger.clone(),
-            refresh_token_store: logger.clone(),
-            password_reset_token_store: logger.clone(),
-            balance_store: logger.clone(),
-            subscription_store: logger.clone(),
-        });
+        let app_state = crate::server::test_harness::app_state(settings, logger.clone());
 
         (dir, app_state, token.token)
     }
@@ -664,7 +582,10 @@ mod tests {
             Json(GatewayChatCompletionRequest {
                 request: req,
                 top_k: None,
+                provider: None,
+                provider_key: None,
             }),
+            "/v1/chat/completions",
         )
         .await?;
 
@@ -772,6 +693,92 @@ mod tests {
         assert_eq!(logs[0].total_tokens, Some(11));
     }
 
+    #[tokio::test]
+    async fn token_with_streaming_disabled_rejects_stream_chat() {
+        let base_url = spawn_mock_openai_stream_server().await;
+        let (_dir, app_state, token) =
+            test_stream_app_state(&base_url, true, PricingMode::Strict).await;
+
+        let patch: crate::admin::UpdateTokenPayload =
+            serde_json::from_value(json!({"allow_streaming": false})).unwrap();
+        let updated = app_state
+            .token_store
+            .update_token(&token, patch)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(!updated.allow_streaming);
+
+        let err = invoke_stream_and_collect_text(app_state.clone(), &token, "m1")
+            .await
+            .unwrap_err();
+        assert_eq!(err.status_code(), axum::http::StatusCode::FORBIDDEN);
+        assert!(err.to_string().contains("streaming is disabled"));
+
+        let logs = app_state.log_store.get_request_logs(5, None).await.unwrap();
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].status_code, 403);
+    }
+
+    #[tokio::test]
+    async fn fault_injection_errors_and_truncates_streams_with_marked_logs() {
+        let base_url = spawn_mock_openai_stream_server().await;
+        let (_dir, app_state, token) =
+            test_stream_app_state(&base_url, true, PricingMode::Strict).await;
+
+        app_state
+            .fault_injector
+            .set(
+                serde_json::from_value(json!({
+                    "enabled": true,
+                    "percent": 100,
+                    "error_status": 429,
+                    "model": "m1"
+                }))
+                .unwrap(),
+            )
+            .unwrap();
+        let err = invoke_stream_and_collect_text(app_state.clone(), &token, "m1")
+            .await
+            .unwrap_err();
+        assert_eq!(err.status_code(), axum::http::StatusCode::TOO_MANY_REQUESTS);
+
+        app_state
+            .fault_injector
+            .set(
+                serde_json::from_value(json!({
+                    "enabled": true,
+                    "percent": 100,
+                    "truncate_after_chunks": 1
+                }))
+                .unwrap(),
+            )
+            .unwrap();
+        let body = invoke_stream_and_collect_text(app_state.clone(), &token, "m1")
+            .await
+            .unwrap();
+        let lines = stream_data_lines(&body);
+        assert_eq!(lines.len(), 1);
+        assert_ne!(lines[0], "[DONE]");
+
+        let logs = app_state
+            .log_store
+            .get_request_logs(10, None)
+            .await
+            .unwrap();
+        let injected: Vec<_> = logs
+            .iter()
+            .filter(|l| l.request_type == crate::logging::types::REQ_TYPE_CHAT_FAULT_INJECTED)
+            .collect();
+        assert_eq!(injected.len(), 2);
+        assert!(injected.iter().all(|l| {
+            l.error_message
+                .as_deref()
+                .is_some_and(|m| m.starts_with("[fault-injection]"))
+        }));
+        assert_eq!(app_state.fault_injector.injected_total(), 2);
+    }
+
     #[tokio::test]
     async fn user_balance_depleted_rejects_stream_and_disables_tokens() {
         let dir = tempdir().unwrap();
@@ -806,22 +813,7 @@ mod tests {
             .await
             .unwrap();
 
-        let app_state = Arc::new(AppState {
-            config: settings,
-            load_balancer_state: Arc::new(crate::routing::LoadBalancerState::default()),
-            log_store: logger.clone(),
-            model_cache: logger.clone(),
-            providers: logger.clone(),
-            token_store: logger.clone(),
-            favorites_store: logger.clone(),
-            organizations: logger.clone(),
-            login_manager: Arc::new(LoginManager::new(logger.clone())),
-            user_store: logger.clone(),
-            refresh_token_store: logger.clone(),
-            password_reset_token_store: logger.clone(),
-            balance_store: logger.clone(),
-            subscription_store: logger.clone(),
-        });
+        let app_state = crate::server::test_harness::app_state(settings, logger.clone());
 
         let user = logger
             .create_user(CreateUserPayload {
@@ -840,40 +832,18 @@ mod tests {
 
         let t1 = logger
             .create_token(CreateTokenPayload {
-                id: None,
                 user_id: Some(user.id.clone()),
                 name: Some("t1".into()),
-                token: None,
-                allowed_models: None,
-                model_blacklist: None,
-                max_tokens: None,
-                max_amount: None,
-                enabled: true,
-                expires_at: None,
-                remark: None,
-                organization_id: None,
-                ip_whitelist: None,
-                ip_blacklist: None,
+                ..Default::default()
             })
             .await
             .unwrap();
 
         let _t2 = logger
             .create_token(CreateTokenPayload {
-                id: None,
                 user_id: Some(user.id.clone()),
                 name: Some("t2".into()),
-                token: None,
-                allowed_models: None,
-                model_blacklist: None,
-                max_tokens: None,
-                max_amount: None,
-                enabled: true,
-                expires_at: None,
-                remark: None,
-                organization_id: None,
-                ip_whitelist: None,
-                ip_blacklist: None,
+                ..Default::default()
             })
             .await
             .unwrap();
@@ -897,7 +867,10 @@ mod tests {
             Json(GatewayChatCompletionRequest {
                 request: req,
                 top_k: None,
+                provider: None,
+                provider_key: None,
             }),
+            "/v1/chat/completions",
         )
         .await
         .unwrap_err();
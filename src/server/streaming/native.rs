@@ -22,7 +22,7 @@ use crate::providers::{
         classify_aws_claude_error, classify_azure_error, classify_cohere_error,
         classify_gemini_error, classify_vertex_error, cohere_error_message, cohere_finish_reason,
         gateway_error_from_normalized, gemini_error_message, gemini_finish_reason,
-        gemini_generate_content_url, vertex_access_token, vertex_error_message,
+        gemini_generate_content_url, retry_after_secs, vertex_access_token, vertex_error_message,
         vertex_stream_generate_content_url,
     },
     openai::{ChatCompletionRequest, Usage},
@@ -169,17 +169,33 @@ impl OpenAiSseEmitter {
 }
 
 #[derive(Default)]
-struct SseMessageDecoder {
+pub(super) struct SseMessageDecoder {
     buffer: String,
+    /// push_bytes 尚未遇到换行的字节；多字节字符可能跨网络分片，整行到齐后再解码
+    pending: Vec<u8>,
 }
 
-struct SseMessage {
-    event: String,
-    data: String,
+pub(super) struct SseMessage {
+    pub(super) event: String,
+    pub(super) data: String,
 }
 
 impl SseMessageDecoder {
-    fn push(&mut self, chunk: &str) -> Vec<SseMessage> {
+    /// 按原始字节追加：只解码到最后一个换行为止（换行是 ASCII，必然落在字符边界上）
+    pub(super) fn push_bytes(&mut self, chunk: &[u8]) -> Vec<SseMessage> {
+        self.pending.extend_from_slice(chunk);
+        let Some(end) = self
+            .pending
+            .iter()
+            .rposition(|b| matches!(b, b'\n' | b'\r'))
+        else {
+            return Vec::new();
+        };
+        let lines: Vec<u8> = self.pending.drain(..=end).collect();
+        self.push(&String::from_utf8_lossy(&lines))
+    }
+
+    pub(super) fn push(&mut self, chunk: &str) -> Vec<SseMessage> {
         self.buffer.push_str(chunk);
         self.buffer = self.buffer.replace("\r\n", "\n").replace('\r', "\n");
 
@@ -823,13 +839,13 @@ pub async fn stream_native_chat(
             let base = Url::parse(&base_url).map_err(|err| {
                 GatewayError::Config(format!("Azure OpenAI base_url 无效：{err}"))
             })?;
-            let url = azure_openai_chat_completions_url(&base, &provider_config).map_err(
-                |(_, detail)| {
-                    GatewayError::Config(
-                        detail.unwrap_or_else(|| "Azure OpenAI 配置不完整。".into()),
-                    )
-                },
-            )?;
+            let url =
+                azure_openai_chat_completions_url(&base, &provider_config, &upstream_req.model)
+                    .map_err(|(_, detail)| {
+                        GatewayError::Config(
+                            detail.unwrap_or_else(|| "Azure OpenAI 配置不完整。".into()),
+                        )
+                    })?;
             let mut payload = serde_json::to_value(&upstream_req)?;
             if let Some(object) = payload.as_object_mut() {
                 object.remove("model");
@@ -845,12 +861,14 @@ pub async fn stream_native_chat(
                 .await?;
             if !response.status().is_success() {
                 let status = response.status();
+                let retry_after = retry_after_secs(response.headers());
                 let bytes = response.bytes().await?;
                 let (error_type, detail) = classify_azure_error(status, &bytes);
                 return Err(gateway_error_from_normalized(
                     &error_type,
                     detail.unwrap_or_else(|| azure_error_message(status, &bytes)),
-                ));
+                )
+                .with_retry_after(retry_after));
             }
             response
         }
@@ -878,12 +896,14 @@ pub async fn stream_native_chat(
                 .await?;
             if !response.status().is_success() {
                 let status = response.status();
+                let retry_after = retry_after_secs(response.headers());
                 let bytes = response.bytes().await?;
                 let (error_type, detail) = classify_gemini_error(status, &bytes);
                 return Err(gateway_error_from_normalized(
                     &error_type,
                     detail.unwrap_or_else(|| gemini_error_message(status, &bytes)),
-                ));
+                )
+                .with_retry_after(retry_after));
             }
             response
         }
@@ -917,12 +937,14 @@ pub async fn stream_native_chat(
                 .await?;
             if !response.status().is_success() {
                 let status = response.status();
+                let retry_after = retry_after_secs(response.headers());
                 let bytes = response.bytes().await?;
                 let (error_type, detail) = classify_vertex_error(status, &bytes);
                 return Err(gateway_error_from_normalized(
                     &error_type,
                     detail.unwrap_or_else(|| vertex_error_message(status, &bytes)),
-                ));
+                )
+                .with_retry_after(retry_after));
             }
             response
         }
@@ -942,12 +964,14 @@ pub async fn stream_native_chat(
                 .await?;
             if !response.status().is_success() {
                 let status = response.status();
+                let retry_after = retry_after_secs(response.headers());
                 let bytes = response.bytes().await?;
                 let (error_type, detail) = classify_cohere_error(status, &bytes);
                 return Err(gateway_error_from_normalized(
                     &error_type,
                     detail.unwrap_or_else(|| cohere_error_message(status, &bytes)),
-                ));
+                )
+                .with_retry_after(retry_after));
             }
             response
         }
@@ -979,12 +1003,14 @@ pub async fn stream_native_chat(
                 .await?;
             if !response.status().is_success() {
                 let status = response.status();
+                let retry_after = retry_after_secs(response.headers());
                 let bytes = response.bytes().await?;
                 let (error_type, detail) = baidu_error_response(status, &bytes);
                 return Err(gateway_error_from_normalized(
                     &error_type,
                     detail.unwrap_or_else(|| "百度文心旧版流式请求失败。".into()),
-                ));
+                )
+                .with_retry_after(retry_after));
             }
             response
         }
@@ -1036,12 +1062,14 @@ pub async fn stream_native_chat(
             let response = request.send().await?;
             if !response.status().is_success() {
                 let status = response.status();
+                let retry_after = retry_after_secs(response.headers());
                 let bytes = response.bytes().await?;
                 let (error_type, detail) = classify_aws_claude_error(status, &bytes);
                 return Err(gateway_error_from_normalized(
                     &error_type,
                     detail.unwrap_or_else(|| aws_claude_error_message(status, &bytes)),
-                ));
+                )
+                .with_retry_after(retry_after));
             }
             response
         }
@@ -1065,7 +1093,8 @@ pub async fn stream_native_chat(
     let client_token_for_task = client_token.clone();
     let response_model = effective_model.clone();
 
-    tokio::spawn(async move {
+    let tasks = app_state.task_registry.clone();
+    tasks.spawn_fallible("stream_native", async move {
         let mut log_context = log_context;
         let outcome: Result<(), String> = match provider_type {
             ProviderType::AzureOpenAI => {
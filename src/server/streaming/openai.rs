@@ -1,6 +1,9 @@
 use std::{
     convert::Infallible,
-    sync::{Arc, Mutex},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
 };
 
 use axum::response::{IntoResponse, Response, Sse};
@@ -11,6 +14,9 @@ use async_openai::types::{ChatCompletionStreamOptions, CreateChatCompletionStrea
 use serde_json::Value;
 
 use crate::error::GatewayError;
+use crate::providers::adapters::retry_after_secs;
+use crate::providers::openai::client::bearer_auth;
+use crate::routing::OpenAIAccountHeaders;
 
 fn join_openai_compat_endpoint(base_url: &str, path: &str) -> String {
     let base = base_url.trim_end_matches('/');
@@ -28,6 +34,7 @@ fn join_openai_compat_endpoint(base_url: &str, path: &str) -> String {
 }
 use crate::providers::openai::{ChatCompletionRequest, Usage};
 use crate::server::AppState;
+use crate::server::chat_plan::{estimate_prompt_tokens, estimated_usage};
 
 use crate::server::util::mask_key;
 
@@ -35,6 +42,7 @@ use crate::server::util::mask_key;
 /// - 将请求改写为 SSE 流式接口并启用 usage 回传
 /// - 持续消费 EventSource 事件，解析 usage 并通过 common 模块记录日志与计费
 /// - 将原始 SSE 数据透传给网关调用方（含 [DONE] 事件与错误信息）
+/// - estimate_missing_usage：上游始终不返回 usage 时（本地运行时）按请求与输出文本估算
 #[allow(clippy::too_many_arguments)]
 pub async fn stream_openai_chat(
     app_state: Arc<AppState>,
@@ -45,9 +53,11 @@ pub async fn stream_openai_chat(
     base_url: String,
     provider_name: String,
     api_key: String,
+    openai_account: OpenAIAccountHeaders,
     client_token: Option<String>,
     mut upstream_req: ChatCompletionRequest,
     log_context: super::common::StreamLogContext,
+    estimate_missing_usage: bool,
 ) -> Result<Response, GatewayError> {
     let url = join_openai_compat_endpoint(&base_url, "chat/completions");
     let client = crate::http_client::client_for_url(&url)?;
@@ -57,14 +67,17 @@ pub async fn stream_openai_chat(
         include_usage: true,
     });
 
-    let request_builder = client
-        .post(&url)
-        .header("Authorization", format!("Bearer {}", api_key))
-        .header("Content-Type", "application/json")
-        .header("Accept", "text/event-stream")
+    let request_builder = openai_account
+        .apply(
+            bearer_auth(client.post(&url), &api_key)
+                .header("Content-Type", "application/json")
+                .header("Accept", "text/event-stream"),
+        )
         .json(&upstream_req);
 
     let usage_cell: Arc<Mutex<Option<Usage>>> = Arc::new(Mutex::new(None));
+    let prompt_estimate = estimate_missing_usage.then(|| estimate_prompt_tokens(&upstream_req));
+    let completion_chars = Arc::new(AtomicUsize::new(0));
     let preview_cell: Arc<Mutex<String>> = Arc::new(Mutex::new(String::new()));
     let logged_flag = Arc::new(std::sync::atomic::AtomicBool::new(false));
     // 统计与日志关联使用稳定脱敏值，避免明文泄露
@@ -76,8 +89,16 @@ pub async fn stream_openai_chat(
     let app_state_clone = app_state.clone();
     let client_token_for_outer = client_token.clone();
     let _client_token_outer = client_token.clone();
-    tokio::spawn(async move {
+    let tasks = app_state.task_registry.clone();
+    tasks.spawn("stream_openai", async move {
         let mut log_context = log_context;
+        let mut citations = crate::server::citations::StreamCitations::default();
+        let usage_snapshot = || {
+            usage_cell_for_task.lock().unwrap().clone().or_else(|| {
+                prompt_estimate
+                    .map(|prompt| estimated_usage(prompt, completion_chars.load(Ordering::Relaxed)))
+            })
+        };
         let mut es = match request_builder.eventsource() {
             Ok(es) => es,
             Err(e) => {
@@ -119,7 +140,7 @@ pub async fn stream_openai_chat(
                 Ok(Event::Message(m)) => {
                     if m.data.trim() == "[DONE]" {
                         if !logged_flag.swap(true, std::sync::atomic::Ordering::SeqCst) {
-                            let usage_snapshot = usage_cell_for_task.lock().unwrap().clone();
+                            let usage_snapshot = usage_snapshot();
                             let log_context_for_done = super::common::context_with_stream_preview(
                                 &log_context,
                                 &preview_cell_for_task,
@@ -153,10 +174,7 @@ pub async fn stream_openai_chat(
                         break;
                     }
 
-                    super::common::record_first_token_latency(
-                        &mut log_context,
-                        start_time,
-                    );
+                    super::common::record_first_token_latency(&mut log_context, start_time);
 
                     // Primary: try typed parse
                     let mut captured = false;
@@ -168,21 +186,42 @@ pub async fn stream_openai_chat(
                         captured = true;
                     }
                     // Fallback: Value parse to extract usage (tolerate vendor extensions)
-                    if let Ok(v) = serde_json::from_str::<Value>(&m.data) {
+                    let mut data = m.data;
+                    if let Ok(mut v) = serde_json::from_str::<Value>(&data) {
                         if !captured && let Some(usage) = super::common::parse_usage_from_value(&v)
                         {
                             *usage_cell_for_task.lock().unwrap() = Some(usage);
                         }
+                        let fragment =
+                            crate::server::response_text::stream_chunk_preview_fragment(&v);
+                        if let Some(fragment) = &fragment {
+                            completion_chars.fetch_add(fragment.chars().count(), Ordering::Relaxed);
+                        }
                         super::common::append_response_preview_fragment(
                             &preview_cell_for_task,
-                            crate::server::response_text::stream_chunk_preview_fragment(&v),
+                            fragment,
                         );
+                        // Perplexity 等在 chunk 顶层携带的来源归一为 delta.annotations；未改写时原样透传
+                        if citations.normalize_chunk(&mut v) {
+                            data = v.to_string();
+                        }
                     }
 
-                    let _ = tx.send(axum::response::sse::Event::default().data(m.data));
+                    let _ = tx.send(axum::response::sse::Event::default().data(data));
                 }
                 Err(e) => {
                     tracing::error!("Stream error: {}", e);
+                    // SSE 已开始返回，无法再透传响应头；仅按上游 Retry-After 冷却该密钥
+                    if let reqwest_eventsource::Error::InvalidStatusCode(status, response) = &e
+                        && *status == reqwest::StatusCode::TOO_MANY_REQUESTS
+                        && let Some(secs) = retry_after_secs(response.headers())
+                    {
+                        app_state_clone.load_balancer_state.cool_down_key(
+                            &provider_name,
+                            &api_key,
+                            secs,
+                        );
+                    }
                     let error_msg = e.to_string();
                     if !logged_flag.swap(true, std::sync::atomic::Ordering::SeqCst) {
                         let log_context_for_stream_error =
@@ -225,7 +264,7 @@ pub async fn stream_openai_chat(
 
         // Safety net: log if stream closed without [DONE]
         if !logged_flag.load(std::sync::atomic::Ordering::SeqCst) {
-            let usage_snapshot = usage_cell_for_task.lock().unwrap().clone();
+            let usage_snapshot = usage_snapshot();
             let log_context_for_fallback =
                 super::common::context_with_stream_preview(&log_context, &preview_cell_for_task);
             let ct_fallback = client_token_for_outer.clone();
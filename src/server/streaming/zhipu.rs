@@ -38,8 +38,8 @@ pub async fn stream_zhipu_chat(
         base_url.trim_end_matches('/')
     );
 
-    // 适配请求内容（base64 前缀清洗、top_p 修正）
-    let adapted = crate::providers::zhipu::adapt_openai_request_for_zhipu(upstream_req);
+    // 适配请求内容（base64 前缀清洗、top_p 修正、tools / web_search / do_sample 映射）
+    let adapted = crate::providers::zhipu::build_zhipu_request_body(upstream_req)?;
 
     let request_builder = client
         .post(&url)
@@ -59,8 +59,10 @@ pub async fn stream_zhipu_chat(
     let preview_cell_for_task = preview_cell.clone();
     let app_state_clone = app_state.clone();
     let client_token_for_outer = client_token.clone();
-    tokio::spawn(async move {
+    let tasks = app_state.task_registry.clone();
+    tasks.spawn("stream_zhipu", async move {
         let mut log_context = log_context;
+        let mut citations = crate::server::citations::StreamCitations::default();
         let mut es = match request_builder.eventsource() {
             Ok(es) => es,
             Err(e) => {
@@ -136,13 +138,11 @@ pub async fn stream_zhipu_chat(
                         break;
                     }
 
-                    super::common::record_first_token_latency(
-                        &mut log_context,
-                        start_time,
-                    );
+                    super::common::record_first_token_latency(&mut log_context, start_time);
 
-                    // 捕获 usage（Zhipu：宽松提取）
-                    if let Ok(v) = serde_json::from_str::<Value>(&m.data) {
+                    // 捕获 usage（Zhipu：宽松提取），并将工具调用 delta 转为 OpenAI 兼容格式
+                    let mut data = m.data;
+                    if let Ok(mut v) = serde_json::from_str::<Value>(&data) {
                         if let Some(usage) = super::common::parse_usage_from_value(&v) {
                             *usage_cell_for_task.lock().unwrap() = Some(usage);
                         }
@@ -150,9 +150,12 @@ pub async fn stream_zhipu_chat(
                             &preview_cell_for_task,
                             crate::server::response_text::stream_chunk_preview_fragment(&v),
                         );
+                        crate::providers::zhipu::normalize_zhipu_response(&mut v);
+                        citations.normalize_chunk(&mut v);
+                        data = v.to_string();
                     }
 
-                    let _ = tx.send(axum::response::sse::Event::default().data(m.data));
+                    let _ = tx.send(axum::response::sse::Event::default().data(data));
                 }
                 Err(e) => {
                     tracing::error!("Stream error: {}", e);
@@ -0,0 +1,265 @@
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tokio::sync::watch;
+use tokio::task::AbortHandle;
+
+/// 保留最近失败/被取消的任务条数
+const RECENT_CAPACITY: usize = 50;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskInfo {
+    pub id: u64,
+    pub name: String,
+    pub started_at: DateTime<Utc>,
+    pub uptime_secs: i64,
+    pub last_error: Option<String>,
+    pub last_error_at: Option<DateTime<Utc>>,
+    /// running / failed / cancelled
+    pub status: &'static str,
+}
+
+struct TaskEntry {
+    name: String,
+    started_at: DateTime<Utc>,
+    last_error: Option<(String, DateTime<Utc>)>,
+    abort: Option<AbortHandle>,
+}
+
+impl TaskEntry {
+    fn info(&self, id: u64, now: DateTime<Utc>, status: &'static str) -> TaskInfo {
+        TaskInfo {
+            id,
+            name: self.name.clone(),
+            started_at: self.started_at,
+            uptime_secs: (now - self.started_at).num_seconds().max(0),
+            last_error: self.last_error.as_ref().map(|(e, _)| e.clone()),
+            last_error_at: self.last_error.as_ref().map(|(_, at)| *at),
+            status,
+        }
+    }
+}
+
+#[derive(Default)]
+struct RegistryState {
+    running: HashMap<u64, TaskEntry>,
+    recent: VecDeque<TaskInfo>,
+}
+
+/// 后台任务登记表：为 tokio::spawn 的任务命名、记录运行时长与最近错误，
+/// 并通过共享的关闭信号支持优雅退出
+pub struct TaskRegistry {
+    next_id: AtomicU64,
+    state: Mutex<RegistryState>,
+    shutdown: watch::Sender<bool>,
+}
+
+impl Default for TaskRegistry {
+    fn default() -> Self {
+        Self {
+            next_id: AtomicU64::new(1),
+            state: Mutex::new(RegistryState::default()),
+            shutdown: watch::channel(false).0,
+        }
+    }
+}
+
+/// 传入长期任务的上下文：用于上报错误与监听关闭信号
+pub struct TaskContext {
+    id: u64,
+    registry: Arc<TaskRegistry>,
+    shutdown: watch::Receiver<bool>,
+}
+
+impl TaskContext {
+    pub fn report_error(&self, error: impl ToString) {
+        self.registry.report_error(self.id, error.to_string());
+    }
+
+    /// 收到关闭信号时完成
+    pub async fn cancelled(&mut self) {
+        let _ = self.shutdown.wait_for(|stop| *stop).await;
+    }
+}
+
+static REGISTRY: OnceLock<Arc<TaskRegistry>> = OnceLock::new();
+
+/// 进程级任务登记表（AppState 中持有的是同一实例）
+pub fn task_registry() -> Arc<TaskRegistry> {
+    REGISTRY
+        .get_or_init(|| Arc::new(TaskRegistry::default()))
+        .clone()
+}
+
+impl TaskRegistry {
+    fn lock(&self) -> std::sync::MutexGuard<'_, RegistryState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 登记并启动一个一次性任务，完成后自动移除
+    pub fn spawn<F>(self: &Arc<Self>, name: impl Into<String>, fut: F) -> u64
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        self.spawn_with(name, move |_ctx| fut)
+    }
+
+    /// 同 spawn，任务返回 Err 时记录为最近错误
+    pub fn spawn_fallible<F, E>(self: &Arc<Self>, name: impl Into<String>, fut: F) -> u64
+    where
+        F: Future<Output = Result<(), E>> + Send + 'static,
+        E: std::fmt::Display,
+    {
+        self.spawn_with(name, move |ctx| async move {
+            if let Err(e) = fut.await {
+                ctx.report_error(e);
+            }
+        })
+    }
+
+    /// 登记并启动一个需要上报错误或响应关闭信号的任务
+    pub fn spawn_with<F, Fut>(self: &Arc<Self>, name: impl Into<String>, f: F) -> u64
+    where
+        F: FnOnce(TaskContext) -> Fut,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let ctx = TaskContext {
+            id,
+            registry: self.clone(),
+            shutdown: self.shutdown.subscribe(),
+        };
+        let fut = f(ctx);
+        let registry = self.clone();
+        // 持锁完成登记与 spawn，保证任务结束时的移除一定发生在登记之后
+        let mut state = self.lock();
+        state.running.insert(
+            id,
+            TaskEntry {
+                name: name.into(),
+                started_at: Utc::now(),
+                last_error: None,
+                abort: None,
+            },
+        );
+        let handle = tokio::spawn(async move {
+            fut.await;
+            registry.finish(id, None);
+        });
+        if let Some(entry) = state.running.get_mut(&id) {
+            entry.abort = Some(handle.abort_handle());
+        }
+        id
+    }
+
+    fn report_error(&self, id: u64, error: String) {
+        if let Some(entry) = self.lock().running.get_mut(&id) {
+            entry.last_error = Some((error, Utc::now()));
+        }
+    }
+
+    fn finish(&self, id: u64, status: Option<&'static str>) {
+        let mut state = self.lock();
+        let Some(entry) = state.running.remove(&id) else {
+            return;
+        };
+        let status = status.or_else(|| entry.last_error.as_ref().map(|_| "failed"));
+        if let Some(status) = status {
+            let info = entry.info(id, Utc::now(), status);
+            if state.recent.len() >= RECENT_CAPACITY {
+                state.recent.pop_front();
+            }
+            state.recent.push_back(info);
+        }
+    }
+
+    /// 运行中的任务（按 id 升序）
+    pub fn list_running(&self) -> Vec<TaskInfo> {
+        let now = Utc::now();
+        let state = self.lock();
+        let mut out: Vec<TaskInfo> = state
+            .running
+            .iter()
+            .map(|(id, entry)| entry.info(*id, now, "running"))
+            .collect();
+        out.sort_by_key(|t| t.id);
+        out
+    }
+
+    /// 最近失败或被取消的任务（最新在前）
+    pub fn list_recent(&self) -> Vec<TaskInfo> {
+        self.lock().recent.iter().rev().cloned().collect()
+    }
+
+    /// 取消指定任务；任务不存在时返回 false
+    pub fn cancel(&self, id: u64) -> bool {
+        let abort = self
+            .lock()
+            .running
+            .get(&id)
+            .and_then(|entry| entry.abort.clone());
+        match abort {
+            Some(abort) => {
+                abort.abort();
+                self.finish(id, Some("cancelled"));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 广播关闭信号，等待任务自行退出；超时后强制取消剩余任务
+    pub async fn shutdown(&self, grace: Duration) {
+        let _ = self.shutdown.send(true);
+        let deadline = tokio::time::Instant::now() + grace;
+        while !self.lock().running.is_empty() && tokio::time::Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        let remaining: Vec<u64> = self.lock().running.keys().copied().collect();
+        for id in remaining {
+            self.cancel(id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn tracks_errors_cancellation_and_shutdown() {
+        let registry = Arc::new(TaskRegistry::default());
+
+        let done = registry.spawn("one_shot", async {});
+        let failing = registry.spawn_with("failing", |ctx| async move {
+            ctx.report_error("boom");
+        });
+        let stuck = registry.spawn("stuck", std::future::pending());
+        let service = registry.spawn_with("service", |mut ctx| async move {
+            ctx.cancelled().await;
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let running: Vec<u64> = registry.list_running().iter().map(|t| t.id).collect();
+        assert_eq!(running, vec![stuck, service]);
+        let recent = registry.list_recent();
+        assert_eq!(recent.len(), 1);
+        assert_eq!(recent[0].id, failing);
+        assert_eq!(recent[0].status, "failed");
+        assert_eq!(recent[0].last_error.as_deref(), Some("boom"));
+        assert!(!registry.cancel(done));
+
+        assert!(registry.cancel(stuck));
+        assert_eq!(registry.list_recent()[0].status, "cancelled");
+
+        registry.shutdown(Duration::from_secs(1)).await;
+        assert!(registry.list_running().is_empty());
+        // service 响应关闭信号正常退出，不计入失败
+        assert!(registry.list_recent().iter().all(|t| t.id != service));
+    }
+}
@@ -0,0 +1,308 @@
+//! 端到端测试工具：以 SQLite（临时目录）启动完整的 axum 应用（与 create_app 相同的路由与中间件栈），
+//! 配合 wiremock 模拟的 OpenAI 兼容上游，通过 `gateway::client` 以真实 HTTP 调用网关。
+//!
+//! 不启动后台任务，也不生成管理员密钥文件；管理面使用预置的 TUI 会话令牌。
+
+use std::sync::Arc;
+
+use chrono::{Duration, Utc};
+use gateway::client::{ClientToken, CreateToken, GatewayClient};
+use serde_json::{Value, json};
+use tempfile::TempDir;
+use tokio::net::TcpListener;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+use crate::config::settings::{
+    BalanceStrategy, DEFAULT_PROVIDER_COLLECTION, LoadBalancing, LoggingConfig, Provider,
+    ProviderConfig, ProviderType, ServerConfig, Settings,
+};
+use crate::logging::{DatabaseLogger, ModelPriceUpsert};
+use crate::server::AppState;
+use crate::server::app_state_builder::{AppStateBuilder, Stores};
+use crate::server::storage_traits::{AdminPublicKeyRecord, LoginStore, TuiSessionRecord};
+
+pub const ADMIN_TOKEN: &str = "e2e-admin-session";
+pub const UPSTREAM_KEY: &str = "mock-upstream-key";
+
+/// 待注册的供应商（默认 OpenAI 兼容、单个密钥）
+pub struct TestProvider {
+    pub name: String,
+    pub api_type: ProviderType,
+    pub base_url: String,
+    pub provider_config: ProviderConfig,
+}
+
+impl TestProvider {
+    /// `upstream` 为 wiremock 服务，上游路径为 `/v1/chat/completions`
+    pub fn openai(name: &str, upstream: &MockServer) -> Self {
+        Self {
+            name: name.into(),
+            api_type: ProviderType::OpenAI,
+            base_url: format!("{}/v1", upstream.uri()),
+            provider_config: ProviderConfig::default(),
+        }
+    }
+
+    /// Google Gemini 原生协议，上游路径为 `/v1beta/models/{model}:generateContent`
+    pub fn gemini(name: &str, upstream: &MockServer) -> Self {
+        Self {
+            name: name.into(),
+            api_type: ProviderType::GoogleGemini,
+            base_url: upstream.uri(),
+            provider_config: ProviderConfig::default(),
+        }
+    }
+
+    /// Anthropic Messages 协议，上游路径为 `/v1/messages`
+    pub fn anthropic(name: &str, upstream: &MockServer) -> Self {
+        Self {
+            name: name.into(),
+            api_type: ProviderType::Anthropic,
+            base_url: upstream.uri(),
+            provider_config: ProviderConfig::default(),
+        }
+    }
+
+    /// 本地运行时（Ollama 等），不登记上游密钥
+    pub fn local(name: &str, upstream: &MockServer) -> Self {
+        Self {
+            name: name.into(),
+            api_type: ProviderType::Local,
+            base_url: format!("{}/v1", upstream.uri()),
+            provider_config: ProviderConfig::default(),
+        }
+    }
+}
+
+type Configure = Box<dyn FnOnce(&mut Settings)>;
+
+#[derive(Default)]
+pub struct TestGatewayBuilder {
+    providers: Vec<TestProvider>,
+    /// (provider, model, prompt_price, completion_price)，价格单位为每百万 token
+    prices: Vec<(String, String, f64, f64)>,
+    configure: Option<Configure>,
+}
+
+impl TestGatewayBuilder {
+    pub fn provider(mut self, provider: TestProvider) -> Self {
+        self.providers.push(provider);
+        self
+    }
+
+    pub fn price(mut self, provider: &str, model: &str, prompt: f64, completion: f64) -> Self {
+        self.prices
+            .push((provider.into(), model.into(), prompt, completion));
+        self
+    }
+
+    /// 调整启动配置（负载均衡策略、定价模式等）
+    pub fn configure(mut self, f: impl FnOnce(&mut Settings) + 'static) -> Self {
+        self.configure = Some(Box::new(f));
+        self
+    }
+
+    pub async fn start(self) -> TestGateway {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("gateway.db");
+        let mut settings = Settings {
+            load_balancing: LoadBalancing {
+                strategy: BalanceStrategy::FirstAvailable,
+            },
+            server: ServerConfig::default(),
+            logging: LoggingConfig {
+                database_path: db_path.to_string_lossy().to_string(),
+                ..Default::default()
+            },
+        };
+        if let Some(configure) = self.configure {
+            configure(&mut settings);
+        }
+        let logger = Arc::new(
+            DatabaseLogger::new(&settings.logging.database_path)
+                .await
+                .unwrap(),
+        );
+
+        for p in self.providers {
+            let keyless = p.api_type.allows_keyless();
+            logger
+                .insert_provider(&Provider {
+                    name: p.name.clone(),
+                    display_name: None,
+                    collection: DEFAULT_PROVIDER_COLLECTION.into(),
+                    api_type: p.api_type,
+                    api_type_raw: None,
+                    base_url: p.base_url,
+                    api_keys: Vec::new(),
+                    models_endpoint: None,
+                    provider_config: p.provider_config,
+                    enabled: true,
+                    created_at: None,
+                    updated_at: None,
+                })
+                .await
+                .unwrap();
+            if !keyless {
+                logger
+                    .add_provider_key(&p.name, UPSTREAM_KEY, &settings.logging.key_log_strategy)
+                    .await
+                    .unwrap();
+            }
+        }
+        for (provider, model, prompt, completion) in self.prices {
+            logger
+                .upsert_model_price(ModelPriceUpsert::manual(
+                    &provider,
+                    &model,
+                    prompt,
+                    completion,
+                    Some("USD".into()),
+                    None,
+                ))
+                .await
+                .unwrap();
+        }
+
+        let now = Utc::now();
+        logger
+            .insert_admin_key(&AdminPublicKeyRecord {
+                fingerprint: "e2e-fp".into(),
+                public_key: vec![0u8; ed25519_dalek::PUBLIC_KEY_LENGTH],
+                comment: Some("e2e".into()),
+                enabled: true,
+                created_at: now,
+                last_used_at: None,
+                expires_at: None,
+            })
+            .await
+            .unwrap();
+        logger
+            .create_tui_session(&TuiSessionRecord {
+                session_id: ADMIN_TOKEN.into(),
+                fingerprint: "e2e-fp".into(),
+                issued_at: now,
+                expires_at: now + Duration::hours(1),
+                revoked: false,
+                last_code_at: None,
+            })
+            .await
+            .unwrap();
+
+        let state = app_state(settings, logger);
+
+        let app = crate::server::build_router(state.clone());
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+        TestGateway {
+            state,
+            base_url: format!("http://{}", addr),
+            _dir: dir,
+        }
+    }
+}
+
+/// 以单个 SQLite 实例承载全部存储的 AppState（经 [`AppStateBuilder::build_detached`] 组装，不启动后台任务）
+pub fn app_state(config: Settings, logger: Arc<DatabaseLogger>) -> Arc<AppState> {
+    AppStateBuilder::new(config)
+        .stores(Stores::uniform(logger.clone(), logger))
+        .build_detached()
+}
+
+/// 运行中的网关；drop 时删除临时数据库
+pub struct TestGateway {
+    pub state: Arc<AppState>,
+    pub base_url: String,
+    _dir: TempDir,
+}
+
+impl TestGateway {
+    pub fn builder() -> TestGatewayBuilder {
+        TestGatewayBuilder::default()
+    }
+
+    /// 使用指定 bearer 凭据的客户端
+    pub fn client(&self, token: &str) -> GatewayClient {
+        GatewayClient::new(&self.base_url)
+            .unwrap()
+            .with_token(token)
+    }
+
+    /// 以管理员身份访问管理面
+    pub fn admin(&self) -> GatewayClient {
+        self.client(ADMIN_TOKEN)
+    }
+
+    /// 经管理接口创建客户端令牌
+    pub async fn create_token(&self, payload: CreateToken) -> ClientToken {
+        self.admin().create_token(&payload).await.unwrap()
+    }
+}
+
+/// OpenAI 兼容的非流式补全响应
+pub fn completion_body(
+    model: &str,
+    content: &str,
+    prompt_tokens: u32,
+    completion_tokens: u32,
+) -> Value {
+    json!({
+        "id": "chatcmpl-e2e",
+        "object": "chat.completion",
+        "created": 1_700_000_000,
+        "model": model,
+        "choices": [{
+            "index": 0,
+            "message": {"role": "assistant", "content": content},
+            "finish_reason": "stop"
+        }],
+        "usage": {
+            "prompt_tokens": prompt_tokens,
+            "completion_tokens": completion_tokens,
+            "total_tokens": prompt_tokens + completion_tokens
+        }
+    })
+}
+
+/// OpenAI 兼容的 SSE 响应体：每段文本一个 chunk，最后附带 usage 与 `[DONE]`
+pub fn stream_body(model: &str, parts: &[&str]) -> String {
+    let mut body = String::new();
+    for (i, part) in parts.iter().enumerate() {
+        let finish = (i + 1 == parts.len()).then_some("stop");
+        let chunk = json!({
+            "id": "chatcmpl-e2e",
+            "object": "chat.completion.chunk",
+            "created": 1_700_000_000,
+            "model": model,
+            "choices": [{"index": 0, "delta": {"content": part}, "finish_reason": finish}]
+        });
+        body.push_str(&format!("data: {}\n\n", chunk));
+    }
+    let usage = json!({
+        "id": "chatcmpl-e2e",
+        "object": "chat.completion.chunk",
+        "created": 1_700_000_000,
+        "model": model,
+        "choices": [],
+        "usage": {"prompt_tokens": 5, "completion_tokens": parts.len(), "total_tokens": 5 + parts.len()}
+    });
+    body.push_str(&format!("data: {}\n\ndata: [DONE]\n\n", usage));
+    body
+}
+
+/// 挂载上游聊天接口的固定响应
+pub async fn mock_chat(upstream: &MockServer, response: ResponseTemplate) {
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(response)
+        .mount(upstream)
+        .await;
+}
+
+pub fn sse(body: String) -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_raw(body, "text/event-stream")
+}
@@ -0,0 +1,539 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+use chrono::{Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::admin::{ClientToken, CreateTokenPayload, TokenStore};
+use crate::error::GatewayError;
+use crate::logging::time::{parse_beijing_string, to_beijing_string};
+use crate::server::AppState;
+use crate::server::request_signing::resolved_client_token;
+use crate::server::token_model_limits::{enforce_model_allowed_for_token, normalize_model_list};
+
+pub const EXCHANGE_DEFAULT_TTL_SECS: i64 = 15 * 60;
+pub const EXCHANGE_MIN_TTL_SECS: i64 = 60;
+pub const EXCHANGE_MAX_TTL_SECS: i64 = 60 * 60;
+/// 未指定 max_amount 时子令牌的默认额度
+pub const EXCHANGE_DEFAULT_MAX_AMOUNT: f64 = 1.0;
+/// 每个父令牌每分钟最多交换的子令牌数
+pub const EXCHANGES_PER_MINUTE: usize = 30;
+/// 交换签发的子令牌过期后保留一天（便于排查），之后由定时任务删除
+const EXCHANGED_TOKEN_RETENTION_HOURS: i64 = 24;
+/// 向上汇总时最多追溯的层级，防止异常数据形成环
+const MAX_LINEAGE_DEPTH: usize = 8;
+
+/// POST /v1/token/exchange 请求体：所有字段可选，缺省时继承父令牌（并收窄到短期/小额度）
+#[derive(Debug, Default, Deserialize)]
+pub struct TokenExchangeRequest {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub allowed_models: Option<Vec<String>>,
+    #[serde(default)]
+    pub max_amount: Option<f64>,
+    #[serde(default)]
+    pub ttl_secs: Option<i64>,
+}
+
+/// 由父令牌与交换请求推导子令牌：模型只能收窄、额度不超过父令牌剩余额度、
+/// 过期时间不晚于父令牌；其余访问限制（IP、流式、沙箱等）原样继承
+pub fn child_token_payload(
+    parent: &ClientToken,
+    req: TokenExchangeRequest,
+) -> Result<CreateTokenPayload, GatewayError> {
+    let requested_models = normalize_model_list("allowed_models", req.allowed_models)?;
+    let (allowed_models, model_blacklist) = match requested_models {
+        Some(models) => {
+            for m in &models {
+                enforce_model_allowed_for_token(parent, m)?;
+            }
+            (Some(models), None)
+        }
+        None => (
+            parent.allowed_models.clone(),
+            parent.model_blacklist.clone(),
+        ),
+    };
+
+    // 绑定用户的令牌按用户余额计费，不设置金额额度
+    let max_amount = if parent.user_id.is_some() {
+        if req.max_amount.is_some() {
+            return Err(GatewayError::Config(
+                "user-bound tokens cannot set max_amount on exchanged tokens".into(),
+            ));
+        }
+        None
+    } else {
+        let amount = req.max_amount.unwrap_or(EXCHANGE_DEFAULT_MAX_AMOUNT);
+        if !amount.is_finite() || amount <= 0.0 {
+            return Err(GatewayError::Config("max_amount must be positive".into()));
+        }
+        match parent.max_amount {
+            Some(parent_max) => {
+                let remaining = (parent_max - parent.amount_spent).max(0.0);
+                if remaining <= 0.0 {
+                    return Err(GatewayError::Forbidden(
+                        "parent token budget exceeded".into(),
+                    ));
+                }
+                if req.max_amount.is_some() && amount > remaining {
+                    return Err(GatewayError::Config(format!(
+                        "max_amount exceeds the parent token's remaining budget ({:.4})",
+                        remaining
+                    )));
+                }
+                Some(amount.min(remaining))
+            }
+            None => Some(amount),
+        }
+    };
+
+    let ttl = req.ttl_secs.unwrap_or(EXCHANGE_DEFAULT_TTL_SECS);
+    if !(EXCHANGE_MIN_TTL_SECS..=EXCHANGE_MAX_TTL_SECS).contains(&ttl) {
+        return Err(GatewayError::Config(format!(
+            "ttl_secs must be between {} and {}",
+            EXCHANGE_MIN_TTL_SECS, EXCHANGE_MAX_TTL_SECS
+        )));
+    }
+    let mut expires_at = Utc::now() + Duration::seconds(ttl);
+    if let Some(parent_exp) = parent.expires_at {
+        expires_at = expires_at.min(parent_exp);
+    }
+
+    let name = req
+        .name
+        .map(|n| n.trim().to_string())
+        .filter(|n| !n.is_empty())
+        .unwrap_or_else(|| format!("{} (exchanged)", parent.name));
+    if name.chars().count() > 64 || name.chars().any(|c| c.is_control()) {
+        return Err(GatewayError::Config(
+            "name must be at most 64 characters without control characters".into(),
+        ));
+    }
+
+    Ok(CreateTokenPayload {
+        user_id: parent.user_id.clone(),
+        name: Some(name),
+        allowed_models,
+        model_blacklist,
+        max_amount,
+        expires_at: Some(to_beijing_string(&expires_at)),
+        organization_id: parent.organization_id.clone(),
+        ip_whitelist: parent.ip_whitelist.clone(),
+        ip_blacklist: parent.ip_blacklist.clone(),
+        allow_streaming: parent.allow_streaming,
+        sandbox: parent.sandbox,
+        strip_reasoning: parent.strip_reasoning,
+        parent_token_id: Some(parent.id.clone()),
+        ..Default::default()
+    })
+}
+
+/// 令牌交换签发的子令牌：有父令牌，且有效期不超过交换允许的最长时长（时间按秒存储，留一分钟余量）
+pub fn is_exchanged_token(token: &ClientToken) -> bool {
+    token.parent_token_id.is_some()
+        && token.expires_at.is_some_and(|exp| {
+            exp - token.created_at <= Duration::seconds(EXCHANGE_MAX_TTL_SECS + 60)
+        })
+}
+
+/// 令牌交换按父令牌限流：最近一分钟内签发的交换子令牌不超过 EXCHANGES_PER_MINUTE
+pub async fn ensure_exchange_rate(
+    app_state: &AppState,
+    parent: &ClientToken,
+) -> Result<(), GatewayError> {
+    let since = Utc::now() - Duration::minutes(1);
+    let recent = app_state
+        .token_store
+        .list_child_tokens(&parent.id)
+        .await?
+        .iter()
+        .filter(|t| t.created_at >= since && is_exchanged_token(t))
+        .count();
+    if recent >= EXCHANGES_PER_MINUTE {
+        return Err(GatewayError::RateLimited(
+            "too many token exchanges for this token, try again later".into(),
+        ));
+    }
+    Ok(())
+}
+
+/// 删除过期超过保留时长、且没有下级令牌的交换子令牌；返回删除数
+pub async fn prune_expired_exchanged_tokens(
+    app_state: &AppState,
+    now: chrono::DateTime<Utc>,
+) -> Result<usize, GatewayError> {
+    let cutoff = now - Duration::hours(EXCHANGED_TOKEN_RETENTION_HOURS);
+    let tokens = app_state.token_store.list_tokens().await?;
+    let mut pruned = 0;
+    for token in tokens.iter().filter(|t| {
+        is_exchanged_token(t)
+            && t.expires_at.is_some_and(|exp| exp < cutoff)
+            && !tokens
+                .iter()
+                .any(|c| c.parent_token_id.as_deref() == Some(t.id.as_str()))
+    }) {
+        if app_state.token_store.delete_token_by_id(&token.id).await? {
+            pruned += 1;
+        }
+    }
+    Ok(pruned)
+}
+
+/// 子令牌的金额/用量同步累加到各级父令牌；父令牌超出额度时自动禁用
+pub async fn roll_up_to_parents(
+    app_state: &AppState,
+    client_token: &str,
+    amount_spent: Option<f64>,
+    usage: Option<(i64, i64, i64)>,
+) {
+    let Ok(Some(child)) = app_state.token_store.get_token(client_token).await else {
+        return;
+    };
+    let mut parent_id = child.parent_token_id;
+    for _ in 0..MAX_LINEAGE_DEPTH {
+        let Some(id) = parent_id.take() else { return };
+        let Ok(Some(parent)) = app_state.token_store.get_token_by_id(&id).await else {
+            return;
+        };
+        if let Some(delta) = amount_spent
+            && let Err(e) = app_state
+                .token_store
+                .add_amount_spent(&parent.token, delta)
+                .await
+        {
+            tracing::warn!("Failed to roll up spent to parent token: {}", e);
+        }
+        if let Some((prompt, completion, total)) = usage
+            && let Err(e) = app_state
+                .token_store
+                .add_usage_spent(&parent.token, prompt, completion, total)
+                .await
+        {
+            tracing::warn!("Failed to roll up tokens to parent token: {}", e);
+        }
+        if let Some(max_amount) = parent.max_amount
+            && parent.amount_spent + amount_spent.unwrap_or(0.0) > max_amount
+        {
+            let _ = app_state
+                .token_store
+                .set_enabled(&parent.token, false)
+                .await;
+            if let Err(e) = cascade_disable(app_state, &parent.id).await {
+                tracing::warn!("Failed to disable child tokens: {}", e);
+            }
+        }
+        parent_id = parent.parent_token_id;
+    }
+}
+
+/// 父令牌链（由近到远）；层级超过 MAX_LINEAGE_DEPTH 视为异常数据
+pub async fn ancestors(
+    app_state: &AppState,
+    token: &ClientToken,
+) -> Result<Vec<ClientToken>, GatewayError> {
+    ancestors_in(app_state.token_store.as_ref(), token).await
+}
+
+/// 同 [`ancestors`]，直接从给定的令牌存储读取（启动阶段尚无 AppState 时使用）
+pub async fn ancestors_in(
+    store: &(dyn TokenStore + Send + Sync),
+    token: &ClientToken,
+) -> Result<Vec<ClientToken>, GatewayError> {
+    let mut out = Vec::new();
+    let mut parent_id = token.parent_token_id.clone();
+    while let Some(id) = parent_id {
+        if out.len() >= MAX_LINEAGE_DEPTH {
+            return Err(GatewayError::Config("token hierarchy is too deep".into()));
+        }
+        let Some(parent) = store.get_token_by_id(&id).await? else {
+            break;
+        };
+        parent_id = parent.parent_token_id.clone();
+        out.push(parent);
+    }
+    Ok(out)
+}
+
+/// 新子令牌挂在 parent 下是否超出层级上限
+pub async fn ensure_can_add_child(
+    app_state: &AppState,
+    parent: &ClientToken,
+) -> Result<(), GatewayError> {
+    if ancestors(app_state, parent).await?.len() + 1 >= MAX_LINEAGE_DEPTH {
+        return Err(GatewayError::Config("token hierarchy is too deep".into()));
+    }
+    Ok(())
+}
+
+/// 子令牌请求时校验各级父令牌：任一父令牌禁用、过期或额度耗尽即拒绝
+pub async fn ensure_ancestors_active(
+    app_state: &AppState,
+    token: &ClientToken,
+) -> Result<(), GatewayError> {
+    for parent in ancestors(app_state, token).await? {
+        if !parent.enabled {
+            return Err(GatewayError::Unauthorized("parent token disabled".into()));
+        }
+        if parent.expires_at.is_some_and(|exp| Utc::now() > exp) {
+            return Err(GatewayError::Unauthorized("parent token expired".into()));
+        }
+        if parent
+            .max_amount
+            .is_some_and(|max| parent.amount_spent >= max)
+        {
+            return Err(GatewayError::Unauthorized(
+                "parent token budget exceeded".into(),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// 所有后代令牌（广度优先，父在前）
+pub async fn descendants(app_state: &AppState, id: &str) -> Result<Vec<ClientToken>, GatewayError> {
+    let mut out: Vec<ClientToken> = Vec::new();
+    let mut frontier = vec![id.to_string()];
+    for _ in 0..MAX_LINEAGE_DEPTH {
+        let mut next = Vec::new();
+        for parent_id in frontier {
+            for child in app_state.token_store.list_child_tokens(&parent_id).await? {
+                next.push(child.id.clone());
+                out.push(child);
+            }
+        }
+        if next.is_empty() {
+            break;
+        }
+        frontier = next;
+    }
+    Ok(out)
+}
+
+/// 禁用父令牌时级联禁用全部后代；重新启用父令牌不会自动启用后代
+pub async fn cascade_disable(app_state: &AppState, id: &str) -> Result<(), GatewayError> {
+    for child in descendants(app_state, id).await? {
+        if child.enabled {
+            app_state
+                .token_store
+                .set_enabled_by_id(&child.id, false)
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+/// 删除令牌及其全部后代（先删最深层）
+pub async fn delete_with_descendants(app_state: &AppState, id: &str) -> Result<bool, GatewayError> {
+    for child in descendants(app_state, id).await?.iter().rev() {
+        app_state.token_store.delete_token_by_id(&child.id).await?;
+    }
+    app_state.token_store.delete_token_by_id(id).await
+}
+
+/// 在父令牌下创建子令牌（管理端/分销商接口共用）：组织与绑定用户继承父令牌，
+/// 过期时间不晚于父令牌；max_amount 为空时直接消耗父令牌额度，否则不得超过父令牌剩余额度
+pub async fn attach_to_parent(
+    app_state: &AppState,
+    parent: &ClientToken,
+    payload: &mut CreateTokenPayload,
+) -> Result<(), GatewayError> {
+    ensure_can_add_child(app_state, parent).await?;
+    if payload
+        .user_id
+        .as_deref()
+        .is_some_and(|u| Some(u) != parent.user_id.as_deref())
+    {
+        return Err(GatewayError::Config(
+            "child tokens must be bound to the same user as the parent".into(),
+        ));
+    }
+    payload.user_id = parent.user_id.clone();
+    payload.organization_id = parent.organization_id.clone();
+    if let (Some(amount), Some(parent_max)) = (payload.max_amount, parent.max_amount) {
+        let remaining = (parent_max - parent.amount_spent).max(0.0);
+        if amount > remaining {
+            return Err(GatewayError::Config(format!(
+                "max_amount exceeds the parent token's remaining budget ({:.4})",
+                remaining
+            )));
+        }
+    }
+    if let Some(parent_exp) = parent.expires_at {
+        let child_exp = match payload.expires_at.as_deref() {
+            Some(s) => Some(parse_beijing_string(s)?),
+            None => None,
+        };
+        if child_exp.is_none_or(|exp| exp > parent_exp) {
+            payload.expires_at = Some(to_beijing_string(&parent_exp));
+        }
+    }
+    payload.parent_token_id = Some(parent.id.clone());
+    Ok(())
+}
+
+/// 层级用量报表节点：父令牌的计数已包含所有后代的用量（见 roll_up_to_parents），
+/// own_* 为扣除直接子令牌后的自身用量
+#[derive(Debug, Clone, Serialize)]
+pub struct HierarchyUsageNode {
+    pub id: String,
+    pub name: String,
+    pub enabled: bool,
+    pub max_amount: Option<f64>,
+    pub amount_spent: f64,
+    pub own_amount_spent: f64,
+    pub total_tokens_spent: i64,
+    pub own_total_tokens_spent: i64,
+    pub children: Vec<HierarchyUsageNode>,
+}
+
+fn build_usage_node(token: &ClientToken, all: &[ClientToken]) -> HierarchyUsageNode {
+    let children: Vec<HierarchyUsageNode> = all
+        .iter()
+        .filter(|t| t.parent_token_id.as_deref() == Some(token.id.as_str()))
+        .map(|t| build_usage_node(t, all))
+        .collect();
+    let child_amount: f64 = children.iter().map(|c| c.amount_spent).sum();
+    let child_tokens: i64 = children.iter().map(|c| c.total_tokens_spent).sum();
+    HierarchyUsageNode {
+        id: token.id.clone(),
+        name: token.name.clone(),
+        enabled: token.enabled,
+        max_amount: token.max_amount,
+        amount_spent: token.amount_spent,
+        own_amount_spent: (token.amount_spent - child_amount).max(0.0),
+        total_tokens_spent: token.total_tokens_spent,
+        own_total_tokens_spent: (token.total_tokens_spent - child_tokens).max(0),
+        children,
+    }
+}
+
+pub async fn hierarchy_usage(
+    app_state: &AppState,
+    root: &ClientToken,
+) -> Result<HierarchyUsageNode, GatewayError> {
+    let all = descendants(app_state, &root.id).await?;
+    Ok(build_usage_node(root, &all))
+}
+
+/// `/v1/*` 子令牌请求在进入业务处理前校验父令牌链
+pub async fn enforce_active_ancestors(
+    State(app_state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Result<Response, GatewayError> {
+    if crate::server::request_signing::is_client_api_path(request.uri().path())
+        && let Some(t) = resolved_client_token(&request)
+        && t.parent_token_id.is_some()
+    {
+        ensure_ancestors_active(&app_state, t).await?;
+    }
+    Ok(next.run(request).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parent() -> ClientToken {
+        ClientToken {
+            id: "atk_parent".into(),
+            user_id: None,
+            name: "reseller".into(),
+            token: "tok".into(),
+            allowed_models: Some(vec!["p/a".into(), "p/b".into()]),
+            model_blacklist: None,
+            max_tokens: None,
+            max_amount: Some(10.0),
+            enabled: true,
+            expires_at: None,
+            created_at: Utc::now(),
+            amount_spent: 7.5,
+            prompt_tokens_spent: 0,
+            completion_tokens_spent: 0,
+            total_tokens_spent: 0,
+            remark: None,
+            organization_id: Some("default".into()),
+            ip_whitelist: None,
+            ip_blacklist: None,
+            allow_streaming: true,
+            sandbox: false,
+            strip_reasoning: false,
+            usage_webhook_url: Some("https://hooks.example.com/usage".into()),
+            signing_secret: None,
+            require_signature: false,
+            parent_token_id: None,
+            allow_debug_capture: false,
+            allow_provider_override: false,
+            auto_truncate_prompt: false,
+            semantic_cache: false,
+            allow_login_codes: false,
+            max_requests: None,
+            max_requests_per_day: None,
+            watermark_responses: false,
+            queue_weight: None,
+            usage_webhook_secret: None,
+        }
+    }
+
+    #[test]
+    fn child_is_narrowed_to_parent_scope() {
+        let p = parent();
+        let child = child_token_payload(&p, TokenExchangeRequest::default()).unwrap();
+        assert_eq!(child.parent_token_id.as_deref(), Some("atk_parent"));
+        assert_eq!(child.allowed_models, p.allowed_models);
+        assert_eq!(child.max_amount, Some(EXCHANGE_DEFAULT_MAX_AMOUNT));
+        assert!(child.usage_webhook_url.is_none());
+        assert!(child.expires_at.is_some());
+
+        let req = TokenExchangeRequest {
+            allowed_models: Some(vec!["p/c".into()]),
+            ..Default::default()
+        };
+        assert!(matches!(
+            child_token_payload(&p, req),
+            Err(GatewayError::Forbidden(_))
+        ));
+        let req = TokenExchangeRequest {
+            max_amount: Some(3.0),
+            ..Default::default()
+        };
+        assert!(child_token_payload(&p, req).is_err());
+        let req = TokenExchangeRequest {
+            ttl_secs: Some(EXCHANGE_MAX_TTL_SECS + 1),
+            ..Default::default()
+        };
+        assert!(child_token_payload(&p, req).is_err());
+    }
+
+    #[test]
+    fn usage_tree_separates_own_and_rolled_up_usage() {
+        let mut root = parent();
+        root.amount_spent = 5.0;
+        root.total_tokens_spent = 500;
+        let mut a = parent();
+        a.id = "atk_a".into();
+        a.parent_token_id = Some(root.id.clone());
+        a.amount_spent = 3.0;
+        a.total_tokens_spent = 300;
+        let mut a1 = parent();
+        a1.id = "atk_a1".into();
+        a1.parent_token_id = Some(a.id.clone());
+        a1.amount_spent = 1.0;
+        a1.total_tokens_spent = 100;
+
+        let tree = build_usage_node(&root, &[a, a1]);
+        assert_eq!(tree.amount_spent, 5.0);
+        assert_eq!(tree.own_amount_spent, 2.0);
+        assert_eq!(tree.own_total_tokens_spent, 200);
+        assert_eq!(tree.children.len(), 1);
+        assert_eq!(tree.children[0].own_amount_spent, 2.0);
+        assert_eq!(tree.children[0].children[0].id, "atk_a1");
+        assert_eq!(tree.children[0].children[0].own_total_tokens_spent, 100);
+    }
+}
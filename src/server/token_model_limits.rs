@@ -149,6 +149,23 @@ mod tests {
             organization_id: None,
             ip_whitelist: None,
             ip_blacklist: None,
+            allow_streaming: true,
+            sandbox: false,
+            strip_reasoning: false,
+            usage_webhook_url: None,
+            signing_secret: None,
+            require_signature: false,
+            parent_token_id: None,
+            allow_debug_capture: false,
+            allow_provider_override: false,
+            auto_truncate_prompt: false,
+            semantic_cache: false,
+            allow_login_codes: false,
+            max_requests: None,
+            max_requests_per_day: None,
+            watermark_responses: false,
+            queue_weight: None,
+            usage_webhook_secret: None,
         }
     }
 
@@ -0,0 +1,157 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Request, State},
+    http::HeaderValue,
+    middleware::Next,
+    response::Response,
+};
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+
+use crate::admin::{ClientToken, TokenRotationPrefs};
+use crate::error::GatewayError;
+use crate::server::AppState;
+use crate::server::request_signing::resolved_client_token;
+use crate::server::runtime_settings::RuntimeSettings;
+
+/// 宽限期内附加在响应上的提醒头
+pub const ROTATION_HEADER: &str = "x-gateway-token-rotation";
+/// 宽限期截止时间（RFC 3339），过后请求将被拒绝
+pub const ROTATION_DEADLINE_HEADER: &str = "x-gateway-token-rotation-deadline";
+pub const ROTATE_PATH: &str = "/v1/token/rotate";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum RotationStatus {
+    /// 策略未启用或令牌已豁免
+    NotApplicable,
+    Current {
+        due_at: DateTime<Utc>,
+    },
+    /// 已到期，宽限期内仅提醒
+    GracePeriod {
+        deadline: DateTime<Utc>,
+    },
+    /// 宽限期已过，拒绝请求直到轮换
+    Overdue {
+        deadline: DateTime<Utc>,
+    },
+}
+
+/// 按签发/上次轮换时间计算令牌的轮换状态
+pub fn rotation_status(
+    issued_at: DateTime<Utc>,
+    now: DateTime<Utc>,
+    rotation_days: u32,
+    grace_days: u32,
+) -> RotationStatus {
+    let due_at = issued_at + Duration::days(rotation_days as i64);
+    let deadline = due_at + Duration::days(grace_days as i64);
+    if now < due_at {
+        RotationStatus::Current { due_at }
+    } else if now < deadline {
+        RotationStatus::GracePeriod { deadline }
+    } else {
+        RotationStatus::Overdue { deadline }
+    }
+}
+
+/// 结合全局策略与令牌豁免设置计算状态
+pub fn status_for_token(
+    settings: &RuntimeSettings,
+    token: &ClientToken,
+    prefs: &TokenRotationPrefs,
+    now: DateTime<Utc>,
+) -> RotationStatus {
+    let Some(days) = settings.token_rotation_days else {
+        return RotationStatus::NotApplicable;
+    };
+    if prefs.exempt {
+        return RotationStatus::NotApplicable;
+    }
+    let issued_at = prefs.rotated_at.unwrap_or(token.created_at);
+    rotation_status(
+        issued_at,
+        now,
+        days,
+        settings.token_rotation_grace_days.unwrap_or(0),
+    )
+}
+
+/// 生成新的令牌值（与创建令牌时格式一致）
+pub fn generate_token_value() -> String {
+    use rand::Rng;
+    use rand::distr::Alphanumeric;
+    rand::rng()
+        .sample_iter(&Alphanumeric)
+        .take(40)
+        .map(char::from)
+        .collect()
+}
+
+/// 客户端 API 请求的轮换策略：宽限期内在响应头提醒，超期则拒绝（轮换接口本身除外）
+pub async fn enforce(
+    State(app_state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Result<Response, GatewayError> {
+    let settings = app_state.runtime_settings.snapshot();
+    let path = request.uri().path();
+    if settings.token_rotation_days.is_none()
+        || !crate::server::request_signing::is_client_api_path(path)
+        || path.strip_prefix("/api").unwrap_or(path) == ROTATE_PATH
+    {
+        return Ok(next.run(request).await);
+    }
+    let Some(token) = resolved_client_token(&request).cloned() else {
+        return Ok(next.run(request).await);
+    };
+    let prefs = app_state.token_store.get_rotation_prefs(&token.id).await?;
+    match status_for_token(&settings, &token, &prefs, Utc::now()) {
+        RotationStatus::Overdue { .. } => Err(GatewayError::TokenRotationRequired(format!(
+            "token is past its rotation deadline; rotate it via POST {}",
+            ROTATE_PATH
+        ))),
+        RotationStatus::GracePeriod { deadline } => {
+            let mut response = next.run(request).await;
+            let headers = response.headers_mut();
+            headers.insert(ROTATION_HEADER, HeaderValue::from_static("required"));
+            if let Ok(v) = HeaderValue::from_str(&deadline.to_rfc3339()) {
+                headers.insert(ROTATION_DEADLINE_HEADER, v);
+            }
+            Ok(response)
+        }
+        _ => Ok(next.run(request).await),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rotation_status_moves_through_grace_period() {
+        let issued = DateTime::parse_from_rfc3339("2026-01-01T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let at = |days: i64| issued + Duration::days(days);
+        assert_eq!(
+            rotation_status(issued, at(29), 30, 7),
+            RotationStatus::Current { due_at: at(30) }
+        );
+        assert_eq!(
+            rotation_status(issued, at(30), 30, 7),
+            RotationStatus::GracePeriod { deadline: at(37) }
+        );
+        assert_eq!(
+            rotation_status(issued, at(37), 30, 7),
+            RotationStatus::Overdue { deadline: at(37) }
+        );
+        // 无宽限期时到期即拒绝
+        assert_eq!(
+            rotation_status(issued, at(30), 30, 0),
+            RotationStatus::Overdue { deadline: at(30) }
+        );
+    }
+}
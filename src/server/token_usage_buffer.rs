@@ -0,0 +1,872 @@
+//! 令牌用量计数的延迟合并写入：`add_amount_spent` / `add_usage_spent` 先在内存中按令牌累加，
+//! 由后台任务定期批量写入 client_tokens，热点令牌不再每个请求一次 UPDATE。
+//! 读取令牌时叠加尚未写库的计数，额度校验不受延迟影响。
+//!
+//! 每次写库后记录已覆盖的最大请求日志 ID（水位）；异常退出时内存中的计数丢失，
+//! 下次启动按水位之后的请求日志补记（含各级父令牌）。补记假定单实例部署
+//! （配置了 cluster_peers 时不启用延迟写入，见 [`flush_interval_secs`]），
+//! 且仅能找回写入了请求日志的用量。
+//!
+//! 请求先将计数入账、再写请求日志，入账期间持有计费区段（[`UsageSections`]）；
+//! 批量写库先读取水位、再在写锁下取出计数，水位内日志的计数都在本批或更早写入。
+//! 入账后、写日志前恰有批次取出时，该日志可能落在水位之后，请求写完日志后将其 ID 记入排除列表；
+//! 降级模式回放的日志在请求时已入账，同样记入排除列表，补记时跳过
+//! （写入日志后、记录 ID 前退出时仍可能重复补记）。
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+
+use crate::admin::{
+    ClientToken, CreateTokenPayload, TokenAutoDisablePrefs, TokenNotificationRecord,
+    TokenRotationPrefs, TokenStore, UpdateTokenPayload,
+};
+use crate::config::settings::ServerConfig;
+use crate::error::GatewayError;
+use crate::logging::types::TokenUsageDelta;
+use crate::server::AppState;
+use crate::server::pagination::PageRequest;
+use crate::server::storage_traits::{RequestLogStore, SettingsStore};
+
+/// 已写入 client_tokens 的请求日志水位；空字符串表示未启用延迟写入
+const WATERMARK_KEY: &str = "token_usage_flushed_log_id";
+/// 补记时排除的请求日志 ID（逗号分隔）：降级队列回放的日志，以及计数已随更早水位写库的日志
+const REPLAYED_KEY: &str = "token_usage_replayed_log_ids";
+
+/// 计费区段：请求持有读锁将令牌计数入账，批量写库取出计数时持有写锁。
+/// 仅在启用延迟写入时创建（`AppState::usage_sections`）
+pub struct UsageSections {
+    lock: tokio::sync::RwLock<()>,
+    /// 已取出的批次数
+    batches: AtomicU64,
+    settings: Arc<dyn SettingsStore + Send + Sync>,
+}
+
+/// 进行中的计费区段；区段内不可再次进入，否则与等待中的批量写库互相等待
+pub struct UsageSection<'a> {
+    sections: &'a UsageSections,
+    batch: u64,
+    _guard: tokio::sync::RwLockReadGuard<'a, ()>,
+}
+
+/// 已入账、尚未写入请求日志的计数
+pub struct CountedUsage<'a> {
+    sections: &'a UsageSections,
+    batch: u64,
+}
+
+impl UsageSections {
+    pub fn new(settings: Arc<dyn SettingsStore + Send + Sync>) -> Self {
+        Self {
+            lock: tokio::sync::RwLock::new(()),
+            batches: AtomicU64::new(0),
+            settings,
+        }
+    }
+
+    pub async fn enter(&self) -> UsageSection<'_> {
+        let guard = self.lock.read().await;
+        UsageSection {
+            sections: self,
+            batch: self.batches.load(Ordering::SeqCst),
+            _guard: guard,
+        }
+    }
+
+    /// 等待进行中的区段结束后取出一批计数
+    async fn take_batch<T>(&self, take: impl FnOnce() -> T) -> T {
+        let _guard = self.lock.write().await;
+        self.batches.fetch_add(1, Ordering::SeqCst);
+        take()
+    }
+}
+
+impl<'a> UsageSection<'a> {
+    /// 计数（含父令牌汇总）已入账，结束区段
+    pub fn close(self) -> CountedUsage<'a> {
+        CountedUsage {
+            sections: self.sections,
+            batch: self.batch,
+        }
+    }
+}
+
+impl CountedUsage<'_> {
+    /// 请求日志写入后调用：入账后已有批次取出时，该批水位可能早于这条日志，
+    /// 将日志记入排除列表，避免异常退出后重复补记
+    pub async fn logged(self, log_id: Option<i64>) {
+        let Some(log_id) = log_id else {
+            return;
+        };
+        if self.sections.batches.load(Ordering::SeqCst) == self.batch {
+            return;
+        }
+        if let Err(e) = note_replayed(self.sections.settings.as_ref(), &[log_id]).await {
+            tracing::warn!("Failed to record flushed request log {}: {}", log_id, e);
+        }
+    }
+}
+
+/// 进入计费区段；未启用延迟写入时不加锁
+pub async fn usage_section(app_state: &AppState) -> Option<UsageSection<'_>> {
+    match app_state.usage_sections.as_deref() {
+        Some(sections) => Some(sections.enter().await),
+        None => None,
+    }
+}
+
+/// 单个令牌尚未写库的计数
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct PendingUsage {
+    amount: f64,
+    prompt: i64,
+    completion: i64,
+    total: i64,
+}
+
+impl PendingUsage {
+    fn merge(&mut self, other: PendingUsage) {
+        self.amount += other.amount;
+        self.prompt += other.prompt;
+        self.completion += other.completion;
+        self.total += other.total;
+    }
+
+    fn has_tokens(&self) -> bool {
+        self.prompt != 0 || self.completion != 0 || self.total != 0
+    }
+
+    fn apply_to(&self, token: &mut ClientToken) {
+        token.amount_spent += self.amount;
+        token.prompt_tokens_spent += self.prompt;
+        token.completion_tokens_spent += self.completion;
+        token.total_tokens_spent += self.total;
+    }
+}
+
+#[derive(Default)]
+struct BufferState {
+    /// 令牌值 -> 尚未写库的计数
+    pending: HashMap<String, PendingUsage>,
+    /// 已取出、正在写库的计数；写库完成前仍叠加到读取结果
+    flushing: HashMap<String, PendingUsage>,
+}
+
+/// 延迟写入用量计数的 TokenStore 装饰器，其余操作直接转发
+pub struct DeferredUsageTokenStore {
+    inner: Arc<dyn TokenStore + Send + Sync>,
+    state: Mutex<BufferState>,
+    /// 串行化批量写入与令牌轮换前的单令牌写入
+    flush_lock: tokio::sync::Mutex<()>,
+    sections: Arc<UsageSections>,
+}
+
+impl DeferredUsageTokenStore {
+    pub fn new(inner: Arc<dyn TokenStore + Send + Sync>, sections: Arc<UsageSections>) -> Self {
+        Self {
+            inner,
+            state: Mutex::new(BufferState::default()),
+            flush_lock: tokio::sync::Mutex::new(()),
+            sections,
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BufferState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn record(&self, token: &str, usage: PendingUsage) {
+        self.lock()
+            .pending
+            .entry(token.to_string())
+            .or_default()
+            .merge(usage);
+    }
+
+    fn overlay(&self, token: &mut ClientToken) {
+        let state = self.lock();
+        for buffered in [&state.pending, &state.flushing] {
+            if let Some(usage) = buffered.get(&token.token) {
+                usage.apply_to(token);
+            }
+        }
+    }
+
+    fn overlay_all(&self, tokens: &mut [ClientToken]) {
+        for token in tokens {
+            self.overlay(token);
+        }
+    }
+
+    /// 写入单个令牌的计数；失败时返回未写入的部分
+    async fn write(
+        &self,
+        token: &str,
+        usage: PendingUsage,
+    ) -> Result<(), (PendingUsage, GatewayError)> {
+        if usage.amount != 0.0
+            && let Err(e) = self.inner.add_amount_spent(token, usage.amount).await
+        {
+            return Err((usage, e));
+        }
+        if usage.has_tokens()
+            && let Err(e) = self
+                .inner
+                .add_usage_spent(token, usage.prompt, usage.completion, usage.total)
+                .await
+        {
+            return Err((
+                PendingUsage {
+                    amount: 0.0,
+                    ..usage
+                },
+                e,
+            ));
+        }
+        Ok(())
+    }
+
+    /// 写入一批计数；写库失败的部分放回待写队列
+    async fn write_batch(&self, batch: HashMap<String, PendingUsage>) -> Result<(), GatewayError> {
+        let mut first_error = None;
+        for (token, usage) in batch {
+            let result = self.write(&token, usage).await;
+            let mut state = self.lock();
+            state.flushing.remove(&token);
+            if let Err((rest, e)) = result {
+                state.pending.entry(token).or_default().merge(rest);
+                first_error.get_or_insert(e);
+            }
+        }
+        first_error.map_or(Ok(()), Err)
+    }
+
+    /// 批量写库并推进请求日志水位。先读取日志最大 ID 再取出计数：计数先于日志入账，
+    /// 水位内日志的计数都在本批或更早写入
+    pub async fn flush(
+        &self,
+        log_store: &(dyn RequestLogStore + Send + Sync),
+        settings: &(dyn SettingsStore + Send + Sync),
+    ) -> Result<(), GatewayError> {
+        let _guard = self.flush_lock.lock().await;
+        let watermark = log_store.max_request_log_id().await?;
+        let batch = self
+            .sections
+            .take_batch(|| {
+                let mut state = self.lock();
+                let batch = std::mem::take(&mut state.pending);
+                state.flushing = batch.clone();
+                batch
+            })
+            .await;
+        self.write_batch(batch).await?;
+        settings
+            .set_setting(WATERMARK_KEY, &watermark.to_string())
+            .await?;
+        Ok(())
+    }
+
+    /// 令牌值即将变更（轮换）前写入该令牌的计数，避免之后按旧值写库
+    async fn flush_token(&self, token: &str) -> Result<(), GatewayError> {
+        let _guard = self.flush_lock.lock().await;
+        let usage = {
+            let mut state = self.lock();
+            let Some(usage) = state.pending.remove(token) else {
+                return Ok(());
+            };
+            state.flushing.insert(token.to_string(), usage);
+            usage
+        };
+        self.write_batch(HashMap::from([(token.to_string(), usage)]))
+            .await
+    }
+}
+
+/// 将请求日志汇总的用量补记到令牌及其各级父令牌
+async fn apply_deltas(
+    store: &(dyn TokenStore + Send + Sync),
+    deltas: &[TokenUsageDelta],
+) -> Result<(), GatewayError> {
+    for delta in deltas {
+        let Some(token) = store.get_token_by_id(&delta.token_id).await? else {
+            continue;
+        };
+        let mut targets = vec![token.token.clone()];
+        targets.extend(
+            crate::server::token_lineage::ancestors_in(store, &token)
+                .await?
+                .into_iter()
+                .map(|t| t.token),
+        );
+        for target in targets {
+            if delta.amount_spent != 0.0 {
+                store.add_amount_spent(&target, delta.amount_spent).await?;
+            }
+            store
+                .add_usage_spent(
+                    &target,
+                    delta.prompt_tokens,
+                    delta.completion_tokens,
+                    delta.total_tokens,
+                )
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+fn parse_log_ids(raw: &str) -> Vec<i64> {
+    raw.split(',')
+        .filter_map(|id| id.trim().parse::<i64>().ok())
+        .collect()
+}
+
+async fn get_watermark(
+    settings: &(dyn SettingsStore + Send + Sync),
+) -> Result<Option<i64>, GatewayError> {
+    Ok(settings
+        .get_setting(WATERMARK_KEY)
+        .await?
+        .and_then(|raw| raw.trim().parse::<i64>().ok()))
+}
+
+/// 记录降级队列回放写入的请求日志 ID：其计数在请求时已入账，补记时不再计入。
+/// 仅在延迟写入启用（存在水位）时记录，并顺带清理已落在水位内的旧 ID
+pub async fn note_replayed(
+    settings: &(dyn SettingsStore + Send + Sync),
+    log_ids: &[i64],
+) -> Result<(), GatewayError> {
+    if log_ids.is_empty() {
+        return Ok(());
+    }
+    let Some(watermark) = get_watermark(settings).await? else {
+        return Ok(());
+    };
+    let existing = settings
+        .get_setting(REPLAYED_KEY)
+        .await?
+        .unwrap_or_default();
+    let ids: Vec<String> = parse_log_ids(&existing)
+        .into_iter()
+        .chain(log_ids.iter().copied())
+        .filter(|id| *id > watermark)
+        .map(|id| id.to_string())
+        .collect();
+    settings.set_setting(REPLAYED_KEY, &ids.join(",")).await?;
+    Ok(())
+}
+
+/// 启动时按水位补记上次异常退出丢失的计数（跳过回放的日志），返回当前日志最大 ID；
+/// 未记录水位（从未启用或已正常关闭写入模式）时不补记
+async fn reconcile(
+    store: &(dyn TokenStore + Send + Sync),
+    log_store: &(dyn RequestLogStore + Send + Sync),
+    settings: &(dyn SettingsStore + Send + Sync),
+) -> Result<i64, GatewayError> {
+    let max_id = log_store.max_request_log_id().await?;
+    let watermark = get_watermark(settings).await?;
+    if let Some(after) = watermark.filter(|after| *after < max_id) {
+        let replayed = parse_log_ids(
+            &settings
+                .get_setting(REPLAYED_KEY)
+                .await?
+                .unwrap_or_default(),
+        );
+        let deltas = log_store
+            .sum_token_usage_between(after, max_id, &replayed)
+            .await?;
+        if !deltas.is_empty() {
+            tracing::warn!(
+                tokens = deltas.len(),
+                after_log_id = after,
+                "Reconciling token usage counters lost before the last shutdown"
+            );
+        }
+        apply_deltas(store, &deltas).await?;
+    }
+    settings.set_setting(REPLAYED_KEY, "").await?;
+    Ok(max_id)
+}
+
+/// 生效的写库间隔：多实例部署（配置了 cluster_peers）时强制为 0。
+/// 水位与补记按单实例设计，其他副本的日志会被当作本实例遗留的计数重复补记
+pub fn flush_interval_secs(config: &ServerConfig) -> u64 {
+    let interval = config.token_usage_flush_interval_secs;
+    let clustered = config.cluster_peers.iter().any(|p| !p.trim().is_empty());
+    if interval > 0 && clustered {
+        tracing::warn!(
+            "token_usage_flush_interval_secs is ignored when cluster_peers is configured; writing token usage on every request"
+        );
+        return 0;
+    }
+    interval
+}
+
+/// 延迟写入装配结果；未启用时 `sections` 为 None
+pub struct Installed {
+    pub token_store: Arc<dyn TokenStore + Send + Sync>,
+    pub sections: Option<Arc<UsageSections>>,
+}
+
+/// 按配置装配令牌存储：
+/// - 先按水位补记上次运行遗留的计数
+/// - `interval_secs` 为 0 时清除水位并原样返回（每次请求直接写库，不设计费区段）
+/// - 否则包装为延迟写入并启动定期写库任务（关闭时最后写一次）
+pub async fn install(
+    interval_secs: u64,
+    tasks: &Arc<crate::server::tasks::TaskRegistry>,
+    store: Arc<dyn TokenStore + Send + Sync>,
+    log_store: Arc<dyn RequestLogStore + Send + Sync>,
+    settings: Arc<dyn SettingsStore + Send + Sync>,
+) -> Result<Installed, GatewayError> {
+    let max_id = match reconcile(store.as_ref(), log_store.as_ref(), settings.as_ref()).await {
+        Ok(max_id) => Some(max_id),
+        Err(e) => {
+            tracing::warn!("Failed to reconcile token usage counters: {}", e);
+            None
+        }
+    };
+    if interval_secs == 0 {
+        if max_id.is_some() {
+            settings.set_setting(WATERMARK_KEY, "").await?;
+        }
+        return Ok(Installed {
+            token_store: store,
+            sections: None,
+        });
+    }
+    if let Some(max_id) = max_id {
+        settings
+            .set_setting(WATERMARK_KEY, &max_id.to_string())
+            .await?;
+    }
+
+    let sections = Arc::new(UsageSections::new(settings.clone()));
+    let deferred = Arc::new(DeferredUsageTokenStore::new(store, sections.clone()));
+    let buffer = deferred.clone();
+    tasks.spawn_with("token_usage_flush", move |mut ctx| async move {
+        let period = std::time::Duration::from_secs(interval_secs);
+        let mut ticker = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+        loop {
+            let stopping = tokio::select! {
+                _ = ticker.tick() => false,
+                _ = ctx.cancelled() => true,
+            };
+            if let Err(e) = buffer.flush(log_store.as_ref(), settings.as_ref()).await {
+                tracing::warn!("Failed to flush token usage counters: {}", e);
+                ctx.report_error(e);
+            }
+            if stopping {
+                break;
+            }
+        }
+    });
+    Ok(Installed {
+        token_store: deferred,
+        sections: Some(sections),
+    })
+}
+
+#[async_trait]
+impl TokenStore for DeferredUsageTokenStore {
+    async fn create_token(&self, payload: CreateTokenPayload) -> Result<ClientToken, GatewayError> {
+        self.inner.create_token(payload).await
+    }
+    async fn update_token(
+        &self,
+        token: &str,
+        payload: UpdateTokenPayload,
+    ) -> Result<Option<ClientToken>, GatewayError> {
+        let mut result = self.inner.update_token(token, payload).await?;
+        if let Some(token) = result.as_mut() {
+            self.overlay(token);
+        }
+        Ok(result)
+    }
+    async fn set_enabled(&self, token: &str, enabled: bool) -> Result<bool, GatewayError> {
+        self.inner.set_enabled(token, enabled).await
+    }
+    async fn set_enabled_for_user(
+        &self,
+        user_id: &str,
+        enabled: bool,
+    ) -> Result<u64, GatewayError> {
+        self.inner.set_enabled_for_user(user_id, enabled).await
+    }
+    async fn get_token(&self, token: &str) -> Result<Option<ClientToken>, GatewayError> {
+        let mut result = self.inner.get_token(token).await?;
+        if let Some(token) = result.as_mut() {
+            self.overlay(token);
+        }
+        Ok(result)
+    }
+    async fn get_token_by_id(&self, id: &str) -> Result<Option<ClientToken>, GatewayError> {
+        let mut result = self.inner.get_token_by_id(id).await?;
+        if let Some(token) = result.as_mut() {
+            self.overlay(token);
+        }
+        Ok(result)
+    }
+    async fn get_token_by_id_scoped(
+        &self,
+        user_id: &str,
+        id: &str,
+    ) -> Result<Option<ClientToken>, GatewayError> {
+        let mut result = self.inner.get_token_by_id_scoped(user_id, id).await?;
+        if let Some(token) = result.as_mut() {
+            self.overlay(token);
+        }
+        Ok(result)
+    }
+    async fn list_tokens(&self) -> Result<Vec<ClientToken>, GatewayError> {
+        let mut tokens = self.inner.list_tokens().await?;
+        self.overlay_all(&mut tokens);
+        Ok(tokens)
+    }
+    async fn list_tokens_by_user(&self, user_id: &str) -> Result<Vec<ClientToken>, GatewayError> {
+        let mut tokens = self.inner.list_tokens_by_user(user_id).await?;
+        self.overlay_all(&mut tokens);
+        Ok(tokens)
+    }
+    async fn list_tokens_by_organization(
+        &self,
+        organization_id: &str,
+    ) -> Result<Vec<ClientToken>, GatewayError> {
+        let mut tokens = self
+            .inner
+            .list_tokens_by_organization(organization_id)
+            .await?;
+        self.overlay_all(&mut tokens);
+        Ok(tokens)
+    }
+    async fn list_tokens_page(
+        &self,
+        organization_id: Option<&str>,
+        page: &PageRequest,
+    ) -> Result<(Vec<ClientToken>, u64), GatewayError> {
+        let (mut tokens, total) = self.inner.list_tokens_page(organization_id, page).await?;
+        self.overlay_all(&mut tokens);
+        Ok((tokens, total))
+    }
+    async fn list_child_tokens(&self, parent_id: &str) -> Result<Vec<ClientToken>, GatewayError> {
+        let mut tokens = self.inner.list_child_tokens(parent_id).await?;
+        self.overlay_all(&mut tokens);
+        Ok(tokens)
+    }
+    async fn add_amount_spent(&self, token: &str, delta: f64) -> Result<(), GatewayError> {
+        self.record(
+            token,
+            PendingUsage {
+                amount: delta,
+                ..Default::default()
+            },
+        );
+        Ok(())
+    }
+    async fn add_usage_spent(
+        &self,
+        token: &str,
+        prompt: i64,
+        completion: i64,
+        total: i64,
+    ) -> Result<(), GatewayError> {
+        self.record(
+            token,
+            PendingUsage {
+                amount: 0.0,
+                prompt,
+                completion,
+                total,
+            },
+        );
+        Ok(())
+    }
+    async fn delete_token(&self, token: &str) -> Result<bool, GatewayError> {
+        self.inner.delete_token(token).await
+    }
+    async fn delete_token_by_id(&self, id: &str) -> Result<bool, GatewayError> {
+        self.inner.delete_token_by_id(id).await
+    }
+    async fn update_token_by_id(
+        &self,
+        id: &str,
+        payload: UpdateTokenPayload,
+    ) -> Result<Option<ClientToken>, GatewayError> {
+        let mut result = self.inner.update_token_by_id(id, payload).await?;
+        if let Some(token) = result.as_mut() {
+            self.overlay(token);
+        }
+        Ok(result)
+    }
+    async fn set_enabled_by_id(&self, id: &str, enabled: bool) -> Result<bool, GatewayError> {
+        self.inner.set_enabled_by_id(id, enabled).await
+    }
+    async fn get_notifications_opt_out(&self, id: &str) -> Result<bool, GatewayError> {
+        self.inner.get_notifications_opt_out(id).await
+    }
+    async fn set_notifications_opt_out(&self, id: &str, opt_out: bool) -> Result<(), GatewayError> {
+        self.inner.set_notifications_opt_out(id, opt_out).await
+    }
+    async fn record_token_notification(
+        &self,
+        record: &TokenNotificationRecord,
+    ) -> Result<(), GatewayError> {
+        self.inner.record_token_notification(record).await
+    }
+    async fn token_notification_sent(
+        &self,
+        id: &str,
+        kind: &str,
+        reference: &str,
+    ) -> Result<bool, GatewayError> {
+        self.inner
+            .token_notification_sent(id, kind, reference)
+            .await
+    }
+    async fn list_token_notifications(
+        &self,
+        id: &str,
+        limit: i64,
+    ) -> Result<Vec<TokenNotificationRecord>, GatewayError> {
+        self.inner.list_token_notifications(id, limit).await
+    }
+    async fn get_auto_disable_prefs(
+        &self,
+        id: &str,
+    ) -> Result<TokenAutoDisablePrefs, GatewayError> {
+        self.inner.get_auto_disable_prefs(id).await
+    }
+    async fn set_auto_disable_exempt(&self, id: &str, exempt: bool) -> Result<(), GatewayError> {
+        self.inner.set_auto_disable_exempt(id, exempt).await
+    }
+    async fn mark_auto_disabled(&self, id: &str, at: DateTime<Utc>) -> Result<(), GatewayError> {
+        self.inner.mark_auto_disabled(id, at).await
+    }
+    async fn get_rotation_prefs(&self, id: &str) -> Result<TokenRotationPrefs, GatewayError> {
+        self.inner.get_rotation_prefs(id).await
+    }
+    async fn set_rotation_exempt(&self, id: &str, exempt: bool) -> Result<(), GatewayError> {
+        self.inner.set_rotation_exempt(id, exempt).await
+    }
+    async fn rotate_token(
+        &self,
+        id: &str,
+        new_token: &str,
+        at: DateTime<Utc>,
+    ) -> Result<bool, GatewayError> {
+        if let Some(current) = self.inner.get_token_by_id(id).await? {
+            self.flush_token(&current.token).await?;
+        }
+        self.inner.rotate_token(id, new_token, at).await
+    }
+    async fn import_token(&self, token: &ClientToken) -> Result<(), GatewayError> {
+        self.inner.import_token(token).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logging::DatabaseLogger;
+    use crate::logging::types::RequestLog;
+    use tempfile::tempdir;
+
+    #[test]
+    fn deferred_usage_is_disabled_in_cluster_mode() {
+        let mut config = ServerConfig {
+            token_usage_flush_interval_secs: 5,
+            ..Default::default()
+        };
+        assert_eq!(flush_interval_secs(&config), 5);
+        config.cluster_peers = vec!["  ".into()];
+        assert_eq!(flush_interval_secs(&config), 5);
+        config.cluster_peers = vec!["http://10.0.0.2:8000".into()];
+        assert_eq!(flush_interval_secs(&config), 0);
+    }
+
+    fn request_log(token_id: &str, amount: f64, total: u32) -> RequestLog {
+        RequestLog {
+            id: None,
+            timestamp: Utc::now(),
+            method: "POST".into(),
+            path: "/v1/chat/completions".into(),
+            request_type: "chat_once".into(),
+            requested_model: Some("m1".into()),
+            effective_model: Some("m1".into()),
+            model: Some("m1".into()),
+            provider: Some("p1".into()),
+            api_key: None,
+            client_token: Some(token_id.to_string()),
+            user_id: None,
+            amount_spent: Some(amount),
+            status_code: 200,
+            response_time_ms: 10,
+            prompt_tokens: Some(total / 2),
+            completion_tokens: Some(total - total / 2),
+            total_tokens: Some(total),
+            cached_tokens: None,
+            reasoning_tokens: None,
+            error_message: None,
+            latency: Default::default(),
+            profile: Default::default(),
+        }
+    }
+
+    fn token_payload() -> CreateTokenPayload {
+        CreateTokenPayload {
+            name: Some("usage".into()),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn buffers_usage_overlays_reads_and_reconciles_after_crash() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("usage.db");
+        let logger = Arc::new(
+            DatabaseLogger::new(db_path.to_str().unwrap())
+                .await
+                .unwrap(),
+        );
+        let created = logger.create_token(token_payload()).await.unwrap();
+        let tasks = Arc::new(crate::server::tasks::TaskRegistry::default());
+        let store = install(60, &tasks, logger.clone(), logger.clone(), logger.clone())
+            .await
+            .unwrap()
+            .token_store;
+
+        logger
+            .log_request(request_log(&created.id, 0.5, 10))
+            .await
+            .unwrap();
+        store.add_amount_spent(&created.token, 0.5).await.unwrap();
+        store
+            .add_usage_spent(&created.token, 5, 5, 10)
+            .await
+            .unwrap();
+        let raw = logger.get_token(&created.token).await.unwrap().unwrap();
+        assert_eq!(raw.amount_spent, 0.0);
+        let seen = store.get_token(&created.token).await.unwrap().unwrap();
+        assert_eq!(seen.amount_spent, 0.5);
+        assert_eq!(seen.total_tokens_spent, 10);
+
+        let deferred = DeferredUsageTokenStore::new(
+            logger.clone(),
+            Arc::new(UsageSections::new(logger.clone())),
+        );
+        deferred
+            .add_amount_spent(&created.token, 0.5)
+            .await
+            .unwrap();
+        deferred
+            .add_usage_spent(&created.token, 5, 5, 10)
+            .await
+            .unwrap();
+        deferred
+            .flush(logger.as_ref(), logger.as_ref())
+            .await
+            .unwrap();
+        let flushed = logger.get_token(&created.token).await.unwrap().unwrap();
+        assert_eq!(flushed.amount_spent, 0.5);
+        assert_eq!(flushed.total_tokens_spent, 10);
+
+        // 写库后又完成一个请求但未写库即退出：下次启动按请求日志补记
+        logger
+            .log_request(request_log(&created.id, 0.25, 4))
+            .await
+            .unwrap();
+        drop(store);
+        install(0, &tasks, logger.clone(), logger.clone(), logger.clone())
+            .await
+            .unwrap();
+        let recovered = logger.get_token(&created.token).await.unwrap().unwrap();
+        assert_eq!(recovered.amount_spent, 0.75);
+        assert_eq!(recovered.total_tokens_spent, 14);
+        assert_eq!(
+            logger.get_setting(WATERMARK_KEY).await.unwrap().as_deref(),
+            Some("")
+        );
+    }
+
+    #[tokio::test]
+    async fn flush_between_usage_update_and_log_insert_keeps_counters() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("usage.db");
+        let logger = Arc::new(
+            DatabaseLogger::new(db_path.to_str().unwrap())
+                .await
+                .unwrap(),
+        );
+        let created = logger.create_token(token_payload()).await.unwrap();
+        let tasks = Arc::new(crate::server::tasks::TaskRegistry::default());
+        install(60, &tasks, logger.clone(), logger.clone(), logger.clone())
+            .await
+            .unwrap();
+        let sections = Arc::new(UsageSections::new(logger.clone()));
+        let deferred = Arc::new(DeferredUsageTokenStore::new(
+            logger.clone(),
+            sections.clone(),
+        ));
+
+        // 计数入账期间触发批量写库：取出计数需等待计费区段结束
+        let section = sections.enter().await;
+        let flush = tokio::spawn({
+            let deferred = deferred.clone();
+            let logger = logger.clone();
+            async move { deferred.flush(logger.as_ref(), logger.as_ref()).await }
+        });
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert!(!flush.is_finished());
+        deferred
+            .add_amount_spent(&created.token, 0.5)
+            .await
+            .unwrap();
+        deferred
+            .add_usage_spent(&created.token, 5, 5, 10)
+            .await
+            .unwrap();
+        let counted = section.close();
+        flush.await.unwrap().unwrap();
+        // 计数已随水位更早的批次写库，之后写入的日志记入排除列表
+        let log_id = logger
+            .log_request(request_log(&created.id, 0.5, 10))
+            .await
+            .unwrap();
+        counted.logged(Some(log_id)).await;
+
+        // 写库后异常退出：水位内的日志不重复补记，计数也未丢失
+        install(60, &tasks, logger.clone(), logger.clone(), logger.clone())
+            .await
+            .unwrap();
+        let recovered = logger.get_token(&created.token).await.unwrap().unwrap();
+        assert_eq!(recovered.amount_spent, 0.5);
+        assert_eq!(recovered.total_tokens_spent, 10);
+
+        // 降级回放的日志在请求时已入账：记录其 ID 后补记时排除
+        logger.add_amount_spent(&created.token, 0.25).await.unwrap();
+        logger
+            .add_usage_spent(&created.token, 2, 2, 4)
+            .await
+            .unwrap();
+        let replayed_id = logger
+            .log_request(request_log(&created.id, 0.25, 4))
+            .await
+            .unwrap();
+        note_replayed(logger.as_ref(), &[replayed_id])
+            .await
+            .unwrap();
+        install(0, &tasks, logger.clone(), logger.clone(), logger.clone())
+            .await
+            .unwrap();
+        let recovered = logger.get_token(&created.token).await.unwrap().unwrap();
+        assert_eq!(recovered.amount_spent, 0.75);
+        assert_eq!(recovered.total_tokens_spent, 14);
+        assert_eq!(
+            logger.get_setting(REPLAYED_KEY).await.unwrap().as_deref(),
+            Some("")
+        );
+    }
+}
@@ -0,0 +1,153 @@
+use hmac::{Hmac, Mac};
+use rand::Rng;
+use sha1::Sha1;
+
+/// RFC 6238 TOTP：30 秒步长、6 位数字、HMAC-SHA1（认证器 App 的通用默认值，otpauth URL 不声明 algorithm）
+pub const STEP_SECS: i64 = 30;
+const DIGITS: u32 = 6;
+/// 允许前后各 1 个步长的时钟偏差
+const ALLOWED_DRIFT_STEPS: i64 = 1;
+const SECRET_LEN: usize = 20;
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// RFC 4648 Base32（无填充），认证器 App 要求的密钥格式
+pub fn base32_encode(data: &[u8]) -> String {
+    let mut out = String::with_capacity(data.len().div_ceil(5) * 8);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for &b in data {
+        buffer = (buffer << 8) | b as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(BASE32_ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        out.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+    }
+    out
+}
+
+/// 解码时忽略大小写、空格与填充符
+pub fn base32_decode(raw: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(raw.len() * 5 / 8);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for c in raw.chars().filter(|c| !c.is_whitespace() && *c != '=') {
+        let upper = c.to_ascii_uppercase() as u8;
+        let val = BASE32_ALPHABET.iter().position(|&a| a == upper)? as u32;
+        buffer = (buffer << 5) | val;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+        }
+    }
+    Some(out)
+}
+
+pub fn generate_secret() -> String {
+    let mut bytes = [0u8; SECRET_LEN];
+    rand::rng().fill(&mut bytes);
+    base32_encode(&bytes)
+}
+
+fn code_at_step(secret: &[u8], step: i64) -> u32 {
+    let mut mac = Hmac::<Sha1>::new_from_slice(secret).expect("HMAC accepts any key length");
+    mac.update(&step.to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let bin = u32::from_be_bytes([
+        digest[offset] & 0x7f,
+        digest[offset + 1],
+        digest[offset + 2],
+        digest[offset + 3],
+    ]);
+    bin % 10u32.pow(DIGITS)
+}
+
+#[cfg(test)]
+pub(crate) fn code_for(secret_b32: &str, unix_secs: i64) -> String {
+    let secret = base32_decode(secret_b32).expect("valid base32");
+    format!(
+        "{:0width$}",
+        code_at_step(&secret, unix_secs / STEP_SECS),
+        width = DIGITS as usize
+    )
+}
+
+/// 校验验证码，成功时返回匹配的步长序号（调用方据此拒绝同一验证码的重复使用）
+pub fn verify(secret_b32: &str, code: &str, unix_secs: i64) -> Option<i64> {
+    let code = code.trim();
+    if code.len() != DIGITS as usize || !code.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let expected: u32 = code.parse().ok()?;
+    let secret = base32_decode(secret_b32)?;
+    let current = unix_secs / STEP_SECS;
+    (current - ALLOWED_DRIFT_STEPS..=current + ALLOWED_DRIFT_STEPS)
+        .find(|step| code_at_step(&secret, *step) == expected)
+}
+
+fn url_encode(raw: &str) -> String {
+    raw.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// 供认证器 App 扫码导入的 otpauth URL
+pub fn otpauth_url(issuer: &str, account: &str, secret_b32: &str) -> String {
+    format!(
+        "otpauth://totp/{}:{}?secret={}&issuer={}&digits={}&period={}",
+        url_encode(issuer),
+        url_encode(account),
+        secret_b32,
+        url_encode(issuer),
+        DIGITS,
+        STEP_SECS
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base32_round_trips_and_matches_rfc4648() {
+        assert_eq!(base32_encode(b"foobar"), "MZXW6YTBOI");
+        assert_eq!(base32_decode("mzxw6ytboi======").unwrap(), b"foobar");
+        assert!(base32_decode("MZ1W").is_none());
+        let secret = generate_secret();
+        assert_eq!(base32_decode(&secret).unwrap().len(), SECRET_LEN);
+    }
+
+    #[test]
+    fn totp_matches_rfc6238_sha1_vectors() {
+        // RFC 6238 附录 B 的 SHA1 种子（20 字节），取后 6 位
+        let secret = base32_encode(b"12345678901234567890");
+        assert_eq!(code_for(&secret, 59), "287082");
+        assert_eq!(code_for(&secret, 1_111_111_109), "081804");
+        assert_eq!(code_for(&secret, 20_000_000_000), "353130");
+    }
+
+    #[test]
+    fn verify_allows_one_step_of_drift() {
+        let secret = generate_secret();
+        let now = 1_700_000_000;
+        let step = now / STEP_SECS;
+        assert_eq!(verify(&secret, &code_for(&secret, now), now), Some(step));
+        assert_eq!(
+            verify(&secret, &code_for(&secret, now - STEP_SECS), now),
+            Some(step - 1)
+        );
+        assert!(verify(&secret, &code_for(&secret, now - 3 * STEP_SECS), now).is_none());
+        assert!(verify(&secret, "12345", now).is_none());
+        assert!(verify(&secret, "abcdef", now).is_none());
+    }
+}
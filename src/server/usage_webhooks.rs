@@ -0,0 +1,324 @@
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::Sha256;
+use tokio::sync::{Semaphore, mpsc};
+
+use crate::logging::RequestLog;
+use crate::logging::types::UsageWebhookDeadLetter;
+use crate::server::AppState;
+use crate::server::storage_traits::RequestLogStore;
+
+/// 签名请求头：`sha256=<hex(HMAC-SHA256(usage_webhook_secret, "{timestamp}.{body}"))>`
+pub const SIGNATURE_HEADER: &str = "x-gateway-signature";
+/// 签名时间戳（Unix 秒），接收方可据此拒绝过旧的重放请求
+pub const TIMESTAMP_HEADER: &str = "x-gateway-timestamp";
+
+const DELIVERY_TIMEOUT_SECS: u64 = 10;
+const DEFAULT_RETRY_DELAYS_SECS: [u64; 3] = [1, 10, 60];
+/// 同时进行中的投递（含重试等待）上限；超出时任务在队列中排队
+const MAX_CONCURRENT_DELIVERIES: usize = 32;
+/// 网关生成的签名密钥前缀，便于接收方识别
+const SECRET_PREFIX: &str = "whsec_";
+
+/// 每次请求完成后推送给令牌所有者的用量事件
+#[derive(Debug, Clone, Serialize)]
+pub struct UsageEvent {
+    /// 对应 request_logs.id（日志写入失败时为 None）
+    pub request_id: Option<i64>,
+    pub token_id: Option<String>,
+    pub request_type: String,
+    pub model: Option<String>,
+    pub provider: Option<String>,
+    pub prompt_tokens: Option<u32>,
+    pub completion_tokens: Option<u32>,
+    pub total_tokens: Option<u32>,
+    pub cost: Option<f64>,
+    pub latency_ms: i64,
+    pub status_code: u16,
+    pub error: Option<String>,
+    pub timestamp: String,
+}
+
+impl UsageEvent {
+    /// 需在 log_request 消费日志前构建；request_id 在写入日志后补齐
+    pub fn from_request_log(log: &RequestLog) -> Self {
+        Self {
+            request_id: log.id,
+            token_id: log.client_token.clone(),
+            request_type: log.request_type.clone(),
+            model: log.requested_model.clone().or_else(|| log.model.clone()),
+            provider: log.provider.clone(),
+            prompt_tokens: log.prompt_tokens,
+            completion_tokens: log.completion_tokens,
+            total_tokens: log.total_tokens,
+            cost: log.amount_spent,
+            latency_ms: log.response_time_ms,
+            status_code: log.status_code,
+            error: log.error_message.clone(),
+            timestamp: crate::logging::time::to_iso8601_utc_string(&log.timestamp),
+        }
+    }
+}
+
+/// 生成用量 Webhook 签名密钥（设置 usage_webhook_url 时由网关签发，随令牌详情返回给所有者）
+pub fn generate_secret() -> String {
+    use rand::Rng;
+    let mut bytes = [0u8; 32];
+    rand::rng().fill(&mut bytes);
+    format!("{}{}", SECRET_PREFIX, hex::encode(bytes))
+}
+
+pub fn sign_payload(secret: &str, timestamp: i64, body: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body.as_bytes());
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+struct UsageWebhookJob {
+    token_id: String,
+    url: String,
+    /// 签名密钥：令牌专属的 usage_webhook_secret（与令牌明文无关）
+    secret: String,
+    payload: String,
+}
+
+/// 用量 Webhook 后台投递队列：首次入队时启动 worker，每条任务独立重试，
+/// 重试耗尽后写入死信表（管理端可查看/重新投递）
+pub struct UsageWebhookQueue {
+    sender: OnceLock<mpsc::UnboundedSender<UsageWebhookJob>>,
+    retry_delays: Vec<Duration>,
+}
+
+impl Default for UsageWebhookQueue {
+    fn default() -> Self {
+        Self::with_retry_delays(
+            DEFAULT_RETRY_DELAYS_SECS
+                .iter()
+                .map(|s| Duration::from_secs(*s))
+                .collect(),
+        )
+    }
+}
+
+impl UsageWebhookQueue {
+    pub fn with_retry_delays(retry_delays: Vec<Duration>) -> Self {
+        Self {
+            sender: OnceLock::new(),
+            retry_delays,
+        }
+    }
+
+    fn enqueue(&self, app_state: &AppState, job: UsageWebhookJob) {
+        let sender = self.sender.get_or_init(|| {
+            let (tx, mut rx) = mpsc::unbounded_channel::<UsageWebhookJob>();
+            let log_store = app_state.log_store.clone();
+            let retry_delays = self.retry_delays.clone();
+            let tasks = app_state.task_registry.clone();
+            let slots = Arc::new(Semaphore::new(MAX_CONCURRENT_DELIVERIES));
+            app_state
+                .task_registry
+                .spawn("usage_webhook_queue", async move {
+                    while let Some(job) = rx.recv().await {
+                        let Ok(permit) = slots.clone().acquire_owned().await else {
+                            break;
+                        };
+                        let retry_delays = retry_delays.clone();
+                        let log_store = log_store.clone();
+                        tasks.spawn("usage_webhook_delivery", async move {
+                            deliver_with_retries(job, retry_delays, log_store).await;
+                            drop(permit);
+                        });
+                    }
+                });
+            tx
+        });
+        if sender.send(job).is_err() {
+            tracing::warn!("usage webhook queue is closed; dropping event");
+        }
+    }
+}
+
+async fn send_once(job: &UsageWebhookJob) -> Result<(), String> {
+    let client = crate::http_client::client_for_url(&job.url).map_err(|e| e.to_string())?;
+    let timestamp = Utc::now().timestamp();
+    let resp = client
+        .post(&job.url)
+        .timeout(Duration::from_secs(DELIVERY_TIMEOUT_SECS))
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header(TIMESTAMP_HEADER, timestamp.to_string())
+        .header(
+            SIGNATURE_HEADER,
+            sign_payload(&job.secret, timestamp, &job.payload),
+        )
+        .body(job.payload.clone())
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    if !resp.status().is_success() {
+        return Err(format!("webhook responded with {}", resp.status()));
+    }
+    Ok(())
+}
+
+async fn deliver_with_retries(
+    job: UsageWebhookJob,
+    retry_delays: Vec<Duration>,
+    log_store: Arc<dyn RequestLogStore + Send + Sync>,
+) {
+    let mut attempts = 0i64;
+    let mut last_error;
+    let mut delays = retry_delays.into_iter();
+    loop {
+        attempts += 1;
+        match send_once(&job).await {
+            Ok(()) => return,
+            Err(e) => last_error = e,
+        }
+        match delays.next() {
+            Some(delay) => tokio::time::sleep(delay).await,
+            None => break,
+        }
+    }
+    tracing::warn!(
+        token_id = %job.token_id,
+        attempts,
+        "usage webhook delivery failed: {}",
+        last_error
+    );
+    let letter = UsageWebhookDeadLetter {
+        id: 0,
+        token_id: job.token_id,
+        url: job.url,
+        payload_json: job.payload,
+        attempts,
+        last_error: Some(last_error),
+        created_at: Utc::now(),
+    };
+    if let Err(e) = log_store.insert_usage_webhook_dead_letter(letter).await {
+        tracing::error!("Failed to store usage webhook dead letter: {}", e);
+    }
+}
+
+/// 请求完成后调用：令牌配置了 usage_webhook_url 时将用量事件加入投递队列
+pub async fn enqueue_for_request(
+    app_state: &AppState,
+    client_token: Option<&str>,
+    mut event: UsageEvent,
+    request_id: Option<i64>,
+) {
+    let Some(token) = client_token else { return };
+    let Ok(Some(t)) = app_state.token_store.get_token(token).await else {
+        return;
+    };
+    let Some(url) = t.usage_webhook_url.filter(|u| !u.trim().is_empty()) else {
+        return;
+    };
+    let Some(secret) = t.usage_webhook_secret else {
+        tracing::warn!(token_id = %t.id, "usage webhook secret missing; skipping delivery");
+        return;
+    };
+    event.request_id = request_id;
+    event.token_id = Some(t.id.clone());
+    let payload = match serde_json::to_string(&event) {
+        Ok(v) => v,
+        Err(e) => {
+            tracing::warn!("Failed to serialize usage event: {}", e);
+            return;
+        }
+    };
+    app_state.usage_webhooks.enqueue(
+        app_state,
+        UsageWebhookJob {
+            token_id: t.id,
+            url,
+            secret,
+            payload,
+        },
+    );
+}
+
+/// 管理端重新投递死信：使用令牌当前的 usage_webhook_secret 签名，URL 沿用死信记录
+pub fn requeue_dead_letter(app_state: &AppState, letter: UsageWebhookDeadLetter, secret: String) {
+    app_state.usage_webhooks.enqueue(
+        app_state,
+        UsageWebhookJob {
+            token_id: letter.token_id,
+            url: letter.url,
+            secret,
+            payload: letter.payload_json,
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logging::DatabaseLogger;
+    use tempfile::tempdir;
+
+    #[test]
+    fn signature_covers_timestamp_and_body() {
+        let sig = sign_payload("secret", 1_700_000_000, r#"{"a":1}"#);
+        assert!(sig.starts_with("sha256="));
+        assert_eq!(sig.len(), "sha256=".len() + 64);
+        assert_eq!(sig, sign_payload("secret", 1_700_000_000, r#"{"a":1}"#));
+        assert_ne!(sig, sign_payload("secret", 1_700_000_001, r#"{"a":1}"#));
+        assert_ne!(sig, sign_payload("other", 1_700_000_000, r#"{"a":1}"#));
+    }
+
+    #[tokio::test]
+    async fn exhausted_retries_land_in_dead_letters() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("test.db");
+        let db = Arc::new(
+            DatabaseLogger::new(db_path.to_str().unwrap())
+                .await
+                .unwrap(),
+        );
+        // 绑定后立即释放端口，保证连接被拒绝
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        drop(listener);
+
+        let job = UsageWebhookJob {
+            token_id: "tok-1".into(),
+            url: url.clone(),
+            secret: "secret".into(),
+            payload: r#"{"request_id":1}"#.into(),
+        };
+        deliver_with_retries(job, vec![Duration::ZERO, Duration::ZERO], db.clone()).await;
+
+        let letters = RequestLogStore::list_usage_webhook_dead_letters(db.as_ref(), 10)
+            .await
+            .unwrap();
+        assert_eq!(letters.len(), 1);
+        assert_eq!(letters[0].attempts, 3);
+        assert_eq!(letters[0].url, url);
+        assert_eq!(letters[0].payload_json, r#"{"request_id":1}"#);
+        let id = letters[0].id;
+        assert!(
+            RequestLogStore::get_usage_webhook_dead_letter(db.as_ref(), id)
+                .await
+                .unwrap()
+                .is_some()
+        );
+        assert!(
+            RequestLogStore::delete_usage_webhook_dead_letter(db.as_ref(), id)
+                .await
+                .unwrap()
+        );
+        assert!(
+            RequestLogStore::get_usage_webhook_dead_letter(db.as_ref(), id)
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+}
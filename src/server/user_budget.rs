@@ -0,0 +1,118 @@
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::admin::ClientToken;
+use crate::error::GatewayError;
+use crate::server::AppState;
+
+/// 单个令牌在用户级预算中的消费占比
+#[derive(Debug, Clone, Serialize)]
+pub struct UserBudgetToken {
+    pub id: String,
+    pub name: String,
+    pub amount_spent: f64,
+}
+
+/// 用户级预算状态：汇总该用户名下客户端令牌的累计消费（子令牌并入父令牌）
+#[derive(Debug, Clone, Serialize)]
+pub struct UserBudgetStatus {
+    pub user_id: String,
+    pub max_amount: Option<f64>,
+    pub amount_spent: f64,
+    pub remaining: Option<f64>,
+    pub tokens: Vec<UserBudgetToken>,
+}
+
+impl UserBudgetStatus {
+    pub fn exhausted(&self) -> bool {
+        self.max_amount
+            .is_some_and(|max_amount| self.amount_spent >= max_amount)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UserBudgetPayload {
+    /// 为空表示清除用户级预算
+    #[serde(default)]
+    pub max_amount: Option<f64>,
+}
+
+impl UserBudgetPayload {
+    pub fn validated(self) -> Result<Option<f64>, GatewayError> {
+        match self.max_amount {
+            Some(max_amount) if !max_amount.is_finite() || max_amount < 0.0 => Err(
+                GatewayError::Config("max_amount must be a non-negative number".into()),
+            ),
+            other => Ok(other),
+        }
+    }
+}
+
+/// 子令牌（令牌交换签发或挂接到父令牌）的消费已由 `roll_up_to_parents` 计入祖先令牌，
+/// 父令牌同属该用户时跳过子令牌，避免重复计算
+fn summarize(user_id: &str, max_amount: Option<f64>, tokens: Vec<ClientToken>) -> UserBudgetStatus {
+    let ids: HashSet<String> = tokens.iter().map(|t| t.id.clone()).collect();
+    let tokens: Vec<UserBudgetToken> = tokens
+        .into_iter()
+        .filter(|t| {
+            t.parent_token_id
+                .as_ref()
+                .is_none_or(|parent| !ids.contains(parent))
+        })
+        .map(|t| UserBudgetToken {
+            id: t.id,
+            name: t.name,
+            amount_spent: t.amount_spent,
+        })
+        .collect();
+    let amount_spent = tokens.iter().map(|t| t.amount_spent).sum::<f64>();
+    UserBudgetStatus {
+        user_id: user_id.to_string(),
+        max_amount,
+        amount_spent,
+        remaining: max_amount.map(|m| (m - amount_spent).max(0.0)),
+        tokens,
+    }
+}
+
+/// 查询用户级预算状态（未设置预算时 max_amount/remaining 为空）
+pub async fn status(app_state: &AppState, user_id: &str) -> Result<UserBudgetStatus, GatewayError> {
+    let max_amount = app_state.user_store.get_budget(user_id).await?;
+    let tokens = app_state.token_store.list_tokens_by_user(user_id).await?;
+    Ok(summarize(user_id, max_amount, tokens))
+}
+
+/// 授权阶段使用：仅当用户设置了预算时才汇总令牌消费，返回已超出预算时的状态
+pub async fn exceeded(
+    app_state: &AppState,
+    user_id: &str,
+) -> Result<Option<UserBudgetStatus>, GatewayError> {
+    let Some(max_amount) = app_state.user_store.get_budget(user_id).await? else {
+        return Ok(None);
+    };
+    let tokens = app_state.token_store.list_tokens_by_user(user_id).await?;
+    let status = summarize(user_id, Some(max_amount), tokens);
+    Ok(status.exhausted().then_some(status))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn payload_rejects_negative_and_non_finite_amounts() {
+        for bad in [-1.0, f64::NAN, f64::INFINITY] {
+            let payload = UserBudgetPayload {
+                max_amount: Some(bad),
+            };
+            assert!(payload.validated().is_err());
+        }
+        let cleared = UserBudgetPayload { max_amount: None };
+        assert_eq!(cleared.validated().unwrap(), None);
+        let set = UserBudgetPayload {
+            max_amount: Some(0.0),
+        };
+        assert_eq!(set.validated().unwrap(), Some(0.0));
+    }
+}
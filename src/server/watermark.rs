@@ -0,0 +1,192 @@
+//! 响应水印：令牌开启 `watermark_responses` 后，网关在响应头（以及非流式 JSON 的
+//! `gateway_watermark` 字段、流式响应 `[DONE]` 前的 SSE 注释）中注入签名，
+//! 内容为请求 ID、令牌 ID 哈希与签发时间，泄露的输出可据此追溯到签发令牌。
+//!
+//! 格式：`v1.<request_id>.<token_hash>.<issued_at>.<signature>`，签名为 HMAC-SHA256，
+//! 密钥取 `GW_WATERMARK_SECRET`，未设置时回退到 `GW_JWT_SECRET`。
+
+use axum::body::{Body, Bytes};
+use axum::http::HeaderValue;
+use axum::response::Response;
+use chrono::{DateTime, Utc};
+use futures_util::StreamExt;
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::error::GatewayError;
+
+pub const WATERMARK_HEADER: &str = "x-gateway-watermark";
+/// 非流式 JSON 响应与流式 SSE 注释中携带水印的字段名
+pub const WATERMARK_FIELD: &str = "gateway_watermark";
+const VERSION: &str = "v1";
+/// 签名截断长度（十六进制字符，128 位）
+const SIGNATURE_HEX_LEN: usize = 32;
+
+fn secret() -> Option<Vec<u8>> {
+    ["GW_WATERMARK_SECRET", "GW_JWT_SECRET"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|s| !s.is_empty())
+        .map(String::into_bytes)
+}
+
+/// 令牌 ID 的短哈希；水印中不出现令牌 ID 本身
+pub fn token_hash(token_id: &str) -> String {
+    hex::encode(Sha256::digest(token_id.as_bytes()))[..16].to_string()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Watermark {
+    pub request_id: String,
+    pub token_hash: String,
+    /// Unix 秒
+    pub issued_at: i64,
+}
+
+impl Watermark {
+    pub fn issue(token_id: &str, now: DateTime<Utc>) -> Self {
+        Self {
+            request_id: uuid::Uuid::new_v4().simple().to_string(),
+            token_hash: token_hash(token_id),
+            issued_at: now.timestamp(),
+        }
+    }
+
+    fn payload(&self) -> String {
+        format!(
+            "{}.{}.{}.{}",
+            VERSION, self.request_id, self.token_hash, self.issued_at
+        )
+    }
+
+    fn mac(secret: &[u8], payload: &str) -> Hmac<Sha256> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("HMAC accepts any key length");
+        mac.update(payload.as_bytes());
+        mac
+    }
+
+    fn encode_with(&self, secret: &[u8]) -> String {
+        let payload = self.payload();
+        let sig = hex::encode(Self::mac(secret, &payload).finalize().into_bytes());
+        format!("{}.{}", payload, &sig[..SIGNATURE_HEX_LEN])
+    }
+
+    /// 未配置签名密钥时返回 None（不注入水印）
+    pub fn encode(&self) -> Option<String> {
+        secret().map(|secret| self.encode_with(&secret))
+    }
+
+    fn verify_with(value: &str, secret: &[u8]) -> Result<Self, GatewayError> {
+        let invalid = || GatewayError::Config("invalid watermark".into());
+        let (payload, sig) = value.trim().rsplit_once('.').ok_or_else(invalid)?;
+        let parts: Vec<&str> = payload.split('.').collect();
+        let [version, request_id, token_hash, issued_at] = parts[..] else {
+            return Err(invalid());
+        };
+        if version != VERSION || sig.len() != SIGNATURE_HEX_LEN {
+            return Err(invalid());
+        }
+        let sig = hex::decode(sig).map_err(|_| invalid())?;
+        Self::mac(secret, payload)
+            .verify_truncated_left(&sig)
+            .map_err(|_| GatewayError::Forbidden("watermark signature mismatch".into()))?;
+        Ok(Self {
+            request_id: request_id.to_string(),
+            token_hash: token_hash.to_string(),
+            issued_at: issued_at.parse().map_err(|_| invalid())?,
+        })
+    }
+
+    /// 解析并校验水印签名
+    pub fn verify(value: &str) -> Result<Self, GatewayError> {
+        let secret = secret().ok_or_else(|| {
+            GatewayError::Config(
+                "watermark secret not configured (set `GW_WATERMARK_SECRET` or `GW_JWT_SECRET`)"
+                    .into(),
+            )
+        })?;
+        Self::verify_with(value, &secret)
+    }
+}
+
+pub fn insert_header(response: &mut Response, encoded: &str) {
+    if let Ok(value) = HeaderValue::from_str(encoded) {
+        response.headers_mut().insert(WATERMARK_HEADER, value);
+    }
+}
+
+/// 非流式响应体顶层追加 `gateway_watermark` 字段
+pub fn annotate_json(body: &mut serde_json::Value, encoded: &str) {
+    if let Some(obj) = body.as_object_mut() {
+        obj.insert(WATERMARK_FIELD.into(), encoded.into());
+    }
+}
+
+/// 流式响应在 `data: [DONE]` 前插入 `: gateway_watermark <水印>` 注释帧；
+/// SSE 客户端会忽略注释，需要追溯的客户端可自行读取
+pub fn watermark_stream_response(response: Response, encoded: String) -> Response {
+    let (parts, body) = response.into_parts();
+    let mut pending: Vec<u8> = Vec::new();
+    let stream = body.into_data_stream().map(move |chunk| {
+        chunk.map(|bytes| {
+            pending.extend_from_slice(&bytes);
+            let mut out = Vec::new();
+            while let Some(pos) = pending.windows(2).position(|w| w == b"\n\n") {
+                let frame: Vec<u8> = pending.drain(..pos + 2).collect();
+                if is_done_frame(&frame[..pos]) {
+                    out.extend_from_slice(
+                        format!(": {} {}\n\n", WATERMARK_FIELD, encoded).as_bytes(),
+                    );
+                }
+                out.extend_from_slice(&frame);
+            }
+            Bytes::from(out)
+        })
+    });
+    Response::from_parts(parts, Body::from_stream(stream))
+}
+
+fn is_done_frame(frame: &[u8]) -> bool {
+    String::from_utf8_lossy(frame)
+        .lines()
+        .any(|line| line.strip_prefix("data:").map(str::trim) == Some("[DONE]"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encoded_watermark_round_trips_and_rejects_tampering() {
+        let wm = Watermark::issue("atk_123", Utc::now());
+        let encoded = wm.encode_with(b"secret");
+        assert_eq!(Watermark::verify_with(&encoded, b"secret").unwrap(), wm);
+        assert!(matches!(
+            Watermark::verify_with(&encoded, b"other"),
+            Err(GatewayError::Forbidden(_))
+        ));
+        let forged = encoded.replacen(&wm.token_hash, &token_hash("atk_456"), 1);
+        assert!(Watermark::verify_with(&forged, b"secret").is_err());
+        assert!(Watermark::verify_with("garbage", b"secret").is_err());
+    }
+
+    #[tokio::test]
+    async fn stream_watermark_precedes_done_frame() {
+        let chunks = vec![
+            Ok::<_, std::io::Error>(Bytes::from("data: {\"choices\":[]}\n\ndata: [DO")),
+            Ok(Bytes::from("NE]\n\n")),
+        ];
+        let response = Response::new(Body::from_stream(futures_util::stream::iter(chunks)));
+        let body = axum::body::to_bytes(
+            watermark_stream_response(response, "v1.x".into()).into_body(),
+            usize::MAX,
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(body.to_vec()).unwrap(),
+            "data: {\"choices\":[]}\n\n: gateway_watermark v1.x\n\ndata: [DONE]\n\n"
+        );
+    }
+}
@@ -209,6 +209,14 @@ pub trait UserStore: Send + Sync {
     async fn list_users(&self) -> Result<Vec<User>, GatewayError>;
     async fn delete_user(&self, id: &str) -> Result<bool, GatewayError>;
     async fn add_balance(&self, user_id: &str, delta: f64) -> Result<Option<f64>, GatewayError>;
+    /// 用户级预算：该用户名下全部客户端令牌累计消费金额的上限（未设置返回 None）
+    async fn get_budget(&self, user_id: &str) -> Result<Option<f64>, GatewayError>;
+    /// 设置或清除用户级预算；用户不存在时返回 false
+    async fn set_budget(
+        &self,
+        user_id: &str,
+        max_amount: Option<f64>,
+    ) -> Result<bool, GatewayError>;
 }
 
 #[cfg(test)]